@@ -245,6 +245,23 @@
 			     (and color  `(:color ,color))
 			     (and style  `(:style ,style)))))))))
 
+    (:text-shadow
+     (choice :tag "Text shadow"
+	     :help-echo "Control shadow or glow under text."
+	     (const :tag "Off" nil)
+	     (const :tag "On" t)
+	     (color :tag "Colored")
+	     (list :tag "Shadow"
+		   :value (:color "black" :offset (1 . 1) :blur 2)
+		   (const :format "" :value :color)
+		   (color :tag "Color")
+		   (const :format "" :value :offset)
+		   (cons :tag "Offset" :extra-offset 2
+			 (integer :tag "X")
+			 (integer :tag "Y"))
+		   (const :format "" :value :blur)
+		   (natnum :tag "Blur radius"))))
+
     (:inverse-video
      (choice :tag "Inverse-video"
 	     :help-echo "Control whether text should be in inverse-video."
@@ -812,6 +812,21 @@ is `released-button', draw a box looking like a released 3D button.
 If STYLE is `pressed-button', draw a box that looks like a pressed
 button.  If STYLE is nil, `flat-button', or omitted, draw a 2D box.
 
+`:text-shadow'
+
+VALUE specifies whether characters in FACE cast a shadow.  If VALUE
+is nil, explicitly don't.  If VALUE is t, draw a shadow in the
+background color of the frame, 1 pixel below and to the right of the
+text and blurred by 2 pixels.  If VALUE is a string, it is the color
+of that shadow.  Otherwise, VALUE must be a property list of the form
+
+ (:color COLOR :offset (X . Y) :blur RADIUS)
+
+where missing keyword/value pairs take the values above.  OFFSET may
+also be a single integer for both directions.  A shadow with offset 0
+and a blur radius is a glow around the text.  Text shadows are only
+drawn on graphical frames that support them.
+
 `:inverse-video'
 
 VALUE specifies whether characters in FACE should be displayed in
@@ -1208,7 +1223,7 @@ an integer value."
 	   ((or :inverse-video :extend)
             (mapcar (lambda (x) (cons (symbol-name x) x))
 		    (internal-lisp-face-attribute-values attribute)))
-           ((or :underline :overline :strike-through :box)
+           ((or :underline :overline :strike-through :box :text-shadow)
             (if (window-system frame)
                 (nconc (mapcar (lambda (x) (cons (symbol-name x) x))
                                (internal-lisp-face-attribute-values attribute))
@@ -1254,6 +1269,7 @@ an integer value."
     (:extend . "extend")
     (:strike-through . "strike-through")
     (:box . "box")
+    (:text-shadow . "text shadow")
     (:inverse-video . "inverse-video display")
     (:foreground . "foreground color")
     (:background . "background color")
@@ -1322,7 +1338,8 @@ of a global face.  Value is the new attribute value."
     ;; out.  Stipple can be a vector; (WIDTH HEIGHT DATA).  Box can be
     ;; a list `(:width WIDTH :color COLOR)' or `(:width WIDTH :shadow
     ;; SHADOW)'.  Underline can be `(:color COLOR :style STYLE)'.
-    (and (memq attribute '(:box :stipple :underline))
+    ;; Text shadow can be `(:color COLOR :offset (X . Y) :blur RADIUS)'.
+    (and (memq attribute '(:box :stipple :underline :text-shadow))
 	 (or (consp old-value)
 	     (vectorp old-value))
 	 (setq old-value (prin1-to-string old-value)))
@@ -1364,7 +1381,7 @@ of a global face.  Value is the new attribute value."
     ;; Convert stipple and box value text we read back to a list or
     ;; vector if it looks like one.  This makes the assumption that a
     ;; pixmap file name won't start with an open-paren.
-    (and (memq attribute '(:stipple :box :underline))
+    (and (memq attribute '(:stipple :box :underline :text-shadow))
 	 (stringp new-value)
 	 (string-match-p "^[[(]" new-value)
 	 (setq new-value (read new-value)))
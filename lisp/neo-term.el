@@ -16,6 +16,26 @@
 ;; Usage:
 ;;   M-x neo-term          -- open a terminal in the current window
 ;;   M-x neo-term-floating -- open a floating terminal overlay
+;;
+;; Keys are encoded the way the program in the terminal asks for:
+;; legacy xterm sequences by default, or the kitty keyboard protocol
+;; (disambiguated modifiers, key release events, ...) for programs such
+;; as helix or neovim that enable it.  Likewise mouse clicks, drags,
+;; wheel scrolling and motion are reported to programs that enable
+;; mouse tracking; hold `neo-term-mouse-bypass-modifier' to use the
+;; mouse in Emacs instead.
+;;
+;; Each terminal has its own palette, which follows the theme by
+;; default; see `neo-term-palette' and `neo-term-set-palette'.
+;;
+;; Shells that mark their prompts and commands with OSC 133 (shell
+;; integration) get a separator line between commands; C-c C-p and
+;; C-c C-n jump between prompts and C-c C-o copies the output of the
+;; last command.
+;;
+;; When a program rings the bell, the terminal flashes and the frame
+;; asks for attention; see `neo-term-bell'.  C-c C-b silences the bell
+;; of one terminal.
 
 ;;; Code:
 
@@ -41,6 +61,52 @@
   :type 'integer
   :group 'neo-term)
 
+(defcustom neo-term-mouse-bypass-modifier 'shift
+  "Modifier that keeps mouse events in Emacs.
+While the program in the terminal tracks the mouse, mouse events are
+reported to it, except those with this modifier: Emacs handles them
+as if the modifier was not held, e.g. to select text."
+  :type '(choice (const shift) (const control) (const meta)
+                 (const super) (const hyper) (const alt))
+  :group 'neo-term)
+
+(defcustom neo-term-palette 'theme
+"Colors of neo-term terminals.
+`theme' takes the 16 ANSI colors from the `ansi-color-*' faces and
+the default colors from the `default' face, and follows theme changes.
+nil uses the built-in xterm colors.  Otherwise, a list of up to 16
+color names (nil entries keep the built-in color), optionally followed
+by the keywords :foreground and :background with color names.
+
+Set it buffer-locally with `neo-term-set-palette' to give one terminal
+its own colors.  Programs may still change colors with OSC 4, 10 and
+11 escape sequences."
+  :type '(choice (const :tag "Follow the theme" theme)
+                 (const :tag "Built-in colors" nil)
+                 (sexp :tag "Colors"))
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (fboundp 'neo-term--refresh-palettes)
+           (neo-term--refresh-palettes)))
+  :group 'neo-term)
+
+(defcustom neo-term-bell '(visual urgent)
+"How to respond when a program in the terminal rings the bell.
+A list of any of these symbols:
+  `audible'  play `neo-term-bell-sound', or beep if that is nil;
+  `visual'   flash the terminal's view;
+  `urgent'   set the urgency hint on the frame when it lacks focus.
+nil ignores the bell.  `neo-term-toggle-bell' silences one terminal."
+  :type '(set (const :tag "Sound" audible)
+              (const :tag "Flash the terminal" visual)
+              (const :tag "Urgency hint" urgent))
+  :group 'neo-term)
+
+(defcustom neo-term-bell-sound nil
+  "Sound file played for an audible bell, or nil to beep."
+  :type '(choice (const :tag "Beep" nil) file)
+  :group 'neo-term)
+
 (defvar neo-term--terminals (make-hash-table :test 'eql)
   "Hash table mapping terminal-id to terminal info plists.")
 
@@ -60,6 +126,24 @@
                   (terminal-id x y opacity))
 (declare-function neomacs-terminal-get-text "neomacsterm.c"
                   (terminal-id))
+(declare-function neomacs-terminal-send-key "neomacsterm.c"
+                  (terminal-id key &optional modifiers kind))
+(declare-function neomacs-terminal-focus "neomacsterm.c"
+                  (terminal-id))
+(declare-function neomacs-terminal-send-mouse "neomacsterm.c"
+                  (terminal-id button kind col row &optional modifiers))
+(declare-function neomacs-terminal-mouse-mode "neomacsterm.c"
+                  (terminal-id))
+(declare-function neomacs-terminal-flash "neomacsterm.c"
+                  (terminal-id))
+(declare-function neomacs-terminal-set-palette "neomacsterm.c"
+                  (terminal-id colors &optional fg bg))
+(declare-function neomacs-terminal-jump-to-prompt "neomacsterm.c"
+                  (terminal-id n))
+(declare-function neomacs-terminal-last-output "neomacsterm.c"
+                  (terminal-id))
+(declare-function neomacs-request-attention "neomacsterm.c"
+                  (&optional urgent))
 
 (defun neo-term--shell-path ()
   "Return shell program to use."
@@ -101,6 +185,10 @@ Returns terminal ID or nil on failure."
 
 ;;; Major mode
 
+(defconst neo-term--mouse-item
+  '(menu-item "" neo-term-send-mouse :filter neo-term--mouse-filter)
+  "Binding of mouse events, active while the terminal tracks the mouse.")
+
 (defvar neo-term-mode-map
   (let ((map (make-sparse-keymap)))
     ;; Suppress default self-insert so stray keys don't modify the buffer
@@ -112,18 +200,33 @@ Returns terminal ID or nil on failure."
 
     ;; Standard editing keys
     (define-key map (kbd "RET") #'neo-term-send-return)
+    (define-key map (kbd "<return>") #'neo-term-send-return)
     (define-key map (kbd "DEL") #'neo-term-send-backspace)
     (define-key map (kbd "<backspace>") #'neo-term-send-backspace)
     (define-key map (kbd "TAB") #'neo-term-send-tab)
+    (define-key map (kbd "<tab>") #'neo-term-send-tab)
     (define-key map [escape] #'neo-term-send-escape)
 
-    ;; Arrow keys, navigation keys, and function keys (send ANSI sequences)
-    (dolist (key '("<up>" "<down>" "<left>" "<right>"
-                   "<home>" "<end>" "<prior>" "<next>"
-                   "<insert>" "<delete>"
-                   "<f1>" "<f2>" "<f3>" "<f4>" "<f5>" "<f6>"
-                   "<f7>" "<f8>" "<f9>" "<f10>" "<f11>" "<f12>"))
-      (define-key map (kbd key) #'neo-term-send-special))
+    ;; Arrow keys, navigation keys, and function keys, also with
+    ;; modifiers (encoded by the terminal's keyboard protocol)
+    (dolist (key '("up" "down" "left" "right"
+                   "home" "end" "prior" "next"
+                   "insert" "delete"
+                   "f1" "f2" "f3" "f4" "f5" "f6"
+                   "f7" "f8" "f9" "f10" "f11" "f12"
+                   "return" "tab" "backspace" "escape"))
+      (dolist (mods '("" "S-" "C-" "M-" "C-S-" "M-S-" "C-M-"))
+        (unless (and (equal mods "") (member key '("return" "tab" "backspace" "escape")))
+          (define-key map (kbd (format "<%s%s>" mods key)) #'neo-term-send-special))))
+
+    ;; Meta and Control-Shift letters, for programs that tell them apart.
+    ;; M-x and M-: stay with Emacs.
+    (dotimes (i 95)
+      (let ((char (+ i 32)))
+        (unless (memq char '(?x ?:))
+          (define-key map (vector (logior char ?\M-\0)) #'neo-term-send-key))))
+    (dotimes (i 26)
+      (define-key map (kbd (format "C-S-%c" (+ ?a i))) #'neo-term-send-key))
 
     ;; Control keys sent directly to terminal (C-a=1 .. C-z=26)
     ;; Skip C-c(3)=our prefix, C-i(9)=TAB, C-m(13)=RET
@@ -132,12 +235,27 @@ Returns terminal ID or nil on failure."
         (unless (memq ctrl-char '(3 9 13))
           (define-key map (vector ctrl-char) #'neo-term-send-ctrl))))
 
+    ;; Mouse events, reported while the program tracks the mouse
+    (dolist (mods '("" "C-" "M-" "S-" "C-M-" "C-S-" "M-S-"))
+      (dolist (kind '("down-" "" "drag-" "double-down-" "double-"
+                      "triple-down-" "triple-"))
+        (dotimes (i 5)
+          (define-key map (kbd (format "<%s%smouse-%d>" mods kind (1+ i)))
+                      neo-term--mouse-item)))
+      (dolist (wheel '("wheel-up" "wheel-down" "wheel-left" "wheel-right"))
+        (define-key map (kbd (format "<%s%s>" mods wheel)) neo-term--mouse-item)))
+    (define-key map [mouse-movement] neo-term--mouse-item)
+
     ;; C-c prefix for Emacs-level commands
     (define-key map (kbd "C-c C-c") #'neo-term-send-ctrl-c)
     (define-key map (kbd "C-c C-d") #'neo-term-send-ctrl-d)
     (define-key map (kbd "C-c C-z") #'neo-term-send-ctrl-z)
     (define-key map (kbd "C-c C-\\") #'neo-term-send-ctrl-backslash)
     (define-key map (kbd "C-c C-q") #'neo-term-quit)
+    (define-key map (kbd "C-c C-b") #'neo-term-toggle-bell)
+    (define-key map (kbd "C-c C-p") #'neo-term-previous-prompt)
+    (define-key map (kbd "C-c C-n") #'neo-term-next-prompt)
+    (define-key map (kbd "C-c C-o") #'neo-term-copy-last-output)
     map)
   "Keymap for `neo-term-mode'.")
 
@@ -148,55 +266,77 @@ Returns terminal ID or nil on failure."
   :group 'neo-term
   (setq-local buffer-read-only t)
   (setq-local truncate-lines t)
+  ;; Motion events, for programs tracking the mouse
+  (setq-local track-mouse t)
   (setq-local neo-term--id nil))
 
 (defvar-local neo-term--id nil
   "Terminal ID for this buffer.")
 
+(defvar-local neo-term-bell-silenced nil
+  "Non-nil means this terminal ignores the bell.")
+
+(defun neo-term--send-event (event)
+  "Send key EVENT to the terminal.
+The terminal encodes it for the keyboard protocol its program enabled."
+  (when neo-term--id
+    (let ((base (event-basic-type event))
+          (mods (event-modifiers event)))
+      ;; RET, TAB, ESC and DEL typed as control characters are still
+      ;; those keys, not C-m, C-i and C-[
+      (when (and (integerp event)
+                 (memq (logand event #x3fffff) '(?\r ?\t ?\e)))
+        (setq base (logand event #x3fffff)
+              mods (remq 'control mods)))
+      (unless (and (fboundp 'neomacs-terminal-send-key)
+                   (neomacs-terminal-send-key neo-term--id base mods))
+        ;; Fall back to a plain escape sequence
+        (let ((seq (if (characterp event) (string event)
+                     (neo-term--key-to-ansi base))))
+          (when seq (neo-term--write neo-term--id seq)))))))
+
 (defun neo-term-send-key ()
   "Send the current key to the terminal."
   (interactive)
-  (when neo-term--id
-    (let* ((keys (this-command-keys))
-           (str (if (stringp keys) keys (string (event-basic-type last-input-event)))))
-      (neo-term--write neo-term--id str))))
+  (neo-term--send-event last-input-event))
 
 (defun neo-term-send-return ()
   "Send Return to the terminal."
   (interactive)
-  (when neo-term--id (neo-term--write neo-term--id "\r")))
+  (neo-term--send-event 'return))
 
 (defun neo-term-send-backspace ()
   "Send Backspace to the terminal."
   (interactive)
-  (when neo-term--id (neo-term--write neo-term--id "\177")))
+  (neo-term--send-event ?\d))
 
 (defun neo-term-send-tab ()
   "Send Tab to the terminal."
   (interactive)
-  (when neo-term--id (neo-term--write neo-term--id "\t")))
+  (neo-term--send-event 'tab))
 
 (defun neo-term-send-escape ()
   "Send Escape to the terminal."
   (interactive)
-  (when neo-term--id (neo-term--write neo-term--id "\e")))
+  (neo-term--send-event 'escape))
 
 (defun neo-term-send-ctrl ()
   "Send control character to the terminal."
   (interactive)
-  (when neo-term--id
-    (neo-term--write neo-term--id (string last-input-event))))
+  (neo-term--send-event last-input-event))
 
 (defun neo-term-send-special ()
-  "Send special key (arrows, function keys, etc.) as ANSI escape sequence."
+  "Send special key (arrows, function keys, etc.) to the terminal."
   (interactive)
-  (when neo-term--id
-    (let ((seq (neo-term--key-to-ansi last-input-event)))
-      (when seq (neo-term--write neo-term--id seq)))))
+  (neo-term--send-event last-input-event))
 
 (defun neo-term--key-to-ansi (key)
   "Convert Emacs KEY event symbol to ANSI escape sequence string."
   (pcase key
+    ('return "\r")
+    ('tab    "\t")
+    ('backspace "\177")
+    ('escape "\e")
     ('up     "\e[A")
     ('down   "\e[B")
     ('right  "\e[C")
@@ -220,6 +360,66 @@ Returns terminal ID or nil on failure."
     ('f11 "\e[23~")
     ('f12 "\e[24~")))
 
+;;; Mouse
+
+(defconst neo-term--modifier-prefixes
+  '((alt . "A-") (control . "C-") (hyper . "H-") (meta . "M-")
+    (shift . "S-") (super . "s-"))
+  "Prefixes of modifier symbols in event names.")
+
+(defun neo-term--mouse-filter (cmd)
+  "Return the command for the mouse event being read.
+That is CMD to report it to the terminal, `neo-term-mouse-bypass' if
+it has the bypass modifier, or nil if the terminal does not track the
+mouse."
+  (when (and neo-term--id (fboundp 'neomacs-terminal-mouse-mode)
+             (neomacs-terminal-mouse-mode neo-term--id))
+    (if (memq neo-term-mouse-bypass-modifier (event-modifiers last-input-event))
+        (unless (eq (event-basic-type last-input-event) 'mouse-movement)
+          #'neo-term-mouse-bypass)
+      cmd)))
+
+(defun neo-term--mouse-button (type)
+  "Terminal mouse button of event basic TYPE, or nil for motion."
+  (pcase type
+    ('mouse-1 1)
+    ('mouse-2 2)
+    ('mouse-3 3)
+    ('mouse-4 'wheel-up)
+    ('mouse-5 'wheel-down)
+    ((or 'wheel-up 'wheel-down 'wheel-left 'wheel-right) type)))
+
+(defun neo-term-send-mouse (event)
+  "Report mouse EVENT to the program in the terminal."
+  (interactive "e")
+  (let* ((posn (event-end event))
+         (window (posn-window posn))
+         (type (event-basic-type event))
+         (mods (event-modifiers event))
+         (button (neo-term--mouse-button type))
+         (kind (cond ((eq type 'mouse-movement) 'motion)
+                     ((or (memq 'down mods)
+                          (memq button '(wheel-up wheel-down wheel-left wheel-right)))
+                      'press)
+                     (t 'release)))
+         (col-row (posn-col-row posn))
+         (id (and (windowp window)
+                  (buffer-local-value 'neo-term--id (window-buffer window)))))
+    (when id
+      (neomacs-terminal-send-mouse
+       id button kind (max 0 (car col-row)) (max 0 (cdr col-row))
+       (cl-remove-if-not (lambda (m) (assq m neo-term--modifier-prefixes)) mods)))))
+
+(defun neo-term-mouse-bypass (event)
+  "Handle mouse EVENT as Emacs would without `neo-term-mouse-bypass-modifier'."
+  (interactive "e")
+  (let* ((prefix (alist-get neo-term-mouse-bypass-modifier
+                            neo-term--modifier-prefixes))
+         (type (intern (string-replace prefix "" (symbol-name (car event)))))
+         (cmd (lookup-key global-map (vector type))))
+    (when (commandp cmd)
+      (call-interactively cmd nil (vector (cons type (cdr event)))))))
+
 (defun neo-term-send-ctrl-c ()
   "Send C-c to the terminal."
   (interactive)
@@ -268,6 +468,153 @@ Returns terminal ID or nil on failure."
                  (eql neo-term--id terminal-id))
         (rename-buffer (format "*neo-term: %s*" title) t)))))
 
+;;; Colors
+
+(defconst neo-term--ansi-faces
+  '(ansi-color-black ansi-color-red ansi-color-green ansi-color-yellow
+    ansi-color-blue ansi-color-magenta ansi-color-cyan ansi-color-white
+    ansi-color-bright-black ansi-color-bright-red ansi-color-bright-green
+    ansi-color-bright-yellow ansi-color-bright-blue
+    ansi-color-bright-magenta ansi-color-bright-cyan
+    ansi-color-bright-white)
+  "Faces giving the 16 ANSI colors when `neo-term-palette' is `theme'.")
+
+(defun neo-term--palette-colors (palette)
+  "Return (COLORS FG BG) for the value PALETTE of `neo-term-palette'."
+  (cond
+   ((eq palette 'theme)
+    (require 'ansi-color)
+    (list (mapcar (lambda (face)
+                    (and (facep face) (face-foreground face nil 'default)))
+                  neo-term--ansi-faces)
+          (face-foreground 'default nil t)
+          (face-background 'default nil t)))
+   (t
+    (let ((colors (cl-loop for c in palette
+                           until (keywordp c) collect c)))
+      (list colors
+            (plist-get (memq :foreground palette) :foreground)
+            (plist-get (memq :background palette) :background))))))
+
+(defun neo-term--apply-palette (terminal-id palette)
+  "Give terminal TERMINAL-ID the colors of PALETTE.
+PALETTE is a value of `neo-term-palette'."
+  (when (fboundp 'neomacs-terminal-set-palette)
+    (apply #'neomacs-terminal-set-palette terminal-id
+           (neo-term--palette-colors palette))))
+
+(defun neo-term--refresh-palettes (&rest _)
+  "Reapply the palette of every terminal, e.g. after a theme change."
+  (maphash
+   (lambda (id _)
+     (let ((buf (cl-find-if (lambda (b)
+                              (eql (buffer-local-value 'neo-term--id b) id))
+                            (buffer-list))))
+       (neo-term--apply-palette
+        id (if buf (buffer-local-value 'neo-term-palette buf)
+             neo-term-palette))))
+   neo-term--terminals))
+
+(defun neo-term-set-palette (palette)
+  "Set the colors of this buffer's terminal to PALETTE.
+PALETTE is a value of `neo-term-palette', which becomes buffer-local.
+Interactively, choose between following the theme and the built-in
+colors."
+  (interactive
+   (list (intern (completing-read "Palette: " '("theme" "nil") nil t))))
+  (unless neo-term--id
+    (user-error "No terminal in this buffer"))
+  (setq-local neo-term-palette palette)
+  (neo-term--apply-palette neo-term--id palette))
+
+(add-hook 'enable-theme-functions #'neo-term--refresh-palettes)
+(add-hook 'disable-theme-functions #'neo-term--refresh-palettes)
+
+;;; Shell integration
+
+(defun neo-term-previous-prompt (n)
+  "Scroll back to the Nth previous shell prompt.
+This needs a shell that marks its prompts with OSC 133 escape
+sequences, as most shells' terminal integration scripts do."
+  (interactive "p")
+  (unless neo-term--id
+    (user-error "No terminal in this buffer"))
+  (neomacs-terminal-jump-to-prompt neo-term--id (- n)))
+
+(defun neo-term-next-prompt (n)
+  "Scroll forward to the Nth next shell prompt.
+See `neo-term-previous-prompt'."
+  (interactive "p")
+  (unless neo-term--id
+    (user-error "No terminal in this buffer"))
+  (neomacs-terminal-jump-to-prompt neo-term--id n))
+
+(defun neo-term-last-output ()
+  "Return the output of the last finished command, or nil.
+Commands are known from the shell's OSC 133 marks."
+  (and neo-term--id (neomacs-terminal-last-output neo-term--id)))
+
+(defun neo-term-copy-last-output ()
+  "Copy the output of the last finished command to the kill ring."
+  (interactive)
+  (let ((output (neo-term-last-output)))
+    (unless output
+      (user-error "No command output (does the shell send OSC 133 marks?)"))
+    (kill-new output)
+    (message "Copied %d lines of output" (cl-count ?\n output))))
+
+;;; Bell
+
+(defun neo-term--ring-bell (terminal-id)
+  "Respond to a bell from TERMINAL-ID as `neo-term-bell' says."
+  (when (memq 'audible neo-term-bell)
+    (if neo-term-bell-sound
+        (with-demoted-errors "neo-term: %S"
+          (play-sound-file neo-term-bell-sound))
+      (let ((visible-bell nil)
+            (ring-bell-function nil))
+        (ding t))))
+  (when (and (memq 'visual neo-term-bell)
+             (fboundp 'neomacs-terminal-flash))
+    (neomacs-terminal-flash terminal-id))
+  (when (and (memq 'urgent neo-term-bell)
+             (fboundp 'neomacs-request-attention)
+             (not (eq (frame-focus-state) t)))
+    (neomacs-request-attention)))
+
+(defun neo-term--handle-bell (terminal-id)
+  "Handle a bell rung by the program in terminal TERMINAL-ID."
+  (let ((buf (cl-find-if (lambda (b)
+                           (eql (buffer-local-value 'neo-term--id b)
+                                terminal-id))
+                         (buffer-list))))
+    ;; Floating terminals have no buffer, so only the global setting applies
+    (unless (and buf (buffer-local-value 'neo-term-bell-silenced buf))
+      (neo-term--ring-bell terminal-id))))
+
+(defun neo-term-toggle-bell ()
+  "Toggle whether this terminal ignores the bell."
+  (interactive)
+  (setq neo-term-bell-silenced (not neo-term-bell-silenced))
+  (message "neo-term: bell %s" (if neo-term-bell-silenced "silenced" "enabled")))
+
+;;; Keyboard focus
+
+(defvar neo-term--focused nil
+  "ID of the terminal with the keyboard focus, or nil.")
+
+(defun neo-term--update-focus (&rest _)
+  "Give the keyboard focus to the terminal of the selected window.
+The focused terminal gets key releases when its program asks for them."
+  (let ((id (buffer-local-value 'neo-term--id (window-buffer (selected-window)))))
+    (unless (eql id neo-term--focused)
+      (setq neo-term--focused id)
+      (when (fboundp 'neomacs-terminal-focus)
+        (neomacs-terminal-focus id)))))
+
+(add-hook 'window-selection-change-functions #'neo-term--update-focus)
+(add-hook 'window-buffer-change-functions #'neo-term--update-focus)
+
 ;;; Public API
 
 ;;;###autoload
@@ -285,6 +632,8 @@ Returns terminal ID or nil on failure."
     (switch-to-buffer buf)
     (neo-term-mode)
     (setq-local neo-term--id id)
+    (neo-term--apply-palette id neo-term-palette)
+    (neo-term--update-focus)
     (message "neo-term: terminal %d created (%dx%d)"
              id neo-term-default-cols neo-term-default-rows)))
 
@@ -299,6 +648,7 @@ Optional COLS, ROWS set the terminal size."
          (id (neo-term--create cols rows 2))) ; mode=2 (Floating)
     (unless id
       (error "Failed to create floating terminal"))
+    (neo-term--apply-palette id neo-term-palette)
     (when (or x y)
       (neomacs-terminal-set-float
        id (or x 100.0) (or y 100.0) 0.95))
@@ -0,0 +1,268 @@
+;;; neomacs-icons.el --- Icon font glyphs for Neomacs -*- lexical-binding: t -*-
+
+;; Copyright (C) 2024-2026 Free Software Foundation, Inc.
+
+;; Author: Neomacs Contributors
+;; Keywords: faces, convenience
+
+;; This file is part of GNU Emacs.
+
+;; GNU Emacs is free software: you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; GNU Emacs is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Commentary:
+
+;; This package names icons from an icon font (a Nerd Font by default)
+;; and shows them in the mode line, in Dired and next to completion
+;; candidates.
+;;
+;; Each icon is registered with the display engine, which draws its
+;; character from the icon font directly, moved up or down so it lines
+;; up with the text and optionally in a color of its own:
+;;
+;;   (neomacs-define-icon 'rust #xe7a8 :color "#dea584")
+;;   (insert (neomacs-icon 'rust))
+;;
+;; Usage:
+;;   (add-to-list 'mode-line-format neomacs-icons-mode-line-format)
+;;   M-x neomacs-icons-dired-mode        icons before Dired file names
+;;   M-x neomacs-icons-completion-mode   icons before completion candidates
+;;
+;; The icons need an installed Nerd Font, such as "Symbols Nerd Font
+;; Mono"; see `neomacs-icons-font-family'.
+
+;;; Code:
+
+(declare-function neomacs-register-icon "neomacsterm.c"
+  (name codepoint &optional family y-offset scale color))
+(declare-function neomacs-remove-icon "neomacsterm.c" (name))
+(declare-function dired-get-filename "dired" (&optional localp no-error-if-not-filep))
+(declare-function dired-move-to-filename "dired" (&optional raise-error eol))
+(defvar dired-after-readin-hook)
+
+(defgroup neomacs-icons nil
+  "Icon font glyphs for Neomacs."
+  :group 'neomacs
+  :prefix "neomacs-icons-")
+
+(defcustom neomacs-icons-font-family "Symbols Nerd Font Mono"
+  "Font family icons are drawn from, unless an icon names its own."
+  :type 'string
+  :group 'neomacs-icons)
+
+(defcustom neomacs-icons-y-offset 0.0
+  "Default vertical correction of icons, as a fraction of the font size.
+Positive values move icons up.  Nerd Fonts sit their icons on the
+baseline, so the default is no correction."
+  :type 'number
+  :group 'neomacs-icons)
+
+(defvar neomacs-icons--table (make-hash-table :test #'eq)
+  "Icons by name: a plist of :codepoint, :family, :y-offset, :scale, :color.")
+
+(defun neomacs-icons--register (name icon)
+  "Register ICON, the plist of icon NAME, with the display engine."
+  (when (fboundp 'neomacs-register-icon)
+    (neomacs-register-icon name (plist-get icon :codepoint)
+                           (or (plist-get icon :family)
+                               neomacs-icons-font-family)
+                           (or (plist-get icon :y-offset)
+                               neomacs-icons-y-offset)
+                           (plist-get icon :scale)
+                           (plist-get icon :color))))
+
+(defun neomacs-define-icon (name codepoint &rest props)
+  "Define icon NAME, a symbol, as character CODEPOINT of an icon font.
+PROPS may contain :family (the font, default `neomacs-icons-font-family'),
+:y-offset (see `neomacs-icons-y-offset'), :scale (size relative to the
+text) and :color (a \"#rrggbb\" string drawn instead of the face's
+foreground).  Redefining NAME replaces the icon."
+  (let ((icon (append (list :codepoint codepoint) props)))
+    (puthash name icon neomacs-icons--table)
+    (neomacs-icons--register name icon)
+    name))
+
+(defun neomacs-icons-register-all ()
+  "Register all defined icons with the display engine.
+Icons defined before the display engine started are registered by
+this, which runs from `window-setup-hook'."
+  (maphash #'neomacs-icons--register neomacs-icons--table))
+
+(defun neomacs-icon (name &optional face)
+  "Return the icon NAME as a string, or nil if it is not defined.
+FACE, if non-nil, is the face of the string."
+  (when-let* ((icon (gethash name neomacs-icons--table)))
+    (let ((string (string (plist-get icon :codepoint))))
+      (when face
+        (put-text-property 0 1 'face face string))
+      string)))
+
+;; Nerd Font icons used by the mode line, Dired and completion
+(dolist (icon '((file #xf016) (directory #xf07b) (directory-open #xf07c)
+                (code #xf121) (image #xf1c5) (archive #xf1c6)
+                (config #xf013) (emacs #xe632 :color "#7f5ab6")
+                (rust #xe7a8 :color "#dea584") (c #xe61e :color "#599eff")
+                (python #xe73c :color "#ffbc03") (org #xe633 :color "#77aa99")
+                (markdown #xe73e) (json #xe60b :color "#cbcb41")
+                (shell #xf489) (git-branch #xe725 :color "#f14c28")
+                (modified #xf040) (read-only #xf023) (buffer #xf15b)
+                (command #xf120) (function #xea8c :color "#b180d7")
+                (variable #xea88 :color "#75beff")
+                (face #xeb5c :color "#ee9d28")))
+  (unless (gethash (car icon) neomacs-icons--table)
+    (apply #'neomacs-define-icon icon)))
+
+(add-hook 'window-setup-hook #'neomacs-icons-register-all)
+
+(defcustom neomacs-icons-file-alist
+  '(("\\.rs\\'" . rust)
+    ("\\.[ch]\\(pp\\|xx\\)?\\'" . c)
+    ("\\.py\\'" . python)
+    ("\\.el\\(c\\)?\\'" . emacs)
+    ("\\.org\\'" . org)
+    ("\\.md\\'" . markdown)
+    ("\\.json\\'" . json)
+    ("\\.\\(toml\\|ya?ml\\|ini\\|conf\\)\\'" . config)
+    ("\\.\\(sh\\|bash\\|zsh\\|fish\\)\\'" . shell)
+    ("\\.\\(png\\|jpe?g\\|gif\\|svg\\|webp\\)\\'" . image)
+    ("\\.\\(zip\\|tar\\|gz\\|xz\\|zst\\|7z\\)\\'" . archive)
+    ("\\.\\(js\\|ts\\|go\\|java\\|rb\\|lua\\|hs\\)\\'" . code))
+  "Icons of files, as (REGEXP . ICON) matched against file names."
+  :type '(alist :key-type regexp :value-type symbol)
+  :group 'neomacs-icons)
+
+(defcustom neomacs-icons-mode-alist
+  '((emacs-lisp-mode . emacs) (lisp-interaction-mode . emacs)
+    (rust-mode . rust) (rust-ts-mode . rust)
+    (c-mode . c) (c-ts-mode . c) (c++-mode . c) (c++-ts-mode . c)
+    (python-mode . python) (python-ts-mode . python)
+    (org-mode . org) (markdown-mode . markdown)
+    (json-mode . json) (json-ts-mode . json)
+    (sh-mode . shell) (bash-ts-mode . shell)
+    (dired-mode . directory-open) (prog-mode . code))
+  "Icons of major modes, as (MODE . ICON).
+A mode without an entry uses the icon of the nearest mode it derives
+from."
+  :type '(alist :key-type symbol :value-type symbol)
+  :group 'neomacs-icons)
+
+(defun neomacs-icon-for-file (file)
+  "Return the name of the icon of FILE."
+  (if (directory-name-p file)
+      'directory
+    (or (assoc-default file neomacs-icons-file-alist #'string-match-p)
+        'file)))
+
+(defun neomacs-icon-for-mode (mode)
+  "Return the name of the icon of major MODE, or nil if it has none."
+  (let (icon)
+    (while (and mode (not (setq icon (alist-get mode neomacs-icons-mode-alist))))
+      (setq mode (get mode 'derived-mode-parent)))
+    icon))
+
+;;;; Mode line
+
+(defun neomacs-icons-mode-line ()
+  "Return the icons of the mode line segment of the current buffer."
+  (concat
+   (when-let* ((icon (neomacs-icon-for-mode major-mode)))
+     (concat (neomacs-icon icon) " "))
+   (cond (buffer-read-only (concat (neomacs-icon 'read-only) " "))
+         ((and buffer-file-name (buffer-modified-p))
+          (concat (neomacs-icon 'modified) " ")))))
+
+(defvar neomacs-icons-mode-line-format '(:eval (neomacs-icons-mode-line))
+  "Mode line construct showing the major mode and buffer state as icons.")
+(put 'neomacs-icons-mode-line-format 'risky-local-variable t)
+
+;;;; Dired
+
+(defun neomacs-icons--dired-decorate ()
+  "Put an icon before each file name of the current Dired buffer."
+  (remove-overlays (point-min) (point-max) 'neomacs-icon t)
+  (save-excursion
+    (goto-char (point-min))
+    (while (not (eobp))
+      (when-let* ((file (dired-get-filename nil t))
+                  ((dired-move-to-filename)))
+        (let ((ov (make-overlay (point) (point)))
+              (name (neomacs-icon-for-file
+                     (if (file-directory-p file)
+                         (file-name-as-directory file)
+                       file))))
+          (overlay-put ov 'neomacs-icon t)
+          (overlay-put ov 'evaporate t)
+          (overlay-put ov 'before-string (concat (neomacs-icon name) " "))))
+      (forward-line 1))))
+
+;;;###autoload
+(define-minor-mode neomacs-icons-dired-mode
+  "Show file icons before file names in Dired."
+  :group 'neomacs-icons
+  (if neomacs-icons-dired-mode
+      (progn
+        (add-hook 'dired-after-readin-hook #'neomacs-icons--dired-decorate nil t)
+        (neomacs-icons--dired-decorate))
+    (remove-hook 'dired-after-readin-hook #'neomacs-icons--dired-decorate t)
+    (remove-overlays (point-min) (point-max) 'neomacs-icon t)))
+
+;;;; Completion
+
+(defun neomacs-icons--candidate-icon (category candidate)
+  "Return the icon name of CANDIDATE of completion CATEGORY, or nil."
+  (pcase category
+    ('file (neomacs-icon-for-file candidate))
+    ('project-file (neomacs-icon-for-file candidate))
+    ('buffer (or (when-let* ((buffer (get-buffer candidate)))
+                   (neomacs-icon-for-mode (buffer-local-value 'major-mode buffer)))
+                 'buffer))
+    ('command 'command)
+    ((or 'symbol 'symbol-help)
+     (let ((symbol (intern-soft candidate)))
+       (cond ((fboundp symbol) 'function)
+             ((facep symbol) 'face)
+             ((boundp symbol) 'variable))))))
+
+(defun neomacs-icons--affixation (category annotate)
+  "Return an affixation function for completion CATEGORY.
+ANNOTATE is the category's annotation function, if any."
+  (lambda (candidates)
+    (mapcar (lambda (candidate)
+              (let ((icon (neomacs-icons--candidate-icon category candidate)))
+                (list candidate
+                      (if icon (concat (neomacs-icon icon) " ") "  ")
+                      (or (and annotate (funcall annotate candidate)) ""))))
+            candidates)))
+
+(defun neomacs-icons--metadata-get (get metadata prop)
+  "Add icons to completion METADATA without an affixation function.
+GET is `completion-metadata-get', called with METADATA and PROP."
+  (or (funcall get metadata prop)
+      (when (eq prop 'affixation-function)
+        (let ((category (funcall get metadata 'category)))
+          (when (memq category '(file project-file buffer command symbol symbol-help))
+            (neomacs-icons--affixation
+             category (funcall get metadata 'annotation-function)))))))
+
+;;;###autoload
+(define-minor-mode neomacs-icons-completion-mode
+  "Show icons before file, buffer, command and symbol completion candidates."
+  :global t
+  :group 'neomacs-icons
+  (if neomacs-icons-completion-mode
+      (advice-add 'completion-metadata-get :around #'neomacs-icons--metadata-get)
+    (advice-remove 'completion-metadata-get #'neomacs-icons--metadata-get)))
+
+(provide 'neomacs-icons)
+
+;;; neomacs-icons.el ends here
@@ -33,6 +33,10 @@
 ;;   `neomacs-image-free' - Free an image from cache
 ;;   `neomacs-image-floating' - Display image as floating layer
 ;;   `neomacs-image-floating-clear' - Remove floating image layer
+;;
+;; `neomacs-image-edit-mode' edits the image of an image buffer without
+;; touching its file: rotate, flip and crop it, draw arrows, rectangles
+;; and text over it with the mouse, and export the result as PNG.
 
 ;;; Code:
 
@@ -149,5 +153,313 @@ Returns the image ID on success."
                  neomacs-image--cache)
         (message "Resized image %d to %dx%d" image-id width height)))))
 
+;;; Editing
+
+(defgroup neomacs-image-edit nil
+  "Non-destructive image editing in Neomacs."
+  :group 'multimedia
+  :prefix "neomacs-image-edit-")
+
+(defcustom neomacs-image-edit-color "#ff3b30"
+  "Color of new annotations, a \"#rrggbb\" string."
+  :type 'string)
+
+(defcustom neomacs-image-edit-line-width 0.008
+  "Line width of new arrows and rectangles.
+A fraction of the image's shorter side."
+  :type 'number)
+
+(defcustom neomacs-image-edit-text-size 0.05
+  "Height of new text annotations, as a fraction of the image's shorter side."
+  :type 'number)
+
+(defvar-local neomacs-image-edit--id nil
+  "Neomacs image ID of the image being edited.")
+
+(defvar-local neomacs-image-edit--base-size nil
+  "Unedited display size of the image, as (WIDTH . HEIGHT).")
+
+(defvar-local neomacs-image-edit--size nil
+  "Display size of the edited image, as (WIDTH . HEIGHT).")
+
+(defvar-local neomacs-image-edit--overlay nil
+  "Overlay displaying the edited image over the buffer.")
+
+(defvar-local neomacs-image-edit--edits nil
+  "Edits of the image, a plist as for `neomacs-image-set-edits'.")
+
+(defvar-local neomacs-image-edit--history nil
+  "Earlier values of `neomacs-image-edit--edits', most recent first.")
+
+(defvar-local neomacs-image-edit--tool 'arrow
+  "What dragging over the image does: `arrow', `rect', `text' or `crop'.")
+
+(defun neomacs-image-edit--show ()
+  "Apply the current edits and display the edited image."
+  (let ((size (neomacs-image-set-edits neomacs-image-edit--id
+                                        neomacs-image-edit--edits
+                                        (car neomacs-image-edit--base-size)
+                                        (cdr neomacs-image-edit--base-size))))
+    (when size
+      (setq neomacs-image-edit--size size)
+      (overlay-put neomacs-image-edit--overlay 'display
+                   `(image :type neomacs :neomacs-id ,neomacs-image-edit--id
+                           :width ,(car size) :height ,(cdr size)
+                           :file ,buffer-file-name)))))
+
+(defun neomacs-image-edit--set (edits)
+  "Make EDITS the image's edits, remembering the old ones for undo."
+  (push neomacs-image-edit--edits neomacs-image-edit--history)
+  (setq neomacs-image-edit--edits edits)
+  (neomacs-image-edit--show))
+
+(defun neomacs-image-edit--crop (edits)
+  "Crop of EDITS as (X Y W H); the whole image if there is none."
+  (or (plist-get edits :crop) '(0.0 0.0 1.0 1.0)))
+
+(defun neomacs-image-edit--transform (edits move-point crop)
+  "Return EDITS with CROP, and annotations kept on the same image content.
+MOVE-POINT maps a point (X . Y) of the uncropped image before the change
+to the uncropped image after it; CROP is the crop after the change."
+  (pcase-let* ((`(,cx ,cy ,cw ,ch) (neomacs-image-edit--crop edits))
+               (`(,nx ,ny ,nw ,nh) crop)
+               (move (lambda (x y)
+                       (let ((p (funcall move-point
+                                         (cons (+ cx (* x cw)) (+ cy (* y ch))))))
+                         (list (/ (- (car p) nx) nw) (/ (- (cdr p) ny) nh))))))
+    (let ((edits (copy-sequence edits)))
+      (setq edits (plist-put edits :crop
+                             (unless (equal crop '(0.0 0.0 1.0 1.0)) crop)))
+      (plist-put edits :annotations
+                 (mapcar (lambda (a)
+                           (pcase a
+                             (`(text ,x ,y . ,rest)
+                              `(text ,@(funcall move x y) ,@rest))
+                             (`(,kind ,x0 ,y0 ,x1 ,y1 . ,rest)
+                              `(,kind ,@(funcall move x0 y0)
+                                      ,@(funcall move x1 y1) ,@rest))))
+                         (plist-get edits :annotations))))))
+
+(defun neomacs-image-edit-rotate (&optional turns)
+  "Rotate the image by TURNS quarter turns clockwise (default 1).
+Interactively, a negative prefix argument rotates counterclockwise."
+  (interactive "p")
+  (let ((edits neomacs-image-edit--edits))
+    (dotimes (_ (mod (or turns 1) 4))
+      (pcase-let ((`(,x ,y ,w ,h) (neomacs-image-edit--crop edits))
+                  (flipped (plist-get edits :flip-h)))
+        (setq edits (neomacs-image-edit--transform
+                     edits (lambda (p) (cons (- 1.0 (cdr p)) (car p)))
+                     (list (- 1.0 y h) x h w)))
+        ;; A clockwise turn after a mirror is a counterclockwise one before
+        (setq edits (plist-put edits :rotate
+                               (mod (+ (or (plist-get edits :rotate) 0)
+                                       (if flipped -1 1))
+                                    4)))))
+    (neomacs-image-edit--set edits)))
+
+(defun neomacs-image-edit-rotate-counterclockwise ()
+  "Rotate the image a quarter turn counterclockwise."
+  (interactive)
+  (neomacs-image-edit-rotate 3))
+
+(defun neomacs-image-edit-flip-horizontally ()
+  "Mirror the image left to right."
+  (interactive)
+  (pcase-let* ((edits neomacs-image-edit--edits)
+               (`(,x ,y ,w ,h) (neomacs-image-edit--crop edits)))
+    (setq edits (neomacs-image-edit--transform
+                 edits (lambda (p) (cons (- 1.0 (car p)) (cdr p)))
+                 (list (- 1.0 x w) y w h)))
+    (neomacs-image-edit--set
+     (plist-put edits :flip-h (not (plist-get edits :flip-h))))))
+
+(defun neomacs-image-edit-flip-vertically ()
+  "Mirror the image top to bottom."
+  (interactive)
+  (pcase-let* ((edits neomacs-image-edit--edits)
+               (`(,x ,y ,w ,h) (neomacs-image-edit--crop edits)))
+    (setq edits (neomacs-image-edit--transform
+                 edits (lambda (p) (cons (car p) (- 1.0 (cdr p))))
+                 (list x (- 1.0 y h) w h)))
+    ;; A vertical mirror is a horizontal one and a half turn
+    (setq edits (plist-put edits :flip-h (not (plist-get edits :flip-h))))
+    (neomacs-image-edit--set
+     (plist-put edits :rotate (mod (+ (or (plist-get edits :rotate) 0) 2) 4)))))
+
+(defun neomacs-image-edit--crop-to (x0 y0 x1 y1)
+  "Crop the image to the rectangle between (X0, Y0) and (X1, Y1).
+Coordinates are fractions of the image as displayed."
+  (pcase-let* ((`(,cx ,cy ,cw ,ch) (neomacs-image-edit--crop
+                                    neomacs-image-edit--edits))
+               (left (min x0 x1)) (top (min y0 y1))
+               (width (abs (- x1 x0))) (height (abs (- y1 y0))))
+    (when (or (< (* width (car neomacs-image-edit--size)) 2)
+              (< (* height (cdr neomacs-image-edit--size)) 2))
+      (user-error "Drag over the part of the image to keep"))
+    (neomacs-image-edit--set
+     (neomacs-image-edit--transform
+      neomacs-image-edit--edits #'identity
+      (list (+ cx (* left cw)) (+ cy (* top ch)) (* width cw) (* height ch))))))
+
+(defun neomacs-image-edit--annotate (annotation)
+  "Draw ANNOTATION over the image, above the existing ones."
+  (let ((edits (copy-sequence neomacs-image-edit--edits)))
+    (neomacs-image-edit--set
+     (plist-put edits :annotations
+                (append (plist-get edits :annotations) (list annotation))))))
+
+(defun neomacs-image-edit--event-point (event)
+  "Position of mouse EVENT on the image, as fractions (X . Y), or nil."
+  (let* ((posn (event-end event))
+         (xy (and (posn-image posn) (posn-object-x-y posn))))
+    (when xy
+      (cons (min 1.0 (max 0.0 (/ (float (car xy))
+                                 (car neomacs-image-edit--size))))
+            (min 1.0 (max 0.0 (/ (float (cdr xy))
+                                 (cdr neomacs-image-edit--size))))))))
+
+(defun neomacs-image-edit-drag (event)
+  "Annotate or crop the image by dragging the mouse from EVENT.
+What the drag does depends on the tool chosen with
+`neomacs-image-edit-use-tool'."
+  (interactive "e")
+  (let ((start (neomacs-image-edit--event-point event))
+        end)
+    (unless start
+      (user-error "Not on the image"))
+    (if (eq neomacs-image-edit--tool 'text)
+        (let ((text (read-string "Text: ")))
+          (unless (string-empty-p text)
+            (neomacs-image-edit--annotate
+             (list 'text (car start) (cdr start) text
+                   neomacs-image-edit-color neomacs-image-edit-text-size))))
+      (track-mouse
+        (let (ev)
+          (while (progn (setq ev (read-event))
+                        (mouse-movement-p ev))
+            (setq end (or (neomacs-image-edit--event-point ev) end)))
+          (when (consp ev)
+            (setq end (or (neomacs-image-edit--event-point ev) end)))))
+      (unless end
+        (user-error "Drag over the image"))
+      (pcase neomacs-image-edit--tool
+        ('crop (neomacs-image-edit--crop-to (car start) (cdr start)
+                                            (car end) (cdr end)))
+        (tool (neomacs-image-edit--annotate
+               (list tool (car start) (cdr start) (car end) (cdr end)
+                     neomacs-image-edit-color
+                     neomacs-image-edit-line-width)))))))
+
+(defun neomacs-image-edit-use-tool (tool)
+  "Make dragging over the image use TOOL.
+TOOL is `arrow', `rect' or `text' to annotate, or `crop'."
+  (interactive
+   (list (intern (completing-read "Tool: " '("arrow" "rect" "text" "crop")
+                                  nil t))))
+  (setq neomacs-image-edit--tool tool)
+  (message "Drag over the image to %s"
+           (pcase tool
+             ('arrow "draw an arrow")
+             ('rect "draw a rectangle")
+             ('text "place text")
+             ('crop "crop it"))))
+
+(defun neomacs-image-edit-arrow-tool ()
+  "Make dragging over the image draw an arrow."
+  (interactive)
+  (neomacs-image-edit-use-tool 'arrow))
+
+(defun neomacs-image-edit-rect-tool ()
+  "Make dragging over the image draw a rectangle."
+  (interactive)
+  (neomacs-image-edit-use-tool 'rect))
+
+(defun neomacs-image-edit-text-tool ()
+  "Make clicking on the image place text."
+  (interactive)
+  (neomacs-image-edit-use-tool 'text))
+
+(defun neomacs-image-edit-crop-tool ()
+  "Make dragging over the image crop it."
+  (interactive)
+  (neomacs-image-edit-use-tool 'crop))
+
+(defun neomacs-image-edit-undo ()
+  "Undo the last edit of the image."
+  (interactive)
+  (unless neomacs-image-edit--history
+    (user-error "No further undo information"))
+  (setq neomacs-image-edit--edits (pop neomacs-image-edit--history))
+  (neomacs-image-edit--show))
+
+(defun neomacs-image-edit-reset ()
+  "Undo all edits of the image."
+  (interactive)
+  (neomacs-image-edit--set nil))
+
+(defun neomacs-image-edit-export (output)
+  "Save the edited image as the PNG file OUTPUT.
+The edits are applied to the image at its full size."
+  (interactive
+   (list (read-file-name "Export to PNG: " nil nil nil
+                         (concat (file-name-base buffer-file-name)
+                                 "-edited.png"))))
+  (let ((size (neomacs-image-export-png buffer-file-name
+                                        neomacs-image-edit--edits
+                                        output)))
+    (message "Saved %s (%dx%d)" output (car size) (cdr size))))
+
+(defvar-keymap neomacs-image-edit-mode-map
+  :doc "Keymap for `neomacs-image-edit-mode'."
+  "r" #'neomacs-image-edit-rotate
+  "R" #'neomacs-image-edit-rotate-counterclockwise
+  "h" #'neomacs-image-edit-flip-horizontally
+  "v" #'neomacs-image-edit-flip-vertically
+  "a" #'neomacs-image-edit-arrow-tool
+  "b" #'neomacs-image-edit-rect-tool
+  "t" #'neomacs-image-edit-text-tool
+  "c" #'neomacs-image-edit-crop-tool
+  "u" #'neomacs-image-edit-undo
+  "0" #'neomacs-image-edit-reset
+  "C-c C-w" #'neomacs-image-edit-export
+  "<down-mouse-1>" #'neomacs-image-edit-drag)
+
+(define-minor-mode neomacs-image-edit-mode
+  "Edit the image of this buffer without changing its file.
+The image is shown scaled to the window.  Rotate it with \`r' and \`R',
+mirror it with \`h' and \`v', then drag over it with the mouse to draw
+an arrow (\`a'), a rectangle (\`b') or text (\`t'), or to crop it
+\(\`c').  \`u' undoes the last edit, \`0' all of them, and \`C-c C-w'
+exports the result as PNG.
+
+\{neomacs-image-edit-mode-map}"
+  :lighter " ImgEdit"
+  (cond
+   (neomacs-image-edit-mode
+    (unless (and buffer-file-name (fboundp 'neomacs-image-set-edits))
+      (setq neomacs-image-edit-mode nil)
+      (user-error "Needs a Neomacs frame and a buffer visiting an image file"))
+    (let* ((edges (window-body-pixel-edges))
+           (spec (neomacs-insert-image buffer-file-name
+                                       (- (nth 2 edges) (nth 0 edges))
+                                       (- (nth 3 edges) (nth 1 edges)))))
+      (setq neomacs-image-edit--id (plist-get (cdr spec) :neomacs-id)
+            neomacs-image-edit--base-size (cons (plist-get (cdr spec) :width)
+                                                (plist-get (cdr spec) :height))
+            neomacs-image-edit--size neomacs-image-edit--base-size
+            neomacs-image-edit--edits nil
+            neomacs-image-edit--history nil
+            neomacs-image-edit--overlay (make-overlay (point-min) (point-max)
+                                                      nil nil t))
+      (overlay-put neomacs-image-edit--overlay 'display spec)))
+   (t
+    (when neomacs-image-edit--overlay
+      (delete-overlay neomacs-image-edit--overlay))
+    (when neomacs-image-edit--id
+      (neomacs-image-free neomacs-image-edit--id))
+    (setq neomacs-image-edit--id nil
+          neomacs-image-edit--overlay nil))))
+
 (provide 'neomacs-image)
 ;;; neomacs-image.el ends here
@@ -0,0 +1,236 @@
+;;; neomacs-log.el --- Viewer for the Neomacs display engine log -*- lexical-binding: t -*-
+
+;; Copyright (C) 2024-2026 Free Software Foundation, Inc.
+
+;; Author: Neomacs Contributors
+;; Keywords: internal, tools
+
+;; This file is part of GNU Emacs.
+
+;; GNU Emacs is free software: you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; GNU Emacs is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Commentary:
+
+;; This package shows the log of the Neomacs display engine (the Rust
+;; renderer, layout engine and FFI layer) in a buffer.
+;;
+;; Basic usage:
+;;   M-x neomacs-log
+;;
+;; The display engine keeps its recent log records in memory, at the
+;; levels selected by `neomacs-log-capture-level'.  Each line shows the
+;; time, level, module and span (such as "render/glyphs") of a record.
+;;
+;; Keys:
+;;   g   `neomacs-log-refresh'        reread the log
+;;   l   `neomacs-log-set-level'      hide records below a level
+;;   m   `neomacs-log-set-module'     show one module only
+;;   f   `neomacs-log-follow-mode'    append new records as they arrive
+;;   c   `neomacs-log-clear'          drop all records
+
+;;; Code:
+
+(declare-function neomacs-log-records "neomacsterm.c"
+  (&optional after level module))
+(declare-function neomacs-set-log-level "neomacsterm.c" (level))
+(declare-function neomacs-clear-log "neomacsterm.c" ())
+
+(defgroup neomacs-log nil
+  "Viewer for the Neomacs display engine log."
+  :group 'neomacs
+  :prefix "neomacs-log-")
+
+(defconst neomacs-log--levels '(error warning info debug trace)
+  "Log levels, most severe first.")
+
+(defcustom neomacs-log-capture-level 'info
+  "Least severe level the display engine keeps for the log viewer.
+Capturing `debug' or `trace' records costs time on every frame."
+  :type '(choice (const error) (const warning) (const info)
+                 (const debug) (const trace)
+                 (const :tag "Nothing" nil))
+  :group 'neomacs-log
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (fboundp 'neomacs-set-log-level)
+           (neomacs-set-log-level val))))
+
+(defcustom neomacs-log-follow-interval 0.5
+  "Seconds between checks for new records in `neomacs-log-follow-mode'."
+  :type 'number
+  :group 'neomacs-log)
+
+(defface neomacs-log-time '((t :inherit shadow))
+  "Face of record times in the log viewer.")
+
+(defface neomacs-log-module '((t :inherit font-lock-constant-face))
+  "Face of module names and spans in the log viewer.")
+
+(defconst neomacs-log--level-faces
+  '((error . error)
+    (warning . warning)
+    (info . success)
+    (debug . shadow)
+    (trace . shadow))
+  "Face of each log level.")
+
+(defvar-local neomacs-log--level nil
+  "Least severe level shown, or nil for all.")
+
+(defvar-local neomacs-log--module nil
+  "Module substring records must match, or nil for all.")
+
+(defvar-local neomacs-log--last-seq 0
+  "Sequence number of the last record inserted.")
+
+(defvar-local neomacs-log--timer nil
+  "Timer of `neomacs-log-follow-mode'.")
+
+(defun neomacs-log--format (record)
+  "Return the line showing log RECORD."
+  (let ((time (aref record 1))
+        (level (aref record 2))
+        (module (string-remove-prefix "neomacs_display::" (aref record 3)))
+        (span (aref record 4)))
+    (concat
+     (propertize (format-time-string "%T.%3N" time) 'face 'neomacs-log-time)
+     " "
+     (propertize (format "%-7s" level)
+                 'face (alist-get level neomacs-log--level-faces))
+     " "
+     (propertize module 'face 'neomacs-log-module)
+     (if (string-empty-p span)
+         ""
+       (propertize (format " [%s]" span) 'face 'neomacs-log-module))
+     "  "
+     (aref record 5)
+     "\n")))
+
+(defvar neomacs-log-follow-mode)
+
+(defun neomacs-log--update-header ()
+  "Show the active filters in the header line."
+  (setq header-line-format
+        (format " Level: %s   Module: %s%s"
+                (or neomacs-log--level "all")
+                (or neomacs-log--module "all")
+                (if neomacs-log-follow-mode "   [following]" ""))))
+
+(defun neomacs-log--insert-new ()
+  "Append the records logged since the last one shown.
+Keep point at the end if it was there."
+  (when (fboundp 'neomacs-log-records)
+    (let ((records (neomacs-log-records neomacs-log--last-seq
+                                        neomacs-log--level
+                                        neomacs-log--module)))
+      (when records
+        (let ((inhibit-read-only t)
+              (at-end (eobp)))
+          (save-excursion
+            (goto-char (point-max))
+            (dolist (record records)
+              (insert (neomacs-log--format record))))
+          (setq neomacs-log--last-seq (aref (car (last records)) 0))
+          (when at-end
+            (goto-char (point-max))
+            (dolist (window (get-buffer-window-list nil nil t))
+              (set-window-point window (point-max)))))))))
+
+(defun neomacs-log-refresh ()
+  "Reread the display engine log with the current filters."
+  (interactive nil neomacs-log-mode)
+  (let ((inhibit-read-only t))
+    (erase-buffer))
+  (setq neomacs-log--last-seq 0)
+  (neomacs-log--insert-new)
+  (neomacs-log--update-header))
+
+(defun neomacs-log-set-level (level)
+  "Show only records at LEVEL or more severe; nil shows all."
+  (interactive
+   (list (let ((name (completing-read
+                      "Level (empty for all): "
+                      (mapcar #'symbol-name neomacs-log--levels) nil t)))
+           (and (not (string-empty-p name)) (intern name))))
+   neomacs-log-mode)
+  (setq neomacs-log--level level)
+  (neomacs-log-refresh))
+
+(defun neomacs-log-set-module (module)
+  "Show only records whose module contains MODULE; nil shows all."
+  (interactive
+   (list (let ((name (read-string "Module (empty for all): ")))
+           (and (not (string-empty-p name)) name)))
+   neomacs-log-mode)
+  (setq neomacs-log--module module)
+  (neomacs-log-refresh))
+
+(defun neomacs-log-clear ()
+  "Drop all records from the display engine log."
+  (interactive nil neomacs-log-mode)
+  (when (fboundp 'neomacs-clear-log)
+    (neomacs-clear-log))
+  (neomacs-log-refresh))
+
+(define-minor-mode neomacs-log-follow-mode
+  "Append new display engine log records as they arrive."
+  :lighter " Follow"
+  (when (timerp neomacs-log--timer)
+    (cancel-timer neomacs-log--timer)
+    (setq neomacs-log--timer nil))
+  (when neomacs-log-follow-mode
+    (let ((buffer (current-buffer))
+          timer)
+      (setq timer
+            (run-at-time t neomacs-log-follow-interval
+                         (lambda ()
+                           (if (buffer-live-p buffer)
+                               (with-current-buffer buffer
+                                 (neomacs-log--insert-new))
+                             (cancel-timer timer)))))
+      (setq neomacs-log--timer timer)))
+  (neomacs-log--update-header))
+
+(defvar-keymap neomacs-log-mode-map
+  :doc "Keymap for `neomacs-log-mode'."
+  "l" #'neomacs-log-set-level
+  "m" #'neomacs-log-set-module
+  "f" #'neomacs-log-follow-mode
+  "c" #'neomacs-log-clear)
+
+(define-derived-mode neomacs-log-mode special-mode "Neomacs-Log"
+  "Major mode for viewing the Neomacs display engine log.
+
+\\{neomacs-log-mode-map}"
+  (setq-local revert-buffer-function
+              (lambda (_ignore-auto _noconfirm) (neomacs-log-refresh)))
+  (setq truncate-lines t)
+  (add-hook 'kill-buffer-hook
+            (lambda () (neomacs-log-follow-mode -1))
+            nil t))
+
+;;;###autoload
+(defun neomacs-log ()
+  "Show the log of the Neomacs display engine."
+  (interactive)
+  (let ((buffer (get-buffer-create "*Neomacs Log*")))
+    (with-current-buffer buffer
+      (unless (derived-mode-p 'neomacs-log-mode)
+        (neomacs-log-mode)
+        (neomacs-log-refresh)
+        (goto-char (point-max))))
+    (pop-to-buffer buffer)))
+
+(provide 'neomacs-log)
+;;; neomacs-log.el ends here
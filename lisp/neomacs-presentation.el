@@ -0,0 +1,260 @@
+;;; neomacs-presentation.el --- Presentation mode for Neomacs -*- lexical-binding: t -*-
+
+;; Copyright (C) 2024-2026 Free Software Foundation, Inc.
+
+;; Author: Neomacs Contributors
+;; Keywords: multimedia, outlines, presentation
+
+;; This file is part of GNU Emacs.
+
+;; GNU Emacs is free software: you can redistribute it and/or modify
+;; it under the terms of the GNU General Public License as published by
+;; the Free Software Foundation, either version 3 of the License, or
+;; (at your option) any later version.
+
+;; GNU Emacs is distributed in the hope that it will be useful,
+;; but WITHOUT ANY WARRANTY; without even the implied warranty of
+;; MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+;; GNU General Public License for more details.
+
+;; You should have received a copy of the GNU General Public License
+;; along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.
+
+;;; Commentary:
+
+;; This package turns an Org or Outline buffer into a slide show.
+;;
+;; Basic usage:
+;;   M-x neomacs-presentation-mode
+;;
+;; Entering the mode smoothly scales the buffer text up to
+;; `neomacs-presentation-scale' and hides the mode line, fringes and
+;; line numbers.  Each heading (with its subtree) is a slide; the buffer
+;; is narrowed to the current slide, and moving between slides plays a
+;; GPU transition such as a page curl.
+;;
+;; Keys:
+;;   SPC, <right>, <next>   `neomacs-presentation-next-slide'
+;;   DEL, <left>, <prior>   `neomacs-presentation-previous-slide'
+;;   q                      `neomacs-presentation-mode' (quit)
+
+;;; Code:
+
+(require 'outline)
+
+(defgroup neomacs-presentation nil
+  "Presentation mode for Neomacs."
+  :group 'neomacs
+  :prefix "neomacs-presentation-")
+
+(defcustom neomacs-presentation-scale 1.8
+  "Text scale factor used while presenting."
+  :type 'number
+  :group 'neomacs-presentation)
+
+(defcustom neomacs-presentation-scale-duration 0.3
+  "Seconds taken to animate the text scale in and out.
+Zero changes the scale instantly."
+  :type 'number
+  :group 'neomacs-presentation)
+
+(defcustom neomacs-presentation-transition "page-curl"
+  "Transition effect played between slides.
+See `neomacs-start-buffer-transition' for the available effects."
+  :type '(choice (const "page-curl")
+                 (const "crossfade")
+                 (const "slide-left")
+                 (const "scale-fade")
+                 (const "blur")
+                 (const "none")
+                 (string :tag "Other effect"))
+  :group 'neomacs-presentation)
+
+(defcustom neomacs-presentation-transition-duration 400
+  "Duration of the slide transition in milliseconds."
+  :type 'integer
+  :group 'neomacs-presentation)
+
+(defvar-local neomacs-presentation--scale 1.0
+  "Text scale currently applied to the buffer.")
+
+(defvar-local neomacs-presentation--remap-cookie nil
+  "Face remapping cookie for the scaled `default' face.")
+
+(defvar-local neomacs-presentation--scale-timer nil
+  "Timer driving the text scale animation.")
+
+(defvar-local neomacs-presentation--saved nil
+  "Alist of buffer settings to restore when presentation mode ends.")
+
+(defun neomacs-presentation--set-scale (scale)
+  "Scale the `default' face in the current buffer to SCALE."
+  (when neomacs-presentation--remap-cookie
+    (face-remap-remove-relative neomacs-presentation--remap-cookie))
+  (setq neomacs-presentation--remap-cookie
+        (and (/= scale 1.0)
+             (face-remap-add-relative 'default :height scale)))
+  (setq neomacs-presentation--scale scale))
+
+(defun neomacs-presentation--animate-scale (target)
+  "Animate the text scale of the current buffer towards TARGET.
+Uses an ease-out curve over `neomacs-presentation-scale-duration'."
+  (when (timerp neomacs-presentation--scale-timer)
+    (cancel-timer neomacs-presentation--scale-timer)
+    (setq neomacs-presentation--scale-timer nil))
+  (let ((duration (float neomacs-presentation-scale-duration)))
+    (if (<= duration 0)
+        (neomacs-presentation--set-scale target)
+      (let ((buffer (current-buffer))
+            (from neomacs-presentation--scale)
+            (start (float-time))
+            timer)
+        (setq timer
+              (run-at-time
+               0 (/ 1.0 60)
+               (lambda ()
+                 (if (not (buffer-live-p buffer))
+                     (cancel-timer timer)
+                   (with-current-buffer buffer
+                     (let* ((p (min 1.0 (/ (- (float-time) start) duration)))
+                            (eased (- 1.0 (expt (- 1.0 p) 3))))
+                       (neomacs-presentation--set-scale
+                        (+ from (* (- target from) eased)))
+                       (when (>= p 1.0)
+                         (cancel-timer timer)
+                         (setq neomacs-presentation--scale-timer nil))))))))
+        (setq neomacs-presentation--scale-timer timer)))))
+
+(defun neomacs-presentation--set-fringes (width)
+  "Set both fringes of windows showing the current buffer to WIDTH.
+A nil WIDTH restores the frame defaults."
+  (dolist (window (get-buffer-window-list nil nil t))
+    (set-window-fringes window width width)))
+
+(defun neomacs-presentation--transition ()
+  "Ask the display to animate the next change of the selected window."
+  (when (fboundp 'neomacs-start-buffer-transition)
+    (neomacs-start-buffer-transition
+     neomacs-presentation-transition
+     neomacs-presentation-transition-duration)))
+
+(defun neomacs-presentation--narrow-to-slide ()
+  "Narrow to the heading at or before point and its subtree.
+Return non-nil if there is such a heading."
+  (when (ignore-errors (outline-back-to-heading t) t)
+    (narrow-to-region (point)
+                      (save-excursion
+                        (outline-end-of-subtree)
+                        (if (eobp) (point) (1+ (point)))))
+    (outline-show-subtree)
+    (goto-char (point-min))
+    (set-window-start nil (point-min))
+    t))
+
+(defun neomacs-presentation--show-slide (pos)
+  "Display the slide whose heading starts at POS with a transition."
+  (neomacs-presentation--transition)
+  (widen)
+  (goto-char pos)
+  (neomacs-presentation--narrow-to-slide))
+
+(defun neomacs-presentation-next-slide ()
+  "Show the next slide."
+  (interactive)
+  (let ((end (point-max))
+        target)
+    (save-restriction
+      (widen)
+      (save-excursion
+        (goto-char end)
+        (unless (and (bolp) (outline-on-heading-p t))
+          (outline-next-heading))
+        (when (outline-on-heading-p t)
+          (setq target (point)))))
+    (if target
+        (neomacs-presentation--show-slide target)
+      (message "Last slide"))))
+
+(defun neomacs-presentation-previous-slide ()
+  "Show the previous slide."
+  (interactive)
+  (let ((start (point-min))
+        target)
+    (save-restriction
+      (widen)
+      (save-excursion
+        (goto-char start)
+        (let ((level (if (outline-on-heading-p t)
+                         (funcall outline-level)
+                       most-positive-fixnum)))
+          (while (and (not target) (outline-previous-heading))
+            (when (<= (funcall outline-level) level)
+              (setq target (point)))))))
+    (if target
+        (neomacs-presentation--show-slide target)
+      (message "First slide"))))
+
+(defvar-keymap neomacs-presentation-mode-map
+  :doc "Keymap for `neomacs-presentation-mode'."
+  "SPC"     #'neomacs-presentation-next-slide
+  "<right>" #'neomacs-presentation-next-slide
+  "<next>"  #'neomacs-presentation-next-slide
+  "DEL"     #'neomacs-presentation-previous-slide
+  "<left>"  #'neomacs-presentation-previous-slide
+  "<prior>" #'neomacs-presentation-previous-slide
+  "q"       #'neomacs-presentation-mode)
+
+(defun neomacs-presentation--start ()
+  "Set up the current buffer for presenting."
+  (setq neomacs-presentation--saved
+        `((mode-line-format . ,(and (local-variable-p 'mode-line-format)
+                                    (list mode-line-format)))
+          (display-line-numbers-mode . ,(bound-and-true-p
+                                         display-line-numbers-mode))
+          (buffer-read-only . ,buffer-read-only)
+          (point . ,(point-marker))))
+  (setq-local mode-line-format nil)
+  (when (bound-and-true-p display-line-numbers-mode)
+    (display-line-numbers-mode -1))
+  (neomacs-presentation--set-fringes 0)
+  (setq buffer-read-only t)
+  (neomacs-presentation--animate-scale neomacs-presentation-scale)
+  (unless (neomacs-presentation--narrow-to-slide)
+    (goto-char (point-min))
+    (outline-next-heading)
+    (neomacs-presentation--narrow-to-slide)))
+
+(defun neomacs-presentation--stop ()
+  "Restore the current buffer after presenting."
+  (let-alist neomacs-presentation--saved
+    (widen)
+    (if .mode-line-format
+        (setq-local mode-line-format (car .mode-line-format))
+      (kill-local-variable 'mode-line-format))
+    (when .display-line-numbers-mode
+      (display-line-numbers-mode 1))
+    (setq buffer-read-only .buffer-read-only)
+    (when (markerp .point)
+      (goto-char .point)
+      (set-marker .point nil)))
+  (setq neomacs-presentation--saved nil)
+  (neomacs-presentation--set-fringes nil)
+  (neomacs-presentation--animate-scale 1.0))
+
+;;;###autoload
+(define-minor-mode neomacs-presentation-mode
+  "Present the current Org or Outline buffer as a slide show.
+Text is smoothly scaled to `neomacs-presentation-scale', the mode
+line, fringes and line numbers are hidden, and the buffer is narrowed
+to one heading at a time.  Moving between slides plays
+`neomacs-presentation-transition'.
+
+\\{neomacs-presentation-mode-map}"
+  :lighter nil
+  :keymap neomacs-presentation-mode-map
+  (if neomacs-presentation-mode
+      (neomacs-presentation--start)
+    (neomacs-presentation--stop)))
+
+(provide 'neomacs-presentation)
+;;; neomacs-presentation.el ends here
@@ -417,6 +417,648 @@ is drawn by the render thread.  When disabled the title bar is hidden."
   :init-value nil
   (neomacs-set-titlebar-height (if neomacs-custom-titlebar-mode 30 0)))
 
+;;; Magnifier
+
+(declare-function neomacs-set-magnifier "neomacsterm.c"
+  (&optional enabled shape size zoom follow border-color))
+
+(defgroup neomacs-magnifier nil
+  "Magnifier lens overlay."
+  :group 'frames)
+
+(defun neomacs--apply-magnifier ()
+  "Send the current magnifier settings to the display engine."
+  (when (fboundp 'neomacs-set-magnifier)
+    (neomacs-set-magnifier (bound-and-true-p neomacs-magnifier-mode)
+                           (bound-and-true-p neomacs-magnifier-shape)
+                           (bound-and-true-p neomacs-magnifier-size)
+                           (bound-and-true-p neomacs-magnifier-zoom)
+                           (bound-and-true-p neomacs-magnifier-follow)
+                           (bound-and-true-p neomacs-magnifier-border-color))))
+
+(defun neomacs--set-magnifier-option (sym val)
+  "Set magnifier option SYM to VAL and apply it if the lens is shown."
+  (set-default sym val)
+  (when (bound-and-true-p neomacs-magnifier-mode)
+    (neomacs--apply-magnifier)))
+
+(defcustom neomacs-magnifier-shape 'circle
+  "Shape of the magnifier lens."
+  :type '(choice (const circle) (const rectangle))
+  :set #'neomacs--set-magnifier-option)
+
+(defcustom neomacs-magnifier-size 240
+  "Size of the magnifier lens in pixels.
+An integer is the diameter (or side) of the lens; a cons (WIDTH . HEIGHT)
+gives the size of a rectangular lens."
+  :type '(choice (integer :tag "Diameter")
+                 (cons (integer :tag "Width") (integer :tag "Height")))
+  :set #'neomacs--set-magnifier-option)
+
+(defcustom neomacs-magnifier-zoom 200
+  "Magnification of the lens, as a percentage (100-800)."
+  :type 'integer
+  :set #'neomacs--set-magnifier-option)
+
+(defcustom neomacs-magnifier-follow 'mouse
+  "What the magnifier lens follows: the mouse pointer or point."
+  :type '(choice (const mouse) (const point))
+  :set #'neomacs--set-magnifier-option)
+
+(defcustom neomacs-magnifier-border-color "#6699FF"
+  "Border color of the magnifier lens, as \"#RRGGBB\"."
+  :type 'string
+  :set #'neomacs--set-magnifier-option)
+
+(define-minor-mode neomacs-magnifier-mode
+  "Toggle the magnifier lens.
+The lens re-renders the area under the mouse pointer (or point, see
+`neomacs-magnifier-follow') enlarged by `neomacs-magnifier-zoom'."
+  :global t
+  :group 'neomacs-magnifier
+  (neomacs--apply-magnifier))
+
+;;; CRT effect
+
+(declare-function neomacs-set-crt-effect "neomacsterm.c"
+  (&optional enabled scanlines curvature glow aberration scope))
+
+(defgroup neomacs-crt nil
+  "CRT/retro post-processing effect."
+  :group 'frames)
+
+(defun neomacs--apply-crt ()
+  "Send the current CRT effect settings to the display engine."
+  (when (fboundp 'neomacs-set-crt-effect)
+    (neomacs-set-crt-effect (bound-and-true-p neomacs-crt-mode)
+                            (bound-and-true-p neomacs-crt-scanlines)
+                            (bound-and-true-p neomacs-crt-curvature)
+                            (bound-and-true-p neomacs-crt-glow)
+                            (bound-and-true-p neomacs-crt-aberration)
+                            (bound-and-true-p neomacs-crt-scope))))
+
+(defun neomacs--set-crt-option (sym val)
+  "Set CRT effect option SYM to VAL and apply it if the effect is on."
+  (set-default sym val)
+  (when (bound-and-true-p neomacs-crt-mode)
+    (neomacs--apply-crt)))
+
+(defcustom neomacs-crt-scanlines 0.5
+  "Intensity of the CRT scanlines, from 0.0 (none) to 1.0."
+  :type 'number
+  :set #'neomacs--set-crt-option)
+
+(defcustom neomacs-crt-curvature 0.3
+  "Intensity of the CRT barrel distortion, from 0.0 (flat) to 1.0."
+  :type 'number
+  :set #'neomacs--set-crt-option)
+
+(defcustom neomacs-crt-glow 0.3
+  "Intensity of the CRT phosphor glow, from 0.0 (none) to 1.0."
+  :type 'number
+  :set #'neomacs--set-crt-option)
+
+(defcustom neomacs-crt-aberration 0.2
+  "Intensity of the CRT chromatic aberration, from 0.0 (none) to 1.0."
+  :type 'number
+  :set #'neomacs--set-crt-option)
+
+(defcustom neomacs-crt-scope 'frame
+  "Where the CRT effect applies: the whole frame or terminal windows only."
+  :type '(choice (const :tag "Whole frame" frame)
+                 (const :tag "Terminal windows" terminal))
+  :set #'neomacs--set-crt-option)
+
+(defcustom neomacs-crt-presets
+  '((subtle :scanlines 0.25 :curvature 0.1 :glow 0.15 :aberration 0.05)
+    (classic :scanlines 0.5 :curvature 0.3 :glow 0.3 :aberration 0.2)
+    (arcade :scanlines 0.8 :curvature 0.6 :glow 0.6 :aberration 0.5))
+  "Named sets of CRT effect intensities for `neomacs-crt-apply-preset'.
+Each entry is (NAME . PLIST) with the keys :scanlines, :curvature,
+:glow and :aberration."
+  :type '(alist :key-type symbol :value-type plist))
+
+(define-minor-mode neomacs-crt-mode
+  "Toggle the CRT/retro post-processing effect.
+Renders scanlines, barrel distortion, phosphor glow and chromatic
+aberration over the whole frame, or only over terminal windows (see
+`neomacs-crt-scope')."
+  :global t
+  :group 'neomacs-crt
+  (neomacs--apply-crt))
+
+(defun neomacs-crt-apply-preset (preset)
+  "Set the CRT effect intensities from PRESET in `neomacs-crt-presets'.
+Also turns on `neomacs-crt-mode'."
+  (interactive
+   (list (intern (completing-read "CRT preset: "
+                                  (mapcar #'car neomacs-crt-presets) nil t))))
+  (let ((plist (or (cdr (assq preset neomacs-crt-presets))
+                   (user-error "Unknown CRT preset: %s" preset))))
+    (dolist (option '((:scanlines . neomacs-crt-scanlines)
+                      (:curvature . neomacs-crt-curvature)
+                      (:glow . neomacs-crt-glow)
+                      (:aberration . neomacs-crt-aberration)))
+      (when (plist-member plist (car option))
+        (set-default (cdr option) (plist-get plist (car option)))))
+    (if neomacs-crt-mode
+        (neomacs--apply-crt)
+      (neomacs-crt-mode 1))))
+
+;;; Presentation and GPU selection
+
+(declare-function neomacs-set-present-mode "neomacsterm.c"
+  (mode &optional frame-latency))
+(declare-function neomacs-set-gpu-preference "neomacsterm.c" (preference))
+
+(defun neomacs--apply-present-mode ()
+  "Send the current present mode settings to the display engine."
+  (when (fboundp 'neomacs-set-present-mode)
+    (neomacs-set-present-mode (bound-and-true-p neomacs-present-mode)
+                              (bound-and-true-p neomacs-frame-latency))))
+
+(defun neomacs--set-present-option (sym val)
+  "Set present mode option SYM to VAL and apply it."
+  (set-default sym val)
+  (neomacs--apply-present-mode))
+
+(defcustom neomacs-present-mode 'fifo
+  "How frames are presented to the screen.
+`fifo' waits for vertical sync, `mailbox' syncs without blocking,
+`immediate' presents at once (lowest latency, may tear) and
+`fifo-relaxed' syncs but tears when a frame is late.  Modes the GPU
+does not support fall back to the closest supported one."
+  :type '(choice (const :tag "VSync" fifo)
+                 (const :tag "Mailbox (low-latency VSync)" mailbox)
+                 (const :tag "Immediate (may tear)" immediate)
+                 (const :tag "Relaxed VSync" fifo-relaxed))
+  :group 'frames
+  :set #'neomacs--set-present-option)
+
+(defcustom neomacs-frame-latency 2
+  "Maximum number of frames queued ahead of the display (1 to 3).
+Lower values reduce input latency at the cost of throughput."
+  :type 'integer
+  :group 'frames
+  :set #'neomacs--set-present-option)
+
+(defcustom neomacs-gpu-preference nil
+  "GPU the display engine prefers when several are available.
+nil uses the NEOMACS_GPU environment variable.  Only takes effect when
+the GPU device is created, so set it in your early init file."
+  :type '(choice (const :tag "Default" nil)
+                 (const :tag "Integrated (low power)" integrated)
+                 (const :tag "Discrete (high performance)" discrete))
+  :group 'frames
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (fboundp 'neomacs-set-gpu-preference)
+           (neomacs-set-gpu-preference val))))
+
+;;; Background throttling
+
+(declare-function neomacs-set-background-throttle "neomacsterm.c"
+  (enabled &optional unfocused-fps hidden-fps pause-video))
+
+(defun neomacs--apply-background-throttle ()
+  "Send the current background throttling settings to the display engine."
+  (when (fboundp 'neomacs-set-background-throttle)
+    (neomacs-set-background-throttle
+     (bound-and-true-p neomacs-background-throttle)
+     (bound-and-true-p neomacs-background-fps)
+     (bound-and-true-p neomacs-hidden-fps)
+     (bound-and-true-p neomacs-background-pause-video))))
+
+(defun neomacs--set-background-option (sym val)
+  "Set background throttling option SYM to VAL and apply it."
+  (set-default sym val)
+  (neomacs--apply-background-throttle))
+
+(defcustom neomacs-background-throttle t
+  "Non-nil means throttle rendering while Neomacs is in the background.
+While no Neomacs frame has focus, or the frame is hidden, the cursor
+stops blinking, continuous effects pause and frames are rendered at
+`neomacs-background-fps' (`neomacs-hidden-fps' while hidden).  Full
+speed resumes as soon as a frame gets focus."
+  :type 'boolean
+  :group 'frames
+  :set #'neomacs--set-background-option)
+
+(defcustom neomacs-background-fps 10
+  "Frames per second rendered while no Neomacs frame has focus."
+  :type 'integer
+  :group 'frames
+  :set #'neomacs--set-background-option)
+
+(defcustom neomacs-hidden-fps 1
+  "Frames per second rendered while the frame is hidden."
+  :type 'integer
+  :group 'frames
+  :set #'neomacs--set-background-option)
+
+(defcustom neomacs-background-pause-video nil
+  "Non-nil means pause playing videos while Neomacs is in the background.
+Videos paused this way resume when a frame gets focus."
+  :type 'boolean
+  :group 'frames
+  :set #'neomacs--set-background-option)
+
+;;; Monitors
+
+(defun neomacs-monitor-attributes (monitor &optional frame)
+  "Return the attributes of MONITOR on FRAME's display.
+MONITOR is an index into `display-monitor-attributes-list' (0 is the
+primary monitor) or a monitor name.  Return nil if there is no such
+monitor."
+  (let ((monitors (display-monitor-attributes-list frame)))
+    (if (integerp monitor)
+        (nth monitor monitors)
+      (seq-find (lambda (m) (equal (alist-get 'name m) monitor)) monitors))))
+
+(defun neomacs-move-frame-to-monitor (monitor &optional frame)
+  "Center FRAME in the work area of MONITOR.
+MONITOR is a monitor index or name, as for `neomacs-monitor-attributes'.
+Interactively, read the monitor name with completion."
+  (interactive
+   (list (completing-read
+          "Monitor: "
+          (delq nil (mapcar (lambda (m) (alist-get 'name m))
+                            (display-monitor-attributes-list)))
+          nil t)))
+  (let* ((frame (or frame (selected-frame)))
+         (attributes (or (neomacs-monitor-attributes monitor frame)
+                         (user-error "No monitor %s" monitor)))
+         (workarea (alist-get 'workarea attributes))
+         (width (frame-pixel-width frame))
+         (height (frame-pixel-height frame)))
+    (set-frame-position frame
+                        (+ (nth 0 workarea) (max 0 (/ (- (nth 2 workarea) width) 2)))
+                        (+ (nth 1 workarea) (max 0 (/ (- (nth 3 workarea) height) 2))))))
+
+(defun neomacs--place-frame-on-monitor (frame)
+  "Move FRAME to the monitor named by its `neomacs-monitor' parameter.
+Used so `(make-frame \='((neomacs-monitor . 1)))' opens a frame on the
+second monitor."
+  (let ((monitor (frame-parameter frame 'neomacs-monitor)))
+    (when (and monitor (neomacs-monitor-attributes monitor frame))
+      (neomacs-move-frame-to-monitor monitor frame))))
+
+(add-hook 'after-make-frame-functions #'neomacs--place-frame-on-monitor)
+
+;;; Window icon and attention
+
+(declare-function neomacs-set-window-icon "neomacsterm.c" (&optional data))
+(declare-function neomacs-request-attention "neomacsterm.c" (&optional urgent))
+(declare-function neomacs-cancel-attention "neomacsterm.c" ())
+(declare-function image-convert "image-converter" (image &optional image-format))
+
+(defconst neomacs--icon-formats '("png" "ico" "jpg" "jpeg" "bmp" "gif")
+  "File extensions of icon images the display engine decodes itself.")
+
+(defun neomacs-set-frame-icon (file)
+  "Use the image in FILE as the icon of the Neomacs windows.
+PNG, ICO, JPEG, BMP and GIF files are used as they are; other formats,
+such as SVG, are converted to PNG with `image-convert'.  If FILE is nil,
+restore the Neomacs logo."
+  (interactive "fIcon file: ")
+  (neomacs-set-window-icon
+   (when file
+     (let ((file (expand-file-name file)))
+       (if (member (downcase (or (file-name-extension file) ""))
+                   neomacs--icon-formats)
+           (with-temp-buffer
+             (set-buffer-multibyte nil)
+             (insert-file-contents-literally file)
+             (buffer-string))
+         (require 'image-converter)
+         (image-convert file))))))
+
+(defun neomacs--set-icon-type (value)
+  "Apply the `icon-type' frame parameter VALUE.
+A string names an icon file; any other value restores the Neomacs logo."
+  (when (fboundp 'neomacs-set-window-icon)
+    (condition-case err
+        (neomacs-set-frame-icon (and (stringp value) value))
+      (error (message "Cannot set icon from %s: %s"
+                      value (error-message-string err))))))
+
+;;; Popup frames
+
+(defcustom neomacs-popup-frame-width 80
+  "Width in columns of popup minibuffer frames."
+  :type 'natnum
+  :group 'frames)
+
+(defvar neomacs-popup-frame-parameters
+  '((minibuffer . only)
+    (neomacs-popup . t)
+    (undecorated . t)
+    (skip-taskbar . t)
+    (unsplittable . t)
+    (no-other-frame . t)
+    (menu-bar-lines . 0)
+    (tool-bar-lines . 0)
+    (tab-bar-lines . 0)
+    (vertical-scroll-bars . nil)
+    (internal-border-width . 8)
+    (height . 1))
+  "Frame parameters of popup minibuffer frames.
+The `neomacs-popup' parameter gives a frame an undecorated window of
+its own that stays on top, centered on the focused monitor.")
+
+(defun neomacs-popup-frame-call (function)
+  "Call FUNCTION in a new popup minibuffer frame and return its value.
+The frame is centered on the focused monitor and grows with the
+minibuffer contents, e.g. a vertical completion list.  It is deleted
+when FUNCTION returns or exits non-locally, for instance when the
+minibuffer is aborted."
+  (let* ((frame (make-frame `((width . ,neomacs-popup-frame-width)
+                              ,@neomacs-popup-frame-parameters)))
+         (resize-mini-frames t))
+    (unwind-protect
+        (with-selected-frame frame
+          (select-frame-set-input-focus frame)
+          (funcall function))
+      (when (frame-live-p frame)
+        (delete-frame frame t)))))
+
+(defun neomacs-popup-launcher (&optional command)
+  "Run COMMAND interactively in a popup minibuffer frame.
+COMMAND defaults to `execute-extended-command'.  Meant to be bound to
+a desktop shortcut running
+
+  emacsclient -e \='(neomacs-popup-launcher)\='
+
+so commands can be run without switching to an Emacs frame.  Quitting
+the minibuffer just closes the popup."
+  (interactive)
+  (condition-case nil
+      (neomacs-popup-frame-call
+       (lambda () (call-interactively (or command #'execute-extended-command))))
+    (quit nil)))
+
+(declare-function neomacs-place-popup "neomacsfns.c"
+                  (anchor width height &optional side gap frame))
+
+(defun neomacs-popup-position-at-point (width height &optional side gap)
+  "Return where a WIDTH x HEIGHT popup showing at point goes, as (X . Y).
+The popup is placed on SIDE of the character at point (`below' by
+default, else `above', `right' or `left'), GAP pixels away, flipped
+and shifted by `neomacs-place-popup' to stay on screen and off the
+minibuffer.  X and Y are relative to the selected frame, as for a
+child frame's `left' and `top' parameters.  Return nil if point is
+not visible."
+  (when-let* ((posn (posn-at-point))
+              (xy (posn-x-y posn)))
+    (let* ((edges (window-inside-pixel-edges))
+           (size (posn-object-width-height posn))
+           (anchor (list (+ (nth 0 edges) (car xy))
+                         (+ (nth 1 edges) (cdr xy))
+                         (max 1 (or (car size) (frame-char-width)))
+                         (max 1 (or (cdr size) (line-pixel-height)))))
+           (place (neomacs-place-popup anchor width height side gap)))
+      (cons (nth 0 place) (nth 1 place)))))
+
+;;; GPU memory
+
+(declare-function neomacs-set-memory-budget "neomacsterm.c"
+  (&optional images glyphs snapshots staging))
+(declare-function neomacs-memory-stats "neomacsterm.c" ())
+
+(defun neomacs--apply-memory-budget ()
+  "Send the current memory budgets to the display engine."
+  (when (fboundp 'neomacs-set-memory-budget)
+    (neomacs-set-memory-budget
+     (bound-and-true-p neomacs-image-cache-budget)
+     (bound-and-true-p neomacs-glyph-cache-budget)
+     (bound-and-true-p neomacs-snapshot-budget)
+     (bound-and-true-p neomacs-staging-buffer-budget))))
+
+(defun neomacs--set-memory-option (sym val)
+  "Set memory budget option SYM to VAL and apply it."
+  (set-default sym val)
+  (neomacs--apply-memory-budget))
+
+(defcustom neomacs-image-cache-budget 64
+  "GPU memory budget of the image cache, in megabytes.
+When it is exceeded the least recently displayed images are evicted and
+decoded again when next displayed."
+  :type 'integer
+  :group 'frames
+  :set #'neomacs--set-memory-option)
+
+(defcustom neomacs-glyph-cache-budget 32
+  "GPU memory budget of the glyph cache, in megabytes."
+  :type 'integer
+  :group 'frames
+  :set #'neomacs--set-memory-option)
+
+(defcustom neomacs-snapshot-budget 128
+  "GPU memory budget of window snapshots held by transitions, in megabytes.
+When it is exceeded the oldest transitions end early."
+  :type 'integer
+  :group 'frames
+  :set #'neomacs--set-memory-option)
+
+(defcustom neomacs-staging-buffer-budget 64
+  "Memory kept in reusable image and video upload buffers, in megabytes."
+  :type 'integer
+  :group 'frames
+  :set #'neomacs--set-memory-option)
+
+(defun neomacs-memory-report ()
+  "Show the GPU memory usage of the display engine."
+  (interactive)
+  (let ((stats (and (fboundp 'neomacs-memory-stats) (neomacs-memory-stats))))
+    (if (not stats)
+        (message "Display engine memory stats unavailable")
+      (let ((get (lambda (key) (or (alist-get key stats) 0)))
+            (mb (lambda (bytes) (/ bytes 1048576.0))))
+        (message (concat "Images %.1f/%.0fMB (%d, %d evicted), "
+                         "glyphs %.1f/%.0fMB (%d), video %.1fMB, "
+                         "snapshots %.1fMB, upload buffers %.1fMB")
+                 (funcall mb (funcall get 'image-bytes))
+                 (funcall mb (funcall get 'image-budget))
+                 (funcall get 'image-count)
+                 (funcall get 'image-evictions)
+                 (funcall mb (funcall get 'glyph-bytes))
+                 (funcall mb (funcall get 'glyph-budget))
+                 (funcall get 'glyph-count)
+                 (funcall mb (funcall get 'video-bytes))
+                 (funcall mb (funcall get 'snapshot-bytes))
+                 (funcall mb (funcall get 'staging-bytes)))))))
+
+;;; Startup time
+
+(declare-function neomacs-startup-report "neomacsterm.c" ())
+
+(defun neomacs-show-startup-report ()
+  "Show how long each step of starting the display engine took."
+  (interactive)
+  (let ((report (and (fboundp 'neomacs-startup-report)
+                     (neomacs-startup-report))))
+    (if (not report)
+        (message "Display engine startup report unavailable")
+      (with-help-window "*Neomacs Startup*"
+        (princ report)))))
+
+;;; Buffer transitions
+
+(declare-function neomacs-set-buffer-transition "neomacsterm.c"
+  (effect &optional duration auto))
+(declare-function neomacs-prepare-buffer-transition "neomacsterm.c" ())
+(declare-function neomacs-trigger-buffer-transition "neomacsterm.c" ())
+
+(defvar neomacs--buffer-transition-sent nil
+  "Effect, duration and mode state last sent to the display engine.")
+
+(defun neomacs--send-buffer-transition (effect)
+  "Send buffer transition EFFECT and the current settings to the display."
+  (let ((settings (list effect
+                        (bound-and-true-p neomacs-buffer-transition-duration)
+                        (bound-and-true-p neomacs-buffer-transition-mode))))
+    (when (and (fboundp 'neomacs-set-buffer-transition)
+               (not (equal settings neomacs--buffer-transition-sent)))
+      (apply #'neomacs-set-buffer-transition settings)
+      (setq neomacs--buffer-transition-sent settings))))
+
+(defun neomacs--command-buffer-transition ()
+  "Select the buffer transition effect for `this-command'.
+See `neomacs-buffer-transition-command-effects'."
+  (neomacs--send-buffer-transition
+   (or (and (symbolp this-command)
+            (alist-get this-command
+                       (bound-and-true-p
+                        neomacs-buffer-transition-command-effects)))
+       (bound-and-true-p neomacs-buffer-transition-effect)
+       "crossfade")))
+
+(defun neomacs--set-buffer-transition-option (sym val)
+  "Set buffer transition option SYM to VAL and apply it."
+  (set-default sym val)
+  (neomacs--send-buffer-transition
+   (or (bound-and-true-p neomacs-buffer-transition-effect) "crossfade")))
+
+(defcustom neomacs-buffer-transition-effect "crossfade"
+  "Effect animating a window from a snapshot of its old buffer.
+Used by `neomacs-with-buffer-transition' and
+`neomacs-buffer-transition-mode'.  \"flip\" turns the window over like
+a card and \"cube\" rotates it like the face of a cube."
+  :type '(choice (const "crossfade")
+                 (const "slide-left")
+                 (const "slide-right")
+                 (const "slide-up")
+                 (const "slide-down")
+                 (const "scale-fade")
+                 (const "push")
+                 (const "page-curl")
+                 (const "flip")
+                 (const "cube")
+                 (const "none"))
+  :group 'frames
+  :set #'neomacs--set-buffer-transition-option)
+
+(defcustom neomacs-buffer-transition-duration 200
+  "Duration of buffer transitions in milliseconds."
+  :type 'integer
+  :group 'frames
+  :set #'neomacs--set-buffer-transition-option)
+
+(defcustom neomacs-buffer-transition-command-effects nil
+  "Alist of (COMMAND . EFFECT) overriding `neomacs-buffer-transition-effect'.
+Buffer changes made by COMMAND are animated with EFFECT, for example
+\='((next-buffer . \"cube\") (previous-buffer . \"cube\")).  See
+`neomacs-buffer-transition-effect' for the effects."
+  :type '(alist :key-type (symbol :tag "Command")
+                :value-type (string :tag "Effect"))
+  :group 'frames)
+
+(defmacro neomacs-with-buffer-transition (&rest body)
+  "Run BODY, animating the selected window from its current contents.
+The window is snapshotted before BODY runs and animated with the effect
+for the current command once BODY has changed it."
+  (declare (indent 0) (debug t))
+  `(progn
+     (when (fboundp 'neomacs-prepare-buffer-transition)
+       (neomacs-prepare-buffer-transition))
+     (prog1 (progn ,@body)
+       (when (fboundp 'neomacs-trigger-buffer-transition)
+         (neomacs--command-buffer-transition)
+         (neomacs-trigger-buffer-transition)))))
+
+(define-minor-mode neomacs-buffer-transition-mode
+  "Animate windows whose buffer changes with `neomacs-buffer-transition-effect'.
+Each window is animated on its own, clipped to the window; the
+minibuffer and popup frames are not animated.  Commands listed in
+`neomacs-buffer-transition-command-effects' use their own effect."
+  :global t
+  :group 'frames
+  (if neomacs-buffer-transition-mode
+      (add-hook 'post-command-hook #'neomacs--command-buffer-transition)
+    (remove-hook 'post-command-hook #'neomacs--command-buffer-transition))
+  (neomacs--set-buffer-transition-option
+   'neomacs-buffer-transition-mode neomacs-buffer-transition-mode))
+
+;;; Animation timeline
+
+(declare-function neomacs-animate-window "neomacsterm.c"
+                  (window property keyframes &optional delay iterations))
+
+(declare-function neomacs-set-animation-curve "neomacsterm.c" (target curve))
+
+(defcustom neomacs-animation-curves nil
+  "Easing curves of Neomacs animations, as an alist of (TARGET . CURVE).
+TARGET is `cursor', `scroll', `crossfade' or `buffer-transition'.
+CURVE is (spring STIFFNESS DAMPING MASS), (cubic-bezier X1 Y1 X2 Y2)
+or an easing symbol; see `neomacs-set-animation-curve'.  Targets not
+listed use their default easing."
+  :type '(alist :key-type (choice (const cursor)
+                                  (const scroll)
+                                  (const crossfade)
+                                  (const buffer-transition))
+                :value-type (choice (list :tag "Spring"
+                                          (const spring)
+                                          (number :tag "Stiffness")
+                                          (number :tag "Damping")
+                                          (number :tag "Mass"))
+                                    (list :tag "Cubic Bezier"
+                                          (const cubic-bezier)
+                                          (number :tag "X1") (number :tag "Y1")
+                                          (number :tag "X2") (number :tag "Y2"))
+                                    (const linear)
+                                    (const ease-out)
+                                    (const ease-in)
+                                    (const ease-in-out)
+                                    (const ease-out-back)))
+  :group 'frames
+  :set (lambda (symbol value)
+         (let ((old (and (boundp symbol) (symbol-value symbol))))
+           (set-default symbol value)
+           (when (fboundp 'neomacs-set-animation-curve)
+             ;; Only touch listed targets, and those no longer listed
+             (dolist (target '(cursor scroll crossfade buffer-transition))
+               (when (or (assq target value) (assq target old))
+                 (neomacs-set-animation-curve
+                  target (cdr (assq target value)))))))))
+
+(defun neomacs-shake-window (&optional window amplitude)
+  "Shake WINDOW horizontally by AMPLITUDE pixels (default 8).
+WINDOW defaults to the selected window."
+  (interactive)
+  (when (fboundp 'neomacs-animate-window)
+    (let ((a (or amplitude 8)))
+      (neomacs-animate-window window 'offset-x
+                              `((0 0) (50 ,a linear) (100 ,(- a) linear)
+                                (150 ,(/ a 2.0) linear) (200 0 ease-out))))))
+
+(defun neomacs-fade-in-window (&optional window duration)
+  "Fade WINDOW in over DURATION milliseconds (default 250).
+WINDOW defaults to the selected window."
+  (interactive)
+  (when (fboundp 'neomacs-animate-window)
+    (neomacs-animate-window window 'opacity
+                            `((0 0.0) (,(or duration 250) 1.0 ease-out)))))
+
 ;;; Borderless mode toggle
 
 (defun neomacs-toggle-decorations (&optional frame)
@@ -1178,6 +1820,4264 @@ text content for a focused writing experience."
                 neomacs-zen-content-width nil)
             val))))
 
+;;; Typewriter scrolling and zen writing
+
+(declare-function neomacs-set-typewriter-scrolling "neomacsterm.c" (enabled))
+(declare-function neomacs-set-scroll-anchoring "neomacsterm.c" (enabled))
+(declare-function neomacs-set-centered-column "neomacsterm.c"
+  (enabled &optional columns margin-color))
+
+(defgroup neomacs-writing nil
+  "Display-level writing aids."
+  :group 'frames)
+
+(define-minor-mode neomacs-typewriter-scrolling-mode
+  "Toggle typewriter scrolling.
+The display engine keeps the line containing point vertically centered
+in every window, scrolling smoothly as point moves."
+  :global t
+  :group 'neomacs-writing
+  (when (fboundp 'neomacs-set-typewriter-scrolling)
+    (neomacs-set-typewriter-scrolling neomacs-typewriter-scrolling-mode)
+    (force-window-update)))
+
+(define-minor-mode neomacs-scroll-anchoring-mode
+  "Toggle scroll anchoring.
+When a window is resized, or text between its start and point changes
+height (a fold opens or closes, an image is inserted), the display
+engine keeps the line at point on the same screen row instead of
+letting the text jump.  Commands that move point or scroll are not
+affected."
+  :global t
+  :init-value t
+  :group 'neomacs-writing
+  (when (fboundp 'neomacs-set-scroll-anchoring)
+    (neomacs-set-scroll-anchoring neomacs-scroll-anchoring-mode)))
+
+(defun neomacs--apply-zen-writing ()
+  "Send the current zen writing settings to the display engine."
+  (when (fboundp 'neomacs-set-centered-column)
+    (neomacs-set-centered-column (bound-and-true-p neomacs-zen-writing-mode)
+                                 (bound-and-true-p neomacs-zen-writing-width)
+                                 (bound-and-true-p
+                                  neomacs-zen-writing-margin-color))
+    (force-window-update)))
+
+(defun neomacs--set-zen-writing-option (sym val)
+  "Set zen writing option SYM to VAL and apply it if the mode is on."
+  (set-default sym val)
+  (when (bound-and-true-p neomacs-zen-writing-mode)
+    (neomacs--apply-zen-writing)))
+
+(defcustom neomacs-zen-writing-width 80
+  "Width of the centered text column in `neomacs-zen-writing-mode'."
+  :type 'natnum
+  :set #'neomacs--set-zen-writing-option)
+
+(defcustom neomacs-zen-writing-margin-color nil
+  "Color of the space beside the centered text column, as "#RRGGBB".
+nil uses the window background."
+  :type '(choice (const :tag "Window background" nil)
+                 (string :tag "Color"))
+  :set #'neomacs--set-zen-writing-option)
+
+(define-minor-mode neomacs-zen-writing-mode
+  "Toggle zen writing.
+The display engine narrows the text of every window to a column of
+`neomacs-zen-writing-width' characters centered horizontally, without
+changing window margins.  See also `neomacs-zen-mode', which only dims
+the sides of the window."
+  :global t
+  :group 'neomacs-writing
+  (neomacs--apply-zen-writing))
+
+;;; Inline suggestions (ghost text)
+
+(declare-function neomacs-accept-ghost-text "neomacsterm.c" (&optional how))
+(declare-function neomacs-dismiss-ghost-text "neomacsterm.c" ())
+
+(defun neomacs-ghost-text-accept ()
+  "Insert the whole inline suggestion shown after point."
+  (interactive)
+  (neomacs-accept-ghost-text nil))
+
+(defun neomacs-ghost-text-accept-word ()
+  "Insert the next word of the inline suggestion shown after point."
+  (interactive)
+  (neomacs-accept-ghost-text 'word))
+
+(defun neomacs-ghost-text-accept-line ()
+  "Insert the current line of the inline suggestion shown after point."
+  (interactive)
+  (neomacs-accept-ghost-text 'line))
+
+(defun neomacs-ghost-text-dismiss ()
+  "Remove the inline suggestion shown after point."
+  (interactive)
+  (neomacs-dismiss-ghost-text))
+
+;;; Annotations (margin notes)
+
+(declare-function neomacs-annotation-add "neomacsterm.c"
+                  (pos text &optional icon fg bg style))
+(declare-function neomacs-annotation-clear "neomacsterm.c" (&optional buffer))
+(declare-function neomacs-annotation-adjust "neomacsterm.c" (beg end old-len))
+
+(defface neomacs-annotation
+  '((((background dark)) :foreground "#e0e0e0" :background "#303040")
+    (t :foreground "#202020" :background "#fff8c0"))
+  "Default face of notes added with `neomacs-annotate'.
+Only the foreground and background colors are used."
+  :group 'frames)
+
+(defun neomacs--annotation-color (face attribute)
+  "Return the ATTRIBUTE color of FACE as a \"#rrggbb\" string, or nil."
+  (let* ((color (face-attribute face attribute nil t))
+         (values (and (stringp color) (color-values color))))
+    (when values
+      (apply #'format "#%02x%02x%02x"
+             (mapcar (lambda (v) (ash v -8)) values)))))
+
+(defun neomacs--annotation-kill-buffer ()
+  "Drop the annotations of the buffer being killed."
+  (neomacs-annotation-clear))
+
+(defun neomacs-annotate (pos text &optional icon face style)
+  "Attach the note TEXT to position POS of the current buffer.
+ICON is an optional short string shown before TEXT.  FACE supplies
+the note's colors and defaults to `neomacs-annotation'.  STYLE
+`card' floats the note over the right edge of the text; otherwise it
+is drawn in the right margin when the window has one.  Notes follow
+edits to the buffer.  Interactively, annotate point.
+Returns the annotation id for `neomacs-annotation-remove'."
+  (interactive (list (point) (read-string "Note: ")))
+  (let ((face (or face 'neomacs-annotation)))
+    (add-hook 'after-change-functions #'neomacs-annotation-adjust nil t)
+    (add-hook 'kill-buffer-hook #'neomacs--annotation-kill-buffer nil t)
+    (neomacs-annotation-add pos text icon
+                            (neomacs--annotation-color face :foreground)
+                            (neomacs--annotation-color face :background)
+                            style)))
+
+(defun neomacs-annotations-clear ()
+  "Remove all notes from the current buffer."
+  (interactive)
+  (neomacs-annotation-clear)
+  (remove-hook 'after-change-functions #'neomacs-annotation-adjust t)
+  (remove-hook 'kill-buffer-hook #'neomacs--annotation-kill-buffer t))
+
+;;; Scroll bar marks
+
+(declare-function neomacs-set-scroll-bar-marks "neomacsterm.c"
+                  (category positions &optional color buffer))
+(declare-function neomacs-clear-scroll-bar-marks "neomacsterm.c" (&optional buffer))
+(declare-function neomacs-scroll-bar-mark-at "neomacsterm.c" (window y height))
+(declare-function bookmark-get-filename "bookmark" (bookmark-name-or-record))
+(declare-function bookmark-get-position "bookmark" (bookmark-name-or-record))
+(declare-function flymake-diagnostics "flymake" (&optional beg end))
+(declare-function flymake-diagnostic-beg "flymake" (diag))
+(defvar bookmark-alist)
+(defvar flymake-mode)
+
+(defcustom neomacs-scroll-bar-mark-colors
+  '((bookmarks . "#4fa3ff")
+    (mark-ring . "#9a9a9a")
+    (diagnostics . "#ff5555")
+    (search . "#ffc000"))
+  "Colors of the tick marks `neomacs-scroll-bar-marks-mode' draws.
+Each element is (CATEGORY . COLOR) with COLOR a \"#rrggbb\" string.
+Categories not listed here are not drawn by the mode; other packages
+can publish their own with `neomacs-set-scroll-bar-marks'."
+  :type '(alist :key-type symbol :value-type string)
+  :group 'frames)
+
+(defvar neomacs--scroll-bar-marks-timer nil
+  "Idle timer refreshing scroll bar marks of the current buffer.")
+
+(defun neomacs--scroll-bar-marks-set (category positions)
+  "Publish POSITIONS as CATEGORY if it has a color, else remove it."
+  (let ((color (alist-get category neomacs-scroll-bar-mark-colors)))
+    (neomacs-set-scroll-bar-marks category (and color positions) color)))
+
+(defun neomacs--scroll-bar-marks-refresh ()
+  "Refresh bookmark, mark ring and diagnostic marks of the current buffer."
+  (when (fboundp 'neomacs-set-scroll-bar-marks)
+    (neomacs--scroll-bar-marks-set
+     'bookmarks
+     (when (and buffer-file-name (boundp 'bookmark-alist))
+       (let ((file (expand-file-name buffer-file-name)))
+         (delq nil
+               (mapcar (lambda (bm)
+                         (and (equal (ignore-errors
+                                       (expand-file-name
+                                        (bookmark-get-filename bm)))
+                                     file)
+                              (bookmark-get-position bm)))
+                       bookmark-alist)))))
+    (neomacs--scroll-bar-marks-set
+     'mark-ring
+     (delq nil (mapcar #'marker-position (cons (mark-marker) mark-ring))))
+    (neomacs--scroll-bar-marks-set
+     'diagnostics
+     (when (bound-and-true-p flymake-mode)
+       (mapcar #'flymake-diagnostic-beg (flymake-diagnostics))))))
+
+(defun neomacs--scroll-bar-marks-search ()
+  "Publish the positions of the current isearch matches."
+  (let (matches)
+    (when (and (fboundp 'neomacs-set-scroll-bar-marks)
+               (> (length isearch-string) 0))
+      (save-excursion
+        (goto-char (point-min))
+        (let ((search (isearch-search-fun))
+              (count 0))
+          (while (and (< count 1000)
+                      (not (eobp))
+                      (ignore-errors (funcall search isearch-string nil t)))
+            (push (match-beginning 0) matches)
+            (setq count (1+ count))
+            (when (= (match-beginning 0) (match-end 0))
+              (forward-char 1)))))
+      (neomacs--scroll-bar-marks-set 'search matches))))
+
+(defun neomacs--scroll-bar-marks-search-end ()
+  "Remove the isearch match marks."
+  (when (fboundp 'neomacs-set-scroll-bar-marks)
+    (neomacs-set-scroll-bar-marks 'search nil)))
+
+(defun neomacs-scroll-bar-jump-to-mark (event)
+  "Move point to the scroll bar mark clicked in EVENT.
+If no mark is under the click, run the normal scroll bar binding."
+  (interactive "e")
+  (let* ((posn (event-start event))
+         (window (posn-window posn))
+         (portion-whole (nth 2 posn))
+         (pos (and (windowp window) (consp portion-whole)
+                   (neomacs-scroll-bar-mark-at window
+                                               (car portion-whole)
+                                               (cdr portion-whole)))))
+    (if pos
+        (progn
+          (select-window window)
+          (goto-char pos)
+          (recenter))
+      (let ((command (lookup-key global-map
+                                 (vector 'vertical-scroll-bar (car event)))))
+        (when (commandp command)
+          (funcall command event))))))
+
+(defvar-keymap neomacs-scroll-bar-marks-mode-map
+  :doc "Keymap for `neomacs-scroll-bar-marks-mode'."
+  "<vertical-scroll-bar> <down-mouse-1>" #'neomacs-scroll-bar-jump-to-mark)
+
+(define-minor-mode neomacs-scroll-bar-marks-mode
+  "Show bookmarks, the mark ring, diagnostics and search matches on the scroll bar.
+Each is drawn as a colored tick at its relative position in the
+buffer, with colors from `neomacs-scroll-bar-mark-colors'.  Clicking
+a tick moves point there."
+  :global t
+  :group 'frames
+  :keymap neomacs-scroll-bar-marks-mode-map
+  (when (timerp neomacs--scroll-bar-marks-timer)
+    (cancel-timer neomacs--scroll-bar-marks-timer)
+    (setq neomacs--scroll-bar-marks-timer nil))
+  (if neomacs-scroll-bar-marks-mode
+      (progn
+        (setq neomacs--scroll-bar-marks-timer
+              (run-with-idle-timer 0.5 t #'neomacs--scroll-bar-marks-refresh))
+        (add-hook 'isearch-update-post-hook #'neomacs--scroll-bar-marks-search)
+        (add-hook 'isearch-mode-end-hook #'neomacs--scroll-bar-marks-search-end)
+        (add-hook 'kill-buffer-hook #'neomacs-clear-scroll-bar-marks))
+    (remove-hook 'kill-buffer-hook #'neomacs-clear-scroll-bar-marks)
+    (remove-hook 'isearch-update-post-hook #'neomacs--scroll-bar-marks-search)
+    (remove-hook 'isearch-mode-end-hook #'neomacs--scroll-bar-marks-search-end)
+    (when (fboundp 'neomacs-set-scroll-bar-marks)
+      (dolist (buffer (buffer-list))
+        (dolist (category '(bookmarks mark-ring diagnostics search))
+          (neomacs-set-scroll-bar-marks category nil nil buffer))))))
+
+;;; Rectangular region
+
+(declare-function neomacs-set-rectangle-region "neomacsterm.c"
+                  (window &optional start end left right bg
+                          cursor-column cursor-color))
+(declare-function rectangle--pos-cols "rect" (start end &optional window))
+
+(defcustom neomacs-native-rectangle-region t
+  "Non-nil means the layout engine draws `rectangle-mark-mode' regions.
+The rectangle is highlighted as one block spanning its columns,
+including the space past the end of short lines, instead of with
+per-line overlays.  A zero-width rectangle shows an insertion cursor
+on each of its lines."
+  :type 'boolean
+  :group 'frames)
+
+(defun neomacs--rectangle-highlight (orig start end window rol)
+  "Draw the rectangular region START..END of WINDOW natively.
+Calls ORIG with ROL, the overlay-based highlight, for ordinary regions
+or when the Rust layout engine is not in use."
+  (if (not (and (bound-and-true-p rectangle-mark-mode)
+                (not (bound-and-true-p rectangle--inhibit-region-highlight))
+                neomacs-native-rectangle-region
+                neomacs-rust-display-engine
+                (fboundp 'neomacs-set-rectangle-region)))
+      (funcall orig start end window rol)
+    (unless (eq 'neomacs-rectangle (car-safe rol))
+      (funcall redisplay-unhighlight-region-function rol))
+    ;; `rectangle--pos-cols' looks up the selected window's crutches.
+    (let* ((cols (save-excursion
+                   (with-selected-window window
+                     (rectangle--pos-cols start end))))
+           (left (min (car cols) (cdr cols)))
+           (right (max (car cols) (cdr cols))))
+      (neomacs-set-rectangle-region
+       window
+       (save-excursion (goto-char start) (line-beginning-position))
+       end left right
+       (neomacs--annotation-color 'region :background)
+       (and (= left right)
+            (bound-and-true-p rectangle-indicate-zero-width-rectangle)
+            left)
+       (neomacs--annotation-color 'cursor :background)))
+    (list 'neomacs-rectangle window)))
+
+(defun neomacs--rectangle-unhighlight (orig rol)
+  "Remove the native rectangle ROL, or call ORIG for other highlights."
+  (if (eq 'neomacs-rectangle (car-safe rol))
+      (when (and (window-live-p (cadr rol))
+                 (fboundp 'neomacs-set-rectangle-region))
+        (neomacs-set-rectangle-region (cadr rol)))
+    (funcall orig rol)))
+
+;; Outermost, so that rect.el's own overlay highlight is bypassed.
+(add-function :around redisplay-highlight-region-function
+              #'neomacs--rectangle-highlight '((depth . -50)))
+(add-function :around redisplay-unhighlight-region-function
+              #'neomacs--rectangle-unhighlight '((depth . -50)))
+
+;;; Region
+
+(defcustom neomacs-native-region t
+  "Non-nil means the layout engine draws the active region.
+The region is highlighted in the `region' face as runs along the text
+it covers, across wrapped lines and past the end of selected lines,
+instead of with an overlay.  The secondary selection is drawn the same
+way."
+  :type 'boolean
+  :group 'frames)
+
+(defun neomacs--region-highlight (orig start end window rol)
+  "Leave the region START..END of WINDOW to the layout engine.
+Calls ORIG with ROL, the overlay-based highlight, when the Rust layout
+engine is not in use."
+  (if (not (and neomacs-native-region
+                neomacs-rust-display-engine))
+      (funcall orig start end window rol)
+    (unless (eq 'neomacs-region (car-safe rol))
+      (funcall redisplay-unhighlight-region-function rol))
+    (set-window-parameter window 'neomacs-region (cons start end))
+    (list 'neomacs-region window)))
+
+(defun neomacs--region-unhighlight (orig rol)
+  "Remove the native region ROL, or call ORIG for other highlights."
+  (if (eq 'neomacs-region (car-safe rol))
+      (when (window-live-p (cadr rol))
+        (set-window-parameter (cadr rol) 'neomacs-region nil))
+    (funcall orig rol)))
+
+;; Inside the rectangle advice, which handles `rectangle-mark-mode'.
+(add-function :around redisplay-highlight-region-function
+              #'neomacs--region-highlight '((depth . -40)))
+(add-function :around redisplay-unhighlight-region-function
+              #'neomacs--region-unhighlight '((depth . -40)))
+
+;;; Virtual space past the end of lines
+
+(declare-function neomacs-set-virtual-cursor "neomacsterm.c" (window columns))
+
+(defcustom neomacs-virtual-space-padding-commands
+  '(self-insert-command quoted-insert yank yank-pop)
+  "Commands that first pad the line with spaces up to a virtual cursor.
+When `neomacs-virtual-space-mode' shows the cursor past the end of a
+line, running one of these commands inserts the missing spaces so the
+text lands where the cursor is drawn."
+  :type '(repeat function)
+  :group 'frames)
+
+(defvar-local neomacs--virtual-columns 0
+  "Columns between the end of the line at point and the cursor.")
+
+(defvar-local neomacs--virtual-point nil
+  "Point when `neomacs--virtual-columns' was set.")
+
+(defvar-local neomacs--virtual-goal-column nil
+  "Column kept by consecutive vertical moves in virtual space.")
+
+(defun neomacs--virtual-space-set (columns)
+  "Show the cursor COLUMNS columns past point, which is at end of line."
+  (setq neomacs--virtual-columns (max 0 columns)
+        neomacs--virtual-point (point))
+  (when (fboundp 'neomacs-set-virtual-cursor)
+    (neomacs-set-virtual-cursor (selected-window) neomacs--virtual-columns)))
+
+(defun neomacs-virtual-column ()
+  "Return the cursor's column, counting virtual space past the end of line."
+  (+ (current-column)
+     (if (eql neomacs--virtual-point (point)) neomacs--virtual-columns 0)))
+
+(defun neomacs-virtual-move-to-column (column)
+  "Move to COLUMN, into the virtual space past the end of a short line."
+  (move-to-column column)
+  (neomacs--virtual-space-set (if (eolp) (- column (current-column)) 0)))
+
+(defun neomacs-virtual-forward-char (&optional n)
+  "Move N columns right, continuing past the end of the line."
+  (interactive "p")
+  (let ((n (or n 1)))
+    (if (< n 0)
+        (neomacs-virtual-backward-char (- n))
+      (let ((room (- (line-end-position) (point))))
+        (forward-char (min n room))
+        (neomacs--virtual-space-set
+         (+ (if (eql neomacs--virtual-point (point)) neomacs--virtual-columns 0)
+            (- n (min n room))))))))
+
+(defun neomacs-virtual-backward-char (&optional n)
+  "Move N columns left, through the virtual space past the end of line."
+  (interactive "p")
+  (let* ((n (or n 1))
+         (virtual (if (eql neomacs--virtual-point (point))
+                      neomacs--virtual-columns
+                    0)))
+    (if (< n 0)
+        (neomacs-virtual-forward-char (- n))
+      (neomacs--virtual-space-set (- virtual (min n virtual)))
+      (when (> n virtual)
+        (backward-char (- n virtual))))))
+
+(defun neomacs-virtual-next-line (&optional n)
+  "Move N lines down, keeping the column even past the end of lines."
+  (interactive "p")
+  (unless (memq last-command '(neomacs-virtual-next-line
+                               neomacs-virtual-previous-line))
+    (setq neomacs--virtual-goal-column (neomacs-virtual-column)))
+  (forward-line (or n 1))
+  (neomacs-virtual-move-to-column neomacs--virtual-goal-column))
+
+(defun neomacs-virtual-previous-line (&optional n)
+  "Move N lines up, keeping the column even past the end of lines."
+  (interactive "p")
+  (neomacs-virtual-next-line (- (or n 1))))
+
+(defun neomacs-virtual-mouse-set-point (event)
+  "Move point to the click in EVENT, past the end of the line if needed."
+  (interactive "e")
+  (mouse-set-point event)
+  (let* ((posn (event-start event))
+         (col (+ (car (posn-col-row posn t))
+                 (window-hscroll (posn-window posn))
+                 (- (ceiling (line-number-display-width 'columns))))))
+    (neomacs-virtual-move-to-column (max col (current-column)))))
+
+(defun neomacs--virtual-space-pre-command ()
+  "Pad the line up to a virtual cursor before inserting commands."
+  (when (and (> neomacs--virtual-columns 0)
+             (eql neomacs--virtual-point (point))
+             (memq this-command neomacs-virtual-space-padding-commands)
+             (not buffer-read-only))
+    (insert (make-string neomacs--virtual-columns ?\s))
+    (neomacs--virtual-space-set 0)))
+
+(defun neomacs--virtual-space-post-command ()
+  "Drop the virtual cursor once point has moved away from it."
+  (when (and (> neomacs--virtual-columns 0)
+             (not (eql neomacs--virtual-point (point))))
+    (neomacs--virtual-space-set 0)))
+
+(defvar-keymap neomacs-virtual-space-mode-map
+  :doc "Keymap for `neomacs-virtual-space-mode'."
+  "<remap> <forward-char>" #'neomacs-virtual-forward-char
+  "<remap> <right-char>" #'neomacs-virtual-forward-char
+  "<remap> <backward-char>" #'neomacs-virtual-backward-char
+  "<remap> <left-char>" #'neomacs-virtual-backward-char
+  "<remap> <next-line>" #'neomacs-virtual-next-line
+  "<remap> <previous-line>" #'neomacs-virtual-previous-line
+  "<mouse-1>" #'neomacs-virtual-mouse-set-point)
+
+(define-minor-mode neomacs-virtual-space-mode
+  "Let the cursor move into the empty space past the end of lines.
+Horizontal and vertical motion keep their column even on short lines:
+the cursor is drawn in the space after the line without changing the
+buffer, and typing there first pads the line with spaces (see
+`neomacs-virtual-space-padding-commands').  Useful for column editing
+as in `picture-mode' or `artist-mode'.  Requires the Rust layout
+engine."
+  :group 'frames
+  :keymap neomacs-virtual-space-mode-map
+  (if neomacs-virtual-space-mode
+      (progn
+        (add-hook 'pre-command-hook #'neomacs--virtual-space-pre-command nil t)
+        (add-hook 'post-command-hook #'neomacs--virtual-space-post-command nil t))
+    (remove-hook 'pre-command-hook #'neomacs--virtual-space-pre-command t)
+    (remove-hook 'post-command-hook #'neomacs--virtual-space-post-command t)
+    (neomacs--virtual-space-set 0)))
+
+;;; Native tables
+
+(declare-function neomacs-add-table "neomacsterm.c"
+                  (rows aligns &optional grid-color buffer))
+(declare-function neomacs-clear-tables "neomacsterm.c" (&optional buffer))
+
+(defface neomacs-table-grid
+  '((t :inherit shadow))
+  "Face whose foreground colors the grid lines of native tables."
+  :group 'frames)
+
+(defvar-local neomacs--table-state nil
+  "(TICK . WINDOW-START) for which the buffer's tables were last sent.")
+
+(defun neomacs--table-line-p ()
+  "Return non-nil if the current line is a table line."
+  (save-excursion
+    (beginning-of-line)
+    (looking-at-p "[ \t]*|")))
+
+(defun neomacs--table-rule-p ()
+  "Return non-nil if the current line is a table rule line."
+  (save-excursion
+    (beginning-of-line)
+    (looking-at-p "[ \t]*|[-+:| ]*-[-+:| ]*$")))
+
+(defun neomacs--table-cells ()
+  "Return the cells of the current table line as (START END TEXT)."
+  (let ((eol (line-end-position))
+        cells)
+    (save-excursion
+      (beginning-of-line)
+      (skip-chars-forward " \t")
+      (forward-char 1)
+      (while (< (point) eol)
+        (let* ((bar (save-excursion
+                      (if (search-forward "|" eol t) (1- (point)) eol)))
+               (start (progn (skip-chars-forward " \t" bar) (point)))
+               (end (save-excursion
+                      (goto-char bar)
+                      (skip-chars-backward " \t" start)
+                      (point))))
+          (unless (and (= bar eol) (= start end))
+            (push (list start end (buffer-substring-no-properties start end))
+                  cells))
+          (goto-char (min eol (1+ bar))))))
+    (nreverse cells)))
+
+(defun neomacs--table-rule-aligns ()
+  "Return the column alignments written in the current rule line."
+  (save-excursion
+    (let ((eol (line-end-position))
+          aligns)
+      (beginning-of-line)
+      (while (re-search-forward "\\(:\\)?-+\\(:\\)?" eol t)
+        (push (cond ((and (match-beginning 1) (match-beginning 2)) 'center)
+                    ((match-beginning 2) 'right)
+                    (t 'left))
+              aligns))
+      (nreverse aligns))))
+
+(defun neomacs--table-aligns (rows)
+  "Return column alignments for the table ROWS.
+Markdown colons in a rule line win; otherwise columns whose cells are
+mostly numbers are right aligned."
+  (or (seq-some (lambda (row)
+                  (and (eq (nth 2 row) 'rule)
+                       (save-excursion
+                         (goto-char (car row))
+                         (and (looking-at-p ".*:") (neomacs--table-rule-aligns)))))
+                rows)
+      (let* ((data (seq-remove (lambda (row) (eq (nth 2 row) 'rule)) rows))
+             (ncols (apply #'max 0 (mapcar (lambda (row) (length (nth 2 row)))
+                                           data))))
+        (mapcar (lambda (col)
+                  (let ((numeric 0) (total 0))
+                    (dolist (row data)
+                      (let ((text (nth 2 (nth col (nth 2 row)))))
+                        (when (and text (not (string-empty-p text)))
+                          (setq total (1+ total))
+                          (when (string-match-p
+                                 "\\`[-+]?[0-9][0-9.,]*%?\\'" text)
+                            (setq numeric (1+ numeric))))))
+                    (if (and (> total 0) (> (* 2 numeric) total))
+                        'right
+                      'left)))
+                (number-sequence 0 (1- ncols))))))
+
+(defun neomacs--table-scan (beg end)
+  "Return the tables with a line between BEG and END, as lists of rows.
+Each row is (START END CELLS) as expected by `neomacs-add-table'."
+  (save-excursion
+    (goto-char beg)
+    (while (and (not (bobp)) (neomacs--table-line-p)
+                (save-excursion (forward-line -1) (neomacs--table-line-p)))
+      (forward-line -1))
+    (let (tables rows)
+      (while (and (not (eobp)) (or (< (point) end) rows))
+        (if (neomacs--table-line-p)
+            (push (list (line-beginning-position) (line-end-position)
+                        (if (neomacs--table-rule-p) 'rule (neomacs--table-cells)))
+                  rows)
+          (when rows
+            (push (nreverse rows) tables)
+            (setq rows nil)))
+        (forward-line 1))
+      (when rows
+        (push (nreverse rows) tables))
+      (nreverse tables))))
+
+(defun neomacs--table-refresh (&optional force)
+  "Send the tables shown in the selected window to the layout engine.
+Only rescans when the buffer text or the window start changed, unless
+FORCE is non-nil."
+  (let ((state (cons (buffer-chars-modified-tick) (window-start))))
+    (when (and (fboundp 'neomacs-add-table)
+               (or force (not (equal state neomacs--table-state))))
+      (setq neomacs--table-state state)
+      (neomacs-clear-tables)
+      (let ((grid (neomacs--annotation-color 'neomacs-table-grid :foreground))
+            (end (save-excursion
+                   (goto-char (window-start))
+                   (forward-line (window-body-height))
+                   (point))))
+        (dolist (rows (neomacs--table-scan (window-start) end))
+          (neomacs-add-table rows (neomacs--table-aligns rows) grid))))))
+
+(define-minor-mode neomacs-table-mode
+  "Draw org and markdown tables with pixel-aligned columns.
+Lines starting with `|' are laid out as tables: every column is as wide
+as its widest cell measured with the real font, so columns line up even
+with proportional fonts or CJK text, and the typed separators are
+replaced by grid lines.  Rule lines such as `|---+---|' become
+horizontal lines, markdown alignment colons are honored and mostly
+numeric columns are right aligned.  Point and mouse clicks still map to
+the cell text.  Requires the Rust layout engine."
+  :group 'frames
+  (if neomacs-table-mode
+      (progn
+        (add-hook 'post-command-hook #'neomacs--table-refresh nil t)
+        (add-hook 'window-scroll-functions #'neomacs--table-scrolled nil t)
+        (neomacs--table-refresh t))
+    (remove-hook 'post-command-hook #'neomacs--table-refresh t)
+    (remove-hook 'window-scroll-functions #'neomacs--table-scrolled t)
+    (setq neomacs--table-state nil)
+    (when (fboundp 'neomacs-clear-tables)
+      (neomacs-clear-tables))))
+
+(defun neomacs--table-scrolled (window _start)
+  "Refresh the tables of WINDOW's buffer after it scrolled."
+  (with-selected-window window
+    (neomacs--table-refresh)))
+
+;;; Shell command blocks
+
+(declare-function neomacs-set-command-blocks "neomacsterm.c"
+                  (blocks &optional colors buffer))
+
+(defvar comint-last-prompt)
+(defvar comint-last-output-start)
+(defvar comint-prompt-regexp)
+(defvar eshell-last-input-start)
+(defvar eshell-last-input-end)
+(defvar eshell-last-command-status)
+
+(defface neomacs-command-block
+  '((((background dark)) :background "#1e232b")
+    (t :background "#eef1f5"))
+  "Face whose background tints the rows of shell command blocks."
+  :group 'frames)
+
+(defface neomacs-command-block-success
+  '((t :inherit success))
+  "Face whose foreground marks shell commands that succeeded."
+  :group 'frames)
+
+(defface neomacs-command-block-failure
+  '((t :inherit error))
+  "Face whose foreground marks shell commands that failed."
+  :group 'frames)
+
+(defface neomacs-command-block-label
+  '((t :inherit shadow))
+  "Face whose foreground colors the labels of shell command blocks."
+  :group 'frames)
+
+(defvar-local neomacs--command-block-pending nil
+  "(START OUTPUT-START TIME) of the command running, or nil.
+START is a marker at its prompt, OUTPUT-START a marker at its output
+or nil before any output, and TIME when it was sent.")
+
+(defvar-local neomacs--command-blocks-state nil
+  "(TICK . WINDOW-STARTS) for which the buffer's blocks were last sent.")
+
+(defun neomacs--command-block-begin (start output-start)
+  "Note that a command with its prompt at START was sent.
+OUTPUT-START is where its output begins, or nil if not known yet."
+  (setq neomacs--command-block-pending
+        (list (copy-marker start)
+              (and output-start (copy-marker output-start))
+              (current-time))))
+
+(defun neomacs--command-block-finish (end &optional exit-code)
+  "Close the running command's block at END, with EXIT-CODE if known.
+The block is recorded as a `neomacs-command-block' text property over
+the prompt, input and output, whose value is the plist (:output
+OUTPUT-START :exit EXIT-CODE :duration MILLISECONDS :collapsed nil)."
+  (pcase neomacs--command-block-pending
+    (`(,start ,output ,time)
+     (setq neomacs--command-block-pending nil)
+     (when (> end start)
+       (with-silent-modifications
+         (put-text-property
+          start end 'neomacs-command-block
+          (list :output (min end (if output (marker-position output) end))
+                :exit exit-code
+                :duration (round (* 1000 (float-time (time-subtract nil time))))
+                :collapsed nil)))
+       (neomacs--command-blocks-refresh t))
+     (set-marker start nil)
+     (when output
+       (set-marker output nil)))))
+
+(defun neomacs--command-block-comint-input (_input)
+  "Start a command block for the input being sent by comint."
+  (let ((proc (get-buffer-process (current-buffer))))
+    (when proc
+      (neomacs--command-block-begin
+       (if (markerp (car-safe comint-last-prompt))
+           (car comint-last-prompt)
+         (save-excursion (goto-char (process-mark proc)) (line-beginning-position)))
+       nil))))
+
+(defun neomacs--command-block-comint-output (_string)
+  "Track the output of the running command and close its block at the
+next prompt, recognized by `comint-prompt-regexp'."
+  (let ((proc (get-buffer-process (current-buffer))))
+    (when (and proc neomacs--command-block-pending)
+      (unless (nth 1 neomacs--command-block-pending)
+        (setf (nth 1 neomacs--command-block-pending)
+              (copy-marker comint-last-output-start)))
+      (let ((prompt (save-excursion
+                      (goto-char (process-mark proc))
+                      (line-beginning-position))))
+        (when (and (> prompt (car neomacs--command-block-pending))
+                   (save-excursion
+                     (goto-char prompt)
+                     (looking-at-p comint-prompt-regexp)))
+          (neomacs--command-block-finish prompt))))))
+
+(defun neomacs--command-block-eshell-pre ()
+  "Start a command block for the command eshell is about to run."
+  (neomacs--command-block-begin
+   (save-excursion (goto-char eshell-last-input-start) (line-beginning-position))
+   eshell-last-input-end))
+
+(defun neomacs--command-block-eshell-post ()
+  "Close the block of the command eshell just ran, before the next prompt."
+  (neomacs--command-block-finish (point-max) eshell-last-command-status))
+
+(defun neomacs--command-blocks-in (beg end)
+  "Return the command blocks overlapping BEG..END as (START END PROPS)."
+  (let ((pos (if (and (< beg (point-max))
+                      (get-text-property beg 'neomacs-command-block))
+                 (previous-single-property-change
+                  (1+ beg) 'neomacs-command-block nil (point-min))
+               beg))
+        blocks)
+    (while (< pos end)
+      (let ((props (get-text-property pos 'neomacs-command-block))
+            (next (next-single-property-change
+                   pos 'neomacs-command-block nil (point-max))))
+        (when props
+          (push (list pos next props) blocks))
+        (setq pos next)))
+    (nreverse blocks)))
+
+(defun neomacs--command-blocks-refresh (&optional force)
+  "Send the command blocks shown in windows on the current buffer.
+Only rescans when the buffer or the window starts changed, unless
+FORCE is non-nil."
+  (let* ((windows (get-buffer-window-list nil nil t))
+         (state (cons (buffer-modified-tick) (mapcar #'window-start windows))))
+    (when (and windows (fboundp 'neomacs-set-command-blocks)
+               (or force (not (equal state neomacs--command-blocks-state))))
+      (setq neomacs--command-blocks-state state)
+      (let ((beg (apply #'min (mapcar #'window-start windows)))
+            (end (apply #'max
+                        (mapcar (lambda (w)
+                                  (save-excursion
+                                    (goto-char (window-start w))
+                                    (forward-line (window-body-height w))
+                                    (point)))
+                                windows))))
+        (neomacs-set-command-blocks
+         (mapcar (pcase-lambda (`(,start ,end ,props))
+                   (let ((output (plist-get props :output)))
+                     (list start output end
+                           (plist-get props :exit)
+                           (plist-get props :duration)
+                           (plist-get props :collapsed)
+                           (and (plist-get props :collapsed)
+                                (count-lines output end)))))
+                 (neomacs--command-blocks-in beg end))
+         (list (neomacs--annotation-color 'neomacs-command-block :background)
+               (neomacs--annotation-color 'neomacs-command-block-success :foreground)
+               (neomacs--annotation-color 'neomacs-command-block-failure :foreground)
+               (neomacs--annotation-color 'neomacs-command-block-label :foreground)))))))
+
+(defun neomacs--command-blocks-scrolled (window _start)
+  "Refresh the command blocks of WINDOW's buffer after it scrolled."
+  (with-current-buffer (window-buffer window)
+    (neomacs--command-blocks-refresh)))
+
+(defun neomacs-command-block-toggle (&optional pos)
+  "Collapse or expand the output of the shell command at POS.
+POS defaults to point.  Collapsed output is hidden and the prompt row
+shows how many lines it has."
+  (interactive)
+  (setq pos (or pos (point)))
+  (let ((block (car (neomacs--command-blocks-in pos (1+ pos)))))
+    (unless block
+      (user-error "No shell command here"))
+    (pcase-let* ((`(,start ,end ,props) block)
+                 (output (plist-get props :output))
+                 (collapsed (not (plist-get props :collapsed))))
+      ;; The plist is shared by the whole block, so changing it in place
+      ;; keeps the block a single property run.
+      (plist-put props :collapsed collapsed)
+      ;; Hide from the input's newline to before the output's last one
+      (when (< output end)
+        (with-silent-modifications
+          (if collapsed
+              (put-text-property (max start (1- output)) (1- end)
+                                 'invisible 'neomacs-command-block)
+            (remove-list-of-text-properties (max start (1- output)) (1- end)
+                                            '(invisible)))))
+      (neomacs--command-blocks-refresh t))))
+
+(defvar-keymap neomacs-command-blocks-mode-map
+  :doc "Keymap for `neomacs-command-blocks-mode'."
+  "C-c TAB" #'neomacs-command-block-toggle)
+
+(define-minor-mode neomacs-command-blocks-mode
+  "Draw the commands of a shell buffer as separated blocks.
+Each command run from a comint (e.g. `shell') or eshell buffer is
+recorded as a block of its prompt, input and output.  The rows of a
+block get a tinted background and a bar in the color of its exit
+status, and its prompt row shows the status and how long the command
+ran.  \\[neomacs-command-block-toggle] collapses or expands the output
+of the command at point.  Comint shells do not report exit statuses,
+so their blocks show an unknown status.  Requires the Rust layout
+engine.
+
+To use it in all shell buffers:
+
+  (add-hook \\='shell-mode-hook #\\='neomacs-command-blocks-mode)
+  (add-hook \\='eshell-mode-hook #\\='neomacs-command-blocks-mode)"
+  :group 'frames
+  (if neomacs-command-blocks-mode
+      (progn
+        (add-to-invisibility-spec 'neomacs-command-block)
+        (cond
+         ((derived-mode-p 'eshell-mode)
+          (add-hook 'eshell-pre-command-hook #'neomacs--command-block-eshell-pre nil t)
+          (add-hook 'eshell-post-command-hook #'neomacs--command-block-eshell-post -50 t))
+         ((derived-mode-p 'comint-mode)
+          (add-hook 'comint-input-filter-functions #'neomacs--command-block-comint-input nil t)
+          (add-hook 'comint-output-filter-functions #'neomacs--command-block-comint-output nil t)))
+        (add-hook 'post-command-hook #'neomacs--command-blocks-refresh nil t)
+        (add-hook 'window-scroll-functions #'neomacs--command-blocks-scrolled nil t)
+        (neomacs--command-blocks-refresh t))
+    (remove-hook 'eshell-pre-command-hook #'neomacs--command-block-eshell-pre t)
+    (remove-hook 'eshell-post-command-hook #'neomacs--command-block-eshell-post t)
+    (remove-hook 'comint-input-filter-functions #'neomacs--command-block-comint-input t)
+    (remove-hook 'comint-output-filter-functions #'neomacs--command-block-comint-output t)
+    (remove-hook 'post-command-hook #'neomacs--command-blocks-refresh t)
+    (remove-hook 'window-scroll-functions #'neomacs--command-blocks-scrolled t)
+    (setq neomacs--command-block-pending nil
+          neomacs--command-blocks-state nil)
+    (when (fboundp 'neomacs-set-command-blocks)
+      (neomacs-set-command-blocks nil))))
+
+;;; Chat buffers
+
+(declare-function neomacs-set-chat-view "neomacsterm.c"
+                  (input-start &optional unread colors buffer))
+
+(defvar erc-insert-marker)
+(defvar rcirc-prompt-start-marker)
+(defvar lui-output-marker)
+(defvar telega-chatbuf--input-marker)
+
+(defface neomacs-chat-input
+  '((((background dark)) :background "#1e232b")
+    (t :background "#eef1f5"))
+  "Face whose background is behind input pinned to the bottom of chat windows."
+  :group 'frames)
+
+(defface neomacs-chat-separator
+  '((t :inherit shadow))
+  "Face whose foreground draws the line above pinned chat input."
+  :group 'frames)
+
+(defface neomacs-chat-new-messages
+  '((((background dark)) :foreground "#ffffff" :background "#3a6fc8")
+    (t :foreground "#ffffff" :background "#2f66c4"))
+  "Face of the pill counting new messages in scrolled up chat windows.
+Only the foreground and background colors are used."
+  :group 'frames)
+
+(defcustom neomacs-chat-coalesce-delay 0.05
+  "Seconds to wait for more messages before scrolling chat windows.
+Messages arriving in a burst, such as the backlog sent after joining a
+channel, scroll the windows following them once, when it is over."
+  :type 'number
+  :group 'frames)
+
+(defcustom neomacs-chat-inline-images t
+  "Non-nil means image links in chat messages show the image below them.
+Links to PNG, JPEG, GIF and WebP files are fetched in the background."
+  :type 'boolean
+  :group 'frames)
+
+(defcustom neomacs-chat-image-max-height 240
+  "Maximum height in pixels of images shown in chat buffers."
+  :type 'natnum
+  :group 'frames)
+
+(defcustom neomacs-chat-emoji-aliases
+  '(("smile" . "😄") ("smiley" . "😃") ("grin" . "😁") ("joy" . "😂")
+    ("wink" . "😉") ("heart" . "❤️") ("thumbsup" . "👍") ("+1" . "👍")
+    ("thumbsdown" . "👎") ("-1" . "👎") ("tada" . "🎉") ("fire" . "🔥")
+    ("eyes" . "👀") ("pray" . "🙏") ("rocket" . "🚀") ("cry" . "😢")
+    ("thinking" . "🤔") ("ok_hand" . "👌") ("wave" . "👋") ("100" . "💯"))
+  "Emoji shown in place of :NAME: shortcodes in chat messages.
+Each element is (NAME . EMOJI).  Other shortcodes are looked up as the
+Unicode names of emoji, with underscores for spaces, so :red_apple:
+shows a red apple.  The text of the message is unchanged."
+  :type '(alist :key-type string :value-type string)
+  :group 'frames)
+
+(defvar neomacs-chat-input-start-function #'neomacs-chat-input-start
+  "Function returning where the current chat buffer's input area begins.
+The input area is the prompt followed by the message being typed, up
+to the end of the buffer; messages are inserted just before it.  The
+function returns nil when the buffer has none.")
+
+(defconst neomacs--chat-image-regexp
+  "https?://[^ \t\n<>\"]+\\.\\(?:png\\|jpe?g\\|gif\\|webp\\)\\(?:\\?[^ \t\n<>\"]*\\)?\\>"
+  "Regexp matching links to images in chat messages.")
+
+(defvar-local neomacs--chat-burst nil
+  "(START . MESSAGES) of the burst of messages being coalesced, or nil.
+START is a marker where the burst began.")
+
+(defvar-local neomacs--chat-following nil
+  "Windows showing the end of the buffer when the current burst began.")
+
+(defvar-local neomacs--chat-timer nil
+  "Timer ending the current burst of messages.")
+
+(defvar-local neomacs--chat-unread 0
+  "Messages that arrived while no window showed the end of the buffer.")
+
+(defvar-local neomacs--chat-state nil
+  "(INPUT-START . UNREAD) last sent to the display.")
+
+(defun neomacs-chat-input-start ()
+  "Return where the input area of the current chat buffer begins, or nil.
+Knows the buffers of ERC, rcirc, Circe and telega."
+  (let ((marker (cond ((derived-mode-p 'erc-mode) erc-insert-marker)
+                      ((derived-mode-p 'rcirc-mode) rcirc-prompt-start-marker)
+                      ((derived-mode-p 'lui-mode) lui-output-marker)
+                      ((derived-mode-p 'telega-chat-mode)
+                       telega-chatbuf--input-marker))))
+    (and (markerp marker) (marker-position marker))))
+
+(defun neomacs--chat-input ()
+  "Where the input area of the current buffer begins, or nil."
+  (funcall neomacs-chat-input-start-function))
+
+(defun neomacs--chat-send (&optional force)
+  "Send the input start and unread count of the current buffer.
+Only sends when they changed, unless FORCE is non-nil."
+  (let ((state (cons (neomacs--chat-input) neomacs--chat-unread)))
+    (when (and (fboundp 'neomacs-set-chat-view)
+               (or force (not (equal state neomacs--chat-state))))
+      (setq neomacs--chat-state state)
+      (neomacs-set-chat-view
+       (car state) (cdr state)
+       (list (neomacs--annotation-color 'neomacs-chat-input :background)
+             (neomacs--annotation-color 'neomacs-chat-separator :foreground)
+             (neomacs--annotation-color 'neomacs-chat-new-messages :background)
+             (neomacs--annotation-color 'neomacs-chat-new-messages :foreground))))))
+
+(defun neomacs--chat-at-end-p (window input)
+  "Non-nil if WINDOW showed INPUT, the input area, when last laid out."
+  (>= (window-end window) input))
+
+(defun neomacs--chat-show-end (window)
+  "Scroll WINDOW so the end of its buffer is on its last row.
+With scroll animations enabled, the text slides up smoothly."
+  (with-selected-window window
+    (save-excursion
+      (goto-char (point-max))
+      (recenter -1))
+    (when (< (point) (window-start))
+      (goto-char (point-max)))))
+
+(defun neomacs--chat-emoji (name)
+  "The emoji for the shortcode :NAME:, or nil."
+  (or (cdr (assoc name neomacs-chat-emoji-aliases))
+      (let ((char (char-from-name (subst-char-in-string ?_ ?\s name) t)))
+        (and char
+             (eq (aref char-script-table char) 'emoji)
+             (string char)))))
+
+(defun neomacs--chat-image (url pos)
+  "Show the image at URL below the line ending at POS once it arrives."
+  (let ((marker (copy-marker pos)))
+    (require 'url)
+    (url-retrieve
+     url
+     (lambda (status)
+       (let ((data (and (not (plist-get status :error))
+                        (progn
+                          (goto-char (point-min))
+                          (search-forward "\n\n" nil t))
+                        (buffer-substring-no-properties (point) (point-max))))
+             (buffer (marker-buffer marker)))
+         (kill-buffer)
+         (when-let* (((buffer-live-p buffer))
+                     (image (and data
+                                 (ignore-errors
+                                   (create-image
+                                    data nil t
+                                    :max-height neomacs-chat-image-max-height
+                                    :max-width (* 2 neomacs-chat-image-max-height))))))
+           (with-current-buffer buffer
+             (when neomacs-chat-mode
+               (let ((following
+                      (seq-filter (lambda (w)
+                                    (neomacs--chat-at-end-p w (neomacs--chat-input)))
+                                  (get-buffer-window-list nil nil t)))
+                     (overlay (make-overlay marker marker)))
+                 (overlay-put overlay 'neomacs-chat-image t)
+                 (overlay-put overlay 'after-string
+                              (concat "\n" (propertize " " 'display image)))
+                 (mapc #'neomacs--chat-show-end following)))))
+         (set-marker marker nil)))
+     nil t t)))
+
+(defun neomacs--chat-decorate (beg end)
+  "Show the emoji shortcodes and image links of messages from BEG to END."
+  (save-excursion
+    (goto-char beg)
+    (let ((inhibit-read-only t))
+      (with-silent-modifications
+        (while (re-search-forward ":\\([a-z0-9_+-]+\\):" end t)
+          (when-let* ((emoji (neomacs--chat-emoji (match-string-no-properties 1))))
+            (put-text-property (match-beginning 0) (match-end 0)
+                               'display emoji)))))
+    (when neomacs-chat-inline-images
+      (goto-char beg)
+      (while (re-search-forward neomacs--chat-image-regexp end t)
+        (neomacs--chat-image (match-string-no-properties 0)
+                             (line-end-position))))))
+
+(defun neomacs--chat-after-change (beg end len)
+  "Count a message inserted from BEG to END and start or extend a burst.
+A message is text ending in a newline inserted just before the input
+area; LEN is 0 for insertions."
+  (let ((input (neomacs--chat-input)))
+    (when (and input (< beg input))
+      (when (and (= len 0) (= end input) (> end beg)
+                 (eq (char-before end) ?\n))
+        (unless neomacs--chat-burst
+          (setq neomacs--chat-burst (cons (copy-marker beg) 0)
+                neomacs--chat-following
+                (seq-filter (lambda (w) (neomacs--chat-at-end-p w input))
+                            (get-buffer-window-list nil nil t))))
+        (setcdr neomacs--chat-burst (1+ (cdr neomacs--chat-burst)))
+        (when (timerp neomacs--chat-timer)
+          (cancel-timer neomacs--chat-timer))
+        (setq neomacs--chat-timer
+              (run-with-timer neomacs-chat-coalesce-delay nil
+                              #'neomacs--chat-end-burst (current-buffer))))
+      (neomacs--chat-send))))
+
+(defun neomacs--chat-end-burst (buffer)
+  "Show the burst of messages that arrived in BUFFER.
+Windows that showed the end of BUFFER when it began scroll to show it
+again; if others show BUFFER, the messages count as unread."
+  (when (buffer-live-p buffer)
+    (with-current-buffer buffer
+      (pcase-let ((`(,start . ,messages) neomacs--chat-burst)
+                  (input (neomacs--chat-input)))
+        (setq neomacs--chat-timer nil
+              neomacs--chat-burst nil)
+        (when (and start input)
+          (neomacs--chat-decorate start input)
+          (set-marker start nil)
+          (let ((windows (seq-filter #'window-live-p
+                                     (get-buffer-window-list nil nil t))))
+            (dolist (window windows)
+              (when (memq window neomacs--chat-following)
+                (neomacs--chat-show-end window)))
+            (when (seq-some (lambda (w) (not (memq w neomacs--chat-following)))
+                            windows)
+              (setq neomacs--chat-unread (+ neomacs--chat-unread messages))))
+          (setq neomacs--chat-following nil)
+          (neomacs--chat-send))))))
+
+(defun neomacs--chat-refresh ()
+  "Forget the unread messages once a window shows the end of the buffer."
+  (let ((input (neomacs--chat-input)))
+    (when (and input (> neomacs--chat-unread 0)
+               (seq-some (lambda (w) (pos-visible-in-window-p input w))
+                         (get-buffer-window-list nil nil t)))
+      (setq neomacs--chat-unread 0))
+    (neomacs--chat-send)))
+
+(defun neomacs-chat-scroll-to-bottom ()
+  "Show the newest messages and move point to the input area."
+  (interactive)
+  (unless neomacs-chat-mode
+    (user-error "Not a chat buffer"))
+  (goto-char (point-max))
+  (neomacs--chat-show-end (selected-window))
+  (setq neomacs--chat-unread 0)
+  (neomacs--chat-send))
+
+(defvar-keymap neomacs-chat-mode-map
+  :doc "Keymap for `neomacs-chat-mode'."
+  "C-c <end>" #'neomacs-chat-scroll-to-bottom)
+
+(define-minor-mode neomacs-chat-mode
+  "Display a chat buffer like a chat client.
+Windows showing the newest messages keep showing them, sliding up
+smoothly as messages arrive; messages arriving in a burst scroll them
+once (see `neomacs-chat-coalesce-delay').  A window scrolled up to read
+older messages stays put: its input stays pinned to its bottom, and a
+pill above the input counts the messages that arrived since.  Clicking
+the pill or \\[neomacs-chat-scroll-to-bottom] goes back to the newest
+messages.  Image links in messages show the image below them (see
+`neomacs-chat-inline-images') and emoji shortcodes such as :tada: show
+the emoji (see `neomacs-chat-emoji-aliases').
+
+The input area is found by `neomacs-chat-input-start-function', which
+knows ERC, rcirc, Circe and telega.  Requires the Rust layout engine.
+
+To use it in all ERC buffers:
+
+  (add-hook \\='erc-mode-hook #\\='neomacs-chat-mode)"
+  :group 'frames
+  (when (timerp neomacs--chat-timer)
+    (cancel-timer neomacs--chat-timer))
+  (when (car neomacs--chat-burst)
+    (set-marker (car neomacs--chat-burst) nil))
+  (setq neomacs--chat-timer nil
+        neomacs--chat-burst nil
+        neomacs--chat-following nil
+        neomacs--chat-unread 0
+        neomacs--chat-state nil)
+  (if neomacs-chat-mode
+      (progn
+        (add-hook 'after-change-functions #'neomacs--chat-after-change nil t)
+        (add-hook 'post-command-hook #'neomacs--chat-refresh nil t)
+        (neomacs--chat-decorate (point-min) (or (neomacs--chat-input) (point-max)))
+        (neomacs--chat-send t))
+    (remove-hook 'after-change-functions #'neomacs--chat-after-change t)
+    (remove-hook 'post-command-hook #'neomacs--chat-refresh t)
+    (remove-overlays nil nil 'neomacs-chat-image t)
+    (when (fboundp 'neomacs-set-chat-view)
+      (neomacs-set-chat-view nil))))
+
+;;; Region pulses
+
+(declare-function neomacs-pulse-region "neomacsterm.c"
+                  (start end &optional face duration buffer))
+
+(defface neomacs-pulse
+  '((((background dark)) :background "#b58900")
+    (t :background "#ffd54f"))
+  "Face whose background colors the highlight of `neomacs-pulse-region'."
+  :group 'frames)
+
+(defcustom neomacs-pulse-duration 0.4
+  "Seconds a highlight of `neomacs-pulse-edits-mode' takes to fade out."
+  :type 'number
+  :group 'frames)
+
+(defcustom neomacs-pulse-commands
+  '(yank yank-pop clipboard-yank kill-ring-save clipboard-kill-ring-save
+    indent-region indent-rigidly)
+  "Commands after which `neomacs-pulse-edits-mode' flashes the region.
+Each leaves the text it yanked, copied or indented between mark and
+point."
+  :type '(repeat function)
+  :group 'frames)
+
+(defun neomacs--pulse-after-command ()
+  "Flash the text between mark and point after a pulsed command."
+  (when (and (memq this-command neomacs-pulse-commands)
+             (mark t)
+             (/= (mark t) (point))
+             (fboundp 'neomacs-pulse-region))
+    (neomacs-pulse-region (mark t) (point) 'neomacs-pulse
+                          neomacs-pulse-duration)))
+
+(define-minor-mode neomacs-pulse-edits-mode
+  "Flash a fading highlight over text that was yanked, copied or indented.
+The commands are listed in `neomacs-pulse-commands'; the highlight uses
+the face `neomacs-pulse' and fades out over `neomacs-pulse-duration'
+seconds.  Requires the Rust layout engine."
+  :global t
+  :group 'frames
+  (if neomacs-pulse-edits-mode
+      (add-hook 'post-command-hook #'neomacs--pulse-after-command)
+    (remove-hook 'post-command-hook #'neomacs--pulse-after-command)))
+
+;;; Mouse pointer
+
+(declare-function neomacs-set-pointer-auto-hide "neomacsterm.c" (enabled))
+(defvar neomacs-pointer-shapes)
+
+(defcustom neomacs-hide-pointer-while-typing t
+  "Non-nil means hide the mouse pointer as soon as a key is typed.
+The pointer reappears when the mouse moves.  The shape of the pointer
+over text, links, mode lines, fringes and window dividers is set by
+`neomacs-pointer-shapes'."
+  :type 'boolean
+  :group 'frames
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (fboundp 'neomacs-set-pointer-auto-hide)
+           (neomacs-set-pointer-auto-hide val))))
+
+;;; Mouse wheel and touchpad
+
+(declare-function neomacs-set-wheel-config "neomacsterm.c"
+                  (wheel-lines wheel-reverse touchpad-speed touchpad-reverse
+                               shift-horizontal alt-page))
+
+(defun neomacs--apply-wheel-config ()
+  "Send the scroll settings to the display engine."
+  (when (and (fboundp 'neomacs-set-wheel-config)
+             (boundp 'neomacs-wheel-alt-page))
+    (neomacs-set-wheel-config neomacs-wheel-lines
+                              neomacs-wheel-reverse
+                              neomacs-touchpad-scroll-speed
+                              neomacs-touchpad-reverse
+                              neomacs-wheel-shift-horizontal
+                              neomacs-wheel-alt-page)))
+
+(defun neomacs--set-wheel-option (sym val)
+  "Set scroll option SYM to VAL and apply the scroll settings."
+  (set-default sym val)
+  (neomacs--apply-wheel-config))
+
+(defcustom neomacs-wheel-lines 1
+  "Lines one notch of a mouse wheel scrolls.
+This multiplies the amount of `mouse-wheel-scroll-amount'.  Touchpads
+are set by `neomacs-touchpad-scroll-speed' instead."
+  :type 'number
+  :group 'mouse
+  :set #'neomacs--set-wheel-option)
+
+(defcustom neomacs-wheel-reverse nil
+  "Non-nil means the mouse wheel scrolls the other way."
+  :type 'boolean
+  :group 'mouse
+  :set #'neomacs--set-wheel-option)
+
+(defcustom neomacs-touchpad-scroll-speed 1.0
+  "Factor touchpad scrolling distances are multiplied by.
+Touchpads and other precise devices are told apart from notched wheels
+by the display engine."
+  :type 'number
+  :group 'mouse
+  :set #'neomacs--set-wheel-option)
+
+(defcustom neomacs-touchpad-reverse nil
+  "Non-nil means touchpads scroll the other way (\"natural\" scrolling)."
+  :type 'boolean
+  :group 'mouse
+  :set #'neomacs--set-wheel-option)
+
+(defcustom neomacs-wheel-shift-horizontal nil
+  "Non-nil means Shift+wheel scrolls horizontally.
+The wheel events arrive as `wheel-left' and `wheel-right' without the
+Shift modifier, so they scroll by `mouse-wheel-scroll-amount-horizontal'."
+  :type 'boolean
+  :group 'mouse
+  :set #'neomacs--set-wheel-option)
+
+(defcustom neomacs-wheel-alt-page nil
+  "Non-nil means Alt+wheel scrolls a page of the window under the mouse.
+The wheel events arrive without the Meta modifier."
+  :type 'boolean
+  :group 'mouse
+  :set #'neomacs--set-wheel-option)
+
+(add-hook 'window-setup-hook #'neomacs--apply-wheel-config)
+
+;;; Pinch to zoom
+
+(defvar text-scale-mode-amount)
+(defvar text-scale-mode-step)
+
+(defun neomacs-text-scale-pinch (event)
+  "Scale the text of the window of pinch EVENT to the nearest font size.
+The display engine scales the text on the GPU while the fingers move
+and sends a single event with the total scale when they lift.  The
+height of the default font is multiplied by that scale and rounded to
+whole pixels, so the text is rasterized crisply at the size that was
+previewed."
+  (interactive "e")
+  (require 'face-remap)
+  (let ((window (posn-window (event-start event)))
+        (scale (nth 4 event)))
+    (when (and (window-live-p window) (numberp scale) (> scale 0))
+      (with-selected-window window
+        (let* ((height (default-font-height))
+               (target (max 4 (round (* height scale)))))
+          (unless (= target height)
+            (text-scale-set
+             (+ (if (bound-and-true-p text-scale-mode) text-scale-mode-amount 0)
+                (log (/ (float target) height) text-scale-mode-step)))))))))
+
+(global-set-key [pinch] #'neomacs-text-scale-pinch)
+
+;;; Link spans
+
+(declare-function neomacs-link-add "neomacsterm.c"
+                  (start end target &optional color buffer))
+(declare-function neomacs-link-remove "neomacsterm.c" (id))
+(declare-function neomacs-link-clear "neomacsterm.c" (&optional buffer))
+(declare-function neomacs-link-adjust "neomacsterm.c" (beg end old-len))
+(declare-function neomacs-link-at "neomacsterm.c" (pos &optional buffer))
+(declare-function compile-goto-error "compile" (&optional event))
+(declare-function org-open-at-point "org" (&optional arg))
+(defvar org-link-bracket-re)
+(defvar org-link-plain-re)
+
+(defface neomacs-link
+  '((((background dark)) :foreground "#6ab0f3")
+    (t :foreground "#1a5fb4"))
+  "Face of links added with `neomacs-add-link'.
+Only the foreground color is used, for the text and its underline."
+  :group 'frames)
+
+(defcustom neomacs-link-follow-function #'neomacs-link-follow-default
+  "Function that follows a link without an action of its own.
+It is called with the link's target string and its buffer position,
+in the link's window."
+  :type 'function
+  :group 'frames)
+
+(defvar-local neomacs--link-actions nil
+  "Actions of the current buffer's links: an alist of (ID . FUNCTION).")
+
+(defun neomacs--link-kill-buffer ()
+  "Drop the links of the buffer being killed."
+  (neomacs-link-clear))
+
+(defun neomacs-add-link (start end target &optional action)
+  "Make the text from START to END of the current buffer a link to TARGET.
+The text is underlined in the face `neomacs-link', the mouse pointer
+becomes a hand over it, and mouse-2 or C-mouse-1 follows it: ACTION,
+if non-nil, is called with TARGET and the clicked position; otherwise
+`neomacs-link-follow-function' is.  Links follow edits to the buffer.
+Returns the link id for `neomacs-remove-link'."
+  (add-hook 'after-change-functions #'neomacs-link-adjust nil t)
+  (add-hook 'kill-buffer-hook #'neomacs--link-kill-buffer nil t)
+  (let ((id (neomacs-link-add start end target
+                              (neomacs--annotation-color 'neomacs-link
+                                                         :foreground))))
+    (when (and id action)
+      (push (cons id action) neomacs--link-actions))
+    id))
+
+(defun neomacs-remove-link (id)
+  "Remove the link with id ID from the current buffer."
+  (setq neomacs--link-actions (assq-delete-all id neomacs--link-actions))
+  (neomacs-link-remove id))
+
+(defun neomacs-links-clear ()
+  "Remove all links from the current buffer."
+  (interactive)
+  (neomacs-link-clear)
+  (setq neomacs--link-actions nil)
+  (remove-hook 'after-change-functions #'neomacs-link-adjust t)
+  (remove-hook 'kill-buffer-hook #'neomacs--link-kill-buffer t))
+
+(defun neomacs-link-follow-default (target _pos)
+  "Follow TARGET by its form.
+A URL is browsed, \"file:FILE[:LINE]\" visits FILE at LINE, and
+\"help:SYMBOL\" describes SYMBOL."
+  (cond
+   ((string-match "\\`file:\\(.+?\\)\\(?::\\([0-9]+\\)\\)?\\'" target)
+    (find-file-other-window (match-string 1 target))
+    (when (match-string 2 target)
+      (goto-char (point-min))
+      (forward-line (1- (string-to-number (match-string 2 target))))))
+   ((string-match "\\`help:\\(.+\\)\\'" target)
+    (describe-symbol (intern (match-string 1 target))))
+   (t (browse-url target))))
+
+(defun neomacs-link-follow (pos)
+  "Follow the link at POS of the current buffer, if any."
+  (interactive "d")
+  (if-let* ((link (neomacs-link-at pos)))
+      (funcall (or (alist-get (car link) neomacs--link-actions)
+                   neomacs-link-follow-function)
+               (cdr link) pos)
+    (user-error "No link here")))
+
+(defun neomacs-link--handle-click (target window pos)
+  "Follow the link to TARGET clicked at POS of WINDOW.
+Called by the display engine; the link is followed from a timer, out
+of event reading."
+  (run-at-time 0 nil
+               (lambda ()
+                 (when (window-live-p window)
+                   (select-window window)
+                   (if (neomacs-link-at pos)
+                       (neomacs-link-follow pos)
+                     (funcall neomacs-link-follow-function target pos))))))
+
+;; Link sources: help buttons, Org links and compilation messages
+
+(defun neomacs--links-from-buttons ()
+  "Return the links of the buttons in the current buffer."
+  (let ((pos (point-min)) links button)
+    (while (setq button (next-button pos))
+      (let ((args (button-get button 'help-args)))
+        (push (list (button-start button) (button-end button)
+                    (cond ((eq (button-type button) 'help-url) (car args))
+                          ((and args (symbolp (car args)))
+                           (format "help:%s" (car args)))
+                          (t (button-label button)))
+                    (lambda (_target pos) (push-button pos)))
+              links))
+      (setq pos (button-end button)))
+    links))
+
+(defun neomacs--links-from-org ()
+  "Return the bracket and plain links of the current Org buffer."
+  (let (links)
+    (save-excursion
+      (dolist (re (list org-link-bracket-re org-link-plain-re))
+        (goto-char (point-min))
+        (while (re-search-forward re nil t)
+          (push (list (match-beginning 0) (match-end 0)
+                      (if (eq re org-link-bracket-re)
+                          (match-string-no-properties 1)
+                        (match-string-no-properties 0))
+                      (lambda (_target pos)
+                        (goto-char pos)
+                        (org-open-at-point)))
+                links))))
+    links))
+
+(defun neomacs--links-from-compilation ()
+  "Return the error locations of the current compilation buffer."
+  (let ((pos (point-min)) links)
+    (while (setq pos (text-property-not-all pos (point-max)
+                                            'compilation-message nil))
+      (let ((end (next-single-property-change pos 'compilation-message
+                                              nil (point-max))))
+        (push (list pos end (buffer-substring-no-properties pos end)
+                    (lambda (_target pos)
+                      (goto-char pos)
+                      (compile-goto-error)))
+              links)
+        (setq pos end)))
+    links))
+
+(defcustom neomacs-link-sources
+  '((help-mode . neomacs--links-from-buttons)
+    (org-mode . neomacs--links-from-org)
+    (compilation-mode . neomacs--links-from-compilation))
+  "Functions finding the links of buffers in `neomacs-links-mode'.
+Each element is (MODE . FUNCTION): in buffers whose major mode is or
+derives from MODE, FUNCTION returns a list of (START END TARGET ACTION)
+passed to `neomacs-add-link'."
+  :type '(alist :key-type symbol :value-type function)
+  :group 'frames)
+
+(defvar-local neomacs--links-timer nil
+  "Idle timer refreshing the current buffer's links after changes.")
+
+(defun neomacs-links-refresh ()
+  "Replace the current buffer's links by those `neomacs-link-sources' finds."
+  (interactive)
+  (neomacs-links-clear)
+  (pcase-dolist (`(,mode . ,source) neomacs-link-sources)
+    (when (derived-mode-p mode)
+      (pcase-dolist (`(,start ,end ,target ,action) (funcall source))
+        (neomacs-add-link start end target action)))))
+
+(defun neomacs--links-schedule (&rest _)
+  "Refresh the current buffer's links once Emacs is idle."
+  (unless neomacs--links-timer
+    (let ((buffer (current-buffer)))
+      (setq neomacs--links-timer
+            (run-with-idle-timer
+             0.3 nil
+             (lambda ()
+               (when (buffer-live-p buffer)
+                 (with-current-buffer buffer
+                   (setq neomacs--links-timer nil)
+                   (when neomacs-links-mode
+                     (neomacs-links-refresh))))))))))
+
+(define-minor-mode neomacs-links-mode
+  "Draw the links of the current buffer as clickable link spans.
+Links come from `neomacs-link-sources' and are refreshed as the buffer
+changes.  Requires the Rust layout engine."
+  :group 'frames
+  (if neomacs-links-mode
+      (progn
+        (add-hook 'after-change-functions #'neomacs--links-schedule nil t)
+        (neomacs--links-schedule))
+    (remove-hook 'after-change-functions #'neomacs--links-schedule t)
+    (neomacs-links-clear)))
+
+(defun neomacs--links-mode-maybe ()
+  "Turn on `neomacs-links-mode' in buffers with a link source."
+  (when (apply #'derived-mode-p (mapcar #'car neomacs-link-sources))
+    (neomacs-links-mode 1)))
+
+(define-globalized-minor-mode global-neomacs-links-mode neomacs-links-mode
+  neomacs--links-mode-maybe
+  :group 'frames)
+
+;;; Workspaces
+
+(declare-function neomacs-prepare-frame-transition "neomacsterm.c" ())
+(defvar desktop-globals-to-save)
+
+(defcustom neomacs-workspace-transition-effect nil
+  "Effect animating the windows when `neomacs-workspace-switch' changes layout.
+nil slides the new layout in from the right when cycling forward and
+from the left when cycling backward, and crossfades otherwise.  See
+`neomacs-buffer-transition-effect' for the effects."
+  :type '(choice (const :tag "Slide in the cycling direction" nil)
+                 (string :tag "Effect"))
+  :group 'frames)
+
+(defcustom neomacs-workspace-swipe-interval 0.5
+  "Seconds after a workspace swipe during which further swipes are ignored.
+A touchpad swipe sends many horizontal wheel events; only the first
+one of a swipe cycles workspaces."
+  :type 'number
+  :group 'frames)
+
+(defvar neomacs-workspaces nil
+  "Named window layouts, as an alist of (NAME . STATE).
+STATE is a writable `window-state-get' of a frame's root window: its
+window tree with each window's buffer, size, point and start.  Kept
+across sessions by `desktop-save-mode' while `neomacs-workspace-mode'
+is on.")
+
+(defvar neomacs--workspace-last-swipe 0
+  "Time of the last swipe that cycled workspaces.")
+
+(defun neomacs-workspace-current (&optional frame)
+  "Name of the workspace shown in FRAME, or nil."
+  (frame-parameter frame 'neomacs-workspace))
+
+(defun neomacs--workspace-read (prompt &optional require-match)
+  "Read a workspace name with PROMPT.
+Unless REQUIRE-MATCH, a new name may be given."
+  (let ((names (mapcar #'car neomacs-workspaces)))
+    (completing-read (format-prompt prompt (car names))
+                     names nil require-match nil nil (car names))))
+
+(defun neomacs-workspace-save (name)
+  "Save the window layout of the selected frame as workspace NAME.
+NAME becomes the frame's current workspace, and with
+`neomacs-tab-workspace-mode' the name of its current tab."
+  (interactive
+   (list (let ((current (neomacs-workspace-current)))
+           (completing-read (format-prompt "Save workspace" current)
+                            (mapcar #'car neomacs-workspaces)
+                            nil nil nil nil current))))
+  (when (string-empty-p name)
+    (user-error "No workspace name"))
+  (let ((state (window-state-get (frame-root-window) t))
+        (entry (assoc name neomacs-workspaces)))
+    (if entry
+        (setcdr entry state)
+      (setq neomacs-workspaces
+            (append neomacs-workspaces (list (cons name state))))))
+  (when (bound-and-true-p neomacs-tab-workspace-mode)
+    (tab-bar-rename-tab name))
+  (set-frame-parameter nil 'neomacs-workspace name))
+
+(defun neomacs--workspace-transition (effect function)
+  "Call FUNCTION, animating all windows of the frame with EFFECT.
+EFFECT may also be a function called after FUNCTION to return the
+effect, or nil not to animate."
+  (if (and (fboundp 'neomacs-prepare-frame-transition)
+           (neomacs-prepare-frame-transition))
+      (prog1 (funcall function)
+        (when (functionp effect)
+          (setq effect (funcall effect)))
+        (when effect
+          (neomacs--send-buffer-transition effect)
+          (neomacs-trigger-buffer-transition)
+          ;; Back to the effect of ordinary buffer changes
+          (neomacs--send-buffer-transition
+           (or neomacs-buffer-transition-effect "crossfade"))))
+    (funcall function)))
+
+(defun neomacs-workspace-switch (name &optional direction)
+  "Show workspace NAME in the selected frame.
+The layout being left is saved under the frame's current workspace
+first.  A NAME without a workspace gets a new one holding just the
+selected window.  The change is animated with
+`neomacs-workspace-transition-effect'; DIRECTION, 1 or -1, is the
+cycling direction the default effect slides in.
+With `neomacs-tab-workspace-mode', the tab named NAME is selected, or
+a new tab made for it."
+  (interactive (list (neomacs--workspace-read "Switch to workspace")))
+  (when (string-empty-p name)
+    (user-error "No workspace name"))
+  (let ((current (neomacs-workspace-current))
+        (state (cdr (assoc name neomacs-workspaces)))
+        (tabs (bound-and-true-p neomacs-tab-workspace-mode)))
+    (unless (equal name current)
+      (when current
+        (neomacs-workspace-save current))
+      (if-let* ((index (and tabs (tab-bar--tab-index-by-name name))))
+          (progn
+            ;; Animated by `neomacs--tab-transition'
+            (tab-bar-select-tab (1+ index))
+            (neomacs-workspace-save name))
+        (neomacs--workspace-transition
+         (or neomacs-workspace-transition-effect
+             (pcase direction
+               (1 "slide-left")
+               (-1 "slide-right")
+               (_ "crossfade")))
+         (lambda ()
+           (when tabs
+             (tab-bar-new-tab)
+             (tab-bar-rename-tab name))
+           (if state
+               (window-state-put state (frame-root-window) 'safe)
+             (delete-other-windows))
+           (neomacs-workspace-save name))))
+      (message "Workspace %s" name))))
+
+(defun neomacs-workspace-next (&optional n)
+  "Switch to the Nth next workspace, cycling through `neomacs-workspaces'."
+  (interactive "p")
+  (setq n (or n 1))
+  (let* ((names (mapcar #'car neomacs-workspaces))
+         (tail (member (neomacs-workspace-current) names))
+         (pos (and tail (- (length names) (length tail)))))
+    (cond
+     ((null names) (user-error "No workspaces"))
+     ((and pos (null (cdr names))) (message "Only workspace %s" (car names)))
+     (t
+      (neomacs-workspace-switch
+       (nth (mod (if pos (+ pos n) (if (> n 0) (1- n) n)) (length names))
+            names)
+       (if (> n 0) 1 -1))))))
+
+(defun neomacs-workspace-previous (&optional n)
+  "Switch to the Nth previous workspace, cycling through `neomacs-workspaces'."
+  (interactive "p")
+  (neomacs-workspace-next (- (or n 1))))
+
+(defun neomacs-workspace-kill (name)
+  "Forget workspace NAME.
+Frames showing it keep their windows but no longer have a workspace."
+  (interactive (list (neomacs--workspace-read "Kill workspace" t)))
+  (setq neomacs-workspaces (assoc-delete-all name neomacs-workspaces))
+  (dolist (frame (frame-list))
+    (when (equal (neomacs-workspace-current frame) name)
+      (set-frame-parameter frame 'neomacs-workspace nil))))
+
+(defun neomacs-workspace-swipe (event)
+  "Cycle workspaces with a horizontal swipe EVENT.
+Swiping right shows the next workspace and left the previous one; see
+`neomacs-workspace-swipe-interval'."
+  (interactive "e")
+  (let ((now (float-time)))
+    (when (> (- now neomacs--workspace-last-swipe)
+             neomacs-workspace-swipe-interval)
+      (setq neomacs--workspace-last-swipe now)
+      (if (eq (event-basic-type event) 'wheel-right)
+          (neomacs-workspace-next)
+        (neomacs-workspace-previous)))))
+
+(defvar-keymap neomacs-workspace-mode-map
+  "C-x w <right>" #'neomacs-workspace-next
+  "C-x w <left>" #'neomacs-workspace-previous
+  "C-x w RET" #'neomacs-workspace-switch
+  "C-x w =" #'neomacs-workspace-save
+  "C-x w k" #'neomacs-workspace-kill
+  "M-<wheel-right>" #'neomacs-workspace-swipe
+  "M-<wheel-left>" #'neomacs-workspace-swipe)
+
+(defun neomacs--workspace-save-current ()
+  "Save the layout of each frame's workspace, before the desktop is saved."
+  (dolist (frame (frame-list))
+    (when-let* ((name (neomacs-workspace-current frame))
+                ((assoc name neomacs-workspaces)))
+      (with-selected-frame frame
+        (neomacs-workspace-save name)))))
+
+(define-minor-mode neomacs-workspace-mode
+  "Keep named window layouts and switch between them with an animation.
+A workspace holds the windows of a frame: their buffers, sizes, points
+and starts.  `neomacs-workspace-save' names the selected frame's layout,
+`neomacs-workspace-switch' shows a workspace, and
+`neomacs-workspace-next' and `neomacs-workspace-previous' cycle through
+them, as does swiping sideways on a touchpad with Meta held.  With
+`desktop-save-mode', the workspaces are saved with the desktop and
+restored in the next session.
+
+\\{neomacs-workspace-mode-map}"
+  :global t
+  :group 'frames
+  (if neomacs-workspace-mode
+      (with-eval-after-load 'desktop
+        (add-to-list 'desktop-globals-to-save 'neomacs-workspaces)
+        (add-hook 'desktop-save-hook #'neomacs--workspace-save-current))
+    (when (boundp 'desktop-globals-to-save)
+      (setq desktop-globals-to-save
+            (delq 'neomacs-workspaces desktop-globals-to-save))
+      (remove-hook 'desktop-save-hook #'neomacs--workspace-save-current))))
+
+;;; Tab workspaces
+
+(defvar tab-bar--dragging-in-progress)
+
+(defcustom neomacs-tab-buffer-filter t
+  "Non-nil means each tab offers only the buffers shown in it.
+With `neomacs-tab-workspace-mode', `other-buffer', `previous-buffer'
+and `neomacs-tab-switch-to-buffer' then skip buffers that were never
+displayed in the current tab."
+  :type 'boolean
+  :group 'frames)
+
+(defcustom neomacs-tab-detach-on-drag t
+  "Non-nil means a tab dragged off the tab bar moves to a new frame.
+The new frame opens where the tab is dropped.  Used by
+`neomacs-tab-workspace-mode'."
+  :type 'boolean
+  :group 'frames)
+
+(defun neomacs--tab-transition (orig &rest args)
+  "Call ORIG with ARGS, animating the frame if another tab is selected.
+The new layout slides in from the right when the tab is further right
+and from the left otherwise, unless
+`neomacs-workspace-transition-effect' names an effect."
+  (let ((from (tab-bar--current-tab-index)))
+    (neomacs--workspace-transition
+     (lambda ()
+       (let ((to (tab-bar--current-tab-index)))
+         (cond ((eql from to) nil)
+               (neomacs-workspace-transition-effect)
+               ((and from to (< from to)) "slide-left")
+               (t "slide-right"))))
+     (lambda () (apply orig args)))))
+
+(defun neomacs--tab-select-workspace (from-tab to-tab)
+  "Save FROM-TAB's layout to its workspace and make TO-TAB's current.
+A tab belongs to the workspace of the same name."
+  (when-let* ((entry (assoc (alist-get 'name from-tab) neomacs-workspaces))
+              (state (alist-get 'ws from-tab)))
+    (setcdr entry state))
+  (let ((name (alist-get 'name to-tab)))
+    (set-frame-parameter nil 'neomacs-workspace
+                         (and (assoc name neomacs-workspaces) name))))
+
+(defun neomacs--tab-record-buffers (frame)
+  "Add the buffers shown in FRAME to the buffers of its current tab.
+The tab parameter `neomacs-buffers' holds their names, so that it can
+be saved with the desktop."
+  (unless (frame-parameter frame 'parent-frame)
+    (let* ((tab (tab-bar--current-tab-find nil frame))
+           (names (seq-filter #'get-buffer
+                              (alist-get 'neomacs-buffers (cdr tab)))))
+      (dolist (window (window-list frame 'no-minibuf))
+        (let ((name (buffer-name (window-buffer window))))
+          (unless (member name names)
+            (push name names))))
+      (setf (alist-get 'neomacs-buffers (cdr tab)) names))))
+
+(defun neomacs--tab-buffer-p (buffer)
+  "Return non-nil if BUFFER belongs to the current tab.
+This is the `buffer-predicate' of frames while
+`neomacs-tab-workspace-mode' is on; see `neomacs-tab-buffer-filter'."
+  (let ((names (alist-get 'neomacs-buffers (tab-bar--current-tab-find))))
+    (or (not neomacs-tab-buffer-filter)
+        (null names)
+        (member (buffer-name buffer) names))))
+
+(defun neomacs-tab-switch-to-buffer (buffer)
+  "Display BUFFER in the selected window, read among the current tab's buffers.
+See `neomacs-tab-buffer-filter'."
+  (interactive
+   (list (read-buffer "Switch to buffer in tab: "
+                      (other-buffer (current-buffer))
+                      (confirm-nonexistent-file-or-buffer)
+                      (lambda (b)
+                        (neomacs--tab-buffer-p
+                         (get-buffer (if (consp b) (cdr b) b)))))))
+  (switch-to-buffer buffer))
+
+(defun neomacs--tab-drag (orig event)
+  "Move the tab dragged by EVENT to a new frame if dropped off the tab bar.
+Otherwise call ORIG, which moves it along the tab bar.  See
+`neomacs-tab-detach-on-drag'."
+  (let ((from (tab-bar--key-to-number
+               (nth 0 (tab-bar--event-to-item (event-start event))))))
+    (if (and neomacs-tab-detach-on-drag
+             (not (eq from t))
+             (not (eq (posn-area (event-end event)) 'tab-bar))
+             (cdr (funcall tab-bar-tabs-function)))
+        (let* ((position (mouse-absolute-pixel-position))
+               (default-frame-alist `((left . ,(car position))
+                                      (top . ,(cdr position))
+                                      ,@default-frame-alist)))
+          (setq tab-bar--dragging-in-progress nil)
+          (tab-bar-detach-tab from))
+      (funcall orig event))))
+
+(defun neomacs--tab-setup-frame (frame)
+  "Filter the buffers FRAME offers by tab, unless it has a predicate."
+  (unless (frame-parameter frame 'buffer-predicate)
+    (set-frame-parameter frame 'buffer-predicate #'neomacs--tab-buffer-p)))
+
+(define-minor-mode neomacs-tab-workspace-mode
+  "Make each tab of the tab bar a workspace with its own buffers.
+Tabs already keep their own window layout; this mode animates the
+change of layout when another tab is selected, as
+`neomacs-workspace-mode' does, and makes a tab named like a workspace
+that workspace: `neomacs-workspace-switch' selects its tab and
+`neomacs-workspace-save' names the current tab.  Each tab remembers
+the buffers shown in it, and with `neomacs-tab-buffer-filter' buffer
+switching offers only those.  With `neomacs-tab-detach-on-drag',
+dragging a tab off the tab bar moves it to a new frame."
+  :global t
+  :group 'frames
+  (if neomacs-tab-workspace-mode
+      (progn
+        (advice-add 'tab-bar-select-tab :around #'neomacs--tab-transition
+                    '((name . neomacs-tab-transition)))
+        (advice-add 'tab-bar-mouse-move-tab :around #'neomacs--tab-drag
+                    '((name . neomacs-tab-drag)))
+        (add-hook 'tab-bar-tab-post-select-functions
+                  #'neomacs--tab-select-workspace)
+        (add-hook 'window-buffer-change-functions
+                  #'neomacs--tab-record-buffers)
+        (add-hook 'after-make-frame-functions #'neomacs--tab-setup-frame)
+        (mapc #'neomacs--tab-setup-frame (frame-list))
+        (mapc #'neomacs--tab-record-buffers (frame-list)))
+    (advice-remove 'tab-bar-select-tab 'neomacs-tab-transition)
+    (advice-remove 'tab-bar-mouse-move-tab 'neomacs-tab-drag)
+    (remove-hook 'tab-bar-tab-post-select-functions
+                 #'neomacs--tab-select-workspace)
+    (remove-hook 'window-buffer-change-functions
+                 #'neomacs--tab-record-buffers)
+    (remove-hook 'after-make-frame-functions #'neomacs--tab-setup-frame)
+    (dolist (frame (frame-list))
+      (when (eq (frame-parameter frame 'buffer-predicate)
+                #'neomacs--tab-buffer-p)
+        (set-frame-parameter frame 'buffer-predicate nil)))))
+
+;;; Paragraph alignment
+
+(defun neomacs--paragraph-bounds ()
+  "Beginning and end of the region if active, else of the paragraph at point."
+  (if (use-region-p)
+      (list (region-beginning) (region-end))
+    (save-excursion
+      (list (progn (backward-paragraph) (point))
+            (progn (forward-paragraph) (point))))))
+
+(defun neomacs-align-region (start end align)
+  "Display the lines between START and END aligned as ALIGN.
+ALIGN is `left', `right', `center' or `full'.  `full' widens the
+spaces between words so each row but the last of a line reaches the
+right edge of the window; it is meant for lines wrapped by
+`visual-line-mode'.  The lines get a `paragraph-align' text property,
+which the Rust layout engine applies when drawing them, so no padding
+is inserted into the buffer.  Interactively, align the region if
+active, else the paragraph at point."
+  (interactive
+   (append (neomacs--paragraph-bounds)
+           (list (intern (completing-read
+                          "Align (left, right, center, full): "
+                          '("left" "right" "center" "full") nil t)))))
+  (save-excursion
+    (let ((start (progn (goto-char start) (line-beginning-position)))
+          (end (progn (goto-char end) (line-end-position))))
+      (with-silent-modifications
+        (if (eq align 'left)
+            (remove-text-properties start end '(paragraph-align nil))
+          (put-text-property start end 'paragraph-align align))))))
+
+(defvar-local neomacs--justify-overlay nil
+  "Overlay of `neomacs-justify-mode' spanning the buffer.")
+
+(define-minor-mode neomacs-justify-mode
+  "Display the paragraphs of the buffer fully justified.
+Rows wrapped by `visual-line-mode', which this mode turns on, are
+spread to the right edge of the window; the last row of each line
+stays left-aligned.  Requires the Rust layout engine."
+  :group 'frames
+  (when neomacs--justify-overlay
+    (delete-overlay neomacs--justify-overlay)
+    (setq neomacs--justify-overlay nil))
+  (when neomacs-justify-mode
+    (visual-line-mode 1)
+    (setq neomacs--justify-overlay (make-overlay (point-min) (point-max) nil nil t))
+    (overlay-put neomacs--justify-overlay 'paragraph-align 'full)))
+
+;;; Hanging indents
+
+(defvar neomacs-hanging-indent)
+
+(defcustom neomacs-hanging-indent-extra 0
+  "Extra columns `neomacs-hanging-indent-mode' indents wrapped rows by.
+Added to the indentation of the text the row continues."
+  :type 'natnum
+  :group 'frames)
+
+(define-minor-mode neomacs-hanging-indent-mode
+  "Indent wrapped rows under the start of the text of their line.
+Continuation rows of lines without a `wrap-prefix' are indented past
+the line's leading whitespace and any list bullet or number, by
+`neomacs-hanging-indent-extra' columns more.  Turns on
+`visual-line-mode'.  Requires the Rust layout engine."
+  :group 'frames
+  (if neomacs-hanging-indent-mode
+      (progn
+        (visual-line-mode 1)
+        (setq neomacs-hanging-indent
+              (if (> neomacs-hanging-indent-extra 0)
+                  neomacs-hanging-indent-extra
+                t)))
+    (kill-local-variable 'neomacs-hanging-indent)))
+
+;;; Hyphenation
+
+(defvar neomacs-hyphenation)
+
+(defcustom neomacs-hyphenation-language "en-us"
+  "Language `neomacs-hyphenation-mode' hyphenates in.
+A code naming a pattern file hyph-LANG.pat.txt in
+`neomacs-hyphenation-directory': the bundled ones are \"en-us\",
+\"de\", \"fr\" and \"es\"."
+  :type 'string
+  :group 'frames)
+
+(define-minor-mode neomacs-hyphenation-mode
+  "Hyphenate words at the ends of wrapped rows.
+A word that `word-wrap' would move to the next row, leaving more than
+`neomacs-hyphenation-ragged-columns' empty at the end of the row, is
+broken at a hyphenation point of `neomacs-hyphenation-language' and a
+hyphen is drawn where it breaks.  The buffer text is not changed.
+Turns on `visual-line-mode'.  Requires the Rust layout engine."
+  :group 'frames
+  (if neomacs-hyphenation-mode
+      (progn
+        (visual-line-mode 1)
+        (setq neomacs-hyphenation neomacs-hyphenation-language))
+    (kill-local-variable 'neomacs-hyphenation)))
+
+;;; Vertical writing
+
+(defun neomacs-toggle-window-vertical-writing (&optional window arg)
+  "Toggle vertical writing in WINDOW, the selected window by default.
+With ARG, turn it on if ARG is positive and off otherwise.  In vertical
+writing the window shows its text in columns running top to bottom,
+the first at its right edge, as East Asian books are set: ideographs
+and kana stand upright and other text is turned sideways.  Requires
+the Rust layout engine."
+  (interactive (list nil current-prefix-arg))
+  (let* ((window (window-normalize-window window t))
+         (on (if arg
+                 (> (prefix-numeric-value arg) 0)
+               (not (window-parameter window 'vertical-writing)))))
+    (set-window-parameter window 'vertical-writing on)
+    (force-window-update window)
+    on))
+
+(defun neomacs--vertical-writing-windows (&optional frame)
+  "Match vertical writing in FRAME's windows to their buffers.
+Windows showing a buffer in `neomacs-vertical-writing-mode' are
+turned vertical, and windows the mode turned vertical that show
+another buffer now are turned back.  Windows toggled by hand are
+left alone."
+  (dolist (window (window-list frame 'no-minibuf))
+    (let ((param (window-parameter window 'vertical-writing)))
+      (if (buffer-local-value 'neomacs-vertical-writing-mode
+                              (window-buffer window))
+          (unless param
+            (set-window-parameter window 'vertical-writing 'mode)
+            (force-window-update window))
+        (when (eq param 'mode)
+          (set-window-parameter window 'vertical-writing nil)
+          (force-window-update window))))))
+
+(defvar-keymap neomacs-vertical-writing-mode-map
+  :doc "Keymap for `neomacs-vertical-writing-mode'.
+Arrow keys move the way they point on the screen: up and down along
+a column, left to the next line and right to the previous one."
+  "<down>" #'forward-char
+  "<up>" #'backward-char
+  "<left>" #'next-line
+  "<right>" #'previous-line)
+
+(define-minor-mode neomacs-vertical-writing-mode
+  "Show the buffer in vertical columns, for East Asian documents.
+Windows showing the buffer lay its text out top to bottom in columns
+running from right to left (see
+`neomacs-toggle-window-vertical-writing'), and the arrow keys move
+the way they point.  Lines are moved by logically, since Emacs's own
+visual line motion does not know about the columns.  Requires the
+Rust layout engine."
+  :group 'frames
+  :keymap neomacs-vertical-writing-mode-map
+  (if neomacs-vertical-writing-mode
+      (progn
+        (setq-local line-move-visual nil)
+        (add-hook 'window-buffer-change-functions
+                  #'neomacs--vertical-writing-windows))
+    (kill-local-variable 'line-move-visual))
+  (dolist (frame (frame-list))
+    (neomacs--vertical-writing-windows frame)))
+
+;;; Window background images
+
+(declare-function neomacs-set-window-background "neomacsterm.c"
+                  (target file &optional opacity scaling position))
+
+(defvar-local neomacs--buffer-background nil
+  "The entry of `neomacs-buffer-background-images' applied to this buffer.")
+
+(defun neomacs--buffer-background-kill ()
+  "Drop the background image of the buffer being killed."
+  (neomacs-set-window-background nil nil))
+
+(defun neomacs--buffer-background-apply (&optional buffer)
+  "Give BUFFER the background of the first matching entry, if any.
+See `neomacs-buffer-background-images'."
+  (when (fboundp 'neomacs-set-window-background)
+    (with-current-buffer (or buffer (current-buffer))
+      (let ((entry (and (boundp 'neomacs-buffer-background-images)
+                        (seq-find (lambda (e) (buffer-match-p (car e) (current-buffer)))
+                                  neomacs-buffer-background-images))))
+        (unless (equal entry neomacs--buffer-background)
+          (setq neomacs--buffer-background entry)
+          (if (null entry)
+              (progn
+                (neomacs-set-window-background nil nil)
+                (remove-hook 'kill-buffer-hook #'neomacs--buffer-background-kill t))
+            (let ((props (cddr entry)))
+              (neomacs-set-window-background nil (cadr entry)
+                                             (plist-get props :opacity)
+                                             (plist-get props :scaling)
+                                             (plist-get props :position))
+              (add-hook 'kill-buffer-hook #'neomacs--buffer-background-kill nil t))))))))
+
+(defcustom neomacs-buffer-background-images nil
+  "Background images or watermarks drawn under the text of buffers.
+Each element is (CONDITION FILE . PROPS).  CONDITION is a
+`buffer-match-p' condition, such as a buffer name regexp or
+\(major-mode . org-agenda-mode); the first matching element applies.
+FILE is an image file.  PROPS is a plist with optional :opacity (0.0
+to 1.0), :scaling and :position, as for `neomacs-set-window-background'.
+For example:
+  ((\"\\\\*dashboard\\\\*\" \"~/logo.png\" :opacity 0.2 :position bottom-right))"
+  :type '(repeat (cons (sexp :tag "Condition")
+                       (cons (file :tag "Image file")
+                             (plist :tag "Properties"))))
+  :group 'frames
+  :set (lambda (sym val)
+         (set-default sym val)
+         (dolist (buffer (buffer-list))
+           (neomacs--buffer-background-apply buffer))))
+
+(add-hook 'after-change-major-mode-hook #'neomacs--buffer-background-apply)
+
+(defun neomacs-set-buffer-background (file &optional opacity scaling position)
+  "Draw image FILE under the text of the current buffer.
+OPACITY, SCALING and POSITION are as for `neomacs-set-window-background'.
+Interactively, prompt for FILE; with a prefix argument, remove the
+current buffer's background instead."
+  (interactive
+   (list (unless current-prefix-arg
+           (read-file-name "Background image: " nil nil t))))
+  (setq neomacs--buffer-background nil)
+  (neomacs-set-window-background nil file opacity scaling position)
+  (if file
+      (add-hook 'kill-buffer-hook #'neomacs--buffer-background-kill nil t)
+    (remove-hook 'kill-buffer-hook #'neomacs--buffer-background-kill t)))
+
+;;; Character picker
+
+(declare-function neomacs-char-picker "neomacsterm.c" (candidates &optional title))
+
+(defvar neomacs--char-picker-cache nil
+  "Alist of (ALL . CANDIDATES) built by `neomacs--char-picker-candidates'.")
+
+(defun neomacs--char-picker-shortcode (name)
+  "Shortcode for the character named NAME, like \":thumbs_up_sign:\"."
+  (concat ":" (replace-regexp-in-string "[^a-z0-9+]+" "_" (downcase name)) ":"))
+
+(defun neomacs--char-picker-candidates (&optional all)
+  "Candidates for `neomacs-char-picker', in code point order.
+Only emoji, unless ALL is non-nil, in which case every named character.
+A character with several names gets the others as extra shortcodes."
+  (or (cdr (assq (and all t) neomacs--char-picker-cache))
+      (let ((table (make-hash-table)))
+        (maphash (lambda (name char)
+                   (when (or all (eq (aref char-script-table char) 'emoji))
+                     (let ((entry (gethash char table))
+                           (code (neomacs--char-picker-shortcode name)))
+                       (if entry
+                           (nconc entry (list code))
+                         (puthash char (list char (downcase name) code) table)))))
+                 (ucs-names))
+        (let (cands)
+          (maphash (lambda (_char entry) (push entry cands)) table)
+          (setq cands (sort cands (lambda (a b) (< (car a) (car b)))))
+          (push (cons (and all t) cands) neomacs--char-picker-cache)
+          cands))))
+
+(defun neomacs-insert-char-from-picker (&optional all)
+  "Pick an emoji from a searchable grid and insert it at point.
+Type to filter by name or shortcode, move with the arrow keys or the
+mouse, and press RET or click to insert.  With a prefix argument ALL,
+offer every named Unicode character instead of only emoji."
+  (interactive "*P")
+  (let ((text (neomacs-char-picker (neomacs--char-picker-candidates all)
+                                   (if all "Character" "Emoji"))))
+    (when text
+      (insert text))))
+
+;;; Command palette
+
+(declare-function neomacs-command-palette "neomacsterm.c"
+                  (candidates &optional title))
+
+(defvar neomacs--command-palette-docs (make-hash-table :test #'eq)
+  "Documentation previews of commands, computed once per command.")
+
+(defun neomacs--command-palette-doc (command)
+  "Return the documentation preview of COMMAND, its first paragraph."
+  (let ((doc (gethash command neomacs--command-palette-docs 'none)))
+    (when (eq doc 'none)
+      (setq doc (when-let* ((text (ignore-errors (documentation command))))
+                  (truncate-string-to-width
+                   (car (split-string text "\n[ \t]*\n")) 600 nil nil "...")))
+      (puthash command doc neomacs--command-palette-docs))
+    doc))
+
+(defun neomacs--command-palette-weights ()
+  "Return the ranking bonus of recently run commands, by name.
+Each run in `extended-command-history' counts, recent ones more."
+  (let ((weights (make-hash-table :test #'equal))
+        (age 0))
+    (dolist (name extended-command-history)
+      (puthash name (min 64 (+ (gethash name weights 0) (max 4 (- 32 (* 2 age)))))
+               weights)
+      (setq age (1+ age)))
+    weights))
+
+(defun neomacs--command-palette-candidates ()
+  "Candidates for `neomacs-command-palette': the commands of this buffer.
+Commands excluded by `read-extended-command-predicate' are left out."
+  (let ((weights (neomacs--command-palette-weights))
+        (buffer (current-buffer))
+        cands)
+    (mapatoms
+     (lambda (sym)
+       (when (and (commandp sym)
+                  (not (get sym 'byte-obsolete-info))
+                  (or (null read-extended-command-predicate)
+                      (funcall read-extended-command-predicate sym buffer)))
+         (let ((name (symbol-name sym))
+               (key (where-is-internal sym nil t)))
+           (push (list name (and key (key-description key))
+                       (neomacs--command-palette-doc sym)
+                       (gethash name weights 0))
+                 cands)))))
+    (sort cands (lambda (a b) (string< (car a) (car b))))))
+
+(defun neomacs-execute-extended-command (prefixarg)
+  "Choose a command from the command palette and run it.
+Like \\[execute-extended-command], but the command is chosen in a
+palette drawn by the display engine: typing fuzzy matches command
+names, key bindings are shown beside them and the documentation of the
+selected command on the right.  Commands run recently or often come
+first.  PREFIXARG is passed on to the command."
+  (interactive "P")
+  (let ((name (neomacs-command-palette (neomacs--command-palette-candidates)
+                                       (if prefixarg "C-u M-x" "M-x"))))
+    (when name
+      (add-to-history 'extended-command-history name)
+      (with-suppressed-warnings ((interactive-only execute-extended-command))
+        (execute-extended-command prefixarg name)))))
+
+(define-minor-mode neomacs-command-palette-mode
+  "Choose commands for \\[execute-extended-command] from the command palette.
+See `neomacs-execute-extended-command'."
+  :global t
+  :group 'convenience
+  :keymap '(([remap execute-extended-command] . neomacs-execute-extended-command)))
+
+;;; Color swatches and picker
+
+(declare-function neomacs-set-color-swatches "neomacsterm.c"
+                  (start end swatches &optional buffer))
+(declare-function neomacs-color-swatches-clear "neomacsterm.c" (&optional buffer))
+(declare-function neomacs-color-swatches-adjust "neomacsterm.c" (beg end old-len))
+(declare-function neomacs-color-picker "neomacsterm.c"
+                  (initial &optional title original))
+(declare-function dbus-get-unique-name "dbus" (bus))
+(declare-function dbus-register-signal "dbus"
+                  (bus service path interface signal handler &rest args))
+(declare-function dbus-unregister-object "dbus" (object))
+(declare-function dbus-call-method "dbus"
+                  (bus service path interface method &rest args))
+
+(defconst neomacs--color-literal-regexp
+  (concat "#\\(?:[[:xdigit:]]\\{6\\}\\|[[:xdigit:]]\\{3\\}\\)\\b"
+          "\\|rgba?([ \t]*[0-9.]+%?\\(?:[ \t]*[, ][ \t]*[0-9.]+%?\\)\\{2,3\\}[ \t]*)")
+  "Regexp matching the color literals `neomacs-color-swatch-mode' shows:
+#rgb, #rrggbb, rgb(r, g, b) and rgba(r, g, b, a).")
+
+(defun neomacs--color-literal-value (literal)
+  "Return the color LITERAL denotes as a \"#rrggbb\" string, or nil."
+  (cond
+   ((string-match "\\`#\\([[:xdigit:]]\\)\\([[:xdigit:]]\\)\\([[:xdigit:]]\\)\\'"
+                  literal)
+    (downcase (apply #'concat "#" (mapcar (lambda (i)
+                                            (make-string 2 (aref (match-string i literal) 0)))
+                                          '(1 2 3)))))
+   ((string-match-p "\\`#[[:xdigit:]]\\{6\\}\\'" literal)
+    (downcase literal))
+   ((string-prefix-p "rgb" literal)
+    (let ((parts (split-string (substring literal (1+ (string-search "(" literal)) -1)
+                               "[ \t,]+" t)))
+      (apply #'format "#%02x%02x%02x"
+             (mapcar (lambda (part)
+                       (let ((n (string-to-number part)))
+                         (max 0 (min 255 (round (if (string-suffix-p "%" part)
+                                                    (* n 2.55)
+                                                  n))))))
+                     (take 3 parts)))))))
+
+(defun neomacs--color-literal-format (literal color)
+  "Return COLOR, a \"#rrggbb\" string, written like LITERAL.
+The alpha of an rgba() literal and upper case hex digits are kept."
+  (if (string-prefix-p "rgb" literal)
+      (let* ((rgb (mapcar (lambda (i) (string-to-number (substring color i (+ i 2)) 16))
+                          '(1 3 5)))
+             (parts (split-string (substring literal (1+ (string-search "(" literal)) -1)
+                                  "[ \t,]+" t))
+             (alpha (nth 3 parts)))
+        (if alpha
+            (format "rgba(%d, %d, %d, %s)" (nth 0 rgb) (nth 1 rgb) (nth 2 rgb) alpha)
+          (format "rgb(%d, %d, %d)" (nth 0 rgb) (nth 1 rgb) (nth 2 rgb))))
+    (if (let ((case-fold-search nil)) (string-match-p "[A-F]" literal))
+        (upcase color)
+      color)))
+
+(defun neomacs--color-swatch-fontify (start end)
+  "Show swatches for the color literals between START and END."
+  (save-excursion
+    (save-match-data
+      (goto-char start)
+      (let ((case-fold-search t)
+            swatches)
+        (while (re-search-forward neomacs--color-literal-regexp end t)
+          (when-let* ((color (neomacs--color-literal-value
+                              (match-string-no-properties 0))))
+            (push (cons (match-beginning 0) (cons (match-end 0) color))
+                  swatches)))
+        (neomacs-set-color-swatches start end (nreverse swatches))))))
+
+(defun neomacs--color-swatches-kill-buffer ()
+  "Drop the color swatches of the buffer being killed."
+  (neomacs-color-swatches-clear))
+
+(define-minor-mode neomacs-color-swatch-mode
+  "Show a swatch of the color before each color literal.
+Hex (#rgb, #rrggbb) and CSS rgb()/rgba() literals get a square of the
+color they denote, drawn by the display engine before the literal
+without changing the text.  Use \\[neomacs-pick-color-at-point] to
+change a literal with the color picker."
+  :group 'frames
+  (if neomacs-color-swatch-mode
+      (progn
+        (add-hook 'after-change-functions #'neomacs-color-swatches-adjust nil t)
+        (add-hook 'kill-buffer-hook #'neomacs--color-swatches-kill-buffer nil t)
+        (jit-lock-register #'neomacs--color-swatch-fontify)
+        (jit-lock-refontify))
+    (jit-lock-unregister #'neomacs--color-swatch-fontify)
+    (remove-hook 'after-change-functions #'neomacs-color-swatches-adjust t)
+    (remove-hook 'kill-buffer-hook #'neomacs--color-swatches-kill-buffer t)
+    (neomacs-color-swatches-clear)))
+
+(defun neomacs-pick-screen-color ()
+  "Let the user click anywhere on the screen and return its color.
+The color is returned as a \"#rrggbb\" string, or nil if picking was
+cancelled or is not available.  This uses the PickColor method of the
+desktop portal (xdg-desktop-portal) over D-Bus."
+  (require 'dbus)
+  (let* ((token (format "neomacs%d" (random 1000000)))
+         (sender (replace-regexp-in-string
+                  "\\." "_" (substring (dbus-get-unique-name :session) 1)))
+         (request (format "/org/freedesktop/portal/desktop/request/%s/%s"
+                          sender token))
+         (result 'waiting)
+         (signal (dbus-register-signal
+                  :session nil request
+                  "org.freedesktop.portal.Request" "Response"
+                  (lambda (response results)
+                    (setq result
+                          (when-let* (((eq response 0))
+                                      (rgb (caadr (assoc "color" results))))
+                            (apply #'format "#%02x%02x%02x"
+                                   (mapcar (lambda (c) (round (* 255 c))) rgb))))))))
+    (unwind-protect
+        (condition-case err
+            (progn
+              (dbus-call-method
+               :session "org.freedesktop.portal.Desktop"
+               "/org/freedesktop/portal/desktop"
+               "org.freedesktop.portal.Screenshot" "PickColor"
+               "" `(:array (:dict-entry "handle_token" (:variant ,token))))
+              (with-timeout (120 (setq result nil))
+                (while (eq result 'waiting)
+                  (accept-process-output nil 0.05)))
+              result)
+          (dbus-error
+           (message "Cannot pick a color from the screen: %s"
+                    (error-message-string err))
+           nil))
+      (dbus-unregister-object signal))))
+
+(defun neomacs-read-color-with-picker (&optional initial title)
+  "Let the user choose a color with the color picker.
+INITIAL is the \"#rrggbb\" color shown first.  The eyedropper button
+of the picker picks a color from the screen with
+`neomacs-pick-screen-color' and goes back to the picker.  TITLE is
+shown before the color's value.  Return the chosen color as a
+\"#rrggbb\" string, or nil if cancelled."
+  (let* ((original (or initial "#808080"))
+         (color original)
+         result)
+    (while (eq (setq result (neomacs-color-picker color title original))
+               'eyedropper)
+      (setq color (or (neomacs-pick-screen-color) color)))
+    result))
+
+(defun neomacs--color-literal-at-point ()
+  "Return (START END . LITERAL) of the color literal around point, or nil."
+  (save-excursion
+    (save-match-data
+      (let ((pos (point))
+            (case-fold-search t)
+            found)
+        (goto-char (line-beginning-position))
+        (while (and (not found)
+                    (re-search-forward neomacs--color-literal-regexp
+                                       (line-end-position) t))
+          (when (<= (match-beginning 0) pos (match-end 0))
+            (setq found (cons (match-beginning 0)
+                              (cons (match-end 0)
+                                    (match-string-no-properties 0))))))
+        found))))
+
+(defun neomacs-pick-color-at-point ()
+  "Change the color literal at point with the color picker.
+The chosen color replaces the literal, written the same way (hex or
+CSS rgb()).  Without a literal at point, the chosen color is inserted
+as \"#rrggbb\"."
+  (interactive "*")
+  (let* ((literal (neomacs--color-literal-at-point))
+         (color (neomacs-read-color-with-picker
+                 (and literal (neomacs--color-literal-value (cddr literal)))
+                 "Color")))
+    (when color
+      (if literal
+          (save-excursion
+            (goto-char (car literal))
+            (delete-region (car literal) (cadr literal))
+            (insert (neomacs--color-literal-format (cddr literal) color)))
+        (insert color)))))
+
+;;; Agenda timeline
+
+(declare-function neomacs-agenda-timeline "neomacsterm.c" (items &optional title))
+(declare-function org-map-entries "org" (func &optional match scope &rest skip))
+(declare-function org-entry-get "org" (epom property &optional inherit literal-nil))
+(declare-function org-entry-is-done-p "org" ())
+(declare-function org-get-category "org" (&optional pos _))
+(declare-function org-get-heading "org" (&optional no-tags no-todo no-priority no-comment))
+(declare-function org-time-string-to-time "org" (s))
+(declare-function org-fold-show-context "org-fold" (&optional key))
+
+(defcustom neomacs-agenda-timeline-colors
+  '("#4a86c5" "#5aa469" "#d08c3a" "#a066c8" "#c9534f" "#3aa3a3" "#b5a23a")
+  "Bar colors of the agenda timeline, one per org category in turn.
+Colors are names or \"#rrggbb\" strings."
+  :type '(repeat color)
+  :group 'org-agenda)
+
+(defun neomacs--agenda-timeline-hex (color)
+  "Return COLOR, a name or \"#rrggbb\" string, as \"#rrggbb\", or nil."
+  (when-let* ((rgb (and color (color-values color))))
+    (apply #'format "#%02x%02x%02x" (mapcar (lambda (c) (ash c -8)) rgb))))
+
+(defun neomacs-show-agenda-timeline (items &optional title)
+  "Show ITEMS in the agenda timeline and act on the one chosen.
+ITEMS is a list of plists, one row each in this order, with the keys
+:label (a string), :start and :end (Lisp times; :end nil or equal to
+:start for a milestone), and optionally :category (a string), :color
+(a color name or \"#rrggbb\" string), :done (non-nil draws the item
+dimmed), and :action, a function called with the chosen plist, or
+:marker, a marker to jump to.  TITLE is shown above the chart.
+Return the chosen plist, or nil if the timeline was closed."
+  (let ((chosen (neomacs-agenda-timeline
+                 (mapcar (lambda (item)
+                           (list (plist-get item :label)
+                                 (plist-get item :start)
+                                 (plist-get item :end)
+                                 (plist-get item :category)
+                                 (neomacs--agenda-timeline-hex (plist-get item :color))
+                                 (plist-get item :done)
+                                 item))
+                         items)
+                 title)))
+    (when-let* ((item (nth 6 chosen)))
+      (cond ((plist-get item :action)
+             (funcall (plist-get item :action) item))
+            ((markerp (plist-get item :marker))
+             (neomacs--agenda-timeline-visit (plist-get item :marker))))
+      item)))
+
+(defun neomacs--agenda-timeline-visit (marker)
+  "Show the org entry at MARKER in the selected window, unfolded."
+  (pop-to-buffer-same-window (marker-buffer marker))
+  (unless (<= (point-min) marker (point-max))
+    (widen))
+  (goto-char marker)
+  (when (derived-mode-p 'org-mode)
+    (org-fold-show-context 'agenda)))
+
+(defun neomacs--agenda-timeline-span (timestamp)
+  "Return (START . END) of the org TIMESTAMP string, as Lisp times.
+A timestamp with a time range like 10:00-12:00 spans that range, one
+with a single time of day is a point in time and one without a time
+spans the whole day."
+  (let ((start (org-time-string-to-time timestamp)))
+    (cons start
+          (cond ((string-match "[0-9]+:[0-9]+-\\([0-9]+\\):\\([0-9]+\\)" timestamp)
+                 (let ((time (decode-time start)))
+                   (setf (decoded-time-hour time)
+                         (string-to-number (match-string 1 timestamp))
+                         (decoded-time-minute time)
+                         (string-to-number (match-string 2 timestamp)))
+                   (encode-time time)))
+                ((string-match "[0-9]+:[0-9]+" timestamp) start)
+                (t (encode-time (decoded-time-add (decode-time start)
+                                                  (make-decoded-time :day 1))))))))
+
+(defun neomacs--agenda-timeline-entry (colors)
+  "Return the timeline item of the org entry at point, or nil.
+Entries without a SCHEDULED or DEADLINE time have none.  An entry with
+both spans from the one to the other, one with only a deadline is a
+milestone.  COLORS is a hash table of the colors of the categories
+seen so far, updated for a new one."
+  (let ((scheduled (org-entry-get nil "SCHEDULED"))
+        (deadline (org-entry-get nil "DEADLINE")))
+    (when (or scheduled deadline)
+      (let* ((span (and scheduled (neomacs--agenda-timeline-span scheduled)))
+             (due (and deadline (org-time-string-to-time deadline)))
+             (category (org-get-category))
+             (color (or (gethash category colors)
+                        (puthash category
+                                 (nth (mod (hash-table-count colors)
+                                           (max 1 (length neomacs-agenda-timeline-colors)))
+                                      neomacs-agenda-timeline-colors)
+                                 colors))))
+        (list :label (org-get-heading t t t t)
+              :category category
+              :start (if span (car span) due)
+              :end (cond ((and span due (time-less-p (car span) due)) due)
+                         (span (cdr span))
+                         (t due))
+              :color color
+              :done (org-entry-is-done-p)
+              :marker (point-marker))))))
+
+(defun neomacs-org-agenda-timeline (&optional match)
+  "Show the scheduled tasks and deadlines of the agenda files as a timeline.
+Each org entry with a SCHEDULED or DEADLINE time gets a row, earliest
+first: a bar from the scheduled time to the deadline (or over the
+scheduled day or time range), or a diamond for a deadline alone, colored
+by category from `neomacs-agenda-timeline-colors'.  Done tasks are
+dimmed.  Pan with the arrow keys, the wheel or by dragging, zoom with
++ and - or C-wheel, press t for today and f to fit everything; RET or a
+click visits the entry.  With a prefix argument, only entries matching
+the tags/property MATCH, read from the minibuffer, are shown."
+  (interactive (list (and current-prefix-arg
+                          (read-string "Match (tags/properties): "))))
+  (require 'org)
+  (let* ((colors (make-hash-table :test #'equal))
+         (items (delq nil (org-map-entries
+                           (lambda () (neomacs--agenda-timeline-entry colors))
+                           (and match (not (string-empty-p match)) match)
+                           'agenda))))
+    (if (null items)
+        (message "No scheduled tasks or deadlines in the agenda files")
+      (neomacs-show-agenda-timeline
+       (sort items (lambda (a b)
+                     (time-less-p (plist-get a :start) (plist-get b :start))))
+       "Agenda"))))
+
+;;; Charts
+
+(declare-function neomacs-render-chart "neomacsterm.c"
+                  (type width height series &optional labels title colors range))
+(declare-function org-at-table-p "org" (&optional table-type))
+(declare-function org-table-to-lisp "org-table" (&optional txt))
+(defvar csv-separators)
+
+(defcustom neomacs-chart-colors
+  '("#4c9be8" "#e8804c" "#5aa469" "#c9534f" "#a066c8" "#3aa3a3" "#b5a23a")
+  "Colors of the data series of charts, in turn.
+Colors are names or \"#rrggbb\" strings."
+  :type '(repeat color)
+  :group 'neomacs)
+
+(defun neomacs--chart-series (series)
+  "Return SERIES as a list of (NAME COLOR . VALUES) for `neomacs-render-chart'.
+SERIES is a list of numbers, one series, or a list of (NAME . VALUES)."
+  (let ((series (if (consp (car series))
+                    series
+                  (list (cons "" series))))
+        (i -1))
+    (mapcar (lambda (s)
+              (setq i (1+ i))
+              (cons (format "%s" (car s))
+                    (cons (neomacs--agenda-timeline-hex
+                           (nth (mod i (max 1 (length neomacs-chart-colors)))
+                                neomacs-chart-colors))
+                          (cdr s))))
+            series)))
+
+(defun neomacs--chart-help (series labels index)
+  "Return the tooltip of position INDEX of the chart of SERIES and LABELS."
+  (mapconcat #'identity
+             (delq nil
+                   (cons (nth index labels)
+                         (mapcar (lambda (s)
+                                   (let ((v (nth index (cddr s))))
+                                     (when v
+                                       (if (string-empty-p (car s))
+                                           (format "%g" v)
+                                         (format "%s: %g" (car s) v)))))
+                                 series)))
+             "\n"))
+
+(defun neomacs-chart-image (type series &rest props)
+  "Return an image of a chart of SERIES.
+TYPE is `line', `bar' or `sparkline'.  SERIES is a list of numbers or a
+list of (NAME . VALUES), each drawn in the next color of
+`neomacs-chart-colors'; nil values are missing.  PROPS is a plist with
+the keys :width and :height, in pixels, by default 480 x 240, or 12
+columns x one line for a sparkline; :labels, strings shown under the x
+positions; :title, shown above the chart; :range, (MIN . MAX) of the
+values shown, nil to fit the data; and :background, a color, nil for
+transparent.  Hovering over the chart shows the label and values at
+the mouse."
+  (let* ((sparkline (eq type 'sparkline))
+         (width (or (plist-get props :width)
+                    (if sparkline (* 12 (frame-char-width)) 480)))
+         (height (or (plist-get props :height)
+                     (if sparkline (frame-char-height) 240)))
+         (series (neomacs--chart-series series))
+         (labels (mapcar (lambda (l) (format "%s" l)) (plist-get props :labels)))
+         (colors (cons (neomacs--agenda-timeline-hex
+                        (face-foreground 'default nil t))
+                       (neomacs--agenda-timeline-hex
+                        (plist-get props :background))))
+         (chart (neomacs-render-chart type width height series labels
+                                      (plist-get props :title) colors
+                                      (plist-get props :range))))
+    (create-image
+     (car chart) 'png t
+     :scale 1 :ascent 'center
+     :map (mapcar (lambda (area)
+                    (pcase-let ((`(,index ,x0 ,y0 ,x1 ,y1) area))
+                      (list (cons 'rect (cons (cons x0 y0) (cons x1 y1)))
+                            (intern (format "chart-%d" index))
+                            (list 'help-echo
+                                  (neomacs--chart-help series labels index)
+                                  'pointer 'hand))))
+                  (cdr chart)))))
+
+(defun neomacs-sparkline (values &optional width)
+  "Return a string showing VALUES as a sparkline one line high.
+WIDTH is in columns, 12 by default.  Hovering over the sparkline shows
+the value at the mouse."
+  (propertize " " 'display
+              (neomacs-chart-image 'sparkline values
+                                   :width (* (or width 12) (frame-char-width)))))
+
+(defun neomacs-chart-insert (type series &rest props)
+  "Insert a chart of SERIES at point.
+TYPE, SERIES and PROPS are as for `neomacs-chart-image'."
+  (insert-image (apply #'neomacs-chart-image type series props) "[chart]"))
+
+(defun neomacs-chart-show (type series &rest props)
+  "Show a chart of SERIES in the *Chart* buffer, filling its window.
+TYPE, SERIES and PROPS are as for `neomacs-chart-image', but the
+chart's size is that of the window.  Return the window."
+  (let ((buffer (get-buffer-create "*Chart*")))
+    (with-current-buffer buffer
+      (let ((inhibit-read-only t))
+        (erase-buffer)
+        (special-mode)))
+    (let ((window (display-buffer buffer)))
+      (with-current-buffer buffer
+        (let ((inhibit-read-only t))
+          (apply #'neomacs-chart-insert type series
+                 :width (- (window-body-width window t) (frame-char-width))
+                 :height (- (window-body-height window t) (frame-char-height))
+                 props))
+        (goto-char (point-min)))
+      window)))
+
+(defun neomacs--chart-number (cell)
+  "Return the number in table CELL, a string, or nil if it has none."
+  (let ((cell (string-trim cell)))
+    (when (string-match-p "\\`[-+]?[0-9.,]*[0-9][0-9.,]*%?\\'" cell)
+      (string-to-number (replace-regexp-in-string "[,%]" "" cell)))))
+
+(defun neomacs--chart-table (rows)
+  "Return (LABELS . SERIES) of the table ROWS, lists of cell strings.
+The first row is taken as column names if its cells after the first
+aren't numbers; the first column labels the rows; every other column
+with a number is a series."
+  (let* ((header (and (cdr rows)
+                      (not (delq nil (mapcar #'neomacs--chart-number (cdar rows))))
+                      (pop rows)))
+         (columns (apply #'max 0 (mapcar #'length rows)))
+         (series nil))
+    (dotimes (c (1- columns))
+      (let ((values (mapcar (lambda (row)
+                              (neomacs--chart-number (or (nth (1+ c) row) "")))
+                            rows)))
+        (when (delq nil (copy-sequence values))
+          (push (cons (or (nth (1+ c) header) (format "%d" (+ c 2))) values)
+                series))))
+    (cons (mapcar (lambda (row) (string-trim (car row))) rows)
+          (nreverse series))))
+
+(defun neomacs--chart-read-type ()
+  "Read a chart type in the minibuffer."
+  (intern (completing-read "Chart type: " '("line" "bar") nil t nil nil "bar")))
+
+(defun neomacs-chart-org-table (type)
+  "Chart the org table at point.
+The first column labels the rows and each numeric column is drawn as a
+series of TYPE, `line' or `bar', named by the table's first row if it
+is a header."
+  (interactive (list (neomacs--chart-read-type)))
+  (require 'org-table)
+  (unless (org-at-table-p)
+    (user-error "Not in an org table"))
+  (let ((table (neomacs--chart-table (delq 'hline (org-table-to-lisp)))))
+    (neomacs-chart-show type (cdr table) :labels (car table)
+                        :title (buffer-name))))
+
+(defun neomacs-chart-csv (type)
+  "Chart the comma (or `csv-separators') separated values of the buffer.
+The first column labels the rows and each numeric column is drawn as a
+series of TYPE, `line' or `bar', named by the first line if it is a
+header.  With an active region, only its lines are used."
+  (interactive (list (neomacs--chart-read-type)))
+  (let* ((separators (if (bound-and-true-p csv-separators)
+                         (apply #'concat csv-separators)
+                       ","))
+         (text (if (use-region-p)
+                   (buffer-substring-no-properties (region-beginning) (region-end))
+                 (buffer-substring-no-properties (point-min) (point-max))))
+         (rows (mapcar (lambda (line)
+                         (mapcar (lambda (cell) (string-trim cell "[ \t\"]+" "[ \t\"]+"))
+                                 (split-string line (regexp-opt (mapcar #'string separators)))))
+                       (split-string text "\n" t "[ \t\r]*")))
+         (table (neomacs--chart-table rows)))
+    (unless (cdr table)
+      (user-error "No numeric columns"))
+    (neomacs-chart-show type (cdr table) :labels (car table)
+                        :title (buffer-name))))
+
+;;; Graph view
+
+(declare-function neomacs-graph-view "neomacsterm.c"
+                  (nodes edges &optional layered title current))
+(declare-function org-roam-db-query "org-roam-db" (sql &rest args))
+(declare-function org-roam-node-at-point "org-roam-node" (&optional assert))
+(declare-function org-roam-node-id "org-roam-node" (node))
+(declare-function org-roam-node-from-id "org-roam-node" (id))
+(declare-function org-roam-node-visit "org-roam-node" (node &optional other-window force))
+(declare-function undo-list-transfer-to-tree "undo-tree" ())
+(declare-function undo-tree-set "undo-tree" (node &optional preserve-timestamps))
+(declare-function undo-tree-root "undo-tree" (tree))
+(declare-function undo-tree-current "undo-tree" (tree))
+(declare-function undo-tree-node-next "undo-tree" (node))
+(declare-function undo-tree-node-timestamp "undo-tree" (node))
+(defvar buffer-undo-tree)
+
+(defun neomacs-show-graph (nodes edges &rest props)
+  "Show a graph of NODES and EDGES and act on the node chosen.
+NODES is a list of plists, one per node, with the keys :id (any value,
+compared with `equal') and :label (a string), and optionally :color (a
+color name or \"#rrggbb\" string) and :action, a function called with
+the chosen plist.  EDGES is a list of (FROM . TO) ids; an edge with an
+end that is not a node is ignored.  PROPS are keyword arguments:
+:layered non-nil places the nodes in layers following the edges, as for
+trees, instead of by a force-directed layout; :title is shown above the
+graph; :current is the id of the node selected at first.
+Return the chosen plist, or nil if the graph was closed."
+  (let ((index (make-hash-table :test #'equal))
+        (i 0))
+    (dolist (node nodes)
+      (puthash (plist-get node :id) i index)
+      (setq i (1+ i)))
+    (let ((chosen
+           (neomacs-graph-view
+            (mapcar (lambda (node)
+                      (list (format "%s" (or (plist-get node :label) ""))
+                            (neomacs--agenda-timeline-hex (plist-get node :color))
+                            node))
+                    nodes)
+            (delq nil (mapcar (lambda (edge)
+                                (let ((from (gethash (car edge) index))
+                                      (to (gethash (cdr edge) index)))
+                                  (and from to (cons from to))))
+                              edges))
+            (plist-get props :layered)
+            (plist-get props :title)
+            (gethash (plist-get props :current) index))))
+      (when-let* ((node (nth 2 chosen)))
+        (when (plist-get node :action)
+          (funcall (plist-get node :action) node))
+        node))))
+
+(defun neomacs-org-roam-graph ()
+  "Show the org-roam notes and the links between them as a graph.
+The notes settle into place by a force-directed layout, with the note
+at point selected.  RET or a click visits the note chosen."
+  (interactive)
+  (require 'org-roam)
+  (let ((here (when-let* ((node (org-roam-node-at-point)))
+                (org-roam-node-id node)))
+        (visit (lambda (node)
+                 (org-roam-node-visit
+                  (org-roam-node-from-id (plist-get node :id))))))
+    (neomacs-show-graph
+     (mapcar (lambda (row)
+               (list :id (car row) :label (cadr row) :action visit))
+             (org-roam-db-query [:select [id title] :from nodes]))
+     (mapcar (lambda (row) (cons (car row) (cadr row)))
+             (org-roam-db-query [:select :distinct [source dest] :from links
+                                 :where (= type "id")]))
+     :title "Org-roam"
+     :current here)))
+
+(defun neomacs-undo-tree-graph ()
+  "Show the undo tree of the current buffer as a graph.
+Each buffer state is a node labelled with the time it was made, below
+the state it was made from, with the current state selected.  RET or a
+click on a node undoes or redoes to that state.  Needs `undo-tree-mode'."
+  (interactive)
+  (unless (bound-and-true-p undo-tree-mode)
+    (user-error "Undo-tree mode is not enabled in this buffer"))
+  (undo-list-transfer-to-tree)
+  (let* ((buffer (current-buffer))
+         (root (undo-tree-root buffer-undo-tree))
+         (ids (make-hash-table :test #'eq))
+         (queue (list root))
+         (go (lambda (node)
+               (with-current-buffer buffer
+                 (undo-tree-set (plist-get node :state)))))
+         nodes edges)
+    ;; Breadth first, numbering each state as it is queued so the
+    ;; nodes come out in the order of their ids
+    (puthash root 0 ids)
+    (while queue
+      (let* ((state (pop queue))
+             (id (gethash state ids))
+             (time (undo-tree-node-timestamp state)))
+        (push (list :id id
+                    :label (cond ((eq state root) "root")
+                                 (time (format-time-string "%H:%M:%S" time))
+                                 (t "?"))
+                    :state state
+                    :action go)
+              nodes)
+        (dolist (next (undo-tree-node-next state))
+          (puthash next (hash-table-count ids) ids)
+          (push (cons id (gethash next ids)) edges)
+          (setq queue (append queue (list next))))))
+    (neomacs-show-graph (nreverse nodes) (nreverse edges)
+                        :layered t
+                        :title (format "Undo tree of %s" (buffer-name))
+                        :current (gethash (undo-tree-current buffer-undo-tree) ids))))
+
+;;; Directory view
+
+(declare-function neomacs-directory-view "neomacsterm.c"
+                  (directory &optional show-hidden icons))
+
+(defcustom neomacs-directory-view-icons
+  '((".rs" . rust) (".c" . c) (".h" . c) (".cpp" . c) (".py" . python)
+    (".el" . emacs) (".elc" . emacs) (".org" . org) (".md" . markdown)
+    (".json" . json) (".toml" . config) (".yml" . config)
+    (".yaml" . config) (".ini" . config) (".conf" . config)
+    (".sh" . shell) (".bash" . shell) (".zsh" . shell)
+    (".png" . image) (".jpg" . image) (".jpeg" . image) (".gif" . image)
+    (".svg" . image) (".webp" . image) (".zip" . archive)
+    (".tar" . archive) (".gz" . archive) (".xz" . archive)
+    (".zst" . archive) (".7z" . archive) (".js" . code) (".ts" . code)
+    (".go" . code) (".java" . code) (".rb" . code) (".lua" . code))
+  "Icons of files in the directory view, as (SUFFIX . ICON).
+A file uses the icon of the longest SUFFIX its name ends in, ignoring
+case.  ICON names an icon defined with `neomacs-define-icon'."
+  :type '(alist :key-type string :value-type symbol)
+  :group 'neomacs)
+
+(defcustom neomacs-directory-view-show-hidden nil
+  "Non-nil means the directory view lists files starting with a dot."
+  :type 'boolean
+  :group 'neomacs)
+
+(defun neomacs-directory-view-find-file (&optional directory)
+  "Browse DIRECTORY in the directory view and visit the file chosen.
+DIRECTORY defaults to `default-directory'; with a prefix argument,
+read it.  Unlike Dired, the listing is read in the background and
+drawn by the display engine, so even huge directories open at once."
+  (interactive
+   (list (and current-prefix-arg
+              (read-directory-name "Directory view: "))))
+  (when-let* ((file (neomacs-directory-view
+                     (or directory default-directory)
+                     neomacs-directory-view-show-hidden
+                     neomacs-directory-view-icons)))
+    (find-file file)))
+
+;;; Process monitor
+
+(declare-function neomacs-process-monitor "neomacsterm.c"
+                  (&optional interval))
+
+(defcustom neomacs-process-monitor-interval 2
+  "Seconds between samples of the process monitor."
+  :type 'number
+  :group 'neomacs)
+
+(defun neomacs-proced ()
+  "Browse running processes in the process monitor.
+Like `proced', but sampled in the background and drawn by the display
+engine, with a CPU sparkline for each process.  Signals and niceness
+are changed from within the monitor; the attributes of the process
+chosen with RET are shown in a help buffer."
+  (interactive)
+  (when-let* ((pid (neomacs-process-monitor
+                    neomacs-process-monitor-interval))
+              (attributes (process-attributes pid)))
+    (with-help-window (format "*Process %d*" pid)
+      (dolist (attribute attributes)
+        (princ (format "%-10s %S\n" (car attribute) (cdr attribute)))))))
+
+;;; Date picker
+
+(declare-function neomacs-date-picker "neomacsterm.c"
+                  (&optional initial title highlight months week-start))
+(declare-function holiday-in-range "holidays" (d1 d2))
+(declare-function calendar-absolute-from-gregorian "calendar" (date))
+(declare-function calendar-gregorian-from-absolute "calendar" (date))
+(declare-function calendar-current-date "calendar" (&optional offset))
+(defvar calendar-week-start-day)
+(defvar org-time-was-given)
+(defvar org-end-time-was-given)
+
+(defface neomacs-date-picker-today
+  '((((background dark)) :foreground "#f0a040")
+    (t :foreground "#c06000"))
+  "Face whose foreground rings today in the date picker."
+  :group 'calendar)
+
+(defface neomacs-date-picker-selected
+  '((((background dark)) :foreground "#ffffff" :background "#3a6fc8")
+    (t :foreground "#ffffff" :background "#2f66c4"))
+  "Face of the selected day in the date picker.
+Only the foreground and background colors are used."
+  :group 'calendar)
+
+(defface neomacs-date-picker-highlight
+  '((((background dark)) :foreground "#f08080")
+    (t :foreground "#c02020"))
+  "Face whose foreground colors highlighted days in the date picker."
+  :group 'calendar)
+
+(defcustom neomacs-date-picker-months 1
+  "Number of months the date picker shows side by side, 1 to 3."
+  :type '(choice (const 1) (const 2) (const 3))
+  :group 'calendar)
+
+(defcustom neomacs-date-picker-highlight-function
+  #'neomacs-date-picker-holidays
+  "Function returning the dates the date picker highlights.
+It is called with the first and last calendar dates, lists (MONTH DAY
+YEAR), around the initial date and returns a list of dates in between.
+nil highlights nothing."
+  :type '(choice (const :tag "None" nil)
+                 (function-item neomacs-date-picker-holidays)
+                 function)
+  :group 'calendar)
+
+(defun neomacs-date-picker-holidays (start end)
+  "Return the dates of `calendar-holidays' from START to END."
+  (require 'holidays)
+  (mapcar #'car (holiday-in-range (calendar-absolute-from-gregorian start)
+                                  (calendar-absolute-from-gregorian end))))
+
+(defun neomacs-read-date (&optional prompt initial)
+  "Let the user choose a date in the date picker and return it.
+PROMPT is shown above the months and INITIAL, a calendar date (MONTH
+DAY YEAR), selected at first, today if nil.  Highlighted dates come
+from `neomacs-date-picker-highlight-function' and weeks start on
+`calendar-week-start-day'.  Return the chosen date, or nil if the
+picker was closed."
+  (require 'calendar)
+  (let* ((initial (or initial (calendar-current-date)))
+         (day (calendar-absolute-from-gregorian initial))
+         (highlight
+          (and neomacs-date-picker-highlight-function
+               (funcall neomacs-date-picker-highlight-function
+                        (calendar-gregorian-from-absolute (- day 93))
+                        (calendar-gregorian-from-absolute (+ day 366))))))
+    (neomacs-date-picker initial prompt highlight neomacs-date-picker-months
+                         calendar-week-start-day)))
+
+(defun neomacs--org-read-date (read-date &optional with-time to-time
+                                         from-string prompt default-time
+                                         default-input inactive)
+  "Read an org date in the date picker, around READ-DATE.
+Dates given as FROM-STRING or with DEFAULT-INPUT, and dates read on
+other displays, are read by READ-DATE as usual.  With WITH-TIME, a
+time of day is read in the minibuffer after the date; an empty answer
+gives none.  Return the date as READ-DATE would, a Lisp time if
+TO-TIME and else a string like \"2026-10-16 14:30\"; quit if the
+picker is closed."
+  (if (or from-string default-input
+          (not (eq (framep (selected-frame)) 'neomacs)))
+      (funcall read-date with-time to-time from-string prompt default-time
+               default-input inactive)
+    (let* ((default (decode-time (or default-time (current-time))))
+           (date (or (neomacs-read-date
+                      (string-trim-right (or prompt "Date") "[: ]+")
+                      (list (decoded-time-month default)
+                            (decoded-time-day default)
+                            (decoded-time-year default)))
+                     (keyboard-quit)))
+           (time (and with-time
+                      (let ((answer (read-string "Time (HH:MM, empty for none): ")))
+                        (and (string-match "\\`\\([0-9]\\{1,2\\}\\):\\([0-9]\\{2\\}\\)\\'"
+                                           answer)
+                             (list (string-to-number (match-string 1 answer))
+                                   (string-to-number (match-string 2 answer))))))))
+      (setq org-time-was-given (and time t)
+            org-end-time-was-given nil)
+      (if to-time
+          (encode-time (list 0 (or (nth 1 time) 0) (or (car time) 0)
+                             (nth 1 date) (car date) (nth 2 date)
+                             nil -1 nil))
+        (concat (format "%04d-%02d-%02d" (nth 2 date) (car date) (nth 1 date))
+                (and time (apply #'format " %02d:%02d" time)))))))
+
+(define-minor-mode neomacs-org-date-picker-mode
+  "Read org dates in the date picker instead of the calendar.
+The prompts of `org-schedule', `org-deadline', `org-time-stamp' and
+everything else reading a date through `org-read-date' show the date
+picker, with holidays highlighted; see `neomacs-read-date'.  Dates
+typed as text, such as \"+2w\", are still read from the minibuffer
+when a command passes one."
+  :global t
+  :group 'calendar
+  (if neomacs-org-date-picker-mode
+      (advice-add 'org-read-date :around #'neomacs--org-read-date)
+    (advice-remove 'org-read-date #'neomacs--org-read-date)))
+
+;;; Encrypted files
+
+(declare-function neomacs-crypto-available-p "neomacsfns.c" ())
+(declare-function neomacs-crypto-configure "neomacsfns.c"
+                  (age-identities openpgp-secret-keys cache-timeout))
+(declare-function neomacs-crypto-clear-cache "neomacsfns.c" ())
+(declare-function neomacs-crypto-decrypt-file "neomacsfns.c"
+                  (file &optional passphrase))
+(declare-function neomacs-crypto-decrypt-string "neomacsfns.c"
+                  (string &optional context passphrase))
+(declare-function neomacs-crypto-encrypt "neomacsfns.c"
+                  (string format recipients &optional passphrase context
+                          file armor))
+(declare-function neomacs-crypto-job-result "neomacsfns.c" (job))
+
+(defvar neomacs-crypto-mode)
+
+(define-error 'neomacs-crypto-error "Cannot decrypt" 'file-error)
+(define-error 'neomacs-crypto-no-key "No secret key can decrypt"
+  'neomacs-crypto-error)
+
+(defun neomacs--crypto-set (symbol value)
+  "Set SYMBOL to VALUE and hand the keys to the display engine."
+  (set-default symbol value)
+  (when (bound-and-true-p neomacs-crypto-mode)
+    (neomacs--crypto-configure)))
+
+(defcustom neomacs-crypto-age-identities '("~/.config/age/keys.txt")
+  "Files of age identities that decrypt age files.
+Missing files are ignored.  Files encrypted with a passphrase do not
+need an identity."
+  :type '(repeat file)
+  :set #'neomacs--crypto-set
+  :group 'neomacs)
+
+(defcustom neomacs-crypto-openpgp-secret-keys nil
+  "Files of exported OpenPGP secret keys that decrypt .gpg files.
+Export a key with \"gpg --export-secret-keys KEY > FILE\".  Missing
+files are ignored."
+  :type '(repeat file)
+  :set #'neomacs--crypto-set
+  :group 'neomacs)
+
+(defcustom neomacs-crypto-cache-timeout 600
+  "Seconds a passphrase that opened an encrypted file is remembered.
+Saving the file again within that time does not ask for it.  0 means
+always ask."
+  :type 'number
+  :set #'neomacs--crypto-set
+  :group 'neomacs)
+
+(defcustom neomacs-crypto-age-recipients nil
+  "Age recipients (\"age1...\") that saved .age files are encrypted to.
+If nil, they are encrypted with a passphrase."
+  :type '(repeat string)
+  :group 'neomacs)
+
+(defcustom neomacs-crypto-openpgp-recipients nil
+  "Files of OpenPGP public keys that saved .gpg files are encrypted to.
+If nil, they are encrypted with a passphrase."
+  :type '(repeat file)
+  :group 'neomacs)
+
+(defconst neomacs-crypto-file-regexp "\\.\\(age\\|gpg\\)\\'"
+  "Regexp matching the names of files `neomacs-crypto-mode' handles.")
+
+(defun neomacs--crypto-configure ()
+  "Hand the secret keys and cache timeout to the display engine."
+  (let (age openpgp)
+    (dolist (file neomacs-crypto-age-identities)
+      (when (file-readable-p file)
+        (push (expand-file-name file) age)))
+    (dolist (file neomacs-crypto-openpgp-secret-keys)
+      (when (file-readable-p file)
+        (push (expand-file-name file) openpgp)))
+    (neomacs-crypto-configure (nreverse age) (nreverse openpgp)
+                              neomacs-crypto-cache-timeout)))
+
+(defun neomacs--crypto-wait (job)
+  "Wait for JOB to finish and return its result.
+Emacs keeps redisplaying and running timers meanwhile."
+  (let (result)
+    (while (eq (setq result (neomacs-crypto-job-result job)) 'pending)
+      (accept-process-output nil 0.02))
+    result))
+
+(defun neomacs--crypto-run (start what &optional confirm)
+  "Run the job returned by START, called with a passphrase or nil.
+START is first called with nil, so that the secret keys and cached
+passphrases are tried; if they do not do, the passphrase for WHAT is
+read in the minibuffer, and read again if it is wrong, three times at
+most.  With CONFIRM, a new passphrase is read twice.  Return the
+result of the job, a unibyte string."
+  (let ((tries 0) passphrase result)
+    (unwind-protect
+        (while (not (stringp (setq result (neomacs--crypto-wait
+                                           (funcall start passphrase)))))
+          (pcase result
+            ((or 'need-passphrase 'bad-passphrase)
+             (when (eq result 'bad-passphrase)
+               (when (>= (setq tries (1+ tries)) 3)
+                 (signal 'neomacs-crypto-error
+                         (list "Wrong passphrase" what))))
+             (when passphrase
+               (clear-string passphrase))
+             (setq passphrase
+                   (read-passwd
+                    (format (if (eq result 'bad-passphrase)
+                                "Wrong passphrase, try again for %s: "
+                              "Passphrase for %s: ")
+                            (file-name-nondirectory what))
+                    confirm)))
+            ('no-key
+             (signal 'neomacs-crypto-no-key (list what)))
+            (`(failed . ,message)
+             (signal 'neomacs-crypto-error (list message what)))
+            (_
+             (signal 'neomacs-crypto-error (list "Job lost" what)))))
+      (when passphrase
+        (clear-string passphrase)))
+    result))
+
+(defun neomacs--crypto-real-handler (operation args)
+  "Run OPERATION on ARGS without `neomacs--crypto-file-handler'."
+  (let ((inhibit-file-name-handlers
+         (cons #'neomacs--crypto-file-handler
+               (and (eq inhibit-file-name-operation operation)
+                    inhibit-file-name-handlers)))
+        (inhibit-file-name-operation operation))
+    (apply operation args)))
+
+(defun neomacs--crypto-insert-file-contents (file &optional visit beg end
+                                                  replace)
+  "Insert the decrypted contents of FILE, as `insert-file-contents'."
+  (setq file (expand-file-name file))
+  (if (not (file-exists-p file))
+      (neomacs--crypto-real-handler
+       'insert-file-contents (list file visit beg end replace))
+    (let* ((plaintext (neomacs--crypto-run
+                       (lambda (passphrase)
+                         (neomacs-crypto-decrypt-file file passphrase))
+                       file))
+           (text (decode-coding-string
+                  (if (or beg end) (substring plaintext (or beg 0) end)
+                    plaintext)
+                  (or coding-system-for-read 'undecided)))
+           (inhibit-read-only t))
+      (clear-string plaintext)
+      (when replace
+        (delete-region (point-min) (point-max)))
+      (save-excursion (insert text))
+      (when visit
+        ;; Auto-save files would hold the plain text.
+        (setq-local auto-save-default nil)
+        (auto-save-mode -1)
+        (setq buffer-file-name file)
+        (set-visited-file-modtime)
+        (set-buffer-modified-p nil))
+      (list file (length text)))))
+
+(defun neomacs--crypto-write-region (start end file &optional append visit
+                                           _lockname mustbenew)
+  "Encrypt the region from START to END into FILE, as `write-region'."
+  (setq file (expand-file-name file))
+  (when append
+    (signal 'file-error (list "Cannot append to an encrypted file" file)))
+  (when (and mustbenew (file-exists-p file))
+    (if (eq mustbenew 'excl)
+        (signal 'file-already-exists (list "File exists" file))
+      (unless (yes-or-no-p (format "File %s exists; overwrite? " file))
+        (signal 'file-already-exists (list "File exists" file)))))
+  (let* ((coding (or coding-system-for-write buffer-file-coding-system
+                     'utf-8))
+         (plaintext (encode-coding-string
+                     (if (stringp start) start
+                       (buffer-substring-no-properties
+                        (or start (point-min)) (or end (point-max))))
+                     coding))
+         (format (if (string-match-p "\\.age\\'" file) 'age 'openpgp))
+         (recipients (if (eq format 'age) neomacs-crypto-age-recipients
+                       neomacs-crypto-openpgp-recipients)))
+    (unwind-protect
+        (neomacs--crypto-run
+         (lambda (passphrase)
+           (neomacs-crypto-encrypt plaintext format recipients passphrase
+                                   file file))
+         file (null recipients))
+      (clear-string plaintext))
+    (setq last-coding-system-used coding)
+    (when (or (eq visit t) (stringp visit))
+      (setq buffer-file-name (if (stringp visit) (expand-file-name visit)
+                               file))
+      (set-visited-file-modtime)
+      (set-buffer-modified-p nil))
+    (when (or (null visit) (eq visit t) (stringp visit))
+      (message "Wrote %s" file))
+    nil))
+
+(defun neomacs--crypto-file-handler (operation &rest args)
+  "Decrypt and encrypt files matching `neomacs-crypto-file-regexp'."
+  (pcase operation
+    ('insert-file-contents
+     (apply #'neomacs--crypto-insert-file-contents args))
+    ('write-region
+     (apply #'neomacs--crypto-write-region args))
+    (_ (neomacs--crypto-real-handler operation args))))
+
+(defun neomacs--crypto-epg-decrypt (decrypt context cipher)
+  "Decrypt CIPHER for org-crypt, or call DECRYPT with CONTEXT to use gpg.
+GPG is only used if none of `neomacs-crypto-openpgp-secret-keys' fits."
+  (condition-case nil
+      (neomacs--crypto-run
+       (lambda (passphrase)
+         (neomacs-crypto-decrypt-string cipher buffer-file-name passphrase))
+       (or buffer-file-name (buffer-name)))
+    (neomacs-crypto-no-key (funcall decrypt context cipher))))
+
+(defun neomacs--crypto-epg-encrypt (encrypt context plain recipients
+                                            &optional sign always-trust)
+  "Encrypt PLAIN with a passphrase for org-crypt.
+Encrypting to RECIPIENTS, or with SIGN, calls ENCRYPT with CONTEXT and
+ALWAYS-TRUST to use gpg."
+  (if (or recipients sign)
+      (funcall encrypt context plain recipients sign always-trust)
+    (let ((plaintext (if (multibyte-string-p plain)
+                         (encode-coding-string plain 'utf-8)
+                       plain)))
+      (neomacs--crypto-run
+       (lambda (passphrase)
+         (neomacs-crypto-encrypt plaintext 'openpgp nil passphrase
+                                 buffer-file-name nil t))
+       (or buffer-file-name (buffer-name)) t))))
+
+(defun neomacs-crypto-forget-passphrases ()
+  "Forget the passphrases remembered for encrypted files."
+  (interactive)
+  (neomacs-crypto-clear-cache)
+  (message "Passphrases forgotten"))
+
+(define-minor-mode neomacs-crypto-mode
+  "Decrypt age and OpenPGP files when visited and encrypt them on save.
+Files named *.age or *.gpg are decrypted by the display engine, without
+an external gpg, using `neomacs-crypto-age-identities',
+`neomacs-crypto-openpgp-secret-keys' or a passphrase read in the
+minibuffer, which is then remembered for `neomacs-crypto-cache-timeout'
+seconds.  The major mode is chosen from the name without the suffix.
+Saving encrypts to `neomacs-crypto-age-recipients' or
+`neomacs-crypto-openpgp-recipients', or with a passphrase.
+
+This also covers files read through `insert-file-contents', such as
+~/.authinfo.gpg for auth-source and pass entries, and org-crypt
+entries encrypted with a passphrase."
+  :global t
+  :group 'neomacs
+  (let ((handler (cons neomacs-crypto-file-regexp
+                       #'neomacs--crypto-file-handler))
+        (mode (list neomacs-crypto-file-regexp nil t)))
+    (setq file-name-handler-alist (delete handler file-name-handler-alist)
+          auto-mode-alist (delete mode auto-mode-alist))
+    (advice-remove 'epg-decrypt-string #'neomacs--crypto-epg-decrypt)
+    (advice-remove 'epg-encrypt-string #'neomacs--crypto-epg-encrypt)
+    (when neomacs-crypto-mode
+      (unless (neomacs-crypto-available-p)
+        (setq neomacs-crypto-mode nil)
+        (user-error "This build cannot decrypt files"))
+      (neomacs--crypto-configure)
+      (push handler file-name-handler-alist)
+      (push mode auto-mode-alist)
+      (advice-add 'epg-decrypt-string :around #'neomacs--crypto-epg-decrypt)
+      (advice-add 'epg-encrypt-string :around
+                  #'neomacs--crypto-epg-encrypt))))
+
+;;; Secrets
+
+(declare-function neomacs-secrets-available-p "neomacsfns.c" ())
+(declare-function neomacs-secrets-search "neomacsfns.c"
+                  (&optional host user port max))
+(declare-function neomacs-secrets-get "neomacsfns.c" (host user port))
+(declare-function neomacs-secrets-store "neomacsfns.c"
+                  (host user port secret))
+(declare-function neomacs-secrets-delete "neomacsfns.c"
+                  (&optional host user port))
+(declare-function neomacs-secrets-reload "neomacsfns.c" ())
+(declare-function auth-source-backend "auth-source")
+(declare-function auth-source-backend-parse-parameters "auth-source"
+                  (entry backend))
+(declare-function auth-source-forget-all-cached "auth-source" ())
+(declare-function auth-source-netrc-parse "auth-source")
+(defvar auth-sources)
+(defvar auth-source-backend-parser-functions)
+
+(defun neomacs--secrets-values (value)
+  "The fields to search for VALUE of an auth-source spec; nil means any."
+  (cond ((memq value '(nil t)) '(nil))
+        ((consp value) (mapcan #'neomacs--secrets-values value))
+        ((numberp value) (list (number-to-string value)))
+        ((symbolp value) (list (symbol-name value)))
+        (t (list value))))
+
+(defun neomacs--secrets-entry (host user port)
+  "The auth-source entry for the secret stored for HOST, USER and PORT."
+  (append (list :host host)
+          (and user (list :user user))
+          (and port (list :port port))
+          (list :secret (lambda () (neomacs-secrets-get host user port)))))
+
+(defun neomacs--secrets-create (spec)
+  "Read a secret for auth-source SPEC, and return an entry that saves it."
+  (let* ((first (lambda (key)
+                  (car (delq nil (neomacs--secrets-values
+                                  (plist-get spec key))))))
+         (host (or (funcall first :host) (read-string "Host: ")))
+         (user (or (funcall first :user)
+                   (let ((user (read-string
+                                (format "User for %s (empty for none): "
+                                        host))))
+                     (and (not (string-empty-p user)) user))))
+         (port (funcall first :port))
+         (secret (read-passwd (format "Secret for %s%s: "
+                                      (if user (concat user "@") "")
+                                      host))))
+    (append (list :host host)
+            (and user (list :user user))
+            (and port (list :port port))
+            (list :secret (lambda () secret)
+                  :save-function
+                  (lambda () (neomacs-secrets-store host user port secret))))))
+
+(defun neomacs--secrets-search (&rest spec)
+  "Search the system keyring for auth-source, as SPEC asks.
+SPEC holds :host, :user and :port, each a string, a list of them or t,
+and :max, :create and :delete, as for `auth-source-search'."
+  (let ((max (or (plist-get spec :max) 1))
+        found)
+    (dolist (host (neomacs--secrets-values (plist-get spec :host)))
+      (dolist (user (neomacs--secrets-values (plist-get spec :user)))
+        (dolist (port (neomacs--secrets-values (plist-get spec :port)))
+          (dolist (entry (neomacs-secrets-search host user port max))
+            (unless (member entry found)
+              (push entry found))))))
+    (setq found (take max (nreverse found)))
+    (cond
+     ((plist-get spec :delete)
+      (dolist (entry found)
+        (apply #'neomacs-secrets-delete
+               (mapcar (lambda (field) (or field "")) entry)))
+      (mapcar (lambda (entry) (apply #'neomacs--secrets-entry entry)) found))
+     ((and (null found) (plist-get spec :create))
+      (list (neomacs--secrets-create spec)))
+     (t
+      (mapcar (lambda (entry) (apply #'neomacs--secrets-entry entry))
+              found)))))
+
+(defun neomacs--secrets-backend-parse (entry)
+  "Return the auth-source backend for ENTRY `neomacs-secrets'."
+  (when (eq entry 'neomacs-secrets)
+    (auth-source-backend-parse-parameters
+     entry
+     (auth-source-backend
+      :source "neomacs-secrets"
+      :type 'neomacs-secrets
+      :search-function #'neomacs--secrets-search))))
+
+(defun neomacs-secrets-add (host user port secret)
+  "Keep SECRET for HOST, USER and PORT in the system keyring.
+USER and PORT may be empty, for a secret that any user or port gets,
+such as an API token."
+  (interactive
+   (let* ((host (read-string "Host: "))
+          (user (read-string (format "User for %s (empty for none): " host)))
+          (port (read-string (format "Port for %s (empty for any): " host))))
+     (list host user port (read-passwd "Secret: " t))))
+  (neomacs-secrets-store host
+                         (and (not (string-empty-p user)) user)
+                         (and (not (string-empty-p port)) port)
+                         secret)
+  (when (featurep 'auth-source)
+    (auth-source-forget-all-cached)))
+
+(defun neomacs-secrets-import-authinfo (file)
+  "Move the secrets of the authinfo FILE into the system keyring.
+FILE may be encrypted, like ~/.authinfo.gpg.  Once imported, FILE is
+not needed by `neomacs-secrets-mode' and can be deleted."
+  (interactive
+   (list (read-file-name "Import authinfo file: " "~/" nil t
+                         ".authinfo.gpg")))
+  (require 'auth-source)
+  (let ((count 0))
+    (dolist (entry (auth-source-netrc-parse
+                    :file (expand-file-name file)
+                    :max most-positive-fixnum :host t :user t :port t))
+      (let ((field (lambda (&rest keys)
+                     (seq-some (lambda (key) (cdr (assoc key entry))) keys))))
+        (when-let* ((host (funcall field "machine" "host"))
+                    (secret (funcall field "password")))
+          (neomacs-secrets-store host
+                                 (funcall field "login" "user" "account")
+                                 (funcall field "port" "protocol")
+                                 (if (functionp secret) (funcall secret)
+                                   secret))
+          (setq count (1+ count)))))
+    (auth-source-forget-all-cached)
+    (message "Imported %d secret%s from %s"
+             count (if (= count 1) "" "s") file)))
+
+(define-minor-mode neomacs-secrets-mode
+  "Look up auth-source secrets in the system keyring first.
+Passwords and API tokens are kept by the Secret Service on GNU/Linux,
+the Keychain on macOS or the Credential Manager on Windows, instead of
+a plain ~/.authinfo file.  Packages that call `auth-source-search'
+find them without change; secrets entered at their prompts are saved
+in the keyring.  Add secrets with `neomacs-secrets-add' or
+`neomacs-secrets-import-authinfo'."
+  :global t
+  :group 'neomacs
+  (require 'auth-source)
+  (setq auth-sources (delq 'neomacs-secrets auth-sources))
+  (remove-hook 'auth-source-backend-parser-functions
+               #'neomacs--secrets-backend-parse)
+  (when neomacs-secrets-mode
+    (unless (neomacs-secrets-available-p)
+      (setq neomacs-secrets-mode nil)
+      (user-error "This build cannot use the system keyring"))
+    (neomacs-secrets-reload)
+    (add-hook 'auth-source-backend-parser-functions
+              #'neomacs--secrets-backend-parse)
+    (push 'neomacs-secrets auth-sources))
+  (auth-source-forget-all-cached))
+
+;;; HTML documents
+
+(declare-function neomacs-html-render "neomacsfns.c" (html &optional width))
+(declare-function url-expand-file-name "url-expand" (url &optional default))
+(declare-function url-retrieve "url" (url callback &optional cbargs silent
+                                          inhibit-cookies))
+(declare-function text-property-search-forward "text-property-search")
+(declare-function text-property-search-backward "text-property-search")
+(declare-function prop-match-beginning "text-property-search" (match))
+(declare-function mm-insert-part "mm-decode" (handle &optional no-cache))
+(defvar mm-text-html-renderer-alist)
+(eval-when-compile (require 'mm-decode))
+
+(defcustom neomacs-html-width nil
+  "Width in columns to fill rendered HTML to.
+nil means the width of the window, up to 100 columns."
+  :type '(choice (const :tag "Window width" nil) integer)
+  :group 'neomacs)
+
+(defcustom neomacs-html-use-colors t
+  "Non-nil means rendered HTML shows the colors the document asks for.
+nil leaves the colors to the theme."
+  :type 'boolean
+  :group 'neomacs)
+
+(defcustom neomacs-html-show-images t
+  "Non-nil means rendered HTML shows images in place of their text.
+Images are fetched in the background; until they arrive, and if they
+cannot be shown, their alternative text stands in."
+  :type 'boolean
+  :group 'neomacs)
+
+(defvar neomacs-html-cid-function nil
+  "Function that returns the data of the image at a \"cid:\" URL, or nil.
+It is called with the Content-ID.  Mail readers bind this while they
+insert an HTML part, so the part can show the images sent with it.")
+
+(defvar-keymap neomacs-html-link-map
+  :doc "Keymap on links in rendered HTML."
+  "RET" #'neomacs-html-browse-url
+  "<mouse-2>" #'neomacs-html-browse-url
+  "w" #'neomacs-html-copy-url)
+
+(defun neomacs--html-width ()
+  "The width to fill rendered HTML to."
+  (or neomacs-html-width (max 20 (min 100 (1- (window-body-width))))))
+
+(defun neomacs--html-expand (url base-url)
+  "URL, which may be relative, made absolute against BASE-URL."
+  (if (and base-url (not (string-match-p "\\`[a-z][a-z0-9+.-]*:" url)))
+      (progn (require 'url-expand) (url-expand-file-name url base-url))
+    url))
+
+(defun neomacs--html-image (url start end width height)
+  "Show the image at URL in place of the text between START and END.
+WIDTH and HEIGHT are its size from the document, or 0."
+  (let* ((start (copy-marker start))
+         (end (copy-marker end))
+         (max-width (* (neomacs--html-width) (frame-char-width)))
+         (show
+          (lambda (data)
+            (when-let* ((buffer (marker-buffer start))
+                        ((buffer-live-p buffer))
+                        (image (ignore-errors
+                                 (apply #'create-image data nil t
+                                        :max-width max-width
+                                        (append
+                                         (and (> width 0) (list :width width))
+                                         (and (> height 0)
+                                              (list :height height)))))))
+              (with-current-buffer buffer
+                (with-silent-modifications
+                  (put-text-property start end 'display image)))))))
+    (cond
+     ((string-match "\\`cid:" url)
+      (when neomacs-html-cid-function
+        (funcall show (funcall neomacs-html-cid-function
+                               (url-unhex-string
+                                (substring url (match-end 0)))))))
+     ((string-match "\\`data:[^,]*;base64," url)
+      (funcall show (ignore-errors
+                      (base64-decode-string (substring url (match-end 0))))))
+     ((string-match "\\`file://" url)
+      (let ((file (url-unhex-string (substring url (match-end 0)))))
+        (when (file-readable-p file)
+          (funcall show (with-temp-buffer
+                          (set-buffer-multibyte nil)
+                          (insert-file-contents-literally file)
+                          (buffer-string))))))
+     ((string-match-p "\\`https?:" url)
+      (require 'url)
+      (url-retrieve
+       url
+       (lambda (status)
+         (let ((data (and (not (plist-get status :error))
+                          (progn
+                            (goto-char (point-min))
+                            (search-forward "\n\n" nil t))
+                          (buffer-substring-no-properties (point)
+                                                          (point-max)))))
+           (kill-buffer)
+           (when data
+             (funcall show data))))
+       nil t t)))))
+
+(defun neomacs--html-style-run (run offset base-url)
+  "Put the faces and properties of RUN on the text inserted at OFFSET.
+RUN is as returned by `neomacs-html-render'; links and images are
+relative to BASE-URL."
+  (pcase-let* ((`[,start ,end ,flags ,heading ,fg ,bg ,link ,image] run)
+               (start (+ offset start))
+               (end (+ offset end))
+               (faces nil))
+    (when link
+      (push 'shr-link faces))
+    (when (> heading 0)
+      (push (intern (format "shr-h%d" heading)) faces))
+    (pcase-dolist (`(,bit . ,face) '((1 . bold) (2 . italic) (4 . underline)
+                                     (8 . shr-strike-through) (16 . shr-code)))
+      (when (/= 0 (logand flags bit))
+        (push face faces)))
+    (when (and neomacs-html-use-colors (or fg bg))
+      (push (append (and fg (list :foreground fg))
+                    (and bg (list :background bg)))
+            faces))
+    (when faces
+      (add-face-text-property start end (nreverse faces)))
+    (when link
+      (let ((url (neomacs--html-expand link base-url)))
+        (add-text-properties start end
+                             (list 'neomacs-html-url url
+                                   'help-echo url
+                                   'mouse-face 'highlight
+                                   'follow-link t
+                                   'keymap neomacs-html-link-map))))
+    (when (and image neomacs-html-show-images)
+      (pcase-let ((`(,src ,width ,height) image))
+        (neomacs--html-image (neomacs--html-expand src base-url)
+                             start end width height)))))
+
+(defun neomacs-html-insert (html &optional base-url width)
+  "Insert the HTML document HTML, rendered as text, at point.
+Relative links and images are resolved against BASE-URL.  The text is
+filled to WIDTH columns, by default `neomacs-html-width'.  Headings,
+links and code use the `shr' faces, so themes that style EWW style this
+too.  Return the title of the document, or nil."
+  (require 'shr)
+  (pcase-let ((`(,text ,title ,runs)
+               (neomacs-html-render html (or width (neomacs--html-width))))
+              (start (point)))
+    (insert text)
+    (dolist (run runs)
+      (neomacs--html-style-run run start base-url))
+    title))
+
+(defun neomacs-html-browse-url (&optional event)
+  "Follow the link at point, or at the mouse for EVENT."
+  (interactive (list last-nonmenu-event))
+  (let ((url (get-text-property (if (mouse-event-p event)
+                                    (posn-point (event-end event))
+                                  (point))
+                                'neomacs-html-url)))
+    (unless url
+      (user-error "No link here"))
+    (browse-url url)))
+
+(defun neomacs-html-copy-url ()
+  "Copy the target of the link at point to the kill ring."
+  (interactive)
+  (let ((url (get-text-property (point) 'neomacs-html-url)))
+    (unless url
+      (user-error "No link here"))
+    (kill-new url)
+    (message "%s" url)))
+
+(defun neomacs-html-next-link (&optional n)
+  "Move to the start of the Nth next link; backward if N is negative."
+  (interactive "p")
+  (require 'text-property-search)
+  (setq n (or n 1))
+  (dotimes (_ (abs n))
+    (let ((match (if (> n 0)
+                     (text-property-search-forward 'neomacs-html-url nil nil t)
+                   (text-property-search-backward 'neomacs-html-url nil nil t))))
+      (unless match
+        (user-error "No more links"))
+      (goto-char (prop-match-beginning match)))))
+
+(defun neomacs-html-previous-link (&optional n)
+  "Move to the start of the Nth previous link."
+  (interactive "p")
+  (neomacs-html-next-link (- (or n 1))))
+
+(defvar-local neomacs--html-source nil
+  "The HTML and base URL shown in a `neomacs-html-mode' buffer.")
+
+(defun neomacs--html-revert (&rest _)
+  "Render the document of this buffer again, at the window's width."
+  (pcase-let ((`(,html . ,base-url) neomacs--html-source)
+              (inhibit-read-only t)
+              (line (line-number-at-pos)))
+    (erase-buffer)
+    (neomacs-html-insert html base-url)
+    (goto-char (point-min))
+    (forward-line (1- line))))
+
+(defvar-keymap neomacs-html-mode-map
+  :parent special-mode-map
+  "TAB" #'neomacs-html-next-link
+  "<backtab>" #'neomacs-html-previous-link)
+
+(define-derived-mode neomacs-html-mode special-mode "HTML"
+  "Mode for reading HTML rendered as text.
+\\<neomacs-html-mode-map>\\[neomacs-html-next-link] and \
+\\[neomacs-html-previous-link] move between links, and \\[revert-buffer] fills
+the text to the window's width again."
+  (setq-local revert-buffer-function #'neomacs--html-revert)
+  (setq truncate-lines nil))
+
+(defun neomacs-html-view (html &optional base-url)
+  "Show the HTML document HTML rendered as text, in its own buffer.
+Relative links and images are resolved against BASE-URL.  Return the
+buffer."
+  (let ((buffer (generate-new-buffer "*html*")))
+    (pop-to-buffer buffer)
+    (neomacs-html-mode)
+    (setq neomacs--html-source (cons html base-url))
+    (let ((inhibit-read-only t)
+          (title (neomacs-html-insert html base-url)))
+      (when title
+        (rename-buffer (format "*html: %s*" title) t)))
+    (goto-char (point-min))
+    buffer))
+
+(defun neomacs-html-view-file (file)
+  "Show the HTML file FILE rendered as text.
+This suits simple documents such as package READMEs and saved mail,
+where a browser would be overkill."
+  (interactive "fView HTML file: ")
+  (let ((file (expand-file-name file)))
+    (neomacs-html-view (with-temp-buffer
+                         (insert-file-contents file)
+                         (buffer-string))
+                       (concat "file://" file))))
+
+(defun neomacs-html-view-buffer ()
+  "Show the HTML in the current buffer rendered as text."
+  (interactive)
+  (neomacs-html-view (buffer-substring-no-properties (point-min) (point-max))
+                     (and buffer-file-name
+                          (concat "file://" buffer-file-name))))
+
+(defun neomacs-html-render-region (start end)
+  "Replace the HTML between START and END by its rendering as text."
+  (interactive "r")
+  (let ((html (buffer-substring-no-properties start end)))
+    (save-excursion
+      (delete-region start end)
+      (goto-char start)
+      (neomacs-html-insert html))))
+
+(defun neomacs-html-mm-render (handle)
+  "Render the HTML mail part HANDLE as text.
+To read HTML mail in Gnus this way, set `mm-text-html-renderer' to
+`neomacs'."
+  (let ((html (with-temp-buffer
+                (mm-insert-part handle)
+                (buffer-string)))
+        (start (point-marker)))
+    (neomacs-html-insert html)
+    (mm-handle-set-undisplayer
+     handle
+     (let ((end (point-marker)))
+       (lambda ()
+         (let ((inhibit-read-only t))
+           (delete-region start end)))))))
+
+(with-eval-after-load 'mm-decode
+  (add-to-list 'mm-text-html-renderer-alist
+               '(neomacs . neomacs-html-mm-render)))
+
+;;; Mail messages
+
+(declare-function neomacs-mime-parse "neomacsfns.c" (message &optional prefer-plain))
+(declare-function rfc2047-decode-string "rfc2047" (string &optional address-mime))
+(declare-function mu4e-message-field "mu4e-message" (msg field))
+(declare-function notmuch-show-get-filename "notmuch-show" ())
+(defvar mu4e-view-actions)
+
+(defcustom neomacs-mime-prefer-html t
+  "Non-nil means messages offering HTML and plain text show the HTML.
+The HTML is rendered as text by `neomacs-html-insert'."
+  :type 'boolean
+  :group 'neomacs)
+
+(defcustom neomacs-mime-headers '("From" "To" "Cc" "Subject" "Date")
+  "Headers shown above a message, in this order."
+  :type '(repeat string)
+  :group 'neomacs)
+
+(defcustom neomacs-mime-show-images t
+  "Non-nil means images sent inline in a message are shown.
+Otherwise they are listed with the attachments."
+  :type 'boolean
+  :group 'neomacs)
+
+(defcustom neomacs-mime-save-directory nil
+  "Directory offered for saving attachments.
+nil means the `default-directory' of the message buffer."
+  :type '(choice (const :tag "Default directory" nil) directory)
+  :group 'neomacs)
+
+(defvar-keymap neomacs-mime-attachment-map
+  :doc "Keymap on attachments and images in a message."
+  "RET" #'neomacs-mime-open-attachment
+  "<mouse-2>" #'neomacs-mime-open-attachment
+  "o" #'neomacs-mime-open-attachment
+  "s" #'neomacs-mime-save-attachment)
+
+(defun neomacs--mime-decode (body charset)
+  "BODY, a unibyte string, decoded from CHARSET.
+The coding is guessed when CHARSET is nil or unknown to Emacs."
+  (let ((coding (and charset (coding-system-from-name charset))))
+    (decode-coding-string body (if (coding-system-p coding) coding 'undecided))))
+
+(defun neomacs--mime-header-value (value)
+  "VALUE with the encoded words `neomacs-mime-parse' left decoded."
+  (if (string-search "=?" value)
+      (progn (require 'rfc2047) (rfc2047-decode-string value))
+    value))
+
+(defun neomacs--mime-insert-headers (headers)
+  "Insert those of HEADERS named in `neomacs-mime-headers'."
+  (dolist (name neomacs-mime-headers)
+    (when-let* ((value (cdr (assoc-string name headers t))))
+      (insert (propertize (concat name ":") 'face 'message-header-name) " "
+              (propertize (neomacs--mime-header-value value)
+                          'face (if (string-equal-ignore-case name "subject")
+                                    'message-header-subject
+                                  'message-header-other))
+              "\n"))))
+
+(defun neomacs--mime-file-name (part)
+  "The file name to save PART under, without a directory."
+  (pcase-let ((`[,_ ,_ ,_ ,filename ,_ ,_ ,_ ,_] part))
+    (if (and filename (not (string-empty-p (file-name-nondirectory filename))))
+        (file-name-nondirectory filename)
+      "attachment")))
+
+(defun neomacs--mime-insert-attachment (part)
+  "Insert a line for PART, from `neomacs-mime-parse', to open or save."
+  (pcase-let ((`[,_ ,type ,_ ,filename ,_ ,description ,body ,_] part)
+              (start (point)))
+    (insert (format "[%s: %s, %s]" (or filename description "attachment")
+                    type (file-size-human-readable (length body))))
+    (add-text-properties start (point)
+                         (list 'neomacs-mime-part part
+                               'face 'button
+                               'mouse-face 'highlight
+                               'follow-link t
+                               'help-echo "RET: open, s: save"
+                               'keymap neomacs-mime-attachment-map))
+    (insert "\n")))
+
+(defun neomacs--mime-insert-image (part)
+  "Insert the image PART inline; return nil if it cannot be shown."
+  (when-let* ((neomacs-mime-show-images)
+              (image (ignore-errors
+                       (create-image (aref part 6) nil t
+                                     :max-width (* (neomacs--html-width)
+                                                   (frame-char-width))))))
+    (let ((start (point)))
+      (insert-image image)
+      (add-text-properties start (point)
+                           (list 'neomacs-mime-part part
+                                 'keymap neomacs-mime-attachment-map))
+      (insert "\n")
+      t)))
+
+(defun neomacs-mime-insert (message &optional prefer-plain)
+  "Insert the MIME message MESSAGE, a unibyte string, at point for reading.
+The headers in `neomacs-mime-headers' come first, then the text and
+images of the message, with HTML rendered by `neomacs-html-insert'
+and images it refers to by Content-ID taken from the message.
+Attachments are listed where they occur, to open or save.  Plain text
+is chosen over HTML if PREFER-PLAIN is non-nil."
+  (require 'message)
+  (pcase-let* ((`(,headers ,parts) (neomacs-mime-parse message prefer-plain))
+               (related (delq nil (mapcar (lambda (part)
+                                            (when-let* ((id (aref part 4)))
+                                              (cons id (aref part 6))))
+                                          parts))))
+    (let ((neomacs-html-cid-function (lambda (id) (cdr (assoc id related)))))
+      (neomacs--mime-insert-headers headers)
+      (dolist (part parts)
+        (pcase-let ((`[,role ,type ,charset ,_ ,_ ,_ ,body ,message-headers]
+                     part))
+          (pcase role
+            ('body
+             (insert "\n")
+             (cond
+              ((equal type "text/html")
+               (neomacs-html-insert (neomacs--mime-decode body charset)))
+              ((string-prefix-p "image/" type)
+               (unless (neomacs--mime-insert-image part)
+                 (neomacs--mime-insert-attachment part)))
+              (t (insert (neomacs--mime-decode body charset))))
+             (unless (bolp)
+               (insert "\n")))
+            ('attachment (neomacs--mime-insert-attachment part))
+            ('message
+             (insert "\n" (propertize "Forwarded message" 'face 'shadow) "\n")
+             (neomacs--mime-insert-headers message-headers))))))))
+
+(defun neomacs--mime-part-at (pos)
+  "The attachment at POS, or a user error."
+  (or (get-text-property pos 'neomacs-mime-part)
+      (user-error "No attachment here")))
+
+(defun neomacs-mime-save-attachment (file)
+  "Save the attachment at point to FILE.
+If FILE is a directory, the attachment keeps its own name in it."
+  (interactive
+   (let ((part (neomacs--mime-part-at (point))))
+     (list (read-file-name "Save attachment to: "
+                           (or neomacs-mime-save-directory default-directory)
+                           nil nil (neomacs--mime-file-name part)))))
+  (let ((part (neomacs--mime-part-at (point)))
+        (coding-system-for-write 'no-conversion))
+    (when (file-directory-p file)
+      (setq file (expand-file-name (neomacs--mime-file-name part) file)))
+    (write-region (aref part 6) nil file nil nil nil t)))
+
+(defun neomacs-mime-save-all-attachments (directory)
+  "Save the attachments of the message in this buffer to DIRECTORY."
+  (interactive
+   (list (read-directory-name "Save attachments to: "
+                              (or neomacs-mime-save-directory
+                                  default-directory))))
+  (let ((coding-system-for-write 'no-conversion)
+        (pos (point-min))
+        (count 0))
+    (while (setq pos (text-property-not-all pos (point-max)
+                                            'neomacs-mime-part nil))
+      (let ((part (get-text-property pos 'neomacs-mime-part)))
+        (write-region (aref part 6) nil
+                      (expand-file-name (neomacs--mime-file-name part)
+                                        directory)
+                      nil nil nil t)
+        (setq count (1+ count)
+              pos (or (next-single-property-change pos 'neomacs-mime-part)
+                      (point-max)))))
+    (message "Saved %d attachment%s" count (if (= count 1) "" "s"))))
+
+(defun neomacs-mime-open-attachment (&optional event)
+  "Open the attachment at point, or at the mouse for EVENT, in Emacs.
+It is written to a temporary file of its own name, so the file name
+picks the mode: images, PDFs and archives open as they would on disk."
+  (interactive (list last-nonmenu-event))
+  (let* ((part (neomacs--mime-part-at (if (mouse-event-p event)
+                                          (posn-point (event-end event))
+                                        (point))))
+         (file (expand-file-name (neomacs--mime-file-name part)
+                                 (make-temp-file "neomacs-mime" t)))
+         (coding-system-for-write 'no-conversion))
+    (write-region (aref part 6) nil file nil 'silent)
+    (find-file-other-window file)))
+
+(defvar-local neomacs--mime-source nil
+  "The message shown in a `neomacs-mime-mode' buffer.")
+
+(defvar-local neomacs--mime-prefer-plain nil
+  "Non-nil if this `neomacs-mime-mode' buffer shows plain text over HTML.")
+
+(defun neomacs--mime-revert (&rest _)
+  "Show the message of this buffer again."
+  (let ((inhibit-read-only t)
+        (line (line-number-at-pos)))
+    (erase-buffer)
+    (neomacs-mime-insert neomacs--mime-source neomacs--mime-prefer-plain)
+    (goto-char (point-min))
+    (forward-line (1- line))))
+
+(defun neomacs-mime-toggle-html ()
+  "Switch between the HTML and plain text versions of the message."
+  (interactive)
+  (setq neomacs--mime-prefer-plain (not neomacs--mime-prefer-plain))
+  (neomacs--mime-revert)
+  (message (if neomacs--mime-prefer-plain "Showing plain text" "Showing HTML")))
+
+(defvar-keymap neomacs-mime-mode-map
+  "h" #'neomacs-mime-toggle-html
+  "S" #'neomacs-mime-save-all-attachments)
+
+(define-derived-mode neomacs-mime-mode neomacs-html-mode "Message"
+  "Mode for reading a mail message.
+\\<neomacs-mime-mode-map>\\[neomacs-mime-toggle-html] switches between HTML and plain text, and \
+\\[neomacs-mime-save-all-attachments]
+saves all attachments.  On an attachment, \
+\\<neomacs-mime-attachment-map>\\[neomacs-mime-open-attachment] opens it and \
+\\[neomacs-mime-save-attachment] saves it."
+  (setq-local revert-buffer-function #'neomacs--mime-revert))
+
+(defun neomacs-mime-view (message &optional name)
+  "Show the MIME message MESSAGE, a unibyte string, in a buffer.
+The buffer is called NAME, by default \"*message*\".  Return it."
+  (let ((buffer (get-buffer-create (or name "*message*"))))
+    (pop-to-buffer buffer)
+    (neomacs-mime-mode)
+    (setq neomacs--mime-source message
+          neomacs--mime-prefer-plain (not neomacs-mime-prefer-html))
+    (neomacs--mime-revert)
+    (goto-char (point-min))
+    buffer))
+
+(defun neomacs-mime-view-file (file)
+  "Show the mail message in FILE, such as a Maildir file or a .eml."
+  (interactive "fView message file: ")
+  (neomacs-mime-view (with-temp-buffer
+                       (set-buffer-multibyte nil)
+                       (insert-file-contents-literally file)
+                       (buffer-string))
+                     (format "*message: %s*" (file-name-nondirectory file))))
+
+(defun neomacs-mime-mu4e-view (msg)
+  "Show the mu4e message MSG natively, without Gnus.
+This is offered among the `mu4e-view-actions'."
+  (neomacs-mime-view-file (mu4e-message-field msg :path)))
+
+(with-eval-after-load 'mu4e-view
+  (add-to-list 'mu4e-view-actions
+               '("neomacs view" . neomacs-mime-mu4e-view) t))
+
+(defun neomacs-mime-notmuch-view ()
+  "Show the notmuch message at point natively, without Gnus."
+  (interactive)
+  (neomacs-mime-view-file (notmuch-show-get-filename)))
+
+;;; Printing
+
+(declare-function neomacs-print-buffer "neomacsfns.c"
+                  (file &optional start end window))
+(declare-function neomacs-print-open-file "neomacsfns.c" (file))
+(declare-function neomacs-print-close-file "neomacsfns.c" (fd))
+(defvar neomacs-print-paper)
+
+(defun neomacs--print-portal-call (method args options)
+  "Call METHOD of the print portal and wait for its response.
+ARGS are the arguments of METHOD before its options, OPTIONS a list of
+\(:dict-entry KEY (:variant VALUE)) forms for these.  Return the results
+of the response, or nil if the user cancelled."
+  (let* ((token (format "neomacs%d" (random 1000000)))
+         (sender (replace-regexp-in-string
+                  "\\." "_" (substring (dbus-get-unique-name :session) 1)))
+         (request (format "/org/freedesktop/portal/desktop/request/%s/%s"
+                          sender token))
+         (result 'waiting)
+         (signal (dbus-register-signal
+                  :session nil request
+                  "org.freedesktop.portal.Request" "Response"
+                  (lambda (response results)
+                    (setq result (and (eq response 0) (or results t)))))))
+    (unwind-protect
+        (progn
+          (apply #'dbus-call-method
+                 :session "org.freedesktop.portal.Desktop"
+                 "/org/freedesktop/portal/desktop"
+                 "org.freedesktop.portal.Print" method
+                 (append args
+                         `((:array (:dict-entry "handle_token" (:variant ,token))
+                                   ,@options))))
+          (with-timeout (600 (setq result nil))
+            (while (eq result 'waiting)
+              (accept-process-output nil 0.05)))
+          result)
+      (dbus-unregister-object signal))))
+
+(defun neomacs--print-paper (page-setup)
+  "Return the paper of PAGE-SETUP, a print portal page setup.
+The paper is a cons (WIDTH . HEIGHT) in points, turned for landscape
+orientations, or nil if PAGE-SETUP gives no size."
+  (let ((width (caadr (assoc "Width" page-setup)))
+        (height (caadr (assoc "Height" page-setup)))
+        (orientation (caadr (assoc "Orientation" page-setup)))
+        (points (/ 72 25.4)))
+    (when (and (numberp width) (numberp height) (> width 0) (> height 0))
+      (if (member orientation '("landscape" "reverse_landscape"))
+          (cons (* height points) (* width points))
+        (cons (* width points) (* height points))))))
+
+(defun neomacs-print (&optional file start end)
+  "Print the current buffer, or the region if it is active.
+The desktop's print dialog (through xdg-desktop-portal) chooses the
+printer, the paper and the copies; the text is laid out on the chosen
+paper as the selected window shows it, see `neomacs-print-buffer'.
+
+With a prefix argument, or if FILE is non-nil, write the pages to the
+PDF file FILE instead.  START and END delimit the text to print."
+  (interactive
+   (let ((region (use-region-p)))
+     (list (and current-prefix-arg
+                (read-file-name "Print to PDF file: " nil nil nil
+                                (concat (file-name-base (buffer-name)) ".pdf")))
+           (and region (region-beginning))
+           (and region (region-end)))))
+  (if file
+      (message "Printed %d pages to %s"
+               (neomacs-print-buffer file start end) file)
+    (require 'dbus)
+    (unless (and (featurep 'dbusbind)
+                 (ignore-errors (dbus-get-unique-name :session)))
+      (user-error "Printing needs D-Bus; use a prefix argument to print to a file"))
+    (let* ((title (buffer-name))
+           (prepared
+            (condition-case err
+                (neomacs--print-portal-call
+                 "PreparePrint"
+                 (list "" title '(:array :signature "{sv}")
+                       '(:array :signature "{sv}"))
+                 '((:dict-entry "modal" (:variant :boolean t))))
+              (dbus-error
+               (user-error "Cannot print: %s" (error-message-string err)))))
+           (token (and (consp prepared) (caadr (assoc "token" prepared)))))
+      (unless (natnump token)
+        (user-error "Printing cancelled"))
+      (let ((neomacs-print-paper
+             (or (neomacs--print-paper (caadr (assoc "page-setup" prepared)))
+                 neomacs-print-paper))
+            (pdf (make-temp-file "neomacs-print" nil ".pdf"))
+            (fd nil))
+        (unwind-protect
+            (let ((pages (neomacs-print-buffer pdf start end)))
+              (setq fd (neomacs-print-open-file pdf))
+              (if (neomacs--print-portal-call
+                   "Print" (list "" title :unix-fd fd)
+                   `((:dict-entry "token" (:variant :uint32 ,token))))
+                  (message "Sent %d pages to the printer" pages)
+                (message "Printing cancelled")))
+          (when fd
+            (neomacs-print-close-file fd))
+          (delete-file pdf))))))
+
+;;; Breadcrumb bar
+
+(declare-function neomacs-set-breadcrumb-bar "neomacsterm.c"
+                  (window segments &optional separator separator-color))
+(declare-function neomacs-breadcrumb-bar-segment-at "neomacsterm.c"
+                  (window x))
+(declare-function treesit-node-at "treesit" (pos &optional parser-or-lang named))
+(declare-function treesit-node-parent "treesit.c" (node))
+(declare-function treesit-node-start "treesit.c" (node))
+(declare-function treesit-defun-name "treesit" (node))
+(declare-function treesit-parser-list "treesit.c" (&optional buffer language tag))
+(declare-function which-function "which-func" ())
+(declare-function project-root "project" (project))
+
+(defcustom neomacs-breadcrumb-bar-separator " \u203a "
+  "String drawn between the segments of the breadcrumb bar.
+It may contain an icon registered with `neomacs-register-icon'."
+  :type 'string
+  :group 'frames)
+
+(defvar neomacs--breadcrumb-bar-timer nil
+  "Idle timer updating the breadcrumb bars of all windows.")
+
+(defvar neomacs--breadcrumb-bar-windows nil
+  "Windows showing a breadcrumb bar, including deleted ones not yet cleared.")
+
+(defun neomacs--breadcrumb-bar-file-segments ()
+  "Return the path segments of the current buffer's file.
+Directories are relative to the project root, which is the first
+segment.  Each segment is (KIND LABEL TARGET)."
+  (if-let* ((file buffer-file-name))
+      (let* ((project (and (fboundp 'project-current) (project-current)))
+             (root (if project
+                       (expand-file-name (project-root project))
+                     (file-name-directory file)))
+             (dir root)
+             (segments (list (list 'directory
+                                   (file-name-nondirectory
+                                    (directory-file-name root))
+                                   root))))
+        (dolist (name (butlast (file-name-split (file-relative-name file root))))
+          (setq dir (file-name-as-directory (expand-file-name name dir)))
+          (push (list 'directory name dir) segments))
+        (nreverse (cons (list 'file (file-name-nondirectory file) file)
+                        segments)))
+    (list (list 'file (buffer-name) nil))))
+
+(defun neomacs--breadcrumb-bar-symbol-segments ()
+  "Return the definitions enclosing point, outermost first.
+They come from tree-sitter when the buffer has a parser, and from
+`which-function' otherwise.  Each segment is (symbol NAME POS)."
+  (if (and (fboundp 'treesit-parser-list) (treesit-parser-list))
+      (let ((node (treesit-node-at (point)))
+            segments)
+        (while node
+          (when-let* ((name (treesit-defun-name node)))
+            (push (list 'symbol name (treesit-node-start node)) segments))
+          (setq node (treesit-node-parent node)))
+        segments)
+    (when-let* (((require 'which-func nil t))
+                (name (which-function)))
+      (list (list 'symbol name
+                  (save-excursion
+                    (end-of-line)
+                    (and (beginning-of-defun) (point))))))))
+
+(defun neomacs--breadcrumb-bar-segments (window)
+  "Return the breadcrumb bar segments of WINDOW at its point."
+  (with-current-buffer (window-buffer window)
+    (save-excursion
+      (goto-char (window-point window))
+      (append (neomacs--breadcrumb-bar-file-segments)
+              (ignore-errors (neomacs--breadcrumb-bar-symbol-segments))))))
+
+(defun neomacs--breadcrumb-bar-update ()
+  "Show the breadcrumb bar of each window whose buffer has one.
+Windows whose buffer turned the bar off get their header line back."
+  ;; A deleted window's bar would show up in a new window allocated at
+  ;; the same address.
+  (dolist (window neomacs--breadcrumb-bar-windows)
+    (unless (window-live-p window)
+      (neomacs-set-breadcrumb-bar window nil)))
+  (setq neomacs--breadcrumb-bar-windows
+        (seq-filter #'window-live-p neomacs--breadcrumb-bar-windows))
+  (let ((any nil))
+    (dolist (frame (frame-list))
+      (dolist (window (window-list frame 'nomini))
+        (let ((old (window-parameter window 'neomacs--breadcrumb-bar))
+              (new (and (buffer-local-value 'neomacs-breadcrumb-bar-mode
+                                            (window-buffer window))
+                        (neomacs--breadcrumb-bar-segments window))))
+          (when new
+            (setq any t)
+            (cl-pushnew window neomacs--breadcrumb-bar-windows))
+          (unless (equal old new)
+            (set-window-parameter window 'neomacs--breadcrumb-bar new)
+            (neomacs-set-breadcrumb-bar
+             window
+             (mapcar (lambda (segment) (cons (car segment) (cadr segment)))
+                     new)
+             neomacs-breadcrumb-bar-separator
+             (neomacs--annotation-color 'shadow :foreground))))))
+    (when (and (not any) neomacs--breadcrumb-bar-timer)
+      (cancel-timer neomacs--breadcrumb-bar-timer)
+      (setq neomacs--breadcrumb-bar-timer nil))))
+
+(defun neomacs-breadcrumb-bar-click (event)
+  "Go to the breadcrumb bar segment clicked in EVENT.
+A directory opens in Dired, the file is shown in its window, and a
+definition moves point to its start."
+  (interactive "e")
+  (let* ((posn (event-start event))
+         (window (posn-window posn))
+         (index (and (windowp window)
+                     (neomacs-breadcrumb-bar-segment-at
+                      window (car (posn-x-y posn)))))
+         (segment (and index (nth index (window-parameter
+                                         window 'neomacs--breadcrumb-bar)))))
+    (when segment
+      (select-window window)
+      (pcase-let ((`(,kind ,_label ,target) segment))
+        (pcase kind
+          ('directory (dired target))
+          ('file (when target (find-file target)))
+          ('symbol (when target
+                     (push-mark)
+                     (goto-char target))))))))
+
+(defvar-keymap neomacs-breadcrumb-bar-mode-map
+  :doc "Keymap for `neomacs-breadcrumb-bar-mode'."
+  "<header-line> <mouse-1>" #'neomacs-breadcrumb-bar-click)
+
+(defvar-local neomacs--breadcrumb-bar-saved-header nil
+  "The `header-line-format' the breadcrumb bar replaced, as (VALUE).")
+
+(define-minor-mode neomacs-breadcrumb-bar-mode
+  "Show where point is in the header line, as a clickable breadcrumb bar.
+The bar shows the directories of the buffer's file within its project,
+the file name, then the definitions enclosing point, from tree-sitter
+or `which-function'.  Clicking a directory opens it in Dired and
+clicking a definition moves to it.  When the window is too narrow,
+leading directories collapse into \"...\" and long names are cut.
+Segments are joined by `neomacs-breadcrumb-bar-separator'.  Requires
+the Rust layout engine."
+  :group 'frames
+  :keymap neomacs-breadcrumb-bar-mode-map
+  (if neomacs-breadcrumb-bar-mode
+      (progn
+        (unless neomacs--breadcrumb-bar-saved-header
+          (setq neomacs--breadcrumb-bar-saved-header (list header-line-format)))
+        ;; The bar is drawn instead of the header line's text; it only
+        ;; needs the header line to exist.
+        (setq header-line-format (or header-line-format ""))
+        (when (and (fboundp 'neomacs-set-breadcrumb-bar)
+                   (not neomacs--breadcrumb-bar-timer))
+          (setq neomacs--breadcrumb-bar-timer
+                (run-with-idle-timer 0.2 t #'neomacs--breadcrumb-bar-update))))
+    (when neomacs--breadcrumb-bar-saved-header
+      (setq header-line-format (car neomacs--breadcrumb-bar-saved-header))
+      (setq neomacs--breadcrumb-bar-saved-header nil)))
+  (when (fboundp 'neomacs-set-breadcrumb-bar)
+    (neomacs--breadcrumb-bar-update)))
+
+(defun neomacs--breadcrumb-bar-mode-maybe ()
+  "Turn on `neomacs-breadcrumb-bar-mode' in buffers visiting files."
+  (when buffer-file-name
+    (neomacs-breadcrumb-bar-mode 1)))
+
+(define-globalized-minor-mode global-neomacs-breadcrumb-bar-mode
+  neomacs-breadcrumb-bar-mode neomacs--breadcrumb-bar-mode-maybe
+  :group 'frames)
+
 ;; --- Cursor color cycling ---
 (declare-function neomacs-set-cursor-color-cycle "neomacsterm.c"
   (&optional enabled speed saturation lightness))
@@ -1696,6 +6596,118 @@ buffer content changes (scroll, buffer switch)."
                     neomacs-text-fade-in)
            (neomacs-set-text-fade-in t val))))
 
+;; --- Text scale zoom animation ---
+(declare-function neomacs-set-text-zoom-animation "neomacsterm.c"
+  (&optional enabled duration-ms))
+
+(defcustom neomacs-text-zoom-animation t
+  "Enable the zoom animation for text scale changes.
+Non-nil makes `text-scale-adjust' zoom the text smoothly to its new
+size instead of snapping.  Nil crossfades instead."
+  :type 'boolean
+  :group 'frames
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (fboundp 'neomacs-set-text-zoom-animation)
+           (neomacs-set-text-zoom-animation val
+            (if (boundp 'neomacs-text-zoom-animation-duration)
+                neomacs-text-zoom-animation-duration nil)))))
+
+(defcustom neomacs-text-zoom-animation-duration 150
+  "Text scale zoom duration in milliseconds."
+  :type '(integer :tag "Duration (ms)")
+  :group 'frames
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (and (fboundp 'neomacs-set-text-zoom-animation)
+                    (boundp 'neomacs-text-zoom-animation)
+                    neomacs-text-zoom-animation)
+           (neomacs-set-text-zoom-animation t val))))
+
+;; --- Smooth text update ---
+(declare-function neomacs-set-smooth-text-update "neomacsterm.c"
+  (&optional enabled duration-ms))
+(declare-function neomacs-start-smooth-text-update "neomacsterm.c" ())
+
+(defcustom neomacs-smooth-text-update-commands
+  '(indent-region indent-buffer fill-paragraph sort-lines reverse-region
+    eglot-format eglot-format-buffer lsp-format-buffer format-all-buffer
+    apheleia-format-buffer)
+  "Commands whose rewrites of the buffer are animated line by line.
+See `neomacs-smooth-text-update'.  Reverting a buffer is always
+animated."
+  :type '(repeat function)
+  :group 'frames)
+
+(defun neomacs--smooth-text-update-post-command ()
+  "Animate the redisplay after a command in `neomacs-smooth-text-update-commands'."
+  (when (memq this-command neomacs-smooth-text-update-commands)
+    (neomacs-start-smooth-text-update)))
+
+(defcustom neomacs-smooth-text-update nil
+  "Animate buffer rewrites line by line.
+Non-nil makes reverts and the commands in
+`neomacs-smooth-text-update-commands' diff the visible lines of the
+selected window: moved lines slide to their new positions and new
+lines fade in, instead of an instant repaint."
+  :type 'boolean
+  :group 'frames
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (fboundp 'neomacs-set-smooth-text-update)
+           (neomacs-set-smooth-text-update val
+            (if (boundp 'neomacs-smooth-text-update-duration)
+                neomacs-smooth-text-update-duration nil))
+           (if val
+               (progn
+                 (add-hook 'after-revert-hook #'neomacs-start-smooth-text-update)
+                 (add-hook 'post-command-hook #'neomacs--smooth-text-update-post-command))
+             (remove-hook 'after-revert-hook #'neomacs-start-smooth-text-update)
+             (remove-hook 'post-command-hook #'neomacs--smooth-text-update-post-command)))))
+
+(defcustom neomacs-smooth-text-update-duration 250
+  "Smooth text update duration in milliseconds."
+  :type '(integer :tag "Duration (ms)")
+  :group 'frames
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (and (fboundp 'neomacs-set-smooth-text-update)
+                    (boundp 'neomacs-smooth-text-update)
+                    neomacs-smooth-text-update)
+           (neomacs-set-smooth-text-update t val))))
+
+;; --- User post-processing shader ---
+(declare-function neomacs-set-post-shader "neomacsterm.c" (file))
+
+(defcustom neomacs-post-shader nil
+  "WGSL shader file applied to every rendered frame, or nil for none.
+The file defines `fn effect(uv: vec2<f32>) -> vec4<f32>'; see
+`neomacs-set-post-shader' for the functions and uniforms it can use.
+The shader is reloaded whenever the file changes."
+  :type '(choice (const :tag "None" nil) file)
+  :group 'frames
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (fboundp 'neomacs-set-post-shader)
+           (neomacs-set-post-shader val))))
+
+;; --- Display settings file ---
+(declare-function neomacs-set-display-settings-file "neomacsterm.c" (file))
+
+(defcustom neomacs-display-settings-file
+  (expand-file-name "neomacs/display.toml"
+                    (or (getenv "XDG_CONFIG_HOME") "~/.config"))
+  "TOML file of display settings applied live, or nil for none.
+The file sets cursor, scroll and crossfade animations, line spacing,
+effects and effect colors; see `neomacs-set-display-settings-file'.
+It is applied whenever it changes, and need not exist."
+  :type '(choice (const :tag "None" nil) file)
+  :group 'frames
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (fboundp 'neomacs-set-display-settings-file)
+           (neomacs-set-display-settings-file val))))
+
 ;; --- Scroll line spacing animation ---
 (declare-function neomacs-set-scroll-line-spacing "neomacsterm.c"
   (&optional enabled max-spacing duration-ms))
@@ -2526,6 +7538,71 @@ Non-nil renders collapsing rings when cursor jumps far."
                 neomacs-cursor-magnetism-duration-ms nil)
             val))))
 
+;; --- Cursor beacon ---
+(declare-function neomacs-set-cursor-beacon "neomacsterm.c"
+  (&optional enabled color style duration-ms opacity triggers))
+
+(defun neomacs--apply-cursor-beacon ()
+  "Send the current cursor beacon settings to the display engine."
+  (when (fboundp 'neomacs-set-cursor-beacon)
+    ;; No triggers means nil, which the display engine reads as "all"
+    (neomacs-set-cursor-beacon (and (bound-and-true-p neomacs-cursor-beacon)
+                                    (bound-and-true-p neomacs-cursor-beacon-triggers)
+                                    t)
+                               (bound-and-true-p neomacs-cursor-beacon-color)
+                               (bound-and-true-p neomacs-cursor-beacon-style)
+                               (bound-and-true-p neomacs-cursor-beacon-duration-ms)
+                               (bound-and-true-p neomacs-cursor-beacon-opacity)
+                               (bound-and-true-p neomacs-cursor-beacon-triggers))))
+
+(defun neomacs--set-cursor-beacon-option (sym val)
+  "Set cursor beacon option SYM to VAL and apply it."
+  (set-default sym val)
+  (neomacs--apply-cursor-beacon))
+
+(defcustom neomacs-cursor-beacon nil
+  "Highlight the cursor after switching windows or scrolling far.
+The events are chosen by `neomacs-cursor-beacon-triggers'.  The
+command `neomacs-cursor-beacon' highlights it on demand even when
+this is nil."
+  :type 'boolean
+  :group 'frames
+  :set #'neomacs--set-cursor-beacon-option)
+
+(defcustom neomacs-cursor-beacon-color "#FF803D"
+  "Cursor beacon color."
+  :type '(string :tag "Color (#RRGGBB)")
+  :group 'frames
+  :set #'neomacs--set-cursor-beacon-option)
+
+(defcustom neomacs-cursor-beacon-style 'ring
+  "How the cursor beacon is drawn.
+`ring' collapses a ring onto the cursor, `line' flashes the cursor
+line, and `both' does both."
+  :type '(choice (const ring) (const line) (const both))
+  :group 'frames
+  :set #'neomacs--set-cursor-beacon-option)
+
+(defcustom neomacs-cursor-beacon-duration-ms 500
+  "Cursor beacon duration in milliseconds."
+  :type '(integer :tag "Duration (ms)")
+  :group 'frames
+  :set #'neomacs--set-cursor-beacon-option)
+
+(defcustom neomacs-cursor-beacon-opacity 60
+  "Cursor beacon max opacity (0-100)."
+  :type '(integer :tag "Opacity (%)")
+  :group 'frames
+  :set #'neomacs--set-cursor-beacon-option)
+
+(defcustom neomacs-cursor-beacon-triggers '(switch scroll)
+  "Events that fire the cursor beacon when `neomacs-cursor-beacon' is on.
+`switch' is selecting another window or buffer, `scroll' is scrolling
+by more than a screenful."
+  :type '(set (const switch) (const scroll))
+  :group 'frames
+  :set #'neomacs--set-cursor-beacon-option)
+
 ;; --- Window depth shadow layers ---
 (declare-function neomacs-set-depth-shadow "neomacsterm.c"
   (&optional enabled layers offset color opacity))
@@ -3633,6 +8710,55 @@ in windows whose buffer has very little content."
                     neomacs-window-watermark)
            (neomacs-set-window-watermark t nil val))))
 
+;; --- Text contrast over background images ---
+(declare-function neomacs-set-background-contrast "neomacsterm.c"
+  (&optional enabled style min-ratio max-scrim))
+
+(defun neomacs--apply-background-contrast ()
+  "Send the background contrast options to the display engine."
+  (when (fboundp 'neomacs-set-background-contrast)
+    (neomacs-set-background-contrast
+     (bound-and-true-p neomacs-background-contrast)
+     (bound-and-true-p neomacs-background-contrast-style)
+     (bound-and-true-p neomacs-background-contrast-min-ratio)
+     (bound-and-true-p neomacs-background-contrast-max-scrim))))
+
+(defun neomacs--set-background-contrast-option (sym val)
+  "Set background contrast option SYM to VAL and apply it."
+  (set-default sym val)
+  (neomacs--apply-background-contrast))
+
+(defcustom neomacs-background-contrast nil
+  "Keep text readable over window background images.
+Non-nil compares the color of each character drawn over a background
+image with the part of the image under it, and fixes the characters
+with too little contrast as `neomacs-background-contrast-style' says."
+  :type 'boolean
+  :group 'frames
+  :set #'neomacs--set-background-contrast-option)
+
+(defcustom neomacs-background-contrast-style 'scrim
+  "How text with too little contrast over a background image is fixed.
+`scrim' draws a translucent shade behind it; `recolor' moves its color
+towards black or white."
+  :type '(choice (const :tag "Scrim behind text" scrim)
+                 (const :tag "Recolor text" recolor))
+  :group 'frames
+  :set #'neomacs--set-background-contrast-option)
+
+(defcustom neomacs-background-contrast-min-ratio 4.5
+  "Contrast ratio to keep between text and background images (1 to 21).
+4.5 is the WCAG minimum for body text, 7 the enhanced level."
+  :type 'number
+  :group 'frames
+  :set #'neomacs--set-background-contrast-option)
+
+(defcustom neomacs-background-contrast-max-scrim 60
+  "Highest opacity of the scrim behind text over an image (0-100)."
+  :type '(integer :tag "Opacity")
+  :group 'frames
+  :set #'neomacs--set-background-contrast-option)
+
 ;; --- Cursor trail fade ---
 (declare-function neomacs-set-cursor-trail-fade "neomacsterm.c"
   (&optional enabled length fade-ms))
@@ -3810,6 +8936,53 @@ corner of the active window."
          (when (fboundp 'neomacs-set-typing-speed)
            (neomacs-set-typing-speed val))))
 
+;; --- Typeahead echo ---
+(declare-function neomacs-set-typeahead-echo "neomacsterm.c"
+  (&optional enabled opacity timeout-ms))
+
+(defcustom neomacs-typeahead-echo nil
+  "Enable typeahead echo of typed characters.
+Non-nil draws typed printable characters at the cursor immediately,
+dimmed and underlined, until redisplay shows them inserted, so that
+typing feels instant while Lisp is busy."
+  :type 'boolean
+  :group 'frames
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (fboundp 'neomacs-set-typeahead-echo)
+           (neomacs-set-typeahead-echo
+            val
+            (if (boundp 'neomacs-typeahead-echo-opacity)
+                neomacs-typeahead-echo-opacity nil)
+            (if (boundp 'neomacs-typeahead-echo-timeout)
+                neomacs-typeahead-echo-timeout nil)))))
+
+(defcustom neomacs-typeahead-echo-opacity 60
+  "Opacity of echoed typeahead text (0-100)."
+  :type '(integer :tag "Opacity")
+  :group 'frames
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (and (fboundp 'neomacs-set-typeahead-echo)
+                    (boundp 'neomacs-typeahead-echo)
+                    neomacs-typeahead-echo)
+           (neomacs-set-typeahead-echo
+            t val
+            (if (boundp 'neomacs-typeahead-echo-timeout)
+                neomacs-typeahead-echo-timeout nil)))))
+
+(defcustom neomacs-typeahead-echo-timeout 3000
+  "Milliseconds echoed typeahead waits for redisplay before it is dropped."
+  :type '(integer :tag "Timeout (ms)")
+  :group 'frames
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (and (fboundp 'neomacs-set-typeahead-echo)
+                    (boundp 'neomacs-typeahead-echo)
+                    neomacs-typeahead-echo)
+           (neomacs-set-typeahead-echo
+            t neomacs-typeahead-echo-opacity val))))
+
 ;; --- Window switch highlight fade ---
 (declare-function neomacs-set-window-switch-fade "neomacsterm.c"
   (&optional enabled duration-ms intensity))
@@ -9958,6 +15131,22 @@ Non-nil renders animated flowing color bands at the top of the frame."
                 neomacs-cursor-pendulum-damping nil)
             val))))
 
+;; --- Accessibility tree for screen readers ---
+(declare-function neomacs-accessibility-enable "neomacsfns.c" (arg))
+
+(defcustom neomacs-accessibility nil
+  "Non-nil means describe frame contents for screen readers.
+When enabled, each redisplay updates an accessibility tree of the
+frame's windows, mode lines and popups.  The tree is exported to screen
+readers (over AT-SPI on GNU/Linux) and change events can also be read
+with `neomacs-accessibility-events'."
+  :type 'boolean
+  :group 'neomacs
+  :set (lambda (sym val)
+         (set-default sym val)
+         (when (fboundp 'neomacs-accessibility-enable)
+           (neomacs-accessibility-enable val))))
+
 ;; Provide the feature
 (provide 'neomacs-win)
 (provide 'term/neomacs-win)
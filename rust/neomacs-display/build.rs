@@ -3,9 +3,10 @@
 //! Generates:
 //! 1. C header files for FFI using cbindgen
 //! 2. Rust bindings for WPE WebKit using bindgen
+//! 3. Emacs struct offsets using bindgen on the Emacs headers
 
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 fn main() {
     let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
@@ -16,6 +17,9 @@ fn main() {
     // Generate C headers with cbindgen
     generate_c_headers(&crate_dir);
 
+    // Generate Emacs struct offsets with bindgen
+    generate_emacs_layout(&crate_dir, &PathBuf::from(env::var("OUT_DIR").unwrap()));
+
     // Generate WPE bindings with bindgen (if feature enabled)
     #[cfg(feature = "wpe-webkit")]
     {
@@ -46,6 +50,141 @@ fn generate_c_headers(crate_dir: &str) {
     }
 }
 
+/// Fields of `StructOffsets` (src/layout/emacs_types.rs), in order, and
+/// the expressions giving them from the bindgen types in `sys`.
+const EMACS_LAYOUT: &[(&str, &str)] = &[
+    ("buf_text", "offset_of!(sys::buffer, text)"),
+    ("buf_pt", "offset_of!(sys::buffer, pt)"),
+    ("buf_pt_byte", "offset_of!(sys::buffer, pt_byte)"),
+    ("buf_begv", "offset_of!(sys::buffer, begv)"),
+    ("buf_begv_byte", "offset_of!(sys::buffer, begv_byte)"),
+    ("buf_zv", "offset_of!(sys::buffer, zv)"),
+    ("buf_zv_byte", "offset_of!(sys::buffer, zv_byte)"),
+    ("buf_base_buffer", "offset_of!(sys::buffer, base_buffer)"),
+    ("buf_lisp_field_count", "sys::BUFFER_LISP_SIZE as usize"),
+    ("buftext_beg", "offset_of!(sys::buffer_text, beg)"),
+    ("buftext_gpt", "offset_of!(sys::buffer_text, gpt)"),
+    ("buftext_z", "offset_of!(sys::buffer_text, z)"),
+    ("buftext_gpt_byte", "offset_of!(sys::buffer_text, gpt_byte)"),
+    ("buftext_z_byte", "offset_of!(sys::buffer_text, z_byte)"),
+    ("buftext_gap_size", "offset_of!(sys::buffer_text, gap_size)"),
+    ("buf_tab_width", "offset_of!(sys::buffer, tab_width_)"),
+    ("buf_truncate_lines", "offset_of!(sys::buffer, truncate_lines_)"),
+    ("buf_enable_multibyte", "offset_of!(sys::buffer, enable_multibyte_characters_)"),
+    ("buf_pt_marker", "offset_of!(sys::buffer, pt_marker_)"),
+    ("buf_begv_marker", "offset_of!(sys::buffer, begv_marker_)"),
+    ("buf_zv_marker", "offset_of!(sys::buffer, zv_marker_)"),
+    ("buf_word_wrap", "offset_of!(sys::buffer, word_wrap_)"),
+    ("buf_selective_display", "offset_of!(sys::buffer, selective_display_)"),
+    ("win_frame", "offset_of!(sys::window, frame)"),
+    ("win_next", "offset_of!(sys::window, next)"),
+    ("win_contents", "offset_of!(sys::window, contents)"),
+    ("frame_root_window", "offset_of!(sys::frame, root_window)"),
+    ("frame_selected_window", "offset_of!(sys::frame, selected_window)"),
+    ("frame_minibuffer_window", "offset_of!(sys::frame, minibuffer_window)"),
+    ("pvec_window", "sys::pvec_type_PVEC_WINDOW as usize"),
+    ("pvec_buffer", "sys::pvec_type_PVEC_BUFFER as usize"),
+    ("pseudovector_area_bits", "sys::PSEUDOVECTOR_AREA_BITS as usize"),
+    ("pseudovector_flag", "(isize::MAX - isize::MAX / 2) as usize"),
+];
+
+/// Write `emacs_layout.rs` for `emacs_types::generated`: the Emacs
+/// structs layout reads directly, generated by bindgen from the headers
+/// of the Emacs being built, and `LAYOUT`, the offsets in them.
+///
+/// The Emacs build passes `NEOMACS_EMACS_BUILDDIR` (its src directory,
+/// with config.h) and `NEOMACS_EMACS_CFLAGS` (its `EMACS_CFLAGS`, whose
+/// relative include paths are taken from there).  Without them the
+/// headers are looked for in ../../src.  When they are not found, as in
+/// a standalone `cargo build`, `LAYOUT` is None and only the runtime
+/// checks against C run.
+fn generate_emacs_layout(crate_dir: &str, out_dir: &Path) {
+    println!("cargo:rerun-if-env-changed=NEOMACS_EMACS_BUILDDIR");
+    println!("cargo:rerun-if-env-changed=NEOMACS_EMACS_CFLAGS");
+    let out_file = out_dir.join("emacs_layout.rs");
+    let fallback = "pub const LAYOUT: Option<super::StructOffsets> = None;\n";
+
+    let explicit = env::var_os("NEOMACS_EMACS_BUILDDIR").is_some();
+    let builddir = env::var_os("NEOMACS_EMACS_BUILDDIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(crate_dir).join("../../src"));
+    if !builddir.join("config.h").exists() {
+        if explicit {
+            println!("cargo:warning=no config.h in {}, Emacs struct layout not generated",
+                     builddir.display());
+        }
+        std::fs::write(&out_file, fallback).expect("Failed to write emacs_layout.rs");
+        return;
+    }
+
+    // Keep the include paths and macro definitions of the Emacs flags
+    let cflags = env::var("NEOMACS_EMACS_CFLAGS").unwrap_or_else(|_| {
+        "-Demacs -I. -I../lib".to_string()
+    });
+    let mut args = Vec::new();
+    let mut words = cflags.split_whitespace();
+    while let Some(word) = words.next() {
+        let (flag, value) = match word {
+            "-I" | "-D" | "-U" => (word, words.next().unwrap_or("").to_string()),
+            _ if word.len() > 2 && ["-I", "-D", "-U"].contains(&&word[..2]) => {
+                (&word[..2], word[2..].to_string())
+            }
+            _ => continue,
+        };
+        if flag == "-I" && Path::new(&value).is_relative() {
+            args.push(format!("-I{}", builddir.join(&value).display()));
+        } else {
+            args.push(format!("{}{}", flag, value));
+        }
+    }
+
+    for header in ["lisp.h", "buffer.h", "window.h", "frame.h"] {
+        println!("cargo:rerun-if-changed={}", builddir.join(header).display());
+    }
+    println!("cargo:rerun-if-changed={}", builddir.join("config.h").display());
+
+    let bindings = bindgen::Builder::default()
+        .header_contents(
+            "emacs_layout_wrapper.h",
+            r#"
+            #include <config.h>
+            #include "lisp.h"
+            #include "buffer.h"
+            #include "window.h"
+            #include "frame.h"
+            "#,
+        )
+        .clang_args(&args)
+        .allowlist_type("buffer|buffer_text|window|frame|pvec_type")
+        .allowlist_item("BUFFER_LISP_SIZE|PSEUDOVECTOR_AREA_BITS")
+        .layout_tests(false)
+        .generate_comments(false)
+        .generate();
+
+    let bindings = match bindings {
+        Ok(bindings) => bindings,
+        Err(e) => {
+            println!("cargo:warning=bindgen failed on the Emacs headers ({}), \
+                      Emacs struct layout not generated", e);
+            std::fs::write(&out_file, fallback).expect("Failed to write emacs_layout.rs");
+            return;
+        }
+    };
+
+    let mut code = String::from(
+        "#[allow(non_upper_case_globals, non_camel_case_types, non_snake_case, dead_code, clippy::all)]\n\
+         mod sys {\n",
+    );
+    code.push_str(&bindings.to_string());
+    code.push_str("}\n\nuse std::mem::offset_of;\n\n");
+    code.push_str("pub const LAYOUT: Option<super::StructOffsets> = Some(super::StructOffsets {\n");
+    for (field, expr) in EMACS_LAYOUT {
+        code.push_str(&format!("    {}: {},\n", field, expr));
+    }
+    code.push_str("});\n");
+    std::fs::write(&out_file, code).expect("Failed to write emacs_layout.rs");
+}
+
 #[cfg(feature = "wpe-webkit")]
 fn generate_wpe_bindings(out_dir: &PathBuf) {
     // Find WPE libraries using pkg-config
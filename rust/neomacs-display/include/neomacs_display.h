@@ -1544,6 +1544,11 @@ void neomacs_display_set_frosted_glass(struct NeomacsDisplay *handle,
 
 void neomacs_display_set_typing_speed(struct NeomacsDisplay *handle, int enabled);
 
+void neomacs_display_set_typeahead_echo(struct NeomacsDisplay *handle,
+                                        int enabled,
+                                        int opacity,
+                                        int timeoutMs);
+
 void neomacs_display_set_title_fade(struct NeomacsDisplay *handle, int enabled, int durationMs);
 
 void neomacs_display_set_region_glow(struct NeomacsDisplay *handle,
@@ -2709,6 +2714,85 @@ uint32_t neomacs_display_terminal_create(uint16_t cols,
  */
 void neomacs_display_terminal_write(uint32_t terminalId, const uint8_t *data, uintptr_t len);
 
+/**
+ * Send a key event to a terminal.
+ *
+ * `key` is a single character or an Emacs key name (`return`, `up`,
+ * `f5`, ...); `modifiers` are kitty modifier bits (1=Shift, 2=Alt,
+ * 4=Ctrl, 8=Super, 16=Hyper, 32=Meta); `kind`: 0=press, 1=repeat,
+ * 2=release.  Returns 0 if the key name is unknown.
+ */
+int neomacs_display_terminal_send_key(uint32_t terminalId,
+                                      const char *key,
+                                      uint8_t modifiers,
+                                      uint8_t kind);
+
+/**
+ * Report a mouse event to the program in a terminal.
+ *
+ * `button`: 0=none (motion), 1-3=left/middle/right, 4-7=wheel
+ * up/down/left/right; `kind`: 0=press, 1=release, 2=motion; `col` and
+ * `row` are 0-based cells; `modifiers` are kitty modifier bits as for
+ * `neomacs_display_terminal_send_key`.  Nothing is sent unless the
+ * program enabled mouse tracking.
+ */
+void neomacs_display_terminal_send_mouse(uint32_t terminalId,
+                                         uint8_t button,
+                                         uint8_t kind,
+                                         uint32_t col,
+                                         uint32_t row,
+                                         uint8_t modifiers);
+
+/**
+ * Get the mouse tracking mode the program in a terminal enabled.
+ *
+ * Returns 0=off, 1=X10, 2=normal, 3=button-event, 4=any-event.
+ */
+int neomacs_display_terminal_mouse_mode(uint32_t terminalId);
+
+/**
+ * Set the terminal with keyboard focus (0 for none).
+ *
+ * Emacs only sees key presses, so the render thread reports key
+ * releases to this terminal itself when its program asked for them.
+ */
+void neomacs_display_terminal_focus(uint32_t terminalId);
+
+/**
+ * Flash a terminal's view briefly, as a visual bell.
+ */
+void neomacs_display_terminal_flash(uint32_t terminalId);
+
+/**
+ * Scroll a terminal's view so that the Nth shell prompt after its top
+ * line (before it if `n` is negative) is at the top.  Needs a shell
+ * that marks its prompts (OSC 133).
+ */
+void neomacs_display_terminal_jump_to_prompt(uint32_t terminalId, int n);
+
+/**
+ * Get the output of the last finished command in a terminal, as
+ * marked by the shell (OSC 133).
+ *
+ * Returns a malloc'd C string (caller must free with `free()`).
+ * Returns NULL if there is no such output.
+ */
+char *neomacs_display_terminal_last_output(uint32_t terminalId);
+
+/**
+ * Set a terminal's colors.
+ *
+ * `colors` points to `ncolors` (at most 16 used) ANSI palette entries,
+ * `fg` and `bg` are the default colors, all as 0xRRGGBB; 0xFFFFFFFF
+ * keeps the built-in color.  Colors the program sets itself with OSC
+ * 4/10/11 still take precedence.
+ */
+void neomacs_display_terminal_set_palette(uint32_t terminalId,
+                                          const uint32_t *colors,
+                                          int ncolors,
+                                          uint32_t fg,
+                                          uint32_t bg);
+
 /**
  * Resize a terminal.
  */
@@ -3438,6 +3522,20 @@ extern int neomacs_layout_check_invisible(EmacsBuffer buffer,
                                           int64_t charpos,
                                           int64_t *nextVisibleOut);
 
+/**
+ * Check for a static composition (`composition' text property) at charpos.
+ * Writes the composed characters into str_buf as UTF-8 and returns the
+ * byte count, with *end_out set to the end of the composed text and
+ * *width_out to its width in columns.  Returns 0 if there is none;
+ * *end_out is then where the next composition starts.
+ */
+extern int neomacs_layout_composition_at(EmacsBuffer buffer,
+                                         int64_t charpos,
+                                         uint8_t *strBuf,
+                                         int strBufLen,
+                                         int64_t *endOut,
+                                         int *widthOut);
+
 /**
  * Get mode-line text for a window as plain UTF-8.
  * Returns the number of bytes written, or -1 on error.
@@ -3569,14 +3667,17 @@ extern int neomacs_layout_margin_strings_at(EmacsBuffer buffer,
                                             int *rightLenOut);
 
 /**
- * Check line-height and line-spacing text properties at a position.
- * Returns extra height (pixels) beyond base_height for the row.
+ * Check line-height and line-spacing text properties at a position
+ * (the newline ending a line).  Returns the extra pixels beyond
+ * base_height above the line's text (line-height) and below it
+ * (line-spacing).
  */
 extern int neomacs_layout_check_line_spacing(EmacsBuffer buffer,
                                              EmacsWindow window,
                                              int64_t charpos,
                                              float baseHeight,
-                                             float *extraHeightOut);
+                                             float *extraAboveOut,
+                                             float *extraBelowOut);
 
 /**
  * Check line-prefix or wrap-prefix text property at a position.
@@ -1,6 +1,7 @@
 //! Backend trait and module exports.
 
 use crate::core::error::DisplayResult;
+use crate::core::monitor::MonitorInfo;
 use crate::core::scene::Scene;
 
 pub mod tty;
@@ -23,6 +24,11 @@ pub trait DisplayBackend {
     fn is_initialized(&self) -> bool;
     fn resize(&mut self, width: u32, height: u32);
     fn set_vsync(&mut self, enabled: bool);
+
+    /// Monitors the backend can show frames on, primary first
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        Vec::new()
+    }
 }
 
 /// Backend type selection
@@ -14,6 +14,7 @@ use std::io::{self, Write};
 use crate::backend::DisplayBackend;
 use crate::core::error::{DisplayError, DisplayResult};
 use crate::core::frame_glyphs::{CursorStyle, FrameGlyph, FrameGlyphBuffer};
+use crate::core::monitor::MonitorInfo;
 use crate::core::scene::Scene;
 use crate::core::types::Color;
 
@@ -361,6 +362,37 @@ fn get_terminal_size() -> Option<(u16, u16)> {
     None
 }
 
+/// Get the terminal's size in pixels, if the terminal reports it.
+#[cfg(unix)]
+fn get_terminal_pixel_size() -> Option<(u16, u16)> {
+    use std::mem::MaybeUninit;
+    unsafe {
+        let mut ws = MaybeUninit::<libc::winsize>::uninit();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, ws.as_mut_ptr()) == 0 {
+            let ws = ws.assume_init();
+            if ws.ws_xpixel > 0 && ws.ws_ypixel > 0 {
+                return Some((ws.ws_xpixel, ws.ws_ypixel));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(not(unix))]
+fn get_terminal_pixel_size() -> Option<(u16, u16)> {
+    None
+}
+
+/// The terminal as the only monitor: its pixel size if known, else its
+/// size in cells.
+fn terminal_monitor(cols: u32, rows: u32, pixels: Option<(u16, u16)>) -> MonitorInfo {
+    let (width, height) = pixels.map_or((cols as i32, rows as i32), |(w, h)| (w as i32, h as i32));
+    let mut monitor = MonitorInfo::new(0, 0, width, height, 1.0);
+    monitor.primary = true;
+    monitor.name = std::env::var("TERM").ok();
+    monitor
+}
+
 // ---------------------------------------------------------------------------
 // Frame diffing
 // ---------------------------------------------------------------------------
@@ -984,6 +1016,10 @@ impl DisplayBackend for TtyBackend {
     fn set_vsync(&mut self, _enabled: bool) {
         // No vsync on TTY
     }
+
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        vec![terminal_monitor(self.width, self.height, get_terminal_pixel_size())]
+    }
 }
 
 impl Drop for TtyBackend {
@@ -2182,4 +2218,14 @@ mod tests {
         assert!(s.contains("\u{4E2D}"));
         assert!(s.contains("A"));
     }
+
+    #[test]
+    fn test_terminal_monitor_size() {
+        let cells = terminal_monitor(80, 24, None);
+        assert_eq!((cells.width, cells.height), (80, 24));
+        assert!(cells.primary);
+
+        let pixels = terminal_monitor(80, 24, Some((1280, 768)));
+        assert_eq!((pixels.width, pixels.work_height), (1280, 768));
+    }
 }
@@ -115,9 +115,9 @@ pub struct Animation {
 }
 
 impl Animation {
-    /// Get the current interpolated value based on elapsed time and easing.
-    pub fn current_value(&self) -> f32 {
-        let elapsed = self.started.elapsed();
+    /// Get the interpolated value at the frame time `now`.
+    pub fn current_value(&self, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.started);
         let progress = if self.duration.as_secs_f32() > 0.0 {
             (elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
         } else {
@@ -128,9 +128,9 @@ impl Animation {
         self.from + (self.to - self.from) * eased
     }
 
-    /// Check if the animation has completed.
-    pub fn is_complete(&self) -> bool {
-        self.started.elapsed() >= self.duration
+    /// Check if the animation has completed at the frame time `now`.
+    pub fn is_complete(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.started) >= self.duration
     }
 }
 
@@ -152,7 +152,7 @@ impl AnimationEngine {
         }
     }
 
-    /// Start a new animation.
+    /// Start a new animation at the frame time `now`.
     ///
     /// Returns the animation ID which can be used to cancel the animation.
     pub fn animate(
@@ -163,6 +163,7 @@ impl AnimationEngine {
         to: f32,
         duration: Duration,
         easing: Easing,
+        now: Instant,
     ) -> u64 {
         let id = self.next_id;
         self.next_id += 1;
@@ -178,7 +179,7 @@ impl AnimationEngine {
             to,
             duration,
             easing,
-            started: Instant::now(),
+            started: now,
         };
 
         self.animations.push(animation);
@@ -190,22 +191,29 @@ impl AnimationEngine {
         self.animations.retain(|a| a.id != id);
     }
 
-    /// Tick the animation engine, removing completed animations.
+    /// Tick the animation engine to the frame time `now`, removing
+    /// completed animations.
     ///
     /// Returns `true` if there are any active animations remaining.
-    pub fn tick(&mut self) -> bool {
-        self.animations.retain(|a| !a.is_complete());
+    pub fn tick(&mut self, now: Instant) -> bool {
+        self.animations.retain(|a| !a.is_complete(now));
         !self.animations.is_empty()
     }
 
-    /// Get the current animated value for a target and property.
+    /// Get the animated value for a target and property at the frame time
+    /// `now`.
     ///
     /// Returns `None` if there is no active animation for this combination.
-    pub fn get_value(&self, target: AnimationTarget, property: AnimatedProperty) -> Option<f32> {
+    pub fn get_value(
+        &self,
+        target: AnimationTarget,
+        property: AnimatedProperty,
+        now: Instant,
+    ) -> Option<f32> {
         self.animations
             .iter()
             .find(|a| a.target == target && a.property == property)
-            .map(|a| a.current_value())
+            .map(|a| a.current_value(now))
     }
 
     /// Check if there are any active animations.
@@ -223,7 +231,6 @@ impl Default for AnimationEngine {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::thread::sleep;
     use std::time::Duration;
 
     #[test]
@@ -270,6 +277,7 @@ mod tests {
     #[test]
     fn test_animation_engine_basic() {
         let mut engine = AnimationEngine::new();
+        let now = Instant::now();
 
         let id = engine.animate(
             AnimationTarget::Window(1),
@@ -278,10 +286,11 @@ mod tests {
             100.0,
             Duration::from_millis(100),
             Easing::Linear,
+            now,
         );
 
         assert!(engine.has_animations());
-        assert!(engine.get_value(AnimationTarget::Window(1), AnimatedProperty::X).is_some());
+        assert!(engine.get_value(AnimationTarget::Window(1), AnimatedProperty::X, now).is_some());
 
         engine.cancel(id);
         assert!(!engine.has_animations());
@@ -290,6 +299,7 @@ mod tests {
     #[test]
     fn test_animation_completion() {
         let mut engine = AnimationEngine::new();
+        let now = Instant::now();
 
         engine.animate(
             AnimationTarget::Cursor,
@@ -298,20 +308,21 @@ mod tests {
             1.0,
             Duration::from_millis(10),
             Easing::Linear,
+            now,
         );
 
         assert!(engine.has_animations());
+        assert!(engine.tick(now + Duration::from_millis(5)));
 
-        // Wait for animation to complete
-        sleep(Duration::from_millis(20));
-
-        engine.tick();
+        // Past the end of the animation
+        assert!(!engine.tick(now + Duration::from_millis(20)));
         assert!(!engine.has_animations());
     }
 
     #[test]
     fn test_animation_replaces_same_target_property() {
         let mut engine = AnimationEngine::new();
+        let now = Instant::now();
 
         engine.animate(
             AnimationTarget::Window(1),
@@ -320,6 +331,7 @@ mod tests {
             100.0,
             Duration::from_secs(1),
             Easing::Linear,
+            now,
         );
 
         engine.animate(
@@ -329,11 +341,12 @@ mod tests {
             150.0,
             Duration::from_secs(1),
             Easing::Linear,
+            now,
         );
 
         // Should only have one animation
         assert_eq!(engine.animations.len(), 1);
         // The new animation should start from 50
-        assert!((engine.get_value(AnimationTarget::Window(1), AnimatedProperty::X).unwrap() - 50.0).abs() < 1.0);
+        assert_eq!(engine.get_value(AnimationTarget::Window(1), AnimatedProperty::X, now), Some(50.0));
     }
 }
@@ -20,6 +20,7 @@ use crate::backend::DisplayBackend;
 use crate::core::error::{DisplayError, DisplayResult};
 use crate::core::face::Face;
 use crate::core::frame_glyphs::FrameGlyphBuffer;
+use crate::core::monitor::MonitorInfo;
 use crate::core::scene::Scene;
 
 use super::window_state::WindowState;
@@ -719,6 +720,10 @@ impl WinitBackend {
         #[cfg(feature = "video")]
         renderer.process_pending_videos();
 
+        // The legacy path has no animation clock; sample effects at the
+        // wall clock
+        renderer.set_frame_time(std::time::Instant::now());
+
         // Get mutable reference to glyph atlas
         if let Some(ref mut glyph_atlas) = self.glyph_atlas {
             log::debug!("end_frame_for_window: calling render_frame_glyphs");
@@ -837,6 +842,16 @@ impl DisplayBackend for WinitBackend {
         "winit-wgpu"
     }
 
+    fn monitors(&self) -> Vec<MonitorInfo> {
+        match self.window.as_ref() {
+            Some(window) => super::monitors::collect_monitors(
+                window.available_monitors(),
+                window.primary_monitor(),
+            ),
+            None => Vec::new(),
+        }
+    }
+
     fn is_initialized(&self) -> bool {
         self.initialized
     }
@@ -19,6 +19,17 @@ pub enum EventKind {
     MenuSelection = 13,
     FileDrop = 14,
     TerminalTitleChanged = 15,
+    MonitorsChanged = 16,
+    CharPickerSelection = 17,
+    TerminalBell = 18,
+    PinchZoom = 19,
+    CommandPaletteSelection = 20,
+    ColorPickerSelection = 21,
+    AgendaTimelineSelection = 22,
+    GraphViewSelection = 23,
+    DirViewSelection = 24,
+    ProcessMonitorSelection = 25,
+    DatePickerSelection = 26,
 }
 
 /// Modifier flags matching Emacs.
@@ -43,6 +54,17 @@ pub const NEOMACS_EVENT_TERMINAL_EXITED: u32 = EventKind::TerminalExited as u32;
 pub const NEOMACS_EVENT_MENU_SELECTION: u32 = EventKind::MenuSelection as u32;
 pub const NEOMACS_EVENT_FILE_DROP: u32 = EventKind::FileDrop as u32;
 pub const NEOMACS_EVENT_TERMINAL_TITLE_CHANGED: u32 = EventKind::TerminalTitleChanged as u32;
+pub const NEOMACS_EVENT_MONITORS_CHANGED: u32 = EventKind::MonitorsChanged as u32;
+pub const NEOMACS_EVENT_CHAR_PICKER_SELECTION: u32 = EventKind::CharPickerSelection as u32;
+pub const NEOMACS_EVENT_TERMINAL_BELL: u32 = EventKind::TerminalBell as u32;
+pub const NEOMACS_EVENT_PINCH_ZOOM: u32 = EventKind::PinchZoom as u32;
+pub const NEOMACS_EVENT_COMMAND_PALETTE_SELECTION: u32 = EventKind::CommandPaletteSelection as u32;
+pub const NEOMACS_EVENT_COLOR_PICKER_SELECTION: u32 = EventKind::ColorPickerSelection as u32;
+pub const NEOMACS_EVENT_AGENDA_TIMELINE_SELECTION: u32 = EventKind::AgendaTimelineSelection as u32;
+pub const NEOMACS_EVENT_GRAPH_VIEW_SELECTION: u32 = EventKind::GraphViewSelection as u32;
+pub const NEOMACS_EVENT_DIR_VIEW_SELECTION: u32 = EventKind::DirViewSelection as u32;
+pub const NEOMACS_EVENT_PROCESS_MONITOR_SELECTION: u32 = EventKind::ProcessMonitorSelection as u32;
+pub const NEOMACS_EVENT_DATE_PICKER_SELECTION: u32 = EventKind::DatePickerSelection as u32;
 
 /// Input event structure passed to C.
 #[repr(C)]
@@ -64,6 +86,8 @@ pub struct NeomacsInputEvent {
     pub height: u32,
     /// Target frame pointer for child frame mouse event routing (0 = parent frame)
     pub target_frame_id: u64,
+    /// Lines a scroll event moves (0 = unspecified)
+    pub scroll_lines: i32,
 }
 
 impl Default for NeomacsInputEvent {
@@ -84,6 +108,7 @@ impl Default for NeomacsInputEvent {
             width: 0,
             height: 0,
             target_frame_id: 0,
+            scroll_lines: 0,
         }
     }
 }
@@ -111,6 +136,17 @@ mod tests {
         assert_eq!(EventKind::MenuSelection as u32, 13);
         assert_eq!(EventKind::FileDrop as u32, 14);
         assert_eq!(EventKind::TerminalTitleChanged as u32, 15);
+        assert_eq!(EventKind::MonitorsChanged as u32, 16);
+        assert_eq!(EventKind::CharPickerSelection as u32, 17);
+        assert_eq!(EventKind::TerminalBell as u32, 18);
+        assert_eq!(EventKind::PinchZoom as u32, 19);
+        assert_eq!(EventKind::CommandPaletteSelection as u32, 20);
+        assert_eq!(EventKind::ColorPickerSelection as u32, 21);
+        assert_eq!(EventKind::AgendaTimelineSelection as u32, 22);
+        assert_eq!(EventKind::GraphViewSelection as u32, 23);
+        assert_eq!(EventKind::DirViewSelection as u32, 24);
+        assert_eq!(EventKind::ProcessMonitorSelection as u32, 25);
+        assert_eq!(EventKind::DatePickerSelection as u32, 26);
     }
 
     // ---- FFI event kind constants match enum ----
@@ -132,6 +168,17 @@ mod tests {
         assert_eq!(NEOMACS_EVENT_MENU_SELECTION, EventKind::MenuSelection as u32);
         assert_eq!(NEOMACS_EVENT_FILE_DROP, EventKind::FileDrop as u32);
         assert_eq!(NEOMACS_EVENT_TERMINAL_TITLE_CHANGED, EventKind::TerminalTitleChanged as u32);
+        assert_eq!(NEOMACS_EVENT_MONITORS_CHANGED, EventKind::MonitorsChanged as u32);
+        assert_eq!(NEOMACS_EVENT_CHAR_PICKER_SELECTION, EventKind::CharPickerSelection as u32);
+        assert_eq!(NEOMACS_EVENT_TERMINAL_BELL, EventKind::TerminalBell as u32);
+        assert_eq!(NEOMACS_EVENT_PINCH_ZOOM, EventKind::PinchZoom as u32);
+        assert_eq!(NEOMACS_EVENT_COMMAND_PALETTE_SELECTION, EventKind::CommandPaletteSelection as u32);
+        assert_eq!(NEOMACS_EVENT_COLOR_PICKER_SELECTION, EventKind::ColorPickerSelection as u32);
+        assert_eq!(NEOMACS_EVENT_AGENDA_TIMELINE_SELECTION, EventKind::AgendaTimelineSelection as u32);
+        assert_eq!(NEOMACS_EVENT_GRAPH_VIEW_SELECTION, EventKind::GraphViewSelection as u32);
+        assert_eq!(NEOMACS_EVENT_DIR_VIEW_SELECTION, EventKind::DirViewSelection as u32);
+        assert_eq!(NEOMACS_EVENT_PROCESS_MONITOR_SELECTION, EventKind::ProcessMonitorSelection as u32);
+        assert_eq!(NEOMACS_EVENT_DATE_PICKER_SELECTION, EventKind::DatePickerSelection as u32);
     }
 
     // ---- Modifier mask constants ----
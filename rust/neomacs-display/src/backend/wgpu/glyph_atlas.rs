@@ -8,7 +8,10 @@ use cosmic_text::{
     Attrs, Buffer, Family, FontSystem, Metrics, ShapeBuffer, SwashCache, Style, Weight,
 };
 
+use super::glyph_disk_cache::{GlyphDiskCache, RasterKey, RasterMetrics};
+use super::memory::MemoryBudget;
 use crate::core::face::Face;
+use crate::core::icons::IconRegistry;
 
 /// Key for glyph cache lookup
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
@@ -58,6 +61,19 @@ pub struct CachedGlyph {
     last_accessed: u64,
 }
 
+impl CachedGlyph {
+    /// Texture memory in bytes
+    fn memory_size(&self) -> usize {
+        glyph_bytes(self.width, self.height, self.is_color)
+    }
+}
+
+/// Texture memory of a WIDTH x HEIGHT glyph (RGBA for color glyphs, R8
+/// for masks)
+fn glyph_bytes(width: u32, height: u32, is_color: bool) -> usize {
+    width as usize * height as usize * if is_color { 4 } else { 1 }
+}
+
 /// Wgpu-based glyph atlas for text rendering
 pub struct WgpuGlyphAtlas {
     /// Cached glyphs: (charcode, face_id) -> CachedGlyph
@@ -83,15 +99,31 @@ pub struct WgpuGlyphAtlas {
     scale_factor: f32,
     /// Maximum cache size
     max_size: usize,
+    /// Texture memory of all cached glyphs, in bytes
+    memory_bytes: usize,
+    /// Memory budget for cached glyphs, in bytes
+    max_memory: usize,
+    /// Number of glyphs evicted so far
+    evictions: u64,
     /// Interned font family names (avoids Box::leak memory growth)
     interned_families: HashSet<&'static str>,
     /// Frame generation counter (incremented each frame)
     generation: u64,
+    /// Icon-font glyphs drawn from their own font, shifted and scaled
+    icons: IconRegistry,
+    /// Rasterized glyphs kept across sessions
+    disk_cache: Option<GlyphDiskCache>,
 }
 
 impl WgpuGlyphAtlas {
     /// Create a new wgpu glyph atlas
     pub fn new(device: &wgpu::Device) -> Self {
+        Self::with_font_system(device, 1.0, FontSystem::new())
+    }
+
+    /// Create a glyph atlas rasterizing with FONT_SYSTEM, whose font
+    /// database may have been loaded on another thread
+    pub fn with_font_system(device: &wgpu::Device, scale_factor: f32, font_system: FontSystem) -> Self {
         // Create bind group layout for glyph texture + sampler
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Glyph Bind Group Layout"),
@@ -131,25 +163,28 @@ impl WgpuGlyphAtlas {
         Self {
             cache: HashMap::new(),
             composed_cache: HashMap::new(),
-            font_system: FontSystem::new(),
+            font_system,
             swash_cache: SwashCache::new(),
             shape_buffer: ShapeBuffer::default(),
             bind_group_layout,
             sampler,
             default_font_size: 13.0,
             default_line_height: 17.0,
-            scale_factor: 1.0,
+            scale_factor,
             max_size: 4096,
+            memory_bytes: 0,
+            max_memory: MemoryBudget::default().glyphs,
+            evictions: 0,
             interned_families: HashSet::new(),
             generation: 0,
+            icons: IconRegistry::new(),
+            disk_cache: None,
         }
     }
 
     /// Create a new wgpu glyph atlas with a specific scale factor for HiDPI
     pub fn new_with_scale(device: &wgpu::Device, scale_factor: f32) -> Self {
-        let mut atlas = Self::new(device);
-        atlas.scale_factor = scale_factor;
-        atlas
+        Self::with_font_system(device, scale_factor, FontSystem::new())
     }
 
     /// Get the bind group layout for glyph textures
@@ -184,7 +219,15 @@ impl WgpuGlyphAtlas {
             return None;
         }
 
-        let rasterize_result = self.rasterize_glyph(c, face);
+        let rasterize_result = match self.icons.get(c) {
+            Some(icon) => {
+                let raster_face = icon.raster_face(face, self.default_font_size);
+                let shift = icon.baseline_shift(raster_face.font_size) * self.scale_factor;
+                self.rasterize_cached(&c.to_string(), Some(&raster_face))
+                    .map(|(w, h, data, bx, by, color)| (w, h, data, bx, by + shift, color))
+            }
+            None => self.rasterize_cached(&c.to_string(), face),
+        };
         if rasterize_result.is_none() {
             log::warn!("glyph_atlas: failed to rasterize '{}' (U+{:04X}) face_id={} has_face={}",
                 c, key.charcode, key.face_id, face.is_some());
@@ -263,18 +306,13 @@ impl WgpuGlyphAtlas {
         });
 
         // Evict least-recently-used entries if cache is full
-        if self.cache.len() >= self.max_size {
-            let mut entries: Vec<_> = self.cache.iter()
-                .map(|(k, v)| (k.clone(), v.last_accessed))
-                .collect();
-            entries.sort_by_key(|(_, gen)| *gen);
-            let evict_count = self.max_size / 4;
-            for (k, _) in entries.into_iter().take(evict_count) {
-                self.cache.remove(&k);
-            }
+        let size = glyph_bytes(width, height, is_color);
+        if self.cache.len() >= self.max_size || self.memory_bytes + size > self.max_memory {
+            self.evict_lru(size);
         }
 
         // Insert into cache
+        self.memory_bytes += size;
         let gen = self.generation;
         let cached_glyph = CachedGlyph {
             texture,
@@ -317,7 +355,7 @@ impl WgpuGlyphAtlas {
         }
 
         // Rasterize the composed text
-        let rasterize_result = self.rasterize_text(text, face);
+        let rasterize_result = self.rasterize_cached(text, face);
         if rasterize_result.is_none() {
             log::warn!("glyph_atlas: failed to rasterize composed text '{}'", text);
             return None;
@@ -370,6 +408,11 @@ impl WgpuGlyphAtlas {
             ],
         });
 
+        let size = glyph_bytes(width, height, is_color);
+        if self.memory_bytes + size > self.max_memory {
+            self.evict_lru(size);
+        }
+        self.memory_bytes += size;
         let gen = self.generation;
         self.composed_cache.insert(key.clone(), CachedGlyph {
             texture, view, bind_group, width, height,
@@ -559,13 +602,43 @@ impl WgpuGlyphAtlas {
         Some((total_w, total_h, composite, min_x, -min_y, any_color || sub_glyphs.len() > 1))
     }
 
-    /// Rasterize a single glyph and return pixel data (convenience wrapper)
-    fn rasterize_glyph(
+    /// Rasterize text like `rasterize_text`, taking the pixels from the
+    /// disk cache when a past session rasterized it already
+    fn rasterize_cached(
         &mut self,
-        c: char,
+        text: &str,
         face: Option<&Face>,
     ) -> Option<(u32, u32, Vec<u8>, f32, f32, bool)> {
-        self.rasterize_text(&c.to_string(), face)
+        let Some(cache) = self.disk_cache.as_mut() else {
+            return self.rasterize_text(text, face);
+        };
+        let key = RasterKey::new(text, face, self.default_font_size, self.scale_factor);
+        if let Some((m, pixels)) = cache.get(&key) {
+            return Some((m.width, m.height, pixels.to_vec(), m.bearing_x, m.bearing_y, m.is_color));
+        }
+        let (width, height, pixels, bearing_x, bearing_y, is_color) = self.rasterize_text(text, face)?;
+        if let Some(cache) = self.disk_cache.as_mut() {
+            let metrics = RasterMetrics { width, height, bearing_x, bearing_y, is_color };
+            cache.insert(key, metrics, pixels.clone());
+        }
+        Some((width, height, pixels, bearing_x, bearing_y, is_color))
+    }
+
+    /// Keep rasterized glyphs in CACHE across sessions.  Glyphs a past
+    /// session stored there are used instead of rasterizing them again;
+    /// CACHE must have been opened with the `font_fingerprint` of this
+    /// atlas's fonts.
+    pub fn set_disk_cache(&mut self, cache: GlyphDiskCache) {
+        self.disk_cache = Some(cache);
+    }
+
+    /// Write the glyphs rasterized this session to the disk cache
+    pub fn save_disk_cache(&mut self) {
+        if let Some(cache) = self.disk_cache.as_mut() {
+            if let Err(e) = cache.save() {
+                log::warn!("glyph cache: cannot save: {}", e);
+            }
+        }
     }
 
     /// Convert Face to cosmic-text Attrs
@@ -607,6 +680,27 @@ impl WgpuGlyphAtlas {
         attrs
     }
 
+    /// Measure TEXT shaped in FACE (or the default monospace font) as
+    /// laid out without wrapping.  Returns (width, height) in logical
+    /// pixels; the height counts one line per newline-separated line.
+    pub fn text_extent(&mut self, text: &str, face: Option<&Face>) -> (f32, f32) {
+        let attrs = self.face_to_attrs(face);
+        let font_size = face.map(|f| f.font_size).unwrap_or(self.default_font_size);
+        let line_height = font_size * 1.3;
+        let mut buffer = Buffer::new(&mut self.font_system, Metrics::new(font_size, line_height));
+        buffer.set_size(&mut self.font_system, None, None);
+        buffer.set_text(&mut self.font_system, text, attrs, cosmic_text::Shaping::Advanced);
+        buffer.shape_until_scroll(&mut self.font_system, false);
+
+        let mut width: f32 = 0.0;
+        let mut lines = 0;
+        for run in buffer.layout_runs() {
+            width = width.max(run.line_w);
+            lines += 1;
+        }
+        (width, lines.max(1) as f32 * line_height)
+    }
+
     /// Get a cached glyph without creating it
     ///
     /// Returns a reference to the cached glyph if it exists.
@@ -615,10 +709,96 @@ impl WgpuGlyphAtlas {
         self.cache.get(key)
     }
 
+    /// Evict least-recently-used glyphs: a quarter of the cache, and
+    /// enough to fit INCOMING more bytes within three quarters of the
+    /// memory budget.  Glyphs used in the current frame are kept.
+    fn evict_lru(&mut self, incoming: usize) {
+        let mut entries: Vec<(u64, Option<GlyphKey>, Option<ComposedGlyphKey>)> = self.cache.iter()
+            .filter(|(_, v)| v.last_accessed != self.generation)
+            .map(|(k, v)| (v.last_accessed, Some(k.clone()), None))
+            .chain(self.composed_cache.iter()
+                .filter(|(_, v)| v.last_accessed != self.generation)
+                .map(|(k, v)| (v.last_accessed, None, Some(k.clone()))))
+            .collect();
+        entries.sort_by_key(|(gen, _, _)| *gen);
+
+        let target = (self.max_memory / 4 * 3).saturating_sub(incoming);
+        let mut evict_count = if self.cache.len() >= self.max_size { self.max_size / 4 } else { 0 };
+        for (_, key, composed_key) in entries {
+            if evict_count == 0 && self.memory_bytes <= target {
+                break;
+            }
+            let removed = match (key, composed_key) {
+                (Some(k), _) => self.cache.remove(&k),
+                (_, Some(k)) => self.composed_cache.remove(&k),
+                _ => None,
+            };
+            if let Some(glyph) = removed {
+                self.memory_bytes -= glyph.memory_size();
+                self.evictions += 1;
+            }
+            evict_count = evict_count.saturating_sub(1);
+        }
+    }
+
+    /// Replace the registered icons with ICONS, dropping the cached
+    /// glyphs of codepoints whose icon changed
+    pub fn set_icons(&mut self, icons: IconRegistry) {
+        let changed: HashSet<u32> = self.icons_changed(&icons).into_iter().map(|c| c as u32).collect();
+        self.icons = icons;
+        if changed.is_empty() {
+            return;
+        }
+        let mut freed = 0;
+        self.cache.retain(|key, glyph| {
+            let keep = !changed.contains(&key.charcode);
+            if !keep {
+                freed += glyph.memory_size();
+            }
+            keep
+        });
+        self.memory_bytes = self.memory_bytes.saturating_sub(freed);
+    }
+
+    /// Codepoints drawn differently under ICONS than now
+    fn icons_changed(&self, icons: &IconRegistry) -> Vec<char> {
+        let mut changed: Vec<char> = Vec::new();
+        for c in self.icons.codepoints().chain(icons.codepoints()) {
+            if self.icons.get(c) != icons.get(c) && !changed.contains(&c) {
+                changed.push(c);
+            }
+        }
+        changed
+    }
+
+    /// Registered icons
+    pub fn icons(&self) -> &IconRegistry {
+        &self.icons
+    }
+
     /// Clear the cache
     pub fn clear(&mut self) {
         self.cache.clear();
         self.composed_cache.clear();
+        self.memory_bytes = 0;
+    }
+
+    /// Texture memory of cached glyphs, in bytes
+    pub fn memory_usage(&self) -> usize {
+        self.memory_bytes
+    }
+
+    /// Number of glyphs evicted so far
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Set the memory budget for cached glyphs, in bytes
+    pub fn set_memory_budget(&mut self, bytes: usize) {
+        self.max_memory = bytes;
+        if self.memory_bytes > bytes {
+            self.evict_lru(0);
+        }
     }
 
     /// Update the scale factor and clear the cache so glyphs are
@@ -626,8 +806,7 @@ impl WgpuGlyphAtlas {
     pub fn set_scale_factor(&mut self, scale_factor: f32) {
         if (self.scale_factor - scale_factor).abs() > 0.001 {
             self.scale_factor = scale_factor;
-            self.cache.clear();
-            self.composed_cache.clear();
+            self.clear();
             log::info!("Glyph atlas: scale factor -> {}, cache cleared", scale_factor);
         }
     }
@@ -674,7 +853,15 @@ impl WgpuGlyphAtlas {
         // which generate more composed cache entries per frame.
         if self.composed_cache.len() > 1024 {
             let cutoff = self.generation.saturating_sub(60);
-            self.composed_cache.retain(|_, v| v.last_accessed >= cutoff);
+            let mut freed = 0;
+            self.composed_cache.retain(|_, v| {
+                let keep = v.last_accessed >= cutoff;
+                if !keep {
+                    freed += v.memory_size();
+                }
+                keep
+            });
+            self.memory_bytes -= freed;
         }
     }
 }
@@ -0,0 +1,479 @@
+//! Rasterized glyphs kept on disk across sessions.
+//!
+//! Rasterizing every glyph of the first frame makes startup visibly
+//! slow with large fonts or many faces.  The glyph atlas therefore
+//! stores each glyph it rasterizes here, keyed by what determines its
+//! pixels (text, font family, weight, slant, size and display scale),
+//! and writes the cache to a file on exit.  The next session maps that
+//! file into memory and uploads glyphs straight from it, so a warm start
+//! draws its first frame without rasterizing anything.
+//!
+//! The file starts with a fingerprint of the installed fonts; when fonts
+//! are added, removed or updated the whole cache is discarded rather
+//! than risk stale pixels.  Glyphs used in the session are written
+//! first, so when the file reaches `MAX_FILE_BYTES` the ones dropped are
+//! those not used for the longest.
+//!
+//! Layout (little endian): magic, version, fingerprint, entry count,
+//! then the entries' keys and metrics with offsets into the pixel data
+//! that follows them.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::core::face::{Face, FaceAttributes};
+
+const MAGIC: &[u8; 8] = b"NMGLYPH\0";
+const VERSION: u32 = 1;
+
+/// Largest cache file written
+pub const MAX_FILE_BYTES: usize = 64 * 1024 * 1024;
+
+/// What determines the pixels of a rasterized glyph
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct RasterKey {
+    /// Character or grapheme cluster
+    pub text: String,
+    /// Font family as given by the face ("" for the default monospace)
+    pub family: String,
+    pub weight: u16,
+    pub italic: bool,
+    /// Font size and display scale factor, as f32 bits
+    pub size_bits: u32,
+    pub scale_bits: u32,
+}
+
+impl RasterKey {
+    /// Key of TEXT rasterized in FACE (or the default font at
+    /// DEFAULT_SIZE) at SCALE_FACTOR.
+    pub fn new(text: &str, face: Option<&Face>, default_size: f32, scale_factor: f32) -> Self {
+        Self {
+            text: text.to_string(),
+            family: face.map(|f| f.font_family.clone()).unwrap_or_default(),
+            weight: face.map_or(0, |f| f.font_weight),
+            italic: face.is_some_and(|f| f.attributes.contains(FaceAttributes::ITALIC)),
+            size_bits: face.map_or(default_size, |f| f.font_size).to_bits(),
+            scale_bits: scale_factor.to_bits(),
+        }
+    }
+}
+
+/// Hash of the font faces in DB, which changes when fonts are added,
+/// removed or replaced by other versions
+pub fn font_fingerprint(db: &cosmic_text::fontdb::Database) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut faces: Vec<_> = db
+        .faces()
+        .map(|f| {
+            let path = match &f.source {
+                cosmic_text::fontdb::Source::File(path)
+                | cosmic_text::fontdb::Source::SharedFile(path, _) => {
+                    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+                    Some((path.clone(), modified))
+                }
+                cosmic_text::fontdb::Source::Binary(_) => None,
+            };
+            (f.post_script_name.clone(), f.index, path)
+        })
+        .collect();
+    faces.sort();
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    faces.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Metrics of a stored glyph
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RasterMetrics {
+    pub width: u32,
+    pub height: u32,
+    pub bearing_x: f32,
+    pub bearing_y: f32,
+    pub is_color: bool,
+}
+
+/// Where a glyph's pixels are
+#[derive(Debug, Clone)]
+enum Pixels {
+    /// Range of the mapped file
+    Stored(usize, usize),
+    /// Rasterized this session
+    New(Vec<u8>),
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    metrics: RasterMetrics,
+    pixels: Pixels,
+    /// Looked up or rasterized this session
+    used: bool,
+}
+
+/// The bytes of the cache file: mapped when possible
+enum FileBytes {
+    #[cfg(unix)]
+    Mapped { ptr: *mut libc::c_void, len: usize },
+    Read(Vec<u8>),
+}
+
+// The mapping is read-only and owned by this value.
+unsafe impl Send for FileBytes {}
+
+impl FileBytes {
+    fn open(path: &Path) -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            let file = fs::File::open(path)?;
+            let len = file.metadata()?.len() as usize;
+            if len == 0 {
+                return Ok(Self::Read(Vec::new()));
+            }
+            // SAFETY: mapping a regular file read-only and private; the
+            // cache is replaced by rename, never written in place.
+            let ptr = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    libc::PROT_READ,
+                    libc::MAP_PRIVATE,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if ptr == libc::MAP_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            // Start reading the pages in now, not when the first frame
+            // needs them.
+            // SAFETY: advice on the mapping just created.
+            unsafe {
+                libc::madvise(ptr, len, libc::MADV_WILLNEED);
+            }
+            Ok(Self::Mapped { ptr, len })
+        }
+        #[cfg(not(unix))]
+        {
+            fs::read(path).map(Self::Read)
+        }
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self {
+            #[cfg(unix)]
+            // SAFETY: PTR maps LEN readable bytes until dropped.
+            Self::Mapped { ptr, len } => unsafe { std::slice::from_raw_parts(*ptr as *const u8, *len) },
+            Self::Read(bytes) => bytes,
+        }
+    }
+}
+
+impl Drop for FileBytes {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Self::Mapped { ptr, len } = *self {
+            // SAFETY: unmapping the mapping created in `open`.
+            unsafe {
+                libc::munmap(ptr, len);
+            }
+        }
+    }
+}
+
+/// Reads little-endian fields from a byte slice, failing past its end
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(slice)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take(8).map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+}
+
+/// Parse the index of a cache file written for FINGERPRINT.  None if
+/// the file is from another version or font set, or is damaged.
+fn parse_index(bytes: &[u8], fingerprint: u64) -> Option<HashMap<RasterKey, Entry>> {
+    let mut r = Reader { bytes, pos: 0 };
+    if r.take(MAGIC.len())? != MAGIC || r.u32()? != VERSION || r.u64()? != fingerprint {
+        return None;
+    }
+    let count = r.u32()? as usize;
+    let mut entries = Vec::with_capacity(count.min(1 << 16));
+    for _ in 0..count {
+        let key = RasterKey {
+            text: r.string()?,
+            family: r.string()?,
+            weight: r.u16()?,
+            italic: r.u8()? != 0,
+            size_bits: r.u32()?,
+            scale_bits: r.u32()?,
+        };
+        let metrics = RasterMetrics {
+            width: r.u32()?,
+            height: r.u32()?,
+            bearing_x: f32::from_bits(r.u32()?),
+            bearing_y: f32::from_bits(r.u32()?),
+            is_color: r.u8()? != 0,
+        };
+        let offset = r.u64()? as usize;
+        let len = r.u32()? as usize;
+        entries.push((key, metrics, offset, len));
+    }
+    let data_start = r.pos;
+    let mut index = HashMap::with_capacity(entries.len());
+    for (key, metrics, offset, len) in entries {
+        let start = data_start.checked_add(offset)?;
+        let expected = metrics.width as usize * metrics.height as usize * if metrics.is_color { 4 } else { 1 };
+        if len != expected || start.checked_add(len)? > bytes.len() {
+            return None;
+        }
+        index.insert(key, Entry { metrics, pixels: Pixels::Stored(start, len), used: false });
+    }
+    Some(index)
+}
+
+/// Glyph rasters of past sessions and this one
+pub struct GlyphDiskCache {
+    path: PathBuf,
+    fingerprint: u64,
+    file: Option<FileBytes>,
+    entries: HashMap<RasterKey, Entry>,
+    /// Whether anything would change in the file if saved now
+    dirty: bool,
+}
+
+impl GlyphDiskCache {
+    /// Open the cache at PATH for fonts with FINGERPRINT.  A missing,
+    /// outdated or damaged file gives an empty cache, replaced on save.
+    pub fn open(path: PathBuf, fingerprint: u64) -> Self {
+        let mut cache = Self { path, fingerprint, file: None, entries: HashMap::new(), dirty: false };
+        match FileBytes::open(&cache.path) {
+            Ok(file) => match parse_index(file.bytes(), fingerprint) {
+                Some(entries) => {
+                    log::info!("glyph cache: {} glyphs from {}", entries.len(), cache.path.display());
+                    cache.entries = entries;
+                    cache.file = Some(file);
+                }
+                None => {
+                    log::info!("glyph cache: {} is outdated, starting over", cache.path.display());
+                    cache.dirty = true;
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!("glyph cache: cannot read {}: {}", cache.path.display(), e),
+        }
+        cache
+    }
+
+    /// `$XDG_CACHE_HOME/neomacs/glyph-cache.bin`
+    pub fn default_path() -> PathBuf {
+        std::env::var_os("XDG_CACHE_HOME")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .unwrap_or_else(std::env::temp_dir)
+            .join("neomacs")
+            .join("glyph-cache.bin")
+    }
+
+    /// Metrics and pixels of the glyph for KEY, if stored
+    pub fn get(&mut self, key: &RasterKey) -> Option<(RasterMetrics, &[u8])> {
+        let entry = self.entries.get_mut(key)?;
+        entry.used = true;
+        let pixels = match &entry.pixels {
+            Pixels::Stored(start, len) => &self.file.as_ref()?.bytes()[*start..*start + *len],
+            Pixels::New(pixels) => pixels.as_slice(),
+        };
+        Some((entry.metrics, pixels))
+    }
+
+    /// Store the glyph rasterized for KEY
+    pub fn insert(&mut self, key: RasterKey, metrics: RasterMetrics, pixels: Vec<u8>) {
+        self.entries.insert(key, Entry { metrics, pixels: Pixels::New(pixels), used: true });
+        self.dirty = true;
+    }
+
+    /// Number of stored glyphs
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no glyph is stored
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Write the cache file if glyphs were added.  The file is replaced
+    /// atomically, so a concurrent session keeps reading its old copy.
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let stored = self.file.as_ref().map_or(&[][..], |f| f.bytes());
+        let mut order: Vec<(&RasterKey, &Entry)> = self.entries.iter().collect();
+        // Glyphs of this session first, so they survive the size limit
+        order.sort_by_key(|(_, e)| !e.used);
+
+        let mut index = Vec::new();
+        let mut data = Vec::new();
+        let mut count = 0u32;
+        for (key, entry) in order {
+            let pixels = match &entry.pixels {
+                Pixels::Stored(start, len) => &stored[*start..*start + *len],
+                Pixels::New(pixels) => pixels.as_slice(),
+            };
+            if key.text.len() > u16::MAX as usize || key.family.len() > u16::MAX as usize {
+                continue;
+            }
+            if index.len() + data.len() + pixels.len() + 128 > MAX_FILE_BYTES {
+                break;
+            }
+            for s in [&key.text, &key.family] {
+                index.extend_from_slice(&(s.len() as u16).to_le_bytes());
+                index.extend_from_slice(s.as_bytes());
+            }
+            index.extend_from_slice(&key.weight.to_le_bytes());
+            index.push(key.italic as u8);
+            index.extend_from_slice(&key.size_bits.to_le_bytes());
+            index.extend_from_slice(&key.scale_bits.to_le_bytes());
+            let m = entry.metrics;
+            index.extend_from_slice(&m.width.to_le_bytes());
+            index.extend_from_slice(&m.height.to_le_bytes());
+            index.extend_from_slice(&m.bearing_x.to_bits().to_le_bytes());
+            index.extend_from_slice(&m.bearing_y.to_bits().to_le_bytes());
+            index.push(m.is_color as u8);
+            index.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            index.extend_from_slice(&(pixels.len() as u32).to_le_bytes());
+            data.extend_from_slice(pixels);
+            count += 1;
+        }
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension(format!("tmp{}", std::process::id()));
+        let mut out = io::BufWriter::new(fs::File::create(&tmp)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&self.fingerprint.to_le_bytes())?;
+        out.write_all(&count.to_le_bytes())?;
+        out.write_all(&index)?;
+        out.write_all(&data)?;
+        out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        self.dirty = false;
+        log::info!("glyph cache: wrote {} glyphs to {}", count, self.path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("neomacs-glyph-cache-{}-{}", std::process::id(), name))
+            .join("glyph-cache.bin")
+    }
+
+    fn key(text: &str, size: f32) -> RasterKey {
+        RasterKey {
+            text: text.to_string(),
+            family: "Iosevka".to_string(),
+            weight: 400,
+            italic: false,
+            size_bits: size.to_bits(),
+            scale_bits: 1.0f32.to_bits(),
+        }
+    }
+
+    fn metrics(width: u32, height: u32, is_color: bool) -> RasterMetrics {
+        RasterMetrics { width, height, bearing_x: 1.0, bearing_y: 9.5, is_color }
+    }
+
+    #[test]
+    fn glyphs_survive_a_restart() {
+        let path = temp_path("restart");
+        let mut cache = GlyphDiskCache::open(path.clone(), 42);
+        assert!(cache.is_empty());
+        cache.insert(key("a", 14.0), metrics(2, 3, false), vec![1, 2, 3, 4, 5, 6]);
+        cache.insert(key("👍", 14.0), metrics(1, 1, true), vec![9, 8, 7, 6]);
+        cache.save().unwrap();
+
+        let mut warm = GlyphDiskCache::open(path.clone(), 42);
+        assert_eq!(warm.len(), 2);
+        assert_eq!(warm.get(&key("a", 14.0)), Some((metrics(2, 3, false), &[1, 2, 3, 4, 5, 6][..])));
+        assert_eq!(warm.get(&key("👍", 14.0)).map(|(m, p)| (m.is_color, p.len())), Some((true, 4)));
+        // Same text at another size is another glyph
+        assert!(warm.get(&key("a", 15.0)).is_none());
+
+        // Adding to a mapped cache keeps the stored glyphs
+        warm.insert(key("b", 14.0), metrics(1, 2, false), vec![7, 7]);
+        warm.save().unwrap();
+        let mut again = GlyphDiskCache::open(path.clone(), 42);
+        assert_eq!(again.len(), 3);
+        assert_eq!(again.get(&key("a", 14.0)).map(|(_, p)| p.to_vec()), Some(vec![1, 2, 3, 4, 5, 6]));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn other_fonts_or_damage_discard_the_cache() {
+        let path = temp_path("fingerprint");
+        let mut cache = GlyphDiskCache::open(path.clone(), 1);
+        cache.insert(key("a", 14.0), metrics(1, 1, false), vec![255]);
+        cache.save().unwrap();
+        assert!(GlyphDiskCache::open(path.clone(), 2).is_empty());
+
+        // Truncated pixel data
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(GlyphDiskCache::open(path.clone(), 1).is_empty());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn key_follows_face() {
+        let mut face = Face::new(3);
+        face.font_family = "Iosevka".to_string();
+        face.font_size = 16.0;
+        face.attributes |= FaceAttributes::ITALIC;
+        let k = RasterKey::new("x", Some(&face), 13.0, 2.0);
+        assert_eq!((k.family.as_str(), k.italic, f32::from_bits(k.size_bits)), ("Iosevka", true, 16.0));
+        // Face ids are not stable across sessions and are not part of it
+        let mut other = face.clone();
+        other.id = 99;
+        assert_eq!(RasterKey::new("x", Some(&other), 13.0, 2.0), k);
+        let default = RasterKey::new("x", None, 13.0, 2.0);
+        assert_eq!((default.family.as_str(), f32::from_bits(default.size_bits)), ("", 13.0));
+    }
+}
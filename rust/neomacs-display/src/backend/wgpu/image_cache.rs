@@ -4,9 +4,12 @@
 //! - Fast dimension query (header only)
 //! - Background decoding in thread pool
 //! - GPU texture upload when ready
-//! - LRU cache with memory limits
+//! - LRU cache with memory limits; evicted file/data images are decoded
+//!   again when next drawn
+//! - Non-destructive edits (see `core::image_edit`) applied while decoding
 
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
@@ -16,12 +19,20 @@ use std::thread;
 
 #[cfg(target_os = "linux")]
 use super::external_buffer::DmaBufBuffer;
+use super::media_budget::{MediaBudget, MediaType};
+use super::memory::{MemoryBudget, StagingPool};
+use crate::core::chart::{render_chart, ChartHotSpot, ChartSpec};
+use crate::core::image_edit::{ImageEdits, TextMask};
+use crate::core::text_contrast::LuminanceGrid;
+use cosmic_text::{Attrs, Buffer, Color, FontSystem, Metrics, Shaping, SwashCache};
+use once_cell::sync::Lazy;
 
 /// Maximum texture dimension (width or height)
 const MAX_TEXTURE_SIZE: u32 = 4096;
 
-/// Maximum total cache memory in bytes (64MB)
-const MAX_CACHE_MEMORY: usize = 64 * 1024 * 1024;
+/// Fonts for text annotations, shared by the decoder threads
+static ANNOTATION_FONTS: Lazy<Mutex<(FontSystem, SwashCache)>> =
+    Lazy::new(|| Mutex::new((FontSystem::new(), SwashCache::new())));
 
 /// Get number of decoder threads (use all available CPU cores)
 fn decoder_thread_count() -> usize {
@@ -39,6 +50,9 @@ pub enum ImageState {
     Decoding,
     /// Ready with texture
     Ready,
+    /// Texture evicted to stay within the memory budget; decoded again
+    /// when next requested
+    Evicted,
     /// Failed to load
     Failed(String),
 }
@@ -52,6 +66,9 @@ pub struct CachedImage {
     pub height: u32,
     /// Memory size in bytes
     pub memory_size: usize,
+    /// Luminance of the image's regions, for text drawn over it (None
+    /// for images imported straight into GPU memory)
+    pub luminance: Option<LuminanceGrid>,
 }
 
 /// Decoded image data waiting for GPU upload
@@ -60,6 +77,8 @@ struct DecodedImage {
     width: u32,
     height: u32,
     data: Vec<u8>, // RGBA
+    /// Edits applied to the pixels
+    edits: Option<Arc<ImageEdits>>,
 }
 
 /// Image dimensions (from header)
@@ -87,8 +106,20 @@ pub struct ImageCache {
     bind_group_layout: wgpu::BindGroupLayout,
     /// Sampler for image textures
     sampler: wgpu::Sampler,
-    /// Total cached memory
-    total_memory: usize,
+    /// LRU accounting of textures that can be decoded again
+    budget: MediaBudget,
+    /// Memory of textures that can't be evicted (raw pixels, DMA-BUF)
+    pinned_memory: usize,
+    /// Sources to decode evicted images from, with their max size
+    reload_sources: HashMap<u32, (ImageSource, u32, u32)>,
+    /// Edits of file/data images, applied whenever they are decoded
+    edits: HashMap<u32, Arc<ImageEdits>>,
+    /// Images requested since the last `process_pending`
+    accessed: RefCell<HashSet<u32>>,
+    /// Number of textures evicted so far
+    evictions: u64,
+    /// Buffers for raw pixel uploads, shared with the video cache
+    staging: StagingPool,
 }
 
 /// Request to decode an image
@@ -97,9 +128,11 @@ struct DecodeRequest {
     source: ImageSource,
     max_width: u32,
     max_height: u32,
+    edits: Option<Arc<ImageEdits>>,
 }
 
 /// Image source
+#[derive(Clone)]
 enum ImageSource {
     File(String),
     Data(Vec<u8>),
@@ -164,14 +197,17 @@ impl ImageCache {
         // Wrap receiver in Arc<Mutex> for sharing across threads
         let decode_rx = Arc::new(Mutex::new(decode_rx));
 
+        let staging = StagingPool::default();
+
         // Spawn decoder thread pool (one per CPU core)
         let num_threads = decoder_thread_count();
         log::info!("Starting {} image decoder threads", num_threads);
         for i in 0..num_threads {
             let rx = Arc::clone(&decode_rx);
             let tx = decoded_tx.clone();
+            let pool = staging.clone();
             thread::spawn(move || {
-                Self::decoder_thread_pooled(i, rx, tx);
+                Self::decoder_thread_pooled(i, rx, tx, pool);
             });
         }
 
@@ -184,7 +220,13 @@ impl ImageCache {
             decode_tx,
             bind_group_layout,
             sampler,
-            total_memory: 0,
+            budget: MediaBudget::with_limit(MemoryBudget::default().images),
+            pinned_memory: 0,
+            reload_sources: HashMap::new(),
+            edits: HashMap::new(),
+            accessed: RefCell::new(HashSet::new()),
+            evictions: 0,
+            staging,
         }
     }
 
@@ -193,6 +235,7 @@ impl ImageCache {
         thread_id: usize,
         rx: Arc<Mutex<mpsc::Receiver<DecodeRequest>>>,
         tx: mpsc::Sender<DecodedImage>,
+        pool: StagingPool,
     ) {
         log::debug!("Decoder thread {} started", thread_id);
         loop {
@@ -213,19 +256,33 @@ impl ImageCache {
                             Self::decode_data(&data, request.max_width, request.max_height)
                         }
                         ImageSource::RawArgb32 { data, width, height, stride } => {
-                            Self::convert_argb32_to_rgba(&data, width, height, stride, request.max_width, request.max_height)
+                            let result = Self::convert_argb32_to_rgba(&data, width, height, stride, request.max_width, request.max_height);
+                            pool.give(data);
+                            result
                         }
                         ImageSource::RawRgb24 { data, width, height, stride } => {
-                            Self::convert_rgb24_to_rgba(&data, width, height, stride, request.max_width, request.max_height)
+                            let result = Self::convert_rgb24_to_rgba(&data, width, height, stride, request.max_width, request.max_height);
+                            pool.give(data);
+                            result
                         }
                     };
 
+                    let result = match (result, &request.edits) {
+                        (Some((width, height, data)), Some(edits)) => {
+                            let edited = edits.apply(width, height, &data, &mut Self::render_text_mask);
+                            pool.give(data);
+                            Some(edited)
+                        }
+                        (result, _) => result,
+                    };
+
                     if let Some((width, height, data)) = result {
                         let _ = tx.send(DecodedImage {
                             id: request.id,
                             width,
                             height,
                             data,
+                            edits: request.edits,
                         });
                     }
                 }
@@ -240,7 +297,10 @@ impl ImageCache {
 
     /// Decode image file with size constraints
     fn decode_file(path: &str, max_width: u32, max_height: u32) -> Option<(u32, u32, Vec<u8>)> {
-        let img = image::open(path).ok()?;
+        let _span = crate::logging::span("image-file");
+        let img = image::open(path)
+            .map_err(|e| log::warn!("Cannot load image {}: {}", path, e))
+            .ok()?;
         Self::process_image(img, max_width, max_height)
     }
 
@@ -287,6 +347,37 @@ impl ImageCache {
         Some((width, height, rgba.into_raw()))
     }
 
+    /// Render TEXT at SIZE pixels as a coverage mask, for text annotations
+    fn render_text_mask(text: &str, size: f32) -> Option<TextMask> {
+        let mut fonts = ANNOTATION_FONTS.lock().unwrap_or_else(|e| e.into_inner());
+        let (font_system, cache) = &mut *fonts;
+        let mut buffer = Buffer::new(font_system, Metrics::new(size, size * 1.2));
+        buffer.set_size(font_system, None, None);
+        buffer.set_text(font_system, text, Attrs::new(), Shaping::Advanced);
+        buffer.shape_until_scroll(font_system, false);
+
+        let (mut width, mut height) = (0.0f32, 0.0f32);
+        for run in buffer.layout_runs() {
+            width = width.max(run.line_w);
+            height = height.max(run.line_top + run.line_height);
+        }
+        let (width, height) = (width.ceil() as u32, height.ceil() as u32);
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let mut alpha = vec![0u8; (width * height) as usize];
+        buffer.draw(font_system, cache, Color::rgb(255, 255, 255), |x, y, w, h, color| {
+            for py in y.max(0)..(y + h as i32).min(height as i32) {
+                for px in x.max(0)..(x + w as i32).min(width as i32) {
+                    let i = (py as u32 * width + px as u32) as usize;
+                    alpha[i] = alpha[i].max(color.a());
+                }
+            }
+        });
+        Some(TextMask { width, height, alpha })
+    }
+
     /// Convert ARGB32 raw pixel data to RGBA
     /// Input format: A,R,G,B byte order (4 bytes per pixel)
     /// Output format: R,G,B,A byte order (4 bytes per pixel)
@@ -451,16 +542,88 @@ impl ImageCache {
             self.pending_dimensions.insert(id, ImageDimensions { width: w, height: h });
         }
 
-        // Queue for async decode
+        self.queue_reloadable(id, ImageSource::File(path.to_string()), max_width, max_height);
+    }
+
+    /// Queue a file or data image for decoding, remembering its source so
+    /// the image can be decoded again after eviction.
+    fn queue_reloadable(&mut self, id: u32, source: ImageSource, max_width: u32, max_height: u32) {
         self.states.insert(id, ImageState::Pending);
+        self.reload_sources.insert(id, (source.clone(), max_width, max_height));
         let _ = self.decode_tx.send(DecodeRequest {
             id,
-            source: ImageSource::File(path.to_string()),
+            source,
             max_width,
             max_height,
+            edits: self.edits.get(&id).cloned(),
         });
     }
 
+    /// Set the edits of image ID (None to undo them all) and decode the
+    /// image again with them.  Only file and data images can be edited.
+    /// Returns the dimensions the image will have, or None if ID can't be
+    /// edited.
+    pub fn set_edits(&mut self, id: u32, edits: Option<ImageEdits>) -> Option<ImageDimensions> {
+        let (source, max_width, max_height) = self.reload_sources.get(&id)?.clone();
+        match edits.filter(|e| !e.is_identity()) {
+            Some(edits) => self.edits.insert(id, Arc::new(edits)),
+            None => self.edits.remove(&id),
+        };
+
+        let dims = match &source {
+            ImageSource::File(path) => Self::query_file_dimensions(path),
+            ImageSource::Data(data) => Self::query_data_dimensions(data),
+            _ => None,
+        }?;
+        let (w, h) = Self::constrain_dimensions(dims.width, dims.height, max_width, max_height);
+        let (w, h) = match self.edits.get(&id) {
+            Some(edits) => edits.output_size(w, h),
+            None => (w, h),
+        };
+        let dims = ImageDimensions { width: w, height: h };
+        // The current texture stays up until the edited one replaces it
+        self.pending_dimensions.insert(id, dims);
+        self.queue_reloadable(id, source, max_width, max_height);
+        Some(dims)
+    }
+
+    /// Decode the file at SOURCE at full size, apply EDITS and save the
+    /// result as a PNG file at OUTPUT.  Returns the saved image's size.
+    pub fn export_edited(source: &str, edits: &ImageEdits, output: &str) -> Result<ImageDimensions, String> {
+        let img = image::open(source).map_err(|e| format!("{}: {}", source, e))?;
+        let rgba = img.to_rgba8();
+        let (width, height, data) =
+            edits.apply(rgba.width(), rgba.height(), rgba.as_raw(), &mut Self::render_text_mask);
+        image::save_buffer_with_format(
+            output,
+            &data,
+            width,
+            height,
+            image::ColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+        .map_err(|e| format!("{}: {}", output, e))?;
+        Ok(ImageDimensions { width, height })
+    }
+
+    /// Draw the chart SPEC and encode it as PNG.  Returns the PNG data and
+    /// the area of each x position of the chart.
+    pub fn render_chart_png(spec: &ChartSpec) -> Result<(Vec<u8>, Vec<ChartHotSpot>), String> {
+        let chart = render_chart(spec, &mut Self::render_text_mask);
+        let mut png = Vec::new();
+        let encoder = image::codecs::png::PngEncoder::new(&mut png);
+        image::ImageEncoder::write_image(encoder, &chart.rgba, chart.width, chart.height, image::ColorType::Rgba8)
+            .map_err(|e| format!("chart: {}", e))?;
+        Ok((png, chart.hot_spots))
+    }
+
+    /// Copy raw pixel DATA into a pooled buffer
+    fn staged_copy(&self, data: &[u8]) -> Vec<u8> {
+        let mut buf = self.staging.take(data.len());
+        buf.extend_from_slice(data);
+        buf
+    }
+
     /// Allocate the next available image ID without loading anything.
     /// Used by threaded mode to pre-allocate IDs before sending commands.
     pub fn allocate_id(&self) -> u32 {
@@ -477,14 +640,7 @@ impl ImageCache {
             self.pending_dimensions.insert(id, ImageDimensions { width: w, height: h });
         }
 
-        // Queue for async decode
-        self.states.insert(id, ImageState::Pending);
-        let _ = self.decode_tx.send(DecodeRequest {
-            id,
-            source: ImageSource::Data(data.to_vec()),
-            max_width,
-            max_height,
-        });
+        self.queue_reloadable(id, ImageSource::Data(data.to_vec()), max_width, max_height);
 
         id
     }
@@ -513,13 +669,14 @@ impl ImageCache {
         let _ = self.decode_tx.send(DecodeRequest {
             id,
             source: ImageSource::RawArgb32 {
-                data: data.to_vec(),
+                data: self.staged_copy(data),
                 width,
                 height,
                 stride,
             },
             max_width,
             max_height,
+            edits: None,
         });
 
         id
@@ -549,13 +706,14 @@ impl ImageCache {
         let _ = self.decode_tx.send(DecodeRequest {
             id,
             source: ImageSource::RawRgb24 {
-                data: data.to_vec(),
+                data: self.staged_copy(data),
                 width,
                 height,
                 stride,
             },
             max_width,
             max_height,
+            edits: None,
         });
 
         id
@@ -576,13 +734,14 @@ impl ImageCache {
         let _ = self.decode_tx.send(DecodeRequest {
             id,
             source: ImageSource::RawArgb32 {
-                data: data.to_vec(),
+                data: self.staged_copy(data),
                 width,
                 height,
                 stride,
             },
             max_width: 0,
             max_height: 0,
+            edits: None,
         });
     }
 
@@ -601,13 +760,14 @@ impl ImageCache {
         let _ = self.decode_tx.send(DecodeRequest {
             id,
             source: ImageSource::RawRgb24 {
-                data: data.to_vec(),
+                data: self.staged_copy(data),
                 width,
                 height,
                 stride,
             },
             max_width: 0,
             max_height: 0,
+            edits: None,
         });
     }
 
@@ -641,7 +801,7 @@ impl ImageCache {
             });
 
             let memory_size = (width * height * 4) as usize;
-            self.total_memory += memory_size;
+            self.pinned_memory += memory_size;
 
             self.textures.insert(id, CachedImage {
                 texture,
@@ -650,6 +810,7 @@ impl ImageCache {
                 width,
                 height,
                 memory_size,
+                luminance: None,
             });
             self.states.insert(id, ImageState::Ready);
 
@@ -689,12 +850,45 @@ impl ImageCache {
             self.upload_texture(device, queue, decoded);
         }
 
+        // Mark images drawn since the last call as recently used, and
+        // decode evicted ones again
+        let accessed = self.accessed.take();
+        for &id in &accessed {
+            if self.textures.contains_key(&id) {
+                self.budget.touch(MediaType::Image, id);
+            } else if matches!(self.states.get(&id), Some(ImageState::Evicted)) {
+                if let Some((source, max_width, max_height)) = self.reload_sources.get(&id) {
+                    log::debug!("Reloading evicted image {}", id);
+                    self.states.insert(id, ImageState::Pending);
+                    let _ = self.decode_tx.send(DecodeRequest {
+                        id,
+                        source: source.clone(),
+                        max_width: *max_width,
+                        max_height: *max_height,
+                        edits: self.edits.get(&id).cloned(),
+                    });
+                }
+            }
+        }
+
         // Evict if over memory limit
-        self.evict_if_needed();
+        self.evict_if_needed(&accessed);
     }
 
     /// Upload decoded image to GPU texture
     fn upload_texture(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, decoded: DecodedImage) {
+        // Drop decodes made before the image's edits last changed
+        let current = self.edits.get(&decoded.id);
+        let stale = match (&decoded.edits, current) {
+            (Some(a), Some(b)) => !Arc::ptr_eq(a, b),
+            (None, None) => false,
+            _ => true,
+        };
+        if stale {
+            self.staging.give(decoded.data);
+            return;
+        }
+
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("Image Texture"),
             size: wgpu::Extent3d {
@@ -748,7 +942,14 @@ impl ImageCache {
         });
 
         let memory_size = (decoded.width * decoded.height * 4) as usize;
-        self.total_memory += memory_size;
+        let luminance = LuminanceGrid::from_rgba(&decoded.data, decoded.width, decoded.height);
+        self.forget_texture(decoded.id);
+        if self.reload_sources.contains_key(&decoded.id) {
+            self.budget.register(MediaType::Image, decoded.id, memory_size);
+        } else {
+            self.pinned_memory += memory_size;
+        }
+        self.staging.give(decoded.data);
 
         self.textures.insert(decoded.id, CachedImage {
             texture,
@@ -757,6 +958,7 @@ impl ImageCache {
             width: decoded.width,
             height: decoded.height,
             memory_size,
+            luminance,
         });
 
         self.states.insert(decoded.id, ImageState::Ready);
@@ -766,37 +968,95 @@ impl ImageCache {
                    decoded.id, decoded.width, decoded.height, memory_size / 1024);
     }
 
-    /// Evict old textures if over memory limit
-    fn evict_if_needed(&mut self) {
-        // Simple strategy: remove oldest entries until under limit
-        while self.total_memory > MAX_CACHE_MEMORY && !self.textures.is_empty() {
-            // Find smallest ID (oldest)
-            if let Some(&id) = self.textures.keys().min() {
-                if let Some(cached) = self.textures.remove(&id) {
-                    self.total_memory -= cached.memory_size;
-                    self.states.remove(&id);
-                    log::debug!("Evicted image {} to free {}KB", id, cached.memory_size / 1024);
-                }
+    /// Remove the texture of image ID and its memory accounting
+    fn forget_texture(&mut self, id: u32) -> Option<CachedImage> {
+        let cached = self.textures.remove(&id)?;
+        if self.reload_sources.contains_key(&id) {
+            self.budget.unregister(MediaType::Image, id);
+        } else {
+            self.pinned_memory = self.pinned_memory.saturating_sub(cached.memory_size);
+        }
+        Some(cached)
+    }
+
+    /// Evict least recently used textures while over the memory budget.
+    /// Images drawn since the last frame (IN_USE) are kept, even if that
+    /// leaves the cache over budget.
+    fn evict_if_needed(&mut self, in_use: &HashSet<u32>) {
+        if !self.budget.is_over_budget() {
+            return;
+        }
+        let candidates = self.budget.get_eviction_candidates(0);
+        for (_, id) in candidates {
+            if !self.budget.is_over_budget() {
+                break;
+            }
+            if in_use.contains(&id) {
+                continue;
+            }
+            if let Some(cached) = self.forget_texture(id) {
+                self.states.insert(id, ImageState::Evicted);
+                self.pending_dimensions.insert(id, ImageDimensions {
+                    width: cached.width,
+                    height: cached.height,
+                });
+                self.evictions += 1;
+                log::debug!("Evicted image {} to free {}KB", id, cached.memory_size / 1024);
             }
         }
     }
 
-    /// Get cached image if ready
+    /// Get cached image if ready.  Records the access for LRU eviction;
+    /// requesting an evicted image queues it for decoding again.
     pub fn get(&self, id: u32) -> Option<&CachedImage> {
+        self.accessed.borrow_mut().insert(id);
         self.textures.get(&id)
     }
 
+    /// Set the memory budget for evictable image textures, in bytes
+    pub fn set_memory_budget(&mut self, bytes: usize) {
+        self.budget.set_limit(bytes);
+        self.evict_if_needed(&HashSet::new());
+    }
+
+    /// Total texture memory in bytes
+    pub fn total_memory(&self) -> usize {
+        self.budget.current_usage() + self.pinned_memory
+    }
+
+    /// Number of textures currently uploaded
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    /// True if no textures are uploaded
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    /// Number of textures evicted so far
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Pool of upload buffers, for sharing with other caches
+    pub fn staging_pool(&self) -> &StagingPool {
+        &self.staging
+    }
+
     /// Get image dimensions (pending or loaded)
     pub fn get_dimensions(&self, id: u32) -> Option<ImageDimensions> {
-        // Check loaded textures first
+        // Dimensions of an edit being decoded win over the old texture
+        if let Some(dims) = self.pending_dimensions.get(&id) {
+            return Some(*dims);
+        }
         if let Some(cached) = self.textures.get(&id) {
             return Some(ImageDimensions {
                 width: cached.width,
                 height: cached.height,
             });
         }
-        // Check pending dimensions
-        self.pending_dimensions.get(&id).copied()
+        None
     }
 
     /// Get image state
@@ -811,9 +1071,9 @@ impl ImageCache {
 
     /// Free an image from cache
     pub fn free(&mut self, id: u32) {
-        if let Some(cached) = self.textures.remove(&id) {
-            self.total_memory -= cached.memory_size;
-        }
+        self.forget_texture(id);
+        self.reload_sources.remove(&id);
+        self.edits.remove(&id);
         self.states.remove(&id);
         self.pending_dimensions.remove(&id);
     }
@@ -823,7 +1083,10 @@ impl ImageCache {
         self.textures.clear();
         self.states.clear();
         self.pending_dimensions.clear();
-        self.total_memory = 0;
+        self.reload_sources.clear();
+        self.edits.clear();
+        self.budget = MediaBudget::with_limit(self.budget.max_limit());
+        self.pinned_memory = 0;
     }
 }
 
@@ -138,6 +138,12 @@ impl MediaBudget {
     pub fn max_limit(&self) -> usize {
         self.max_memory
     }
+
+    /// Change the memory limit; entries over it are left for the caller
+    /// to evict via `get_eviction_candidates`
+    pub fn set_limit(&mut self, max_memory: usize) {
+        self.max_memory = max_memory;
+    }
 }
 
 impl Default for MediaBudget {
@@ -176,6 +182,16 @@ mod tests {
         assert_eq!(budget.current_usage(), 0);
     }
 
+    #[test]
+    fn test_set_limit() {
+        let mut budget = MediaBudget::with_limit(1000);
+        budget.register(MediaType::Image, 1, 600);
+        budget.set_limit(500);
+        assert_eq!(budget.max_limit(), 500);
+        assert!(budget.is_over_budget());
+        assert_eq!(budget.get_eviction_candidates(0), vec![(MediaType::Image, 1)]);
+    }
+
     #[test]
     fn test_zero_budget_limit() {
         let budget = MediaBudget::with_limit(0);
@@ -0,0 +1,223 @@
+//! GPU memory management: byte budgets for the renderer's caches, a pool
+//! of reusable staging buffers for texture uploads, and usage statistics.
+//!
+//! Each cache enforces its own budget with LRU eviction (images through
+//! `MediaBudget`, glyphs by access generation, transition snapshots by
+//! age); the render thread collects their usage into `GpuMemoryStats`.
+
+use std::sync::{Arc, Mutex};
+
+const MB: usize = 1024 * 1024;
+
+/// Byte budgets for the renderer's caches
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryBudget {
+    /// Image textures
+    pub images: usize,
+    /// Glyph textures (single and composed glyphs)
+    pub glyphs: usize,
+    /// Window snapshots held by running transitions
+    pub snapshots: usize,
+    /// Upload buffers kept around for reuse (video frames, images)
+    pub staging: usize,
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        Self {
+            images: 64 * MB,
+            glyphs: 32 * MB,
+            snapshots: 128 * MB,
+            staging: 64 * MB,
+        }
+    }
+}
+
+impl MemoryBudget {
+    /// Budgets given in megabytes (at least 1MB each).
+    pub fn from_mb(images: u32, glyphs: u32, snapshots: u32, staging: u32) -> Self {
+        let bytes = |mb: u32| (mb.max(1) as usize) * MB;
+        Self {
+            images: bytes(images),
+            glyphs: bytes(glyphs),
+            snapshots: bytes(snapshots),
+            staging: bytes(staging),
+        }
+    }
+}
+
+/// Memory usage of the renderer, refreshed by the render thread each frame.
+/// Sizes are estimates from texture dimensions and formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GpuMemoryStats {
+    pub image_bytes: usize,
+    pub image_count: usize,
+    pub image_evictions: u64,
+    pub glyph_bytes: usize,
+    pub glyph_count: usize,
+    pub glyph_evictions: u64,
+    pub video_bytes: usize,
+    pub snapshot_bytes: usize,
+    pub snapshot_evictions: u64,
+    pub staging_bytes: usize,
+    pub budget: MemoryBudget,
+}
+
+impl GpuMemoryStats {
+    /// Total bytes in use across all caches
+    pub fn total_bytes(&self) -> usize {
+        self.image_bytes + self.glyph_bytes + self.video_bytes + self.snapshot_bytes + self.staging_bytes
+    }
+}
+
+/// Pool of CPU buffers reused for texture uploads, so steady streams of
+/// frames don't allocate a fresh buffer each time.  Cheap to clone; all
+/// clones share the same buffers.
+#[derive(Clone)]
+pub struct StagingPool {
+    inner: Arc<Mutex<PoolInner>>,
+}
+
+struct PoolInner {
+    free: Vec<Vec<u8>>,
+    pooled_bytes: usize,
+    limit: usize,
+}
+
+impl PoolInner {
+    /// Drop the oldest buffers until the pool holds at most LIMIT bytes
+    fn trim(&mut self, limit: usize) {
+        while self.pooled_bytes > limit && !self.free.is_empty() {
+            let buf = self.free.remove(0);
+            self.pooled_bytes -= buf.capacity();
+        }
+    }
+}
+
+impl Default for StagingPool {
+    fn default() -> Self {
+        Self::new(MemoryBudget::default().staging)
+    }
+}
+
+impl StagingPool {
+    /// Create a pool keeping at most LIMIT bytes of idle buffers
+    pub fn new(limit: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(PoolInner { free: Vec::new(), pooled_bytes: 0, limit })),
+        }
+    }
+
+    /// An empty buffer with room for at least LEN bytes, reusing the
+    /// smallest pooled buffer that fits.
+    pub fn take(&self, len: usize) -> Vec<u8> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let best = inner
+            .free
+            .iter()
+            .enumerate()
+            .filter(|(_, buf)| buf.capacity() >= len)
+            .min_by_key(|(_, buf)| buf.capacity())
+            .map(|(i, _)| i);
+        match best {
+            Some(i) => {
+                let mut buf = inner.free.swap_remove(i);
+                inner.pooled_bytes -= buf.capacity();
+                buf.clear();
+                buf
+            }
+            None => Vec::with_capacity(len),
+        }
+    }
+
+    /// Return BUF to the pool once its contents have been uploaded.
+    pub fn give(&self, buf: Vec<u8>) {
+        let size = buf.capacity();
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if size == 0 || size > inner.limit {
+            return;
+        }
+        let limit = inner.limit - size;
+        inner.trim(limit);
+        inner.pooled_bytes += size;
+        inner.free.push(buf);
+    }
+
+    /// Bytes held by idle buffers
+    pub fn pooled_bytes(&self) -> usize {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).pooled_bytes
+    }
+
+    /// Change the pool limit, releasing buffers over it
+    pub fn set_limit(&self, limit: usize) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.limit = limit;
+        inner.trim(limit);
+    }
+}
+
+/// Estimated size of a texture in bytes
+pub fn texture_bytes(texture: &wgpu::Texture) -> usize {
+    let size = texture.size();
+    let bytes_per_pixel = texture.format().block_copy_size(None).unwrap_or(4);
+    size.width as usize * size.height as usize * size.depth_or_array_layers as usize * bytes_per_pixel as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget_from_mb() {
+        let budget = MemoryBudget::from_mb(10, 0, 1, 2);
+        assert_eq!(budget.images, 10 * MB);
+        assert_eq!(budget.glyphs, MB);
+        assert_eq!(budget.snapshots, MB);
+        assert_eq!(budget.staging, 2 * MB);
+    }
+
+    #[test]
+    fn pool_reuses_smallest_fitting_buffer() {
+        let pool = StagingPool::new(1 << 20);
+        pool.give(Vec::with_capacity(1000));
+        pool.give(Vec::with_capacity(200));
+        assert_eq!(pool.pooled_bytes(), 1200);
+
+        let buf = pool.take(150);
+        assert_eq!(buf.capacity(), 200);
+        assert!(buf.is_empty());
+        assert_eq!(pool.pooled_bytes(), 1000);
+
+        // Nothing large enough: allocate fresh
+        assert_eq!(pool.take(5000).capacity(), 5000);
+        assert_eq!(pool.pooled_bytes(), 1000);
+    }
+
+    #[test]
+    fn pool_stays_within_limit() {
+        let pool = StagingPool::new(1000);
+        pool.give(Vec::with_capacity(600));
+        pool.give(Vec::with_capacity(600));
+        // The older buffer was dropped to make room
+        assert_eq!(pool.pooled_bytes(), 600);
+        // Buffers larger than the whole pool are not kept
+        pool.give(Vec::with_capacity(2000));
+        assert_eq!(pool.pooled_bytes(), 600);
+
+        pool.set_limit(100);
+        assert_eq!(pool.pooled_bytes(), 0);
+    }
+
+    #[test]
+    fn stats_total() {
+        let stats = GpuMemoryStats {
+            image_bytes: 1,
+            glyph_bytes: 2,
+            video_bytes: 4,
+            snapshot_bytes: 8,
+            staging_bytes: 16,
+            ..GpuMemoryStats::default()
+        };
+        assert_eq!(stats.total_bytes(), 31);
+    }
+}
@@ -20,7 +20,11 @@ mod va_dmabuf_export;
 #[cfg(feature = "video")]
 mod video_cache;
 
+pub mod glyph_disk_cache;
 pub mod media_budget;
+pub mod memory;
+pub mod monitors;
+pub mod thumbnail;
 
 #[cfg(feature = "video")]
 pub use video_cache::{VideoCache, CachedVideo, VideoState, DecodedFrame};
@@ -29,6 +33,7 @@ pub use renderer::WgpuRenderer;
 pub use backend::{WinitBackend, UserEvent, Callbacks, NeomacsApp, run_event_loop};
 pub use glyph_atlas::{WgpuGlyphAtlas, GlyphKey, CachedGlyph};
 pub use image_cache::{ImageCache, CachedImage, ImageDimensions, ImageState};
+pub use memory::{GpuMemoryStats, MemoryBudget, StagingPool};
 pub use vertex::GlyphVertex;
 
 pub use external_buffer::{ExternalBuffer, SharedMemoryBuffer, BufferFormat, PlatformBuffer};
@@ -51,6 +56,17 @@ pub use events::{
     NEOMACS_EVENT_MENU_SELECTION,
     NEOMACS_EVENT_FILE_DROP,
     NEOMACS_EVENT_TERMINAL_TITLE_CHANGED,
+    NEOMACS_EVENT_MONITORS_CHANGED,
+    NEOMACS_EVENT_CHAR_PICKER_SELECTION,
+    NEOMACS_EVENT_TERMINAL_BELL,
+    NEOMACS_EVENT_PINCH_ZOOM,
+    NEOMACS_EVENT_COMMAND_PALETTE_SELECTION,
+    NEOMACS_EVENT_COLOR_PICKER_SELECTION,
+    NEOMACS_EVENT_AGENDA_TIMELINE_SELECTION,
+    NEOMACS_EVENT_GRAPH_VIEW_SELECTION,
+    NEOMACS_EVENT_DIR_VIEW_SELECTION,
+    NEOMACS_EVENT_PROCESS_MONITOR_SELECTION,
+    NEOMACS_EVENT_DATE_PICKER_SELECTION,
 };
 
 #[cfg(all(feature = "wpe-webkit", target_os = "linux"))]
@@ -0,0 +1,33 @@
+//! Monitor descriptions from winit.
+
+use winit::monitor::MonitorHandle;
+
+use crate::core::monitor::{self, MonitorInfo};
+
+/// Describe MONITOR; PRIMARY is the platform's primary monitor, if known
+pub fn monitor_info(monitor: &MonitorHandle, primary: Option<&MonitorHandle>) -> MonitorInfo {
+    let pos = monitor.position();
+    let size = monitor.size();
+    let mut info = MonitorInfo::new(
+        pos.x,
+        pos.y,
+        size.width as i32,
+        size.height as i32,
+        monitor.scale_factor(),
+    );
+    info.refresh_mhz = monitor.refresh_rate_millihertz().unwrap_or(0);
+    info.primary = primary == Some(monitor);
+    info.name = monitor.name();
+    info
+}
+
+/// Describe MONITORS, primary first
+pub fn collect_monitors(
+    monitors: impl Iterator<Item = MonitorHandle>,
+    primary: Option<MonitorHandle>,
+) -> Vec<MonitorInfo> {
+    let mut list: Vec<MonitorInfo> =
+        monitors.map(|m| monitor_info(&m, primary.as_ref())).collect();
+    monitor::sort_primary_first(&mut list);
+    list
+}
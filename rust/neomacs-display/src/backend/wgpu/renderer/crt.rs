@@ -0,0 +1,174 @@
+//! CRT/retro post-processing effect for WgpuRenderer.
+//!
+//! Runs `crt.wgsl` as a pass of the post-processing chain (see
+//! `post_shader`): scanlines, barrel distortion, phosphor glow and
+//! chromatic aberration, applied to the whole frame or only to the
+//! windows showing a terminal.
+
+use bytemuck::{Pod, Zeroable};
+
+use super::post_shader::{post_bind_group_layout, PostPass};
+use super::WgpuRenderer;
+use crate::core::frame_glyphs::FrameGlyphBuffer;
+use crate::core::types::Rect;
+use crate::effect_config::CrtConfig;
+
+/// Maximum number of regions the shader handles (`MAX_REGIONS` in crt.wgsl).
+pub(super) const MAX_CRT_REGIONS: usize = 8;
+
+/// Uniform block matching `CrtUniforms` in crt.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub(super) struct CrtUniforms {
+    pub resolution: [f32; 2],
+    pub scale: f32,
+    pub region_count: u32,
+    pub params: [f32; 4],
+    pub regions: [[f32; 4]; MAX_CRT_REGIONS],
+}
+
+impl CrtUniforms {
+    /// Uniforms for a WIDTH x HEIGHT frame; REGIONS are in logical pixels.
+    pub(super) fn new(config: &CrtConfig, width: u32, height: u32, scale: f32, regions: &[Rect]) -> Self {
+        let mut packed = [[0.0; 4]; MAX_CRT_REGIONS];
+        for (slot, r) in packed.iter_mut().zip(regions) {
+            *slot = [r.x * scale, r.y * scale, r.width * scale, r.height * scale];
+        }
+        Self {
+            resolution: [width as f32, height as f32],
+            scale,
+            region_count: regions.len().min(MAX_CRT_REGIONS) as u32,
+            params: [
+                config.scanlines.clamp(0.0, 1.0),
+                config.curvature.clamp(0.0, 1.0),
+                config.glow.clamp(0.0, 1.0),
+                config.aberration.clamp(0.0, 1.0),
+            ],
+            regions: packed,
+        }
+    }
+}
+
+/// Regions of FRAME (logical pixels, SCREEN sized) the effect applies to:
+/// the whole screen, or with `terminals_only` the windows showing a
+/// terminal.  Empty when there is nothing to apply the effect to.
+#[cfg_attr(not(feature = "neo-term"), allow(unused_variables))]
+pub(super) fn crt_regions(config: &CrtConfig, frame: &FrameGlyphBuffer, screen: (f32, f32)) -> Vec<Rect> {
+    if !config.enabled {
+        return Vec::new();
+    }
+    if !config.terminals_only {
+        return vec![Rect::new(0.0, 0.0, screen.0, screen.1)];
+    }
+    #[allow(unused_mut)]
+    let mut regions: Vec<Rect> = Vec::new();
+    #[cfg(feature = "neo-term")]
+    for glyph in &frame.glyphs {
+        if let crate::core::frame_glyphs::FrameGlyph::Terminal { x, y, width, height, .. } = *glyph {
+            let region = frame
+                .window_infos
+                .iter()
+                .map(|info| info.bounds)
+                .find(|b| x >= b.x && x < b.x + b.width && y >= b.y && y < b.y + b.height)
+                .unwrap_or(Rect::new(x, y, width, height));
+            if !regions.contains(&region) && regions.len() < MAX_CRT_REGIONS {
+                regions.push(region);
+            }
+        }
+    }
+    regions
+}
+
+impl WgpuRenderer {
+    /// Pass running crt.wgsl, created on first use.
+    pub(super) fn crt_pass(&mut self) -> &PostPass {
+        if self.crt_pass.is_none() {
+            let layout = post_bind_group_layout(&self.device);
+            let module = self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("CRT Shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/crt.wgsl").into()),
+            });
+            let pipeline = self.create_post_pipeline(&module, "vs_main", "fs_main", &layout);
+            let uniform_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("CRT Uniforms"),
+                size: std::mem::size_of::<CrtUniforms>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            self.crt_pass = Some(PostPass { pipeline, bind_group_layout: layout, uniform_buffer });
+        }
+        self.crt_pass.as_ref().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled(terminals_only: bool) -> CrtConfig {
+        CrtConfig { enabled: true, terminals_only, ..CrtConfig::default() }
+    }
+
+    #[test]
+    fn disabled_effect_has_no_regions() {
+        let frame = FrameGlyphBuffer::default();
+        assert!(crt_regions(&CrtConfig::default(), &frame, (800.0, 600.0)).is_empty());
+    }
+
+    #[test]
+    fn whole_frame_region() {
+        let frame = FrameGlyphBuffer::default();
+        assert_eq!(
+            crt_regions(&enabled(false), &frame, (800.0, 600.0)),
+            vec![Rect::new(0.0, 0.0, 800.0, 600.0)]
+        );
+    }
+
+    #[test]
+    fn terminals_only_without_terminals_has_no_regions() {
+        let frame = FrameGlyphBuffer::default();
+        assert!(crt_regions(&enabled(true), &frame, (800.0, 600.0)).is_empty());
+    }
+
+    #[cfg(feature = "neo-term")]
+    #[test]
+    fn terminal_regions_cover_their_windows() {
+        use crate::core::frame_glyphs::{FrameGlyph, WindowInfo};
+        let mut frame = FrameGlyphBuffer::default();
+        frame.window_infos.push(WindowInfo {
+            window_id: 1,
+            buffer_id: 1,
+            window_start: 1,
+            window_end: 1,
+            buffer_size: 1,
+            bounds: Rect::new(400.0, 0.0, 400.0, 600.0),
+            mode_line_height: 0.0,
+            header_line_height: 0.0,
+            tab_line_height: 0.0,
+            selected: true,
+            is_minibuffer: false,
+            char_height: 16.0,
+            buffer_file_name: String::new(),
+            modified: false,
+        });
+        for y in [0.0, 16.0] {
+            frame.glyphs.push(FrameGlyph::Terminal { terminal_id: 1, x: 400.0, y, width: 400.0, height: 16.0 });
+        }
+        assert_eq!(
+            crt_regions(&enabled(true), &frame, (800.0, 600.0)),
+            vec![Rect::new(400.0, 0.0, 400.0, 600.0)]
+        );
+    }
+
+    #[test]
+    fn uniforms_scale_regions_and_clamp_params() {
+        let config = CrtConfig { scanlines: 2.0, ..CrtConfig::default() };
+        let regions = [Rect::new(10.0, 20.0, 30.0, 40.0)];
+        let u = CrtUniforms::new(&config, 1600, 1200, 2.0, &regions);
+        assert_eq!(u.region_count, 1);
+        assert_eq!(u.regions[0], [20.0, 40.0, 60.0, 80.0]);
+        assert_eq!(u.params[0], 1.0);
+        // Must match the WGSL struct layout
+        assert_eq!(std::mem::size_of::<CrtUniforms>(), 32 + 16 * MAX_CRT_REGIONS);
+    }
+}
@@ -8,7 +8,7 @@ use super::super::vertex::RectVertex;
 use super::effect_common::{EffectCtx, push_rect, find_cursor_pos};
 use super::{CursorParticle, MatrixColumn, RippleWaveEntry, SonarPingEntry, SparkleBurstEntry};
 use crate::core::types::Color;
-use crate::core::frame_glyphs::FrameGlyph;
+use crate::core::frame_glyphs::{FrameGlyph, WindowInfo};
 
 /// Emit cursor glow effect vertices.
 pub(super) fn emit_cursor_glow(
@@ -28,7 +28,7 @@ pub(super) fn emit_cursor_glow(
 
         // Apply pulse modulation if enabled
         if ctx.effects.cursor_pulse.enabled {
-            let elapsed = cursor_pulse_start.elapsed().as_secs_f32();
+            let elapsed = ctx.now.saturating_duration_since(*cursor_pulse_start).as_secs_f32();
             let phase = elapsed * ctx.effects.cursor_pulse.speed * 2.0 * std::f32::consts::PI;
             let t = (phase.sin() + 1.0) / 2.0;
             let factor = ctx.effects.cursor_pulse.min_opacity + t * (1.0 - ctx.effects.cursor_pulse.min_opacity);
@@ -109,7 +109,7 @@ pub(super) fn emit_cursor_magnetism(
     if !ctx.effects.cursor_magnetism.enabled {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let dur = std::time::Duration::from_millis(ctx.effects.cursor_magnetism.duration_ms as u64);
 
     // Detect cursor jump (large movement) and record
@@ -163,6 +163,96 @@ pub(super) fn emit_cursor_magnetism(
     (verts, needs_redraw)
 }
 
+/// Whether the cursor beacon fires for the SELECTED window, given the
+/// selected window of the previous frame as (window_id, buffer_id,
+/// window_start).  Switching windows or buffers fires when
+/// ON_WINDOW_SWITCH; scrolling by more than a screenful fires when
+/// ON_SCROLL.
+pub(super) fn beacon_triggered(
+    prev: Option<(i64, u64, i64)>,
+    selected: &WindowInfo,
+    on_window_switch: bool,
+    on_scroll: bool,
+) -> bool {
+    let Some((window_id, buffer_id, window_start)) = prev else {
+        return false;
+    };
+    if window_id != selected.window_id || buffer_id != selected.buffer_id {
+        return on_window_switch;
+    }
+    let screenful = (selected.window_end - selected.window_start).max(1);
+    on_scroll && (selected.window_start - window_start).abs() > screenful
+}
+
+/// Emit cursor beacon vertices.
+///
+/// Pulses a ring collapsing onto the cursor and/or flashes the cursor
+/// line, fading out over the configured duration.  Fires on window
+/// switches and large scrolls when enabled, or when START was set by an
+/// explicit beacon request.  The beacon is drawn even while the cursor
+/// is blinked off.
+/// Returns (vertices, needs_continuous_redraw).
+pub(super) fn emit_cursor_beacon(
+    ctx: &EffectCtx,
+    start: &mut Option<std::time::Instant>,
+    prev: &mut Option<(i64, u64, i64)>,
+) -> (Vec<RectVertex>, bool) {
+    let cfg = &ctx.effects.cursor_beacon;
+    let now = ctx.now;
+    let selected = ctx.frame_glyphs.window_infos.iter()
+        .find(|w| w.selected && !w.is_minibuffer);
+
+    // The minibuffer is skipped, so leaving it doesn't count as a switch
+    if let Some(sel) = selected {
+        if cfg.enabled && beacon_triggered(*prev, sel, cfg.on_window_switch, cfg.on_scroll) {
+            *start = Some(now);
+        }
+        *prev = Some((sel.window_id, sel.buffer_id, sel.window_start));
+    }
+
+    let mut verts = Vec::new();
+    let Some(started) = *start else {
+        return (verts, false);
+    };
+    let dur = cfg.duration_ms.max(1) as f32 / 1000.0;
+    let t = now.duration_since(started).as_secs_f32() / dur;
+    if t >= 1.0 {
+        *start = None;
+        return (verts, false);
+    }
+    let Some((x, y, w, h)) = find_cursor_pos(ctx.animated_cursor, ctx.frame_glyphs) else {
+        return (verts, true);
+    };
+    let (br, bg, bb) = cfg.color;
+    let fade = cfg.opacity * (1.0 - t);
+
+    // Line flash across the selected window
+    if cfg.style >= 1 {
+        let (lx, lw) = selected.map_or((x, w), |s| (s.bounds.x, s.bounds.width));
+        let c = Color::new(br, bg, bb, fade * 0.5);
+        push_rect(&mut verts, lx, y, lw, h, &c);
+    }
+
+    // Ring collapsing onto the cursor
+    if cfg.style != 1 {
+        let cx = x + w / 2.0;
+        let cy = y + h / 2.0;
+        let end_r = w.max(h) / 2.0 + 2.0;
+        let start_r = end_r + 6.0 * h.max(8.0);
+        let radius = end_r + (start_r - end_r) * (1.0 - t) * (1.0 - t);
+        let c = Color::new(br, bg, bb, fade);
+        let segs = 48;
+        let dot = 3.0;
+        for seg in 0..segs {
+            let a = seg as f32 / segs as f32 * std::f32::consts::TAU;
+            let px = cx + a.cos() * radius;
+            let py = cy + a.sin() * radius;
+            push_rect(&mut verts, px - dot / 2.0, py - dot / 2.0, dot, dot, &c);
+        }
+    }
+    (verts, true)
+}
+
 /// Emit line number pulse overlay on the cursor line.
 ///
 /// Renders a pulsing highlight over the line-number gutter area of the
@@ -175,9 +265,9 @@ pub(super) fn emit_line_number_pulse(ctx: &EffectCtx) -> (Vec<RectVertex>, bool)
     let mut verts = Vec::new();
     let mut needs_redraw = false;
     if let Some(ref anim) = ctx.animated_cursor {
-        let now = std::time::Instant::now();
+        let now = ctx.now;
         let cycle = ctx.effects.line_number_pulse.cycle_ms as f64 / 1000.0;
-        let elapsed = now.elapsed().as_secs_f64();
+        let elapsed = now.duration_since(ctx.aurora_start).as_secs_f64();
         let phase = (elapsed % cycle) / cycle;
         let pulse = ((phase * std::f64::consts::TAU).sin() * 0.5 + 0.5) as f32;
         let alpha = ctx.effects.line_number_pulse.intensity * pulse;
@@ -238,7 +328,7 @@ pub(super) fn emit_cursor_comet(
     if !ctx.effects.cursor_comet.enabled {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let fade_dur = std::time::Duration::from_millis(ctx.effects.cursor_comet.fade_ms as u64);
 
     // Record cursor position
@@ -299,7 +389,7 @@ pub(super) fn emit_cursor_particles(
     if !ctx.effects.cursor_particles.enabled {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let lifetime = std::time::Duration::from_millis(ctx.effects.cursor_particles.lifetime_ms as u64);
 
     // Detect cursor movement and emit particles
@@ -310,7 +400,7 @@ pub(super) fn emit_cursor_particles(
             let dy = (cur_pos.1 - prev.1).abs();
             if dx > 1.0 || dy > 1.0 {
                 // Emit particles from cursor center
-                let seed = (now.elapsed().subsec_nanos() as u64).wrapping_mul(2654435761);
+                let seed = (now.duration_since(ctx.aurora_start).subsec_nanos() as u64).wrapping_mul(2654435761);
                 for i in 0..ctx.effects.cursor_particles.count {
                     // Simple hash-based pseudo-random
                     let h = seed.wrapping_add(i as u64).wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
@@ -372,7 +462,7 @@ pub(super) fn emit_matrix_rain(
     let fw = ctx.surface_width as f32 / ctx.scale_factor;
     let fh = ctx.surface_height as f32 / ctx.scale_factor;
     let dt = 1.0 / 60.0_f32;
-    let now_ns = std::time::Instant::now().elapsed().subsec_nanos() as u64;
+    let now_ns = ctx.now.duration_since(ctx.aurora_start).subsec_nanos() as u64;
 
     // Spawn columns if needed
     while columns.len() < ctx.effects.matrix_rain.column_count as usize {
@@ -498,7 +588,7 @@ pub(super) fn emit_cursor_ripple_wave(
     if !ctx.effects.cursor_ripple_wave.enabled {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
 
     // Detect cursor movement and spawn ripple
     if let Some(ref anim) = ctx.animated_cursor {
@@ -568,7 +658,7 @@ pub(super) fn emit_cursor_lighthouse_beam(ctx: &EffectCtx) -> Vec<RectVertex> {
         return verts;
     }
     if let Some(ref anim) = *ctx.animated_cursor {
-        let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+        let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
         let center_x = anim.x + anim.width / 2.0;
         let center_y = anim.y + anim.height / 2.0;
         let angle = now * ctx.effects.cursor_lighthouse.rotation_speed * std::f32::consts::PI * 2.0;
@@ -605,7 +695,7 @@ pub(super) fn emit_cursor_sonar_ping(
     if !ctx.effects.cursor_sonar_ping.enabled {
         return (verts, needs_redraw);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     entries.retain(|e| now.duration_since(e.started) < e.duration);
     let (pr, pg, pb) = ctx.effects.cursor_sonar_ping.color;
     let ring_count = ctx.effects.cursor_sonar_ping.ring_count;
@@ -656,7 +746,7 @@ pub(super) fn emit_lightning_bolt(
     if !ctx.effects.lightning_bolt.enabled {
         return (verts, false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let dt = now.duration_since(*last).as_secs_f32();
     *last = now;
     *age += dt;
@@ -715,7 +805,7 @@ pub(super) fn emit_cursor_orbit_particles(ctx: &EffectCtx) -> Vec<RectVertex> {
         return verts;
     }
     if let Some(ref anim) = *ctx.animated_cursor {
-        let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+        let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
         let cx = anim.x + anim.width / 2.0;
         let cy = anim.y + anim.height / 2.0;
         let (pr, pg, pb) = ctx.effects.cursor_orbit_particles.color;
@@ -747,7 +837,7 @@ pub(super) fn emit_cursor_heartbeat_pulse(ctx: &EffectCtx) -> Vec<RectVertex> {
         return verts;
     }
     if let Some(ref anim) = *ctx.animated_cursor {
-        let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+        let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
         let cx = anim.x + anim.width / 2.0;
         let cy = anim.y + anim.height / 2.0;
         let (hr, hg, hb) = ctx.effects.cursor_heartbeat.color;
@@ -802,12 +892,12 @@ pub(super) fn emit_cursor_metronome_tick(
         let cy = anim.y;
         // Detect cursor move
         if (cx - *last_x).abs() > 1.0 || (cy - *last_y).abs() > 1.0 {
-            *tick_start = Some(std::time::Instant::now());
+            *tick_start = Some(ctx.now);
             *last_x = cx;
             *last_y = cy;
         }
         if let Some(start) = *tick_start {
-            let elapsed = start.elapsed().as_secs_f32();
+            let elapsed = ctx.now.saturating_duration_since(start).as_secs_f32();
             let duration = ctx.effects.cursor_metronome.fade_ms as f32 / 1000.0;
             if elapsed < duration {
                 let t = elapsed / duration;
@@ -839,7 +929,7 @@ pub(super) fn emit_cursor_radar_sweep(ctx: &EffectCtx) -> Vec<RectVertex> {
         return verts;
     }
     if let Some(ref anim) = *ctx.animated_cursor {
-        let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+        let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
         let cx = anim.x + anim.width / 2.0;
         let cy = anim.y + anim.height / 2.0;
         let (rr, rg, rb) = ctx.effects.cursor_radar.color;
@@ -908,12 +998,12 @@ pub(super) fn emit_cursor_ripple_ring(
         let cy = anim.y + anim.height / 2.0;
         // Detect cursor move
         if (cx - *last_x).abs() > 1.0 || (cy - *last_y).abs() > 1.0 {
-            *start = Some(std::time::Instant::now());
+            *start = Some(ctx.now);
             *last_x = cx;
             *last_y = cy;
         }
         if let Some(start_time) = *start {
-            let elapsed = start_time.elapsed().as_secs_f32();
+            let elapsed = ctx.now.saturating_duration_since(start_time).as_secs_f32();
             let max_r = ctx.effects.cursor_ripple_ring.max_radius;
             let duration = max_r / (ctx.effects.cursor_ripple_ring.speed * 60.0);
             if elapsed < duration {
@@ -999,12 +1089,12 @@ pub(super) fn emit_cursor_shockwave(
         let cy = anim.y + anim.height / 2.0;
         // Detect cursor move
         if (cx - *last_x).abs() > 1.0 || (cy - *last_y).abs() > 1.0 {
-            *start = Some(std::time::Instant::now());
+            *start = Some(ctx.now);
             *last_x = cx;
             *last_y = cy;
         }
         if let Some(start_time) = *start {
-            let elapsed = start_time.elapsed().as_secs_f32();
+            let elapsed = ctx.now.saturating_duration_since(start_time).as_secs_f32();
             let max_r = ctx.effects.cursor_shockwave.radius;
             let duration = 1.0 / ctx.effects.cursor_shockwave.decay;
             if elapsed < duration {
@@ -1054,7 +1144,7 @@ pub(super) fn emit_cursor_gravity_well(ctx: &EffectCtx) -> Vec<RectVertex> {
         let gop = ctx.effects.cursor_gravity_well.opacity;
         let field_r = ctx.effects.cursor_gravity_well.field_radius;
         let lines = ctx.effects.cursor_gravity_well.line_count.clamp(4, 24);
-        let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+        let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
         for line in 0..lines {
             let base_angle = line as f32 * std::f32::consts::PI * 2.0 / lines as f32 + now * 0.2;
             let steps = 20;
@@ -1083,7 +1173,7 @@ pub(super) fn emit_cursor_portal(ctx: &EffectCtx) -> Vec<RectVertex> {
         return verts;
     }
     if let Some(ref anim) = *ctx.animated_cursor {
-        let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+        let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
         let cx = anim.x + anim.width / 2.0;
         let cy = anim.y + anim.height / 2.0;
         let (pr, pg, pb) = ctx.effects.cursor_portal.color;
@@ -1137,12 +1227,12 @@ pub(super) fn emit_cursor_bubble(
         let cy = anim.y + anim.height / 2.0;
         // Detect cursor move
         if (cx - *last_x).abs() > 1.0 || (cy - *last_y).abs() > 1.0 {
-            *spawn_time = Some(std::time::Instant::now());
+            *spawn_time = Some(ctx.now);
             *last_x = cx;
             *last_y = cy;
         }
         if let Some(spawn) = *spawn_time {
-            let elapsed = spawn.elapsed().as_secs_f32();
+            let elapsed = ctx.now.saturating_duration_since(spawn).as_secs_f32();
             let duration = 1.5;
             if elapsed < duration {
                 let (br, bg, bb) = ctx.effects.cursor_bubble.color;
@@ -1202,12 +1292,12 @@ pub(super) fn emit_cursor_firework(
         let dx = cx - *last_x;
         let dy = cy - *last_y;
         if dx.abs() > 1.0 || dy.abs() > 1.0 {
-            *start = Some(std::time::Instant::now());
+            *start = Some(ctx.now);
             *last_x = cx;
             *last_y = cy;
         }
         if let Some(start_time) = *start {
-            let elapsed = start_time.elapsed().as_secs_f32();
+            let elapsed = ctx.now.saturating_duration_since(start_time).as_secs_f32();
             let duration = 0.6;
             if elapsed < duration {
                 let t = elapsed / duration;
@@ -1246,7 +1336,7 @@ pub(super) fn emit_cursor_tornado(ctx: &EffectCtx) -> Vec<RectVertex> {
     if let Some(ref anim) = *ctx.animated_cursor {
         let cx = anim.x + anim.width / 2.0;
         let cy = anim.y + anim.height / 2.0;
-        let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+        let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
         let (cr, cg, cb) = ctx.effects.cursor_tornado.color;
         let radius = ctx.effects.cursor_tornado.radius;
         let opacity = ctx.effects.cursor_tornado.opacity;
@@ -1301,12 +1391,12 @@ pub(super) fn emit_cursor_lightning(
         let dx = cx - *last_x;
         let dy = cy - *last_y;
         if dx.abs() > 1.0 || dy.abs() > 1.0 {
-            *start = Some(std::time::Instant::now());
+            *start = Some(ctx.now);
             *last_x = cx;
             *last_y = cy;
         }
         if let Some(start_time) = *start {
-            let elapsed = start_time.elapsed().as_secs_f32();
+            let elapsed = ctx.now.saturating_duration_since(start_time).as_secs_f32();
             let duration = 0.4;
             if elapsed < duration {
                 let t = elapsed / duration;
@@ -1365,12 +1455,12 @@ pub(super) fn emit_cursor_snowflake(
         let dx = cx - *last_x;
         let dy = cy - *last_y;
         if dx.abs() > 1.0 || dy.abs() > 1.0 {
-            *start = Some(std::time::Instant::now());
+            *start = Some(ctx.now);
             *last_x = cx;
             *last_y = cy;
         }
         if let Some(start_time) = *start {
-            let elapsed = start_time.elapsed().as_secs_f32();
+            let elapsed = ctx.now.saturating_duration_since(start_time).as_secs_f32();
             let duration = 2.0;
             if elapsed < duration {
                 let t = elapsed / duration;
@@ -1414,7 +1504,7 @@ pub(super) fn emit_cursor_flame(ctx: &EffectCtx) -> Vec<RectVertex> {
     if let Some(ref anim) = *ctx.animated_cursor {
         let cx = anim.x + anim.width / 2.0;
         let cy = anim.y;
-        let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+        let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
         let (cr, cg, cb) = ctx.effects.cursor_flame.color;
         let opacity = ctx.effects.cursor_flame.opacity;
         let flame_h = ctx.effects.cursor_flame.height;
@@ -1449,7 +1539,7 @@ pub(super) fn emit_cursor_crystal(ctx: &EffectCtx) -> Vec<RectVertex> {
     };
     let cx = anim.x + anim.width / 2.0;
     let cy = anim.y + anim.height / 2.0;
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (cr, cg, cb) = ctx.effects.cursor_crystal.color;
     let opacity = ctx.effects.cursor_crystal.opacity;
     let radius = ctx.effects.cursor_crystal.radius;
@@ -1494,7 +1584,7 @@ pub(super) fn emit_cursor_water_drop(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (wr, wg, wb) = ctx.effects.cursor_water_drop.color;
     let ripple_count = ctx.effects.cursor_water_drop.ripple_count;
     let speed = ctx.effects.cursor_water_drop.expand_speed;
@@ -1530,7 +1620,7 @@ pub(super) fn emit_cursor_pixel_dust(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (pr, pg, pb) = ctx.effects.cursor_pixel_dust.color;
     let dust_count = ctx.effects.cursor_pixel_dust.count;
     let scatter = ctx.effects.cursor_pixel_dust.scatter_speed;
@@ -1569,7 +1659,7 @@ pub(super) fn emit_cursor_candle_flame(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (fr, fg, fb) = ctx.effects.cursor_candle_flame.color;
     let flame_h = ctx.effects.cursor_candle_flame.height as f32;
     let flicker = ctx.effects.cursor_candle_flame.flicker_speed;
@@ -1620,7 +1710,7 @@ pub(super) fn emit_cursor_moth_flame(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (mr, mg, mb) = ctx.effects.cursor_moth_flame.color;
     let moth_count = ctx.effects.cursor_moth_flame.moth_count;
     let orbit = ctx.effects.cursor_moth_flame.orbit_speed;
@@ -1661,7 +1751,7 @@ pub(super) fn emit_cursor_sparkler(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (sr, sg, sb) = ctx.effects.cursor_sparkler.color;
     let spark_count = ctx.effects.cursor_sparkler.spark_count;
     let burn = ctx.effects.cursor_sparkler.burn_speed;
@@ -1700,7 +1790,7 @@ pub(super) fn emit_cursor_plasma_ball(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (pr, pg, pb) = ctx.effects.cursor_plasma_ball.color;
     let tendril_count = ctx.effects.cursor_plasma_ball.tendril_count;
     let arc_speed = ctx.effects.cursor_plasma_ball.arc_speed;
@@ -1747,7 +1837,7 @@ pub(super) fn emit_cursor_quill_pen(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (qr, qg, qb) = ctx.effects.cursor_quill_pen.color;
     let trail_len = ctx.effects.cursor_quill_pen.trail_length;
     let ink_speed = ctx.effects.cursor_quill_pen.ink_speed;
@@ -1796,7 +1886,7 @@ pub(super) fn emit_cursor_aurora_borealis(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (ar, ag, ab) = ctx.effects.cursor_aurora_borealis.color;
     let band_count = ctx.effects.cursor_aurora_borealis.band_count;
     let shimmer = ctx.effects.cursor_aurora_borealis.shimmer_speed;
@@ -1842,7 +1932,7 @@ pub(super) fn emit_cursor_feather(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let cx = anim.x + anim.width / 2.0;
     let cy = anim.y + anim.height / 2.0;
     let (fr, fg, fb) = ctx.effects.cursor_feather.color;
@@ -1890,7 +1980,7 @@ pub(super) fn emit_cursor_stardust(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let cx = anim.x + anim.width / 2.0;
     let cy = anim.y + anim.height / 2.0;
     let (sr, sg, sb) = ctx.effects.cursor_stardust.color;
@@ -1922,7 +2012,7 @@ pub(super) fn emit_cursor_compass_needle(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let cx = anim.x + anim.width / 2.0;
     let cy = anim.y + anim.height / 2.0;
     let (nr, ng, nb) = ctx.effects.cursor_compass_needle.color;
@@ -1964,7 +2054,7 @@ pub(super) fn emit_cursor_galaxy(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let cx = anim.x + anim.width / 2.0;
     let cy = anim.y + anim.height / 2.0;
     let (gr, gg, gb) = ctx.effects.cursor_galaxy.color;
@@ -1998,7 +2088,7 @@ pub(super) fn emit_cursor_prism(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let cx = anim.x + anim.width / 2.0;
     let cy = anim.y + anim.height / 2.0;
     let ray_count = ctx.effects.cursor_prism.ray_count;
@@ -2034,7 +2124,7 @@ pub(super) fn emit_cursor_moth(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let cx = anim.x + anim.width / 2.0;
     let cy = anim.y + anim.height / 2.0;
     let moth_count = ctx.effects.cursor_moth.count;
@@ -2088,11 +2178,11 @@ pub(super) fn emit_cursor_sparkle_burst(
     };
     if should_spawn {
         let seed = (cx as u32).wrapping_mul(31).wrapping_add(cy as u32).wrapping_mul(17).wrapping_add(
-            std::time::Instant::now().elapsed().subsec_nanos()
+            ctx.now.duration_since(ctx.aurora_start).subsec_nanos()
         );
         entries.push(SparkleBurstEntry {
             cx, cy,
-            started: std::time::Instant::now(),
+            started: ctx.now,
             seed,
         });
         if entries.len() > 20 {
@@ -2105,9 +2195,9 @@ pub(super) fn emit_cursor_sparkle_burst(
     let radius = ctx.effects.cursor_sparkle_burst.radius;
     let mut verts: Vec<RectVertex> = Vec::new();
     let duration = 0.4_f32;
-    entries.retain(|e| e.started.elapsed().as_secs_f32() < duration);
+    entries.retain(|e| ctx.now.saturating_duration_since(e.started).as_secs_f32() < duration);
     for entry in entries.iter() {
-        let t = entry.started.elapsed().as_secs_f32() / duration;
+        let t = ctx.now.saturating_duration_since(entry.started).as_secs_f32() / duration;
         let fade = 1.0 - t;
         for i in 0..count {
             let mut h = entry.seed.wrapping_add(i * 2654435761);
@@ -2137,7 +2227,7 @@ pub(super) fn emit_cursor_compass_rose(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let cx = anim.x + anim.width / 2.0;
     let cy = anim.y + anim.height / 2.0;
     let (cr, cg, cb) = ctx.effects.cursor_compass.color;
@@ -2192,7 +2282,7 @@ pub(super) fn emit_cursor_dna_helix(ctx: &EffectCtx) -> Vec<RectVertex> {
         Some(ref anim) => anim,
         None => return Vec::new(),
     };
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let cx = anim.x + anim.width / 2.0;
     let cy = anim.y + anim.height / 2.0;
     let (c1r, c1g, c1b) = ctx.effects.cursor_dna_helix.color1;
@@ -2249,7 +2339,7 @@ pub(super) fn emit_cursor_pendulum(
     let cy = anim.y + anim.height / 2.0;
     // Detect cursor move
     if (cx - *last_x).abs() > 1.0 || (cy - *last_y).abs() > 1.0 {
-        *swing_start = Some(std::time::Instant::now());
+        *swing_start = Some(ctx.now);
         *last_x = cx;
         *last_y = cy;
     }
@@ -2257,7 +2347,7 @@ pub(super) fn emit_cursor_pendulum(
         Some(s) => *s,
         None => return (Vec::new(), false),
     };
-    let elapsed = start.elapsed().as_secs_f32();
+    let elapsed = ctx.now.saturating_duration_since(start).as_secs_f32();
     let (pr, pg, pb) = ctx.effects.cursor_pendulum.color;
     let pop = ctx.effects.cursor_pendulum.opacity;
     let arc_len = ctx.effects.cursor_pendulum.arc_length;
@@ -2323,7 +2413,7 @@ pub(super) fn emit_cursor_trail_fade(
     if !ctx.effects.cursor_trail_fade.enabled || positions.is_empty() {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let fade_dur = *fade_duration;
 
     // Remove expired positions
@@ -2349,7 +2439,7 @@ mod tests {
     use super::*;
     use super::super::effect_common::EffectCtx;
     use crate::effect_config::EffectsConfig;
-    use crate::core::frame_glyphs::{FrameGlyphBuffer, WindowInfo};
+    use crate::core::frame_glyphs::FrameGlyphBuffer;
     use crate::core::types::{AnimatedCursor, Rect};
 
     /// Helper to create an EffectCtx for testing
@@ -2368,6 +2458,7 @@ mod tests {
             surface_width: 800,
             surface_height: 600,
             aurora_start: std::time::Instant::now(),
+            now: std::time::Instant::now(),
             scale_factor: 1.0,
             logical_w: 800.0,
             logical_h: 600.0,
@@ -2680,21 +2771,99 @@ mod tests {
         let ctx = make_ctx(&config, &fgb, &anim_cursor, true);
 
         // Add an old entry at a different position
-        let old_time = std::time::Instant::now() - std::time::Duration::from_secs(10);
+        let old_time = ctx.now - std::time::Duration::from_secs(10);
         let mut entries = vec![(50.0, 50.0, old_time)];
 
-        std::thread::sleep(std::time::Duration::from_millis(5));
-
         let (verts, _) = emit_cursor_magnetism(&ctx, &mut entries);
 
         // Old entry should be pruned, new one added (cursor jumped from 50,50 to 200,100)
         assert_eq!(entries.len(), 1);
-        assert!(entries[0].2.elapsed().as_millis() < 100);
+        assert_eq!(entries[0].2, ctx.now);
         // New entry should be near cursor position (205, 110 = cursor center)
         assert!((entries[0].0 - 205.0).abs() < 1.0);
         assert!((entries[0].1 - 110.0).abs() < 1.0);
     }
 
+    // ========================================================================
+    // emit_cursor_beacon tests
+    // ========================================================================
+
+    #[test]
+    fn test_beacon_triggers() {
+        let win = make_selected_window_info(0.0, 0.0, 800.0, 600.0);
+        // First frame: nothing to compare against
+        assert!(!beacon_triggered(None, &win, true, true));
+        // Same window, small scroll
+        assert!(!beacon_triggered(Some((1, 1, 50)), &win, true, true));
+        // Scrolled by more than the 100-char screenful
+        assert!(beacon_triggered(Some((1, 1, 150)), &win, true, true));
+        assert!(!beacon_triggered(Some((1, 1, 150)), &win, true, false));
+        // Different window or buffer
+        assert!(beacon_triggered(Some((2, 1, 0)), &win, true, false));
+        assert!(beacon_triggered(Some((1, 2, 0)), &win, true, false));
+        assert!(!beacon_triggered(Some((2, 1, 0)), &win, false, true));
+    }
+
+    #[test]
+    fn test_cursor_beacon_idle() {
+        let mut config = EffectsConfig::default();
+        config.cursor_beacon.enabled = true;
+        let mut fgb = FrameGlyphBuffer::default();
+        fgb.window_infos.push(make_selected_window_info(0.0, 0.0, 800.0, 600.0));
+        let anim_cursor = Some(make_animated_cursor(100.0, 100.0, 10.0, 20.0, 1));
+        let ctx = make_ctx(&config, &fgb, &anim_cursor, true);
+
+        let mut start = None;
+        let mut prev = None;
+        let (verts, needs_redraw) = emit_cursor_beacon(&ctx, &mut start, &mut prev);
+        assert!(verts.is_empty());
+        assert!(!needs_redraw);
+        assert_eq!(prev, Some((1, 1, 0)));
+    }
+
+    #[test]
+    fn test_cursor_beacon_window_switch() {
+        let mut config = EffectsConfig::default();
+        config.cursor_beacon.enabled = true;
+        config.cursor_beacon.style = 2;
+        let mut fgb = FrameGlyphBuffer::default();
+        fgb.window_infos.push(make_selected_window_info(0.0, 0.0, 800.0, 600.0));
+        // Blinked-off cursor still gets a beacon
+        let anim_cursor = Some(make_animated_cursor(100.0, 100.0, 10.0, 20.0, 1));
+        let ctx = make_ctx(&config, &fgb, &anim_cursor, false);
+
+        let mut start = None;
+        let mut prev = Some((2, 1, 0));
+        let (verts, needs_redraw) = emit_cursor_beacon(&ctx, &mut start, &mut prev);
+        assert!(start.is_some());
+        assert!(needs_redraw);
+        // Line flash + 48 ring dots
+        assert_eq!(verts.len(), 49 * 6);
+        validate_vertex_count(&verts);
+        validate_vertices(&verts);
+    }
+
+    #[test]
+    fn test_cursor_beacon_on_demand_when_disabled() {
+        let config = EffectsConfig::default();
+        let fgb = FrameGlyphBuffer::default();
+        let anim_cursor = Some(make_animated_cursor(100.0, 100.0, 10.0, 20.0, 1));
+        let ctx = make_ctx(&config, &fgb, &anim_cursor, true);
+
+        let mut start = Some(std::time::Instant::now());
+        let mut prev = None;
+        let (verts, needs_redraw) = emit_cursor_beacon(&ctx, &mut start, &mut prev);
+        assert!(!verts.is_empty());
+        assert!(needs_redraw);
+
+        // Expired beacon is cleared
+        let mut start = Some(std::time::Instant::now() - std::time::Duration::from_secs(10));
+        let (verts, needs_redraw) = emit_cursor_beacon(&ctx, &mut start, &mut prev);
+        assert!(verts.is_empty());
+        assert!(!needs_redraw);
+        assert!(start.is_none());
+    }
+
     // ========================================================================
     // emit_line_number_pulse tests
     // ========================================================================
@@ -19,6 +19,8 @@ pub(super) struct EffectCtx<'a> {
     pub surface_width: u32,
     pub surface_height: u32,
     pub aurora_start: std::time::Instant,
+    /// Frame time from the render thread's animation clock
+    pub now: std::time::Instant,
     pub scale_factor: f32,
     /// Logical frame width (frame_glyphs.width or surface_width/scale_factor)
     pub logical_w: f32,
@@ -26,7 +26,7 @@ impl WgpuRenderer {
             window_bounds,
             edit_y,
             initial_offset: offset,
-            started: std::time::Instant::now(),
+            started: self.frame_time,
             duration: std::time::Duration::from_millis(duration_ms as u64),
         });
         self.needs_continuous_redraw = true;
@@ -42,7 +42,7 @@ impl WgpuRenderer {
                 && gy >= b.y && gy < b.y + b.height
                 && gy >= anim.edit_y
             {
-                let elapsed = anim.started.elapsed();
+                let elapsed = self.frame_time.saturating_duration_since(anim.started);
                 let t = (elapsed.as_secs_f32() / anim.duration.as_secs_f32()).min(1.0);
                 // Ease-out quadratic: t * (2 - t)
                 let eased = t * (2.0 - t);
@@ -50,7 +50,7 @@ impl WgpuRenderer {
             }
         }
         // Scroll line spacing accordion effect
-        let now = std::time::Instant::now();
+        let now = self.frame_time;
         for entry in &self.active_scroll_spacings {
             let b = &entry.bounds;
             if gx >= b.x && gx < b.x + b.width
@@ -86,7 +86,7 @@ impl WgpuRenderer {
             return 1.0;
         }
         if let Some(started) = self.cursor_wake_started {
-            let elapsed = started.elapsed().as_millis() as f32;
+            let elapsed = self.frame_time.saturating_duration_since(started).as_millis() as f32;
             let duration = self.effects.cursor_wake.duration_ms as f32;
             if elapsed >= duration {
                 return 1.0;
@@ -155,7 +155,7 @@ impl WgpuRenderer {
     /// Get current resize padding amount (eases from max to 0)
     pub(super) fn resize_padding_amount(&self) -> f32 {
         if let Some(started) = self.resize_padding_started {
-            let elapsed = started.elapsed().as_millis() as f32;
+            let elapsed = self.frame_time.saturating_duration_since(started).as_millis() as f32;
             let duration = self.effects.resize_padding.duration_ms as f32;
             if elapsed >= duration {
                 return 0.0;
@@ -179,7 +179,7 @@ impl WgpuRenderer {
             return None;
         }
         if let Some(started) = self.cursor_error_pulse_started {
-            let elapsed = started.elapsed().as_millis() as f32;
+            let elapsed = self.frame_time.saturating_duration_since(started).as_millis() as f32;
             let duration = self.effects.cursor_error_pulse.duration_ms as f32;
             if elapsed >= duration {
                 return None;
@@ -253,7 +253,7 @@ impl WgpuRenderer {
         if !self.effects.mode_line_transition.enabled || self.active_mode_line_fades.is_empty() {
             return 1.0;
         }
-        let now = std::time::Instant::now();
+        let now = self.frame_time;
         for entry in &self.active_mode_line_fades {
             if gx >= entry.bounds_x && gx < entry.bounds_x + entry.bounds_w
                 && gy >= entry.mode_line_y && gy < entry.mode_line_y + entry.mode_line_h
@@ -288,7 +288,7 @@ impl WgpuRenderer {
         if !self.effects.text_fade_in.enabled || self.active_text_fades.is_empty() {
             return 1.0;
         }
-        let now = std::time::Instant::now();
+        let now = self.frame_time;
         for entry in &self.active_text_fades {
             let b = &entry.bounds;
             if gx >= b.x && gx < b.x + b.width
@@ -326,7 +326,7 @@ impl WgpuRenderer {
         let dist = ((x - self.cursor_trail_last_pos.0).powi(2)
             + (y - self.cursor_trail_last_pos.1).powi(2)).sqrt();
         if dist < 2.0 { return; } // Skip tiny movements
-        self.cursor_trail_positions.push((x, y, w, h, std::time::Instant::now()));
+        self.cursor_trail_positions.push((x, y, w, h, self.frame_time));
         self.cursor_trail_last_pos = (x, y);
         // Trim to max length
         while self.cursor_trail_positions.len() > self.effects.cursor_trail_fade.length {
@@ -346,7 +346,7 @@ impl WgpuRenderer {
         self.active_window_fades.push(WindowFadeEntry {
             window_id,
             bounds,
-            started: std::time::Instant::now(),
+            started: self.frame_time,
             duration: std::time::Duration::from_millis(self.effects.window_switch_fade.duration_ms as u64),
             intensity: self.effects.window_switch_fade.intensity,
         });
@@ -380,7 +380,7 @@ impl WgpuRenderer {
     /// Spawn a new ripple at the given position
     pub fn spawn_ripple(&mut self, cx: f32, cy: f32) {
         if self.effects.typing_ripple.enabled {
-            self.active_ripples.push((cx, cy, std::time::Instant::now()));
+            self.active_ripples.push((cx, cy, self.frame_time));
         }
     }
 
@@ -13,6 +13,9 @@ use crate::core::types::{Color, Rect, AnimatedCursor};
 use crate::core::frame_glyphs::{CursorStyle, FrameGlyph, FrameGlyphBuffer, StipplePattern};
 use crate::core::face::{BoxType, Face, FaceAttributes};
 use super::super::glyph_atlas::{ComposedGlyphKey, GlyphKey, WgpuGlyphAtlas};
+use crate::core::text_contrast::{
+    fix_contrast, luminance, luminance_under, ContrastFix, ContrastStyle, LuminanceGrid,
+};
 
 /// Draw effect vertices produced by a pure effect function.
 macro_rules! draw_effect {
@@ -51,6 +54,94 @@ macro_rules! draw_stateful {
     }};
 }
 
+/// Turn the quad VERTS of a character whose cell starts at (X, Y) a
+/// quarter turn clockwise, into a vertical column COLUMN_W wide: the
+/// top of the cell goes to the column's right edge.
+fn rotate_sideways(verts: &mut [GlyphVertex; 6], x: f32, y: f32, column_w: f32) {
+    for v in verts.iter_mut() {
+        let [vx, vy] = v.position;
+        v.position = [x + column_w - (vy - y), y + (vx - x)];
+    }
+}
+
+/// Cut the quad VERTS of a character at LEFT, as the edge of an
+/// hscrolled window's text area, taking the texture along.
+fn clip_quad_left(verts: &mut [GlyphVertex; 6], left: f32) {
+    let (x0, x1) = (verts[0].position[0], verts[1].position[0]);
+    if x1 <= left {
+        for v in verts.iter_mut() {
+            v.position[0] = left;
+        }
+        return;
+    }
+    let u = ((left - x0) / (x1 - x0)).max(0.0);
+    for v in verts.iter_mut() {
+        if v.position[0] < left {
+            v.position[0] = left;
+            v.tex_coords[0] = u;
+        }
+    }
+}
+
+/// Contrast fixes for the text of window INFO, drawn over its
+/// background image QUADS summarized by GRID at OPACITY: colors replacing
+/// the foreground of glyphs, keyed by glyph index, and scrims, merged
+/// into runs along each row.
+fn background_contrast_fixes(
+    frame_glyphs: &FrameGlyphBuffer,
+    info: &crate::core::frame_glyphs::WindowInfo,
+    quads: &[crate::core::window_background::BackgroundQuad],
+    grid: &LuminanceGrid,
+    opacity: f32,
+    config: &crate::effect_config::BackgroundContrastConfig,
+) -> (HashMap<usize, Color>, Vec<(Rect, Color)>) {
+    let style = ContrastStyle::from_ffi(config.style);
+    let top = info.bounds.y + info.tab_line_height + info.header_line_height;
+    let bottom = info.bounds.y + info.bounds.height - info.mode_line_height;
+    let mut colors = HashMap::new();
+    let mut scrims: Vec<(Rect, Color)> = Vec::new();
+    for (i, glyph) in frame_glyphs.glyphs.iter().enumerate() {
+        let FrameGlyph::Char { char, x, y, width, height, fg, bg, is_overlay: false, .. } = glyph else {
+            continue;
+        };
+        if char.is_whitespace()
+            || *x < info.bounds.x
+            || *x >= info.bounds.x + info.bounds.width
+            || *y < top
+            || *y >= bottom
+        {
+            continue;
+        }
+        let cell = Rect::new(*x, *y, *width, *height);
+        let base = luminance(bg.as_ref().unwrap_or(&frame_glyphs.background));
+        let Some(under) = luminance_under(&cell, quads, grid, opacity, base) else {
+            continue;
+        };
+        match fix_contrast(fg, under, style, config.min_ratio, config.max_scrim) {
+            Some(ContrastFix::Foreground(color)) => {
+                colors.insert(i, color);
+            }
+            Some(ContrastFix::Scrim(color)) => match scrims.last_mut() {
+                // Continue the run of the previous character on the row,
+                // across the spaces between words
+                Some((run, run_color))
+                    if run.y == cell.y
+                        && run.height == cell.height
+                        && run_color.r == color.r
+                        && cell.x >= run.x + run.width - 0.5
+                        && cell.x <= run.x + run.width + cell.width * 1.5 =>
+                {
+                    run.width = cell.x + cell.width - run.x;
+                    run_color.a = run_color.a.max(color.a);
+                }
+                _ => scrims.push((cell, color)),
+            },
+            None => {}
+        }
+    }
+    (colors, scrims)
+}
+
 impl WgpuRenderer {
     /// Render frame glyphs to a texture view
     ///
@@ -69,6 +160,7 @@ impl WgpuRenderer {
         mouse_pos: (f32, f32),
         background_gradient: Option<((f32, f32, f32), (f32, f32, f32))>,
     ) {
+        let _span = crate::logging::span("glyphs");
         log::debug!(
             "render_frame_glyphs: frame={}x{} surface={}x{}, {} glyphs, {} faces",
             frame_glyphs.width,
@@ -83,13 +175,13 @@ impl WgpuRenderer {
         self.needs_continuous_redraw = false;
 
         // Clean up expired line animations
-        self.active_line_anims.retain(|a| a.started.elapsed() < a.duration);
+        self.active_line_anims.retain(|a| self.frame_time.saturating_duration_since(a.started) < a.duration);
         if !self.active_line_anims.is_empty() {
             self.needs_continuous_redraw = true;
         }
 
         // Clean up expired mode-line transition fades
-        self.active_mode_line_fades.retain(|e| e.started.elapsed() < e.duration);
+        self.active_mode_line_fades.retain(|e| self.frame_time.saturating_duration_since(e.started) < e.duration);
         if !self.active_mode_line_fades.is_empty() {
             self.needs_continuous_redraw = true;
         }
@@ -98,7 +190,7 @@ impl WgpuRenderer {
         if self.effects.mode_line_transition.enabled {
             use std::collections::hash_map::DefaultHasher;
             use std::hash::{Hash, Hasher};
-            let now_ml = std::time::Instant::now();
+            let now_ml = self.frame_time;
             for info in &frame_glyphs.window_infos {
                 if info.mode_line_height < 1.0 || info.is_minibuffer {
                     continue;
@@ -136,13 +228,13 @@ impl WgpuRenderer {
         }
 
         // Clean up expired text fade-in animations
-        self.active_text_fades.retain(|e| e.started.elapsed() < e.duration);
+        self.active_text_fades.retain(|e| self.frame_time.saturating_duration_since(e.started) < e.duration);
         if !self.active_text_fades.is_empty() {
             self.needs_continuous_redraw = true;
         }
 
         // Clean up expired scroll line spacing animations
-        let now_spacing = std::time::Instant::now();
+        let now_spacing = self.frame_time;
         self.active_scroll_spacings.retain(|e| {
             now_spacing.duration_since(e.started) < e.duration
         });
@@ -153,7 +245,7 @@ impl WgpuRenderer {
         // Clear expired cursor wake animation
         if let Some(started) = self.cursor_wake_started {
             let dur = std::time::Duration::from_millis(self.effects.cursor_wake.duration_ms as u64);
-            if started.elapsed() >= dur {
+            if self.frame_time.saturating_duration_since(started) >= dur {
                 self.cursor_wake_started = None;
             } else {
                 self.needs_continuous_redraw = true;
@@ -163,7 +255,7 @@ impl WgpuRenderer {
         // Clear expired cursor error pulse
         if let Some(started) = self.cursor_error_pulse_started {
             let dur = std::time::Duration::from_millis(self.effects.cursor_error_pulse.duration_ms as u64);
-            if started.elapsed() >= dur {
+            if self.frame_time.saturating_duration_since(started) >= dur {
                 self.cursor_error_pulse_started = None;
             } else {
                 self.needs_continuous_redraw = true;
@@ -171,7 +263,7 @@ impl WgpuRenderer {
         }
 
         // Clean up expired scroll momentum entries
-        self.active_scroll_momentums.retain(|e| e.started.elapsed() < e.duration);
+        self.active_scroll_momentums.retain(|e| self.frame_time.saturating_duration_since(e.started) < e.duration);
         if !self.active_scroll_momentums.is_empty() {
             self.needs_continuous_redraw = true;
         }
@@ -418,7 +510,8 @@ impl WgpuRenderer {
                     if let Some(bg_color) = bg {
                         if !overlaps_rounded_box_span(*x, *y, false, &box_spans) {
                             let ya = if has_line_anims { *y + self.line_y_offset(*x, *y) } else { *y };
-                            self.add_rect(&mut non_overlay_rect_vertices, *x, ya, *width, *height, bg_color);
+                            let left = frame_glyphs.text_clip_left(*x, *y, *width).unwrap_or(*x);
+                            self.add_rect(&mut non_overlay_rect_vertices, left, ya, *x + *width - left, *height, bg_color);
                         }
                     }
                 }
@@ -698,7 +791,7 @@ impl WgpuRenderer {
                     // Compute effective cursor color (possibly overridden by color cycling)
                     let cycle_color;
                     let effective_color = if self.effects.cursor_color_cycle.enabled && !style.is_hollow() {
-                        let elapsed = self.cursor_color_cycle_start.elapsed().as_secs_f32();
+                        let elapsed = self.frame_time.saturating_duration_since(self.cursor_color_cycle_start).as_secs_f32();
                         let hue = (elapsed * self.effects.cursor_color_cycle.speed) % 1.0;
                         cycle_color = Self::hsl_to_color(hue, self.effects.cursor_color_cycle.saturation, self.effects.cursor_color_cycle.lightness);
                         self.needs_continuous_redraw = true;
@@ -889,6 +982,73 @@ impl WgpuRenderer {
                 render_pass.draw(0..non_overlay_rect_vertices.len() as u32, 0..1);
             }
 
+            // === Step 1 bg image: Per-window background images / watermarks ===
+            // Drawn over the background colors and under everything else,
+            // clipped to the window's text area.  Text over an image
+            // without enough contrast gets a scrim here, or another color
+            // (CONTRAST_FG) when drawn.
+            let mut contrast_fg: HashMap<usize, Color> = HashMap::new();
+            let mut contrast_scrims: Vec<RectVertex> = Vec::new();
+            if !self.window_backgrounds.is_empty() {
+                use crate::core::window_background::background_quads;
+                render_pass.set_pipeline(&self.image_pipeline);
+                render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+                for info in &frame_glyphs.window_infos {
+                    let Some(bg) = self.window_backgrounds.lookup(info.window_id, info.buffer_id) else {
+                        continue;
+                    };
+                    let Some(cached) = self.image_cache.get(bg.image_id) else {
+                        continue;
+                    };
+                    let top = info.bounds.y + info.tab_line_height + info.header_line_height;
+                    let bottom = info.bounds.y + info.bounds.height - info.mode_line_height;
+                    let area = Rect::new(info.bounds.x, top, info.bounds.width, bottom - top);
+                    let quads = background_quads(
+                        bg.scaling, bg.anchor,
+                        cached.width as f32, cached.height as f32, area,
+                    );
+                    if quads.is_empty() {
+                        continue;
+                    }
+                    let color = [1.0, 1.0, 1.0, bg.opacity.clamp(0.0, 1.0)];
+                    let mut vertices: Vec<GlyphVertex> = Vec::with_capacity(quads.len() * 6);
+                    for q in &quads {
+                        let (x0, y0) = (q.rect.x, q.rect.y);
+                        let (x1, y1) = (q.rect.x + q.rect.width, q.rect.y + q.rect.height);
+                        let [u0, v0, u1, v1] = q.uv;
+                        vertices.extend_from_slice(&[
+                            GlyphVertex { position: [x0, y0], tex_coords: [u0, v0], color },
+                            GlyphVertex { position: [x1, y0], tex_coords: [u1, v0], color },
+                            GlyphVertex { position: [x1, y1], tex_coords: [u1, v1], color },
+                            GlyphVertex { position: [x0, y0], tex_coords: [u0, v0], color },
+                            GlyphVertex { position: [x1, y1], tex_coords: [u1, v1], color },
+                            GlyphVertex { position: [x0, y1], tex_coords: [u0, v1], color },
+                        ]);
+                    }
+                    let bg_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Window Background Image Buffer"),
+                        contents: bytemuck::cast_slice(&vertices),
+                        usage: wgpu::BufferUsages::VERTEX,
+                    });
+                    render_pass.set_bind_group(1, &cached.bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, bg_buffer.slice(..));
+                    render_pass.draw(0..vertices.len() as u32, 0..1);
+
+                    if let (true, Some(grid)) = (self.effects.background_contrast.enabled, cached.luminance.as_ref()) {
+                        let (colors, scrims) = background_contrast_fixes(
+                            frame_glyphs, info, &quads, grid,
+                            bg.opacity.clamp(0.0, 1.0),
+                            &self.effects.background_contrast,
+                        );
+                        contrast_fg.extend(colors);
+                        for (rect, color) in &scrims {
+                            self.add_rect(&mut contrast_scrims, rect.x, rect.y, rect.width, rect.height, color);
+                        }
+                    }
+                }
+            }
+            draw_effect!(self, render_pass, "Background Contrast Scrims", contrast_scrims);
+
             // Build shared effect context for all effect functions
             let ctx = super::effect_common::EffectCtx {
                 effects: &self.effects,
@@ -899,6 +1059,7 @@ impl WgpuRenderer {
                 surface_width,
                 surface_height,
                 aurora_start: self.aurora_start,
+                now: self.frame_time,
                 scale_factor: self.scale_factor,
                 logical_w: frame_glyphs.width,
                 logical_h: frame_glyphs.height,
@@ -1021,6 +1182,10 @@ impl WgpuRenderer {
             draw_stateful!(self, render_pass, "Cursor Magnetism",
                 super::cursor_effects::emit_cursor_magnetism(&ctx, &mut self.cursor_magnetism_entries));
 
+            // === Step 1i_beacon: Cursor beacon ===
+            draw_stateful!(self, render_pass, "Cursor Beacon",
+                super::cursor_effects::emit_cursor_beacon(&ctx, &mut self.cursor_beacon_start, &mut self.cursor_beacon_prev));
+
             // === Step 1i2: Window corner fold effect ===
             draw_effect!(self, render_pass, "Corner Fold",
                 super::window_effects::emit_window_corner_fold(&ctx));
@@ -1458,14 +1623,24 @@ impl WgpuRenderer {
                 // Composed glyphs rendered individually (each is unique, no batching)
                 let mut composed_mask_data: Vec<(ComposedGlyphKey, [GlyphVertex; 6])> = Vec::new();
                 let mut composed_color_data: Vec<(ComposedGlyphKey, [GlyphVertex; 6])> = Vec::new();
+                // Text shadows and glows: shifted, tinted copies of mask
+                // glyphs, drawn before any text so they stay underneath
+                let mut shadow_data: Vec<(GlyphKey, [GlyphVertex; 6])> = Vec::new();
+                let mut composed_shadow_data: Vec<(ComposedGlyphKey, [GlyphVertex; 6])> = Vec::new();
 
-                for glyph in &frame_glyphs.glyphs {
-                    if let FrameGlyph::Char { char, composed, x, y, width, ascent, fg, face_id, font_size, is_overlay, overstrike, .. } = glyph {
+                for (glyph_index, glyph) in frame_glyphs.glyphs.iter().enumerate() {
+                    if let FrameGlyph::Char { char, composed, x, y, width, ascent, fg, face_id, font_size, is_overlay, overstrike, sideways, .. } = glyph {
                         if *is_overlay != want_overlay {
                             continue;
                         }
 
                         let face = faces.get(face_id);
+                        // Registered icons may have a color of their own
+                        let icon_color = if composed.is_none() {
+                            glyph_atlas.icons().get(*char).and_then(|icon| icon.color)
+                        } else {
+                            None
+                        };
 
                         // Look up or create the glyph texture
                         let cached_opt = if let Some(ref text) = composed {
@@ -1497,6 +1672,7 @@ impl WgpuRenderer {
                             let glyph_h = cached.height as f32 / sf;
 
                             // Determine effective foreground color.
+                            let fg = icon_color.as_ref().or(contrast_fg.get(&glyph_index)).unwrap_or(fg);
                             // For the character under a filled box cursor, swap to
                             // cursor_fg (inverse video) when cursor is visible.
                             let effective_fg = if cursor_visible {
@@ -1546,7 +1722,7 @@ impl WgpuRenderer {
                                 );
                             }
 
-                            let vertices = [
+                            let mut vertices = [
                                 GlyphVertex { position: [glyph_x, glyph_y], tex_coords: [0.0, 0.0], color },
                                 GlyphVertex { position: [glyph_x + glyph_w, glyph_y], tex_coords: [1.0, 0.0], color },
                                 GlyphVertex { position: [glyph_x + glyph_w, glyph_y + glyph_h], tex_coords: [1.0, 1.0], color },
@@ -1559,7 +1735,7 @@ impl WgpuRenderer {
                             // glyph a second time shifted 1px right.
                             // This matches official Emacs behavior when
                             // a bold font variant is unavailable.
-                            let overstrike_vertices = if *overstrike {
+                            let mut overstrike_vertices = if *overstrike {
                                 let ox = 1.0 / self.scale_factor;
                                 Some([
                                     GlyphVertex { position: [glyph_x + ox, glyph_y], tex_coords: [0.0, 0.0], color },
@@ -1573,12 +1749,58 @@ impl WgpuRenderer {
                                 None
                             };
 
+                            let mut shadow_vertices: Vec<[GlyphVertex; 6]> = match face.and_then(|f| f.text_shadow) {
+                                Some(shadow) if !cached.is_color => shadow.taps().into_iter()
+                                    .map(|(dx, dy, alpha)| {
+                                        let (sx, sy) = (glyph_x + dx, glyph_y + dy);
+                                        let color = [shadow.color.r, shadow.color.g, shadow.color.b, alpha * fade_alpha];
+                                        [
+                                            GlyphVertex { position: [sx, sy], tex_coords: [0.0, 0.0], color },
+                                            GlyphVertex { position: [sx + glyph_w, sy], tex_coords: [1.0, 0.0], color },
+                                            GlyphVertex { position: [sx + glyph_w, sy + glyph_h], tex_coords: [1.0, 1.0], color },
+                                            GlyphVertex { position: [sx, sy], tex_coords: [0.0, 0.0], color },
+                                            GlyphVertex { position: [sx + glyph_w, sy + glyph_h], tex_coords: [1.0, 1.0], color },
+                                            GlyphVertex { position: [sx, sy + glyph_h], tex_coords: [0.0, 1.0], color },
+                                        ]
+                                    })
+                                    .collect(),
+                                _ => Vec::new(),
+                            };
+
+                            // The character straddling the left edge of an
+                            // hscrolled window shows only its right part
+                            if let Some(left) = frame_glyphs.text_clip_left(*x, *y, *width).filter(|_| !*sideways) {
+                                clip_quad_left(&mut vertices, left);
+                                if let Some(ov) = overstrike_vertices.as_mut() {
+                                    clip_quad_left(ov, left);
+                                }
+                                for sv in shadow_vertices.iter_mut() {
+                                    clip_quad_left(sv, left);
+                                }
+                            }
+
+                            // Sideways text in a vertical column: turn the
+                            // quads about the cell, whose row height is the
+                            // column's width
+                            if *sideways {
+                                rotate_sideways(&mut vertices, *x, ya, *width);
+                                if let Some(ov) = overstrike_vertices.as_mut() {
+                                    rotate_sideways(ov, *x, ya, *width);
+                                }
+                                for sv in shadow_vertices.iter_mut() {
+                                    rotate_sideways(sv, *x, ya, *width);
+                                }
+                            }
+
                             if let Some(ref text) = composed {
                                 let ckey = ComposedGlyphKey {
                                     text: text.clone(),
                                     face_id: *face_id,
                                     font_size_bits: font_size.to_bits(),
                                 };
+                                for sv in shadow_vertices {
+                                    composed_shadow_data.push((ckey.clone(), sv));
+                                }
                                 if cached.is_color {
                                     composed_color_data.push((ckey.clone(), vertices));
                                     if let Some(ov) = overstrike_vertices {
@@ -1596,6 +1818,9 @@ impl WgpuRenderer {
                                     face_id: *face_id,
                                     font_size_bits: font_size.to_bits(),
                                 };
+                                for sv in shadow_vertices {
+                                    shadow_data.push((key.clone(), sv));
+                                }
                                 if cached.is_color {
                                     color_data.push((key.clone(), vertices));
                                     if let Some(ov) = overstrike_vertices {
@@ -1624,6 +1849,64 @@ impl WgpuRenderer {
                     }
                 }
 
+                // Draw text shadows under all text, with the glyph pipeline
+                // tinting each copy with the shadow color
+                if !shadow_data.is_empty() {
+                    shadow_data.sort_by(|(a, _), (b, _)| {
+                        a.face_id.cmp(&b.face_id)
+                            .then(a.font_size_bits.cmp(&b.font_size_bits))
+                            .then(a.charcode.cmp(&b.charcode))
+                    });
+
+                    render_pass.set_pipeline(&self.glyph_pipeline);
+                    render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+
+                    let all_vertices: Vec<GlyphVertex> = shadow_data.iter()
+                        .flat_map(|(_, verts)| verts.iter().copied())
+                        .collect();
+
+                    let shadow_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Text Shadow Vertex Buffer"),
+                        contents: bytemuck::cast_slice(&all_vertices),
+                        usage: wgpu::BufferUsages::VERTEX,
+                    });
+
+                    render_pass.set_vertex_buffer(0, shadow_buffer.slice(..));
+
+                    let mut i = 0;
+                    while i < shadow_data.len() {
+                        let (ref key, _) = shadow_data[i];
+                        if let Some(cached) = glyph_atlas.get(key) {
+                            let batch_start = i;
+                            i += 1;
+                            while i < shadow_data.len() && shadow_data[i].0 == *key {
+                                i += 1;
+                            }
+                            render_pass.set_bind_group(1, &cached.bind_group, &[]);
+                            render_pass.draw((batch_start * 6) as u32..(i * 6) as u32, 0..1);
+                        } else {
+                            i += 1;
+                        }
+                    }
+                }
+                if !composed_shadow_data.is_empty() {
+                    render_pass.set_pipeline(&self.glyph_pipeline);
+                    render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+
+                    for (ref ckey, verts) in &composed_shadow_data {
+                        if let Some(cached) = glyph_atlas.get_composed(ckey) {
+                            let vbuf = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                                label: Some("Composed Text Shadow VB"),
+                                contents: bytemuck::cast_slice(verts),
+                                usage: wgpu::BufferUsages::VERTEX,
+                            });
+                            render_pass.set_vertex_buffer(0, vbuf.slice(..));
+                            render_pass.set_bind_group(1, &cached.bind_group, &[]);
+                            render_pass.draw(0..6, 0..1);
+                        }
+                    }
+                }
+
                 // Draw mask glyphs with glyph pipeline (alpha tinted with foreground)
                 // Sort by GlyphKey so identical characters batch into single draw calls,
                 // significantly reducing GPU state changes (set_bind_group calls).
@@ -0,0 +1,538 @@
+//! Magnifier lens overlay for WgpuRenderer.
+//!
+//! The lens is drawn into its own texture at the zoomed size: the region
+//! of the rendered frame around the focus point is scaled up as a base
+//! (backgrounds, images), then the text under the lens is drawn again with
+//! glyphs rasterized at the zoomed font size, so it stays sharp.  The lens
+//! texture is composited on top of the surface with a border.
+
+use super::WgpuRenderer;
+use wgpu::util::DeviceExt;
+use super::super::glyph_atlas::{CachedGlyph, ComposedGlyphKey, GlyphKey, WgpuGlyphAtlas};
+use super::super::vertex::{GlyphVertex, RectVertex, Uniforms};
+use crate::core::face::Face;
+use crate::core::frame_glyphs::{CursorStyle, FrameGlyph, FrameGlyphBuffer};
+use crate::core::types::Color;
+use crate::effect_config::MagnifierConfig;
+use std::collections::HashMap;
+
+/// Number of segments used to approximate a circular lens.
+const LENS_SEGMENTS: usize = 64;
+
+/// Placement of the lens on screen and of the sampled source region, in
+/// logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct LensGeometry {
+    /// Lens center on screen.
+    pub center: (f32, f32),
+    /// Lens half extents on screen.
+    pub half: (f32, f32),
+    /// Center of the magnified source region.
+    pub source: (f32, f32),
+    pub zoom: f32,
+}
+
+impl LensGeometry {
+    /// Place a lens magnifying the area around FOCUS on a screen of
+    /// SCREEN logical pixels.  The lens is kept on screen; the source
+    /// region is clamped so the lens never samples outside the frame.
+    pub(super) fn new(config: &MagnifierConfig, focus: (f32, f32), screen: (f32, f32)) -> Self {
+        let zoom = config.zoom.clamp(1.0, 8.0);
+        let (w, h) = if config.circular {
+            let d = config.width.max(16.0);
+            (d, d)
+        } else {
+            (config.width.max(16.0), config.height.max(16.0))
+        };
+        let half = ((w / 2.0).min(screen.0 / 2.0), (h / 2.0).min(screen.1 / 2.0));
+        let center = (
+            focus.0.clamp(half.0, (screen.0 - half.0).max(half.0)),
+            focus.1.clamp(half.1, (screen.1 - half.1).max(half.1)),
+        );
+        let src_half = (half.0 / zoom, half.1 / zoom);
+        let source = (
+            focus.0.clamp(src_half.0, (screen.0 - src_half.0).max(src_half.0)),
+            focus.1.clamp(src_half.1, (screen.1 - src_half.1).max(src_half.1)),
+        );
+        Self { center, half, source, zoom }
+    }
+
+    /// Size of the lens image in logical pixels.
+    pub(super) fn size(&self) -> (f32, f32) {
+        (self.half.0 * 2.0, self.half.1 * 2.0)
+    }
+
+    /// Magnified region of the frame as (x0, y0, x1, y1).
+    pub(super) fn source_rect(&self) -> (f32, f32, f32, f32) {
+        let (hw, hh) = (self.half.0 / self.zoom, self.half.1 / self.zoom);
+        (self.source.0 - hw, self.source.1 - hh, self.source.0 + hw, self.source.1 + hh)
+    }
+
+    /// Position in the lens image of frame point (X, Y).
+    pub(super) fn lens_point(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.half.0 + (x - self.source.0) * self.zoom,
+            self.half.1 + (y - self.source.1) * self.zoom,
+        )
+    }
+
+    /// Texture coordinates in the rendered frame of the source point under
+    /// offset (DX, DY) from the lens center.
+    fn uv(&self, dx: f32, dy: f32, screen: (f32, f32)) -> [f32; 2] {
+        [
+            (self.source.0 + dx / self.zoom) / screen.0,
+            (self.source.1 + dy / self.zoom) / screen.1,
+        ]
+    }
+
+    /// Quad filling the lens image with the scaled-up source region of
+    /// the rendered frame.
+    pub(super) fn source_vertices(&self, screen: (f32, f32)) -> Vec<GlyphVertex> {
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let (hw, hh) = self.half;
+        let vertex = |dx: f32, dy: f32| GlyphVertex {
+            position: [hw + dx, hh + dy],
+            tex_coords: self.uv(dx, dy, screen),
+            color: white,
+        };
+        vec![
+            vertex(-hw, -hh), vertex(hw, -hh), vertex(hw, hh),
+            vertex(-hw, -hh), vertex(hw, hh), vertex(-hw, hh),
+        ]
+    }
+
+    /// Textured triangles covering the lens on screen, sampling the lens
+    /// image.
+    pub(super) fn lens_vertices(&self, circular: bool) -> Vec<GlyphVertex> {
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let vertex = |dx: f32, dy: f32| GlyphVertex {
+            position: [self.center.0 + dx, self.center.1 + dy],
+            tex_coords: [0.5 + dx / (self.half.0 * 2.0), 0.5 + dy / (self.half.1 * 2.0)],
+            color: white,
+        };
+        if !circular {
+            let (hw, hh) = self.half;
+            return vec![
+                vertex(-hw, -hh), vertex(hw, -hh), vertex(hw, hh),
+                vertex(-hw, -hh), vertex(hw, hh), vertex(-hw, hh),
+            ];
+        }
+        let r = self.half.0.min(self.half.1);
+        let mut vertices = Vec::with_capacity(LENS_SEGMENTS * 3);
+        for i in 0..LENS_SEGMENTS {
+            let a0 = i as f32 / LENS_SEGMENTS as f32 * std::f32::consts::TAU;
+            let a1 = (i + 1) as f32 / LENS_SEGMENTS as f32 * std::f32::consts::TAU;
+            vertices.push(vertex(0.0, 0.0));
+            vertices.push(vertex(r * a0.cos(), r * a0.sin()));
+            vertices.push(vertex(r * a1.cos(), r * a1.sin()));
+        }
+        vertices
+    }
+
+    /// Border ring (circular) or frame (rectangular) of WIDTH around the lens.
+    pub(super) fn border_vertices(&self, circular: bool, width: f32, color: &Color) -> Vec<RectVertex> {
+        let color = [color.r, color.g, color.b, color.a];
+        let (cx, cy) = self.center;
+        let mut vertices = Vec::new();
+        let mut quad = |p: [[f32; 2]; 4]| {
+            for i in [0, 1, 2, 0, 2, 3] {
+                vertices.push(RectVertex { position: p[i], color });
+            }
+        };
+        if circular {
+            let r0 = self.half.0.min(self.half.1);
+            let r1 = r0 + width;
+            for i in 0..LENS_SEGMENTS {
+                let a0 = i as f32 / LENS_SEGMENTS as f32 * std::f32::consts::TAU;
+                let a1 = (i + 1) as f32 / LENS_SEGMENTS as f32 * std::f32::consts::TAU;
+                let (c0, s0, c1, s1) = (a0.cos(), a0.sin(), a1.cos(), a1.sin());
+                quad([
+                    [cx + r0 * c0, cy + r0 * s0],
+                    [cx + r1 * c0, cy + r1 * s0],
+                    [cx + r1 * c1, cy + r1 * s1],
+                    [cx + r0 * c1, cy + r0 * s1],
+                ]);
+            }
+        } else {
+            let (hw, hh) = self.half;
+            let (x0, y0, x1, y1) = (cx - hw - width, cy - hh - width, cx + hw + width, cy + hh + width);
+            quad([[x0, y0], [x1, y0], [x1, cy - hh], [x0, cy - hh]]);
+            quad([[x0, cy + hh], [x1, cy + hh], [x1, y1], [x0, y1]]);
+            quad([[x0, cy - hh], [cx - hw, cy - hh], [cx - hw, cy + hh], [x0, cy + hh]]);
+            quad([[cx + hw, cy - hh], [x1, cy - hh], [x1, cy + hh], [cx + hw, cy + hh]]);
+        }
+        vertices
+    }
+}
+
+/// Atlas key of a glyph drawn under the lens
+enum LensGlyphKey {
+    Char(GlyphKey),
+    Composed(ComposedGlyphKey),
+}
+
+impl LensGlyphKey {
+    fn get<'a>(&self, atlas: &'a WgpuGlyphAtlas) -> Option<&'a CachedGlyph> {
+        match self {
+            LensGlyphKey::Char(key) => atlas.get(key),
+            LensGlyphKey::Composed(key) => atlas.get_composed(key),
+        }
+    }
+}
+
+impl WgpuRenderer {
+    /// Draw the magnifier lens around FOCUS (logical pixels).  The scaled
+    /// base comes from the already-rendered frame bound in SOURCE; the
+    /// text of FRAME under the lens is drawn again at the zoomed size.
+    pub fn render_magnifier(
+        &self,
+        view: &wgpu::TextureView,
+        source: &wgpu::BindGroup,
+        frame: &FrameGlyphBuffer,
+        glyph_atlas: &mut WgpuGlyphAtlas,
+        faces: &HashMap<u32, Face>,
+        cursor_visible: bool,
+        focus: (f32, f32),
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        let config = &self.effects.magnifier;
+        if !config.enabled {
+            return;
+        }
+        let logical_w = surface_width as f32 / self.scale_factor;
+        let logical_h = surface_height as f32 / self.scale_factor;
+        if logical_w <= 0.0 || logical_h <= 0.0 {
+            return;
+        }
+
+        let screen = (logical_w, logical_h);
+        let lens = LensGeometry::new(config, focus, screen);
+        let zoom = lens.zoom;
+        let (lens_w, lens_h) = lens.size();
+        let sf = self.scale_factor;
+        let (lens_texture_w, lens_texture_h) = (
+            (lens_w * sf).ceil().max(1.0) as u32,
+            (lens_h * sf).ceil().max(1.0) as u32,
+        );
+
+        // Text under the lens, in lens image coordinates
+        let (sx0, sy0, sx1, sy1) = lens.source_rect();
+        let inverse = frame.cursor_inverse.as_ref().filter(|_| cursor_visible);
+        let mut back_rects: Vec<RectVertex> = Vec::new();
+        let mut front_rects: Vec<RectVertex> = Vec::new();
+        let mut text: Vec<(LensGlyphKey, f32, f32, [f32; 4])> = Vec::new();
+        let mut scaled_faces: HashMap<(u32, u32), Face> = HashMap::new();
+        for glyph in &frame.glyphs {
+            match glyph {
+                FrameGlyph::Char { char: ch, composed, x, y, width, height, ascent,
+                                   fg, bg, face_id, font_size,
+                                   underline, underline_color, .. } => {
+                    if *x + *width < sx0 || *x > sx1 || *y + *height < sy0 || *y > sy1 {
+                        continue;
+                    }
+                    let (lx, ly) = lens.lens_point(*x, *y);
+                    let at_cursor = inverse
+                        .filter(|inv| (*x - inv.x).abs() < 1.0 && (*y - inv.y).abs() < 1.0);
+                    // Cover the scaled-up text of the base with the cell background
+                    let cell_bg = match at_cursor {
+                        Some(inv) => &inv.cursor_bg,
+                        None => bg.as_ref().unwrap_or(&frame.background),
+                    };
+                    self.add_rect(&mut back_rects, lx, ly, *width * zoom, *height * zoom, cell_bg);
+
+                    let fg = at_cursor.map_or(fg, |inv| &inv.cursor_fg);
+                    let size = *font_size * zoom;
+                    let face = scaled_faces.entry((*face_id, size.to_bits())).or_insert_with(|| {
+                        let mut face = faces.get(face_id).cloned().unwrap_or_else(|| Face::new(*face_id));
+                        face.font_size = size;
+                        face
+                    });
+                    let key = match composed {
+                        Some(cluster) => {
+                            glyph_atlas.get_or_create_composed(
+                                &self.device, &self.queue, cluster, *face_id, size.to_bits(), Some(face),
+                            );
+                            LensGlyphKey::Composed(ComposedGlyphKey {
+                                text: cluster.clone(),
+                                face_id: *face_id,
+                                font_size_bits: size.to_bits(),
+                            })
+                        }
+                        None => {
+                            let key = GlyphKey {
+                                charcode: *ch as u32,
+                                face_id: *face_id,
+                                font_size_bits: size.to_bits(),
+                            };
+                            glyph_atlas.get_or_create(&self.device, &self.queue, &key, Some(face));
+                            LensGlyphKey::Char(key)
+                        }
+                    };
+                    let baseline = ly + *ascent * zoom;
+                    text.push((key, lx, baseline, [fg.r, fg.g, fg.b, fg.a]));
+
+                    if *underline > 0 {
+                        let uc = underline_color.as_ref().unwrap_or(fg);
+                        self.add_rect(&mut front_rects, lx, baseline + 2.0 * zoom, *width * zoom, zoom, uc);
+                    }
+                }
+                FrameGlyph::Cursor { x, y, width, height, style, color, .. } if cursor_visible => {
+                    if *x + *width < sx0 || *x > sx1 || *y + *height < sy0 || *y > sy1 {
+                        continue;
+                    }
+                    let (lx, ly) = lens.lens_point(*x, *y);
+                    let (w, h) = (*width * zoom, *height * zoom);
+                    match style {
+                        // Drawn as the cell background of the character under it
+                        CursorStyle::FilledBox => {}
+                        CursorStyle::Bar(bar_w) => {
+                            self.add_rect(&mut front_rects, lx, ly, *bar_w * zoom, h, color);
+                        }
+                        CursorStyle::Hbar(hbar_h) => {
+                            self.add_rect(&mut front_rects, lx, ly + h - *hbar_h * zoom, w, *hbar_h * zoom, color);
+                        }
+                        CursorStyle::Hollow => {
+                            self.add_rect(&mut front_rects, lx, ly, w, zoom, color);
+                            self.add_rect(&mut front_rects, lx, ly + h - zoom, w, zoom, color);
+                            self.add_rect(&mut front_rects, lx, ly, zoom, h, color);
+                            self.add_rect(&mut front_rects, lx + w - zoom, ly, zoom, h, color);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Glyph quads; atlas metrics are in physical pixels
+        let mut glyph_vertices: Vec<GlyphVertex> = Vec::with_capacity(text.len() * 6);
+        let mut glyph_draws: Vec<(&wgpu::BindGroup, bool)> = Vec::with_capacity(text.len());
+        for (key, x, baseline, color) in &text {
+            let Some(cached) = key.get(glyph_atlas) else {
+                continue;
+            };
+            let gx = *x + cached.bearing_x / sf;
+            let gy = *baseline - cached.bearing_y / sf;
+            let gw = cached.width as f32 / sf;
+            let gh = cached.height as f32 / sf;
+            let color = if cached.is_color { [1.0, 1.0, 1.0, 1.0] } else { *color };
+            glyph_vertices.extend_from_slice(&[
+                GlyphVertex { position: [gx, gy], tex_coords: [0.0, 0.0], color },
+                GlyphVertex { position: [gx + gw, gy], tex_coords: [1.0, 0.0], color },
+                GlyphVertex { position: [gx + gw, gy + gh], tex_coords: [1.0, 1.0], color },
+                GlyphVertex { position: [gx, gy], tex_coords: [0.0, 0.0], color },
+                GlyphVertex { position: [gx + gw, gy + gh], tex_coords: [1.0, 1.0], color },
+                GlyphVertex { position: [gx, gy + gh], tex_coords: [0.0, 1.0], color },
+            ]);
+            glyph_draws.push((&cached.bind_group, cached.is_color));
+        }
+
+        let vertex_buffer = |label: &str, contents: &[u8]| {
+            (!contents.is_empty()).then(|| {
+                self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents,
+                    usage: wgpu::BufferUsages::VERTEX,
+                })
+            })
+        };
+        let source_vertices = lens.source_vertices(screen);
+        let source_buffer = vertex_buffer("Magnifier Source Buffer", bytemuck::cast_slice(&source_vertices));
+        let back_buffer = vertex_buffer("Magnifier Background Buffer", bytemuck::cast_slice(&back_rects));
+        let glyph_buffer = vertex_buffer("Magnifier Glyph Buffer", bytemuck::cast_slice(&glyph_vertices));
+        let front_buffer = vertex_buffer("Magnifier Decoration Buffer", bytemuck::cast_slice(&front_rects));
+
+        // Pass 1: the lens image, in lens coordinates
+        let (_lens_texture, lens_view) = self.create_offscreen_texture(lens_texture_w, lens_texture_h);
+        let lens_bind_group = self.create_texture_bind_group(&lens_view);
+        let uniforms = Uniforms {
+            screen_size: [lens_w, lens_h],
+            _padding: [0.0, 0.0],
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Magnifier Lens Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Magnifier Lens Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &lens_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            if let Some(ref buffer) = source_buffer {
+                pass.set_pipeline(&self.image_pipeline);
+                pass.set_bind_group(1, source, &[]);
+                pass.set_vertex_buffer(0, buffer.slice(..));
+                pass.draw(0..source_vertices.len() as u32, 0..1);
+            }
+            if let Some(ref buffer) = back_buffer {
+                pass.set_pipeline(&self.rect_pipeline);
+                pass.set_vertex_buffer(0, buffer.slice(..));
+                pass.draw(0..back_rects.len() as u32, 0..1);
+            }
+            if let Some(ref buffer) = glyph_buffer {
+                pass.set_vertex_buffer(0, buffer.slice(..));
+                for (i, (bind_group, is_color)) in glyph_draws.iter().enumerate() {
+                    // Color glyphs (emoji) are drawn as images, masks tinted
+                    pass.set_pipeline(if *is_color { &self.opaque_image_pipeline } else { &self.glyph_pipeline });
+                    pass.set_bind_group(1, *bind_group, &[]);
+                    let first = i as u32 * 6;
+                    pass.draw(first..first + 6, 0..1);
+                }
+            }
+            if let Some(ref buffer) = front_buffer {
+                pass.set_pipeline(&self.rect_pipeline);
+                pass.set_vertex_buffer(0, buffer.slice(..));
+                pass.draw(0..front_rects.len() as u32, 0..1);
+            }
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        // Pass 2: composite the lens image and its border onto the surface
+        let uniforms = Uniforms {
+            screen_size: [logical_w, logical_h],
+            _padding: [0.0, 0.0],
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+        let lens_vertices = lens.lens_vertices(config.circular);
+        let (r, g, b) = config.border_color;
+        let border_color = Color::new(r, g, b, 1.0).srgb_to_linear();
+        let border_vertices = if config.border_width > 0.0 {
+            lens.border_vertices(config.circular, config.border_width, &border_color)
+        } else {
+            Vec::new()
+        };
+        let lens_buffer = vertex_buffer("Magnifier Lens Buffer", bytemuck::cast_slice(&lens_vertices));
+        let border_buffer = vertex_buffer("Magnifier Border Buffer", bytemuck::cast_slice(&border_vertices));
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Magnifier Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Magnifier Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            if let Some(ref buffer) = lens_buffer {
+                pass.set_pipeline(&self.image_pipeline);
+                pass.set_bind_group(1, &lens_bind_group, &[]);
+                pass.set_vertex_buffer(0, buffer.slice(..));
+                pass.draw(0..lens_vertices.len() as u32, 0..1);
+            }
+            if let Some(ref buffer) = border_buffer {
+                pass.set_pipeline(&self.rect_pipeline);
+                pass.set_vertex_buffer(0, buffer.slice(..));
+                pass.draw(0..border_vertices.len() as u32, 0..1);
+            }
+        }
+        self.queue.submit(Some(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(circular: bool, zoom: f32) -> MagnifierConfig {
+        MagnifierConfig { enabled: true, circular, width: 200.0, height: 100.0, zoom, ..Default::default() }
+    }
+
+    #[test]
+    fn lens_is_kept_on_screen() {
+        let lens = LensGeometry::new(&config(false, 2.0), (10.0, 990.0), (800.0, 1000.0));
+        assert_eq!(lens.half, (100.0, 50.0));
+        assert_eq!(lens.center, (100.0, 950.0));
+        // Source region (100x50) is clamped independently.
+        assert_eq!(lens.source, (50.0, 975.0));
+    }
+
+    #[test]
+    fn circular_lens_uses_width_as_diameter() {
+        let lens = LensGeometry::new(&config(true, 4.0), (400.0, 300.0), (800.0, 600.0));
+        assert_eq!(lens.half, (100.0, 100.0));
+        assert_eq!(lens.center, (400.0, 300.0));
+        let vertices = lens.lens_vertices(true);
+        assert_eq!(vertices.len(), LENS_SEGMENTS * 3);
+        // The rim samples the edge of the lens image
+        let rim = vertices[1];
+        assert!((rim.position[0] - 500.0).abs() < 1e-3);
+        assert!((rim.tex_coords[0] - 1.0).abs() < 1e-5);
+        assert!((vertices[0].tex_coords[0] - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn rect_lens_maps_corners() {
+        let lens = LensGeometry::new(&config(false, 2.0), (400.0, 300.0), (800.0, 600.0));
+        let vertices = lens.lens_vertices(false);
+        assert_eq!(vertices.len(), 6);
+        assert_eq!(vertices[0].position, [300.0, 250.0]);
+        assert_eq!(vertices[0].tex_coords, [0.0, 0.0]);
+        assert_eq!(vertices[2].tex_coords, [1.0, 1.0]);
+    }
+
+    #[test]
+    fn source_quad_samples_zoomed_region() {
+        let lens = LensGeometry::new(&config(false, 2.0), (400.0, 300.0), (800.0, 600.0));
+        let vertices = lens.source_vertices((800.0, 600.0));
+        assert_eq!(vertices.len(), 6);
+        // 200x100 lens image covers 100x50 of the frame
+        assert_eq!(vertices[0].position, [0.0, 0.0]);
+        assert_eq!(vertices[0].tex_coords, [350.0 / 800.0, 275.0 / 600.0]);
+        assert_eq!(vertices[2].position, [200.0, 100.0]);
+        assert_eq!(vertices[2].tex_coords, [450.0 / 800.0, 325.0 / 600.0]);
+    }
+
+    #[test]
+    fn frame_points_map_into_lens_image() {
+        let lens = LensGeometry::new(&config(false, 4.0), (400.0, 300.0), (800.0, 600.0));
+        assert_eq!(lens.size(), (200.0, 100.0));
+        assert_eq!(lens.source_rect(), (375.0, 287.5, 425.0, 312.5));
+        assert_eq!(lens.lens_point(400.0, 300.0), (100.0, 50.0));
+        // A 10px glyph cell at the left edge of the source is 40px wide
+        assert_eq!(lens.lens_point(375.0, 287.5), (0.0, 0.0));
+        assert_eq!(lens.lens_point(385.0, 287.5), (40.0, 0.0));
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        let lens = LensGeometry::new(&config(true, 0.5), (400.0, 300.0), (800.0, 600.0));
+        assert_eq!(lens.zoom, 1.0);
+        let lens = LensGeometry::new(&config(true, 20.0), (400.0, 300.0), (800.0, 600.0));
+        assert_eq!(lens.zoom, 8.0);
+    }
+
+    #[test]
+    fn border_surrounds_lens() {
+        let lens = LensGeometry::new(&config(false, 2.0), (400.0, 300.0), (800.0, 600.0));
+        let color = Color::new(1.0, 0.0, 0.0, 1.0);
+        let vertices = lens.border_vertices(false, 3.0, &color);
+        assert_eq!(vertices.len(), 24);
+        let min_x = vertices.iter().map(|v| v.position[0]).fold(f32::MAX, f32::min);
+        let max_y = vertices.iter().map(|v| v.position[1]).fold(f32::MIN, f32::max);
+        assert_eq!(min_x, 297.0);
+        assert_eq!(max_y, 353.0);
+    }
+}
@@ -4,7 +4,10 @@ use super::WgpuRenderer;
 use wgpu::util::DeviceExt;
 use super::super::vertex::{GlyphVertex};
 use crate::core::types::{Color};
+use crate::core::chart::{ChartHotSpot, ChartSpec};
+use crate::core::image_edit::ImageEdits;
 use super::super::image_cache::ImageCache;
+use super::super::memory::{GpuMemoryStats, MemoryBudget};
 #[cfg(feature = "video")]
 use super::super::video_cache::VideoCache;
 use crate::core::scene::FloatingWebKit;
@@ -51,6 +54,17 @@ impl WgpuRenderer {
         ImageCache::query_file_dimensions(path).map(|d| (d.width, d.height))
     }
 
+    /// Apply EDITS to the image file at SOURCE and save the result as a
+    /// PNG file at OUTPUT (blocks while decoding and encoding)
+    pub fn export_edited_image(source: &str, edits: &ImageEdits, output: &str) -> Result<(u32, u32), String> {
+        ImageCache::export_edited(source, edits, output).map(|d| (d.width, d.height))
+    }
+
+    /// Draw the chart SPEC as PNG data, with the area of each x position
+    pub fn render_chart_png(spec: &ChartSpec) -> Result<(Vec<u8>, Vec<ChartHotSpot>), String> {
+        ImageCache::render_chart_png(spec)
+    }
+
     /// Query image data dimensions (fast - reads header only)
     pub fn query_image_data_size(data: &[u8]) -> Option<(u32, u32)> {
         ImageCache::query_data_dimensions(data).map(|d| (d.width, d.height))
@@ -71,11 +85,35 @@ impl WgpuRenderer {
         self.image_cache.free(id)
     }
 
+    /// Set the edits of image ID and decode it again.  Returns the size
+    /// the edited image will have, or None if ID can't be edited.
+    pub fn set_image_edits(&mut self, id: u32, edits: Option<ImageEdits>) -> Option<(u32, u32)> {
+        self.image_cache.set_edits(id, edits).map(|d| (d.width, d.height))
+    }
+
     /// Process pending decoded images (call each frame before rendering)
     pub fn process_pending_images(&mut self) {
         self.image_cache.process_pending(&self.device, &self.queue);
     }
 
+    /// Apply BUDGET to the image cache and the upload buffer pool
+    pub fn set_memory_budget(&mut self, budget: &MemoryBudget) {
+        self.image_cache.set_memory_budget(budget.images);
+        self.image_cache.staging_pool().set_limit(budget.staging);
+    }
+
+    /// Fill in the image, video and staging buffer usage of STATS
+    pub fn collect_memory_stats(&self, stats: &mut GpuMemoryStats) {
+        stats.image_bytes = self.image_cache.total_memory();
+        stats.image_count = self.image_cache.len();
+        stats.image_evictions = self.image_cache.evictions();
+        stats.staging_bytes = self.image_cache.staging_pool().pooled_bytes();
+        #[cfg(feature = "video")]
+        {
+            stats.video_bytes = self.video_cache.memory_usage();
+        }
+    }
+
     /// Load video from file path (async - returns immediately)
     /// Returns video ID, frames decode in background
     #[cfg(feature = "video")]
@@ -142,6 +180,12 @@ impl WgpuRenderer {
         self.video_cache.has_playing_videos()
     }
 
+    /// Ids of the videos currently playing
+    #[cfg(feature = "video")]
+    pub fn playing_videos(&self) -> Vec<u32> {
+        self.video_cache.playing_videos()
+    }
+
     /// Get cached video for rendering
     #[cfg(feature = "video")]
     pub fn get_video(&self, id: u32) -> Option<&super::super::video_cache::CachedVideo> {
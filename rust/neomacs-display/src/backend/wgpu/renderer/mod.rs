@@ -27,6 +27,9 @@ mod cursor_effects;
 mod effect_common;
 mod window_effects;
 mod pattern_effects;
+mod magnifier;
+mod post_shader;
+mod crt;
 
 /// GPU-accelerated renderer using wgpu.
 pub struct WgpuRenderer {
@@ -104,6 +107,9 @@ pub struct WgpuRenderer {
     pub(super) click_halos: Vec<ClickHaloEntry>,
     pub(super) edge_snaps: Vec<EdgeSnapEntry>,
     pub(super) cursor_magnetism_entries: Vec<(f32, f32, std::time::Instant)>, // x, y, time
+    pub(super) cursor_beacon_start: Option<std::time::Instant>,
+    /// Selected window of the previous frame: (window_id, buffer_id, window_start)
+    pub(super) cursor_beacon_prev: Option<(i64, u64, i64)>,
     pub(super) cursor_comet_positions: Vec<(f32, f32, f32, f32, std::time::Instant)>, // x, y, w, h, time
     pub(super) cursor_particles: Vec<CursorParticle>,
     pub(super) cursor_particles_prev_pos: Option<(f32, f32)>,
@@ -150,6 +156,19 @@ pub struct WgpuRenderer {
     pub(super) rain_last_spawn: std::time::Instant,
     pub(super) cursor_ripple_waves: Vec<RippleWaveEntry>,
     pub(super) aurora_start: std::time::Instant,
+    /// Frame time from the render thread's animation clock; effects
+    /// sample their animations at this instant
+    pub(super) frame_time: std::time::Instant,
+    /// Per-window and per-buffer background images
+    pub window_backgrounds: crate::core::window_background::WindowBackgrounds,
+    /// User WGSL post-processing shader
+    post_shader: Option<post_shader::PostShader>,
+    /// Built-in CRT effect pass, created on first use
+    crt_pass: Option<post_shader::PostPass>,
+    /// Regions the CRT effect applies to this frame (logical pixels)
+    crt_regions: Vec<Rect>,
+    /// Intermediate textures of the post-processing chain
+    post_targets: Vec<(wgpu::Texture, u32, u32)>,
 }
 
 /// Entry for an active scroll momentum indicator
@@ -651,7 +670,7 @@ impl WgpuRenderer {
 
         // Create video cache
         #[cfg(feature = "video")]
-        let mut video_cache = VideoCache::new();
+        let mut video_cache = VideoCache::with_staging_pool(image_cache.staging_pool().clone());
         #[cfg(feature = "video")]
         video_cache.init_gpu(&device);
 
@@ -824,6 +843,8 @@ impl WgpuRenderer {
             click_halos: Vec::new(),
             edge_snaps: Vec::new(),
             cursor_magnetism_entries: Vec::new(),
+            cursor_beacon_start: None,
+            cursor_beacon_prev: None,
             cursor_comet_positions: Vec::new(),
             cursor_particles: Vec::new(),
             cursor_particles_prev_pos: None,
@@ -869,6 +890,12 @@ impl WgpuRenderer {
             rain_last_spawn: std::time::Instant::now(),
             cursor_ripple_waves: Vec::new(),
             aurora_start: std::time::Instant::now(),
+            frame_time: std::time::Instant::now(),
+            window_backgrounds: crate::core::window_background::WindowBackgrounds::new(),
+            post_shader: None,
+            crt_pass: None,
+            crt_regions: Vec::new(),
+            post_targets: Vec::new(),
         }
     }
 
@@ -956,6 +983,18 @@ impl WgpuRenderer {
         self.scale_factor = scale_factor;
     }
 
+    /// Set the frame time that effects and fades are sampled at.  The
+    /// render thread passes its animation clock's tick here once per frame.
+    pub fn set_frame_time(&mut self, now: std::time::Instant) {
+        self.frame_time = now;
+    }
+
+    /// Pulse the cursor beacon now, whether or not its automatic
+    /// triggers are enabled
+    pub fn trigger_cursor_beacon(&mut self) {
+        self.cursor_beacon_start = Some(self.frame_time);
+    }
+
     /// Get the glyph bind group layout for creating glyph bind groups
     pub fn glyph_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
         &self.glyph_bind_group_layout
@@ -6,10 +6,19 @@ use wgpu::util::DeviceExt;
 use super::super::vertex::{GlyphVertex, RectVertex, RoundedRectVertex, Uniforms};
 use crate::core::types::{AnimatedCursor, Color, Rect};
 use crate::core::frame_glyphs::{CursorStyle, FrameGlyph, FrameGlyphBuffer};
-use super::super::glyph_atlas::{GlyphKey, WgpuGlyphAtlas};
+use super::super::glyph_atlas::{ComposedGlyphKey, GlyphKey, WgpuGlyphAtlas};
 use crate::core::face::Face;
+use crate::render_thread::AgendaTimelineState;
+use crate::render_thread::CharPickerState;
+use crate::render_thread::{hsv_to_rgb, ColorPickerState};
+use crate::render_thread::CommandPaletteState;
+use crate::render_thread::DatePickerState;
+use crate::render_thread::DirViewState;
+use crate::render_thread::GraphViewState;
 use crate::render_thread::PopupMenuState;
+use crate::render_thread::ProcessMonitorState;
 use crate::render_thread::TooltipState;
+use crate::render_thread::TypeaheadEcho;
 use std::collections::HashMap;
 
 impl WgpuRenderer {
@@ -777,6 +786,1712 @@ impl WgpuRenderer {
         }
     }
 
+    /// Render the character picker overlay: search line, grid of
+    /// character previews and the selected character's name.
+    pub(crate) fn render_char_picker(
+        &self,
+        view: &wgpu::TextureView,
+        picker: &CharPickerState,
+        glyph_atlas: &mut WgpuGlyphAtlas,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        use wgpu::util::DeviceExt;
+
+        /// Face id of the preview glyphs, so their larger rasterization
+        /// is cached apart from text of the same characters.
+        const PREVIEW_FACE_ID: u32 = u32::MAX - 2;
+
+        let logical_w = surface_width as f32 / self.scale_factor;
+        let logical_h = surface_height as f32 / self.scale_factor;
+        let uniforms = Uniforms {
+            screen_size: [logical_w, logical_h],
+            _padding: [0.0, 0.0],
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+        let (fg_r, fg_g, fg_b) = picker.face_fg.unwrap_or((0.9, 0.9, 0.9));
+        let (bg_r, bg_g, bg_b) = picker.face_bg.unwrap_or((0.15, 0.15, 0.18));
+        let bg_color = Color::new(bg_r, bg_g, bg_b, 0.97).srgb_to_linear();
+        let border_color = Color::new(
+            (bg_r * 0.6 + 0.15).min(1.0),
+            (bg_g * 0.6 + 0.15).min(1.0),
+            (bg_b * 0.6 + 0.15).min(1.0),
+            1.0,
+        ).srgb_to_linear();
+        let field_color = Color::new(bg_r * 0.8, bg_g * 0.8, bg_b * 0.8, 1.0).srgb_to_linear();
+        let selected_color = Color::new(
+            bg_r * 0.5 + fg_r * 0.3,
+            bg_g * 0.5 + fg_g * 0.3,
+            bg_b * 0.5 + fg_b * 0.3,
+            0.9,
+        ).srgb_to_linear();
+        let text_color = {
+            let c = Color::new(fg_r, fg_g, fg_b, 1.0).srgb_to_linear();
+            [c.r, c.g, c.b, c.a]
+        };
+        let dim_color = {
+            let c = Color::new(
+                fg_r * 0.6 + bg_r * 0.4,
+                fg_g * 0.6 + bg_g * 0.4,
+                fg_b * 0.6 + bg_b * 0.4,
+                1.0,
+            ).srgb_to_linear();
+            [c.r, c.g, c.b, c.a]
+        };
+
+        let (px, py, pw, ph) = picker.bounds;
+        let pad = picker.padding;
+        let line_h = picker.line_height;
+        let cell = picker.cell_size;
+        let cells = picker.visible_cells();
+
+        // === Pass 1: Background rectangles ===
+        let mut rect_vertices: Vec<RectVertex> = Vec::new();
+        for i in 1..=4 {
+            let offset = i as f32 * 1.5;
+            let alpha = 0.12 * (1.0 - (i - 1) as f32 / 4.0);
+            self.add_rect(&mut rect_vertices, px + offset, py + offset, pw, ph, &Color::new(0.0, 0.0, 0.0, alpha));
+        }
+        self.add_rect(&mut rect_vertices, px, py, pw, ph, &bg_color);
+        self.add_rect(&mut rect_vertices, px, py, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py + ph - 1.0, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py, 1.0, ph, &border_color);
+        self.add_rect(&mut rect_vertices, px + pw - 1.0, py, 1.0, ph, &border_color);
+        // Search field
+        self.add_rect(&mut rect_vertices, px + pad, py + pad, pw - 2.0 * pad, line_h - 4.0, &field_color);
+        // Selected cell
+        if let Some(&(_, cx, cy)) = cells.iter().find(|c| c.0 == picker.selected) {
+            self.add_rect(&mut rect_vertices, cx + 1.0, cy + 1.0, cell - 2.0, cell - 2.0, &selected_color);
+        }
+        // Separator above the name line
+        let name_y = py + ph - pad - line_h;
+        self.add_rect(&mut rect_vertices, px + pad, name_y, pw - 2.0 * pad, 1.0, &border_color);
+
+        let rect_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Char Picker Rect Buffer"),
+            contents: bytemuck::cast_slice(&rect_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Char Picker Rect Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Char Picker Rect Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.rect_pipeline);
+            pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            pass.set_vertex_buffer(0, rect_buffer.slice(..));
+            pass.draw(0..rect_vertices.len() as u32, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        // === Pass 2: Search line and name line text ===
+        let font_size = glyph_atlas.default_font_size();
+        let char_width = font_size * 0.6;
+        let font_size_bits = 0.0_f32.to_bits();
+        let mut overlay_glyphs: Vec<(GlyphKey, f32, f32, [f32; 4])> = Vec::new();
+        let max_chars = ((pw - 4.0 * pad) / char_width).max(0.0) as usize;
+        let add_line = |glyphs: &mut Vec<(GlyphKey, f32, f32, [f32; 4])>,
+                            atlas: &mut WgpuGlyphAtlas,
+                            text: &str, x: f32, y: f32, color: [f32; 4]| {
+            for (ci, ch) in text.chars().take(max_chars).enumerate() {
+                let key = GlyphKey { charcode: ch as u32, face_id: 0, font_size_bits };
+                atlas.get_or_create(&self.device, &self.queue, &key, None);
+                glyphs.push((key, x + ci as f32 * char_width, y, color));
+            }
+        };
+
+        let prompt = format!("{}: ", picker.title.as_deref().unwrap_or("Search"));
+        let text_x = px + pad * 2.0;
+        add_line(&mut overlay_glyphs, glyph_atlas, &prompt, text_x, py + pad, dim_color);
+        let query_x = text_x + prompt.chars().count() as f32 * char_width;
+        add_line(&mut overlay_glyphs, glyph_atlas, &picker.query, query_x, py + pad, text_color);
+
+        let name_line = match picker.selected_entry().map(|i| &picker.entries[i]) {
+            Some(e) if e.shortcodes.is_empty() => e.name.clone(),
+            Some(e) => format!("{}  {}", e.name, e.shortcodes.join(" ")),
+            None => "No match".to_string(),
+        };
+        add_line(&mut overlay_glyphs, glyph_atlas, &name_line, text_x, name_y + 4.0, dim_color);
+        self.render_overlay_glyphs(view, &mut overlay_glyphs, glyph_atlas);
+
+        // === Pass 3: Character previews ===
+        // Rasterized at the preview size through the composed-glyph path,
+        // which handles multi-codepoint emoji and color glyphs alike.
+        let mut preview = Face::new(PREVIEW_FACE_ID);
+        preview.font_size = (cell * 0.62).round();
+        let preview_bits = preview.font_size.to_bits();
+        let mut previews: Vec<(ComposedGlyphKey, f32, f32)> = Vec::with_capacity(cells.len());
+        for &(i, cx, cy) in &cells {
+            let text = picker.entries[picker.filtered[i]].text.as_str();
+            let Some(g) = glyph_atlas.get_or_create_composed(
+                &self.device, &self.queue, text, PREVIEW_FACE_ID, preview_bits, Some(&preview),
+            ) else {
+                continue;
+            };
+            // Center the glyph's ink box in the cell
+            let gx = cx + ((cell - g.width as f32) / 2.0).floor() - g.bearing_x;
+            let baseline = cy + ((cell - g.height as f32) / 2.0).floor() + g.bearing_y;
+            let key = ComposedGlyphKey { text: text.into(), face_id: PREVIEW_FACE_ID, font_size_bits: preview_bits };
+            previews.push((key, gx, baseline));
+        }
+        self.render_overlay_composed_glyphs(view, &previews, text_color, glyph_atlas);
+    }
+
+    /// Render the command palette overlay: search line, list of
+    /// commands with their key bindings and the selected command's
+    /// documentation.
+    pub(crate) fn render_command_palette(
+        &self,
+        view: &wgpu::TextureView,
+        palette: &CommandPaletteState,
+        glyph_atlas: &mut WgpuGlyphAtlas,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        use wgpu::util::DeviceExt;
+
+        let logical_w = surface_width as f32 / self.scale_factor;
+        let logical_h = surface_height as f32 / self.scale_factor;
+        let uniforms = Uniforms {
+            screen_size: [logical_w, logical_h],
+            _padding: [0.0, 0.0],
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+        let (fg_r, fg_g, fg_b) = palette.face_fg.unwrap_or((0.9, 0.9, 0.9));
+        let (bg_r, bg_g, bg_b) = palette.face_bg.unwrap_or((0.15, 0.15, 0.18));
+        let bg_color = Color::new(bg_r, bg_g, bg_b, 0.97).srgb_to_linear();
+        let border_color = Color::new(
+            (bg_r * 0.6 + 0.15).min(1.0),
+            (bg_g * 0.6 + 0.15).min(1.0),
+            (bg_b * 0.6 + 0.15).min(1.0),
+            1.0,
+        ).srgb_to_linear();
+        let field_color = Color::new(bg_r * 0.8, bg_g * 0.8, bg_b * 0.8, 1.0).srgb_to_linear();
+        let selected_color = Color::new(
+            bg_r * 0.5 + fg_r * 0.3,
+            bg_g * 0.5 + fg_g * 0.3,
+            bg_b * 0.5 + fg_b * 0.3,
+            0.9,
+        ).srgb_to_linear();
+        let text_color = {
+            let c = Color::new(fg_r, fg_g, fg_b, 1.0).srgb_to_linear();
+            [c.r, c.g, c.b, c.a]
+        };
+        let dim_color = {
+            let c = Color::new(
+                fg_r * 0.6 + bg_r * 0.4,
+                fg_g * 0.6 + bg_g * 0.4,
+                fg_b * 0.6 + bg_b * 0.4,
+                1.0,
+            ).srgb_to_linear();
+            [c.r, c.g, c.b, c.a]
+        };
+        let match_color = {
+            let (r, g, b) = palette.match_fg.unwrap_or((0.45, 0.7, 1.0));
+            let c = Color::new(r, g, b, 1.0).srgb_to_linear();
+            [c.r, c.g, c.b, c.a]
+        };
+
+        let (px, py, pw, ph) = palette.bounds;
+        let pad = palette.padding;
+        let line_h = palette.line_height;
+        let (lx, ly) = palette.list_origin();
+        let list_w = palette.list_width - pad;
+        let rows = palette.visible_rows();
+
+        // === Pass 1: Background rectangles ===
+        let mut rect_vertices: Vec<RectVertex> = Vec::new();
+        for i in 1..=4 {
+            let offset = i as f32 * 1.5;
+            let alpha = 0.12 * (1.0 - (i - 1) as f32 / 4.0);
+            self.add_rect(&mut rect_vertices, px + offset, py + offset, pw, ph, &Color::new(0.0, 0.0, 0.0, alpha));
+        }
+        self.add_rect(&mut rect_vertices, px, py, pw, ph, &bg_color);
+        self.add_rect(&mut rect_vertices, px, py, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py + ph - 1.0, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py, 1.0, ph, &border_color);
+        self.add_rect(&mut rect_vertices, px + pw - 1.0, py, 1.0, ph, &border_color);
+        // Search field
+        self.add_rect(&mut rect_vertices, px + pad, py + pad, pw - 2.0 * pad, line_h - 4.0, &field_color);
+        // Selected row
+        if let Some(&(_, ry)) = rows.iter().find(|r| r.0 == palette.selected) {
+            self.add_rect(&mut rect_vertices, lx, ry, list_w, line_h, &selected_color);
+        }
+        // Separator left of the documentation pane
+        self.add_rect(&mut rect_vertices, lx + list_w + pad / 2.0, ly, 1.0, ph - (ly - py) - pad, &border_color);
+
+        let rect_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Command Palette Rect Buffer"),
+            contents: bytemuck::cast_slice(&rect_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Command Palette Rect Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Command Palette Rect Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.rect_pipeline);
+            pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            pass.set_vertex_buffer(0, rect_buffer.slice(..));
+            pass.draw(0..rect_vertices.len() as u32, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        // === Pass 2: Text ===
+        let char_width = palette.char_width;
+        let font_size_bits = 0.0_f32.to_bits();
+        let mut overlay_glyphs: Vec<(GlyphKey, f32, f32, [f32; 4])> = Vec::new();
+        // Characters of TEXT from X, at most MAX_CHARS, each colored by
+        // COLOR_OF its index
+        let add_text = |glyphs: &mut Vec<(GlyphKey, f32, f32, [f32; 4])>,
+                        atlas: &mut WgpuGlyphAtlas,
+                        text: &str, x: f32, y: f32, max_chars: usize,
+                        color_of: &dyn Fn(usize) -> [f32; 4]| {
+            for (ci, ch) in text.chars().take(max_chars).enumerate() {
+                let key = GlyphKey { charcode: ch as u32, face_id: 0, font_size_bits };
+                atlas.get_or_create(&self.device, &self.queue, &key, None);
+                glyphs.push((key, x + ci as f32 * char_width, y, color_of(ci)));
+            }
+        };
+
+        let prompt = format!("{}: ", palette.title.as_deref().unwrap_or("M-x"));
+        let text_x = px + pad * 2.0;
+        let field_chars = ((pw - 4.0 * pad) / char_width).max(0.0) as usize;
+        add_text(&mut overlay_glyphs, glyph_atlas, &prompt, text_x, py + pad, field_chars, &|_| dim_color);
+        let query_x = text_x + prompt.chars().count() as f32 * char_width;
+        add_text(&mut overlay_glyphs, glyph_atlas, &palette.query, query_x, py + pad,
+                 field_chars.saturating_sub(prompt.chars().count()), &|_| text_color);
+
+        let list_chars = ((list_w - pad) / char_width).max(0.0) as usize;
+        if rows.is_empty() {
+            add_text(&mut overlay_glyphs, glyph_atlas, "No match", lx + pad / 2.0, ly + 2.0, list_chars, &|_| dim_color);
+        }
+        for &(i, ry) in &rows {
+            let m = &palette.matches[i];
+            let entry = &palette.entries[m.entry];
+            // Key binding right-aligned, the name gets what is left
+            let binding_chars = entry.binding.chars().count().min(list_chars / 2);
+            let bx = lx + list_w - pad / 2.0 - binding_chars as f32 * char_width;
+            add_text(&mut overlay_glyphs, glyph_atlas, &entry.binding, bx, ry + 2.0, binding_chars, &|_| dim_color);
+            let name_chars = list_chars.saturating_sub(binding_chars + 1);
+            add_text(&mut overlay_glyphs, glyph_atlas, &entry.name, lx + pad / 2.0, ry + 2.0, name_chars,
+                     &|ci| if m.positions.contains(&ci) { match_color } else { text_color });
+        }
+
+        let doc_x = lx + palette.list_width;
+        for (k, line) in palette.doc_lines().iter().enumerate() {
+            add_text(&mut overlay_glyphs, glyph_atlas, line, doc_x, ly + 2.0 + k as f32 * line_h,
+                     usize::MAX, &|_| dim_color);
+        }
+        self.render_overlay_glyphs(view, &mut overlay_glyphs, glyph_atlas);
+    }
+
+    /// Render the color picker overlay: hue/saturation wheel, value
+    /// slider, original and new colors and the eyedropper button.
+    pub(crate) fn render_color_picker(
+        &self,
+        view: &wgpu::TextureView,
+        picker: &ColorPickerState,
+        glyph_atlas: &mut WgpuGlyphAtlas,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        use wgpu::util::DeviceExt;
+
+        /// Rings and hue segments the wheel is drawn with
+        const WHEEL_RINGS: usize = 12;
+        const WHEEL_SEGMENTS: usize = 90;
+        const SLIDER_SEGMENTS: usize = 24;
+
+        let logical_w = surface_width as f32 / self.scale_factor;
+        let logical_h = surface_height as f32 / self.scale_factor;
+        let uniforms = Uniforms {
+            screen_size: [logical_w, logical_h],
+            _padding: [0.0, 0.0],
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+        let (fg_r, fg_g, fg_b) = picker.face_fg.unwrap_or((0.9, 0.9, 0.9));
+        let (bg_r, bg_g, bg_b) = picker.face_bg.unwrap_or((0.15, 0.15, 0.18));
+        let bg_color = Color::new(bg_r, bg_g, bg_b, 0.97).srgb_to_linear();
+        let border_color = Color::new(
+            (bg_r * 0.6 + 0.15).min(1.0),
+            (bg_g * 0.6 + 0.15).min(1.0),
+            (bg_b * 0.6 + 0.15).min(1.0),
+            1.0,
+        ).srgb_to_linear();
+        let field_color = Color::new(bg_r * 0.8, bg_g * 0.8, bg_b * 0.8, 1.0).srgb_to_linear();
+        let fg_color = Color::new(fg_r, fg_g, fg_b, 1.0).srgb_to_linear();
+        let text_color = [fg_color.r, fg_color.g, fg_color.b, fg_color.a];
+        let dim_color = {
+            let c = Color::new(
+                fg_r * 0.6 + bg_r * 0.4,
+                fg_g * 0.6 + bg_g * 0.4,
+                fg_b * 0.6 + bg_b * 0.4,
+                1.0,
+            ).srgb_to_linear();
+            [c.r, c.g, c.b, c.a]
+        };
+        let hsv = |h: f32, s: f32, v: f32| {
+            let (r, g, b) = hsv_to_rgb(h, s, v);
+            let c = Color::new(r, g, b, 1.0).srgb_to_linear();
+            [c.r, c.g, c.b, c.a]
+        };
+        let pixel = |rgb: u32| Color::from_pixel(rgb);
+        // Markers are black on light colors and white on dark ones
+        let marker_color = if picker.value > 0.6 && picker.saturation < 0.6 {
+            Color::new(0.0, 0.0, 0.0, 1.0)
+        } else {
+            Color::new(1.0, 1.0, 1.0, 1.0)
+        };
+        let quad = |verts: &mut Vec<RectVertex>, p: [(f32, f32); 4], c: [[f32; 4]; 4]| {
+            // p and c in order top-left, top-right, bottom-left, bottom-right
+            for i in [0, 1, 2, 1, 3, 2] {
+                verts.push(RectVertex { position: [p[i].0, p[i].1], color: c[i] });
+            }
+        };
+
+        let (px, py, pw, ph) = picker.bounds;
+        let pad = picker.padding;
+
+        // === Pass 1: Panel, wheel, slider and previews ===
+        let mut rect_vertices: Vec<RectVertex> = Vec::new();
+        for i in 1..=4 {
+            let offset = i as f32 * 1.5;
+            let alpha = 0.12 * (1.0 - (i - 1) as f32 / 4.0);
+            self.add_rect(&mut rect_vertices, px + offset, py + offset, pw, ph, &Color::new(0.0, 0.0, 0.0, alpha));
+        }
+        self.add_rect(&mut rect_vertices, px, py, pw, ph, &bg_color);
+        self.add_rect(&mut rect_vertices, px, py, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py + ph - 1.0, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py, 1.0, ph, &border_color);
+        self.add_rect(&mut rect_vertices, px + pw - 1.0, py, 1.0, ph, &border_color);
+
+        // Wheel at the current value, as rings of segments with the
+        // colors of their corners
+        for ring in 0..WHEEL_RINGS {
+            let s0 = ring as f32 / WHEEL_RINGS as f32;
+            let s1 = (ring + 1) as f32 / WHEEL_RINGS as f32;
+            for seg in 0..WHEEL_SEGMENTS {
+                let h0 = seg as f32 * 360.0 / WHEEL_SEGMENTS as f32;
+                let h1 = (seg + 1) as f32 * 360.0 / WHEEL_SEGMENTS as f32;
+                quad(
+                    &mut rect_vertices,
+                    [picker.wheel_point(h0, s1), picker.wheel_point(h1, s1),
+                     picker.wheel_point(h0, s0), picker.wheel_point(h1, s0)],
+                    [hsv(h0, s1, picker.value), hsv(h1, s1, picker.value),
+                     hsv(h0, s0, picker.value), hsv(h1, s0, picker.value)],
+                );
+            }
+        }
+        let (mx, my) = picker.wheel_point(picker.hue, picker.saturation);
+        for (x, y, w, h) in [(-5.0, -5.0, 10.0, 2.0), (-5.0, 3.0, 10.0, 2.0), (-5.0, -3.0, 2.0, 6.0), (3.0, -3.0, 2.0, 6.0)] {
+            self.add_rect(&mut rect_vertices, mx + x, my + y, w, h, &marker_color);
+        }
+
+        // Value slider from full value at the top to black
+        let (sx, sy, sw, sh) = picker.slider_rect();
+        for seg in 0..SLIDER_SEGMENTS {
+            let v0 = 1.0 - seg as f32 / SLIDER_SEGMENTS as f32;
+            let v1 = 1.0 - (seg + 1) as f32 / SLIDER_SEGMENTS as f32;
+            let y0 = sy + (1.0 - v0) * sh;
+            let y1 = sy + (1.0 - v1) * sh;
+            let (c0, c1) = (hsv(picker.hue, picker.saturation, v0), hsv(picker.hue, picker.saturation, v1));
+            quad(&mut rect_vertices, [(sx, y0), (sx + sw, y0), (sx, y1), (sx + sw, y1)], [c0, c0, c1, c1]);
+        }
+        let vy = (sy + (1.0 - picker.value) * sh).round();
+        self.add_rect(&mut rect_vertices, sx - 3.0, vy - 1.0, sw + 6.0, 2.0, &fg_color);
+
+        // Original and new colors, and the eyedropper button: a crosshair
+        let [(ox, oy, ow, oh), (nx, ny, nw, nh), (ex, ey, ew, eh)] = picker.preview_rects();
+        self.add_rect(&mut rect_vertices, ox - 1.0, oy - 1.0, ow + nw + 2.0, oh + 2.0, &border_color);
+        self.add_rect(&mut rect_vertices, ox, oy, ow, oh, &pixel(picker.original));
+        self.add_rect(&mut rect_vertices, nx, ny, nw, nh, &pixel(picker.rgb()));
+        self.add_rect(&mut rect_vertices, ex, ey, ew, eh, &field_color);
+        let (ecx, ecy) = ((ex + ew / 2.0).round(), (ey + eh / 2.0).round());
+        let arm = (ew.min(eh) * 0.35).round();
+        self.add_rect(&mut rect_vertices, ecx - arm, ecy - 1.0, 2.0 * arm, 2.0, &fg_color);
+        self.add_rect(&mut rect_vertices, ecx - 1.0, ecy - arm, 2.0, 2.0 * arm, &fg_color);
+
+        let rect_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Color Picker Rect Buffer"),
+            contents: bytemuck::cast_slice(&rect_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Color Picker Rect Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Color Picker Rect Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.rect_pipeline);
+            pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            pass.set_vertex_buffer(0, rect_buffer.slice(..));
+            pass.draw(0..rect_vertices.len() as u32, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        // === Pass 2: Title line with the hex value ===
+        let font_size = glyph_atlas.default_font_size();
+        let char_width = font_size * 0.6;
+        let font_size_bits = 0.0_f32.to_bits();
+        let mut overlay_glyphs: Vec<(GlyphKey, f32, f32, [f32; 4])> = Vec::new();
+        let max_chars = ((pw - 2.0 * pad) / char_width).max(0.0) as usize;
+        let mut add_text = |atlas: &mut WgpuGlyphAtlas, text: &str, x: f32, color: [f32; 4]| {
+            let skip = ((x - px - pad) / char_width).max(0.0) as usize;
+            for (ci, ch) in text.chars().take(max_chars.saturating_sub(skip)).enumerate() {
+                let key = GlyphKey { charcode: ch as u32, face_id: 0, font_size_bits };
+                atlas.get_or_create(&self.device, &self.queue, &key, None);
+                overlay_glyphs.push((key, x + ci as f32 * char_width, py + pad, color));
+            }
+        };
+        let prompt = format!("{}: ", picker.title.as_deref().unwrap_or("Color"));
+        let hex = format!("#{:06x}", picker.rgb());
+        let text_x = px + pad;
+        let hex_x = text_x + prompt.chars().count() as f32 * char_width;
+        add_text(glyph_atlas, &prompt, text_x, dim_color);
+        add_text(glyph_atlas, &hex, hex_x, text_color);
+        if !picker.typed.is_empty() {
+            let typed = format!("#{}", picker.typed);
+            add_text(glyph_atlas, &typed, hex_x + 9.0 * char_width, dim_color);
+        }
+        self.render_overlay_glyphs(view, &mut overlay_glyphs, glyph_atlas);
+    }
+
+    /// Render the agenda timeline overlay: time axis, grid, today line,
+    /// one row per item with its label and bar, and a footer describing
+    /// the selected item.
+    pub(crate) fn render_agenda_timeline(
+        &self,
+        view: &wgpu::TextureView,
+        timeline: &AgendaTimelineState,
+        glyph_atlas: &mut WgpuGlyphAtlas,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        use wgpu::util::DeviceExt;
+
+        let logical_w = surface_width as f32 / self.scale_factor;
+        let logical_h = surface_height as f32 / self.scale_factor;
+        let uniforms = Uniforms {
+            screen_size: [logical_w, logical_h],
+            _padding: [0.0, 0.0],
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+        let (fg_r, fg_g, fg_b) = timeline.face_fg.unwrap_or((0.9, 0.9, 0.9));
+        let (bg_r, bg_g, bg_b) = timeline.face_bg.unwrap_or((0.15, 0.15, 0.18));
+        let mix = |t: f32, (r, g, b): (f32, f32, f32)| {
+            Color::new(r * t + bg_r * (1.0 - t), g * t + bg_g * (1.0 - t), b * t + bg_b * (1.0 - t), 1.0)
+                .srgb_to_linear()
+        };
+        let bg_color = Color::new(bg_r, bg_g, bg_b, 0.97).srgb_to_linear();
+        let border_color = Color::new(
+            (bg_r * 0.6 + 0.15).min(1.0),
+            (bg_g * 0.6 + 0.15).min(1.0),
+            (bg_b * 0.6 + 0.15).min(1.0),
+            1.0,
+        ).srgb_to_linear();
+        let fg = (fg_r, fg_g, fg_b);
+        let fg_color = Color::new(fg_r, fg_g, fg_b, 1.0).srgb_to_linear();
+        let text_color = [fg_color.r, fg_color.g, fg_color.b, fg_color.a];
+        let dim_color = {
+            let c = mix(0.6, fg);
+            [c.r, c.g, c.b, c.a]
+        };
+        let stripe_color = mix(0.04, fg);
+        let hover_color = mix(0.1, fg);
+        let selected_color = mix(0.18, fg);
+        let minor_line = mix(0.12, fg);
+        let major_line = mix(0.3, fg);
+        let now_color = Color::new(0.9, 0.3, 0.3, 1.0).srgb_to_linear();
+        let default_bar = (0.35, 0.55, 0.85);
+
+        let (px, py, pw, ph) = timeline.bounds;
+        let pad = timeline.padding;
+        let row_h = timeline.line_height;
+        let (cx, cy, cw, ch) = timeline.chart_rect();
+        let axis_top = cy - 2.0 * row_h;
+        let rows = timeline.visible_rows();
+        let shown = timeline.scroll_row..(timeline.scroll_row + rows).min(timeline.items.len());
+        let ticks = timeline.ticks();
+
+        // === Pass 1: Panel, grid, rows and bars ===
+        let mut rect_vertices: Vec<RectVertex> = Vec::new();
+        for i in 1..=4 {
+            let offset = i as f32 * 1.5;
+            let alpha = 0.12 * (1.0 - (i - 1) as f32 / 4.0);
+            self.add_rect(&mut rect_vertices, px + offset, py + offset, pw, ph, &Color::new(0.0, 0.0, 0.0, alpha));
+        }
+        self.add_rect(&mut rect_vertices, px, py, pw, ph, &bg_color);
+        self.add_rect(&mut rect_vertices, px, py, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py + ph - 1.0, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py, 1.0, ph, &border_color);
+        self.add_rect(&mut rect_vertices, px + pw - 1.0, py, 1.0, ph, &border_color);
+
+        // Row backgrounds: stripes, then the hovered and selected rows
+        let row_x = px + pad;
+        let row_w = cx + cw - row_x;
+        for i in shown.clone() {
+            let y = cy + (i - timeline.scroll_row) as f32 * row_h;
+            let color = if timeline.selected == Some(i) {
+                Some(&selected_color)
+            } else if timeline.hover == Some(i) {
+                Some(&hover_color)
+            } else if i % 2 == 1 {
+                Some(&stripe_color)
+            } else {
+                None
+            };
+            if let Some(color) = color {
+                self.add_rect(&mut rect_vertices, row_x, y, row_w, row_h, color);
+            }
+        }
+
+        // Grid lines: major ones through both axis rows, minor ones
+        // from the lower one
+        for tick in &ticks {
+            let x = timeline.time_to_x(tick.time).round();
+            if x < cx || x >= cx + cw {
+                continue;
+            }
+            if tick.major {
+                self.add_rect(&mut rect_vertices, x, axis_top, 1.0, cy + ch - axis_top, &major_line);
+            } else {
+                self.add_rect(&mut rect_vertices, x, cy - row_h, 1.0, ch + row_h, &minor_line);
+            }
+        }
+        self.add_rect(&mut rect_vertices, cx, cy - 1.0, cw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, cx - 1.0, axis_top, 1.0, cy + ch - axis_top, &border_color);
+
+        // Bars and milestones, clipped to the chart
+        let bar_h = (row_h * 0.6).round();
+        for i in shown.clone() {
+            let item = &timeline.items[i];
+            let y = cy + (i - timeline.scroll_row) as f32 * row_h;
+            let (r, g, b) = item.color.unwrap_or(default_bar);
+            let color = mix(if item.done { 0.4 } else { 1.0 }, (r, g, b));
+            let selected = timeline.selected == Some(i);
+            if item.end > item.start {
+                let x0 = timeline.time_to_x(item.start).max(cx - 2.0);
+                let x1 = timeline.time_to_x(item.end).min(cx + cw + 2.0);
+                if x1 <= cx || x0 >= cx + cw {
+                    continue;
+                }
+                let (x0, x1) = (x0.max(cx), x1.min(cx + cw).max(x0.max(cx) + 2.0));
+                let by = (y + (row_h - bar_h) / 2.0).round();
+                if selected {
+                    self.add_rect(&mut rect_vertices, x0 - 1.0, by - 1.0, x1 - x0 + 2.0, bar_h + 2.0, &fg_color);
+                }
+                self.add_rect(&mut rect_vertices, x0, by, x1 - x0, bar_h, &color);
+            } else {
+                let x = timeline.time_to_x(item.start);
+                let half = bar_h / 2.0 + if selected { 1.5 } else { 0.0 };
+                if x + half <= cx || x - half >= cx + cw {
+                    continue;
+                }
+                let mid = y + row_h / 2.0;
+                let mut diamond = |half: f32, c: &Color| {
+                    let c = [c.r, c.g, c.b, c.a];
+                    let p = [(x, mid - half), (x + half, mid), (x - half, mid), (x, mid + half)];
+                    for k in [0, 1, 2, 1, 3, 2] {
+                        rect_vertices.push(RectVertex { position: [p[k].0, p[k].1], color: c });
+                    }
+                };
+                if selected {
+                    diamond(half, &fg_color);
+                }
+                diamond(bar_h / 2.0, &color);
+            }
+        }
+
+        // The current time
+        let now_x = timeline.time_to_x(timeline.now).round();
+        if now_x >= cx && now_x < cx + cw {
+            self.add_rect(&mut rect_vertices, now_x - 1.0, axis_top, 2.0, cy + ch - axis_top, &now_color);
+        }
+
+        let rect_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Agenda Timeline Rect Buffer"),
+            contents: bytemuck::cast_slice(&rect_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Agenda Timeline Rect Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Agenda Timeline Rect Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.rect_pipeline);
+            pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            pass.set_vertex_buffer(0, rect_buffer.slice(..));
+            pass.draw(0..rect_vertices.len() as u32, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        // === Pass 2: Title, axis labels, item labels and footer ===
+        let font_size = glyph_atlas.default_font_size();
+        let char_width = font_size * 0.6;
+        let font_size_bits = 0.0_f32.to_bits();
+        let mut overlay_glyphs: Vec<(GlyphKey, f32, f32, [f32; 4])> = Vec::new();
+        // Text at (X, Y) cut at LIMIT
+        let mut add_text = |atlas: &mut WgpuGlyphAtlas, text: &str, x: f32, y: f32, limit: f32, color: [f32; 4]| {
+            let max_chars = ((limit - x) / char_width).max(0.0) as usize;
+            for (ci, ch) in text.chars().take(max_chars).enumerate() {
+                let key = GlyphKey { charcode: ch as u32, face_id: 0, font_size_bits };
+                atlas.get_or_create(&self.device, &self.queue, &key, None);
+                overlay_glyphs.push((key, x + ci as f32 * char_width, y, color));
+            }
+        };
+        let right = px + pw - pad;
+        let text_dy = 3.0;
+
+        let title = timeline.title.as_deref().unwrap_or("Agenda");
+        add_text(glyph_atlas, title, px + pad, py + pad, right, text_color);
+        let hint = "\u{2190}\u{2192} pan  +/- zoom  t today  f fit  RET open";
+        let hint_x = (right - hint.chars().count() as f32 * char_width)
+            .max(px + pad + (title.chars().count() + 2) as f32 * char_width);
+        add_text(glyph_atlas, hint, hint_x, py + pad, right, dim_color);
+
+        // Axis labels right of their line, the upper row for major
+        // ticks; a label is dropped where it would run into the next
+        for (n, tick) in ticks.iter().enumerate() {
+            let x = timeline.time_to_x(tick.time) + 3.0;
+            if x < cx || x >= cx + cw {
+                continue;
+            }
+            let next = ticks[n + 1..]
+                .iter()
+                .find(|t| t.major == tick.major)
+                .map_or(cx + cw, |t| timeline.time_to_x(t.time));
+            let width = tick.label.chars().count() as f32 * char_width;
+            if x + width > next.min(cx + cw) {
+                continue;
+            }
+            let (y, color) = if tick.major { (axis_top, text_color) } else { (cy - row_h, dim_color) };
+            add_text(glyph_atlas, &tick.label, x, y + text_dy, cx + cw, color);
+        }
+
+        // Item labels
+        for i in shown {
+            let item = &timeline.items[i];
+            let y = cy + (i - timeline.scroll_row) as f32 * row_h + text_dy;
+            let label = AgendaTimelineState::item_label(item);
+            let color = if item.done { dim_color } else { text_color };
+            add_text(glyph_atlas, &label, px + pad, y, cx - char_width / 2.0, color);
+        }
+
+        // Footer: the hovered or selected item
+        if let Some(i) = timeline.hover.or(timeline.selected) {
+            add_text(glyph_atlas, &timeline.describe(i), px + pad, cy + ch + text_dy, right, dim_color);
+        } else if timeline.items.is_empty() {
+            add_text(glyph_atlas, "No agenda items", px + pad, cy + text_dy, right, dim_color);
+        }
+        self.render_overlay_glyphs(view, &mut overlay_glyphs, glyph_atlas);
+    }
+
+    /// Render the graph view overlay: panel, edges, nodes and their
+    /// labels, clipped to the chart, and a footer describing the node
+    /// under the mouse or the selected one.
+    pub(crate) fn render_graph_view(
+        &self,
+        view: &wgpu::TextureView,
+        graph: &GraphViewState,
+        glyph_atlas: &mut WgpuGlyphAtlas,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        use wgpu::util::DeviceExt;
+
+        let logical_w = surface_width as f32 / self.scale_factor;
+        let logical_h = surface_height as f32 / self.scale_factor;
+        let uniforms = Uniforms {
+            screen_size: [logical_w, logical_h],
+            _padding: [0.0, 0.0],
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+        let (fg_r, fg_g, fg_b) = graph.face_fg.unwrap_or((0.9, 0.9, 0.9));
+        let (bg_r, bg_g, bg_b) = graph.face_bg.unwrap_or((0.15, 0.15, 0.18));
+        let mix = |t: f32, (r, g, b): (f32, f32, f32)| {
+            Color::new(r * t + bg_r * (1.0 - t), g * t + bg_g * (1.0 - t), b * t + bg_b * (1.0 - t), 1.0)
+                .srgb_to_linear()
+        };
+        let bg_color = Color::new(bg_r, bg_g, bg_b, 0.97).srgb_to_linear();
+        let border_color = Color::new(
+            (bg_r * 0.6 + 0.15).min(1.0),
+            (bg_g * 0.6 + 0.15).min(1.0),
+            (bg_b * 0.6 + 0.15).min(1.0),
+            1.0,
+        ).srgb_to_linear();
+        let fg = (fg_r, fg_g, fg_b);
+        let fg_color = Color::new(fg_r, fg_g, fg_b, 1.0).srgb_to_linear();
+        let text_color = [fg_color.r, fg_color.g, fg_color.b, fg_color.a];
+        let dim_color = {
+            let c = mix(0.6, fg);
+            [c.r, c.g, c.b, c.a]
+        };
+        let edge_color = mix(0.25, fg);
+        let default_node = (0.35, 0.55, 0.85);
+        let accent = Color::new(0.95, 0.7, 0.25, 1.0).srgb_to_linear();
+
+        let (px, py, pw, ph) = graph.bounds;
+        let pad = graph.padding;
+        let (cx, cy, cw, ch) = graph.chart_rect();
+        let radius = graph.node_radius();
+        let selected = graph.selected;
+        let near_selected = |i: usize| selected.is_some_and(|s| s == i || graph.neighbors[s].contains(&i));
+        let on_chart = |x: f32, y: f32, margin: f32| {
+            x >= cx - margin && x < cx + cw + margin && y >= cy - margin && y < cy + ch + margin
+        };
+
+        // === Pass 1: Panel ===
+        let mut rect_vertices: Vec<RectVertex> = Vec::new();
+        for i in 1..=4 {
+            let offset = i as f32 * 1.5;
+            let alpha = 0.12 * (1.0 - (i - 1) as f32 / 4.0);
+            self.add_rect(&mut rect_vertices, px + offset, py + offset, pw, ph, &Color::new(0.0, 0.0, 0.0, alpha));
+        }
+        self.add_rect(&mut rect_vertices, px, py, pw, ph, &bg_color);
+        self.add_rect(&mut rect_vertices, px, py, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py + ph - 1.0, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py, 1.0, ph, &border_color);
+        self.add_rect(&mut rect_vertices, px + pw - 1.0, py, 1.0, ph, &border_color);
+        self.add_rect(&mut rect_vertices, cx, cy - 1.0, cw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, cx, cy + ch, cw, 1.0, &border_color);
+        let panel_vertices = rect_vertices.len() as u32;
+
+        // === Pass 2: Edges and nodes, clipped to the chart ===
+        // Edges of the selected node are drawn last, thicker and
+        // highlighted
+        let add_edge = |vertices: &mut Vec<RectVertex>, a: usize, b: usize, width: f32, color: &Color| {
+            let (x0, y0) = graph.to_screen(graph.positions[a]);
+            let (x1, y1) = graph.to_screen(graph.positions[b]);
+            let len = (x1 - x0).hypot(y1 - y0);
+            if len < 0.5 {
+                return;
+            }
+            // Skip edges whose bounding box misses the chart
+            if x0.max(x1) < cx || x0.min(x1) > cx + cw || y0.max(y1) < cy || y0.min(y1) > cy + ch {
+                return;
+            }
+            let (nx, ny) = (-(y1 - y0) / len * width / 2.0, (x1 - x0) / len * width / 2.0);
+            self.add_quad(
+                vertices,
+                &[(x0 + nx, y0 + ny), (x1 + nx, y1 + ny), (x1 - nx, y1 - ny), (x0 - nx, y0 - ny)],
+                color,
+            );
+        };
+        let mut highlighted = Vec::new();
+        for &(a, b) in &graph.edges {
+            if selected.is_some_and(|s| s == a || s == b) {
+                highlighted.push((a, b));
+            } else {
+                add_edge(&mut rect_vertices, a, b, 1.0, &edge_color);
+            }
+        }
+        for (a, b) in highlighted {
+            add_edge(&mut rect_vertices, a, b, 2.0, &accent);
+        }
+
+        // Nodes as 16-sided discs, ringed when selected or hovered
+        let disc = |vertices: &mut Vec<RectVertex>, x: f32, y: f32, r: f32, c: &Color| {
+            const SIDES: usize = 16;
+            let c = [c.r, c.g, c.b, c.a];
+            for k in 0..SIDES {
+                let a0 = k as f32 / SIDES as f32 * std::f32::consts::TAU;
+                let a1 = (k + 1) as f32 / SIDES as f32 * std::f32::consts::TAU;
+                for (vx, vy) in [(x, y), (x + r * a0.cos(), y + r * a0.sin()), (x + r * a1.cos(), y + r * a1.sin())] {
+                    vertices.push(RectVertex { position: [vx, vy], color: c });
+                }
+            }
+        };
+        for (i, node) in graph.nodes.iter().enumerate() {
+            let (x, y) = graph.to_screen(graph.positions[i]);
+            if !on_chart(x, y, radius + 2.0) {
+                continue;
+            }
+            let dimmed = selected.is_some() && !graph.shows_label(i);
+            let color = mix(if dimmed { 0.45 } else { 1.0 }, node.color.unwrap_or(default_node));
+            if selected == Some(i) {
+                disc(&mut rect_vertices, x, y, radius + 2.5, &accent);
+            } else if graph.hover == Some(i) {
+                disc(&mut rect_vertices, x, y, radius + 1.5, &fg_color);
+            }
+            disc(&mut rect_vertices, x, y, radius, &color);
+        }
+
+        let rect_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Graph View Rect Buffer"),
+            contents: bytemuck::cast_slice(&rect_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Graph View Rect Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Graph View Rect Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.rect_pipeline);
+            pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            pass.set_vertex_buffer(0, rect_buffer.slice(..));
+            pass.draw(0..panel_vertices, 0..1);
+            // The scissor rect is in physical pixels
+            let sx = (cx * self.scale_factor).max(0.0) as u32;
+            let sy = (cy * self.scale_factor).max(0.0) as u32;
+            let sw = ((cw * self.scale_factor) as u32).min(surface_width.saturating_sub(sx));
+            let sh = ((ch * self.scale_factor) as u32).min(surface_height.saturating_sub(sy));
+            if sw > 0 && sh > 0 {
+                pass.set_scissor_rect(sx, sy, sw, sh);
+                pass.draw(panel_vertices..rect_vertices.len() as u32, 0..1);
+            }
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        // === Pass 3: Title, node labels and footer ===
+        let font_size = glyph_atlas.default_font_size();
+        let char_width = font_size * 0.6;
+        let font_size_bits = 0.0_f32.to_bits();
+        let mut overlay_glyphs: Vec<(GlyphKey, f32, f32, [f32; 4])> = Vec::new();
+        // Text at (X, Y) with the characters between LEFT and RIGHT
+        let mut add_text = |atlas: &mut WgpuGlyphAtlas, text: &str, x: f32, y: f32, left: f32, right: f32, color: [f32; 4]| {
+            for (ci, ch) in text.chars().enumerate() {
+                let gx = x + ci as f32 * char_width;
+                if gx < left {
+                    continue;
+                }
+                if gx + char_width > right {
+                    break;
+                }
+                let key = GlyphKey { charcode: ch as u32, face_id: 0, font_size_bits };
+                atlas.get_or_create(&self.device, &self.queue, &key, None);
+                overlay_glyphs.push((key, gx, y, color));
+            }
+        };
+        let right = px + pw - pad;
+        let text_dy = 3.0;
+
+        let title = graph.title.as_deref().unwrap_or("Graph");
+        add_text(glyph_atlas, title, px + pad, py + pad, px + pad, right, text_color);
+        let hint = "arrows select  drag/wheel pan  +/- zoom  f fit  RET open";
+        let hint_x = (right - hint.chars().count() as f32 * char_width)
+            .max(px + pad + (title.chars().count() + 2) as f32 * char_width);
+        add_text(glyph_atlas, hint, hint_x, py + pad, px + pad, right, dim_color);
+
+        // Labels centered under their nodes, on the chart only
+        for i in 0..graph.nodes.len() {
+            if !graph.shows_label(i) {
+                continue;
+            }
+            let (x, y) = graph.to_screen(graph.positions[i]);
+            let label_y = y + radius + 2.0;
+            if !on_chart(x, y, 0.0) || label_y + graph.line_height > cy + ch {
+                continue;
+            }
+            let label = graph.label(i);
+            let lx = x - label.chars().count() as f32 * char_width / 2.0;
+            let color = if near_selected(i) || graph.hover == Some(i) { text_color } else { dim_color };
+            add_text(glyph_atlas, &label, lx, label_y, cx, cx + cw, color);
+        }
+
+        // Footer: the hovered or selected node
+        if let Some(i) = graph.hover.or(selected) {
+            add_text(glyph_atlas, &graph.describe(i), px + pad, cy + ch + pad / 2.0 + text_dy, px + pad, right, dim_color);
+        } else if graph.nodes.is_empty() {
+            add_text(glyph_atlas, "Empty graph", px + pad, cy + text_dy, px + pad, right, dim_color);
+        }
+        self.render_overlay_glyphs(view, &mut overlay_glyphs, glyph_atlas);
+    }
+
+    /// Render composed glyphs, each given as (key, pen x, baseline y).
+    /// Color glyphs keep their colors; mask glyphs are tinted COLOR.
+    fn render_overlay_composed_glyphs(
+        &self,
+        view: &wgpu::TextureView,
+        glyphs: &[(ComposedGlyphKey, f32, f32)],
+        color: [f32; 4],
+        glyph_atlas: &WgpuGlyphAtlas,
+    ) {
+        use wgpu::util::DeviceExt;
+
+        let drawn: Vec<_> = glyphs
+            .iter()
+            .filter_map(|(key, x, y)| glyph_atlas.get_composed(key).map(|g| (g, *x, *y)))
+            .collect();
+        if drawn.is_empty() {
+            return;
+        }
+
+        let mut vertices: Vec<GlyphVertex> = Vec::with_capacity(drawn.len() * 6);
+        for (g, x, y) in &drawn {
+            let gx = x + g.bearing_x;
+            let gy = y - g.bearing_y;
+            let (gw, gh) = (g.width as f32, g.height as f32);
+            vertices.extend_from_slice(&[
+                GlyphVertex { position: [gx, gy], tex_coords: [0.0, 0.0], color },
+                GlyphVertex { position: [gx + gw, gy], tex_coords: [1.0, 0.0], color },
+                GlyphVertex { position: [gx + gw, gy + gh], tex_coords: [1.0, 1.0], color },
+                GlyphVertex { position: [gx, gy], tex_coords: [0.0, 0.0], color },
+                GlyphVertex { position: [gx + gw, gy + gh], tex_coords: [1.0, 1.0], color },
+                GlyphVertex { position: [gx, gy + gh], tex_coords: [0.0, 1.0], color },
+            ]);
+        }
+
+        let buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overlay Composed Glyph Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Overlay Composed Glyph Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Overlay Composed Glyph Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            pass.set_vertex_buffer(0, buffer.slice(..));
+            for (i, (g, _, _)) in drawn.iter().enumerate() {
+                if g.is_color {
+                    pass.set_pipeline(&self.opaque_image_pipeline);
+                } else {
+                    pass.set_pipeline(&self.image_pipeline);
+                }
+                pass.set_bind_group(1, &g.bind_group, &[]);
+                let v = i as u32 * 6;
+                pass.draw(v..v + 6, 0..1);
+            }
+        }
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Render the directory view overlay: title, sortable column headers,
+    /// the rows on screen with their icons, a scroll bar and a footer
+    /// with the query and entry count.
+    pub(crate) fn render_dir_view(
+        &self,
+        view: &wgpu::TextureView,
+        dir: &DirViewState,
+        glyph_atlas: &mut WgpuGlyphAtlas,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        use crate::core::dir_listing::{format_size, SortKey};
+        use crate::render_thread::{format_modified, DATE_CHARS, SIZE_CHARS};
+        use wgpu::util::DeviceExt;
+
+        let logical_w = surface_width as f32 / self.scale_factor;
+        let logical_h = surface_height as f32 / self.scale_factor;
+        let uniforms = Uniforms {
+            screen_size: [logical_w, logical_h],
+            _padding: [0.0, 0.0],
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+        let (fg_r, fg_g, fg_b) = dir.face_fg.unwrap_or((0.9, 0.9, 0.9));
+        let (bg_r, bg_g, bg_b) = dir.face_bg.unwrap_or((0.15, 0.15, 0.18));
+        let bg_color = Color::new(bg_r, bg_g, bg_b, 0.97).srgb_to_linear();
+        let border_color = Color::new(
+            (bg_r * 0.6 + 0.15).min(1.0),
+            (bg_g * 0.6 + 0.15).min(1.0),
+            (bg_b * 0.6 + 0.15).min(1.0),
+            1.0,
+        ).srgb_to_linear();
+        let selected_color = Color::new(
+            bg_r * 0.5 + fg_r * 0.3,
+            bg_g * 0.5 + fg_g * 0.3,
+            bg_b * 0.5 + fg_b * 0.3,
+            0.9,
+        ).srgb_to_linear();
+        let hover_color = Color::new(
+            bg_r * 0.8 + fg_r * 0.12,
+            bg_g * 0.8 + fg_g * 0.12,
+            bg_b * 0.8 + fg_b * 0.12,
+            0.9,
+        ).srgb_to_linear();
+        let thumb_color = Color::new(fg_r, fg_g, fg_b, 0.35).srgb_to_linear();
+        let to_rgba = |c: Color| [c.r, c.g, c.b, c.a];
+        let text_color = to_rgba(Color::new(fg_r, fg_g, fg_b, 1.0).srgb_to_linear());
+        let dim_color = to_rgba(Color::new(
+            fg_r * 0.6 + bg_r * 0.4,
+            fg_g * 0.6 + bg_g * 0.4,
+            fg_b * 0.6 + bg_b * 0.4,
+            1.0,
+        ).srgb_to_linear());
+        let dir_color = to_rgba(Color::new(0.45, 0.7, 1.0, 1.0).srgb_to_linear());
+
+        let (px, py, pw, ph) = dir.bounds;
+        let pad = dir.padding;
+        let line_h = dir.line_height;
+        let char_width = dir.char_width;
+        let (lx, ly, lw, lh) = dir.list_rect();
+        let [icon_x, name_x, size_x, date_x] = dir.columns();
+        let rows = dir.visible_rows();
+        let first = dir.scroll_row;
+        let last = (first + rows).min(dir.filter.matches.len());
+        let row_y = |pos: usize| ly + (pos - first) as f32 * line_h;
+
+        // === Pass 1: Background rectangles ===
+        let mut rect_vertices: Vec<RectVertex> = Vec::new();
+        for i in 1..=4 {
+            let offset = i as f32 * 1.5;
+            let alpha = 0.12 * (1.0 - (i - 1) as f32 / 4.0);
+            self.add_rect(&mut rect_vertices, px + offset, py + offset, pw, ph, &Color::new(0.0, 0.0, 0.0, alpha));
+        }
+        self.add_rect(&mut rect_vertices, px, py, pw, ph, &bg_color);
+        self.add_rect(&mut rect_vertices, px, py, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py + ph - 1.0, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py, 1.0, ph, &border_color);
+        self.add_rect(&mut rect_vertices, px + pw - 1.0, py, 1.0, ph, &border_color);
+        // Lines under the column headers and above the footer
+        self.add_rect(&mut rect_vertices, lx, ly - 1.0, lw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, lx, ly + lh, lw, 1.0, &border_color);
+        if let Some(hover) = dir.hover.filter(|&h| h != dir.selected && (first..last).contains(&h)) {
+            self.add_rect(&mut rect_vertices, lx, row_y(hover), lw, line_h, &hover_color);
+        }
+        if (first..last).contains(&dir.selected) {
+            self.add_rect(&mut rect_vertices, lx, row_y(dir.selected), lw, line_h, &selected_color);
+        }
+        // Scroll bar thumb
+        let total = dir.filter.matches.len();
+        if total > rows {
+            let thumb_h = (lh * rows as f32 / total as f32).max(line_h / 2.0);
+            let thumb_y = ly + (lh - thumb_h) * first as f32 / (total - rows) as f32;
+            self.add_rect(&mut rect_vertices, lx + lw + pad / 2.0 - 2.0, thumb_y, 3.0, thumb_h, &thumb_color);
+        }
+
+        let rect_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Dir View Rect Buffer"),
+            contents: bytemuck::cast_slice(&rect_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Dir View Rect Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Dir View Rect Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.rect_pipeline);
+            pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            pass.set_vertex_buffer(0, rect_buffer.slice(..));
+            pass.draw(0..rect_vertices.len() as u32, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        // === Pass 2: Text ===
+        let font_size_bits = 0.0_f32.to_bits();
+        let mut overlay_glyphs: Vec<(GlyphKey, f32, f32, [f32; 4])> = Vec::new();
+        // Characters of TEXT from X, at most MAX_CHARS
+        let add_text = |glyphs: &mut Vec<(GlyphKey, f32, f32, [f32; 4])>,
+                        atlas: &mut WgpuGlyphAtlas,
+                        text: &str, x: f32, y: f32, max_chars: usize, color: [f32; 4]| {
+            for (ci, ch) in text.chars().take(max_chars).enumerate() {
+                let key = GlyphKey { charcode: ch as u32, face_id: 0, font_size_bits };
+                atlas.get_or_create(&self.device, &self.queue, &key, None);
+                glyphs.push((key, x + ci as f32 * char_width, y, color));
+            }
+        };
+        let chars_in = |w: f32| (w / char_width).max(0.0) as usize;
+
+        // Title: the directory, keeping its end when it does not fit
+        let title = dir.dir.to_string_lossy();
+        let title_chars = chars_in(pw - 2.0 * pad);
+        let skip = title.chars().count().saturating_sub(title_chars);
+        let title: String = if skip > 0 {
+            std::iter::once('\u{2026}').chain(title.chars().skip(skip + 1)).collect()
+        } else {
+            title.into_owned()
+        };
+        add_text(&mut overlay_glyphs, glyph_atlas, &title, px + pad, py + pad + 2.0, title_chars, text_color);
+
+        // Column headers, the sorted one marked with its direction
+        let header_y = ly - line_h + 2.0;
+        let arrow = if dir.descending { " \u{25bc}" } else { " \u{25b2}" };
+        let headers = [(SortKey::Name, "Name", name_x), (SortKey::Size, "Size", size_x), (SortKey::Modified, "Modified", date_x)];
+        for (key, label, x) in headers {
+            let (label, color) = if key == dir.sort {
+                (format!("{}{}", label, arrow), text_color)
+            } else {
+                (label.to_string(), dim_color)
+            };
+            add_text(&mut overlay_glyphs, glyph_atlas, &label, x, header_y, usize::MAX, color);
+        }
+
+        // Only the rows on screen
+        let name_chars = chars_in(size_x - name_x - char_width);
+        for pos in first..last {
+            let Some(entry) = dir.entry_at(pos) else {
+                break;
+            };
+            let y = row_y(pos) + 2.0;
+            let icon = glyph_atlas.icons().lookup(dir.icon_name(entry)).map(|icon| (icon.codepoint, icon.color));
+            if let Some((codepoint, icon_color)) = icon {
+                let color = icon_color.map_or(if entry.is_dir() { dir_color } else { dim_color }, to_rgba);
+                let key = GlyphKey { charcode: codepoint as u32, face_id: 0, font_size_bits };
+                glyph_atlas.get_or_create(&self.device, &self.queue, &key, None);
+                overlay_glyphs.push((key, icon_x, y, color));
+            }
+            let mut name = entry.name.clone();
+            if entry.is_dir() {
+                name.push('/');
+            } else if entry.symlink {
+                name.push('@');
+            }
+            if name.chars().count() > name_chars {
+                name = name.chars().take(name_chars.saturating_sub(1)).chain(std::iter::once('\u{2026}')).collect();
+            }
+            let color = if entry.is_dir() { dir_color } else { text_color };
+            add_text(&mut overlay_glyphs, glyph_atlas, &name, name_x, y, name_chars, color);
+            if !entry.is_dir() {
+                let size = format_size(entry.size);
+                let sx = size_x + (SIZE_CHARS.saturating_sub(size.len())) as f32 * char_width;
+                add_text(&mut overlay_glyphs, glyph_atlas, &size, sx, y, SIZE_CHARS, dim_color);
+            }
+            let date = format_modified(entry.modified, dir.utc_offset);
+            add_text(&mut overlay_glyphs, glyph_atlas, &date, date_x, y, DATE_CHARS, dim_color);
+        }
+
+        // Footer: the query on the left, the count on the right
+        let footer_y = ly + lh + 4.0;
+        let status = dir.describe();
+        let status_chars = status.chars().count().min(chars_in(lw / 2.0));
+        let status_x = lx + lw - status_chars as f32 * char_width;
+        add_text(&mut overlay_glyphs, glyph_atlas, &status, status_x, footer_y, status_chars, dim_color);
+        if !dir.filter.query.is_empty() {
+            let query = format!("Filter: {}", dir.filter.query);
+            add_text(&mut overlay_glyphs, glyph_atlas, &query, lx, footer_y,
+                     chars_in(lw - status_chars as f32 * char_width - char_width), text_color);
+        }
+        self.render_overlay_glyphs(view, &mut overlay_glyphs, glyph_atlas);
+    }
+
+    /// Render the process monitor overlay: a title with the system's
+    /// load, sortable column headers, the rows on screen with CPU
+    /// sparklines, a scroll bar and a footer with the prompt and process
+    /// count.
+    pub(crate) fn render_process_monitor(
+        &self,
+        view: &wgpu::TextureView,
+        monitor: &ProcessMonitorState,
+        glyph_atlas: &mut WgpuGlyphAtlas,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        use crate::core::dir_listing::format_size;
+        use crate::core::process_monitor::ProcessSortKey;
+        use crate::render_thread::{sparkline_bars, CPU_CHARS, MEM_CHARS, PID_CHARS, SPARK_CHARS, USER_CHARS};
+        use wgpu::util::DeviceExt;
+
+        let logical_w = surface_width as f32 / self.scale_factor;
+        let logical_h = surface_height as f32 / self.scale_factor;
+        let uniforms = Uniforms {
+            screen_size: [logical_w, logical_h],
+            _padding: [0.0, 0.0],
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+        let (fg_r, fg_g, fg_b) = monitor.face_fg.unwrap_or((0.9, 0.9, 0.9));
+        let (bg_r, bg_g, bg_b) = monitor.face_bg.unwrap_or((0.15, 0.15, 0.18));
+        let bg_color = Color::new(bg_r, bg_g, bg_b, 0.97).srgb_to_linear();
+        let border_color = Color::new(
+            (bg_r * 0.6 + 0.15).min(1.0),
+            (bg_g * 0.6 + 0.15).min(1.0),
+            (bg_b * 0.6 + 0.15).min(1.0),
+            1.0,
+        ).srgb_to_linear();
+        let selected_color = Color::new(
+            bg_r * 0.5 + fg_r * 0.3,
+            bg_g * 0.5 + fg_g * 0.3,
+            bg_b * 0.5 + fg_b * 0.3,
+            0.9,
+        ).srgb_to_linear();
+        let hover_color = Color::new(
+            bg_r * 0.8 + fg_r * 0.12,
+            bg_g * 0.8 + fg_g * 0.12,
+            bg_b * 0.8 + fg_b * 0.12,
+            0.9,
+        ).srgb_to_linear();
+        let thumb_color = Color::new(fg_r, fg_g, fg_b, 0.35).srgb_to_linear();
+        let spark_color = Color::new(0.45, 0.7, 1.0, 0.9).srgb_to_linear();
+        let to_rgba = |c: Color| [c.r, c.g, c.b, c.a];
+        let text_color = to_rgba(Color::new(fg_r, fg_g, fg_b, 1.0).srgb_to_linear());
+        let dim_color = to_rgba(Color::new(
+            fg_r * 0.6 + bg_r * 0.4,
+            fg_g * 0.6 + bg_g * 0.4,
+            fg_b * 0.6 + bg_b * 0.4,
+            1.0,
+        ).srgb_to_linear());
+        let prompt_color = to_rgba(Color::new(1.0, 0.75, 0.35, 1.0).srgb_to_linear());
+
+        let (px, py, pw, ph) = monitor.bounds;
+        let pad = monitor.padding;
+        let line_h = monitor.line_height;
+        let char_width = monitor.char_width;
+        let (lx, ly, lw, lh) = monitor.list_rect();
+        let [pid_x, user_x, cpu_x, spark_x, mem_x, command_x] = monitor.columns();
+        let spark_w = SPARK_CHARS as f32 * char_width;
+        let rows = monitor.visible_rows();
+        let first = monitor.scroll_row;
+        let last = (first + rows).min(monitor.matches.len());
+        let row_y = |pos: usize| ly + (pos - first) as f32 * line_h;
+
+        // Title: the system's CPU use and its sparkline, then memory, at
+        // the right
+        let snapshot = &monitor.snapshot;
+        let mem_text = format!("Mem {} / {}", format_size(snapshot.mem_used), format_size(snapshot.mem_total));
+        let cpu_text = format!("CPU {:>3.0}%", snapshot.cpu);
+        let mem_title_x = px + pw - pad - mem_text.chars().count() as f32 * char_width;
+        let spark_title_x = mem_title_x - spark_w - 2.0 * char_width;
+        let cpu_title_x = spark_title_x - (cpu_text.chars().count() + 1) as f32 * char_width;
+
+        // === Pass 1: Background rectangles and sparklines ===
+        let mut rect_vertices: Vec<RectVertex> = Vec::new();
+        for i in 1..=4 {
+            let offset = i as f32 * 1.5;
+            let alpha = 0.12 * (1.0 - (i - 1) as f32 / 4.0);
+            self.add_rect(&mut rect_vertices, px + offset, py + offset, pw, ph, &Color::new(0.0, 0.0, 0.0, alpha));
+        }
+        self.add_rect(&mut rect_vertices, px, py, pw, ph, &bg_color);
+        self.add_rect(&mut rect_vertices, px, py, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py + ph - 1.0, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py, 1.0, ph, &border_color);
+        self.add_rect(&mut rect_vertices, px + pw - 1.0, py, 1.0, ph, &border_color);
+        // Lines under the column headers and above the footer
+        self.add_rect(&mut rect_vertices, lx, ly - 1.0, lw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, lx, ly + lh, lw, 1.0, &border_color);
+        if let Some(hover) = monitor.hover.filter(|&h| h != monitor.selected && (first..last).contains(&h)) {
+            self.add_rect(&mut rect_vertices, lx, row_y(hover), lw, line_h, &hover_color);
+        }
+        if (first..last).contains(&monitor.selected) {
+            self.add_rect(&mut rect_vertices, lx, row_y(monitor.selected), lw, line_h, &selected_color);
+        }
+        if monitor.sampled {
+            let spark_h = line_h - 6.0;
+            for (x, y, w, h) in sparkline_bars(&snapshot.cpu_history, spark_title_x, py + pad + 3.0, spark_w, spark_h) {
+                self.add_rect(&mut rect_vertices, x, y, w, h, &spark_color);
+            }
+            for pos in first..last {
+                let Some(process) = monitor.process_at(pos) else {
+                    break;
+                };
+                for (x, y, w, h) in sparkline_bars(&process.cpu_history, spark_x, row_y(pos) + 3.0, spark_w, spark_h) {
+                    self.add_rect(&mut rect_vertices, x, y, w, h, &spark_color);
+                }
+            }
+        }
+        // Scroll bar thumb
+        let total = monitor.matches.len();
+        if total > rows {
+            let thumb_h = (lh * rows as f32 / total as f32).max(line_h / 2.0);
+            let thumb_y = ly + (lh - thumb_h) * first as f32 / (total - rows) as f32;
+            self.add_rect(&mut rect_vertices, lx + lw + pad / 2.0 - 2.0, thumb_y, 3.0, thumb_h, &thumb_color);
+        }
+
+        let rect_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Process Monitor Rect Buffer"),
+            contents: bytemuck::cast_slice(&rect_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Process Monitor Rect Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Process Monitor Rect Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.rect_pipeline);
+            pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            pass.set_vertex_buffer(0, rect_buffer.slice(..));
+            pass.draw(0..rect_vertices.len() as u32, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        // === Pass 2: Text ===
+        let font_size_bits = 0.0_f32.to_bits();
+        let mut overlay_glyphs: Vec<(GlyphKey, f32, f32, [f32; 4])> = Vec::new();
+        // Characters of TEXT from X, at most MAX_CHARS
+        let add_text = |glyphs: &mut Vec<(GlyphKey, f32, f32, [f32; 4])>,
+                        atlas: &mut WgpuGlyphAtlas,
+                        text: &str, x: f32, y: f32, max_chars: usize, color: [f32; 4]| {
+            for (ci, ch) in text.chars().take(max_chars).enumerate() {
+                let key = GlyphKey { charcode: ch as u32, face_id: 0, font_size_bits };
+                atlas.get_or_create(&self.device, &self.queue, &key, None);
+                glyphs.push((key, x + ci as f32 * char_width, y, color));
+            }
+        };
+        // TEXT right-aligned in a column of WIDTH characters from X
+        let add_right = |glyphs: &mut Vec<(GlyphKey, f32, f32, [f32; 4])>,
+                         atlas: &mut WgpuGlyphAtlas,
+                         text: &str, x: f32, y: f32, width: usize, color: [f32; 4]| {
+            let tx = x + width.saturating_sub(text.chars().count()) as f32 * char_width;
+            add_text(glyphs, atlas, text, tx, y, width, color);
+        };
+        let chars_in = |w: f32| (w / char_width).max(0.0) as usize;
+
+        let title_y = py + pad + 2.0;
+        add_text(&mut overlay_glyphs, glyph_atlas, "Processes", px + pad, title_y, chars_in(cpu_title_x - px - pad), text_color);
+        if monitor.sampled {
+            add_text(&mut overlay_glyphs, glyph_atlas, &cpu_text, cpu_title_x, title_y, usize::MAX, dim_color);
+            add_text(&mut overlay_glyphs, glyph_atlas, &mem_text, mem_title_x, title_y, usize::MAX, dim_color);
+        }
+
+        // Column headers, the sorted one marked with its direction
+        let header_y = ly - line_h + 2.0;
+        let arrow = if monitor.descending { "\u{25bc}" } else { "\u{25b2}" };
+        let headers = [
+            (ProcessSortKey::Pid, "PID", pid_x, Some(PID_CHARS)),
+            (ProcessSortKey::User, "User", user_x, None),
+            (ProcessSortKey::Cpu, "CPU%", cpu_x, Some(CPU_CHARS)),
+            (ProcessSortKey::Memory, "Mem", mem_x, Some(MEM_CHARS)),
+            (ProcessSortKey::Command, "Command", command_x, None),
+        ];
+        for (key, label, x, right_width) in headers {
+            let color = if key == monitor.sort { text_color } else { dim_color };
+            match right_width {
+                // Numeric columns keep the arrow left of the label
+                Some(width) => {
+                    let label = if key == monitor.sort { format!("{} {}", arrow, label) } else { label.to_string() };
+                    add_right(&mut overlay_glyphs, glyph_atlas, &label, x, header_y, width, color);
+                }
+                None => {
+                    let label = if key == monitor.sort { format!("{} {}", label, arrow) } else { label.to_string() };
+                    add_text(&mut overlay_glyphs, glyph_atlas, &label, x, header_y, usize::MAX, color);
+                }
+            }
+        }
+
+        // Only the rows on screen; stopped processes and zombies dimmed
+        let command_chars = chars_in(lx + lw - command_x);
+        for pos in first..last {
+            let Some(process) = monitor.process_at(pos) else {
+                break;
+            };
+            let y = row_y(pos) + 2.0;
+            let color = if matches!(process.state, 'Z' | 'T' | 't') { dim_color } else { text_color };
+            add_right(&mut overlay_glyphs, glyph_atlas, &process.pid.to_string(), pid_x, y, PID_CHARS, dim_color);
+            let mut user = process.user.clone();
+            if user.chars().count() > USER_CHARS {
+                user = user.chars().take(USER_CHARS - 1).chain(std::iter::once('\u{2026}')).collect();
+            }
+            add_text(&mut overlay_glyphs, glyph_atlas, &user, user_x, y, USER_CHARS, dim_color);
+            add_right(&mut overlay_glyphs, glyph_atlas, &format!("{:.1}", process.cpu), cpu_x, y, CPU_CHARS, color);
+            add_right(&mut overlay_glyphs, glyph_atlas, &format_size(process.rss), mem_x, y, MEM_CHARS, color);
+            add_text(&mut overlay_glyphs, glyph_atlas, &process.command, command_x, y, command_chars, color);
+        }
+
+        // Footer: the prompt on the left, the count on the right
+        let footer_y = ly + lh + 4.0;
+        let status = monitor.describe();
+        let status_chars = status.chars().count().min(chars_in(lw / 2.0));
+        let status_x = lx + lw - status_chars as f32 * char_width;
+        add_text(&mut overlay_glyphs, glyph_atlas, &status, status_x, footer_y, status_chars, dim_color);
+        if let Some(prompt) = monitor.prompt() {
+            let color = if monitor.pending.is_some() { prompt_color } else { text_color };
+            add_text(&mut overlay_glyphs, glyph_atlas, &prompt, lx, footer_y,
+                     chars_in(lw - status_chars as f32 * char_width - char_width), color);
+        }
+        self.render_overlay_glyphs(view, &mut overlay_glyphs, glyph_atlas);
+    }
+
+    /// Render the date picker overlay: an optional title, then for each
+    /// month shown its name between the turning arrows, the weekday
+    /// names and the day grid with today ringed, the selection filled
+    /// and highlighted days colored and dotted.
+    pub(crate) fn render_date_picker(
+        &self,
+        view: &wgpu::TextureView,
+        picker: &DatePickerState,
+        glyph_atlas: &mut WgpuGlyphAtlas,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        use crate::render_thread::{DatePickerPart, MONTH_NAMES, WEEKDAY_NAMES};
+        use wgpu::util::DeviceExt;
+
+        let logical_w = surface_width as f32 / self.scale_factor;
+        let logical_h = surface_height as f32 / self.scale_factor;
+        let uniforms = Uniforms {
+            screen_size: [logical_w, logical_h],
+            _padding: [0.0, 0.0],
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+        let (fg_r, fg_g, fg_b) = picker.face_fg.unwrap_or((0.9, 0.9, 0.9));
+        let (bg_r, bg_g, bg_b) = picker.face_bg.unwrap_or((0.15, 0.15, 0.18));
+        let bg_color = Color::new(bg_r, bg_g, bg_b, 0.97).srgb_to_linear();
+        let border_color = Color::new(
+            (bg_r * 0.6 + 0.15).min(1.0),
+            (bg_g * 0.6 + 0.15).min(1.0),
+            (bg_b * 0.6 + 0.15).min(1.0),
+            1.0,
+        ).srgb_to_linear();
+        let hover_color = Color::new(
+            bg_r * 0.8 + fg_r * 0.12,
+            bg_g * 0.8 + fg_g * 0.12,
+            bg_b * 0.8 + fg_b * 0.12,
+            0.9,
+        ).srgb_to_linear();
+        let (sel_r, sel_g, sel_b) = picker.selected_bg.unwrap_or((0.25, 0.45, 0.85));
+        let selected_color = Color::new(sel_r, sel_g, sel_b, 1.0).srgb_to_linear();
+        let (today_r, today_g, today_b) = picker.today_color.unwrap_or((1.0, 0.75, 0.35));
+        let today_color = Color::new(today_r, today_g, today_b, 1.0).srgb_to_linear();
+        let (hl_r, hl_g, hl_b) = picker.highlight_color.unwrap_or((0.95, 0.45, 0.45));
+        let highlight_color = Color::new(hl_r, hl_g, hl_b, 1.0).srgb_to_linear();
+        let to_rgba = |c: Color| [c.r, c.g, c.b, c.a];
+        let text_color = to_rgba(Color::new(fg_r, fg_g, fg_b, 1.0).srgb_to_linear());
+        let dim_color = to_rgba(Color::new(
+            fg_r * 0.6 + bg_r * 0.4,
+            fg_g * 0.6 + bg_g * 0.4,
+            fg_b * 0.6 + bg_b * 0.4,
+            1.0,
+        ).srgb_to_linear());
+        let selected_dot = match picker.selected_fg {
+            Some((r, g, b)) => Color::new(r, g, b, 1.0).srgb_to_linear(),
+            None => Color::new(1.0, 1.0, 1.0, 1.0),
+        };
+        let selected_text = to_rgba(selected_dot);
+
+        let (px, py, pw, ph) = picker.bounds;
+        let pad = picker.padding;
+        let (cell_w, cell_h) = (picker.cell_width, picker.cell_height);
+
+        // === Pass 1: Panel, hover, selection, today and highlight dots ===
+        let mut rect_vertices: Vec<RectVertex> = Vec::new();
+        for i in 1..=4 {
+            let offset = i as f32 * 1.5;
+            let alpha = 0.12 * (1.0 - (i - 1) as f32 / 4.0);
+            self.add_rect(&mut rect_vertices, px + offset, py + offset, pw, ph, &Color::new(0.0, 0.0, 0.0, alpha));
+        }
+        self.add_rect(&mut rect_vertices, px, py, pw, ph, &bg_color);
+        self.add_rect(&mut rect_vertices, px, py, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py + ph - 1.0, pw, 1.0, &border_color);
+        self.add_rect(&mut rect_vertices, px, py, 1.0, ph, &border_color);
+        self.add_rect(&mut rect_vertices, px + pw - 1.0, py, 1.0, ph, &border_color);
+        // A rule under the weekday names of each month
+        for i in 0..picker.months {
+            let rule_y = picker.grid_y() - 2.0;
+            self.add_rect(&mut rect_vertices, picker.month_x(i), rule_y, 7.0 * cell_w, 1.0, &border_color);
+        }
+
+        let [previous, next] = picker.arrow_rects();
+        let hovered = match picker.hover {
+            Some(DatePickerPart::Day(day)) => picker.day_rect(day),
+            Some(DatePickerPart::PreviousMonth) => Some(previous),
+            Some(DatePickerPart::NextMonth) => Some(next),
+            None => None,
+        };
+        if let Some((x, y, w, h)) = hovered {
+            self.add_rect(&mut rect_vertices, x + 1.0, y + 1.0, w - 2.0, h - 2.0, &hover_color);
+        }
+        if let Some((x, y, w, h)) = picker.day_rect(picker.selected) {
+            self.add_rect(&mut rect_vertices, x + 1.0, y + 1.0, w - 2.0, h - 2.0, &selected_color);
+        }
+        if let Some((x, y, w, h)) = picker.day_rect(picker.today) {
+            let (x, y, w, h) = (x + 1.0, y + 1.0, w - 2.0, h - 2.0);
+            self.add_rect(&mut rect_vertices, x, y, w, 1.5, &today_color);
+            self.add_rect(&mut rect_vertices, x, y + h - 1.5, w, 1.5, &today_color);
+            self.add_rect(&mut rect_vertices, x, y, 1.5, h, &today_color);
+            self.add_rect(&mut rect_vertices, x + w - 1.5, y, 1.5, h, &today_color);
+        }
+        let highlighted = (0..picker.months)
+            .flat_map(|i| picker.month_days(i))
+            .filter(|&day| picker.is_highlighted(day));
+        for day in highlighted {
+            if let Some((x, y, w, h)) = picker.day_rect(day) {
+                let dot = if day == picker.selected { selected_dot } else { highlight_color };
+                self.add_rect(&mut rect_vertices, (x + w / 2.0 - 1.5).round(), y + h - 5.0, 3.0, 2.0, &dot);
+            }
+        }
+
+        let rect_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Date Picker Rect Buffer"),
+            contents: bytemuck::cast_slice(&rect_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Date Picker Rect Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Date Picker Rect Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.rect_pipeline);
+            pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            pass.set_vertex_buffer(0, rect_buffer.slice(..));
+            pass.draw(0..rect_vertices.len() as u32, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        // === Pass 2: Title, month names, weekday names and days ===
+        let char_width = glyph_atlas.default_font_size() * 0.6;
+        let font_size_bits = 0.0_f32.to_bits();
+        let mut overlay_glyphs: Vec<(GlyphKey, f32, f32, [f32; 4])> = Vec::new();
+        // TEXT centered in a cell of width W from X, on the row at Y
+        let add_centered = |glyphs: &mut Vec<(GlyphKey, f32, f32, [f32; 4])>,
+                            atlas: &mut WgpuGlyphAtlas,
+                            text: &str, x: f32, y: f32, w: f32, color: [f32; 4]| {
+            let max_chars = (w / char_width).max(0.0) as usize;
+            let count = text.chars().count().min(max_chars);
+            let tx = (x + (w - count as f32 * char_width) / 2.0).round();
+            for (ci, ch) in text.chars().take(count).enumerate() {
+                let key = GlyphKey { charcode: ch as u32, face_id: 0, font_size_bits };
+                atlas.get_or_create(&self.device, &self.queue, &key, None);
+                glyphs.push((key, tx + ci as f32 * char_width, y + 3.0, color));
+            }
+        };
+
+        if let Some(ref title) = picker.title {
+            add_centered(&mut overlay_glyphs, glyph_atlas, title, px + pad, py + pad, pw - 2.0 * pad, dim_color);
+        }
+        let header_y = picker.header_y();
+        for i in 0..picker.months {
+            let (year, month) = picker.shown_month(i);
+            let name = format!("{} {}", MONTH_NAMES[month as usize - 1], year);
+            let x = picker.month_x(i);
+            add_centered(&mut overlay_glyphs, glyph_atlas, &name, x + cell_w, header_y, 5.0 * cell_w, text_color);
+            for col in 0..7 {
+                let weekday = WEEKDAY_NAMES[picker.column_weekday(col)];
+                add_centered(&mut overlay_glyphs, glyph_atlas, weekday,
+                             x + col as f32 * cell_w, header_y + cell_h, cell_w, dim_color);
+            }
+        }
+        add_centered(&mut overlay_glyphs, glyph_atlas, "\u{2039}", previous.0, previous.1, previous.2, text_color);
+        add_centered(&mut overlay_glyphs, glyph_atlas, "\u{203a}", next.0, next.1, next.2, text_color);
+        for i in 0..picker.months {
+            for (n, day) in picker.month_days(i).enumerate() {
+                let Some((x, y, w, _)) = picker.day_rect(day) else {
+                    continue;
+                };
+                let color = if day == picker.selected {
+                    selected_text
+                } else if picker.is_highlighted(day) {
+                    to_rgba(highlight_color)
+                } else {
+                    text_color
+                };
+                add_centered(&mut overlay_glyphs, glyph_atlas, &(n + 1).to_string(), x, y, w, color);
+            }
+        }
+        self.render_overlay_glyphs(view, &mut overlay_glyphs, glyph_atlas);
+    }
+
     /// Render a batch of overlay glyphs in a single render pass.
     ///
     /// Each entry is (GlyphKey, x, y, color). Glyphs are sorted by key
@@ -1742,14 +3457,14 @@ impl WgpuRenderer {
                         bounds: info.bounds,
                         old_text,
                         new_text: new_text.clone(),
-                        started: std::time::Instant::now(),
+                        started: self.frame_time,
                         duration: std::time::Duration::from_millis(self.effects.title_fade.duration_ms as u64),
                     });
                 }
                 self.prev_breadcrumb_text.insert(wid, new_text.clone());
             }
             // Clean up expired fades
-            self.active_title_fades.retain(|f| f.started.elapsed() < f.duration);
+            self.active_title_fades.retain(|f| self.frame_time.saturating_duration_since(f.started) < f.duration);
             if !self.active_title_fades.is_empty() {
                 self.needs_continuous_redraw = true;
             }
@@ -1773,7 +3488,7 @@ impl WgpuRenderer {
 
             if let Some(fade) = active_fade {
                 // Crossfade: render old text fading out, new text fading in
-                let t = (fade.started.elapsed().as_secs_f32() / fade.duration.as_secs_f32()).min(1.0);
+                let t = (self.frame_time.saturating_duration_since(fade.started).as_secs_f32() / fade.duration.as_secs_f32()).min(1.0);
                 // Ease-out quadratic
                 let eased = t * (2.0 - t);
                 let new_alpha = eased;
@@ -1980,6 +3695,80 @@ impl WgpuRenderer {
         }
     }
 
+    /// Render typed characters the core has not drawn yet after the
+    /// cursor: over a patch of frame background, in the cursor's COLOR
+    /// at OPACITY and underlined, with a bar where typing continues.
+    pub fn render_typeahead_echo(
+        &self,
+        view: &wgpu::TextureView,
+        frame_glyphs: &FrameGlyphBuffer,
+        glyph_atlas: &mut WgpuGlyphAtlas,
+        echo: &TypeaheadEcho,
+        color: Color,
+        opacity: f32,
+    ) {
+        let Some(anchor) = echo.anchor() else {
+            return;
+        };
+
+        let mut rect_vertices: Vec<RectVertex> = Vec::new();
+        let bg = &frame_glyphs.background;
+        let bg_color = Color::new(bg.r, bg.g, bg.b, 1.0);
+        let ink = Color::new(color.r, color.g, color.b, opacity);
+        let font_size_bits = 0.0_f32.to_bits();
+        let mut text_glyphs: Vec<(GlyphKey, f32, f32, [f32; 4])> = Vec::new();
+        let mut end_x = anchor.x;
+        for c in echo.chars() {
+            self.add_rect(&mut rect_vertices, c.x, anchor.y, c.width, anchor.height, &bg_color);
+            let key = GlyphKey {
+                charcode: c.ch as u32,
+                face_id: 0,
+                font_size_bits,
+            };
+            glyph_atlas.get_or_create(&self.device, &self.queue, &key, None);
+            text_glyphs.push((key, c.x, anchor.y, [ink.r, ink.g, ink.b, ink.a]));
+            end_x = c.x + c.width;
+        }
+        // Provisional style: underline the echo, then show the caret
+        self.add_rect(&mut rect_vertices, anchor.x, anchor.y + anchor.height - 1.0,
+                      end_x - anchor.x, 1.0, &ink);
+        self.add_rect(&mut rect_vertices, end_x, anchor.y, 2.0, anchor.height, &color);
+
+        let rect_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Typeahead Echo Rect Buffer"),
+            contents: bytemuck::cast_slice(&rect_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Typeahead Echo Rect Encoder"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Typeahead Echo Rect Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.rect_pipeline);
+            pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            pass.set_vertex_buffer(0, rect_buffer.slice(..));
+            pass.draw(0..rect_vertices.len() as u32, 0..1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        if !text_glyphs.is_empty() {
+            self.render_overlay_glyphs(view, &mut text_glyphs, glyph_atlas);
+        }
+    }
+
     pub fn render_fps_overlay(
         &self,
         view: &wgpu::TextureView,
@@ -2083,9 +3872,46 @@ impl WgpuRenderer {
         surface_width: u32,
         surface_height: u32,
         alpha: f32,
+    ) {
+        let logical_w = surface_width as f32 / self.scale_factor;
+        let logical_h = surface_height as f32 / self.scale_factor;
+        self.render_flash_rects(
+            view, surface_width, surface_height,
+            &[(0.0, 0.0, logical_w, logical_h, alpha)],
+        );
+    }
+
+    /// Flash RECTS, each (x, y, width, height, alpha) in logical pixels,
+    /// with a semi-transparent white overlay drawn above all content.
+    pub fn render_flash_rects(
+        &self,
+        view: &wgpu::TextureView,
+        surface_width: u32,
+        surface_height: u32,
+        rects: &[(f32, f32, f32, f32, f32)],
+    ) {
+        // Semi-transparent white overlay in linear space
+        let rects: Vec<_> = rects
+            .iter()
+            .map(|&(x, y, w, h, alpha)| (x, y, w, h, Color::new(1.0, 1.0, 1.0, alpha).srgb_to_linear()))
+            .collect();
+        self.render_tinted_rects(view, surface_width, surface_height, &rects);
+    }
+
+    /// Draw RECTS, each (x, y, width, height, color) in logical pixels
+    /// with a linear, usually translucent color, above all content.
+    pub fn render_tinted_rects(
+        &self,
+        view: &wgpu::TextureView,
+        surface_width: u32,
+        surface_height: u32,
+        rects: &[(f32, f32, f32, f32, Color)],
     ) {
         use wgpu::util::DeviceExt;
 
+        if rects.is_empty() {
+            return;
+        }
         let logical_w = surface_width as f32 / self.scale_factor;
         let logical_h = surface_height as f32 / self.scale_factor;
         let uniforms = Uniforms {
@@ -2095,24 +3921,23 @@ impl WgpuRenderer {
         self.queue
             .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
 
-        // Semi-transparent white overlay in linear space
-        let flash_color = Color::new(1.0, 1.0, 1.0, alpha).srgb_to_linear();
-
         let mut rect_vertices: Vec<RectVertex> = Vec::new();
-        self.add_rect(&mut rect_vertices, 0.0, 0.0, logical_w, logical_h, &flash_color);
+        for (x, y, w, h, color) in rects {
+            self.add_rect(&mut rect_vertices, *x, *y, *w, *h, color);
+        }
 
         let rect_buffer = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Visual Bell Buffer"),
+            label: Some("Tinted Rects Buffer"),
             contents: bytemuck::cast_slice(&rect_vertices),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Visual Bell Encoder"),
+            label: Some("Tinted Rects Encoder"),
         });
         {
             let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Visual Bell Pass"),
+                label: Some("Tinted Rects Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view,
                     resolve_target: None,
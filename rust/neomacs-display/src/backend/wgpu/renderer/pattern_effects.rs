@@ -99,7 +99,7 @@ pub(super) fn emit_heat_distortion(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.heat_distortion.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let ew = ctx.effects.heat_distortion.edge_width;
     let intensity = ctx.effects.heat_distortion.intensity;
     let spd = ctx.effects.heat_distortion.speed;
@@ -138,7 +138,7 @@ pub(super) fn emit_neon_border(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.neon_border.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (nr, ng, nb) = ctx.effects.neon_border.color;
     let thick = ctx.effects.neon_border.thickness;
     let intensity = ctx.effects.neon_border.intensity;
@@ -177,7 +177,7 @@ pub(super) fn emit_plasma_border(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.plasma_border.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (r1, g1, b1) = ctx.effects.plasma_border.color1;
     let (r2, g2, b2) = ctx.effects.plasma_border.color2;
     let bw = ctx.effects.plasma_border.width;
@@ -228,7 +228,7 @@ pub(super) fn emit_topographic_contour(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.topo_contour.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (tr, tg, tb) = ctx.effects.topo_contour.color;
     let top = ctx.effects.topo_contour.opacity;
     let spacing = ctx.effects.topo_contour.spacing.max(5.0);
@@ -265,7 +265,7 @@ pub(super) fn emit_constellation(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.constellation.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (sr, sg, sb) = ctx.effects.constellation.color;
     let sop = ctx.effects.constellation.opacity;
     let count = ctx.effects.constellation.star_count.min(200);
@@ -318,7 +318,7 @@ pub(super) fn emit_kaleidoscope(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.kaleidoscope.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (kr, kg, kb) = ctx.effects.kaleidoscope.color;
     let kop = ctx.effects.kaleidoscope.opacity;
     let segs = ctx.effects.kaleidoscope.segments.clamp(3, 12);
@@ -356,7 +356,7 @@ pub(super) fn emit_noise_field(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.noise_field.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (nr, ng, nb) = ctx.effects.noise_field.color;
     let nop = ctx.effects.noise_field.opacity;
     let scale = ctx.effects.noise_field.scale.max(10.0);
@@ -394,7 +394,7 @@ pub(super) fn emit_spiral_vortex(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.spiral_vortex.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (vr, vg, vb) = ctx.effects.spiral_vortex.color;
     let vop = ctx.effects.spiral_vortex.opacity;
     let arms = ctx.effects.spiral_vortex.arms.clamp(2, 12);
@@ -431,7 +431,7 @@ pub(super) fn emit_diamond_lattice(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.diamond_lattice.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (dr, dg, db) = ctx.effects.diamond_lattice.color;
     let dop = ctx.effects.diamond_lattice.opacity;
     let cell = ctx.effects.diamond_lattice.cell_size.max(10.0);
@@ -479,7 +479,7 @@ pub(super) fn emit_wave_interference(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.wave_interference.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (wr, wg, wb) = ctx.effects.wave_interference.color;
     let wop = ctx.effects.wave_interference.opacity;
     let wl = ctx.effects.wave_interference.wavelength.max(10.0);
@@ -532,7 +532,7 @@ pub(super) fn emit_chevron(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.chevron_pattern.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (cr, cg, cb) = ctx.effects.chevron_pattern.color;
     let cop = ctx.effects.chevron_pattern.opacity;
     let spacing = ctx.effects.chevron_pattern.spacing.max(15.0);
@@ -579,7 +579,7 @@ pub(super) fn emit_sunburst(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.sunburst_pattern.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (cr, cg, cb) = ctx.effects.sunburst_pattern.color;
     let ray_count = ctx.effects.sunburst_pattern.ray_count.max(4) as f32;
     let speed = ctx.effects.sunburst_pattern.speed;
@@ -630,7 +630,7 @@ pub(super) fn emit_honeycomb_dissolve(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.honeycomb_dissolve.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (cr, cg, cb) = ctx.effects.honeycomb_dissolve.color;
     let cell = ctx.effects.honeycomb_dissolve.cell_size.max(8.0);
     let speed = ctx.effects.honeycomb_dissolve.speed;
@@ -671,7 +671,7 @@ pub(super) fn emit_moire(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.moire_pattern.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (cr, cg, cb) = ctx.effects.moire_pattern.color;
     let spacing = ctx.effects.moire_pattern.line_spacing.max(4.0);
     let angle_off = ctx.effects.moire_pattern.angle_offset * std::f32::consts::PI / 180.0;
@@ -712,7 +712,7 @@ pub(super) fn emit_dot_matrix(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.dot_matrix.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (cr, cg, cb) = ctx.effects.dot_matrix.color;
     let spacing = ctx.effects.dot_matrix.spacing.max(4.0);
     let pulse = ctx.effects.dot_matrix.pulse_speed;
@@ -744,7 +744,7 @@ pub(super) fn emit_concentric_rings(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.concentric_rings.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (cr, cg, cb) = ctx.effects.concentric_rings.color;
     let spacing = ctx.effects.concentric_rings.spacing.max(10.0);
     let speed = ctx.effects.concentric_rings.expansion_speed;
@@ -784,7 +784,7 @@ pub(super) fn emit_zigzag(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.zigzag_pattern.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (cr, cg, cb) = ctx.effects.zigzag_pattern.color;
     let amplitude = ctx.effects.zigzag_pattern.amplitude;
     let freq = ctx.effects.zigzag_pattern.frequency;
@@ -888,7 +888,7 @@ pub(super) fn emit_guilloche(ctx: &EffectCtx) -> Vec<RectVertex> {
     }
     let width = ctx.renderer_width;
     let height = ctx.renderer_height;
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (gr, gg, gb) = ctx.effects.guilloche.color;
     let curves = ctx.effects.guilloche.curve_count;
     let freq = ctx.effects.guilloche.wave_freq;
@@ -930,7 +930,7 @@ pub(super) fn emit_celtic_knot(ctx: &EffectCtx) -> Vec<RectVertex> {
     }
     let width = ctx.renderer_width;
     let height = ctx.renderer_height;
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (kr, kg, kb) = ctx.effects.celtic_knot.color;
     let scale = ctx.effects.celtic_knot.scale;
     let speed = ctx.effects.celtic_knot.weave_speed;
@@ -1135,7 +1135,7 @@ pub(super) fn emit_trefoil_knot(ctx: &EffectCtx) -> Vec<RectVertex> {
     }
     let width = ctx.renderer_width;
     let height = ctx.renderer_height;
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (kr, kg, kb) = ctx.effects.trefoil_knot.color;
     let knot_size = ctx.effects.trefoil_knot.size;
     let rot_speed = ctx.effects.trefoil_knot.rotation_speed;
@@ -1245,7 +1245,7 @@ pub(super) fn emit_target_reticle(ctx: &EffectCtx) -> Vec<RectVertex> {
     }
     let width = ctx.renderer_width;
     let height = ctx.renderer_height;
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (tr, tg, tb) = ctx.effects.target_reticle.color;
     let ring_count = ctx.effects.target_reticle.ring_count;
     let pulse = ctx.effects.target_reticle.pulse_speed;
@@ -1348,7 +1348,7 @@ pub(super) fn emit_sine_wave(ctx: &EffectCtx) -> Vec<RectVertex> {
     }
     let width = ctx.renderer_width;
     let height = ctx.renderer_height;
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (sr, sg, sb) = ctx.effects.sine_wave.color;
     let amplitude = ctx.effects.sine_wave.amplitude;
     let wavelength = ctx.effects.sine_wave.wavelength;
@@ -1382,7 +1382,7 @@ pub(super) fn emit_rotating_gear(ctx: &EffectCtx) -> Vec<RectVertex> {
     }
     let width = ctx.renderer_width;
     let height = ctx.renderer_height;
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (gr, gg, gb) = ctx.effects.rotating_gear.color;
     let gear_size = ctx.effects.rotating_gear.size;
     let speed = ctx.effects.rotating_gear.speed;
@@ -1431,7 +1431,7 @@ pub(super) fn emit_crosshatch(ctx: &EffectCtx) -> Vec<RectVertex> {
     }
     let width = ctx.renderer_width;
     let height = ctx.renderer_height;
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (cr, cg, cb) = ctx.effects.crosshatch_pattern.color;
     let spacing = ctx.effects.crosshatch_pattern.line_spacing;
     let angle_deg = ctx.effects.crosshatch_pattern.angle;
@@ -1494,7 +1494,7 @@ pub(super) fn emit_hex_grid(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.hex_grid.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (hr, hg, hb) = ctx.effects.hex_grid.color;
     let hop = ctx.effects.hex_grid.opacity;
     let cell = ctx.effects.hex_grid.cell_size.max(10.0);
@@ -1542,7 +1542,7 @@ pub(super) fn emit_circuit_board(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.circuit_trace.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (cr, cg, cb) = ctx.effects.circuit_trace.color;
     let cop = ctx.effects.circuit_trace.opacity;
     let tw = ctx.effects.circuit_trace.width;
@@ -1606,7 +1606,7 @@ pub(super) fn emit_warp_grid(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.warp_grid.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let (wr, wg, wb) = ctx.effects.warp_grid.color;
     let wop = ctx.effects.warp_grid.opacity;
     let density = ctx.effects.warp_grid.density.max(2) as f32;
@@ -1651,7 +1651,7 @@ pub(super) fn emit_prism_rainbow_edge(ctx: &EffectCtx) -> Vec<RectVertex> {
     if !ctx.effects.prism_edge.enabled {
         return Vec::new();
     }
-    let now = std::time::Instant::now().duration_since(ctx.aurora_start).as_secs_f32();
+    let now = ctx.now.duration_since(ctx.aurora_start).as_secs_f32();
     let pw = ctx.effects.prism_edge.width;
     let pop = ctx.effects.prism_edge.opacity;
     let sat = ctx.effects.prism_edge.saturation;
@@ -1737,6 +1737,7 @@ mod tests {
             surface_width: 800,
             surface_height: 600,
             aurora_start: std::time::Instant::now(),
+            now: std::time::Instant::now(),
             scale_factor: 1.0,
             logical_w: 800.0,
             logical_h: 600.0,
@@ -0,0 +1,477 @@
+//! Post-processing chain and user post-processing shader for WgpuRenderer.
+//!
+//! When a post-processing pass is active (the built-in CRT effect or a
+//! user WGSL shader), the whole frame (including overlays) is rendered to
+//! an intermediate texture, and full-screen passes run over it to produce
+//! the surface image.  A user shader provides an `effect` function; its
+//! source is wrapped in a fixed prelude declaring the bindings, so it can
+//! only read the frame and the uniforms below.
+//! Shaders are validated before use and reloaded when their file changes;
+//! a shader that fails to compile leaves the previous one in place.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use bytemuck::{Pod, Zeroable};
+
+use super::crt::{crt_regions, CrtUniforms};
+use super::WgpuRenderer;
+use crate::core::frame_glyphs::FrameGlyphBuffer;
+
+/// How often the shader file is checked for modifications.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Declarations available to user shaders.
+const PRELUDE: &str = r#"struct PostUniforms {
+    // Frame size in pixels
+    resolution: vec2<f32>,
+    // Seconds since the shader was installed
+    time: f32,
+    _pad0: f32,
+    // Cursor x, y, width, height in pixels
+    cursor: vec4<f32>,
+    // Mouse position in pixels
+    mouse: vec2<f32>,
+    _pad1: vec2<f32>,
+}
+
+@group(0) @binding(0) var<uniform> fx: PostUniforms;
+@group(0) @binding(1) var fx_frame: texture_2d<f32>;
+@group(0) @binding(2) var fx_sampler: sampler;
+
+// Color of the rendered frame at UV (0..1, origin at the top left)
+fn frame_color(uv: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(fx_frame, fx_sampler, uv, 0.0);
+}
+"#;
+
+/// Full-screen triangle and fragment entry point calling the user's
+/// `fn effect(uv: vec2<f32>) -> vec4<f32>`.
+const EPILOGUE: &str = r#"
+struct PostVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn post_vs_main(@builtin(vertex_index) index: u32) -> PostVertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: PostVertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@fragment
+fn post_fs_main(in: PostVertexOutput) -> @location(0) vec4<f32> {
+    return effect(in.uv);
+}
+"#;
+
+/// Complete WGSL module for the user shader SOURCE.
+pub(super) fn assemble_source(source: &str) -> String {
+    let mut wgsl = String::with_capacity(PRELUDE.len() + source.len() + EPILOGUE.len() + 1);
+    wgsl.push_str(PRELUDE);
+    wgsl.push_str(source);
+    wgsl.push('\n');
+    wgsl.push_str(EPILOGUE);
+    wgsl
+}
+
+/// Whether SOURCE depends on time, i.e. needs continuous redraws.
+pub(super) fn is_animated(source: &str) -> bool {
+    source.contains("fx.time")
+}
+
+/// Uniform block matching `PostUniforms` in the prelude.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub(super) struct PostUniforms {
+    pub resolution: [f32; 2],
+    pub time: f32,
+    pub _pad0: f32,
+    pub cursor: [f32; 4],
+    pub mouse: [f32; 2],
+    pub _pad1: [f32; 2],
+}
+
+impl PostUniforms {
+    /// Uniforms for a WIDTH x HEIGHT frame; CURSOR (x, y, w, h) and MOUSE
+    /// are in logical pixels and scaled to physical ones.
+    pub(super) fn new(
+        width: u32,
+        height: u32,
+        scale: f32,
+        time: f32,
+        cursor: (f32, f32, f32, f32),
+        mouse: (f32, f32),
+    ) -> Self {
+        Self {
+            resolution: [width as f32, height as f32],
+            time,
+            _pad0: 0.0,
+            cursor: [cursor.0 * scale, cursor.1 * scale, cursor.2 * scale, cursor.3 * scale],
+            mouse: [mouse.0 * scale, mouse.1 * scale],
+            _pad1: [0.0, 0.0],
+        }
+    }
+}
+
+/// Modification time of PATH, if it can be read.
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Bind group layout shared by all post-processing passes: uniforms,
+/// the previous frame texture and its sampler.
+pub(super) fn post_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Post Pass Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
+
+/// One full-screen pass of the post-processing chain.
+pub(super) struct PostPass {
+    pub(super) pipeline: wgpu::RenderPipeline,
+    pub(super) bind_group_layout: wgpu::BindGroupLayout,
+    pub(super) uniform_buffer: wgpu::Buffer,
+}
+
+/// Installed user shader.
+pub(super) struct PostShader {
+    path: PathBuf,
+    /// Modification time of the source the current pipeline was built from
+    modified: Option<SystemTime>,
+    last_check: Instant,
+    started: Instant,
+    animated: bool,
+    /// None until the file compiles successfully
+    pipeline: Option<wgpu::RenderPipeline>,
+    bind_group_layout: wgpu::BindGroupLayout,
+    uniform_buffer: wgpu::Buffer,
+}
+
+impl WgpuRenderer {
+    /// Install the WGSL post-processing shader in PATH, or remove the
+    /// current one if PATH is None.
+    pub fn set_post_shader(&mut self, path: Option<PathBuf>) {
+        let Some(path) = path else {
+            self.post_shader = None;
+            return;
+        };
+        let uniform_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post Shader Uniforms"),
+            size: std::mem::size_of::<PostUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        self.post_shader = Some(PostShader {
+            path,
+            modified: None,
+            last_check: Instant::now(),
+            started: self.frame_time,
+            animated: false,
+            pipeline: None,
+            bind_group_layout: post_bind_group_layout(&self.device),
+            uniform_buffer,
+        });
+        self.reload_post_shader();
+    }
+
+    /// Recompile the installed shader from its file.  On failure the
+    /// previous pipeline (if any) is kept.
+    fn reload_post_shader(&mut self) {
+        let Some(shader) = self.post_shader.as_ref() else {
+            return;
+        };
+        let modified = modified_time(&shader.path);
+        let source = match std::fs::read_to_string(&shader.path) {
+            Ok(source) => source,
+            Err(e) => {
+                log::warn!("Post shader {}: {}", shader.path.display(), e);
+                if let Some(shader) = self.post_shader.as_mut() {
+                    shader.modified = modified;
+                }
+                return;
+            }
+        };
+        let result = self.build_post_pipeline(&source, &shader.bind_group_layout);
+        let shader = self.post_shader.as_mut().unwrap();
+        shader.modified = modified;
+        match result {
+            Ok(pipeline) => {
+                log::info!("Post shader loaded from {}", shader.path.display());
+                shader.pipeline = Some(pipeline);
+                shader.animated = is_animated(&source);
+            }
+            Err(e) => log::warn!("Post shader {} rejected: {}", shader.path.display(), e),
+        }
+    }
+
+    /// Compile and validate the user SOURCE into a pipeline.
+    fn build_post_pipeline(
+        &self,
+        source: &str,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Result<wgpu::RenderPipeline, String> {
+        // Catch validation errors instead of letting them reach the
+        // device's uncaptured error handler (which panics).
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = self.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Post Shader"),
+            source: wgpu::ShaderSource::Wgsl(assemble_source(source).into()),
+        });
+        let pipeline = self.create_post_pipeline(&module, "post_vs_main", "post_fs_main", bind_group_layout);
+        match pollster::block_on(self.device.pop_error_scope()) {
+            Some(error) => Err(error.to_string()),
+            None => Ok(pipeline),
+        }
+    }
+
+    /// Full-screen pipeline for a post-processing pass.
+    pub(super) fn create_post_pipeline(
+        &self,
+        module: &wgpu::ShaderModule,
+        vs_entry: &str,
+        fs_entry: &str,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::RenderPipeline {
+        let layout = self.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Pass Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        self.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Post Pass Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module,
+                entry_point: Some(vs_entry),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module,
+                entry_point: Some(fs_entry),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: self.surface_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    /// Reload the shader if its file changed.  Returns true if the frame
+    /// needs redrawing: the shader was reloaded or animates over time.
+    pub fn poll_post_shader(&mut self) -> bool {
+        let Some(shader) = self.post_shader.as_mut() else {
+            return false;
+        };
+        let mut reloaded = false;
+        if shader.last_check.elapsed() >= RELOAD_CHECK_INTERVAL {
+            shader.last_check = Instant::now();
+            if modified_time(&shader.path) != shader.modified {
+                self.reload_post_shader();
+                reloaded = true;
+            }
+        }
+        let shader = self.post_shader.as_ref().unwrap();
+        reloaded || (shader.animated && shader.pipeline.is_some())
+    }
+
+    /// Prepare the post-processing chain for FRAME.  Returns the view of
+    /// the intermediate texture the frame should be rendered to, or None
+    /// if no post-processing pass is active.
+    pub fn prepare_post_effects(&mut self, frame: Option<&FrameGlyphBuffer>) -> Option<wgpu::TextureView> {
+        let screen = (
+            self.width as f32 / self.scale_factor,
+            self.height as f32 / self.scale_factor,
+        );
+        self.crt_regions = frame
+            .map(|frame| crt_regions(&self.effects.crt, frame, screen))
+            .unwrap_or_default();
+        let user_active = self.post_shader.as_ref().is_some_and(|s| s.pipeline.is_some());
+        let passes = usize::from(!self.crt_regions.is_empty()) + usize::from(user_active);
+        if passes == 0 {
+            return None;
+        }
+        // The last pass writes to the surface; earlier ones ping-pong
+        // between two intermediate textures.
+        let (width, height) = (self.width, self.height);
+        self.post_targets.truncate(passes.min(2));
+        for i in 0..passes.min(2) {
+            if !matches!(self.post_targets.get(i), Some((_, w, h)) if *w == width && *h == height) {
+                let (texture, _) = self.create_offscreen_texture(width, height);
+                if i < self.post_targets.len() {
+                    self.post_targets[i] = (texture, width, height);
+                } else {
+                    self.post_targets.push((texture, width, height));
+                }
+            }
+        }
+        Some(self.post_targets[0].0.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    /// Run the post-processing passes prepared by `prepare_post_effects`
+    /// over the intermediate texture, writing the result into VIEW.
+    /// CURSOR (x, y, w, h) and MOUSE are in logical pixels.
+    pub fn render_post_effects(
+        &mut self,
+        view: &wgpu::TextureView,
+        cursor: (f32, f32, f32, f32),
+        mouse: (f32, f32),
+    ) {
+        let (width, height, scale) = (self.width, self.height, self.scale_factor);
+        if !self.crt_regions.is_empty() {
+            let uniforms = CrtUniforms::new(&self.effects.crt, width, height, scale, &self.crt_regions);
+            let queue = self.queue.clone();
+            let pass = self.crt_pass();
+            queue.write_buffer(&pass.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+        }
+        let mut passes: Vec<(&wgpu::RenderPipeline, &wgpu::BindGroupLayout, &wgpu::Buffer)> = Vec::new();
+        if !self.crt_regions.is_empty() {
+            if let Some(pass) = self.crt_pass.as_ref() {
+                passes.push((&pass.pipeline, &pass.bind_group_layout, &pass.uniform_buffer));
+            }
+        }
+        if let Some(shader) = self.post_shader.as_ref() {
+            if let Some(pipeline) = shader.pipeline.as_ref() {
+                let uniforms = PostUniforms::new(
+                    width,
+                    height,
+                    scale,
+                    self.frame_time.saturating_duration_since(shader.started).as_secs_f32(),
+                    cursor,
+                    mouse,
+                );
+                self.queue
+                    .write_buffer(&shader.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+                passes.push((pipeline, &shader.bind_group_layout, &shader.uniform_buffer));
+            }
+        }
+        if passes.is_empty() || self.post_targets.len() < passes.len().min(2) {
+            return;
+        }
+
+        let views: Vec<wgpu::TextureView> = self
+            .post_targets
+            .iter()
+            .map(|(texture, ..)| texture.create_view(&wgpu::TextureViewDescriptor::default()))
+            .collect();
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Post Pass Encoder"),
+        });
+        for (i, (pipeline, layout, uniform_buffer)) in passes.iter().enumerate() {
+            let source = &views[i % 2];
+            let target = if i + 1 == passes.len() { view } else { &views[(i + 1) % 2] };
+            let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Post Pass Bind Group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(source),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(self.image_cache.sampler()),
+                    },
+                ],
+            });
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Post Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_is_wrapped_between_prelude_and_entry_points() {
+        let user = "fn effect(uv: vec2<f32>) -> vec4<f32> { return frame_color(uv); }";
+        let wgsl = assemble_source(user);
+        let prelude = wgsl.find("var<uniform> fx").unwrap();
+        let body = wgsl.find(user).unwrap();
+        let entry = wgsl.find("fn post_fs_main").unwrap();
+        assert!(prelude < body && body < entry);
+    }
+
+    #[test]
+    fn animation_is_detected_from_time_uniform() {
+        assert!(is_animated("return frame_color(uv) * sin(fx.time);"));
+        assert!(!is_animated("return frame_color(uv).bgra;"));
+    }
+
+    #[test]
+    fn uniforms_are_scaled_to_physical_pixels() {
+        let u = PostUniforms::new(1600, 1200, 2.0, 1.5, (10.0, 20.0, 8.0, 16.0), (5.0, 6.0));
+        assert_eq!(u.resolution, [1600.0, 1200.0]);
+        assert_eq!(u.time, 1.5);
+        assert_eq!(u.cursor, [20.0, 40.0, 16.0, 32.0]);
+        assert_eq!(u.mouse, [10.0, 12.0]);
+        // Must match the WGSL struct layout (vec4 aligned to 16 bytes)
+        assert_eq!(std::mem::size_of::<PostUniforms>(), 48);
+    }
+
+    #[test]
+    fn missing_file_has_no_modification_time() {
+        assert_eq!(modified_time(Path::new("/nonexistent/neomacs-post.wgsl")), None);
+    }
+}
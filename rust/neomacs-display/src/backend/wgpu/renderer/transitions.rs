@@ -2,7 +2,7 @@
 
 use super::WgpuRenderer;
 use wgpu::util::DeviceExt;
-use super::super::vertex::{GlyphVertex};
+use super::super::vertex::{GlyphVertex, RectVertex};
 use crate::core::types::{Color, Rect};
 use crate::core::scroll_animation::{ScrollEffect, ScrollEasing};
 
@@ -386,6 +386,371 @@ impl WgpuRenderer {
         }
     }
 
+    /// Text scale zoom: the old and new frames are scaled by OLD_SCALE and
+    /// NEW_SCALE about the top-left corner of BOUNDS, crossfading with T.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_text_zoom(
+        &self,
+        surface_view: &wgpu::TextureView,
+        old_bind_group: &wgpu::BindGroup,
+        new_bind_group: &wgpu::BindGroup,
+        old_scale: f32,
+        new_scale: f32,
+        t: f32,
+        bounds: &crate::core::types::Rect,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        let (sx, sy, sw, sh, _w, _h, uv_l, uv_t, uv_r, uv_b) =
+            match self.scroll_scissor_and_uv(bounds, surface_width, surface_height) {
+                Some(v) => v,
+                None => return,
+            };
+        let x0 = bounds.x;
+        let y0 = bounds.y;
+
+        let make_quad = |scale: f32, alpha: f32| -> [GlyphVertex; 6] {
+            let x1 = x0 + bounds.width * scale;
+            let y1 = y0 + bounds.height * scale;
+            [
+                GlyphVertex { position: [x0, y0], tex_coords: [uv_l, uv_t], color: [1.0, 1.0, 1.0, alpha] },
+                GlyphVertex { position: [x1, y0], tex_coords: [uv_r, uv_t], color: [1.0, 1.0, 1.0, alpha] },
+                GlyphVertex { position: [x1, y1], tex_coords: [uv_r, uv_b], color: [1.0, 1.0, 1.0, alpha] },
+                GlyphVertex { position: [x0, y0], tex_coords: [uv_l, uv_t], color: [1.0, 1.0, 1.0, alpha] },
+                GlyphVertex { position: [x1, y1], tex_coords: [uv_r, uv_b], color: [1.0, 1.0, 1.0, alpha] },
+                GlyphVertex { position: [x0, y1], tex_coords: [uv_l, uv_b], color: [1.0, 1.0, 1.0, alpha] },
+            ]
+        };
+
+        let old_verts = make_quad(old_scale, 1.0 - t);
+        let new_verts = make_quad(new_scale, t);
+        self.submit_scroll_two_quad_pass(
+            surface_view, old_bind_group, new_bind_group,
+            &old_verts, &new_verts, sx, sy, sw, sh,
+        );
+    }
+
+    /// Render a smooth text update: clear BOUNDS to BACKGROUND, then draw
+    /// horizontal strips of the old and new frames.  Strips are
+    /// (source y, destination y, height, alpha) and span the width of
+    /// BOUNDS.
+    pub fn render_line_diff(
+        &self,
+        surface_view: &wgpu::TextureView,
+        old_bind_group: &wgpu::BindGroup,
+        new_bind_group: &wgpu::BindGroup,
+        old_strips: &[(f32, f32, f32, f32)],
+        new_strips: &[(f32, f32, f32, f32)],
+        background: Color,
+        bounds: &crate::core::types::Rect,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        let (sx, sy, sw, sh, _w, h, uv_l, _, uv_r, _) =
+            match self.scroll_scissor_and_uv(bounds, surface_width, surface_height) {
+                Some(v) => v,
+                None => return,
+            };
+        let x0 = bounds.x;
+        let x1 = bounds.x + bounds.width;
+        let strip_vertices = |strips: &[(f32, f32, f32, f32)]| -> Vec<GlyphVertex> {
+            let mut verts = Vec::with_capacity(strips.len() * 6);
+            for &(src_y, dst_y, height, alpha) in strips {
+                let (v0, v1) = (src_y / h, (src_y + height) / h);
+                let (y0, y1) = (dst_y, dst_y + height);
+                let color = [1.0, 1.0, 1.0, alpha];
+                verts.extend_from_slice(&[
+                    GlyphVertex { position: [x0, y0], tex_coords: [uv_l, v0], color },
+                    GlyphVertex { position: [x1, y0], tex_coords: [uv_r, v0], color },
+                    GlyphVertex { position: [x1, y1], tex_coords: [uv_r, v1], color },
+                    GlyphVertex { position: [x0, y0], tex_coords: [uv_l, v0], color },
+                    GlyphVertex { position: [x1, y1], tex_coords: [uv_r, v1], color },
+                    GlyphVertex { position: [x0, y1], tex_coords: [uv_l, v1], color },
+                ]);
+            }
+            verts
+        };
+        let old_verts = strip_vertices(old_strips);
+        let new_verts = strip_vertices(new_strips);
+
+        let c = [background.r, background.g, background.b, 1.0];
+        let (bx1, by1) = (bounds.x + bounds.width, bounds.y + bounds.height);
+        let clear_verts = [
+            RectVertex { position: [bounds.x, bounds.y], color: c },
+            RectVertex { position: [bx1, bounds.y], color: c },
+            RectVertex { position: [bx1, by1], color: c },
+            RectVertex { position: [bounds.x, bounds.y], color: c },
+            RectVertex { position: [bx1, by1], color: c },
+            RectVertex { position: [bounds.x, by1], color: c },
+        ];
+        let clear_vb = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Line Diff Clear VB"),
+            contents: bytemuck::cast_slice(&clear_verts),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let old_vb = (!old_verts.is_empty()).then(|| self.create_scroll_vb(&old_verts));
+        let new_vb = (!new_verts.is_empty()).then(|| self.create_scroll_vb(&new_verts));
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Line Diff Encoder"),
+        });
+        {
+            let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Line Diff Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: surface_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            rp.set_scissor_rect(sx, sy, sw, sh);
+            rp.set_pipeline(&self.rect_pipeline);
+            rp.set_bind_group(0, &self.uniform_bind_group, &[]);
+            rp.set_vertex_buffer(0, clear_vb.slice(..));
+            rp.draw(0..6, 0..1);
+
+            rp.set_pipeline(&self.image_pipeline);
+            rp.set_bind_group(0, &self.uniform_bind_group, &[]);
+            if let Some(vb) = old_vb.as_ref() {
+                rp.set_bind_group(1, old_bind_group, &[]);
+                rp.set_vertex_buffer(0, vb.slice(..));
+                rp.draw(0..old_verts.len() as u32, 0..1);
+            }
+            if let Some(vb) = new_vb.as_ref() {
+                rp.set_bind_group(1, new_bind_group, &[]);
+                rp.set_vertex_buffer(0, vb.slice(..));
+                rp.draw(0..new_verts.len() as u32, 0..1);
+            }
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Draw window BOUNDS of the frame moved, scaled and faded by
+    /// TRANSFORM.  The window is first cleared to BACKGROUND; the result
+    /// is clipped to BOUNDS.
+    pub fn render_window_transform(
+        &self,
+        surface_view: &wgpu::TextureView,
+        frame_bind_group: &wgpu::BindGroup,
+        transform: &crate::core::timeline::WindowTransform,
+        background: Color,
+        bounds: &Rect,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        let (sx, sy, sw, sh, _w, _h, uv_l, uv_t, uv_r, uv_b) =
+            match self.scroll_scissor_and_uv(bounds, surface_width, surface_height) {
+                Some(v) => v,
+                None => return,
+            };
+        let (bx1, by1) = (bounds.x + bounds.width, bounds.y + bounds.height);
+        let c = [background.r, background.g, background.b, 1.0];
+        let clear_verts = [
+            RectVertex { position: [bounds.x, bounds.y], color: c },
+            RectVertex { position: [bx1, bounds.y], color: c },
+            RectVertex { position: [bx1, by1], color: c },
+            RectVertex { position: [bounds.x, bounds.y], color: c },
+            RectVertex { position: [bx1, by1], color: c },
+            RectVertex { position: [bounds.x, by1], color: c },
+        ];
+        let clear_vb = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Window Transform Clear VB"),
+            contents: bytemuck::cast_slice(&clear_verts),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        // Scale about the window center, then offset
+        let width = bounds.width * transform.scale;
+        let height = bounds.height * transform.scale;
+        let x0 = bounds.x + (bounds.width - width) / 2.0 + transform.offset_x;
+        let y0 = bounds.y + (bounds.height - height) / 2.0 + transform.offset_y;
+        let (x1, y1) = (x0 + width, y0 + height);
+        let color = [1.0, 1.0, 1.0, transform.opacity];
+        let vertices = [
+            GlyphVertex { position: [x0, y0], tex_coords: [uv_l, uv_t], color },
+            GlyphVertex { position: [x1, y0], tex_coords: [uv_r, uv_t], color },
+            GlyphVertex { position: [x1, y1], tex_coords: [uv_r, uv_b], color },
+            GlyphVertex { position: [x0, y0], tex_coords: [uv_l, uv_t], color },
+            GlyphVertex { position: [x1, y1], tex_coords: [uv_r, uv_b], color },
+            GlyphVertex { position: [x0, y1], tex_coords: [uv_l, uv_b], color },
+        ];
+        let vb = self.create_scroll_vb(&vertices);
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Window Transform Encoder"),
+        });
+        {
+            let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Window Transform Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: surface_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            rp.set_scissor_rect(sx, sy, sw, sh);
+            rp.set_pipeline(&self.rect_pipeline);
+            rp.set_bind_group(0, &self.uniform_bind_group, &[]);
+            rp.set_vertex_buffer(0, clear_vb.slice(..));
+            rp.draw(0..6, 0..1);
+
+            rp.set_pipeline(&self.image_pipeline);
+            rp.set_bind_group(0, &self.uniform_bind_group, &[]);
+            rp.set_bind_group(1, frame_bind_group, &[]);
+            rp.set_vertex_buffer(0, vb.slice(..));
+            rp.draw(0..6, 0..1);
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Copy the BOUNDS region (logical pixels) of SOURCE, a frame-sized
+    /// offscreen texture, into a texture of its own for use as the old
+    /// content of a buffer transition.
+    pub fn snapshot_region(
+        &self,
+        source: &wgpu::Texture,
+        bounds: &Rect,
+    ) -> Option<(wgpu::Texture, wgpu::TextureView, wgpu::BindGroup)> {
+        let size = source.size();
+        let sf = self.scale_factor;
+        let x = ((bounds.x.max(0.0) * sf) as u32).min(size.width);
+        let y = ((bounds.y.max(0.0) * sf) as u32).min(size.height);
+        let width = ((bounds.width * sf) as u32).min(size.width - x);
+        let height = ((bounds.height * sf) as u32).min(size.height - y);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let (snap, snap_view) = self.create_offscreen_texture(width, height);
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Window Snapshot Encoder"),
+        });
+        encoder.copy_texture_to_texture(
+            wgpu::ImageCopyTexture {
+                texture: source,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyTexture {
+                texture: &snap,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+        let bind_group = self.create_texture_bind_group(&snap_view);
+        Some((snap, snap_view, bind_group))
+    }
+
+    /// Composite a buffer transition over BOUNDS.  OLD_BIND_GROUP samples
+    /// a snapshot of the window (see `snapshot_region`), NEW_BIND_GROUP
+    /// the current frame; QUADS are relative to the window.
+    pub fn render_buffer_transition(
+        &self,
+        surface_view: &wgpu::TextureView,
+        old_bind_group: &wgpu::BindGroup,
+        new_bind_group: &wgpu::BindGroup,
+        quads: &[crate::core::buffer_transition::TransitionQuad],
+        bounds: &Rect,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        use crate::core::buffer_transition::TransitionLayer;
+        let (sx, sy, sw, sh, w, h, ..) =
+            match self.scroll_scissor_and_uv(bounds, surface_width, surface_height) {
+                Some(v) => v,
+                None => return,
+            };
+        if quads.is_empty() {
+            return;
+        }
+        let mut vertices = Vec::with_capacity(quads.len() * 6);
+        for quad in quads {
+            // The snapshot covers only the window; the frame covers the surface
+            let (u0, v0, u1, v1) = match quad.layer {
+                TransitionLayer::Old => (
+                    quad.src.x / bounds.width,
+                    quad.src.y / bounds.height,
+                    (quad.src.x + quad.src.width) / bounds.width,
+                    (quad.src.y + quad.src.height) / bounds.height,
+                ),
+                TransitionLayer::New => (
+                    (bounds.x + quad.src.x) / w,
+                    (bounds.y + quad.src.y) / h,
+                    (bounds.x + quad.src.x + quad.src.width) / w,
+                    (bounds.y + quad.src.y + quad.src.height) / h,
+                ),
+            };
+            let x0 = bounds.x + quad.dest.x;
+            let x1 = x0 + quad.dest.width;
+            // Left and right edges, each centered on the destination
+            let cy = bounds.y + quad.dest.y + quad.dest.height / 2.0;
+            let [hl, hr] = quad.edge_heights;
+            let (l0, l1) = (cy - hl / 2.0, cy + hl / 2.0);
+            let (r0, r1) = (cy - hr / 2.0, cy + hr / 2.0);
+            let b = quad.brightness;
+            let color = [b, b, b, quad.alpha];
+            vertices.extend_from_slice(&[
+                GlyphVertex { position: [x0, l0], tex_coords: [u0, v0], color },
+                GlyphVertex { position: [x1, r0], tex_coords: [u1, v0], color },
+                GlyphVertex { position: [x1, r1], tex_coords: [u1, v1], color },
+                GlyphVertex { position: [x0, l0], tex_coords: [u0, v0], color },
+                GlyphVertex { position: [x1, r1], tex_coords: [u1, v1], color },
+                GlyphVertex { position: [x0, l1], tex_coords: [u0, v1], color },
+            ]);
+        }
+        let vb = self.create_scroll_vb(&vertices);
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Buffer Transition Encoder"),
+        });
+        {
+            let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Buffer Transition Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: surface_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            rp.set_scissor_rect(sx, sy, sw, sh);
+            rp.set_pipeline(&self.image_pipeline);
+            rp.set_bind_group(0, &self.uniform_bind_group, &[]);
+            rp.set_vertex_buffer(0, vb.slice(..));
+            for (i, quad) in quads.iter().enumerate() {
+                let bind_group = match quad.layer {
+                    TransitionLayer::Old => old_bind_group,
+                    TransitionLayer::New => new_bind_group,
+                };
+                rp.set_bind_group(1, bind_group, &[]);
+                let first = (i * 6) as u32;
+                rp.draw(first..first + 6, 0..1);
+            }
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
     /// Helper: compute scissor rect and content UV from bounds.
     fn scroll_scissor_and_uv(
         &self,
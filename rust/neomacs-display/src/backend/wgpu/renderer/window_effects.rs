@@ -12,7 +12,7 @@ use super::{
 };
 use crate::core::types::{Color, Rect};
 use crate::core::face::Face;
-use crate::core::frame_glyphs::FrameGlyph;
+use crate::core::frame_glyphs::{FrameGlyph, SelectionKind, SelectionRun};
 use std::collections::HashMap;
 
 // ---------------------------------------------------------------------------
@@ -97,7 +97,7 @@ pub(super) fn emit_typing_heatmap(
     if !ctx.effects.typing_heatmap.enabled {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let fade_dur = std::time::Duration::from_millis(ctx.effects.typing_heatmap.fade_ms as u64);
 
     // Detect cursor movement and record heat entry
@@ -372,9 +372,9 @@ pub(super) fn emit_window_breathing_border(ctx: &EffectCtx) -> (Vec<RectVertex>,
     if !ctx.effects.breathing_border.enabled {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let cycle = ctx.effects.breathing_border.cycle_ms as f64 / 1000.0;
-    let elapsed = now.elapsed().as_secs_f64();
+    let elapsed = now.duration_since(ctx.aurora_start).as_secs_f64();
     let phase = (elapsed % cycle) / cycle;
     let breath = ((phase * std::f64::consts::TAU).sin() * 0.5 + 0.5) as f32;
     let alpha = ctx.effects.breathing_border.min_opacity
@@ -425,7 +425,7 @@ pub(super) fn emit_cursor_ghost(
     if !ctx.effects.cursor_ghost.enabled {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let fade_dur = std::time::Duration::from_millis(ctx.effects.cursor_ghost.fade_ms as u64);
 
     // Detect cursor movement and spawn ghost
@@ -488,7 +488,7 @@ pub(super) fn emit_edge_glow(
     if !ctx.effects.edge_glow.enabled {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     edge_glow_entries.retain(|e| now.duration_since(e.started) < e.duration);
 
     let mut verts: Vec<RectVertex> = Vec::new();
@@ -531,14 +531,14 @@ pub(super) fn emit_rain_effect(
     if !ctx.effects.rain_effect.enabled {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let fw = ctx.logical_w;
     let fh = ctx.logical_h;
     let dt = 1.0 / 60.0_f32;
 
     // Spawn drops if needed
     while rain_drops.len() < ctx.effects.rain_effect.drop_count as usize {
-        let seed = now.elapsed().subsec_nanos() as u64;
+        let seed = now.duration_since(ctx.aurora_start).subsec_nanos() as u64;
         let h = seed
             .wrapping_mul(2654435761)
             .wrapping_add(rain_drops.len() as u64 * 6364136223846793005);
@@ -561,7 +561,7 @@ pub(super) fn emit_rain_effect(
     for drop in rain_drops.iter_mut() {
         drop.y += drop.speed * dt;
         if drop.y > fh {
-            let seed = now.elapsed().subsec_nanos() as u64;
+            let seed = now.duration_since(ctx.aurora_start).subsec_nanos() as u64;
             let h = seed
                 .wrapping_mul(2654435761)
                 .wrapping_add((drop.x * 1000.0) as u64);
@@ -588,7 +588,7 @@ pub(super) fn emit_aurora_overlay(ctx: &EffectCtx) -> (Vec<RectVertex>, bool) {
     if !ctx.effects.aurora.enabled {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let elapsed = now.duration_since(ctx.aurora_start).as_secs_f64() * ctx.effects.aurora.speed as f64;
     let fw = ctx.logical_w;
     let ah = ctx.effects.aurora.height;
@@ -735,7 +735,7 @@ pub(super) fn emit_focus_ring(
     if !ctx.effects.focus_ring.enabled {
         return (Vec::new(), false);
     }
-    let elapsed = focus_ring_start.elapsed().as_secs_f32();
+    let elapsed = ctx.now.saturating_duration_since(focus_ring_start).as_secs_f32();
     let offset = (elapsed * ctx.effects.focus_ring.speed)
         % (ctx.effects.focus_ring.dash_length * 2.0);
     let dash = ctx.effects.focus_ring.dash_length;
@@ -880,7 +880,7 @@ pub(super) fn emit_border_transition(
     if !ctx.effects.border_transition.enabled || ctx.frame_glyphs.window_infos.len() <= 1 {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let (ar, ag, ab) = ctx.effects.border_transition.active_color;
     let duration = border_transition_duration;
 
@@ -1236,7 +1236,7 @@ pub(super) fn emit_inactive_window_dimming(
     if !ctx.effects.inactive_dim.enabled || ctx.frame_glyphs.window_infos.len() <= 1 {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let dt = now.duration_since(*last_dim_tick).as_secs_f32().min(0.1);
     *last_dim_tick = now;
     let fade_speed = 8.0;
@@ -1396,7 +1396,7 @@ pub(super) fn emit_search_highlight(
         return (Vec::new(), false);
     }
 
-    let elapsed = search_pulse_start.elapsed().as_secs_f32();
+    let elapsed = ctx.now.saturating_duration_since(search_pulse_start).as_secs_f32();
     let phase = elapsed * 3.0 * std::f32::consts::PI;
     let pulse = (phase.sin() + 1.0) / 2.0;
 
@@ -1433,7 +1433,18 @@ pub(super) fn emit_selection_glow(
     let glow_radius = ctx.effects.region_glow.radius.max(1.0);
     let glow_opacity = ctx.effects.region_glow.opacity.clamp(0.0, 1.0);
 
-    // Collect per-row bounding boxes for region glyphs
+    // Rows of a region the layout engine highlighted itself
+    let runs: Vec<&SelectionRun> = ctx.frame_glyphs.selection_runs.iter()
+        .filter(|r| r.kind != SelectionKind::Secondary)
+        .collect();
+    if let Some(first) = runs.first() {
+        let row_bounds: Vec<(f32, f32, f32, f32)> =
+            runs.iter().map(|r| (r.x, r.y, r.width, r.height)).collect();
+        let c = first.bg;
+        return glow_rows(&row_bounds, (c.r, c.g, c.b), glow_radius, glow_opacity);
+    }
+
+    // Otherwise per-row bounding boxes of glyphs in the region face
     let mut row_bounds: Vec<(f32, f32, f32, f32)> = Vec::new();
     let mut current_row_y: f32 = -9999.0;
     let mut row_min_x: f32 = f32::MAX;
@@ -1479,11 +1490,20 @@ pub(super) fn emit_selection_glow(
         .get(&target_face)
         .map(|f| (f.background.r, f.background.g, f.background.b))
         .unwrap_or((0.4, 0.6, 1.0));
+    glow_rows(&row_bounds, region_color, glow_radius, glow_opacity)
+}
 
+/// Glow of REGION_COLOR around each of ROW_BOUNDS (x, y, width, height).
+fn glow_rows(
+    row_bounds: &[(f32, f32, f32, f32)],
+    region_color: (f32, f32, f32),
+    glow_radius: f32,
+    glow_opacity: f32,
+) -> Vec<RectVertex> {
     let mut verts: Vec<RectVertex> = Vec::new();
     let steps = (glow_radius as i32).max(2);
 
-    for (rx, ry, rw, rh) in &row_bounds {
+    for (rx, ry, rw, rh) in row_bounds {
         for i in 1..=steps {
             let t = i as f32 / steps as f32;
             let alpha = glow_opacity * (1.0 - t) * (1.0 - t);
@@ -1515,7 +1535,7 @@ pub(super) fn emit_typing_ripple(
     if !ctx.effects.typing_ripple.enabled || active_ripples.is_empty() {
         return (Vec::new(), false);
     }
-    let now = std::time::Instant::now();
+    let now = ctx.now;
     let duration = typing_ripple_duration;
     let max_r = ctx.effects.typing_ripple.max_radius;
 
@@ -2031,7 +2051,7 @@ pub(super) fn emit_scroll_velocity_fade(
     let mut verts: Vec<RectVertex> = Vec::new();
 
     for entry in scroll_velocity_fades.iter() {
-        let elapsed = entry.started.elapsed().as_millis() as f32;
+        let elapsed = ctx.now.saturating_duration_since(entry.started).as_millis() as f32;
         let duration = entry.duration.as_millis() as f32;
         if elapsed >= duration {
             continue;
@@ -2051,7 +2071,7 @@ pub(super) fn emit_scroll_velocity_fade(
     }
 
     // Cleanup expired entries
-    scroll_velocity_fades.retain(|e| e.started.elapsed() < e.duration);
+    scroll_velocity_fades.retain(|e| ctx.now.saturating_duration_since(e.started) < e.duration);
     let needs_redraw = !scroll_velocity_fades.is_empty();
     (verts, needs_redraw)
 }
@@ -2071,7 +2091,7 @@ pub(super) fn emit_click_halo(
     let ring_steps = 8;
 
     for entry in click_halos.iter() {
-        let elapsed = entry.started.elapsed().as_millis() as f32;
+        let elapsed = ctx.now.saturating_duration_since(entry.started).as_millis() as f32;
         let duration = entry.duration.as_millis() as f32;
         if elapsed >= duration {
             continue;
@@ -2101,7 +2121,7 @@ pub(super) fn emit_click_halo(
         }
     }
 
-    click_halos.retain(|e| e.started.elapsed() < e.duration);
+    click_halos.retain(|e| ctx.now.saturating_duration_since(e.started) < e.duration);
     let needs_redraw = !click_halos.is_empty();
     (verts, needs_redraw)
 }
@@ -2121,7 +2141,7 @@ pub(super) fn emit_edge_snap(
     let steps = 3;
 
     for entry in edge_snaps.iter() {
-        let elapsed = entry.started.elapsed().as_millis() as f32;
+        let elapsed = ctx.now.saturating_duration_since(entry.started).as_millis() as f32;
         let duration = entry.duration.as_millis() as f32;
         if elapsed >= duration {
             continue;
@@ -2168,7 +2188,7 @@ pub(super) fn emit_edge_snap(
         }
     }
 
-    edge_snaps.retain(|e| e.started.elapsed() < e.duration);
+    edge_snaps.retain(|e| ctx.now.saturating_duration_since(e.started) < e.duration);
     let needs_redraw = !edge_snaps.is_empty();
     (verts, needs_redraw)
 }
@@ -2230,7 +2250,7 @@ pub(super) fn emit_scroll_momentum(
     }
     let bar_w = ctx.effects.scroll_momentum.width.max(1.0);
     let mut verts: Vec<RectVertex> = Vec::new();
-    let now = std::time::Instant::now();
+    let now = ctx.now;
 
     for entry in active_scroll_momentums {
         let elapsed = now.duration_since(entry.started);
@@ -2329,7 +2349,7 @@ pub(super) fn emit_window_switch_fade(
         return (Vec::new(), false);
     }
     let mut verts: Vec<RectVertex> = Vec::new();
-    let now = std::time::Instant::now();
+    let now = ctx.now;
 
     for fade in active_window_fades.iter() {
         let elapsed = now.duration_since(fade.started);
@@ -2352,7 +2372,7 @@ pub(super) fn emit_window_switch_fade(
     }
 
     // Clean up completed fades
-    active_window_fades.retain(|f| f.started.elapsed().as_secs_f32() < f.duration.as_secs_f32());
+    active_window_fades.retain(|f| ctx.now.saturating_duration_since(f.started).as_secs_f32() < f.duration.as_secs_f32());
     let needs_redraw = !active_window_fades.is_empty();
     (verts, needs_redraw)
 }
@@ -2383,6 +2403,7 @@ mod tests {
             surface_width: 800,
             surface_height: 600,
             aurora_start: Instant::now(),
+            now: Instant::now(),
             scale_factor: 1.0,
             logical_w: 800.0,
             logical_h: 600.0,
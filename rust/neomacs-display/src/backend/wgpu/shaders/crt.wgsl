@@ -0,0 +1,102 @@
+// CRT/retro post-processing: barrel distortion, chromatic aberration,
+// phosphor glow and scanlines, applied inside a set of screen regions
+
+const MAX_REGIONS: u32 = 8u;
+
+struct CrtUniforms {
+    // Frame size in pixels
+    resolution: vec2<f32>,
+    // Display scale factor (physical / logical pixels)
+    scale: f32,
+    region_count: u32,
+    // scanlines, curvature, glow, aberration (0..1)
+    params: vec4<f32>,
+    // x, y, width, height in pixels
+    regions: array<vec4<f32>, 8>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> crt: CrtUniforms;
+@group(0) @binding(1)
+var t_frame: texture_2d<f32>;
+@group(0) @binding(2)
+var t_sampler: sampler;
+
+// Full-screen triangle
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn frame_rgb(px: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(t_frame, t_sampler, px / crt.resolution, 0.0).rgb;
+}
+
+fn crt_effect(px: vec2<f32>, region: vec4<f32>) -> vec4<f32> {
+    let scanlines = crt.params.x;
+    let curvature = crt.params.y;
+    let glow = crt.params.z;
+    let aberration = crt.params.w;
+
+    // Barrel distortion around the region's center (-1..1)
+    var p = (px - region.xy) / region.zw * 2.0 - 1.0;
+    p = p * (1.0 + curvature * 0.2 * dot(p, p));
+    if (abs(p.x) > 1.0 || abs(p.y) > 1.0) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    let src = region.xy + (p * 0.5 + 0.5) * region.zw;
+
+    // Chromatic aberration: red and blue drift apart toward the edges
+    let shift = vec2<f32>(aberration * 3.0 * crt.scale * p.x, 0.0);
+    var color = vec3<f32>(
+        frame_rgb(src + shift).r,
+        frame_rgb(src).g,
+        frame_rgb(src - shift).b,
+    );
+
+    // Phosphor glow: add a blurred copy of the neighborhood
+    if (glow > 0.0) {
+        let r = 2.0 * crt.scale;
+        var halo = vec3<f32>(0.0);
+        halo += frame_rgb(src + vec2<f32>(r, 0.0));
+        halo += frame_rgb(src - vec2<f32>(r, 0.0));
+        halo += frame_rgb(src + vec2<f32>(0.0, r));
+        halo += frame_rgb(src - vec2<f32>(0.0, r));
+        halo += frame_rgb(src + vec2<f32>(r, r));
+        halo += frame_rgb(src - vec2<f32>(r, r));
+        halo += frame_rgb(src + vec2<f32>(r, -r));
+        halo += frame_rgb(src - vec2<f32>(r, -r));
+        color += halo / 8.0 * glow * 0.6;
+    }
+
+    // Scanlines: darken every other (logical) pixel row
+    let phase = cos((src.y - region.y) / crt.scale * 3.14159265);
+    color *= 1.0 - scanlines * 0.35 * (1.0 - phase);
+
+    // Slight vignette toward the curved edges
+    color *= 1.0 - curvature * 0.25 * dot(p * p, p * p);
+
+    return vec4<f32>(color, 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let px = in.uv * crt.resolution;
+    for (var i = 0u; i < min(crt.region_count, MAX_REGIONS); i = i + 1u) {
+        let region = crt.regions[i];
+        if (px.x >= region.x && px.y >= region.y
+            && px.x < region.x + region.z && px.y < region.y + region.w) {
+            return crt_effect(px, region);
+        }
+    }
+    return textureSampleLevel(t_frame, t_sampler, in.uv, 0.0);
+}
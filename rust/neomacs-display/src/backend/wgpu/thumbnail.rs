@@ -0,0 +1,664 @@
+//! Thumbnail generation and caching (freedesktop.org thumbnail spec).
+//!
+//! Thumbnails are PNG files stored under `$XDG_CACHE_HOME/thumbnails/<size>/`
+//! and named after the MD5 of the source file's URI, so they are shared with
+//! file managers and other spec-compliant applications.
+//!
+//! Generation runs on a pool of worker threads:
+//! - `Thumbnailer::request` returns the cached thumbnail if it is still
+//!   valid (its `Thumb::MTime` matches the source), otherwise queues the file
+//! - Workers decode images with the `image` crate and videos with GStreamer
+//!   (`video` feature), downscale, and write the PNG atomically
+//! - Files that cannot be thumbnailed get a failure marker under
+//!   `fail/neomacs-display/` so they are not retried until they change
+//!
+//! Emacs displays the resulting files as ordinary inline images.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::UNIX_EPOCH;
+
+use thiserror::Error;
+
+/// Directory (under `fail/`) for failure markers, per the spec.
+const FAIL_DIR: &str = "neomacs-display";
+
+/// Number of worker threads
+const WORKER_COUNT: usize = 2;
+
+/// Standard thumbnail sizes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ThumbnailSize {
+    /// 128x128
+    Normal,
+    /// 256x256
+    Large,
+    /// 512x512
+    XLarge,
+    /// 1024x1024
+    XXLarge,
+}
+
+impl ThumbnailSize {
+    /// Maximum edge length in pixels
+    pub fn pixels(self) -> u32 {
+        match self {
+            ThumbnailSize::Normal => 128,
+            ThumbnailSize::Large => 256,
+            ThumbnailSize::XLarge => 512,
+            ThumbnailSize::XXLarge => 1024,
+        }
+    }
+
+    /// Cache subdirectory name
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            ThumbnailSize::Normal => "normal",
+            ThumbnailSize::Large => "large",
+            ThumbnailSize::XLarge => "x-large",
+            ThumbnailSize::XXLarge => "xx-large",
+        }
+    }
+
+    /// Smallest standard size that is at least `pixels` wide.
+    pub fn for_pixels(pixels: u32) -> Self {
+        match pixels {
+            0..=128 => ThumbnailSize::Normal,
+            129..=256 => ThumbnailSize::Large,
+            257..=512 => ThumbnailSize::XLarge,
+            _ => ThumbnailSize::XXLarge,
+        }
+    }
+}
+
+/// Thumbnail generation errors
+#[derive(Error, Debug)]
+pub enum ThumbnailError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Cannot decode {0}")]
+    Decode(String),
+
+    #[error("PNG encoding failed: {0}")]
+    Encode(String),
+
+    #[error("Unsupported file type")]
+    Unsupported,
+}
+
+/// State of a thumbnail request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThumbnailState {
+    /// Queued or being generated
+    Pending,
+    /// Thumbnail written to this path
+    Ready(PathBuf),
+    /// The file cannot be thumbnailed
+    Failed,
+}
+
+// ============================================================================
+// Cache layout
+// ============================================================================
+
+/// Root of the thumbnail cache: `$XDG_CACHE_HOME/thumbnails`.
+pub fn default_cache_root() -> PathBuf {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir);
+    cache.join("thumbnails")
+}
+
+/// `file://` URI for an absolute path, percent-encoded as the spec
+/// requires.  The encoding is of the path's bytes, so names that are not
+/// UTF-8 get the URI other clients compute for them.
+pub fn file_uri(path: &Path) -> String {
+    #[cfg(unix)]
+    let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let bytes = path.to_string_lossy().into_owned().into_bytes();
+    let mut uri = String::from("file://");
+    for byte in bytes {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Path of the thumbnail for `uri` at `size`.
+pub fn thumbnail_path(root: &Path, uri: &str, size: ThumbnailSize) -> PathBuf {
+    root.join(size.dir_name())
+        .join(format!("{}.png", md5_hex(uri.as_bytes())))
+}
+
+/// Path of the failure marker for `uri`.
+pub fn failure_path(root: &Path, uri: &str) -> PathBuf {
+    root.join("fail")
+        .join(FAIL_DIR)
+        .join(format!("{}.png", md5_hex(uri.as_bytes())))
+}
+
+fn source_mtime(source: &Path) -> Option<u64> {
+    fs::metadata(source)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+/// Read the tEXt chunks of a thumbnail PNG.
+fn read_text_chunks(thumb: &Path) -> Option<HashMap<String, String>> {
+    let file = fs::File::open(thumb).ok()?;
+    let reader = png::Decoder::new(std::io::BufReader::new(file))
+        .read_info()
+        .ok()?;
+    Some(
+        reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+            .collect(),
+    )
+}
+
+/// Whether `thumb` exists and was made from the current version of `uri`.
+pub fn is_valid(thumb: &Path, uri: &str, mtime: u64) -> bool {
+    match read_text_chunks(thumb) {
+        Some(chunks) => {
+            chunks.get("Thumb::URI").map(String::as_str) == Some(uri)
+                && chunks.get("Thumb::MTime") == Some(&mtime.to_string())
+        }
+        None => false,
+    }
+}
+
+// ============================================================================
+// Generation
+// ============================================================================
+
+fn is_video(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    matches!(
+        ext.as_str(),
+        "mp4" | "mkv" | "webm" | "mov" | "avi" | "m4v" | "ogv" | "mpg" | "mpeg"
+    )
+}
+
+fn decode_source(source: &Path, max_edge: u32) -> Result<image::RgbaImage, ThumbnailError> {
+    if is_video(source) {
+        return grab_video_frame(source, max_edge);
+    }
+    let img = image::open(source).map_err(|e| ThumbnailError::Decode(e.to_string()))?;
+    Ok(img.to_rgba8())
+}
+
+/// Grab a frame about 10% into the video.
+#[cfg(feature = "video")]
+fn grab_video_frame(source: &Path, _max_edge: u32) -> Result<image::RgbaImage, ThumbnailError> {
+    use gstreamer as gst;
+    use gstreamer::prelude::*;
+    use gstreamer_app as gst_app;
+    use gstreamer_video as gst_video;
+
+    let fail = |msg: &str| ThumbnailError::Decode(format!("{}: {}", source.display(), msg));
+
+    gst::init().map_err(|e| fail(&e.to_string()))?;
+    let make = |factory: &str| {
+        gst::ElementFactory::make(factory)
+            .build()
+            .map_err(|_| fail(&format!("no {} element", factory)))
+    };
+    // The path is a property, not pipeline text: any bytes are fine
+    let filesrc = make("filesrc")?;
+    filesrc.set_property("location", source.to_value());
+    let decodebin = make("decodebin")?;
+    let convert = make("videoconvert")?;
+    let scale = make("videoscale")?;
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("format", "RGBA")
+        .field("pixel-aspect-ratio", gst::Fraction::new(1, 1))
+        .build();
+    let sink = gst_app::AppSink::builder().caps(&caps).build();
+
+    let pipeline = gst::Pipeline::new();
+    pipeline
+        .add_many([&filesrc, &decodebin, &convert, &scale, sink.upcast_ref()])
+        .and_then(|_| filesrc.link(&decodebin))
+        .and_then(|_| gst::Element::link_many([&convert, &scale, sink.upcast_ref()]))
+        .map_err(|_| fail("cannot create pipeline"))?;
+    // decodebin adds its pads once it has found the streams
+    let convert_weak = convert.downgrade();
+    decodebin.connect_pad_added(move |_, pad| {
+        let Some(convert) = convert_weak.upgrade() else {
+            return;
+        };
+        let Some(sink_pad) = convert.static_pad("sink") else {
+            return;
+        };
+        let is_video = pad
+            .current_caps()
+            .and_then(|caps| caps.structure(0).map(|s| s.name().as_str().starts_with("video/")))
+            .unwrap_or(false);
+        if is_video && !sink_pad.is_linked() {
+            let _ = pad.link(&sink_pad);
+        }
+    });
+
+    let result = (|| {
+        pipeline
+            .set_state(gst::State::Paused)
+            .map_err(|_| fail("cannot preroll"))?;
+        let (res, _, _) = pipeline.state(gst::ClockTime::from_seconds(5));
+        res.map_err(|_| fail("cannot preroll"))?;
+
+        // Skip past fade-ins and black title frames.
+        if let Some(duration) = pipeline.query_duration::<gst::ClockTime>() {
+            let target = gst::ClockTime::from_nseconds(duration.nseconds() / 10);
+            if pipeline
+                .seek_simple(gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT, target)
+                .is_ok()
+            {
+                let _ = pipeline.state(gst::ClockTime::from_seconds(5));
+            }
+        }
+
+        let sample = sink.pull_preroll().map_err(|_| fail("no frame"))?;
+        let caps = sample.caps().ok_or_else(|| fail("no caps"))?;
+        let info = gst_video::VideoInfo::from_caps(caps).map_err(|_| fail("bad caps"))?;
+        let buffer = sample.buffer().ok_or_else(|| fail("no buffer"))?;
+        let map = buffer.map_readable().map_err(|_| fail("unmappable buffer"))?;
+
+        let (width, height) = (info.width(), info.height());
+        let stride = info.stride()[0] as usize;
+        let row_bytes = width as usize * 4;
+        let mut rgba = Vec::with_capacity(row_bytes * height as usize);
+        for row in 0..height as usize {
+            let start = row * stride;
+            rgba.extend_from_slice(
+                map.as_slice()
+                    .get(start..start + row_bytes)
+                    .ok_or_else(|| fail("short buffer"))?,
+            );
+        }
+        image::RgbaImage::from_raw(width, height, rgba).ok_or_else(|| fail("bad frame"))
+    })();
+
+    let _ = pipeline.set_state(gst::State::Null);
+    result
+}
+
+#[cfg(not(feature = "video"))]
+fn grab_video_frame(_source: &Path, _max_edge: u32) -> Result<image::RgbaImage, ThumbnailError> {
+    Err(ThumbnailError::Unsupported)
+}
+
+/// Write `img` as a thumbnail PNG with the spec's metadata, atomically.
+fn write_png(
+    dest: &Path,
+    img: &image::RgbaImage,
+    chunks: &[(&str, String)],
+) -> Result<(), ThumbnailError> {
+    let dir = dest.parent().expect("thumbnail path has a parent");
+    fs::create_dir_all(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(dir, fs::Permissions::from_mode(0o700));
+    }
+
+    let tmp = dir.join(format!(
+        ".{}.{}.tmp",
+        dest.file_name().unwrap_or_default().to_string_lossy(),
+        std::process::id()
+    ));
+    let encode = || -> Result<(), ThumbnailError> {
+        let file = fs::File::create(&tmp)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+        let mut out = BufWriter::new(file);
+        let mut encoder = png::Encoder::new(&mut out, img.width(), img.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        for (key, value) in chunks {
+            encoder
+                .add_text_chunk(key.to_string(), value.clone())
+                .map_err(|e| ThumbnailError::Encode(e.to_string()))?;
+        }
+        let mut writer = encoder
+            .write_header()
+            .map_err(|e| ThumbnailError::Encode(e.to_string()))?;
+        writer
+            .write_image_data(img.as_raw())
+            .map_err(|e| ThumbnailError::Encode(e.to_string()))?;
+        writer
+            .finish()
+            .map_err(|e| ThumbnailError::Encode(e.to_string()))?;
+        out.flush()?;
+        Ok(())
+    };
+    if let Err(e) = encode() {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+    fs::rename(&tmp, dest)?;
+    Ok(())
+}
+
+/// Generate the thumbnail for `source` at `size` under `root`.
+///
+/// On failure a marker is written to the `fail/` directory so the file is
+/// skipped until it changes.
+pub fn generate(root: &Path, source: &Path, size: ThumbnailSize) -> Result<PathBuf, ThumbnailError> {
+    let uri = file_uri(source);
+    let mtime = source_mtime(source).ok_or_else(|| {
+        ThumbnailError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            source.display().to_string(),
+        ))
+    })?;
+    let mut chunks = vec![
+        ("Thumb::URI", uri.clone()),
+        ("Thumb::MTime", mtime.to_string()),
+        ("Software", format!("neomacs-display {}", crate::VERSION)),
+    ];
+
+    let max_edge = size.pixels();
+    let full = match decode_source(source, max_edge) {
+        Ok(img) => img,
+        Err(e) => {
+            let marker = image::RgbaImage::new(1, 1);
+            let _ = write_png(&failure_path(root, &uri), &marker, &chunks);
+            return Err(e);
+        }
+    };
+    let (width, height) = full.dimensions();
+    let thumb = if width > max_edge || height > max_edge {
+        // Fit the longer edge, keeping the aspect ratio.
+        let scale = max_edge as f64 / width.max(height) as f64;
+        let w = ((width as f64 * scale).round() as u32).max(1);
+        let h = ((height as f64 * scale).round() as u32).max(1);
+        image::imageops::thumbnail(&full, w, h)
+    } else {
+        full
+    };
+
+    if let Ok(meta) = fs::metadata(source) {
+        chunks.push(("Thumb::Size", meta.len().to_string()));
+    }
+    chunks.push(("Thumb::Image::Width", width.to_string()));
+    chunks.push(("Thumb::Image::Height", height.to_string()));
+
+    let dest = thumbnail_path(root, &uri, size);
+    write_png(&dest, &thumb, &chunks)?;
+    Ok(dest)
+}
+
+// ============================================================================
+// Thumbnailer service
+// ============================================================================
+
+type JobKey = (PathBuf, ThumbnailSize);
+
+struct Shared {
+    root: PathBuf,
+    states: Mutex<HashMap<JobKey, ThumbnailState>>,
+}
+
+/// Thread-pooled thumbnail generator with an in-memory state table.
+pub struct Thumbnailer {
+    shared: Arc<Shared>,
+    sender: crossbeam_channel::Sender<JobKey>,
+}
+
+impl Thumbnailer {
+    /// Create a thumbnailer writing to the standard cache root.
+    pub fn new() -> Self {
+        Self::with_root(default_cache_root())
+    }
+
+    /// Create a thumbnailer writing to `root`.
+    pub fn with_root(root: PathBuf) -> Self {
+        let shared = Arc::new(Shared {
+            root,
+            states: Mutex::new(HashMap::new()),
+        });
+        let (sender, receiver) = crossbeam_channel::unbounded::<JobKey>();
+        for i in 0..WORKER_COUNT {
+            let shared = Arc::clone(&shared);
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("thumbnailer-{}", i))
+                .spawn(move || {
+                    let _span = crate::logging::span("thumbnail");
+                    while let Ok((source, size)) = receiver.recv() {
+                        let state = match generate(&shared.root, &source, size) {
+                            Ok(path) => ThumbnailState::Ready(path),
+                            Err(e) => {
+                                log::debug!("Thumbnail for {} failed: {}", source.display(), e);
+                                ThumbnailState::Failed
+                            }
+                        };
+                        shared.states.lock().unwrap().insert((source, size), state);
+                    }
+                })
+                .expect("spawn thumbnailer thread");
+        }
+        Self { shared, sender }
+    }
+
+    /// Cache root in use
+    pub fn root(&self) -> &Path {
+        &self.shared.root
+    }
+
+    /// Look up the thumbnail for `source`, queueing generation if there is
+    /// no valid cached one.
+    pub fn request(&self, source: &Path, size: ThumbnailSize) -> ThumbnailState {
+        let Some(mtime) = source_mtime(source) else {
+            return ThumbnailState::Failed;
+        };
+        let uri = file_uri(source);
+        let key = (source.to_path_buf(), size);
+        let mut states = self.shared.states.lock().unwrap();
+        if states.get(&key) == Some(&ThumbnailState::Pending) {
+            return ThumbnailState::Pending;
+        }
+
+        let cached = thumbnail_path(&self.shared.root, &uri, size);
+        if is_valid(&cached, &uri, mtime) {
+            let state = ThumbnailState::Ready(cached);
+            states.insert(key, state.clone());
+            return state;
+        }
+        if is_valid(&failure_path(&self.shared.root, &uri), &uri, mtime) {
+            states.insert(key, ThumbnailState::Failed);
+            return ThumbnailState::Failed;
+        }
+
+        states.insert(key.clone(), ThumbnailState::Pending);
+        drop(states);
+        if self.sender.send(key.clone()).is_err() {
+            self.shared.states.lock().unwrap().insert(key, ThumbnailState::Failed);
+            return ThumbnailState::Failed;
+        }
+        ThumbnailState::Pending
+    }
+
+    /// Current state of a previous request, without queueing anything.
+    pub fn state(&self, source: &Path, size: ThumbnailSize) -> Option<ThumbnailState> {
+        self.shared
+            .states
+            .lock()
+            .unwrap()
+            .get(&(source.to_path_buf(), size))
+            .cloned()
+    }
+
+    /// Number of requests still being generated
+    pub fn pending_count(&self) -> usize {
+        self.shared
+            .states
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|s| **s == ThumbnailState::Pending)
+            .count()
+    }
+}
+
+impl Default for Thumbnailer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lowercase hex MD5 digest of `data`, as thumbnail file names use.
+pub fn md5_hex(data: &[u8]) -> String {
+    format!("{:x}", md5::compute(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("neomacs-thumb-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_test_image(path: &Path, width: u32, height: u32) {
+        let img = image::RgbaImage::from_pixel(width, height, image::Rgba([200, 10, 10, 255]));
+        img.save(path).unwrap();
+    }
+
+    #[test]
+    fn test_md5_vectors() {
+        assert_eq!(md5_hex(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(md5_hex(b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            md5_hex(b"The quick brown fox jumps over the lazy dog"),
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+    }
+
+    #[test]
+    fn test_file_uri_escaping() {
+        assert_eq!(
+            file_uri(Path::new("/home/user/photos/me.png")),
+            "file:///home/user/photos/me.png"
+        );
+        assert_eq!(file_uri(Path::new("/tmp/a b#c.png")), "file:///tmp/a%20b%23c.png");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_uri_keeps_non_utf8_bytes() {
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"/tmp/caf\xe9.png"));
+        assert_eq!(file_uri(path), "file:///tmp/caf%E9.png");
+    }
+
+    #[test]
+    fn test_size_buckets() {
+        assert_eq!(ThumbnailSize::for_pixels(64), ThumbnailSize::Normal);
+        assert_eq!(ThumbnailSize::for_pixels(200), ThumbnailSize::Large);
+        assert_eq!(ThumbnailSize::for_pixels(512), ThumbnailSize::XLarge);
+        assert_eq!(ThumbnailSize::for_pixels(4000), ThumbnailSize::XXLarge);
+        assert_eq!(ThumbnailSize::Large.dir_name(), "large");
+    }
+
+    #[test]
+    fn test_generate_writes_valid_thumbnail() {
+        let root = temp_root("generate");
+        let source = root.join("wide.png");
+        write_test_image(&source, 400, 200);
+
+        let thumb = generate(&root, &source, ThumbnailSize::Normal).unwrap();
+        assert!(thumb.starts_with(root.join("normal")));
+        let img = image::open(&thumb).unwrap();
+        assert_eq!((img.width(), img.height()), (128, 64));
+
+        let uri = file_uri(&source);
+        let mtime = source_mtime(&source).unwrap();
+        assert!(is_valid(&thumb, &uri, mtime));
+        assert!(!is_valid(&thumb, &uri, mtime + 1));
+        let chunks = read_text_chunks(&thumb).unwrap();
+        assert_eq!(chunks.get("Thumb::Image::Width").unwrap(), "400");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_small_images_are_not_upscaled() {
+        let root = temp_root("small");
+        let source = root.join("icon.png");
+        write_test_image(&source, 16, 16);
+        let thumb = generate(&root, &source, ThumbnailSize::Large).unwrap();
+        let img = image::open(&thumb).unwrap();
+        assert_eq!((img.width(), img.height()), (16, 16));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_undecodable_file_gets_failure_marker() {
+        let root = temp_root("fail");
+        let source = root.join("broken.png");
+        fs::write(&source, b"not a png").unwrap();
+        assert!(generate(&root, &source, ThumbnailSize::Normal).is_err());
+        let uri = file_uri(&source);
+        assert!(is_valid(&failure_path(&root, &uri), &uri, source_mtime(&source).unwrap()));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_thumbnailer_request_flow() {
+        let root = temp_root("service");
+        let source = root.join("photo.png");
+        write_test_image(&source, 300, 300);
+        let thumbnailer = Thumbnailer::with_root(root.join("cache"));
+
+        let mut state = thumbnailer.request(&source, ThumbnailSize::Normal);
+        for _ in 0..500 {
+            if state != ThumbnailState::Pending {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+            state = thumbnailer.state(&source, ThumbnailSize::Normal).unwrap();
+        }
+        let ThumbnailState::Ready(path) = state else {
+            panic!("thumbnail not generated: {:?}", state);
+        };
+        assert!(path.exists());
+        assert_eq!(thumbnailer.pending_count(), 0);
+        // A second request is served from the cache.
+        assert_eq!(
+            thumbnailer.request(&source, ThumbnailSize::Normal),
+            ThumbnailState::Ready(path)
+        );
+        assert_eq!(
+            thumbnailer.request(&root.join("missing.png"), ThumbnailSize::Normal),
+            ThumbnailState::Failed
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -34,25 +34,26 @@ pub struct BufferTransition {
 }
 
 impl BufferTransition {
-    /// Create a new buffer transition.
+    /// Create a new buffer transition starting at the frame time `now`.
     pub fn new(
         from_texture: Arc<wgpu::Texture>,
         to_texture: Arc<wgpu::Texture>,
         transition_type: TransitionType,
         duration: Duration,
+        now: Instant,
     ) -> Self {
         Self {
             from_texture,
             to_texture,
             transition_type,
             duration,
-            started: Instant::now(),
+            started: now,
         }
     }
 
-    /// Get the current progress of the transition (0.0 to 1.0).
-    pub fn progress(&self) -> f32 {
-        let elapsed = self.started.elapsed();
+    /// Get the progress of the transition (0.0 to 1.0) at the frame time `now`.
+    pub fn progress(&self, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.started);
         if self.duration.as_secs_f32() > 0.0 {
             (elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
         } else {
@@ -60,9 +61,9 @@ impl BufferTransition {
         }
     }
 
-    /// Check if the transition has completed.
-    pub fn is_complete(&self) -> bool {
-        self.started.elapsed() >= self.duration
+    /// Check if the transition has completed at the frame time `now`.
+    pub fn is_complete(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.started) >= self.duration
     }
 
     /// Get the rotation angles for page flip transitions in degrees.
@@ -76,8 +77,8 @@ impl BufferTransition {
     ///
     /// For `PageFlipRight`, old page rotates to -90 (away from viewer on right)
     /// and new page rotates from +90 to 0 (revealing from left).
-    pub fn page_flip_angles(&self) -> (f32, f32) {
-        let progress = self.progress();
+    pub fn page_flip_angles(&self, now: Instant) -> (f32, f32) {
+        let progress = self.progress(now);
 
         match self.transition_type {
             TransitionType::PageFlipLeft => {
@@ -101,8 +102,8 @@ impl BufferTransition {
     /// Get the opacity values for fade transitions.
     ///
     /// Returns `(old_opacity, new_opacity)` where both range from 0.0 to 1.0.
-    pub fn fade_opacity(&self) -> (f32, f32) {
-        let progress = self.progress();
+    pub fn fade_opacity(&self, now: Instant) -> (f32, f32) {
+        let progress = self.progress(now);
 
         match self.transition_type {
             TransitionType::Fade => {
@@ -120,8 +121,8 @@ impl BufferTransition {
     /// Returns `(old_offset, new_offset)` as fractions of the screen width (-1.0 to 1.0).
     /// - Negative offset means content is to the left of its normal position
     /// - Positive offset means content is to the right of its normal position
-    pub fn slide_offset(&self) -> (f32, f32) {
-        let progress = self.progress();
+    pub fn slide_offset(&self, now: Instant) -> (f32, f32) {
+        let progress = self.progress(now);
 
         match self.transition_type {
             TransitionType::SlideLeft => {
@@ -158,7 +159,7 @@ impl TransitionManager {
         Self { active: None }
     }
 
-    /// Start a new transition.
+    /// Start a new transition at the frame time `now`.
     ///
     /// This will replace any currently active transition.
     pub fn start(
@@ -167,12 +168,14 @@ impl TransitionManager {
         to_texture: Arc<wgpu::Texture>,
         transition_type: TransitionType,
         duration: Duration,
+        now: Instant,
     ) {
         self.active = Some(BufferTransition::new(
             from_texture,
             to_texture,
             transition_type,
             duration,
+            now,
         ));
     }
 
@@ -181,12 +184,13 @@ impl TransitionManager {
         self.active.as_ref()
     }
 
-    /// Tick the transition manager, cleaning up completed transitions.
+    /// Tick the transition manager to the frame time `now`, cleaning up
+    /// completed transitions.
     ///
     /// Returns `true` if there was an active transition that completed.
-    pub fn tick(&mut self) -> bool {
+    pub fn tick(&mut self, now: Instant) -> bool {
         if let Some(ref transition) = self.active {
-            if transition.is_complete() {
+            if transition.is_complete(now) {
                 self.active = None;
                 return true;
             }
@@ -246,11 +250,17 @@ mod tests {
         Some((t1, t2))
     }
 
+    /// Frame time the tests evaluate transitions at.
+    fn now() -> Instant {
+        static NOW: std::sync::OnceLock<Instant> = std::sync::OnceLock::new();
+        *NOW.get_or_init(Instant::now)
+    }
+
     /// Build a `BufferTransition` with a controlled `started` time so that
-    /// `progress()` returns a deterministic value.
+    /// `progress(now())` returns a deterministic value.
     ///
     /// `elapsed` is how much time has "already passed" since the transition
-    /// started (i.e., `started = Instant::now() - elapsed`).
+    /// started (i.e., `started = now() - elapsed`).
     fn make_transition(
         from: Arc<wgpu::Texture>,
         to: Arc<wgpu::Texture>,
@@ -263,7 +273,7 @@ mod tests {
             to_texture: to,
             transition_type: tt,
             duration,
-            started: Instant::now() - elapsed,
+            started: now() - elapsed,
         }
     }
 
@@ -346,7 +356,7 @@ mod tests {
     fn test_transition_manager_tick_empty() {
         let mut manager = TransitionManager::new();
         // tick on empty manager should return false
-        assert!(!manager.tick());
+        assert!(!manager.tick(now()));
         assert!(!manager.has_transition());
     }
 
@@ -355,7 +365,7 @@ mod tests {
         let mut manager = TransitionManager::new();
         // Multiple ticks on empty manager always return false
         for _ in 0..10 {
-            assert!(!manager.tick());
+            assert!(!manager.tick(now()));
         }
     }
 
@@ -377,7 +387,7 @@ mod tests {
             return;
         };
         let mut manager = TransitionManager::new();
-        manager.start(t1, t2, TransitionType::Fade, Duration::from_secs(10));
+        manager.start(t1, t2, TransitionType::Fade, Duration::from_secs(10), now());
         assert!(manager.has_transition());
         assert!(manager.active().is_some());
     }
@@ -394,11 +404,12 @@ mod tests {
             t2.clone(),
             TransitionType::Fade,
             Duration::from_secs(10),
+            now(),
         );
         assert!(manager.has_transition());
 
         // Start a new transition -- it should replace the old one
-        manager.start(t1, t2, TransitionType::SlideLeft, Duration::from_secs(5));
+        manager.start(t1, t2, TransitionType::SlideLeft, Duration::from_secs(5), now());
         assert!(manager.has_transition());
         let active = manager.active().unwrap();
         assert_eq!(active.transition_type, TransitionType::SlideLeft);
@@ -411,9 +422,9 @@ mod tests {
             return;
         };
         let mut manager = TransitionManager::new();
-        manager.start(t1, t2, TransitionType::Fade, Duration::from_secs(60));
+        manager.start(t1, t2, TransitionType::Fade, Duration::from_secs(60), now());
         // Duration is 60s -- transition cannot be complete yet
-        assert!(!manager.tick());
+        assert!(!manager.tick(now()));
         assert!(manager.has_transition());
     }
 
@@ -425,9 +436,9 @@ mod tests {
         };
         let mut manager = TransitionManager::new();
         // Use a zero-duration transition so it completes immediately
-        manager.start(t1, t2, TransitionType::Fade, Duration::ZERO);
+        manager.start(t1, t2, TransitionType::Fade, Duration::ZERO, now());
         // Should be complete now
-        assert!(manager.tick());
+        assert!(manager.tick(now()));
         // After tick cleans it up, no more active transition
         assert!(!manager.has_transition());
         assert!(manager.active().is_none());
@@ -440,10 +451,10 @@ mod tests {
             return;
         };
         let mut manager = TransitionManager::new();
-        manager.start(t1, t2, TransitionType::Fade, Duration::ZERO);
-        assert!(manager.tick()); // completes and cleans up
+        manager.start(t1, t2, TransitionType::Fade, Duration::ZERO, now());
+        assert!(manager.tick(now())); // completes and cleans up
         // Second tick: no active transition, returns false
-        assert!(!manager.tick());
+        assert!(!manager.tick(now()));
     }
 
     #[test]
@@ -453,7 +464,7 @@ mod tests {
             return;
         };
         let mut manager = TransitionManager::new();
-        manager.start(t1, t2, TransitionType::PageFlipRight, Duration::from_secs(1));
+        manager.start(t1, t2, TransitionType::PageFlipRight, Duration::from_secs(1), now());
         let active = manager.active().unwrap();
         assert_eq!(active.transition_type, TransitionType::PageFlipRight);
         assert_eq!(active.duration, Duration::from_secs(1));
@@ -469,20 +480,18 @@ mod tests {
             eprintln!("Skipping: no GPU adapter available");
             return;
         };
-        let before = Instant::now();
         let bt = BufferTransition::new(
             t1,
             t2,
             TransitionType::SlideRight,
             Duration::from_millis(300),
+            now(),
         );
-        let after = Instant::now();
 
         assert_eq!(bt.transition_type, TransitionType::SlideRight);
         assert_eq!(bt.duration, Duration::from_millis(300));
-        // started should be between before and after
-        assert!(bt.started >= before);
-        assert!(bt.started <= after);
+        // started is the frame time it was created at
+        assert_eq!(bt.started, now());
     }
 
     // ---------------------------------------------------------------
@@ -502,7 +511,7 @@ mod tests {
             Duration::from_secs(10),
             Duration::ZERO,
         );
-        let p = bt.progress();
+        let p = bt.progress(now());
         // Just started, progress should be very close to 0.0
         assert!(p >= 0.0 && p < 0.01, "progress was {}", p);
     }
@@ -520,7 +529,7 @@ mod tests {
             Duration::from_secs(10),
             Duration::from_secs(5),
         );
-        let p = bt.progress();
+        let p = bt.progress(now());
         // Should be approximately 0.5
         assert!(
             (p - 0.5).abs() < 0.05,
@@ -542,7 +551,7 @@ mod tests {
             Duration::from_secs(1),
             Duration::from_secs(2), // elapsed > duration
         );
-        let p = bt.progress();
+        let p = bt.progress(now());
         // Should be clamped to 1.0
         assert_eq!(p, 1.0);
     }
@@ -560,7 +569,7 @@ mod tests {
             Duration::from_millis(100),
             Duration::from_secs(100), // way past duration
         );
-        assert_eq!(bt.progress(), 1.0);
+        assert_eq!(bt.progress(now()), 1.0);
     }
 
     #[test]
@@ -577,7 +586,7 @@ mod tests {
             Duration::ZERO,
         );
         // Zero duration should immediately return 1.0
-        assert_eq!(bt.progress(), 1.0);
+        assert_eq!(bt.progress(now()), 1.0);
     }
 
     #[test]
@@ -596,7 +605,7 @@ mod tests {
                 duration,
                 Duration::from_millis(elapsed_ms),
             );
-            let p = bt.progress();
+            let p = bt.progress(now());
             assert!(
                 p >= last_progress,
                 "progress went backwards: {} -> {} at elapsed={}ms",
@@ -625,7 +634,7 @@ mod tests {
             Duration::from_secs(60),
             Duration::ZERO,
         );
-        assert!(!bt.is_complete());
+        assert!(!bt.is_complete(now()));
     }
 
     #[test]
@@ -641,7 +650,7 @@ mod tests {
             Duration::from_millis(100),
             Duration::from_secs(1), // well past 100ms
         );
-        assert!(bt.is_complete());
+        assert!(bt.is_complete(now()));
     }
 
     #[test]
@@ -657,7 +666,7 @@ mod tests {
             Duration::ZERO,
             Duration::ZERO,
         );
-        assert!(bt.is_complete());
+        assert!(bt.is_complete(now()));
     }
 
     // ---------------------------------------------------------------
@@ -678,7 +687,7 @@ mod tests {
             Duration::from_secs(10),
             Duration::ZERO,
         );
-        let (old_angle, new_angle) = bt.page_flip_angles();
+        let (old_angle, new_angle) = bt.page_flip_angles(now());
         // At start: old ~0, new ~-90
         assert!(old_angle.abs() < 1.0, "old_angle at start: {}", old_angle);
         assert!(
@@ -702,7 +711,7 @@ mod tests {
             Duration::from_secs(1),
             Duration::from_secs(2),
         );
-        let (old_angle, new_angle) = bt.page_flip_angles();
+        let (old_angle, new_angle) = bt.page_flip_angles(now());
         // At end: old = 90, new = 0
         assert!(
             (old_angle - 90.0).abs() < 0.01,
@@ -730,7 +739,7 @@ mod tests {
             Duration::from_secs(10),
             Duration::from_secs(5),
         );
-        let (old_angle, new_angle) = bt.page_flip_angles();
+        let (old_angle, new_angle) = bt.page_flip_angles(now());
         // At midpoint: old ~45, new ~-45
         assert!(
             (old_angle - 45.0).abs() < 2.0,
@@ -757,7 +766,7 @@ mod tests {
             Duration::from_secs(10),
             Duration::ZERO,
         );
-        let (old_angle, new_angle) = bt.page_flip_angles();
+        let (old_angle, new_angle) = bt.page_flip_angles(now());
         // At start: old ~0, new ~90
         assert!(old_angle.abs() < 1.0, "old_angle at start: {}", old_angle);
         assert!(
@@ -780,7 +789,7 @@ mod tests {
             Duration::from_secs(1),
             Duration::from_secs(2),
         );
-        let (old_angle, new_angle) = bt.page_flip_angles();
+        let (old_angle, new_angle) = bt.page_flip_angles(now());
         // At end: old = -90, new = 0
         assert!(
             (old_angle + 90.0).abs() < 0.01,
@@ -807,7 +816,7 @@ mod tests {
             Duration::from_secs(10),
             Duration::from_secs(5),
         );
-        let (old_angle, new_angle) = bt.page_flip_angles();
+        let (old_angle, new_angle) = bt.page_flip_angles(now());
         // At midpoint: old ~-45, new ~45
         assert!(
             (old_angle + 45.0).abs() < 2.0,
@@ -839,7 +848,7 @@ mod tests {
                 Duration::from_secs(1),
                 Duration::from_millis(500),
             );
-            let (old_angle, new_angle) = bt.page_flip_angles();
+            let (old_angle, new_angle) = bt.page_flip_angles(now());
             assert_eq!(old_angle, 0.0, "non-flip type {:?} old_angle", tt);
             assert_eq!(new_angle, 0.0, "non-flip type {:?} new_angle", tt);
         }
@@ -862,7 +871,7 @@ mod tests {
             Duration::from_secs(10),
             Duration::ZERO,
         );
-        let (old_op, new_op) = bt.fade_opacity();
+        let (old_op, new_op) = bt.fade_opacity(now());
         // At start: old ~1.0, new ~0.0
         assert!(
             (old_op - 1.0).abs() < 0.01,
@@ -889,7 +898,7 @@ mod tests {
             Duration::from_secs(1),
             Duration::from_secs(2),
         );
-        let (old_op, new_op) = bt.fade_opacity();
+        let (old_op, new_op) = bt.fade_opacity(now());
         // At end: old = 0.0, new = 1.0
         assert!(
             old_op.abs() < 0.01,
@@ -916,7 +925,7 @@ mod tests {
             Duration::from_secs(10),
             Duration::from_secs(5),
         );
-        let (old_op, new_op) = bt.fade_opacity();
+        let (old_op, new_op) = bt.fade_opacity(now());
         // At midpoint: both ~0.5
         assert!(
             (old_op - 0.5).abs() < 0.05,
@@ -945,7 +954,7 @@ mod tests {
                 Duration::from_secs(10),
                 Duration::from_millis(elapsed_ms),
             );
-            let (old_op, new_op) = bt.fade_opacity();
+            let (old_op, new_op) = bt.fade_opacity(now());
             let sum = old_op + new_op;
             assert!(
                 (sum - 1.0).abs() < 0.01,
@@ -975,7 +984,7 @@ mod tests {
                 Duration::from_secs(1),
                 Duration::from_millis(500),
             );
-            let (old_op, new_op) = bt.fade_opacity();
+            let (old_op, new_op) = bt.fade_opacity(now());
             assert_eq!(old_op, 1.0, "non-fade type {:?} old_opacity", tt);
             assert_eq!(new_op, 1.0, "non-fade type {:?} new_opacity", tt);
         }
@@ -998,7 +1007,7 @@ mod tests {
             Duration::from_secs(10),
             Duration::ZERO,
         );
-        let (old_off, new_off) = bt.slide_offset();
+        let (old_off, new_off) = bt.slide_offset(now());
         // At start: old ~0.0, new ~1.0
         assert!(old_off.abs() < 0.01, "old_offset at start: {}", old_off);
         assert!(
@@ -1021,7 +1030,7 @@ mod tests {
             Duration::from_secs(1),
             Duration::from_secs(2),
         );
-        let (old_off, new_off) = bt.slide_offset();
+        let (old_off, new_off) = bt.slide_offset(now());
         // At end: old = -1.0, new = 0.0
         assert!(
             (old_off + 1.0).abs() < 0.01,
@@ -1048,7 +1057,7 @@ mod tests {
             Duration::from_secs(10),
             Duration::from_secs(5),
         );
-        let (old_off, new_off) = bt.slide_offset();
+        let (old_off, new_off) = bt.slide_offset(now());
         // At midpoint: old ~-0.5, new ~0.5
         assert!(
             (old_off + 0.5).abs() < 0.05,
@@ -1075,7 +1084,7 @@ mod tests {
             Duration::from_secs(10),
             Duration::ZERO,
         );
-        let (old_off, new_off) = bt.slide_offset();
+        let (old_off, new_off) = bt.slide_offset(now());
         // At start: old ~0.0, new ~-1.0
         assert!(old_off.abs() < 0.01, "old_offset at start: {}", old_off);
         assert!(
@@ -1098,7 +1107,7 @@ mod tests {
             Duration::from_secs(1),
             Duration::from_secs(2),
         );
-        let (old_off, new_off) = bt.slide_offset();
+        let (old_off, new_off) = bt.slide_offset(now());
         // At end: old = 1.0, new = 0.0
         assert!(
             (old_off - 1.0).abs() < 0.01,
@@ -1125,7 +1134,7 @@ mod tests {
             Duration::from_secs(10),
             Duration::from_secs(5),
         );
-        let (old_off, new_off) = bt.slide_offset();
+        let (old_off, new_off) = bt.slide_offset(now());
         // At midpoint: old ~0.5, new ~-0.5
         assert!(
             (old_off - 0.5).abs() < 0.05,
@@ -1157,7 +1166,7 @@ mod tests {
                 Duration::from_secs(1),
                 Duration::from_millis(500),
             );
-            let (old_off, new_off) = bt.slide_offset();
+            let (old_off, new_off) = bt.slide_offset(now());
             assert_eq!(old_off, 0.0, "non-slide type {:?} old_offset", tt);
             assert_eq!(new_off, 0.0, "non-slide type {:?} new_offset", tt);
         }
@@ -1179,7 +1188,7 @@ mod tests {
                 Duration::from_secs(10),
                 Duration::from_millis(elapsed_ms),
             );
-            let (old_off, new_off) = bt.slide_offset();
+            let (old_off, new_off) = bt.slide_offset(now());
             let gap = new_off - old_off;
             assert!(
                 (gap - 1.0).abs() < 0.01,
@@ -1207,7 +1216,7 @@ mod tests {
                 Duration::from_secs(10),
                 Duration::from_millis(elapsed_ms),
             );
-            let (old_off, new_off) = bt.slide_offset();
+            let (old_off, new_off) = bt.slide_offset(now());
             let gap = new_off - old_off;
             assert!(
                 (gap + 1.0).abs() < 0.01,
@@ -1237,8 +1246,8 @@ mod tests {
             Duration::from_nanos(1),
             Duration::from_millis(1),
         );
-        assert!(bt.is_complete());
-        assert_eq!(bt.progress(), 1.0);
+        assert!(bt.is_complete(now()));
+        assert_eq!(bt.progress(now()), 1.0);
     }
 
     #[test]
@@ -1254,8 +1263,8 @@ mod tests {
             Duration::from_secs(86400), // 1 day
             Duration::ZERO,
         );
-        assert!(!bt.is_complete());
-        let p = bt.progress();
+        assert!(!bt.is_complete(now()));
+        let p = bt.progress(now());
         assert!(p < 0.001, "progress for 1-day duration should be tiny, got {}", p);
     }
 
@@ -1279,8 +1288,8 @@ mod tests {
                 Duration::ZERO,
                 Duration::ZERO,
             );
-            assert_eq!(bt.progress(), 1.0, "{:?} with zero duration", tt);
-            assert!(bt.is_complete(), "{:?} with zero duration", tt);
+            assert_eq!(bt.progress(now()), 1.0, "{:?} with zero duration", tt);
+            assert!(bt.is_complete(now()), "{:?} with zero duration", tt);
         }
     }
 
@@ -1300,7 +1309,7 @@ mod tests {
                 Duration::from_secs(10),
                 Duration::from_millis(elapsed_ms),
             );
-            let (old_angle, new_angle) = bt.page_flip_angles();
+            let (old_angle, new_angle) = bt.page_flip_angles(now());
             assert!(
                 old_angle >= -0.01 && old_angle <= 90.01,
                 "old_angle out of range: {} at {}ms",
@@ -1331,7 +1340,7 @@ mod tests {
                 Duration::from_secs(10),
                 Duration::from_millis(elapsed_ms),
             );
-            let (old_angle, new_angle) = bt.page_flip_angles();
+            let (old_angle, new_angle) = bt.page_flip_angles(now());
             assert!(
                 old_angle >= -90.01 && old_angle <= 0.01,
                 "old_angle out of range: {} at {}ms",
@@ -1361,7 +1370,7 @@ mod tests {
                 Duration::from_secs(10),
                 Duration::from_millis(elapsed_ms),
             );
-            let (old_op, new_op) = bt.fade_opacity();
+            let (old_op, new_op) = bt.fade_opacity(now());
             assert!(
                 old_op >= -0.01 && old_op <= 1.01,
                 "old_opacity out of range: {} at {}ms",
@@ -1391,7 +1400,7 @@ mod tests {
                 Duration::from_secs(10),
                 Duration::from_millis(elapsed_ms),
             );
-            let (old_off, new_off) = bt.slide_offset();
+            let (old_off, new_off) = bt.slide_offset(now());
             // old: 0 -> -1, new: 1 -> 0
             assert!(
                 old_off >= -1.01 && old_off <= 0.01,
@@ -1422,7 +1431,7 @@ mod tests {
                 Duration::from_secs(10),
                 Duration::from_millis(elapsed_ms),
             );
-            let (old_off, new_off) = bt.slide_offset();
+            let (old_off, new_off) = bt.slide_offset(now());
             // old: 0 -> 1, new: -1 -> 0
             assert!(
                 old_off >= -0.01 && old_off <= 1.01,
@@ -1453,7 +1462,7 @@ mod tests {
 
         // Initially empty
         assert!(!manager.has_transition());
-        assert!(!manager.tick());
+        assert!(!manager.tick(now()));
 
         // Start a transition with zero duration (completes immediately)
         manager.start(
@@ -1461,11 +1470,12 @@ mod tests {
             t2.clone(),
             TransitionType::Fade,
             Duration::ZERO,
+            now(),
         );
         assert!(manager.has_transition());
 
         // Tick should complete it
-        assert!(manager.tick());
+        assert!(manager.tick(now()));
         assert!(!manager.has_transition());
 
         // Start a long transition
@@ -1474,18 +1484,19 @@ mod tests {
             t2.clone(),
             TransitionType::SlideLeft,
             Duration::from_secs(60),
+            now(),
         );
         assert!(manager.has_transition());
-        assert!(!manager.tick()); // Not complete yet
+        assert!(!manager.tick(now())); // Not complete yet
 
         // Replace with another zero-duration transition
-        manager.start(t1, t2, TransitionType::PageFlipLeft, Duration::ZERO);
+        manager.start(t1, t2, TransitionType::PageFlipLeft, Duration::ZERO, now());
         assert!(manager.has_transition());
         let active = manager.active().unwrap();
         assert_eq!(active.transition_type, TransitionType::PageFlipLeft);
 
         // Tick completes it
-        assert!(manager.tick());
+        assert!(manager.tick(now()));
         assert!(!manager.has_transition());
     }
 
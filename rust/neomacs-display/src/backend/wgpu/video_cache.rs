@@ -16,6 +16,8 @@ use gstreamer_app as gst_app;
 #[cfg(target_os = "linux")]
 use gstreamer_allocators as gst_allocators;
 
+use super::memory::{texture_bytes, StagingPool};
+
 /// Video playback state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VideoState {
@@ -136,11 +138,19 @@ pub struct VideoCache {
     bind_group_layout: Option<wgpu::BindGroupLayout>,
     /// Sampler for video textures (created in init_gpu)
     sampler: Option<wgpu::Sampler>,
+    /// Reusable buffers for CPU-path frames
+    staging: StagingPool,
 }
 
 impl VideoCache {
     /// Create a new video cache
     pub fn new() -> Self {
+        Self::with_staging_pool(StagingPool::default())
+    }
+
+    /// Create a new video cache copying CPU-path frames into buffers
+    /// from STAGING
+    pub fn with_staging_pool(staging: StagingPool) -> Self {
         // Initialize GStreamer
         if let Err(e) = gst::init() {
             log::error!("Failed to initialize GStreamer: {}", e);
@@ -150,8 +160,9 @@ impl VideoCache {
         let (frame_tx, frame_rx) = mpsc::channel::<DecodedFrame>();
 
         // Spawn decoder thread
+        let pool = staging.clone();
         thread::spawn(move || {
-            Self::decoder_thread(load_rx, frame_tx);
+            Self::decoder_thread(load_rx, frame_tx, pool);
         });
 
         Self {
@@ -161,6 +172,7 @@ impl VideoCache {
             frame_rx,
             bind_group_layout: None,
             sampler: None,
+            staging,
         }
     }
 
@@ -282,6 +294,15 @@ impl VideoCache {
         log::debug!("VideoCache: removed video {}", id);
     }
 
+    /// Texture memory of all videos, in bytes
+    pub fn memory_usage(&self) -> usize {
+        self.videos
+            .values()
+            .filter_map(|v| v.texture.as_ref())
+            .map(texture_bytes)
+            .sum()
+    }
+
     /// Check if any video is currently in Playing state
     pub fn has_playing_videos(&self) -> bool {
         self.videos
@@ -289,6 +310,15 @@ impl VideoCache {
             .any(|v| v.state.keeps_render_loop_active())
     }
 
+    /// Ids of the videos in Playing state
+    pub fn playing_videos(&self) -> Vec<u32> {
+        self.videos
+            .iter()
+            .filter(|(_, v)| v.state == VideoState::Playing)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
     /// Process pending decoded frames using stored GPU resources (call each frame)
     pub fn process_pending_frames(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
         // Take resources temporarily to avoid borrow conflict
@@ -521,6 +551,7 @@ impl VideoCache {
                 video.frame_count
             );
         }
+        self.staging.give(frame.data);
     }
 
     /// Try to extract DMA-BUF info from a GStreamer buffer
@@ -676,15 +707,17 @@ impl VideoCache {
     fn decoder_thread(
         rx: mpsc::Receiver<LoadRequest>,
         tx: mpsc::Sender<DecodedFrame>,
+        pool: StagingPool,
     ) {
         log::debug!("Video decoder thread started");
 
         while let Ok(request) = rx.recv() {
             log::info!("Decoder thread: dispatching video {}: {}", request.id, request.path);
             let tx_clone = tx.clone();
+            let pool_clone = pool.clone();
             // Spawn a dedicated thread per video so multiple videos load/play concurrently
             thread::spawn(move || {
-                Self::decode_single_video(request.id, &request.path, tx_clone);
+                Self::decode_single_video(request.id, &request.path, tx_clone, pool_clone);
             });
         }
 
@@ -696,6 +729,7 @@ impl VideoCache {
         video_id: u32,
         raw_path: &str,
         tx: mpsc::Sender<DecodedFrame>,
+        pool: StagingPool,
     ) {
         log::info!("Video thread: loading video {}: {}", video_id, raw_path);
 
@@ -815,7 +849,9 @@ impl VideoCache {
                                     }
 
                                     let data = if let Ok(map) = buffer.map_readable() {
-                                        map.as_slice().to_vec()
+                                        let mut data = pool.take(map.as_slice().len());
+                                        data.extend_from_slice(map.as_slice());
+                                        data
                                     } else if has_dmabuf {
                                         log::debug!("DMA-BUF memory not mappable (expected for zero-copy)");
                                         Vec::new()
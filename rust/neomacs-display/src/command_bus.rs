@@ -0,0 +1,412 @@
+//! Render command bus: the command channel from Emacs to the render
+//! thread, with batching, coalescing and ordering classes.
+//!
+//! Emacs sends commands through a `CommandSender`, which counts what is
+//! sent and what is dropped because the channel is full.  Once per loop
+//! iteration the render thread drains the channel into a batch with
+//! `CommandBus::drain`:
+//!
+//! - Setters whose last value is all that matters (cursor blink, present
+//!   mode, a webkit view's size, ...) are coalesced: of a run of them
+//!   sent back to back for the same target, only the last is kept.
+//! - The batch keeps the order commands were sent in, whatever their
+//!   `CommandClass`, so a setter runs after the command that creates
+//!   its target and a query sees everything sent before it.
+//!
+//! `CommandStats` lets Emacs see how far behind the renderer is and
+//! adapt, e.g. by sending fewer animation updates.
+
+use std::mem::Discriminant;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crossbeam_channel::{Receiver, SendError, Sender, TrySendError};
+
+use crate::thread_comm::RenderCommand;
+
+/// Class of a render command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CommandClass {
+    /// Thread control (shutdown)
+    Control,
+    /// Persistent settings: animation and effect configuration, window
+    /// properties
+    State,
+    /// Commands acting on displayed content: resources, views, one-shot
+    /// effects and transitions
+    Frame,
+    /// Commands answered with a reply to Emacs
+    Query,
+}
+
+/// Commands coalesce when their keys are equal: the variant, and the
+/// id of the object they apply to, if any
+type CoalesceKey = (Discriminant<RenderCommand>, u64);
+
+impl RenderCommand {
+    /// Class of this command
+    pub fn class(&self) -> CommandClass {
+        match self {
+            Self::Shutdown => CommandClass::Control,
+            Self::Query { .. } => CommandClass::Query,
+            Self::ScrollBlit { .. }
+            | Self::ImageLoadFile { .. }
+            | Self::ImageLoadArgb32 { .. }
+            | Self::ImageLoadRgb24 { .. }
+            | Self::ImageFree { .. }
+            | Self::ImageSetEdits { .. }
+            | Self::WebKitCreate { .. }
+            | Self::WebKitLoadUri { .. }
+            | Self::WebKitResize { .. }
+            | Self::WebKitDestroy { .. }
+            | Self::WebKitClick { .. }
+            | Self::WebKitPointerEvent { .. }
+            | Self::WebKitScroll { .. }
+            | Self::WebKitKeyEvent { .. }
+            | Self::WebKitGoBack { .. }
+            | Self::WebKitGoForward { .. }
+            | Self::WebKitReload { .. }
+            | Self::WebKitExecuteJavaScript { .. }
+            | Self::WebKitSetFloating { .. }
+            | Self::WebKitRemoveFloating { .. }
+            | Self::VideoCreate { .. }
+            | Self::VideoPlay { .. }
+            | Self::VideoPause { .. }
+            | Self::VideoDestroy { .. }
+            | Self::WarpMouse { .. }
+            | Self::SetPointerAutoHide { .. }
+            | Self::SetWheelConfig { .. }
+            | Self::ShowPopupMenu { .. }
+            | Self::HidePopupMenu
+            | Self::ShowCharPicker { .. }
+            | Self::HideCharPicker
+            | Self::ShowCommandPalette { .. }
+            | Self::HideCommandPalette
+            | Self::ShowColorPicker { .. }
+            | Self::HideColorPicker
+            | Self::ShowAgendaTimeline { .. }
+            | Self::HideAgendaTimeline
+            | Self::ShowGraphView { .. }
+            | Self::HideGraphView
+            | Self::ShowDirView { .. }
+            | Self::HideDirView
+            | Self::ShowProcessMonitor { .. }
+            | Self::HideProcessMonitor
+            | Self::ShowDatePicker { .. }
+            | Self::HideDatePicker
+            | Self::ShowTooltip { .. }
+            | Self::HideTooltip
+            | Self::VisualBell
+            | Self::RequestAttention { .. }
+            | Self::CancelAttention
+            | Self::CursorBeacon
+            | Self::StartSmoothTextUpdate
+            | Self::StartWindowTransition { .. }
+            | Self::PrepareBufferTransition
+            | Self::PrepareFrameTransition
+            | Self::TriggerBufferTransition
+            | Self::AddTimelineAnimation { .. }
+            | Self::CancelTimelineAnimation { .. }
+            | Self::RemoveChildFrame { .. }
+            | Self::CreateWindow { .. }
+            | Self::DestroyWindow { .. }
+            | Self::CreatePopupWindow { .. }
+            | Self::ResizePopupWindow { .. } => CommandClass::Frame,
+            #[cfg(feature = "neo-term")]
+            Self::TerminalCreate { .. }
+            | Self::TerminalWrite { .. }
+            | Self::TerminalResize { .. }
+            | Self::TerminalDestroy { .. }
+            | Self::TerminalSetFloat { .. }
+            | Self::TerminalKey { .. }
+            | Self::TerminalMouse { .. }
+            | Self::TerminalFocus { .. }
+            | Self::TerminalFlash { .. }
+            | Self::TerminalSetPalette { .. }
+            | Self::TerminalJumpToPrompt { .. } => CommandClass::Frame,
+            _ => CommandClass::State,
+        }
+    }
+
+    /// Key of this command if, of a run of commands with the same key,
+    /// only the last needs to run
+    fn coalesce_key(&self) -> Option<CoalesceKey> {
+        let id = match self {
+            Self::WebKitResize { id, .. }
+            | Self::WebKitSetFloating { id, .. }
+            | Self::ImageSetEdits { id, .. } => *id as u64,
+            #[cfg(feature = "neo-term")]
+            Self::TerminalResize { id, .. } | Self::TerminalSetFloat { id, .. } => *id as u64,
+            Self::SetAnimationCurve { target, .. } => *target as u64,
+            Self::SetMouseCursor { .. }
+            | Self::WarpMouse { .. }
+            | Self::SetWindowTitle { .. }
+            | Self::SetWindowFullscreen { .. }
+            | Self::SetWindowMinimized { .. }
+            | Self::SetWindowPosition { .. }
+            | Self::SetWindowSize { .. }
+            | Self::SetWindowDecorated { .. }
+            | Self::SetWindowIcon { .. }
+            | Self::SetCursorBlink { .. }
+            | Self::SetCursorAnimation { .. }
+            | Self::SetAnimationConfig { .. }
+            | Self::SetScrollIndicators { .. }
+            | Self::SetPostShader { .. }
+            | Self::SetPresentMode { .. }
+            | Self::SetSettingsFile { .. }
+            | Self::SetMemoryBudget { .. }
+            | Self::SetBufferTransition { .. }
+            | Self::SetBackgroundThrottle { .. }
+            | Self::SetTitlebarHeight { .. }
+            | Self::SetShowFps { .. }
+            | Self::SetCornerRadius { .. }
+            | Self::SetExtraSpacing { .. }
+            | Self::SetIndentGuideRainbow { .. }
+            | Self::SetCursorSizeTransition { .. }
+            | Self::SetLigaturesEnabled { .. }
+            | Self::SetChildFrameStyle { .. }
+            | Self::UpdateAccessibility { .. } => 0,
+            _ => return None,
+        };
+        Some((std::mem::discriminant(self), id))
+    }
+}
+
+/// Counters of the command bus, shared by both threads
+#[derive(Debug, Default)]
+pub struct CommandStats {
+    sent: AtomicU64,
+    dropped: AtomicU64,
+    coalesced: AtomicU64,
+    processed: AtomicU64,
+    batches: AtomicU64,
+    max_batch: AtomicU64,
+}
+
+/// Command bus counters at one point in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommandStatsSnapshot {
+    /// Commands accepted by the channel
+    pub sent: u64,
+    /// Commands dropped because the channel was full
+    pub dropped: u64,
+    /// Commands superseded by a later one in the same batch
+    pub coalesced: u64,
+    /// Commands run by the render thread
+    pub processed: u64,
+    /// Batches drained
+    pub batches: u64,
+    /// Largest batch drained
+    pub max_batch: u64,
+    /// Commands waiting in the channel
+    pub pending: u64,
+    /// Capacity of the channel
+    pub capacity: u64,
+}
+
+impl CommandStatsSnapshot {
+    /// Whether the renderer is falling behind: the channel is at least
+    /// three quarters full
+    pub fn is_congested(&self) -> bool {
+        self.capacity > 0 && self.pending * 4 >= self.capacity * 3
+    }
+}
+
+/// Sending side of the command channel
+#[derive(Debug, Clone)]
+pub struct CommandSender {
+    tx: Sender<RenderCommand>,
+    stats: Arc<CommandStats>,
+}
+
+impl CommandSender {
+    pub fn new(tx: Sender<RenderCommand>, stats: Arc<CommandStats>) -> Self {
+        Self { tx, stats }
+    }
+
+    /// Send CMD without blocking; fails if the channel is full
+    pub fn try_send(&self, cmd: RenderCommand) -> Result<(), TrySendError<RenderCommand>> {
+        let result = self.tx.try_send(cmd);
+        match result {
+            Ok(()) => self.stats.sent.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.stats.dropped.fetch_add(1, Ordering::Relaxed),
+        };
+        result
+    }
+
+    /// Send CMD, blocking while the channel is full
+    pub fn send(&self, cmd: RenderCommand) -> Result<(), SendError<RenderCommand>> {
+        let result = self.tx.send(cmd);
+        if result.is_ok() {
+            self.stats.sent.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Commands waiting in the channel
+    pub fn len(&self) -> usize {
+        self.tx.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tx.is_empty()
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> CommandStatsSnapshot {
+        let s = &self.stats;
+        CommandStatsSnapshot {
+            sent: s.sent.load(Ordering::Relaxed),
+            dropped: s.dropped.load(Ordering::Relaxed),
+            coalesced: s.coalesced.load(Ordering::Relaxed),
+            processed: s.processed.load(Ordering::Relaxed),
+            batches: s.batches.load(Ordering::Relaxed),
+            max_batch: s.max_batch.load(Ordering::Relaxed),
+            pending: self.tx.len() as u64,
+            capacity: self.tx.capacity().unwrap_or(0) as u64,
+        }
+    }
+}
+
+/// Most commands drained into one batch, so a flood of commands cannot
+/// starve rendering
+const MAX_BATCH: usize = 256;
+
+/// Receiving side of the command bus, on the render thread
+#[derive(Debug)]
+pub struct CommandBus {
+    stats: Arc<CommandStats>,
+}
+
+impl CommandBus {
+    pub fn new(stats: Arc<CommandStats>) -> Self {
+        Self { stats }
+    }
+
+    /// Drain the commands waiting in RX into a batch, coalesced and in
+    /// send order
+    pub fn drain(&self, rx: &Receiver<RenderCommand>) -> Vec<RenderCommand> {
+        let received: Vec<RenderCommand> = rx.try_iter().take(MAX_BATCH).collect();
+        if received.is_empty() {
+            return received;
+        }
+        let count = received.len() as u64;
+        let batch = coalesce(received);
+        let s = &self.stats;
+        s.coalesced.fetch_add(count - batch.len() as u64, Ordering::Relaxed);
+        s.processed.fetch_add(batch.len() as u64, Ordering::Relaxed);
+        s.batches.fetch_add(1, Ordering::Relaxed);
+        s.max_batch.fetch_max(count, Ordering::Relaxed);
+        batch
+    }
+}
+
+/// Coalesce COMMANDS, keeping their send order: a command is dropped
+/// when the next one has the same coalesce key
+fn coalesce(commands: Vec<RenderCommand>) -> Vec<RenderCommand> {
+    let mut batch: Vec<RenderCommand> = Vec::with_capacity(commands.len());
+    for cmd in commands {
+        let key = cmd.coalesce_key();
+        if key.is_some() && batch.last().and_then(RenderCommand::coalesce_key) == key {
+            batch.pop();
+        }
+        batch.push(cmd);
+    }
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::bounded;
+
+    fn bus(capacity: usize) -> (CommandSender, Receiver<RenderCommand>, CommandBus) {
+        let (tx, rx) = bounded(capacity);
+        let stats = Arc::new(CommandStats::default());
+        (CommandSender::new(tx, stats.clone()), rx, CommandBus::new(stats))
+    }
+
+    #[test]
+    fn classes() {
+        assert_eq!(RenderCommand::Shutdown.class(), CommandClass::Control);
+        assert_eq!(RenderCommand::VisualBell.class(), CommandClass::Frame);
+        assert_eq!(RenderCommand::SetShowFps { enabled: true }.class(), CommandClass::State);
+        assert_eq!(RenderCommand::ImageFree { id: 1 }.class(), CommandClass::Frame);
+        let query = RenderCommand::Query { id: 1, query: crate::query::RenderQuery::Monitors };
+        assert_eq!(query.class(), CommandClass::Query);
+    }
+
+    #[test]
+    fn coalesces_runs_of_the_same_setter() {
+        let (tx, rx, bus) = bus(16);
+        tx.try_send(RenderCommand::SetCornerRadius { radius: 1.0 }).unwrap();
+        tx.try_send(RenderCommand::SetCornerRadius { radius: 2.0 }).unwrap();
+        tx.try_send(RenderCommand::WebKitResize { id: 1, width: 10, height: 10 }).unwrap();
+        tx.try_send(RenderCommand::WebKitResize { id: 1, width: 20, height: 20 }).unwrap();
+        tx.try_send(RenderCommand::WebKitResize { id: 2, width: 30, height: 30 }).unwrap();
+        tx.try_send(RenderCommand::WebKitResize { id: 1, width: 40, height: 40 }).unwrap();
+
+        let batch = bus.drain(&rx);
+        assert_eq!(batch.len(), 4);
+        assert!(matches!(batch[0], RenderCommand::SetCornerRadius { radius } if radius == 2.0));
+        assert!(matches!(batch[1], RenderCommand::WebKitResize { id: 1, width: 20, .. }));
+        assert!(matches!(batch[2], RenderCommand::WebKitResize { id: 2, .. }));
+        assert!(matches!(batch[3], RenderCommand::WebKitResize { id: 1, width: 40, .. }));
+
+        let stats = tx.stats();
+        assert_eq!((stats.sent, stats.coalesced, stats.processed), (6, 2, 4));
+        assert_eq!((stats.batches, stats.max_batch), (1, 6));
+    }
+
+    #[test]
+    fn configures_what_the_batch_creates() {
+        let (tx, rx, bus) = bus(16);
+        tx.try_send(RenderCommand::WebKitCreate { id: 1, width: 10, height: 10 }).unwrap();
+        tx.try_send(RenderCommand::WebKitSetFloating { id: 1, x: 0.0, y: 0.0, width: 10.0, height: 10.0 }).unwrap();
+        tx.try_send(RenderCommand::WebKitResize { id: 1, width: 20, height: 20 }).unwrap();
+        tx.try_send(RenderCommand::WebKitResize { id: 1, width: 30, height: 30 }).unwrap();
+
+        let batch = bus.drain(&rx);
+        assert_eq!(batch.len(), 3);
+        assert!(matches!(batch[0], RenderCommand::WebKitCreate { id: 1, .. }));
+        assert!(matches!(batch[1], RenderCommand::WebKitSetFloating { id: 1, .. }));
+        assert!(matches!(batch[2], RenderCommand::WebKitResize { id: 1, width: 30, .. }));
+    }
+
+    #[test]
+    fn keeps_send_order_across_classes() {
+        let (tx, rx, bus) = bus(16);
+        tx.try_send(RenderCommand::ImageFree { id: 1 }).unwrap();
+        tx.try_send(RenderCommand::SetShowFps { enabled: true }).unwrap();
+        tx.try_send(RenderCommand::ImageFree { id: 2 }).unwrap();
+        tx.try_send(RenderCommand::Shutdown).unwrap();
+        tx.try_send(RenderCommand::UpdateEffect(crate::thread_comm::EffectUpdater(Box::new(|_| {})))).unwrap();
+
+        let classes: Vec<_> = bus.drain(&rx).iter().map(|c| c.class()).collect();
+        assert_eq!(classes, vec![
+            CommandClass::Frame,
+            CommandClass::State,
+            CommandClass::Frame,
+            CommandClass::Control,
+            CommandClass::State,
+        ]);
+        assert!(bus.drain(&rx).is_empty());
+    }
+
+    #[test]
+    fn counts_drops_and_congestion() {
+        let (tx, rx, bus) = bus(4);
+        for _ in 0..3 {
+            tx.try_send(RenderCommand::VisualBell).unwrap();
+        }
+        assert!(tx.stats().is_congested());
+        tx.try_send(RenderCommand::VisualBell).unwrap();
+        assert!(tx.try_send(RenderCommand::VisualBell).is_err());
+        let stats = tx.stats();
+        assert_eq!((stats.sent, stats.dropped, stats.pending, stats.capacity), (4, 1, 4, 4));
+
+        bus.drain(&rx);
+        assert!(!tx.stats().is_congested());
+        assert!(tx.is_empty());
+    }
+}
@@ -2,6 +2,8 @@
 
 use std::time::{Duration, Instant};
 
+use crate::core::animation_clock::AnimationClock;
+
 /// Easing functions for animations
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Easing {
@@ -53,13 +55,13 @@ pub struct Animation {
 }
 
 impl Animation {
-    /// Create a new animation
-    pub fn new(from: f32, to: f32, duration: Duration, easing: Easing) -> Self {
+    /// Create a new animation starting at `start_time`
+    pub fn new(from: f32, to: f32, duration: Duration, easing: Easing, start_time: Instant) -> Self {
         Self {
             from,
             to,
             duration,
-            start_time: Instant::now(),
+            start_time,
             easing,
             completed: false,
         }
@@ -67,7 +69,7 @@ impl Animation {
 
     /// Get current value at time `now`
     pub fn value_at(&mut self, now: Instant) -> f32 {
-        let elapsed = now.duration_since(self.start_time);
+        let elapsed = now.saturating_duration_since(self.start_time);
 
         if elapsed >= self.duration {
             self.completed = true;
@@ -80,11 +82,6 @@ impl Animation {
         self.from + (self.to - self.from) * eased_t
     }
 
-    /// Get current value (using current time)
-    pub fn current_value(&mut self) -> f32 {
-        self.value_at(Instant::now())
-    }
-
     /// Check if animation is complete
     pub fn is_complete(&self) -> bool {
         self.completed
@@ -104,6 +101,9 @@ pub struct AnimationManager {
 
     /// Frame time tracking
     last_frame_time: Option<Instant>,
+
+    /// Clock sampled by all animations, ticked once per frame
+    clock: AnimationClock,
 }
 
 impl Default for AnimationManager {
@@ -114,12 +114,18 @@ impl Default for AnimationManager {
 
 impl AnimationManager {
     pub fn new() -> Self {
+        Self::with_clock(AnimationClock::new())
+    }
+
+    /// Manager driven by CLOCK (a virtual clock in tests)
+    pub fn with_clock(clock: AnimationClock) -> Self {
         Self {
             scroll_animations: Vec::new(),
             cursor_blink_on: true,
-            last_cursor_toggle: Instant::now(),
+            last_cursor_toggle: clock.now(),
             cursor_blink_interval: Duration::from_millis(530),
             last_frame_time: None,
+            clock,
         }
     }
 
@@ -133,6 +139,7 @@ impl AnimationManager {
             to,
             Duration::from_millis(150),
             Easing::EaseOut,
+            self.clock.sample(),
         );
 
         self.scroll_animations.push((window_id, animation));
@@ -140,7 +147,7 @@ impl AnimationManager {
 
     /// Get current scroll offset for a window (returns None if no animation)
     pub fn get_scroll_offset(&mut self, window_id: i32) -> Option<f32> {
-        let now = Instant::now();
+        let now = self.clock.now();
 
         for (id, anim) in &mut self.scroll_animations {
             if *id == window_id {
@@ -153,17 +160,20 @@ impl AnimationManager {
 
     /// Update all animations, returns true if any animation is active
     pub fn tick(&mut self) -> bool {
-        let now = Instant::now();
+        let now = self.clock.tick();
         self.last_frame_time = Some(now);
 
         // Update cursor blink
-        if now.duration_since(self.last_cursor_toggle) >= self.cursor_blink_interval {
+        if now.saturating_duration_since(self.last_cursor_toggle) >= self.cursor_blink_interval {
             self.cursor_blink_on = !self.cursor_blink_on;
             self.last_cursor_toggle = now;
         }
 
         // Remove completed scroll animations
-        self.scroll_animations.retain(|(_, anim)| !anim.is_complete());
+        self.scroll_animations.retain_mut(|(_, anim)| {
+            anim.value_at(now);
+            !anim.is_complete()
+        });
 
         // Return true if there are active animations
         !self.scroll_animations.is_empty()
@@ -177,7 +187,7 @@ impl AnimationManager {
     /// Reset cursor blink (call when cursor moves)
     pub fn reset_cursor_blink(&mut self) {
         self.cursor_blink_on = true;
-        self.last_cursor_toggle = Instant::now();
+        self.last_cursor_toggle = self.clock.sample();
     }
 
     /// Set cursor blink interval
@@ -194,7 +204,11 @@ impl AnimationManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::thread::sleep;
+
+    /// Manager on a virtual clock, moved with `mgr.clock.advance`
+    fn manager() -> AnimationManager {
+        AnimationManager::with_clock(AnimationClock::new_virtual(Instant::now()))
+    }
 
     #[test]
     fn test_easing() {
@@ -205,20 +219,19 @@ mod tests {
 
     #[test]
     fn test_animation() {
-        let mut anim = Animation::new(0.0, 100.0, Duration::from_millis(100), Easing::Linear);
+        let start = Instant::now();
+        let mut anim = Animation::new(0.0, 100.0, Duration::from_millis(100), Easing::Linear, start);
 
         // At start
-        let v1 = anim.current_value();
+        let v1 = anim.value_at(start);
         assert!(v1 < 50.0);
 
-        // Wait and check progress
-        sleep(Duration::from_millis(50));
-        let v2 = anim.current_value();
+        // Step and check progress
+        let v2 = anim.value_at(start + Duration::from_millis(50));
         assert!(v2 > v1);
 
-        // Wait until complete
-        sleep(Duration::from_millis(60));
-        let v3 = anim.current_value();
+        // Step until complete
+        let v3 = anim.value_at(start + Duration::from_millis(110));
         assert_eq!(v3, 100.0);
         assert!(anim.is_complete());
     }
@@ -347,7 +360,7 @@ mod tests {
 
     #[test]
     fn test_animation_new_initial_state() {
-        let anim = Animation::new(10.0, 50.0, Duration::from_millis(200), Easing::EaseOut);
+        let anim = Animation::new(10.0, 50.0, Duration::from_millis(200), Easing::EaseOut, Instant::now());
         assert_eq!(anim.from, 10.0);
         assert_eq!(anim.to, 50.0);
         assert_eq!(anim.duration, Duration::from_millis(200));
@@ -357,7 +370,7 @@ mod tests {
 
     #[test]
     fn test_animation_value_at_start_time() {
-        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(1), Easing::Linear);
+        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(1), Easing::Linear, Instant::now());
         // Query at the exact start time should return the from value
         let val = anim.value_at(anim.start_time);
         assert!(
@@ -370,7 +383,7 @@ mod tests {
 
     #[test]
     fn test_animation_value_at_end_time() {
-        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(1), Easing::Linear);
+        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(1), Easing::Linear, Instant::now());
         let end_time = anim.start_time + Duration::from_secs(1);
         let val = anim.value_at(end_time);
         assert_eq!(val, 100.0);
@@ -379,7 +392,7 @@ mod tests {
 
     #[test]
     fn test_animation_value_past_end_time() {
-        let mut anim = Animation::new(0.0, 100.0, Duration::from_millis(50), Easing::Linear);
+        let mut anim = Animation::new(0.0, 100.0, Duration::from_millis(50), Easing::Linear, Instant::now());
         let way_after = anim.start_time + Duration::from_secs(10);
         let val = anim.value_at(way_after);
         assert_eq!(val, 100.0);
@@ -388,7 +401,7 @@ mod tests {
 
     #[test]
     fn test_animation_linear_midpoint_value() {
-        let mut anim = Animation::new(0.0, 200.0, Duration::from_secs(2), Easing::Linear);
+        let mut anim = Animation::new(0.0, 200.0, Duration::from_secs(2), Easing::Linear, Instant::now());
         let mid = anim.start_time + Duration::from_secs(1);
         let val = anim.value_at(mid);
         assert!(
@@ -401,7 +414,7 @@ mod tests {
     #[test]
     fn test_animation_negative_range() {
         // Animation can go from high to low
-        let mut anim = Animation::new(100.0, 0.0, Duration::from_secs(1), Easing::Linear);
+        let mut anim = Animation::new(100.0, 0.0, Duration::from_secs(1), Easing::Linear, Instant::now());
         let mid = anim.start_time + Duration::from_millis(500);
         let val = anim.value_at(mid);
         assert!(
@@ -418,7 +431,7 @@ mod tests {
     #[test]
     fn test_animation_zero_duration() {
         // A zero-duration animation should immediately complete
-        let mut anim = Animation::new(0.0, 42.0, Duration::from_millis(0), Easing::Linear);
+        let mut anim = Animation::new(0.0, 42.0, Duration::from_millis(0), Easing::Linear, Instant::now());
         let val = anim.value_at(anim.start_time);
         assert_eq!(val, 42.0);
         assert!(anim.is_complete());
@@ -427,7 +440,7 @@ mod tests {
     #[test]
     fn test_animation_same_from_to() {
         // Animation where from == to should always return that value
-        let mut anim = Animation::new(77.0, 77.0, Duration::from_secs(1), Easing::EaseInOut);
+        let mut anim = Animation::new(77.0, 77.0, Duration::from_secs(1), Easing::EaseInOut, Instant::now());
         let mid = anim.start_time + Duration::from_millis(500);
         let val = anim.value_at(mid);
         assert!(
@@ -439,7 +452,7 @@ mod tests {
 
     #[test]
     fn test_animation_completed_flag_stays_set() {
-        let mut anim = Animation::new(0.0, 10.0, Duration::from_millis(10), Easing::Linear);
+        let mut anim = Animation::new(0.0, 10.0, Duration::from_millis(10), Easing::Linear, Instant::now());
         assert!(!anim.is_complete());
 
         // Complete it
@@ -485,7 +498,7 @@ mod tests {
 
     #[test]
     fn test_animate_scroll_creates_animation() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         assert!(!mgr.has_active_animations());
 
         mgr.animate_scroll(1, 0.0, 100.0);
@@ -494,13 +507,13 @@ mod tests {
 
     #[test]
     fn test_get_scroll_offset_returns_none_for_unknown_window() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         assert!(mgr.get_scroll_offset(999).is_none());
     }
 
     #[test]
     fn test_get_scroll_offset_returns_value_for_active_animation() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.animate_scroll(1, 0.0, 100.0);
 
         let offset = mgr.get_scroll_offset(1);
@@ -512,7 +525,7 @@ mod tests {
 
     #[test]
     fn test_animate_scroll_replaces_existing_for_same_window() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.animate_scroll(1, 0.0, 50.0);
         mgr.animate_scroll(1, 50.0, 200.0);
 
@@ -529,7 +542,7 @@ mod tests {
 
     #[test]
     fn test_multiple_concurrent_scroll_animations() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.animate_scroll(1, 0.0, 100.0);
         mgr.animate_scroll(2, 50.0, 200.0);
         mgr.animate_scroll(3, 10.0, 30.0);
@@ -546,11 +559,11 @@ mod tests {
 
     #[test]
     fn test_tick_removes_completed_animations() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.animate_scroll(1, 0.0, 100.0);
 
         // Wait for the scroll animation (150ms) to complete
-        sleep(Duration::from_millis(200));
+        mgr.clock.advance(Duration::from_millis(200));
 
         // Force completion by reading the value (which sets completed flag)
         let _ = mgr.get_scroll_offset(1);
@@ -563,7 +576,7 @@ mod tests {
 
     #[test]
     fn test_tick_sets_last_frame_time() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         assert!(mgr.last_frame_time.is_none());
 
         mgr.tick();
@@ -572,7 +585,7 @@ mod tests {
 
     #[test]
     fn test_tick_returns_true_with_active_animations() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.animate_scroll(1, 0.0, 100.0);
 
         let has_active = mgr.tick();
@@ -581,17 +594,17 @@ mod tests {
 
     #[test]
     fn test_cursor_blink_initial_visibility() {
-        let mgr = AnimationManager::new();
+        let mgr = manager();
         assert!(mgr.cursor_visible(), "Cursor should be visible initially");
     }
 
     #[test]
     fn test_cursor_blink_toggles_after_interval() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.set_cursor_blink_interval(Duration::from_millis(50));
         assert!(mgr.cursor_visible());
 
-        sleep(Duration::from_millis(60));
+        mgr.clock.advance(Duration::from_millis(60));
         mgr.tick();
 
         assert!(
@@ -599,7 +612,7 @@ mod tests {
             "Cursor should toggle off after interval"
         );
 
-        sleep(Duration::from_millis(60));
+        mgr.clock.advance(Duration::from_millis(60));
         mgr.tick();
 
         assert!(
@@ -610,11 +623,11 @@ mod tests {
 
     #[test]
     fn test_reset_cursor_blink_makes_visible() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.set_cursor_blink_interval(Duration::from_millis(50));
 
         // Wait for blink to toggle off
-        sleep(Duration::from_millis(60));
+        mgr.clock.advance(Duration::from_millis(60));
         mgr.tick();
         assert!(!mgr.cursor_visible());
 
@@ -628,7 +641,7 @@ mod tests {
 
     #[test]
     fn test_set_cursor_blink_interval() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         assert_eq!(mgr.cursor_blink_interval, Duration::from_millis(530));
 
         mgr.set_cursor_blink_interval(Duration::from_millis(1000));
@@ -769,7 +782,7 @@ mod tests {
     fn test_animation_value_at_quarter_with_ease_in() {
         // EaseIn at t=0.25: eased = 0.25^2 = 0.0625
         // from=0, to=100 => value = 0 + 100*0.0625 = 6.25
-        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(4), Easing::EaseIn);
+        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(4), Easing::EaseIn, Instant::now());
         let quarter = anim.start_time + Duration::from_secs(1);
         let val = anim.value_at(quarter);
         assert!(
@@ -784,7 +797,7 @@ mod tests {
     fn test_animation_value_at_quarter_with_ease_out() {
         // EaseOut at t=0.25: eased = 1-(1-0.25)^2 = 1-0.5625 = 0.4375
         // from=0, to=100 => value = 43.75
-        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(4), Easing::EaseOut);
+        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(4), Easing::EaseOut, Instant::now());
         let quarter = anim.start_time + Duration::from_secs(1);
         let val = anim.value_at(quarter);
         assert!(
@@ -798,7 +811,7 @@ mod tests {
     fn test_animation_value_at_half_with_ease_in_out() {
         // EaseInOut at t=0.5: eased = 0.5
         // from=0, to=100 => value = 50.0
-        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(2), Easing::EaseInOut);
+        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(2), Easing::EaseInOut, Instant::now());
         let mid = anim.start_time + Duration::from_secs(1);
         let val = anim.value_at(mid);
         assert!(
@@ -811,7 +824,7 @@ mod tests {
     #[test]
     fn test_animation_large_value_range() {
         let mut anim =
-            Animation::new(-1_000_000.0, 1_000_000.0, Duration::from_secs(2), Easing::Linear);
+            Animation::new(-1_000_000.0, 1_000_000.0, Duration::from_secs(2), Easing::Linear, Instant::now());
         let mid = anim.start_time + Duration::from_secs(1);
         let val = anim.value_at(mid);
         assert!(
@@ -827,7 +840,7 @@ mod tests {
 
     #[test]
     fn test_animation_negative_values() {
-        let mut anim = Animation::new(-50.0, -200.0, Duration::from_secs(1), Easing::Linear);
+        let mut anim = Animation::new(-50.0, -200.0, Duration::from_secs(1), Easing::Linear, Instant::now());
         let mid = anim.start_time + Duration::from_millis(500);
         let val = anim.value_at(mid);
         assert!(
@@ -843,10 +856,9 @@ mod tests {
     #[test]
     fn test_animation_very_short_duration() {
         let mut anim =
-            Animation::new(0.0, 100.0, Duration::from_nanos(1), Easing::Linear);
-        // Even reading immediately will likely be past the 1ns duration
-        sleep(Duration::from_millis(1));
-        let val = anim.current_value();
+            Animation::new(0.0, 100.0, Duration::from_nanos(1), Easing::Linear, Instant::now());
+        // Any later read is past the 1ns duration
+        let val = anim.value_at(anim.start_time + Duration::from_millis(1));
         assert_eq!(val, 100.0);
         assert!(anim.is_complete());
     }
@@ -854,7 +866,7 @@ mod tests {
     #[test]
     fn test_animation_very_long_duration() {
         let mut anim =
-            Animation::new(0.0, 100.0, Duration::from_secs(3600), Easing::Linear);
+            Animation::new(0.0, 100.0, Duration::from_secs(3600), Easing::Linear, Instant::now());
         // At the start time, value should be exactly from
         let val = anim.value_at(anim.start_time);
         assert!((val - 0.0).abs() < 1e-6);
@@ -874,7 +886,7 @@ mod tests {
 
     #[test]
     fn test_animation_progresses_monotonically_with_linear() {
-        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(1), Easing::Linear);
+        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(1), Easing::Linear, Instant::now());
         let mut prev = anim.value_at(anim.start_time);
         for i in 1..=100 {
             let t = anim.start_time + Duration::from_millis(i * 10);
@@ -892,7 +904,7 @@ mod tests {
 
     #[test]
     fn test_animation_progresses_monotonically_with_ease_in_out() {
-        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(1), Easing::EaseInOut);
+        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(1), Easing::EaseInOut, Instant::now());
         let mut prev = anim.value_at(anim.start_time);
         for i in 1..=100 {
             let t = anim.start_time + Duration::from_millis(i * 10);
@@ -910,7 +922,7 @@ mod tests {
 
     #[test]
     fn test_animation_is_complete_not_set_mid_animation() {
-        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(10), Easing::Linear);
+        let mut anim = Animation::new(0.0, 100.0, Duration::from_secs(10), Easing::Linear, Instant::now());
         let mid = anim.start_time + Duration::from_secs(5);
         let _ = anim.value_at(mid);
         assert!(
@@ -922,7 +934,7 @@ mod tests {
     #[test]
     fn test_animation_value_at_multiple_times_without_completion() {
         // Calling value_at at different mid-animation times gives consistent interpolation
-        let mut anim = Animation::new(10.0, 110.0, Duration::from_secs(10), Easing::Linear);
+        let mut anim = Animation::new(10.0, 110.0, Duration::from_secs(10), Easing::Linear, Instant::now());
         let t1 = anim.start_time + Duration::from_secs(2);
         let t2 = anim.start_time + Duration::from_secs(5);
         let t3 = anim.start_time + Duration::from_secs(8);
@@ -939,7 +951,7 @@ mod tests {
 
     #[test]
     fn test_animation_with_fractional_values() {
-        let mut anim = Animation::new(0.1, 0.9, Duration::from_secs(1), Easing::Linear);
+        let mut anim = Animation::new(0.1, 0.9, Duration::from_secs(1), Easing::Linear, Instant::now());
         let mid = anim.start_time + Duration::from_millis(500);
         let val = anim.value_at(mid);
         assert!(
@@ -955,7 +967,7 @@ mod tests {
 
     #[test]
     fn test_tick_returns_false_with_no_animations() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         let has_active = mgr.tick();
         assert!(
             !has_active,
@@ -965,7 +977,7 @@ mod tests {
 
     #[test]
     fn test_cursor_does_not_toggle_before_interval() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.set_cursor_blink_interval(Duration::from_millis(500));
         assert!(mgr.cursor_visible());
 
@@ -979,15 +991,15 @@ mod tests {
 
     #[test]
     fn test_reset_cursor_blink_resets_toggle_timer() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.set_cursor_blink_interval(Duration::from_millis(50));
 
         // Wait almost long enough for a toggle
-        sleep(Duration::from_millis(40));
+        mgr.clock.advance(Duration::from_millis(40));
         mgr.reset_cursor_blink();
 
         // Now wait another 30ms (total 30ms since reset, not enough for 50ms interval)
-        sleep(Duration::from_millis(30));
+        mgr.clock.advance(Duration::from_millis(30));
         mgr.tick();
         assert!(
             mgr.cursor_visible(),
@@ -997,7 +1009,7 @@ mod tests {
 
     #[test]
     fn test_multiple_reset_cursor_blink_calls() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.set_cursor_blink_interval(Duration::from_millis(50));
 
         for _ in 0..5 {
@@ -1008,11 +1020,11 @@ mod tests {
 
     #[test]
     fn test_get_scroll_offset_returns_none_after_tick_removes_completed() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.animate_scroll(1, 0.0, 10.0);
 
         // Wait for animation to complete (scroll duration is 150ms)
-        sleep(Duration::from_millis(200));
+        mgr.clock.advance(Duration::from_millis(200));
 
         // Read to set completed flag
         let _ = mgr.get_scroll_offset(1);
@@ -1029,7 +1041,7 @@ mod tests {
 
     #[test]
     fn test_replacing_one_window_animation_does_not_affect_other() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.animate_scroll(1, 0.0, 100.0);
         mgr.animate_scroll(2, 0.0, 200.0);
 
@@ -1049,7 +1061,7 @@ mod tests {
 
     #[test]
     fn test_animate_scroll_uses_ease_out_and_150ms() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.animate_scroll(1, 0.0, 100.0);
 
         let (_, anim) = &mgr.scroll_animations[0];
@@ -1059,11 +1071,11 @@ mod tests {
 
     #[test]
     fn test_tick_updates_last_frame_time_each_call() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.tick();
         let first = mgr.last_frame_time.unwrap();
 
-        sleep(Duration::from_millis(10));
+        mgr.clock.advance(Duration::from_millis(10));
         mgr.tick();
         let second = mgr.last_frame_time.unwrap();
 
@@ -1075,26 +1087,26 @@ mod tests {
 
     #[test]
     fn test_cursor_blink_multiple_toggles() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         mgr.set_cursor_blink_interval(Duration::from_millis(30));
 
         // Toggle 1: off
-        sleep(Duration::from_millis(40));
+        mgr.clock.advance(Duration::from_millis(40));
         mgr.tick();
         assert!(!mgr.cursor_visible());
 
         // Toggle 2: on
-        sleep(Duration::from_millis(40));
+        mgr.clock.advance(Duration::from_millis(40));
         mgr.tick();
         assert!(mgr.cursor_visible());
 
         // Toggle 3: off
-        sleep(Duration::from_millis(40));
+        mgr.clock.advance(Duration::from_millis(40));
         mgr.tick();
         assert!(!mgr.cursor_visible());
 
         // Toggle 4: on
-        sleep(Duration::from_millis(40));
+        mgr.clock.advance(Duration::from_millis(40));
         mgr.tick();
         assert!(mgr.cursor_visible());
     }
@@ -1113,7 +1125,7 @@ mod tests {
 
     #[test]
     fn test_animation_clone() {
-        let anim = Animation::new(0.0, 100.0, Duration::from_secs(1), Easing::Linear);
+        let anim = Animation::new(0.0, 100.0, Duration::from_secs(1), Easing::Linear, Instant::now());
         let cloned = anim.clone();
         assert_eq!(cloned.from, anim.from);
         assert_eq!(cloned.to, anim.to);
@@ -1125,7 +1137,7 @@ mod tests {
 
     #[test]
     fn test_animation_debug_format() {
-        let anim = Animation::new(0.0, 100.0, Duration::from_secs(1), Easing::Linear);
+        let anim = Animation::new(0.0, 100.0, Duration::from_secs(1), Easing::Linear, Instant::now());
         let debug_str = format!("{:?}", anim);
         assert!(debug_str.contains("Animation"));
         assert!(debug_str.contains("from"));
@@ -1134,21 +1146,21 @@ mod tests {
 
     #[test]
     fn test_animation_manager_debug_format() {
-        let mgr = AnimationManager::new();
+        let mgr = manager();
         let debug_str = format!("{:?}", mgr);
         assert!(debug_str.contains("AnimationManager"));
     }
 
     #[test]
     fn test_has_active_animations_after_adding_and_removing() {
-        let mut mgr = AnimationManager::new();
+        let mut mgr = manager();
         assert!(!mgr.has_active_animations());
 
         mgr.animate_scroll(1, 0.0, 10.0);
         assert!(mgr.has_active_animations());
 
         // Wait for completion + read + tick
-        sleep(Duration::from_millis(200));
+        mgr.clock.advance(Duration::from_millis(200));
         let _ = mgr.get_scroll_offset(1);
         mgr.tick();
         assert!(!mgr.has_active_animations());
@@ -0,0 +1,186 @@
+//! Central clock sampled by all animations.
+//!
+//! The render thread ticks the clock once per iteration of its loop;
+//! animations then read `now()` instead of calling `Instant::now()`
+//! themselves, so everything animated in one frame sees the same time.
+//! Integrated animations (springs, exponential decay, fades) advance in
+//! `steps()` fixed timesteps of `step()` each, so they move the same
+//! way at 30, 60 or 144 Hz.  A virtual clock only moves when
+//! `advance()` is called, which makes animations testable.
+
+use std::time::{Duration, Instant};
+
+/// Length of one fixed animation step (240 Hz)
+pub const ANIMATION_STEP: Duration = Duration::from_nanos(1_000_000_000 / 240);
+
+/// Longest time one tick accounts for.  Longer stalls (suspend, a
+/// blocked render thread) skip ahead instead of running hundreds of
+/// steps at once.
+const MAX_TICK: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClockSource {
+    /// Wall clock
+    Real,
+    /// Time moved only by `advance`
+    Virtual(Instant),
+}
+
+/// Frame clock shared by the animations of the render thread
+#[derive(Debug, Clone)]
+pub struct AnimationClock {
+    source: ClockSource,
+    /// Time of the current tick
+    now: Instant,
+    /// Time elapsed between the previous tick and this one
+    frame_dt: Duration,
+    /// Time not yet consumed by fixed steps
+    accumulator: Duration,
+    /// Fixed steps to run this tick
+    steps: u32,
+}
+
+impl Default for AnimationClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnimationClock {
+    /// Clock following the wall clock
+    pub fn new() -> Self {
+        Self::with_source(ClockSource::Real, Instant::now())
+    }
+
+    /// Virtual clock starting at START
+    pub fn new_virtual(start: Instant) -> Self {
+        Self::with_source(ClockSource::Virtual(start), start)
+    }
+
+    fn with_source(source: ClockSource, now: Instant) -> Self {
+        Self {
+            source,
+            now,
+            frame_dt: Duration::ZERO,
+            accumulator: Duration::ZERO,
+            steps: 0,
+        }
+    }
+
+    pub fn is_virtual(&self) -> bool {
+        matches!(self.source, ClockSource::Virtual(_))
+    }
+
+    /// Move a virtual clock forward by DT; the next tick sees the new
+    /// time.  Does nothing on a real clock.
+    pub fn advance(&mut self, dt: Duration) {
+        if let ClockSource::Virtual(t) = &mut self.source {
+            *t += dt;
+        }
+    }
+
+    /// Current time of the source, without starting a new frame.  For
+    /// stamping events that arrive between ticks (input, resizes).
+    pub fn sample(&self) -> Instant {
+        match self.source {
+            ClockSource::Real => Instant::now(),
+            ClockSource::Virtual(t) => t,
+        }
+    }
+
+    /// Sample the source and start a new frame.  Returns the frame time.
+    pub fn tick(&mut self) -> Instant {
+        let sampled = self.sample();
+        let dt = sampled.saturating_duration_since(self.now).min(MAX_TICK);
+        self.now = sampled;
+        self.frame_dt = dt;
+        self.accumulator += dt;
+        let steps = self.accumulator.as_nanos() / ANIMATION_STEP.as_nanos();
+        self.accumulator -= ANIMATION_STEP * steps as u32;
+        self.steps = steps as u32;
+        self.now
+    }
+
+    /// Time of the current frame
+    pub fn now(&self) -> Instant {
+        self.now
+    }
+
+    /// Time since the previous frame
+    pub fn frame_dt(&self) -> Duration {
+        self.frame_dt
+    }
+
+    /// Fixed steps integrated animations run this frame
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Length of a fixed step in seconds
+    pub fn step_secs(&self) -> f32 {
+        ANIMATION_STEP.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn virtual_clock_moves_only_when_advanced() {
+        let start = Instant::now();
+        let mut clock = AnimationClock::new_virtual(start);
+        assert!(clock.is_virtual());
+        assert_eq!(clock.tick(), start);
+        assert_eq!(clock.steps(), 0);
+        clock.advance(ms(100));
+        assert_eq!(clock.now(), start);
+        assert_eq!(clock.tick(), start + ms(100));
+        assert_eq!(clock.frame_dt(), ms(100));
+        assert_eq!(clock.steps(), 24);
+    }
+
+    #[test]
+    fn sample_reads_the_source_without_ticking() {
+        let start = Instant::now();
+        let mut clock = AnimationClock::new_virtual(start);
+        clock.tick();
+        clock.advance(ms(40));
+        assert_eq!(clock.sample(), start + ms(40));
+        assert_eq!(clock.now(), start);
+        assert_eq!(clock.tick(), start + ms(40));
+    }
+
+    #[test]
+    fn steps_do_not_depend_on_frame_rate() {
+        let start = Instant::now();
+        let total = |hz: u64| {
+            let mut clock = AnimationClock::new_virtual(start);
+            let mut steps = 0;
+            for _ in 0..hz {
+                clock.advance(Duration::from_nanos(1_000_000_000 / hz));
+                clock.tick();
+                steps += clock.steps();
+            }
+            steps
+        };
+        let expected = total(30);
+        assert!((239..=240).contains(&expected));
+        assert_eq!(total(60), expected);
+        assert!(total(144).abs_diff(expected) <= 1);
+    }
+
+    #[test]
+    fn long_stalls_are_capped() {
+        let start = Instant::now();
+        let mut clock = AnimationClock::new_virtual(start);
+        clock.advance(Duration::from_secs(10));
+        clock.tick();
+        assert_eq!(clock.frame_dt(), MAX_TICK);
+        assert_eq!(clock.steps(), 60);
+    }
+}
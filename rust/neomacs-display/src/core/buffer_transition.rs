@@ -2,6 +2,9 @@
 
 use std::time::{Duration, Instant};
 
+use crate::core::easing::{CubicBezier, Spring};
+use crate::core::types::Rect;
+
 /// Buffer transition animation effect
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum BufferTransitionEffect {
@@ -26,6 +29,10 @@ pub enum BufferTransitionEffect {
     Blur,
     /// 3D page curl (book page turn)
     PageCurl,
+    /// 3D card flip around the vertical axis (new buffer on the back)
+    Flip,
+    /// 3D cube rotation (new buffer on the face to the right)
+    Cube,
 }
 
 impl BufferTransitionEffect {
@@ -41,13 +48,25 @@ impl BufferTransitionEffect {
             "push" | "stack" => Self::Push,
             "blur" => Self::Blur,
             "page" | "page-curl" | "book" => Self::PageCurl,
+            "flip" | "card-flip" => Self::Flip,
+            "cube" | "cube-rotate" => Self::Cube,
             _ => Self::Crossfade,
         }
     }
+
+    /// Direction the content moves in for directional effects
+    pub fn direction(&self) -> TransitionDirection {
+        match self {
+            Self::SlideRight => TransitionDirection::Right,
+            Self::SlideUp => TransitionDirection::Up,
+            Self::SlideDown => TransitionDirection::Down,
+            _ => TransitionDirection::Left,
+        }
+    }
 }
 
 /// Easing function for animations
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum TransitionEasing {
     Linear,
     #[default]
@@ -56,9 +75,30 @@ pub enum TransitionEasing {
     EaseInOut,
     /// Overshoot then settle (bouncy)
     EaseOutBack,
+    /// Spring-damper physics
+    Spring(Spring),
+    /// Cubic Bezier through control points
+    CubicBezier(CubicBezier),
 }
 
 impl TransitionEasing {
+    /// Easing from its FFI code: 0 = linear, 1 = ease-out, 2 = ease-in,
+    /// 3 = ease-in-out, 4 = ease-out-back, 5 = spring with PARAMS
+    /// (stiffness, damping, mass), 6 = cubic Bezier with PARAMS
+    /// (x1, y1, x2, y2)
+    pub fn from_ffi(code: i32, params: [f32; 4]) -> Option<Self> {
+        match code {
+            0 => Some(Self::Linear),
+            1 => Some(Self::EaseOut),
+            2 => Some(Self::EaseIn),
+            3 => Some(Self::EaseInOut),
+            4 => Some(Self::EaseOutBack),
+            5 => Some(Self::Spring(Spring::new(params[0], params[1], params[2]))),
+            6 => Some(Self::CubicBezier(CubicBezier::new(params[0], params[1], params[2], params[3]))),
+            _ => None,
+        }
+    }
+
     pub fn apply(&self, t: f32) -> f32 {
         let t = t.clamp(0.0, 1.0);
         match self {
@@ -77,6 +117,8 @@ impl TransitionEasing {
                 let c3 = c1 + 1.0;
                 1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
             }
+            Self::Spring(spring) => spring.apply(t),
+            Self::CubicBezier(bezier) => bezier.apply(t),
         }
     }
 }
@@ -123,13 +165,14 @@ pub struct BufferTransition {
 }
 
 impl BufferTransition {
-    pub fn new(effect: BufferTransitionEffect, direction: TransitionDirection, duration: Duration) -> Self {
+    /// Create a transition starting at the frame time `now`.
+    pub fn new(effect: BufferTransitionEffect, direction: TransitionDirection, duration: Duration, now: Instant) -> Self {
         Self {
             effect,
             direction,
             progress: 0.0,
             duration,
-            start_time: Instant::now(),
+            start_time: now,
             easing: TransitionEasing::EaseOut,
             completed: false,
             old_width: 0.0,
@@ -137,13 +180,13 @@ impl BufferTransition {
         }
     }
     
-    /// Update progress based on elapsed time
-    pub fn update(&mut self) -> bool {
+    /// Update progress to time NOW
+    pub fn update_at(&mut self, now: Instant) -> bool {
         if self.completed {
             return false;
         }
         
-        let elapsed = Instant::now().duration_since(self.start_time);
+        let elapsed = now.saturating_duration_since(self.start_time);
         let raw_progress = elapsed.as_secs_f32() / self.duration.as_secs_f32();
         
         if raw_progress >= 1.0 {
@@ -156,25 +199,6 @@ impl BufferTransition {
         true
     }
 
-    /// Update progress with explicit delta time
-    pub fn update_with_dt(&mut self, dt: f32) -> bool {
-        if self.completed {
-            return false;
-        }
-        
-        let elapsed = Instant::now().duration_since(self.start_time);
-        let raw_progress = elapsed.as_secs_f32() / self.duration.as_secs_f32();
-        
-        if raw_progress >= 1.0 {
-            self.progress = 1.0;
-            self.completed = true;
-            return false;
-        }
-        
-        self.progress = self.easing.apply(raw_progress);
-        true
-    }
-    
     /// Get the eased progress value
     pub fn eased_progress(&self) -> f32 {
         self.progress
@@ -246,6 +270,228 @@ impl BufferTransition {
     }
 }
 
+/// Content sampled by a `TransitionQuad`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionLayer {
+    /// Snapshot of the window before the switch
+    Old,
+    /// The window as currently displayed
+    New,
+}
+
+/// A textured quad of a buffer transition.  Coordinates are relative to
+/// the window; a negative destination width draws the source mirrored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransitionQuad {
+    pub layer: TransitionLayer,
+    /// Region of the layer sampled
+    pub src: Rect,
+    /// Where the region is drawn
+    pub dest: Rect,
+    /// Heights of the left and right edges of the destination, centered
+    /// vertically on it (both `dest.height` for a rectangle).  Unequal
+    /// heights draw a perspective-projected strip.
+    pub edge_heights: [f32; 2],
+    /// Color multiplier (1.0 = unchanged, lower darkens)
+    pub brightness: f32,
+    pub alpha: f32,
+}
+
+impl TransitionQuad {
+    fn new(layer: TransitionLayer, src: Rect, dest: Rect) -> Self {
+        Self { layer, src, dest, edge_heights: [dest.height; 2], brightness: 1.0, alpha: 1.0 }
+    }
+}
+
+/// Number of strips the curled part of a page is drawn with
+const PAGE_CURL_STRIPS: usize = 24;
+
+/// Number of strips each face of a flip or cube is drawn with; narrow
+/// strips keep the affine texture mapping close to perspective-correct
+const FACE_STRIPS: usize = 16;
+
+/// Light reaching a face turned ANGLE radians away from the viewer:
+/// ambient plus Lambertian falloff
+fn face_brightness(angle: f32) -> f32 {
+    0.3 + 0.7 * angle.cos().max(0.0)
+}
+
+/// A face of a rotating flip card or cube, `width` wide, projected onto
+/// a WIDTH x HEIGHT window.  POINT maps a horizontal position on the face
+/// (-0.5..0.5 of its width) to (x, depth) relative to the window center
+/// and plane, depth increasing away from the viewer.
+fn face_quads(
+    layer: TransitionLayer,
+    width: f32,
+    height: f32,
+    brightness: f32,
+    point: impl Fn(f32) -> (f32, f32),
+) -> Vec<TransitionQuad> {
+    // Eye distance: a face at depth 0 is drawn at its actual size
+    let eye = 2.0 * width.max(height);
+    let project = |u: f32| {
+        let (x, depth) = point(u);
+        let scale = eye / (eye + depth).max(1.0);
+        (width / 2.0 + x * scale, height * scale)
+    };
+    (0..FACE_STRIPS)
+        .map(|i| {
+            let u0 = i as f32 / FACE_STRIPS as f32 - 0.5;
+            let u1 = (i + 1) as f32 / FACE_STRIPS as f32 - 0.5;
+            let ((x0, h0), (x1, h1)) = (project(u0), project(u1));
+            let src = Rect::new((u0 + 0.5) * width, 0.0, width / FACE_STRIPS as f32, height);
+            TransitionQuad {
+                edge_heights: [h0, h1],
+                brightness,
+                ..TransitionQuad::new(layer, src, Rect::new(x0, 0.0, x1 - x0, height))
+            }
+        })
+        .collect()
+}
+
+impl BufferTransition {
+    /// Quads compositing this transition over the window, back to front.
+    /// The window is assumed to already show the new content, so effects
+    /// only draw what differs from it.  Blur has no blur pass and is drawn
+    /// as a crossfade.
+    pub fn quads(&self) -> Vec<TransitionQuad> {
+        use TransitionLayer::{New, Old};
+        let (w, h) = (self.old_width, self.old_height);
+        if w <= 0.0 || h <= 0.0 {
+            return Vec::new();
+        }
+        let full = Rect::new(0.0, 0.0, w, h);
+        let offset = |(dx, dy): (f32, f32)| Rect::new(dx, dy, w, h);
+        match self.effect {
+            BufferTransitionEffect::None => Vec::new(),
+            BufferTransitionEffect::Crossfade | BufferTransitionEffect::Blur => {
+                vec![TransitionQuad { alpha: self.crossfade_old_opacity(), ..TransitionQuad::new(Old, full, full) }]
+            }
+            BufferTransitionEffect::SlideLeft
+            | BufferTransitionEffect::SlideRight
+            | BufferTransitionEffect::SlideUp
+            | BufferTransitionEffect::SlideDown => vec![
+                TransitionQuad::new(Old, full, offset(self.slide_old_offset())),
+                TransitionQuad::new(New, full, offset(self.slide_new_offset())),
+            ],
+            BufferTransitionEffect::Push => vec![
+                TransitionQuad { brightness: 1.0 - 0.3 * self.progress, ..TransitionQuad::new(Old, full, full) },
+                TransitionQuad::new(New, full, offset(self.slide_new_offset())),
+            ],
+            BufferTransitionEffect::ScaleFade => {
+                let scale = self.scale_old();
+                let (sw, sh) = (w * scale, h * scale);
+                let dest = Rect::new((w - sw) / 2.0, (h - sh) / 2.0, sw, sh);
+                vec![TransitionQuad { alpha: self.crossfade_old_opacity(), ..TransitionQuad::new(Old, full, dest) }]
+            }
+            BufferTransitionEffect::PageCurl => self.page_curl_quads(),
+            BufferTransitionEffect::Flip => self.flip_quads(),
+            BufferTransitionEffect::Cube => self.cube_quads(),
+        }
+    }
+
+    /// Dark backdrop behind 3D effects, covering the new content at rest
+    fn backdrop(&self) -> TransitionQuad {
+        let full = Rect::new(0.0, 0.0, self.old_width, self.old_height);
+        TransitionQuad { brightness: 0.0, ..TransitionQuad::new(TransitionLayer::New, full, full) }
+    }
+
+    /// A card turning half a revolution around its vertical center line,
+    /// the old buffer on its front and the new one on its back.
+    fn flip_quads(&self) -> Vec<TransitionQuad> {
+        use std::f32::consts::PI;
+        let (w, h) = (self.old_width, self.old_height);
+        let angle = self.progress * PI;
+        // The back faces the viewer past the half turn, seen from the
+        // back so it is not mirrored
+        let (layer, face_angle) = if angle < PI / 2.0 {
+            (TransitionLayer::Old, angle)
+        } else {
+            (TransitionLayer::New, angle - PI)
+        };
+        let mut quads = vec![self.backdrop()];
+        quads.extend(face_quads(layer, w, h, face_brightness(face_angle), |u| {
+            (u * w * face_angle.cos(), u * w * face_angle.sin())
+        }));
+        quads
+    }
+
+    /// A cube turning a quarter revolution to the left: the old buffer on
+    /// the front face leaves to the left as the new buffer on the right
+    /// face comes to the front.
+    fn cube_quads(&self) -> Vec<TransitionQuad> {
+        use std::f32::consts::FRAC_PI_2;
+        let (w, h) = (self.old_width, self.old_height);
+        let angle = self.progress * FRAC_PI_2;
+        let (sin, cos) = angle.sin_cos();
+        let half = w / 2.0;
+        // Face points relative to the cube center, rotated by ANGLE and
+        // moved back so the front face starts in the window plane
+        let front = move |u: f32| (-half * sin + u * w * cos, half - half * cos - u * w * sin);
+        let right = move |u: f32| (half * cos + u * w * sin, half - half * sin + u * w * cos);
+        let old = face_quads(TransitionLayer::Old, w, h, face_brightness(angle), front);
+        let new = face_quads(TransitionLayer::New, w, h, face_brightness(FRAC_PI_2 - angle), right);
+        // Farther face first
+        let mut quads = vec![self.backdrop()];
+        if angle < FRAC_PI_2 / 2.0 {
+            quads.extend(new);
+            quads.extend(old);
+        } else {
+            quads.extend(old);
+            quads.extend(new);
+        }
+        quads
+    }
+
+    /// The old page turning from its right edge: the flat part left of the
+    /// fold, the new page under it with a shadow along the fold, and the
+    /// curled part wrapped around a cylinder and folded back over the page.
+    fn page_curl_quads(&self) -> Vec<TransitionQuad> {
+        use TransitionLayer::{New, Old};
+        let (w, h) = (self.old_width, self.old_height);
+        let params = PageCurlParams::from_progress(self.progress, w, h);
+        let r = params.radius;
+        // The fold sweeps from the right edge until the cylinder is off-page
+        let fold = (w + r) * (1.0 - self.progress) - r;
+        let mut quads = Vec::with_capacity(PAGE_CURL_STRIPS + 2);
+        if fold > 0.0 {
+            let flat = Rect::new(0.0, 0.0, fold.min(w), h);
+            quads.push(TransitionQuad::new(Old, flat, flat));
+        }
+        let shadow_width = (r * 1.5).min(w);
+        if params.shadow > 0.0 && fold < w {
+            let x = fold.max(0.0);
+            let band = Rect::new(x, 0.0, shadow_width.min(w - x), h);
+            quads.push(TransitionQuad { brightness: 1.0 - params.shadow, ..TransitionQuad::new(New, band, band) });
+        }
+        // Position of the point at arc length D past the fold
+        let wrap = |d: f32| {
+            let theta = d / r;
+            if theta < std::f32::consts::PI { fold + r * theta.sin() } else { fold - (d - std::f32::consts::PI * r) }
+        };
+        let curled = w - fold.max(0.0);
+        let start = (-fold).max(0.0);
+        let step = curled / PAGE_CURL_STRIPS as f32;
+        for i in 0..PAGE_CURL_STRIPS {
+            let (d0, d1) = (start + step * i as f32, start + step * (i + 1) as f32);
+            let (x0, x1) = (wrap(d0), wrap(d1));
+            let theta = (d0 + d1) / 2.0 / r;
+            // Front side shades toward the top of the cylinder; the back
+            // side of the page is darker
+            let brightness = if theta < std::f32::consts::FRAC_PI_2 {
+                1.0 - 0.3 * theta.sin()
+            } else {
+                1.0 - params.backside_darken - 0.2 * theta.min(std::f32::consts::PI).sin()
+            };
+            quads.push(TransitionQuad {
+                brightness,
+                ..TransitionQuad::new(Old, Rect::new(fold + d0, 0.0, d1 - d0, h), Rect::new(x0, 0.0, x1 - x0, h))
+            });
+        }
+        quads
+    }
+}
+
 /// Buffer transition animator - manages transition state and snapshot
 #[derive(Debug)]
 pub struct BufferTransitionAnimator {
@@ -254,6 +500,9 @@ pub struct BufferTransitionAnimator {
     
     /// Default duration
     pub default_duration: Duration,
+
+    /// Default easing
+    pub default_easing: TransitionEasing,
     
     /// Currently active transition (if any)
     pub active_transition: Option<BufferTransition>,
@@ -282,6 +531,7 @@ impl BufferTransitionAnimator {
         Self {
             default_effect: BufferTransitionEffect::Crossfade,
             default_duration: Duration::from_millis(200),
+            default_easing: TransitionEasing::EaseOut,
             active_transition: None,
             has_snapshot: false,
             snapshot_id: 0,
@@ -291,22 +541,20 @@ impl BufferTransitionAnimator {
     }
     
     /// Start a transition with default settings
-    pub fn start_transition(&mut self) {
-        self.start_transition_with(self.default_effect, TransitionDirection::Left);
+    pub fn start_transition(&mut self, now: Instant) {
+        self.start_transition_with(self.default_effect, TransitionDirection::Left, now);
     }
     
     /// Start a transition with specific effect and direction
-    pub fn start_transition_with(&mut self, effect: BufferTransitionEffect, direction: TransitionDirection) {
+    pub fn start_transition_with(&mut self, effect: BufferTransitionEffect, direction: TransitionDirection, now: Instant) {
         if effect == BufferTransitionEffect::None {
             self.active_transition = None;
             return;
         }
         
-        self.active_transition = Some(BufferTransition::new(
-            effect,
-            direction,
-            self.default_duration,
-        ));
+        let mut transition = BufferTransition::new(effect, direction, self.default_duration, now);
+        transition.easing = self.default_easing;
+        self.active_transition = Some(transition);
     }
     
     /// Request snapshot capture (call before buffer switch)
@@ -323,11 +571,11 @@ impl BufferTransitionAnimator {
         }
     }
     
-    /// Update the active transition
+    /// Update the active transition to the frame time `now`
     /// Returns true if transition is still active (needs redraw)
-    pub fn update(&mut self) -> bool {
+    pub fn update(&mut self, now: Instant) -> bool {
         if let Some(ref mut transition) = self.active_transition {
-            let still_active = transition.update();
+            let still_active = transition.update_at(now);
             if !still_active {
                 self.active_transition = None;
                 self.has_snapshot = false;
@@ -338,20 +586,6 @@ impl BufferTransitionAnimator {
         }
     }
 
-    /// Update with explicit delta time
-    pub fn update_with_dt(&mut self, dt: f32) -> bool {
-        if let Some(ref mut transition) = self.active_transition {
-            let still_active = transition.update_with_dt(dt);
-            if !still_active {
-                self.active_transition = None;
-                self.has_snapshot = false;
-            }
-            still_active
-        } else {
-            false
-        }
-    }
-    
     /// Check if a transition is currently active
     pub fn is_active(&self) -> bool {
         self.active_transition.is_some()
@@ -371,6 +605,11 @@ impl BufferTransitionAnimator {
     pub fn set_default_duration(&mut self, duration: Duration) {
         self.default_duration = duration;
     }
+
+    /// Set default easing
+    pub fn set_default_easing(&mut self, easing: TransitionEasing) {
+        self.default_easing = easing;
+    }
     
     /// Simple hash for content change detection
     pub fn update_content_hash(&mut self, hash: u64) -> bool {
@@ -458,6 +697,9 @@ mod tests {
         assert_eq!(BufferTransitionEffect::from_str("page"), BufferTransitionEffect::PageCurl);
         assert_eq!(BufferTransitionEffect::from_str("page-curl"), BufferTransitionEffect::PageCurl);
         assert_eq!(BufferTransitionEffect::from_str("book"), BufferTransitionEffect::PageCurl);
+        assert_eq!(BufferTransitionEffect::from_str("flip"), BufferTransitionEffect::Flip);
+        assert_eq!(BufferTransitionEffect::from_str("card-flip"), BufferTransitionEffect::Flip);
+        assert_eq!(BufferTransitionEffect::from_str("cube"), BufferTransitionEffect::Cube);
     }
 
     #[test]
@@ -543,6 +785,8 @@ mod tests {
             TransitionEasing::EaseOut,
             TransitionEasing::EaseInOut,
             TransitionEasing::EaseOutBack,
+            TransitionEasing::Spring(Spring::default()),
+            TransitionEasing::CubicBezier(CubicBezier::EASE),
         ];
         for e in &easings {
             assert!(
@@ -558,6 +802,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn easing_from_ffi_parametric() {
+        let spring = TransitionEasing::from_ffi(5, [200.0, 10.0, 2.0, 0.0]);
+        assert_eq!(spring, Some(TransitionEasing::Spring(Spring::new(200.0, 10.0, 2.0))));
+        let bezier = TransitionEasing::from_ffi(6, [0.4, 0.0, 0.2, 1.0]);
+        assert_eq!(bezier, Some(TransitionEasing::CubicBezier(CubicBezier::new(0.4, 0.0, 0.2, 1.0))));
+    }
+
     // ---- BufferTransition creation and initial state ----
 
     #[test]
@@ -566,6 +818,7 @@ mod tests {
             BufferTransitionEffect::Crossfade,
             TransitionDirection::Left,
             Duration::from_millis(200),
+            Instant::now(),
         );
         assert_eq!(t.effect, BufferTransitionEffect::Crossfade);
         assert_eq!(t.direction, TransitionDirection::Left);
@@ -585,6 +838,7 @@ mod tests {
             BufferTransitionEffect::Crossfade,
             TransitionDirection::Left,
             Duration::from_millis(200),
+            Instant::now(),
         );
         // progress = 0.0 at creation
         assert_eq!(t.crossfade_old_opacity(), 1.0);
@@ -597,6 +851,7 @@ mod tests {
             BufferTransitionEffect::Crossfade,
             TransitionDirection::Left,
             Duration::from_millis(200),
+            Instant::now(),
         );
         t.progress = 1.0;
         assert_eq!(t.crossfade_old_opacity(), 0.0);
@@ -609,6 +864,7 @@ mod tests {
             BufferTransitionEffect::Crossfade,
             TransitionDirection::Left,
             Duration::from_millis(200),
+            Instant::now(),
         );
         for p in [0.0, 0.25, 0.5, 0.75, 1.0] {
             t.progress = p;
@@ -625,6 +881,7 @@ mod tests {
             BufferTransitionEffect::SlideLeft,
             TransitionDirection::Left,
             Duration::from_millis(200),
+            Instant::now(),
         );
         t.old_width = 800.0;
         t.old_height = 600.0;
@@ -639,6 +896,7 @@ mod tests {
             BufferTransitionEffect::SlideLeft,
             TransitionDirection::Left,
             Duration::from_millis(200),
+            Instant::now(),
         );
         t.old_width = 800.0;
         t.old_height = 600.0;
@@ -654,6 +912,7 @@ mod tests {
             BufferTransitionEffect::SlideRight,
             TransitionDirection::Right,
             Duration::from_millis(200),
+            Instant::now(),
         );
         t.old_width = 800.0;
         t.progress = 1.0;
@@ -667,6 +926,7 @@ mod tests {
             BufferTransitionEffect::SlideUp,
             TransitionDirection::Up,
             Duration::from_millis(200),
+            Instant::now(),
         );
         t.old_height = 600.0;
         t.progress = 1.0;
@@ -680,6 +940,7 @@ mod tests {
             BufferTransitionEffect::SlideDown,
             TransitionDirection::Down,
             Duration::from_millis(200),
+            Instant::now(),
         );
         t.old_height = 600.0;
         t.progress = 1.0;
@@ -695,6 +956,7 @@ mod tests {
             BufferTransitionEffect::ScaleFade,
             TransitionDirection::Left,
             Duration::from_millis(200),
+            Instant::now(),
         );
         // At start: old=1.0, new=0.9
         assert_eq!(t.scale_old(), 1.0);
@@ -713,6 +975,7 @@ mod tests {
             BufferTransitionEffect::Blur,
             TransitionDirection::Left,
             Duration::from_millis(200),
+            Instant::now(),
         );
         // At start: old has no blur, new has full blur
         assert_eq!(t.blur_old_radius(), 0.0);
@@ -731,6 +994,7 @@ mod tests {
             BufferTransitionEffect::PageCurl,
             TransitionDirection::Left,
             Duration::from_millis(300),
+            Instant::now(),
         );
         // At start
         let (curl, angle, shadow) = t.page_curl_params();
@@ -751,6 +1015,7 @@ mod tests {
             BufferTransitionEffect::PageCurl,
             TransitionDirection::Left,
             Duration::from_millis(300),
+            Instant::now(),
         );
         t.progress = 0.5;
         let (_, _, shadow) = t.page_curl_params();
@@ -762,14 +1027,15 @@ mod tests {
 
     #[test]
     fn transition_completes_after_duration() {
+        let start = Instant::now();
         let mut t = BufferTransition::new(
             BufferTransitionEffect::Crossfade,
             TransitionDirection::Left,
             Duration::from_millis(1), // very short
+            start,
         );
-        // Sleep just past the duration
-        std::thread::sleep(Duration::from_millis(5));
-        let still_active = t.update();
+        // Step just past the duration
+        let still_active = t.update_at(start + Duration::from_millis(5));
         assert!(!still_active);
         assert!(t.completed);
         assert_eq!(t.progress, 1.0);
@@ -781,9 +1047,10 @@ mod tests {
             BufferTransitionEffect::Crossfade,
             TransitionDirection::Left,
             Duration::from_millis(1),
+            Instant::now(),
         );
         t.completed = true;
-        assert!(!t.update());
+        assert!(!t.update_at(Instant::now()));
     }
 
     #[test]
@@ -792,6 +1059,7 @@ mod tests {
             BufferTransitionEffect::Crossfade,
             TransitionDirection::Left,
             Duration::from_millis(200),
+            Instant::now(),
         );
         t.progress = 0.42;
         assert_eq!(t.eased_progress(), 0.42);
@@ -820,7 +1088,7 @@ mod tests {
     #[test]
     fn animator_start_transition_creates_active() {
         let mut a = BufferTransitionAnimator::new();
-        a.start_transition();
+        a.start_transition(Instant::now());
         assert!(a.is_active());
         let t = a.get_transition().unwrap();
         assert_eq!(t.effect, BufferTransitionEffect::Crossfade);
@@ -830,16 +1098,16 @@ mod tests {
     #[test]
     fn animator_start_transition_with_none_clears() {
         let mut a = BufferTransitionAnimator::new();
-        a.start_transition(); // first create an active transition
+        a.start_transition(Instant::now()); // first create an active transition
         assert!(a.is_active());
-        a.start_transition_with(BufferTransitionEffect::None, TransitionDirection::Left);
+        a.start_transition_with(BufferTransitionEffect::None, TransitionDirection::Left, Instant::now());
         assert!(!a.is_active());
     }
 
     #[test]
     fn animator_start_transition_with_specific_effect() {
         let mut a = BufferTransitionAnimator::new();
-        a.start_transition_with(BufferTransitionEffect::SlideUp, TransitionDirection::Up);
+        a.start_transition_with(BufferTransitionEffect::SlideUp, TransitionDirection::Up, Instant::now());
         assert!(a.is_active());
         let t = a.get_transition().unwrap();
         assert_eq!(t.effect, BufferTransitionEffect::SlideUp);
@@ -849,7 +1117,7 @@ mod tests {
     #[test]
     fn animator_snapshot_workflow() {
         let mut a = BufferTransitionAnimator::new();
-        a.start_transition();
+        a.start_transition(Instant::now());
         a.request_snapshot();
         assert!(!a.has_snapshot);
         a.snapshot_captured(800.0, 600.0);
@@ -871,10 +1139,10 @@ mod tests {
     fn animator_update_completes_and_clears() {
         let mut a = BufferTransitionAnimator::new();
         a.default_duration = Duration::from_millis(1);
-        a.start_transition();
+        let start = Instant::now();
+        a.start_transition(start);
         a.has_snapshot = true;
-        std::thread::sleep(Duration::from_millis(5));
-        let still_active = a.update();
+        let still_active = a.update(start + Duration::from_millis(5));
         assert!(!still_active);
         assert!(!a.is_active());
         assert!(!a.has_snapshot);
@@ -883,7 +1151,7 @@ mod tests {
     #[test]
     fn animator_update_no_transition_returns_false() {
         let mut a = BufferTransitionAnimator::new();
-        assert!(!a.update());
+        assert!(!a.update(Instant::now()));
     }
 
     #[test]
@@ -891,7 +1159,7 @@ mod tests {
         let mut a = BufferTransitionAnimator::new();
         a.set_default_effect(BufferTransitionEffect::Blur);
         assert_eq!(a.default_effect, BufferTransitionEffect::Blur);
-        a.start_transition();
+        a.start_transition(Instant::now());
         let t = a.get_transition().unwrap();
         assert_eq!(t.effect, BufferTransitionEffect::Blur);
     }
@@ -901,7 +1169,7 @@ mod tests {
         let mut a = BufferTransitionAnimator::new();
         a.set_default_duration(Duration::from_millis(500));
         assert_eq!(a.default_duration, Duration::from_millis(500));
-        a.start_transition();
+        a.start_transition(Instant::now());
         let t = a.get_transition().unwrap();
         assert_eq!(t.duration, Duration::from_millis(500));
     }
@@ -977,4 +1245,116 @@ mod tests {
     fn direction_default_is_left() {
         assert_eq!(TransitionDirection::default(), TransitionDirection::Left);
     }
+
+    #[test]
+    fn effect_directions() {
+        assert_eq!(BufferTransitionEffect::SlideLeft.direction(), TransitionDirection::Left);
+        assert_eq!(BufferTransitionEffect::SlideRight.direction(), TransitionDirection::Right);
+        assert_eq!(BufferTransitionEffect::SlideUp.direction(), TransitionDirection::Up);
+        assert_eq!(BufferTransitionEffect::SlideDown.direction(), TransitionDirection::Down);
+        assert_eq!(BufferTransitionEffect::Push.direction(), TransitionDirection::Left);
+    }
+
+    // ---- Quads ----
+
+    fn sized(effect: BufferTransitionEffect, progress: f32) -> BufferTransition {
+        let mut t = BufferTransition::new(effect, effect.direction(), Duration::from_millis(200), Instant::now());
+        t.old_width = 800.0;
+        t.old_height = 600.0;
+        t.progress = progress;
+        t
+    }
+
+    #[test]
+    fn quads_need_snapshot_size() {
+        let t = BufferTransition::new(BufferTransitionEffect::Crossfade, TransitionDirection::Left, Duration::from_millis(200), Instant::now());
+        assert!(t.quads().is_empty());
+    }
+
+    #[test]
+    fn crossfade_quad_fades_old_snapshot() {
+        let quads = sized(BufferTransitionEffect::Crossfade, 0.25).quads();
+        assert_eq!(quads.len(), 1);
+        assert_eq!(quads[0].layer, TransitionLayer::Old);
+        assert_eq!(quads[0].dest, Rect::new(0.0, 0.0, 800.0, 600.0));
+        assert!((quads[0].alpha - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn slide_quads_cover_window() {
+        let quads = sized(BufferTransitionEffect::SlideLeft, 0.25).quads();
+        assert_eq!(quads.len(), 2);
+        assert_eq!(quads[0].dest.x, -200.0);
+        assert_eq!(quads[1].dest.x, 600.0);
+        assert_eq!(quads[1].layer, TransitionLayer::New);
+    }
+
+    #[test]
+    fn page_curl_starts_flat_and_ends_turned() {
+        let start = sized(BufferTransitionEffect::PageCurl, 0.0).quads();
+        assert_eq!(start[0].dest, Rect::new(0.0, 0.0, 800.0, 600.0));
+        assert!(start.iter().skip(1).all(|q| q.dest.width.abs() < 1e-3));
+
+        // Fully turned: nothing of the old page remains on screen
+        let end = sized(BufferTransitionEffect::PageCurl, 1.0).quads();
+        assert!(end.iter().filter(|q| q.layer == TransitionLayer::Old)
+            .all(|q| q.dest.x.max(q.dest.x + q.dest.width) <= 1e-3));
+    }
+
+    /// Quads of LAYER drawing a face, without the backdrop
+    fn face(quads: &[TransitionQuad], layer: TransitionLayer) -> Vec<TransitionQuad> {
+        quads.iter().skip(1).filter(|q| q.layer == layer).copied().collect()
+    }
+
+    #[test]
+    fn flip_shows_old_front_then_new_back() {
+        let start = sized(BufferTransitionEffect::Flip, 0.0).quads();
+        assert_eq!(start[0].brightness, 0.0); // backdrop
+        let front = face(&start, TransitionLayer::Old);
+        assert_eq!(front.len(), FACE_STRIPS);
+        assert!((front[0].dest.x).abs() < 1e-3);
+        assert!((front.last().unwrap().dest.x + front.last().unwrap().dest.width - 800.0).abs() < 1e-3);
+        assert!(front.iter().all(|q| (q.edge_heights[0] - 600.0).abs() < 1e-3 && q.brightness == 1.0));
+
+        let quarter = sized(BufferTransitionEffect::Flip, 0.25).quads();
+        let turning = face(&quarter, TransitionLayer::Old);
+        // The receding right edge is drawn smaller and the face darker
+        assert!(turning[0].edge_heights[0] > turning.last().unwrap().edge_heights[1]);
+        assert!(turning[0].brightness < 1.0);
+
+        let end = sized(BufferTransitionEffect::Flip, 1.0).quads();
+        let back = face(&end, TransitionLayer::New);
+        assert_eq!(back.len(), FACE_STRIPS);
+        // Not mirrored once fully turned
+        assert!(back.iter().all(|q| q.dest.width > 0.0));
+        assert!((back[0].dest.x).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cube_turns_new_face_to_front() {
+        let mid = sized(BufferTransitionEffect::Cube, 0.5).quads();
+        let old = face(&mid, TransitionLayer::Old);
+        let new = face(&mid, TransitionLayer::New);
+        // Faces meet at the cube edge nearest the viewer
+        let old_right = old.last().unwrap();
+        assert!((old_right.dest.x + old_right.dest.width - new[0].dest.x).abs() < 1e-3);
+        assert!((old_right.edge_heights[1] - new[0].edge_heights[0]).abs() < 1e-3);
+        assert!(new[0].edge_heights[0] > 600.0);
+        // Both faces are turned 45 degrees, so equally lit
+        assert!((old[0].brightness - new[0].brightness).abs() < 1e-5);
+
+        let end = sized(BufferTransitionEffect::Cube, 1.0).quads();
+        let front = face(&end, TransitionLayer::New);
+        assert!((front[0].dest.x).abs() < 1e-3);
+        assert!((front.last().unwrap().dest.x + front.last().unwrap().dest.width - 800.0).abs() < 1e-3);
+        assert!((front[0].brightness - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn page_curl_folds_back_over_page() {
+        let quads = sized(BufferTransitionEffect::PageCurl, 0.5).quads();
+        // The strips past the top of the cylinder are drawn mirrored
+        assert!(quads.iter().any(|q| q.layer == TransitionLayer::Old && q.dest.width < 0.0));
+        assert!(quads.iter().any(|q| q.layer == TransitionLayer::New && q.brightness < 1.0));
+    }
 }
@@ -0,0 +1,662 @@
+//! Charts rasterized to images.
+//!
+//! Lisp describes a chart (line, bar or sparkline, one or more data
+//! series, labels of the x positions and a few style settings) and gets
+//! back an RGBA image to display inline, plus the rectangle of each x
+//! position so hovering over it can show the values there.  Line and bar
+//! charts get a value axis with round tick values, grid lines, labels
+//! under the x positions, an optional title and a legend when there is
+//! more than one series; sparklines are just the line, sized to sit in
+//! a line of text.  Lines and points are antialiased.
+
+use super::image_edit::TextMask;
+
+/// How the series are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartKind {
+    Line,
+    Bar,
+    Sparkline,
+}
+
+/// One data series; NaN values are missing
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartSeries {
+    pub name: String,
+    pub values: Vec<f64>,
+    /// sRGB color (0xRRGGBB)
+    pub color: u32,
+}
+
+/// A chart to draw
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartSpec {
+    pub kind: ChartKind,
+    /// Image size in pixels
+    pub width: u32,
+    pub height: u32,
+    pub series: Vec<ChartSeries>,
+    /// Labels of the x positions, shown under line and bar charts
+    pub labels: Vec<String>,
+    pub title: Option<String>,
+    /// Text and axis color (0xRRGGBB)
+    pub fg: u32,
+    /// Background color (0xRRGGBB), None = transparent
+    pub bg: Option<u32>,
+    /// Value range shown, None = from the data
+    pub range: Option<(f64, f64)>,
+    /// Pixel size of the labels
+    pub font_size: f32,
+}
+
+/// Area of the image showing x position INDEX
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChartHotSpot {
+    pub index: usize,
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+}
+
+/// A drawn chart
+pub struct ChartImage {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA pixels, not premultiplied
+    pub rgba: Vec<u8>,
+    pub hot_spots: Vec<ChartHotSpot>,
+}
+
+/// Largest image drawn, in pixels per side
+const MAX_SIZE: u32 = 4096;
+/// Margin around line and bar charts
+const PAD: f32 = 4.0;
+
+/// Round tick values covering LO..HI with at most MAX_TICKS ticks, as
+/// (first, last, step): steps are 1, 2 or 5 times a power of ten.
+pub fn nice_ticks(lo: f64, hi: f64, max_ticks: usize) -> (f64, f64, f64) {
+    let span = (hi - lo).abs().max(f64::EPSILON);
+    let rough = span / max_ticks.max(2) as f64;
+    let magnitude = 10f64.powf(rough.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|&s| span / s <= max_ticks as f64)
+        .unwrap_or(10.0 * magnitude);
+    ((lo / step).floor() * step, (hi / step).ceil() * step, step)
+}
+
+/// Divisor and suffix for axis values up to MAX_ABS: thousands above
+/// ten thousand, millions, billions.
+fn axis_scale(max_abs: f64) -> (f64, &'static str) {
+    if max_abs >= 1e9 {
+        (1e9, "G")
+    } else if max_abs >= 1e6 {
+        (1e6, "M")
+    } else if max_abs >= 1e4 {
+        (1e3, "k")
+    } else {
+        (1.0, "")
+    }
+}
+
+/// Text of tick value V on an axis with STEP between ticks, divided by
+/// SCALE with SUFFIX appended.
+fn format_tick(v: f64, step: f64, (scale, suffix): (f64, &str)) -> String {
+    let step = step / scale;
+    let decimals = if step >= 1.0 {
+        0
+    } else {
+        (-step.log10().floor()).clamp(0.0, 6.0) as usize
+    };
+    let text = format!("{:.*}{}", decimals, v / scale, suffix);
+    // No "-0"
+    if text.starts_with('-')
+        && text[1..]
+            .chars()
+            .all(|c| c == '0' || c == '.' || c.is_alphabetic())
+    {
+        text[1..].to_string()
+    } else {
+        text
+    }
+}
+
+/// Value range of the chart: the given one, or the data's, including
+/// zero for bar charts, never empty.
+fn value_range(spec: &ChartSpec) -> (f64, f64) {
+    let (mut lo, mut hi) = spec.range.unwrap_or_else(|| {
+        spec.series
+            .iter()
+            .flat_map(|s| s.values.iter().copied())
+            .filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                (lo.min(v), hi.max(v))
+            })
+    });
+    if !lo.is_finite() || !hi.is_finite() {
+        (lo, hi) = (0.0, 1.0);
+    }
+    if spec.kind == ChartKind::Bar {
+        lo = lo.min(0.0);
+        hi = hi.max(0.0);
+    }
+    if hi - lo <= f64::EPSILON * hi.abs().max(1.0) {
+        let pad = (hi.abs() * 0.1).max(1.0);
+        (lo, hi) = (lo - pad, hi + pad);
+    }
+    (lo.min(hi), hi.max(lo))
+}
+
+/// RGBA pixels being drawn
+struct Canvas {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32, bg: Option<u32>) -> Self {
+        let pixel = match bg {
+            Some(c) => [(c >> 16) as u8, (c >> 8) as u8, c as u8, 255],
+            None => [0, 0, 0, 0],
+        };
+        Canvas {
+            width,
+            height,
+            data: pixel.repeat((width * height) as usize),
+        }
+    }
+
+    /// Draw COLOR with ALPHA over pixel (X, Y).
+    fn blend(&mut self, x: i64, y: i64, color: u32, alpha: f32) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 || alpha <= 0.0 {
+            return;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let a = alpha.min(1.0);
+        let dst_a = self.data[i + 3] as f32 / 255.0;
+        let out_a = a + dst_a * (1.0 - a);
+        for (k, shift) in [16, 8, 0].into_iter().enumerate() {
+            let src = ((color >> shift) & 0xFF) as f32;
+            let dst = self.data[i + k] as f32;
+            self.data[i + k] = ((src * a + dst * dst_a * (1.0 - a)) / out_a).round() as u8;
+        }
+        self.data[i + 3] = (out_a * 255.0).round() as u8;
+    }
+
+    /// Fill the rectangle X0..X1, Y0..Y1, with partial coverage of the
+    /// pixels on its edges.
+    fn fill(&mut self, x0: f32, y0: f32, x1: f32, y1: f32, color: u32, alpha: f32) {
+        let overlap = |p: f32, a: f32, b: f32| ((p + 1.0).min(b) - p.max(a)).max(0.0);
+        for y in y0.floor() as i64..y1.ceil() as i64 {
+            let cy = overlap(y as f32, y0, y1);
+            for x in x0.floor() as i64..x1.ceil() as i64 {
+                self.blend(x, y, color, alpha * cy * overlap(x as f32, x0, x1));
+            }
+        }
+    }
+
+    /// Draw a line through POINTS, WIDTH pixels wide, with round joins.
+    /// Each pixel is blended once, with its largest coverage.
+    fn polyline(&mut self, points: &[(f32, f32)], width: f32, color: u32) {
+        if points.is_empty() {
+            return;
+        }
+        let r = width / 2.0;
+        let (mut lx, mut ly, mut hx, mut hy) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for &(x, y) in points {
+            (lx, ly, hx, hy) = (lx.min(x), ly.min(y), hx.max(x), hy.max(y));
+        }
+        let x0 = ((lx - r - 1.0).floor() as i64).max(0);
+        let y0 = ((ly - r - 1.0).floor() as i64).max(0);
+        let x1 = ((hx + r + 1.0).ceil() as i64).min(self.width as i64);
+        let y1 = ((hy + r + 1.0).ceil() as i64).min(self.height as i64);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+        let w = (x1 - x0) as usize;
+        let mut coverage = vec![0.0f32; w * (y1 - y0) as usize];
+        let segments: Vec<_> = if points.len() == 1 {
+            vec![(points[0], points[0])]
+        } else {
+            points.windows(2).map(|p| (p[0], p[1])).collect()
+        };
+        for ((ax, ay), (bx, by)) in segments {
+            let sx0 = ((ax.min(bx) - r - 1.0).floor() as i64).max(x0);
+            let sx1 = ((ax.max(bx) + r + 1.0).ceil() as i64).min(x1);
+            let sy0 = ((ay.min(by) - r - 1.0).floor() as i64).max(y0);
+            let sy1 = ((ay.max(by) + r + 1.0).ceil() as i64).min(y1);
+            for y in sy0..sy1 {
+                for x in sx0..sx1 {
+                    let d = segment_distance(x as f32 + 0.5, y as f32 + 0.5, ax, ay, bx, by);
+                    let c = (r + 0.5 - d).clamp(0.0, 1.0);
+                    let cell = &mut coverage[(y - y0) as usize * w + (x - x0) as usize];
+                    *cell = cell.max(c);
+                }
+            }
+        }
+        for (i, &c) in coverage.iter().enumerate() {
+            if c > 0.0 {
+                self.blend(x0 + (i % w) as i64, y0 + (i / w) as i64, color, c);
+            }
+        }
+    }
+
+    /// Draw text MASK with its top left corner at (X, Y).
+    fn text(&mut self, mask: &TextMask, x: f32, y: f32, color: u32) {
+        let (x, y) = (x.round() as i64, y.round() as i64);
+        for my in 0..mask.height as i64 {
+            for mx in 0..mask.width as i64 {
+                let a = mask.alpha[(my * mask.width as i64 + mx) as usize];
+                if a > 0 {
+                    self.blend(x + mx, y + my, color, a as f32 / 255.0);
+                }
+            }
+        }
+    }
+}
+
+/// Distance from (PX, PY) to the segment from (AX, AY) to (BX, BY).
+fn segment_distance(px: f32, py: f32, ax: f32, ay: f32, bx: f32, by: f32) -> f32 {
+    let (dx, dy) = (bx - ax, by - ay);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((px - ax) * dx + (py - ay) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (px - ax - t * dx).hypot(py - ay - t * dy)
+}
+
+/// Split the points of VALUES at X positions X_AT into runs without
+/// missing values, mapping values to y with Y_AT.
+fn line_runs(
+    values: &[f64],
+    x_at: impl Fn(usize) -> f32,
+    y_at: impl Fn(f64) -> f32,
+) -> Vec<Vec<(f32, f32)>> {
+    let mut runs = vec![Vec::new()];
+    for (i, &v) in values.iter().enumerate() {
+        if v.is_finite() {
+            runs.last_mut().unwrap().push((x_at(i), y_at(v)));
+        } else if !runs.last().unwrap().is_empty() {
+            runs.push(Vec::new());
+        }
+    }
+    runs.retain(|run| !run.is_empty());
+    runs
+}
+
+/// Draw SPEC.  TEXT renders a string at a pixel size as a coverage mask.
+pub fn render_chart(
+    spec: &ChartSpec,
+    text: &mut dyn FnMut(&str, f32) -> Option<TextMask>,
+) -> ChartImage {
+    let width = spec.width.clamp(1, MAX_SIZE);
+    let height = spec.height.clamp(1, MAX_SIZE);
+    let mut canvas = Canvas::new(width, height, spec.bg);
+    let count = spec
+        .series
+        .iter()
+        .map(|s| s.values.len())
+        .max()
+        .unwrap_or(0)
+        .max(spec.labels.len());
+    let (lo, hi) = value_range(spec);
+    let mut hot_spots = Vec::with_capacity(count);
+    if count == 0 {
+        return ChartImage {
+            width,
+            height,
+            rgba: canvas.data,
+            hot_spots,
+        };
+    }
+
+    if spec.kind == ChartKind::Sparkline {
+        // Edge to edge, leaving room for the line and the last point
+        let inset = 2.0;
+        let (w, h) = (width as f32 - 2.0 * inset, height as f32 - 2.0 * inset);
+        let step = if count > 1 {
+            w / (count - 1) as f32
+        } else {
+            0.0
+        };
+        let x_at = |i: usize| {
+            if count > 1 {
+                inset + i as f32 * step
+            } else {
+                width as f32 / 2.0
+            }
+        };
+        let y_at = |v: f64| inset + ((hi - v) / (hi - lo)) as f32 * h;
+        for series in &spec.series {
+            for run in line_runs(&series.values, x_at, y_at) {
+                canvas.polyline(&run, 1.5, series.color);
+            }
+            if let Some(&last) = series.values.iter().rev().find(|v| v.is_finite()) {
+                let i = series
+                    .values
+                    .iter()
+                    .rposition(|v| v.is_finite())
+                    .unwrap_or(0);
+                canvas.polyline(&[(x_at(i), y_at(last))], 3.5, series.color);
+            }
+        }
+        for i in 0..count {
+            let x = x_at(i);
+            let x0 = if i == 0 { 0.0 } else { x - step / 2.0 };
+            let x1 = if i + 1 == count {
+                width as f32
+            } else {
+                x + step / 2.0
+            };
+            hot_spots.push(ChartHotSpot {
+                index: i,
+                x0: x0 as u32,
+                y0: 0,
+                x1: x1.ceil() as u32,
+                y1: height,
+            });
+        }
+        return ChartImage {
+            width,
+            height,
+            rgba: canvas.data,
+            hot_spots,
+        };
+    }
+
+    let font_size = spec.font_size.max(6.0);
+    let line_h = (font_size * 1.3).ceil();
+    let mut top = PAD;
+    if let Some(title) = spec.title.as_deref().filter(|t| !t.is_empty()) {
+        if let Some(mask) = text(title, font_size * 1.15) {
+            canvas.text(
+                &mask,
+                (width as f32 - mask.width as f32) / 2.0,
+                top,
+                spec.fg,
+            );
+            top += mask.height as f32 + 2.0;
+        }
+    }
+    if spec.series.len() > 1 {
+        // Legend: a swatch and the name of each series, centered
+        let masks: Vec<_> = spec
+            .series
+            .iter()
+            .map(|s| text(&s.name, font_size))
+            .collect();
+        let swatch = (font_size * 0.7).round();
+        let item_w =
+            |m: &Option<TextMask>| swatch + 4.0 + m.as_ref().map_or(0.0, |m| m.width as f32) + 12.0;
+        let total: f32 = masks.iter().map(item_w).sum();
+        let mut x = ((width as f32 - total) / 2.0).max(PAD);
+        for (series, mask) in spec.series.iter().zip(&masks) {
+            let sy = top + (line_h - swatch) / 2.0;
+            canvas.fill(x, sy, x + swatch, sy + swatch, series.color, 1.0);
+            if let Some(mask) = mask {
+                canvas.text(
+                    mask,
+                    x + swatch + 4.0,
+                    top + (line_h - mask.height as f32) / 2.0,
+                    spec.fg,
+                );
+            }
+            x += item_w(mask);
+        }
+        top += line_h;
+    }
+
+    // Value axis: round ticks, about one per two lines of text
+    let plot_h_guess = height as f32 - top - PAD - line_h;
+    let max_ticks = ((plot_h_guess / (line_h * 2.0)) as usize).clamp(2, 10);
+    let (lo, hi, step) = if spec.range.is_some() {
+        let (_, _, step) = nice_ticks(lo, hi, max_ticks);
+        (lo, hi, step)
+    } else {
+        nice_ticks(lo, hi, max_ticks)
+    };
+    let scale = axis_scale(lo.abs().max(hi.abs()));
+    let first = (lo / step).ceil() as i64;
+    let last = (hi / step).floor() as i64;
+    let ticks: Vec<(f64, Option<TextMask>)> = (first..=last)
+        .take(50)
+        .map(|k| {
+            let v = k as f64 * step;
+            (v, text(&format_tick(v, step, scale), font_size))
+        })
+        .collect();
+    let axis_w = ticks
+        .iter()
+        .filter_map(|(_, m)| m.as_ref())
+        .map(|m| m.width)
+        .max()
+        .unwrap_or(0) as f32;
+
+    let left = PAD + axis_w + 6.0;
+    let right = width as f32 - PAD - 2.0;
+    let bottom = height as f32 - PAD - if spec.labels.is_empty() { 0.0 } else { line_h };
+    let top = top + font_size / 2.0;
+    if right - left < 4.0 || bottom - top < 4.0 {
+        return ChartImage {
+            width,
+            height,
+            rgba: canvas.data,
+            hot_spots,
+        };
+    }
+    let y_at = |v: f64| bottom - ((v - lo) / (hi - lo)) as f32 * (bottom - top);
+    let slot = (right - left) / count as f32;
+    let x_at = |i: usize| left + (i as f32 + 0.5) * slot;
+
+    // Grid lines with their values, then the axes
+    for (v, mask) in &ticks {
+        let y = y_at(*v).round();
+        canvas.fill(left, y, right, y + 1.0, spec.fg, 0.15);
+        if let Some(mask) = mask {
+            canvas.text(
+                mask,
+                left - 6.0 - mask.width as f32,
+                y - mask.height as f32 / 2.0,
+                spec.fg,
+            );
+        }
+    }
+    canvas.fill(left - 1.0, top, left, bottom, spec.fg, 0.5);
+    let base = y_at(0.0_f64.clamp(lo, hi)).round();
+    canvas.fill(left, base, right, base + 1.0, spec.fg, 0.5);
+
+    // Labels under their position, every Nth where they would overlap
+    if !spec.labels.is_empty() {
+        let masks: Vec<_> = spec.labels.iter().map(|l| text(l, font_size)).collect();
+        let widest = masks.iter().flatten().map(|m| m.width).max().unwrap_or(0) as f32 + 8.0;
+        let every = ((widest / slot).ceil() as usize).max(1);
+        for (i, mask) in masks.iter().enumerate().step_by(every) {
+            if let Some(mask) = mask {
+                let x = (x_at(i) - mask.width as f32 / 2.0)
+                    .clamp(0.0, width as f32 - mask.width as f32);
+                canvas.text(mask, x, bottom + 3.0, spec.fg);
+            }
+        }
+    }
+
+    match spec.kind {
+        ChartKind::Bar => {
+            let group = slot * 0.8;
+            let bar_w = group / spec.series.len().max(1) as f32;
+            for (s, series) in spec.series.iter().enumerate() {
+                for (i, &v) in series.values.iter().enumerate() {
+                    if !v.is_finite() {
+                        continue;
+                    }
+                    let x = x_at(i) - group / 2.0 + s as f32 * bar_w;
+                    let (y0, y1) = (y_at(v.clamp(lo, hi)), base);
+                    let gap = if bar_w > 4.0 { 1.0 } else { 0.0 };
+                    canvas.fill(
+                        x + gap / 2.0,
+                        y0.min(y1),
+                        x + bar_w - gap / 2.0,
+                        y0.max(y1),
+                        series.color,
+                        1.0,
+                    );
+                }
+            }
+        }
+        _ => {
+            let width = if slot >= 6.0 { 2.0 } else { 1.5 };
+            for series in &spec.series {
+                for run in line_runs(&series.values, x_at, |v| y_at(v.clamp(lo, hi))) {
+                    canvas.polyline(&run, width, series.color);
+                    if slot >= 10.0 {
+                        for &p in &run {
+                            canvas.polyline(&[p], 5.0, series.color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for i in 0..count {
+        hot_spots.push(ChartHotSpot {
+            index: i,
+            x0: (left + i as f32 * slot) as u32,
+            y0: top as u32,
+            x1: (left + (i + 1) as f32 * slot).ceil() as u32,
+            y1: bottom.ceil() as u32,
+        });
+    }
+    ChartImage {
+        width,
+        height,
+        rgba: canvas.data,
+        hot_spots,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_text(_: &str, _: f32) -> Option<TextMask> {
+        None
+    }
+
+    fn spec(kind: ChartKind, values: Vec<f64>) -> ChartSpec {
+        ChartSpec {
+            kind,
+            width: 200,
+            height: 100,
+            series: vec![ChartSeries {
+                name: "a".into(),
+                values,
+                color: 0xff0000,
+            }],
+            labels: Vec::new(),
+            title: None,
+            fg: 0x000000,
+            bg: Some(0xffffff),
+            range: None,
+            font_size: 12.0,
+        }
+    }
+
+    fn pixel(img: &ChartImage, x: u32, y: u32) -> [u8; 4] {
+        let i = ((y * img.width + x) * 4) as usize;
+        img.rgba[i..i + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn ticks_are_round() {
+        assert_eq!(nice_ticks(0.0, 97.0, 5), (0.0, 100.0, 20.0));
+        assert_eq!(nice_ticks(-3.2, 4.1, 8), (-4.0, 5.0, 1.0));
+        assert_eq!(nice_ticks(0.013, 0.048, 4), (0.01, 0.05, 0.01));
+        let scale = axis_scale(25_000.0);
+        assert_eq!(format_tick(20_000.0, 5000.0, scale), "20k");
+        assert_eq!(format_tick(2.5, 0.5, axis_scale(3.0)), "2.5");
+        assert_eq!(format_tick(-0.0001, 0.5, axis_scale(3.0)), "0.0");
+        assert_eq!(format_tick(3e6, 1e6, axis_scale(5e6)), "3M");
+    }
+
+    #[test]
+    fn value_range_never_empty() {
+        let s = spec(ChartKind::Line, vec![5.0, 5.0]);
+        assert_eq!(value_range(&s), (4.0, 6.0));
+        let s = spec(ChartKind::Bar, vec![5.0, 7.0]);
+        assert_eq!(value_range(&s), (0.0, 7.0));
+        let s = spec(ChartKind::Line, vec![f64::NAN]);
+        assert_eq!(value_range(&s), (0.0, 1.0));
+    }
+
+    #[test]
+    fn sparkline_spans_the_image() {
+        let img = render_chart(
+            &spec(ChartKind::Sparkline, vec![0.0, 10.0, 0.0, 10.0]),
+            &mut no_text,
+        );
+        assert_eq!(img.hot_spots.len(), 4);
+        assert_eq!((img.hot_spots[0].x0, img.hot_spots[3].x1), (0, 200));
+        // The first point is at the bottom left, the last at the top right
+        assert_eq!(pixel(&img, 2, 97)[1], 0);
+        assert_eq!(pixel(&img, 197, 2)[1], 0);
+        // Between the lines stays background
+        assert_eq!(pixel(&img, 66, 50), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn bars_rise_from_zero_and_hot_spots_tile_the_plot() {
+        let mut s = spec(ChartKind::Bar, vec![10.0, f64::NAN, 5.0]);
+        s.labels = vec!["a".into(), "b".into(), "c".into()];
+        let img = render_chart(&s, &mut no_text);
+        assert_eq!(img.hot_spots.len(), 3);
+        for pair in img.hot_spots.windows(2) {
+            assert!(pair[0].x1 >= pair[1].x0 && pair[0].x0 < pair[1].x0);
+        }
+        let center = |h: &ChartHotSpot| (h.x0 + h.x1) / 2;
+        let bottom = img.hot_spots[0].y1 - 2;
+        assert_eq!(
+            pixel(&img, center(&img.hot_spots[0]), bottom),
+            [255, 0, 0, 255]
+        );
+        // The missing value has no bar; the half value's bar is lower
+        assert_ne!(
+            pixel(&img, center(&img.hot_spots[1]), bottom),
+            [255, 0, 0, 255]
+        );
+        let mid = (img.hot_spots[2].y0 + img.hot_spots[2].y1) / 2 - 8;
+        assert_eq!(
+            pixel(&img, center(&img.hot_spots[0]), mid),
+            [255, 0, 0, 255]
+        );
+        assert_ne!(
+            pixel(&img, center(&img.hot_spots[2]), mid),
+            [255, 0, 0, 255]
+        );
+    }
+
+    #[test]
+    fn transparent_background_and_labels_use_text_masks() {
+        let mut s = spec(ChartKind::Line, vec![1.0, 2.0, 3.0]);
+        s.bg = None;
+        s.title = Some("T".into());
+        let mut asked = Vec::new();
+        let img = render_chart(&s, &mut |t: &str, size: f32| {
+            asked.push(t.to_string());
+            Some(TextMask {
+                width: 4,
+                height: size as u32,
+                alpha: vec![255; 4 * size as usize],
+            })
+        });
+        assert_eq!(pixel(&img, 199, 99)[3], 0);
+        assert!(asked.contains(&"T".to_string()));
+        assert!(asked.iter().any(|t| t == "3.0" || t == "3"));
+        // The title is drawn centered at the top
+        assert_eq!(pixel(&img, 100, PAD as u32 + 1), [0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,1013 @@
+//! Encrypted files: age and OpenPGP, without an external gpg.
+//!
+//! Files named `*.age` or `*.gpg` are decrypted when visited and
+//! encrypted again when saved, like EasyPG's `epa-file` but with the
+//! ciphers in process (the `age` and `pgp` crates, `crypto` feature).
+//! Work runs on a thread per job so a large file or a slow scrypt never
+//! blocks Emacs; Lisp starts a job and polls it.
+//!
+//! Passphrases are asked for in the minibuffer: a job that needs one
+//! fails with `CryptoError::NeedPassphrase` and is started again with
+//! what the user typed.  Passphrases that worked are kept in a `KeyCache`
+//! for a while, like gpg-agent's cache, under the file they decrypted
+//! or the secret key file they unlocked, so one passphrase for a key
+//! serves every file encrypted to it (a password store, say).
+//!
+//! Secret keys are files: age identity files, and OpenPGP secret keys
+//! exported with `gpg --export-secret-keys`.  OpenPGP recipients are
+//! public key files.  Output is binary, or ASCII-armored for text in
+//! buffers (org-crypt entries).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+/// How long passphrases are cached by default, as gpg-agent does
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(600);
+
+/// First line of a binary age file
+const AGE_MAGIC: &[u8] = b"age-encryption.org/v1\n";
+const AGE_ARMOR_BEGIN: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+const PGP_ARMOR_BEGIN: &str = "-----BEGIN PGP MESSAGE-----";
+const PGP_ARMOR_END: &str = "-----END PGP MESSAGE-----";
+
+/// Encrypted file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CryptoFormat {
+    Age,
+    OpenPgp,
+}
+
+impl CryptoFormat {
+    /// The format files named PATH are written in.
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_ascii_lowercase();
+        match ext.as_str() {
+            "age" => Some(CryptoFormat::Age),
+            "gpg" | "pgp" | "asc" => Some(CryptoFormat::OpenPgp),
+            _ => None,
+        }
+    }
+
+    /// The format of encrypted DATA, binary or armored.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        let text = trim_leading_space(data);
+        if text.starts_with(AGE_MAGIC) || text.starts_with(AGE_ARMOR_BEGIN.as_bytes()) {
+            Some(CryptoFormat::Age)
+        } else if text.starts_with(PGP_ARMOR_BEGIN.as_bytes())
+            || matches!(first_packet_tag(data), Some(PKESK_TAG | SKESK_TAG))
+        {
+            Some(CryptoFormat::OpenPgp)
+        } else {
+            None
+        }
+    }
+}
+
+fn trim_leading_space(data: &[u8]) -> &[u8] {
+    let start = data.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(data.len());
+    &data[start..]
+}
+
+/// Errors of encryption and decryption
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CryptoError {
+    #[error("A passphrase is needed")]
+    NeedPassphrase,
+
+    #[error("Bad passphrase")]
+    BadPassphrase,
+
+    #[error("No secret key can decrypt this")]
+    NoKey,
+
+    #[error("Not encrypted with age or OpenPGP")]
+    NotEncrypted,
+
+    #[error("No recipients to encrypt to")]
+    NoRecipients,
+
+    #[error("{0}")]
+    Failed(String),
+
+    #[error("Built without encryption support")]
+    Unsupported,
+}
+
+impl From<std::io::Error> for CryptoError {
+    fn from(e: std::io::Error) -> Self {
+        CryptoError::Failed(e.to_string())
+    }
+}
+
+// ============================================================================
+// ASCII armor
+// ============================================================================
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64, padded.
+pub fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (chunk.get(1).copied().unwrap_or(0) as u32) << 8
+            | chunk.get(2).copied().unwrap_or(0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode standard base64, ignoring whitespace; None if malformed.
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    let mut padding = false;
+    for byte in text.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => {
+                padding = true;
+                continue;
+            }
+            _ if byte.is_ascii_whitespace() => continue,
+            _ => return None,
+        };
+        if padding {
+            return None;
+        }
+        acc = acc << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// The OpenPGP armor checksum (RFC 4880, 6.1).
+pub fn crc24(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xB704CE;
+    for &byte in data {
+        crc ^= (byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= 0x1864CFB;
+            }
+        }
+    }
+    crc & 0xFFFFFF
+}
+
+/// A binary OpenPGP message as ASCII armor.
+pub fn armor(data: &[u8]) -> String {
+    let body = base64_encode(data);
+    let mut out = String::with_capacity(body.len() + body.len() / 64 + 80);
+    out.push_str(PGP_ARMOR_BEGIN);
+    out.push_str("\n\n");
+    for line in body.as_bytes().chunks(64) {
+        out.push_str(std::str::from_utf8(line).unwrap_or_default());
+        out.push('\n');
+    }
+    let crc = crc24(data);
+    out.push('=');
+    out.push_str(&base64_encode(&[(crc >> 16) as u8, (crc >> 8) as u8, crc as u8]));
+    out.push('\n');
+    out.push_str(PGP_ARMOR_END);
+    out.push('\n');
+    out
+}
+
+/// The binary message in armored TEXT, checked against its checksum.
+pub fn dearmor(text: &str) -> Result<Vec<u8>, CryptoError> {
+    let malformed = || CryptoError::Failed("Malformed ASCII armor".into());
+    let mut lines = text.lines().map(str::trim_end).skip_while(|l| l.trim() != PGP_ARMOR_BEGIN);
+    if lines.next().is_none() {
+        return Err(CryptoError::NotEncrypted);
+    }
+    // Armor headers run up to a blank line, which some writers omit
+    let mut body = String::new();
+    let mut checksum = None;
+    let mut in_headers = true;
+    for line in lines {
+        if line.starts_with(PGP_ARMOR_END) {
+            let data = base64_decode(&body).ok_or_else(malformed)?;
+            return match checksum {
+                Some(sum) if sum != crc24(&data) => {
+                    Err(CryptoError::Failed("ASCII armor checksum mismatch".into()))
+                }
+                _ => Ok(data),
+            };
+        }
+        if in_headers {
+            if line.is_empty() || !line.contains(": ") {
+                in_headers = false;
+            }
+            if line.is_empty() || line.contains(": ") {
+                continue;
+            }
+        }
+        if let Some(sum) = line.strip_prefix('=') {
+            let bytes = base64_decode(sum).filter(|b| b.len() == 3).ok_or_else(malformed)?;
+            checksum = Some((bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32);
+        } else {
+            body.push_str(line);
+        }
+    }
+    Err(malformed())
+}
+
+/// Public-key encrypted session key packet
+const PKESK_TAG: u8 = 1;
+/// Passphrase (symmetric-key) encrypted session key packet
+const SKESK_TAG: u8 = 3;
+
+/// Tag of the first OpenPGP packet of binary DATA, old or new format.
+fn first_packet_tag(data: &[u8]) -> Option<u8> {
+    let byte = *data.first()?;
+    if byte & 0x80 == 0 {
+        None
+    } else if byte & 0x40 != 0 {
+        Some(byte & 0x3f)
+    } else {
+        Some((byte >> 2) & 0x0f)
+    }
+}
+
+/// The binary form of an OpenPGP message, armored or not.
+fn openpgp_binary(data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if trim_leading_space(data).starts_with(PGP_ARMOR_BEGIN.as_bytes()) {
+        dearmor(&String::from_utf8_lossy(data))
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
+// ============================================================================
+// Passphrase cache
+// ============================================================================
+
+/// What a cached passphrase unlocks
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CacheKey {
+    /// A file encrypted with a passphrase, or text from it
+    File(PathBuf),
+    /// A secret key file
+    Key(PathBuf),
+}
+
+/// Overwrite a secret before freeing it.
+fn wipe(secret: String) {
+    let mut bytes = secret.into_bytes();
+    for byte in bytes.iter_mut() {
+        // Volatile so the stores are not optimized away
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+}
+
+/// A passphrase that is wiped however the scope holding it is left.
+struct Passphrase(String);
+
+impl Passphrase {
+    fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Drop for Passphrase {
+    fn drop(&mut self) {
+        wipe(std::mem::take(&mut self.0));
+    }
+}
+
+/// Passphrases that worked, forgotten after a while.
+pub struct KeyCache {
+    ttl: Duration,
+    entries: HashMap<CacheKey, (String, Instant)>,
+}
+
+impl KeyCache {
+    pub fn new(ttl: Duration) -> Self {
+        KeyCache { ttl, entries: HashMap::new() }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Keep passphrases for TTL from now on; zero disables the cache.
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl;
+        if ttl.is_zero() {
+            self.clear();
+        }
+    }
+
+    pub fn insert_at(&mut self, key: CacheKey, passphrase: &str, now: Instant) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        if let Some((old, _)) = self.entries.insert(key, (passphrase.to_string(), now + self.ttl)) {
+            wipe(old);
+        }
+    }
+
+    pub fn insert(&mut self, key: CacheKey, passphrase: &str) {
+        self.insert_at(key, passphrase, Instant::now());
+    }
+
+    /// The passphrase for KEY, if cached and not expired at NOW.
+    pub fn get_at(&mut self, key: &CacheKey, now: Instant) -> Option<String> {
+        self.purge_at(now);
+        self.entries.get(key).map(|(passphrase, _)| passphrase.clone())
+    }
+
+    pub fn get(&mut self, key: &CacheKey) -> Option<String> {
+        self.get_at(key, Instant::now())
+    }
+
+    /// Forget passphrases that expired by NOW.
+    pub fn purge_at(&mut self, now: Instant) {
+        let expired: Vec<CacheKey> = self.entries.iter()
+            .filter(|(_, (_, until))| *until <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in expired {
+            if let Some((passphrase, _)) = self.entries.remove(&key) {
+                wipe(passphrase);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &CacheKey) {
+        if let Some((passphrase, _)) = self.entries.remove(key) {
+            wipe(passphrase);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for (_, (passphrase, _)) in self.entries.drain() {
+            wipe(passphrase);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Drop for KeyCache {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+// ============================================================================
+// Ciphers
+// ============================================================================
+
+/// Secret key files used for decryption
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Keyring {
+    /// age identity files (`age-keygen` output)
+    pub age_identities: Vec<PathBuf>,
+    /// Exported OpenPGP secret keys, binary or armored
+    pub openpgp_secret_keys: Vec<PathBuf>,
+}
+
+/// Who can decrypt what is encrypted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recipients {
+    /// Anyone with the passphrase
+    Passphrase(String),
+    /// age recipients (`age1...`), or OpenPGP public key files
+    Keys(Vec<String>),
+}
+
+impl Drop for Recipients {
+    fn drop(&mut self) {
+        if let Recipients::Passphrase(passphrase) = self {
+            wipe(std::mem::take(passphrase));
+        }
+    }
+}
+
+/// What unlocked a decrypted message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Unlocked {
+    /// The passphrase it was encrypted with
+    Passphrase,
+    /// The secret key in this file, with the passphrase if one was used
+    Key(PathBuf),
+}
+
+/// Decrypt DATA, age or OpenPGP, with the keys in KEYRING or with
+/// PASSPHRASE, which also unlocks protected secret keys.
+pub fn decrypt(
+    data: &[u8],
+    keyring: &Keyring,
+    passphrase: Option<&str>,
+) -> Result<(Vec<u8>, Unlocked), CryptoError> {
+    match CryptoFormat::detect(data) {
+        Some(CryptoFormat::Age) => backend::age_decrypt(data, keyring, passphrase),
+        Some(CryptoFormat::OpenPgp) => {
+            let binary = openpgp_binary(data)?;
+            let symmetric = first_packet_tag(&binary) == Some(SKESK_TAG);
+            if symmetric && passphrase.is_none() {
+                return Err(CryptoError::NeedPassphrase);
+            }
+            backend::openpgp_decrypt(&binary, symmetric, keyring, passphrase)
+        }
+        None => Err(CryptoError::NotEncrypted),
+    }
+}
+
+/// Encrypt PLAINTEXT in FORMAT for RECIPIENTS; ARMOR gives OpenPGP
+/// text instead of binary.
+pub fn encrypt(
+    format: CryptoFormat,
+    plaintext: &[u8],
+    recipients: &Recipients,
+    armor_output: bool,
+) -> Result<Vec<u8>, CryptoError> {
+    if matches!(recipients, Recipients::Keys(keys) if keys.is_empty()) {
+        return Err(CryptoError::NoRecipients);
+    }
+    match format {
+        CryptoFormat::Age => backend::age_encrypt(plaintext, recipients),
+        CryptoFormat::OpenPgp => {
+            let binary = backend::openpgp_encrypt(plaintext, recipients)?;
+            Ok(if armor_output { armor(&binary).into_bytes() } else { binary })
+        }
+    }
+}
+
+#[cfg(feature = "crypto")]
+mod backend {
+    use std::io::{Read, Write};
+    use std::path::Path;
+
+    use age::secrecy::Secret;
+    use pgp::crypto::sym::SymmetricKeyAlgorithm;
+    use pgp::ser::Serialize;
+    use pgp::types::{KeyTrait, StringToKey};
+    use pgp::{Deserializable, Message, SignedPublicKey, SignedSecretKey};
+
+    use super::{CryptoError, Keyring, Recipients, Unlocked};
+
+    fn failed(e: impl std::fmt::Display) -> CryptoError {
+        CryptoError::Failed(e.to_string())
+    }
+
+    pub fn age_decrypt(
+        data: &[u8],
+        keyring: &Keyring,
+        passphrase: Option<&str>,
+    ) -> Result<(Vec<u8>, Unlocked), CryptoError> {
+        let decryptor = || age::Decryptor::new(age::armor::ArmoredReader::new(data)).map_err(failed);
+        let mut plaintext = Vec::new();
+        if let age::Decryptor::Passphrase(decryptor) = decryptor()? {
+            let passphrase = passphrase.ok_or(CryptoError::NeedPassphrase)?;
+            let mut reader = decryptor
+                .decrypt(&Secret::new(passphrase.to_string()), None)
+                .map_err(|e| match e {
+                    age::DecryptError::DecryptionFailed
+                    | age::DecryptError::KeyDecryptionFailed => CryptoError::BadPassphrase,
+                    e => failed(e),
+                })?;
+            reader.read_to_end(&mut plaintext)?;
+            return Ok((plaintext, Unlocked::Passphrase));
+        }
+
+        // Identity files are tried one at a time, to know which matched
+        for path in &keyring.age_identities {
+            let identities = age::IdentityFile::from_file(path.to_string_lossy().into_owned())
+                .map_err(failed)?
+                .into_identities()
+                .map_err(failed)?;
+            let age::Decryptor::Recipients(decryptor) = decryptor()? else {
+                break;
+            };
+            match decryptor.decrypt(identities.iter().map(|i| i.as_ref() as &dyn age::Identity)) {
+                Ok(mut reader) => {
+                    reader.read_to_end(&mut plaintext)?;
+                    return Ok((plaintext, Unlocked::Key(path.clone())));
+                }
+                Err(age::DecryptError::NoMatchingKeys) => continue,
+                Err(e) => return Err(failed(e)),
+            }
+        }
+        Err(CryptoError::NoKey)
+    }
+
+    pub fn age_encrypt(plaintext: &[u8], recipients: &Recipients) -> Result<Vec<u8>, CryptoError> {
+        let encryptor = match recipients {
+            Recipients::Passphrase(passphrase) => {
+                age::Encryptor::with_user_passphrase(Secret::new(passphrase.clone()))
+            }
+            Recipients::Keys(keys) => {
+                let mut parsed: Vec<Box<dyn age::Recipient + Send>> = Vec::new();
+                for key in keys {
+                    let recipient: age::x25519::Recipient = key.trim().parse().map_err(failed)?;
+                    parsed.push(Box::new(recipient));
+                }
+                age::Encryptor::with_recipients(parsed).ok_or(CryptoError::NoRecipients)?
+            }
+        };
+        let mut out = Vec::new();
+        let mut writer = encryptor.wrap_output(&mut out)?;
+        writer.write_all(plaintext)?;
+        writer.finish()?;
+        Ok(out)
+    }
+
+    fn read_key_file(path: &Path) -> Result<Vec<u8>, CryptoError> {
+        std::fs::read(path).map_err(|e| failed(format!("{}: {}", path.display(), e)))
+    }
+
+    fn is_armored(data: &[u8]) -> bool {
+        super::trim_leading_space(data).starts_with(b"-----BEGIN")
+    }
+
+    fn secret_key(path: &Path) -> Result<SignedSecretKey, CryptoError> {
+        let data = read_key_file(path)?;
+        if is_armored(&data) {
+            SignedSecretKey::from_armor_single(&data[..]).map(|(key, _)| key).map_err(failed)
+        } else {
+            SignedSecretKey::from_bytes(&data[..]).map_err(failed)
+        }
+    }
+
+    fn public_key(path: &Path) -> Result<SignedPublicKey, CryptoError> {
+        let data = read_key_file(path)?;
+        if is_armored(&data) {
+            SignedPublicKey::from_armor_single(&data[..]).map(|(key, _)| key).map_err(failed)
+        } else {
+            SignedPublicKey::from_bytes(&data[..]).map_err(failed)
+        }
+    }
+
+    fn content(message: Message) -> Result<Vec<u8>, CryptoError> {
+        message.decompress().map_err(failed)?
+            .get_content().map_err(failed)?
+            .ok_or_else(|| failed("Encrypted message has no literal data"))
+    }
+
+    pub fn openpgp_decrypt(
+        binary: &[u8],
+        symmetric: bool,
+        keyring: &Keyring,
+        passphrase: Option<&str>,
+    ) -> Result<(Vec<u8>, Unlocked), CryptoError> {
+        let message = Message::from_bytes(binary).map_err(failed)?;
+        if symmetric {
+            let passphrase = passphrase.unwrap_or_default().to_string();
+            let decrypted = message
+                .decrypt_with_password(|| passphrase)
+                .map_err(|_| CryptoError::BadPassphrase)?;
+            return Ok((content(decrypted)?, Unlocked::Passphrase));
+        }
+
+        // Keys without a passphrase open with an empty one
+        let mut locked = false;
+        for path in &keyring.openpgp_secret_keys {
+            let key = secret_key(path)?;
+            let key_passphrase = passphrase.unwrap_or_default().to_string();
+            match message.decrypt(|| key_passphrase, &[&key]) {
+                Ok((decrypted, _)) => return Ok((content(decrypted)?, Unlocked::Key(path.clone()))),
+                Err(e) => {
+                    log::debug!("crypto: {} does not decrypt: {}", path.display(), e);
+                    locked = true;
+                }
+            }
+        }
+        match (locked, passphrase) {
+            (true, None) => Err(CryptoError::NeedPassphrase),
+            (true, Some(_)) => Err(CryptoError::BadPassphrase),
+            (false, _) => Err(CryptoError::NoKey),
+        }
+    }
+
+    pub fn openpgp_encrypt(plaintext: &[u8], recipients: &Recipients) -> Result<Vec<u8>, CryptoError> {
+        let mut rng = rand::thread_rng();
+        let message = Message::new_literal_bytes("", plaintext);
+        let encrypted = match recipients {
+            Recipients::Passphrase(passphrase) => {
+                let passphrase = passphrase.clone();
+                message.encrypt_with_password(
+                    &mut rng,
+                    StringToKey::new_default(&mut rng),
+                    SymmetricKeyAlgorithm::AES256,
+                    || passphrase,
+                )
+            }
+            Recipients::Keys(files) => {
+                let keys = files.iter()
+                    .map(|file| public_key(Path::new(file)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                // Messages go to the encryption subkeys
+                let subkeys: Vec<_> = keys.iter()
+                    .filter_map(|key| key.public_subkeys.iter().find(|sub| sub.is_encryption_key()))
+                    .collect();
+                if subkeys.len() < keys.len() {
+                    return Err(failed("A recipient key has no encryption subkey"));
+                }
+                message.encrypt_to_keys(&mut rng, SymmetricKeyAlgorithm::AES256, &subkeys)
+            }
+        }
+        .map_err(failed)?;
+        encrypted.to_bytes().map_err(failed)
+    }
+}
+
+#[cfg(not(feature = "crypto"))]
+mod backend {
+    use super::{CryptoError, Keyring, Recipients, Unlocked};
+
+    pub fn age_decrypt(
+        _data: &[u8],
+        _keyring: &Keyring,
+        _passphrase: Option<&str>,
+    ) -> Result<(Vec<u8>, Unlocked), CryptoError> {
+        Err(CryptoError::Unsupported)
+    }
+
+    pub fn age_encrypt(_plaintext: &[u8], _recipients: &Recipients) -> Result<Vec<u8>, CryptoError> {
+        Err(CryptoError::Unsupported)
+    }
+
+    pub fn openpgp_decrypt(
+        _binary: &[u8],
+        _symmetric: bool,
+        _keyring: &Keyring,
+        _passphrase: Option<&str>,
+    ) -> Result<(Vec<u8>, Unlocked), CryptoError> {
+        Err(CryptoError::Unsupported)
+    }
+
+    pub fn openpgp_encrypt(_plaintext: &[u8], _recipients: &Recipients) -> Result<Vec<u8>, CryptoError> {
+        Err(CryptoError::Unsupported)
+    }
+}
+
+/// Whether this build can encrypt and decrypt.
+pub fn available() -> bool {
+    cfg!(feature = "crypto")
+}
+
+// ============================================================================
+// Jobs
+// ============================================================================
+
+/// State of a job
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobState {
+    Running,
+    /// Plaintext or ciphertext; empty when the job wrote a file
+    Done(Vec<u8>),
+    Failed(CryptoError),
+}
+
+/// Jobs running on their own threads, with results kept until taken.
+#[derive(Default)]
+pub struct CryptoJobs {
+    next_id: AtomicU64,
+    jobs: Arc<Mutex<HashMap<u64, JobState>>>,
+}
+
+impl CryptoJobs {
+    pub fn new() -> Self {
+        CryptoJobs::default()
+    }
+
+    /// Run WORK on a thread; returns the job's id, never 0.
+    pub fn start<F>(&self, work: F) -> u64
+    where
+        F: FnOnce() -> Result<Vec<u8>, CryptoError> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.jobs.lock().unwrap().insert(id, JobState::Running);
+        let jobs = Arc::clone(&self.jobs);
+        let spawned = std::thread::Builder::new()
+            .name("crypto".into())
+            .spawn(move || {
+                let _span = crate::logging::span("crypto");
+                let state = match work() {
+                    Ok(data) => JobState::Done(data),
+                    Err(e) => JobState::Failed(e),
+                };
+                jobs.lock().unwrap().insert(id, state);
+            });
+        if let Err(e) = spawned {
+            log::warn!("crypto: cannot start job thread: {}", e);
+            self.jobs.lock().unwrap().insert(id, JobState::Failed(CryptoError::Failed(e.to_string())));
+        }
+        id
+    }
+
+    /// Whether job ID is still running; None if there is no such job.
+    pub fn is_running(&self, id: u64) -> Option<bool> {
+        self.jobs.lock().unwrap().get(&id).map(|state| *state == JobState::Running)
+    }
+
+    /// The result of job ID once it finished, forgetting the job.
+    pub fn take(&self, id: u64) -> Option<JobState> {
+        let mut jobs = self.jobs.lock().unwrap();
+        match jobs.get(&id) {
+            Some(JobState::Running) => Some(JobState::Running),
+            Some(_) => jobs.remove(&id),
+            None => None,
+        }
+    }
+
+    /// Number of jobs running.
+    pub fn running_count(&self) -> usize {
+        self.jobs.lock().unwrap().values().filter(|s| **s == JobState::Running).count()
+    }
+}
+
+// ============================================================================
+// Service
+// ============================================================================
+
+/// Decryption and encryption jobs sharing a keyring and a passphrase
+/// cache.
+pub struct Crypto {
+    pub keyring: Arc<Mutex<Keyring>>,
+    pub cache: Arc<Mutex<KeyCache>>,
+    pub jobs: CryptoJobs,
+}
+
+impl Default for Crypto {
+    fn default() -> Self {
+        Crypto::new()
+    }
+}
+
+impl Crypto {
+    pub fn new() -> Self {
+        Crypto {
+            keyring: Arc::new(Mutex::new(Keyring::default())),
+            cache: Arc::new(Mutex::new(KeyCache::new(DEFAULT_CACHE_TTL))),
+            jobs: CryptoJobs::new(),
+        }
+    }
+
+    /// Decrypt the file at PATH in the background.
+    pub fn decrypt_file(&self, path: PathBuf, passphrase: Option<String>) -> u64 {
+        let keyring = self.keyring.lock().unwrap().clone();
+        let cache = Arc::clone(&self.cache);
+        self.jobs.start(move || {
+            let data = std::fs::read(&path)
+                .map_err(|e| CryptoError::Failed(format!("{}: {}", path.display(), e)))?;
+            decrypt_cached(&data, &path, &keyring, &cache, passphrase)
+        })
+    }
+
+    /// Decrypt DATA in the background.  CONTEXT, the file it comes from,
+    /// is what a passphrase for it is cached under.
+    pub fn decrypt_data(&self, data: Vec<u8>, context: PathBuf, passphrase: Option<String>) -> u64 {
+        let keyring = self.keyring.lock().unwrap().clone();
+        let cache = Arc::clone(&self.cache);
+        self.jobs.start(move || decrypt_cached(&data, &context, &keyring, &cache, passphrase))
+    }
+
+    /// Encrypt PLAINTEXT in FORMAT in the background, to KEYS or, if
+    /// there are none, with PASSPHRASE or the one cached for CONTEXT.
+    /// With OUTPUT the ciphertext replaces that file and the job's
+    /// result is empty.
+    #[allow(clippy::too_many_arguments)]
+    pub fn encrypt(
+        &self,
+        format: CryptoFormat,
+        plaintext: Vec<u8>,
+        keys: Vec<String>,
+        passphrase: Option<String>,
+        context: PathBuf,
+        output: Option<PathBuf>,
+        armor_output: bool,
+    ) -> u64 {
+        let cache = Arc::clone(&self.cache);
+        self.jobs.start(move || {
+            // RECIPIENTS wipes the passphrase when dropped, on every way
+            // out of the job
+            let recipients = if !keys.is_empty() {
+                Recipients::Keys(keys)
+            } else {
+                match passphrase.or_else(|| cache.lock().unwrap().get(&CacheKey::File(context.clone()))) {
+                    Some(passphrase) => Recipients::Passphrase(passphrase),
+                    None => return Err(CryptoError::NeedPassphrase),
+                }
+            };
+            let ciphertext = encrypt(format, &plaintext, &recipients, armor_output)?;
+            if let Some(path) = &output {
+                write_atomically(path, &ciphertext)?;
+            }
+            // Cache the passphrase only once the file is encrypted with it
+            if let Recipients::Passphrase(passphrase) = &recipients {
+                cache.lock().unwrap().insert(CacheKey::File(context), passphrase);
+            }
+            Ok(if output.is_some() { Vec::new() } else { ciphertext })
+        })
+    }
+}
+
+/// Decrypt DATA from CONTEXT with PASSPHRASE, which is cached under
+/// what it unlocked, or else with the passphrases cached for CONTEXT
+/// and for the secret keys.
+fn decrypt_cached(
+    data: &[u8],
+    context: &Path,
+    keyring: &Keyring,
+    cache: &Mutex<KeyCache>,
+    passphrase: Option<String>,
+) -> Result<Vec<u8>, CryptoError> {
+    if let Some(passphrase) = passphrase.map(Passphrase) {
+        let (plaintext, unlocked) = decrypt(data, keyring, Some(passphrase.as_str()))?;
+        let key = match unlocked {
+            Unlocked::Passphrase => CacheKey::File(context.to_path_buf()),
+            Unlocked::Key(path) => CacheKey::Key(path),
+        };
+        cache.lock().unwrap().insert(key, passphrase.as_str());
+        return Ok(plaintext);
+    }
+
+    let result = decrypt(data, keyring, None);
+    if result != Err(CryptoError::NeedPassphrase) {
+        return result.map(|(plaintext, _)| plaintext);
+    }
+    let candidates: Vec<Passphrase> = {
+        let mut cache = cache.lock().unwrap();
+        std::iter::once(CacheKey::File(context.to_path_buf()))
+            .chain(keyring.openpgp_secret_keys.iter().cloned().map(CacheKey::Key))
+            .filter_map(|key| cache.get(&key))
+            .map(Passphrase)
+            .collect()
+    };
+    for candidate in &candidates {
+        if let Ok((plaintext, _)) = decrypt(data, keyring, Some(candidate.as_str())) {
+            return Ok(plaintext);
+        }
+    }
+    Err(CryptoError::NeedPassphrase)
+}
+
+/// Replace PATH with DATA through a temporary file in the same
+/// directory, keeping the file's permissions, or making it private.
+fn write_atomically(path: &Path, data: &[u8]) -> Result<(), CryptoError> {
+    use std::io::Write;
+
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let tmp = dir.join(format!(".#{}.{}.tmp", name, std::process::id()));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let mode = std::fs::metadata(path).map(|m| m.permissions().mode() & 0o7777).unwrap_or(0o600);
+        options.mode(mode);
+    }
+    // Elsewhere the new file gets the default permissions
+    let mut file = options.open(&tmp)?;
+    let written = file.write_all(data).and_then(|_| file.sync_all());
+    drop(file);
+    if let Err(e) = written.and_then(|_| std::fs::rename(&tmp, path)) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn armor_round_trips_with_its_checksum() {
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_decode("Zm9v\nYmE=").as_deref(), Some(&b"fooba"[..]));
+        assert_eq!(base64_decode("Zm9v*"), None);
+        assert_eq!(crc24(b""), 0xB704CE);
+
+        let data: Vec<u8> = (0..=255).collect();
+        let text = armor(&data);
+        assert!(text.starts_with("-----BEGIN PGP MESSAGE-----\n\n"));
+        assert!(text.lines().all(|line| line.len() <= 64));
+        assert_eq!(dearmor(&text), Ok(data.clone()));
+        // Armor headers are skipped, and a damaged body is caught
+        let with_header = text.replacen("\n\n", "\nComment: test\n\n", 1);
+        assert_eq!(dearmor(&with_header), Ok(data));
+        let damaged = text.replacen("AAEC", "AAED", 1);
+        assert!(matches!(dearmor(&damaged), Err(CryptoError::Failed(_))));
+        assert_eq!(dearmor("plain text"), Err(CryptoError::NotEncrypted));
+    }
+
+    #[test]
+    fn formats_are_recognized() {
+        assert_eq!(CryptoFormat::for_path(Path::new("/x/notes.org.age")), Some(CryptoFormat::Age));
+        assert_eq!(CryptoFormat::for_path(Path::new("pass/mail.GPG")), Some(CryptoFormat::OpenPgp));
+        assert_eq!(CryptoFormat::for_path(Path::new("notes.org")), None);
+
+        assert_eq!(CryptoFormat::detect(b"age-encryption.org/v1\n-> X25519"), Some(CryptoFormat::Age));
+        assert_eq!(
+            CryptoFormat::detect(b"\n-----BEGIN AGE ENCRYPTED FILE-----\n"),
+            Some(CryptoFormat::Age),
+        );
+        assert_eq!(CryptoFormat::detect(armor(&[0xc3, 0x0d]).as_bytes()), Some(CryptoFormat::OpenPgp));
+        // Session key packets, new and old format
+        assert_eq!(CryptoFormat::detect(&[0xc3, 0x0d, 0x04]), Some(CryptoFormat::OpenPgp));
+        assert_eq!(CryptoFormat::detect(&[0x85, 0x01, 0x0c]), Some(CryptoFormat::OpenPgp));
+        assert_eq!(CryptoFormat::detect(b"hello"), None);
+        assert_eq!(decrypt(b"hello", &Keyring::default(), None), Err(CryptoError::NotEncrypted));
+        // Passphrase-encrypted OpenPGP needs one before any cipher runs
+        assert_eq!(
+            decrypt(&[0xc3, 0x0d, 0x04], &Keyring::default(), None),
+            Err(CryptoError::NeedPassphrase),
+        );
+    }
+
+    #[test]
+    fn cached_passphrases_expire() {
+        let start = Instant::now();
+        let mut cache = KeyCache::new(Duration::from_secs(60));
+        let file = CacheKey::File("/tmp/a.gpg".into());
+        let key = CacheKey::Key("/keys/me.asc".into());
+        cache.insert_at(file.clone(), "one", start);
+        cache.insert_at(key.clone(), "two", start + Duration::from_secs(30));
+        assert_eq!(cache.get_at(&file, start + Duration::from_secs(59)).as_deref(), Some("one"));
+        assert_eq!(cache.get_at(&file, start + Duration::from_secs(60)), None);
+        assert_eq!(cache.get_at(&key, start + Duration::from_secs(60)).as_deref(), Some("two"));
+        assert_eq!(cache.len(), 1);
+
+        // A zero timeout forgets everything and caches nothing
+        cache.set_ttl(Duration::ZERO);
+        assert!(cache.is_empty());
+        cache.insert_at(file.clone(), "one", start);
+        assert_eq!(cache.get_at(&file, start), None);
+    }
+
+    #[test]
+    fn jobs_report_results_once() {
+        let jobs = CryptoJobs::new();
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let slow = jobs.start(move || {
+            rx.recv().ok();
+            Ok(b"plain".to_vec())
+        });
+        let failing = jobs.start(|| Err(CryptoError::BadPassphrase));
+        assert_ne!(slow, failing);
+        assert_eq!(jobs.take(slow), Some(JobState::Running));
+        assert_eq!(jobs.is_running(slow), Some(true));
+
+        tx.send(()).unwrap();
+        while jobs.running_count() > 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(jobs.take(slow), Some(JobState::Done(b"plain".to_vec())));
+        assert_eq!(jobs.take(slow), None);
+        assert_eq!(jobs.take(failing), Some(JobState::Failed(CryptoError::BadPassphrase)));
+    }
+
+    #[test]
+    fn failed_encryption_does_not_cache_the_passphrase() {
+        let crypto = Crypto::new();
+        let context = PathBuf::from("/nonexistent/dir/notes.age");
+        let job = crypto.encrypt(
+            CryptoFormat::Age,
+            b"secret".to_vec(),
+            Vec::new(),
+            Some("hunter2".into()),
+            context.clone(),
+            Some(context.clone()),
+            false,
+        );
+        while crypto.jobs.running_count() > 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(matches!(crypto.jobs.take(job), Some(JobState::Failed(_))));
+        assert_eq!(crypto.cache.lock().unwrap().get(&CacheKey::File(context)), None);
+    }
+}
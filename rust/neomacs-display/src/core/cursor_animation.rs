@@ -214,15 +214,10 @@ pub struct CursorAnimator {
     animating: bool,
 }
 
-impl Default for CursorAnimator {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
 impl CursorAnimator {
-    pub fn new() -> Self {
-        let now = Instant::now();
+    /// Create an animator whose clock starts at `now` (normally the render
+    /// thread's `AnimationClock::now()`).
+    pub fn new(now: Instant) -> Self {
         Self {
             mode: CursorAnimationMode::Smooth,
             target_x: 0.0,
@@ -280,9 +275,8 @@ impl CursorAnimator {
         
         // Reset blink when cursor moves
         self.blink_on = true;
-        self.last_blink_toggle = Instant::now();
+        self.last_blink_toggle = self.last_update;
         
-        let now = Instant::now();
         let dx = self.target_x - self.last_target_x;
         let dy = self.target_y - self.last_target_y;
         let distance = (dx * dx + dy * dy).sqrt();
@@ -322,7 +316,7 @@ impl CursorAnimator {
     }
     
     fn spawn_railgun_particles(&mut self, dx: f32, dy: f32, distance: f32) {
-        let now = Instant::now();
+        let now = self.last_update;
         let norm_dx = -dx / distance; // Opposite direction
         let norm_dy = -dy / distance;
         
@@ -354,7 +348,7 @@ impl CursorAnimator {
     }
     
     fn spawn_pixiedust_particles(&mut self) {
-        let now = Instant::now();
+        let now = self.last_update;
         
         for i in 0..self.particle_count {
             // Random direction
@@ -384,7 +378,7 @@ impl CursorAnimator {
         self.trail.push_back(TrailPoint {
             x: self.current_x + self.current_width / 2.0,
             y: self.current_y + self.current_height / 2.0,
-            time: Instant::now(),
+            time: self.last_update,
         });
         
         while self.trail.len() > self.max_trail_length {
@@ -393,7 +387,7 @@ impl CursorAnimator {
     }
     
     fn spawn_sonicboom(&mut self) {
-        let now = Instant::now();
+        let now = self.last_update;
         self.rings.push(Ring {
             x: self.target_x + self.target_width / 2.0,
             y: self.target_y + self.target_height / 2.0,
@@ -407,7 +401,7 @@ impl CursorAnimator {
     }
     
     fn spawn_ripple(&mut self) {
-        let now = Instant::now();
+        let now = self.last_update;
         // Spawn multiple concentric rings
         for i in 0..3 {
             self.rings.push(Ring {
@@ -423,11 +417,10 @@ impl CursorAnimator {
         }
     }
     
-    /// Update animation state - call each frame
+    /// Update animation state to the frame time `now` - call each frame
     /// Returns true if animation is still active (needs redraw)
-    pub fn update(&mut self) -> bool {
-        let now = Instant::now();
-        let dt = now.duration_since(self.last_update).as_secs_f32();
+    pub fn update(&mut self, now: Instant) -> bool {
+        let dt = now.saturating_duration_since(self.last_update).as_secs_f32();
         self.last_update = now;
         
         // Update cursor blink
@@ -518,63 +511,8 @@ impl CursorAnimator {
 
     /// Update with explicit delta time (for external time management)
     pub fn update_with_dt(&mut self, dt: f32) -> bool {
-        let now = Instant::now();
-
-        // Update cursor blink
-        if now.duration_since(self.last_blink_toggle) >= self.blink_interval {
-            self.blink_on = !self.blink_on;
-            self.last_blink_toggle = now;
-        }
-
-        // Smooth cursor movement (exponential interpolation)
-        if self.mode != CursorAnimationMode::None {
-            let factor = 1.0 - (-self.animation_speed * dt).exp();
-
-            self.current_x += (self.target_x - self.current_x) * factor;
-            self.current_y += (self.target_y - self.current_y) * factor;
-            self.current_width += (self.target_width - self.current_width) * factor;
-            self.current_height += (self.target_height - self.current_height) * factor;
-
-            // Check if we've reached the target
-            let dx = (self.target_x - self.current_x).abs();
-            let dy = (self.target_y - self.current_y).abs();
-            if dx < 0.5 && dy < 0.5 {
-                self.current_x = self.target_x;
-                self.current_y = self.target_y;
-                self.animating = false;
-            }
-        } else {
-            // No animation - instant movement
-            self.current_x = self.target_x;
-            self.current_y = self.target_y;
-            self.current_width = self.target_width;
-            self.current_height = self.target_height;
-            self.animating = false;
-        }
-
-        // Update particles
-        for particle in &mut self.particles {
-            particle.update(dt);
-        }
-        self.particles.retain(|p| p.is_alive(now));
-
-        // Update rings
-        for ring in &mut self.rings {
-            ring.update(dt);
-        }
-        self.rings.retain(|r| r.is_alive(now));
-
-        // Update trail (remove old points)
-        let trail_lifetime = Duration::from_millis(200);
-        self.trail.retain(|p| now.duration_since(p.time) < trail_lifetime);
-
-        // Add trail point for torpedo while moving
-        if self.mode == CursorAnimationMode::Torpedo && self.animating {
-            self.add_trail_point();
-        }
-
-        // Return true if any animation is active
-        self.animating || !self.particles.is_empty() || !self.rings.is_empty() || !self.trail.is_empty()
+        let now = self.last_update + Duration::from_secs_f32(dt.max(0.0));
+        self.update(now)
     }
 }
 
@@ -756,7 +694,7 @@ mod tests {
 
     #[test]
     fn new_animator_initial_state() {
-        let a = CursorAnimator::new();
+        let a = CursorAnimator::new(Instant::now());
         assert_eq!(a.mode, CursorAnimationMode::Smooth);
         assert_eq!(a.target_x, 0.0);
         assert_eq!(a.target_y, 0.0);
@@ -777,23 +715,13 @@ mod tests {
         assert!(!a.is_animating());
     }
 
-    #[test]
-    fn default_matches_new() {
-        let a = CursorAnimator::default();
-        let b = CursorAnimator::new();
-        assert_eq!(a.mode, b.mode);
-        assert_eq!(a.target_x, b.target_x);
-        assert_eq!(a.animation_speed, b.animation_speed);
-        assert_eq!(a.glow_intensity, b.glow_intensity);
-    }
-
     // -----------------------------------------------------------------------
     // set_target / update_target
     // -----------------------------------------------------------------------
 
     #[test]
     fn set_target_updates_target_fields() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_target(100.0, 200.0, 10.0, 20.0, 1, [0.5, 0.5, 0.5, 1.0]);
         assert_eq!(a.target_x, 100.0);
         assert_eq!(a.target_y, 200.0);
@@ -805,7 +733,7 @@ mod tests {
 
     #[test]
     fn set_target_starts_animation_on_move() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         assert!(!a.animating);
         a.set_target(100.0, 200.0, 10.0, 20.0, 0, [1.0; 4]);
         assert!(a.animating, "moving cursor should set animating=true");
@@ -813,7 +741,7 @@ mod tests {
 
     #[test]
     fn set_target_no_animation_for_tiny_move() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         // Move less than 0.5 in both axes -- should not trigger on_cursor_move
         a.set_target(0.3, 0.3, 8.0, 16.0, 0, [1.0; 4]);
         assert!(!a.animating, "sub-threshold move should not animate");
@@ -821,7 +749,7 @@ mod tests {
 
     #[test]
     fn set_target_records_last_position() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_target(50.0, 60.0, 8.0, 16.0, 0, [1.0; 4]);
         assert_eq!(a.last_target_x, 0.0);
         assert_eq!(a.last_target_y, 0.0);
@@ -836,7 +764,7 @@ mod tests {
 
     #[test]
     fn update_with_dt_moves_toward_target() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_target(200.0, 300.0, 8.0, 16.0, 0, [1.0; 4]);
 
         // Simulate several frames
@@ -853,7 +781,7 @@ mod tests {
 
     #[test]
     fn update_with_dt_converges_to_target() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_target(100.0, 100.0, 8.0, 16.0, 0, [1.0; 4]);
 
         // Simulate many frames (~2 seconds at 60fps)
@@ -871,7 +799,7 @@ mod tests {
 
     #[test]
     fn update_with_dt_snaps_when_close() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         // Place current very close to target
         a.target_x = 10.0;
         a.target_y = 10.0;
@@ -888,7 +816,7 @@ mod tests {
 
     #[test]
     fn update_returns_false_when_idle() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         // No target change, no particles -- should be idle
         let active = a.update_with_dt(0.016);
         assert!(!active, "update should return false when nothing is happening");
@@ -896,7 +824,7 @@ mod tests {
 
     #[test]
     fn update_returns_true_while_animating() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_target(500.0, 500.0, 8.0, 16.0, 0, [1.0; 4]);
         let active = a.update_with_dt(0.016);
         assert!(active, "update should return true while cursor is in motion");
@@ -908,7 +836,7 @@ mod tests {
 
     #[test]
     fn mode_none_instant_movement() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::None);
         a.set_target(500.0, 400.0, 12.0, 24.0, 0, [1.0; 4]);
         a.update_with_dt(0.016);
@@ -926,13 +854,12 @@ mod tests {
 
     #[test]
     fn blink_toggles_after_interval() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         assert!(a.blink_on);
         assert!(a.is_visible());
 
-        // Wait longer than blink interval (530ms)
-        thread::sleep(Duration::from_millis(550));
-        a.update_with_dt(0.0);
+        // Step past the blink interval (530ms)
+        a.update_with_dt(0.55);
 
         assert!(!a.blink_on, "blink should have toggled off");
         assert!(!a.is_visible(), "cursor should be invisible when blink is off");
@@ -940,10 +867,9 @@ mod tests {
 
     #[test]
     fn blink_resets_on_cursor_move() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         // Force blink off
-        thread::sleep(Duration::from_millis(550));
-        a.update_with_dt(0.0);
+        a.update_with_dt(0.55);
         assert!(!a.blink_on);
 
         // Move cursor -- blink should reset to on
@@ -953,7 +879,7 @@ mod tests {
 
     #[test]
     fn visibility_depends_on_visible_flag() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         assert!(a.is_visible());
         a.visible = false;
         assert!(!a.is_visible(), "should be invisible when visible=false");
@@ -965,7 +891,7 @@ mod tests {
 
     #[test]
     fn set_mode_clears_particles_and_rings() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Railgun);
         // Trigger particle spawn by moving
         a.set_target(100.0, 100.0, 8.0, 16.0, 0, [1.0; 4]);
@@ -984,7 +910,7 @@ mod tests {
 
     #[test]
     fn set_animation_speed_clamps() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_animation_speed(0.5);
         assert_eq!(a.animation_speed, 1.0, "speed below 1 should clamp to 1");
 
@@ -1001,7 +927,7 @@ mod tests {
 
     #[test]
     fn set_particle_count_clamps() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_particle_count(0);
         assert_eq!(a.particle_count, 1, "count 0 should clamp to 1");
 
@@ -1018,7 +944,7 @@ mod tests {
 
     #[test]
     fn railgun_spawns_particles_on_move() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Railgun);
         a.set_target(200.0, 200.0, 8.0, 16.0, 0, [1.0; 4]);
 
@@ -1027,7 +953,7 @@ mod tests {
 
     #[test]
     fn pixiedust_spawns_particles_on_move() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Pixiedust);
         a.set_target(200.0, 200.0, 8.0, 16.0, 0, [1.0; 4]);
 
@@ -1036,7 +962,7 @@ mod tests {
 
     #[test]
     fn sonicboom_spawns_ring_on_move() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Sonicboom);
         a.set_target(200.0, 200.0, 8.0, 16.0, 0, [1.0; 4]);
 
@@ -1045,7 +971,7 @@ mod tests {
 
     #[test]
     fn ripple_spawns_three_rings_on_move() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Ripple);
         a.set_target(200.0, 200.0, 8.0, 16.0, 0, [1.0; 4]);
 
@@ -1054,7 +980,7 @@ mod tests {
 
     #[test]
     fn torpedo_adds_trail_point_on_move() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Torpedo);
         a.set_target(200.0, 200.0, 8.0, 16.0, 0, [1.0; 4]);
 
@@ -1063,7 +989,7 @@ mod tests {
 
     #[test]
     fn torpedo_adds_trail_points_while_animating() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Torpedo);
         a.set_target(500.0, 500.0, 8.0, 16.0, 0, [1.0; 4]);
         let initial_count = a.trail.len();
@@ -1077,7 +1003,7 @@ mod tests {
 
     #[test]
     fn smooth_mode_no_particles_or_rings() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Smooth);
         a.set_target(200.0, 200.0, 8.0, 16.0, 0, [1.0; 4]);
 
@@ -1087,7 +1013,7 @@ mod tests {
 
     #[test]
     fn wireframe_mode_no_particles_or_rings() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Wireframe);
         a.set_target(200.0, 200.0, 8.0, 16.0, 0, [1.0; 4]);
 
@@ -1101,28 +1027,26 @@ mod tests {
 
     #[test]
     fn particles_expire_after_lifetime() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Pixiedust);
         // Use very short particle lifetime
         a.particle_lifetime = Duration::from_millis(10);
         a.set_target(200.0, 200.0, 8.0, 16.0, 0, [1.0; 4]);
         assert!(!a.particles.is_empty());
 
-        thread::sleep(Duration::from_millis(20));
-        a.update_with_dt(0.0);
+        a.update_with_dt(0.02);
         assert!(a.particles.is_empty(), "particles should be removed after lifetime");
     }
 
     #[test]
     fn rings_expire_after_lifetime() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Sonicboom);
         a.set_target(200.0, 200.0, 8.0, 16.0, 0, [1.0; 4]);
         assert!(!a.rings.is_empty());
 
         // Sonicboom rings have 300ms lifetime
-        thread::sleep(Duration::from_millis(350));
-        a.update_with_dt(0.0);
+        a.update_with_dt(0.35);
         assert!(a.rings.is_empty(), "rings should be removed after lifetime");
     }
 
@@ -1132,7 +1056,7 @@ mod tests {
 
     #[test]
     fn trail_capped_at_max_length() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Torpedo);
 
         // Add many trail points by repeatedly moving
@@ -1151,7 +1075,7 @@ mod tests {
 
     #[test]
     fn is_animating_reflects_particles() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Railgun);
         a.set_target(200.0, 200.0, 8.0, 16.0, 0, [1.0; 4]);
         assert!(a.is_animating(), "should be animating with active particles");
@@ -1159,7 +1083,7 @@ mod tests {
 
     #[test]
     fn is_animating_reflects_rings() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         a.set_mode(CursorAnimationMode::Sonicboom);
         a.set_target(200.0, 200.0, 8.0, 16.0, 0, [1.0; 4]);
         assert!(a.is_animating(), "should be animating with active rings");
@@ -1171,11 +1095,11 @@ mod tests {
 
     #[test]
     fn higher_speed_converges_faster() {
-        let mut slow = CursorAnimator::new();
+        let mut slow = CursorAnimator::new(Instant::now());
         slow.set_animation_speed(5.0);
         slow.set_target(200.0, 0.0, 8.0, 16.0, 0, [1.0; 4]);
 
-        let mut fast = CursorAnimator::new();
+        let mut fast = CursorAnimator::new(Instant::now());
         fast.set_animation_speed(50.0);
         fast.set_target(200.0, 0.0, 8.0, 16.0, 0, [1.0; 4]);
 
@@ -1196,7 +1120,7 @@ mod tests {
 
     #[test]
     fn width_and_height_animate() {
-        let mut a = CursorAnimator::new();
+        let mut a = CursorAnimator::new(Instant::now());
         // Default width=8, height=16; change to 20x40
         a.set_target(0.0, 0.0, 20.0, 40.0, 0, [1.0; 4]);
         a.update_with_dt(0.016);
@@ -1213,15 +1137,15 @@ mod tests {
     }
 
     // -----------------------------------------------------------------------
-    // update() (wall-clock based) basic smoke test
+    // update() with frame times from the caller's clock
     // -----------------------------------------------------------------------
 
     #[test]
-    fn update_wall_clock_smoke_test() {
-        let mut a = CursorAnimator::new();
+    fn update_advances_to_frame_time() {
+        let start = Instant::now();
+        let mut a = CursorAnimator::new(start);
         a.set_target(100.0, 100.0, 8.0, 16.0, 0, [1.0; 4]);
-        thread::sleep(Duration::from_millis(16));
-        let active = a.update();
+        let active = a.update(start + Duration::from_millis(16));
         // Should be animating since we just moved
         assert!(active);
         assert!(a.current_x > 0.0, "should have moved from origin");
@@ -0,0 +1,324 @@
+//! Directory listings for the directory view.
+//!
+//! The directory view shows a directory as a list drawn by the render
+//! thread instead of text Emacs inserts for every file, so directories
+//! with tens of thousands of entries open at once.  `DirectoryService`
+//! reads and sorts a directory on a worker thread and the render thread
+//! polls it for the listing.  Listings sort by name, size or
+//! modification time with directories first, and `EntryFilter` narrows
+//! them incrementally as the query grows.
+
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::UNIX_EPOCH;
+
+/// What a directory entry is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Directory,
+    File,
+    /// Devices, sockets, pipes and dangling symlinks
+    Other,
+}
+
+/// One entry of a directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirEntry {
+    pub name: String,
+    /// Kind of the entry, or of its target for a symlink
+    pub kind: EntryKind,
+    pub symlink: bool,
+    /// Size in bytes (0 for directories)
+    pub size: u64,
+    /// Modification time in seconds since the epoch (0 = unknown)
+    pub modified: i64,
+}
+
+impl DirEntry {
+    pub fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Directory
+    }
+}
+
+/// Column a listing is sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Size,
+    Modified,
+}
+
+impl SortKey {
+    /// The next column, for cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            SortKey::Name => SortKey::Size,
+            SortKey::Size => SortKey::Modified,
+            SortKey::Modified => SortKey::Name,
+        }
+    }
+}
+
+/// Read the entries of directory PATH, without `.` and `..`, and
+/// without hidden (dot) files unless SHOW_HIDDEN.
+pub fn read_directory(path: &Path, show_hidden: bool) -> std::io::Result<Vec<DirEntry>> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !show_hidden && name.starts_with('.') {
+            continue;
+        }
+        let symlink = entry.file_type().is_ok_and(|t| t.is_symlink());
+        // Symlinks are listed as what they point to
+        let metadata = if symlink { std::fs::metadata(entry.path()) } else { entry.metadata() };
+        let (kind, size, modified) = match metadata {
+            Ok(m) => {
+                let kind = if m.is_dir() {
+                    EntryKind::Directory
+                } else if m.is_file() {
+                    EntryKind::File
+                } else {
+                    EntryKind::Other
+                };
+                let modified = m
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |d| d.as_secs() as i64);
+                (kind, if m.is_dir() { 0 } else { m.len() }, modified)
+            }
+            Err(_) => (EntryKind::Other, 0, 0),
+        };
+        entries.push(DirEntry { name, kind, symlink, size, modified });
+    }
+    Ok(entries)
+}
+
+/// Compare names ignoring case, then by case so the order is total.
+fn compare_names(a: &str, b: &str) -> Ordering {
+    let folded = a.chars().flat_map(char::to_lowercase).cmp(b.chars().flat_map(char::to_lowercase));
+    folded.then_with(|| a.cmp(b))
+}
+
+/// Sort ENTRIES by KEY, directories first; DESCENDING reverses the
+/// order within directories and files.  Ties are ordered by name.
+pub fn sort_entries(entries: &mut [DirEntry], key: SortKey, descending: bool) {
+    entries.sort_by(|a, b| {
+        let by_key = match key {
+            SortKey::Name => compare_names(&a.name, &b.name),
+            SortKey::Size => a.size.cmp(&b.size).then_with(|| compare_names(&a.name, &b.name)),
+            SortKey::Modified => a.modified.cmp(&b.modified).then_with(|| compare_names(&a.name, &b.name)),
+        };
+        b.is_dir()
+            .cmp(&a.is_dir())
+            .then(if descending { by_key.reverse() } else { by_key })
+    });
+}
+
+/// Size as `ls -h` prints it: bytes below 1024, then one decimal below
+/// ten units ("1.5k") and none above ("23M").
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 6] = ["k", "M", "G", "T", "P", "E"];
+    if size < 1024 {
+        return size.to_string();
+    }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+/// Entries matching a query: those whose name contains it, ignoring
+/// case.  A query extending the previous one only searches the entries
+/// that matched it.
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+    pub query: String,
+    /// Indices of the matching entries, in listing order
+    pub matches: Vec<usize>,
+}
+
+impl EntryFilter {
+    /// A filter matching all of ENTRIES.
+    pub fn new(entries: &[DirEntry]) -> Self {
+        EntryFilter { query: String::new(), matches: (0..entries.len()).collect() }
+    }
+
+    /// Filter ENTRIES by QUERY.
+    pub fn update(&mut self, entries: &[DirEntry], query: &str) {
+        let needle = query.to_lowercase();
+        let matches = |i: &usize| entries[*i].name.to_lowercase().contains(&needle);
+        if query.starts_with(self.query.as_str()) {
+            self.matches.retain(matches);
+        } else {
+            self.matches = (0..entries.len()).filter(matches).collect();
+        }
+        self.query = query.to_string();
+    }
+}
+
+/// A directory read by `DirectoryService`
+#[derive(Debug)]
+pub struct Listing {
+    pub path: PathBuf,
+    /// Sorted entries, or the error reading the directory
+    pub entries: Result<Vec<DirEntry>, String>,
+}
+
+/// Reads directories on a worker thread.  A new request supersedes the
+/// one pending: its listing is dropped when it arrives.
+#[derive(Default)]
+pub struct DirectoryService {
+    pending: Option<mpsc::Receiver<Listing>>,
+}
+
+impl DirectoryService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start reading directory PATH, sorted by KEY.
+    pub fn request(&mut self, path: PathBuf, show_hidden: bool, key: SortKey, descending: bool) {
+        let (tx, rx) = mpsc::channel();
+        self.pending = Some(rx);
+        let spawned = std::thread::Builder::new()
+            .name("dir-listing".into())
+            .spawn(move || {
+                let entries = read_directory(&path, show_hidden)
+                    .map(|mut entries| {
+                        sort_entries(&mut entries, key, descending);
+                        entries
+                    })
+                    .map_err(|e| e.to_string());
+                let _ = tx.send(Listing { path, entries });
+            });
+        if let Err(e) = spawned {
+            log::warn!("dir-listing: cannot start reader thread: {}", e);
+            self.pending = None;
+        }
+    }
+
+    /// Whether a listing is still being read.
+    pub fn busy(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// The listing requested last, once it has been read.
+    pub fn poll(&mut self) -> Option<Listing> {
+        let listing = self.pending.as_ref()?.try_recv();
+        match listing {
+            Ok(listing) => {
+                self.pending = None;
+                Some(listing)
+            }
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.pending = None;
+                None
+            }
+        }
+    }
+
+    /// Block until the listing requested last has been read.
+    pub fn wait(&mut self) -> Option<Listing> {
+        self.pending.take()?.recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, kind: EntryKind, size: u64, modified: i64) -> DirEntry {
+        DirEntry { name: name.to_string(), kind, symlink: false, size, modified }
+    }
+
+    fn names(entries: &[DirEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn sorting_puts_directories_first() {
+        let mut entries = vec![
+            entry("b.txt", EntryKind::File, 10, 3),
+            entry("src", EntryKind::Directory, 0, 1),
+            entry("A.md", EntryKind::File, 300, 2),
+            entry("a.rs", EntryKind::File, 20, 5),
+            entry("Docs", EntryKind::Directory, 0, 9),
+        ];
+        sort_entries(&mut entries, SortKey::Name, false);
+        assert_eq!(names(&entries), ["Docs", "src", "A.md", "a.rs", "b.txt"]);
+        sort_entries(&mut entries, SortKey::Size, true);
+        assert_eq!(names(&entries), ["src", "Docs", "A.md", "a.rs", "b.txt"]);
+        sort_entries(&mut entries, SortKey::Modified, false);
+        assert_eq!(names(&entries), ["src", "Docs", "A.md", "b.txt", "a.rs"]);
+        assert_eq!(SortKey::Modified.next(), SortKey::Name);
+    }
+
+    #[test]
+    fn sizes_read_like_ls() {
+        assert_eq!(format_size(0), "0");
+        assert_eq!(format_size(1023), "1023");
+        assert_eq!(format_size(1536), "1.5k");
+        assert_eq!(format_size(23 * 1024 * 1024 + 5), "23M");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024 * 1024), "5.0T");
+    }
+
+    #[test]
+    fn filtering_narrows_incrementally() {
+        let entries = vec![
+            entry("Makefile", EntryKind::File, 0, 0),
+            entry("main.rs", EntryKind::File, 0, 0),
+            entry("mod.rs", EntryKind::File, 0, 0),
+            entry("readme", EntryKind::File, 0, 0),
+        ];
+        let mut filter = EntryFilter::new(&entries);
+        assert_eq!(filter.matches, [0, 1, 2, 3]);
+        filter.update(&entries, "m");
+        assert_eq!(filter.matches, [0, 1, 2, 3]);
+        filter.update(&entries, "ma");
+        assert_eq!(filter.matches, [0, 1]);
+        filter.update(&entries, "mai");
+        assert_eq!(filter.matches, [1]);
+        // Deleting a character searches everything again
+        filter.update(&entries, "m");
+        assert_eq!(filter.matches, [0, 1, 2, 3]);
+        filter.update(&entries, ".RS");
+        assert_eq!(filter.matches, [1, 2]);
+    }
+
+    #[test]
+    fn service_reads_directories_in_the_background() {
+        let dir = std::env::temp_dir().join(format!("neomacs-dir-listing-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("file.txt"), b"hello").unwrap();
+        std::fs::write(dir.join(".hidden"), b"").unwrap();
+
+        let mut service = DirectoryService::new();
+        service.request(dir.clone(), false, SortKey::Name, false);
+        assert!(service.busy());
+        let listing = service.wait().unwrap();
+        assert!(!service.busy());
+        let entries = listing.entries.unwrap();
+        assert_eq!(names(&entries), ["sub", "file.txt"]);
+        assert_eq!(entries[1].size, 5);
+        assert!(entries[1].modified > 0);
+
+        service.request(dir.clone(), true, SortKey::Name, false);
+        assert_eq!(service.wait().unwrap().entries.unwrap().len(), 3);
+        service.request(dir.join("missing"), false, SortKey::Name, false);
+        assert!(service.wait().unwrap().entries.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
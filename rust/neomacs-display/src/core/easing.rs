@@ -0,0 +1,250 @@
+//! Parametric easing curves: spring-damper physics and cubic Bezier.
+//!
+//! Both map a normalized time t ∈ [0, 1] to progress, so they can be
+//! used wherever a `TransitionEasing` is: cursor motion, scroll and
+//! crossfade transitions, buffer transitions and timeline keyframes.
+
+/// Damped spring pulled from 0 to 1, starting at rest.
+///
+/// The curve is the step response of a mass on a spring with a damper.
+/// A damping ratio below 1 overshoots and oscillates, 1 is critically
+/// damped, above 1 approaches the target slowly without overshoot.  The
+/// response until the spring settles is fitted into the duration of the
+/// animation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spring {
+    /// Spring constant k
+    pub stiffness: f32,
+    /// Damping coefficient c
+    pub damping: f32,
+    /// Mass m
+    pub mass: f32,
+}
+
+impl Default for Spring {
+    fn default() -> Self {
+        Self { stiffness: 170.0, damping: 26.0, mass: 1.0 }
+    }
+}
+
+/// Distance from the target at which a spring counts as settled
+const SPRING_SETTLE_EPSILON: f32 = 1e-4;
+
+impl Spring {
+    pub fn new(stiffness: f32, damping: f32, mass: f32) -> Self {
+        Self {
+            stiffness: stiffness.max(1e-3),
+            damping: damping.max(0.0),
+            mass: mass.max(1e-3),
+        }
+    }
+
+    /// Undamped angular frequency ω0 = sqrt(k/m)
+    pub fn natural_frequency(&self) -> f32 {
+        (self.stiffness.max(1e-3) / self.mass.max(1e-3)).sqrt()
+    }
+
+    /// Damping ratio ζ = c / (2 sqrt(k m))
+    pub fn damping_ratio(&self) -> f32 {
+        self.damping.max(0.0) / (2.0 * (self.stiffness.max(1e-3) * self.mass.max(1e-3)).sqrt())
+    }
+
+    /// Position at TIME seconds after release
+    pub fn value_at(&self, time: f32) -> f32 {
+        let w0 = self.natural_frequency();
+        let zeta = self.damping_ratio();
+        let t = time.max(0.0);
+        if (zeta - 1.0).abs() < 1e-3 {
+            1.0 - (-w0 * t).exp() * (1.0 + w0 * t)
+        } else if zeta < 1.0 {
+            let wd = w0 * (1.0 - zeta * zeta).sqrt();
+            let envelope = (-zeta * w0 * t).exp();
+            1.0 - envelope * ((wd * t).cos() + (zeta * w0 / wd) * (wd * t).sin())
+        } else {
+            let root = (zeta * zeta - 1.0).sqrt();
+            let r1 = -w0 * (zeta - root);
+            let r2 = -w0 * (zeta + root);
+            1.0 - (r2 * (r1 * t).exp() - r1 * (r2 * t).exp()) / (r2 - r1)
+        }
+    }
+
+    /// Seconds until the spring stays within `SPRING_SETTLE_EPSILON` of
+    /// the target
+    pub fn settle_time(&self) -> f32 {
+        let w0 = self.natural_frequency();
+        let zeta = self.damping_ratio();
+        // Decay rate of the slowest mode
+        let rate = if zeta <= 1.0 {
+            zeta * w0
+        } else {
+            w0 * (zeta - (zeta * zeta - 1.0).sqrt())
+        };
+        // The extra 3 time constants cover the polynomial factor of the
+        // critically damped response.  Undamped springs never settle;
+        // cap at a minute.
+        ((3.0 - SPRING_SETTLE_EPSILON.ln()) / rate.max(1e-3)).min(60.0)
+    }
+
+    /// Progress at normalized time T, the settling response fitted into
+    /// [0, 1]
+    pub fn apply(&self, t: f32) -> f32 {
+        if t >= 1.0 {
+            return 1.0;
+        }
+        self.value_at(t.max(0.0) * self.settle_time())
+    }
+}
+
+/// CSS-style cubic Bezier easing through (0, 0), (X1, Y1), (X2, Y2)
+/// and (1, 1).  Y values outside [0, 1] overshoot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CubicBezier {
+    pub x1: f32,
+    pub y1: f32,
+    pub x2: f32,
+    pub y2: f32,
+}
+
+impl CubicBezier {
+    /// CSS `ease`
+    pub const EASE: Self = Self { x1: 0.25, y1: 0.1, x2: 0.25, y2: 1.0 };
+
+    /// Curve with control points (X1, Y1) and (X2, Y2).  X values are
+    /// clamped to [0, 1] so the curve stays a function of time.
+    pub fn new(x1: f32, y1: f32, x2: f32, y2: f32) -> Self {
+        Self { x1: x1.clamp(0.0, 1.0), y1, x2: x2.clamp(0.0, 1.0), y2 }
+    }
+
+    fn coord(p1: f32, p2: f32, s: f32) -> f32 {
+        let u = 1.0 - s;
+        3.0 * u * u * s * p1 + 3.0 * u * s * s * p2 + s * s * s
+    }
+
+    fn coord_slope(p1: f32, p2: f32, s: f32) -> f32 {
+        let u = 1.0 - s;
+        3.0 * u * u * p1 + 6.0 * u * s * (p2 - p1) + 3.0 * s * s * (1.0 - p2)
+    }
+
+    /// Curve parameter whose x is X
+    fn solve_x(&self, x: f32) -> f32 {
+        // Newton's method, falling back to bisection on flat slopes
+        let mut s = x;
+        for _ in 0..8 {
+            let err = Self::coord(self.x1, self.x2, s) - x;
+            if err.abs() < 1e-5 {
+                return s;
+            }
+            let slope = Self::coord_slope(self.x1, self.x2, s);
+            if slope.abs() < 1e-6 {
+                break;
+            }
+            s = (s - err / slope).clamp(0.0, 1.0);
+        }
+        let (mut lo, mut hi) = (0.0f32, 1.0f32);
+        s = x;
+        for _ in 0..32 {
+            let v = Self::coord(self.x1, self.x2, s);
+            if (v - x).abs() < 1e-5 {
+                break;
+            }
+            if v < x {
+                lo = s;
+            } else {
+                hi = s;
+            }
+            s = (lo + hi) / 2.0;
+        }
+        s
+    }
+
+    /// Progress at normalized time T
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        if t <= 0.0 || t >= 1.0 {
+            return t;
+        }
+        Self::coord(self.y1, self.y2, self.solve_x(t))
+    }
+}
+
+/// Animation whose easing can be set to a curve from Lisp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EasingTarget {
+    /// Cursor motion
+    Cursor,
+    /// Scroll transitions
+    Scroll,
+    /// Crossfades on buffer change
+    Crossfade,
+    /// Snapshot-based buffer transitions
+    BufferTransition,
+}
+
+impl EasingTarget {
+    /// Target from its FFI code: 0 = cursor, 1 = scroll, 2 = crossfade,
+    /// 3 = buffer transition
+    pub fn from_ffi(code: i32) -> Option<Self> {
+        match code {
+            0 => Some(Self::Cursor),
+            1 => Some(Self::Scroll),
+            2 => Some(Self::Crossfade),
+            3 => Some(Self::BufferTransition),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spring_starts_at_rest_and_settles() {
+        for spring in [Spring::new(170.0, 26.0, 1.0), Spring::new(100.0, 20.0, 1.0), Spring::new(100.0, 60.0, 1.0)] {
+            assert!(spring.value_at(0.0).abs() < 1e-5);
+            assert!((spring.value_at(spring.settle_time()) - 1.0).abs() < 1e-3);
+            assert_eq!(spring.apply(0.0), 0.0);
+            assert_eq!(spring.apply(1.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn underdamped_spring_overshoots() {
+        let bouncy = Spring::new(200.0, 8.0, 1.0);
+        assert!(bouncy.damping_ratio() < 1.0);
+        let peak = (1..100).map(|i| bouncy.apply(i as f32 / 100.0)).fold(0.0, f32::max);
+        assert!(peak > 1.05, "peak = {}", peak);
+
+        let stiff = Spring::new(100.0, 40.0, 1.0);
+        assert!(stiff.damping_ratio() > 1.0);
+        assert!((1..100).all(|i| stiff.apply(i as f32 / 100.0) <= 1.0 + 1e-4));
+    }
+
+    #[test]
+    fn critical_damping_is_monotonic() {
+        let spring = Spring::new(100.0, 20.0, 1.0);
+        assert!((spring.damping_ratio() - 1.0).abs() < 1e-4);
+        let values: Vec<f32> = (0..=50).map(|i| spring.apply(i as f32 / 50.0)).collect();
+        assert!(values.windows(2).all(|w| w[1] >= w[0] - 1e-5));
+    }
+
+    #[test]
+    fn bezier_endpoints_and_linear() {
+        let linear = CubicBezier::new(0.0, 0.0, 1.0, 1.0);
+        for i in 0..=10 {
+            let t = i as f32 / 10.0;
+            assert!((linear.apply(t) - t).abs() < 1e-3);
+        }
+        assert_eq!(CubicBezier::EASE.apply(0.0), 0.0);
+        assert_eq!(CubicBezier::EASE.apply(1.0), 1.0);
+        // CSS ease is ahead of linear in the middle
+        assert!(CubicBezier::EASE.apply(0.5) > 0.7);
+    }
+
+    #[test]
+    fn bezier_clamps_x_and_overshoots_in_y() {
+        let back = CubicBezier::new(-1.0, 0.0, 0.3, 1.6);
+        assert_eq!(back.x1, 0.0);
+        assert!((1..100).any(|i| back.apply(i as f32 / 100.0) > 1.0));
+    }
+}
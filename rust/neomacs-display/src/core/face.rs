@@ -41,6 +41,57 @@ pub enum BoxType {
     Sunken3D,
 }
 
+/// Shadow or glow cast by the text of a face
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextShadow {
+    /// Shadow color
+    pub color: Color,
+    /// Offset from the glyphs in pixels
+    pub offset_x: f32,
+    pub offset_y: f32,
+    /// Blur radius in pixels (0 = hard-edged)
+    pub blur: f32,
+}
+
+impl TextShadow {
+    /// Samples approximating the blurred shadow: (dx, dy, alpha) for
+    /// each copy of a glyph to draw under it, with DX and DY relative to
+    /// the glyph.  The weights come from a Gaussian kernel with sigma
+    /// half the blur radius, sampled at up to 5 points per axis and
+    /// applied separably.  They are scaled so that a stroke one sample
+    /// wide keeps the shadow's opacity instead of fading with the
+    /// number of samples.
+    pub fn taps(&self) -> Vec<(f32, f32, f32)> {
+        let alpha = self.color.a;
+        if self.blur <= 0.0 {
+            return vec![(self.offset_x, self.offset_y, alpha)];
+        }
+        let n = (self.blur.ceil() as i32).min(2);
+        let step = self.blur / n as f32;
+        let sigma = self.blur / 2.0;
+        let weights: Vec<f32> = (-n..=n)
+            .map(|i| {
+                let d = i as f32 * step;
+                (-d * d / (2.0 * sigma * sigma)).exp()
+            })
+            .collect();
+        let sum: f32 = weights.iter().sum();
+        let side = weights.len() as f32;
+        let mut taps = Vec::with_capacity(weights.len() * weights.len());
+        for (j, wy) in weights.iter().enumerate() {
+            for (i, wx) in weights.iter().enumerate() {
+                let w = wx * wy / (sum * sum);
+                taps.push((
+                    self.offset_x + (i as i32 - n) as f32 * step,
+                    self.offset_y + (j as i32 - n) as f32 * step,
+                    alpha * (w * side).min(1.0),
+                ));
+            }
+        }
+        taps
+    }
+}
+
 /// A face defines text styling (colors, font, decorations)
 #[repr(C)]
 #[derive(Debug, Clone)]
@@ -99,6 +150,9 @@ pub struct Face {
     pub underline_position: i32,
     /// Underline thickness (font->underline_thickness)
     pub underline_thickness: i32,
+
+    /// Shadow or glow under the text
+    pub text_shadow: Option<TextShadow>,
 }
 
 impl Default for Face {
@@ -123,6 +177,7 @@ impl Default for Face {
             font_descent: 0,
             underline_position: 1,
             underline_thickness: 1,
+            text_shadow: None,
         }
     }
 }
@@ -269,6 +324,7 @@ mod tests {
         assert_eq!(face.font_descent, 0);
         assert_eq!(face.underline_position, 1);
         assert_eq!(face.underline_thickness, 1);
+        assert!(face.text_shadow.is_none());
     }
 
     #[test]
@@ -571,6 +627,34 @@ mod tests {
         assert_eq!(face.underline_thickness, 1);
     }
 
+    #[test]
+    fn test_text_shadow_taps() {
+        let mut shadow = TextShadow {
+            color: Color::new(0.0, 0.0, 0.0, 0.8),
+            offset_x: 2.0,
+            offset_y: 1.0,
+            blur: 0.0,
+        };
+        // Hard shadow: one copy at the offset
+        assert_eq!(shadow.taps(), vec![(2.0, 1.0, 0.8)]);
+
+        // Glow: centered on the glyph, strongest in the middle
+        shadow.offset_x = 0.0;
+        shadow.offset_y = 0.0;
+        shadow.blur = 4.0;
+        let taps = shadow.taps();
+        assert_eq!(taps.len(), 25);
+        let center = taps[12];
+        assert_eq!((center.0, center.1), (0.0, 0.0));
+        assert!(taps.iter().all(|t| t.2 > 0.0 && t.2 <= center.2 && t.2 <= 0.8));
+        assert_eq!((taps[0].0, taps[0].1), (-4.0, -4.0));
+        assert_eq!((taps[24].0, taps[24].1), (4.0, 4.0));
+
+        // Small radius: fewer samples
+        shadow.blur = 1.0;
+        assert_eq!(shadow.taps().len(), 9);
+    }
+
     // --- FaceCache tests ---
 
     #[test]
@@ -7,6 +7,7 @@
 use crate::core::face::Face;
 use crate::core::types::{Color, Rect};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 /// Cursor visual style, carrying bar/hbar dimensions.
 ///
@@ -93,6 +94,10 @@ pub enum FrameGlyph {
         /// Overstrike: draw glyph twice (at x and x+1) to simulate bold.
         /// Set when Emacs can't find a bold variant for the font.
         overstrike: bool,
+        /// Drawn a quarter turn clockwise, as Latin text in a vertical
+        /// writing column.  X/Y/width/height are then the cell on screen:
+        /// width across the column, height along it.
+        sideways: bool,
     },
 
     /// Stretch (whitespace) glyph
@@ -220,6 +225,83 @@ pub struct CursorInverseInfo {
     pub cursor_fg: Color,
 }
 
+/// Geometry of one screen row of a window's text area.  Rows have
+/// individual heights: tall faces, images and the `line-height` /
+/// `line-spacing` properties make a row taller than the default line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextRow {
+    /// Window the row belongs to (same id as `WindowInfo::window_id`)
+    pub window_id: i64,
+    /// Frame-absolute top of the row, including space above its text
+    pub y: f32,
+    /// Full row height, including line spacing
+    pub height: f32,
+}
+
+/// Part of a window's text area that its text glyphs do not extend
+/// left of.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextClip {
+    /// Window the area belongs to (same id as `WindowInfo::window_id`)
+    pub window_id: i64,
+    pub rect: Rect,
+}
+
+/// A fading highlight drawn over text that was just yanked, killed or
+/// indented.  The layout engine emits one per covered row part; the
+/// render thread fades it out from the time it was requested.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegionPulse {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Highlight color; its alpha is the opacity at the start
+    pub color: Color,
+    /// When the pulse was requested
+    pub started: Instant,
+    pub duration: Duration,
+}
+
+/// What a selection run highlights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionKind {
+    /// The active region
+    Region,
+    /// A `rectangle-mark-mode' rectangle
+    Rectangle,
+    /// The secondary selection
+    Secondary,
+}
+
+/// The background behind part of a row of selected text.  The layout
+/// engine draws it as a stretch and also lists it here, so the selection
+/// can be found without matching glyph colors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelectionRun {
+    /// Window the run belongs to (same id as `WindowInfo::window_id`)
+    pub window_id: i64,
+    pub kind: SelectionKind,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub bg: Color,
+}
+
+impl RegionPulse {
+    /// The highlight color at NOW, easing out to transparent, or None
+    /// once the pulse is over.
+    pub fn color_at(&self, now: Instant) -> Option<Color> {
+        let elapsed = now.saturating_duration_since(self.started);
+        if elapsed >= self.duration {
+            return None;
+        }
+        let t = 1.0 - elapsed.as_secs_f32() / self.duration.as_secs_f32();
+        Some(Color { a: self.color.a * t * t, ..self.color })
+    }
+}
+
 /// Stipple pattern: XBM bitmap data for tiled background patterns
 #[derive(Debug, Clone)]
 pub struct StipplePattern {
@@ -314,6 +396,19 @@ pub struct FrameGlyphBuffer {
     /// Per-window metadata for animation detection
     pub window_infos: Vec<WindowInfo>,
 
+    /// Screen rows of all windows' text areas, top to bottom per window
+    pub text_rows: Vec<TextRow>,
+
+    /// Fading highlights over recently changed text
+    pub region_pulses: Vec<RegionPulse>,
+
+    /// Backgrounds of the region, rectangles and secondary selection
+    pub selection_runs: Vec<SelectionRun>,
+
+    /// Text areas whose glyphs are cut at the left edge, where an
+    /// hscrolled window shows part of a character
+    pub text_clips: Vec<TextClip>,
+
     /// Inverse video info for filled box cursor (set by C for style 0)
     pub cursor_inverse: Option<CursorInverseInfo>,
 
@@ -366,6 +461,10 @@ impl FrameGlyphBuffer {
             window_regions: Vec::with_capacity(16),
             prev_window_regions: Vec::with_capacity(16),
             window_infos: Vec::with_capacity(16),
+            text_rows: Vec::new(),
+            region_pulses: Vec::new(),
+            selection_runs: Vec::new(),
+            text_clips: Vec::new(),
             cursor_inverse: None,
             layout_changed: false,
             current_face_id: 0,
@@ -396,12 +495,105 @@ impl FrameGlyphBuffer {
         }
     }
 
+    /// Make this buffer a copy of OTHER, reusing its allocations.
+    pub fn copy_from(&mut self, other: &FrameGlyphBuffer) {
+        // Destructured so a new field cannot be missed
+        let FrameGlyphBuffer {
+            width,
+            height,
+            char_width,
+            char_height,
+            font_pixel_size,
+            background,
+            frame_id,
+            parent_id,
+            parent_x,
+            parent_y,
+            z_order,
+            border_width,
+            border_color,
+            background_alpha,
+            no_accept_focus,
+            glyphs,
+            window_regions,
+            prev_window_regions,
+            window_infos,
+            text_rows,
+            region_pulses,
+            selection_runs,
+            text_clips,
+            cursor_inverse,
+            layout_changed,
+            current_face_id,
+            current_fg,
+            current_bg,
+            current_font_family,
+            current_font_weight,
+            current_italic,
+            current_font_size,
+            current_underline,
+            current_underline_color,
+            current_strike_through,
+            current_strike_through_color,
+            current_overline,
+            current_overline_color,
+            current_overstrike,
+            faces,
+            stipple_patterns,
+        } = other;
+        self.width = *width;
+        self.height = *height;
+        self.char_width = *char_width;
+        self.char_height = *char_height;
+        self.font_pixel_size = *font_pixel_size;
+        self.background = *background;
+        self.frame_id = *frame_id;
+        self.parent_id = *parent_id;
+        self.parent_x = *parent_x;
+        self.parent_y = *parent_y;
+        self.z_order = *z_order;
+        self.border_width = *border_width;
+        self.border_color = *border_color;
+        self.background_alpha = *background_alpha;
+        self.no_accept_focus = *no_accept_focus;
+        self.glyphs.clone_from(glyphs);
+        self.window_regions.clone_from(window_regions);
+        self.prev_window_regions.clone_from(prev_window_regions);
+        self.window_infos.clone_from(window_infos);
+        self.text_rows.clone_from(text_rows);
+        self.region_pulses.clone_from(region_pulses);
+        self.selection_runs.clone_from(selection_runs);
+        self.text_clips.clone_from(text_clips);
+        self.cursor_inverse.clone_from(cursor_inverse);
+        self.layout_changed = *layout_changed;
+        self.current_face_id = *current_face_id;
+        self.current_fg = *current_fg;
+        self.current_bg = *current_bg;
+        self.current_font_family.clone_from(current_font_family);
+        self.current_font_weight = *current_font_weight;
+        self.current_italic = *current_italic;
+        self.current_font_size = *current_font_size;
+        self.current_underline = *current_underline;
+        self.current_underline_color = *current_underline_color;
+        self.current_strike_through = *current_strike_through;
+        self.current_strike_through_color = *current_strike_through_color;
+        self.current_overline = *current_overline;
+        self.current_overline_color = *current_overline_color;
+        self.current_overstrike = *current_overstrike;
+        self.faces.clone_from(faces);
+        self.stipple_patterns.clone_from(stipple_patterns);
+    }
+
     /// Clear all glyphs for a fresh full-frame rebuild.
     /// Called at the start of each frame by the matrix walker.
     pub fn clear_all(&mut self) {
         self.glyphs.clear();
         self.window_regions.clear();
         self.window_infos.clear();
+        self.text_rows.clear();
+        self.region_pulses.clear();
+        self.selection_runs.clear();
+        self.text_clips.clear();
         self.cursor_inverse = None;
         self.stipple_patterns.clear();
         self.faces.clear();
@@ -551,6 +743,7 @@ impl FrameGlyphBuffer {
             overline_color: self.current_overline_color,
             is_overlay,
             overstrike: self.current_overstrike,
+            sideways: false,
         });
     }
 
@@ -579,6 +772,7 @@ impl FrameGlyphBuffer {
             overline_color: self.current_overline_color,
             is_overlay,
             overstrike: self.current_overstrike,
+            sideways: false,
         });
     }
 
@@ -653,6 +847,42 @@ impl FrameGlyphBuffer {
         });
     }
 
+    /// Record a screen row of WINDOW_ID's text area
+    pub fn add_text_row(&mut self, window_id: i64, y: f32, height: f32) {
+        self.text_rows.push(TextRow { window_id, y, height });
+    }
+
+    /// The row of WINDOW_ID's text area containing frame Y coordinate Y
+    pub fn text_row_at(&self, window_id: i64, y: f32) -> Option<&TextRow> {
+        self.text_rows
+            .iter()
+            .find(|r| r.window_id == window_id && y >= r.y && y < r.y + r.height)
+    }
+
+    /// Cut text glyphs of WINDOW_ID in RECT at its left edge
+    pub fn add_text_clip(&mut self, window_id: i64, rect: Rect) {
+        self.text_clips.push(TextClip { window_id, rect });
+    }
+
+    /// Left edge a glyph at X, Y and WIDTH wide is cut at, if it
+    /// straddles the left edge of a clip area
+    pub fn text_clip_left(&self, x: f32, y: f32, width: f32) -> Option<f32> {
+        self.text_clips.iter().find_map(|c| {
+            let r = &c.rect;
+            (y >= r.y && y < r.y + r.height && x < r.x && x + width > r.x + 0.01).then_some(r.x)
+        })
+    }
+
+    /// Add a fading highlight
+    pub fn add_region_pulse(&mut self, pulse: RegionPulse) {
+        self.region_pulses.push(pulse);
+    }
+
+    /// Add the background of part of a selected row
+    pub fn add_selection_run(&mut self, run: SelectionRun) {
+        self.selection_runs.push(run);
+    }
+
     /// Set cursor inverse video info (for filled box cursor)
     pub fn set_cursor_inverse(&mut self, x: f32, y: f32, width: f32, height: f32,
                               cursor_bg: Color, cursor_fg: Color) {
@@ -749,6 +979,57 @@ mod tests {
         assert_eq!(buf.len(), 0);
     }
 
+    // =======================================================================
+    // text rows
+    // =======================================================================
+
+    #[test]
+    fn text_rows_have_individual_heights() {
+        let mut buf = FrameGlyphBuffer::new();
+        buf.add_text_row(1, 0.0, 16.0);
+        buf.add_text_row(1, 16.0, 40.0);
+        buf.add_text_row(2, 0.0, 16.0);
+        assert_eq!(buf.text_row_at(1, 20.0).map(|r| r.height), Some(40.0));
+        assert_eq!(buf.text_row_at(1, 55.9).map(|r| r.y), Some(16.0));
+        assert!(buf.text_row_at(1, 56.0).is_none());
+        assert_eq!(buf.text_row_at(2, 5.0).map(|r| r.window_id), Some(2));
+        buf.clear_all();
+        assert!(buf.text_rows.is_empty());
+    }
+
+    #[test]
+    fn text_clips_cut_glyphs_straddling_their_left_edge() {
+        let mut buf = FrameGlyphBuffer::new();
+        buf.add_text_clip(1, Rect::new(108.0, 20.0, 400.0, 320.0));
+        assert_eq!(buf.text_clip_left(104.0, 36.0, 8.0), Some(108.0));
+        // Wholly left of the edge, as the `$' is, or right of it
+        assert_eq!(buf.text_clip_left(100.0, 36.0, 8.0), None);
+        assert_eq!(buf.text_clip_left(108.0, 36.0, 8.0), None);
+        // Rows outside the text area
+        assert_eq!(buf.text_clip_left(104.0, 4.0, 8.0), None);
+        buf.clear_all();
+        assert!(buf.text_clips.is_empty());
+    }
+
+    #[test]
+    fn region_pulses_fade_out() {
+        let mut buf = FrameGlyphBuffer::new();
+        let start = Instant::now();
+        let color = Color::new(1.0, 0.5, 0.0, 0.4);
+        buf.add_region_pulse(RegionPulse {
+            x: 0.0, y: 0.0, width: 80.0, height: 16.0,
+            color, started: start, duration: Duration::from_millis(200),
+        });
+        let pulse = buf.region_pulses[0];
+        assert_eq!(pulse.color_at(start), Some(color));
+        let half = pulse.color_at(start + Duration::from_millis(100)).unwrap();
+        assert!((half.a - 0.1).abs() < 1e-4);
+        assert_eq!(half.r, 1.0);
+        assert_eq!(pulse.color_at(start + Duration::from_millis(200)), None);
+        buf.clear_all();
+        assert!(buf.region_pulses.is_empty());
+    }
+
     // =======================================================================
     // with_size()
     // =======================================================================
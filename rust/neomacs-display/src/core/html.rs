@@ -0,0 +1,1401 @@
+//! HTML documents as styled text, for mail and READMEs.
+//!
+//! A WebKit view is heavy for a mail message or a package README.  This
+//! renders the simple HTML those use (headings, paragraphs, lists,
+//! quotes, preformatted text, tables, links and images) into text filled
+//! to a width, with runs of `TextStyle` over it.  Lisp inserts the text
+//! in an ordinary buffer and turns the runs into faces and link
+//! properties, so the result can be searched, copied and themed like
+//! any other text, as with EWW's shr.
+//!
+//! The parser forgives what browsers forgive in mail: unclosed
+//! paragraphs, list items and table cells, stray end tags and unquoted
+//! attributes.  CSS is a subset: `<style>` rules with type, class, id
+//! and descendant selectors, and `style` attributes, for the properties
+//! that map to faces (weight, slant, decoration, colors, monospace)
+//! plus `display: none` and `white-space: pre`.
+
+use super::char_utils::char_display_width;
+
+// ============================================================================
+// Document tree
+// ============================================================================
+
+/// A node of the document tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+/// An element, with its attributes in document order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Element {
+    /// Lowercase tag name; `#root` for the document
+    pub tag: String,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    fn new(tag: &str, attrs: Vec<(String, String)>) -> Self {
+        Element { tag: tag.to_string(), attrs, children: Vec::new() }
+    }
+
+    /// Value of attribute NAME.
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    fn has_class(&self, class: &str) -> bool {
+        self.attr("class").is_some_and(|c| c.split_ascii_whitespace().any(|c| c == class))
+    }
+
+    /// Child elements.
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|n| match n {
+            Node::Element(e) => Some(e),
+            Node::Text(_) => None,
+        })
+    }
+}
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose text is not markup
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "title", "textarea"];
+
+/// Elements whose start ends an open paragraph
+const CLOSES_PARAGRAPH: &[&str] = &[
+    "address", "article", "aside", "blockquote", "center", "details", "div", "dl", "fieldset",
+    "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "main", "nav",
+    "ol", "p", "pre", "section", "table", "ul",
+];
+
+/// Parse HTML into a tree under a `#root` element.
+pub fn parse(html: &str) -> Element {
+    let mut stack = vec![Element::new("#root", Vec::new())];
+    let mut text = String::new();
+    let mut i = 0;
+    while i < html.len() {
+        let rest = &html[i..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            add_text(&mut stack, &mut text);
+            i += 4 + comment.find("-->").map_or(comment.len(), |e| e + 3);
+            continue;
+        }
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata.find("]]>").unwrap_or(cdata.len());
+            add_text(&mut stack, &mut text);
+            push_text(&mut stack, &cdata[..end]);
+            i += 9 + (end + 3).min(cdata.len());
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            add_text(&mut stack, &mut text);
+            i += rest.find('>').map_or(rest.len(), |e| e + 1);
+            continue;
+        }
+        if rest.starts_with("</") && rest[2..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            add_text(&mut stack, &mut text);
+            let end = rest.find('>').unwrap_or(rest.len());
+            let name = rest[2..end].split(|c: char| c.is_ascii_whitespace() || c == '/').next().unwrap_or("");
+            close(&mut stack, &name.to_ascii_lowercase());
+            i += (end + 1).min(rest.len());
+            continue;
+        }
+        if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            if let Some((tag, attrs, self_closing, len)) = parse_start_tag(rest) {
+                add_text(&mut stack, &mut text);
+                i += len;
+                if RAW_TEXT_ELEMENTS.contains(&tag.as_str()) && !self_closing {
+                    // Everything up to the end tag is text
+                    let body = &html[i..];
+                    let end = body.to_ascii_lowercase().find(&format!("</{}", tag)).unwrap_or(body.len());
+                    let mut element = Element::new(&tag, attrs);
+                    let content = &body[..end];
+                    if !content.is_empty() {
+                        let content = if tag == "script" || tag == "style" {
+                            content.to_string()
+                        } else {
+                            decode_entities(content)
+                        };
+                        element.children.push(Node::Text(content));
+                    }
+                    stack.last_mut().unwrap().children.push(Node::Element(element));
+                    i += end;
+                    i += html[i..].find('>').map_or(html.len() - i, |e| e + 1);
+                } else {
+                    open(&mut stack, tag, attrs, self_closing);
+                }
+                continue;
+            }
+        }
+        let ch = rest.chars().next().unwrap();
+        text.push(ch);
+        i += ch.len_utf8();
+    }
+    add_text(&mut stack, &mut text);
+    while stack.len() > 1 {
+        pop(&mut stack);
+    }
+    stack.pop().unwrap()
+}
+
+/// A start tag: its name, attributes, whether it ends in "/>", and its
+/// length
+type StartTag = (String, Vec<(String, String)>, bool, usize);
+
+/// The start tag S begins with; None if it is not terminated.
+fn parse_start_tag(s: &str) -> Option<StartTag> {
+    let b = s.as_bytes();
+    let mut i = 1;
+    while i < b.len() && !b[i].is_ascii_whitespace() && b[i] != b'>' && b[i] != b'/' {
+        i += 1;
+    }
+    let tag = s[1..i].to_ascii_lowercase();
+    let mut attrs = Vec::new();
+    let mut self_closing = false;
+    loop {
+        while i < b.len() && b[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i >= b.len() {
+            return None;
+        }
+        match b[i] {
+            b'>' => return Some((tag, attrs, self_closing, i + 1)),
+            b'/' => {
+                self_closing = true;
+                i += 1;
+            }
+            _ => {
+                self_closing = false;
+                let start = i;
+                while i < b.len() && !b[i].is_ascii_whitespace() && !matches!(b[i], b'=' | b'>' | b'/') {
+                    i += 1;
+                }
+                let name = s[start..i].to_ascii_lowercase();
+                while i < b.len() && b[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                let mut value = String::new();
+                if i < b.len() && b[i] == b'=' {
+                    i += 1;
+                    while i < b.len() && b[i].is_ascii_whitespace() {
+                        i += 1;
+                    }
+                    if i < b.len() && (b[i] == b'"' || b[i] == b'\'') {
+                        let quote = b[i];
+                        let value_start = i + 1;
+                        i = value_start;
+                        while i < b.len() && b[i] != quote {
+                            i += 1;
+                        }
+                        if i >= b.len() {
+                            return None;
+                        }
+                        value = decode_entities(&s[value_start..i]);
+                        i += 1;
+                    } else {
+                        let value_start = i;
+                        while i < b.len() && !b[i].is_ascii_whitespace() && b[i] != b'>' {
+                            i += 1;
+                        }
+                        value = decode_entities(&s[value_start..i]);
+                    }
+                }
+                if !name.is_empty() && !attrs.iter().any(|(n, _): &(String, String)| *n == name) {
+                    attrs.push((name, value));
+                }
+            }
+        }
+    }
+}
+
+/// Add the text gathered in TEXT to the open element.
+fn add_text(stack: &mut [Element], text: &mut String) {
+    if !text.is_empty() {
+        push_text(stack, &decode_entities(text));
+        text.clear();
+    }
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    let parent = stack.last_mut().unwrap();
+    if let Some(Node::Text(last)) = parent.children.last_mut() {
+        last.push_str(text);
+    } else {
+        parent.children.push(Node::Text(text.to_string()));
+    }
+}
+
+/// Close the open element, adding it to its parent.
+fn pop(stack: &mut Vec<Element>) {
+    let element = stack.pop().unwrap();
+    stack.last_mut().unwrap().children.push(Node::Element(element));
+}
+
+/// Close the innermost open element named one of NAMES, and those in
+/// it, unless one of BOUNDARIES is nearer.
+fn close_implied(stack: &mut Vec<Element>, names: &[&str], boundaries: &[&str]) {
+    for depth in (1..stack.len()).rev() {
+        let tag = stack[depth].tag.as_str();
+        if names.contains(&tag) {
+            while stack.len() > depth {
+                pop(stack);
+            }
+            return;
+        }
+        if boundaries.contains(&tag) {
+            return;
+        }
+    }
+}
+
+fn open(stack: &mut Vec<Element>, tag: String, attrs: Vec<(String, String)>, self_closing: bool) {
+    // End tags HTML lets authors leave out
+    match tag.as_str() {
+        "li" => close_implied(stack, &["li"], &["ul", "ol", "menu", "table"]),
+        "dt" | "dd" => close_implied(stack, &["dt", "dd"], &["dl", "table"]),
+        "tr" => close_implied(stack, &["tr"], &["table"]),
+        "td" | "th" => close_implied(stack, &["td", "th"], &["tr", "table"]),
+        "thead" | "tbody" | "tfoot" => close_implied(stack, &["thead", "tbody", "tfoot"], &["table"]),
+        "option" => close_implied(stack, &["option"], &["select"]),
+        _ => {}
+    }
+    if CLOSES_PARAGRAPH.contains(&tag.as_str()) {
+        close_implied(stack, &["p"], &["table", "td", "th", "li", "button"]);
+    }
+    let element = Element::new(&tag, attrs);
+    if self_closing || VOID_ELEMENTS.contains(&tag.as_str()) {
+        stack.last_mut().unwrap().children.push(Node::Element(element));
+    } else {
+        stack.push(element);
+    }
+}
+
+/// Close the innermost open element named NAME; stray end tags are
+/// ignored.
+fn close(stack: &mut Vec<Element>, name: &str) {
+    if let Some(depth) = (1..stack.len()).rev().find(|&d| stack[d].tag == name) {
+        while stack.len() > depth {
+            pop(stack);
+        }
+    }
+}
+
+/// Replace character references in S.
+pub fn decode_entities(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let end = rest[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#'))
+            .map_or(rest.len(), |e| e + 1);
+        let name = &rest[1..end];
+        let decoded = match name.strip_prefix('#') {
+            Some(number) => {
+                let code = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => number.parse().ok(),
+                };
+                code.map(|c| char::from_u32(c).filter(|&c| c != '\0').unwrap_or('\u{fffd}'))
+            }
+            None => named_entity(name),
+        };
+        match decoded {
+            Some(ch) => {
+                out.push(ch);
+                rest = &rest[end..];
+                rest = rest.strip_prefix(';').unwrap_or(rest);
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn named_entity(name: &str) -> Option<char> {
+    Some(match name {
+        "amp" | "AMP" => '&',
+        "lt" | "LT" => '<',
+        "gt" | "GT" => '>',
+        "quot" | "QUOT" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "shy" => '\u{ad}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "sbquo" => '‚',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bdquo" => '„',
+        "laquo" => '«',
+        "raquo" => '»',
+        "bull" => '•',
+        "middot" => '·',
+        "deg" => '°',
+        "plusmn" => '±',
+        "times" => '×',
+        "divide" => '÷',
+        "euro" => '€',
+        "pound" => '£',
+        "yen" => '¥',
+        "cent" => '¢',
+        "sect" => '§',
+        "para" => '¶',
+        "larr" => '←',
+        "rarr" => '→',
+        "uarr" => '↑',
+        "darr" => '↓',
+        "harr" => '↔',
+        "check" => '✓',
+        "zwj" => '\u{200d}',
+        "zwnj" => '\u{200c}',
+        "ensp" => '\u{2002}',
+        "emsp" => '\u{2003}',
+        "thinsp" => '\u{2009}',
+        _ => return None,
+    })
+}
+
+// ============================================================================
+// Styles
+// ============================================================================
+
+/// How a run of text looks, for Lisp to map to faces
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strike: bool,
+    /// Monospace: code, preformatted text
+    pub code: bool,
+    /// Heading level 1-6, 0 for body text
+    pub heading: u8,
+    /// Colors from the document, as 0xRRGGBB
+    pub fg: Option<u32>,
+    pub bg: Option<u32>,
+    /// Target of the link the text is in
+    pub link: Option<String>,
+    /// The text is the alternative text of this image
+    pub image: Option<ImageRef>,
+}
+
+/// An image in the document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageRef {
+    pub src: String,
+    /// Size from the `width' and `height' attributes, 0 if unknown
+    pub width: u32,
+    pub height: u32,
+}
+
+/// One step of a selector: `tag.class#id`, any part optional
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+impl Compound {
+    fn parse(text: &str) -> Option<Self> {
+        let mut compound = Compound::default();
+        let mut parts = text.split_inclusive(['.', '#']).peekable();
+        // "div.note#x" splits as "div." "note#" "x"; track the
+        // delimiter that started each part
+        let mut kind = ' ';
+        while let Some(part) = parts.next() {
+            let (name, next) = match part.strip_suffix(['.', '#']) {
+                Some(name) if parts.peek().is_some() => (name, part.chars().last().unwrap()),
+                Some(_) => return None,
+                None => (part, ' '),
+            };
+            if !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == '*') {
+                return None;
+            }
+            match kind {
+                '.' => compound.classes.push(name.to_string()),
+                '#' => compound.id = Some(name.to_string()),
+                _ if name.is_empty() || name == "*" => {}
+                _ => compound.tag = Some(name.to_ascii_lowercase()),
+            }
+            kind = next;
+        }
+        Some(compound)
+    }
+
+    fn matches(&self, element: &Element) -> bool {
+        self.tag.as_ref().is_none_or(|tag| *tag == element.tag)
+            && self.id.as_ref().is_none_or(|id| element.attr("id") == Some(id.as_str()))
+            && self.classes.iter().all(|class| element.has_class(class))
+    }
+
+    fn specificity(&self) -> u32 {
+        self.id.is_some() as u32 * 100 + self.classes.len() as u32 * 10 + self.tag.is_some() as u32
+    }
+}
+
+/// A rule of a style sheet
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    /// Compounds from the outermost ancestor to the element
+    selector: Vec<Compound>,
+    declarations: Vec<(String, String)>,
+}
+
+impl Rule {
+    fn matches(&self, element: &Element, ancestors: &[&Element]) -> bool {
+        let Some((last, outer)) = self.selector.split_last() else { return false };
+        if !last.matches(element) {
+            return false;
+        }
+        // Each outer compound must match some ancestor, innermost first
+        let mut ancestors = ancestors.iter().rev();
+        outer.iter().rev().all(|compound| ancestors.any(|a| compound.matches(a)))
+    }
+
+    fn specificity(&self) -> u32 {
+        self.selector.iter().map(Compound::specificity).sum()
+    }
+}
+
+/// Rules from `<style>` elements
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stylesheet {
+    rules: Vec<Rule>,
+}
+
+impl Stylesheet {
+    /// Parse CSS, keeping the rules this renderer understands: those
+    /// whose selectors are type, class and id selectors, possibly of
+    /// descendants.  At-rules such as @media are skipped.
+    pub fn parse(css: &str) -> Self {
+        let css = strip_comments(css);
+        let mut rules = Vec::new();
+        let mut rest = css.as_str();
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break;
+            }
+            if rest.starts_with('@') {
+                let block = rest.find('{');
+                let semicolon = rest.find(';');
+                rest = match (block, semicolon) {
+                    (Some(b), Some(s)) if s < b => &rest[s + 1..],
+                    (Some(b), _) => &rest[b + skip_block(&rest[b..])..],
+                    (None, Some(s)) => &rest[s + 1..],
+                    (None, None) => "",
+                };
+                continue;
+            }
+            let Some(open) = rest.find('{') else { break };
+            let close = rest[open..].find('}').map_or(rest.len(), |c| open + c);
+            let declarations = parse_declarations(&rest[open + 1..close]);
+            for selector in rest[..open].split(',') {
+                let compounds: Option<Vec<Compound>> = selector.split_ascii_whitespace().map(Compound::parse).collect();
+                if let Some(compounds) = compounds.filter(|c| !c.is_empty()) {
+                    rules.push(Rule { selector: compounds, declarations: declarations.clone() });
+                }
+            }
+            rest = rest.get(close + 1..).unwrap_or("");
+        }
+        Stylesheet { rules }
+    }
+
+    /// Add the rules of another style sheet after these.
+    pub fn extend(&mut self, other: Stylesheet) {
+        self.rules.extend(other.rules);
+    }
+
+    /// Declarations for ELEMENT inside ANCESTORS, least specific first.
+    fn declarations(&self, element: &Element, ancestors: &[&Element]) -> Vec<(String, String)> {
+        let mut matched: Vec<&Rule> = self.rules.iter().filter(|r| r.matches(element, ancestors)).collect();
+        matched.sort_by_key(|r| r.specificity());
+        matched.iter().flat_map(|r| r.declarations.iter().cloned()).collect()
+    }
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(&rest[..start]);
+        rest = rest[start + 2..].find("*/").map_or("", |e| &rest[start + 4 + e..]);
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Length of the brace-balanced block TEXT starts with.
+fn skip_block(text: &str) -> usize {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// The `property: value` pairs of a declaration block or a `style`
+/// attribute.
+fn parse_declarations(text: &str) -> Vec<(String, String)> {
+    text.split(';')
+        .filter_map(|decl| {
+            let (property, value) = decl.split_once(':')?;
+            let value = value.trim();
+            let value = value.strip_suffix("!important").unwrap_or(value).trim();
+            Some((property.trim().to_ascii_lowercase(), value.to_ascii_lowercase()))
+        })
+        .filter(|(p, v)| !p.is_empty() && !v.is_empty())
+        .collect()
+}
+
+/// A CSS color as 0xRRGGBB; None for colors it cannot show, such as
+/// `transparent' or `inherit'.
+pub fn parse_color(value: &str) -> Option<u32> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits: Vec<u32> = hex.chars().map(|c| c.to_digit(16)).collect::<Option<_>>()?;
+        return match digits.len() {
+            3 | 4 => Some(digits[..3].iter().fold(0, |acc, d| (acc << 8) | (d * 17))),
+            6 | 8 => Some(digits[..6].iter().fold(0, |acc, d| (acc << 4) | d)),
+            _ => None,
+        };
+    }
+    if let Some(args) = value.strip_prefix("rgb(").or_else(|| value.strip_prefix("rgba(")) {
+        let channels: Vec<u32> = args
+            .trim_end_matches(')')
+            .split([',', ' ', '/'])
+            .filter(|s| !s.is_empty())
+            .take(3)
+            .map(|s| match s.strip_suffix('%') {
+                Some(pct) => pct.parse::<f32>().ok().map(|p| (p.clamp(0.0, 100.0) * 2.55).round() as u32),
+                None => s.parse::<f32>().ok().map(|v| v.clamp(0.0, 255.0).round() as u32),
+            })
+            .collect::<Option<_>>()?;
+        return (channels.len() == 3).then(|| (channels[0] << 16) | (channels[1] << 8) | channels[2]);
+    }
+    Some(match value {
+        "black" => 0x000000,
+        "white" => 0xffffff,
+        "red" => 0xff0000,
+        "green" => 0x008000,
+        "lime" => 0x00ff00,
+        "blue" => 0x0000ff,
+        "yellow" => 0xffff00,
+        "orange" => 0xffa500,
+        "purple" => 0x800080,
+        "fuchsia" | "magenta" => 0xff00ff,
+        "aqua" | "cyan" => 0x00ffff,
+        "gray" | "grey" => 0x808080,
+        "silver" => 0xc0c0c0,
+        "maroon" => 0x800000,
+        "navy" => 0x000080,
+        "olive" => 0x808000,
+        "teal" => 0x008080,
+        _ => return None,
+    })
+}
+
+/// Style of an element, given its parent's
+#[derive(Debug, Clone, Default)]
+struct Computed {
+    style: TextStyle,
+    hidden: bool,
+    pre: bool,
+}
+
+fn compute(element: &Element, parent: &Computed, sheet: &Stylesheet, ancestors: &[&Element]) -> Computed {
+    let mut c = Computed { style: parent.style.clone(), hidden: false, pre: parent.pre };
+    // The image of a parent is not that of its children
+    c.style.image = None;
+    let style = &mut c.style;
+    match element.tag.as_str() {
+        "b" | "strong" | "th" | "dt" | "summary" => style.bold = true,
+        "i" | "em" | "cite" | "var" | "dfn" | "address" => style.italic = true,
+        "u" | "ins" => style.underline = true,
+        "s" | "strike" | "del" => style.strike = true,
+        "code" | "kbd" | "samp" | "tt" => style.code = true,
+        "pre" | "listing" | "xmp" => {
+            style.code = true;
+            c.pre = true;
+        }
+        "a" => {
+            if let Some(href) = element.attr("href").filter(|h| !h.is_empty()) {
+                style.link = Some(href.trim().to_string());
+            }
+        }
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            style.heading = element.tag.as_bytes()[1] - b'0';
+            style.bold = true;
+        }
+        "head" | "script" | "style" | "template" | "title" | "select" | "datalist" => c.hidden = true,
+        _ => {}
+    }
+    if element.attr("hidden").is_some() {
+        c.hidden = true;
+    }
+    let mut declarations = sheet.declarations(element, ancestors);
+    if let Some(inline) = element.attr("style") {
+        declarations.extend(parse_declarations(inline));
+    }
+    for (property, value) in &declarations {
+        let style = &mut c.style;
+        match property.as_str() {
+            "display" => c.hidden = value == "none",
+            "visibility" => c.hidden = value == "hidden" || value == "collapse",
+            "font-weight" => {
+                style.bold = matches!(value.as_str(), "bold" | "bolder")
+                    || value.parse::<u32>().is_ok_and(|w| w >= 600);
+            }
+            "font-style" => style.italic = value == "italic" || value == "oblique",
+            "text-decoration" | "text-decoration-line" => {
+                if value.contains("none") {
+                    style.underline = false;
+                    style.strike = false;
+                }
+                style.underline |= value.contains("underline");
+                style.strike |= value.contains("line-through");
+            }
+            "font-family" if value.contains("mono") || value.contains("courier") => style.code = true,
+            "color" => style.fg = parse_color(value).or(style.fg),
+            "background-color" | "background" => {
+                if let Some(color) = parse_color(value).or_else(|| value.split_ascii_whitespace().find_map(parse_color)) {
+                    style.bg = Some(color);
+                }
+            }
+            "white-space" => c.pre = value.starts_with("pre"),
+            _ => {}
+        }
+    }
+    c
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+/// A run of text in one style, in characters from the start
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyledRun {
+    pub start: usize,
+    pub end: usize,
+    pub style: TextStyle,
+}
+
+/// A document rendered as text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HtmlDocument {
+    /// Lines filled to the width, each ending in a newline
+    pub text: String,
+    /// Styled runs, in order and not overlapping; plain text has none
+    pub runs: Vec<StyledRun>,
+    /// Contents of `<title>'
+    pub title: Option<String>,
+}
+
+/// Elements laid out as blocks of their own
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "body", "center", "dd", "details", "dl", "div",
+    "dt", "fieldset", "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6",
+    "header", "html", "legend", "li", "listing", "main", "nav", "ol", "p", "pre", "section",
+    "summary", "ul", "xmp", "caption",
+];
+
+/// Blocks set off by blank lines
+const SPACED_BLOCKS: &[&str] = &[
+    "blockquote", "details", "dl", "figure", "h1", "h2", "h3", "h4", "h5", "h6", "ol", "p", "pre",
+    "ul", "listing", "xmp",
+];
+
+/// Text between columns of a table
+const COLUMN_SEPARATOR: &str = " │ ";
+
+/// Render the document HTML as text filled to WIDTH columns.
+pub fn render(html: &str, width: usize) -> HtmlDocument {
+    let root = parse(html);
+    let mut sheet = Stylesheet::default();
+    let mut title = None;
+    collect_head(&root, &mut sheet, &mut title);
+    let mut renderer = Renderer::new(width.max(10), &sheet);
+    let mut ancestors = Vec::new();
+    renderer.walk(&root, &Computed::default(), &mut ancestors);
+    renderer.flush();
+    let mut text = renderer.text;
+    while text.ends_with("\n\n") {
+        text.pop();
+    }
+    HtmlDocument { text, runs: renderer.runs, title }
+}
+
+/// Gather the style sheets and the title, wherever they are.
+fn collect_head(element: &Element, sheet: &mut Stylesheet, title: &mut Option<String>) {
+    for child in element.elements() {
+        match child.tag.as_str() {
+            "style" => {
+                for node in &child.children {
+                    if let Node::Text(css) = node {
+                        sheet.extend(Stylesheet::parse(css));
+                    }
+                }
+            }
+            "title" if title.is_none() => {
+                let text = text_content(child);
+                let text = collapse_whitespace(&text);
+                if !text.is_empty() {
+                    *title = Some(text);
+                }
+            }
+            _ => collect_head(child, sheet, title),
+        }
+    }
+}
+
+fn text_content(element: &Element) -> String {
+    element
+        .children
+        .iter()
+        .map(|n| match n {
+            Node::Text(t) => t.clone(),
+            Node::Element(e) => text_content(e),
+        })
+        .collect()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn text_width(text: &str) -> usize {
+    text.chars().map(char_display_width).sum()
+}
+
+/// Inline content: text in a style
+type Fragment = (String, TextStyle);
+
+/// Cells of a table row: content and whether it is a header
+type Row = Vec<(Vec<Fragment>, bool)>;
+
+/// Something to fill lines with
+enum Token {
+    /// Pieces of one word, which may change style midway
+    Word(Vec<Fragment>, usize),
+    /// A space, in the style of the text it was in
+    Space(TextStyle),
+    /// A forced line break
+    Break,
+}
+
+fn tokenize(fragments: &[Fragment]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word: Vec<Fragment> = Vec::new();
+    let mut word_width = 0;
+    let end_word = |tokens: &mut Vec<Token>, word: &mut Vec<Fragment>, width: &mut usize| {
+        if !word.is_empty() {
+            tokens.push(Token::Word(std::mem::take(word), *width));
+            *width = 0;
+        }
+    };
+    for (text, style) in fragments {
+        if text == "\n" {
+            end_word(&mut tokens, &mut word, &mut word_width);
+            tokens.push(Token::Break);
+            continue;
+        }
+        if style.image.is_some() {
+            // Alternative text stays on one line
+            word.push((text.clone(), style.clone()));
+            word_width += text_width(text);
+            continue;
+        }
+        let mut piece = String::new();
+        for ch in text.chars() {
+            if ch.is_ascii_whitespace() {
+                if !piece.is_empty() {
+                    word_width += text_width(&piece);
+                    word.push((std::mem::take(&mut piece), style.clone()));
+                }
+                end_word(&mut tokens, &mut word, &mut word_width);
+                if !matches!(tokens.last(), Some(Token::Space(_))) {
+                    tokens.push(Token::Space(style.clone()));
+                }
+            } else if ch != '\u{ad}' {
+                piece.push(ch);
+            }
+        }
+        if !piece.is_empty() {
+            word_width += text_width(&piece);
+            word.push((piece, style.clone()));
+        }
+    }
+    end_word(&mut tokens, &mut word, &mut word_width);
+    tokens
+}
+
+/// Fill FRAGMENTS into lines at most WIDTH columns wide; words longer
+/// than that get a line of their own.
+fn fill(fragments: &[Fragment], width: usize) -> Vec<Vec<Fragment>> {
+    let mut lines = Vec::new();
+    let mut line: Vec<Fragment> = Vec::new();
+    let mut line_width = 0;
+    let mut space: Option<TextStyle> = None;
+    for token in tokenize(fragments) {
+        match token {
+            Token::Space(style) => {
+                if line_width > 0 {
+                    space = Some(style);
+                }
+            }
+            Token::Break => {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+                space = None;
+            }
+            Token::Word(pieces, word_width) => {
+                if line_width > 0 && line_width + 1 + word_width > width {
+                    lines.push(std::mem::take(&mut line));
+                    line_width = 0;
+                    space = None;
+                }
+                if let Some(style) = space.take() {
+                    line.push((" ".to_string(), style));
+                    line_width += 1;
+                }
+                line.extend(pieces);
+                line_width += word_width;
+            }
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Split preformatted FRAGMENTS into their lines, tabs expanded.
+fn pre_lines(fragments: &[Fragment]) -> Vec<Vec<Fragment>> {
+    let mut lines = vec![Vec::new()];
+    let mut column = 0;
+    for (text, style) in fragments {
+        let mut piece = String::new();
+        for ch in text.chars() {
+            match ch {
+                '\n' => {
+                    if !piece.is_empty() {
+                        lines.last_mut().unwrap().push((std::mem::take(&mut piece), style.clone()));
+                    }
+                    lines.push(Vec::new());
+                    column = 0;
+                }
+                '\r' => {}
+                '\t' => {
+                    let spaces = 8 - column % 8;
+                    piece.extend(std::iter::repeat_n(' ', spaces));
+                    column += spaces;
+                }
+                _ => {
+                    piece.push(ch);
+                    column += char_display_width(ch);
+                }
+            }
+        }
+        if !piece.is_empty() {
+            lines.last_mut().unwrap().push((piece, style.clone()));
+        }
+    }
+    // A newline right after <pre> and the one before </pre> are not lines
+    if lines.first().is_some_and(|l| l.is_empty()) && lines.len() > 1 {
+        lines.remove(0);
+    }
+    if lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+fn line_width(line: &[Fragment]) -> usize {
+    line.iter().map(|(t, _)| text_width(t)).sum()
+}
+
+struct Renderer<'s> {
+    width: usize,
+    sheet: &'s Stylesheet,
+    text: String,
+    /// Characters in `text`
+    chars: usize,
+    runs: Vec<StyledRun>,
+    /// Inline content of the block being filled
+    inline: Vec<Fragment>,
+    /// Whether that block is preformatted
+    inline_pre: bool,
+    /// What starts each line: indentation and quote bars
+    margin: Vec<String>,
+    /// A list item marker to show in place of the margin piece at this
+    /// depth, on the next line
+    marker: Option<(usize, String)>,
+    /// Open lists: the next number of each ordered one
+    lists: Vec<Option<(usize, usize)>>,
+    /// A blank line is due before the next line
+    want_blank: bool,
+    /// Inside a table cell: blocks end with a line break in `inline`
+    /// instead of being output
+    in_cell: usize,
+}
+
+impl<'s> Renderer<'s> {
+    fn new(width: usize, sheet: &'s Stylesheet) -> Self {
+        Renderer {
+            width,
+            sheet,
+            text: String::new(),
+            chars: 0,
+            runs: Vec::new(),
+            inline: Vec::new(),
+            inline_pre: false,
+            margin: Vec::new(),
+            marker: None,
+            lists: Vec::new(),
+            want_blank: false,
+            in_cell: 0,
+        }
+    }
+
+    fn margin_width(&self) -> usize {
+        self.margin.iter().map(|m| text_width(m)).sum()
+    }
+
+    fn push_text(&mut self, text: &str, style: Option<&TextStyle>) {
+        let start = self.chars;
+        self.text.push_str(text);
+        self.chars += text.chars().count();
+        let Some(style) = style.filter(|s| **s != TextStyle::default()) else { return };
+        match self.runs.last_mut() {
+            Some(run) if run.end == start && run.style == *style => run.end = self.chars,
+            _ => self.runs.push(StyledRun { start, end: self.chars, style: style.clone() }),
+        }
+    }
+
+    /// Output LINE after the margin.
+    fn emit_line(&mut self, line: &[Fragment]) {
+        if self.want_blank {
+            if !self.text.is_empty() && !self.text.ends_with("\n\n") {
+                self.push_text("\n", None);
+            }
+            self.want_blank = false;
+        }
+        let mut margin = self.margin.clone();
+        if let Some((depth, marker)) = self.marker.take() {
+            if let Some(piece) = margin.get_mut(depth) {
+                *piece = marker;
+            }
+        }
+        let prefix: String = margin.concat();
+        self.push_text(prefix.trim_end_matches(' '), None);
+        if !line.is_empty() {
+            self.push_text(&prefix[prefix.trim_end_matches(' ').len()..], None);
+        }
+        for (text, style) in line {
+            self.push_text(&text.replace('\u{a0}', " "), Some(style));
+        }
+        self.push_text("\n", None);
+    }
+
+    /// Output the block being filled.
+    fn flush(&mut self) {
+        if self.in_cell > 0 {
+            if self.inline.last().is_some_and(|(t, _)| t != "\n") {
+                self.inline.push(("\n".to_string(), TextStyle::default()));
+            }
+            return;
+        }
+        let inline = std::mem::take(&mut self.inline);
+        let lines = if self.inline_pre {
+            pre_lines(&inline)
+        } else if inline.iter().all(|(t, s)| s.image.is_none() && t.chars().all(|c| c.is_ascii_whitespace())) {
+            Vec::new()
+        } else {
+            fill(&inline, self.width.saturating_sub(self.margin_width()).max(10))
+        };
+        for line in &lines {
+            self.emit_line(line);
+        }
+    }
+
+    fn walk<'t>(&mut self, element: &'t Element, computed: &Computed, ancestors: &mut Vec<&'t Element>) {
+        ancestors.push(element);
+        for child in &element.children {
+            match child {
+                Node::Text(text) => {
+                    if computed.pre {
+                        self.inline_pre = true;
+                    }
+                    self.inline.push((text.clone(), computed.style.clone()));
+                }
+                Node::Element(child) => {
+                    let child_computed = compute(child, computed, self.sheet, ancestors);
+                    if !child_computed.hidden {
+                        self.element(child, &child_computed, ancestors);
+                    }
+                }
+            }
+        }
+        ancestors.pop();
+    }
+
+    fn element<'t>(&mut self, element: &'t Element, computed: &Computed, ancestors: &mut Vec<&'t Element>) {
+        let tag = element.tag.as_str();
+        match tag {
+            "br" => self.inline.push(("\n".to_string(), computed.style.clone())),
+            "img" => {
+                let alt = element.attr("alt").map(collapse_whitespace).unwrap_or_default();
+                let dimension = |name| element.attr(name).and_then(|v| v.trim_end_matches("px").parse().ok());
+                let mut style = computed.style.clone();
+                style.image = Some(ImageRef {
+                    src: element.attr("src").unwrap_or("").to_string(),
+                    width: dimension("width").unwrap_or(0),
+                    height: dimension("height").unwrap_or(0),
+                });
+                let text = if alt.is_empty() { "[image]".to_string() } else { format!("[{}]", alt) };
+                self.inline.push((text, style));
+            }
+            "hr" => {
+                self.flush();
+                if self.in_cell == 0 {
+                    let rule = "─".repeat(self.width.saturating_sub(self.margin_width()).max(1));
+                    self.emit_line(&[(rule, computed.style.clone())]);
+                }
+            }
+            "table" if self.in_cell == 0 => {
+                self.flush();
+                self.want_blank = true;
+                self.table(element, computed, ancestors);
+                self.want_blank = true;
+            }
+            "input" => {
+                let value = element.attr("value").or(element.attr("placeholder")).unwrap_or("");
+                if !value.is_empty() && element.attr("type") != Some("hidden") {
+                    self.inline.push((format!("[{}]", value), computed.style.clone()));
+                }
+            }
+            _ if BLOCK_ELEMENTS.contains(&tag) || (self.in_cell == 0 && matches!(tag, "tr" | "td" | "th")) => {
+                self.block(element, computed, ancestors);
+            }
+            _ => self.walk(element, computed, ancestors),
+        }
+    }
+
+    fn block<'t>(&mut self, element: &'t Element, computed: &Computed, ancestors: &mut Vec<&'t Element>) {
+        let tag = element.tag.as_str();
+        let nested_list = matches!(tag, "ul" | "ol") && !self.lists.is_empty();
+        let spaced = SPACED_BLOCKS.contains(&tag) && !nested_list;
+        self.flush();
+        if spaced {
+            self.want_blank = true;
+        }
+        let margin_depth = self.margin.len();
+        let was_pre = std::mem::replace(&mut self.inline_pre, computed.pre);
+        match tag {
+            "ul" => {
+                self.lists.push(None);
+                self.margin.push("  ".into());
+            }
+            "ol" => {
+                let start = element.attr("start").and_then(|s| s.trim().parse().ok()).unwrap_or(1);
+                let items = element.elements().filter(|e| e.tag == "li").count().max(1);
+                let digits = (start + items - 1).to_string().len();
+                self.lists.push(Some((start, digits)));
+                self.margin.push("  ".into());
+            }
+            "li" => {
+                let marker = match self.lists.last_mut() {
+                    Some(Some((next, digits))) => {
+                        let number = element.attr("value").and_then(|v| v.trim().parse().ok()).unwrap_or(*next);
+                        *next = number + 1;
+                        format!("{:>width$}. ", number, width = *digits)
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.margin.push(" ".repeat(text_width(&marker)));
+                self.marker = Some((margin_depth, marker));
+            }
+            "blockquote" => self.margin.push("│ ".into()),
+            "dd" => self.margin.push("    ".into()),
+            _ => {}
+        }
+        self.walk(element, computed, ancestors);
+        self.flush();
+        self.inline_pre = was_pre;
+        self.margin.truncate(margin_depth);
+        if matches!(tag, "ul" | "ol") {
+            self.lists.pop();
+        }
+        if tag == "li" {
+            self.marker = None;
+        }
+        if spaced {
+            self.want_blank = true;
+        }
+    }
+
+    /// Inline content of a table cell, each of its blocks ending in a
+    /// line break.
+    fn cell<'t>(&mut self, cell: &'t Element, computed: &Computed, ancestors: &mut Vec<&'t Element>) -> Vec<Fragment> {
+        let saved = std::mem::take(&mut self.inline);
+        self.in_cell += 1;
+        self.walk(cell, computed, ancestors);
+        self.in_cell -= 1;
+        let mut content = std::mem::replace(&mut self.inline, saved);
+        while content.last().is_some_and(|(t, _)| t == "\n") {
+            content.pop();
+        }
+        content
+    }
+
+    /// Add the cells of TR to ROWS.
+    fn row<'t>(&mut self, tr: &'t Element, computed: &Computed, ancestors: &mut Vec<&'t Element>, rows: &mut Vec<Row>) {
+        ancestors.push(tr);
+        let mut row = Vec::new();
+        for cell in tr.elements().filter(|c| c.tag == "td" || c.tag == "th") {
+            let cell_computed = compute(cell, computed, self.sheet, ancestors);
+            if !cell_computed.hidden {
+                row.push((self.cell(cell, &cell_computed, ancestors), cell.tag == "th"));
+            }
+        }
+        ancestors.pop();
+        if !row.is_empty() {
+            rows.push(row);
+        }
+    }
+
+    fn table<'t>(&mut self, table: &'t Element, computed: &Computed, ancestors: &mut Vec<&'t Element>) {
+        let mut rows: Vec<Row> = Vec::new();
+        let mut caption = None;
+        ancestors.push(table);
+        for child in table.elements() {
+            let child_computed = compute(child, computed, self.sheet, ancestors);
+            if child_computed.hidden {
+                continue;
+            }
+            match child.tag.as_str() {
+                "caption" => caption = Some(self.cell(child, &child_computed, ancestors)),
+                "thead" | "tbody" | "tfoot" => {
+                    ancestors.push(child);
+                    for tr in child.elements().filter(|e| e.tag == "tr") {
+                        let tr_computed = compute(tr, &child_computed, self.sheet, ancestors);
+                        if !tr_computed.hidden {
+                            self.row(tr, &tr_computed, ancestors, &mut rows);
+                        }
+                    }
+                    ancestors.pop();
+                }
+                "tr" => self.row(child, &child_computed, ancestors, &mut rows),
+                _ => {}
+            }
+        }
+        ancestors.pop();
+
+        if let Some(caption) = caption.filter(|c| !c.is_empty()) {
+            for line in fill(&caption, self.width.saturating_sub(self.margin_width()).max(10)) {
+                self.emit_line(&line);
+            }
+        }
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+
+        // Natural and smallest widths of each column
+        let mut natural = vec![0usize; columns];
+        let mut smallest = vec![1usize; columns];
+        for row in &rows {
+            for (c, (content, _)) in row.iter().enumerate() {
+                for line in fill(content, usize::MAX) {
+                    natural[c] = natural[c].max(line_width(&line));
+                }
+                for token in tokenize(content) {
+                    if let Token::Word(_, w) = token {
+                        smallest[c] = smallest[c].max(w.min(20));
+                    }
+                }
+            }
+        }
+        let available = self
+            .width
+            .saturating_sub(self.margin_width() + text_width(COLUMN_SEPARATOR) * (columns - 1))
+            .max(columns);
+        let mut widths: Vec<usize> = natural.iter().map(|&w| w.max(1)).collect();
+        // Narrow the widest columns until the table fits
+        while widths.iter().sum::<usize>() > available {
+            let (widest, &w) = widths
+                .iter()
+                .enumerate()
+                .filter(|&(c, &w)| w > smallest[c].min(w))
+                .max_by_key(|&(_, &w)| w)
+                .unwrap_or((0, &0));
+            if w == 0 || w <= smallest[widest] {
+                break;
+            }
+            widths[widest] -= 1;
+        }
+
+        let header_rows = rows.iter().take_while(|row| row.iter().all(|(_, header)| *header)).count();
+        for (r, row) in rows.iter().enumerate() {
+            let cells: Vec<Vec<Vec<Fragment>>> = row.iter().enumerate().map(|(c, (content, _))| fill(content, widths[c])).collect();
+            let height = cells.iter().map(Vec::len).max().unwrap_or(0).max(1);
+            for l in 0..height {
+                let mut line: Vec<Fragment> = Vec::new();
+                for (c, &width) in widths.iter().enumerate() {
+                    if c > 0 {
+                        line.push((COLUMN_SEPARATOR.to_string(), TextStyle::default()));
+                    }
+                    let cell_line = cells.get(c).and_then(|cell| cell.get(l));
+                    let used = cell_line.map_or(0, |cl| line_width(cl));
+                    if let Some(cell_line) = cell_line {
+                        line.extend(cell_line.iter().cloned());
+                    }
+                    if c + 1 < columns && used < width {
+                        line.push((" ".repeat(width - used), TextStyle::default()));
+                    }
+                }
+                // No separators or padding after the last cell with text
+                while line.last().is_some_and(|(t, s)| *s == TextStyle::default() && (t.trim().is_empty() || t == COLUMN_SEPARATOR)) {
+                    line.pop();
+                }
+                self.emit_line(&line);
+            }
+            if r + 1 == header_rows && r + 1 < rows.len() {
+                let rule: Vec<String> = widths.iter().map(|&w| "─".repeat(w)).collect();
+                self.emit_line(&[(rule.join("─┼─"), TextStyle::default())]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styled<'d>(doc: &'d HtmlDocument, text: &str) -> Option<&'d TextStyle> {
+        let start = doc.text.find(text)?;
+        let start = doc.text[..start].chars().count();
+        doc.runs.iter().find(|r| r.start <= start && start < r.end).map(|r| &r.style)
+    }
+
+    #[test]
+    fn sloppy_html_parses_as_browsers_do() {
+        let root = parse("<!DOCTYPE html><p class=x>one<p>two &amp; <b>three</p><ul><li>a<li>b</ul></p>");
+        let tags: Vec<&str> = root.elements().map(|e| e.tag.as_str()).collect();
+        assert_eq!(tags, ["p", "p", "ul"]);
+        let second = root.elements().nth(1).unwrap();
+        assert_eq!(second.children[0], Node::Text("two & ".into()));
+        let items: Vec<String> = root.elements().nth(2).unwrap().elements().map(text_content).collect();
+        assert_eq!(items, ["a", "b"]);
+        assert_eq!(root.elements().next().unwrap().attr("class"), Some("x"));
+
+        assert_eq!(decode_entities("&lt;a&gt; &#233;&#x2014; &bogus; AT&T"), "<a> é— &bogus; AT&T");
+        let script = parse("<script>if (a < b) { x = '</p>' }</script>z");
+        assert_eq!(text_content(script.elements().next().unwrap()), "if (a < b) { x = '</p>' }");
+    }
+
+    #[test]
+    fn paragraphs_and_lists_are_filled_to_the_width() {
+        let doc = render(
+            "<h1>Title</h1><p>The quick brown fox jumps over the lazy dog.</p>\
+             <ul><li>one<li>two<ol start=9><li>nine<li>ten</ol></ul>",
+            24,
+        );
+        assert_eq!(
+            doc.text,
+            "Title\n\nThe quick brown fox\njumps over the lazy dog.\n\n  • one\n  • two\n       9. nine\n      10. ten\n"
+        );
+        let heading = styled(&doc, "Title").unwrap();
+        assert_eq!((heading.heading, heading.bold), (1, true));
+        assert_eq!(styled(&doc, "quick"), None);
+    }
+
+    #[test]
+    fn links_images_and_code_are_styled() {
+        let doc = render(
+            "<p>See <a href=\"/docs\">the <em>manual</em></a> and <code>M-x</code> <img src=a.png alt=\"logo\" width=32></p>\
+             <pre>\n  (setq x 1)\n\tdone\n</pre><blockquote>quoted <s>text</s></blockquote>",
+            60,
+        );
+        assert_eq!(doc.text, "See the manual and M-x [logo]\n\n  (setq x 1)\n        done\n\n│ quoted text\n");
+        assert_eq!(styled(&doc, "the").unwrap().link.as_deref(), Some("/docs"));
+        let manual = styled(&doc, "manual").unwrap();
+        assert!(manual.italic && manual.link.is_some());
+        assert!(styled(&doc, "M-x").unwrap().code);
+        let image = styled(&doc, "[logo]").unwrap().image.clone().unwrap();
+        assert_eq!((image.src.as_str(), image.width), ("a.png", 32));
+        assert!(styled(&doc, "(setq").unwrap().code);
+        assert!(styled(&doc, "text").unwrap().strike);
+        // The space inside the link is part of it
+        let the = doc.runs.iter().find(|r| r.style.link.is_some() && !r.style.italic).unwrap();
+        assert_eq!(&doc.text[the.start..the.end], "the ");
+    }
+
+    #[test]
+    fn style_sheets_set_faces_and_hide_elements() {
+        let doc = render(
+            "<html><head><title> Hello\n world </title><style>\
+             /* mail */ .note { color: #f00; font-weight: bold } div p.x { display: none }\
+             @media print { p { color: blue } } #big { background: rgb(0, 0, 255) }</style></head>\
+             <body><div><p class=x>hidden</p><p class=note>red</p></div>\
+             <p id=big style=\"font-style: italic\">blue</p></body></html>",
+            40,
+        );
+        assert_eq!(doc.title.as_deref(), Some("Hello world"));
+        assert_eq!(doc.text, "red\n\nblue\n");
+        let red = styled(&doc, "red").unwrap();
+        assert_eq!((red.fg, red.bold), (Some(0xff0000), true));
+        let blue = styled(&doc, "blue").unwrap();
+        assert_eq!((blue.bg, blue.italic, blue.fg), (Some(0x0000ff), true, None));
+        assert_eq!(parse_color("#abc"), Some(0xaabbcc));
+        assert_eq!(parse_color("transparent"), None);
+    }
+
+    #[test]
+    fn tables_line_up_and_wrap_to_fit() {
+        let doc = render(
+            "<table><tr><th>Name<th>Notes</tr>\
+             <tr><td>age<td>a file encryption tool</tr><tr><td>gpg</td></tr></table>",
+            24,
+        );
+        assert_eq!(
+            doc.text,
+            "Name │ Notes\n─────┼──────────────────\nage  │ a file encryption\n     │ tool\ngpg\n"
+        );
+        assert!(styled(&doc, "Notes").unwrap().bold);
+    }
+}
@@ -0,0 +1,191 @@
+//! Named icon glyphs from icon fonts (Nerd Fonts, all-the-icons).
+//!
+//! Icon fonts put their pictures in the Private Use Area, and the text
+//! font's fallback rarely finds the right one: the codepoint may exist
+//! in several icon fonts, or in none the fallback chain knows about.
+//! Registering an icon names the font its codepoint comes from, so the
+//! glyph atlas rasterizes it from that font directly.
+//!
+//! Icon fonts also disagree about the baseline: some sit their icons on
+//! it, others center them on the em box.  Each icon carries a vertical
+//! correction, as a fraction of the font size, and a scale, so icons
+//! line up with the text around them.  An icon may also have its own
+//! color, drawn instead of the face's foreground (color glyphs keep
+//! their own colors).
+
+use std::collections::HashMap;
+
+use crate::core::face::Face;
+use crate::core::types::Color;
+
+/// An icon registered by name
+#[derive(Debug, Clone, PartialEq)]
+pub struct IconSpec {
+    pub name: String,
+    pub codepoint: char,
+    /// Font family to draw it from (empty = the face's font)
+    pub family: String,
+    /// Move the icon up by this fraction of the font size (negative
+    /// moves it down)
+    pub y_offset: f32,
+    /// Size relative to the face's font size
+    pub scale: f32,
+    /// Color drawn instead of the face's foreground
+    pub color: Option<Color>,
+}
+
+impl IconSpec {
+    pub fn new(name: impl Into<String>, codepoint: char, family: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            codepoint,
+            family: family.into(),
+            y_offset: 0.0,
+            scale: 1.0,
+            color: None,
+        }
+    }
+
+    /// The face to rasterize the icon with, drawn in FACE (None for the
+    /// default font of DEFAULT_SIZE pixels)
+    pub fn raster_face(&self, face: Option<&Face>, default_size: f32) -> Face {
+        let mut raster = face.cloned().unwrap_or_else(|| {
+            let mut f = Face::new(0);
+            f.font_size = default_size;
+            f
+        });
+        if !self.family.is_empty() {
+            raster.font_family = self.family.clone();
+        }
+        raster.font_size *= self.scale;
+        raster
+    }
+
+    /// Pixels to move the icon up at FONT_SIZE
+    pub fn baseline_shift(&self, font_size: f32) -> f32 {
+        self.y_offset * font_size
+    }
+}
+
+/// Registered icons, by codepoint and by name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IconRegistry {
+    by_char: HashMap<char, IconSpec>,
+    by_name: HashMap<String, char>,
+}
+
+impl IconRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register SPEC, replacing any icon with its name or codepoint.
+    /// Returns the codepoints whose glyphs changed.
+    pub fn register(&mut self, spec: IconSpec) -> Vec<char> {
+        let mut changed = Vec::new();
+        if let Some(old) = self.by_name.get(&spec.name).copied() {
+            if old != spec.codepoint {
+                self.by_char.remove(&old);
+                changed.push(old);
+            }
+        }
+        if let Some(old) = self.by_char.get(&spec.codepoint) {
+            if old.name != spec.name {
+                self.by_name.remove(&old.name);
+            }
+        }
+        changed.push(spec.codepoint);
+        self.by_name.insert(spec.name.clone(), spec.codepoint);
+        self.by_char.insert(spec.codepoint, spec);
+        changed
+    }
+
+    /// Forget the icon NAME.  Returns its codepoint if it was registered.
+    pub fn remove(&mut self, name: &str) -> Option<char> {
+        let c = self.by_name.remove(name)?;
+        self.by_char.remove(&c);
+        Some(c)
+    }
+
+    /// Forget all icons.  Returns their codepoints.
+    pub fn clear(&mut self) -> Vec<char> {
+        self.by_name.clear();
+        self.by_char.drain().map(|(c, _)| c).collect()
+    }
+
+    /// The icon drawn for codepoint C
+    pub fn get(&self, c: char) -> Option<&IconSpec> {
+        self.by_char.get(&c)
+    }
+
+    /// The icon named NAME
+    pub fn lookup(&self, name: &str) -> Option<&IconSpec> {
+        self.by_name.get(name).and_then(|c| self.by_char.get(c))
+    }
+
+    /// Codepoints of all icons, in no particular order
+    pub fn codepoints(&self) -> impl Iterator<Item = char> + '_ {
+        self.by_char.keys().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_char.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_char.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_codepoints_stay_one_to_one() {
+        let mut icons = IconRegistry::new();
+        assert_eq!(icons.register(IconSpec::new("folder", '\u{f07b}', "Symbols Nerd Font")), vec!['\u{f07b}']);
+        icons.register(IconSpec::new("git", '\u{e702}', "Symbols Nerd Font"));
+
+        // Moving a name to another codepoint frees the old one
+        assert_eq!(
+            icons.register(IconSpec::new("folder", '\u{f115}', "Symbols Nerd Font")),
+            vec!['\u{f07b}', '\u{f115}']
+        );
+        assert!(icons.get('\u{f07b}').is_none());
+        assert_eq!(icons.lookup("folder").unwrap().codepoint, '\u{f115}');
+
+        // Taking a codepoint from another name drops that name
+        icons.register(IconSpec::new("branch", '\u{e702}', ""));
+        assert!(icons.lookup("git").is_none());
+        assert_eq!(icons.get('\u{e702}').unwrap().name, "branch");
+        assert_eq!(icons.len(), 2);
+
+        assert_eq!(icons.remove("branch"), Some('\u{e702}'));
+        assert_eq!(icons.remove("branch"), None);
+        assert_eq!(icons.clear(), vec!['\u{f115}']);
+        assert!(icons.is_empty());
+    }
+
+    #[test]
+    fn raster_face_uses_icon_font_and_scale() {
+        let mut spec = IconSpec::new("folder", '\u{f07b}', "Symbols Nerd Font");
+        spec.scale = 1.25;
+        spec.y_offset = -0.1;
+        let mut face = Face::new(4);
+        face.font_family = "JetBrains Mono".to_string();
+        face.font_size = 16.0;
+        face.font_weight = 700;
+
+        let raster = spec.raster_face(Some(&face), 13.0);
+        assert_eq!(raster.font_family, "Symbols Nerd Font");
+        assert_eq!(raster.font_size, 20.0);
+        assert_eq!(raster.font_weight, 700);
+        assert_eq!(spec.raster_face(None, 12.0).font_size, 15.0);
+        assert!((spec.baseline_shift(20.0) + 2.0).abs() < 1e-5);
+
+        // No family: the face's own font
+        let plain = IconSpec::new("dot", '\u{2022}', "");
+        assert_eq!(plain.raster_face(Some(&face), 13.0).font_family, "JetBrains Mono");
+    }
+}
@@ -0,0 +1,380 @@
+//! Non-destructive edits of displayed images.
+//!
+//! An image buffer keeps its file untouched and describes what was done
+//! to it as an `ImageEdits`: quarter turns, flips, a crop and a list of
+//! annotations (arrows, rectangles, text).  The image cache applies the
+//! edits to the decoded pixels before uploading the texture, so changing
+//! them only re-runs the decoder; exporting applies them again to the
+//! full-size image.
+//!
+//! Edits are applied in a fixed order: rotate, flip, crop, then draw the
+//! annotations, each as its own layer over the result.  Crop rectangles
+//! and annotation coordinates are fractions (0..1) of the image they
+//! apply to, so the same edits fit any decoded size of the image.
+
+/// A shape drawn over the image, in fractions of the edited image
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationShape {
+    /// Line from (X0, Y0) with an arrow head at (X1, Y1)
+    Arrow { x0: f32, y0: f32, x1: f32, y1: f32 },
+    /// Outline of the rectangle between (X0, Y0) and (X1, Y1)
+    Rect { x0: f32, y0: f32, x1: f32, y1: f32 },
+    /// TEXT with its top left corner at (X, Y)
+    Text { x: f32, y: f32, text: String },
+}
+
+/// One annotation layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageAnnotation {
+    pub shape: AnnotationShape,
+    /// sRGB color (0xRRGGBB)
+    pub color: u32,
+    /// Line width, or text size, as a fraction of the image's shorter side
+    pub size: f32,
+}
+
+/// Edits of one image
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImageEdits {
+    /// Quarter turns clockwise (0-3)
+    pub rotation: u8,
+    /// Mirror left to right, after rotating
+    pub flip_h: bool,
+    /// Mirror top to bottom, after rotating
+    pub flip_v: bool,
+    /// Part of the rotated and flipped image to keep, as (x, y, w, h)
+    pub crop: Option<[f32; 4]>,
+    /// Layers drawn over the cropped image, bottom first
+    pub annotations: Vec<ImageAnnotation>,
+}
+
+/// Coverage mask of rendered text (one byte of alpha per pixel)
+pub struct TextMask {
+    pub width: u32,
+    pub height: u32,
+    pub alpha: Vec<u8>,
+}
+
+impl ImageEdits {
+    /// Whether the edits leave an image unchanged
+    pub fn is_identity(&self) -> bool {
+        self.rotation.is_multiple_of(4)
+            && !self.flip_h
+            && !self.flip_v
+            && self.crop.is_none()
+            && self.annotations.is_empty()
+    }
+
+    /// Size of the rotated image, before cropping
+    fn rotated_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if self.rotation % 2 == 1 {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Crop rectangle in pixels of a rotated image of WIDTH x HEIGHT, as
+    /// (x, y, w, h); at least one pixel and inside the image.
+    fn crop_pixels(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let Some([x, y, w, h]) = self.crop else {
+            return (0, 0, width, height);
+        };
+        let span = |start: f32, len: f32, size: u32| {
+            let a = (start.clamp(0.0, 1.0) * size as f32).round() as u32;
+            let b = ((start + len).clamp(0.0, 1.0) * size as f32).round() as u32;
+            let a = a.min(size.saturating_sub(1));
+            (a, b.saturating_sub(a).max(1))
+        };
+        let (cx, cw) = span(x, w, width);
+        let (cy, ch) = span(y, h, height);
+        (cx, cy, cw, ch)
+    }
+
+    /// Size of a WIDTH x HEIGHT image after the edits
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (rw, rh) = self.rotated_size(width, height);
+        let (_, _, w, h) = self.crop_pixels(rw, rh);
+        (w, h)
+    }
+
+    /// Pixel of the source image shown at (X, Y) of the rotated and
+    /// flipped image, for a source of WIDTH x HEIGHT.
+    fn source_pixel(&self, x: u32, y: u32, width: u32, height: u32) -> (u32, u32) {
+        let (rw, rh) = self.rotated_size(width, height);
+        let x = if self.flip_h { rw - 1 - x } else { x };
+        let y = if self.flip_v { rh - 1 - y } else { y };
+        match self.rotation % 4 {
+            1 => (y, height - 1 - x),
+            2 => (width - 1 - x, height - 1 - y),
+            3 => (width - 1 - y, x),
+            _ => (x, y),
+        }
+    }
+
+    /// Apply the edits to RGBA pixels of a WIDTH x HEIGHT image.  TEXT
+    /// renders the string of a text annotation at a pixel size.  Returns
+    /// the edited image as (width, height, RGBA pixels).
+    pub fn apply(
+        &self,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        text: &mut dyn FnMut(&str, f32) -> Option<TextMask>,
+    ) -> (u32, u32, Vec<u8>) {
+        let (rw, rh) = self.rotated_size(width, height);
+        let (cx, cy, w, h) = self.crop_pixels(rw, rh);
+        let mut out = Vec::with_capacity((w * h * 4) as usize);
+        for y in cy..cy + h {
+            for x in cx..cx + w {
+                let (sx, sy) = self.source_pixel(x, y, width, height);
+                let i = ((sy * width + sx) * 4) as usize;
+                out.extend_from_slice(data.get(i..i + 4).unwrap_or(&[0, 0, 0, 0]));
+            }
+        }
+        for annotation in &self.annotations {
+            draw_annotation(&mut out, w, h, annotation, text);
+        }
+        (w, h, out)
+    }
+}
+
+/// Blend COLOR over pixel (X, Y) of IMAGE with opacity COVERAGE (0-1)
+fn blend(image: &mut [u8], width: u32, x: u32, y: u32, color: u32, coverage: f32) {
+    if coverage <= 0.0 {
+        return;
+    }
+    let a = coverage.min(1.0);
+    let i = ((y * width + x) * 4) as usize;
+    let rgb = [(color >> 16) & 0xff, (color >> 8) & 0xff, color & 0xff];
+    for (c, &v) in rgb.iter().enumerate() {
+        let old = image[i + c] as f32;
+        image[i + c] = (old + (v as f32 - old) * a).round() as u8;
+    }
+    let old = image[i + 3] as f32;
+    image[i + 3] = (old + (255.0 - old) * a).round() as u8;
+}
+
+/// Distance from (PX, PY) to the segment from (X0, Y0) to (X1, Y1)
+fn segment_distance(px: f32, py: f32, x0: f32, y0: f32, x1: f32, y1: f32) -> f32 {
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 > 0.0 {
+        (((px - x0) * dx + (py - y0) * dy) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (cx, cy) = (x0 + t * dx, y0 + t * dy);
+    ((px - cx).powi(2) + (py - cy).powi(2)).sqrt()
+}
+
+/// Coverage of the pixel centered at (PX, PY) by the convex polygon
+/// POINTS (clockwise in image coordinates), antialiased over one pixel.
+fn polygon_coverage(px: f32, py: f32, points: &[(f32, f32)]) -> f32 {
+    let mut inside = f32::MAX;
+    for (i, &(x0, y0)) in points.iter().enumerate() {
+        let (x1, y1) = points[(i + 1) % points.len()];
+        let (ex, ey) = (x1 - x0, y1 - y0);
+        let len = (ex * ex + ey * ey).sqrt();
+        if len == 0.0 {
+            continue;
+        }
+        // Signed distance, positive on the inner side of the edge
+        inside = inside.min(((px - x0) * ey - (py - y0) * ex) / -len);
+    }
+    (inside + 0.5).clamp(0.0, 1.0)
+}
+
+/// Draw one annotation layer over IMAGE of WIDTH x HEIGHT
+fn draw_annotation(
+    image: &mut [u8],
+    width: u32,
+    height: u32,
+    annotation: &ImageAnnotation,
+    text: &mut dyn FnMut(&str, f32) -> Option<TextMask>,
+) {
+    let (fw, fh) = (width as f32, height as f32);
+    let scale = fw.min(fh);
+    let stroke = (annotation.size * scale).max(1.0);
+    let half = stroke / 2.0;
+
+    // Segments stroked with round caps, and filled polygons
+    let mut segments: Vec<[f32; 4]> = Vec::new();
+    let mut polygons: Vec<Vec<(f32, f32)>> = Vec::new();
+    match &annotation.shape {
+        AnnotationShape::Arrow { x0, y0, x1, y1 } => {
+            let (ax, ay, bx, by) = (x0 * fw, y0 * fh, x1 * fw, y1 * fh);
+            let len = ((bx - ax).powi(2) + (by - ay).powi(2)).sqrt();
+            if len == 0.0 {
+                return;
+            }
+            let (ux, uy) = ((bx - ax) / len, (by - ay) / len);
+            let head = (stroke * 4.0).max(8.0).min(len);
+            let spread = head * 0.5;
+            let (hx, hy) = (bx - ux * head, by - uy * head);
+            // Stop the shaft inside the head so its cap doesn't show
+            segments.push([ax, ay, hx + ux * half, hy + uy * half]);
+            polygons.push(vec![
+                (bx, by),
+                (hx - uy * spread, hy + ux * spread),
+                (hx + uy * spread, hy - ux * spread),
+            ]);
+        }
+        AnnotationShape::Rect { x0, y0, x1, y1 } => {
+            let (l, r) = (x0.min(*x1) * fw, x0.max(*x1) * fw);
+            let (t, b) = (y0.min(*y1) * fh, y0.max(*y1) * fh);
+            segments.extend([[l, t, r, t], [r, t, r, b], [r, b, l, b], [l, b, l, t]]);
+        }
+        AnnotationShape::Text { x, y, text: string } => {
+            let size = (annotation.size * scale).max(6.0);
+            let Some(mask) = text(string, size) else {
+                return;
+            };
+            let (ox, oy) = ((x * fw).round() as i64, (y * fh).round() as i64);
+            for my in 0..mask.height {
+                for mx in 0..mask.width {
+                    let (px, py) = (ox + mx as i64, oy + my as i64);
+                    if px < 0 || py < 0 || px >= width as i64 || py >= height as i64 {
+                        continue;
+                    }
+                    let alpha = mask.alpha[(my * mask.width + mx) as usize];
+                    blend(image, width, px as u32, py as u32, annotation.color, alpha as f32 / 255.0);
+                }
+            }
+            return;
+        }
+    }
+
+    // Bounding box of everything drawn, in pixels
+    let mut bounds = [f32::MAX, f32::MAX, f32::MIN, f32::MIN];
+    let points = segments
+        .iter()
+        .flat_map(|s| [(s[0], s[1]), (s[2], s[3])])
+        .chain(polygons.iter().flatten().copied());
+    for (x, y) in points {
+        bounds = [bounds[0].min(x), bounds[1].min(y), bounds[2].max(x), bounds[3].max(y)];
+    }
+    let pixel = |v: f32, size: u32| (v.max(0.0) as u32).min(size);
+    let (left, top) = (pixel(bounds[0] - half - 1.0, width), pixel(bounds[1] - half - 1.0, height));
+    let (right, bottom) = (pixel(bounds[2] + half + 2.0, width), pixel(bounds[3] + half + 2.0, height));
+
+    for y in top..bottom {
+        for x in left..right {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let mut coverage: f32 = 0.0;
+            for s in &segments {
+                let d = segment_distance(px, py, s[0], s[1], s[2], s[3]);
+                coverage = coverage.max((half - d + 0.5).clamp(0.0, 1.0));
+            }
+            for polygon in &polygons {
+                coverage = coverage.max(polygon_coverage(px, py, polygon));
+            }
+            blend(image, width, x, y, annotation.color, coverage);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// WIDTH x HEIGHT image whose red channel numbers the pixels
+    fn numbered(width: u32, height: u32) -> Vec<u8> {
+        (0..width * height).flat_map(|i| [i as u8, 0, 0, 255]).collect()
+    }
+
+    fn reds(data: &[u8]) -> Vec<u8> {
+        data.chunks(4).map(|p| p[0]).collect()
+    }
+
+    fn no_text(_: &str, _: f32) -> Option<TextMask> {
+        None
+    }
+
+    #[test]
+    fn rotate_flip_and_crop() {
+        // 0 1 2
+        // 3 4 5
+        let image = numbered(3, 2);
+        let mut edits = ImageEdits { rotation: 1, ..Default::default() };
+        let (w, h, data) = edits.apply(3, 2, &image, &mut no_text);
+        assert_eq!((w, h), (2, 3));
+        assert_eq!(reds(&data), vec![3, 0, 4, 1, 5, 2]);
+
+        edits.rotation = 2;
+        assert_eq!(reds(&edits.apply(3, 2, &image, &mut no_text).2), vec![5, 4, 3, 2, 1, 0]);
+        edits.rotation = 3;
+        assert_eq!(reds(&edits.apply(3, 2, &image, &mut no_text).2), vec![2, 5, 1, 4, 0, 3]);
+
+        edits = ImageEdits { flip_h: true, ..Default::default() };
+        assert_eq!(reds(&edits.apply(3, 2, &image, &mut no_text).2), vec![2, 1, 0, 5, 4, 3]);
+        edits = ImageEdits { flip_v: true, ..Default::default() };
+        assert_eq!(reds(&edits.apply(3, 2, &image, &mut no_text).2), vec![3, 4, 5, 0, 1, 2]);
+
+        // Crop applies to the rotated image
+        edits = ImageEdits { rotation: 1, crop: Some([0.5, 0.0, 0.5, 2.0 / 3.0]), ..Default::default() };
+        assert_eq!(edits.output_size(3, 2), (1, 2));
+        assert_eq!(reds(&edits.apply(3, 2, &image, &mut no_text).2), vec![0, 1]);
+        assert!(!edits.is_identity());
+        assert!(ImageEdits::default().is_identity());
+    }
+
+    #[test]
+    fn crop_keeps_at_least_one_pixel() {
+        let edits = ImageEdits { crop: Some([1.0, 0.5, 0.0, -1.0]), ..Default::default() };
+        assert_eq!(edits.output_size(10, 10), (1, 1));
+    }
+
+    #[test]
+    fn annotations_draw_over_the_image() {
+        let black = vec![0u8; 40 * 40 * 4];
+        let edits = ImageEdits {
+            annotations: vec![
+                ImageAnnotation {
+                    shape: AnnotationShape::Rect { x0: 0.25, y0: 0.25, x1: 0.75, y1: 0.75 },
+                    color: 0xff0000,
+                    size: 0.05,
+                },
+                ImageAnnotation {
+                    shape: AnnotationShape::Arrow { x0: 0.1, y0: 0.9, x1: 0.9, y1: 0.9 },
+                    color: 0x00ff00,
+                    size: 0.05,
+                },
+            ],
+            ..Default::default()
+        };
+        let (_, _, data) = edits.apply(40, 40, &black, &mut no_text);
+        let pixel = |x: u32, y: u32| &data[((y * 40 + x) * 4) as usize..][..4];
+        // On the rectangle's edge, not inside it
+        assert_eq!(pixel(20, 10), &[255, 0, 0, 255]);
+        assert_eq!(pixel(20, 20), &[0, 0, 0, 0]);
+        // Along the arrow's shaft and in its head
+        assert_eq!(pixel(10, 36)[1], 255);
+        assert_eq!(pixel(31, 36)[1], 255);
+        assert_eq!(pixel(30, 34)[1], 255);
+        assert_eq!(pixel(10, 33)[1], 0);
+    }
+
+    #[test]
+    fn text_annotations_use_the_rendered_mask() {
+        let black = vec![0u8; 10 * 10 * 4];
+        let edits = ImageEdits {
+            annotations: vec![ImageAnnotation {
+                shape: AnnotationShape::Text { x: 0.8, y: 0.0, text: "A".into() },
+                color: 0xffffff,
+                size: 1.0,
+            }],
+            ..Default::default()
+        };
+        let mut sizes = Vec::new();
+        let mut text = |s: &str, size: f32| {
+            sizes.push((s.to_string(), size));
+            Some(TextMask { width: 4, height: 1, alpha: vec![255, 128, 255, 255] })
+        };
+        let (_, _, data) = edits.apply(10, 10, &black, &mut text);
+        assert_eq!(sizes, vec![("A".to_string(), 10.0)]);
+        assert_eq!(&data[8 * 4..10 * 4], &[255, 255, 255, 255, 128, 128, 128, 128]);
+        // Clipped at the right edge
+        assert_eq!(&data[10 * 4..10 * 4 + 4], &[0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,168 @@
+//! Fuzzy matcher for incremental filtering of names.
+//!
+//! A pattern matches a candidate when its characters appear in the
+//! candidate in order (case-insensitively), like Emacs' `flex'
+//! completion style.  Matches are scored so that contiguous runs,
+//! matches at word starts and prefix matches rank first, and shorter
+//! candidates win ties.
+
+/// Result of matching a pattern against one candidate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    /// Higher is better.
+    pub score: i32,
+    /// Character indices in the candidate of the matched characters.
+    pub positions: Vec<usize>,
+}
+
+const SCORE_MATCH: i32 = 16;
+const BONUS_WORD_START: i32 = 24;
+const BONUS_CONSECUTIVE: i32 = 20;
+const BONUS_PREFIX: i32 = 32;
+const PENALTY_GAP: i32 = 2;
+
+fn is_separator(c: char) -> bool {
+    c == ' ' || c == '_' || c == '-' || c == ':' || c == '.' || c == '/'
+}
+
+/// Positions of PAT's characters in CAND, scanning forward.  With
+/// PREFER_WORDS, each character takes a later word-start or consecutive
+/// occurrence over the first one; that can use up an occurrence a later
+/// pattern character needed, so callers retry without it.
+fn scan(pat: &[char], cand: &[char], prefer_words: bool) -> Option<Vec<usize>> {
+    let mut positions: Vec<usize> = Vec::with_capacity(pat.len());
+    let mut ci = 0;
+    for &pc in pat {
+        let mut first = None;
+        let mut preferred = None;
+        for (i, &c) in cand.iter().enumerate().skip(ci) {
+            if !c.to_lowercase().eq(std::iter::once(pc)) {
+                continue;
+            }
+            if first.is_none() {
+                first = Some(i);
+                if !prefer_words {
+                    break;
+                }
+            }
+            let word_start = i == 0 || is_separator(cand[i - 1]);
+            let consecutive = positions.last().is_some_and(|&p| p + 1 == i);
+            if word_start || consecutive {
+                preferred = Some(i);
+                break;
+            }
+        }
+        let i = preferred.or(first)?;
+        positions.push(i);
+        ci = i + 1;
+    }
+    Some(positions)
+}
+
+/// Match PATTERN against CANDIDATE.  An empty pattern matches everything
+/// with score 0.  Returns `None` if the pattern's characters do not all
+/// occur in order.
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let pat: Vec<char> = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if pat.is_empty() {
+        return Some(FuzzyMatch { score: 0, positions: Vec::new() });
+    }
+    let cand: Vec<char> = candidate.chars().collect();
+
+    let positions = scan(&pat, &cand, true).or_else(|| scan(&pat, &cand, false))?;
+
+    let mut score = 0;
+    for (k, &i) in positions.iter().enumerate() {
+        score += SCORE_MATCH;
+        if i == 0 || is_separator(cand[i - 1]) {
+            score += BONUS_WORD_START;
+        }
+        if k > 0 {
+            let prev = positions[k - 1];
+            if prev + 1 == i {
+                score += BONUS_CONSECUTIVE;
+            } else {
+                score -= PENALTY_GAP * (i - prev - 1).min(8) as i32;
+            }
+        }
+    }
+    if positions[0] == 0 {
+        score += BONUS_PREFIX;
+    }
+    score -= (cand.len() as i32 - pat.len() as i32).max(0).min(32) / 4;
+    Some(FuzzyMatch { score, positions })
+}
+
+/// Indices of the CANDIDATES matching PATTERN, best first.  Each
+/// candidate may have several keys (e.g. a name and shortcodes); its
+/// best-scoring key counts.  Equal scores keep the candidates' order.
+pub fn rank<'a, I, K>(pattern: &str, candidates: I) -> Vec<usize>
+where
+    I: IntoIterator<Item = K>,
+    K: IntoIterator<Item = &'a str>,
+{
+    let mut scored: Vec<(i32, usize)> = candidates
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, keys)| {
+            keys.into_iter()
+                .filter_map(|k| fuzzy_match(pattern, k))
+                .map(|m| m.score)
+                .max()
+                .map(|s| (s, idx))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    scored.into_iter().map(|(_, idx)| idx).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_in_order_case_insensitively() {
+        assert!(fuzzy_match("smiley", "Smiling Face").is_none());
+        let m = fuzzy_match("sf", "Smiling Face").unwrap();
+        assert_eq!(m.positions, vec![0, 8]);
+        assert!(fuzzy_match("fs", "smiling face").is_none());
+        // Preferring the word start "b_a" would leave no "b" after it
+        assert_eq!(fuzzy_match("ab", "xa b_a").unwrap().positions, vec![1, 3]);
+        assert_eq!(fuzzy_match("", "anything").unwrap().score, 0);
+    }
+
+    #[test]
+    fn prefers_word_starts_and_runs() {
+        // "heart" as a word beats scattered letters
+        let word = fuzzy_match("heart", "red heart").unwrap();
+        let scattered = fuzzy_match("heart", "hot beverage art").unwrap();
+        assert!(word.score > scattered.score);
+        assert_eq!(word.positions, vec![4, 5, 6, 7, 8]);
+        // A prefix match beats the same letters later on
+        let prefix = fuzzy_match("cat", "cat face").unwrap();
+        let later = fuzzy_match("cat", "grinning cat").unwrap();
+        assert!(prefix.score > later.score);
+    }
+
+    #[test]
+    fn rank_uses_best_key() {
+        let cands: Vec<Vec<&str>> = vec![
+            vec!["grinning face", ":grinning:"],
+            vec!["thumbs up sign", ":+1:", ":thumbsup:"],
+            vec!["face with tears of joy", ":joy:"],
+        ];
+        let order = rank("joy", cands.iter().map(|k| k.iter().copied()));
+        assert_eq!(order, vec![2]);
+        let order = rank("thumb", cands.iter().map(|k| k.iter().copied()));
+        assert_eq!(order, vec![1]);
+        let order = rank("", cands.iter().map(|k| k.iter().copied()));
+        assert_eq!(order, vec![0, 1, 2]);
+        // "face ..." starts with the pattern, so it ranks first
+        let order = rank("face", cands.iter().map(|k| k.iter().copied()));
+        assert_eq!(order, vec![2, 0]);
+    }
+}
@@ -0,0 +1,681 @@
+//! MIME messages, for mail readers.
+//!
+//! mu4e and notmuch show a message by handing it to Gnus's mm-decode,
+//! which splits and decodes it in Lisp and is slow on large messages
+//! with attachments.  This parses the message once: headers are
+//! unfolded and their encoded words decoded, multiparts are split on
+//! their boundaries and bodies have their quoted-printable or base64
+//! transfer encoding undone.  `layout` then walks the tree the way a
+//! reader shows it: one alternative of each `multipart/alternative`,
+//! the root of each `multipart/related`, forwarded messages inline, and
+//! everything else not fit to show as an attachment.
+//!
+//! Charsets are left to Lisp, which knows all of them: bodies come back
+//! as bytes with their charset, and encoded words in a charset other
+//! than UTF-8, ASCII, Latin-1 or Windows-1252 are kept as written, for
+//! `rfc2047-decode-string`.
+
+use super::crypto::base64_decode;
+
+/// How deep multiparts and forwarded messages may nest
+const MAX_DEPTH: usize = 32;
+
+// ============================================================================
+// Parts
+// ============================================================================
+
+/// A header, unfolded, with its encoded words decoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header {
+    pub name: String,
+    pub value: String,
+}
+
+/// A message or one of its parts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Part {
+    pub headers: Vec<Header>,
+    /// Lowercase `type/subtype`
+    pub mime_type: String,
+    /// Content-Type parameters, names lowercase
+    pub params: Vec<(String, String)>,
+    /// Content-Disposition is `attachment`
+    pub attachment: bool,
+    pub filename: Option<String>,
+    /// Content-ID, without its angle brackets
+    pub content_id: Option<String>,
+    pub description: Option<String>,
+    /// The body with its transfer encoding undone; empty for a multipart
+    pub body: Vec<u8>,
+    /// The parts of a multipart, or the message of a `message/rfc822`
+    pub children: Vec<Part>,
+}
+
+fn lookup<'a>(headers: &'a [Header], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
+}
+
+impl Part {
+    /// The first header called NAME, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        lookup(&self.headers, name)
+    }
+
+    /// The Content-Type parameter NAME
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// The charset of a text part, lowercase
+    pub fn charset(&self) -> Option<String> {
+        self.param("charset").map(str::to_ascii_lowercase)
+    }
+
+    fn is_text(&self) -> bool {
+        self.mime_type.starts_with("text/")
+    }
+
+    /// Whether a reader shows this leaf part in the message body
+    fn is_inline(&self) -> bool {
+        !self.attachment
+            && (self.is_text()
+                || matches!(
+                    self.mime_type.as_str(),
+                    "image/png" | "image/jpeg" | "image/gif" | "image/webp" | "image/svg+xml"
+                ))
+    }
+}
+
+// ============================================================================
+// Decoding
+// ============================================================================
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|d| d as u8)
+}
+
+/// Undo quoted-printable encoding (RFC 2045, 6.7).  Malformed escapes
+/// are kept as written.
+pub fn decode_quoted_printable(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] != b'=' {
+            out.push(data[i]);
+            i += 1;
+            continue;
+        }
+        // A soft line break: "=", maybe trailing blanks, then a newline
+        let mut j = i + 1;
+        while j < data.len() && matches!(data[j], b' ' | b'\t') {
+            j += 1;
+        }
+        match data.get(j..) {
+            Some([b'\r', b'\n', ..]) => i = j + 2,
+            Some([b'\n', ..]) => i = j + 1,
+            Some([]) => i = j,
+            _ => match (data.get(i + 1).and_then(|&b| hex_digit(b)), data.get(i + 2).and_then(|&b| hex_digit(b))) {
+                (Some(high), Some(low)) => {
+                    out.push(high << 4 | low);
+                    i += 3;
+                }
+                _ => {
+                    out.push(b'=');
+                    i += 1;
+                }
+            },
+        }
+    }
+    out
+}
+
+/// Undo base64 encoding, skipping the junk some mailers leave in it.
+fn decode_base64_lenient(data: &[u8]) -> Vec<u8> {
+    let text: String = data
+        .iter()
+        .take_while(|&&b| b != b'=')
+        .filter(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/'))
+        .map(|&b| b as char)
+        .collect();
+    base64_decode(&text).unwrap_or_default()
+}
+
+fn decode_transfer(encoding: Option<&str>, body: &[u8]) -> Vec<u8> {
+    match encoding.map(|e| e.trim().to_ascii_lowercase()).as_deref() {
+        Some("base64") => decode_base64_lenient(body),
+        Some("quoted-printable") => decode_quoted_printable(body),
+        _ => body.to_vec(),
+    }
+}
+
+/// Windows-1252 characters 0x80 to 0x9F; unassigned ones map to the
+/// C1 control of the same code, as in Latin-1
+const CP1252_HIGH: [u16; 32] = [
+    0x20AC, 0x81, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039,
+    0x0152, 0x8D, 0x017D, 0x8F, 0x90, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014,
+    0x02DC, 0x2122, 0x0161, 0x203A, 0x0153, 0x9D, 0x017E, 0x0178,
+];
+
+/// Decode BYTES in CHARSET, if it is one decoded here; None otherwise.
+pub fn decode_charset(bytes: &[u8], charset: &str) -> Option<String> {
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" | "us-ascii" | "ascii" => Some(String::from_utf8_lossy(bytes).into_owned()),
+        "iso-8859-1" | "latin1" | "latin-1" => Some(bytes.iter().map(|&b| b as char).collect()),
+        "windows-1252" | "cp1252" => Some(
+            bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => char::from_u32(CP1252_HIGH[b as usize - 0x80] as u32).unwrap_or('\u{FFFD}'),
+                    _ => b as char,
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Header text as a string: UTF-8 if it is, else Latin-1.
+fn header_text(bytes: &[u8]) -> String {
+    String::from_utf8(bytes.to_vec()).unwrap_or_else(|_| bytes.iter().map(|&b| b as char).collect())
+}
+
+/// The Q encoding of encoded words: quoted-printable with `_` for space.
+fn decode_q(text: &str) -> Vec<u8> {
+    decode_quoted_printable(text.replace('_', " ").as_bytes())
+}
+
+/// Decode the encoded word at the start of TEXT, which starts with
+/// "=?".  Returns the decoded text and the length of the word.
+fn encoded_word(text: &str) -> Option<(String, usize)> {
+    let inner = &text[2..];
+    let charset_end = inner.find('?')?;
+    // RFC 2231 lets a language follow the charset, after a `*`
+    let charset = inner[..charset_end].split('*').next()?;
+    let rest = &inner[charset_end + 1..];
+    let (encoding, rest) = rest.split_once('?')?;
+    let end = rest.find("?=")?;
+    let encoded = &rest[..end];
+    if charset.is_empty() || encoded.contains(char::is_whitespace) || charset.contains(char::is_whitespace) {
+        return None;
+    }
+    let bytes = match encoding {
+        "B" | "b" => base64_decode(encoded)?,
+        "Q" | "q" => decode_q(encoded),
+        _ => return None,
+    };
+    let decoded = decode_charset(&bytes, charset)?;
+    Some((decoded, 2 + charset_end + 1 + encoding.len() + 1 + end + 2))
+}
+
+/// Decode the encoded words (RFC 2047) in a header value.  Blanks
+/// between two encoded words go, as the RFC says; words that cannot be
+/// decoded here are kept as written.
+pub fn decode_words(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    let mut after_word = false;
+    while let Some(i) = rest.find("=?") {
+        let (before, candidate) = rest.split_at(i);
+        match encoded_word(candidate) {
+            Some((text, len)) => {
+                if !(after_word && before.chars().all(char::is_whitespace)) {
+                    out.push_str(before);
+                }
+                out.push_str(&text);
+                rest = &candidate[len..];
+                after_word = true;
+            }
+            None => {
+                out.push_str(before);
+                out.push_str("=?");
+                rest = &candidate[2..];
+                after_word = false;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// ============================================================================
+// Headers and parameters
+// ============================================================================
+
+/// Split ENTITY into its headers and body.  A leading mbox "From " line
+/// is skipped.
+fn split_entity(entity: &[u8]) -> (Vec<Header>, &[u8]) {
+    let mut headers: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut pos = 0;
+    while pos < entity.len() {
+        let end = entity[pos..].iter().position(|&b| b == b'\n').map_or(entity.len(), |n| pos + n);
+        let line = entity[pos..end].strip_suffix(b"\r").unwrap_or(&entity[pos..end]);
+        let next = (end + 1).min(entity.len());
+        if line.is_empty() {
+            pos = next;
+            break;
+        }
+        if matches!(line[0], b' ' | b'\t') {
+            if let Some((_, value)) = headers.last_mut() {
+                value.extend_from_slice(line);
+            }
+        } else if let Some(colon) = line.iter().position(|&b| b == b':') {
+            let name = header_text(&line[..colon]).trim().to_string();
+            headers.push((name, line[colon + 1..].to_vec()));
+        } else if !(pos == 0 && line.starts_with(b"From ")) {
+            // Not a header: the entity has no header section
+            return (Vec::new(), entity);
+        }
+        pos = next;
+    }
+    let headers = headers
+        .into_iter()
+        .map(|(name, value)| Header { name, value: decode_words(header_text(&value).trim()) })
+        .collect();
+    (headers, &entity[pos.min(entity.len())..])
+}
+
+/// Split a structured header value on `;` outside quotes.
+fn split_params(value: &str) -> Vec<String> {
+    let mut segments = vec![String::new()];
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        let segment = segments.last_mut().unwrap();
+        if escaped {
+            segment.push(c);
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                segments.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        if !escaped && c != '"' {
+            segment.push(c);
+        }
+    }
+    segments
+}
+
+fn percent_decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1).and_then(|&b| hex_digit(b)), bytes.get(i + 2).and_then(|&b| hex_digit(b))) {
+            (b'%', Some(high), Some(low)) => {
+                out.push(high << 4 | low);
+                i += 3;
+            }
+            (byte, _, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Parse a structured header such as Content-Type into its lowercase
+/// value and parameters.  Parameters split and encoded as RFC 2231 says
+/// (`name*0*=utf-8''...; name*1*=...`) are joined and decoded.
+pub fn parse_params(value: &str) -> (String, Vec<(String, String)>) {
+    let mut segments = split_params(value).into_iter();
+    let main = segments.next().unwrap_or_default().trim().to_ascii_lowercase();
+    let mut params: Vec<(String, String)> = Vec::new();
+    // (name, section, percent-encoded, value) of RFC 2231 parameters
+    let mut sections: Vec<(String, u32, bool, String)> = Vec::new();
+    for segment in segments {
+        let Some((name, value)) = segment.split_once('=') else { continue };
+        let name = name.trim().to_ascii_lowercase();
+        let value = value.trim().to_string();
+        match name.split_once('*') {
+            Some((base, spec)) => {
+                let encoded = spec.is_empty() || spec.ends_with('*');
+                let section = spec.trim_end_matches('*').parse().unwrap_or(0);
+                sections.push((base.to_string(), section, encoded, value));
+            }
+            None => params.push((name, value)),
+        }
+    }
+    sections.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+    let mut i = 0;
+    while i < sections.len() {
+        let name = sections[i].0.clone();
+        let mut charset = String::new();
+        let mut bytes = Vec::new();
+        while i < sections.len() && sections[i].0 == name {
+            let (_, section, encoded, value) = &sections[i];
+            if *encoded {
+                let mut value = value.as_str();
+                if *section == 0 {
+                    // charset'language'text
+                    if let Some((set, rest)) = value.split_once('\'') {
+                        charset = set.to_string();
+                        value = rest.split_once('\'').map_or(rest, |(_, text)| text);
+                    }
+                }
+                bytes.extend(percent_decode(value));
+            } else {
+                bytes.extend_from_slice(value.as_bytes());
+            }
+            i += 1;
+        }
+        let value = decode_charset(&bytes, if charset.is_empty() { "utf-8" } else { &charset })
+            .unwrap_or_else(|| String::from_utf8_lossy(&bytes).into_owned());
+        params.retain(|(n, _)| *n != name);
+        params.push((name, value));
+    }
+    (main, params)
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+/// The parts of a multipart body, between its BOUNDARY lines.  The
+/// preamble and epilogue are dropped; an unterminated last part is
+/// kept.
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{boundary}");
+    let mut parts = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut pos = 0;
+    while pos < body.len() {
+        let end = body[pos..].iter().position(|&b| b == b'\n').map_or(body.len(), |n| pos + n);
+        let line = body[pos..end].trim_ascii_end();
+        if let Some(rest) = line.strip_prefix(delimiter.as_bytes()) {
+            if rest.is_empty() || rest == b"--" {
+                if let Some(start) = start {
+                    // The line break before a boundary belongs to it
+                    let mut stop = pos;
+                    if stop > start && body[stop - 1] == b'\n' {
+                        stop -= 1;
+                        if stop > start && body[stop - 1] == b'\r' {
+                            stop -= 1;
+                        }
+                    }
+                    parts.push(&body[start..stop.max(start)]);
+                }
+                if rest == b"--" {
+                    return parts;
+                }
+                start = Some((end + 1).min(body.len()));
+            }
+        }
+        pos = end + 1;
+    }
+    if let Some(start) = start.filter(|&s| s < body.len()) {
+        parts.push(&body[start..]);
+    }
+    parts
+}
+
+fn parse_entity(entity: &[u8], default_type: &str, depth: usize) -> Part {
+    let (headers, body) = split_entity(entity);
+    let (mime_type, params) = lookup(&headers, "content-type")
+        .map(parse_params)
+        .filter(|(t, _)| t.contains('/'))
+        .unwrap_or_else(|| (default_type.to_string(), Vec::new()));
+    let (disposition, disposition_params) = lookup(&headers, "content-disposition").map(parse_params).unwrap_or_default();
+    let filename = disposition_params
+        .iter()
+        .find(|(n, _)| n == "filename")
+        .or_else(|| params.iter().find(|(n, _)| n == "name"))
+        .map(|(_, v)| v.clone());
+    let content_id = lookup(&headers, "content-id").map(|id| id.trim().trim_start_matches('<').trim_end_matches('>').to_string());
+    let description = lookup(&headers, "content-description").map(str::to_string);
+    let encoding = lookup(&headers, "content-transfer-encoding").map(str::to_string);
+    let mut part = Part {
+        mime_type,
+        params,
+        attachment: disposition == "attachment",
+        filename,
+        content_id,
+        description,
+        ..Part::default()
+    };
+    part.headers = headers;
+    if depth < MAX_DEPTH && part.mime_type.starts_with("multipart/") {
+        if let Some(boundary) = part.param("boundary").map(str::to_string) {
+            let child_type = if part.mime_type == "multipart/digest" { "message/rfc822" } else { "text/plain" };
+            part.children = split_multipart(body, &boundary)
+                .into_iter()
+                .map(|child| parse_entity(child, child_type, depth + 1))
+                .collect();
+            return part;
+        }
+    }
+    part.body = decode_transfer(encoding.as_deref(), body);
+    if depth < MAX_DEPTH && part.mime_type == "message/rfc822" {
+        part.children = vec![parse_entity(&part.body, "text/plain", depth + 1)];
+    }
+    part
+}
+
+/// Parse the message DATA.  Its headers are those of the returned part.
+pub fn parse(data: &[u8]) -> Part {
+    parse_entity(data, "text/plain", 0)
+}
+
+// ============================================================================
+// Layout
+// ============================================================================
+
+/// How a reader shows a part
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// In the message body: text, or an image
+    Body,
+    /// As an attachment, to save or open
+    Attachment,
+    /// A forwarded message, shown inline: its headers, then its parts
+    Message,
+    /// Not shown: an alternative not chosen, or an image that an HTML
+    /// part refers to by Content-ID
+    Hidden,
+}
+
+/// How well an alternative suits the reader, None if it cannot be shown.
+fn alternative_rank(part: &Part, prefer_html: bool) -> Option<u8> {
+    let shown = match part.mime_type.as_str() {
+        "multipart/related" => part.children.first().map_or("", |root| root.mime_type.as_str()),
+        other => other,
+    };
+    match shown {
+        "text/html" => Some(if prefer_html { 3 } else { 2 }),
+        "text/plain" => Some(if prefer_html { 2 } else { 3 }),
+        t if t.starts_with("text/") || t.starts_with("multipart/") => Some(1),
+        _ => None,
+    }
+}
+
+fn walk<'a>(part: &'a Part, prefer_html: bool, hidden: bool, out: &mut Vec<(Role, &'a Part)>) {
+    let multipart = part.mime_type.starts_with("multipart/") && !part.children.is_empty();
+    if hidden {
+        if multipart {
+            for child in &part.children {
+                walk(child, prefer_html, true, out);
+            }
+        } else {
+            out.push((Role::Hidden, part));
+        }
+        return;
+    }
+    match part.mime_type.as_str() {
+        _ if !multipart && part.mime_type.starts_with("multipart/") => out.push((Role::Attachment, part)),
+        "multipart/alternative" => {
+            // The last of the best, as alternatives go from plain to fancy
+            let chosen = part
+                .children
+                .iter()
+                .enumerate()
+                .filter_map(|(i, child)| alternative_rank(child, prefer_html).map(|rank| (rank, i)))
+                .max()
+                .map(|(_, i)| i);
+            for (i, child) in part.children.iter().enumerate() {
+                walk(child, prefer_html, chosen.is_some_and(|c| c != i), out);
+            }
+        }
+        "multipart/related" => {
+            let start = part.param("start").map(|s| s.trim_start_matches('<').trim_end_matches('>'));
+            let root = start
+                .and_then(|id| part.children.iter().position(|c| c.content_id.as_deref() == Some(id)))
+                .unwrap_or(0);
+            for (i, child) in part.children.iter().enumerate() {
+                walk(child, prefer_html, i != root, out);
+            }
+        }
+        _ if multipart => {
+            for child in &part.children {
+                walk(child, prefer_html, false, out);
+            }
+        }
+        "message/rfc822" if !part.attachment && !part.children.is_empty() => {
+            out.push((Role::Message, part));
+            walk(&part.children[0], prefer_html, false, out);
+        }
+        _ if part.is_inline() => out.push((Role::Body, part)),
+        _ => out.push((Role::Attachment, part)),
+    }
+}
+
+/// The parts of MESSAGE in document order, each with how a reader shows
+/// it.  Multiparts are walked rather than listed.  PREFER_HTML chooses
+/// HTML over plain text where a message offers both.
+pub fn layout(message: &Part, prefer_html: bool) -> Vec<(Role, &Part)> {
+    let mut out = Vec::new();
+    walk(message, prefer_html, false, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_quoted_printable_and_encoded_words() {
+        assert_eq!(decode_quoted_printable(b"caf=C3=A9 =\r\nbar=3D1 =ZZ"), "café bar=1 =ZZ".as_bytes());
+        assert_eq!(decode_words("=?UTF-8?B?Y2Fmw6k=?= =?iso-8859-1?Q?na=EFve_test?= ok"), "cafénaïve test ok");
+        assert_eq!(decode_words("=?windows-1252?Q?=93hi=94?="), "\u{201C}hi\u{201D}");
+        // Unknown charsets are left for Lisp
+        assert_eq!(decode_words("=?koi8-r?B?8NLJ18XU?= x"), "=?koi8-r?B?8NLJ18XU?= x");
+        assert_eq!(decode_words("price =? no"), "price =? no");
+    }
+
+    #[test]
+    fn parses_parameters() {
+        let (value, params) = parse_params("Text/Plain; charset=\"UTF-8\"; format=flowed");
+        assert_eq!(value, "text/plain");
+        assert_eq!(params, [("charset".into(), "UTF-8".into()), ("format".into(), "flowed".into())]);
+        let (_, params) = parse_params("attachment; filename=\"a;b \\\"c\\\".txt\"");
+        assert_eq!(params, [("filename".into(), "a;b \"c\".txt".into())]);
+        let (_, params) = parse_params("attachment; filename=old; filename*0*=utf-8''r%C3%A9sum; filename*1=\"e.pdf\"");
+        assert_eq!(params, [("filename".into(), "résume.pdf".into())]);
+    }
+
+    const MESSAGE: &str = "From: =?utf-8?Q?Zo=C3=AB?= <zoe@example.org>\r\n\
+Subject: Quarterly\r\n report\r\n\
+MIME-Version: 1.0\r\n\
+Content-Type: multipart/mixed; boundary=\"outer\"\r\n\
+\r\n\
+This is a multi-part message.\r\n\
+--outer\r\n\
+Content-Type: multipart/alternative; boundary=inner\r\n\
+\r\n\
+--inner\r\n\
+Content-Type: text/plain; charset=utf-8\r\n\
+Content-Transfer-Encoding: quoted-printable\r\n\
+\r\n\
+Numbers are =\r\nup.\r\n\
+--inner\r\n\
+Content-Type: multipart/related; boundary=rel\r\n\
+\r\n\
+--rel\r\n\
+Content-Type: text/html\r\n\
+\r\n\
+<p>Numbers are <b>up</b>.<img src=\"cid:chart@x\"></p>\r\n\
+--rel\r\n\
+Content-Type: image/png\r\n\
+Content-ID: <chart@x>\r\n\
+Content-Transfer-Encoding: base64\r\n\
+\r\n\
+iVBORw0K\r\n\
+--rel--\r\n\
+--inner--\r\n\
+--outer\r\n\
+Content-Type: application/pdf; name=\"report.pdf\"\r\n\
+Content-Disposition: attachment\r\n\
+Content-Transfer-Encoding: base64\r\n\
+\r\n\
+JVBERi0x\r\n\
+LjQ=\r\n\
+--outer\r\n\
+Content-Type: message/rfc822\r\n\
+\r\n\
+Subject: Earlier\r\n\
+\r\n\
+Forwarded text\r\n\
+--outer--\r\n\
+epilogue\r\n";
+
+    #[test]
+    fn parses_a_multipart_message() {
+        let message = parse(MESSAGE.as_bytes());
+        assert_eq!(message.header("from"), Some("Zoë <zoe@example.org>"));
+        assert_eq!(message.header("Subject"), Some("Quarterly report"));
+        assert_eq!(message.mime_type, "multipart/mixed");
+        assert_eq!(message.children.len(), 3);
+        let alternative = &message.children[0];
+        assert_eq!(alternative.children[0].body, b"Numbers are up.");
+        assert_eq!(alternative.children[0].charset().as_deref(), Some("utf-8"));
+        let image = &alternative.children[1].children[1];
+        assert_eq!(image.content_id.as_deref(), Some("chart@x"));
+        assert_eq!(image.body, [0x89, b'P', b'N', b'G', b'\r', b'\n']);
+        let pdf = &message.children[1];
+        assert!(pdf.attachment);
+        assert_eq!(pdf.filename.as_deref(), Some("report.pdf"));
+        assert_eq!(pdf.body, b"%PDF-1.4");
+        let forwarded = &message.children[2];
+        assert_eq!(forwarded.children[0].header("subject"), Some("Earlier"));
+        assert_eq!(forwarded.children[0].body, b"Forwarded text");
+    }
+
+    #[test]
+    fn lays_out_parts_as_a_reader_shows_them() {
+        let message = parse(MESSAGE.as_bytes());
+        let roles = |prefer_html| -> Vec<(Role, String)> {
+            layout(&message, prefer_html)
+                .into_iter()
+                .map(|(role, part)| (role, part.mime_type.clone()))
+                .collect()
+        };
+        let html = roles(true);
+        assert_eq!(
+            html,
+            [
+                (Role::Hidden, "text/plain".into()),
+                (Role::Body, "text/html".into()),
+                (Role::Hidden, "image/png".into()),
+                (Role::Attachment, "application/pdf".into()),
+                (Role::Message, "message/rfc822".into()),
+                (Role::Body, "text/plain".into()),
+            ]
+        );
+        let plain = roles(false);
+        assert_eq!(plain[0], (Role::Body, "text/plain".into()));
+        assert_eq!(plain[1], (Role::Hidden, "text/html".into()));
+        // A message without MIME headers is one plain text body
+        let simple = parse(b"Subject: hi\n\nHello\n");
+        assert_eq!(layout(&simple, true).len(), 1);
+        assert_eq!(simple.body, b"Hello\n");
+    }
+}
@@ -5,6 +5,8 @@ pub mod scene;
 pub mod face;
 pub mod error;
 pub mod animation;
+pub mod easing;
+pub mod animation_clock;
 pub mod frame_glyphs;
 pub mod cursor_animation;
 pub mod buffer_transition;
@@ -27,6 +29,22 @@ pub mod casefiddle;
 pub mod composite;
 pub mod profiler;
 pub mod textprop;
+pub mod window_background;
+pub mod timeline;
+pub mod monitor;
+pub mod popup_placement;
+pub mod matcher;
+pub mod scene_dump;
+pub mod icons;
+pub mod text_contrast;
+pub mod image_edit;
+pub mod chart;
+pub mod dir_listing;
+pub mod process_monitor;
+pub mod crypto;
+pub mod secrets;
+pub mod html;
+pub mod mime;
 
 pub use types::*;
 pub use scene::*;
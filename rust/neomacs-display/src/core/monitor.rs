@@ -0,0 +1,150 @@
+//! Monitor topology: the displays a frame can be placed on.
+//!
+//! Backends describe each connected monitor with a `MonitorInfo`.  The
+//! render thread keeps the list current (monitors come and go, change
+//! resolution or scale) and tells Emacs when it changes, so
+//! `display-monitor-attributes-list` and frame placement stay right.
+
+/// A connected monitor.  Positions and sizes are in physical pixels in
+/// the desktop's coordinate space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorInfo {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    /// Part of the monitor not covered by panels and docks; the whole
+    /// monitor when the platform does not report it
+    pub work_x: i32,
+    pub work_y: i32,
+    pub work_width: i32,
+    pub work_height: i32,
+    pub scale: f64,
+    pub width_mm: i32,
+    pub height_mm: i32,
+    /// Refresh rate in millihertz (0 = unknown)
+    pub refresh_mhz: u32,
+    /// Whether this is the primary monitor
+    pub primary: bool,
+    pub name: Option<String>,
+}
+
+impl MonitorInfo {
+    /// A monitor at X, Y of WIDTH x HEIGHT pixels whose work area is the
+    /// whole monitor and whose physical size is estimated at 96 dpi
+    pub fn new(x: i32, y: i32, width: i32, height: i32, scale: f64) -> Self {
+        let to_mm = |px: i32| if scale > 0.0 { (px as f64 * 25.4 / (96.0 * scale)) as i32 } else { 0 };
+        Self {
+            x,
+            y,
+            width,
+            height,
+            work_x: x,
+            work_y: y,
+            work_width: width,
+            work_height: height,
+            scale,
+            width_mm: to_mm(width),
+            height_mm: to_mm(height),
+            refresh_mhz: 0,
+            primary: false,
+            name: None,
+        }
+    }
+
+    /// Whether the point X, Y is on this monitor
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    /// Position of a WIDTH x HEIGHT frame centered in the work area,
+    /// kept inside it where possible
+    pub fn center(&self, width: i32, height: i32) -> (i32, i32) {
+        let x = self.work_x + ((self.work_width - width) / 2).max(0);
+        let y = self.work_y + ((self.work_height - height) / 2).max(0);
+        (x, y)
+    }
+
+    /// Position of a WIDTH x HEIGHT popup: centered horizontally in the
+    /// work area with its top a fifth of the way down, so the popup can
+    /// grow downwards without moving.  Kept inside the work area where
+    /// possible.
+    pub fn popup_position(&self, width: i32, height: i32) -> (i32, i32) {
+        let (x, _) = self.center(width, height);
+        let room = (self.work_height - height).max(0);
+        (x, self.work_y + (self.work_height / 5).min(room))
+    }
+}
+
+/// Index of the monitor showing the point X, Y, if any
+pub fn monitor_at(monitors: &[MonitorInfo], x: i32, y: i32) -> Option<usize> {
+    monitors.iter().position(|m| m.contains(x, y))
+}
+
+/// Put the primary monitor first, keeping the order of the others.  If
+/// none is marked primary, the first one becomes primary.
+pub fn sort_primary_first(monitors: &mut [MonitorInfo]) {
+    if let Some(i) = monitors.iter().position(|m| m.primary) {
+        monitors[..=i].rotate_right(1);
+    } else if let Some(first) = monitors.first_mut() {
+        first.primary = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_estimates_size_and_workarea() {
+        let m = MonitorInfo::new(1920, 0, 1920, 1080, 1.0);
+        assert_eq!((m.work_x, m.work_width), (1920, 1920));
+        assert_eq!((m.width_mm, m.height_mm), (508, 285));
+    }
+
+    #[test]
+    fn monitor_at_point() {
+        let monitors = vec![
+            MonitorInfo::new(0, 0, 1920, 1080, 1.0),
+            MonitorInfo::new(1920, 0, 2560, 1440, 1.5),
+        ];
+        assert_eq!(monitor_at(&monitors, 100, 100), Some(0));
+        assert_eq!(monitor_at(&monitors, 1920, 1439), Some(1));
+        assert_eq!(monitor_at(&monitors, 100, 1200), None);
+    }
+
+    #[test]
+    fn center_in_workarea() {
+        let mut m = MonitorInfo::new(1920, 0, 2560, 1440, 1.0);
+        m.work_y = 40;
+        m.work_height = 1400;
+        assert_eq!(m.center(800, 600), (1920 + 880, 40 + 400));
+        // Larger than the work area: pinned to its top left corner
+        assert_eq!(m.center(3000, 2000), (1920, 40));
+    }
+
+    #[test]
+    fn popup_in_upper_workarea() {
+        let m = MonitorInfo::new(0, 0, 1000, 1000, 1.0);
+        assert_eq!(m.popup_position(400, 50), (300, 200));
+        // Too tall to sit at a fifth: moved up to stay on screen
+        assert_eq!(m.popup_position(400, 900), (300, 100));
+        assert_eq!(m.popup_position(400, 1200), (300, 0));
+    }
+
+    #[test]
+    fn primary_goes_first() {
+        let mut monitors = vec![
+            MonitorInfo::new(0, 0, 100, 100, 1.0),
+            MonitorInfo::new(100, 0, 100, 100, 1.0),
+            MonitorInfo::new(200, 0, 100, 100, 1.0),
+        ];
+        monitors[2].primary = true;
+        sort_primary_first(&mut monitors);
+        assert_eq!(monitors.iter().map(|m| m.x).collect::<Vec<_>>(), vec![200, 0, 100]);
+
+        let mut monitors = vec![MonitorInfo::new(0, 0, 100, 100, 1.0)];
+        sort_primary_first(&mut monitors);
+        assert!(monitors[0].primary);
+    }
+}
@@ -0,0 +1,254 @@
+//! Placement of popups (tooltips, menus, completion popups, child
+//! frames) next to what they belong to.
+//!
+//! A popup is placed on a preferred side of an anchor rectangle (the
+//! mouse pointer, a menu item, the character at point).  The usable
+//! area is the frame clipped to the work area of each monitor it is on;
+//! the popup stays in the part holding the anchor, so it never straddles
+//! two monitors.  If the popup does not fit on the preferred side, or
+//! covers an area to avoid such as the minibuffer, it flips to the
+//! opposite side; across the side it is shifted to stay inside.  When it
+//! fits on neither side it goes where there is more room, pushed back
+//! inside as far as its size allows.  Positions are rounded to whole
+//! pixels so popups are drawn crisp.
+
+use super::monitor::MonitorInfo;
+use super::types::Rect;
+
+/// Side of the anchor a popup is placed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopupSide {
+    Below,
+    Above,
+    Right,
+    Left,
+}
+
+impl PopupSide {
+    /// Side for FFI value V (0 = below, 1 = above, 2 = right, 3 = left)
+    pub fn from_ffi(v: i32) -> Self {
+        match v {
+            1 => Self::Above,
+            2 => Self::Right,
+            3 => Self::Left,
+            _ => Self::Below,
+        }
+    }
+
+    pub fn to_ffi(self) -> i32 {
+        match self {
+            Self::Below => 0,
+            Self::Above => 1,
+            Self::Right => 2,
+            Self::Left => 3,
+        }
+    }
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Self::Below => Self::Above,
+            Self::Above => Self::Below,
+            Self::Right => Self::Left,
+            Self::Left => Self::Right,
+        }
+    }
+
+    fn vertical(self) -> bool {
+        matches!(self, Self::Below | Self::Above)
+    }
+}
+
+/// Where a popup goes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub x: f32,
+    pub y: f32,
+    /// Side of the anchor it is on
+    pub side: PopupSide,
+}
+
+/// Parts of FRAME (in desktop coordinates) on the work area of each of
+/// MONITORS, relative to the frame.  The whole frame when it is on no
+/// monitor, or none are known.
+pub fn frame_areas(frame: Rect, monitors: &[MonitorInfo]) -> Vec<Rect> {
+    let areas: Vec<Rect> = monitors
+        .iter()
+        .filter_map(|m| {
+            let work = Rect::new(
+                m.work_x as f32, m.work_y as f32,
+                m.work_width as f32, m.work_height as f32,
+            );
+            intersection(&frame, &work)
+        })
+        .map(|r| Rect::new(r.x - frame.x, r.y - frame.y, r.width, r.height))
+        .collect();
+    if areas.is_empty() {
+        vec![Rect::new(0.0, 0.0, frame.width, frame.height)]
+    } else {
+        areas
+    }
+}
+
+fn intersection(a: &Rect, b: &Rect) -> Option<Rect> {
+    let x = a.x.max(b.x);
+    let y = a.y.max(b.y);
+    let right = a.right().min(b.right());
+    let bottom = a.bottom().min(b.bottom());
+    (right > x && bottom > y).then(|| Rect::new(x, y, right - x, bottom - y))
+}
+
+/// The area of AREAS a popup next to ANCHOR goes in: the one holding
+/// the anchor's center, else the one nearest to it.
+fn area_for(anchor: &Rect, areas: &[Rect]) -> Option<Rect> {
+    let cx = anchor.x + anchor.width / 2.0;
+    let cy = anchor.y + anchor.height / 2.0;
+    let distance = |r: &Rect| {
+        let dx = (r.x - cx).max(cx - r.right()).max(0.0);
+        let dy = (r.y - cy).max(cy - r.bottom()).max(0.0);
+        dx * dx + dy * dy
+    };
+    areas.iter().copied().min_by(|a, b| distance(a).total_cmp(&distance(b)))
+}
+
+/// Keep [START, START + SIZE) inside [LOW, HIGH), or at LOW if too big.
+fn clamp_span(start: f32, size: f32, low: f32, high: f32) -> f32 {
+    start.min(high - size).max(low)
+}
+
+/// Place a WIDTH x HEIGHT popup on SIDE of ANCHOR, GAP pixels away,
+/// inside one of AREAS and off the rectangles in AVOID.  See the module
+/// documentation.  Without AREAS the popup goes on SIDE unconstrained.
+pub fn place_popup(
+    anchor: Rect,
+    width: f32,
+    height: f32,
+    side: PopupSide,
+    gap: f32,
+    areas: &[Rect],
+    avoid: &[Rect],
+) -> Placement {
+    // Position next to the anchor on side S, before shifting across
+    let beside = |s: PopupSide| match s {
+        PopupSide::Below => (anchor.x, anchor.bottom() + gap),
+        PopupSide::Above => (anchor.x, anchor.y - gap - height),
+        PopupSide::Right => (anchor.right() + gap, anchor.y),
+        PopupSide::Left => (anchor.x - gap - width, anchor.y),
+    };
+    let Some(area) = area_for(&anchor, areas) else {
+        let (x, y) = beside(side);
+        return Placement { x: x.round(), y: y.round(), side };
+    };
+    // Shift across S to stay inside the area
+    let shifted = |s: PopupSide| {
+        let (x, y) = beside(s);
+        if s.vertical() {
+            (clamp_span(x, width, area.x, area.right()), y)
+        } else {
+            (x, clamp_span(y, height, area.y, area.bottom()))
+        }
+    };
+    let fits = |(x, y): (f32, f32)| {
+        let rect = Rect::new(x, y, width, height);
+        x >= area.x
+            && y >= area.y
+            && rect.right() <= area.right()
+            && rect.bottom() <= area.bottom()
+            && !avoid.iter().any(|r| intersection(&rect, r).is_some())
+    };
+
+    let (side, (x, y)) = [side, side.opposite()]
+        .into_iter()
+        .map(|s| (s, shifted(s)))
+        .find(|&(_, pos)| fits(pos))
+        .unwrap_or_else(|| {
+            // Fits on neither side: the one with more room, pushed inside
+            let room = |s: PopupSide| match s {
+                PopupSide::Below => area.bottom() - anchor.bottom(),
+                PopupSide::Above => anchor.y - area.y,
+                PopupSide::Right => area.right() - anchor.right(),
+                PopupSide::Left => anchor.x - area.x,
+            };
+            let s = if room(side.opposite()) > room(side) { side.opposite() } else { side };
+            let (x, y) = shifted(s);
+            if s.vertical() {
+                (s, (x, clamp_span(y, height, area.y, area.bottom())))
+            } else {
+                (s, (clamp_span(x, width, area.x, area.right()), y))
+            }
+        });
+    Placement { x: x.round(), y: y.round(), side }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Rect = Rect::new(0.0, 0.0, 1000.0, 800.0);
+
+    fn at(p: Placement) -> (f32, f32, PopupSide) {
+        (p.x, p.y, p.side)
+    }
+
+    #[test]
+    fn places_on_preferred_side_and_flips() {
+        let anchor = Rect::new(100.0, 100.0, 10.0, 20.0);
+        let p = place_popup(anchor, 200.0, 100.0, PopupSide::Below, 2.0, &[SCREEN], &[]);
+        assert_eq!(at(p), (100.0, 122.0, PopupSide::Below));
+        // Near the bottom: above instead
+        let anchor = Rect::new(100.0, 750.0, 10.0, 20.0);
+        let p = place_popup(anchor, 200.0, 100.0, PopupSide::Below, 2.0, &[SCREEN], &[]);
+        assert_eq!(at(p), (100.0, 648.0, PopupSide::Above));
+        // Submenu at the right edge opens to the left
+        let item = Rect::new(850.0, 300.0, 150.0, 20.0);
+        let p = place_popup(item, 150.0, 200.0, PopupSide::Right, 0.0, &[SCREEN], &[]);
+        assert_eq!(at(p), (700.0, 300.0, PopupSide::Left));
+    }
+
+    #[test]
+    fn shifts_across_and_rounds() {
+        let anchor = Rect::new(950.4, 100.0, 10.0, 20.0);
+        let p = place_popup(anchor, 200.0, 100.0, PopupSide::Below, 0.0, &[SCREEN], &[]);
+        assert_eq!(at(p), (800.0, 120.0, PopupSide::Below));
+        let anchor = Rect::new(100.6, 100.0, 10.0, 20.0);
+        assert_eq!(place_popup(anchor, 200.0, 100.0, PopupSide::Below, 0.0, &[SCREEN], &[]).x, 101.0);
+    }
+
+    #[test]
+    fn avoids_the_minibuffer() {
+        let minibuffer = Rect::new(0.0, 780.0, 1000.0, 20.0);
+        let anchor = Rect::new(100.0, 700.0, 10.0, 20.0);
+        let p = place_popup(anchor, 200.0, 70.0, PopupSide::Below, 0.0, &[SCREEN], &[minibuffer]);
+        assert_eq!(at(p), (100.0, 630.0, PopupSide::Above));
+    }
+
+    #[test]
+    fn too_big_goes_where_there_is_more_room() {
+        let anchor = Rect::new(100.0, 300.0, 10.0, 20.0);
+        let p = place_popup(anchor, 200.0, 600.0, PopupSide::Below, 0.0, &[SCREEN], &[]);
+        // 480 pixels below, 300 above: below, pushed up to fit
+        assert_eq!(at(p), (100.0, 200.0, PopupSide::Below));
+        let p = place_popup(anchor, 200.0, 900.0, PopupSide::Above, 0.0, &[SCREEN], &[]);
+        assert_eq!(at(p), (100.0, 0.0, PopupSide::Below));
+    }
+
+    #[test]
+    fn stays_on_the_anchors_monitor() {
+        let mut left = MonitorInfo::new(0, 0, 1000, 800, 1.0);
+        left.work_height = 760;
+        let right = MonitorInfo::new(1000, 0, 1000, 800, 1.0);
+        // A frame spanning both monitors
+        let frame = Rect::new(500.0, 100.0, 1000.0, 700.0);
+        let areas = frame_areas(frame, &[left, right.clone()]);
+        assert_eq!(areas, vec![
+            Rect::new(0.0, 0.0, 500.0, 660.0),
+            Rect::new(500.0, 0.0, 500.0, 700.0),
+        ]);
+        // Just left of the monitor edge: shifted back onto the left one
+        let anchor = Rect::new(400.0, 600.0, 10.0, 20.0);
+        let p = place_popup(anchor, 200.0, 100.0, PopupSide::Below, 0.0, &areas, &[]);
+        assert_eq!(at(p), (300.0, 500.0, PopupSide::Above));
+        // Off every monitor: the whole frame
+        assert_eq!(frame_areas(Rect::new(5000.0, 0.0, 100.0, 100.0), &[right]),
+                   vec![Rect::new(0.0, 0.0, 100.0, 100.0)]);
+    }
+}
@@ -0,0 +1,527 @@
+//! Process and system sampling for the process monitor.
+//!
+//! The process monitor is a live table of processes, like Proced, drawn
+//! by the render thread.  `ProcessMonitor` samples `/proc` on a worker
+//! thread every interval: the processes with their CPU use since the
+//! previous sample, resident memory, owner and command line, and the
+//! whole system's CPU and memory use.  Each process keeps a short CPU
+//! history for its sparkline.  Signals and nice changes go straight to
+//! the kernel with `kill` and `setpriority`.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Samples of CPU use kept for sparklines
+pub const HISTORY_LEN: usize = 30;
+
+/// Signals offered for processes, by name
+pub const SIGNALS: [(&str, i32); 6] = [
+    ("TERM", libc::SIGTERM),
+    ("KILL", libc::SIGKILL),
+    ("INT", libc::SIGINT),
+    ("HUP", libc::SIGHUP),
+    ("STOP", libc::SIGSTOP),
+    ("CONT", libc::SIGCONT),
+];
+
+/// One process, as of the last sample
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessInfo {
+    pub pid: i32,
+    pub ppid: i32,
+    /// Name of the executable (`comm`)
+    pub name: String,
+    /// Command line, or the name in brackets for kernel threads
+    pub command: String,
+    pub user: String,
+    /// State letter: R running, S sleeping, D disk wait, Z zombie...
+    pub state: char,
+    pub nice: i32,
+    pub threads: u32,
+    /// Resident memory in bytes
+    pub rss: u64,
+    /// CPU use since the previous sample, in percent of one CPU
+    pub cpu: f32,
+    /// Recent CPU use, oldest first, at most `HISTORY_LEN` samples
+    pub cpu_history: Vec<f32>,
+}
+
+/// Processes and system load at one moment
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub processes: Vec<ProcessInfo>,
+    /// CPU use of the whole system, in percent of all CPUs
+    pub cpu: f32,
+    pub cpu_history: Vec<f32>,
+    pub mem_total: u64,
+    pub mem_used: u64,
+    pub cpus: u32,
+}
+
+/// Column processes are sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessSortKey {
+    Pid,
+    User,
+    Cpu,
+    Memory,
+    Command,
+}
+
+impl ProcessSortKey {
+    /// The next column, for cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            ProcessSortKey::Pid => ProcessSortKey::User,
+            ProcessSortKey::User => ProcessSortKey::Cpu,
+            ProcessSortKey::Cpu => ProcessSortKey::Memory,
+            ProcessSortKey::Memory => ProcessSortKey::Command,
+            ProcessSortKey::Command => ProcessSortKey::Pid,
+        }
+    }
+}
+
+/// Sort PROCESSES by KEY, ties by pid; DESCENDING reverses the order.
+pub fn sort_processes(processes: &mut [ProcessInfo], key: ProcessSortKey, descending: bool) {
+    processes.sort_by(|a, b| {
+        let by_key = match key {
+            ProcessSortKey::Pid => a.pid.cmp(&b.pid),
+            ProcessSortKey::User => a.user.cmp(&b.user),
+            ProcessSortKey::Cpu => a.cpu.total_cmp(&b.cpu),
+            ProcessSortKey::Memory => a.rss.cmp(&b.rss),
+            ProcessSortKey::Command => a.command.cmp(&b.command),
+        };
+        let by_key = by_key.then_with(|| a.pid.cmp(&b.pid));
+        if descending { by_key.reverse() } else { by_key }
+    });
+}
+
+/// Indices of the processes whose command, user or pid contains QUERY,
+/// ignoring case.
+pub fn filter_processes(processes: &[ProcessInfo], query: &str) -> Vec<usize> {
+    let needle = query.to_lowercase();
+    processes
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| {
+            needle.is_empty()
+                || p.command.to_lowercase().contains(&needle)
+                || p.user.to_lowercase().contains(&needle)
+                || p.pid.to_string().contains(&needle)
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Fields of `/proc/PID/stat` the monitor uses
+#[derive(Debug, Clone, PartialEq)]
+struct ProcStat {
+    name: String,
+    state: char,
+    ppid: i32,
+    /// User plus system time, in clock ticks
+    ticks: u64,
+    nice: i32,
+    threads: u32,
+    /// Resident pages
+    rss_pages: u64,
+}
+
+/// Parse the contents of `/proc/PID/stat`.  The name is in parentheses
+/// and may itself contain spaces and parentheses.
+fn parse_stat(contents: &str) -> Option<ProcStat> {
+    let open = contents.find('(')?;
+    let close = contents.rfind(')')?;
+    let name = contents.get(open + 1..close)?.to_string();
+    // Fields from the state on (field 3 of proc(5))
+    let fields: Vec<&str> = contents.get(close + 1..)?.split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3).copied();
+    let number = |n: usize| field(n).and_then(|f| f.parse::<i64>().ok());
+    Some(ProcStat {
+        name,
+        state: field(3)?.chars().next()?,
+        ppid: number(4)? as i32,
+        ticks: (number(14)? + number(15)?) as u64,
+        nice: number(19)? as i32,
+        threads: number(20)? as u32,
+        rss_pages: number(24)?.max(0) as u64,
+    })
+}
+
+/// Total and idle clock ticks of all CPUs, and the number of CPUs, from
+/// the contents of `/proc/stat`.
+fn parse_cpu_times(contents: &str) -> Option<(u64, u64, u32)> {
+    let mut lines = contents.lines();
+    let first = lines.next()?;
+    let mut fields = first.split_whitespace();
+    if fields.next()? != "cpu" {
+        return None;
+    }
+    let ticks: Vec<u64> = fields.take(8).filter_map(|f| f.parse().ok()).collect();
+    if ticks.len() < 4 {
+        return None;
+    }
+    let total = ticks.iter().sum();
+    // idle plus iowait
+    let idle = ticks[3] + ticks.get(4).copied().unwrap_or(0);
+    let cpus = lines
+        .take_while(|l| l.starts_with("cpu"))
+        .count()
+        .max(1) as u32;
+    Some((total, idle, cpus))
+}
+
+/// Total and available memory in bytes from the contents of
+/// `/proc/meminfo`.
+fn parse_meminfo(contents: &str) -> (u64, u64) {
+    let mut total = 0;
+    let mut available = None;
+    let mut free = 0;
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(key), Some(value)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let kib: u64 = value.parse().unwrap_or(0);
+        match key {
+            "MemTotal:" => total = kib * 1024,
+            "MemAvailable:" => available = Some(kib * 1024),
+            "MemFree:" => free = kib * 1024,
+            _ => {}
+        }
+    }
+    (total, available.unwrap_or(free))
+}
+
+/// Real user id from the contents of `/proc/PID/status`.
+fn parse_uid(contents: &str) -> Option<u32> {
+    contents
+        .lines()
+        .find_map(|l| l.strip_prefix("Uid:"))
+        .and_then(|l| l.split_whitespace().next())
+        .and_then(|uid| uid.parse().ok())
+}
+
+/// User names by id from the contents of `/etc/passwd`.
+fn parse_passwd(contents: &str) -> HashMap<u32, String> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+/// Reads processes and system load from a `/proc` tree, working out CPU
+/// use from the difference to the previous sample.
+pub struct Sampler {
+    root: PathBuf,
+    users: HashMap<u32, String>,
+    page_size: u64,
+    /// Clock ticks of the system and of each process at the last sample
+    prev_total: u64,
+    prev_idle: u64,
+    prev_ticks: HashMap<i32, u64>,
+    history: HashMap<i32, VecDeque<f32>>,
+    cpu_history: VecDeque<f32>,
+}
+
+impl Sampler {
+    /// A sampler of the system's `/proc`.
+    pub fn new() -> Self {
+        let users = std::fs::read_to_string("/etc/passwd")
+            .map(|s| parse_passwd(&s))
+            .unwrap_or_default();
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        Self::with_root(PathBuf::from("/proc"), users, page_size.max(1) as u64)
+    }
+
+    /// A sampler of the `/proc` tree at ROOT, naming users from USERS.
+    pub fn with_root(root: PathBuf, users: HashMap<u32, String>, page_size: u64) -> Self {
+        Sampler {
+            root,
+            users,
+            page_size,
+            prev_total: 0,
+            prev_idle: 0,
+            prev_ticks: HashMap::new(),
+            history: HashMap::new(),
+            cpu_history: VecDeque::new(),
+        }
+    }
+
+    fn read(&self, path: impl AsRef<Path>) -> Option<String> {
+        std::fs::read_to_string(self.root.join(path)).ok()
+    }
+
+    /// Read the processes and system load now.
+    pub fn sample(&mut self) -> Snapshot {
+        let (total, idle, cpus) = self
+            .read("stat")
+            .and_then(|s| parse_cpu_times(&s))
+            .unwrap_or((self.prev_total, self.prev_idle, 1));
+        let elapsed = total.saturating_sub(self.prev_total);
+        let cpu = if self.prev_total > 0 && elapsed > 0 {
+            let busy = elapsed.saturating_sub(idle.saturating_sub(self.prev_idle));
+            busy as f32 * 100.0 / elapsed as f32
+        } else {
+            0.0
+        };
+        let first = self.prev_total == 0;
+        self.prev_total = total;
+        self.prev_idle = idle;
+        push_sample(&mut self.cpu_history, cpu);
+
+        let mut processes = Vec::new();
+        let mut ticks = HashMap::new();
+        let dirs = std::fs::read_dir(&self.root).into_iter().flatten().flatten();
+        for entry in dirs {
+            let Some(pid) = entry.file_name().to_str().and_then(|n| n.parse::<i32>().ok()) else {
+                continue;
+            };
+            // Processes can exit while being read
+            let Some(stat) = self.read(format!("{}/stat", pid)).and_then(|s| parse_stat(&s)) else {
+                continue;
+            };
+            let cpu = match self.prev_ticks.get(&pid) {
+                Some(&prev) if !first && elapsed > 0 => {
+                    stat.ticks.saturating_sub(prev) as f32 * 100.0 * cpus as f32 / elapsed as f32
+                }
+                _ => 0.0,
+            };
+            ticks.insert(pid, stat.ticks);
+            let history = self.history.entry(pid).or_default();
+            push_sample(history, cpu);
+
+            let uid = self.read(format!("{}/status", pid)).and_then(|s| parse_uid(&s));
+            let user = uid.map_or_else(String::new, |uid| {
+                self.users.get(&uid).cloned().unwrap_or_else(|| uid.to_string())
+            });
+            let cmdline = self.read(format!("{}/cmdline", pid)).unwrap_or_default();
+            let command = cmdline.trim_end_matches('\0').replace('\0', " ");
+            let command = if command.is_empty() { format!("[{}]", stat.name) } else { command };
+            processes.push(ProcessInfo {
+                pid,
+                ppid: stat.ppid,
+                name: stat.name,
+                command,
+                user,
+                state: stat.state,
+                nice: stat.nice,
+                threads: stat.threads,
+                rss: stat.rss_pages * self.page_size,
+                cpu,
+                cpu_history: self.history[&pid].iter().copied().collect(),
+            });
+        }
+        // Forget processes that have exited
+        self.history.retain(|pid, _| ticks.contains_key(pid));
+        self.prev_ticks = ticks;
+
+        let (mem_total, mem_available) = self.read("meminfo").map_or((0, 0), |s| parse_meminfo(&s));
+        Snapshot {
+            processes,
+            cpu,
+            cpu_history: self.cpu_history.iter().copied().collect(),
+            mem_total,
+            mem_used: mem_total.saturating_sub(mem_available),
+            cpus,
+        }
+    }
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Append SAMPLE to HISTORY, dropping the oldest beyond `HISTORY_LEN`.
+fn push_sample(history: &mut VecDeque<f32>, sample: f32) {
+    if history.len() == HISTORY_LEN {
+        history.pop_front();
+    }
+    history.push_back(sample);
+}
+
+/// Send signal SIGNAL to process PID.
+pub fn send_signal(pid: i32, signal: i32) -> Result<(), String> {
+    if pid <= 0 {
+        return Err(format!("invalid process id {}", pid));
+    }
+    if unsafe { libc::kill(pid, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error().to_string())
+    }
+}
+
+/// Set the nice value of process PID to NICE (-20 to 19).
+pub fn renice(pid: i32, nice: i32) -> Result<(), String> {
+    if pid <= 0 {
+        return Err(format!("invalid process id {}", pid));
+    }
+    let nice = nice.clamp(-20, 19);
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, nice) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error().to_string())
+    }
+}
+
+/// Samples processes on a worker thread every interval until dropped.
+pub struct ProcessMonitor {
+    rx: mpsc::Receiver<Snapshot>,
+}
+
+impl ProcessMonitor {
+    /// Start sampling with SAMPLER every INTERVAL; the first sample is
+    /// taken at once.
+    pub fn start(mut sampler: Sampler, interval: Duration) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let spawned = std::thread::Builder::new()
+            .name("process-monitor".into())
+            .spawn(move || loop {
+                // The receiver is gone once the monitor is dropped
+                if tx.send(sampler.sample()).is_err() {
+                    break;
+                }
+                std::thread::sleep(interval);
+            });
+        if let Err(e) = spawned {
+            log::warn!("process-monitor: cannot start sampler thread: {}", e);
+        }
+        ProcessMonitor { rx }
+    }
+
+    /// The latest snapshot taken since the last call, if any.
+    pub fn poll(&self) -> Option<Snapshot> {
+        self.rx.try_iter().last()
+    }
+
+    /// Block until the next snapshot, or None if sampling stopped.
+    pub fn wait(&self) -> Option<Snapshot> {
+        self.rx.recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: i32, user: &str, cpu: f32, rss: u64, command: &str) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            ppid: 1,
+            name: command.to_string(),
+            command: command.to_string(),
+            user: user.to_string(),
+            state: 'S',
+            nice: 0,
+            threads: 1,
+            rss,
+            cpu,
+            cpu_history: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn proc_files_parse() {
+        let stat = "42 (tmux: server) S 1 42 42 0 -1 4194560 900 0 0 0 150 50 0 0 20 5 3 0 1000 123456 789 18446744073709551615";
+        assert_eq!(
+            parse_stat(stat),
+            Some(ProcStat {
+                name: "tmux: server".to_string(),
+                state: 'S',
+                ppid: 1,
+                ticks: 200,
+                nice: 5,
+                threads: 3,
+                rss_pages: 789,
+            })
+        );
+        assert_eq!(parse_stat("42 (truncated) S 1"), None);
+
+        let cpu = "cpu  100 0 50 800 50 0 0 0 0 0\ncpu0 50 0 25 400 25 0 0 0 0 0\ncpu1 50 0 25 400 25 0 0 0 0 0\nintr 1\n";
+        assert_eq!(parse_cpu_times(cpu), Some((1000, 850, 2)));
+        assert_eq!(parse_meminfo("MemTotal: 1000 kB\nMemFree: 100 kB\nMemAvailable: 400 kB\n"), (1024000, 409600));
+        assert_eq!(parse_uid("Name:\tbash\nUid:\t1000\t1000\t1000\t1000\n"), Some(1000));
+        assert_eq!(parse_passwd("root:x:0:0::/root:/bin/sh\nme:x:1000:1000::/home/me:/bin/sh\n")[&1000], "me");
+    }
+
+    #[test]
+    fn sorting_and_filtering() {
+        let mut procs = vec![
+            process(30, "root", 1.0, 300, "/sbin/init"),
+            process(10, "me", 50.0, 100, "emacs --daemon"),
+            process(20, "me", 5.0, 900, "firefox"),
+        ];
+        sort_processes(&mut procs, ProcessSortKey::Cpu, true);
+        assert_eq!(procs.iter().map(|p| p.pid).collect::<Vec<_>>(), [10, 20, 30]);
+        sort_processes(&mut procs, ProcessSortKey::Memory, false);
+        assert_eq!(procs.iter().map(|p| p.pid).collect::<Vec<_>>(), [10, 30, 20]);
+        sort_processes(&mut procs, ProcessSortKey::User, false);
+        assert_eq!(procs.iter().map(|p| p.pid).collect::<Vec<_>>(), [10, 20, 30]);
+        assert_eq!(ProcessSortKey::Command.next(), ProcessSortKey::Pid);
+
+        assert_eq!(filter_processes(&procs, "EMACS"), [0]);
+        assert_eq!(filter_processes(&procs, "root"), [2]);
+        assert_eq!(filter_processes(&procs, "20"), [1]);
+        assert_eq!(filter_processes(&procs, ""), [0, 1, 2]);
+    }
+
+    #[test]
+    fn cpu_use_is_the_difference_between_samples() {
+        let root = std::env::temp_dir().join(format!("neomacs-proc-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("7")).unwrap();
+        let write = |path: &str, contents: &str| std::fs::write(root.join(path), contents).unwrap();
+        let stat = |ticks: u64| format!("7 (worker) R 1 7 7 0 -1 0 0 0 0 0 {} 0 0 0 20 0 1 0 1 1 10 0", ticks);
+        write("stat", "cpu  100 0 0 900 0 0 0 0\ncpu0 100 0 0 900 0 0 0 0\n");
+        write("meminfo", "MemTotal: 2048 kB\nMemAvailable: 512 kB\n");
+        write("7/stat", &stat(100));
+        write("7/status", "Uid:\t1000\t1000\t1000\t1000\n");
+        write("7/cmdline", "worker\0--fast\0");
+        let users = HashMap::from([(1000, "me".to_string())]);
+        let mut sampler = Sampler::with_root(root.clone(), users, 4096);
+
+        let first = sampler.sample();
+        assert_eq!(first.processes.len(), 1);
+        let p = &first.processes[0];
+        assert_eq!((p.command.as_str(), p.user.as_str(), p.rss, p.cpu), ("worker --fast", "me", 40960, 0.0));
+        assert_eq!((first.mem_total, first.mem_used), (2048 * 1024, 1536 * 1024));
+
+        // 1000 ticks later the worker has used 250 of them and the
+        // system 500
+        write("stat", "cpu  600 0 0 1400 0 0 0 0\ncpu0 600 0 0 1400 0 0 0 0\n");
+        write("7/stat", &stat(350));
+        let second = sampler.sample();
+        assert_eq!(second.cpu, 50.0);
+        assert_eq!(second.processes[0].cpu, 25.0);
+        assert_eq!(second.processes[0].cpu_history, [0.0, 25.0]);
+        assert_eq!(second.cpu_history, [0.0, 50.0]);
+
+        // Exited processes are dropped
+        std::fs::remove_dir_all(root.join("7")).unwrap();
+        assert!(sampler.sample().processes.is_empty());
+        assert!(sampler.history.is_empty());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn monitor_samples_in_the_background() {
+        let monitor = ProcessMonitor::start(Sampler::new(), Duration::from_millis(10));
+        let snapshot = monitor.wait().unwrap();
+        if cfg!(target_os = "linux") {
+            let me = std::process::id() as i32;
+            assert!(snapshot.processes.iter().any(|p| p.pid == me));
+            assert!(snapshot.mem_total > 0);
+        }
+        assert!(send_signal(0, libc::SIGTERM).is_err());
+        assert!(renice(-1, 0).is_err());
+    }
+}
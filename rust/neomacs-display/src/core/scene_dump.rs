@@ -0,0 +1,470 @@
+//! Scene dumps: what the engine thinks it is drawing, as JSON.
+//!
+//! A dump lists every frame the render thread holds (root first, then
+//! child frames) with its windows, the faces its glyphs use, its text as
+//! runs of characters sharing a face and a baseline, its other glyphs and
+//! its cursors, followed by the cursor animation and the running window
+//! animations.  Test harnesses compare dumps instead of screenshots, and
+//! bug reports can attach one.
+//!
+//! Colors are written as sRGB `#rrggbb` (`#rrggbbaa` when translucent),
+//! the way Emacs spells them, not as the linear values the GPU gets.
+//! Coordinates are logical pixels, frame-absolute.
+
+use std::fmt::Write;
+
+use crate::core::frame_glyphs::{CursorStyle, FrameGlyph, FrameGlyphBuffer};
+use crate::core::types::{Color, Rect};
+
+/// A running window animation
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationState {
+    /// "crossfade", "scroll", "text-zoom", "line-diff", "buffer" or
+    /// "keyframe"
+    pub kind: &'static str,
+    pub window_id: i64,
+    /// Fraction done, from 0 to 1
+    pub progress: f32,
+}
+
+/// The animated cursor of the selected window
+#[derive(Debug, Clone, PartialEq)]
+pub struct CursorAnimationState {
+    pub animating: bool,
+    pub blink_on: bool,
+    /// Where the cursor is drawn now
+    pub current: Rect,
+    /// Where it is heading
+    pub target: Option<Rect>,
+}
+
+/// Everything a dump describes
+#[derive(Debug, Default)]
+pub struct SceneDump<'a> {
+    /// Root frame first, then child frames
+    pub frames: Vec<&'a FrameGlyphBuffer>,
+    pub cursor: Option<CursorAnimationState>,
+    pub animations: Vec<AnimationState>,
+}
+
+impl SceneDump<'_> {
+    /// The dump as a JSON object
+    pub fn to_json(&self) -> String {
+        let mut w = JsonWriter::default();
+        w.begin('{');
+        w.key("frames");
+        w.begin('[');
+        for frame in &self.frames {
+            write_frame(&mut w, frame);
+        }
+        w.end(']');
+        w.key("cursor_animation");
+        match &self.cursor {
+            Some(cursor) => {
+                w.begin('{');
+                w.key("animating");
+                w.bool(cursor.animating);
+                w.key("blink_on");
+                w.bool(cursor.blink_on);
+                w.key("current");
+                w.rect(&cursor.current);
+                w.key("target");
+                match &cursor.target {
+                    Some(target) => w.rect(target),
+                    None => w.raw("null"),
+                }
+                w.end('}');
+            }
+            None => w.raw("null"),
+        }
+        w.key("animations");
+        w.begin('[');
+        for animation in &self.animations {
+            w.begin('{');
+            w.key("kind");
+            w.string(animation.kind);
+            w.key("window");
+            w.int(animation.window_id);
+            w.key("progress");
+            w.number(animation.progress);
+            w.end('}');
+        }
+        w.end(']');
+        w.end('}');
+        w.out
+    }
+}
+
+fn write_frame(w: &mut JsonWriter, frame: &FrameGlyphBuffer) {
+    w.begin('{');
+    w.key("id");
+    w.int(frame.frame_id as i64);
+    w.key("parent");
+    w.int(frame.parent_id as i64);
+    w.key("bounds");
+    w.rect(&Rect::new(frame.parent_x, frame.parent_y, frame.width, frame.height));
+    w.key("z_order");
+    w.int(frame.z_order as i64);
+    w.key("background");
+    w.color(&frame.background);
+
+    w.key("windows");
+    w.begin('[');
+    for info in &frame.window_infos {
+        w.begin('{');
+        w.key("id");
+        w.int(info.window_id);
+        w.key("buffer");
+        w.int(info.buffer_id as i64);
+        w.key("file");
+        w.string(&info.buffer_file_name);
+        w.key("start");
+        w.int(info.window_start);
+        w.key("end");
+        w.int(info.window_end);
+        w.key("bounds");
+        w.rect(&info.bounds);
+        w.key("mode_line_height");
+        w.number(info.mode_line_height);
+        w.key("header_line_height");
+        w.number(info.header_line_height);
+        w.key("selected");
+        w.bool(info.selected);
+        w.key("minibuffer");
+        w.bool(info.is_minibuffer);
+        w.end('}');
+    }
+    w.end(']');
+
+    w.key("faces");
+    w.begin('[');
+    let mut face_ids: Vec<u32> = frame.faces.keys().copied().collect();
+    face_ids.sort_unstable();
+    for face in face_ids.iter().map(|id| &frame.faces[id]) {
+        w.begin('{');
+        w.key("id");
+        w.int(face.id as i64);
+        w.key("family");
+        w.string(&face.font_family);
+        w.key("size");
+        w.number(face.font_size);
+        w.key("weight");
+        w.int(face.font_weight as i64);
+        w.key("attributes");
+        w.int(face.attributes.bits() as i64);
+        w.key("foreground");
+        w.color(&face.foreground);
+        w.key("background");
+        w.color(&face.background);
+        w.end('}');
+    }
+    w.end(']');
+
+    w.key("runs");
+    w.begin('[');
+    for run in glyph_runs(&frame.glyphs) {
+        w.begin('{');
+        w.key("face");
+        w.int(run.face_id as i64);
+        w.key("bounds");
+        w.rect(&run.bounds);
+        w.key("text");
+        w.string(&run.text);
+        w.key("overlay");
+        w.bool(run.overlay);
+        w.end('}');
+    }
+    w.end(']');
+
+    w.key("glyphs");
+    w.begin('[');
+    for glyph in &frame.glyphs {
+        let (kind, bounds, id) = match glyph {
+            FrameGlyph::Stretch { x, y, width, height, face_id, .. } => {
+                ("stretch", Rect::new(*x, *y, *width, *height), *face_id)
+            }
+            FrameGlyph::Image { image_id, x, y, width, height } => {
+                ("image", Rect::new(*x, *y, *width, *height), *image_id)
+            }
+            FrameGlyph::Video { video_id, x, y, width, height } => {
+                ("video", Rect::new(*x, *y, *width, *height), *video_id)
+            }
+            FrameGlyph::WebKit { webkit_id, x, y, width, height } => {
+                ("webkit", Rect::new(*x, *y, *width, *height), *webkit_id)
+            }
+            #[cfg(feature = "neo-term")]
+            FrameGlyph::Terminal { terminal_id, x, y, width, height } => {
+                ("terminal", Rect::new(*x, *y, *width, *height), *terminal_id)
+            }
+            FrameGlyph::Background { bounds, .. } => ("background", *bounds, 0),
+            FrameGlyph::Border { x, y, width, height, .. } => {
+                ("border", Rect::new(*x, *y, *width, *height), 0)
+            }
+            FrameGlyph::ScrollBar { x, y, width, height, .. } => {
+                ("scroll-bar", Rect::new(*x, *y, *width, *height), 0)
+            }
+            FrameGlyph::Char { .. } | FrameGlyph::Cursor { .. } => continue,
+        };
+        w.begin('{');
+        w.key("kind");
+        w.string(kind);
+        w.key("id");
+        w.int(id as i64);
+        w.key("bounds");
+        w.rect(&bounds);
+        w.end('}');
+    }
+    w.end(']');
+
+    w.key("cursors");
+    w.begin('[');
+    for glyph in &frame.glyphs {
+        if let FrameGlyph::Cursor { window_id, x, y, width, height, style, color } = glyph {
+            w.begin('{');
+            w.key("window");
+            w.int(*window_id as i64);
+            w.key("bounds");
+            w.rect(&Rect::new(*x, *y, *width, *height));
+            w.key("style");
+            w.string(match style {
+                CursorStyle::FilledBox => "box",
+                CursorStyle::Bar(_) => "bar",
+                CursorStyle::Hbar(_) => "hbar",
+                CursorStyle::Hollow => "hollow",
+            });
+            w.key("color");
+            w.color(color);
+            w.end('}');
+        }
+    }
+    w.end(']');
+    w.end('}');
+}
+
+/// Characters drawn side by side in one face on one row
+#[derive(Debug, PartialEq)]
+struct GlyphRun {
+    face_id: u32,
+    bounds: Rect,
+    text: String,
+    overlay: bool,
+}
+
+/// Group the character glyphs of GLYPHS into runs, in drawing order
+fn glyph_runs(glyphs: &[FrameGlyph]) -> Vec<GlyphRun> {
+    let mut runs: Vec<GlyphRun> = Vec::new();
+    for glyph in glyphs {
+        let FrameGlyph::Char { char, composed, x, y, width, height, face_id, is_overlay, .. } = glyph
+        else {
+            continue;
+        };
+        let text = composed.as_deref().map_or_else(|| char.to_string(), str::to_string);
+        if let Some(run) = runs.last_mut() {
+            if run.face_id == *face_id
+                && run.overlay == *is_overlay
+                && run.bounds.y == *y
+                && (run.bounds.right() - x).abs() < 0.5
+            {
+                run.text.push_str(&text);
+                run.bounds.width = x + width - run.bounds.x;
+                run.bounds.height = run.bounds.height.max(*height);
+                continue;
+            }
+        }
+        runs.push(GlyphRun {
+            face_id: *face_id,
+            bounds: Rect::new(*x, *y, *width, *height),
+            text,
+            overlay: *is_overlay,
+        });
+    }
+    runs
+}
+
+/// Builds compact JSON, tracking where commas go
+#[derive(Default)]
+struct JsonWriter {
+    out: String,
+    /// Whether the innermost open object or array has a member yet
+    nonempty: Vec<bool>,
+    /// A key was just written; its value needs no comma
+    after_key: bool,
+}
+
+impl JsonWriter {
+    fn separate(&mut self) {
+        if self.after_key {
+            self.after_key = false;
+        } else if let Some(nonempty) = self.nonempty.last_mut() {
+            if *nonempty {
+                self.out.push(',');
+            }
+            *nonempty = true;
+        }
+    }
+
+    fn begin(&mut self, bracket: char) {
+        self.separate();
+        self.out.push(bracket);
+        self.nonempty.push(false);
+    }
+
+    fn end(&mut self, bracket: char) {
+        self.nonempty.pop();
+        self.out.push(bracket);
+    }
+
+    fn key(&mut self, key: &str) {
+        self.string(key);
+        self.out.push(':');
+        self.after_key = true;
+    }
+
+    fn raw(&mut self, value: &str) {
+        self.separate();
+        self.out.push_str(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.raw(if value { "true" } else { "false" });
+    }
+
+    fn int(&mut self, value: i64) {
+        self.separate();
+        let _ = write!(self.out, "{}", value);
+    }
+
+    fn number(&mut self, value: f32) {
+        if value.is_finite() {
+            self.separate();
+            // Shortest form that reads back the same f32
+            let _ = write!(self.out, "{}", value);
+        } else {
+            self.raw("null");
+        }
+    }
+
+    fn string(&mut self, value: &str) {
+        self.separate();
+        self.out.push('"');
+        for c in value.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\t' => self.out.push_str("\\t"),
+                c if (c as u32) < 0x20 => {
+                    let _ = write!(self.out, "\\u{:04x}", c as u32);
+                }
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+
+    fn rect(&mut self, rect: &Rect) {
+        self.begin('[');
+        self.number(rect.x);
+        self.number(rect.y);
+        self.number(rect.width);
+        self.number(rect.height);
+        self.end(']');
+    }
+
+    fn color(&mut self, color: &Color) {
+        let srgb = |c: f32| {
+            let c = c.clamp(0.0, 1.0);
+            let c = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+            (c * 255.0).round() as u8
+        };
+        let mut hex = format!("#{:02x}{:02x}{:02x}", srgb(color.r), srgb(color.g), srgb(color.b));
+        if color.a < 1.0 {
+            let _ = write!(hex, "{:02x}", (color.a.clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+        self.string(&hex);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::face::Face;
+
+    #[test]
+    fn characters_group_into_runs_by_face_and_row() {
+        let mut frame = FrameGlyphBuffer::with_size(100.0, 40.0);
+        for (i, c) in "ab".chars().enumerate() {
+            frame.add_char(c, i as f32 * 8.0, 0.0, 8.0, 16.0, 12.0, false);
+        }
+        // Same face, but not adjacent
+        frame.add_char('c', 40.0, 0.0, 8.0, 16.0, 12.0, false);
+        // Next row
+        frame.add_char('d', 48.0, 16.0, 8.0, 16.0, 12.0, false);
+        let runs = glyph_runs(&frame.glyphs);
+        let texts: Vec<(&str, Rect)> = runs.iter().map(|r| (r.text.as_str(), r.bounds)).collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("ab", Rect::new(0.0, 0.0, 16.0, 16.0)),
+                ("c", Rect::new(40.0, 0.0, 8.0, 16.0)),
+                ("d", Rect::new(48.0, 16.0, 8.0, 16.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn dump_is_well_formed_json() {
+        let mut frame = FrameGlyphBuffer::with_size(100.0, 40.0);
+        frame.frame_id = 7;
+        frame.background = Color::rgb(1.0, 1.0, 1.0);
+        let mut face = Face::new(3);
+        face.font_family = "Mono \"Code\"".to_string();
+        frame.faces.insert(3, face);
+        frame.add_char('x', 0.0, 0.0, 8.0, 16.0, 12.0, false);
+        frame.add_cursor(1, 0.0, 0.0, 2.0, 16.0, CursorStyle::Bar(2.0), Color::rgb(0.0, 0.0, 0.0));
+        let dump = SceneDump {
+            frames: vec![&frame],
+            cursor: None,
+            animations: vec![AnimationState { kind: "scroll", window_id: 1, progress: 0.5 }],
+        };
+        let json = dump.to_json();
+
+        assert!(json.starts_with("{\"frames\":[{\"id\":7,\"parent\":0,\"bounds\":[0,0,100,40]"));
+        assert!(json.contains("\"background\":\"#ffffff\""));
+        assert!(json.contains("\"family\":\"Mono \\\"Code\\\"\""));
+        assert!(json.contains("\"text\":\"x\""));
+        assert!(json.contains("\"style\":\"bar\",\"color\":\"#000000\""));
+        assert!(json.ends_with(
+            "\"cursor_animation\":null,\
+             \"animations\":[{\"kind\":\"scroll\",\"window\":1,\"progress\":0.5}]}"
+        ));
+        // Brackets balance outside strings, and no comma is doubled or trails
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut prev = ' ';
+        for c in json.chars() {
+            if in_string {
+                match (escaped, c) {
+                    (false, '\\') => escaped = true,
+                    (false, '"') => in_string = false,
+                    _ => escaped = false,
+                }
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '{' | '[' => depth += 1,
+                '}' | ']' => {
+                    assert_ne!(prev, ',');
+                    depth -= 1;
+                }
+                ',' => assert!(!matches!(prev, ',' | '{' | '[')),
+                _ => {}
+            }
+            prev = c;
+        }
+        assert_eq!(depth, 0);
+        assert!(!in_string);
+    }
+}
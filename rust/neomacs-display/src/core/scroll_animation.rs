@@ -165,6 +165,18 @@ impl ScrollEffect {
         }
     }
 
+    /// Parse a buffer transition effect name as accepted by
+    /// `neomacs-start-buffer-transition`.  Returns `None` for "none".
+    /// Names without a dedicated effect map to the closest scroll effect.
+    pub fn from_transition_name(s: &str) -> Option<Self> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "none" => None,
+            "slide-left" | "slide-right" | "slide-up" | "slide-down" | "push" => Some(Self::Slide),
+            "scale-fade" => Some(Self::ScaleZoom),
+            other => Some(Self::from_str(other)),
+        }
+    }
+
     /// Convert to kebab-case string.
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -238,7 +250,7 @@ impl Default for ScrollEffect {
 // ─── Scroll Easing (how the animation parameter `t` evolves) ────────────
 
 /// Physics model for scroll animation timing.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ScrollEasing {
     /// Standard ease-out quadratic (current default).
     EaseOutQuad,
@@ -254,6 +266,10 @@ pub enum ScrollEasing {
 
     /// Ease-in-out cubic (smooth S-curve).
     EaseInOutCubic,
+
+    /// Any transition easing, including parametric springs and Bezier
+    /// curves.
+    Curve(crate::core::buffer_transition::TransitionEasing),
 }
 
 impl ScrollEasing {
@@ -281,6 +297,7 @@ impl ScrollEasing {
                 let et = (-omega * t).exp();
                 1.0 - (1.0 + omega * t) * et
             }
+            Self::Curve(easing) => easing.apply(t),
         }
     }
 
@@ -302,6 +319,7 @@ impl ScrollEasing {
             Self::Spring => "spring",
             Self::Linear => "linear",
             Self::EaseInOutCubic => "ease-in-out-cubic",
+            Self::Curve(_) => "curve",
         }
     }
 }
@@ -845,6 +863,17 @@ mod tests {
         assert_eq!(ScrollEffect::default(), ScrollEffect::Slide);
     }
 
+    #[test]
+    fn test_scroll_effect_from_transition_name() {
+        assert_eq!(ScrollEffect::from_transition_name("none"), None);
+        assert_eq!(ScrollEffect::from_transition_name("slide-left"), Some(ScrollEffect::Slide));
+        assert_eq!(ScrollEffect::from_transition_name("push"), Some(ScrollEffect::Slide));
+        assert_eq!(ScrollEffect::from_transition_name("scale-fade"), Some(ScrollEffect::ScaleZoom));
+        assert_eq!(ScrollEffect::from_transition_name("blur"), Some(ScrollEffect::MotionBlur));
+        assert_eq!(ScrollEffect::from_transition_name("page-curl"), Some(ScrollEffect::PageCurl));
+        assert_eq!(ScrollEffect::from_transition_name("crossfade"), Some(ScrollEffect::Crossfade));
+    }
+
     #[test]
     fn test_scroll_effect_from_str_case_insensitive() {
         assert_eq!(ScrollEffect::from_str("SLIDE"), ScrollEffect::Slide);
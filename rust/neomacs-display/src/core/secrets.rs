@@ -0,0 +1,347 @@
+//! Secrets in the system keyring, for auth-source.
+//!
+//! Passwords and API tokens are kept by the platform's secret store —
+//! the Secret Service (GNOME Keyring, KWallet) on Linux, the Keychain on
+//! macOS, the Credential Manager on Windows — through the `keyring`
+//! crate (`secrets` feature), so nothing is written to ~/.authinfo.
+//!
+//! Entries are found the way auth-source looks them up, by host, user
+//! and port.  The stores can only fetch a secret whose name is known,
+//! so the (host, user, port) of every entry stored through Neomacs are
+//! listed in an index, itself one more keyring entry; searching reads
+//! the index, and only the secrets asked for are fetched.
+
+use thiserror::Error;
+
+/// Service of the keyring entry holding the index
+const INDEX_SERVICE: &str = "neomacs-secrets";
+const INDEX_USER: &str = "index";
+/// Prefix of the services entries are stored under
+const SERVICE_PREFIX: &str = "neomacs";
+/// Keyring user of entries without a user; some stores refuse ""
+const NO_USER: &str = "*";
+
+/// Errors of the secret store
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SecretsError {
+    #[error("Secret store: {0}")]
+    Backend(String),
+
+    #[error("Built without system keyring support")]
+    Unsupported,
+}
+
+/// A secret's name: an empty field stands for any host, user or port,
+/// as a missing one does in ~/.authinfo.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SecretEntry {
+    pub host: String,
+    pub user: String,
+    pub port: String,
+}
+
+impl SecretEntry {
+    /// An entry; tabs and newlines, which cannot be in the index, are
+    /// dropped.
+    pub fn new(host: &str, user: &str, port: &str) -> Self {
+        SecretEntry { host: clean(host), user: clean(user), port: clean(port) }
+    }
+
+    /// Whether this entry answers QUERY.
+    pub fn matches(&self, query: &SecretQuery) -> bool {
+        fn field(entry: &str, wanted: &Option<String>, ignore_case: bool) -> bool {
+            match wanted {
+                None => true,
+                Some(_) if entry.is_empty() => true,
+                Some(wanted) if ignore_case => entry.eq_ignore_ascii_case(wanted),
+                Some(wanted) => entry == wanted,
+            }
+        }
+        field(&self.host, &query.host, true)
+            && field(&self.user, &query.user, false)
+            && field(&self.port, &query.port, false)
+    }
+
+    /// The keyring service and user it is stored under.
+    fn key(&self) -> (String, String) {
+        let mut service = format!("{}:{}", SERVICE_PREFIX, self.host);
+        if !self.port.is_empty() {
+            service.push(':');
+            service.push_str(&self.port);
+        }
+        let user = if self.user.is_empty() { NO_USER.to_string() } else { self.user.clone() };
+        (service, user)
+    }
+}
+
+fn clean(field: &str) -> String {
+    field.chars().filter(|c| !matches!(c, '\t' | '\n' | '\r')).collect::<String>().trim().to_string()
+}
+
+/// What to look for: None matches anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SecretQuery {
+    pub host: Option<String>,
+    pub user: Option<String>,
+    pub port: Option<String>,
+}
+
+impl SecretQuery {
+    /// Whether it names exactly ENTRY, for deletion.
+    fn names(&self, entry: &SecretEntry) -> bool {
+        fn field(entry: &str, wanted: &Option<String>) -> bool {
+            wanted.as_deref().is_none_or(|wanted| wanted == entry)
+        }
+        field(&entry.host, &self.host) && field(&entry.user, &self.user) && field(&entry.port, &self.port)
+    }
+}
+
+/// A platform secret store, holding secrets by service and user.
+pub trait SecretBackend: Send {
+    fn get(&self, service: &str, user: &str) -> Result<Option<String>, SecretsError>;
+    fn set(&self, service: &str, user: &str, secret: &str) -> Result<(), SecretsError>;
+    /// Delete a secret; false if there was none.
+    fn delete(&self, service: &str, user: &str) -> Result<bool, SecretsError>;
+}
+
+#[cfg(feature = "secrets")]
+mod backend {
+    use super::{SecretBackend, SecretsError};
+
+    /// The system keyring
+    pub struct SystemKeyring;
+
+    fn entry(service: &str, user: &str) -> Result<keyring::Entry, SecretsError> {
+        keyring::Entry::new(service, user).map_err(|e| SecretsError::Backend(e.to_string()))
+    }
+
+    impl SecretBackend for SystemKeyring {
+        fn get(&self, service: &str, user: &str) -> Result<Option<String>, SecretsError> {
+            match entry(service, user)?.get_password() {
+                Ok(secret) => Ok(Some(secret)),
+                Err(keyring::Error::NoEntry) => Ok(None),
+                Err(e) => Err(SecretsError::Backend(e.to_string())),
+            }
+        }
+
+        fn set(&self, service: &str, user: &str, secret: &str) -> Result<(), SecretsError> {
+            entry(service, user)?.set_password(secret).map_err(|e| SecretsError::Backend(e.to_string()))
+        }
+
+        fn delete(&self, service: &str, user: &str) -> Result<bool, SecretsError> {
+            match entry(service, user)?.delete_credential() {
+                Ok(()) => Ok(true),
+                Err(keyring::Error::NoEntry) => Ok(false),
+                Err(e) => Err(SecretsError::Backend(e.to_string())),
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "secrets"))]
+mod backend {
+    use super::{SecretBackend, SecretsError};
+
+    /// Stand-in for builds without a keyring
+    pub struct SystemKeyring;
+
+    impl SecretBackend for SystemKeyring {
+        fn get(&self, _service: &str, _user: &str) -> Result<Option<String>, SecretsError> {
+            Err(SecretsError::Unsupported)
+        }
+
+        fn set(&self, _service: &str, _user: &str, _secret: &str) -> Result<(), SecretsError> {
+            Err(SecretsError::Unsupported)
+        }
+
+        fn delete(&self, _service: &str, _user: &str) -> Result<bool, SecretsError> {
+            Err(SecretsError::Unsupported)
+        }
+    }
+}
+
+pub use backend::SystemKeyring;
+
+/// Whether this build can use the system keyring.
+pub fn available() -> bool {
+    cfg!(feature = "secrets")
+}
+
+/// Secrets stored in a backend, found through the index.
+pub struct Secrets {
+    backend: Box<dyn SecretBackend>,
+    /// The index, read on first use
+    index: Option<Vec<SecretEntry>>,
+}
+
+impl Secrets {
+    pub fn new(backend: Box<dyn SecretBackend>) -> Self {
+        Secrets { backend, index: None }
+    }
+
+    /// Secrets in the system keyring.
+    pub fn system() -> Self {
+        Secrets::new(Box::new(SystemKeyring))
+    }
+
+    fn index(&mut self) -> Result<&mut Vec<SecretEntry>, SecretsError> {
+        if self.index.is_none() {
+            let text = self.backend.get(INDEX_SERVICE, INDEX_USER)?.unwrap_or_default();
+            let entries = text
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    let mut fields = line.split('\t');
+                    let mut next = || fields.next().unwrap_or("");
+                    let (host, user, port) = (next(), next(), next());
+                    SecretEntry::new(host, user, port)
+                })
+                .collect();
+            self.index = Some(entries);
+        }
+        Ok(self.index.as_mut().unwrap())
+    }
+
+    fn save_index(&mut self) -> Result<(), SecretsError> {
+        let Some(entries) = &self.index else { return Ok(()) };
+        let text: String = entries
+            .iter()
+            .map(|e| format!("{}\t{}\t{}\n", e.host, e.user, e.port))
+            .collect();
+        self.backend.set(INDEX_SERVICE, INDEX_USER, &text)
+    }
+
+    /// Read the index again on next use, after another program changed it.
+    pub fn reload(&mut self) {
+        self.index = None;
+    }
+
+    /// The first MAX entries answering QUERY, in the order stored.
+    pub fn search(&mut self, query: &SecretQuery, max: usize) -> Result<Vec<SecretEntry>, SecretsError> {
+        Ok(self.index()?.iter().filter(|e| e.matches(query)).take(max).cloned().collect())
+    }
+
+    /// The secret of ENTRY, None if it has none.
+    pub fn secret(&mut self, entry: &SecretEntry) -> Result<Option<String>, SecretsError> {
+        let (service, user) = entry.key();
+        self.backend.get(&service, &user)
+    }
+
+    /// Store SECRET for ENTRY, replacing any it had.
+    pub fn store(&mut self, entry: SecretEntry, secret: &str) -> Result<(), SecretsError> {
+        let (service, user) = entry.key();
+        self.backend.set(&service, &user, secret)?;
+        let index = self.index()?;
+        if !index.contains(&entry) {
+            index.push(entry);
+            self.save_index()?;
+        }
+        Ok(())
+    }
+
+    /// Delete the entries QUERY names exactly; returns how many.
+    pub fn delete(&mut self, query: &SecretQuery) -> Result<usize, SecretsError> {
+        let doomed: Vec<SecretEntry> = self.index()?.iter().filter(|e| query.names(e)).cloned().collect();
+        for entry in &doomed {
+            let (service, user) = entry.key();
+            self.backend.delete(&service, &user)?;
+        }
+        if !doomed.is_empty() {
+            self.index()?.retain(|e| !doomed.contains(e));
+            self.save_index()?;
+        }
+        Ok(doomed.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// A keyring in memory, shared by its clones
+    #[derive(Clone, Default)]
+    struct MemoryKeyring(Arc<Mutex<HashMap<(String, String), String>>>);
+
+    impl SecretBackend for MemoryKeyring {
+        fn get(&self, service: &str, user: &str) -> Result<Option<String>, SecretsError> {
+            Ok(self.0.lock().unwrap().get(&(service.into(), user.into())).cloned())
+        }
+
+        fn set(&self, service: &str, user: &str, secret: &str) -> Result<(), SecretsError> {
+            self.0.lock().unwrap().insert((service.into(), user.into()), secret.into());
+            Ok(())
+        }
+
+        fn delete(&self, service: &str, user: &str) -> Result<bool, SecretsError> {
+            Ok(self.0.lock().unwrap().remove(&(service.into(), user.into())).is_some())
+        }
+    }
+
+    fn query(host: Option<&str>, user: Option<&str>, port: Option<&str>) -> SecretQuery {
+        SecretQuery {
+            host: host.map(str::to_string),
+            user: user.map(str::to_string),
+            port: port.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn entries_match_like_authinfo_lines() {
+        let entry = SecretEntry::new("api.openai.com", "apikey", "");
+        assert!(entry.matches(&query(Some("API.openai.com"), None, None)));
+        // No port in the entry: any port is fine
+        assert!(entry.matches(&query(Some("api.openai.com"), Some("apikey"), Some("443"))));
+        assert!(!entry.matches(&query(Some("api.openai.com"), Some("ApiKey"), None)));
+        assert!(!entry.matches(&query(Some("example.com"), None, None)));
+
+        let entry = SecretEntry::new(" imap.example.com\t", "me", "993\n");
+        assert_eq!(entry.host, "imap.example.com");
+        assert_eq!(entry.port, "993");
+        assert_eq!(entry.key(), ("neomacs:imap.example.com:993".to_string(), "me".to_string()));
+        assert_eq!(SecretEntry::new("h", "", "").key().1, NO_USER);
+        assert!(!entry.matches(&query(None, None, Some("143"))));
+    }
+
+    #[test]
+    fn stored_secrets_are_found_through_the_index() {
+        let keyring = MemoryKeyring::default();
+        let mut secrets = Secrets::new(Box::new(keyring.clone()));
+        secrets.store(SecretEntry::new("mail.example.com", "me", "993"), "s3cret").unwrap();
+        secrets.store(SecretEntry::new("mail.example.com", "you", "993"), "other").unwrap();
+        secrets.store(SecretEntry::new("api.example.com", "token", ""), "abc").unwrap();
+        // Storing again replaces the secret, not the entry
+        secrets.store(SecretEntry::new("mail.example.com", "me", "993"), "new").unwrap();
+
+        let found = secrets.search(&query(Some("mail.example.com"), None, None), 10).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].user, "me");
+        assert_eq!(secrets.secret(&found[0]).unwrap().as_deref(), Some("new"));
+        assert_eq!(secrets.search(&SecretQuery::default(), 1).unwrap().len(), 1);
+
+        // Another session reads the same index from the keyring
+        let mut again = Secrets::new(Box::new(keyring.clone()));
+        let found = again.search(&query(Some("api.example.com"), None, Some("https")), 10).unwrap();
+        assert_eq!(found, vec![SecretEntry::new("api.example.com", "token", "")]);
+        assert_eq!(again.secret(&found[0]).unwrap().as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn deleting_removes_secrets_and_index_entries() {
+        let keyring = MemoryKeyring::default();
+        let mut secrets = Secrets::new(Box::new(keyring.clone()));
+        secrets.store(SecretEntry::new("a.example", "me", ""), "1").unwrap();
+        secrets.store(SecretEntry::new("b.example", "me", ""), "2").unwrap();
+
+        // Deletion needs exact fields: a blank port names only blank ports
+        assert_eq!(secrets.delete(&query(Some("a.example"), None, Some("22"))).unwrap(), 0);
+        assert_eq!(secrets.delete(&query(Some("a.example"), None, None)).unwrap(), 1);
+        assert_eq!(secrets.search(&SecretQuery::default(), 10).unwrap().len(), 1);
+        assert!(!keyring.0.lock().unwrap().contains_key(&("neomacs:a.example".into(), "me".into())));
+
+        secrets.reload();
+        let left = secrets.search(&SecretQuery::default(), 10).unwrap();
+        assert_eq!(left, vec![SecretEntry::new("b.example", "me", "")]);
+    }
+}
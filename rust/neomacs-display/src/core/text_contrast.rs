@@ -0,0 +1,310 @@
+//! Keeping text readable over window background images.
+//!
+//! A background image (see `window_background`) may be as light or as
+//! dark as the text drawn over it in places.  Each image keeps a coarse
+//! grid of its luminance, computed once when it is decoded.  While
+//! drawing, the luminance under each character is estimated from the
+//! grid cells its cell covers, blended over the window's background
+//! color by the image's opacity and coverage, and compared with the text
+//! color.  When the WCAG contrast ratio falls below the configured
+//! minimum, the text is fixed in one of two ways:
+//!
+//! - a translucent scrim is drawn behind it, darkening or lightening the
+//!   image just enough (up to a maximum opacity) to reach the ratio;
+//! - its color is moved towards black or white, keeping its hue, until
+//!   the ratio is reached.
+//!
+//! Luminances here are relative luminances (0 = black, 1 = white) of
+//! linear colors, as used by the renderer.
+
+use super::types::{Color, Rect};
+use super::window_background::BackgroundQuad;
+
+/// Columns and rows of a luminance grid, at most
+pub const GRID_SIZE: u32 = 32;
+
+/// Pixels sampled along each axis of an image, at most
+const SAMPLES: u32 = 256;
+
+/// How text with too little contrast is fixed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContrastStyle {
+    /// Translucent scrim behind the text
+    Scrim,
+    /// Text color moved towards black or white
+    Recolor,
+}
+
+impl ContrastStyle {
+    /// Decode the FFI representation: 1 = recolor, anything else = scrim.
+    pub fn from_ffi(style: u32) -> Self {
+        match style {
+            1 => Self::Recolor,
+            _ => Self::Scrim,
+        }
+    }
+}
+
+/// What to do to a character with too little contrast
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContrastFix {
+    /// Draw this color (with alpha) behind the character
+    Scrim(Color),
+    /// Draw the character in this color instead
+    Foreground(Color),
+}
+
+/// Relative luminance of linear color C
+pub fn luminance(c: &Color) -> f32 {
+    0.2126 * c.r + 0.7152 * c.g + 0.0722 * c.b
+}
+
+/// WCAG contrast ratio of two luminances, from 1 to 21
+pub fn contrast_ratio(a: f32, b: f32) -> f32 {
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Linear luminance of each sRGB byte value
+fn srgb_luminance_table() -> [f32; 256] {
+    let mut table = [0.0; 256];
+    for (i, v) in table.iter_mut().enumerate() {
+        let c = i as f32 / 255.0;
+        *v = if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+    }
+    table
+}
+
+/// Mean luminance and coverage of the cells of an image
+#[derive(Debug, Clone, PartialEq)]
+pub struct LuminanceGrid {
+    cols: u32,
+    rows: u32,
+    /// Per cell: mean luminance times alpha, and mean alpha
+    cells: Vec<(f32, f32)>,
+}
+
+impl LuminanceGrid {
+    /// Grid of a WIDTH x HEIGHT image of sRGB RGBA pixels DATA.  Large
+    /// images are sampled rather than read whole.
+    pub fn from_rgba(data: &[u8], width: u32, height: u32) -> Option<Self> {
+        if width == 0 || height == 0 || data.len() < (width as usize) * (height as usize) * 4 {
+            return None;
+        }
+        let table = srgb_luminance_table();
+        let cols = width.min(GRID_SIZE);
+        let rows = height.min(GRID_SIZE);
+        let mut sums = vec![(0.0f32, 0.0f32, 0u32); (cols * rows) as usize];
+        let step_x = (width / SAMPLES).max(1);
+        let step_y = (height / SAMPLES).max(1);
+        for y in (0..height).step_by(step_y as usize) {
+            let row = (y * rows / height) * cols;
+            for x in (0..width).step_by(step_x as usize) {
+                let i = ((y * width + x) * 4) as usize;
+                let alpha = data[i + 3] as f32 / 255.0;
+                let lum = 0.2126 * table[data[i] as usize]
+                    + 0.7152 * table[data[i + 1] as usize]
+                    + 0.0722 * table[data[i + 2] as usize];
+                let cell = &mut sums[(row + x * cols / width) as usize];
+                cell.0 += lum * alpha;
+                cell.1 += alpha;
+                cell.2 += 1;
+            }
+        }
+        let cells = sums
+            .into_iter()
+            .map(|(lum, alpha, n)| {
+                let n = n.max(1) as f32;
+                (lum / n, alpha / n)
+            })
+            .collect();
+        Some(Self { cols, rows, cells })
+    }
+
+    /// Mean luminance times alpha, and mean alpha, of the part of the
+    /// image between texture coordinates `[u0, v0, u1, v1]`
+    pub fn sample(&self, uv: [f32; 4]) -> (f32, f32) {
+        let range = |a: f32, b: f32, n: u32| {
+            let first = ((a.clamp(0.0, 1.0) * n as f32) as u32).min(n - 1);
+            let last = ((b.clamp(0.0, 1.0) * n as f32).ceil() as u32).clamp(first + 1, n);
+            first..last
+        };
+        let (mut lum, mut alpha, mut n) = (0.0, 0.0, 0.0);
+        for row in range(uv[1], uv[3], self.rows) {
+            for col in range(uv[0], uv[2], self.cols) {
+                let (l, a) = self.cells[(row * self.cols + col) as usize];
+                lum += l;
+                alpha += a;
+                n += 1.0;
+            }
+        }
+        (lum / n, alpha / n)
+    }
+}
+
+/// Luminance under RECT of a window background drawn as QUADS (see
+/// `background_quads`) from an image summarized by GRID, at OPACITY over
+/// a background of luminance BASE.  None if the image does not reach
+/// RECT.
+pub fn luminance_under(
+    rect: &Rect,
+    quads: &[BackgroundQuad],
+    grid: &LuminanceGrid,
+    opacity: f32,
+    base: f32,
+) -> Option<f32> {
+    let area = rect.width * rect.height;
+    if area <= 0.0 {
+        return None;
+    }
+    let (mut lum, mut covered) = (0.0, 0.0);
+    for q in quads {
+        let left = rect.x.max(q.rect.x);
+        let top = rect.y.max(q.rect.y);
+        let right = (rect.x + rect.width).min(q.rect.x + q.rect.width);
+        let bottom = (rect.y + rect.height).min(q.rect.y + q.rect.height);
+        if right <= left || bottom <= top {
+            continue;
+        }
+        let [u0, v0, u1, v1] = q.uv;
+        let u = |x: f32| u0 + (x - q.rect.x) / q.rect.width * (u1 - u0);
+        let v = |y: f32| v0 + (y - q.rect.y) / q.rect.height * (v1 - v0);
+        let (image_lum, image_alpha) = grid.sample([u(left), v(top), u(right), v(bottom)]);
+        let weight = (right - left) * (bottom - top);
+        // Source-over blend of the image on the background
+        lum += weight * (base * (1.0 - image_alpha * opacity) + image_lum * opacity);
+        covered += weight;
+    }
+    if covered <= 0.0 {
+        return None;
+    }
+    // Parts of RECT outside the image show the background
+    Some((lum + (area - covered).max(0.0) * base) / area.max(covered))
+}
+
+/// FG moved a fraction T of the way towards TARGET
+fn mix(fg: &Color, target: f32, t: f32) -> Color {
+    let m = |c: f32| c + (target - c) * t;
+    Color::new(m(fg.r), m(fg.g), m(fg.b), fg.a)
+}
+
+/// How to make text of color FG readable over a background of
+/// luminance BG with at least MIN_RATIO contrast, in STYLE.  A scrim is
+/// at most MAX_SCRIM opaque.  None if FG already has enough contrast.
+pub fn fix_contrast(
+    fg: &Color,
+    bg: f32,
+    style: ContrastStyle,
+    min_ratio: f32,
+    max_scrim: f32,
+) -> Option<ContrastFix> {
+    let fg_lum = luminance(fg);
+    if contrast_ratio(fg_lum, bg) >= min_ratio {
+        return None;
+    }
+    match style {
+        ContrastStyle::Scrim => {
+            // Move the background away from the text, towards whichever
+            // of black and white can contrast with it most
+            let darken = (fg_lum + 0.05) / 0.05 >= 1.05 / (fg_lum + 0.05);
+            let alpha = if darken {
+                let target = (fg_lum + 0.05) / min_ratio - 0.05;
+                if bg > 0.0 { 1.0 - target.max(0.0) / bg } else { 0.0 }
+            } else {
+                let target = min_ratio * (fg_lum + 0.05) - 0.05;
+                if bg < 1.0 { (target.min(1.0) - bg) / (1.0 - bg) } else { 0.0 }
+            };
+            let alpha = alpha.clamp(0.0, max_scrim);
+            if alpha <= 0.0 {
+                return None;
+            }
+            let shade = if darken { 0.0 } else { 1.0 };
+            Some(ContrastFix::Scrim(Color::new(shade, shade, shade, alpha)))
+        }
+        ContrastStyle::Recolor => {
+            // Move the text away from the background, towards whichever
+            // of black and white can contrast with it most
+            let target = if (bg + 0.05) / 0.05 >= 1.05 / (bg + 0.05) { 0.0 } else { 1.0 };
+            let color = (1..=10)
+                .map(|step| mix(fg, target, step as f32 / 10.0))
+                .find(|c| contrast_ratio(luminance(c), bg) >= min_ratio)
+                .unwrap_or_else(|| mix(fg, target, 1.0));
+            Some(ContrastFix::Foreground(color))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Half white, half black image of WIDTH x HEIGHT (left half white)
+    fn split_image(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        for _ in 0..height {
+            for x in 0..width {
+                let v = if x < width / 2 { 255 } else { 0 };
+                data.extend_from_slice(&[v, v, v, 255]);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn grid_summarizes_image_regions() {
+        let grid = LuminanceGrid::from_rgba(&split_image(1000, 10), 1000, 10).unwrap();
+        assert_eq!((grid.cols, grid.rows), (GRID_SIZE, 10));
+        let (light, alpha) = grid.sample([0.0, 0.0, 0.4, 1.0]);
+        assert!((light - 1.0).abs() < 1e-3 && (alpha - 1.0).abs() < 1e-3);
+        assert!(grid.sample([0.6, 0.0, 1.0, 1.0]).0 < 1e-3);
+        let (mixed, _) = grid.sample([0.0, 0.0, 1.0, 1.0]);
+        assert!((mixed - 0.5).abs() < 0.05);
+        // Too little data for the size
+        assert!(LuminanceGrid::from_rgba(&[0; 4], 2, 2).is_none());
+    }
+
+    #[test]
+    fn luminance_blends_image_over_background() {
+        let grid = LuminanceGrid::from_rgba(&split_image(64, 64), 64, 64).unwrap();
+        let quads = [BackgroundQuad { rect: Rect::new(0.0, 0.0, 100.0, 100.0), uv: [0.0, 0.0, 1.0, 1.0] }];
+        let over_white = |rect: Rect, opacity| luminance_under(&rect, &quads, &grid, opacity, 0.0);
+        assert!((over_white(Rect::new(0.0, 0.0, 20.0, 10.0), 1.0).unwrap() - 1.0).abs() < 1e-3);
+        assert!((over_white(Rect::new(0.0, 0.0, 20.0, 10.0), 0.3).unwrap() - 0.3).abs() < 1e-3);
+        assert!(over_white(Rect::new(80.0, 0.0, 20.0, 10.0), 1.0).unwrap() < 1e-3);
+        // Half of the rect is outside the image, over the background
+        let half = luminance_under(&Rect::new(-20.0, 0.0, 40.0, 10.0), &quads, &grid, 1.0, 0.0).unwrap();
+        assert!((half - 0.5).abs() < 1e-3);
+        assert!(over_white(Rect::new(200.0, 0.0, 20.0, 10.0), 1.0).is_none());
+    }
+
+    #[test]
+    fn fixes_reach_the_ratio() {
+        let white = Color::WHITE;
+        // White text over a light image: a dark scrim just strong enough
+        let Some(ContrastFix::Scrim(scrim)) = fix_contrast(&white, 0.8, ContrastStyle::Scrim, 4.5, 1.0) else {
+            panic!("expected a scrim");
+        };
+        assert_eq!((scrim.r, scrim.g, scrim.b), (0.0, 0.0, 0.0));
+        let under = 0.8 * (1.0 - scrim.a);
+        assert!((contrast_ratio(1.0, under) - 4.5).abs() < 1e-3);
+        // Capped opacity
+        let Some(ContrastFix::Scrim(capped)) = fix_contrast(&white, 0.8, ContrastStyle::Scrim, 4.5, 0.5) else {
+            panic!("expected a scrim");
+        };
+        assert_eq!(capped.a, 0.5);
+        // Dark text over a dark image gets a light scrim
+        let dark = Color::rgb(0.02, 0.02, 0.02);
+        assert!(matches!(
+            fix_contrast(&dark, 0.03, ContrastStyle::Scrim, 4.5, 1.0),
+            Some(ContrastFix::Scrim(c)) if c.r == 1.0
+        ));
+        // Enough contrast already
+        assert_eq!(fix_contrast(&white, 0.0, ContrastStyle::Scrim, 4.5, 1.0), None);
+
+        // Recoloring keeps moving towards black until the ratio holds
+        let Some(ContrastFix::Foreground(fg)) = fix_contrast(&Color::rgb(0.6, 0.5, 0.5), 0.9, ContrastStyle::Recolor, 4.5, 1.0) else {
+            panic!("expected a color");
+        };
+        assert!(contrast_ratio(luminance(&fg), 0.9) >= 4.5);
+        assert!(fg.r > fg.g, "hue kept");
+    }
+}
@@ -0,0 +1,365 @@
+//! Animation timeline: keyframed animations of window properties, ticked
+//! centrally by the render thread.
+//!
+//! Lisp creates animations with `neomacs-animate`, each driving one
+//! property (offset, opacity, scale) of a window through keyframes.
+//! Animations of the same property compose: offsets add up, opacity and
+//! scale multiply.  Other animations (buffer transitions, the cursor)
+//! implement `Animated` so they are advanced the same way.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::core::buffer_transition::{BufferTransition, TransitionEasing};
+use crate::core::cursor_animation::CursorAnimator;
+
+/// An animation advanced once per frame by the render thread
+pub trait Animated {
+    /// Advance to NOW.  Returns true while the animation is running.
+    fn advance(&mut self, now: Instant) -> bool;
+}
+
+impl Animated for BufferTransition {
+    fn advance(&mut self, now: Instant) -> bool {
+        self.update_at(now)
+    }
+}
+
+impl Animated for CursorAnimator {
+    fn advance(&mut self, now: Instant) -> bool {
+        self.update(now)
+    }
+}
+
+/// Window property driven by a timeline animation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnimProperty {
+    /// Horizontal offset in pixels
+    OffsetX,
+    /// Vertical offset in pixels
+    OffsetY,
+    /// Opacity (1.0 = opaque)
+    Opacity,
+    /// Scale about the window center (1.0 = natural size)
+    Scale,
+}
+
+impl AnimProperty {
+    /// Property from its FFI code: 0 = offset-x, 1 = offset-y,
+    /// 2 = opacity, 3 = scale
+    pub fn from_ffi(code: i32) -> Option<Self> {
+        match code {
+            0 => Some(Self::OffsetX),
+            1 => Some(Self::OffsetY),
+            2 => Some(Self::Opacity),
+            3 => Some(Self::Scale),
+            _ => None,
+        }
+    }
+
+    /// Value of the property at rest
+    pub fn rest_value(&self) -> f32 {
+        match self {
+            Self::OffsetX | Self::OffsetY => 0.0,
+            Self::Opacity | Self::Scale => 1.0,
+        }
+    }
+}
+
+/// A value reached at a point of an animation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    /// Time from the start of the animation
+    pub time: Duration,
+    pub value: f32,
+    /// Easing of the segment leading to this keyframe
+    pub easing: TransitionEasing,
+}
+
+/// Keyframed animation of one property of a window
+#[derive(Debug, Clone)]
+pub struct KeyframeAnimation {
+    pub window_id: i64,
+    pub property: AnimProperty,
+    /// Keyframes in time order
+    pub keyframes: Vec<Keyframe>,
+    /// Wait before the first keyframe
+    pub delay: Duration,
+    /// Number of times to play (0 = repeat until cancelled)
+    pub iterations: u32,
+    pub start_time: Instant,
+    value: f32,
+    completed: bool,
+}
+
+impl KeyframeAnimation {
+    /// Animation of PROPERTY of window WINDOW_ID through KEYFRAMES,
+    /// starting at START_TIME
+    pub fn new(window_id: i64, property: AnimProperty, mut keyframes: Vec<Keyframe>, start_time: Instant) -> Self {
+        keyframes.sort_by_key(|k| k.time);
+        let value = keyframes.first().map_or(property.rest_value(), |k| k.value);
+        Self {
+            window_id,
+            property,
+            keyframes,
+            delay: Duration::ZERO,
+            iterations: 1,
+            start_time,
+            value,
+            completed: false,
+        }
+    }
+
+    /// Length of one play
+    pub fn duration(&self) -> Duration {
+        self.keyframes.last().map_or(Duration::ZERO, |k| k.time)
+    }
+
+    /// Current value of the property
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.completed
+    }
+
+    /// Fraction of the current play done at NOW, from 0 to 1
+    pub fn progress_at(&self, now: Instant) -> f32 {
+        let duration = self.duration().as_secs_f64();
+        if self.completed || duration <= 0.0 {
+            return 1.0;
+        }
+        let elapsed = now.saturating_duration_since(self.start_time).saturating_sub(self.delay);
+        (elapsed.as_secs_f64() / duration).fract() as f32
+    }
+
+    /// Value at TIME into a play
+    fn sample(&self, time: Duration) -> f32 {
+        let Some(first) = self.keyframes.first() else {
+            return self.property.rest_value();
+        };
+        if time <= first.time {
+            return first.value;
+        }
+        for pair in self.keyframes.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            if time <= b.time {
+                let span = (b.time - a.time).as_secs_f32();
+                if span <= 0.0 {
+                    return b.value;
+                }
+                let t = (time - a.time).as_secs_f32() / span;
+                return a.value + (b.value - a.value) * b.easing.apply(t);
+            }
+        }
+        self.keyframes.last().map_or(first.value, |k| k.value)
+    }
+}
+
+impl Animated for KeyframeAnimation {
+    fn advance(&mut self, now: Instant) -> bool {
+        if self.completed {
+            return false;
+        }
+        let elapsed = now.saturating_duration_since(self.start_time).saturating_sub(self.delay);
+        let duration = self.duration();
+        if duration.is_zero() {
+            self.value = self.sample(Duration::ZERO);
+            self.completed = true;
+            return false;
+        }
+        let play = (elapsed.as_secs_f64() / duration.as_secs_f64()) as u64;
+        if self.iterations != 0 && play >= self.iterations as u64 {
+            self.value = self.sample(duration);
+            self.completed = true;
+            return false;
+        }
+        let into_play = elapsed.as_secs_f64() - play as f64 * duration.as_secs_f64();
+        self.value = self.sample(Duration::from_secs_f64(into_play.max(0.0)));
+        true
+    }
+}
+
+/// Combined effect of the animations of a window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowTransform {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub opacity: f32,
+    pub scale: f32,
+}
+
+impl Default for WindowTransform {
+    fn default() -> Self {
+        Self { offset_x: 0.0, offset_y: 0.0, opacity: 1.0, scale: 1.0 }
+    }
+}
+
+impl WindowTransform {
+    /// Whether this leaves the window unchanged
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn compose(&mut self, property: AnimProperty, value: f32) {
+        match property {
+            AnimProperty::OffsetX => self.offset_x += value,
+            AnimProperty::OffsetY => self.offset_y += value,
+            AnimProperty::Opacity => self.opacity *= value.clamp(0.0, 1.0),
+            AnimProperty::Scale => self.scale *= value.max(0.0),
+        }
+    }
+}
+
+/// The set of running timeline animations, by id
+#[derive(Debug, Default)]
+pub struct Timeline {
+    animations: HashMap<u32, KeyframeAnimation>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add ANIMATION as ID, replacing any animation with that id
+    pub fn add(&mut self, id: u32, animation: KeyframeAnimation) {
+        self.animations.insert(id, animation);
+    }
+
+    /// Cancel animation ID (0 cancels all).  The property returns to rest.
+    pub fn cancel(&mut self, id: u32) {
+        if id == 0 {
+            self.animations.clear();
+        } else {
+            self.animations.remove(&id);
+        }
+    }
+
+    /// Cancel the animations of window WINDOW_ID
+    pub fn cancel_window(&mut self, window_id: i64) {
+        self.animations.retain(|_, a| a.window_id != window_id);
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.animations.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.animations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.animations.is_empty()
+    }
+
+    /// The running animations, in no particular order
+    pub fn animations(&self) -> impl Iterator<Item = &KeyframeAnimation> {
+        self.animations.values()
+    }
+
+    /// Advance all animations to NOW, dropping finished ones.  Returns
+    /// true while any is running.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        self.animations.retain(|_, a| a.advance(now));
+        self.is_active()
+    }
+
+    /// Combined transform of window WINDOW_ID
+    pub fn window_transform(&self, window_id: i64) -> WindowTransform {
+        let mut transform = WindowTransform::default();
+        for animation in self.animations.values().filter(|a| a.window_id == window_id) {
+            transform.compose(animation.property, animation.value());
+        }
+        transform
+    }
+
+    /// Windows with running animations
+    pub fn windows(&self) -> Vec<i64> {
+        let mut ids: Vec<i64> = self.animations.values().map(|a| a.window_id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    fn key(time: u64, value: f32) -> Keyframe {
+        Keyframe { time: ms(time), value, easing: TransitionEasing::Linear }
+    }
+
+    #[test]
+    fn property_codes() {
+        assert_eq!(AnimProperty::from_ffi(0), Some(AnimProperty::OffsetX));
+        assert_eq!(AnimProperty::from_ffi(3), Some(AnimProperty::Scale));
+        assert_eq!(AnimProperty::from_ffi(4), None);
+        assert_eq!(TransitionEasing::from_ffi(3, [0.0; 4]), Some(TransitionEasing::EaseInOut));
+        assert_eq!(TransitionEasing::from_ffi(9, [0.0; 4]), None);
+    }
+
+    #[test]
+    fn keyframes_interpolate() {
+        let start = Instant::now();
+        let mut anim = KeyframeAnimation::new(1, AnimProperty::OffsetX, vec![key(100, 10.0), key(0, 0.0)], start);
+        assert!(anim.advance(start + ms(50)));
+        assert!((anim.value() - 5.0).abs() < 1e-4);
+        assert!(!anim.advance(start + ms(150)));
+        assert!(anim.is_complete());
+        assert_eq!(anim.value(), 10.0);
+    }
+
+    #[test]
+    fn delay_holds_first_keyframe() {
+        let start = Instant::now();
+        let mut anim = KeyframeAnimation::new(1, AnimProperty::Opacity, vec![key(0, 0.0), key(100, 1.0)], start);
+        anim.delay = ms(100);
+        assert!(anim.advance(start + ms(50)));
+        assert_eq!(anim.value(), 0.0);
+        anim.advance(start + ms(150));
+        assert!((anim.value() - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn repeats_until_iterations_played() {
+        let start = Instant::now();
+        let mut anim = KeyframeAnimation::new(1, AnimProperty::Scale, vec![key(0, 1.0), key(100, 2.0)], start);
+        anim.iterations = 2;
+        assert!(anim.advance(start + ms(125)));
+        assert!((anim.value() - 1.25).abs() < 1e-3);
+        assert!((anim.progress_at(start + ms(125)) - 0.25).abs() < 1e-3);
+        assert!(!anim.advance(start + ms(200)));
+
+        anim = KeyframeAnimation::new(1, AnimProperty::Scale, vec![key(0, 1.0), key(100, 2.0)], start);
+        anim.iterations = 0;
+        assert!(anim.advance(start + ms(10_050)));
+    }
+
+    #[test]
+    fn timeline_composes_and_drops_finished() {
+        let start = Instant::now();
+        let mut timeline = Timeline::new();
+        timeline.add(1, KeyframeAnimation::new(7, AnimProperty::OffsetX, vec![key(0, 10.0), key(100, 10.0)], start));
+        timeline.add(2, KeyframeAnimation::new(7, AnimProperty::OffsetX, vec![key(0, 5.0), key(200, 5.0)], start));
+        timeline.add(3, KeyframeAnimation::new(7, AnimProperty::Opacity, vec![key(0, 0.5), key(200, 0.5)], start));
+        timeline.add(4, KeyframeAnimation::new(7, AnimProperty::Opacity, vec![key(0, 0.5), key(200, 0.5)], start));
+        assert!(timeline.tick(start + ms(50)));
+        let t = timeline.window_transform(7);
+        assert_eq!(t.offset_x, 15.0);
+        assert_eq!(t.opacity, 0.25);
+        assert!(timeline.window_transform(8).is_identity());
+        assert_eq!(timeline.windows(), vec![7]);
+
+        timeline.tick(start + ms(150));
+        assert_eq!(timeline.len(), 3);
+        timeline.cancel(0);
+        assert!(!timeline.is_active());
+    }
+}
@@ -64,6 +64,27 @@ impl Color {
         }
     }
 
+    /// Convert a single linear component (0.0-1.0) back to sRGB.
+    fn linear_component_to_srgb(c: f32) -> f32 {
+        if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    }
+
+    /// Convert this color from linear space back to sRGB, the inverse of
+    /// `srgb_to_linear`.  Use when colors leave the GPU pipeline, e.g.
+    /// for printing.
+    pub fn linear_to_srgb(self) -> Self {
+        Self {
+            r: Self::linear_component_to_srgb(self.r),
+            g: Self::linear_component_to_srgb(self.g),
+            b: Self::linear_component_to_srgb(self.b),
+            a: self.a,
+        }
+    }
+
     // Common colors
     pub const BLACK: Self = Self::rgb(0.0, 0.0, 0.0);
     pub const WHITE: Self = Self::rgb(1.0, 1.0, 1.0);
@@ -0,0 +1,296 @@
+//! Background images and watermarks for individual windows.
+//!
+//! A background is an already-loaded image (see `ImageCache`) drawn under
+//! the text of one window with its own opacity, scaling mode and anchor.
+//! Backgrounds are attached either to a window or to a buffer, so e.g. a
+//! dashboard keeps its logo in whichever window displays it; a window
+//! background takes precedence over a buffer one.
+
+use std::collections::HashMap;
+
+use super::types::Rect;
+
+/// How the image is sized within the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundScaling {
+    /// Natural size.
+    None,
+    /// Stretch to the window, ignoring the aspect ratio.
+    Stretch,
+    /// Largest size that fits entirely inside the window.
+    Fit,
+    /// Smallest size that covers the whole window (cropped).
+    Fill,
+    /// Natural size, repeated to cover the window.
+    Tile,
+}
+
+impl BackgroundScaling {
+    /// Decode the FFI representation: 1 = stretch, 2 = fit, 3 = fill,
+    /// 4 = tile, anything else = natural size.
+    pub fn from_ffi(scaling: i32) -> Self {
+        match scaling {
+            1 => Self::Stretch,
+            2 => Self::Fit,
+            3 => Self::Fill,
+            4 => Self::Tile,
+            _ => Self::None,
+        }
+    }
+}
+
+/// Where the image is placed within the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundAnchor {
+    Center,
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl BackgroundAnchor {
+    /// Decode the FFI representation: 1..8 = top-left, top, top-right,
+    /// left, right, bottom-left, bottom, bottom-right; anything else =
+    /// center.
+    pub fn from_ffi(anchor: i32) -> Self {
+        match anchor {
+            1 => Self::TopLeft,
+            2 => Self::Top,
+            3 => Self::TopRight,
+            4 => Self::Left,
+            5 => Self::Right,
+            6 => Self::BottomLeft,
+            7 => Self::Bottom,
+            8 => Self::BottomRight,
+            _ => Self::Center,
+        }
+    }
+
+    /// Horizontal and vertical alignment factors (0 = start, 1 = end).
+    fn factors(self) -> (f32, f32) {
+        match self {
+            Self::Center => (0.5, 0.5),
+            Self::TopLeft => (0.0, 0.0),
+            Self::Top => (0.5, 0.0),
+            Self::TopRight => (1.0, 0.0),
+            Self::Left => (0.0, 0.5),
+            Self::Right => (1.0, 0.5),
+            Self::BottomLeft => (0.0, 1.0),
+            Self::Bottom => (0.5, 1.0),
+            Self::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+/// A background image of a window or buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowBackground {
+    /// Image id in the renderer's image cache.
+    pub image_id: u32,
+    /// Opacity, 0.0 (invisible) to 1.0.
+    pub opacity: f32,
+    pub scaling: BackgroundScaling,
+    pub anchor: BackgroundAnchor,
+}
+
+/// What a background is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackgroundTarget {
+    /// A window (same id as `WindowInfo::window_id`).
+    Window(i64),
+    /// A buffer (same id as `WindowInfo::buffer_id`).
+    Buffer(u64),
+}
+
+/// Backgrounds of all windows and buffers.
+#[derive(Debug, Default, Clone)]
+pub struct WindowBackgrounds {
+    windows: HashMap<i64, WindowBackground>,
+    buffers: HashMap<u64, WindowBackground>,
+}
+
+impl WindowBackgrounds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty() && self.buffers.is_empty()
+    }
+
+    /// Attach BACKGROUND to TARGET, or remove TARGET's background if None.
+    pub fn set(&mut self, target: BackgroundTarget, background: Option<WindowBackground>) {
+        match (target, background) {
+            (BackgroundTarget::Window(id), Some(bg)) => {
+                self.windows.insert(id, bg);
+            }
+            (BackgroundTarget::Window(id), None) => {
+                self.windows.remove(&id);
+            }
+            (BackgroundTarget::Buffer(id), Some(bg)) => {
+                self.buffers.insert(id, bg);
+            }
+            (BackgroundTarget::Buffer(id), None) => {
+                self.buffers.remove(&id);
+            }
+        }
+    }
+
+    /// Background of window WINDOW_ID showing BUFFER_ID.
+    pub fn lookup(&self, window_id: i64, buffer_id: u64) -> Option<&WindowBackground> {
+        self.windows.get(&window_id).or_else(|| self.buffers.get(&buffer_id))
+    }
+}
+
+/// A textured quad: screen rectangle and texture coordinates
+/// `[u0, v0, u1, v1]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackgroundQuad {
+    pub rect: Rect,
+    pub uv: [f32; 4],
+}
+
+/// Quads drawing an IMAGE_W x IMAGE_H image with SCALING and ANCHOR in
+/// AREA.  Everything is clipped to AREA, with texture coordinates cropped
+/// to match, so the quads can be drawn with a clamping sampler.
+pub fn background_quads(
+    scaling: BackgroundScaling,
+    anchor: BackgroundAnchor,
+    image_w: f32,
+    image_h: f32,
+    area: Rect,
+) -> Vec<BackgroundQuad> {
+    if image_w <= 0.0 || image_h <= 0.0 || area.width <= 0.0 || area.height <= 0.0 {
+        return Vec::new();
+    }
+    let (w, h) = match scaling {
+        BackgroundScaling::None | BackgroundScaling::Tile => (image_w, image_h),
+        BackgroundScaling::Stretch => (area.width, area.height),
+        BackgroundScaling::Fit => {
+            let s = (area.width / image_w).min(area.height / image_h);
+            (image_w * s, image_h * s)
+        }
+        BackgroundScaling::Fill => {
+            let s = (area.width / image_w).max(area.height / image_h);
+            (image_w * s, image_h * s)
+        }
+    };
+    let (fx, fy) = anchor.factors();
+    let x = area.x + (area.width - w) * fx;
+    let y = area.y + (area.height - h) * fy;
+
+    let mut quads = Vec::new();
+    if scaling == BackgroundScaling::Tile {
+        // Repeat in both directions from the anchored tile
+        let x0 = x - ((x - area.x) / w).ceil() * w;
+        let y0 = y - ((y - area.y) / h).ceil() * h;
+        let mut ty = y0;
+        while ty < area.y + area.height {
+            let mut tx = x0;
+            while tx < area.x + area.width {
+                quads.extend(clip_quad(Rect::new(tx, ty, w, h), area));
+                tx += w;
+            }
+            ty += h;
+        }
+    } else {
+        quads.extend(clip_quad(Rect::new(x, y, w, h), area));
+    }
+    quads
+}
+
+/// RECT (showing the whole texture) clipped to AREA.
+fn clip_quad(rect: Rect, area: Rect) -> Option<BackgroundQuad> {
+    let left = rect.x.max(area.x);
+    let top = rect.y.max(area.y);
+    let right = (rect.x + rect.width).min(area.x + area.width);
+    let bottom = (rect.y + rect.height).min(area.y + area.height);
+    if right <= left || bottom <= top {
+        return None;
+    }
+    Some(BackgroundQuad {
+        rect: Rect::new(left, top, right - left, bottom - top),
+        uv: [
+            (left - rect.x) / rect.width,
+            (top - rect.y) / rect.height,
+            (right - rect.x) / rect.width,
+            (bottom - rect.y) / rect.height,
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rect = Rect::new(100.0, 50.0, 400.0, 200.0);
+
+    #[test]
+    fn natural_size_is_anchored() {
+        let q = background_quads(BackgroundScaling::None, BackgroundAnchor::BottomRight, 40.0, 20.0, AREA);
+        assert_eq!(q.len(), 1);
+        assert_eq!(q[0].rect, Rect::new(460.0, 230.0, 40.0, 20.0));
+        assert_eq!(q[0].uv, [0.0, 0.0, 1.0, 1.0]);
+
+        let q = background_quads(BackgroundScaling::None, BackgroundAnchor::Center, 40.0, 20.0, AREA);
+        assert_eq!(q[0].rect, Rect::new(280.0, 140.0, 40.0, 20.0));
+    }
+
+    #[test]
+    fn fit_keeps_aspect_inside_area() {
+        let q = background_quads(BackgroundScaling::Fit, BackgroundAnchor::Center, 100.0, 100.0, AREA);
+        assert_eq!(q[0].rect, Rect::new(200.0, 50.0, 200.0, 200.0));
+        assert_eq!(q[0].uv, [0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn fill_crops_overflow() {
+        let q = background_quads(BackgroundScaling::Fill, BackgroundAnchor::Center, 100.0, 100.0, AREA);
+        // Scaled to 400x400, centered: a quarter cropped at top and bottom
+        assert_eq!(q[0].rect, AREA);
+        assert_eq!(q[0].uv, [0.0, 0.25, 1.0, 0.75]);
+    }
+
+    #[test]
+    fn stretch_covers_area() {
+        let q = background_quads(BackgroundScaling::Stretch, BackgroundAnchor::TopLeft, 10.0, 30.0, AREA);
+        assert_eq!(q[0].rect, AREA);
+        assert_eq!(q[0].uv, [0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn tiles_cover_area_and_clip_edges() {
+        let q = background_quads(BackgroundScaling::Tile, BackgroundAnchor::TopLeft, 150.0, 150.0, AREA);
+        // 3 columns (150, 150, 100) x 2 rows (150, 50)
+        assert_eq!(q.len(), 6);
+        let covered: f32 = q.iter().map(|q| q.rect.width * q.rect.height).sum();
+        assert_eq!(covered, AREA.width * AREA.height);
+        let last = q.last().unwrap();
+        assert_eq!(last.rect, Rect::new(400.0, 200.0, 100.0, 50.0));
+        assert_eq!(last.uv, [0.0, 0.0, 100.0 / 150.0, 50.0 / 150.0]);
+    }
+
+    #[test]
+    fn window_background_overrides_buffer() {
+        let bg = |id| WindowBackground {
+            image_id: id,
+            opacity: 0.5,
+            scaling: BackgroundScaling::Fit,
+            anchor: BackgroundAnchor::Center,
+        };
+        let mut bgs = WindowBackgrounds::new();
+        bgs.set(BackgroundTarget::Buffer(7), Some(bg(1)));
+        assert_eq!(bgs.lookup(1, 7).map(|b| b.image_id), Some(1));
+        bgs.set(BackgroundTarget::Window(1), Some(bg(2)));
+        assert_eq!(bgs.lookup(1, 7).map(|b| b.image_id), Some(2));
+        assert_eq!(bgs.lookup(2, 7).map(|b| b.image_id), Some(1));
+        bgs.set(BackgroundTarget::Window(1), None);
+        bgs.set(BackgroundTarget::Buffer(7), None);
+        assert!(bgs.is_empty());
+    }
+}
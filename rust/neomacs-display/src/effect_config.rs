@@ -62,6 +62,18 @@ effect_config!(
     }
 );
 
+effect_config!(
+    /// Configuration for text contrast over window background images
+    /// (see `crate::core::text_contrast`).  Style 0 draws a scrim behind
+    /// the text, 1 recolors the text.
+    BackgroundContrastConfig {
+        enabled: bool = false,
+        style: u32 = 0,
+        min_ratio: f32 = 4.5,
+        max_scrim: f32 = 0.6,
+    }
+);
+
 effect_config!(
     /// Configuration for the basket weave effect.
     BasketWeaveConfig {
@@ -221,6 +233,20 @@ effect_config!(
     }
 );
 
+effect_config!(
+    /// Configuration for the CRT/retro post-processing effect.
+    /// Intensities range from 0.0 (off) to 1.0; `terminals_only`
+    /// restricts the effect to windows showing a terminal.
+    CrtConfig {
+        enabled: bool = false,
+        scanlines: f32 = 0.5,
+        curvature: f32 = 0.3,
+        glow: f32 = 0.3,
+        aberration: f32 = 0.2,
+        terminals_only: bool = false,
+    }
+);
+
 effect_config!(
     /// Configuration for the cursor aurora borealis effect.
     CursorAuroraBorealisConfig {
@@ -232,6 +258,21 @@ effect_config!(
     }
 );
 
+effect_config!(
+    /// Configuration for the cursor beacon.  `enabled` turns on the
+    /// automatic triggers; an explicit beacon request always pulses.
+    /// style: 0 = collapsing ring, 1 = line flash, 2 = both.
+    CursorBeaconConfig {
+        enabled: bool = false,
+        color: (f32, f32, f32) = (1.0, 0.5, 0.24),
+        style: u32 = 0,
+        duration_ms: u32 = 500,
+        opacity: f32 = 0.6,
+        on_window_switch: bool = true,
+        on_scroll: bool = true,
+    }
+);
+
 effect_config!(
     /// Configuration for the cursor bubble effect.
     CursorBubbleConfig {
@@ -1045,6 +1086,20 @@ effect_config!(
     }
 );
 
+effect_config!(
+    /// Configuration for the magnifier lens overlay.
+    MagnifierConfig {
+        enabled: bool = false,
+        circular: bool = true,
+        width: f32 = 240.0,
+        height: f32 = 160.0,
+        zoom: f32 = 2.0,
+        follow_mouse: bool = true,
+        border_color: (f32, f32, f32) = (0.4, 0.6, 1.0),
+        border_width: f32 = 2.0,
+    }
+);
+
 effect_config!(
     /// Configuration for the matrix rain effect.
     MatrixRainConfig {
@@ -1324,6 +1379,15 @@ effect_config!(
     }
 );
 
+effect_config!(
+    /// Configuration for the smooth text update animation (lines of a
+    /// rewritten buffer slide to their new positions).
+    SmoothTextUpdateConfig {
+        enabled: bool = false,
+        duration_ms: u32 = 250,
+    }
+);
+
 effect_config!(
     /// Configuration for the spiral vortex effect.
     SpiralVortexConfig {
@@ -1385,6 +1449,14 @@ effect_config!(
     }
 );
 
+effect_config!(
+    /// Configuration for the text scale zoom animation.
+    TextZoomConfig {
+        enabled: bool = true,
+        duration_ms: u32 = 150,
+    }
+);
+
 effect_config!(
     /// Configuration for the theme transition effect.
     ThemeTransitionConfig {
@@ -1423,6 +1495,16 @@ effect_config!(
     }
 );
 
+effect_config!(
+    /// Configuration for the typeahead echo: typed characters drawn at
+    /// the cursor before the core catches up.
+    TypeaheadEchoConfig {
+        enabled: bool = false,
+        opacity: f32 = 0.6,
+        timeout_ms: u32 = 3000,
+    }
+);
+
 effect_config!(
     /// Configuration for the typing heatmap effect.
     TypingHeatmapConfig {
@@ -1613,6 +1695,17 @@ mod tests {
         assert_clone_debug(&c);
     }
 
+    // ── BackgroundContrastConfig ──────────────────────────────────────
+    #[test]
+    fn background_contrast_defaults() {
+        let c = BackgroundContrastConfig::default();
+        assert_eq!(c.enabled, false);
+        assert_eq!(c.style, 0);
+        assert_eq!(c.min_ratio, 4.5);
+        assert_eq!(c.max_scrim, 0.6);
+        assert_clone_debug(&c);
+    }
+
     // ── BasketWeaveConfig ─────────────────────────────────────────────
     #[test]
     fn basket_weave_defaults() {
@@ -1785,6 +1878,19 @@ mod tests {
         assert_clone_debug(&c);
     }
 
+    // ── CrtConfig ─────────────────────────────────────────────────────
+    #[test]
+    fn crt_defaults() {
+        let c = CrtConfig::default();
+        assert_eq!(c.enabled, false);
+        assert_eq!(c.scanlines, 0.5);
+        assert_eq!(c.curvature, 0.3);
+        assert_eq!(c.glow, 0.3);
+        assert_eq!(c.aberration, 0.2);
+        assert_eq!(c.terminals_only, false);
+        assert_clone_debug(&c);
+    }
+
     // ── CursorAuroraBorealisConfig ────────────────────────────────────
     #[test]
     fn cursor_aurora_borealis_defaults() {
@@ -1797,6 +1903,20 @@ mod tests {
         assert_clone_debug(&c);
     }
 
+    // ── CursorBeaconConfig ────────────────────────────────────────────
+    #[test]
+    fn cursor_beacon_defaults() {
+        let c = CursorBeaconConfig::default();
+        assert_eq!(c.enabled, false);
+        assert_eq!(c.color, (1.0, 0.5, 0.24));
+        assert_eq!(c.style, 0);
+        assert_eq!(c.duration_ms, 500);
+        assert_eq!(c.opacity, 0.6);
+        assert!(c.on_window_switch);
+        assert!(c.on_scroll);
+        assert_clone_debug(&c);
+    }
+
     // ── CursorBubbleConfig ────────────────────────────────────────────
     #[test]
     fn cursor_bubble_defaults() {
@@ -2687,6 +2807,21 @@ mod tests {
         assert_clone_debug(&c);
     }
 
+    // ── MagnifierConfig ───────────────────────────────────────────────
+    #[test]
+    fn magnifier_defaults() {
+        let c = MagnifierConfig::default();
+        assert_eq!(c.enabled, false);
+        assert_eq!(c.circular, true);
+        assert_eq!(c.width, 240.0);
+        assert_eq!(c.height, 160.0);
+        assert_eq!(c.zoom, 2.0);
+        assert_eq!(c.follow_mouse, true);
+        assert_eq!(c.border_color, (0.4, 0.6, 1.0));
+        assert_eq!(c.border_width, 2.0);
+        assert_clone_debug(&c);
+    }
+
     // ── MatrixRainConfig ──────────────────────────────────────────────
     #[test]
     fn matrix_rain_defaults() {
@@ -2994,6 +3129,15 @@ mod tests {
         assert_clone_debug(&c);
     }
 
+    // ── SmoothTextUpdateConfig ────────────────────────────────────────
+    #[test]
+    fn smooth_text_update_defaults() {
+        let c = SmoothTextUpdateConfig::default();
+        assert_eq!(c.enabled, false);
+        assert_eq!(c.duration_ms, 250);
+        assert_clone_debug(&c);
+    }
+
     // ── SpiralVortexConfig ────────────────────────────────────────────
     #[test]
     fn spiral_vortex_defaults() {
@@ -3061,6 +3205,15 @@ mod tests {
         assert_clone_debug(&c);
     }
 
+    // ── TextZoomConfig ────────────────────────────────────────────────
+    #[test]
+    fn text_zoom_defaults() {
+        let c = TextZoomConfig::default();
+        assert_eq!(c.enabled, true);
+        assert_eq!(c.duration_ms, 150);
+        assert_clone_debug(&c);
+    }
+
     // ── ThemeTransitionConfig ─────────────────────────────────────────
     #[test]
     fn theme_transition_defaults() {
@@ -3114,6 +3267,16 @@ mod tests {
         assert_clone_debug(&c);
     }
 
+    // ── TypeaheadEchoConfig ───────────────────────────────────────────
+    #[test]
+    fn typeahead_echo_defaults() {
+        let c = TypeaheadEchoConfig::default();
+        assert_eq!(c.enabled, false);
+        assert_eq!(c.opacity, 0.6);
+        assert_eq!(c.timeout_ms, 3000);
+        assert_clone_debug(&c);
+    }
+
     // ── TypingRippleConfig ────────────────────────────────────────────
     #[test]
     fn typing_ripple_defaults() {
@@ -3406,6 +3569,7 @@ mod tests {
         assert_eq!(ec.title_fade.duration_ms, 300);
         assert_eq!(ec.topo_contour.spacing, 30.0);
         assert_eq!(ec.trefoil_knot.size, 80.0);
+        assert_eq!(ec.typeahead_echo.timeout_ms, 3000);
         assert_eq!(ec.typing_heatmap.fade_ms, 2000);
         assert_eq!(ec.typing_ripple.max_radius, 40.0);
         assert_eq!(ec.typing_speed.enabled, false);
@@ -3614,6 +3778,7 @@ mod tests {
             ec.tessellation.opacity,
             ec.topo_contour.opacity,
             ec.trefoil_knot.opacity,
+            ec.typeahead_echo.opacity,
             ec.typing_heatmap.opacity,
             ec.vignette.intensity,
             ec.warp_grid.opacity,
@@ -3774,6 +3939,7 @@ mod tests {
             ec.title_fade.enabled,
             ec.topo_contour.enabled,
             ec.trefoil_knot.enabled,
+            ec.typeahead_echo.enabled,
             ec.typing_heatmap.enabled,
             ec.typing_ripple.enabled,
             ec.typing_speed.enabled,
@@ -3867,6 +4033,7 @@ pub struct EffectsConfig {
     pub accent_strip: AccentStripConfig,
     pub argyle_pattern: ArgylePatternConfig,
     pub aurora: AuroraConfig,
+    pub background_contrast: BackgroundContrastConfig,
     pub basket_weave: BasketWeaveConfig,
     pub bg_gradient: BgGradientConfig,
     pub bg_pattern: BgPatternConfig,
@@ -3882,7 +4049,9 @@ pub struct EffectsConfig {
     pub constellation: ConstellationConfig,
     pub corner_fold: CornerFoldConfig,
     pub crosshatch_pattern: CrosshatchPatternConfig,
+    pub crt: CrtConfig,
     pub cursor_aurora_borealis: CursorAuroraBorealisConfig,
+    pub cursor_beacon: CursorBeaconConfig,
     pub cursor_bubble: CursorBubbleConfig,
     pub cursor_candle_flame: CursorCandleFlameConfig,
     pub cursor_color_cycle: CursorColorCycleConfig,
@@ -3960,6 +4129,7 @@ pub struct EffectsConfig {
     pub line_animation: LineAnimationConfig,
     pub line_highlight: LineHighlightConfig,
     pub line_number_pulse: LineNumberPulseConfig,
+    pub magnifier: MagnifierConfig,
     pub matrix_rain: MatrixRainConfig,
     pub minibuffer_highlight: MinibufferHighlightConfig,
     pub minimap: MinimapConfig,
@@ -3988,16 +4158,19 @@ pub struct EffectsConfig {
     pub search_pulse: SearchPulseConfig,
     pub show_whitespace: ShowWhitespaceConfig,
     pub sine_wave: SineWaveConfig,
+    pub smooth_text_update: SmoothTextUpdateConfig,
     pub spiral_vortex: SpiralVortexConfig,
     pub stained_glass: StainedGlassConfig,
     pub sunburst_pattern: SunburstPatternConfig,
     pub target_reticle: TargetReticleConfig,
     pub tessellation: TessellationConfig,
     pub text_fade_in: TextFadeInConfig,
+    pub text_zoom: TextZoomConfig,
     pub theme_transition: ThemeTransitionConfig,
     pub title_fade: TitleFadeConfig,
     pub topo_contour: TopoContourConfig,
     pub trefoil_knot: TrefoilKnotConfig,
+    pub typeahead_echo: TypeaheadEchoConfig,
     pub typing_heatmap: TypingHeatmapConfig,
     pub typing_ripple: TypingRippleConfig,
     pub typing_speed: TypingSpeedConfig,
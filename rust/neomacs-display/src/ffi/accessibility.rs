@@ -0,0 +1,231 @@
+//! Accessibility FFI functions
+//!
+//! The tree is rebuilt by neomacs_accessibility_update() after each frame
+//! layout while accessibility is enabled; events are drained one at a time
+//! with neomacs_accessibility_next_event().  Each changed tree is also
+//! sent to the render thread, which exports it to screen readers when
+//! built with the `accessibility` feature.
+
+use super::*;
+
+use crate::layout::accessibility::{
+    self, AccessibilityBridge, AccessibilityEvent, AccessibilityState, AccessibleNode,
+    AccessibleRole, Bounds, Popup,
+};
+use crate::layout::emacs_ffi::EmacsFrame;
+
+/// Accessibility state; `None` while accessibility is disabled.
+static ACCESSIBILITY: Mutex<Option<AccessibilityState>> = Mutex::new(None);
+
+pub const NEOMACS_A11Y_FOCUS: c_int = 1;
+pub const NEOMACS_A11Y_CARET: c_int = 2;
+pub const NEOMACS_A11Y_INSERT: c_int = 3;
+pub const NEOMACS_A11Y_DELETE: c_int = 4;
+pub const NEOMACS_A11Y_NAME: c_int = 5;
+pub const NEOMACS_A11Y_ADD: c_int = 6;
+pub const NEOMACS_A11Y_REMOVE: c_int = 7;
+
+/// An accessibility event as seen from C.
+#[repr(C)]
+pub struct NeomacsAccessibilityEvent {
+    /// One of NEOMACS_A11Y_*
+    pub kind: c_int,
+    /// Node identifier (window pointer for window nodes)
+    pub node: u64,
+    /// Node role name for NEOMACS_A11Y_ADD, else NULL.  Static string.
+    pub role: *const c_char,
+    /// Buffer position for caret and text events, else 0
+    pub position: i64,
+    /// Inserted/deleted text or new name, else NULL.  Free with
+    /// neomacs_accessibility_free_string().
+    pub text: *mut c_char,
+}
+
+fn role_cstr(role: AccessibleRole) -> *const c_char {
+    let name: &'static [u8] = match role {
+        AccessibleRole::Frame => b"frame\0",
+        AccessibleRole::Text => b"text\0",
+        AccessibleRole::Entry => b"entry\0",
+        AccessibleRole::StatusBar => b"status bar\0",
+        AccessibleRole::PopupMenu => b"popup menu\0",
+        AccessibleRole::MenuItem => b"menu item\0",
+        AccessibleRole::ToolTip => b"tool tip\0",
+    };
+    name.as_ptr() as *const c_char
+}
+
+fn owned_cstr(text: &str) -> *mut c_char {
+    CString::new(text.replace('\0', " ")).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Send ROOT to the render thread's screen reader adapter.
+fn send_tree(root: Option<Box<AccessibleNode>>) {
+    // SAFETY: THREADED_STATE is only written during init and shutdown.
+    if let Some(state) = unsafe { (*std::ptr::addr_of!(THREADED_STATE)).as_ref() } {
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::UpdateAccessibility { root });
+    }
+}
+
+/// Bridge forwarding the tree to the render thread whenever it changes.
+#[derive(Default)]
+struct RenderThreadBridge {
+    last: Option<AccessibleNode>,
+}
+
+impl AccessibilityBridge for RenderThreadBridge {
+    fn publish(&mut self, tree: &AccessibleNode, _events: &[AccessibilityEvent]) {
+        if self.last.as_ref() != Some(tree) {
+            self.last = Some(tree.clone());
+            send_tree(Some(Box::new(tree.clone())));
+        }
+    }
+}
+
+/// Record the popup shown on top of the frame (or its removal).
+pub(crate) fn set_popup(popup: Option<Popup>) {
+    if let Ok(mut guard) = ACCESSIBILITY.lock() {
+        if let Some(state) = guard.as_mut() {
+            state.set_popup(popup);
+        }
+    }
+}
+
+/// Enable or disable accessibility support.  Disabling drops the tree and
+/// any undrained events.
+#[no_mangle]
+pub extern "C" fn neomacs_accessibility_set_enabled(enabled: c_int) {
+    if let Ok(mut guard) = ACCESSIBILITY.lock() {
+        match (enabled != 0, guard.is_some()) {
+            (true, false) => {
+                let mut state = AccessibilityState::new();
+                state.add_bridge(Box::new(RenderThreadBridge::default()));
+                *guard = Some(state);
+            }
+            (false, true) => {
+                *guard = None;
+                send_tree(None);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Rebuild the accessibility tree of FRAME, titled TITLE, after layout.
+/// Does nothing while accessibility is disabled.
+///
+/// # Safety
+/// Must be called on the Emacs thread with a valid frame pointer.
+/// TITLE must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_accessibility_update(frame: EmacsFrame, title: *const c_char) {
+    if frame.is_null() {
+        return;
+    }
+    let Ok(mut guard) = ACCESSIBILITY.lock() else {
+        return;
+    };
+    let Some(state) = guard.as_mut() else {
+        return;
+    };
+    let title = if title.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(title).to_string_lossy().into_owned()
+    };
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| accessibility::collect_frame(frame, &title)));
+    match result {
+        Ok(tree) => state.update(tree),
+        Err(_) => error!("neomacs_accessibility_update: panic while collecting tree"),
+    }
+}
+
+/// Pop the oldest pending event into OUT.  Returns 1 if an event was
+/// stored, 0 if there are none.
+///
+/// # Safety
+/// OUT must point to a writable NeomacsAccessibilityEvent.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_accessibility_next_event(out: *mut NeomacsAccessibilityEvent) -> c_int {
+    if out.is_null() {
+        return 0;
+    }
+    let event = match ACCESSIBILITY.lock() {
+        Ok(mut guard) => match guard.as_mut() {
+            Some(state) => state.queue.pop(),
+            None => None,
+        },
+        Err(_) => None,
+    };
+    let Some(event) = event else {
+        return 0;
+    };
+    let mut c_event = NeomacsAccessibilityEvent {
+        kind: 0,
+        node: event.id(),
+        role: ptr::null(),
+        position: 0,
+        text: ptr::null_mut(),
+    };
+    match &event {
+        AccessibilityEvent::FocusChanged { .. } => c_event.kind = NEOMACS_A11Y_FOCUS,
+        AccessibilityEvent::CaretMoved { position, .. } => {
+            c_event.kind = NEOMACS_A11Y_CARET;
+            c_event.position = *position;
+        }
+        AccessibilityEvent::TextInserted { position, text, .. } => {
+            c_event.kind = NEOMACS_A11Y_INSERT;
+            c_event.position = *position;
+            c_event.text = owned_cstr(text);
+        }
+        AccessibilityEvent::TextDeleted { position, text, .. } => {
+            c_event.kind = NEOMACS_A11Y_DELETE;
+            c_event.position = *position;
+            c_event.text = owned_cstr(text);
+        }
+        AccessibilityEvent::NameChanged { name, .. } => {
+            c_event.kind = NEOMACS_A11Y_NAME;
+            c_event.text = owned_cstr(name);
+        }
+        AccessibilityEvent::NodeAdded { role, name, .. } => {
+            c_event.kind = NEOMACS_A11Y_ADD;
+            c_event.role = role_cstr(*role);
+            c_event.text = owned_cstr(name);
+        }
+        AccessibilityEvent::NodeRemoved { .. } => c_event.kind = NEOMACS_A11Y_REMOVE,
+    }
+    *out = c_event;
+    1
+}
+
+/// Free a string returned in a NeomacsAccessibilityEvent.
+///
+/// # Safety
+/// TEXT must be NULL or the text of an event from
+/// neomacs_accessibility_next_event(), not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_accessibility_free_string(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+/// Record a popup menu at (X, Y) for the accessibility tree.
+pub(crate) fn popup_menu_shown(x: f32, y: f32, title: Option<&str>, items: &[PopupMenuItem]) {
+    set_popup(Some(Popup::Menu {
+        title: title.map(str::to_string),
+        items: items
+            .iter()
+            .filter(|item| !item.separator)
+            .map(|item| (item.label.clone(), item.enabled))
+            .collect(),
+        bounds: Bounds { x, y, width: 0.0, height: 0.0 },
+    }));
+}
+
+/// Record a tooltip at (X, Y) for the accessibility tree.
+pub(crate) fn tooltip_shown(x: f32, y: f32, text: &str) {
+    set_popup(Some(Popup::ToolTip {
+        text: text.to_string(),
+        bounds: Bounds { x, y, width: 0.0, height: 0.0 },
+    }));
+}
@@ -1,6 +1,7 @@
 //! Animation FFI functions
 //!
-//! Smooth scroll, cursor blink, mouse cursor, popup menus, tooltips,
+//! Smooth scroll, cursor blink, mouse cursor, popup menus, character
+//! picker, tooltips,
 //! visual effects, effect_setter! macro and all effect configuration functions,
 //! animation config, and animation stubs.
 
@@ -70,6 +71,47 @@ pub unsafe extern "C" fn neomacs_display_warp_mouse(
     }
 }
 
+/// Hide the mouse pointer on keyboard input (until the mouse moves) if
+/// ENABLED is nonzero.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_pointer_auto_hide(
+    _handle: *mut NeomacsDisplay,
+    enabled: c_int,
+) {
+    let cmd = RenderCommand::SetPointerAutoHide {
+        enabled: enabled != 0,
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Set the mouse wheel and touchpad scroll settings: lines per wheel
+/// notch, touchpad speed factor, reversed directions, Shift for
+/// horizontal scrolling and Alt for page scrolling (nonzero = on).
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_wheel_config(
+    _handle: *mut NeomacsDisplay,
+    wheel_speed: f32,
+    wheel_reverse: c_int,
+    touchpad_speed: f32,
+    touchpad_reverse: c_int,
+    shift_horizontal: c_int,
+    alt_page: c_int,
+) {
+    let cmd = RenderCommand::SetWheelConfig {
+        wheel_speed: wheel_speed.max(0.0),
+        wheel_reverse: wheel_reverse != 0,
+        touchpad_speed: touchpad_speed.max(0.0),
+        touchpad_reverse: touchpad_reverse != 0,
+        shift_horizontal: shift_horizontal != 0,
+        alt_page: alt_page != 0,
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
 /// Popup menu item passed from C.
 #[repr(C)]
 pub struct CPopupMenuItem {
@@ -151,6 +193,8 @@ pub unsafe extern "C" fn neomacs_display_show_popup_menu(
         None
     };
 
+    super::accessibility::popup_menu_shown(x as f32, y as f32, title_str.as_deref(), &menu_items);
+
     let cmd = RenderCommand::ShowPopupMenu {
         x: x as f32,
         y: y as f32,
@@ -169,12 +213,609 @@ pub unsafe extern "C" fn neomacs_display_show_popup_menu(
 pub unsafe extern "C" fn neomacs_display_hide_popup_menu(
     _handle: *mut NeomacsDisplay,
 ) {
+    super::accessibility::set_popup(None);
     let cmd = RenderCommand::HidePopupMenu;
     if let Some(ref state) = THREADED_STATE {
         let _ = state.emacs_comms.cmd_tx.try_send(cmd);
     }
 }
 
+/// Character picker entry passed from C.
+#[repr(C)]
+pub struct CCharPickerEntry {
+    /// The character(s) to insert (UTF-8)
+    pub text: *const c_char,
+    /// Unicode name
+    pub name: *const c_char,
+    /// Space-separated shortcodes, or NULL
+    pub shortcodes: *const c_char,
+}
+
+/// Show the character picker with the given entries.
+/// The render thread will display the picker and send a
+/// CharPickerSelection event with the picked entry's index.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_show_char_picker(
+    _handle: *mut NeomacsDisplay,
+    entries: *const CCharPickerEntry,
+    entry_count: c_int,
+    title: *const c_char,
+    fg_color: u32,
+    bg_color: u32,
+) {
+    let c_string = |p: *const c_char| {
+        if p.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(p).to_string_lossy().into_owned()
+        }
+    };
+    let mut picker_entries = Vec::with_capacity(entry_count.max(0) as usize);
+    for i in 0..entry_count.max(0) as usize {
+        let entry = &*entries.add(i);
+        picker_entries.push(CharPickerEntry {
+            text: c_string(entry.text),
+            name: c_string(entry.name),
+            shortcodes: c_string(entry.shortcodes)
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+        });
+    }
+
+    let title_str = if title.is_null() { None } else { Some(c_string(title)) };
+    let to_rgb = |c: u32| {
+        (c != 0).then(|| {
+            (
+                ((c >> 16) & 0xFF) as f32 / 255.0,
+                ((c >> 8) & 0xFF) as f32 / 255.0,
+                (c & 0xFF) as f32 / 255.0,
+            )
+        })
+    };
+
+    let cmd = RenderCommand::ShowCharPicker {
+        entries: picker_entries,
+        title: title_str,
+        fg: to_rgb(fg_color),
+        bg: to_rgb(bg_color),
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Hide the character picker.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_hide_char_picker(
+    _handle: *mut NeomacsDisplay,
+) {
+    let cmd = RenderCommand::HideCharPicker;
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Command palette entry passed from C.
+#[repr(C)]
+pub struct CCommandPaletteEntry {
+    /// Command name (UTF-8)
+    pub name: *const c_char,
+    /// Key binding description, or NULL
+    pub binding: *const c_char,
+    /// Documentation, or NULL
+    pub doc: *const c_char,
+    /// Ranking bonus from recent and frequent use
+    pub weight: c_int,
+}
+
+/// Show the command palette with the given entries.
+/// The render thread will display the palette and send a
+/// CommandPaletteSelection event with the chosen entry's index.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_show_command_palette(
+    _handle: *mut NeomacsDisplay,
+    entries: *const CCommandPaletteEntry,
+    entry_count: c_int,
+    title: *const c_char,
+    fg_color: u32,
+    bg_color: u32,
+    match_color: u32,
+) {
+    let c_string = |p: *const c_char| {
+        if p.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(p).to_string_lossy().into_owned()
+        }
+    };
+    let mut palette_entries = Vec::with_capacity(entry_count.max(0) as usize);
+    for i in 0..entry_count.max(0) as usize {
+        let entry = &*entries.add(i);
+        palette_entries.push(CommandPaletteEntry {
+            name: c_string(entry.name),
+            binding: c_string(entry.binding),
+            doc: c_string(entry.doc),
+            weight: entry.weight,
+        });
+    }
+
+    let title_str = if title.is_null() { None } else { Some(c_string(title)) };
+    let to_rgb = |c: u32| {
+        (c != 0).then(|| {
+            (
+                ((c >> 16) & 0xFF) as f32 / 255.0,
+                ((c >> 8) & 0xFF) as f32 / 255.0,
+                (c & 0xFF) as f32 / 255.0,
+            )
+        })
+    };
+
+    let cmd = RenderCommand::ShowCommandPalette {
+        entries: palette_entries,
+        title: title_str,
+        fg: to_rgb(fg_color),
+        bg: to_rgb(bg_color),
+        match_fg: to_rgb(match_color),
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Hide the command palette.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_hide_command_palette(
+    _handle: *mut NeomacsDisplay,
+) {
+    let cmd = RenderCommand::HideCommandPalette;
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Show the color picker with COLOR selected and ORIGINAL (both
+/// 0xRRGGBB) as the color to go back to.  The render thread will display
+/// the picker and send a ColorPickerSelection event with the chosen
+/// color.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_show_color_picker(
+    _handle: *mut NeomacsDisplay,
+    color: u32,
+    original: u32,
+    title: *const c_char,
+    fg_color: u32,
+    bg_color: u32,
+) {
+    let title_str = if title.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(title).to_string_lossy().into_owned())
+    };
+    let to_rgb = |c: u32| {
+        (c != 0).then(|| {
+            (
+                ((c >> 16) & 0xFF) as f32 / 255.0,
+                ((c >> 8) & 0xFF) as f32 / 255.0,
+                (c & 0xFF) as f32 / 255.0,
+            )
+        })
+    };
+
+    let cmd = RenderCommand::ShowColorPicker {
+        color: color & 0xFFFFFF,
+        original: original & 0xFFFFFF,
+        title: title_str,
+        fg: to_rgb(fg_color),
+        bg: to_rgb(bg_color),
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Hide the color picker.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_hide_color_picker(
+    _handle: *mut NeomacsDisplay,
+) {
+    let cmd = RenderCommand::HideColorPicker;
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Agenda timeline item passed from C.
+#[repr(C)]
+pub struct CAgendaTimelineItem {
+    /// Title (UTF-8)
+    pub label: *const c_char,
+    /// Category, or NULL
+    pub category: *const c_char,
+    /// Start and end in seconds since the epoch; end == start for a
+    /// milestone
+    pub start: f64,
+    pub end: f64,
+    /// Bar color 0xRRGGBB, 0 = default
+    pub color: u32,
+    /// Nonzero if the task is done
+    pub done: c_int,
+}
+
+/// Show the agenda timeline with the given items.  NOW is the current
+/// time in seconds since the epoch and UTC_OFFSET the local time zone's
+/// offset from UTC in seconds.  The render thread will display the
+/// timeline and send an AgendaTimelineSelection event with the chosen
+/// item's index, or -1 when it is closed.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_show_agenda_timeline(
+    _handle: *mut NeomacsDisplay,
+    items: *const CAgendaTimelineItem,
+    item_count: c_int,
+    now: f64,
+    utc_offset: c_int,
+    title: *const c_char,
+    fg_color: u32,
+    bg_color: u32,
+) {
+    let c_string = |p: *const c_char| {
+        if p.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(p).to_string_lossy().into_owned()
+        }
+    };
+    let to_rgb = |c: u32| {
+        (c != 0).then(|| {
+            (
+                ((c >> 16) & 0xFF) as f32 / 255.0,
+                ((c >> 8) & 0xFF) as f32 / 255.0,
+                (c & 0xFF) as f32 / 255.0,
+            )
+        })
+    };
+    let mut timeline_items = Vec::with_capacity(item_count.max(0) as usize);
+    for i in 0..item_count.max(0) as usize {
+        let item = &*items.add(i);
+        timeline_items.push(AgendaTimelineItem {
+            label: c_string(item.label),
+            category: c_string(item.category),
+            start: item.start,
+            end: item.end.max(item.start),
+            color: to_rgb(item.color),
+            done: item.done != 0,
+        });
+    }
+
+    let title_str = if title.is_null() { None } else { Some(c_string(title)) };
+    let cmd = RenderCommand::ShowAgendaTimeline {
+        items: timeline_items,
+        title: title_str,
+        now,
+        utc_offset,
+        fg: to_rgb(fg_color),
+        bg: to_rgb(bg_color),
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Hide the agenda timeline.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_hide_agenda_timeline(
+    _handle: *mut NeomacsDisplay,
+) {
+    let cmd = RenderCommand::HideAgendaTimeline;
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Graph view node passed from C.
+#[repr(C)]
+pub struct CGraphNode {
+    /// Label (UTF-8)
+    pub label: *const c_char,
+    /// Fill color 0xRRGGBB, 0 = default
+    pub color: u32,
+}
+
+/// Graph view edge passed from C, as node indices.
+#[repr(C)]
+pub struct CGraphEdge {
+    pub from: c_int,
+    pub to: c_int,
+}
+
+/// Show the graph view with the given nodes and edges.  LAYERED nonzero
+/// places the nodes in layers along the edges instead of by forces, and
+/// CURRENT is the node selected at first (-1 = the first one).  The
+/// render thread will display the graph and send a GraphViewSelection
+/// event with the chosen node's index, or -1 when it is closed.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_show_graph_view(
+    _handle: *mut NeomacsDisplay,
+    nodes: *const CGraphNode,
+    node_count: c_int,
+    edges: *const CGraphEdge,
+    edge_count: c_int,
+    layered: c_int,
+    current: c_int,
+    title: *const c_char,
+    fg_color: u32,
+    bg_color: u32,
+) {
+    let to_rgb = |c: u32| {
+        (c != 0).then(|| {
+            (
+                ((c >> 16) & 0xFF) as f32 / 255.0,
+                ((c >> 8) & 0xFF) as f32 / 255.0,
+                (c & 0xFF) as f32 / 255.0,
+            )
+        })
+    };
+    let node_count = node_count.max(0) as usize;
+    let mut graph_nodes = Vec::with_capacity(node_count);
+    for i in 0..node_count {
+        let node = &*nodes.add(i);
+        let label = if node.label.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(node.label).to_string_lossy().into_owned()
+        };
+        graph_nodes.push(GraphNode { label, color: to_rgb(node.color) });
+    }
+    let mut graph_edges = Vec::with_capacity(edge_count.max(0) as usize);
+    for i in 0..edge_count.max(0) as usize {
+        let edge = &*edges.add(i);
+        if edge.from >= 0 && edge.to >= 0 {
+            graph_edges.push((edge.from as usize, edge.to as usize));
+        }
+    }
+
+    let title_str = if title.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(title).to_string_lossy().into_owned())
+    };
+    let cmd = RenderCommand::ShowGraphView {
+        nodes: graph_nodes.into_boxed_slice(),
+        edges: graph_edges.into_boxed_slice(),
+        layered: layered != 0,
+        current: usize::try_from(current).ok(),
+        title: title_str,
+        fg: to_rgb(fg_color),
+        bg: to_rgb(bg_color),
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Hide the graph view.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_hide_graph_view(
+    _handle: *mut NeomacsDisplay,
+) {
+    let cmd = RenderCommand::HideGraphView;
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Directory view icon of files whose names end in a suffix, passed
+/// from C.
+#[repr(C)]
+pub struct CDirViewIcon {
+    /// Name suffix, such as ".rs" (UTF-8)
+    pub suffix: *const c_char,
+    /// Name of a registered icon (UTF-8)
+    pub name: *const c_char,
+}
+
+/// Show the directory view listing directory DIR, with hidden files if
+/// SHOW_HIDDEN is nonzero.  ICONS name the icons of files by suffix and
+/// UTC_OFFSET is the local time zone's offset from UTC in seconds.  The
+/// render thread will display the listing and send a DirViewSelection
+/// event when a file is chosen (x = 1; see
+/// `neomacs_display_take_dir_view_choice`) or the view is closed
+/// (x = -1).
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_show_dir_view(
+    _handle: *mut NeomacsDisplay,
+    dir: *const c_char,
+    show_hidden: c_int,
+    icons: *const CDirViewIcon,
+    icon_count: c_int,
+    utc_offset: c_int,
+    fg_color: u32,
+    bg_color: u32,
+) {
+    if dir.is_null() {
+        return;
+    }
+    let c_string = |p: *const c_char| {
+        if p.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(p).to_string_lossy().into_owned()
+        }
+    };
+    let to_rgb = |c: u32| {
+        (c != 0).then(|| {
+            (
+                ((c >> 16) & 0xFF) as f32 / 255.0,
+                ((c >> 8) & 0xFF) as f32 / 255.0,
+                (c & 0xFF) as f32 / 255.0,
+            )
+        })
+    };
+    let mut dir_icons = Vec::with_capacity(icon_count.max(0) as usize);
+    for i in 0..icon_count.max(0) as usize {
+        let icon = &*icons.add(i);
+        let suffix = c_string(icon.suffix);
+        if !suffix.is_empty() {
+            dir_icons.push((suffix, c_string(icon.name)));
+        }
+    }
+
+    let cmd = RenderCommand::ShowDirView {
+        dir: c_string(dir),
+        show_hidden: show_hidden != 0,
+        icons: dir_icons,
+        utc_offset,
+        fg: to_rgb(fg_color),
+        bg: to_rgb(bg_color),
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Hide the directory view.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_hide_dir_view(
+    _handle: *mut NeomacsDisplay,
+) {
+    let cmd = RenderCommand::HideDirView;
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Show the process monitor, sampling processes every INTERVAL
+/// seconds.  The render thread will display the processes and send a
+/// ProcessMonitorSelection event with the pid of the process chosen in
+/// x, or -1 when the monitor is closed without choosing one.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_show_process_monitor(
+    _handle: *mut NeomacsDisplay,
+    interval: f32,
+    fg_color: u32,
+    bg_color: u32,
+) {
+    let to_rgb = |c: u32| {
+        (c != 0).then(|| {
+            (
+                ((c >> 16) & 0xFF) as f32 / 255.0,
+                ((c >> 8) & 0xFF) as f32 / 255.0,
+                (c & 0xFF) as f32 / 255.0,
+            )
+        })
+    };
+    let cmd = RenderCommand::ShowProcessMonitor {
+        interval,
+        fg: to_rgb(fg_color),
+        bg: to_rgb(bg_color),
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Hide the process monitor.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_hide_process_monitor(
+    _handle: *mut NeomacsDisplay,
+) {
+    let cmd = RenderCommand::HideProcessMonitor;
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Date picker colors passed from C, each 0xRRGGBB, 0 = default.
+#[repr(C)]
+pub struct CDatePickerColors {
+    /// Panel text and background
+    pub fg: u32,
+    pub bg: u32,
+    /// Ring around today
+    pub today: u32,
+    /// Selected day text and fill
+    pub selected_fg: u32,
+    pub selected_bg: u32,
+    /// Highlighted day text
+    pub highlight: u32,
+}
+
+/// Show the date picker with SELECTED chosen, TODAY ringed and the
+/// HIGHLIGHT_COUNT dates at HIGHLIGHTED highlighted, all as YYYYMMDD.
+/// MONTHS months (1-3) are shown side by side, weeks starting on
+/// WEEK_START (0 = Sunday).  The render thread will display the picker
+/// and send a DatePickerSelection event with the chosen date as
+/// YYYYMMDD in x, or -1 when it is cancelled.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_show_date_picker(
+    _handle: *mut NeomacsDisplay,
+    selected: c_int,
+    today: c_int,
+    highlighted: *const c_int,
+    highlight_count: c_int,
+    months: c_int,
+    week_start: c_int,
+    title: *const c_char,
+    colors: *const CDatePickerColors,
+) {
+    use crate::render_thread::days_from_code;
+
+    let to_rgb = |c: u32| {
+        (c != 0).then(|| {
+            (
+                ((c >> 16) & 0xFF) as f32 / 255.0,
+                ((c >> 8) & 0xFF) as f32 / 255.0,
+                (c & 0xFF) as f32 / 255.0,
+            )
+        })
+    };
+    let highlighted = if highlighted.is_null() || highlight_count <= 0 {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(highlighted, highlight_count as usize)
+            .iter()
+            .map(|&code| days_from_code(code))
+            .collect()
+    };
+    let title_str = if title.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(title).to_string_lossy().into_owned())
+    };
+    let colors = colors.as_ref();
+
+    let cmd = RenderCommand::ShowDatePicker {
+        spec: Box::new(DatePickerSpec {
+            selected: days_from_code(selected),
+            today: days_from_code(today),
+            highlighted,
+            months: months.clamp(1, 3) as u32,
+            week_start: week_start.rem_euclid(7) as u32,
+            title: title_str,
+        }),
+        fg: colors.and_then(|c| to_rgb(c.fg)),
+        bg: colors.and_then(|c| to_rgb(c.bg)),
+        today: colors.and_then(|c| to_rgb(c.today)),
+        selected_fg: colors.and_then(|c| to_rgb(c.selected_fg)),
+        selected_bg: colors.and_then(|c| to_rgb(c.selected_bg)),
+        highlight: colors.and_then(|c| to_rgb(c.highlight)),
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Hide the date picker.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_hide_date_picker(
+    _handle: *mut NeomacsDisplay,
+) {
+    let cmd = RenderCommand::HideDatePicker;
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
 /// Show a tooltip at the given position with specified colors.
 #[no_mangle]
 pub unsafe extern "C" fn neomacs_display_show_tooltip(
@@ -193,6 +834,7 @@ pub unsafe extern "C" fn neomacs_display_show_tooltip(
             Err(_) => return,
         }
     };
+    super::accessibility::tooltip_shown(x, y, &text_str);
     let cmd = RenderCommand::ShowTooltip {
         x, y, text: text_str,
         fg_r, fg_g, fg_b,
@@ -208,6 +850,7 @@ pub unsafe extern "C" fn neomacs_display_show_tooltip(
 pub unsafe extern "C" fn neomacs_display_hide_tooltip(
     _handle: *mut NeomacsDisplay,
 ) {
+    super::accessibility::set_popup(None);
     let cmd = RenderCommand::HideTooltip;
     if let Some(ref state) = THREADED_STATE {
         let _ = state.emacs_comms.cmd_tx.try_send(cmd);
@@ -273,6 +916,32 @@ pub unsafe extern "C" fn neomacs_display_request_attention(
     }
 }
 
+/// Withdraw a pending attention request (stop flashing the taskbar entry).
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_cancel_attention(_handle: *mut NeomacsDisplay) {
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::CancelAttention);
+    }
+}
+
+/// Set the window icon from LEN bytes of encoded image DATA (PNG, ICO,
+/// JPEG, ...).  NULL data restores the Neomacs logo.  The data is copied.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_window_icon(
+    _handle: *mut NeomacsDisplay,
+    data: *const u8,
+    len: usize,
+) {
+    let data = if data.is_null() || len == 0 {
+        None
+    } else {
+        Some(std::slice::from_raw_parts(data, len).to_vec())
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::SetWindowIcon { data });
+    }
+}
+
 /// Enable or disable scroll indicators and focus ring.
 /// enabled: non-zero = on, zero = off.
 #[no_mangle]
@@ -366,6 +1035,63 @@ pub unsafe extern "C" fn neomacs_display_set_ligatures_enabled(
     }
 }
 
+/// Draw CODEPOINT as the icon NAME, taken from font FAMILY (NULL or
+/// empty for the face's font).  Y_OFFSET moves it up by that fraction of
+/// the font size and SCALE sizes it relative to the font.  If HAS_COLOR
+/// is nonzero, the icon is drawn in COLOR (0xRRGGBB) instead of the
+/// face's foreground.  Registering a name again replaces its icon.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_register_icon(
+    _handle: *mut NeomacsDisplay,
+    name: *const c_char,
+    codepoint: u32,
+    family: *const c_char,
+    y_offset: f32,
+    scale: f32,
+    color: u32,
+    has_color: c_int,
+) -> c_int {
+    if name.is_null() {
+        return 0;
+    }
+    let Some(codepoint) = char::from_u32(codepoint) else {
+        return 0;
+    };
+    let family = if family.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(family).to_string_lossy().into_owned()
+    };
+    let mut spec = crate::core::icons::IconSpec::new(
+        CStr::from_ptr(name).to_string_lossy().into_owned(),
+        codepoint,
+        family,
+    );
+    spec.y_offset = if y_offset.is_finite() { y_offset.clamp(-1.0, 1.0) } else { 0.0 };
+    spec.scale = if scale.is_finite() && scale > 0.0 { scale.min(4.0) } else { 1.0 };
+    spec.color = (has_color != 0).then(|| Color::from_pixel(color));
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::RegisterIcon { spec });
+    }
+    1
+}
+
+/// Forget the icon NAME, or all icons if NAME is NULL.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_remove_icon(
+    _handle: *mut NeomacsDisplay,
+    name: *const c_char,
+) {
+    let name = if name.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::RemoveIcon { name });
+    }
+}
+
 // ============================================================================
 // effect_setter! macro and invocations
 // ============================================================================
@@ -627,10 +1353,36 @@ effect_setter!(neomacs_display_set_frosted_glass(enabled: c_int, opacity: c_int,
                     effects.frosted_glass.blur = blur as f32;
 });
 
+effect_setter!(neomacs_display_set_magnifier(enabled: c_int, circular: c_int, width: c_int, height: c_int, zoom_pct: c_int, follow_mouse: c_int, r: c_int, g: c_int, b: c_int, border_width: c_int) |effects| {
+        effects.magnifier.enabled = enabled != 0;
+                    effects.magnifier.circular = circular != 0;
+                    effects.magnifier.width = width as f32;
+                    effects.magnifier.height = height as f32;
+                    effects.magnifier.zoom = zoom_pct as f32 / 100.0;
+                    effects.magnifier.follow_mouse = follow_mouse != 0;
+                    effects.magnifier.border_color = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+                    effects.magnifier.border_width = border_width as f32;
+});
+
+effect_setter!(neomacs_display_set_crt_effect(enabled: c_int, scanlines_pct: c_int, curvature_pct: c_int, glow_pct: c_int, aberration_pct: c_int, terminals_only: c_int) |effects| {
+        effects.crt.enabled = enabled != 0;
+                    effects.crt.scanlines = scanlines_pct as f32 / 100.0;
+                    effects.crt.curvature = curvature_pct as f32 / 100.0;
+                    effects.crt.glow = glow_pct as f32 / 100.0;
+                    effects.crt.aberration = aberration_pct as f32 / 100.0;
+                    effects.crt.terminals_only = terminals_only != 0;
+});
+
 effect_setter!(neomacs_display_set_typing_speed(enabled: c_int) |effects| {
         effects.typing_speed.enabled = enabled != 0;
 });
 
+effect_setter!(neomacs_display_set_typeahead_echo(enabled: c_int, opacity: c_int, timeout_ms: c_int) |effects| {
+        effects.typeahead_echo.enabled = enabled != 0;
+                    effects.typeahead_echo.opacity = opacity as f32 / 100.0;
+                    effects.typeahead_echo.timeout_ms = timeout_ms as u32;
+});
+
 effect_setter!(neomacs_display_set_title_fade(enabled: c_int, duration_ms: c_int) |effects| {
         effects.title_fade.enabled = enabled != 0;
                     effects.title_fade.duration_ms = duration_ms as u32;
@@ -669,6 +1421,13 @@ effect_setter!(neomacs_display_set_window_watermark(enabled: c_int, opacity: c_i
                     effects.window_watermark.threshold = threshold as u32;
 });
 
+effect_setter!(neomacs_display_set_background_contrast(enabled: c_int, style: c_int, min_ratio_tenths: c_int, max_scrim: c_int) |effects| {
+        effects.background_contrast.enabled = enabled != 0;
+                    effects.background_contrast.style = style.max(0) as u32;
+                    effects.background_contrast.min_ratio = (min_ratio_tenths as f32 / 10.0).clamp(1.0, 21.0);
+                    effects.background_contrast.max_scrim = (max_scrim as f32 / 100.0).clamp(0.0, 1.0);
+});
+
 
 /// Configure cursor trail fade effect
 #[no_mangle]
@@ -772,6 +1531,25 @@ effect_setter!(neomacs_display_set_cursor_magnetism(enabled: c_int, r: c_int, g:
                     effects.cursor_magnetism.opacity = opacity as f32 / 100.0;
 });
 
+/// Cursor beacon.  triggers: bit 0 = window/buffer switch, bit 1 = large scroll.
+effect_setter!(neomacs_display_set_cursor_beacon(enabled: c_int, r: c_int, g: c_int, b: c_int, style: c_int, duration_ms: c_int, opacity: c_int, triggers: c_int) |effects| {
+        effects.cursor_beacon.enabled = enabled != 0;
+                    effects.cursor_beacon.color = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+                    effects.cursor_beacon.style = style.clamp(0, 2) as u32;
+                    effects.cursor_beacon.duration_ms = duration_ms.max(1) as u32;
+                    effects.cursor_beacon.opacity = opacity as f32 / 100.0;
+                    effects.cursor_beacon.on_window_switch = triggers & 1 != 0;
+                    effects.cursor_beacon.on_scroll = triggers & 2 != 0;
+});
+
+/// Pulse the cursor beacon now.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_cursor_beacon(_handle: *mut NeomacsDisplay) {
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::CursorBeacon);
+    }
+}
+
 effect_setter!(neomacs_display_set_depth_shadow(enabled: c_int, layers: c_int, offset: c_int, r: c_int, g: c_int, b: c_int, opacity: c_int) |effects| {
         effects.depth_shadow.enabled = enabled != 0;
                     effects.depth_shadow.layers = layers as u32;
@@ -926,6 +1704,11 @@ effect_setter!(neomacs_display_set_text_fade_in(enabled: c_int, duration_ms: c_i
                     effects.text_fade_in.duration_ms = duration_ms as u32;
 });
 
+effect_setter!(neomacs_display_set_text_zoom_animation(enabled: c_int, duration_ms: c_int) |effects| {
+        effects.text_zoom.enabled = enabled != 0;
+                    effects.text_zoom.duration_ms = duration_ms as u32;
+});
+
 effect_setter!(neomacs_display_set_scroll_line_spacing(enabled: c_int, max_spacing: c_int, duration_ms: c_int) |effects| {
         effects.scroll_line_spacing.enabled = enabled != 0;
                     effects.scroll_line_spacing.max = max_spacing as f32;
@@ -969,12 +1752,13 @@ effect_setter!(neomacs_display_set_mode_line_separator(style: c_int, r: c_int, g
 });
 
 
-/// Set the window title (threaded mode)
+/// Set the window title (threaded mode).  Returns 1 if the title was
+/// queued, 0 if the command channel was full (retry later).
 #[no_mangle]
 pub unsafe extern "C" fn neomacs_display_set_title(
     _handle: *mut NeomacsDisplay,
     title: *const c_char,
-) {
+) -> c_int {
     let title_str = if title.is_null() {
         "Emacs".to_string()
     } else {
@@ -982,8 +1766,9 @@ pub unsafe extern "C" fn neomacs_display_set_title(
     };
     let cmd = RenderCommand::SetWindowTitle { title: title_str };
     if let Some(ref state) = THREADED_STATE {
-        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+        return state.emacs_comms.cmd_tx.try_send(cmd).is_ok() as c_int;
     }
+    0
 }
 
 /// Set fullscreen mode (threaded mode)
@@ -1183,40 +1968,349 @@ pub unsafe extern "C" fn neomacs_display_animation_active(
     0
 }
 
-/// Trigger a buffer transition animation (stub)
+effect_setter!(neomacs_display_set_smooth_text_update(enabled: c_int, duration_ms: c_int) |effects| {
+        effects.smooth_text_update.enabled = enabled != 0;
+                    effects.smooth_text_update.duration_ms = duration_ms.max(0) as u32;
+});
+
+/// Announce that the selected window's buffer is about to be rewritten,
+/// so the next frame that changes it animates line by line (when smooth
+/// text update is enabled).
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_start_smooth_text_update(_handle: *mut NeomacsDisplay) {
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::StartSmoothTextUpdate);
+    }
+}
+
+/// Install the WGSL post-processing shader in the file PATH, or remove
+/// the current one if PATH is NULL.  The file is validated by the render
+/// thread and reloaded when it changes.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_post_shader(
+    _handle: *mut NeomacsDisplay,
+    path: *const c_char,
+) {
+    let path = if path.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(path).to_string_lossy().into_owned())
+    };
+    let cmd = RenderCommand::SetPostShader { path };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Watch the TOML display settings file PATH, or stop watching if PATH
+/// is NULL.  The file is applied when it is created or changes; it need
+/// not exist yet.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_settings_file(
+    _handle: *mut NeomacsDisplay,
+    path: *const c_char,
+) {
+    let path = if path.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(path).to_string_lossy().into_owned())
+    };
+    let cmd = RenderCommand::SetSettingsFile { path };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Set the present mode and frame latency of all window surfaces.
+/// MODE: 0 = Fifo (vsync), 1 = Mailbox, 2 = Immediate (no vsync, may
+/// tear), 3 = FifoRelaxed.  Unsupported modes fall back to the closest
+/// supported one.  MAX_FRAME_LATENCY is clamped to 1..=3.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_present_mode(
+    _handle: *mut NeomacsDisplay,
+    mode: c_int,
+    max_frame_latency: c_int,
+) {
+    let cmd = RenderCommand::SetPresentMode {
+        mode: crate::render_thread::present::present_mode_from_ffi(mode),
+        max_frame_latency: max_frame_latency.max(1) as u32,
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Set how rendering is throttled while no Neomacs window has focus or
+/// the frame is occluded.  ENABLED 0 renders at full rate in the
+/// background.  UNFOCUSED_FPS and HIDDEN_FPS are the trickle rates
+/// (at least 1); PAUSE_VIDEO non-zero pauses playing videos until focus
+/// returns.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_background_throttle(
+    _handle: *mut NeomacsDisplay,
+    enabled: c_int,
+    unfocused_fps: c_int,
+    hidden_fps: c_int,
+    pause_video: c_int,
+) {
+    let cmd = RenderCommand::SetBackgroundThrottle {
+        policy: crate::render_thread::suspend::SuspendPolicy {
+            enabled: enabled != 0,
+            unfocused_fps: unfocused_fps.max(1) as u32,
+            hidden_fps: hidden_fps.max(1) as u32,
+            pause_video: pause_video != 0,
+        },
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Set the GPU adapter preference used when the display engine next
+/// creates its GPU device: 0 = default (`NEOMACS_GPU`), 1 = integrated
+/// (low power), 2 = discrete (high performance).  Needs no display
+/// handle, so it can be called before the display is opened.
+#[no_mangle]
+pub extern "C" fn neomacs_display_set_gpu_preference(preference: c_int) {
+    crate::set_gpu_power_preference(match preference {
+        1 => Some(wgpu::PowerPreference::LowPower),
+        2 => Some(wgpu::PowerPreference::HighPerformance),
+        _ => None,
+    });
+}
+
+/// Set the memory budgets, in megabytes, of the image cache, the glyph
+/// cache, transition snapshots and the pool of reusable upload buffers.
+/// Caches over budget evict their least recently used entries.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_memory_budget(
+    _handle: *mut NeomacsDisplay,
+    images_mb: c_int,
+    glyphs_mb: c_int,
+    snapshots_mb: c_int,
+    staging_mb: c_int,
+) {
+    let mb = |v: c_int| v.max(1) as u32;
+    let cmd = RenderCommand::SetMemoryBudget {
+        budget: crate::backend::wgpu::MemoryBudget::from_mb(
+            mb(images_mb), mb(glyphs_mb), mb(snapshots_mb), mb(staging_mb),
+        ),
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Animate the selected window from its current contents to whatever the
+/// next frame shows, using the named EFFECT (see
+/// `ScrollEffect::from_transition_name`).  Returns 1 if the request was
+/// queued (or the effect is "none"), 0 otherwise.
 #[no_mangle]
 pub unsafe extern "C" fn neomacs_display_start_buffer_transition(
     _handle: *mut NeomacsDisplay,
-    _effect: *const c_char,
-    _duration_ms: c_int,
+    effect: *const c_char,
+    duration_ms: c_int,
 ) -> c_int {
+    if effect.is_null() {
+        return 0;
+    }
+    let name = std::ffi::CStr::from_ptr(effect).to_string_lossy();
+    let effect = match crate::core::scroll_animation::ScrollEffect::from_transition_name(&name) {
+        Some(effect) => effect,
+        None => return 1,
+    };
+    let cmd = RenderCommand::StartWindowTransition {
+        effect,
+        duration_ms: duration_ms.max(0) as u32,
+    };
+    if let Some(ref state) = THREADED_STATE {
+        if state.emacs_comms.cmd_tx.try_send(cmd).is_ok() {
+            return 1;
+        }
+    }
     0
 }
 
-/// Prepare for buffer transition (stub)
+/// Set the EFFECT (see `BufferTransitionEffect::from_str`) and duration
+/// of buffer transitions.  They are started by
+/// `neomacs_display_trigger_buffer_transition`, and with AUTO_DETECT
+/// non-zero also whenever a window's buffer changes.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_buffer_transition(
+    _handle: *mut NeomacsDisplay,
+    effect: *const c_char,
+    duration_ms: c_int,
+    auto_detect: c_int,
+) {
+    if effect.is_null() {
+        return;
+    }
+    let name = std::ffi::CStr::from_ptr(effect).to_string_lossy();
+    let cmd = RenderCommand::SetBufferTransition {
+        effect: crate::core::buffer_transition::BufferTransitionEffect::from_str(&name),
+        duration_ms: duration_ms.max(0) as u32,
+        auto_detect: auto_detect != 0,
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Snapshot the selected window before switching its buffer.  Returns 1
+/// if the request was queued, 0 otherwise.
 #[no_mangle]
 pub unsafe extern "C" fn neomacs_display_prepare_buffer_transition(
     _handle: *mut NeomacsDisplay,
 ) -> c_int {
+    if let Some(ref state) = THREADED_STATE {
+        state.transition_snapshot.store(false, std::sync::atomic::Ordering::Relaxed);
+        if state.emacs_comms.cmd_tx.try_send(RenderCommand::PrepareBufferTransition).is_ok() {
+            return 1;
+        }
+    }
+    0
+}
+
+/// Snapshot all windows of the frame but the minibuffer before the
+/// window layout is replaced.  Returns 1 if the request was queued, 0
+/// otherwise.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_prepare_frame_transition(
+    _handle: *mut NeomacsDisplay,
+) -> c_int {
+    if let Some(ref state) = THREADED_STATE {
+        state.transition_snapshot.store(false, std::sync::atomic::Ordering::Relaxed);
+        if state.emacs_comms.cmd_tx.try_send(RenderCommand::PrepareFrameTransition).is_ok() {
+            return 1;
+        }
+    }
     0
 }
 
-/// Trigger buffer transition animation (stub)
+/// Animate the selected window, or all windows after
+/// `neomacs_display_prepare_frame_transition`, from the last snapshot
+/// to the new contents.
+/// Returns 1 if the request was queued, 0 otherwise.
 #[no_mangle]
 pub unsafe extern "C" fn neomacs_display_trigger_buffer_transition(
     _handle: *mut NeomacsDisplay,
 ) -> c_int {
+    if let Some(ref state) = THREADED_STATE {
+        if state.emacs_comms.cmd_tx.try_send(RenderCommand::TriggerBufferTransition).is_ok() {
+            return 1;
+        }
+    }
     0
 }
 
-/// Check if buffer transition is ready (stub)
+/// Check whether the render thread holds a snapshot taken by
+/// `neomacs_display_prepare_buffer_transition` that has not been used yet.
 #[no_mangle]
 pub unsafe extern "C" fn neomacs_display_has_transition_snapshot(
     _handle: *mut NeomacsDisplay,
 ) -> c_int {
+    match THREADED_STATE {
+        Some(ref state) => state.transition_snapshot.load(std::sync::atomic::Ordering::Relaxed) as c_int,
+        None => 0,
+    }
+}
+
+/// Ids handed out to timeline animations (0 is never used)
+static NEXT_ANIMATION_ID: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(1);
+
+/// Animate PROPERTY (see `AnimProperty::from_ffi`) of window WINDOW_ID
+/// through COUNT keyframes, given as parallel arrays of TIMES (ms from
+/// the start), VALUES and EASINGS (see `TransitionEasing::from_ffi`).
+/// PARAMS holds 4 easing parameters per keyframe, or is null.
+/// The animation waits DELAY_MS and plays ITERATIONS times (0 = until
+/// cancelled).  Returns the animation id, or 0 if it was not queued.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_animate(
+    _handle: *mut NeomacsDisplay,
+    window_id: i64,
+    property: c_int,
+    times: *const c_int,
+    values: *const f64,
+    easings: *const c_int,
+    params: *const f64,
+    count: c_int,
+    delay_ms: c_int,
+    iterations: c_int,
+) -> u32 {
+    use crate::core::buffer_transition::TransitionEasing;
+    use crate::core::timeline::{AnimProperty, Keyframe, KeyframeAnimation};
+    let Some(property) = AnimProperty::from_ffi(property) else {
+        return 0;
+    };
+    if times.is_null() || values.is_null() || easings.is_null() || count <= 0 {
+        return 0;
+    }
+    let count = count as usize;
+    let times = std::slice::from_raw_parts(times, count);
+    let values = std::slice::from_raw_parts(values, count);
+    let easings = std::slice::from_raw_parts(easings, count);
+    let params = (!params.is_null()).then(|| std::slice::from_raw_parts(params, count * 4));
+    let keyframes = (0..count)
+        .map(|i| {
+            let p = params.map_or([0.0; 4], |p| {
+                [p[i * 4] as f32, p[i * 4 + 1] as f32, p[i * 4 + 2] as f32, p[i * 4 + 3] as f32]
+            });
+            Keyframe {
+                time: std::time::Duration::from_millis(times[i].max(0) as u64),
+                value: values[i] as f32,
+                easing: TransitionEasing::from_ffi(easings[i], p).unwrap_or_default(),
+            }
+        })
+        .collect();
+    let mut animation = KeyframeAnimation::new(window_id, property, keyframes, std::time::Instant::now());
+    animation.delay = std::time::Duration::from_millis(delay_ms.max(0) as u64);
+    animation.iterations = iterations.max(0) as u32;
+    let id = NEXT_ANIMATION_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    if let Some(ref state) = THREADED_STATE {
+        let cmd = RenderCommand::AddTimelineAnimation { id, animation };
+        if state.emacs_comms.cmd_tx.try_send(cmd).is_ok() {
+            return id;
+        }
+    }
     0
 }
 
+/// Cancel timeline animation ID, or all of them if ID is 0.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_cancel_animation(
+    _handle: *mut NeomacsDisplay,
+    id: u32,
+) {
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::CancelTimelineAnimation { id });
+    }
+}
+
+/// Drive TARGET (see `EasingTarget::from_ffi`) with easing EASING and
+/// its parameters P0..P3 (see `TransitionEasing::from_ffi`).  An unknown
+/// EASING, such as -1, restores the target's default easing.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_animation_curve(
+    _handle: *mut NeomacsDisplay,
+    target: c_int,
+    easing: c_int,
+    p0: f64,
+    p1: f64,
+    p2: f64,
+    p3: f64,
+) {
+    let Some(target) = crate::core::easing::EasingTarget::from_ffi(target) else {
+        return;
+    };
+    let easing = crate::core::buffer_transition::TransitionEasing::from_ffi(
+        easing,
+        [p0 as f32, p1 as f32, p2 as f32, p3 as f32],
+    );
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::SetAnimationCurve { target, easing });
+    }
+}
+
 // ============================================================================
 // Additional effect_setter! invocations (from post-threaded section)
 // ============================================================================
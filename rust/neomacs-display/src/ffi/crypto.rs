@@ -0,0 +1,238 @@
+//! Encrypted file FFI functions
+//!
+//! Encryption does not need a display connection, so one
+//! `Crypto`, with its passphrase cache, serves all frames.  Jobs run in
+//! the background; C polls them with neomacs_crypto_job_result().
+
+use super::*;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+
+use crate::core::crypto::{self, Crypto, CryptoError, CryptoFormat, JobState};
+
+static CRYPTO: Lazy<Crypto> = Lazy::new(Crypto::new);
+
+/// The job finished; its result is in the data returned
+pub const NEOMACS_CRYPTO_DONE: c_int = 1;
+/// The job is still running
+pub const NEOMACS_CRYPTO_RUNNING: c_int = 0;
+/// The job failed; the message says why
+pub const NEOMACS_CRYPTO_FAILED: c_int = -1;
+/// A passphrase is needed: start the job again with one
+pub const NEOMACS_CRYPTO_NEED_PASSPHRASE: c_int = -2;
+/// The passphrase given was wrong
+pub const NEOMACS_CRYPTO_BAD_PASSPHRASE: c_int = -3;
+/// None of the secret keys can decrypt it
+pub const NEOMACS_CRYPTO_NO_KEY: c_int = -4;
+/// There is no such job, or its result was already taken
+pub const NEOMACS_CRYPTO_UNKNOWN: c_int = -5;
+
+/// Format codes of neomacs_crypto_encrypt()
+pub const NEOMACS_CRYPTO_AGE: c_int = 0;
+pub const NEOMACS_CRYPTO_OPENPGP: c_int = 1;
+
+unsafe fn opt_string(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok().map(str::to_string)
+}
+
+/// Non-empty lines of a newline-separated C string.
+unsafe fn lines(s: *const c_char) -> Vec<String> {
+    opt_string(s)
+        .map(|s| s.lines().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+unsafe fn bytes(data: *const u8, len: usize) -> Vec<u8> {
+    if data.is_null() || len == 0 {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(data, len).to_vec()
+    }
+}
+
+/// Whether this build can encrypt and decrypt files.
+#[no_mangle]
+pub extern "C" fn neomacs_crypto_available() -> c_int {
+    crypto::available() as c_int
+}
+
+/// Set the secret keys used to decrypt: AGE_IDENTITIES and
+/// OPENPGP_SECRET_KEYS are newline-separated file names (NULL for none),
+/// and passphrases are cached for CACHE_TIMEOUT seconds (0 disables the
+/// cache).
+///
+/// # Safety
+/// `age_identities` and `openpgp_secret_keys` must each be NULL or a
+/// valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_crypto_configure(
+    age_identities: *const c_char,
+    openpgp_secret_keys: *const c_char,
+    cache_timeout: c_double,
+) {
+    {
+        let mut keyring = CRYPTO.keyring.lock().unwrap();
+        keyring.age_identities = lines(age_identities).into_iter().map(PathBuf::from).collect();
+        keyring.openpgp_secret_keys = lines(openpgp_secret_keys).into_iter().map(PathBuf::from).collect();
+    }
+    let ttl = Duration::from_secs_f64(if cache_timeout.is_finite() { cache_timeout.max(0.0) } else { 0.0 });
+    CRYPTO.cache.lock().unwrap().set_ttl(ttl);
+}
+
+/// Forget all cached passphrases.
+#[no_mangle]
+pub extern "C" fn neomacs_crypto_clear_cache() {
+    CRYPTO.cache.lock().unwrap().clear();
+}
+
+/// Start decrypting the file at PATH, with PASSPHRASE or NULL to use
+/// the secret keys and cached passphrases.  Returns the job id, or 0
+/// if PATH is not a valid string.
+///
+/// # Safety
+/// `path` and `passphrase` must each be NULL or a valid NUL-terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_crypto_decrypt_file(
+    path: *const c_char,
+    passphrase: *const c_char,
+) -> u64 {
+    match opt_string(path) {
+        Some(path) => CRYPTO.decrypt_file(PathBuf::from(path), opt_string(passphrase)),
+        None => 0,
+    }
+}
+
+/// Start decrypting LEN bytes at DATA, from the file CONTEXT (for the
+/// passphrase cache), with PASSPHRASE or NULL.  Returns the job id.
+///
+/// # Safety
+/// `data` must be NULL or point to `len` readable bytes; `context` and
+/// `passphrase` must each be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_crypto_decrypt_data(
+    data: *const u8,
+    len: usize,
+    context: *const c_char,
+    passphrase: *const c_char,
+) -> u64 {
+    let context = PathBuf::from(opt_string(context).unwrap_or_default());
+    CRYPTO.decrypt_data(bytes(data, len), context, opt_string(passphrase))
+}
+
+/// Start encrypting LEN bytes at DATA in FORMAT (NEOMACS_CRYPTO_AGE or
+/// _OPENPGP) to RECIPIENTS, newline-separated age recipients or public
+/// key files.  Without recipients, PASSPHRASE or the one cached for
+/// CONTEXT is used.  With OUTPUT the ciphertext replaces that file;
+/// otherwise it is the job's result, armored if ARMOR is nonzero.
+/// Returns the job id.
+///
+/// # Safety
+/// `data` must be NULL or point to `len` readable bytes; the string
+/// arguments must each be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_crypto_encrypt(
+    data: *const u8,
+    len: usize,
+    format: c_int,
+    recipients: *const c_char,
+    passphrase: *const c_char,
+    context: *const c_char,
+    output: *const c_char,
+    armor: c_int,
+) -> u64 {
+    let format = if format == NEOMACS_CRYPTO_AGE { CryptoFormat::Age } else { CryptoFormat::OpenPgp };
+    CRYPTO.encrypt(
+        format,
+        bytes(data, len),
+        lines(recipients),
+        opt_string(passphrase),
+        PathBuf::from(opt_string(context).unwrap_or_default()),
+        opt_string(output).map(PathBuf::from),
+        armor != 0,
+    )
+}
+
+/// State of JOB, a NEOMACS_CRYPTO_* code.  Once it is no longer
+/// running the job is forgotten: on NEOMACS_CRYPTO_DONE its result is
+/// stored in *DATA_OUT and *LEN_OUT (free it with
+/// neomacs_crypto_free_data()), and on NEOMACS_CRYPTO_FAILED a message
+/// in *MESSAGE_OUT (free it with neomacs_crypto_free_message()).
+///
+/// # Safety
+/// `data_out`, `len_out` and `message_out` must each be NULL or point to
+/// writable storage.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_crypto_job_result(
+    job: u64,
+    data_out: *mut *mut u8,
+    len_out: *mut usize,
+    message_out: *mut *mut c_char,
+) -> c_int {
+    if !data_out.is_null() {
+        *data_out = ptr::null_mut();
+    }
+    if !len_out.is_null() {
+        *len_out = 0;
+    }
+    if !message_out.is_null() {
+        *message_out = ptr::null_mut();
+    }
+    match CRYPTO.jobs.take(job) {
+        None => NEOMACS_CRYPTO_UNKNOWN,
+        Some(JobState::Running) => NEOMACS_CRYPTO_RUNNING,
+        Some(JobState::Done(data)) => {
+            if !len_out.is_null() {
+                *len_out = data.len();
+            }
+            if !data_out.is_null() {
+                *data_out = Box::into_raw(data.into_boxed_slice()) as *mut u8;
+            }
+            NEOMACS_CRYPTO_DONE
+        }
+        Some(JobState::Failed(CryptoError::NeedPassphrase)) => NEOMACS_CRYPTO_NEED_PASSPHRASE,
+        Some(JobState::Failed(CryptoError::BadPassphrase)) => NEOMACS_CRYPTO_BAD_PASSPHRASE,
+        Some(JobState::Failed(CryptoError::NoKey)) => NEOMACS_CRYPTO_NO_KEY,
+        Some(JobState::Failed(e)) => {
+            if !message_out.is_null() {
+                *message_out = CString::new(e.to_string()).map_or(ptr::null_mut(), CString::into_raw);
+            }
+            NEOMACS_CRYPTO_FAILED
+        }
+    }
+}
+
+/// Free, after clearing it, LEN bytes returned by
+/// neomacs_crypto_job_result().
+///
+/// # Safety
+/// `data` must be NULL or a pointer returned by
+/// `neomacs_crypto_job_result` with the same `len`, not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_crypto_free_data(data: *mut u8, len: usize) {
+    if data.is_null() {
+        return;
+    }
+    let mut data = Box::from_raw(ptr::slice_from_raw_parts_mut(data, len));
+    for byte in data.iter_mut() {
+        ptr::write_volatile(byte, 0);
+    }
+}
+
+/// Free a message returned by neomacs_crypto_job_result().
+///
+/// # Safety
+/// `message` must be NULL or a message returned by
+/// `neomacs_crypto_job_result`, not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_crypto_free_message(message: *mut c_char) {
+    if !message.is_null() {
+        drop(CString::from_raw(message));
+    }
+}
@@ -78,7 +78,7 @@ pub unsafe extern "C" fn neomacs_display_add_char_glyph(
     }));
 
     if let Err(e) = result {
-        eprintln!("PANIC in neomacs_display_add_char_glyph: {:?}", e);
+        log::error!("PANIC in neomacs_display_add_char_glyph: {:?}", e);
     }
 }
 
@@ -116,7 +116,7 @@ pub unsafe extern "C" fn neomacs_display_add_stretch_glyph(
     }));
 
     if let Err(e) = result {
-        eprintln!("PANIC in neomacs_display_add_stretch_glyph: {:?}", e);
+        log::error!("PANIC in neomacs_display_add_stretch_glyph: {:?}", e);
     }
 }
 
@@ -334,6 +334,7 @@ pub unsafe extern "C" fn neomacs_display_set_face(
         font_descent,
         underline_position: if ul_position > 0 { ul_position } else { 1 },
         underline_thickness: if ul_thickness > 0 { ul_thickness } else { 1 },
+        text_shadow: None,
     };
 
     // Store face for later lookup during rendering
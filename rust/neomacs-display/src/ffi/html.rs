@@ -0,0 +1,132 @@
+//! HTML rendering FFI functions
+//!
+//! Rendering is a pure function of the document, so there is no state
+//! here: C passes the HTML and gets back the text and its styled runs,
+//! which it frees with neomacs_html_free().
+
+use super::*;
+
+use crate::core::html::{self, StyledRun};
+
+pub const NEOMACS_HTML_BOLD: c_int = 1;
+pub const NEOMACS_HTML_ITALIC: c_int = 2;
+pub const NEOMACS_HTML_UNDERLINE: c_int = 4;
+pub const NEOMACS_HTML_STRIKE: c_int = 8;
+pub const NEOMACS_HTML_CODE: c_int = 16;
+
+/// A styled run of rendered text as seen from C.
+#[repr(C)]
+pub struct NeomacsHtmlRun {
+    /// Character offsets in the text
+    pub start: i64,
+    pub end: i64,
+    /// NEOMACS_HTML_* flags
+    pub flags: c_int,
+    /// Heading level 1-6, 0 for body text
+    pub heading: c_int,
+    /// Colors as 0xRRGGBB, -1 for none
+    pub fg: c_int,
+    pub bg: c_int,
+    /// Link target, else NULL
+    pub link: *mut c_char,
+    /// Source of the image the text stands for, else NULL
+    pub image: *mut c_char,
+    /// Size of that image from the document, 0 if not given
+    pub image_width: c_int,
+    pub image_height: c_int,
+}
+
+fn owned_cstr(text: &str) -> *mut c_char {
+    CString::new(text.replace('\0', " ")).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+fn run_to_c(run: &StyledRun) -> NeomacsHtmlRun {
+    let style = &run.style;
+    let flags = [
+        (style.bold, NEOMACS_HTML_BOLD),
+        (style.italic, NEOMACS_HTML_ITALIC),
+        (style.underline, NEOMACS_HTML_UNDERLINE),
+        (style.strike, NEOMACS_HTML_STRIKE),
+        (style.code, NEOMACS_HTML_CODE),
+    ]
+    .iter()
+    .filter(|(set, _)| *set)
+    .fold(0, |flags, (_, flag)| flags | flag);
+    let image = style.image.as_ref();
+    NeomacsHtmlRun {
+        start: run.start as i64,
+        end: run.end as i64,
+        flags,
+        heading: style.heading as c_int,
+        fg: style.fg.map_or(-1, |c| c as c_int),
+        bg: style.bg.map_or(-1, |c| c as c_int),
+        link: style.link.as_deref().map_or(ptr::null_mut(), owned_cstr),
+        image: image.map_or(ptr::null_mut(), |i| owned_cstr(&i.src)),
+        image_width: image.map_or(0, |i| i.width.min(c_int::MAX as u32) as c_int),
+        image_height: image.map_or(0, |i| i.height.min(c_int::MAX as u32) as c_int),
+    }
+}
+
+/// Render LEN bytes of UTF-8 HTML at DATA as text filled to WIDTH
+/// columns.  Stores the text in *TEXT_OUT, its styled runs in *RUNS_OUT
+/// and the document title, or NULL, in *TITLE_OUT; returns the number
+/// of runs, or -1 if DATA is not UTF-8.
+///
+/// # Safety
+/// `data` must be NULL or point to `len` readable bytes; the out pointers
+/// must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_html_render(
+    data: *const u8,
+    len: usize,
+    width: c_int,
+    text_out: *mut *mut c_char,
+    runs_out: *mut *mut NeomacsHtmlRun,
+    title_out: *mut *mut c_char,
+) -> c_int {
+    if text_out.is_null() || runs_out.is_null() || title_out.is_null() {
+        return -1;
+    }
+    *text_out = ptr::null_mut();
+    *runs_out = ptr::null_mut();
+    *title_out = ptr::null_mut();
+    let bytes = if data.is_null() || len == 0 { &[][..] } else { std::slice::from_raw_parts(data, len) };
+    let Ok(source) = std::str::from_utf8(bytes) else { return -1 };
+    let document = html::render(source, width.max(0) as usize);
+    let runs: Vec<NeomacsHtmlRun> = document.runs.iter().map(run_to_c).collect();
+    let count = runs.len() as c_int;
+    *text_out = owned_cstr(&document.text);
+    *runs_out = Box::into_raw(runs.into_boxed_slice()) as *mut NeomacsHtmlRun;
+    *title_out = document.title.as_deref().map_or(ptr::null_mut(), owned_cstr);
+    count
+}
+
+/// Free what neomacs_html_render() returned.
+///
+/// # Safety
+/// The arguments must be exactly what one call to `neomacs_html_render`
+/// stored and returned, each freed only once.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_html_free(
+    text: *mut c_char,
+    runs: *mut NeomacsHtmlRun,
+    nruns: c_int,
+    title: *mut c_char,
+) {
+    for s in [text, title] {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    }
+    if runs.is_null() {
+        return;
+    }
+    let runs = Box::from_raw(ptr::slice_from_raw_parts_mut(runs, nruns.max(0) as usize));
+    for run in runs.iter() {
+        for s in [run.link, run.image] {
+            if !s.is_null() {
+                drop(CString::from_raw(s));
+            }
+        }
+    }
+}
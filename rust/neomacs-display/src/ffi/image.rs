@@ -576,6 +576,321 @@ pub unsafe extern "C" fn neomacs_display_free_image(
     -1
 }
 
+/// Image annotation passed from C
+#[repr(C)]
+pub struct CImageAnnotation {
+    /// 0 = arrow, 1 = rectangle, 2 = text
+    pub kind: c_int,
+    /// Arrow from (x0, y0) to (x1, y1), rectangle between the two
+    /// corners, or top left of text at (x0, y0); fractions of the image
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+    /// sRGB color (0xRRGGBB)
+    pub color: u32,
+    /// Line width or text size, as a fraction of the shorter side
+    pub size: f32,
+    /// Text of a text annotation (UTF-8), else NULL
+    pub text: *const c_char,
+}
+
+/// Image edits passed from C
+#[repr(C)]
+pub struct CImageEdits {
+    /// Quarter turns clockwise
+    pub rotation: c_int,
+    pub flip_h: c_int,
+    pub flip_v: c_int,
+    /// Part to keep, as fractions of the rotated image; no crop if
+    /// crop_w or crop_h is not positive
+    pub crop_x: f32,
+    pub crop_y: f32,
+    pub crop_w: f32,
+    pub crop_h: f32,
+    pub annotations: *const CImageAnnotation,
+    pub annotation_count: c_int,
+}
+
+/// Convert EDITS from C; None for NULL.
+unsafe fn image_edits_from_c(edits: *const CImageEdits) -> Option<ImageEdits> {
+    let edits = edits.as_ref()?;
+    let mut annotations = Vec::new();
+    if !edits.annotations.is_null() && edits.annotation_count > 0 {
+        let items = std::slice::from_raw_parts(edits.annotations, edits.annotation_count as usize);
+        for a in items {
+            let (x0, y0, x1, y1) = (a.x0, a.y0, a.x1, a.y1);
+            let shape = match a.kind {
+                0 => AnnotationShape::Arrow { x0, y0, x1, y1 },
+                1 => AnnotationShape::Rect { x0, y0, x1, y1 },
+                2 if !a.text.is_null() => AnnotationShape::Text {
+                    x: x0,
+                    y: y0,
+                    text: std::ffi::CStr::from_ptr(a.text).to_string_lossy().into_owned(),
+                },
+                _ => continue,
+            };
+            annotations.push(ImageAnnotation { shape, color: a.color, size: a.size });
+        }
+    }
+    Some(ImageEdits {
+        rotation: edits.rotation.rem_euclid(4) as u8,
+        flip_h: edits.flip_h != 0,
+        flip_v: edits.flip_v != 0,
+        crop: (edits.crop_w > 0.0 && edits.crop_h > 0.0)
+            .then_some([edits.crop_x, edits.crop_y, edits.crop_w, edits.crop_h]),
+        annotations,
+    })
+}
+
+/// Set the edits of a file image: rotation, flips, crop and annotations.
+/// EDITS NULL undoes all edits.  The image is decoded again with them;
+/// its new size arrives as an IMAGE_DIMENSIONS_READY event.  Images
+/// created from raw pixels can't be edited.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_image_edits(
+    handle: *mut NeomacsDisplay,
+    image_id: u32,
+    edits: *const CImageEdits,
+) -> c_int {
+    let edits = image_edits_from_c(edits);
+
+    // Threaded path: send command to render thread
+    if let Some(ref state) = THREADED_STATE {
+        let cmd = RenderCommand::ImageSetEdits { id: image_id, edits };
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+        return 0;
+    }
+
+    if handle.is_null() {
+        return -1;
+    }
+    let display = &mut *handle;
+
+    if let Some(ref mut backend) = display.winit_backend {
+        if let Some(renderer) = backend.renderer_mut() {
+            return if renderer.set_image_edits(image_id, edits).is_some() { 0 } else { -1 };
+        }
+    }
+    -1
+}
+
+/// Store in OUT_WIDTH and OUT_HEIGHT the size of a WIDTH x HEIGHT image
+/// after EDITS.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_edited_image_size(
+    edits: *const CImageEdits,
+    width: c_int,
+    height: c_int,
+    out_width: *mut c_int,
+    out_height: *mut c_int,
+) {
+    if out_width.is_null() || out_height.is_null() {
+        return;
+    }
+    let edits = image_edits_from_c(edits).unwrap_or_default();
+    let (w, h) = edits.output_size(width.max(1) as u32, height.max(1) as u32);
+    *out_width = w as c_int;
+    *out_height = h as c_int;
+}
+
+/// Apply EDITS to the image file at SOURCE, at full size, and save the
+/// result as a PNG file at OUTPUT.  Runs on the calling thread.  Returns
+/// 0 and stores the saved size in WIDTH and HEIGHT, or returns -1 and
+/// writes why to ERR_BUF, truncated to ERR_LEN bytes with its NUL.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_export_edited_image(
+    source: *const c_char,
+    edits: *const CImageEdits,
+    output: *const c_char,
+    width: *mut c_int,
+    height: *mut c_int,
+    err_buf: *mut c_char,
+    err_len: usize,
+) -> c_int {
+    if source.is_null() || output.is_null() {
+        return -1;
+    }
+    let source = std::ffi::CStr::from_ptr(source).to_string_lossy();
+    let output = std::ffi::CStr::from_ptr(output).to_string_lossy();
+    let edits = image_edits_from_c(edits).unwrap_or_default();
+
+    use crate::backend::wgpu::WgpuRenderer;
+    match WgpuRenderer::export_edited_image(&source, &edits, &output) {
+        Ok((w, h)) => {
+            if !width.is_null() {
+                *width = w as c_int;
+            }
+            if !height.is_null() {
+                *height = h as c_int;
+            }
+            0
+        }
+        Err(msg) => {
+            log::warn!("Image export failed: {}", msg);
+            if !err_buf.is_null() && err_len > 0 {
+                let len = msg.len().min(err_len - 1);
+                ptr::copy_nonoverlapping(msg.as_ptr(), err_buf as *mut u8, len);
+                *err_buf.add(len) = 0;
+            }
+            -1
+        }
+    }
+}
+
+/// Chart data series passed from C
+#[repr(C)]
+pub struct CChartSeries {
+    /// Name shown in the legend (UTF-8), or NULL
+    pub name: *const c_char,
+    /// COUNT values; NaN for missing ones
+    pub values: *const c_double,
+    pub count: c_int,
+    /// sRGB color (0xRRGGBB)
+    pub color: u32,
+}
+
+/// Chart passed from C
+#[repr(C)]
+pub struct CChartSpec {
+    /// 0 = line, 1 = bar, 2 = sparkline
+    pub kind: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub series: *const CChartSeries,
+    pub series_count: c_int,
+    /// Labels of the x positions (UTF-8)
+    pub labels: *const *const c_char,
+    pub label_count: c_int,
+    /// Title (UTF-8), or NULL
+    pub title: *const c_char,
+    /// Text and axis color (0xRRGGBB)
+    pub fg: u32,
+    /// Background color (0xRRGGBB), used if OPAQUE is nonzero
+    pub bg: u32,
+    pub opaque: c_int,
+    /// Value range; NaN for the data's
+    pub min: c_double,
+    pub max: c_double,
+    /// Pixel size of the labels
+    pub font_size: f32,
+}
+
+/// Area of a chart image showing x position INDEX
+#[repr(C)]
+pub struct CChartHotSpot {
+    pub index: c_int,
+    pub x0: c_int,
+    pub y0: c_int,
+    pub x1: c_int,
+    pub y1: c_int,
+}
+
+unsafe fn optional_str(s: *const c_char) -> Option<String> {
+    (!s.is_null()).then(|| CStr::from_ptr(s).to_string_lossy().into_owned())
+}
+
+/// Convert SPEC from C; None for NULL.
+unsafe fn chart_spec_from_c(spec: *const CChartSpec) -> Option<ChartSpec> {
+    let spec = spec.as_ref()?;
+    let kind = match spec.kind {
+        1 => ChartKind::Bar,
+        2 => ChartKind::Sparkline,
+        _ => ChartKind::Line,
+    };
+    let mut series = Vec::new();
+    if !spec.series.is_null() && spec.series_count > 0 {
+        for s in std::slice::from_raw_parts(spec.series, spec.series_count as usize) {
+            let values = if s.values.is_null() || s.count <= 0 {
+                Vec::new()
+            } else {
+                std::slice::from_raw_parts(s.values, s.count as usize).to_vec()
+            };
+            series.push(ChartSeries { name: optional_str(s.name).unwrap_or_default(), values, color: s.color });
+        }
+    }
+    let mut labels = Vec::new();
+    if !spec.labels.is_null() && spec.label_count > 0 {
+        for &label in std::slice::from_raw_parts(spec.labels, spec.label_count as usize) {
+            labels.push(optional_str(label).unwrap_or_default());
+        }
+    }
+    let range = (spec.min.is_finite() && spec.max.is_finite()).then_some((spec.min, spec.max));
+    Some(ChartSpec {
+        kind,
+        width: spec.width.max(1) as u32,
+        height: spec.height.max(1) as u32,
+        series,
+        labels,
+        title: optional_str(spec.title),
+        fg: spec.fg,
+        bg: (spec.opaque != 0).then_some(spec.bg),
+        range,
+        font_size: spec.font_size,
+    })
+}
+
+/// Draw the chart SPEC as a PNG image.  Runs on the calling thread.
+/// Returns 0 and stores the PNG data in PNG and PNG_LEN and the area of
+/// each x position in HOT_SPOTS and HOT_SPOT_COUNT, or returns -1.  Free
+/// the results with neomacs_display_free_chart().
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_render_chart(
+    spec: *const CChartSpec,
+    png: *mut *mut u8,
+    png_len: *mut usize,
+    hot_spots: *mut *mut CChartHotSpot,
+    hot_spot_count: *mut c_int,
+) -> c_int {
+    if png.is_null() || png_len.is_null() || hot_spots.is_null() || hot_spot_count.is_null() {
+        return -1;
+    }
+    let Some(spec) = chart_spec_from_c(spec) else {
+        return -1;
+    };
+
+    use crate::backend::wgpu::WgpuRenderer;
+    match WgpuRenderer::render_chart_png(&spec) {
+        Ok((data, spots)) => {
+            let spots: Box<[CChartHotSpot]> = spots
+                .iter()
+                .map(|h| CChartHotSpot {
+                    index: h.index as c_int,
+                    x0: h.x0 as c_int,
+                    y0: h.y0 as c_int,
+                    x1: h.x1 as c_int,
+                    y1: h.y1 as c_int,
+                })
+                .collect();
+            *png_len = data.len();
+            *png = Box::into_raw(data.into_boxed_slice()) as *mut u8;
+            *hot_spot_count = spots.len() as c_int;
+            *hot_spots = Box::into_raw(spots) as *mut CChartHotSpot;
+            0
+        }
+        Err(msg) => {
+            log::warn!("Chart rendering failed: {}", msg);
+            -1
+        }
+    }
+}
+
+/// Free the results of neomacs_display_render_chart().
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_free_chart(
+    png: *mut u8,
+    png_len: usize,
+    hot_spots: *mut CChartHotSpot,
+    hot_spot_count: c_int,
+) {
+    if !png.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(png, png_len)));
+    }
+    if !hot_spots.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(hot_spots, hot_spot_count.max(0) as usize)));
+    }
+}
+
 /// Set a floating video at a specific screen position
 #[no_mangle]
 pub unsafe extern "C" fn neomacs_display_set_floating_video(
@@ -664,6 +979,38 @@ pub unsafe extern "C" fn neomacs_display_clear_floating_image(
     display.get_target_scene().remove_floating_image(image_id);
 }
 
+/// Set the background image of a window (WINDOW_ID non-zero) or of a
+/// buffer (BUFFER_ID), drawn under the text.  IMAGE_ID 0 removes it.
+/// OPACITY is 0.0-1.0; SCALING and ANCHOR use the encodings of
+/// `BackgroundScaling::from_ffi` and `BackgroundAnchor::from_ffi`.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_window_background(
+    _handle: *mut NeomacsDisplay,
+    window_id: i64,
+    buffer_id: u64,
+    image_id: u32,
+    opacity: f32,
+    scaling: c_int,
+    anchor: c_int,
+) {
+    use crate::core::window_background::*;
+    let target = if window_id != 0 {
+        BackgroundTarget::Window(window_id)
+    } else {
+        BackgroundTarget::Buffer(buffer_id)
+    };
+    let background = (image_id != 0).then(|| WindowBackground {
+        image_id,
+        opacity: opacity.clamp(0.0, 1.0),
+        scaling: BackgroundScaling::from_ffi(scaling),
+        anchor: BackgroundAnchor::from_ffi(anchor),
+    });
+    let cmd = RenderCommand::SetWindowBackground { target, background };
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
 /// Clear a rectangular area of the display.
 /// No-op with full-frame rebuild (buffer is rebuilt from scratch each frame).
 #[no_mangle]
@@ -779,7 +1126,7 @@ pub unsafe extern "C" fn neomacs_display_end_frame(handle: *mut NeomacsDisplay)
     };
 
     if let Err(e) = result {
-        eprintln!("Render error: {}", e);
+        log::error!("Render error: {}", e);
         return -1;
     }
 
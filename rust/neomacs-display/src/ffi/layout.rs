@@ -1,7 +1,7 @@
 //! Rust Layout Engine FFI Entry Point
 //!
 //! neomacs_rust_layout_frame, neomacs_layout_charpos_at_pixel,
-//! neomacs_layout_window_charpos.
+//! neomacs_layout_window_charpos, neomacs_layout_pos_to_pixel.
 
 use super::*;
 
@@ -20,6 +20,64 @@ pub(crate) static mut PENDING_LIGATURES_ENABLED: Option<bool> = None;
 /// Applied on first engine creation so init.el settings are not lost.
 pub(crate) static mut PENDING_COSMIC_METRICS: Option<bool> = None;
 
+/// Pending typewriter/centered-column settings, set before layout engine is initialized.
+/// Applied on first engine creation so init.el settings are not lost.
+pub(crate) static mut PENDING_WRITING_MODES: Option<crate::layout::WritingModes> = None;
+
+/// Return the global layout engine, creating it on first use with any
+/// settings made before it existed (e.g. from init.el).
+pub(crate) unsafe fn layout_engine_mut() -> &'static mut crate::layout::LayoutEngine {
+    let slot = &mut *std::ptr::addr_of_mut!(LAYOUT_ENGINE);
+    slot.get_or_insert_with(|| {
+        let mut engine = crate::layout::LayoutEngine::new();
+        // Apply pending ligatures setting from init.el (set before engine existed)
+        if let Some(enabled) = *std::ptr::addr_of!(PENDING_LIGATURES_ENABLED) {
+            engine.ligatures_enabled = enabled;
+            log::info!("Applied pending ligatures_enabled={}", enabled);
+        }
+        // Apply pending cosmic metrics setting from init.el
+        if let Some(enabled) = *std::ptr::addr_of!(PENDING_COSMIC_METRICS) {
+            engine.use_cosmic_metrics = enabled;
+            log::info!("Applied pending use_cosmic_metrics={}", enabled);
+        }
+        // Apply pending typewriter/centered-column settings from init.el
+        if let Some(modes) = (*std::ptr::addr_of!(PENDING_WRITING_MODES)).clone() {
+            log::info!("Applied pending writing modes {:?}", modes);
+            engine.writing_modes = modes;
+        }
+        log::info!("Rust layout engine initialized");
+        engine
+    })
+}
+
+/// Check that this library and Emacs agree on the layout of the Emacs
+/// structs layout reads directly (see `emacs_types::check_abi`).
+/// Returns 1 if they do.  Otherwise writes why to ERR_BUF, truncated to
+/// ERR_LEN bytes with its NUL, and returns 0.
+///
+/// # Safety
+/// Must be called on the Emacs thread.  ERR_BUF must be NULL or point
+/// to ERR_LEN writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_rust_check_abi(err_buf: *mut c_char, err_len: usize) -> c_int {
+    // Emacs calls this first, from the thread it will lay out on
+    crate::layout::buffer_snapshot::register_emacs_thread();
+    let result = std::panic::catch_unwind(crate::layout::emacs_types::check_abi)
+        .unwrap_or_else(|_| Err("panic while checking the Emacs FFI ABI".to_string()));
+    match result {
+        Ok(()) => 1,
+        Err(msg) => {
+            log::error!("{}", msg);
+            if !err_buf.is_null() && err_len > 0 {
+                let len = msg.len().min(err_len - 1);
+                ptr::copy_nonoverlapping(msg.as_ptr(), err_buf as *mut u8, len);
+                *err_buf.add(len) = 0;
+            }
+            0
+        }
+    }
+}
+
 /// Called from C when `neomacs-use-rust-display` is enabled.
 /// The Rust layout engine reads buffer data via FFI helpers and produces
 /// a FrameGlyphBuffer, bypassing the C matrix extraction.
@@ -50,35 +108,14 @@ pub unsafe extern "C" fn neomacs_rust_layout_frame(
 
     // Wrap in catch_unwind to prevent Rust panics from crossing FFI boundary
     let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let _span = crate::logging::span("redisplay");
         let display = &mut *handle;
 
         // Validate Emacs struct offsets on first call
+        crate::layout::buffer_snapshot::register_emacs_thread();
         crate::layout::emacs_types::ensure_offsets_valid();
 
-        // Initialize layout engine on first call
-        if (*std::ptr::addr_of!(LAYOUT_ENGINE)).is_none() {
-            let mut engine = crate::layout::LayoutEngine::new();
-            // Apply pending ligatures setting from init.el (set before engine existed)
-            if let Some(enabled) = *std::ptr::addr_of!(PENDING_LIGATURES_ENABLED) {
-                engine.ligatures_enabled = enabled;
-                log::info!("Applied pending ligatures_enabled={}", enabled);
-            }
-            // Apply pending cosmic metrics setting from init.el
-            if let Some(enabled) = *std::ptr::addr_of!(PENDING_COSMIC_METRICS) {
-                engine.use_cosmic_metrics = enabled;
-                log::info!("Applied pending use_cosmic_metrics={}", enabled);
-            }
-            *std::ptr::addr_of_mut!(LAYOUT_ENGINE) = Some(engine);
-            log::info!("Rust layout engine initialized");
-        }
-
-        let engine = match (*std::ptr::addr_of_mut!(LAYOUT_ENGINE)).as_mut() {
-            Some(e) => e,
-            None => {
-                log::error!("Rust layout engine initialization failed");
-                return;
-            }
-        };
+        let engine = layout_engine_mut();
         let frame_params = crate::layout::FrameParams {
             width,
             height,
@@ -142,6 +179,50 @@ pub unsafe extern "C" fn neomacs_layout_window_charpos(
     crate::layout::hit_test_window_charpos(window_id, wx, wy)
 }
 
+/// Where a buffer position is drawn (see neomacs_layout_pos_to_pixel).
+#[repr(C)]
+pub struct NeomacsCharPixel {
+    /// Frame-relative box of its character
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Text row of the window it is on, and column in that row
+    pub row: c_int,
+    pub col: c_int,
+}
+
+/// Store in OUT where CHARPOS is drawn in window WINDOW_ID by the last
+/// Rust layout.  Returns 1 if it is shown, 0 if it is not, and -1 if
+/// the last layout did not include the window, so the caller should
+/// fall back to its own idea of the display.
+///
+/// # Safety
+/// Must be called on the Emacs thread.  OUT must point to a writable
+/// NeomacsCharPixel.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_layout_pos_to_pixel(
+    window_id: i64,
+    charpos: i64,
+    out: *mut NeomacsCharPixel,
+) -> c_int {
+    if out.is_null() || !crate::layout::hit_test_has_window(window_id) {
+        return -1;
+    }
+    let Some(p) = crate::layout::hit_test_char_pixel(window_id, charpos) else {
+        return 0;
+    };
+    *out = NeomacsCharPixel {
+        x: p.x,
+        y: p.y,
+        width: p.width,
+        height: p.height,
+        row: p.row,
+        col: p.col,
+    };
+    1
+}
+
 // Note: Event Polling FFI Functions have been removed
 // Events are now delivered via the threaded mode wakeup mechanism
 // Use neomacs_display_drain_input() instead
@@ -169,3 +250,738 @@ pub unsafe extern "C" fn neomacs_display_set_font_backend(
     // Always store pending so engine init picks it up even if set before creation
     *std::ptr::addr_of_mut!(PENDING_COSMIC_METRICS) = Some(use_cosmic);
 }
+
+/// Modify the layout engine's writing modes, or the pending copy if the
+/// engine has not been created yet.
+unsafe fn update_writing_modes(f: impl FnOnce(&mut crate::layout::WritingModes)) {
+    let pending = &mut *std::ptr::addr_of_mut!(PENDING_WRITING_MODES);
+    let mut modes = match (*std::ptr::addr_of!(LAYOUT_ENGINE)).as_ref() {
+        Some(engine) => engine.writing_modes.clone(),
+        None => pending.clone().unwrap_or_default(),
+    };
+    f(&mut modes);
+    if let Some(ref mut engine) = *std::ptr::addr_of_mut!(LAYOUT_ENGINE) {
+        engine.writing_modes = modes.clone();
+    }
+    *pending = Some(modes);
+}
+
+/// Enable or disable typewriter scrolling: the layout engine keeps point's
+/// line vertically centered in every non-minibuffer window.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_typewriter_scrolling(
+    _handle: *mut NeomacsDisplay,
+    enabled: c_int,
+) {
+    update_writing_modes(|modes| modes.typewriter = enabled != 0);
+}
+
+/// Enable or disable scroll anchoring: when a window is resized or text
+/// before point changes height while point and the window start stay,
+/// the layout engine keeps point's line on the same screen row.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_scroll_anchoring(
+    _handle: *mut NeomacsDisplay,
+    enabled: c_int,
+) {
+    update_writing_modes(|modes| modes.scroll_anchoring = enabled != 0);
+}
+
+/// Center a text column of COLUMNS characters in every non-minibuffer
+/// window (zen writing).  MARGIN_BG is the margin background as an sRGB
+/// pixel (0xRRGGBB), or -1 to use the window background.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_centered_column(
+    _handle: *mut NeomacsDisplay,
+    enabled: c_int,
+    columns: c_int,
+    margin_bg: c_int,
+) {
+    update_writing_modes(|modes| {
+        modes.centered_column = enabled != 0;
+        if columns > 0 {
+            modes.column_width = columns;
+        }
+        modes.margin_bg = (margin_bg >= 0).then_some(margin_bg as u32);
+    });
+}
+
+/// Show TEXT as an inline suggestion at POSITION in the buffer identified
+/// by BUFFER_ID (the `struct buffer` pointer).  FG is the text color as
+/// 0xRRGGBB.  A NULL or empty TEXT removes the suggestion.
+/// Returns 1 if the suggestion is shown, 0 otherwise (e.g. the Rust
+/// layout engine is not in use).
+///
+/// # Safety
+/// Must be called on the Emacs thread.  TEXT must be NULL or a valid C string.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_ghost_text(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    position: i64,
+    text: *const c_char,
+    fg: u32,
+) -> c_int {
+    let engine = match (*std::ptr::addr_of_mut!(LAYOUT_ENGINE)).as_mut() {
+        Some(e) => e,
+        None => return 0,
+    };
+    let text = if text.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(text).to_string_lossy().into_owned()
+    };
+    if text.is_empty() {
+        engine.ghost_text = None;
+        return 0;
+    }
+    engine.ghost_text = Some(crate::layout::ghost_text::GhostText {
+        buffer_id,
+        position,
+        text,
+        fg,
+    });
+    1
+}
+
+/// Accept part of the current inline suggestion.  MODE is 0 for the next
+/// word, 1 for the rest of the line, 2 for everything.  Returns the
+/// accepted text, to be inserted at *POSITION, or NULL if there is no
+/// suggestion.  The suggestion is removed once fully accepted.  Free the
+/// result with `neomacs_display_free_string`.
+///
+/// # Safety
+/// Must be called on the Emacs thread.  POSITION must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_accept_ghost_text(
+    _handle: *mut NeomacsDisplay,
+    mode: c_int,
+    position: *mut i64,
+) -> *mut c_char {
+    let engine = match (*std::ptr::addr_of_mut!(LAYOUT_ENGINE)).as_mut() {
+        Some(e) => e,
+        None => return std::ptr::null_mut(),
+    };
+    let ghost = match engine.ghost_text.as_mut() {
+        Some(g) => g,
+        None => return std::ptr::null_mut(),
+    };
+    if !position.is_null() {
+        *position = ghost.position;
+    }
+    let accepted = ghost.accept(crate::layout::ghost_text::GhostAccept::from_ffi(mode));
+    if ghost.text.is_empty() {
+        engine.ghost_text = None;
+    }
+    CString::new(accepted).map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// Attach a note to the character at POS of the buffer identified by
+/// BUFFER_ID (the `struct buffer` pointer).  ICON may be NULL.  FG and BG
+/// are 0xRRGGBB.  STYLE is 0 to draw in the right margin, 1 for a
+/// floating card.  Returns the annotation id, or 0 if TEXT is NULL.
+///
+/// # Safety
+/// Must be called on the Emacs thread.  TEXT and ICON must be NULL or
+/// valid C strings.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_annotation_add(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    pos: i64,
+    text: *const c_char,
+    icon: *const c_char,
+    fg: u32,
+    bg: u32,
+    style: c_int,
+) -> u32 {
+    if text.is_null() {
+        return 0;
+    }
+    let text = CStr::from_ptr(text).to_string_lossy().into_owned();
+    let icon = if icon.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(icon).to_string_lossy().into_owned()
+    };
+    let annotation = crate::layout::annotations::Annotation {
+        icon,
+        text,
+        fg,
+        bg,
+        style: crate::layout::annotations::AnnotationStyle::from_ffi(style),
+    };
+    layout_engine_mut().annotations.add(buffer_id, pos, annotation)
+}
+
+/// Remove annotation ID.  Returns 1 if it existed, 0 otherwise.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_annotation_remove(
+    _handle: *mut NeomacsDisplay,
+    id: u32,
+) -> c_int {
+    layout_engine_mut().annotations.remove(id) as c_int
+}
+
+/// Remove all annotations of the buffer identified by BUFFER_ID.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_annotation_clear(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+) {
+    layout_engine_mut().annotations.clear_buffer(buffer_id);
+}
+
+/// Move the annotations of BUFFER_ID after an edit at POS that deleted
+/// DELETED characters and inserted INSERTED characters.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_annotation_adjust(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    pos: i64,
+    inserted: i64,
+    deleted: i64,
+) {
+    layout_engine_mut().annotations.adjust(buffer_id, pos, inserted, deleted);
+}
+
+/// Replace the scroll bar tick marks of CATEGORY in the buffer identified
+/// by BUFFER_ID.  POSITIONS points to COUNT buffer positions; a COUNT of
+/// 0 removes the category.  COLOR is 0xRRGGBB.
+///
+/// # Safety
+/// Must be called on the Emacs thread.  CATEGORY must be a valid C string
+/// and POSITIONS must point to COUNT values (or be NULL if COUNT is 0).
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_scroll_bar_marks(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    category: *const c_char,
+    color: u32,
+    positions: *const i64,
+    count: c_int,
+) {
+    if category.is_null() {
+        return;
+    }
+    let category = CStr::from_ptr(category).to_string_lossy();
+    let positions = if positions.is_null() || count <= 0 {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(positions, count as usize).to_vec()
+    };
+    layout_engine_mut().scroll_bar_marks.set(buffer_id, &category, color, positions);
+}
+
+/// Remove all scroll bar tick marks of BUFFER_ID.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_clear_scroll_bar_marks(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+) {
+    layout_engine_mut().scroll_bar_marks.clear_buffer(buffer_id);
+}
+
+/// Return the position of the scroll bar mark of BUFFER_ID drawn nearest
+/// to offset Y of a track TRACK_HEIGHT pixels high showing BEGV..ZV, or
+/// -1 if none is within TOLERANCE pixels.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_scroll_bar_mark_at(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    begv: i64,
+    zv: i64,
+    track_height: c_int,
+    y: c_int,
+    tolerance: c_int,
+) -> i64 {
+    layout_engine_mut()
+        .scroll_bar_marks
+        .mark_at(buffer_id, begv, zv, track_height as f32, y as f32, tolerance as f32)
+        .unwrap_or(-1)
+}
+
+/// Show a rectangular region in window WINDOW_ID (the `struct window`
+/// pointer) showing buffer BUFFER_ID.  The rectangle covers columns
+/// LEFT_COL..RIGHT_COL of the lines from START through the line of END;
+/// BG is its background as 0xRRGGBB.  CURSOR_COL >= 0 draws an insertion
+/// cursor in CURSOR_COLOR at that column on each of its lines.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_rectangle_region(
+    _handle: *mut NeomacsDisplay,
+    window_id: i64,
+    buffer_id: u64,
+    start: i64,
+    end: i64,
+    left_col: c_int,
+    right_col: c_int,
+    bg: u32,
+    cursor_col: c_int,
+    cursor_color: u32,
+) {
+    layout_engine_mut().rectangles.set(
+        window_id,
+        crate::layout::rectangle::RectangleRegion {
+            buffer_id,
+            start,
+            end,
+            left_col: left_col.max(0),
+            right_col: right_col.max(left_col.max(0)),
+            bg,
+            cursor_col: (cursor_col >= 0).then_some(cursor_col),
+            cursor_color,
+        },
+    );
+}
+
+/// Remove the rectangular region of window WINDOW_ID.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_clear_rectangle_region(
+    _handle: *mut NeomacsDisplay,
+    window_id: i64,
+) {
+    layout_engine_mut().rectangles.clear(window_id);
+}
+
+/// Draw the cursor of window WINDOW_ID (the `struct window` pointer)
+/// COLUMNS columns past the end of its line while point is at POINT.
+/// COLUMNS <= 0 puts the cursor back at point.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_virtual_cursor(
+    _handle: *mut NeomacsDisplay,
+    window_id: i64,
+    point: i64,
+    columns: c_int,
+) {
+    layout_engine_mut().virtual_cursors.set(window_id, point, columns);
+}
+
+/// Register a table spanning NROWS lines of buffer BUFFER_ID, replacing
+/// any table it overlaps.  ROWS holds (start, end, ncells) for each line,
+/// ncells < 0 marking a horizontal rule line; CELLS holds (start, end)
+/// for each cell in order and TEXTS its UTF-8 contents.  ALIGNS gives the
+/// alignment of each of the NCOLS columns (0 left, 1 center, 2 right).
+/// GRID_COLOR is the grid line color as 0xRRGGBB.
+///
+/// # Safety
+/// Must be called on the Emacs thread.  ROWS must hold 3 * NROWS values,
+/// CELLS and TEXTS one entry per cell, ALIGNS NCOLS values.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_add_table(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    nrows: c_int,
+    rows: *const i64,
+    cells: *const i64,
+    texts: *const *const c_char,
+    aligns: *const c_int,
+    ncols: c_int,
+    grid_color: u32,
+) {
+    use crate::layout::table::{CellAlign, Table, TableCell, TableRow};
+
+    if rows.is_null() || nrows <= 0 {
+        return;
+    }
+    let rows = std::slice::from_raw_parts(rows, nrows as usize * 3);
+    let mut table_rows = Vec::with_capacity(nrows as usize);
+    let mut next_cell = 0usize;
+    for row in rows.chunks_exact(3) {
+        let ncells = row[2];
+        let mut row_cells = Vec::new();
+        for _ in 0..ncells.max(0) {
+            if cells.is_null() || texts.is_null() {
+                break;
+            }
+            let text_ptr = *texts.add(next_cell);
+            row_cells.push(TableCell {
+                start: *cells.add(next_cell * 2),
+                end: *cells.add(next_cell * 2 + 1),
+                text: if text_ptr.is_null() {
+                    String::new()
+                } else {
+                    CStr::from_ptr(text_ptr).to_string_lossy().into_owned()
+                },
+            });
+            next_cell += 1;
+        }
+        table_rows.push(TableRow { start: row[0], end: row[1], cells: row_cells, rule: ncells < 0 });
+    }
+    let aligns = if aligns.is_null() || ncols <= 0 {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(aligns, ncols as usize)
+            .iter()
+            .map(|&a| CellAlign::from_ffi(a))
+            .collect()
+    };
+    layout_engine_mut().tables.add(
+        buffer_id,
+        Table { rows: table_rows, aligns, grid_color },
+    );
+}
+
+/// Remove all tables of buffer BUFFER_ID.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_clear_tables(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+) {
+    layout_engine_mut().tables.clear_buffer(buffer_id);
+}
+
+/// Replace the command blocks of buffer BUFFER_ID.  BLOCKS holds seven
+/// values per block: (start, output_start, end, exit_code, duration_ms,
+/// hidden_lines, flags), where duration_ms < 0 means unknown and flags
+/// has bit 0 set if the output is collapsed and bit 1 if exit_code is
+/// valid.  COLORS holds the tint, success, failure and label colors as
+/// 0xRRGGBB.  NBLOCKS = 0 removes the buffer's blocks.
+///
+/// # Safety
+/// Must be called on the Emacs thread.  BLOCKS must hold 7 * NBLOCKS
+/// values and COLORS 4.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_command_blocks(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    nblocks: c_int,
+    blocks: *const i64,
+    colors: *const u32,
+) {
+    use crate::layout::command_blocks::{CommandBlock, CommandBlockColors};
+
+    if blocks.is_null() || colors.is_null() || nblocks <= 0 {
+        layout_engine_mut().command_blocks.clear_buffer(buffer_id);
+        return;
+    }
+    let blocks = std::slice::from_raw_parts(blocks, nblocks as usize * 7)
+        .chunks_exact(7)
+        .map(|b| CommandBlock {
+            start: b[0],
+            output_start: b[1],
+            end: b[2],
+            exit_code: (b[6] & 2 != 0).then_some(b[3] as i32),
+            duration_ms: u64::try_from(b[4]).ok(),
+            collapsed: b[6] & 1 != 0,
+            hidden_lines: b[5].clamp(0, u32::MAX as i64) as u32,
+        })
+        .collect();
+    let colors = std::slice::from_raw_parts(colors, 4);
+    layout_engine_mut().command_blocks.set(
+        buffer_id,
+        blocks,
+        CommandBlockColors { tint: colors[0], success: colors[1], failure: colors[2], label: colors[3] },
+    );
+}
+
+/// Make buffer BUFFER_ID a chat buffer whose input area begins at
+/// INPUT_START, with UNREAD messages arrived since the user scrolled
+/// up.  COLORS holds the input background, separator, pill background
+/// and pill text colors as 0xRRGGBB.  INPUT_START <= 0 makes it an
+/// ordinary buffer again.
+///
+/// # Safety
+/// Must be called on the Emacs thread.  COLORS must hold 4 values.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_chat_view(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    input_start: i64,
+    unread: c_int,
+    colors: *const u32,
+) {
+    use crate::layout::chat_view::{ChatColors, ChatView};
+
+    if input_start <= 0 || colors.is_null() {
+        layout_engine_mut().chat_views.clear_buffer(buffer_id);
+        return;
+    }
+    let colors = std::slice::from_raw_parts(colors, 4);
+    layout_engine_mut().chat_views.set(
+        buffer_id,
+        ChatView {
+            input_start,
+            unread: unread.max(0) as u32,
+            colors: ChatColors {
+                input_bg: colors[0],
+                separator: colors[1],
+                pill_bg: colors[2],
+                pill_fg: colors[3],
+            },
+        },
+    );
+}
+
+/// Flash a highlight of COLOR (0xRRGGBB) over positions [START, END) of
+/// buffer BUFFER_ID in every window showing them, fading out over
+/// DURATION_MS milliseconds.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_pulse_region(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    start: i64,
+    end: i64,
+    color: u32,
+    duration_ms: c_int,
+) {
+    use crate::layout::region_pulse::Pulse;
+
+    if end <= start || duration_ms <= 0 {
+        return;
+    }
+    layout_engine_mut().region_pulses.add(Pulse {
+        buffer_id,
+        start,
+        end,
+        color,
+        started: std::time::Instant::now(),
+        duration: std::time::Duration::from_millis(duration_ms as u64),
+    });
+}
+
+/// Make positions [START, END) of the buffer identified by BUFFER_ID a
+/// link to TARGET, drawn underlined in COLOR (0xRRGGBB).  Returns the
+/// link id, or 0 if TARGET is NULL or the range is empty.
+///
+/// # Safety
+/// Must be called on the Emacs thread.  TARGET must be NULL or a valid
+/// C string.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_link_add(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    start: i64,
+    end: i64,
+    target: *const c_char,
+    color: u32,
+) -> u32 {
+    if target.is_null() {
+        return 0;
+    }
+    let target = CStr::from_ptr(target).to_string_lossy().into_owned();
+    layout_engine_mut().links.add(buffer_id, start, end, target, color)
+}
+
+/// Remove link ID.  Returns 1 if it existed, 0 otherwise.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_link_remove(
+    _handle: *mut NeomacsDisplay,
+    id: u32,
+) -> c_int {
+    layout_engine_mut().links.remove(id) as c_int
+}
+
+/// Remove all links of the buffer identified by BUFFER_ID.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_link_clear(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+) {
+    layout_engine_mut().links.clear_buffer(buffer_id);
+}
+
+/// Move the links of BUFFER_ID after an edit at POS that deleted
+/// DELETED characters and inserted INSERTED characters.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_link_adjust(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    pos: i64,
+    inserted: i64,
+    deleted: i64,
+) {
+    layout_engine_mut().links.adjust(buffer_id, pos, inserted, deleted);
+}
+
+/// Return the id of the link of BUFFER_ID covering POS, or 0 if there is
+/// none.  Its target is written to TARGET_BUF, truncated to TARGET_LEN
+/// bytes with its NUL.
+///
+/// # Safety
+/// Must be called on the Emacs thread.  TARGET_BUF must be NULL or point
+/// to TARGET_LEN writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_link_at(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    pos: i64,
+    target_buf: *mut c_char,
+    target_len: usize,
+) -> u32 {
+    let Some((id, link)) = layout_engine_mut().links.at(buffer_id, pos) else {
+        return 0;
+    };
+    if !target_buf.is_null() && target_len > 0 {
+        let mut len = link.target.len().min(target_len - 1);
+        while !link.target.is_char_boundary(len) {
+            len -= 1;
+        }
+        ptr::copy_nonoverlapping(link.target.as_ptr(), target_buf as *mut u8, len);
+        *target_buf.add(len) = 0;
+    }
+    id
+}
+
+/// Replace the color swatches of BUFFER_ID whose literal starts in
+/// [START, END) with COUNT new ones.  RANGES holds the start and end of
+/// each literal (2 * COUNT values) and COLORS the color it denotes
+/// (0xRRGGBB).
+///
+/// # Safety
+/// Must be called on the Emacs thread.  RANGES and COLORS must hold
+/// 2 * COUNT and COUNT values (or be NULL if COUNT is 0).
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_color_swatches(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    start: i64,
+    end: i64,
+    ranges: *const i64,
+    colors: *const u32,
+    count: c_int,
+) {
+    let swatches: Vec<(i64, i64, u32)> = if ranges.is_null() || colors.is_null() || count <= 0 {
+        Vec::new()
+    } else {
+        let ranges = std::slice::from_raw_parts(ranges, count as usize * 2);
+        let colors = std::slice::from_raw_parts(colors, count as usize);
+        ranges.chunks_exact(2).zip(colors).map(|(r, &c)| (r[0], r[1], c)).collect()
+    };
+    layout_engine_mut().color_swatches.set_range(buffer_id, start, end, swatches);
+}
+
+/// Remove all color swatches of BUFFER_ID.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_clear_color_swatches(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+) {
+    layout_engine_mut().color_swatches.clear_buffer(buffer_id);
+}
+
+/// Move the color swatches of BUFFER_ID after an edit at POS that
+/// deleted DELETED characters and inserted INSERTED characters.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_adjust_color_swatches(
+    _handle: *mut NeomacsDisplay,
+    buffer_id: u64,
+    pos: i64,
+    inserted: i64,
+    deleted: i64,
+) {
+    layout_engine_mut().color_swatches.adjust(buffer_id, pos, inserted, deleted);
+}
+
+/// Show a breadcrumb bar of COUNT segments as the header line text of
+/// window WINDOW_ID (the `struct window` pointer).  KINDS gives the kind
+/// of each segment (0 directory, 1 file, 2 symbol) and LABELS its UTF-8
+/// text; SEPARATOR is drawn between segments in SEPARATOR_COLOR
+/// (0xRRGGBB, 0 for the header line's foreground).  COUNT 0 removes the
+/// bar.
+///
+/// # Safety
+/// Must be called on the Emacs thread.  KINDS and LABELS must hold COUNT
+/// entries, and SEPARATOR must be NULL or a valid C string.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_set_breadcrumb_bar(
+    _handle: *mut NeomacsDisplay,
+    window_id: i64,
+    kinds: *const u8,
+    labels: *const *const c_char,
+    count: c_int,
+    separator: *const c_char,
+    separator_color: u32,
+) {
+    use crate::layout::breadcrumb_bar::{Segment, SegmentKind};
+
+    let mut segments = Vec::new();
+    if !kinds.is_null() && !labels.is_null() && count > 0 {
+        for i in 0..count as usize {
+            let label = *labels.add(i);
+            if label.is_null() {
+                continue;
+            }
+            segments.push(Segment {
+                kind: SegmentKind::from_u8(*kinds.add(i)),
+                label: CStr::from_ptr(label).to_string_lossy().into_owned(),
+            });
+        }
+    }
+    let separator = if separator.is_null() {
+        " > ".to_string()
+    } else {
+        CStr::from_ptr(separator).to_string_lossy().into_owned()
+    };
+    layout_engine_mut().breadcrumb_bars.set(window_id, segments, separator, separator_color);
+}
+
+/// Return the index of the breadcrumb bar segment of WINDOW_ID drawn at
+/// X pixels from the window's left edge, or -1 if there is none.
+///
+/// # Safety
+/// Must be called on the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_breadcrumb_bar_segment_at(
+    _handle: *mut NeomacsDisplay,
+    window_id: i64,
+    x: c_int,
+) -> c_int {
+    layout_engine_mut()
+        .breadcrumb_bars
+        .segment_at(window_id, x as f32)
+        .map_or(-1, |(i, _)| i as c_int)
+}
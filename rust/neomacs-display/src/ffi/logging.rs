@@ -0,0 +1,108 @@
+//! Log ring FFI
+//!
+//! Queries of the display engine's log ring for the in-editor log viewer.
+
+use super::*;
+
+use crate::logging::{self, LogFilter};
+
+/// A log record for C FFI.  The strings are owned by the record; free
+/// them with `neomacs_display_free_log_records`.
+#[repr(C)]
+pub struct NeomacsLogRecord {
+    pub seq: u64,
+    /// Milliseconds since the Unix epoch
+    pub time_ms: u64,
+    /// 1 = error, 2 = warn, 3 = info, 4 = debug, 5 = trace
+    pub level: c_int,
+    /// Module that logged the record
+    pub target: *mut c_char,
+    /// Active spans joined with '/', possibly empty
+    pub span: *mut c_char,
+    pub message: *mut c_char,
+}
+
+fn c_string(s: String) -> *mut c_char {
+    // Interior NULs cannot cross the FFI boundary
+    CString::new(s.replace('\0', " ")).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Copy the oldest MAX records after sequence number AFTER into OUT,
+/// keeping those at LEVEL (see `NeomacsLogRecord`) or more severe whose
+/// module contains MODULE (NULL for any).  Returns the number copied;
+/// call again with the last `seq` to page through the rest.
+///
+/// # Safety
+/// OUT must point to at least MAX records.  MODULE must be NULL or a
+/// valid C string.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_get_log_records(
+    after: u64,
+    level: c_int,
+    module: *const c_char,
+    out: *mut NeomacsLogRecord,
+    max: c_int,
+) -> c_int {
+    if out.is_null() || max <= 0 {
+        return 0;
+    }
+    let module = if module.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(module).to_string_lossy().into_owned()).filter(|m| !m.is_empty())
+    };
+    let filter = LogFilter { after, level: logging::level_filter_from_ffi(level), module };
+    let records = logging::records(&filter);
+    let mut count = 0;
+    for record in records.into_iter().take(max as usize) {
+        let time_ms = record.time
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        *out.add(count) = NeomacsLogRecord {
+            seq: record.seq,
+            time_ms,
+            level: record.level as c_int,
+            target: c_string(record.target),
+            span: c_string(record.span),
+            message: c_string(record.message),
+        };
+        count += 1;
+    }
+    count as c_int
+}
+
+/// Free the strings of COUNT records filled by
+/// `neomacs_display_get_log_records`.
+///
+/// # Safety
+/// RECORDS must hold COUNT records filled by
+/// `neomacs_display_get_log_records` and not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_free_log_records(records: *mut NeomacsLogRecord, count: c_int) {
+    if records.is_null() {
+        return;
+    }
+    for i in 0..count.max(0) as usize {
+        let record = &mut *records.add(i);
+        for s in [&mut record.target, &mut record.span, &mut record.message] {
+            if !s.is_null() {
+                drop(CString::from_raw(*s));
+                *s = ptr::null_mut();
+            }
+        }
+    }
+}
+
+/// Set the least severe level kept in the log ring: 0 = off, 1 = error,
+/// 2 = warn, 3 = info (the default), 4 = debug, 5 = trace.  This is
+/// independent of `RUST_LOG`, which filters what goes to stderr.
+#[no_mangle]
+pub extern "C" fn neomacs_display_set_log_level(level: c_int) {
+    logging::set_capture_level(logging::level_filter_from_ffi(level));
+}
+
+/// Drop all records from the log ring
+#[no_mangle]
+pub extern "C" fn neomacs_display_clear_log() {
+    logging::clear();
+}
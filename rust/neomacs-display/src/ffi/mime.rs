@@ -0,0 +1,161 @@
+//! MIME message FFI functions
+//!
+//! Like HTML rendering, parsing is a pure function of the message: C
+//! passes the raw message and gets back its headers and the parts a
+//! reader shows, which it frees with neomacs_mime_free().
+
+use super::*;
+
+use crate::core::mime::{self, Header, Part, Role};
+
+pub const NEOMACS_MIME_BODY: c_int = 0;
+pub const NEOMACS_MIME_ATTACHMENT: c_int = 1;
+pub const NEOMACS_MIME_MESSAGE: c_int = 2;
+pub const NEOMACS_MIME_HIDDEN: c_int = 3;
+
+/// A decoded header as seen from C.
+#[repr(C)]
+pub struct NeomacsMimeHeader {
+    pub name: *mut c_char,
+    pub value: *mut c_char,
+}
+
+/// A part of a message as seen from C.
+#[repr(C)]
+pub struct NeomacsMimePart {
+    /// NEOMACS_MIME_* role
+    pub role: c_int,
+    /// Lowercase type/subtype
+    pub mime_type: *mut c_char,
+    /// These are NULL when the part does not give them
+    pub charset: *mut c_char,
+    pub filename: *mut c_char,
+    pub content_id: *mut c_char,
+    pub description: *mut c_char,
+    /// The decoded body; for a forwarded message, the message itself
+    pub body: *mut u8,
+    pub body_len: usize,
+    /// The headers of a forwarded message, else none
+    pub headers: *mut NeomacsMimeHeader,
+    pub nheaders: c_int,
+}
+
+fn owned_cstr(text: &str) -> *mut c_char {
+    CString::new(text.replace('\0', " ")).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+fn optional_cstr(text: Option<&str>) -> *mut c_char {
+    text.map_or(ptr::null_mut(), owned_cstr)
+}
+
+unsafe fn free_cstr(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+fn headers_to_c(headers: &[Header]) -> (*mut NeomacsMimeHeader, c_int) {
+    let headers: Vec<NeomacsMimeHeader> = headers
+        .iter()
+        .map(|h| NeomacsMimeHeader { name: owned_cstr(&h.name), value: owned_cstr(&h.value) })
+        .collect();
+    let count = headers.len() as c_int;
+    (Box::into_raw(headers.into_boxed_slice()) as *mut NeomacsMimeHeader, count)
+}
+
+unsafe fn free_headers(headers: *mut NeomacsMimeHeader, count: c_int) {
+    if headers.is_null() {
+        return;
+    }
+    let headers = Box::from_raw(ptr::slice_from_raw_parts_mut(headers, count.max(0) as usize));
+    for header in headers.iter() {
+        free_cstr(header.name);
+        free_cstr(header.value);
+    }
+}
+
+fn part_to_c(role: Role, part: &Part) -> NeomacsMimePart {
+    let (headers, nheaders) = match (role, part.children.first()) {
+        (Role::Message, Some(message)) => headers_to_c(&message.headers),
+        _ => (ptr::null_mut(), 0),
+    };
+    let body_len = part.body.len();
+    NeomacsMimePart {
+        role: match role {
+            Role::Body => NEOMACS_MIME_BODY,
+            Role::Attachment => NEOMACS_MIME_ATTACHMENT,
+            Role::Message => NEOMACS_MIME_MESSAGE,
+            Role::Hidden => NEOMACS_MIME_HIDDEN,
+        },
+        mime_type: owned_cstr(&part.mime_type),
+        charset: optional_cstr(part.charset().as_deref()),
+        filename: optional_cstr(part.filename.as_deref()),
+        content_id: optional_cstr(part.content_id.as_deref()),
+        description: optional_cstr(part.description.as_deref()),
+        body: Box::into_raw(part.body.clone().into_boxed_slice()) as *mut u8,
+        body_len,
+        headers,
+        nheaders,
+    }
+}
+
+/// Parse the LEN bytes of the message at DATA.  Stores its headers in
+/// *HEADERS_OUT and their number in *NHEADERS_OUT, and the parts a reader
+/// shows, in order, in *PARTS_OUT; returns the number of parts, or -1 on
+/// bad arguments.  PREFER_HTML nonzero chooses HTML over plain text
+/// where the message offers both.
+///
+/// # Safety
+/// `data` must be NULL or point to `len` readable bytes; the out pointers
+/// must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_mime_parse(
+    data: *const u8,
+    len: usize,
+    prefer_html: c_int,
+    headers_out: *mut *mut NeomacsMimeHeader,
+    nheaders_out: *mut c_int,
+    parts_out: *mut *mut NeomacsMimePart,
+) -> c_int {
+    if headers_out.is_null() || nheaders_out.is_null() || parts_out.is_null() {
+        return -1;
+    }
+    let bytes = if data.is_null() || len == 0 { &[][..] } else { std::slice::from_raw_parts(data, len) };
+    let message = mime::parse(bytes);
+    let parts: Vec<NeomacsMimePart> = mime::layout(&message, prefer_html != 0)
+        .into_iter()
+        .map(|(role, part)| part_to_c(role, part))
+        .collect();
+    let count = parts.len() as c_int;
+    (*headers_out, *nheaders_out) = headers_to_c(&message.headers);
+    *parts_out = Box::into_raw(parts.into_boxed_slice()) as *mut NeomacsMimePart;
+    count
+}
+
+/// Free what neomacs_mime_parse() returned.
+///
+/// # Safety
+/// The arguments must be exactly what one call to `neomacs_mime_parse`
+/// stored and returned, each freed only once.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_mime_free(
+    headers: *mut NeomacsMimeHeader,
+    nheaders: c_int,
+    parts: *mut NeomacsMimePart,
+    nparts: c_int,
+) {
+    free_headers(headers, nheaders);
+    if parts.is_null() {
+        return;
+    }
+    let parts = Box::from_raw(ptr::slice_from_raw_parts_mut(parts, nparts.max(0) as usize));
+    for part in parts.iter() {
+        for s in [part.mime_type, part.charset, part.filename, part.content_id, part.description] {
+            free_cstr(s);
+        }
+        if !part.body.is_null() {
+            drop(Box::from_raw(ptr::slice_from_raw_parts_mut(part.body, part.body_len)));
+        }
+        free_headers(part.headers, part.nheaders);
+    }
+}
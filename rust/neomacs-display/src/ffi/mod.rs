@@ -12,6 +12,14 @@ pub mod layout;
 pub mod threaded;
 pub mod clipboard;
 pub mod itree;
+pub mod thumbnail;
+pub mod crypto;
+pub mod secrets;
+pub mod html;
+pub mod mime;
+pub mod print;
+pub mod accessibility;
+pub mod logging;
 
 use std::collections::HashMap;
 use std::ffi::{c_char, c_int, c_uint, c_double, c_void, CStr, CString};
@@ -39,6 +47,17 @@ use crate::backend::wgpu::{
     NEOMACS_EVENT_MENU_SELECTION,
     NEOMACS_EVENT_FILE_DROP,
     NEOMACS_EVENT_TERMINAL_TITLE_CHANGED,
+    NEOMACS_EVENT_MONITORS_CHANGED,
+    NEOMACS_EVENT_CHAR_PICKER_SELECTION,
+    NEOMACS_EVENT_TERMINAL_BELL,
+    NEOMACS_EVENT_PINCH_ZOOM,
+    NEOMACS_EVENT_COMMAND_PALETTE_SELECTION,
+    NEOMACS_EVENT_COLOR_PICKER_SELECTION,
+    NEOMACS_EVENT_AGENDA_TIMELINE_SELECTION,
+    NEOMACS_EVENT_GRAPH_VIEW_SELECTION,
+    NEOMACS_EVENT_DIR_VIEW_SELECTION,
+    NEOMACS_EVENT_PROCESS_MONITOR_SELECTION,
+    NEOMACS_EVENT_DATE_PICKER_SELECTION,
 };
 
 /// Resize callback function type for C FFI
@@ -57,12 +76,17 @@ pub(crate) static DROPPED_FILES: std::sync::Mutex<Vec<Vec<String>>> = std::sync:
 /// Each entry is (terminal_id, new_title).
 pub(crate) static TERMINAL_TITLES: std::sync::Mutex<Vec<(u32, String)>> = std::sync::Mutex::new(Vec::new());
 
+/// File chosen in the directory view (populated by drain_input, consumed by C)
+pub(crate) static DIR_VIEW_CHOICE: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+
 use crate::backend::tty::TtyBackend;
 use crate::core::types::{Color, Rect};
 use crate::core::scene::{Scene, WindowScene, CursorState, SceneCursorStyle};
 use crate::core::animation::AnimationManager;
 use crate::core::frame_glyphs::{FrameGlyphBuffer, FrameGlyph};
 use crate::core::face::{Face, FaceAttributes, UnderlineStyle, BoxType};
+use crate::core::chart::{ChartKind, ChartSeries, ChartSpec};
+use crate::core::image_edit::{AnnotationShape, ImageAnnotation, ImageEdits};
 
 /// Opaque handle to the display engine
 pub struct NeomacsDisplay {
@@ -209,8 +233,8 @@ pub(crate) static TERMINAL_ID_COUNTER: std::sync::atomic::AtomicU32 = std::sync:
 // Threaded State
 // ============================================================================
 
-use crate::thread_comm::{EmacsComms, EffectUpdater, InputEvent, PopupMenuItem, RenderCommand, ThreadComms};
-use crate::render_thread::{RenderThread, SharedImageDimensions, SharedMonitorInfo};
+use crate::thread_comm::{AgendaTimelineItem, CharPickerEntry, CommandPaletteEntry, DatePickerSpec, EmacsComms, EffectUpdater, GraphNode, InputEvent, PopupMenuItem, RenderCommand, ThreadComms};
+use crate::render_thread::{RenderThread, SharedImageDimensions, SharedMemoryStats, SharedMonitorInfo, SharedTransitionSnapshot};
 
 /// Global state for threaded mode
 pub(crate) static mut THREADED_STATE: Option<ThreadedState> = None;
@@ -224,6 +248,10 @@ pub(crate) struct ThreadedState {
     pub(crate) image_dimensions: Arc<Mutex<HashMap<u32, (u32, u32)>>>,
     /// Shared storage for monitor info from winit
     pub(crate) shared_monitors: SharedMonitorInfo,
+    /// Renderer memory usage, refreshed by the render thread each frame
+    pub(crate) memory_stats: SharedMemoryStats,
+    /// Whether the render thread holds a buffer transition snapshot
+    pub(crate) transition_snapshot: SharedTransitionSnapshot,
     /// Shared terminal handles for cross-thread text extraction
     #[cfg(feature = "neo-term")]
     pub(crate) shared_terminals: crate::terminal::SharedTerminals,
@@ -0,0 +1,114 @@
+//! Printing FFI functions
+//!
+//! neomacs_print_buffer lays out a buffer region on pages with the layout
+//! engine and writes it as PDF.  Sending the file to a printer is left to
+//! Lisp, which hands it to the desktop print portal.
+
+use super::*;
+
+use crate::layout::emacs_ffi::{EmacsFrame, EmacsWindow};
+use crate::layout::host::EmacsHost;
+use crate::layout::print::{self, PaperSize, PrintDocument, PrintOptions};
+use crate::layout::LayoutEngine;
+
+unsafe fn opt_str(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(s).to_string_lossy().into_owned())
+}
+
+/// Print the text between FROM and TO of the buffer WINDOW shows, laid
+/// out as WINDOW of FRAME would show it, to the PDF file PDF_PATH.
+///
+/// PAPER names the paper size ("a4", "letter", "legal"); if it is NULL,
+/// PAPER_WIDTH by PAPER_HEIGHT points is used when both are positive,
+/// and A4 otherwise.  HEADER and FOOTER are templates (%b buffer, %f
+/// file, %p page, %P page count); NULL or "" omits them.
+///
+/// Returns the number of pages, or -1 on error.
+///
+/// # Safety
+/// Must be called on the Emacs thread with valid frame and window
+/// pointers.  String arguments must be NULL or NUL-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_print_buffer(
+    frame: EmacsFrame,
+    window: EmacsWindow,
+    from: i64,
+    to: i64,
+    paper: *const c_char,
+    paper_width: c_double,
+    paper_height: c_double,
+    font_size: c_double,
+    line_numbers: c_int,
+    color: c_int,
+    wrap: c_int,
+    header: *const c_char,
+    footer: *const c_char,
+    title: *const c_char,
+    file_name: *const c_char,
+    pdf_path: *const c_char,
+) -> c_int {
+    if frame.is_null() || window.is_null() {
+        return -1;
+    }
+    let Some(pdf_path) = opt_str(pdf_path) else {
+        return -1;
+    };
+    let paper = match opt_str(paper) {
+        Some(name) => match PaperSize::by_name(&name) {
+            Some(size) => size,
+            None => {
+                warn!("neomacs_print_buffer: unknown paper size {:?}", name);
+                return -1;
+            }
+        },
+        None if paper_width > 0.0 && paper_height > 0.0 => {
+            PaperSize { width: paper_width as f32, height: paper_height as f32 }
+        }
+        None => PaperSize::A4,
+    };
+    let opts = PrintOptions {
+        paper,
+        font_size: if font_size > 0.0 { font_size as f32 } else { 9.0 },
+        line_numbers: line_numbers != 0,
+        header: opt_str(header).unwrap_or_default(),
+        footer: opt_str(footer).unwrap_or_default(),
+        color: color != 0,
+        wrap: wrap != 0,
+        ..Default::default()
+    };
+    let mut doc = PrintDocument::new(
+        &opt_str(title).unwrap_or_default(),
+        &opt_str(file_name).unwrap_or_default(),
+    );
+
+    // Pages are laid out by an engine of their own, so that the screen's
+    // caches and animation state are left alone; it shapes like the
+    // screen's
+    let mut engine = LayoutEngine::new();
+    {
+        let screen = super::layout::layout_engine_mut();
+        engine.use_cosmic_metrics = screen.use_cosmic_metrics;
+        engine.ligatures_enabled = screen.ligatures_enabled;
+    }
+
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        if !print::paginate(&mut engine, &EmacsHost, frame, window, from, to, &opts, &mut doc) {
+            return Err(std::io::Error::other("the frame does not show the window"));
+        }
+        print::save_pdf(&doc, &opts, engine.font_metrics_mut(), std::path::Path::new(&pdf_path))
+    }));
+    match result {
+        Ok(Ok(pages)) => pages as c_int,
+        Ok(Err(err)) => {
+            warn!("neomacs_print_buffer: cannot print to {}: {}", pdf_path, err);
+            -1
+        }
+        Err(_) => {
+            error!("neomacs_print_buffer: panic while printing");
+            -1
+        }
+    }
+}
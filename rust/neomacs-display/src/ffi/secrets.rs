@@ -0,0 +1,181 @@
+//! Secret store FFI functions
+//!
+//! One `Secrets`, holding the index of the system keyring, serves all
+//! frames.  Calls are synchronous, as auth-source expects; the keyring
+//! may ask to be unlocked meanwhile.
+
+use super::*;
+
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use crate::core::secrets::{self, SecretEntry, SecretQuery, Secrets, SecretsError};
+
+static SECRETS: Lazy<Mutex<Secrets>> = Lazy::new(|| Mutex::new(Secrets::system()));
+
+unsafe fn opt_string(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok().map(str::to_string)
+}
+
+unsafe fn query(host: *const c_char, user: *const c_char, port: *const c_char) -> SecretQuery {
+    SecretQuery { host: opt_string(host), user: opt_string(user), port: opt_string(port) }
+}
+
+unsafe fn entry(host: *const c_char, user: *const c_char, port: *const c_char) -> SecretEntry {
+    let field = |s| opt_string(s).unwrap_or_default();
+    SecretEntry::new(&field(host), &field(user), &field(port))
+}
+
+unsafe fn put_string(out: *mut *mut c_char, text: String) {
+    if !out.is_null() {
+        *out = CString::new(text).map_or(ptr::null_mut(), CString::into_raw);
+    }
+}
+
+unsafe fn fail(message_out: *mut *mut c_char, error: SecretsError) -> c_int {
+    put_string(message_out, error.to_string());
+    -1
+}
+
+/// Whether this build can use the system keyring.
+#[no_mangle]
+pub extern "C" fn neomacs_secrets_available() -> c_int {
+    secrets::available() as c_int
+}
+
+/// Find up to MAX entries matching HOST, USER and PORT, each NULL for
+/// any.  Stores them in *RESULTS_OUT as "HOST\tUSER\tPORT\n" lines and
+/// returns how many, or -1 with a message in *MESSAGE_OUT.
+///
+/// # Safety
+/// `host`, `user` and `port` must each be NULL or a valid NUL-terminated
+/// string; `results_out` and `message_out` must each be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_secrets_search(
+    host: *const c_char,
+    user: *const c_char,
+    port: *const c_char,
+    max: c_int,
+    results_out: *mut *mut c_char,
+    message_out: *mut *mut c_char,
+) -> c_int {
+    if !results_out.is_null() {
+        *results_out = ptr::null_mut();
+    }
+    if !message_out.is_null() {
+        *message_out = ptr::null_mut();
+    }
+    let query = query(host, user, port);
+    match SECRETS.lock().unwrap().search(&query, max.max(0) as usize) {
+        Ok(found) => {
+            let text: String = found.iter().map(|e| format!("{}\t{}\t{}\n", e.host, e.user, e.port)).collect();
+            put_string(results_out, text);
+            found.len() as c_int
+        }
+        Err(e) => fail(message_out, e),
+    }
+}
+
+/// The secret of the entry stored under exactly HOST, USER and PORT
+/// (NULL for blank), in *SECRET_OUT.  Returns 1 if found, 0 if not, -1
+/// with a message in *MESSAGE_OUT.
+///
+/// # Safety
+/// `host`, `user` and `port` must each be NULL or a valid NUL-terminated
+/// string; `secret_out` and `message_out` must each be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_secrets_get(
+    host: *const c_char,
+    user: *const c_char,
+    port: *const c_char,
+    secret_out: *mut *mut c_char,
+    message_out: *mut *mut c_char,
+) -> c_int {
+    if !secret_out.is_null() {
+        *secret_out = ptr::null_mut();
+    }
+    if !message_out.is_null() {
+        *message_out = ptr::null_mut();
+    }
+    match SECRETS.lock().unwrap().secret(&entry(host, user, port)) {
+        Ok(Some(secret)) => {
+            put_string(secret_out, secret);
+            1
+        }
+        Ok(None) => 0,
+        Err(e) => fail(message_out, e),
+    }
+}
+
+/// Store SECRET under HOST, USER and PORT (NULL for blank).  Returns 0,
+/// or -1 with a message in *MESSAGE_OUT.
+///
+/// # Safety
+/// The string arguments must each be NULL or a valid NUL-terminated
+/// string; `message_out` must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_secrets_store(
+    host: *const c_char,
+    user: *const c_char,
+    port: *const c_char,
+    secret: *const c_char,
+    message_out: *mut *mut c_char,
+) -> c_int {
+    if !message_out.is_null() {
+        *message_out = ptr::null_mut();
+    }
+    let secret = opt_string(secret).unwrap_or_default();
+    match SECRETS.lock().unwrap().store(entry(host, user, port), &secret) {
+        Ok(()) => 0,
+        Err(e) => fail(message_out, e),
+    }
+}
+
+/// Delete the entries stored under exactly HOST, USER and PORT, each
+/// NULL for any.  Returns how many, or -1 with a message in
+/// *MESSAGE_OUT.
+///
+/// # Safety
+/// `host`, `user` and `port` must each be NULL or a valid NUL-terminated
+/// string; `message_out` must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_secrets_delete(
+    host: *const c_char,
+    user: *const c_char,
+    port: *const c_char,
+    message_out: *mut *mut c_char,
+) -> c_int {
+    if !message_out.is_null() {
+        *message_out = ptr::null_mut();
+    }
+    match SECRETS.lock().unwrap().delete(&query(host, user, port)) {
+        Ok(count) => count as c_int,
+        Err(e) => fail(message_out, e),
+    }
+}
+
+/// Read the index from the keyring again on next use.
+#[no_mangle]
+pub extern "C" fn neomacs_secrets_reload() {
+    SECRETS.lock().unwrap().reload();
+}
+
+/// Clear and free a string returned by the functions above.
+///
+/// # Safety
+/// `s` must be NULL or a string returned by one of the functions above,
+/// not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_secrets_free_string(s: *mut c_char) {
+    if s.is_null() {
+        return;
+    }
+    let mut bytes = CString::from_raw(s).into_bytes();
+    for byte in bytes.iter_mut() {
+        ptr::write_volatile(byte, 0);
+    }
+}
@@ -25,7 +25,7 @@ pub unsafe extern "C" fn neomacs_display_init_threaded(
     height: u32,
     title: *const c_char,
 ) -> c_int {
-    let _ = env_logger::try_init();
+    crate::logging::init();
     log::info!("neomacs_display_init_threaded: {}x{}", width, height);
 
     let title = if title.is_null() {
@@ -52,6 +52,12 @@ pub unsafe extern "C" fn neomacs_display_init_threaded(
     // Create shared monitor info storage (with condvar for sync)
     let shared_monitors: SharedMonitorInfo = Arc::new((Mutex::new(Vec::new()), std::sync::Condvar::new()));
 
+    // Create shared renderer memory stats
+    let memory_stats: SharedMemoryStats = Arc::new(Mutex::new(Default::default()));
+
+    // Create shared buffer transition snapshot flag
+    let transition_snapshot: SharedTransitionSnapshot = Default::default();
+
     // Create shared terminal handles for cross-thread text extraction
     #[cfg(feature = "neo-term")]
     let shared_terminals: crate::terminal::SharedTerminals =
@@ -65,6 +71,8 @@ pub unsafe extern "C" fn neomacs_display_init_threaded(
         title,
         Arc::clone(&image_dimensions),
         Arc::clone(&shared_monitors),
+        Arc::clone(&memory_stats),
+        Arc::clone(&transition_snapshot),
         #[cfg(feature = "neo-term")]
         Arc::clone(&shared_terminals),
     );
@@ -101,6 +109,8 @@ pub unsafe extern "C" fn neomacs_display_init_threaded(
         display_handle: display_ptr,
         image_dimensions,
         shared_monitors,
+        memory_stats,
+        transition_snapshot,
         #[cfg(feature = "neo-term")]
         shared_terminals,
     });
@@ -122,6 +132,15 @@ pub struct NeomacsMonitorInfo {
     pub scale: c_double,
     pub width_mm: c_int,
     pub height_mm: c_int,
+    /// Work area: the monitor minus panels and docks
+    pub work_x: c_int,
+    pub work_y: c_int,
+    pub work_width: c_int,
+    pub work_height: c_int,
+    /// Refresh rate in millihertz (0 = unknown)
+    pub refresh_mhz: u32,
+    /// 1 for the primary monitor
+    pub primary: c_int,
 }
 
 /// Wait for monitor info to be available (with timeout).
@@ -191,6 +210,12 @@ pub unsafe extern "C" fn neomacs_display_get_monitor_info(
             (*info).scale = m.scale;
             (*info).width_mm = m.width_mm as c_int;
             (*info).height_mm = m.height_mm as c_int;
+            (*info).work_x = m.work_x as c_int;
+            (*info).work_y = m.work_y as c_int;
+            (*info).work_width = m.work_width as c_int;
+            (*info).work_height = m.work_height as c_int;
+            (*info).refresh_mhz = m.refresh_mhz;
+            (*info).primary = m.primary as c_int;
             1
         }
         Err(_) => 0,
@@ -230,6 +255,297 @@ pub unsafe extern "C" fn neomacs_display_get_monitor_name(
     }
 }
 
+/// Place a WIDTH x HEIGHT popup on SIDE (see `PopupSide::from_ffi`) of
+/// ANCHOR, GAP pixels away, for a frame at FRAME in desktop coordinates:
+/// inside the frame and the work area of the monitor holding the
+/// anchor, and off AVOID (may be null), flipping or shifting as needed.
+/// Rectangles are 4 ints (x, y, width, height); ANCHOR, AVOID and the
+/// position stored in OUT (2 ints) are relative to the frame.  Returns
+/// the side used.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_place_popup(
+    frame: *const c_int,
+    anchor: *const c_int,
+    width: c_int,
+    height: c_int,
+    side: c_int,
+    gap: c_int,
+    avoid: *const c_int,
+    out: *mut c_int,
+) -> c_int {
+    use crate::core::popup_placement::{frame_areas, place_popup, PopupSide};
+    use crate::core::types::Rect;
+    if frame.is_null() || anchor.is_null() || out.is_null() {
+        return side;
+    }
+    let rect = |r: *const c_int| {
+        let r = std::slice::from_raw_parts(r, 4);
+        Rect::new(r[0] as f32, r[1] as f32, r[2] as f32, r[3] as f32)
+    };
+    let frame = rect(frame);
+    let monitors = threaded_state()
+        .and_then(|state| state.shared_monitors.0.lock().ok().map(|m| m.clone()))
+        .unwrap_or_default();
+    let avoid: Vec<Rect> = if avoid.is_null() { Vec::new() } else { vec![rect(avoid)] };
+    let at = place_popup(
+        rect(anchor),
+        width as f32,
+        height as f32,
+        PopupSide::from_ffi(side),
+        gap as f32,
+        &frame_areas(frame, &monitors),
+        &avoid,
+    );
+    *out = at.x as c_int;
+    *out.add(1) = at.y as c_int;
+    at.side.to_ffi()
+}
+
+// ============================================================================
+// Synchronous Render Queries
+// ============================================================================
+
+/// Send QUERY to the render thread and wait up to TIMEOUT_MS for the
+/// answer (<= 0 for the default).  None on timeout or failure.
+unsafe fn render_query(
+    query: crate::query::RenderQuery,
+    timeout_ms: c_int,
+) -> Option<crate::query::QueryResult> {
+    let state = threaded_state()?;
+    let timeout = if timeout_ms > 0 {
+        std::time::Duration::from_millis(timeout_ms as u64)
+    } else {
+        crate::query::DEFAULT_QUERY_TIMEOUT
+    };
+    let comms = &state.emacs_comms;
+    match comms.queries.query(&comms.cmd_tx, query, timeout) {
+        Ok(result) => Some(result),
+        Err(e) => {
+            log::debug!("render query failed: {}", e);
+            None
+        }
+    }
+}
+
+/// Measure TEXT as the render thread shapes it in FAMILY (NULL or empty
+/// for monospace) at WEIGHT and FONT_SIZE, italic if ITALIC is nonzero.
+/// Stores the extent in logical pixels in WIDTH and HEIGHT.
+/// Returns 1 on success, 0 if the render thread did not answer within
+/// TIMEOUT_MS.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_query_text_extent(
+    text: *const c_char,
+    family: *const c_char,
+    weight: c_int,
+    italic: c_int,
+    font_size: f32,
+    timeout_ms: c_int,
+    width: *mut f32,
+    height: *mut f32,
+) -> c_int {
+    if text.is_null() || width.is_null() || height.is_null() {
+        return 0;
+    }
+    let family = if family.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(family).to_string_lossy().into_owned()
+    };
+    let query = crate::query::RenderQuery::TextExtent {
+        text: CStr::from_ptr(text).to_string_lossy().into_owned(),
+        family,
+        weight: weight.clamp(100, 900) as u16,
+        italic: italic != 0,
+        font_size,
+    };
+    match render_query(query, timeout_ms) {
+        Some(crate::query::QueryResult::TextExtent { width: w, height: h }) => {
+            *width = w;
+            *height = h;
+            1
+        }
+        _ => 0,
+    }
+}
+
+/// Ask the render thread for the size of image IMAGE_ID, waiting up to
+/// TIMEOUT_MS.  Returns 1 and stores the size on success, 0 if the image
+/// is not decoded yet or the render thread did not answer.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_query_image_size(
+    image_id: u32,
+    timeout_ms: c_int,
+    width: *mut c_int,
+    height: *mut c_int,
+) -> c_int {
+    if width.is_null() || height.is_null() {
+        return 0;
+    }
+    match render_query(crate::query::RenderQuery::ImageSize { id: image_id }, timeout_ms) {
+        Some(crate::query::QueryResult::ImageSize(Some((w, h)))) => {
+            *width = w as c_int;
+            *height = h as c_int;
+            1
+        }
+        _ => 0,
+    }
+}
+
+/// Ask the render thread for the current monitors, waiting up to
+/// TIMEOUT_MS, and update the list read by neomacs_display_get_monitor_info.
+/// Returns the number of monitors, or -1 if the render thread did not
+/// answer (the previous list is kept).
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_refresh_monitors(timeout_ms: c_int) -> c_int {
+    let state = match threaded_state() {
+        Some(s) => s,
+        None => return -1,
+    };
+    match render_query(crate::query::RenderQuery::Monitors, timeout_ms) {
+        Some(crate::query::QueryResult::Monitors(monitors)) => {
+            let count = monitors.len() as c_int;
+            let (ref lock, _) = *state.shared_monitors;
+            if let Ok(mut shared) = lock.lock() {
+                *shared = monitors;
+            }
+            count
+        }
+        _ => -1,
+    }
+}
+
+/// Ask the render thread for a JSON description of the frames, glyphs,
+/// faces, cursors and animations it is drawing, waiting up to
+/// TIMEOUT_MS.  Returns the JSON (free with `neomacs_display_free_string`),
+/// or NULL if the render thread did not answer.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_dump_scene(timeout_ms: c_int) -> *mut c_char {
+    match render_query(crate::query::RenderQuery::SceneDump, timeout_ms) {
+        Some(crate::query::QueryResult::SceneDump(json)) => {
+            CString::new(json).map_or(std::ptr::null_mut(), CString::into_raw)
+        }
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Ask the render thread how long each part of its initialization took,
+/// as a table ending at the first frame presented, waiting up to
+/// TIMEOUT_MS.  Returns the table (free with `neomacs_display_free_string`),
+/// or NULL before the first frame or if the render thread did not answer.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_startup_report(timeout_ms: c_int) -> *mut c_char {
+    match render_query(crate::query::RenderQuery::StartupReport, timeout_ms) {
+        Some(crate::query::QueryResult::StartupReport(report)) => {
+            CString::new(report).map_or(std::ptr::null_mut(), CString::into_raw)
+        }
+        _ => std::ptr::null_mut(),
+    }
+}
+
+// ============================================================================
+// Renderer Memory Stats
+// ============================================================================
+
+/// Renderer memory usage for C FFI (bytes, counts, and budgets in bytes)
+#[repr(C)]
+pub struct NeomacsMemoryStats {
+    pub image_bytes: u64,
+    pub image_count: u64,
+    pub image_evictions: u64,
+    pub glyph_bytes: u64,
+    pub glyph_count: u64,
+    pub glyph_evictions: u64,
+    pub video_bytes: u64,
+    pub snapshot_bytes: u64,
+    pub snapshot_evictions: u64,
+    pub staging_bytes: u64,
+    pub image_budget: u64,
+    pub glyph_budget: u64,
+    pub snapshot_budget: u64,
+    pub staging_budget: u64,
+}
+
+/// Get the memory usage of the renderer's caches as of the last frame.
+/// Returns 1 on success, 0 on failure.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_get_memory_stats(
+    out: *mut NeomacsMemoryStats,
+) -> c_int {
+    if out.is_null() {
+        return 0;
+    }
+    let state = match threaded_state() {
+        Some(s) => s,
+        None => return 0,
+    };
+    let stats = *state.memory_stats.lock().unwrap_or_else(|e| e.into_inner());
+    *out = NeomacsMemoryStats {
+        image_bytes: stats.image_bytes as u64,
+        image_count: stats.image_count as u64,
+        image_evictions: stats.image_evictions,
+        glyph_bytes: stats.glyph_bytes as u64,
+        glyph_count: stats.glyph_count as u64,
+        glyph_evictions: stats.glyph_evictions,
+        video_bytes: stats.video_bytes as u64,
+        snapshot_bytes: stats.snapshot_bytes as u64,
+        snapshot_evictions: stats.snapshot_evictions,
+        staging_bytes: stats.staging_bytes as u64,
+        image_budget: stats.budget.images as u64,
+        glyph_budget: stats.budget.glyphs as u64,
+        snapshot_budget: stats.budget.snapshots as u64,
+        staging_budget: stats.budget.staging as u64,
+    };
+    1
+}
+
+// ============================================================================
+// Render Command Bus Stats
+// ============================================================================
+
+/// Render command bus counters for C FFI (see `CommandStatsSnapshot`)
+#[repr(C)]
+pub struct NeomacsCommandStats {
+    pub sent: u64,
+    pub dropped: u64,
+    pub coalesced: u64,
+    pub processed: u64,
+    pub batches: u64,
+    pub max_batch: u64,
+    pub pending: u64,
+    pub capacity: u64,
+    /// 1 if the command channel is at least three quarters full
+    pub congested: c_int,
+}
+
+/// Get the render command bus counters, so Emacs can send fewer
+/// commands while the renderer falls behind.
+/// Returns 1 on success, 0 on failure.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_get_command_stats(
+    out: *mut NeomacsCommandStats,
+) -> c_int {
+    if out.is_null() {
+        return 0;
+    }
+    let state = match threaded_state() {
+        Some(s) => s,
+        None => return 0,
+    };
+    let stats = state.emacs_comms.cmd_tx.stats();
+    *out = NeomacsCommandStats {
+        sent: stats.sent,
+        dropped: stats.dropped,
+        coalesced: stats.coalesced,
+        processed: stats.processed,
+        batches: stats.batches,
+        max_batch: stats.max_batch,
+        pending: stats.pending,
+        capacity: stats.capacity,
+        congested: stats.is_congested() as c_int,
+    };
+    1
+}
+
 // ============================================================================
 // Event Draining
 // ============================================================================
@@ -304,6 +620,7 @@ pub unsafe extern "C" fn neomacs_display_drain_input(
                         y,
                         modifiers,
                         pixel_precise,
+                        lines,
                         target_frame_id,
                     } => {
                         out.kind = NEOMACS_EVENT_SCROLL;
@@ -313,6 +630,14 @@ pub unsafe extern "C" fn neomacs_display_drain_input(
                         out.scroll_delta_y = delta_y;
                         out.modifiers = modifiers;
                         out.pixel_precise = if pixel_precise { 1 } else { 0 };
+                        out.scroll_lines = lines;
+                        out.target_frame_id = target_frame_id;
+                    }
+                    InputEvent::PinchZoom { x, y, scale, target_frame_id } => {
+                        out.kind = NEOMACS_EVENT_PINCH_ZOOM;
+                        out.x = x as i32;
+                        out.y = y as i32;
+                        out.scroll_delta_x = scale;  // reuse scroll field for the scale
                         out.target_frame_id = target_frame_id;
                     }
                     InputEvent::WindowResize { width, height, emacs_frame_id } => {
@@ -333,6 +658,9 @@ pub unsafe extern "C" fn neomacs_display_drain_input(
                         };
                         out.target_frame_id = emacs_frame_id;
                     }
+                    InputEvent::MonitorsChanged => {
+                        out.kind = NEOMACS_EVENT_MONITORS_CHANGED;
+                    }
                     InputEvent::ImageDimensionsReady { id, width, height } => {
                         out.kind = NEOMACS_EVENT_IMAGE_DIMENSIONS_READY;
                         out.window_id = id;  // Reuse window_id field for image_id
@@ -355,6 +683,11 @@ pub unsafe extern "C" fn neomacs_display_drain_input(
                         out.keysym = id;  // reuse keysym field for terminal ID
                     }
                     #[cfg(feature = "neo-term")]
+                    InputEvent::TerminalBell { id } => {
+                        out.kind = NEOMACS_EVENT_TERMINAL_BELL;
+                        out.keysym = id;
+                    }
+                    #[cfg(feature = "neo-term")]
                     InputEvent::TerminalTitleChanged { id, title } => {
                         out.kind = NEOMACS_EVENT_TERMINAL_TITLE_CHANGED;
                         out.keysym = id;
@@ -367,6 +700,42 @@ pub unsafe extern "C" fn neomacs_display_drain_input(
                         out.x = index;
                         // y field unused, set to 0
                     }
+                    InputEvent::CharPickerSelection { index } => {
+                        out.kind = NEOMACS_EVENT_CHAR_PICKER_SELECTION;
+                        out.x = index;
+                    }
+                    InputEvent::CommandPaletteSelection { index } => {
+                        out.kind = NEOMACS_EVENT_COMMAND_PALETTE_SELECTION;
+                        out.x = index;
+                    }
+                    InputEvent::ColorPickerSelection { color } => {
+                        out.kind = NEOMACS_EVENT_COLOR_PICKER_SELECTION;
+                        out.x = color;
+                    }
+                    InputEvent::AgendaTimelineSelection { index } => {
+                        out.kind = NEOMACS_EVENT_AGENDA_TIMELINE_SELECTION;
+                        out.x = index;
+                    }
+                    InputEvent::GraphViewSelection { index } => {
+                        out.kind = NEOMACS_EVENT_GRAPH_VIEW_SELECTION;
+                        out.x = index;
+                    }
+                    InputEvent::DirViewSelection { path } => {
+                        out.kind = NEOMACS_EVENT_DIR_VIEW_SELECTION;
+                        out.x = if path.is_some() { 1 } else { -1 };
+                        // Store the path for C to retrieve
+                        if let Ok(mut choice) = DIR_VIEW_CHOICE.lock() {
+                            *choice = path;
+                        }
+                    }
+                    InputEvent::ProcessMonitorSelection { pid } => {
+                        out.kind = NEOMACS_EVENT_PROCESS_MONITOR_SELECTION;
+                        out.x = pid;
+                    }
+                    InputEvent::DatePickerSelection { date } => {
+                        out.kind = NEOMACS_EVENT_DATE_PICKER_SELECTION;
+                        out.x = date;
+                    }
                     InputEvent::FileDrop { paths, x, y } => {
                         out.kind = NEOMACS_EVENT_FILE_DROP;
                         out.x = x as i32;
@@ -454,6 +823,19 @@ pub unsafe extern "C" fn neomacs_display_get_terminal_title(
     }
 }
 
+/// Take the file chosen in the directory view.  Returns a C string that
+/// must be freed with `neomacs_display_free_dropped_path`, or NULL if
+/// none was chosen.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_take_dir_view_choice() -> *mut c_char {
+    let path = match DIR_VIEW_CHOICE.lock() {
+        Ok(mut choice) => choice.take(),
+        Err(_) => None,
+    };
+    path.and_then(|p| std::ffi::CString::new(p).ok())
+        .map_or(std::ptr::null_mut(), std::ffi::CString::into_raw)
+}
+
 // ============================================================================
 // Frame / Command Sending
 // ============================================================================
@@ -472,9 +854,8 @@ pub unsafe extern "C" fn neomacs_display_send_frame(handle: *mut NeomacsDisplay)
         None => return,
     };
 
-    // Clone frame glyphs and send to render thread
-    let frame = display.frame_glyphs.clone();
-    let _ = state.emacs_comms.frame_tx.try_send(frame);
+    // Publish a copy of the frame glyphs to the render thread
+    state.emacs_comms.scenes.publish(&display.frame_glyphs);
 }
 
 /// Send command to render thread
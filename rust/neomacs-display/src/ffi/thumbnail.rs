@@ -0,0 +1,94 @@
+//! Thumbnail FFI functions
+//!
+//! The thumbnailer does not need a display connection, so it is a global
+//! shared by all frames.
+
+use super::*;
+
+use once_cell::sync::Lazy;
+
+use crate::backend::wgpu::thumbnail::{ThumbnailSize, ThumbnailState, Thumbnailer};
+
+static THUMBNAILER: Lazy<Thumbnailer> = Lazy::new(Thumbnailer::new);
+
+/// Thumbnail is ready
+pub const NEOMACS_THUMBNAIL_READY: c_int = 1;
+/// Thumbnail is being generated
+pub const NEOMACS_THUMBNAIL_PENDING: c_int = 0;
+/// File cannot be thumbnailed
+pub const NEOMACS_THUMBNAIL_FAILED: c_int = -1;
+/// No request has been made for this file
+pub const NEOMACS_THUMBNAIL_UNKNOWN: c_int = -2;
+
+unsafe fn source_path(path: *const c_char) -> Option<std::path::PathBuf> {
+    if path.is_null() {
+        return None;
+    }
+    CStr::from_ptr(path).to_str().ok().map(std::path::PathBuf::from)
+}
+
+fn state_code(state: Option<&ThumbnailState>) -> c_int {
+    match state {
+        Some(ThumbnailState::Ready(_)) => NEOMACS_THUMBNAIL_READY,
+        Some(ThumbnailState::Pending) => NEOMACS_THUMBNAIL_PENDING,
+        Some(ThumbnailState::Failed) => NEOMACS_THUMBNAIL_FAILED,
+        None => NEOMACS_THUMBNAIL_UNKNOWN,
+    }
+}
+
+/// Request a thumbnail of the file at PATH, at least SIZE pixels on its
+/// longer edge.  PATH must be absolute.
+///
+/// Returns a newly allocated C string with the thumbnail file name if a
+/// valid thumbnail exists (free it with neomacs_thumbnail_free_path()).
+/// Otherwise queues generation and returns NULL; poll with
+/// neomacs_thumbnail_status().
+///
+/// # Safety
+/// `path` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_thumbnail_request(path: *const c_char, size: c_int) -> *mut c_char {
+    let Some(source) = source_path(path) else {
+        return ptr::null_mut();
+    };
+    let size = ThumbnailSize::for_pixels(size.max(0) as u32);
+    match THUMBNAILER.request(&source, size) {
+        ThumbnailState::Ready(thumb) => match CString::new(thumb.to_string_lossy().into_owned()) {
+            Ok(c_string) => c_string.into_raw(),
+            Err(_) => ptr::null_mut(),
+        },
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Status of an earlier request for PATH at SIZE: NEOMACS_THUMBNAIL_READY,
+/// _PENDING, _FAILED, or _UNKNOWN if it was never requested.
+///
+/// # Safety
+/// `path` must be NULL or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_thumbnail_status(path: *const c_char, size: c_int) -> c_int {
+    let Some(source) = source_path(path) else {
+        return NEOMACS_THUMBNAIL_UNKNOWN;
+    };
+    let size = ThumbnailSize::for_pixels(size.max(0) as u32);
+    state_code(THUMBNAILER.state(&source, size).as_ref())
+}
+
+/// Number of thumbnails still being generated.
+#[no_mangle]
+pub extern "C" fn neomacs_thumbnail_pending_count() -> c_int {
+    THUMBNAILER.pending_count() as c_int
+}
+
+/// Free a string returned by neomacs_thumbnail_request().
+///
+/// # Safety
+/// `path` must be NULL or a string returned by
+/// `neomacs_thumbnail_request`, not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_thumbnail_free_path(path: *mut c_char) {
+    if !path.is_null() {
+        drop(CString::from_raw(path));
+    }
+}
@@ -75,6 +75,145 @@ pub unsafe extern "C" fn neomacs_display_terminal_write(
     }
 }
 
+/// Send a key event to a terminal.
+///
+/// `key` is a single character or an Emacs key name (`return`, `up`,
+/// `f5`, ...); `modifiers` are kitty modifier bits (1=Shift, 2=Alt,
+/// 4=Ctrl, 8=Super, 16=Hyper, 32=Meta); `kind`: 0=press, 1=repeat,
+/// 2=release.  Returns 0 if the key name is unknown.
+#[cfg(feature = "neo-term")]
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_terminal_send_key(
+    terminal_id: u32,
+    key: *const c_char,
+    modifiers: u8,
+    kind: u8,
+) -> c_int {
+    use crate::terminal::keyboard::{KeyEventKind, TermKey};
+    if key.is_null() {
+        return 0;
+    }
+    let Some(key) = std::ffi::CStr::from_ptr(key).to_str().ok().and_then(TermKey::from_name) else {
+        return 0;
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let cmd = RenderCommand::TerminalKey {
+            id: terminal_id,
+            key,
+            mods: modifiers,
+            kind: KeyEventKind::from_ffi(kind),
+        };
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+    1
+}
+
+/// Report a mouse event to the program in a terminal.
+///
+/// `button`: 0=none (motion), 1-3=left/middle/right, 4-7=wheel
+/// up/down/left/right; `kind`: 0=press, 1=release, 2=motion; `col` and
+/// `row` are 0-based cells; `modifiers` are kitty modifier bits as for
+/// `neomacs_display_terminal_send_key`.  Nothing is sent unless the
+/// program enabled mouse tracking.
+#[cfg(feature = "neo-term")]
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_terminal_send_mouse(
+    terminal_id: u32,
+    button: u8,
+    kind: u8,
+    col: u32,
+    row: u32,
+    modifiers: u8,
+) {
+    use crate::terminal::mouse::{MouseButton, MouseEventKind};
+    if let Some(ref state) = THREADED_STATE {
+        let cmd = RenderCommand::TerminalMouse {
+            id: terminal_id,
+            button: MouseButton::from_ffi(button),
+            kind: MouseEventKind::from_ffi(kind),
+            col,
+            row,
+            mods: modifiers,
+        };
+        let _ = state.emacs_comms.cmd_tx.try_send(cmd);
+    }
+}
+
+/// Get the mouse tracking mode the program in a terminal enabled.
+///
+/// Returns 0=off, 1=X10, 2=normal, 3=button-event, 4=any-event.
+#[cfg(feature = "neo-term")]
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_terminal_mouse_mode(terminal_id: u32) -> c_int {
+    if let Some(ref state) = THREADED_STATE {
+        if let Ok(shared) = state.shared_terminals.lock() {
+            if let Some(shared_term) = shared.get(&terminal_id) {
+                return shared_term.mouse_tracking().to_ffi();
+            }
+        }
+    }
+    0
+}
+
+/// Set the terminal with keyboard focus (0 for none).
+///
+/// Emacs only sees key presses, so the render thread reports key
+/// releases to this terminal itself when its program asked for them.
+#[cfg(feature = "neo-term")]
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_terminal_focus(terminal_id: u32) {
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::TerminalFocus { id: terminal_id });
+    }
+}
+
+/// Flash a terminal's view briefly, as a visual bell.
+#[cfg(feature = "neo-term")]
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_terminal_flash(terminal_id: u32) {
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::TerminalFlash { id: terminal_id });
+    }
+}
+
+/// Set a terminal's colors.
+///
+/// `colors` points to `ncolors` (at most 16 used) ANSI palette entries,
+/// `fg` and `bg` are the default colors, all as 0xRRGGBB; 0xFFFFFFFF
+/// keeps the built-in color.  Colors the program sets itself with OSC
+/// 4/10/11 still take precedence.
+#[cfg(feature = "neo-term")]
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_terminal_set_palette(
+    terminal_id: u32,
+    colors: *const u32,
+    ncolors: c_int,
+    fg: u32,
+    bg: u32,
+) {
+    use crate::terminal::colors::TerminalPalette;
+    let to_color = |pixel: u32| {
+        (pixel != u32::MAX).then(|| {
+            Color::from_u8((pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8, 255)
+        })
+    };
+    let ansi: Vec<Option<Color>> = if colors.is_null() || ncolors <= 0 {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(colors, ncolors.min(16) as usize)
+            .iter()
+            .map(|&c| to_color(c))
+            .collect()
+    };
+    if let Some(ref state) = THREADED_STATE {
+        let palette = TerminalPalette::new(&ansi, to_color(fg), to_color(bg));
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::TerminalSetPalette {
+            id: terminal_id,
+            palette: Box::new(palette),
+        });
+    }
+}
+
 /// Resize a terminal.
 #[cfg(feature = "neo-term")]
 #[no_mangle]
@@ -137,9 +276,9 @@ pub unsafe extern "C" fn neomacs_display_terminal_get_text(
     {
         if let Some(ref state) = THREADED_STATE {
             if let Ok(shared) = state.shared_terminals.lock() {
-                if let Some(term_arc) = shared.get(&terminal_id) {
+                if let Some(shared_term) = shared.get(&terminal_id) {
                     use alacritty_terminal::grid::Dimensions;
-                    let term = term_arc.lock();
+                    let term = shared_term.term.lock();
                     let grid = term.grid();
                     let cols = grid.columns();
                     let rows = grid.screen_lines();
@@ -160,6 +299,40 @@ pub unsafe extern "C" fn neomacs_display_terminal_get_text(
     std::ptr::null_mut()
 }
 
+/// Scroll a terminal's view so that the Nth shell prompt after its top
+/// line (before it if `n` is negative) is at the top.  Needs a shell
+/// that marks its prompts (OSC 133).
+#[cfg(feature = "neo-term")]
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_terminal_jump_to_prompt(terminal_id: u32, n: c_int) {
+    if let Some(ref state) = THREADED_STATE {
+        let _ = state.emacs_comms.cmd_tx.try_send(RenderCommand::TerminalJumpToPrompt {
+            id: terminal_id,
+            n,
+        });
+    }
+}
+
+/// Get the output of the last finished command in a terminal, as
+/// marked by the shell (OSC 133).
+///
+/// Returns a malloc'd C string (caller must free with `free()`).
+/// Returns NULL if there is no such output.
+#[cfg(feature = "neo-term")]
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_terminal_last_output(
+    terminal_id: u32,
+) -> *mut c_char {
+    if let Some(ref state) = THREADED_STATE {
+        if let Ok(shared) = state.shared_terminals.lock() {
+            if let Some(text) = shared.get(&terminal_id).and_then(|t| t.last_output()) {
+                return CString::new(text).map_or(std::ptr::null_mut(), CString::into_raw);
+            }
+        }
+    }
+    std::ptr::null_mut()
+}
+
 /// Callback type for webkit new window requests
 pub type WebKitNewWindowCallback = extern "C" fn(u32, *const c_char, *const c_char) -> bool;
 
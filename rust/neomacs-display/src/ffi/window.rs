@@ -145,8 +145,7 @@ pub unsafe extern "C" fn neomacs_display_end_frame_window(
         // Matrix-based full-frame rendering: always send the complete frame.
         // The buffer was cleared at begin_frame and rebuilt by the matrix walker,
         // so it always contains the complete visible state.
-        let frame = display.frame_glyphs.clone();
-        let _ = state.emacs_comms.frame_tx.try_send(frame);
+        state.emacs_comms.scenes.publish(&display.frame_glyphs);
     } else if let Some(ref mut backend) = display.winit_backend {
         backend.end_frame_for_window(
             window_id,
@@ -194,6 +193,54 @@ pub unsafe extern "C" fn neomacs_display_create_os_window(
     }
 }
 
+/// Create a popup window for a top-level Emacs frame: undecorated,
+/// always on top and centered on the focused monitor.  Its frames are
+/// drawn without the main window's transitions and effects.
+/// `neomacs_display_destroy_os_window` removes it.
+///
+/// # Safety
+/// Must be called from the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_create_popup_window(
+    _handle: *mut NeomacsDisplay,
+    emacs_frame_id: u64,
+    width: c_int,
+    height: c_int,
+) {
+    if let Some(state) = (*std::ptr::addr_of!(super::THREADED_STATE)).as_ref() {
+        let _ = state.emacs_comms.cmd_tx.try_send(
+            RenderCommand::CreatePopupWindow {
+                emacs_frame_id,
+                width: width.max(1) as u32,
+                height: height.max(1) as u32,
+            }
+        );
+    }
+}
+
+/// Resize the popup window of a top-level Emacs frame, keeping it
+/// centered on its monitor.
+///
+/// # Safety
+/// Must be called from the Emacs thread.
+#[no_mangle]
+pub unsafe extern "C" fn neomacs_display_resize_popup_window(
+    _handle: *mut NeomacsDisplay,
+    emacs_frame_id: u64,
+    width: c_int,
+    height: c_int,
+) {
+    if let Some(state) = (*std::ptr::addr_of!(super::THREADED_STATE)).as_ref() {
+        let _ = state.emacs_comms.cmd_tx.try_send(
+            RenderCommand::ResizePopupWindow {
+                emacs_frame_id,
+                width: width.max(1) as u32,
+                height: height.max(1) as u32,
+            }
+        );
+    }
+}
+
 /// Destroy an OS window for a top-level Emacs frame.
 ///
 /// # Safety
@@ -0,0 +1,533 @@
+//! Accessibility tree for screen readers.
+//!
+//! After each redisplay the tree is rebuilt from the frame's windows: one
+//! node per window, holding the visible text and the caret for buffer
+//! windows, the mode line as a status bar, plus any popup menu or tooltip.
+//! Comparing the new tree with the previous one yields AT-SPI style events
+//! (focus, caret, text insert/delete, name changes, popups).
+//!
+//! Events are handed to an [`AccessibilityBridge`].  The built-in
+//! [`EventQueue`] bridge buffers them for Lisp (speech packages drain them
+//! with `neomacs-accessibility-events`), and another sends the tree to the
+//! render thread, whose AccessKit adapter exports it to screen readers
+//! over AT-SPI.
+
+use std::collections::VecDeque;
+
+use super::emacs_ffi::*;
+use super::buffer_snapshot::BufferSnapshot;
+
+/// Maximum characters of window text exposed per window.
+const MAX_TEXT_CHARS: i64 = 8192;
+
+/// Maximum events buffered before the oldest are dropped.
+const MAX_QUEUED_EVENTS: usize = 512;
+
+/// Node role, named after the AT-SPI role it maps to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessibleRole {
+    Frame,
+    /// A window showing a buffer (ATSPI_ROLE_TEXT).
+    Text,
+    /// The minibuffer / echo area (ATSPI_ROLE_ENTRY).
+    Entry,
+    /// A mode line (ATSPI_ROLE_STATUS_BAR).
+    StatusBar,
+    PopupMenu,
+    MenuItem,
+    ToolTip,
+}
+
+impl AccessibleRole {
+    /// AT-SPI role name, as returned by `GetRoleName`.
+    pub fn name(self) -> &'static str {
+        match self {
+            AccessibleRole::Frame => "frame",
+            AccessibleRole::Text => "text",
+            AccessibleRole::Entry => "entry",
+            AccessibleRole::StatusBar => "status bar",
+            AccessibleRole::PopupMenu => "popup menu",
+            AccessibleRole::MenuItem => "menu item",
+            AccessibleRole::ToolTip => "tool tip",
+        }
+    }
+}
+
+/// Frame-relative bounds in pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Bounds {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// One node of the accessibility tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibleNode {
+    /// Stable identifier: the window pointer for window nodes, derived
+    /// identifiers for their children.
+    pub id: u64,
+    pub role: AccessibleRole,
+    pub name: String,
+    /// Exposed text (visible portion of the buffer for text nodes).
+    pub text: String,
+    /// Buffer position of the first character of `text`.
+    pub text_start: i64,
+    /// Caret offset into `text` in characters, if the node has one.
+    pub caret: Option<usize>,
+    pub bounds: Bounds,
+    pub focused: bool,
+    pub enabled: bool,
+    pub children: Vec<AccessibleNode>,
+}
+
+impl AccessibleNode {
+    pub fn new(id: u64, role: AccessibleRole, name: impl Into<String>) -> Self {
+        Self {
+            id,
+            role,
+            name: name.into(),
+            text: String::new(),
+            text_start: 0,
+            caret: None,
+            bounds: Bounds::default(),
+            focused: false,
+            enabled: true,
+            children: Vec::new(),
+        }
+    }
+
+    /// Depth-first search for the node with ID.
+    pub fn find(&self, id: u64) -> Option<&AccessibleNode> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(id))
+    }
+
+    /// The focused node, if any.
+    pub fn focused(&self) -> Option<&AccessibleNode> {
+        if self.focused {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.focused())
+    }
+
+    fn walk<'a>(&'a self, out: &mut Vec<&'a AccessibleNode>) {
+        out.push(self);
+        for child in &self.children {
+            child.walk(out);
+        }
+    }
+}
+
+/// A change notification, mirroring the AT-SPI event it is sent as.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccessibilityEvent {
+    /// object:state-changed:focused
+    FocusChanged { id: u64 },
+    /// object:text-caret-moved, with the caret as a buffer position.
+    CaretMoved { id: u64, position: i64 },
+    /// object:text-changed:insert, at a buffer position.
+    TextInserted { id: u64, position: i64, text: String },
+    /// object:text-changed:delete
+    TextDeleted { id: u64, position: i64, text: String },
+    /// object:property-change:accessible-name
+    NameChanged { id: u64, name: String },
+    /// object:children-changed:add
+    NodeAdded { id: u64, role: AccessibleRole, name: String },
+    /// object:children-changed:remove
+    NodeRemoved { id: u64 },
+}
+
+impl AccessibilityEvent {
+    /// Node the event refers to.
+    pub fn id(&self) -> u64 {
+        match self {
+            AccessibilityEvent::FocusChanged { id }
+            | AccessibilityEvent::CaretMoved { id, .. }
+            | AccessibilityEvent::TextInserted { id, .. }
+            | AccessibilityEvent::TextDeleted { id, .. }
+            | AccessibilityEvent::NameChanged { id, .. }
+            | AccessibilityEvent::NodeAdded { id, .. }
+            | AccessibilityEvent::NodeRemoved { id } => *id,
+        }
+    }
+}
+
+/// Compute the events that turn OLD into NEW.
+pub fn diff_trees(old: Option<&AccessibleNode>, new: &AccessibleNode) -> Vec<AccessibilityEvent> {
+    let mut events = Vec::new();
+    let mut new_nodes = Vec::new();
+    new.walk(&mut new_nodes);
+    let mut old_nodes = Vec::new();
+    if let Some(old) = old {
+        old.walk(&mut old_nodes);
+    }
+
+    for node in &old_nodes {
+        if new.find(node.id).is_none() {
+            events.push(AccessibilityEvent::NodeRemoved { id: node.id });
+        }
+    }
+    for node in &new_nodes {
+        let Some(prev) = old.and_then(|old| old.find(node.id)) else {
+            if old.is_some() {
+                events.push(AccessibilityEvent::NodeAdded {
+                    id: node.id,
+                    role: node.role,
+                    name: node.name.clone(),
+                });
+            }
+            continue;
+        };
+        if prev.name != node.name {
+            events.push(AccessibilityEvent::NameChanged { id: node.id, name: node.name.clone() });
+        }
+        if prev.text_start == node.text_start {
+            diff_text(node.id, node.text_start, &prev.text, &node.text, &mut events);
+        }
+        if let Some(caret) = node.caret {
+            if prev.caret != Some(caret) || prev.text_start != node.text_start {
+                events.push(AccessibilityEvent::CaretMoved { id: node.id, position: node.text_start + caret as i64 });
+            }
+        }
+    }
+
+    let old_focus = old.and_then(|old| old.focused()).map(|n| n.id);
+    if let Some(focus) = new.focused() {
+        if old_focus != Some(focus.id) {
+            events.push(AccessibilityEvent::FocusChanged { id: focus.id });
+        }
+    }
+    events
+}
+
+/// Emit delete/insert events for the edit that turns OLD into NEW, found
+/// by trimming their common prefix and suffix.
+fn diff_text(id: u64, start: i64, old: &str, new: &str, events: &mut Vec<AccessibilityEvent>) {
+    if old == new {
+        return;
+    }
+    let old_chars: Vec<char> = old.chars().collect();
+    let new_chars: Vec<char> = new.chars().collect();
+    let prefix = old_chars.iter().zip(&new_chars).take_while(|(a, b)| a == b).count();
+    let max_suffix = old_chars.len().min(new_chars.len()) - prefix;
+    let suffix = old_chars
+        .iter()
+        .rev()
+        .zip(new_chars.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let position = start + prefix as i64;
+    let deleted: String = old_chars[prefix..old_chars.len() - suffix].iter().collect();
+    let inserted: String = new_chars[prefix..new_chars.len() - suffix].iter().collect();
+    if !deleted.is_empty() {
+        events.push(AccessibilityEvent::TextDeleted { id, position, text: deleted });
+    }
+    if !inserted.is_empty() {
+        events.push(AccessibilityEvent::TextInserted { id, position, text: inserted });
+    }
+}
+
+/// Receiver of tree updates.
+pub trait AccessibilityBridge: Send {
+    /// Called after every update with the new tree and the events that
+    /// led to it.
+    fn publish(&mut self, tree: &AccessibleNode, events: &[AccessibilityEvent]);
+}
+
+/// Bridge that buffers events until they are drained.
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    events: VecDeque<AccessibilityEvent>,
+}
+
+impl EventQueue {
+    pub fn pop(&mut self) -> Option<AccessibilityEvent> {
+        self.events.pop_front()
+    }
+
+    pub fn drain(&mut self) -> Vec<AccessibilityEvent> {
+        self.events.drain(..).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl AccessibilityBridge for EventQueue {
+    fn publish(&mut self, _tree: &AccessibleNode, events: &[AccessibilityEvent]) {
+        self.events.extend(events.iter().cloned());
+        while self.events.len() > MAX_QUEUED_EVENTS {
+            self.events.pop_front();
+        }
+    }
+}
+
+/// Popup shown on top of the frame (menu or tooltip).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Popup {
+    Menu { title: Option<String>, items: Vec<(String, bool)>, bounds: Bounds },
+    ToolTip { text: String, bounds: Bounds },
+}
+
+/// Holds the current tree and the popups reported by the display code.
+pub struct AccessibilityState {
+    tree: Option<AccessibleNode>,
+    popup: Option<Popup>,
+    pub queue: EventQueue,
+    bridges: Vec<Box<dyn AccessibilityBridge>>,
+}
+
+/// Identifier of the popup node; windows are identified by their pointer,
+/// which is never this small.
+const POPUP_ID: u64 = 1;
+
+impl Default for AccessibilityState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AccessibilityState {
+    pub fn new() -> Self {
+        Self { tree: None, popup: None, queue: EventQueue::default(), bridges: Vec::new() }
+    }
+
+    pub fn tree(&self) -> Option<&AccessibleNode> {
+        self.tree.as_ref()
+    }
+
+    /// Register an additional bridge (e.g. an AT-SPI D-Bus exporter).
+    pub fn add_bridge(&mut self, bridge: Box<dyn AccessibilityBridge>) {
+        self.bridges.push(bridge);
+    }
+
+    pub fn set_popup(&mut self, popup: Option<Popup>) {
+        self.popup = popup;
+    }
+
+    /// Replace the tree with ROOT (popup nodes are added here), diff it
+    /// against the previous tree and publish the resulting events.
+    pub fn update(&mut self, mut root: AccessibleNode) {
+        if let Some(popup) = &self.popup {
+            let node = popup_node(popup);
+            for node in root.children.iter_mut() {
+                clear_focus(node);
+            }
+            root.children.push(node);
+        }
+        let events = diff_trees(self.tree.as_ref(), &root);
+        self.queue.publish(&root, &events);
+        for bridge in &mut self.bridges {
+            bridge.publish(&root, &events);
+        }
+        self.tree = Some(root);
+    }
+}
+
+fn clear_focus(node: &mut AccessibleNode) {
+    node.focused = false;
+    for child in &mut node.children {
+        clear_focus(child);
+    }
+}
+
+fn popup_node(popup: &Popup) -> AccessibleNode {
+    match popup {
+        Popup::Menu { title, items, bounds } => {
+            let mut menu = AccessibleNode::new(POPUP_ID, AccessibleRole::PopupMenu, title.clone().unwrap_or_default());
+            menu.bounds = *bounds;
+            menu.focused = true;
+            for (i, (label, enabled)) in items.iter().enumerate() {
+                let mut item = AccessibleNode::new(POPUP_ID + 1 + i as u64, AccessibleRole::MenuItem, label.clone());
+                item.enabled = *enabled;
+                menu.children.push(item);
+            }
+            menu
+        }
+        Popup::ToolTip { text, bounds } => {
+            let mut tip = AccessibleNode::new(POPUP_ID, AccessibleRole::ToolTip, text.clone());
+            tip.text = text.clone();
+            tip.bounds = *bounds;
+            tip
+        }
+    }
+}
+
+// ============================================================================
+// Reading the frame
+// ============================================================================
+
+/// Copy the text of BUFFER between FROM and TO (character positions).
+unsafe fn buffer_text(buffer: EmacsBuffer, from: i64, to: i64) -> String {
+    let mut snapshot = BufferSnapshot::capture(buffer, Vec::new());
+    String::from_utf8_lossy(snapshot.load_text(from, to)).into_owned()
+}
+
+unsafe fn mode_line_text(window: EmacsWindow, frame: EmacsFrame) -> Option<String> {
+    let mut buf = vec![0u8; 1024];
+    let mut face = FaceDataFFI::default();
+    let len = neomacs_layout_mode_line_text(window, frame, buf.as_mut_ptr(), buf.len() as i64, &mut face);
+    if len <= 0 {
+        return None;
+    }
+    buf.truncate(len as usize);
+    Some(String::from_utf8_lossy(&buf).trim_end().to_string())
+}
+
+/// Build the accessibility tree of FRAME named TITLE.
+///
+/// # Safety
+/// Must be called on the Emacs thread with a valid frame, after layout.
+pub unsafe fn collect_frame(frame: EmacsFrame, title: &str) -> AccessibleNode {
+    let mut root = AccessibleNode::new(frame as u64, AccessibleRole::Frame, title);
+    let count = super::emacs_types::frame_window_count(frame as *const std::ffi::c_void);
+    for i in 0..count {
+        let mut wp = WindowParamsFFI::default();
+        if neomacs_layout_get_window_params(frame, i, &mut wp) != 0 || wp.buffer_ptr.is_null() {
+            continue;
+        }
+        let role = if wp.is_minibuffer != 0 { AccessibleRole::Entry } else { AccessibleRole::Text };
+        let mut node = AccessibleNode::new(wp.window_id as u64, role, "");
+        node.bounds = Bounds { x: wp.x, y: wp.y, width: wp.width, height: wp.height };
+        node.focused = wp.selected != 0;
+
+        // Expose the visible portion, extended to include point.
+        let start = wp.window_start.max(wp.buffer_begv).min(wp.point);
+        let end = if wp.window_end > start { wp.window_end.max(wp.point) } else { wp.buffer_zv };
+        let end = end.min(wp.buffer_zv).min(start + MAX_TEXT_CHARS);
+        node.text = buffer_text(wp.buffer_ptr, start, end);
+        node.text_start = start;
+        node.caret = Some((wp.point - start).clamp(0, end - start) as usize);
+
+        if wp.is_minibuffer == 0 {
+            if let Some(mode_line) = mode_line_text(wp.window_ptr, frame) {
+                node.name = mode_line.split_whitespace().collect::<Vec<_>>().join(" ");
+                let mut status = AccessibleNode::new(!(wp.window_id as u64), AccessibleRole::StatusBar, node.name.clone());
+                status.text = mode_line;
+                status.bounds = Bounds {
+                    x: wp.x,
+                    y: wp.text_y + wp.text_height,
+                    width: wp.width,
+                    height: (wp.y + wp.height - wp.text_y - wp.text_height).max(0.0),
+                };
+                node.children.push(status);
+            }
+        }
+        root.children.push(node);
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_node(id: u64, text: &str, caret: usize) -> AccessibleNode {
+        let mut node = AccessibleNode::new(id, AccessibleRole::Text, "buf");
+        node.text = text.to_string();
+        node.text_start = 1;
+        node.caret = Some(caret);
+        node
+    }
+
+    fn frame(children: Vec<AccessibleNode>) -> AccessibleNode {
+        let mut root = AccessibleNode::new(100, AccessibleRole::Frame, "emacs");
+        root.children = children;
+        root
+    }
+
+    #[test]
+    fn initial_tree_reports_only_focus() {
+        let mut win = text_node(10, "hello", 0);
+        win.focused = true;
+        let events = diff_trees(None, &frame(vec![win]));
+        assert_eq!(events, vec![AccessibilityEvent::FocusChanged { id: 10 }]);
+    }
+
+    #[test]
+    fn typing_reports_insert_and_caret() {
+        let old = frame(vec![text_node(10, "helo", 3)]);
+        let new = frame(vec![text_node(10, "hello", 4)]);
+        let events = diff_trees(Some(&old), &new);
+        assert_eq!(
+            events,
+            vec![
+                AccessibilityEvent::TextInserted { id: 10, position: 4, text: "l".into() },
+                AccessibilityEvent::CaretMoved { id: 10, position: 5 },
+            ]
+        );
+    }
+
+    #[test]
+    fn replacement_reports_delete_then_insert() {
+        let old = frame(vec![text_node(10, "a foo b", 0)]);
+        let new = frame(vec![text_node(10, "a bar b", 0)]);
+        let events = diff_trees(Some(&old), &new);
+        assert_eq!(
+            events,
+            vec![
+                AccessibilityEvent::TextDeleted { id: 10, position: 3, text: "foo".into() },
+                AccessibilityEvent::TextInserted { id: 10, position: 3, text: "bar".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn window_changes_report_children_and_focus() {
+        let mut a = text_node(10, "x", 0);
+        a.focused = true;
+        let old = frame(vec![a.clone()]);
+        a.focused = false;
+        let mut b = text_node(20, "y", 0);
+        b.focused = true;
+        let events = diff_trees(Some(&old), &frame(vec![a, b]));
+        assert!(events.contains(&AccessibilityEvent::NodeAdded { id: 20, role: AccessibleRole::Text, name: "buf".into() }));
+        assert_eq!(events.last(), Some(&AccessibilityEvent::FocusChanged { id: 20 }));
+
+        let events = diff_trees(Some(&old), &frame(vec![]));
+        assert_eq!(events, vec![AccessibilityEvent::NodeRemoved { id: 10 }]);
+    }
+
+    #[test]
+    fn popup_takes_focus() {
+        let mut state = AccessibilityState::new();
+        let mut win = text_node(10, "x", 0);
+        win.focused = true;
+        state.update(frame(vec![win.clone()]));
+        state.queue.drain();
+
+        state.set_popup(Some(Popup::Menu {
+            title: Some("Edit".into()),
+            items: vec![("Copy".into(), true), ("Paste".into(), false)],
+            bounds: Bounds::default(),
+        }));
+        state.update(frame(vec![win]));
+        let events = state.queue.drain();
+        assert!(events.contains(&AccessibilityEvent::FocusChanged { id: POPUP_ID }));
+        let menu = state.tree().unwrap().find(POPUP_ID).unwrap();
+        assert_eq!(menu.children.len(), 2);
+        assert!(!menu.children[1].enabled);
+    }
+
+    #[test]
+    fn queue_drops_oldest_events() {
+        let mut queue = EventQueue::default();
+        let root = frame(vec![]);
+        let events: Vec<_> = (0..MAX_QUEUED_EVENTS as u64 + 5)
+            .map(|id| AccessibilityEvent::NodeRemoved { id })
+            .collect();
+        queue.publish(&root, &events);
+        assert_eq!(queue.len(), MAX_QUEUED_EVENTS);
+        assert_eq!(queue.drain()[0].id(), 5);
+    }
+}
@@ -0,0 +1,297 @@
+//! Annotations (margin notes) attached to buffer positions.
+//!
+//! Each annotation anchors a short note (optional icon, text, colors) to a
+//! character of a buffer.  Anchors live in a per-buffer `ItreeTree`, so
+//! they move with the text as the buffer is edited (see
+//! `AnnotationStore::adjust`) and visible ones are found with an interval
+//! query over the window's displayed range.
+//!
+//! After a window is laid out, its annotations are drawn either in the
+//! right margin or as cards floating over the right side of the text
+//! area.  `stack_boxes` resolves collisions so notes on nearby lines stack
+//! instead of overlapping.
+
+use std::collections::HashMap;
+
+use crate::core::itree::{ItreeOrder, ItreeTree, NodeId};
+
+/// Where an annotation is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationStyle {
+    /// In the window's right margin (falls back to a card if it has none).
+    Margin,
+    /// A card floating over the right edge of the text area.
+    Card,
+}
+
+impl AnnotationStyle {
+    /// Decode the FFI representation: 1 = card, anything else = margin.
+    pub fn from_ffi(style: i32) -> Self {
+        if style == 1 { Self::Card } else { Self::Margin }
+    }
+}
+
+/// A note attached to a buffer position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    /// Short icon drawn before the text (e.g. an emoji); may be empty.
+    pub icon: String,
+    /// Note text; newlines start new lines.
+    pub text: String,
+    /// Foreground color (sRGB pixel).
+    pub fg: u32,
+    /// Background color (sRGB pixel).
+    pub bg: u32,
+    pub style: AnnotationStyle,
+}
+
+impl Annotation {
+    /// Lines of the note wrapped to COLS columns, icon first.
+    pub fn lines(&self, cols: usize) -> Vec<String> {
+        let cols = cols.max(1);
+        let full = if self.icon.is_empty() {
+            self.text.clone()
+        } else {
+            format!("{} {}", self.icon, self.text)
+        };
+        let mut lines = Vec::new();
+        for para in full.split('\n') {
+            let chars: Vec<char> = para.chars().collect();
+            if chars.is_empty() {
+                lines.push(String::new());
+                continue;
+            }
+            for chunk in chars.chunks(cols) {
+                lines.push(chunk.iter().collect());
+            }
+        }
+        lines
+    }
+}
+
+/// Annotations of one buffer.
+#[derive(Default)]
+struct BufferAnnotations {
+    tree: ItreeTree,
+    entries: HashMap<u32, (NodeId, Annotation)>,
+}
+
+/// All annotations, keyed by buffer (same id as `WindowParams::buffer_id`).
+#[derive(Default)]
+pub struct AnnotationStore {
+    buffers: HashMap<u64, BufferAnnotations>,
+    owners: HashMap<u32, u64>,
+    next_id: u32,
+}
+
+impl AnnotationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether BUFFER_ID has any annotations.
+    pub fn has_buffer(&self, buffer_id: u64) -> bool {
+        self.buffers.get(&buffer_id).is_some_and(|b| !b.entries.is_empty())
+    }
+
+    /// Attach ANNOTATION to the character at POS of BUFFER_ID.
+    /// Returns its id (never 0).
+    pub fn add(&mut self, buffer_id: u64, pos: i64, annotation: Annotation) -> u32 {
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+        let id = self.next_id;
+        let buf = self.buffers.entry(buffer_id).or_default();
+        // Text inserted before the anchor pushes it forward; text inserted
+        // after it does not extend it.
+        let node = buf.tree.alloc_node(true, false, id as u64);
+        buf.tree.insert(node, pos, pos + 1);
+        buf.entries.insert(id, (node, annotation));
+        self.owners.insert(id, buffer_id);
+        id
+    }
+
+    /// Remove annotation ID.  Returns false if it does not exist.
+    pub fn remove(&mut self, id: u32) -> bool {
+        let Some(buffer_id) = self.owners.remove(&id) else {
+            return false;
+        };
+        let Some(buf) = self.buffers.get_mut(&buffer_id) else {
+            return false;
+        };
+        if let Some((node, _)) = buf.entries.remove(&id) {
+            buf.tree.remove(node);
+            buf.tree.free_node(node);
+        }
+        if buf.entries.is_empty() {
+            self.buffers.remove(&buffer_id);
+        }
+        true
+    }
+
+    /// Remove all annotations of BUFFER_ID.
+    pub fn clear_buffer(&mut self, buffer_id: u64) {
+        if let Some(buf) = self.buffers.remove(&buffer_id) {
+            for id in buf.entries.keys() {
+                self.owners.remove(id);
+            }
+        }
+    }
+
+    /// Move anchors of BUFFER_ID after an edit at POS that deleted
+    /// DELETED characters and inserted INSERTED characters.  An anchor
+    /// whose character was deleted stays at the edit position.
+    pub fn adjust(&mut self, buffer_id: u64, pos: i64, inserted: i64, deleted: i64) {
+        let Some(buf) = self.buffers.get_mut(&buffer_id) else {
+            return;
+        };
+        if deleted > 0 {
+            buf.tree.delete_gap(pos, deleted);
+            // Give collapsed anchors their one character back
+            let mut collapsed = Vec::new();
+            let mut iter = buf.tree.iterator_start(pos, pos + 1, ItreeOrder::Ascending);
+            while let Some(node) = buf.tree.iterator_next(&mut iter) {
+                collapsed.push(node);
+            }
+            for node in collapsed {
+                let begin = buf.tree.node_begin(node);
+                if buf.tree.node_end(node) <= begin {
+                    buf.tree.node_set_region(node, begin, begin + 1);
+                }
+            }
+        }
+        if inserted > 0 {
+            buf.tree.insert_gap(pos, inserted, false);
+        }
+    }
+
+    /// Annotations of BUFFER_ID anchored in [BEGIN, END), ordered by
+    /// position, as (position, id, annotation).
+    pub fn in_range(&mut self, buffer_id: u64, begin: i64, end: i64) -> Vec<(i64, u32, &Annotation)> {
+        let Some(buf) = self.buffers.get_mut(&buffer_id) else {
+            return Vec::new();
+        };
+        let mut found = Vec::new();
+        let mut iter = buf.tree.iterator_start(begin, end, ItreeOrder::Ascending);
+        while let Some(node) = buf.tree.iterator_next(&mut iter) {
+            found.push(node);
+        }
+        let mut result: Vec<(i64, u32, &Annotation)> = Vec::with_capacity(found.len());
+        for node in found {
+            let pos = buf.tree.node_begin(node);
+            let id = buf.tree.node(node).data as u32;
+            result.push((pos, id, &buf.entries[&id].1));
+        }
+        result.sort_by_key(|&(pos, id, _)| (pos, id));
+        result
+    }
+}
+
+/// Vertical placement of annotation boxes.  BOXES are (anchor_y, height)
+/// sorted by anchor_y; each box is placed as close to its anchor as
+/// possible without overlapping the previous one (keeping GAP pixels
+/// between them), then the stack is pushed back up if it runs past
+/// BOTTOM.  Returns the top y of each box.
+pub fn stack_boxes(boxes: &[(f32, f32)], top: f32, bottom: f32, gap: f32) -> Vec<f32> {
+    let mut ys: Vec<f32> = Vec::with_capacity(boxes.len());
+    let mut next_free = top;
+    for &(anchor, height) in boxes {
+        let y = anchor.max(next_free);
+        ys.push(y);
+        next_free = y + height + gap;
+    }
+    // Pull overflowing boxes back up, from the bottom
+    let mut limit = bottom;
+    for (i, &(_, height)) in boxes.iter().enumerate().rev() {
+        if ys[i] + height > limit {
+            ys[i] = (limit - height).max(top);
+        }
+        limit = ys[i] - gap;
+    }
+    ys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(text: &str) -> Annotation {
+        Annotation {
+            icon: String::new(),
+            text: text.to_string(),
+            fg: 0xffffff,
+            bg: 0x202020,
+            style: AnnotationStyle::Margin,
+        }
+    }
+
+    #[test]
+    fn add_and_query_range() {
+        let mut store = AnnotationStore::new();
+        let a = store.add(1, 10, note("a"));
+        let b = store.add(1, 50, note("b"));
+        store.add(2, 10, note("other buffer"));
+        assert_ne!(a, b);
+        let found = store.in_range(1, 0, 40);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, 10);
+        assert_eq!(found[0].1, a);
+        assert_eq!(found[0].2.text, "a");
+        assert_eq!(store.in_range(1, 0, 100).len(), 2);
+    }
+
+    #[test]
+    fn anchors_follow_edits() {
+        let mut store = AnnotationStore::new();
+        store.add(1, 10, note("a"));
+        // Insert 5 chars before the anchor
+        store.adjust(1, 3, 5, 0);
+        assert_eq!(store.in_range(1, 0, 100)[0].0, 15);
+        // Insert after the anchor: unchanged
+        store.adjust(1, 20, 4, 0);
+        assert_eq!(store.in_range(1, 0, 100)[0].0, 15);
+        // Delete 10 chars before it
+        store.adjust(1, 2, 0, 10);
+        assert_eq!(store.in_range(1, 0, 100)[0].0, 5);
+    }
+
+    #[test]
+    fn deleting_anchor_char_keeps_annotation() {
+        let mut store = AnnotationStore::new();
+        store.add(1, 10, note("a"));
+        store.adjust(1, 8, 0, 5);
+        let found = store.in_range(1, 8, 9);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, 8);
+    }
+
+    #[test]
+    fn remove_and_clear() {
+        let mut store = AnnotationStore::new();
+        let a = store.add(1, 10, note("a"));
+        store.add(1, 20, note("b"));
+        assert!(store.remove(a));
+        assert!(!store.remove(a));
+        assert_eq!(store.in_range(1, 0, 100).len(), 1);
+        store.clear_buffer(1);
+        assert!(!store.has_buffer(1));
+        assert!(store.in_range(1, 0, 100).is_empty());
+    }
+
+    #[test]
+    fn lines_wrap_with_icon() {
+        let mut n = note("abcdef\nxy");
+        n.icon = "!".to_string();
+        assert_eq!(n.lines(4), vec!["! ab", "cdef", "xy"]);
+    }
+
+    #[test]
+    fn nearby_boxes_stack() {
+        let ys = stack_boxes(&[(100.0, 40.0), (110.0, 20.0), (300.0, 20.0)], 0.0, 1000.0, 2.0);
+        assert_eq!(ys, vec![100.0, 142.0, 300.0]);
+    }
+
+    #[test]
+    fn boxes_pushed_up_at_bottom() {
+        let ys = stack_boxes(&[(80.0, 20.0), (90.0, 20.0)], 0.0, 100.0, 0.0);
+        assert_eq!(ys, vec![60.0, 80.0]);
+    }
+}
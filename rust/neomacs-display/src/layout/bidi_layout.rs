@@ -6,9 +6,18 @@
 //! The integration works at row completion: after all characters on a line
 //! have been laid out left-to-right, this module reorders their X positions
 //! so that RTL runs appear in the correct visual order.
-
-use crate::core::bidi::{self, BidiDir};
+//!
+//! `WindowBidi` carries what a window's rows need for that: whether the
+//! buffer reorders at all (`bidi-display-reordering`), the base direction
+//! of each paragraph of the text laid out (`bidi-paragraph-direction`, or
+//! the first strong character of the paragraph), and the text area, so
+//! rows of right-to-left paragraphs are aligned to its right edge.  It
+//! also moves the row's hit-test positions along with the characters, so
+//! clicks land on the character drawn under the mouse.
+
+use crate::core::bidi::{self, BidiClass, BidiDir};
 use crate::core::frame_glyphs::{CursorStyle, FrameGlyph, FrameGlyphBuffer};
+use super::unicode::decode_utf8;
 
 /// Quick check whether a character is in an RTL script range.
 /// Used as a fast-path: if no character on a line is RTL, we skip
@@ -80,8 +89,20 @@ pub fn reorder_row_bidi(
     glyph_end: usize,
     _content_x: f32,
 ) {
+    reorder_row_in(frame_glyphs, glyph_start, glyph_end, BidiDir::Auto);
+}
+
+/// Reorder the row like `reorder_row_bidi`, in a paragraph of direction
+/// BASE.  Returns the original and new X of each character glyph, in
+/// logical order, or nothing if the row is left as it is.
+fn reorder_row_in(
+    frame_glyphs: &mut FrameGlyphBuffer,
+    glyph_start: usize,
+    glyph_end: usize,
+    base: BidiDir,
+) -> Vec<(f32, f32)> {
     if glyph_start >= glyph_end {
-        return;
+        return Vec::new();
     }
 
     // Step 1: Collect character glyphs on this row
@@ -107,27 +128,27 @@ pub fn reorder_row_bidi(
     }
 
     if row_chars.is_empty() {
-        return;
+        return Vec::new();
     }
 
     // Step 2: Fast-path check — skip bidi if no RTL characters
     let has_rtl = row_chars.iter().any(|info| is_rtl_char(info.ch));
-    if !has_rtl {
-        return;
+    if !has_rtl && base != BidiDir::RTL {
+        return Vec::new();
     }
 
     // Step 3: Build the character string and resolve bidi levels
     let chars: Vec<char> = row_chars.iter().map(|info| info.ch).collect();
     let text: String = chars.iter().collect();
-    let levels = bidi::resolve_levels(&text, BidiDir::Auto);
+    let levels = bidi::resolve_levels(&text, base);
 
     if levels.is_empty() {
-        return;
+        return Vec::new();
     }
 
     // Fast-path: if all levels are 0, no reordering needed
     if levels.iter().all(|&l| l == 0) {
-        return;
+        return Vec::new();
     }
 
     // Step 4: Get visual reorder indices
@@ -188,6 +209,206 @@ pub fn reorder_row_bidi(
             }
         }
     }
+
+    row_chars.iter().zip(new_x).map(|(info, x)| (info.x, x)).collect()
+}
+
+/// Base direction of each paragraph of TEXT, UTF-8 text starting at
+/// character position START, as (paragraph start, right-to-left) in
+/// order.  Paragraphs are separated by blank lines, as with Emacs's
+/// default `bidi-paragraph-start-re'; each takes the direction of its
+/// first strong character outside isolates, or that of the paragraph
+/// before it if it has none (left-to-right for the first).
+pub fn paragraph_directions(text: &[u8], start: i64) -> Vec<(i64, bool)> {
+    let mut paragraphs: Vec<(i64, Option<bool>)> = vec![(start, None)];
+    let mut charpos = start;
+    let mut byte_idx = 0;
+    let mut line_blank = true;
+    let mut isolates = 0u32;
+    while byte_idx < text.len() {
+        let (ch, len) = decode_utf8(&text[byte_idx..]);
+        byte_idx += len;
+        charpos += 1;
+        if ch == '\n' {
+            // A blank line ends the paragraph; the next starts after it
+            if line_blank && paragraphs.last().is_some_and(|p| p.1.is_some()) {
+                paragraphs.push((charpos, None));
+                isolates = 0;
+            }
+            line_blank = true;
+            continue;
+        }
+        if !matches!(ch, ' ' | '\t' | '\x0c') {
+            line_blank = false;
+        }
+        let paragraph = paragraphs.last_mut().unwrap();
+        if paragraph.1.is_some() {
+            continue;
+        }
+        match bidi::bidi_class(ch) {
+            BidiClass::LRI | BidiClass::RLI | BidiClass::FSI => isolates += 1,
+            BidiClass::PDI => isolates = isolates.saturating_sub(1),
+            BidiClass::L if isolates == 0 => paragraph.1 = Some(false),
+            BidiClass::R | BidiClass::AL if isolates == 0 => paragraph.1 = Some(true),
+            _ => {}
+        }
+    }
+    let mut rtl = false;
+    paragraphs.into_iter().map(|(pos, dir)| {
+        rtl = dir.unwrap_or(rtl);
+        (pos, rtl)
+    }).collect()
+}
+
+/// Bidi reordering of the rows of one window.
+pub struct WindowBidi {
+    /// Rows can need reordering: `bidi-display-reordering' is on and
+    /// the text has right-to-left characters or paragraphs
+    enabled: bool,
+    /// Start and direction (true = right-to-left) of each paragraph,
+    /// in order
+    paragraphs: Vec<(i64, bool)>,
+    /// Left and right edges of the text area
+    left: f32,
+    right: f32,
+}
+
+impl WindowBidi {
+    /// Bidi state of a window laying out TEXT from START, whose text
+    /// area spans LEFT to RIGHT.  DIRECTION is the buffer's
+    /// `bidi-paragraph-direction': Auto finds each paragraph's.
+    pub fn new(enabled: bool, direction: BidiDir, text: &[u8], start: i64, left: f32, right: f32) -> Self {
+        let mut any_rtl = false;
+        let mut probe = 0;
+        while enabled && probe < text.len() && !any_rtl {
+            let (ch, len) = decode_utf8(&text[probe..]);
+            any_rtl = is_rtl_char(ch);
+            probe += len;
+        }
+        let paragraphs = match direction {
+            BidiDir::RTL => vec![(start, true)],
+            BidiDir::Auto if any_rtl => paragraph_directions(text, start),
+            _ => vec![(start, false)],
+        };
+        let enabled = enabled && (any_rtl || direction == BidiDir::RTL);
+        WindowBidi { enabled, paragraphs, left, right }
+    }
+
+    /// Whether any row can be reordered.
+    pub fn active(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether the paragraph containing CHARPOS is right-to-left.
+    pub fn rtl_at(&self, charpos: i64) -> bool {
+        let i = self.paragraphs.partition_point(|&(start, _)| start <= charpos);
+        i > 0 && self.paragraphs[i - 1].1
+    }
+
+    /// Reorder the row whose glyphs start at GLYPH_START and run to the
+    /// end of FRAME_GLYPHS.  STARTS are the row's (charpos, x) hit-test
+    /// positions, x relative to the text area's left edge; they are
+    /// moved with their characters.  Rows of right-to-left paragraphs
+    /// are aligned to the right edge of the text area.
+    pub fn reorder_row(
+        &self,
+        frame_glyphs: &mut FrameGlyphBuffer,
+        glyph_start: usize,
+        starts: &mut [(i64, f32)],
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let Some(&(first, _)) = starts.first() else {
+            return;
+        };
+        let rtl = self.rtl_at(first);
+        let end = frame_glyphs.glyphs.len();
+        let cursor = frame_glyphs.glyphs[glyph_start..].iter().find_map(|g| match g {
+            FrameGlyph::Cursor { x, y, .. } => Some((*x, *y)),
+            _ => None,
+        });
+        let base = if rtl { BidiDir::RTL } else { BidiDir::LTR };
+        let moved = reorder_row_in(frame_glyphs, glyph_start, end, base);
+        for (_, x) in starts.iter_mut() {
+            let old = self.left + *x;
+            if let Some(&(_, new)) = moved.iter().find(|(from, _)| (from - old).abs() < 0.5) {
+                *x = new - self.left;
+            }
+        }
+        if rtl {
+            self.align_right(frame_glyphs, glyph_start, starts);
+        }
+
+        // The character drawn inverted under a box cursor goes with it
+        if let Some((old_x, y)) = cursor {
+            let new_x = frame_glyphs.glyphs[glyph_start..].iter().find_map(|g| match g {
+                FrameGlyph::Cursor { x, .. } => Some(*x),
+                _ => None,
+            });
+            if let (Some(inv), Some(new_x)) = (frame_glyphs.cursor_inverse.as_mut(), new_x) {
+                if (inv.x - old_x).abs() < 0.5 && (inv.y - y).abs() < 0.5 {
+                    inv.x = new_x;
+                }
+            }
+        }
+    }
+
+    /// Shift the text glyphs of the row from GLYPH_START, and its
+    /// hit-test STARTS, so the row ends at the right edge.  Glyphs left
+    /// of the text area (line numbers) and stretches reaching the right
+    /// edge (extended face backgrounds) stay.  A cursor after the last
+    /// character goes to the row's visual end, on its left.
+    fn align_right(&self, frame_glyphs: &mut FrameGlyphBuffer, glyph_start: usize, starts: &mut [(i64, f32)]) {
+        let in_text = |x: f32| x >= self.left - 0.5;
+        let reaches_edge = |x: f32, w: f32| x + w >= self.right - 0.5;
+        let mut extent = self.left;
+        let mut row_left = f32::INFINITY;
+        for glyph in &frame_glyphs.glyphs[glyph_start..] {
+            let (x, w) = match glyph {
+                FrameGlyph::Char { x, width, .. }
+                | FrameGlyph::Image { x, width, .. }
+                | FrameGlyph::Video { x, width, .. }
+                | FrameGlyph::WebKit { x, width, .. } => (*x, *width),
+                FrameGlyph::Stretch { x, width, .. } if !reaches_edge(*x, *width) => (*x, *width),
+                _ => continue,
+            };
+            if in_text(x) {
+                extent = extent.max(x + w);
+                row_left = row_left.min(x);
+            }
+        }
+        let shift = self.right - extent;
+        if shift <= 0.0 {
+            return;
+        }
+        for glyph in &mut frame_glyphs.glyphs[glyph_start..] {
+            match glyph {
+                FrameGlyph::Char { x, .. }
+                | FrameGlyph::Image { x, .. }
+                | FrameGlyph::Video { x, .. }
+                | FrameGlyph::WebKit { x, .. }
+                    if in_text(*x) =>
+                {
+                    *x += shift;
+                }
+                FrameGlyph::Stretch { x, width, .. } if in_text(*x) && !reaches_edge(*x, *width) => {
+                    *x += shift;
+                }
+                FrameGlyph::Cursor { x, width, .. } if in_text(*x) => {
+                    if *x >= extent - 0.5 && row_left.is_finite() {
+                        *x = (row_left + shift - *width).max(self.left);
+                    } else {
+                        *x += shift;
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (_, x) in starts.iter_mut() {
+            *x += shift;
+        }
+    }
 }
 
 #[cfg(test)]
@@ -219,6 +440,7 @@ mod tests {
             overline_color: None,
             overstrike: false,
             is_overlay: false,
+            sideways: false,
         }
     }
 
@@ -988,4 +1210,69 @@ mod tests {
                     "glyph at {} overlaps with previous ending at {}", positions[i].0, prev_end);
         }
     }
+
+    // --- Paragraph directions and window reordering ---
+
+    #[test]
+    fn test_paragraph_directions_split_at_blank_lines() {
+        let text = "abc\n\u{05D0}\u{05D1}\n\n\u{05E9}\u{05DC}\u{05D5}\u{05DD}\n123\n\nxyz";
+        assert_eq!(
+            paragraph_directions(text.as_bytes(), 1),
+            vec![(1, false), (9, true), (19, false)]
+        );
+    }
+
+    #[test]
+    fn test_paragraph_without_strong_chars_inherits_direction() {
+        let text = "\u{05D0}\n\n123";
+        assert_eq!(paragraph_directions(text.as_bytes(), 1), vec![(1, true), (4, true)]);
+    }
+
+    #[test]
+    fn test_window_bidi_inactive_for_ltr_text() {
+        let bidi = WindowBidi::new(true, BidiDir::Auto, b"hello", 1, 0.0, 80.0);
+        assert!(!bidi.active());
+        let bidi = WindowBidi::new(false, BidiDir::RTL, "\u{05D0}".as_bytes(), 1, 0.0, 80.0);
+        assert!(!bidi.active());
+    }
+
+    #[test]
+    fn test_window_bidi_right_aligns_rtl_rows() {
+        let text = "\u{05E9}\u{05DC}";
+        let bidi = WindowBidi::new(true, BidiDir::Auto, text.as_bytes(), 1, 0.0, 80.0);
+        assert!(bidi.active());
+        assert!(bidi.rtl_at(1));
+
+        let mut buf = FrameGlyphBuffer::default();
+        buf.glyphs.push(make_char_glyph('\u{05E9}', 0.0, 8.0));
+        buf.glyphs.push(make_cursor_glyph(0.0, 8.0));
+        buf.glyphs.push(make_char_glyph('\u{05DC}', 8.0, 8.0));
+        let mut starts = vec![(1, 0.0), (2, 8.0)];
+        bidi.reorder_row(&mut buf, 0, &mut starts);
+
+        // The first character is drawn rightmost, at the window's edge
+        assert_eq!(get_char_x(&buf.glyphs[0]), 72.0);
+        assert_eq!(get_char_x(&buf.glyphs[2]), 64.0);
+        assert_eq!(get_cursor_x(&buf.glyphs[1]), 72.0);
+        assert_eq!(starts, vec![(1, 72.0), (2, 64.0)]);
+    }
+
+    #[test]
+    fn test_window_bidi_forced_ltr_keeps_left_alignment() {
+        let text = "a\u{05D0}\u{05D1}";
+        let bidi = WindowBidi::new(true, BidiDir::LTR, text.as_bytes(), 1, 0.0, 80.0);
+        assert!(!bidi.rtl_at(1));
+
+        let mut buf = FrameGlyphBuffer::default();
+        buf.glyphs.push(make_char_glyph('a', 0.0, 8.0));
+        buf.glyphs.push(make_char_glyph('\u{05D0}', 8.0, 8.0));
+        buf.glyphs.push(make_char_glyph('\u{05D1}', 16.0, 8.0));
+        let mut starts = vec![(1, 0.0), (2, 8.0), (3, 16.0)];
+        bidi.reorder_row(&mut buf, 0, &mut starts);
+
+        assert_eq!(get_char_x(&buf.glyphs[0]), 0.0);
+        assert_eq!(get_char_x(&buf.glyphs[1]), 16.0);
+        assert_eq!(get_char_x(&buf.glyphs[2]), 8.0);
+        assert_eq!(starts, vec![(1, 0.0), (2, 16.0), (3, 8.0)]);
+    }
 }
@@ -0,0 +1,292 @@
+//! Breadcrumb bar drawn in a window's header line.
+//!
+//! Lisp publishes the path to point as a list of segments: the
+//! directories and name of the buffer's file, then the definitions
+//! enclosing point (from tree-sitter scopes or `which-function').  When
+//! the window's header line is drawn, the segments replace its text,
+//! joined by a separator (usually an icon registered from an icon font).
+//!
+//! When the bar does not fit, it is shortened in steps, each taken only
+//! if the previous one was not enough:
+//!
+//! 1. leading directories collapse into a single "…", outermost first;
+//! 2. the longest names other than the last are cut, down to a few
+//!    characters each;
+//! 3. the file name and outer definitions collapse into the "…" too;
+//! 4. the last segment is cut.
+//!
+//! The x range of each visible segment is kept after drawing, so a click
+//! on the bar can be mapped back to the segment it hit with
+//! `BreadcrumbBars::segment_at`.  A click on the "…" hits the innermost
+//! segment it hides.
+
+use std::collections::HashMap;
+
+/// What a segment names; Lisp decides what a click on it does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentKind {
+    Directory,
+    File,
+    Symbol,
+}
+
+impl SegmentKind {
+    pub fn from_u8(kind: u8) -> Self {
+        match kind {
+            0 => Self::Directory,
+            1 => Self::File,
+            _ => Self::Symbol,
+        }
+    }
+}
+
+/// One element of the path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub kind: SegmentKind,
+    pub label: String,
+}
+
+/// What a piece of the laid-out bar shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieceKind {
+    /// Label of segment N, possibly shortened
+    Segment(usize),
+    /// Collapsed segments; N is the innermost one hidden
+    Ellipsis(usize),
+    Separator,
+}
+
+/// A run of text of the laid-out bar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Piece {
+    pub kind: PieceKind,
+    pub text: String,
+}
+
+impl Piece {
+    /// Segment a click on this piece selects.
+    pub fn segment(&self) -> Option<usize> {
+        match self.kind {
+            PieceKind::Segment(i) | PieceKind::Ellipsis(i) => Some(i),
+            PieceKind::Separator => None,
+        }
+    }
+}
+
+/// Text standing for collapsed segments and cut names.
+pub const ELLIPSIS: &str = "\u{2026}";
+
+/// Characters a name other than the last keeps when cut, before the
+/// ellipsis.
+const MIN_KEPT: usize = 3;
+
+/// LABEL cut to KEEP characters followed by an ellipsis.
+fn cut(label: &str, keep: usize) -> String {
+    let mut text: String = label.chars().take(keep).collect();
+    text.push_str(ELLIPSIS);
+    text
+}
+
+fn build(segments: &[Segment], labels: &[String], hidden: usize, separator: &str) -> Vec<Piece> {
+    let mut pieces = Vec::with_capacity(2 * segments.len());
+    if hidden > 0 {
+        pieces.push(Piece { kind: PieceKind::Ellipsis(hidden - 1), text: ELLIPSIS.to_string() });
+    }
+    for (i, label) in labels.iter().enumerate().skip(hidden) {
+        if !pieces.is_empty() {
+            pieces.push(Piece { kind: PieceKind::Separator, text: separator.to_string() });
+        }
+        pieces.push(Piece { kind: PieceKind::Segment(i), text: label.clone() });
+    }
+    pieces
+}
+
+/// Lay out SEGMENTS joined by SEPARATOR in at most MAX_WIDTH, measuring
+/// characters with ADVANCE, shortening the bar as described in the
+/// module documentation.
+pub fn fit(
+    segments: &[Segment],
+    separator: &str,
+    max_width: f32,
+    mut advance: impl FnMut(char) -> f32,
+) -> Vec<Piece> {
+    let mut width = |pieces: &[Piece]| -> f32 {
+        pieces.iter().flat_map(|p| p.text.chars()).map(&mut advance).sum()
+    };
+    let Some(last) = segments.len().checked_sub(1) else {
+        return Vec::new();
+    };
+    let mut labels: Vec<String> = segments.iter().map(|s| s.label.clone()).collect();
+    let mut hidden = 0;
+    loop {
+        let pieces = build(segments, &labels, hidden, separator);
+        if width(&pieces) <= max_width {
+            return pieces;
+        }
+        // 1. Collapse the outermost directory still shown
+        if hidden < last && segments[hidden].kind == SegmentKind::Directory {
+            hidden += 1;
+            continue;
+        }
+        // 2. Cut the longest name other than the last
+        let longest = (hidden..last)
+            .map(|i| (labels[i].chars().count(), i))
+            .filter(|&(len, _)| len > MIN_KEPT + 1)
+            .max_by_key(|&(len, i)| (len, std::cmp::Reverse(i)));
+        if let Some((len, i)) = longest {
+            labels[i] = cut(&segments[i].label, len - 2);
+            continue;
+        }
+        // 3. Collapse the outermost segment other than the last
+        if hidden < last {
+            hidden += 1;
+            continue;
+        }
+        // 4. Cut the last segment
+        let len = labels[last].chars().count();
+        if len > 1 {
+            labels[last] = cut(&segments[last].label, len - 2);
+            continue;
+        }
+        return pieces;
+    }
+}
+
+/// Segments of one window and where they were last drawn.
+#[derive(Debug, Default)]
+struct WindowBar {
+    segments: Vec<Segment>,
+    separator: String,
+    /// Separator color (sRGB pixel), 0 for the header line's foreground
+    separator_color: u32,
+    /// Window-relative x ranges of the drawn segments
+    hits: Vec<(f32, f32, usize)>,
+}
+
+/// Breadcrumb bars of all windows, keyed by window id.
+#[derive(Debug, Default)]
+pub struct BreadcrumbBars {
+    windows: HashMap<i64, WindowBar>,
+}
+
+impl BreadcrumbBars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Show SEGMENTS joined by SEPARATOR, drawn in SEPARATOR_COLOR, in
+    /// the header line of WINDOW_ID.  Empty SEGMENTS gives the header
+    /// line back to its text.
+    pub fn set(
+        &mut self,
+        window_id: i64,
+        segments: Vec<Segment>,
+        separator: String,
+        separator_color: u32,
+    ) {
+        if segments.is_empty() {
+            self.windows.remove(&window_id);
+        } else {
+            self.windows.insert(
+                window_id,
+                WindowBar { segments, separator, separator_color, hits: Vec::new() },
+            );
+        }
+    }
+
+    /// Segments, separator and separator color of WINDOW_ID's bar, if it
+    /// has one.
+    pub fn get(&self, window_id: i64) -> Option<(&[Segment], &str, u32)> {
+        self.windows
+            .get(&window_id)
+            .map(|bar| (bar.segments.as_slice(), bar.separator.as_str(), bar.separator_color))
+    }
+
+    /// Remember where the segments of WINDOW_ID were drawn: HITS holds
+    /// (left, right, segment) x ranges relative to the window's left edge.
+    pub fn set_hits(&mut self, window_id: i64, hits: Vec<(f32, f32, usize)>) {
+        if let Some(bar) = self.windows.get_mut(&window_id) {
+            bar.hits = hits;
+        }
+    }
+
+    /// Segment of WINDOW_ID's bar drawn at X, relative to the window's
+    /// left edge.
+    pub fn segment_at(&self, window_id: i64, x: f32) -> Option<(usize, &Segment)> {
+        let bar = self.windows.get(&window_id)?;
+        let &(_, _, i) = bar.hits.iter().find(|&&(left, right, _)| x >= left && x < right)?;
+        bar.segments.get(i).map(|s| (i, s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments() -> Vec<Segment> {
+        let seg = |kind, label: &str| Segment { kind, label: label.to_string() };
+        vec![
+            seg(SegmentKind::Directory, "src"),
+            seg(SegmentKind::Directory, "layout"),
+            seg(SegmentKind::File, "engine.rs"),
+            seg(SegmentKind::Symbol, "LayoutEngine"),
+            seg(SegmentKind::Symbol, "render_status_line"),
+        ]
+    }
+
+    fn text(pieces: &[Piece]) -> String {
+        pieces.iter().map(|p| p.text.as_str()).collect()
+    }
+
+    fn fit_cols(cols: usize) -> Vec<Piece> {
+        fit(&segments(), ">", cols as f32, |_| 1.0)
+    }
+
+    #[test]
+    fn truncates_in_steps() {
+        let full = "src>layout>engine.rs>LayoutEngine>render_status_line";
+        assert_eq!(text(&fit_cols(100)), full);
+        // Directories collapse first, outermost first
+        assert_eq!(text(&fit_cols(full.len() - 1)), "…>layout>engine.rs>LayoutEngine>render_status_line");
+        assert_eq!(text(&fit_cols(45)), "…>engine.rs>LayoutEngine>render_status_line");
+        // Then the longest names but the last are cut
+        assert_eq!(text(&fit_cols(40)), "…>engine.rs>LayoutEn…>render_status_line");
+        assert_eq!(text(&fit_cols(31)), "…>eng…>Layo…>render_status_line");
+        // Then outer segments collapse, and finally the last is cut
+        assert_eq!(text(&fit_cols(25)), "…>Lay…>render_status_line");
+        assert_eq!(text(&fit_cols(10)), "…>render_…");
+        assert!(fit(&[], ">", 10.0, |_| 1.0).is_empty());
+    }
+
+    #[test]
+    fn pieces_map_to_segments() {
+        let pieces = fit_cols(25);
+        let kinds: Vec<_> = pieces.iter().map(|p| p.kind).collect();
+        assert_eq!(kinds, vec![
+            PieceKind::Ellipsis(2),
+            PieceKind::Separator,
+            PieceKind::Segment(3),
+            PieceKind::Separator,
+            PieceKind::Segment(4),
+        ]);
+        // The ellipsis selects the innermost segment it hides
+        assert_eq!(pieces[0].segment(), Some(2));
+        assert_eq!(pieces[1].segment(), None);
+    }
+
+    #[test]
+    fn clicks_resolve_to_drawn_segments() {
+        let mut bars = BreadcrumbBars::new();
+        bars.set(7, segments(), ">".to_string(), 0);
+        assert!(bars.segment_at(7, 5.0).is_none());
+        bars.set_hits(7, vec![(0.0, 30.0, 0), (40.0, 90.0, 2)]);
+        assert_eq!(bars.segment_at(7, 5.0).map(|(i, _)| i), Some(0));
+        assert_eq!(bars.segment_at(7, 50.0).map(|(i, s)| (i, s.kind)), Some((2, SegmentKind::File)));
+        // Separator, and other windows
+        assert!(bars.segment_at(7, 35.0).is_none());
+        assert!(bars.segment_at(8, 5.0).is_none());
+        bars.set(7, Vec::new(), String::new(), 0);
+        assert!(bars.get(7).is_none());
+    }
+}
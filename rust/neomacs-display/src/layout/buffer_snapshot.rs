@@ -0,0 +1,335 @@
+//! Safe snapshot of an Emacs buffer for one layout pass.
+//!
+//! `BufferSnapshot` is the one place layout reads `struct buffer` and its
+//! gap buffer directly.  It is captured once per window per layout pass:
+//! the metadata (narrowing, point, tab width, wrapping) is read up front,
+//! and text is copied out of the gap buffer on request, after checking
+//! the range against the buffer's bounds.  Everything after that works on
+//! the copies, so the unsafe pointer math stays in `emacs_types` and in
+//! this module.
+//!
+//! A snapshot can also hold an in-memory buffer instead, for headless
+//! layout (see `headless`).
+//!
+//! Reading Emacs memory is only safe on the Emacs thread while the
+//! buffer is stable.  A snapshot holds a raw buffer pointer, so it is
+//! neither `Send` nor `Sync`.  Off the Emacs thread, capturing one
+//! gives an empty snapshot and reading text reads nothing; both log an
+//! error rather than panic, as they are called across the FFI.
+
+use std::ffi::c_void;
+use std::rc::Rc;
+use std::sync::OnceLock;
+use std::thread::{self, ThreadId};
+
+use super::emacs_ffi::neomacs_buf_charpos_to_bytepos;
+use super::emacs_types;
+use super::unicode::decode_utf8;
+
+/// The thread Emacs runs Lisp and layout on.
+static EMACS_THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+/// Record the calling thread as the Emacs thread, if none is yet.
+/// Called by the FFI entry points Emacs calls first.
+pub fn register_emacs_thread() {
+    EMACS_THREAD.get_or_init(|| thread::current().id());
+}
+
+/// Whether the calling thread is the Emacs thread (or none is recorded
+/// yet, as in tests).
+pub fn on_emacs_thread() -> bool {
+    EMACS_THREAD.get().is_none_or(|&id| id == thread::current().id())
+}
+
+/// Whether the calling thread is the Emacs thread; if not, log that
+/// WHAT was called off it.
+fn check_emacs_thread(what: &str) -> bool {
+    let ok = on_emacs_thread();
+    if !ok {
+        log::error!(
+            "BufferSnapshot::{} called off the Emacs thread ({:?})",
+            what,
+            thread::current().id()
+        );
+    }
+    ok
+}
+
+/// Metadata and text of a buffer, read once for a layout pass.
+pub struct BufferSnapshot {
+    buffer: *const c_void,
+    /// Whole text of an in-memory buffer, read instead of `buffer`
+    memory: Option<Rc<str>>,
+    begv: i64,
+    zv: i64,
+    point: i64,
+    tab_width: i32,
+    truncate_lines: bool,
+    word_wrap: bool,
+    multibyte: bool,
+    /// Text loaded by `load_text`, as UTF-8
+    text: Vec<u8>,
+    /// Character position of the start of `text`
+    text_start: i64,
+}
+
+impl BufferSnapshot {
+    /// Snapshot of BUFFER, reusing TEXT_BUF's allocation for its text.
+    /// A null BUFFER gives an empty snapshot of an empty buffer.
+    ///
+    /// # Safety
+    /// BUFFER must be null or a valid `struct buffer *` that stays live
+    /// and unmodified while the snapshot is used.
+    ///
+    /// Off the Emacs thread, BUFFER is not read and the snapshot is empty.
+    pub unsafe fn capture(buffer: *const c_void, mut text_buf: Vec<u8>) -> Self {
+        let buffer = if check_emacs_thread("capture") { buffer } else { std::ptr::null() };
+        text_buf.clear();
+        let mut snapshot = BufferSnapshot {
+            buffer,
+            memory: None,
+            begv: 1,
+            zv: 1,
+            point: 1,
+            tab_width: 8,
+            truncate_lines: false,
+            word_wrap: false,
+            multibyte: true,
+            text: text_buf,
+            text_start: 1,
+        };
+        if !buffer.is_null() {
+            (snapshot.begv, snapshot.zv) = emacs_types::buffer_bounds(buffer);
+            snapshot.point = emacs_types::buffer_point(buffer);
+            snapshot.tab_width = emacs_types::buffer_tab_width(buffer);
+            snapshot.truncate_lines = emacs_types::buffer_truncate_lines(buffer);
+            snapshot.word_wrap = emacs_types::buffer_word_wrap(buffer);
+            snapshot.multibyte = emacs_types::buffer_multibyte_p(buffer);
+            snapshot.text_start = snapshot.begv;
+        }
+        snapshot
+    }
+
+    /// Snapshot of an in-memory buffer holding TEXT, all of it
+    /// accessible, reusing TEXT_BUF's allocation.
+    pub fn in_memory(
+        text: Rc<str>,
+        point: i64,
+        tab_width: i32,
+        truncate_lines: bool,
+        word_wrap: bool,
+        mut text_buf: Vec<u8>,
+    ) -> Self {
+        text_buf.clear();
+        let zv = text.chars().count() as i64 + 1;
+        BufferSnapshot {
+            buffer: std::ptr::null(),
+            memory: Some(text),
+            begv: 1,
+            zv,
+            point: point.clamp(1, zv),
+            tab_width,
+            truncate_lines,
+            word_wrap,
+            multibyte: true,
+            text: text_buf,
+            text_start: 1,
+        }
+    }
+
+    /// Start of the accessible portion (BEGV).
+    pub fn begv(&self) -> i64 {
+        self.begv
+    }
+
+    /// End of the accessible portion (ZV).
+    pub fn zv(&self) -> i64 {
+        self.zv
+    }
+
+    /// Point.
+    pub fn point(&self) -> i64 {
+        self.point
+    }
+
+    /// Buffer-local `tab-width`.
+    pub fn tab_width(&self) -> i32 {
+        self.tab_width
+    }
+
+    /// Buffer-local `truncate-lines`.
+    pub fn truncate_lines(&self) -> bool {
+        self.truncate_lines
+    }
+
+    /// Buffer-local `word-wrap`.
+    pub fn word_wrap(&self) -> bool {
+        self.word_wrap
+    }
+
+    /// Whether the buffer is multibyte.  Its text is UTF-8 either way.
+    pub fn multibyte(&self) -> bool {
+        self.multibyte
+    }
+
+    /// Narrow the accessible portion further to FROM..TO, clamped to the
+    /// current one, keeping point inside it.
+    pub fn narrow(&mut self, from: i64, to: i64) {
+        (self.begv, self.zv) = self.clamp(from, to);
+        self.point = self.point.clamp(self.begv, self.zv);
+    }
+
+    /// Move point to POS, clamped to the accessible portion.
+    pub fn set_point(&mut self, pos: i64) {
+        self.point = pos.clamp(self.begv, self.zv);
+    }
+
+    /// Override `truncate-lines` for this pass.
+    pub fn set_truncate_lines(&mut self, truncate: bool) {
+        self.truncate_lines = truncate;
+    }
+
+    /// FROM and TO clamped to the accessible portion, in order.
+    pub fn clamp(&self, from: i64, to: i64) -> (i64, i64) {
+        let from = from.clamp(self.begv, self.zv);
+        (from, to.clamp(from, self.zv))
+    }
+
+    /// Load the text between character positions FROM and TO, clamped
+    /// to the accessible portion, replacing any loaded before, and
+    /// return it.  Nothing is loaded if the byte range the buffer gives
+    /// for it is out of bounds, or, unless the buffer is in memory, off
+    /// the Emacs thread.
+    pub fn load_text(&mut self, from: i64, to: i64) -> &[u8] {
+        let (from, to) = self.clamp(from, to);
+        self.text.clear();
+        self.text_start = from;
+        if let Some(memory) = &self.memory {
+            let byte = |pos: i64| {
+                memory.char_indices().nth((pos - 1) as usize).map_or(memory.len(), |(i, _)| i)
+            };
+            self.text.extend_from_slice(&memory.as_bytes()[byte(from)..byte(to)]);
+            return &self.text;
+        }
+        if self.buffer.is_null() || from >= to || !check_emacs_thread("load_text") {
+            return &self.text;
+        }
+        unsafe {
+            let text = emacs_types::buf_text_ptr(self.buffer);
+            if text.is_null() {
+                return &self.text;
+            }
+            let z_byte = (*text).z_byte as i64;
+            let byte_from = neomacs_buf_charpos_to_bytepos(self.buffer as *mut c_void, from);
+            let byte_to = neomacs_buf_charpos_to_bytepos(self.buffer as *mut c_void, to);
+            if !(1 <= byte_from && byte_from <= byte_to && byte_to <= z_byte) {
+                log::warn!(
+                    "BufferSnapshot: chars {}..{} map to bytes {}..{} outside 1..{}",
+                    from, to, byte_from, byte_to, z_byte
+                );
+                return &self.text;
+            }
+            let mut text = std::mem::take(&mut self.text);
+            emacs_types::gap_buffer_copy_text(self.buffer, byte_from as isize, byte_to as isize, &mut text);
+            self.text = text;
+        }
+        &self.text
+    }
+
+    /// The text last loaded.
+    pub fn text(&self) -> &[u8] {
+        &self.text
+    }
+
+    /// Character position of the start of `text()`.
+    pub fn text_start(&self) -> i64 {
+        self.text_start
+    }
+
+    /// The characters last loaded, with their character positions.
+    pub fn chars(&self) -> Chars<'_> {
+        Chars { bytes: &self.text, charpos: self.text_start }
+    }
+
+    /// Give back the text allocation for the next snapshot.
+    pub fn into_text_buf(self) -> Vec<u8> {
+        self.text
+    }
+}
+
+/// Iterator over the loaded characters of a snapshot and their
+/// character positions.
+pub struct Chars<'a> {
+    bytes: &'a [u8],
+    charpos: i64,
+}
+
+impl Iterator for Chars<'_> {
+    type Item = (i64, char);
+
+    fn next(&mut self) -> Option<(i64, char)> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let (ch, len) = decode_utf8(self.bytes);
+        self.bytes = &self.bytes[len.max(1)..];
+        let pos = self.charpos;
+        self.charpos += 1;
+        Some((pos, ch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Snapshot of a buffer narrowed to BEGV..ZV with TEXT loaded from
+    /// BEGV, without an Emacs buffer behind it.
+    fn snapshot(begv: i64, zv: i64, text: &str) -> BufferSnapshot {
+        let mut snapshot = BufferSnapshot::in_memory("".into(), 1, 8, false, false, Vec::new());
+        (snapshot.begv, snapshot.zv) = (begv, zv);
+        snapshot.text.extend_from_slice(text.as_bytes());
+        snapshot.text_start = begv;
+        snapshot
+    }
+
+    #[test]
+    fn ranges_are_clamped_to_the_accessible_portion() {
+        let mut s = snapshot(10, 20, "");
+        assert_eq!(s.clamp(1, 100), (10, 20));
+        assert_eq!(s.clamp(15, 12), (15, 15));
+        assert_eq!(s.clamp(25, 30), (20, 20));
+        // An empty buffer has no text to load
+        assert!(s.load_text(10, 20).is_empty());
+        assert_eq!(s.text_start(), 10);
+    }
+
+    #[test]
+    fn in_memory_text_is_loaded_by_character_position() {
+        let mut s = BufferSnapshot::in_memory("añ漢\nb".into(), 99, 8, false, true, Vec::new());
+        assert_eq!((s.begv(), s.zv(), s.point()), (1, 6, 6));
+        assert_eq!(s.load_text(2, 4), "ñ漢".as_bytes());
+        assert_eq!(s.text_start(), 2);
+        assert_eq!(s.load_text(4, 100), "\nb".as_bytes());
+    }
+
+    #[test]
+    fn narrowing_keeps_point_inside() {
+        let mut s = BufferSnapshot::in_memory("abcdefgh".into(), 9, 8, false, false, Vec::new());
+        s.narrow(3, 20);
+        assert_eq!((s.begv(), s.zv(), s.point()), (3, 9, 9));
+        s.narrow(1, 5);
+        assert_eq!((s.begv(), s.zv(), s.point()), (3, 5, 5));
+        s.set_point(1);
+        assert_eq!(s.point(), 3);
+        assert_eq!(s.load_text(1, 9), "cd".as_bytes());
+    }
+
+    #[test]
+    fn chars_carry_their_positions() {
+        let s = snapshot(5, 9, "añ漢\n");
+        let chars: Vec<(i64, char)> = s.chars().collect();
+        assert_eq!(chars, vec![(5, 'a'), (6, 'ñ'), (7, '漢'), (8, '\n')]);
+        assert_eq!(s.into_text_buf().len(), 7);
+    }
+}
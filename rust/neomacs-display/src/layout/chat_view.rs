@@ -0,0 +1,203 @@
+//! Chat buffers (ERC, rcirc, telega and other chat clients).
+//!
+//! Lisp registers where a chat buffer's input area begins, the prompt
+//! followed by the message being typed, and how many messages arrived
+//! while the user was reading further up.  When a window is scrolled up
+//! so far that its rows stop before the input area, the engine draws the
+//! input pinned to the bottom of the text area, over the transcript, and
+//! above it a pill counting the new messages.  Clicks on the pinned
+//! input land in the input area, and moving point there scrolls the
+//! window back down.
+
+use std::collections::HashMap;
+
+use super::unicode::{decode_utf8, is_wide_char};
+
+/// Rows of input shown pinned; longer input shows its end.
+pub const MAX_INPUT_ROWS: usize = 3;
+
+/// Characters of input looked at, counting back from the end.
+pub const INPUT_SCAN_LIMIT: i64 = 4096;
+
+/// Colors of a chat buffer's pinned input and pill (sRGB pixels).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChatColors {
+    /// Background of the pinned input.
+    pub input_bg: u32,
+    /// Line between the transcript and the pinned input.
+    pub separator: u32,
+    /// Background and text of the new messages pill.
+    pub pill_bg: u32,
+    pub pill_fg: u32,
+}
+
+/// A chat buffer's input area and unread messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChatView {
+    /// Beginning of the prompt; the input runs to the end of the buffer.
+    pub input_start: i64,
+    /// Messages that arrived since the user scrolled up.
+    pub unread: u32,
+    pub colors: ChatColors,
+}
+
+impl ChatView {
+    /// Text of the pill, e.g. "↓ 3 new messages", or None with nothing
+    /// unread.
+    pub fn pill_label(&self) -> Option<String> {
+        match self.unread {
+            0 => None,
+            1 => Some("↓ 1 new message".to_string()),
+            n => Some(format!("↓ {} new messages", n)),
+        }
+    }
+}
+
+/// Chat views per buffer.
+#[derive(Default)]
+pub struct ChatViewStore {
+    buffers: HashMap<u64, ChatView>,
+}
+
+impl ChatViewStore {
+    pub fn new() -> Self {
+        Self { buffers: HashMap::new() }
+    }
+
+    /// Make BUFFER_ID a chat buffer shown as VIEW.
+    pub fn set(&mut self, buffer_id: u64, view: ChatView) {
+        self.buffers.insert(buffer_id, view);
+    }
+
+    /// Show BUFFER_ID as an ordinary buffer again.
+    pub fn clear_buffer(&mut self, buffer_id: u64) {
+        self.buffers.remove(&buffer_id);
+    }
+
+    pub fn get(&self, buffer_id: u64) -> Option<&ChatView> {
+        self.buffers.get(&buffer_id)
+    }
+}
+
+/// A character of the input laid out on a row.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputCell {
+    pub ch: char,
+    /// Column it starts in, and columns it takes
+    pub col: i32,
+    pub cols: i32,
+    /// Its buffer position
+    pub pos: i64,
+}
+
+/// A row of pinned input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputRow {
+    /// Positions of its first character and after its last
+    pub start: i64,
+    pub end: i64,
+    pub cells: Vec<InputCell>,
+}
+
+/// Lay out the input TEXT, which starts at buffer position START, in
+/// rows of COLS columns: newlines end rows, long lines wrap, tabs reach
+/// the next stop of TAB_WIDTH columns and control characters show as
+/// `^X'.  Only the last MAX_ROWS rows are kept.
+pub fn input_rows(text: &[u8], start: i64, cols: i32, tab_width: i32, max_rows: usize) -> Vec<InputRow> {
+    let cols = cols.max(1);
+    let tab_w = tab_width.max(1);
+    let mut rows = vec![InputRow { start, end: start, cells: Vec::new() }];
+    let (mut col, mut pos, mut byte_idx) = (0, start, 0);
+    loop {
+        let (ch, len) = decode_utf8(&text[byte_idx..]);
+        if len == 0 {
+            break;
+        }
+        byte_idx += len;
+        let cells: Vec<(char, i32)> = if ch == '\n' {
+            Vec::new()
+        } else if ch == '\t' {
+            vec![(' ', ((col / tab_w + 1) * tab_w - col).min(cols))]
+        } else if (ch as u32) < 0x20 || ch == '\u{7f}' {
+            vec![('^', 1), (char::from_u32(ch as u32 ^ 0x40).unwrap_or('?'), 1)]
+        } else {
+            vec![(ch, if is_wide_char(ch) { 2 } else { 1 })]
+        };
+        let width: i32 = cells.iter().map(|&(_, w)| w).sum();
+        if ch != '\n' && col > 0 && col + width > cols {
+            rows.push(InputRow { start: pos, end: pos, cells: Vec::new() });
+            col = 0;
+        }
+        let row = rows.last_mut().unwrap();
+        for (cell_ch, cell_cols) in cells {
+            if ch != '\t' {
+                row.cells.push(InputCell { ch: cell_ch, col, cols: cell_cols, pos });
+            }
+            col += cell_cols;
+        }
+        pos += 1;
+        row.end = pos;
+        if ch == '\n' {
+            rows.push(InputRow { start: pos, end: pos, cells: Vec::new() });
+            col = 0;
+        }
+    }
+    let keep = max_rows.max(1);
+    if rows.len() > keep {
+        rows.drain(..rows.len() - keep);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_of(row: &InputRow) -> String {
+        row.cells.iter().map(|c| c.ch).collect()
+    }
+
+    #[test]
+    fn input_wraps_and_keeps_its_end() {
+        let rows = input_rows("<#emacs> hello world".as_bytes(), 100, 10, 8, 3);
+        assert_eq!(rows.iter().map(text_of).collect::<Vec<_>>(), ["<#emacs> h", "ello world"]);
+        assert_eq!((rows[1].start, rows[1].end), (110, 120));
+        assert_eq!(rows[1].cells[0], InputCell { ch: 'e', col: 0, cols: 1, pos: 110 });
+
+        // Newlines end rows; only the last rows are kept
+        let rows = input_rows(b"> a\nb\nc\nd", 1, 10, 8, 2);
+        assert_eq!(rows.iter().map(text_of).collect::<Vec<_>>(), ["c", "d"]);
+        assert_eq!(rows[0].start, 7);
+
+        // Empty input still has a row, for the cursor
+        let rows = input_rows(b"", 5, 10, 8, 3);
+        assert_eq!(rows, [InputRow { start: 5, end: 5, cells: Vec::new() }]);
+    }
+
+    #[test]
+    fn tabs_wide_and_control_characters_take_their_columns() {
+        let rows = input_rows("a\tb中\u{1}".as_bytes(), 1, 20, 4, 3);
+        let cells: Vec<(char, i32, i64)> = rows[0].cells.iter().map(|c| (c.ch, c.col, c.pos)).collect();
+        assert_eq!(cells, [('a', 0, 1), ('b', 4, 3), ('中', 5, 4), ('^', 7, 5), ('A', 8, 5)]);
+        // A wide character that does not fit moves to the next row
+        let rows = input_rows("abc中".as_bytes(), 1, 4, 8, 3);
+        assert_eq!(rows.iter().map(text_of).collect::<Vec<_>>(), ["abc", "中"]);
+    }
+
+    #[test]
+    fn pill_counts_new_messages() {
+        let colors = ChatColors { input_bg: 0, separator: 0, pill_bg: 0, pill_fg: 0 };
+        let mut view = ChatView { input_start: 1, unread: 0, colors };
+        assert_eq!(view.pill_label(), None);
+        view.unread = 1;
+        assert_eq!(view.pill_label().as_deref(), Some("↓ 1 new message"));
+        view.unread = 12;
+        assert_eq!(view.pill_label().as_deref(), Some("↓ 12 new messages"));
+
+        let mut store = ChatViewStore::new();
+        store.set(7, view);
+        assert_eq!(store.get(7).map(|v| v.unread), Some(12));
+        store.clear_buffer(7);
+        assert!(store.get(7).is_none());
+    }
+}
@@ -0,0 +1,186 @@
+//! Color swatches: small squares of a color drawn before color literals.
+//!
+//! Lisp scans fontified text for color literals (`#rgb`, `#rrggbb`,
+//! `rgb(r, g, b)`) and registers each literal's range with the color it
+//! denotes.  The layout engine draws a swatch of that
+//! color, two columns wide, just before the literal; the swatch is not
+//! buffer text, so it does not change positions, search or copying.
+//!
+//! Like links, ranges live in a per-buffer `ItreeTree` so they follow the
+//! text as the buffer is edited.  Scanning happens in jit-lock chunks, so
+//! swatches are replaced a range at a time (`ColorSwatchStore::set_range`).
+
+use std::collections::HashMap;
+
+use crate::core::itree::{ItreeOrder, ItreeTree, NodeId};
+
+/// A color literal in the buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorSwatch {
+    pub start: i64,
+    pub end: i64,
+    /// The color the literal denotes (sRGB pixel).
+    pub color: u32,
+}
+
+/// Swatches of one buffer; node data is the color.
+#[derive(Default)]
+struct BufferSwatches {
+    tree: ItreeTree,
+    nodes: Vec<NodeId>,
+}
+
+impl BufferSwatches {
+    fn nodes_in(&mut self, begin: i64, end: i64) -> Vec<NodeId> {
+        let mut found = Vec::new();
+        let mut iter = self.tree.iterator_start(begin, end, ItreeOrder::Ascending);
+        while let Some(node) = self.tree.iterator_next(&mut iter) {
+            found.push(node);
+        }
+        found
+    }
+
+    fn remove(&mut self, node: NodeId) {
+        self.tree.remove(node);
+        self.tree.free_node(node);
+        self.nodes.retain(|&n| n != node);
+    }
+}
+
+/// All swatches, keyed by buffer (same id as `WindowParams::buffer_id`).
+#[derive(Default)]
+pub struct ColorSwatchStore {
+    buffers: HashMap<u64, BufferSwatches>,
+}
+
+impl ColorSwatchStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether BUFFER_ID has any swatches.
+    pub fn has_buffer(&self, buffer_id: u64) -> bool {
+        self.buffers.get(&buffer_id).is_some_and(|b| !b.nodes.is_empty())
+    }
+
+    /// Replace the swatches of BUFFER_ID that start in [START, END) with
+    /// SWATCHES, as (start, end, color).  Empty ranges are ignored.
+    pub fn set_range(
+        &mut self,
+        buffer_id: u64,
+        start: i64,
+        end: i64,
+        swatches: impl IntoIterator<Item = (i64, i64, u32)>,
+    ) {
+        let buf = self.buffers.entry(buffer_id).or_default();
+        for node in buf.nodes_in(start, end) {
+            let begin = buf.tree.node_begin(node);
+            if begin >= start && begin < end {
+                buf.remove(node);
+            }
+        }
+        for (lit_start, lit_end, color) in swatches {
+            if lit_end <= lit_start {
+                continue;
+            }
+            // Text typed at either edge is not part of the literal
+            let node = buf.tree.alloc_node(true, false, color as u64);
+            buf.tree.insert(node, lit_start, lit_end);
+            buf.nodes.push(node);
+        }
+        if buf.nodes.is_empty() {
+            self.buffers.remove(&buffer_id);
+        }
+    }
+
+    /// Remove all swatches of BUFFER_ID.
+    pub fn clear_buffer(&mut self, buffer_id: u64) {
+        self.buffers.remove(&buffer_id);
+    }
+
+    /// Move swatches of BUFFER_ID after an edit at POS that deleted
+    /// DELETED characters and inserted INSERTED characters.  Swatches
+    /// whose literal was deleted entirely are removed; partly edited
+    /// literals keep theirs until Lisp rescans them.
+    pub fn adjust(&mut self, buffer_id: u64, pos: i64, inserted: i64, deleted: i64) {
+        let Some(buf) = self.buffers.get_mut(&buffer_id) else {
+            return;
+        };
+        if deleted > 0 {
+            buf.tree.delete_gap(pos, deleted);
+            for node in buf.nodes_in(pos, pos + 1) {
+                if buf.tree.node_end(node) <= buf.tree.node_begin(node) {
+                    buf.remove(node);
+                }
+            }
+        }
+        if inserted > 0 {
+            buf.tree.insert_gap(pos, inserted, false);
+        }
+        if buf.nodes.is_empty() {
+            self.buffers.remove(&buffer_id);
+        }
+    }
+
+    /// Swatches of BUFFER_ID whose literal starts in [BEGIN, END),
+    /// ordered by start.
+    pub fn in_range(&mut self, buffer_id: u64, begin: i64, end: i64) -> Vec<ColorSwatch> {
+        let Some(buf) = self.buffers.get_mut(&buffer_id) else {
+            return Vec::new();
+        };
+        let mut result: Vec<ColorSwatch> = buf
+            .nodes_in(begin, end)
+            .into_iter()
+            .map(|node| ColorSwatch {
+                start: buf.tree.node_begin(node),
+                end: buf.tree.node_end(node),
+                color: buf.tree.node(node).data as u32,
+            })
+            .filter(|s| s.start >= begin && s.start < end)
+            .collect();
+        result.sort_by_key(|s| s.start);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn starts(store: &mut ColorSwatchStore, buffer_id: u64) -> Vec<(i64, u32)> {
+        store.in_range(buffer_id, 0, 1000).iter().map(|s| (s.start, s.color)).collect()
+    }
+
+    #[test]
+    fn set_range_replaces_swatches_of_the_chunk() {
+        let mut swatches = ColorSwatchStore::new();
+        swatches.set_range(1, 0, 100, [(10, 17, 0xff0000), (50, 54, 0x00ff00), (60, 60, 0)]);
+        swatches.set_range(1, 100, 200, [(120, 127, 0x0000ff)]);
+        assert_eq!(starts(&mut swatches, 1), vec![(10, 0xff0000), (50, 0x00ff00), (120, 0x0000ff)]);
+
+        // Rescanning the first chunk leaves the second alone
+        swatches.set_range(1, 0, 100, [(30, 37, 0xffffff)]);
+        assert_eq!(starts(&mut swatches, 1), vec![(30, 0xffffff), (120, 0x0000ff)]);
+        assert_eq!(swatches.in_range(1, 31, 200).len(), 1);
+
+        swatches.set_range(2, 0, 10, []);
+        assert!(!swatches.has_buffer(2));
+        swatches.clear_buffer(1);
+        assert!(!swatches.has_buffer(1));
+    }
+
+    #[test]
+    fn swatches_follow_edits() {
+        let mut swatches = ColorSwatchStore::new();
+        swatches.set_range(1, 0, 100, [(10, 17, 0xff0000), (30, 37, 0x00ff00)]);
+
+        swatches.adjust(1, 0, 5, 0);
+        assert_eq!(starts(&mut swatches, 1), vec![(15, 0xff0000), (35, 0x00ff00)]);
+
+        // Deleting a whole literal removes its swatch
+        swatches.adjust(1, 14, 0, 9);
+        assert_eq!(starts(&mut swatches, 1), vec![(26, 0x00ff00)]);
+        let s = swatches.in_range(1, 0, 100)[0];
+        assert_eq!(s.end, 33);
+    }
+}
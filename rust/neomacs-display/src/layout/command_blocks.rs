@@ -0,0 +1,240 @@
+//! Command blocks for shell buffers (comint, eshell).
+//!
+//! Lisp marks each finished command of a shell buffer with a text
+//! property spanning its prompt, input and output, and registers the
+//! blocks in view with their exit status, run time and whether the
+//! output is collapsed.  After a window is laid out, the rows of each
+//! block get a tinted background and a status bar on the left edge, and
+//! the prompt row shows a collapse indicator, a status icon and the
+//! command's run time at the right edge of the text area.  Collapsing
+//! hides the output with an `invisible' property on the Lisp side; the
+//! engine only shows how many lines are hidden.
+
+use std::collections::HashMap;
+
+/// One command: its prompt and input, then its output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandBlock {
+    /// Beginning of the prompt.
+    pub start: i64,
+    /// Beginning of the output.
+    pub output_start: i64,
+    /// End of the output.
+    pub end: i64,
+    /// Exit status, if the shell reported one.
+    pub exit_code: Option<i32>,
+    /// Run time in milliseconds, if known.
+    pub duration_ms: Option<u64>,
+    /// Whether the output is hidden.
+    pub collapsed: bool,
+    /// Lines of output hidden while collapsed.
+    pub hidden_lines: u32,
+}
+
+impl CommandBlock {
+    /// Icon for the exit status: a check mark for success, a cross for
+    /// failure and a dot when the status is unknown.
+    pub fn status_icon(&self) -> char {
+        match self.exit_code {
+            Some(0) => '✓',
+            Some(_) => '✗',
+            None => '•',
+        }
+    }
+
+    /// Text drawn at the right of the prompt row, e.g. "▾ ✓ 1.2s" or
+    /// "▸ 12 lines ✗ 3 250ms".
+    pub fn label(&self) -> String {
+        let mut label = String::from(if self.collapsed { '▸' } else { '▾' });
+        if self.collapsed && self.hidden_lines > 0 {
+            label.push_str(&format!(
+                " {} line{}",
+                self.hidden_lines,
+                if self.hidden_lines == 1 { "" } else { "s" },
+            ));
+        }
+        label.push(' ');
+        label.push(self.status_icon());
+        if let Some(code) = self.exit_code.filter(|&c| c != 0) {
+            label.push_str(&format!(" {}", code));
+        }
+        if let Some(ms) = self.duration_ms {
+            label.push(' ');
+            label.push_str(&format_duration(ms));
+        }
+        label
+    }
+}
+
+/// Format a run time compactly: "850ms", "2.3s", "1m05s", "2h03m".
+pub fn format_duration(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if ms < 60_000 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else if ms < 3_600_000 {
+        format!("{}m{:02}s", ms / 60_000, ms / 1000 % 60)
+    } else {
+        format!("{}h{:02}m", ms / 3_600_000, ms / 60_000 % 60)
+    }
+}
+
+/// Colors of a buffer's blocks (sRGB pixels).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandBlockColors {
+    /// Background of block rows.
+    pub tint: u32,
+    /// Status bar and icon of successful commands.
+    pub success: u32,
+    /// Status bar and icon of failed commands.
+    pub failure: u32,
+    /// Label text, and the status bar of commands without a status.
+    pub label: u32,
+}
+
+impl CommandBlockColors {
+    /// Color of the status bar and icon of BLOCK.
+    pub fn status(&self, block: &CommandBlock) -> u32 {
+        match block.exit_code {
+            Some(0) => self.success,
+            Some(_) => self.failure,
+            None => self.label,
+        }
+    }
+}
+
+struct BufferBlocks {
+    /// Blocks sorted by start.
+    blocks: Vec<CommandBlock>,
+    colors: CommandBlockColors,
+}
+
+/// Command blocks per buffer.
+pub struct CommandBlockStore {
+    buffers: HashMap<u64, BufferBlocks>,
+}
+
+impl CommandBlockStore {
+    pub fn new() -> Self {
+        Self { buffers: HashMap::new() }
+    }
+
+    /// Replace the blocks of BUFFER_ID.  An empty BLOCKS clears them.
+    pub fn set(&mut self, buffer_id: u64, mut blocks: Vec<CommandBlock>, colors: CommandBlockColors) {
+        if blocks.is_empty() {
+            self.buffers.remove(&buffer_id);
+            return;
+        }
+        blocks.sort_by_key(|b| b.start);
+        self.buffers.insert(buffer_id, BufferBlocks { blocks, colors });
+    }
+
+    /// Remove the blocks of BUFFER_ID.
+    pub fn clear_buffer(&mut self, buffer_id: u64) {
+        self.buffers.remove(&buffer_id);
+    }
+
+    pub fn has_buffer(&self, buffer_id: u64) -> bool {
+        self.buffers.contains_key(&buffer_id)
+    }
+
+    /// The colors of BUFFER_ID's blocks.
+    pub fn colors(&self, buffer_id: u64) -> Option<CommandBlockColors> {
+        self.buffers.get(&buffer_id).map(|b| b.colors)
+    }
+
+    /// The blocks of BUFFER_ID overlapping positions [BEGIN, END).
+    pub fn in_range(&self, buffer_id: u64, begin: i64, end: i64) -> &[CommandBlock] {
+        let Some(b) = self.buffers.get(&buffer_id) else {
+            return &[];
+        };
+        let first = b.blocks.partition_point(|blk| blk.end <= begin);
+        let last = b.blocks.partition_point(|blk| blk.start < end);
+        &b.blocks[first..last.max(first)]
+    }
+}
+
+impl Default for CommandBlockStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Rows of each of BLOCKS, given the buffer position each laid out row
+/// starts at: (block index, first row, last row) for each
+/// block with a row in view.  BLOCKS must be sorted by start; a row
+/// belongs to the block its first character is in.
+pub fn block_rows(blocks: &[CommandBlock], row_starts: &[i64]) -> Vec<(usize, usize, usize)> {
+    let mut spans: Vec<(usize, usize, usize)> = Vec::new();
+    for (r, &start) in row_starts.iter().enumerate() {
+        let i = blocks.partition_point(|b| b.start <= start);
+        if i == 0 || start >= blocks[i - 1].end {
+            continue;
+        }
+        let i = i - 1;
+        match spans.last_mut() {
+            Some(span) if span.0 == i && span.2 + 1 == r => span.2 = r,
+            _ => spans.push((i, r, r)),
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(start: i64, output_start: i64, end: i64, exit_code: Option<i32>) -> CommandBlock {
+        CommandBlock {
+            start, output_start, end, exit_code,
+            duration_ms: None, collapsed: false, hidden_lines: 0,
+        }
+    }
+
+    #[test]
+    fn durations_are_compact() {
+        assert_eq!(format_duration(850), "850ms");
+        assert_eq!(format_duration(2340), "2.3s");
+        assert_eq!(format_duration(65_000), "1m05s");
+        assert_eq!(format_duration(7_380_000), "2h03m");
+    }
+
+    #[test]
+    fn labels_show_status_and_collapsed_lines() {
+        let mut b = block(1, 5, 20, Some(0));
+        b.duration_ms = Some(1200);
+        assert_eq!(b.label(), "▾ ✓ 1.2s");
+        b.exit_code = Some(3);
+        b.collapsed = true;
+        b.hidden_lines = 12;
+        assert_eq!(b.label(), "▸ 12 lines ✗ 3 1.2s");
+        b.exit_code = None;
+        b.duration_ms = None;
+        b.hidden_lines = 1;
+        assert_eq!(b.label(), "▸ 1 line •");
+    }
+
+    #[test]
+    fn store_queries_overlapping_blocks() {
+        let colors = CommandBlockColors { tint: 1, success: 2, failure: 3, label: 4 };
+        let mut store = CommandBlockStore::new();
+        store.set(7, vec![block(30, 35, 50, None), block(1, 5, 30, Some(1))], colors);
+        assert!(store.has_buffer(7));
+        assert_eq!(store.in_range(7, 10, 31).len(), 2);
+        assert_eq!(store.in_range(7, 30, 40)[0].start, 30);
+        assert!(store.in_range(7, 50, 60).is_empty());
+        assert_eq!(store.colors(7).unwrap().status(&store.in_range(7, 1, 2)[0]), 3);
+        store.set(7, Vec::new(), colors);
+        assert!(!store.has_buffer(7));
+    }
+
+    #[test]
+    fn rows_group_by_block() {
+        let blocks = [block(1, 5, 20, Some(0)), block(20, 25, 40, Some(2))];
+        // Lines at 1, 10, 20 and 30, then a trailing prompt
+        let rows = [1, 10, 20, 30, 40];
+        assert_eq!(block_rows(&blocks, &rows), vec![(0, 0, 1), (1, 2, 3)]);
+        // Scrolled into the middle of the first block
+        assert_eq!(block_rows(&blocks, &rows[1..3]), vec![(0, 0, 0), (1, 1, 1)]);
+    }
+}
@@ -17,11 +17,12 @@ pub type EmacsWindow = *mut std::ffi::c_void;
 /// Opaque pointer to an Emacs buffer (struct buffer *)
 pub type EmacsBuffer = *mut std::ffi::c_void;
 
-extern "C" {
-    // ========================================================================
-    // Buffer text access
-    // ========================================================================
+// ========================================================================
+// Buffer text access
+// ========================================================================
 
+#[cfg(not(test))]
+extern "C" {
     /// Convert character position to byte position in a buffer.
     /// Uses Emacs byte-charpos cache (O(log n)).
     /// Works directly on the buffer struct — no set_buffer_internal_1 needed.
@@ -29,12 +30,22 @@ extern "C" {
         buffer: EmacsBuffer,
         charpos: i64,
     ) -> i64;
+}
 
+/// Tests link without Emacs, and have no buffers to read: every
+/// position maps outside the text.
+#[cfg(test)]
+pub unsafe fn neomacs_buf_charpos_to_bytepos(_buffer: EmacsBuffer, _charpos: i64) -> i64 {
+    0
+}
+
+extern "C" {
     // Buffer bulk text copy is now handled directly in Rust via
     // emacs_types::gap_buffer_copy_text() — no FFI call needed.
     //
     // Buffer metadata (bounds, point, tab_width, etc.) is also read
-    // directly from Emacs structs in Rust — see emacs_types.rs.
+    // directly from Emacs structs in Rust — see emacs_types.rs.  Layout
+    // reads both through buffer_snapshot::BufferSnapshot.
 
     // ========================================================================
     // Window geometry
@@ -51,6 +62,16 @@ extern "C" {
         params: *mut WindowParamsFFI,
     ) -> c_int;
 
+    /// Describe all windows of a frame, with the faces and display
+    /// property ranges of their visible text, into ARENA (CAP bytes,
+    /// 8-byte aligned).  Returns the size of the description, written
+    /// only if it fits, or -1 on error.  See `frame_desc`.
+    pub fn neomacs_layout_describe_frame(
+        frame: EmacsFrame,
+        arena: *mut u8,
+        cap: usize,
+    ) -> i64;
+
     // ========================================================================
     // Face resolution
     // ========================================================================
@@ -112,6 +133,16 @@ extern "C" {
         lines_above: c_int,
     ) -> i64;
 
+    /// Set window_start so that point's screen line is ROWS_ABOVE screen
+    /// lines below it, counting continuation lines and skipping invisible
+    /// text.  Returns the new window_start charpos.
+    pub fn neomacs_layout_anchor_window_start(
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        point: i64,
+        rows_above: c_int,
+    ) -> i64;
+
     /// Set window_end_pos on an Emacs window (for window-end Lisp function).
     pub fn neomacs_layout_set_window_end(
         window: EmacsWindow,
@@ -128,6 +159,9 @@ extern "C" {
         vpos: c_int,
     );
 
+    /// Set a window's hscroll, as automatic hscrolling moved it.
+    pub fn neomacs_layout_set_hscroll(window: EmacsWindow, hscroll: c_int);
+
     // ========================================================================
     // Fontification callback
     // ========================================================================
@@ -154,6 +188,24 @@ extern "C" {
         next_visible_out: *mut i64,
     ) -> c_int;
 
+    // ========================================================================
+    // Static compositions
+    // ========================================================================
+
+    /// Check for a static composition (`composition' text property) at
+    /// charpos.  Writes the composed characters into str_buf as UTF-8 and
+    /// returns the byte count, with *end_out set to the end of the composed
+    /// text and *width_out to its width in columns.  Returns 0 if there is
+    /// none; *end_out is then where the next composition starts.
+    pub fn neomacs_layout_composition_at(
+        buffer: EmacsBuffer,
+        charpos: i64,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        end_out: *mut i64,
+        width_out: *mut c_int,
+    ) -> c_int;
+
     // ========================================================================
     // Mode-line
     // ========================================================================
@@ -314,28 +366,63 @@ extern "C" {
     // Line height / spacing
     // ========================================================================
 
-    /// Check line-height and line-spacing text properties at a position.
-    /// Returns extra height (pixels) beyond base_height for the row.
+    /// Check line-height and line-spacing text properties at a position
+    /// (the newline ending a line).  Returns the extra pixels beyond
+    /// base_height above the line's text (line-height) and below it
+    /// (line-spacing).
     pub fn neomacs_layout_check_line_spacing(
         buffer: EmacsBuffer,
         window: EmacsWindow,
         charpos: i64,
         base_height: f32,
-        extra_height_out: *mut f32,
+        extra_above_out: *mut f32,
+        extra_below_out: *mut f32,
     ) -> c_int;
 
-    /// Check line-prefix or wrap-prefix text property at a position.
-    /// prefix_type: 0=line-prefix, 1=wrap-prefix
-    /// Returns prefix width in columns via width_out.
-    /// width_out = -1.0 means no override (use window default).
+    /// Resolve the line-prefix (prefix_type 0) or wrap-prefix (1) of the
+    /// row starting at charpos, from text/overlay properties or the buffer.
+    /// Returns 0 = none, 1 = string (bytes in str_buf), 2 = space of
+    /// width_out pixels, 3 = hanging indent (line text in str_buf, extra
+    /// columns in width_out).  See `line_prefix::LinePrefixKind`.
     pub fn neomacs_layout_check_line_prefix(
         buffer: EmacsBuffer,
         window: EmacsWindow,
         charpos: i64,
         prefix_type: c_int,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        str_len_out: *mut c_int,
         width_out: *mut f32,
     ) -> c_int;
 
+    /// Alignment of the line starting at a position, from its
+    /// `paragraph-align' text or overlay property.
+    /// Returns 0=left (or none), 1=right, 2=center, 3=full.
+    pub fn neomacs_layout_paragraph_align(
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+    ) -> c_int;
+
+    /// Whether a window is in vertical writing mode (its
+    /// `vertical-writing' window parameter is non-nil).
+    pub fn neomacs_layout_vertical_writing(window: EmacsWindow) -> c_int;
+
+    /// Hyphenation settings of a buffer: the language of its
+    /// `neomacs-hyphenation' into lang_buf, the directory of pattern
+    /// files into dir_buf, and the columns a word-wrapped row may leave
+    /// empty before a word is hyphenated instead into ragged_cols_out.
+    /// Returns the length of the language, 0 if hyphenation is off.
+    pub fn neomacs_layout_hyphenation(
+        buffer: EmacsBuffer,
+        lang_buf: *mut u8,
+        lang_buf_len: c_int,
+        dir_buf: *mut u8,
+        dir_buf_len: c_int,
+        dir_len_out: *mut c_int,
+        ragged_cols_out: *mut c_int,
+    ) -> c_int;
+
     /// Get fringe bitmap data for a given bitmap ID.
     /// Writes row data (one u16 per row) into bits_out.
     /// Returns number of rows written, 0 if bitmap not found.
@@ -347,6 +434,43 @@ extern "C" {
         height_out: *mut c_int,
         align_out: *mut c_int,
     ) -> c_int;
+
+    /// Collect the overlay arrows pointing into a window's buffer.
+    /// Writes up to max_arrows entries into arrows_out.
+    /// Returns the number of arrows written.
+    pub fn neomacs_layout_overlay_arrows(
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        arrows_out: *mut OverlayArrowFFI,
+        max_arrows: c_int,
+    ) -> c_int;
+
+    /// Resolve a window's fringe indicators from its
+    /// `fringe-indicator-alist' and `indicate-buffer-boundaries'.
+    /// Returns 1, or 0 if the window shows no buffer.
+    pub fn neomacs_layout_fringe_indicators(
+        window: EmacsWindow,
+        out: *mut FringeIndicatorsFFI,
+    ) -> c_int;
+
+    /// Foreground of a fringe bitmap given its own face by
+    /// `set-fringe-bitmap-face'.  Returns 1 if it has one, else 0.
+    pub fn neomacs_layout_fringe_bitmap_fg(
+        window: EmacsWindow,
+        bitmap_id: c_int,
+        fg_out: *mut u32,
+    ) -> c_int;
+
+    /// Collect the highlighted spans of a window's buffer: the active
+    /// region, when `redisplay-highlight-region-function' left it to
+    /// the engine, and the secondary selection.  Writes up to max_spans
+    /// entries into spans_out.  Returns the number of spans written.
+    pub fn neomacs_layout_region_spans(
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        spans_out: *mut RegionSpanFFI,
+        max_spans: c_int,
+    ) -> c_int;
 }
 
 /// FFI-safe line number configuration struct.
@@ -368,6 +492,95 @@ pub struct LineNumberConfigFFI {
     pub current_absolute: c_int,
     /// display-line-numbers-widen
     pub widen: c_int,
+    /// display-line-numbers-grow-only
+    pub grow_only: c_int,
+}
+
+/// FFI-safe overlay arrow (`overlay-arrow-position` and friends).
+/// Matches the C struct OverlayArrowFFI in fringe.c.
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct OverlayArrowFFI {
+    /// Position the arrow points at
+    pub charpos: i64,
+    /// Left fringe bitmap ID, 0 = draw `text` instead
+    pub bitmap: c_int,
+    /// Bytes of UTF-8 arrow string in `text`
+    pub text_len: c_int,
+    /// Arrow string, for windows without a left fringe
+    pub text: [u8; 32],
+}
+
+/// FFI-safe highlighted span of a buffer.
+/// Matches the C struct RegionSpanFFI in neomacsterm.c.
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct RegionSpanFFI {
+    /// First position highlighted and the one after the last
+    pub start: i64,
+    pub end: i64,
+    /// 0 for the region, 1 for the secondary selection
+    pub kind: c_int,
+    /// Colors of the face it is drawn in (sRGB pixels); the text keeps
+    /// its own foreground unless has_fg
+    pub bg: u32,
+    pub fg: u32,
+    pub has_fg: c_int,
+    /// Whether the face `:extend's past the end of lines
+    pub extend: c_int,
+}
+
+/// FFI-safe fringe indicators of a window.
+/// Matches the C struct FringeIndicatorsFFI in fringe.c.  Bitmap IDs
+/// are 0 for none; sides are 0 for none, 1 for left, 2 for right.
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct FringeIndicatorsFFI {
+    /// Row continued from the one above
+    pub continuation_left: c_int,
+    /// Row continued on the one below
+    pub continuation_right: c_int,
+    /// Text hidden by horizontal scrolling
+    pub truncation_left: c_int,
+    /// Text cut off at the right
+    pub truncation_right: c_int,
+    /// Lines after the end of the buffer (`indicate-empty-lines')
+    pub empty_line: c_int,
+    pub empty_line_side: c_int,
+    /// `indicate-buffer-boundaries': angle on the first row showing the
+    /// buffer's start
+    pub top: c_int,
+    pub top_side: c_int,
+    /// Angle on the last row showing the buffer's end
+    pub bottom: c_int,
+    pub bottom_side: c_int,
+    /// Both angles on one row, when on the same side
+    pub top_bottom: c_int,
+    /// Arrow on the first row when there is text above
+    pub up: c_int,
+    pub up_side: c_int,
+    /// Arrow on the last row when there is text below
+    pub down: c_int,
+    pub down_side: c_int,
+    /// `fringe' face colors
+    pub fg: u32,
+    pub bg: u32,
+}
+
+impl FringeIndicatorsFFI {
+    /// Emacs's default indicators, without buffer boundaries.
+    pub fn standard(fg: u32, bg: u32) -> Self {
+        FringeIndicatorsFFI {
+            continuation_left: 8,  // right-curly-arrow
+            continuation_right: 7, // left-curly-arrow
+            truncation_left: 3,    // left-arrow
+            truncation_right: 4,   // right-arrow
+            empty_line: 24,        // empty-line
+            fg,
+            bg,
+            ..Default::default()
+        }
+    }
 }
 
 /// FFI-safe display text property result.
@@ -514,8 +727,11 @@ pub struct WindowParamsFFI {
     pub extra_line_spacing: f32,
     /// Whether to show cursor in non-selected windows
     pub cursor_in_non_selected: c_int,
-    /// selective-display: 0=off, >0=hide lines indented more than N columns
+    /// selective-display: 0=off, >0=hide lines indented more than N
+    /// columns, -1 (t) = hide the rest of lines after a ^M
     pub selective_display: c_int,
+    /// selective-display-ellipses: show `...' for hidden lines
+    pub selective_display_ellipses: c_int,
     /// escape-glyph face foreground color for control chars
     pub escape_glyph_fg: u32,
     /// nobreak-char-display: 0=off, 1=highlight, 2=escape notation
@@ -537,6 +753,26 @@ pub struct WindowParamsFFI {
     pub buffer_file_name: *const std::ffi::c_char,
     /// Whether the buffer has unsaved modifications
     pub modified: c_int,
+    /// Vertical scroll bar area (frame-absolute x and width, 0 width if none)
+    pub scroll_bar_x: f32,
+    pub scroll_bar_width: f32,
+    /// Buffer text plus overlay modification count
+    pub modiff: i64,
+    /// bidi-display-reordering (0 = off)
+    pub bidi_reordering: c_int,
+    /// bidi-paragraph-direction: 0 = per paragraph, 1 = left-to-right,
+    /// 2 = right-to-left
+    pub bidi_paragraph_direction: c_int,
+    /// auto-hscroll-mode: 0 = off or suspended, 1 = on, 2 = current-line
+    pub auto_hscroll: c_int,
+    /// hscroll-margin in columns
+    pub hscroll_margin: c_int,
+    /// hscroll-step in columns, or as a fraction of the text area
+    /// width (negative when it is a number of columns)
+    pub hscroll_step: c_int,
+    pub hscroll_step_fraction: f32,
+    /// Smallest hscroll, set by scroll-left and scroll-right
+    pub min_hscroll: c_int,
 }
 
 impl Default for WindowParamsFFI {
@@ -611,4 +847,13 @@ pub struct FaceDataFFI {
     pub underline_position: c_int,
     /// Underline thickness in pixels (font->underline_thickness, >=1)
     pub underline_thickness: c_int,
+    /// Text shadow or glow (0=none, 1=enabled)
+    pub text_shadow: c_int,
+    /// Text shadow color (sRGB pixel)
+    pub text_shadow_color: u32,
+    /// Text shadow offset from the glyphs in pixels
+    pub text_shadow_x_offset: c_int,
+    pub text_shadow_y_offset: c_int,
+    /// Text shadow blur radius in pixels
+    pub text_shadow_blur: c_int,
 }
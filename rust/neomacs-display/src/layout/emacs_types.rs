@@ -6,9 +6,13 @@
 //!
 //! # Safety
 //!
-//! All offsets are validated at runtime against C `offsetof()` values on
-//! first use. A mismatch (e.g., from `HAVE_TREE_SITTER` changing the field
-//! count) panics with a clear diagnostic message.
+//! Emacs reports an ABI version and a hash of its offset table at startup
+//! (`check_abi`), before Rust reads the table, so a library built for a
+//! different table fails with a clear message rather than corrupting
+//! memory.  The offsets are then validated against C `offsetof()` values
+//! and, when the build found the Emacs headers, against the layout bindgen
+//! generated from them.  A mismatch (e.g., from `HAVE_TREE_SITTER` changing
+//! the field count) is reported with a clear diagnostic message.
 //!
 //! These types must only be used on the Emacs main thread during layout,
 //! when buffer content is stable (after `ensure_fontified`, before GC).
@@ -656,50 +660,70 @@ pub unsafe fn frame_window_count(frame: *const c_void) -> i32 {
 // Struct offset validation
 // ============================================================================
 
-/// Struct offsets reported by C `neomacs_get_struct_offsets()`.
-/// Each field stores the `offsetof()` value for the corresponding C struct field.
-#[repr(C)]
-#[derive(Debug, Clone)]
-pub struct StructOffsets {
+/// Define `StructOffsets` with the fields listed, all `usize`, and the
+/// list of their names for diagnostics.
+macro_rules! struct_offsets {
+    ($($field:ident),* $(,)?) => {
+        /// Struct offsets reported by C `neomacs_get_struct_offsets()`.
+        /// Each field stores the `offsetof()` value for the corresponding C struct field.
+        #[repr(C)]
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct StructOffsets {
+            $(pub $field: usize,)*
+        }
+
+        impl StructOffsets {
+            /// Names of the fields, in order.
+            pub const FIELDS: &'static [&'static str] = &[$(stringify!($field)),*];
+
+            /// Values of the fields, in order.
+            pub fn values(&self) -> Vec<usize> {
+                vec![$(self.$field),*]
+            }
+        }
+    };
+}
+
+struct_offsets! {
     // struct buffer offsets
-    pub buf_text: usize,
-    pub buf_pt: usize,
-    pub buf_pt_byte: usize,
-    pub buf_begv: usize,
-    pub buf_begv_byte: usize,
-    pub buf_zv: usize,
-    pub buf_zv_byte: usize,
-    pub buf_base_buffer: usize,
-    pub buf_lisp_field_count: usize,
+    buf_text,
+    buf_pt,
+    buf_pt_byte,
+    buf_begv,
+    buf_begv_byte,
+    buf_zv,
+    buf_zv_byte,
+    buf_base_buffer,
+    buf_lisp_field_count,
     // struct buffer_text offsets
-    pub buftext_beg: usize,
-    pub buftext_gpt: usize,
-    pub buftext_z: usize,
-    pub buftext_gpt_byte: usize,
-    pub buftext_z_byte: usize,
-    pub buftext_gap_size: usize,
+    buftext_beg,
+    buftext_gpt,
+    buftext_z,
+    buftext_gpt_byte,
+    buftext_z_byte,
+    buftext_gap_size,
     // BVAR field offsets (for index validation)
-    pub buf_tab_width: usize,
-    pub buf_truncate_lines: usize,
-    pub buf_enable_multibyte: usize,
-    pub buf_pt_marker: usize,
-    pub buf_begv_marker: usize,
-    pub buf_zv_marker: usize,
-    pub buf_word_wrap: usize,
-    pub buf_selective_display: usize,
+    buf_tab_width,
+    buf_truncate_lines,
+    buf_enable_multibyte,
+    buf_pt_marker,
+    buf_begv_marker,
+    buf_zv_marker,
+    buf_word_wrap,
+    buf_selective_display,
     // struct window offsets
-    pub win_frame: usize,
-    pub win_next: usize,
-    pub win_contents: usize,
+    win_frame,
+    win_next,
+    win_contents,
     // struct frame offsets
-    pub frame_root_window: usize,
-    pub frame_selected_window: usize,
-    pub frame_minibuffer_window: usize,
+    frame_root_window,
+    frame_selected_window,
+    frame_minibuffer_window,
     // Pseudovector type constants
-    pub pvec_window: usize,
-    pub pvec_buffer: usize,
-    pub pseudovector_area_bits: usize,
-    pub pseudovector_flag: usize,
+    pvec_window,
+    pvec_buffer,
+    pseudovector_area_bits,
+    pseudovector_flag,
 }
 
 impl Default for StructOffsets {
@@ -708,86 +732,195 @@ impl Default for StructOffsets {
     }
 }
 
+// ============================================================================
+// ABI handshake
+// ============================================================================
+
+/// Version of the offset table exchanged with C.  Bump it together with
+/// `NEOMACS_FFI_ABI_VERSION` in neomacsterm.c whenever `StructOffsets`
+/// changes.
+pub const FFI_ABI_VERSION: u32 = 1;
+
+/// What C `neomacs_ffi_abi()` reports about its offset table, before
+/// Rust asks for the table itself.
+#[repr(C)]
+#[derive(Debug, Default)]
+struct FfiAbi {
+    version: u32,
+    /// `sizeof (struct neomacs_struct_offsets)`
+    offsets_size: usize,
+    /// `schema_hash()` of the table as C fills it in
+    schema_hash: u64,
+}
+
+/// FNV-1a over the ABI version, the table size and the offsets, one
+/// 64-bit word at a time.  Must match `neomacs_ffi_abi()` in C.
+pub fn schema_hash(off: &StructOffsets) -> u64 {
+    let words = [FFI_ABI_VERSION as u64, std::mem::size_of::<StructOffsets>() as u64]
+        .into_iter()
+        .chain(off.values().into_iter().map(|v| v as u64));
+    words.fold(0xcbf2_9ce4_8422_2325, |h, w| (h ^ w).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// Fields whose offsets differ between EXPECTED and ACTUAL, as
+/// "name: expected N, got M".
+pub fn layout_differences(expected: &StructOffsets, actual: &StructOffsets) -> Vec<String> {
+    StructOffsets::FIELDS
+        .iter()
+        .zip(expected.values().into_iter().zip(actual.values()))
+        .filter(|(_, (e, a))| e != a)
+        .map(|(name, (e, a))| format!("{}: expected {}, got {}", name, e, a))
+        .collect()
+}
+
+/// Offsets bindgen computed from the Emacs headers when the library was
+/// built (see build.rs), or None if the headers were not found.
+mod generated {
+    include!(concat!(env!("OUT_DIR"), "/emacs_layout.rs"));
+}
+
+#[cfg(not(test))]
 extern "C" {
+    fn neomacs_ffi_abi(out: *mut FfiAbi);
     fn neomacs_get_struct_offsets(out: *mut StructOffsets);
     fn neomacs_layout_marker_position(marker: LispObject) -> i64;
 }
 
-/// Lazily-initialized and validated struct offsets.
-static OFFSETS: OnceLock<StructOffsets> = OnceLock::new();
+// Tests link without Emacs: it reports no ABI, so the handshake fails
+// and nothing reads Emacs memory.
+#[cfg(test)]
+unsafe fn neomacs_ffi_abi(_out: *mut FfiAbi) {}
+#[cfg(test)]
+unsafe fn neomacs_get_struct_offsets(_out: *mut StructOffsets) {}
+#[cfg(test)]
+unsafe fn neomacs_layout_marker_position(_marker: LispObject) -> i64 {
+    1
+}
+
+/// Struct offsets, or why they could not be used, fetched and validated
+/// on first use.
+static OFFSETS: OnceLock<Result<StructOffsets, String>> = OnceLock::new();
 
 /// Get validated struct offsets. Initializes and validates on first call.
 ///
 /// # Panics
 ///
-/// Panics if C struct offsets don't match our Rust assumptions.
-/// This catches ABI mismatches from config changes (e.g., HAVE_TREE_SITTER).
+/// Panics if C struct offsets don't match our Rust assumptions.  C
+/// checks `check_abi()` at startup, so this only fires if it did not.
 fn offsets() -> &'static StructOffsets {
-    OFFSETS.get_or_init(|| {
-        let mut off = StructOffsets::default();
-        unsafe { neomacs_get_struct_offsets(&mut off) };
-        validate_offsets(&off);
-        log::info!("Emacs struct offsets validated successfully (lisp_fields={})", off.buf_lisp_field_count);
-        off
-    })
+    match OFFSETS.get_or_init(load_offsets) {
+        Ok(off) => off,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+/// Shake hands with C and fetch its offsets.  The table is only fetched
+/// once C has said it is the table we expect, so a mismatched build
+/// gets an error instead of a C write past the end of our table.
+fn load_offsets() -> Result<StructOffsets, String> {
+    let mut abi = FfiAbi::default();
+    unsafe { neomacs_ffi_abi(&mut abi) };
+    let size = std::mem::size_of::<StructOffsets>();
+    if abi.version != FFI_ABI_VERSION || abi.offsets_size != size {
+        return Err(format!(
+            "Emacs and the display library disagree on the FFI ABI: Emacs has \
+             version {} with a {}-byte offset table, the library version {} with \
+             {} bytes.  Rebuild both from the same tree.",
+            abi.version, abi.offsets_size, FFI_ABI_VERSION, size
+        ));
+    }
+
+    let mut off = StructOffsets::default();
+    unsafe { neomacs_get_struct_offsets(&mut off) };
+    let hash = schema_hash(&off);
+    if hash != abi.schema_hash {
+        return Err(format!(
+            "FFI offset table hash mismatch: Emacs reports {:#018x}, the table it \
+             sent hashes to {:#018x}.  neomacsterm.c and emacs_types.rs list the \
+             table's fields differently.",
+            abi.schema_hash, hash
+        ));
+    }
+
+    if let Some(built) = &generated::LAYOUT {
+        let diffs = layout_differences(built, &off);
+        if !diffs.is_empty() {
+            return Err(format!(
+                "Emacs struct layout differs from the headers the display library \
+                 was built against ({}).  Rebuild the library against this Emacs.",
+                diffs.join("; ")
+            ));
+        }
+    }
+
+    validate_offsets(&off)?;
+    log::info!(
+        "Emacs struct offsets validated successfully (lisp_fields={}, schema={:#018x}, from headers: {})",
+        off.buf_lisp_field_count, hash, generated::LAYOUT.is_some()
+    );
+    Ok(off)
 }
 
 /// Validate that our compile-time assumptions match C's struct layout.
-fn validate_offsets(off: &StructOffsets) {
+fn validate_offsets(off: &StructOffsets) -> Result<(), String> {
+    let check = |what: &str, got: usize, expected: usize| {
+        if got == expected {
+            Ok(())
+        } else {
+            Err(format!("{} mismatch: expected {}, got {}", what, expected, got))
+        }
+    };
+
     // Validate buffer_text field offsets (first 6 fields, all 8 bytes, no padding)
-    assert_eq!(off.buftext_beg, 0,
-        "buffer_text.beg offset mismatch: expected 0, got {}", off.buftext_beg);
-    assert_eq!(off.buftext_gpt, 8,
-        "buffer_text.gpt offset mismatch: expected 8, got {}", off.buftext_gpt);
-    assert_eq!(off.buftext_z, 16,
-        "buffer_text.z offset mismatch: expected 16, got {}", off.buftext_z);
-    assert_eq!(off.buftext_gpt_byte, 24,
-        "buffer_text.gpt_byte offset mismatch: expected 24, got {}", off.buftext_gpt_byte);
-    assert_eq!(off.buftext_z_byte, 32,
-        "buffer_text.z_byte offset mismatch: expected 32, got {}", off.buftext_z_byte);
-    assert_eq!(off.buftext_gap_size, 40,
-        "buffer_text.gap_size offset mismatch: expected 40, got {}", off.buftext_gap_size);
+    check("buffer_text.beg offset", off.buftext_beg, 0)?;
+    check("buffer_text.gpt offset", off.buftext_gpt, 8)?;
+    check("buffer_text.z offset", off.buftext_z, 16)?;
+    check("buffer_text.gpt_byte offset", off.buftext_gpt_byte, 24)?;
+    check("buffer_text.z_byte offset", off.buftext_z_byte, 32)?;
+    check("buffer_text.gap_size offset", off.buftext_gap_size, 40)?;
 
     // Validate Lisp_Object field count
-    assert_eq!(off.buf_lisp_field_count, BUFFER_LISP_FIELD_COUNT,
-        "Buffer Lisp field count mismatch: expected {}, got {}. \
-         Check HAVE_TREE_SITTER and other config flags.",
-        BUFFER_LISP_FIELD_COUNT, off.buf_lisp_field_count);
+    check(
+        "Buffer Lisp field count (check HAVE_TREE_SITTER and other config flags)",
+        off.buf_lisp_field_count,
+        BUFFER_LISP_FIELD_COUNT,
+    )?;
 
     // Validate BVAR index calculations: offset should be 8 + index * 8
     let check_bvar = |name: &str, c_offset: usize, index: usize| {
-        let expected = BUFFER_LISP_FIELDS_OFFSET + index * 8;
-        assert_eq!(c_offset, expected,
-            "BVAR {} offset mismatch: C says {}, we computed {} (index {})",
-            name, c_offset, expected, index);
+        check(&format!("BVAR {} offset (index {})", name, index), c_offset,
+              BUFFER_LISP_FIELDS_OFFSET + index * 8)
     };
 
-    check_bvar("tab_width", off.buf_tab_width, bvar::TAB_WIDTH);
-    check_bvar("truncate_lines", off.buf_truncate_lines, bvar::TRUNCATE_LINES);
-    check_bvar("enable_multibyte_characters", off.buf_enable_multibyte, bvar::ENABLE_MULTIBYTE_CHARACTERS);
-    check_bvar("pt_marker", off.buf_pt_marker, bvar::PT_MARKER);
-    check_bvar("begv_marker", off.buf_begv_marker, bvar::BEGV_MARKER);
-    check_bvar("zv_marker", off.buf_zv_marker, bvar::ZV_MARKER);
-    check_bvar("word_wrap", off.buf_word_wrap, bvar::WORD_WRAP);
-    check_bvar("selective_display", off.buf_selective_display, bvar::SELECTIVE_DISPLAY);
+    check_bvar("tab_width", off.buf_tab_width, bvar::TAB_WIDTH)?;
+    check_bvar("truncate_lines", off.buf_truncate_lines, bvar::TRUNCATE_LINES)?;
+    check_bvar("enable_multibyte_characters", off.buf_enable_multibyte, bvar::ENABLE_MULTIBYTE_CHARACTERS)?;
+    check_bvar("pt_marker", off.buf_pt_marker, bvar::PT_MARKER)?;
+    check_bvar("begv_marker", off.buf_begv_marker, bvar::BEGV_MARKER)?;
+    check_bvar("zv_marker", off.buf_zv_marker, bvar::ZV_MARKER)?;
+    check_bvar("word_wrap", off.buf_word_wrap, bvar::WORD_WRAP)?;
+    check_bvar("selective_display", off.buf_selective_display, bvar::SELECTIVE_DISPLAY)?;
 
     // Validate pseudovector constants
-    assert_eq!(off.pseudovector_area_bits, PSEUDOVECTOR_AREA_BITS as usize,
-        "PSEUDOVECTOR_AREA_BITS mismatch: C={}, Rust={}",
-        off.pseudovector_area_bits, PSEUDOVECTOR_AREA_BITS);
-    assert_eq!(off.pseudovector_flag, PSEUDOVECTOR_FLAG as usize,
-        "PSEUDOVECTOR_FLAG mismatch: C={}, Rust={}",
-        off.pseudovector_flag, PSEUDOVECTOR_FLAG);
-    assert_eq!(off.pvec_window, PVEC_WINDOW as usize,
-        "PVEC_WINDOW mismatch: C={}, Rust={}", off.pvec_window, PVEC_WINDOW);
-    assert_eq!(off.pvec_buffer, PVEC_BUFFER as usize,
-        "PVEC_BUFFER mismatch: C={}, Rust={}", off.pvec_buffer, PVEC_BUFFER);
+    check("PSEUDOVECTOR_AREA_BITS", off.pseudovector_area_bits, PSEUDOVECTOR_AREA_BITS as usize)?;
+    check("PSEUDOVECTOR_FLAG", off.pseudovector_flag, PSEUDOVECTOR_FLAG as usize)?;
+    check("PVEC_WINDOW", off.pvec_window, PVEC_WINDOW as usize)?;
+    check("PVEC_BUFFER", off.pvec_buffer, PVEC_BUFFER as usize)?;
 
     // Log window/frame offsets (validated dynamically, not hardcoded)
     log::info!("Window offsets: frame={}, next={}, contents={}",
         off.win_frame, off.win_next, off.win_contents);
     log::info!("Frame offsets: root_window={}, selected_window={}, minibuffer_window={}",
         off.frame_root_window, off.frame_selected_window, off.frame_minibuffer_window);
+    Ok(())
+}
+
+/// Check that Emacs and this library agree on the structs layout reads
+/// directly: the ABI version, the offset table and, when the library
+/// was built against the Emacs headers, every offset in it.  Runs the
+/// handshake on first call.
+pub fn check_abi() -> Result<(), String> {
+    OFFSETS.get_or_init(load_offsets).as_ref().map(|_| ()).map_err(Clone::clone)
 }
 
 /// Explicitly trigger offset validation. Call this on first layout frame.
@@ -797,3 +930,27 @@ pub fn ensure_offsets_valid() -> bool {
     let _ = offsets(); // triggers validation if needed
     first
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_hash_and_layout_differences() {
+        let mut a = StructOffsets { buf_text: 16, pvec_window: 11, ..Default::default() };
+        let b = a.clone();
+        assert_eq!(StructOffsets::FIELDS.len(), std::mem::size_of::<StructOffsets>() / 8);
+        assert_eq!(schema_hash(&a), schema_hash(&b));
+        assert!(layout_differences(&a, &b).is_empty());
+
+        a.win_next = 24;
+        assert_ne!(schema_hash(&a), schema_hash(&b));
+        assert_eq!(layout_differences(&b, &a), vec!["win_next: expected 0, got 24".to_string()]);
+    }
+
+    #[test]
+    fn handshake_without_emacs_fails() {
+        let err = load_offsets().unwrap_err();
+        assert!(err.contains("disagree on the FFI ABI"), "{}", err);
+    }
+}
@@ -8,16 +8,42 @@ use std::ffi::CStr;
 use std::ffi::c_int;
 use std::ffi::c_void;
 
-use crate::core::face::{Face, FaceAttributes, UnderlineStyle, BoxType};
-use crate::core::frame_glyphs::{CursorStyle, FrameGlyphBuffer, StipplePattern};
+use crate::core::face::{Face, FaceAttributes, UnderlineStyle, BoxType, TextShadow};
+use crate::core::frame_glyphs::{
+    CursorStyle, FrameGlyph, FrameGlyphBuffer, RegionPulse, SelectionKind, SelectionRun, StipplePattern,
+};
+use crate::core::bidi::BidiDir;
 use crate::core::types::{Color, Rect};
 use super::types::*;
 use super::emacs_ffi::*;
 use super::unicode::*;
 use super::hit_test::*;
 use super::status_line::*;
-use super::bidi_layout::reorder_row_bidi;
+use super::bidi_layout::WindowBidi;
 use super::font_metrics::FontMetricsService;
+use super::ghost_text::GhostText;
+use super::annotations::{AnnotationStore, AnnotationStyle, stack_boxes};
+use super::scroll_bar_marks::{ScrollBarMarks, TICK_HEIGHT};
+use super::rectangle::{RectangleRegion, RectangleRegions};
+use super::virtual_space::{VirtualCursors, shifted_column};
+use super::table::{Table, TableGeometry, TableStore};
+use super::command_blocks::{block_rows, CommandBlockStore};
+use super::region_pulse::{row_spans, RegionPulses, PEAK_ALPHA};
+use super::link_spans::LinkStore;
+use super::color_swatches::ColorSwatchStore;
+use super::breadcrumb_bar::BreadcrumbBars;
+use super::scroll_anchor::{ScrollAnchor, ScrollAnchors};
+use super::paragraph_align::{row_shifts, ParagraphAlign, RowItem};
+use super::line_prefix::{hanging_indent_columns, LinePrefixKind};
+use super::line_break::break_between;
+use super::hyphenation::Hyphenator;
+use super::vertical::VerticalArea;
+use super::hscroll::{char_extent, point_line, AutoHscroll, HscrollPolicy, LINE_SCAN_LIMIT};
+use super::chat_view::{input_rows, ChatViewStore, INPUT_SCAN_LIMIT, MAX_INPUT_ROWS};
+use super::selection::{row_runs, SelectionRow};
+use super::buffer_snapshot::BufferSnapshot;
+use super::frame_desc::{FrameDescription, WindowCache};
+use super::host::{EmacsHost, LayoutHost};
 
 /// Maximum number of characters in a ligature run before forced flush.
 const MAX_LIGATURE_RUN_LEN: usize = 64;
@@ -105,6 +131,64 @@ fn run_is_pure_ligature(run: &LigatureRunBuffer) -> bool {
     run.chars.iter().all(|&ch| is_ligature_char(ch))
 }
 
+/// Number of rows of the laid out ROWS' average height that fit in
+/// TEXT_HEIGHT, at most MAX_ROWS (the count for default-height rows).
+/// Scroll targets are expressed in rows, and taller rows mean fewer fit.
+fn rows_that_fit(rows: &[HitRow], text_height: f32, max_rows: i32) -> i32 {
+    let (Some(first), Some(last)) = (rows.first(), rows.last()) else {
+        return max_rows;
+    };
+    let avg = (last.y_end - first.y_start) / rows.len() as f32;
+    if avg <= 0.0 {
+        return max_rows;
+    }
+    ((text_height / avg).floor() as i32).clamp(1, max_rows.max(1))
+}
+
+/// Index of the screen row showing POS.  HIT_ROWS are the rows laid
+/// out so far, ROW_Y the top of each row's text, and CURRENT the row
+/// still being laid out as (index, first charpos, last charpos).
+fn row_showing(pos: i64, hit_rows: &[HitRow], row_y: &[f32], current: (usize, i64, i64)) -> Option<usize> {
+    match hit_rows.iter().find(|h| h.charpos_start <= pos && pos < h.charpos_end) {
+        Some(h) => row_y.iter().position(|&y| y >= h.y_start && y < h.y_end),
+        None => {
+            let (row, start, end) = current;
+            (start <= pos && pos <= end && row < row_y.len()).then_some(row)
+        }
+    }
+}
+
+/// What the line number column of each row shows, as (number, line,
+/// is_current), the number None for a blank column.  ROW_LINES holds
+/// the line each row starts, None for continuation rows.  Numbers are
+/// absolute, or relative to POINT_LINE, or in visual mode counted in
+/// screen rows from POINT_ROW, where continuation rows get one too.
+fn line_number_labels(
+    config: &LineNumberConfigFFI,
+    row_lines: &[Option<i64>],
+    point_line: i64,
+    point_row: Option<usize>,
+) -> Vec<(Option<i64>, i64, bool)> {
+    let visual = config.mode == 3;
+    let mut line = row_lines.first().copied().flatten().unwrap_or(point_line);
+    row_lines.iter().enumerate().map(|(r, &starts)| {
+        if let Some(l) = starts {
+            line = l;
+        }
+        let current = match point_row {
+            Some(p) if visual => r == p,
+            _ => line == point_line,
+        };
+        let number = (visual || starts.is_some()).then(|| match config.mode {
+            2 | 3 if current && config.current_absolute != 0 => line + config.offset as i64,
+            2 => (line - point_line).abs(),
+            3 => point_row.map_or((line - point_line).abs(), |p| r.abs_diff(p) as i64),
+            _ => line + config.offset as i64,
+        });
+        (number, line, current)
+    }).collect()
+}
+
 /// Flush the accumulated ligature run as either individual chars or a composed glyph.
 fn flush_run(run: &LigatureRunBuffer, frame_glyphs: &mut FrameGlyphBuffer, ligatures: bool) {
     if run.is_empty() {
@@ -150,8 +234,11 @@ fn flush_run(run: &LigatureRunBuffer, frame_glyphs: &mut FrameGlyphBuffer, ligat
 /// Called on the Emacs thread during redisplay. Reads buffer data via FFI,
 /// resolves faces, computes layout, and produces a FrameGlyphBuffer.
 pub struct LayoutEngine {
-    /// Reusable text buffer to avoid allocation per frame
+    /// Reusable text buffer to avoid allocation per frame, lent to each
+    /// window's `BufferSnapshot`
     text_buf: Vec<u8>,
+    /// Description of the frame being laid out, kept for its arena
+    frame_desc: FrameDescription,
     /// Cached face data to avoid redundant FFI calls
     face_data: FaceDataFFI,
     /// Per-face ASCII width cache: actual glyph widths via text_extents().
@@ -171,6 +258,41 @@ pub struct LayoutEngine {
     font_metrics: Option<FontMetricsService>,
     /// Whether to use cosmic-text for font metrics instead of C FFI
     pub use_cosmic_metrics: bool,
+    /// Typewriter scrolling and centered column settings
+    pub writing_modes: WritingModes,
+    /// Inline suggestion shown after point, if any
+    pub ghost_text: Option<GhostText>,
+    /// Margin notes attached to buffer positions
+    pub annotations: AnnotationStore,
+    /// Tick marks drawn along vertical scroll bars
+    pub scroll_bar_marks: ScrollBarMarks,
+    /// Rectangular regions (`rectangle-mark-mode`) per window
+    pub rectangles: RectangleRegions,
+    /// Cursors drawn past the end of their line, per window
+    pub virtual_cursors: VirtualCursors,
+    /// Tables drawn with aligned columns, per buffer
+    pub tables: TableStore,
+    /// Shell command blocks, per buffer
+    pub command_blocks: CommandBlockStore,
+    /// Fading highlights over recently changed text
+    pub region_pulses: RegionPulses,
+    /// Clickable link ranges, per buffer
+    pub links: LinkStore,
+    /// Color swatches drawn before color literals, per buffer
+    pub color_swatches: ColorSwatchStore,
+    /// Breadcrumb bars shown in header lines, per window
+    pub breadcrumb_bars: BreadcrumbBars,
+    /// Pinned input and new message counts of chat buffers
+    pub chat_views: ChatViewStore,
+    /// Point's screen row per window, kept when the text reflows
+    pub scroll_anchors: ScrollAnchors,
+    /// Hyphenation patterns, per language
+    pub hyphenator: Hyphenator,
+    /// Rows that fit in each window at its last layout, given its row
+    /// heights; used to place point when scrolling
+    rows_fit: std::collections::HashMap<i64, i32>,
+    /// Line number column width of each window at its last layout
+    lnum_widths: std::collections::HashMap<i64, i32>,
 }
 
 impl LayoutEngine {
@@ -178,6 +300,7 @@ impl LayoutEngine {
     pub fn new() -> Self {
         Self {
             text_buf: Vec::with_capacity(64 * 1024), // 64KB initial
+            frame_desc: FrameDescription::default(),
             face_data: FaceDataFFI::default(),
             ascii_width_cache: std::collections::HashMap::new(),
             hit_data: Vec::new(),
@@ -186,12 +309,61 @@ impl LayoutEngine {
             default_font_family: String::new(),
             font_metrics: None,
             use_cosmic_metrics: true,
+            writing_modes: WritingModes::default(),
+            ghost_text: None,
+            annotations: AnnotationStore::new(),
+            scroll_bar_marks: ScrollBarMarks::new(),
+            rectangles: RectangleRegions::new(),
+            virtual_cursors: VirtualCursors::new(),
+            tables: TableStore::new(),
+            command_blocks: CommandBlockStore::new(),
+            region_pulses: RegionPulses::new(),
+            links: LinkStore::new(),
+            color_swatches: ColorSwatchStore::new(),
+            breadcrumb_bars: BreadcrumbBars::new(),
+            chat_views: ChatViewStore::new(),
+            scroll_anchors: ScrollAnchors::new(),
+            hyphenator: Hyphenator::new(),
+            rows_fit: std::collections::HashMap::new(),
+            lnum_widths: std::collections::HashMap::new(),
         }
     }
 
+    /// The cosmic-text font metrics service, created if metrics come
+    /// from C so far.  Used to shape text for printing.
+    pub fn font_metrics_mut(&mut self) -> &mut FontMetricsService {
+        self.font_metrics.get_or_insert_with(FontMetricsService::new)
+    }
+
     // char_advance is a standalone function (below) to avoid borrow conflicts
     // with self.text_buf
 
+    /// Advance width of CH (CHAR_COLS grid columns of CHAR_W) in the
+    /// current face, measured from its font so that variable-width faces
+    /// get pixel-accurate positions.
+    unsafe fn face_char_advance(
+        &mut self,
+        host: &dyn LayoutHost,
+        ch: char,
+        char_cols: i32,
+        char_w: f32,
+        window: EmacsWindow,
+    ) -> f32 {
+        let font_family = if !self.face_data.font_family.is_null() {
+            CStr::from_ptr(self.face_data.font_family).to_str().unwrap_or("")
+        } else {
+            ""
+        };
+        char_advance(
+            host,
+            &mut self.ascii_width_cache,
+            &mut self.font_metrics,
+            ch, char_cols, char_w,
+            self.face_data.face_id, self.face_data.font_size, self.face_data.font_char_width, window,
+            font_family, self.face_data.font_weight as u16, self.face_data.italic != 0,
+        )
+    }
+
     /// Perform layout for an entire frame.
     ///
     /// This is the main entry point, called from FFI when
@@ -205,6 +377,22 @@ impl LayoutEngine {
         frame_params: &FrameParams,
         frame_glyphs: &mut FrameGlyphBuffer,
     ) {
+        self.layout_frame_with(&EmacsHost, frame, frame_params, frame_glyphs);
+    }
+
+    /// Perform layout for an entire frame, reading it from HOST.
+    ///
+    /// # Safety
+    /// As `layout_frame`, for HOST's frames: FRAME must come from HOST.
+    pub unsafe fn layout_frame_with(
+        &mut self,
+        host: &dyn LayoutHost,
+        frame: EmacsFrame,
+        frame_params: &FrameParams,
+        frame_glyphs: &mut FrameGlyphBuffer,
+    ) {
+        let _span = crate::logging::span("layout");
+
         // Set up frame dimensions
         frame_glyphs.width = frame_params.width;
         frame_glyphs.height = frame_params.height;
@@ -215,6 +403,7 @@ impl LayoutEngine {
 
         // Clear hit-test data for new frame
         self.hit_data.clear();
+        self.region_pulses.expire(std::time::Instant::now());
 
         // Lazy-initialize FontMetricsService when cosmic metrics are enabled
         if self.use_cosmic_metrics && self.font_metrics.is_none() {
@@ -231,57 +420,40 @@ impl LayoutEngine {
         // face_id=0 have no Face entry and fall back to generic monospace.
         {
             let mut default_face = FaceDataFFI::default();
-            let rc = neomacs_layout_default_face(frame, &mut default_face);
+            let rc = host.default_face(frame, &mut default_face);
             if rc >= 0 {
-                self.apply_face(&default_face, frame, frame_glyphs);
+                self.apply_face(host, &default_face, frame, frame_glyphs);
             }
         }
 
-        // Get number of windows (direct Rust struct access, no FFI call)
-        let window_count = super::emacs_types::frame_window_count(
-            frame as *const std::ffi::c_void,
-        );
+        // Describe all windows, with the faces and display properties of
+        // their visible text, in one FFI call
+        let mut desc = std::mem::take(&mut self.frame_desc);
+        if !host.describe_frame(frame, &mut desc) {
+            log::warn!("layout_frame: could not describe frame {:?}", frame);
+        }
         log::debug!("layout_frame: {}x{} char={}x{} windows={}",
             frame_params.width, frame_params.height,
             frame_params.char_width, frame_params.char_height,
-            window_count);
+            desc.windows().len());
 
-        for i in 0..window_count {
-            let mut wp = WindowParamsFFI::default();
-            let ret = neomacs_layout_get_window_params(frame, i, &mut wp);
+        for (i, window_desc) in desc.windows().iter().enumerate() {
+            let wp = &window_desc.params;
+            let cache = desc.window_cache(window_desc);
             log::debug!("  window[{}]: id={} mini={} bounds=({},{},{},{}) bufsz={} start={} point={}",
                 i, wp.window_id, wp.is_minibuffer,
                 wp.x, wp.y, wp.width, wp.height,
                 wp.buffer_zv, wp.window_start, wp.point);
-            if ret != 0 {
-                continue;
-            }
 
-            // Read buffer metadata directly from Emacs struct (Phase 2: bypass C wrappers)
-            let (rust_begv, rust_zv) = if !wp.buffer_ptr.is_null() {
-                super::emacs_types::buffer_bounds(wp.buffer_ptr)
-            } else {
-                (1, 1)
-            };
-            let rust_point = if !wp.buffer_ptr.is_null() {
-                super::emacs_types::buffer_point(wp.buffer_ptr)
-            } else {
-                1
-            };
-            let rust_tab_width = if !wp.buffer_ptr.is_null() {
-                super::emacs_types::buffer_tab_width(wp.buffer_ptr)
-            } else {
-                8
-            };
-            let rust_truncate = if !wp.buffer_ptr.is_null() {
-                super::emacs_types::buffer_truncate_lines(wp.buffer_ptr)
-            } else {
-                false
-            };
-            let rust_word_wrap = if !wp.buffer_ptr.is_null() {
-                super::emacs_types::buffer_word_wrap(wp.buffer_ptr)
+            // Read buffer metadata once for this window (Phase 2: bypass C wrappers)
+            let mut snapshot = host.capture_buffer(wp.buffer_ptr, std::mem::take(&mut self.text_buf));
+
+            // The centered column narrows and centers the text area of ordinary windows
+            let window_text_bounds = Rect::new(wp.text_x, wp.text_y, wp.text_width, wp.text_height);
+            let text_bounds = if wp.is_minibuffer != 0 {
+                window_text_bounds
             } else {
-                false
+                self.writing_modes.text_bounds(window_text_bounds, wp.char_width)
             };
 
             // Convert FFI params to our types
@@ -290,19 +462,19 @@ impl LayoutEngine {
                 window_id: wp.window_id,
                 buffer_id: wp.buffer_id,
                 bounds: Rect::new(wp.x, wp.y, wp.width, wp.height),
-                text_bounds: Rect::new(wp.text_x, wp.text_y, wp.text_width, wp.text_height),
+                text_bounds,
                 selected: wp.selected != 0,
                 is_minibuffer: wp.is_minibuffer != 0,
                 window_start: wp.window_start,
                 window_end: wp.window_end,
-                point: rust_point,
-                buffer_size: rust_zv,
-                buffer_begv: rust_begv,
+                point: snapshot.point(),
+                buffer_size: snapshot.zv(),
+                buffer_begv: snapshot.begv(),
                 hscroll: wp.hscroll,
                 vscroll: wp.vscroll,
-                truncate_lines: rust_truncate,
-                word_wrap: rust_word_wrap,
-                tab_width: rust_tab_width,
+                truncate_lines: snapshot.truncate_lines(),
+                word_wrap: snapshot.word_wrap(),
+                tab_width: snapshot.tab_width(),
                 default_fg: wp.default_fg,
                 default_bg: wp.default_bg,
                 char_width: wp.char_width,
@@ -325,6 +497,7 @@ impl LayoutEngine {
                 extra_line_spacing: wp.extra_line_spacing,
                 cursor_in_non_selected: wp.cursor_in_non_selected != 0,
                 selective_display: wp.selective_display,
+                selective_display_ellipses: wp.selective_display_ellipses != 0,
                 escape_glyph_fg: wp.escape_glyph_fg,
                 nobreak_char_display: wp.nobreak_char_display,
                 nobreak_char_fg: wp.nobreak_char_fg,
@@ -341,6 +514,23 @@ impl LayoutEngine {
                 },
                 left_margin_width: wp.left_margin_width,
                 right_margin_width: wp.right_margin_width,
+                bidi_reordering: wp.bidi_reordering != 0,
+                paragraph_direction: match wp.bidi_paragraph_direction {
+                    1 => BidiDir::LTR,
+                    2 => BidiDir::RTL,
+                    _ => BidiDir::Auto,
+                },
+                auto_hscroll: HscrollPolicy {
+                    mode: match wp.auto_hscroll {
+                        1 => AutoHscroll::On,
+                        2 => AutoHscroll::CurrentLine,
+                        _ => AutoHscroll::Off,
+                    },
+                    margin: wp.hscroll_margin,
+                    step: wp.hscroll_step,
+                    step_fraction: (wp.hscroll_step_fraction >= 0.0).then_some(wp.hscroll_step_fraction),
+                    min_hscroll: wp.min_hscroll,
+                },
             };
 
             // Add window background
@@ -352,6 +542,26 @@ impl LayoutEngine {
                 Color::from_pixel(params.default_bg),
             );
 
+            // Centered column margins: fill the area between the window's text edges and
+            // the centered column, between header line and mode line
+            if let Some(margin_bg) = self.writing_modes.margin_bg {
+                if text_bounds != window_text_bounds {
+                    let top = params.header_line_height + params.tab_line_height;
+                    let y = window_text_bounds.y + top;
+                    let h = window_text_bounds.height - top - params.mode_line_height;
+                    let left_w = text_bounds.x - params.left_fringe_width - window_text_bounds.x;
+                    let right_x = text_bounds.x + text_bounds.width + params.right_fringe_width;
+                    let right_w = window_text_bounds.x + window_text_bounds.width - right_x;
+                    let color = Color::from_pixel(margin_bg);
+                    if left_w > 0.0 && h > 0.0 {
+                        frame_glyphs.add_stretch(window_text_bounds.x, y, left_w, h, color, 0, false);
+                    }
+                    if right_w > 0.0 && h > 0.0 {
+                        frame_glyphs.add_stretch(right_x, y, right_w, h, color, 0, false);
+                    }
+                }
+            }
+
             // Add window info for animation detection
             // Extract buffer file name from FFI
             let buffer_file_name = if wp.buffer_file_name.is_null() {
@@ -379,8 +589,36 @@ impl LayoutEngine {
                 wp.modified != 0,
             );
 
-            // Layout this window's content
-            self.layout_window(&params, &wp, frame, frame_glyphs);
+            // Layout this window's content.  In vertical writing mode,
+            // lay it out with the text area's width and height swapped,
+            // then turn the glyphs a quarter turn onto the screen.
+            if host.vertical_writing(wp.window_ptr) != 0 {
+                let bars = params.header_line_height
+                    + params.tab_line_height
+                    + params.mode_line_height;
+                let area = VerticalArea {
+                    x: params.text_bounds.x,
+                    y: params.text_bounds.y + params.header_line_height + params.tab_line_height,
+                    width: params.text_bounds.width,
+                    height: params.text_bounds.height - bars,
+                };
+                let mut turned = params.clone();
+                turned.text_bounds.width = area.height;
+                turned.text_bounds.height = area.width + bars;
+
+                let glyph_start = frame_glyphs.glyphs.len();
+                self.layout_window(host, &turned, wp, &mut snapshot, &cache, frame, frame_glyphs);
+                let inverse = if params.selected { frame_glyphs.cursor_inverse.as_mut() } else { None };
+                area.transform(&mut frame_glyphs.glyphs, glyph_start, inverse);
+                if let Some(hit) = self.hit_data.last_mut() {
+                    if hit.window_id == params.window_id {
+                        hit.vertical = Some(area);
+                    }
+                }
+            } else {
+                self.layout_window(host, &params, wp, &mut snapshot, &cache, frame, frame_glyphs);
+            }
+            self.text_buf = snapshot.into_text_buf();
 
             // Draw window dividers or simple vertical border
             let right_edge = params.bounds.x + params.bounds.width;
@@ -448,14 +686,32 @@ impl LayoutEngine {
             }
         }
 
+        self.frame_desc = desc;
+
         // Publish hit-test data for mouse interaction queries
         unsafe {
             *std::ptr::addr_of_mut!(FRAME_HIT_DATA) = Some(std::mem::take(&mut self.hit_data));
         }
     }
 
+    /// Where CHARPOS is drawn in window WINDOW_ID at the last layout,
+    /// in frame pixels, or None if that window does not show it.  For
+    /// `posn-at-point' and popups anchored to text.
+    pub fn pos_to_pixel(&self, window_id: i64, charpos: i64) -> Option<CharPixel> {
+        hit_test_char_pixel(window_id, charpos)
+    }
+
+    /// Buffer position drawn at frame pixel (X, Y) in window WINDOW_ID
+    /// at the last layout: past the end of a row, the row's last
+    /// position, and below the text, the last position shown.  None if
+    /// the last layout did not include the window.
+    pub fn pixel_to_pos(&self, window_id: i64, x: f32, y: f32) -> Option<i64> {
+        let pos = hit_test_window_charpos(window_id, x, y);
+        (pos >= 0).then_some(pos)
+    }
+
     /// Apply face data from FFI to the FrameGlyphBuffer's current face state.
-    pub(crate) unsafe fn apply_face(&self, face: &FaceDataFFI, frame: EmacsFrame,
+    pub(crate) unsafe fn apply_face(&self, host: &dyn LayoutHost, face: &FaceDataFFI, frame: EmacsFrame,
                           frame_glyphs: &mut FrameGlyphBuffer) {
         let fg = Color::from_pixel(face.fg);
         let bg = Color::from_pixel(face.bg);
@@ -553,6 +809,12 @@ impl LayoutEngine {
             font_descent: face.font_descent,
             underline_position: face.underline_position.max(1),
             underline_thickness: face.underline_thickness.max(1),
+            text_shadow: (face.text_shadow != 0).then(|| TextShadow {
+                color: Color::from_pixel(face.text_shadow_color),
+                offset_x: face.text_shadow_x_offset as f32,
+                offset_y: face.text_shadow_y_offset as f32,
+                blur: face.text_shadow_blur.max(0) as f32,
+            }),
         });
 
         // Fetch stipple pattern data if present and not yet cached
@@ -560,7 +822,7 @@ impl LayoutEngine {
             let mut bits_buf = [0u8; 1024]; // max 1024 bytes for stipple bitmap
             let mut w: c_int = 0;
             let mut h: c_int = 0;
-            let rc = neomacs_layout_get_stipple_bitmap(
+            let rc = host.get_stipple_bitmap(
                 frame as *mut c_void,
                 face.stipple,
                 bits_buf.as_mut_ptr(),
@@ -580,6 +842,30 @@ impl LayoutEngine {
         }
     }
 
+    /// Extra pixels above and below the logical line whose text starts at
+    /// TEXT[BYTE_IDX..] (charpos CHARPOS), from the `line-height` and
+    /// `line-spacing` properties of the newline ending it.
+    unsafe fn line_spacing_at(
+        host: &dyn LayoutHost,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        text: &[u8],
+        byte_idx: usize,
+        charpos: i64,
+        char_h: f32,
+    ) -> (f32, f32) {
+        let Some(nl) = text.get(byte_idx..).and_then(|t| t.iter().position(|&b| b == b'\n')) else {
+            return (0.0, 0.0);
+        };
+        // Characters (not continuation bytes) up to the newline
+        let chars = text[byte_idx..byte_idx + nl].iter().filter(|&&b| b & 0xC0 != 0x80).count();
+        let (mut above, mut below) = (0.0, 0.0);
+        host.check_line_spacing(
+            buffer, window, charpos + chars as i64, char_h, &mut above, &mut below,
+        );
+        (above, below)
+    }
+
     /// Add a stretch glyph, automatically using stipple if the given face has one.
     pub(crate) fn add_stretch_for_face(
         face: &FaceDataFFI,
@@ -595,6 +881,99 @@ impl LayoutEngine {
         }
     }
 
+    /// End row ROW at CHARPOS and start the next one: record the row for
+    /// hit testing, push the rows below down by glyphs taller than CHAR_H
+    /// on it and by SPACING, and reset the per-row tracking.  SPACING is
+    /// the space below the row and above the next one when a line ends
+    /// there, from line-spacing / line-height; rows that wrap have none.
+    #[allow(clippy::too_many_arguments)]
+    fn advance_row(
+        hit_rows: &mut Vec<HitRow>,
+        hit_row_starts: &mut Vec<(i64, f32)>,
+        hit_row_charpos_start: &mut i64,
+        charpos: i64,
+        row: &mut i32,
+        row_y: &mut [f32],
+        row_extra_y: &mut f32,
+        row_above: &mut f32,
+        row_max_height: &mut f32,
+        row_max_ascent: &mut f32,
+        spacing: (f32, f32),
+        text_y: f32, char_h: f32, ascent: f32,
+    ) {
+        let (below, above) = spacing;
+        if (*row as usize) < row_y.len() {
+            hit_rows.push(HitRow {
+                y_start: row_y[*row as usize] - *row_above,
+                y_end: row_y[*row as usize] + *row_max_height + below,
+                charpos_start: *hit_row_charpos_start,
+                charpos_end: charpos,
+                starts: std::mem::take(hit_row_starts),
+            });
+            *hit_row_charpos_start = charpos;
+        }
+        *row += 1;
+        let extra = (*row_max_height - char_h).max(0.0) + below + above;
+        if extra > 0.0 {
+            *row_extra_y += extra;
+            for (ri, y) in row_y.iter_mut().enumerate().skip(*row as usize) {
+                *y = text_y + ri as f32 * char_h + *row_extra_y;
+            }
+        }
+        *row_max_height = char_h;
+        *row_max_ascent = ascent;
+        *row_above = above;
+    }
+
+    /// Take back the cursor of window WINDOW_ID drawn at glyph FROM or
+    /// later, when the text it was on is rewound to be laid out again on
+    /// the next row.
+    fn unplace_cursor(frame_glyphs: &mut FrameGlyphBuffer, window_id: i32, from: usize) {
+        let from = from.min(frame_glyphs.glyphs.len());
+        let found = frame_glyphs.glyphs[from..].iter().rposition(|g| {
+            matches!(g, FrameGlyph::Cursor { window_id: id, .. } if *id == window_id)
+        });
+        if let Some(i) = found {
+            if let FrameGlyph::Cursor { x, y, .. } = frame_glyphs.glyphs.remove(from + i) {
+                if frame_glyphs.cursor_inverse.as_ref().is_some_and(|c| c.x == x && c.y == y) {
+                    frame_glyphs.cursor_inverse = None;
+                }
+            }
+        }
+    }
+
+    /// Show that a row is truncated on the right, without a right
+    /// fringe, with `$` in the last column at TRUNC_X: characters of the
+    /// row from glyph FROM reaching into it give way, a stretch is cut.
+    fn add_right_truncation(
+        frame_glyphs: &mut FrameGlyphBuffer,
+        from: usize,
+        trunc_x: f32,
+        y: f32,
+        char_w: f32,
+        char_h: f32,
+        ascent: f32,
+    ) {
+        let from = from.min(frame_glyphs.glyphs.len());
+        let tail = frame_glyphs.glyphs.split_off(from);
+        frame_glyphs.glyphs.extend(tail.into_iter().filter_map(|mut glyph| {
+            match &mut glyph {
+                FrameGlyph::Char { x, width, is_overlay: false, .. } if *x + *width > trunc_x + 0.5 => {
+                    return None;
+                }
+                FrameGlyph::Stretch { x, width, is_overlay: false, .. } if *x + *width > trunc_x + 0.5 => {
+                    if *x >= trunc_x {
+                        return None;
+                    }
+                    *width = trunc_x - *x;
+                }
+                _ => {}
+            }
+            Some(glyph)
+        }));
+        frame_glyphs.add_char('$', trunc_x, y, char_w, char_h, ascent, false);
+    }
+
     /// Layout a single window's buffer content.
     ///
     /// Phase 1+2: Monospace layout with per-character face resolution.
@@ -605,8 +984,11 @@ impl LayoutEngine {
     /// - Cursor positioning
     unsafe fn layout_window(
         &mut self,
+        host: &dyn LayoutHost,
         params: &WindowParams,
         wp: &WindowParamsFFI,
+        snapshot: &mut BufferSnapshot,
+        cache: &WindowCache,
         frame: EmacsFrame,
         frame_glyphs: &mut FrameGlyphBuffer,
     ) {
@@ -652,7 +1034,7 @@ impl LayoutEngine {
 
         // Check line number configuration
         let mut lnum_config = LineNumberConfigFFI::default();
-        let lnum_enabled = neomacs_layout_line_number_config(
+        let lnum_enabled = host.line_number_config(
             window,
             buffer,
             params.buffer_size,
@@ -660,7 +1042,17 @@ impl LayoutEngine {
             &mut lnum_config,
         ) == 0 && lnum_config.mode > 0;
 
-        let lnum_cols = if lnum_enabled { lnum_config.width } else { 0 };
+        let lnum_cols = if !lnum_enabled {
+            0
+        } else if lnum_config.grow_only != 0 {
+            // display-line-numbers-grow-only: never narrower than before
+            let widest = self.lnum_widths.entry(params.window_id).or_insert(0);
+            *widest = (*widest).max(lnum_config.width);
+            *widest
+        } else {
+            self.lnum_widths.insert(params.window_id, lnum_config.width);
+            lnum_config.width
+        };
         let lnum_pixel_width = lnum_cols as f32 * char_w;
 
         // How many columns and rows fit (accounting for line numbers)
@@ -689,13 +1081,65 @@ impl LayoutEngine {
         let content_x = text_x + lnum_pixel_width;
 
         // --- Scroll adjustment ---
-        let window_start = if params.point > 0
+        // Rows that fit with this window's row heights (line-height,
+        // tall faces), as measured by its last layout
+        let fit_rows = self.rows_fit.get(&params.window_id).copied()
+            .unwrap_or(max_rows).clamp(1, max_rows);
+        let anchor = ScrollAnchor {
+            buffer_id: params.buffer_id,
+            point: params.point,
+            window_start: params.window_start,
+            row: 0,
+            width: params.text_bounds.width,
+            height: params.text_bounds.height,
+            modiff: wp.modiff,
+        };
+        let anchored_row = if self.writing_modes.scroll_anchoring
+            && params.point > 0
+            && !params.is_minibuffer
+        {
+            self.scroll_anchors.row_to_keep(params.window_id, &anchor)
+        } else {
+            None
+        };
+        let window_start = if self.writing_modes.typewriter
+            && params.point > 0
+            && !params.is_minibuffer
+        {
+            // Typewriter scrolling: keep point's line vertically centered.
+            // The render thread animates the resulting window_start change
+            // as a scroll slide, so the text glides instead of jumping.
+            let new_start = host.adjust_window_start(
+                wp.window_ptr,
+                wp.buffer_ptr,
+                params.point,
+                WritingModes::typewriter_lines_above(fit_rows),
+            );
+            if new_start != params.window_start {
+                log::debug!("  typewriter: point={} start {} -> {}",
+                    params.point, params.window_start, new_start);
+            }
+            new_start
+        } else if let Some(row) = anchored_row {
+            // The window was resized or text before point changed height
+            // while point and the start stayed: keep point's line on the
+            // row it was on
+            let new_start = host.anchor_window_start(
+                wp.window_ptr,
+                wp.buffer_ptr,
+                params.point,
+                row.min(fit_rows - 1),
+            );
+            log::debug!("  scroll anchor: point={} row={} start {} -> {}",
+                params.point, row, params.window_start, new_start);
+            new_start
+        } else if params.point > 0
             && params.point < params.window_start
             && !params.is_minibuffer
         {
             // Backward scroll: put point near top (1/4 down)
-            let lines_above = (max_rows / 4).clamp(2, 10);
-            let new_start = neomacs_layout_adjust_window_start(
+            let lines_above = (fit_rows / 4).clamp(2, 10);
+            let new_start = host.adjust_window_start(
                 wp.window_ptr,
                 wp.buffer_ptr,
                 params.point,
@@ -710,8 +1154,8 @@ impl LayoutEngine {
             && !params.is_minibuffer
         {
             // Forward scroll: put point near bottom (3/4 down)
-            let lines_above = if max_rows <= 2 { 1 } else { (max_rows * 3 / 4).clamp(2, max_rows - 1) };
-            let new_start = neomacs_layout_adjust_window_start(
+            let lines_above = if fit_rows <= 2 { 1 } else { (fit_rows * 3 / 4).clamp(2, fit_rows - 1) };
+            let new_start = host.adjust_window_start(
                 wp.window_ptr,
                 wp.buffer_ptr,
                 params.point,
@@ -724,34 +1168,77 @@ impl LayoutEngine {
             params.window_start
         };
 
+        // Report the start actually laid out so the render thread sees
+        // scroll adjustments in this frame, not the next one.
+        if let Some(info) = frame_glyphs.window_infos.last_mut() {
+            if info.window_id == params.window_id {
+                info.window_start = window_start;
+            }
+        }
+
+        // Horizontal scroll in columns, with truncated lines only;
+        // auto-hscroll-mode moves it to keep point in view
+        let mut hscroll = if params.truncate_lines { params.hscroll.max(0) } else { 0 };
+        let mut point_line_start = window_start;
+        if params.truncate_lines
+            && params.auto_hscroll.mode != AutoHscroll::Off
+            && params.point >= window_start
+        {
+            let scan_from = (params.point - LINE_SCAN_LIMIT).max(window_start);
+            let line_text = snapshot.load_text(scan_from, params.point + LINE_SCAN_LIMIT);
+            point_line_start = scan_from;
+            let (mut byte_idx, mut line_byte) = (0, 0);
+            for pos in scan_from..params.point {
+                let (ch, len) = decode_utf8(&line_text[byte_idx..]);
+                if len == 0 {
+                    break;
+                }
+                byte_idx += len;
+                if ch == '\n' {
+                    point_line_start = pos + 1;
+                    line_byte = byte_idx;
+                }
+            }
+            let avail_width = text_width - lnum_pixel_width;
+            let line = point_line(
+                &line_text[line_byte..],
+                (params.point - point_line_start) as usize,
+                params.tab_width,
+                char_w,
+                hscroll as f32 * char_w + avail_width,
+                |ch, ch_cols| self.face_char_advance(host, ch, ch_cols, char_w, window),
+            );
+            if let Some(new_hscroll) = params.auto_hscroll.hscroll_for(hscroll, &line, avail_width, char_w) {
+                log::debug!("  auto hscroll: point={} x={} hscroll {} -> {}",
+                    params.point, line.x, hscroll, new_hscroll);
+                host.set_hscroll(window, new_hscroll);
+                hscroll = new_hscroll;
+            }
+        }
+
         // Trigger fontification (jit-lock) for the visible region so that
         // face text properties are set before we read them.
-        let read_chars = (params.buffer_size - window_start + 1).min(cols as i64 * max_rows as i64 * 2);
+        let read_chars = (params.buffer_size - window_start + 1)
+            .min((cols + hscroll) as i64 * max_rows as i64 * 2);
         let fontify_end = (window_start + read_chars).min(params.buffer_size);
-        neomacs_layout_ensure_fontified(buffer, window_start, fontify_end);
+        host.ensure_fontified(buffer, window_start, fontify_end);
 
         // Read buffer text directly from gap buffer (Phase 3: eliminates
         // per-character FFI overhead from the old neomacs_layout_buffer_text).
-        let bytes_read = if read_chars <= 0 {
-            0i64
-        } else {
-            let text_end = (window_start + read_chars).min(params.buffer_size);
-            let byte_from = neomacs_buf_charpos_to_bytepos(buffer, window_start);
-            let byte_to = neomacs_buf_charpos_to_bytepos(buffer, text_end);
-            super::emacs_types::gap_buffer_copy_text(
-                buffer as *const std::ffi::c_void,
-                byte_from as isize,
-                byte_to as isize,
-                &mut self.text_buf,
-            );
-            self.text_buf.len() as i64
-        };
-
-        let text = if bytes_read > 0 {
-            &self.text_buf[..bytes_read as usize]
-        } else {
+        let text: &[u8] = if read_chars <= 0 {
             &[]
+        } else {
+            snapshot.load_text(window_start, window_start + read_chars)
         };
+        let bytes_read = text.len();
+        let bidi = WindowBidi::new(
+            params.bidi_reordering,
+            params.paragraph_direction,
+            text,
+            window_start,
+            content_x,
+            content_x + (text_width - lnum_pixel_width),
+        );
 
         log::debug!("  layout_window id={}: text_y={:.1} text_h={:.1} char_h={:.1} max_rows={} bytes_read={} bufsz={} is_mini={}",
             params.window_id, text_y, text_height, char_h, max_rows,
@@ -787,6 +1274,10 @@ impl LayoutEngine {
         let mut display_prop = DisplayPropFFI::default();
         let mut display_str_buf = [0u8; 1024];
 
+        // Static composition state (`composition' text property)
+        let mut next_composition_check: i64 = window_start;
+        let mut composition_buf = [0u8; 128];
+
         // Overlay string buffers (4096 to handle fido-vertical-mode completions)
         let mut overlay_before_buf = [0u8; 4096];
         let mut overlay_after_buf = [0u8; 4096];
@@ -800,26 +1291,23 @@ impl LayoutEngine {
 
         // Line number state
         let mut current_line: i64 = if lnum_enabled {
-            neomacs_layout_count_line_number(
+            host.count_line_number(
                 buffer, window_start, lnum_config.widen,
             )
         } else {
             1
         };
-        let point_line: i64 = if lnum_enabled && lnum_config.mode >= 2 {
-            neomacs_layout_count_line_number(
+        let point_line: i64 = if lnum_enabled {
+            host.count_line_number(
                 buffer, params.point, lnum_config.widen,
             )
         } else {
             0
         };
-        let mut lnum_face = FaceDataFFI::default();
-        let mut need_line_number = lnum_enabled; // render on first row
-
-        // Horizontal scroll: skip first hscroll columns
-        let hscroll = if params.truncate_lines { params.hscroll.max(0) } else { 0 };
-        // Reserve 1 column for truncation indicator when needed
-        let show_left_trunc = hscroll > 0;
+        let mut need_line_number = lnum_enabled; // number the first row
+        // Line each row starts, None for continuation rows; the numbers
+        // are drawn after layout, once the row showing point is known
+        let mut row_lines: Vec<Option<i64>> = vec![None; max_rows as usize];
 
         // Available pixel width for text content (excluding line numbers)
         let avail_width = text_width - lnum_pixel_width;
@@ -833,10 +1321,19 @@ impl LayoutEngine {
         let mut cursor_col = 0i32;
         let mut cursor_x: f32 = 0.0;  // pixel X of cursor
         let mut cursor_row = 0i32;
+        // Buffer position the cursor was placed at by the text (0 =
+        // elsewhere), so it follows the text when a row is rewound
+        let mut cursor_charpos = 0i64;
         let mut window_end_charpos = window_start;
         let mut byte_idx = 0usize;
-        // hscroll state: how many columns to skip on each line
-        let mut hscroll_remaining = hscroll;
+        // hscroll state: whether the current line is still to be
+        // scrolled, the pixels left to skip and the columns skipped
+        let mut hscroll_pending = hscroll > 0;
+        let mut hscroll_skip: Option<f32> = None;
+        let mut hscroll_cols = 0i32;
+        // Columns of the line left of column 0, for tab stops
+        let mut tab_origin = 0i32;
+        let mut hscroll_clipped = false;
         // Track current face's space width and line metrics
         let mut face_space_w = char_w;
         let mut face_h: f32 = char_h;    // current face's line height
@@ -848,6 +1345,8 @@ impl LayoutEngine {
         let mut row_continued = vec![false; max_rows as usize];
         let mut row_continuation = vec![false; max_rows as usize];
         let mut row_truncated = vec![false; max_rows as usize];
+        // row_left_truncated[row] = true if hscroll hid text at the row's start
+        let mut row_left_truncated = vec![false; max_rows as usize];
         // Per-row user fringe bitmaps from display properties
         // (bitmap_id, fg_color, bg_color): 0=none
         let mut row_left_fringe: Vec<(i32, u32, u32)> = vec![(0, 0, 0); max_rows as usize];
@@ -862,6 +1361,19 @@ impl LayoutEngine {
         let mut row_extra_y: f32 = 0.0; // cumulative extra height from previous rows
         let mut row_max_height: f32 = char_h; // max glyph height on current row
         let mut row_max_ascent: f32 = ascent; // max ascent on current row
+        // line-height / line-spacing of the current logical line: extra
+        // space above its first row (text sits at the bottom of a taller
+        // line) and below its last row.
+        let (mut row_above, mut line_below) = Self::line_spacing_at(
+            host,
+            buffer, window, text, byte_idx, charpos, char_h,
+        );
+        if row_above > 0.0 {
+            row_extra_y += row_above;
+            for ri in 0..row_y.len() {
+                row_y[ri] = text_y + ri as f32 * char_h + row_extra_y;
+            }
+        }
 
         // Trailing whitespace tracking
         let trailing_ws_bg = if params.show_trailing_whitespace {
@@ -880,9 +1392,46 @@ impl LayoutEngine {
         let mut wrap_break_charpos = window_start;
         let mut wrap_break_glyph_count = 0usize;
         let mut wrap_has_break = false;
+        // Last character decoded, for break opportunities before the next
+        let mut wrap_prev_ch = '\n';
+
+        // Hyphenation: the buffer's patterns, the hyphenation points
+        // of the word being laid out, and the last one passed on this
+        // row, used instead of the word-wrap break when that would
+        // leave more than hyph_ragged_w of the row empty
+        let mut hyph_ragged_w: f32 = 0.0;
+        let hyph_patterns = if params.word_wrap {
+            let mut lang_buf = [0u8; 64];
+            let mut dir_buf = [0u8; 1024];
+            let mut dir_len: c_int = 0;
+            let mut ragged_cols: c_int = 0;
+            let lang_len = host.hyphenation(
+                buffer, lang_buf.as_mut_ptr(), lang_buf.len() as c_int,
+                dir_buf.as_mut_ptr(), dir_buf.len() as c_int, &mut dir_len,
+                &mut ragged_cols,
+            );
+            hyph_ragged_w = ragged_cols as f32 * char_w;
+            if lang_len > 0 {
+                let lang = String::from_utf8_lossy(&lang_buf[..lang_len as usize]);
+                let dir = String::from_utf8_lossy(&dir_buf[..dir_len.max(0) as usize]);
+                self.hyphenator.patterns(std::path::Path::new(&*dir), &lang)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+        let mut hyph_points: Vec<i64> = Vec::new();
+        let mut hyph_word_end: i64 = 0;
+        let mut hyph_row: i32 = -1;
+        let mut hyph_break_x: f32 = 0.0;
+        let mut hyph_break_byte_idx = 0usize;
+        let mut hyph_break_charpos = window_start;
+        let mut hyph_break_glyph_count = 0usize;
 
         // Line/wrap prefix tracking: 0=none, 1=line_prefix, 2=wrap_prefix
-        let mut need_prefix: u8 = if !params.line_prefix.is_empty() { 1 } else { 0 };
+        let mut need_prefix: u8 = 1;
+        let mut prefix_buf = [0u8; 512];
 
         // Raise display property: Y offset applied to glyphs
         let mut raise_y_offset: f32 = 0.0;
@@ -907,6 +1456,21 @@ impl LayoutEngine {
         // Hit-test data for this window
         let mut hit_rows: Vec<HitRow> = Vec::new();
         let mut hit_row_charpos_start: i64 = window_start;
+        let mut hit_row_starts: Vec<(i64, f32)> = Vec::new();
+        let mut hit_cells: Vec<HitCell> = Vec::new();
+
+        // Table column geometry, measured on first use this frame
+        let has_tables = self.tables.has_buffer(params.buffer_id);
+        let mut table_geometry: std::collections::HashMap<usize, TableGeometry> =
+            std::collections::HashMap::new();
+
+        // Color swatches of the text read, drawn as their literals are reached
+        let swatches = if self.color_swatches.has_buffer(params.buffer_id) {
+            self.color_swatches.in_range(params.buffer_id, window_start, window_start + read_chars)
+        } else {
+            Vec::new()
+        };
+        let mut next_swatch = 0usize;
 
         // Ligature run accumulation
         let ligatures = self.ligatures_enabled;
@@ -914,144 +1478,75 @@ impl LayoutEngine {
 
         // Bidi reordering: track where each row's glyphs start in frame_glyphs.glyphs
         let mut row_glyph_start: usize = frame_glyphs.glyphs.len();
+        // First glyph of this window's text area (for post-layout passes)
+        let text_glyph_start = row_glyph_start;
 
-        while byte_idx < bytes_read as usize && row < max_rows
+        while byte_idx < bytes_read && row < max_rows
             && row_y[row as usize] < text_y_limit
         {
-            // Render line number at the start of each new row
+            // Note the line starting this row, for its number
             if need_line_number && lnum_enabled {
-                // Determine displayed number based on mode
-                let display_num = match lnum_config.mode {
-                    2 => {
-                        // Relative mode
-                        if lnum_config.current_absolute != 0
-                            && current_line == point_line
-                        {
-                            current_line + lnum_config.offset as i64
-                        } else {
-                            (current_line - point_line).abs()
-                        }
-                    }
-                    3 => {
-                        // Visual mode: relative to point line
-                        if lnum_config.current_absolute != 0
-                            && current_line == point_line
-                        {
-                            current_line + lnum_config.offset as i64
-                        } else {
-                            (current_line - point_line).abs()
-                        }
-                    }
-                    _ => {
-                        // Absolute mode
-                        current_line + lnum_config.offset as i64
-                    }
-                };
-
-                let is_current = if current_line == point_line { 1 } else { 0 };
-                neomacs_layout_line_number_face(
-                    window,
-                    is_current,
-                    current_line,
-                    lnum_config.major_tick,
-                    lnum_config.minor_tick,
-                    &mut lnum_face,
-                );
-
-                // Apply line number face and render digits
-                self.apply_face(&lnum_face, frame, frame_glyphs);
-                let lnum_bg = Color::from_pixel(lnum_face.bg);
-
-                // Format the number right-aligned
-                let num_str = format!("{}", display_num);
-                let num_chars = num_str.len() as i32;
-                let padding = (lnum_cols - 1) - num_chars; // -1 for trailing space
-
-                let gy = row_y[row as usize];
-
-                // Leading padding
-                if padding > 0 {
-                    frame_glyphs.add_stretch(
-                        text_x, gy,
-                        padding as f32 * char_w, char_h,
-                        lnum_bg, lnum_face.face_id, false,
-                    );
-                }
-
-                // Number digits
-                for (i, ch) in num_str.chars().enumerate() {
-                    let dx = text_x + (padding.max(0) + i as i32) as f32 * char_w;
-                    frame_glyphs.add_char(ch, dx, gy, char_w, char_h, ascent, false);
-                }
-
-                // Trailing space
-                let space_x = text_x + (lnum_cols - 1) as f32 * char_w;
-                frame_glyphs.add_stretch(
-                    space_x, gy,
-                    char_w, char_h,
-                    lnum_bg, lnum_face.face_id, false,
-                );
-
-                // Restore text face
-                if current_face_id >= 0 {
-                    self.apply_face(&self.face_data, frame, frame_glyphs);
-                }
-
+                row_lines[row as usize] = Some(current_line);
                 need_line_number = false;
             }
 
             // Render line-prefix or wrap-prefix at start of visual lines
             if need_prefix > 0 && row < max_rows {
                 let prefix_type = if need_prefix == 2 { 1 } else { 0 };
-                let mut tp_width: f32 = -1.0;
+                let mut prefix_len: c_int = 0;
+                let mut prefix_w: f32 = 0.0;
+
+                // Text property, buffer variable, or hanging indent
+                let kind = LinePrefixKind::from_ffi(host.check_line_prefix(
+                    buffer, window, charpos, prefix_type,
+                    prefix_buf.as_mut_ptr(), prefix_buf.len() as c_int,
+                    &mut prefix_len, &mut prefix_w,
+                ));
+                let prefix_bytes = &prefix_buf[..prefix_len.max(0) as usize];
+
+                let space_w = match kind {
+                    LinePrefixKind::Space => prefix_w,
+                    LinePrefixKind::Hanging => {
+                        let line = String::from_utf8_lossy(prefix_bytes);
+                        hanging_indent_columns(
+                            &line, params.tab_width.max(1) as usize, prefix_w as usize,
+                        ) as f32 * char_w
+                    }
+                    _ => 0.0,
+                };
 
-                // Check text property prefix first (overrides window default)
-                neomacs_layout_check_line_prefix(
-                    buffer, window, charpos, prefix_type, &mut tp_width,
-                );
+                if kind == LinePrefixKind::String {
+                    let mut pi = 0usize;
+                    while pi < prefix_bytes.len() {
+                        let (pch, plen) = decode_utf8(&prefix_bytes[pi..]);
+                        pi += plen;
+                        if pch == '\n' || pch == '\r' { continue; }
+
+                        let pchar_cols = if is_wide_char(pch) { 2 } else { 1 };
+                        let adv = pchar_cols as f32 * char_w;
+                        if x_offset + adv > avail_width { break; }
 
-                if tp_width >= 0.0 {
-                    // Text property prefix: render as space
-                    let px_w = tp_width * char_w;
-                    if px_w > 0.0 && x_offset + px_w <= avail_width {
+                        let gx = content_x + x_offset;
+                        let gy = row_y[row as usize];
+                        frame_glyphs.add_char(
+                            pch, gx, gy, adv,
+                            char_h, ascent, false,
+                        );
+                        col += pchar_cols;
+                        x_offset += adv;
+                    }
+                } else if space_w > 0.0 {
+                    // Leave room for at least one column of text
+                    let px_w = space_w.min((avail_width - x_offset - char_w).max(0.0)).floor();
+                    if px_w > 0.0 {
                         let gx = content_x + x_offset;
                         let gy = row_y[row as usize];
                         frame_glyphs.add_stretch(
                             gx, gy, px_w, char_h, default_bg, 0, false,
                         );
-                        let prefix_cols = tp_width.ceil() as i32;
-                        col += prefix_cols;
+                        col += (px_w / char_w).ceil() as i32;
                         x_offset += px_w;
                     }
-                } else {
-                    // Fall back to window-level prefix string
-                    let prefix_bytes = if need_prefix == 2 {
-                        &params.wrap_prefix[..]
-                    } else {
-                        &params.line_prefix[..]
-                    };
-
-                    if !prefix_bytes.is_empty() {
-                        let mut pi = 0usize;
-                        while pi < prefix_bytes.len() {
-                            let (pch, plen) = decode_utf8(&prefix_bytes[pi..]);
-                            pi += plen;
-                            if pch == '\n' || pch == '\r' { continue; }
-
-                            let pchar_cols = if is_wide_char(pch) { 2 } else { 1 };
-                            let adv = pchar_cols as f32 * char_w;
-                            if x_offset + adv > avail_width { break; }
-
-                            let gx = content_x + x_offset;
-                            let gy = row_y[row as usize];
-                            frame_glyphs.add_char(
-                                pch, gx, gy, adv,
-                                char_h, ascent, false,
-                            );
-                            col += pchar_cols;
-                            x_offset += adv;
-                        }
-                    }
                 }
                 need_prefix = 0;
             }
@@ -1063,7 +1558,7 @@ impl LayoutEngine {
                 let mut right_margin_buf = [0u8; 256];
                 let mut left_len: c_int = 0;
                 let mut right_len: c_int = 0;
-                neomacs_layout_margin_strings_at(
+                host.margin_strings_at(
                     buffer, window, charpos,
                     left_margin_buf.as_mut_ptr(), 256, &mut left_len,
                     right_margin_buf.as_mut_ptr(), 256, &mut right_len,
@@ -1108,44 +1603,76 @@ impl LayoutEngine {
                 }
             }
 
-            // Handle hscroll: show $ indicator and skip columns
-            if hscroll_remaining > 0 {
-                // Skip characters consumed by hscroll
+            // Handle hscroll: skip the line's first hscroll columns' worth
+            // of pixels, marking hidden text with $ or in the fringe
+            if hscroll_pending {
+                let skip_w = *hscroll_skip.get_or_insert_with(|| {
+                    let on_point_line = charpos == point_line_start;
+                    params.auto_hscroll.line_hscroll(hscroll, on_point_line) as f32 * char_w
+                });
                 let (ch, ch_len) = decode_utf8(&text[byte_idx..]);
-                byte_idx += ch_len;
-                charpos += 1;
-
-                if ch == '\n' {
-                    // Newline within hscroll region: new line
-                    reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
-                    col = 0;
-                    x_offset = 0.0;
-                    row += 1;
-                    row_glyph_start = frame_glyphs.glyphs.len();
-                    current_line += 1;
-                    need_line_number = lnum_enabled;
-                    need_margin_check = has_margins;
-                    hscroll_remaining = hscroll; // reset for next line
-                    wrap_has_break = false;
+                let (ch_cols, ch_w) = if ch == '\n' {
+                    (0, f32::INFINITY)
                 } else {
-                    let ch_cols = if ch == '\t' {
-                        let tab_w = params.tab_width.max(1);
-                        ((hscroll - hscroll_remaining) / tab_w + 1) * tab_w - (hscroll - hscroll_remaining)
-                    } else if is_wide_char(ch) {
-                        2
-                    } else {
-                        1
-                    };
-                    hscroll_remaining -= ch_cols.min(hscroll_remaining);
+                    char_extent(ch, hscroll_cols, params.tab_width, face_space_w, |c, c_cols| {
+                        self.face_char_advance(host, c, c_cols, char_w, window)
+                    })
+                };
+                if skip_w > 0.0 && ch_w <= skip_w {
+                    // Wholly hidden
+                    byte_idx += ch_len;
+                    charpos += 1;
+                    hscroll_cols += ch_cols;
+                    hscroll_skip = Some(skip_w - ch_w);
+                    window_end_charpos = charpos;
+                    continue;
+                }
 
-                    // When hscroll is done, show $ at left edge
-                    if hscroll_remaining <= 0 && show_left_trunc {
-                        let gy = row_y[row as usize];
+                // The line shows from here: a tab straddling the edge
+                // shows its visible part, another character is laid out
+                // partly left of the edge and cut there
+                let mut shown_w = 0.0;
+                let mut straddles = false;
+                if skip_w > 0.0 && ch == '\t' {
+                    byte_idx += ch_len;
+                    charpos += 1;
+                    hscroll_cols += ch_cols;
+                    shown_w = ch_w - skip_w;
+                } else if skip_w > 0.0 && ch != '\n' {
+                    shown_w = -skip_w;
+                    straddles = true;
+                }
+                let hidden = hscroll_cols > 0 || straddles;
+                let lead_w = if hidden && left_fringe_width <= 0.0 { char_w } else { 0.0 };
+                let gy = row_y[row as usize];
+                if hidden {
+                    if left_fringe_width > 0.0 {
+                        if let Some(t) = row_left_truncated.get_mut(row as usize) {
+                            *t = true;
+                        }
+                    } else {
                         frame_glyphs.add_char('$', content_x, gy, char_w, char_h, ascent, false);
-                        col = 1; // $ takes 1 column
-                        x_offset = char_w;
                     }
                 }
+                if shown_w > 0.0 {
+                    Self::add_stretch_for_face(
+                        &self.face_data, frame_glyphs, content_x + lead_w, gy, shown_w,
+                        char_h, face_bg, self.face_data.face_id, false,
+                    );
+                }
+                if straddles && !hscroll_clipped {
+                    frame_glyphs.add_text_clip(
+                        params.window_id,
+                        Rect::new(content_x + lead_w, text_y, avail_width - lead_w, text_height),
+                    );
+                    hscroll_clipped = true;
+                }
+                col = if lead_w > 0.0 { 1 } else { 0 };
+                x_offset = lead_w + shown_w;
+                tab_origin = hscroll_cols - col;
+                hscroll_pending = false;
+                hscroll_skip = None;
+                hscroll_cols = 0;
                 window_end_charpos = charpos;
                 continue;
             }
@@ -1153,7 +1680,7 @@ impl LayoutEngine {
             // Check for invisible text at property change boundaries
             if charpos >= next_invis_check {
                 let mut next_visible: i64 = 0;
-                let invis = neomacs_layout_check_invisible(
+                let invis = host.check_invisible(
                     buffer,
                     window,
                     charpos,
@@ -1165,14 +1692,18 @@ impl LayoutEngine {
                     flush_run(&self.run_buf, frame_glyphs, ligatures);
                     self.run_buf.clear();
                     // Skip invisible characters: advance byte_idx
-                    // and charpos to next_visible
+                    // and charpos to next_visible.  Hidden newlines
+                    // still count for line numbers.
                     let chars_to_skip = next_visible - charpos;
                     for _ in 0..chars_to_skip {
-                        if byte_idx >= bytes_read as usize {
+                        if byte_idx >= bytes_read {
                             break;
                         }
-                        let (_, ch_len) = decode_utf8(&text[byte_idx..]);
+                        let (sch, ch_len) = decode_utf8(&text[byte_idx..]);
                         byte_idx += ch_len;
+                        if sch == '\n' {
+                            current_line += 1;
+                        }
                     }
                     // Show ellipsis for invis==2
                     if invis == 2 && x_offset + 3.0 * char_w <= avail_width && row < max_rows {
@@ -1201,40 +1732,200 @@ impl LayoutEngine {
                 }
             }
 
-            // Check for overlay before-string/after-string at this position.
-            // Before-strings render at overlay start, after-strings at end.
-            {
-                overlay_before_len = 0;
-                overlay_after_len = 0;
-                let mut overlay_before_face = FaceDataFFI::default();
-                overlay_after_face = FaceDataFFI::default();
-                overlay_before_nruns = 0;
-                overlay_after_nruns = 0;
-                let mut ovl_left_fringe_bitmap: i32 = 0;
-                let mut ovl_left_fringe_fg: u32 = 0;
-                let mut ovl_left_fringe_bg: u32 = 0;
-                let mut ovl_right_fringe_bitmap: i32 = 0;
-                let mut ovl_right_fringe_fg: u32 = 0;
-                let mut ovl_right_fringe_bg: u32 = 0;
-                overlay_before_naligns = 0;
-                overlay_after_naligns = 0;
-                neomacs_layout_overlay_strings_at(
-                    buffer, window, charpos,
-                    overlay_before_buf.as_mut_ptr(),
-                    overlay_before_buf.len() as i32,
-                    &mut overlay_before_len,
-                    overlay_after_buf.as_mut_ptr(),
-                    overlay_after_buf.len() as i32,
-                    &mut overlay_after_len,
-                    &mut overlay_before_face,
-                    &mut overlay_after_face,
-                    &mut overlay_before_nruns,
-                    &mut overlay_after_nruns,
-                    &mut ovl_left_fringe_bitmap,
-                    &mut ovl_left_fringe_fg,
-                    &mut ovl_left_fringe_bg,
-                    &mut ovl_right_fringe_bitmap,
-                    &mut ovl_right_fringe_fg,
+            // Table line: draw its cells at pixel-aligned column positions
+            // with grid lines instead of the typed separators, then continue
+            // at the line's newline.
+            if has_tables {
+                if let Some((ti, table, ri)) = self.tables.row_at(params.buffer_id, charpos)
+                    .filter(|&(_, t, ri)| t.rows[ri].end >= charpos)
+                {
+                    flush_run(&self.run_buf, frame_glyphs, ligatures);
+                    self.run_buf.clear();
+
+                    // The table is drawn in the face at the start of the line
+                    if charpos >= next_face_check || current_face_id < 0 {
+                        let mut next_check: i64 = 0;
+                        let fid = cache.face_at_pos(
+                            host,
+                            window, charpos,
+                            &mut self.face_data as *mut FaceDataFFI,
+                            &mut next_check,
+                        );
+                        if fid >= 0 && fid != current_face_id {
+                            current_face_id = fid;
+                            face_fg = Color::from_pixel(self.face_data.fg);
+                            face_bg = Color::from_pixel(self.face_data.bg);
+                            self.apply_face(host, &self.face_data, frame, frame_glyphs);
+                        }
+                    }
+
+                    let face_id = self.face_data.face_id;
+                    let font_size = self.face_data.font_size;
+                    let face_char_w = self.face_data.font_char_width;
+                    let font_family = if !self.face_data.font_family.is_null() {
+                        CStr::from_ptr(self.face_data.font_family).to_str().unwrap_or("")
+                    } else {
+                        ""
+                    };
+                    let font_weight = self.face_data.font_weight as u16;
+                    let font_italic = self.face_data.italic != 0;
+                    let ascii_width_cache = &mut self.ascii_width_cache;
+                    let font_metrics = &mut self.font_metrics;
+                    let mut advance = |ch: char| -> f32 {
+                        let char_cols = if is_wide_char(ch) { 2 } else { 1 };
+                        char_advance(
+                            host,
+                            ascii_width_cache, font_metrics,
+                            ch, char_cols, char_w,
+                            face_id, font_size, face_char_w, window,
+                            font_family, font_weight, font_italic,
+                        )
+                    };
+                    let geom = table_geometry.entry(ti).or_insert_with(|| {
+                        TableGeometry::measure(table, (char_w / 2.0).floor(), 1.0, |cell_text| {
+                            cell_text.chars().map(&mut advance).sum()
+                        })
+                    });
+
+                    let table_row = &table.rows[ri];
+                    let gy = row_y[row as usize];
+                    let left = content_x + x_offset;
+                    let clip = content_x + avail_width;
+                    let grid_color = Color::from_pixel(table.grid_color);
+                    let xs = geom.grid_lines();
+                    let table_w = geom.total_width().min(clip - left).max(0.0);
+                    let mut cursor_at: Option<(f32, f32)> = None;
+                    let point_here = !cursor_placed
+                        && params.point >= table_row.start
+                        && params.point <= table_row.end;
+
+                    if table_row.rule {
+                        frame_glyphs.add_border(left, gy + (char_h / 2.0).floor(), table_w, geom.grid, grid_color);
+                        if point_here {
+                            cursor_at = Some((left, char_w));
+                        }
+                    } else {
+                        if ri == 0 {
+                            frame_glyphs.add_border(left, gy, table_w, geom.grid, grid_color);
+                        }
+                        if ri + 1 == table.rows.len() {
+                            frame_glyphs.add_border(left, gy + char_h - geom.grid, table_w, geom.grid, grid_color);
+                        }
+                        for &gx in &xs {
+                            if left + gx < clip {
+                                frame_glyphs.add_border(left + gx, gy, geom.grid, char_h, grid_color);
+                            }
+                        }
+                        let point_cell = if point_here { Table::cell_at(table_row, params.point) } else { None };
+                        for (ci, cell) in table_row.cells.iter().enumerate() {
+                            let advances: Vec<f32> = cell.text.chars().map(&mut advance).collect();
+                            let text_w: f32 = advances.iter().sum();
+                            let mut cx = left + geom.cell_x(ci, text_w, table.align(ci));
+                            let mut char_ends = Vec::with_capacity(advances.len());
+                            for (i, (ch, &adv)) in cell.text.chars().zip(&advances).enumerate() {
+                                if point_cell == Some((ci, i)) {
+                                    cursor_at = Some((cx, adv));
+                                }
+                                if cx + adv <= clip {
+                                    frame_glyphs.add_char(ch, cx, gy, adv, char_h, ascent, false);
+                                }
+                                cx += adv;
+                                char_ends.push(cx);
+                            }
+                            if point_cell == Some((ci, advances.len())) {
+                                cursor_at = Some((cx, char_w));
+                            }
+                            let cell_left = left + xs.get(ci).copied().unwrap_or(0.0);
+                            let cell_right = left + xs.get(ci + 1).copied().unwrap_or(table_w);
+                            hit_cells.push(HitCell {
+                                y_start: gy,
+                                y_end: gy + char_h,
+                                x_start: cell_left,
+                                x_end: cell_right.min(clip),
+                                charpos_start: cell.start,
+                                char_ends,
+                            });
+                        }
+                        if point_here && cursor_at.is_none() {
+                            cursor_at = Some((left, char_w));
+                        }
+                    }
+
+                    if let Some((cx, cw)) = cursor_at {
+                        let cursor_style = if params.selected {
+                            CursorStyle::from_type(params.cursor_type, params.cursor_bar_width)
+                        } else if params.cursor_in_non_selected {
+                            Some(CursorStyle::Hollow)
+                        } else {
+                            None
+                        };
+                        if let Some(style) = cursor_style {
+                            frame_glyphs.add_cursor(
+                                params.window_id as i32, cx, gy, cw, char_h, style, face_fg,
+                            );
+                            if matches!(style, CursorStyle::FilledBox) {
+                                frame_glyphs.set_cursor_inverse(cx, gy, cw, char_h, face_fg, face_bg);
+                            }
+                        }
+                        cursor_x = cx - content_x;
+                        cursor_col = (cursor_x / char_w) as i32;
+                        cursor_row = row;
+                        cursor_placed = true;
+                    }
+
+                    x_offset = (left + table_w - content_x).min(avail_width);
+                    col = (x_offset / char_w).ceil() as i32;
+
+                    // Skip the typed line up to its newline
+                    let end = table_row.end;
+                    for _ in charpos..end {
+                        if byte_idx >= bytes_read { break; }
+                        let (_, ch_len) = decode_utf8(&text[byte_idx..]);
+                        byte_idx += ch_len;
+                    }
+                    charpos = end;
+                    window_end_charpos = charpos;
+                    next_invis_check = charpos;
+                    next_display_check = charpos;
+                    current_face_id = -1;
+                    continue;
+                }
+            }
+
+            // Check for overlay before-string/after-string at this position.
+            // Before-strings render at overlay start, after-strings at end.
+            {
+                overlay_before_len = 0;
+                overlay_after_len = 0;
+                let mut overlay_before_face = FaceDataFFI::default();
+                overlay_after_face = FaceDataFFI::default();
+                overlay_before_nruns = 0;
+                overlay_after_nruns = 0;
+                let mut ovl_left_fringe_bitmap: i32 = 0;
+                let mut ovl_left_fringe_fg: u32 = 0;
+                let mut ovl_left_fringe_bg: u32 = 0;
+                let mut ovl_right_fringe_bitmap: i32 = 0;
+                let mut ovl_right_fringe_fg: u32 = 0;
+                let mut ovl_right_fringe_bg: u32 = 0;
+                overlay_before_naligns = 0;
+                overlay_after_naligns = 0;
+                host.overlay_strings_at(
+                    buffer, window, charpos,
+                    overlay_before_buf.as_mut_ptr(),
+                    overlay_before_buf.len() as i32,
+                    &mut overlay_before_len,
+                    overlay_after_buf.as_mut_ptr(),
+                    overlay_after_buf.len() as i32,
+                    &mut overlay_after_len,
+                    &mut overlay_before_face,
+                    &mut overlay_after_face,
+                    &mut overlay_before_nruns,
+                    &mut overlay_after_nruns,
+                    &mut ovl_left_fringe_bitmap,
+                    &mut ovl_left_fringe_fg,
+                    &mut ovl_left_fringe_bg,
+                    &mut ovl_right_fringe_bitmap,
+                    &mut ovl_right_fringe_fg,
                     &mut ovl_right_fringe_bg,
                     &mut overlay_before_naligns,
                     &mut overlay_after_naligns,
@@ -1273,10 +1964,11 @@ impl LayoutEngine {
                     // Use per-char face runs, overlay face, or resolve face for position
                     if !before_has_runs {
                         if overlay_before_face.face_id != 0 {
-                            self.apply_face(&overlay_before_face, frame, frame_glyphs);
+                            self.apply_face(host, &overlay_before_face, frame, frame_glyphs);
                         } else if charpos >= next_face_check || current_face_id < 0 {
                             let mut next_check: i64 = 0;
-                            let fid = neomacs_layout_face_at_pos(
+                            let fid = cache.face_at_pos(
+                                host,
                                 window, charpos,
                                 &mut self.face_data as *mut FaceDataFFI,
                                 &mut next_check,
@@ -1285,7 +1977,7 @@ impl LayoutEngine {
                                 current_face_id = fid;
                                 face_fg = Color::from_pixel(self.face_data.fg);
                                 face_bg = Color::from_pixel(self.face_data.bg);
-                                self.apply_face(&self.face_data, frame, frame_glyphs);
+                                self.apply_face(host, &self.face_data, frame, frame_glyphs);
                             }
                             next_face_check = if next_check > charpos { next_check } else { charpos + 1 };
                         }
@@ -1329,7 +2021,7 @@ impl LayoutEngine {
                         let (bch, blen) = decode_utf8(&bstr[bi..]);
                         bi += blen;
                         if bch == '\n' {
-                            reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                            bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                             col = 0;
                             x_offset = 0.0;
                             row += 1;
@@ -1344,7 +2036,7 @@ impl LayoutEngine {
                         if x_offset + badv > avail_width {
                             if params.truncate_lines {
                                 // Skip to next newline, then advance to next row
-                                reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                                bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                                 while bi < bstr.len() {
                                     let (sc, sl) = decode_utf8(&bstr[bi..]);
                                     bi += sl;
@@ -1359,7 +2051,7 @@ impl LayoutEngine {
                                 if row >= max_rows { break; }
                                 continue;
                             }
-                            reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                            bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                             col = 0;
                             x_offset = 0.0;
                             row += 1;
@@ -1375,7 +2067,7 @@ impl LayoutEngine {
 
                     // Restore text face after overlay face was used
                     if (before_has_runs || overlay_before_face.face_id != 0) && current_face_id >= 0 {
-                        self.apply_face(&self.face_data, frame, frame_glyphs);
+                        self.apply_face(host, &self.face_data, frame, frame_glyphs);
                     }
                 }
 
@@ -1385,9 +2077,144 @@ impl LayoutEngine {
                 // (Stored in overlay_after_len for use after char rendering)
             }
 
+            // Inline suggestion (ghost text) at point.  The cursor is placed
+            // first so it stays in front of the suggestion, then the
+            // suggestion is laid out like inserted text in its own color.
+            if let Some(ghost) = self.ghost_text.as_ref()
+                .filter(|g| g.visible_at(params.buffer_id, charpos, params.point))
+            {
+                let ghost_fg = Color::from_pixel(ghost.fg);
+                let gstr = ghost.text.clone();
+                flush_run(&self.run_buf, frame_glyphs, ligatures);
+                self.run_buf.clear();
+
+                if !cursor_placed && row < max_rows {
+                    cursor_col = col;
+                    cursor_x = x_offset;
+                    cursor_row = row;
+                    let cursor_px = content_x + x_offset;
+                    let cursor_y = row_y[row as usize];
+
+                    let cursor_face_w = if self.face_data.font_char_width > 0.0 {
+                        self.face_data.font_char_width
+                    } else {
+                        char_w
+                    };
+
+                    let cursor_style = if params.selected {
+                        CursorStyle::from_type(params.cursor_type, params.cursor_bar_width)
+                    } else if params.cursor_in_non_selected {
+                        Some(CursorStyle::Hollow)
+                    } else {
+                        None
+                    };
+
+                    if let Some(style) = cursor_style {
+                        frame_glyphs.add_cursor(
+                            params.window_id as i32,
+                            cursor_px,
+                            cursor_y,
+                            cursor_face_w,
+                            face_h,
+                            style,
+                            face_fg,
+                        );
+
+                        if matches!(style, CursorStyle::FilledBox) {
+                            frame_glyphs.set_cursor_inverse(
+                                cursor_px,
+                                cursor_y,
+                                cursor_face_w,
+                                face_h,
+                                face_fg,
+                                face_bg,
+                            );
+                        }
+                    }
+
+                    cursor_placed = true;
+                }
+
+                frame_glyphs.set_face(
+                    0, ghost_fg, None,
+                    400, false,
+                    0, None, 0, None, 0, None,
+                );
+                for gch in gstr.chars() {
+                    if row >= max_rows { break; }
+                    if gch == '\n' {
+                        bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
+                        col = 0;
+                        x_offset = 0.0;
+                        row += 1;
+                        row_glyph_start = frame_glyphs.glyphs.len();
+                        continue;
+                    }
+                    if gch == '\r' { continue; }
+
+                    let gchar_cols = if is_wide_char(gch) { 2 } else { 1 };
+                    let gadv = gchar_cols as f32 * char_w;
+                    if x_offset + gadv > avail_width {
+                        if params.truncate_lines {
+                            continue;
+                        }
+                        bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
+                        col = 0;
+                        x_offset = 0.0;
+                        row += 1;
+                        row_glyph_start = frame_glyphs.glyphs.len();
+                        if row >= max_rows { break; }
+                    }
+                    let gx = content_x + x_offset;
+                    let gy = row_y[row as usize];
+                    frame_glyphs.add_char(gch, gx, gy, gadv, char_h, ascent, false);
+                    col += gchar_cols;
+                    x_offset += gadv;
+                }
+
+                // Restore the text face for the buffer text that follows
+                if current_face_id >= 0 {
+                    self.apply_face(host, &self.face_data, frame, frame_glyphs);
+                }
+            }
+
+            // Color swatch before a color literal: a square of the color,
+            // outlined in the text color, two columns wide
+            while swatches.get(next_swatch).is_some_and(|s| s.start < charpos) {
+                next_swatch += 1;
+            }
+            if let Some(swatch) = swatches.get(next_swatch).filter(|s| s.start == charpos) {
+                next_swatch += 1;
+                flush_run(&self.run_buf, frame_glyphs, ligatures);
+                self.run_buf.clear();
+
+                let swatch_adv = 2.0 * char_w;
+                let fits = x_offset + swatch_adv <= avail_width;
+                if !fits && !params.truncate_lines {
+                    bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
+                    col = 0;
+                    x_offset = 0.0;
+                    row += 1;
+                    row_glyph_start = frame_glyphs.glyphs.len();
+                }
+                if row < max_rows && (fits || !params.truncate_lines) {
+                    let size = (char_h * 0.7).min(swatch_adv - 2.0).max(4.0).round();
+                    let sx = (content_x + x_offset + (swatch_adv - size) / 2.0).round();
+                    let sy = (row_y[row as usize] + (char_h - size) / 2.0).round();
+                    frame_glyphs.add_border(sx, sy, size, size, face_fg);
+                    frame_glyphs.add_border(
+                        sx + 1.0, sy + 1.0, size - 2.0, size - 2.0,
+                        Color::from_pixel(swatch.color),
+                    );
+                    col += 2;
+                    x_offset += swatch_adv;
+                }
+            }
+
             // Check for display text property at property boundaries
             if charpos >= next_display_check {
-                neomacs_layout_check_display_prop(
+                cache.check_display_prop(
+                    host,
                     buffer,
                     window,
                     charpos,
@@ -1402,6 +2229,118 @@ impl LayoutEngine {
                     self.run_buf.clear();
                 }
 
+                // Text replaced by a string, space or media: media that
+                // doesn't fit the rest of the row starts the next one, and
+                // the replacement is where the covered positions are shown,
+                // for the cursor and for clicks
+                if matches!(display_prop.prop_type, 1 | 2 | 3 | 4 | 9 | 10) {
+                    let media = match display_prop.prop_type {
+                        4 if display_prop.image_gpu_id != 0 => Some((
+                            (display_prop.image_width + 2 * display_prop.image_hmargin) as f32,
+                            (display_prop.image_height + 2 * display_prop.image_vmargin) as f32,
+                        )),
+                        9 if display_prop.video_id != 0 => Some((
+                            display_prop.image_width as f32,
+                            display_prop.image_height as f32,
+                        )),
+                        10 if display_prop.webkit_id != 0 => Some((
+                            display_prop.image_width as f32,
+                            display_prop.image_height as f32,
+                        )),
+                        _ => None,
+                    };
+                    if media.is_some_and(|(w, _)| x_offset > 0.0 && x_offset + w > avail_width)
+                        && !params.truncate_lines
+                    {
+                        bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
+                        let gy = row_y[row as usize];
+                        let remaining = avail_width - x_offset;
+                        Self::add_stretch_for_face(&self.face_data, frame_glyphs, content_x + x_offset, gy, remaining, char_h, face_bg, self.face_data.face_id, false);
+                        if (row as usize) < row_continued.len() {
+                            row_continued[row as usize] = true;
+                        }
+                        Self::advance_row(
+                            &mut hit_rows, &mut hit_row_starts, &mut hit_row_charpos_start, charpos,
+                            &mut row, &mut row_y, &mut row_extra_y, &mut row_above,
+                            &mut row_max_height, &mut row_max_ascent, (0.0, 0.0), text_y, char_h, ascent,
+                        );
+                        col = 0;
+                        x_offset = 0.0;
+                        row_glyph_start = frame_glyphs.glyphs.len();
+                        if (row as usize) < row_continuation.len() {
+                            row_continuation[row as usize] = true;
+                        }
+                        wrap_has_break = false;
+                        if row >= max_rows {
+                            break;
+                        }
+                    }
+
+                    hit_row_starts.push((charpos, x_offset));
+
+                    // Point anywhere in the covered text is shown on the
+                    // first glyph of the replacement, or around the media
+                    if !cursor_placed && params.point < display_prop.covers_to {
+                        cursor_col = col;
+                        cursor_x = x_offset;
+                        cursor_row = row;
+                        cursor_charpos = charpos;
+                        let cursor_px = content_x + x_offset;
+                        let cursor_y = row_y[row as usize];
+                        let (cursor_w, cursor_h) = match (media, display_prop.prop_type) {
+                            (Some(size), _) => size,
+                            (None, 1) if display_prop.str_len > 0 => {
+                                let (dch, _) = decode_utf8(&display_str_buf[..display_prop.str_len as usize]);
+                                let dcols = if is_wide_char(dch) { 2 } else { 1 };
+                                (dcols as f32 * char_w, face_h)
+                            }
+                            (None, 2) if display_prop.space_width > 0.0 => {
+                                (display_prop.space_width * char_w, face_h)
+                            }
+                            _ => (char_w, face_h),
+                        };
+
+                        let cursor_style = if params.selected {
+                            CursorStyle::from_type(params.cursor_type, params.cursor_bar_width)
+                        } else if params.cursor_in_non_selected {
+                            Some(CursorStyle::Hollow)
+                        } else {
+                            None
+                        };
+
+                        if let Some(style) = cursor_style {
+                            // A filled box would hide the media: outline it
+                            let style = if media.is_some() && matches!(style, CursorStyle::FilledBox) {
+                                CursorStyle::Hollow
+                            } else {
+                                style
+                            };
+                            frame_glyphs.add_cursor(
+                                params.window_id as i32,
+                                cursor_px,
+                                cursor_y,
+                                cursor_w,
+                                cursor_h,
+                                style,
+                                face_fg,
+                            );
+
+                            if matches!(style, CursorStyle::FilledBox) {
+                                frame_glyphs.set_cursor_inverse(
+                                    cursor_px,
+                                    cursor_y,
+                                    cursor_w,
+                                    cursor_h,
+                                    face_fg,
+                                    face_bg,
+                                );
+                            }
+                        }
+
+                        cursor_placed = true;
+                    }
+                }
+
                 if display_prop.prop_type == 1 {
                     // String replacement: render the display string instead
                     // of buffer text, skip original chars up to covers_to.
@@ -1409,7 +2348,8 @@ impl LayoutEngine {
                     // First resolve face at this position
                     if charpos >= next_face_check || current_face_id < 0 {
                         let mut next_check: i64 = 0;
-                        let fid = neomacs_layout_face_at_pos(
+                        let fid = cache.face_at_pos(
+                            host,
                             window, charpos,
                             &mut self.face_data as *mut FaceDataFFI,
                             &mut next_check,
@@ -1418,7 +2358,7 @@ impl LayoutEngine {
                             current_face_id = fid;
                             face_fg = Color::from_pixel(self.face_data.fg);
                             face_bg = Color::from_pixel(self.face_data.bg);
-                            self.apply_face(&self.face_data, frame, frame_glyphs);
+                            self.apply_face(host, &self.face_data, frame, frame_glyphs);
                         }
                         next_face_check = if next_check > charpos { next_check } else { charpos + 1 };
                     }
@@ -1503,12 +2443,26 @@ impl LayoutEngine {
                             if params.truncate_lines {
                                 break;
                             }
-                            reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                            bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
+                            if (row as usize) < row_continued.len() {
+                                row_continued[row as usize] = true;
+                            }
+                            // Clicks on the rest of the string still land
+                            // on the text it replaces
+                            Self::advance_row(
+                                &mut hit_rows, &mut hit_row_starts, &mut hit_row_charpos_start, charpos,
+                                &mut row, &mut row_y, &mut row_extra_y, &mut row_above,
+                                &mut row_max_height, &mut row_max_ascent, (0.0, 0.0), text_y, char_h, ascent,
+                            );
                             col = 0;
                             x_offset = 0.0;
-                            row += 1;
                             row_glyph_start = frame_glyphs.glyphs.len();
+                            if (row as usize) < row_continuation.len() {
+                                row_continuation[row as usize] = true;
+                            }
+                            wrap_has_break = false;
                             if row >= max_rows { break; }
+                            hit_row_starts.push((charpos, 0.0));
                         }
 
                         let gx = content_x + x_offset;
@@ -1522,13 +2476,13 @@ impl LayoutEngine {
                     if (has_face_runs || display_prop.display_fg != 0 || display_prop.display_bg != 0)
                         && current_face_id >= 0
                     {
-                        self.apply_face(&self.face_data, frame, frame_glyphs);
+                        self.apply_face(host, &self.face_data, frame, frame_glyphs);
                     }
 
                     // Skip original buffer text covered by this display prop
                     let chars_to_skip = display_prop.covers_to - charpos;
                     for _ in 0..chars_to_skip {
-                        if byte_idx >= bytes_read as usize { break; }
+                        if byte_idx >= bytes_read { break; }
                         let (_, ch_len) = decode_utf8(&text[byte_idx..]);
                         byte_idx += ch_len;
                     }
@@ -1543,7 +2497,8 @@ impl LayoutEngine {
                     // Resolve face first
                     if charpos >= next_face_check || current_face_id < 0 {
                         let mut next_check: i64 = 0;
-                        let fid = neomacs_layout_face_at_pos(
+                        let fid = cache.face_at_pos(
+                            host,
                             window, charpos,
                             &mut self.face_data as *mut FaceDataFFI,
                             &mut next_check,
@@ -1552,7 +2507,7 @@ impl LayoutEngine {
                             current_face_id = fid;
                             face_fg = Color::from_pixel(self.face_data.fg);
                             face_bg = Color::from_pixel(self.face_data.bg);
-                            self.apply_face(&self.face_data, frame, frame_glyphs);
+                            self.apply_face(host, &self.face_data, frame, frame_glyphs);
                         }
                         next_face_check = if next_check > charpos { next_check } else { charpos + 1 };
                     }
@@ -1580,7 +2535,7 @@ impl LayoutEngine {
                     // Skip original buffer text
                     let chars_to_skip = display_prop.covers_to - charpos;
                     for _ in 0..chars_to_skip {
-                        if byte_idx >= bytes_read as usize { break; }
+                        if byte_idx >= bytes_read { break; }
                         let (_, ch_len) = decode_utf8(&text[byte_idx..]);
                         byte_idx += ch_len;
                     }
@@ -1595,7 +2550,8 @@ impl LayoutEngine {
                     // Resolve face first
                     if charpos >= next_face_check || current_face_id < 0 {
                         let mut next_check: i64 = 0;
-                        let fid = neomacs_layout_face_at_pos(
+                        let fid = cache.face_at_pos(
+                            host,
                             window, charpos,
                             &mut self.face_data as *mut FaceDataFFI,
                             &mut next_check,
@@ -1604,7 +2560,7 @@ impl LayoutEngine {
                             current_face_id = fid;
                             face_fg = Color::from_pixel(self.face_data.fg);
                             face_bg = Color::from_pixel(self.face_data.bg);
-                            self.apply_face(&self.face_data, frame, frame_glyphs);
+                            self.apply_face(host, &self.face_data, frame, frame_glyphs);
                         }
                         next_face_check = if next_check > charpos { next_check } else { charpos + 1 };
                     }
@@ -1626,7 +2582,7 @@ impl LayoutEngine {
                     // Skip original buffer text
                     let chars_to_skip = display_prop.covers_to - charpos;
                     for _ in 0..chars_to_skip {
-                        if byte_idx >= bytes_read as usize { break; }
+                        if byte_idx >= bytes_read { break; }
                         let (_, ch_len) = decode_utf8(&text[byte_idx..]);
                         byte_idx += ch_len;
                     }
@@ -1685,7 +2641,7 @@ impl LayoutEngine {
                     // Skip original buffer text
                     let chars_to_skip = display_prop.covers_to - charpos;
                     for _ in 0..chars_to_skip {
-                        if byte_idx >= bytes_read as usize { break; }
+                        if byte_idx >= bytes_read { break; }
                         let (_, ch_len) = decode_utf8(&text[byte_idx..]);
                         byte_idx += ch_len;
                     }
@@ -1720,7 +2676,7 @@ impl LayoutEngine {
                     // Skip original buffer text
                     let chars_to_skip = display_prop.covers_to - charpos;
                     for _ in 0..chars_to_skip {
-                        if byte_idx >= bytes_read as usize { break; }
+                        if byte_idx >= bytes_read { break; }
                         let (_, ch_len) = decode_utf8(&text[byte_idx..]);
                         byte_idx += ch_len;
                     }
@@ -1755,7 +2711,7 @@ impl LayoutEngine {
                     // Skip original buffer text
                     let chars_to_skip = display_prop.covers_to - charpos;
                     for _ in 0..chars_to_skip {
-                        if byte_idx >= bytes_read as usize { break; }
+                        if byte_idx >= bytes_read { break; }
                         let (_, ch_len) = decode_utf8(&text[byte_idx..]);
                         byte_idx += ch_len;
                     }
@@ -1799,7 +2755,7 @@ impl LayoutEngine {
                     // Skip the covered text
                     let chars_to_skip = display_prop.covers_to - charpos;
                     for _ in 0..chars_to_skip {
-                        if byte_idx >= bytes_read as usize { break; }
+                        if byte_idx >= bytes_read { break; }
                         let (_, ch_len) = decode_utf8(&text[byte_idx..]);
                         byte_idx += ch_len;
                     }
@@ -1828,7 +2784,8 @@ impl LayoutEngine {
             // Resolve face if needed (when entering a new face region)
             if charpos >= next_face_check || current_face_id < 0 {
                 let mut next_check: i64 = 0;
-                let fid = neomacs_layout_face_at_pos(
+                let fid = cache.face_at_pos(
+                    host,
                     window,
                     charpos,
                     &mut self.face_data as *mut FaceDataFFI,
@@ -1883,7 +2840,7 @@ impl LayoutEngine {
                         // unavailable. Use default font metrics for layout.
                         overstrike = self.face_data.overstrike != 0;
 
-                        self.apply_face(&self.face_data, frame, frame_glyphs);
+                        self.apply_face(host, &self.face_data, frame, frame_glyphs);
 
                         // Debug: check all face properties
                         if charpos < window_start + 5 {
@@ -1910,19 +2867,87 @@ impl LayoutEngine {
                 }
             }
 
-            // Check if cursor is at this position
-            if !cursor_placed && charpos >= params.point {
+            // Static composition (compose-region, prettify-symbols-mode):
+            // the composed characters replace the buffer text they cover
+            // and are drawn, and edited around, as a single unit.
+            let mut composed: Option<(usize, i64, i32)> = None;
+            if charpos >= next_composition_check {
+                let mut cmp_end: i64 = 0;
+                let mut cmp_cols: c_int = 0;
+                let n = host.composition_at(
+                    buffer,
+                    charpos,
+                    composition_buf.as_mut_ptr(),
+                    composition_buf.len() as c_int,
+                    &mut cmp_end,
+                    &mut cmp_cols,
+                );
+                next_composition_check = cmp_end.max(charpos + 1);
+                if n > 0 && cmp_end > charpos {
+                    composed = Some((n as usize, cmp_end, cmp_cols));
+                }
+            }
+
+            if let Some((_, _, cmp_cols)) = composed {
+                let cmp_w = cmp_cols as f32 * char_w;
+                if x_offset > 0.0 && x_offset + cmp_w > avail_width && !params.truncate_lines {
+                    // Wrap before the composed unit so it is never split
+                    flush_run(&self.run_buf, frame_glyphs, ligatures);
+                    self.run_buf.clear();
+                    bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
+                    let remaining = avail_width - x_offset;
+                    let gy = row_y[row as usize];
+                    Self::add_stretch_for_face(&self.face_data, frame_glyphs, content_x + x_offset, gy, remaining, char_h, face_bg, self.face_data.face_id, false);
+                    if (row as usize) < row_continued.len() {
+                        row_continued[row as usize] = true;
+                    }
+                    Self::advance_row(
+                        &mut hit_rows, &mut hit_row_starts, &mut hit_row_charpos_start, charpos,
+                        &mut row, &mut row_y, &mut row_extra_y, &mut row_above,
+                        &mut row_max_height, &mut row_max_ascent, (0.0, 0.0), text_y, char_h, ascent,
+                    );
+                    col = 0;
+                    x_offset = 0.0;
+                    row_glyph_start = frame_glyphs.glyphs.len();
+                    if (row as usize) < row_continuation.len() {
+                        row_continuation[row as usize] = true;
+                    }
+                    wrap_has_break = false;
+                    need_prefix = 2;
+                    // Re-enter at the same position on the new row
+                    next_composition_check = charpos;
+                    if row >= max_rows {
+                        break;
+                    }
+                    continue;
+                }
+            }
+
+            // Check if cursor is at this position (or inside a composition
+            // starting here: the cursor covers the whole composed unit)
+            if !cursor_placed
+                && (charpos >= params.point
+                    || matches!(composed, Some((_, cmp_end, _)) if params.point < cmp_end))
+            {
                 // Flush ligature run before cursor to split run at cursor position
                 flush_run(&self.run_buf, frame_glyphs, ligatures);
                 self.run_buf.clear();
                 cursor_col = col;
                 cursor_x = x_offset;
                 cursor_row = row;
+                cursor_charpos = charpos;
                 let cursor_px = content_x + x_offset;
                 let cursor_y = row_y[row as usize];
 
-                // Use face-specific dimensions so cursor matches variable-height faces
-                let cursor_face_w = if self.face_data.font_char_width > 0.0 {
+                // Use face-specific dimensions so cursor matches variable-height
+                // faces, and covers the character at point in variable-width ones
+                let next_ch = (byte_idx < bytes_read).then(|| decode_utf8(&text[byte_idx..]).0);
+                let cursor_face_w = if let Some((_, _, cmp_cols)) = composed {
+                    cmp_cols as f32 * char_w
+                } else if let Some(ch) = next_ch.filter(|&c| c >= ' ' && c != '\u{7f}' && !overstrike) {
+                    let char_cols = if is_wide_char(ch) { 2 } else { 1 };
+                    self.face_char_advance(host, ch, char_cols, char_w, window)
+                } else if self.face_data.font_char_width > 0.0 {
                     self.face_data.font_char_width
                 } else {
                     char_w
@@ -1962,10 +2987,54 @@ impl LayoutEngine {
                 cursor_placed = true;
             }
 
+            // Where this position starts on the row, for hit-testing
+            hit_row_starts.push((charpos, x_offset));
+
+            if let Some((cmp_len, cmp_end, cmp_cols)) = composed {
+                flush_run(&self.run_buf, frame_glyphs, ligatures);
+                self.run_buf.clear();
+                let cmp_w = cmp_cols as f32 * char_w;
+                if x_offset + cmp_w > avail_width && x_offset > 0.0 {
+                    // Truncated line: nothing of the unit fits, so let the
+                    // next character show the truncation indicator.
+                    x_offset = avail_width;
+                } else {
+                    let cmp_text = std::str::from_utf8(&composition_buf[..cmp_len]).unwrap_or("");
+                    let mut chars = cmp_text.chars();
+                    let gx = content_x + x_offset;
+                    let gy = row_y[row as usize] + raise_y_offset;
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => {
+                            frame_glyphs.add_char(c, gx, gy, cmp_w, face_h, face_ascent, false)
+                        }
+                        (Some(c), Some(_)) => frame_glyphs.add_composed_char(
+                            cmp_text, c, gx, gy, cmp_w, face_h, face_ascent, false,
+                        ),
+                        _ => {}
+                    }
+                    col += cmp_cols;
+                    x_offset += cmp_w;
+                    if face_h > row_max_height {
+                        row_max_height = face_h;
+                    }
+                }
+
+                // Skip the buffer text the composition covers
+                for _ in charpos..cmp_end {
+                    if byte_idx >= bytes_read { break; }
+                    let (_, ch_len) = decode_utf8(&text[byte_idx..]);
+                    byte_idx += ch_len;
+                }
+                charpos = cmp_end;
+                window_end_charpos = charpos;
+                continue;
+            }
+
             // Decode one UTF-8 character
             let (ch, ch_len) = decode_utf8(&text[byte_idx..]);
             byte_idx += ch_len;
             charpos += 1;
+            let prev_ch = std::mem::replace(&mut wrap_prev_ch, ch);
 
             match ch {
                 '\n' => {
@@ -1973,27 +3042,77 @@ impl LayoutEngine {
                     flush_run(&self.run_buf, frame_glyphs, ligatures);
                     self.run_buf.clear();
 
-                    // Bidi reorder: reorder glyph X positions for this completed row
-                    reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
-
-                    // Highlight trailing whitespace (overlay stretch on top)
-                    if let Some(tw_bg) = trailing_ws_bg {
-                        if trailing_ws_start_col >= 0 && trailing_ws_row == row {
-                            let tw_x = content_x + trailing_ws_start_x;
-                            let tw_w = x_offset - trailing_ws_start_x;
-                            let gy = row_y[row as usize];
-                            if tw_w > 0.0 {
-                                frame_glyphs.add_stretch(tw_x, gy, tw_w, char_h, tw_bg, 0, false);
-                            }
-                        }
-                    }
-                    trailing_ws_start_col = -1;
-
-                    // Fill rest of line with stretch.
-                    // Use face bg if :extend is set, default bg otherwise.
-                    let remaining = avail_width - x_offset;
-                    if remaining > 0.0 {
-                        let gx = content_x + x_offset;
+                    // Selective display: lines indented beyond the threshold
+                    // are hidden and join this row, which ends with "..."
+                    if params.selective_display > 0 {
+                        let mut hidden = false;
+                        while byte_idx < bytes_read {
+                            // Peek at indentation of next line
+                            let mut indent = 0i32;
+                            let mut peek_idx = byte_idx;
+                            while peek_idx < bytes_read {
+                                let (pch, plen) = decode_utf8(&text[peek_idx..]);
+                                if pch == ' ' {
+                                    indent += 1;
+                                } else if pch == '\t' {
+                                    let tab_w = params.tab_width.max(1);
+                                    indent = ((indent / tab_w) + 1) * tab_w;
+                                } else {
+                                    break;
+                                }
+                                peek_idx += plen;
+                            }
+                            if indent <= params.selective_display {
+                                break; // Next line is visible
+                            }
+                            // Skip this hidden line and its newline
+                            hidden = true;
+                            current_face_id = -1;
+                            while byte_idx < bytes_read {
+                                let (sch, slen) = decode_utf8(&text[byte_idx..]);
+                                byte_idx += slen;
+                                charpos += 1;
+                                if sch == '\n' {
+                                    current_line += 1;
+                                    break;
+                                }
+                            }
+                        }
+                        if hidden
+                            && params.selective_display_ellipses
+                            && x_offset + 3.0 * char_w <= avail_width
+                        {
+                            let gy = row_y[row as usize];
+                            for _ in 0..3 {
+                                frame_glyphs.add_char(
+                                    '.', content_x + x_offset, gy, char_w, char_h, ascent, false,
+                                );
+                                x_offset += char_w;
+                            }
+                        }
+                    }
+
+                    // Bidi reorder: reorder glyph X positions for this completed row
+                    bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
+
+                    // Highlight trailing whitespace (overlay stretch on top)
+                    if let Some(tw_bg) = trailing_ws_bg {
+                        if trailing_ws_start_col >= 0 && trailing_ws_row == row {
+                            let tw_x = content_x + trailing_ws_start_x;
+                            let tw_w = x_offset - trailing_ws_start_x;
+                            let gy = row_y[row as usize];
+                            if tw_w > 0.0 {
+                                frame_glyphs.add_stretch(tw_x, gy, tw_w, char_h, tw_bg, 0, false);
+                            }
+                        }
+                    }
+                    trailing_ws_start_col = -1;
+
+                    // Fill rest of line with stretch.
+                    // Use face bg if :extend is set, default bg otherwise.
+                    let remaining = avail_width - x_offset;
+                    if remaining > 0.0 {
+                        let gx = content_x + x_offset;
                         let gy = row_y[row as usize];
                         let fill_bg = if self.face_data.extend != 0 { face_bg } else { default_bg };
                         let fill_face = if self.face_data.extend != 0 { self.face_data.face_id } else { 0 };
@@ -2010,104 +3129,31 @@ impl LayoutEngine {
                         box_start_x = content_x;
                     }
 
-                    // Record hit-test row (newline ends the row)
-                    if (row as usize) < row_y.len() {
-                        hit_rows.push(HitRow {
-                            y_start: row_y[row as usize],
-                            y_end: row_y[row as usize] + row_max_height,
-                            charpos_start: hit_row_charpos_start,
-                            charpos_end: charpos,
-                        });
-                        hit_row_charpos_start = charpos;
-                    }
+                    // Record hit-test row (newline ends the row), with the
+                    // space below the line just ended and above the next
+                    // one, from line-spacing / line-height on their newlines
+                    let (above, below) = Self::line_spacing_at(
+                        host,
+                        buffer, window, text, byte_idx, charpos, char_h,
+                    );
+                    Self::advance_row(
+                        &mut hit_rows, &mut hit_row_starts, &mut hit_row_charpos_start, charpos,
+                        &mut row, &mut row_y, &mut row_extra_y, &mut row_above,
+                        &mut row_max_height, &mut row_max_ascent, (line_below, above), text_y, char_h, ascent,
+                    );
+                    line_below = below;
 
                     col = 0;
                     x_offset = 0.0;
-                    row += 1;
                     row_glyph_start = frame_glyphs.glyphs.len();
 
-                    // Apply extra height from variable-height faces on this row
-                    if row_max_height > char_h {
-                        let extra = row_max_height - char_h;
-                        row_extra_y += extra;
-                        for ri in (row as usize)..row_y.len() {
-                            row_y[ri] = text_y + ri as f32 * char_h + row_extra_y;
-                        }
-                    }
-                    // Reset per-row tracking for the new row
-                    row_max_height = char_h;
-                    row_max_ascent = ascent;
-
-                    // Check line-height / line-spacing text properties on the newline
-                    {
-                        let mut extra_h: f32 = 0.0;
-                        let nl_pos = charpos - 1; // the newline we just consumed
-                        neomacs_layout_check_line_spacing(
-                            buffer, window, nl_pos, char_h, &mut extra_h,
-                        );
-                        if extra_h > 0.0 {
-                            row_extra_y += extra_h;
-                            // Update all remaining row_y entries
-                            for ri in (row as usize)..row_y.len() {
-                                row_y[ri] = text_y + ri as f32 * char_h + row_extra_y;
-                            }
-                        }
-                    }
-
                     if box_active { box_row = row; }
                     current_line += 1;
                     need_line_number = lnum_enabled;
                     need_margin_check = has_margins;
                     wrap_has_break = false;
-                    hscroll_remaining = hscroll;
-                    if !params.line_prefix.is_empty() { need_prefix = 1; }
-
-                    // Selective display: skip lines indented beyond threshold
-                    if params.selective_display > 0 {
-                        let mut shown_ellipsis = false;
-                        while byte_idx < bytes_read as usize && row < max_rows {
-                            // Peek at indentation of next line
-                            let mut indent = 0i32;
-                            let mut peek_idx = byte_idx;
-                            while peek_idx < bytes_read as usize {
-                                let (pch, plen) = decode_utf8(&text[peek_idx..]);
-                                if pch == ' ' {
-                                    indent += 1;
-                                } else if pch == '\t' {
-                                    indent = ((indent / params.tab_width) + 1) * params.tab_width;
-                                } else {
-                                    break;
-                                }
-                                peek_idx += plen;
-                            }
-
-                            if indent > params.selective_display {
-                                // Show ... ellipsis once for the hidden block
-                                if !shown_ellipsis && row > 0 {
-                                    let gy = row_y[(row - 1) as usize];
-                                    for dot_i in 0..3i32.min(cols) {
-                                        frame_glyphs.add_char(
-                                            '.', content_x + dot_i as f32 * char_w,
-                                            gy, char_w, char_h, ascent, false,
-                                        );
-                                    }
-                                    shown_ellipsis = true;
-                                }
-                                // Skip this hidden line
-                                while byte_idx < bytes_read as usize {
-                                    let (sch, slen) = decode_utf8(&text[byte_idx..]);
-                                    byte_idx += slen;
-                                    charpos += 1;
-                                    if sch == '\n' {
-                                        current_line += 1;
-                                        break;
-                                    }
-                                }
-                            } else {
-                                break; // Next line is visible
-                            }
-                        }
-                    }
+                    hscroll_pending = hscroll > 0;
+                    need_prefix = 1;
                 }
                 '\t' => {
                     // Flush ligature run before tab
@@ -2116,7 +3162,7 @@ impl LayoutEngine {
 
                     // Tab: advance to next tab stop (column-based, pixel width uses space_w)
                     let tab_w = params.tab_width.max(1);
-                    let next_tab = ((col / tab_w) + 1) * tab_w;
+                    let next_tab = ((col + tab_origin) / tab_w + 1) * tab_w - tab_origin;
                     let spaces = (next_tab - col).min(cols - col);
                     let tab_pixel_w = spaces as f32 * face_space_w;
 
@@ -2138,24 +3184,42 @@ impl LayoutEngine {
                     }
                     if x_offset >= avail_width {
                         // Bidi reorder before advancing to next row
-                        reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                        bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                         if params.truncate_lines {
+                            if right_fringe_width <= 0.0 {
+                                Self::add_right_truncation(
+                                    frame_glyphs, row_glyph_start, content_x + avail_width - char_w,
+                                    row_y[row as usize], char_w, char_h, ascent,
+                                );
+                            }
                             if (row as usize) < row_truncated.len() {
                                 row_truncated[row as usize] = true;
                             }
-                            while byte_idx < bytes_read as usize {
+                            while byte_idx < bytes_read {
                                 let (c, l) = decode_utf8(&text[byte_idx..]);
                                 byte_idx += l;
                                 charpos += 1;
                                 if c == '\n' {
+                                    // Record hit-test row (the truncated line ends here),
+                                    // with the line spacing below it and above the next line
+                                    let (above, below) = Self::line_spacing_at(
+                                        host,
+                                        buffer, window, text, byte_idx, charpos, char_h,
+                                    );
+                                    Self::advance_row(
+                                        &mut hit_rows, &mut hit_row_starts, &mut hit_row_charpos_start, charpos,
+                                        &mut row, &mut row_y, &mut row_extra_y, &mut row_above,
+                                        &mut row_max_height, &mut row_max_ascent, (line_below, above), text_y, char_h, ascent,
+                                    );
+                                    line_below = below;
                                     col = 0;
                                     x_offset = 0.0;
-                                    row += 1;
                                     row_glyph_start = frame_glyphs.glyphs.len();
                                     current_line += 1;
                                     need_line_number = lnum_enabled;
                                     need_margin_check = has_margins;
                                     wrap_has_break = false;
+                                    hscroll_pending = hscroll > 0;
                                     break;
                                 }
                             }
@@ -2171,7 +3235,7 @@ impl LayoutEngine {
                                 row_continuation[row as usize] = true;
                             }
                             wrap_has_break = false;
-                            if !params.wrap_prefix.is_empty() { need_prefix = 2; }
+                            need_prefix = 2;
                         }
                     }
                 }
@@ -2180,37 +3244,27 @@ impl LayoutEngine {
                     flush_run(&self.run_buf, frame_glyphs, ligatures);
                     self.run_buf.clear();
 
-                    if params.selective_display > 0 {
-                        // In selective-display mode, \r hides until next \n
-                        // Show ... ellipsis
-                        let gy = row_y[row as usize];
-                        if x_offset + 3.0 * char_w <= avail_width {
-                            for dot_i in 0..3 {
-                                frame_glyphs.add_char(
-                                    '.', content_x + x_offset + dot_i as f32 * char_w,
-                                    gy, char_w, char_h, ascent, false,
-                                );
-                            }
-                        }
-                        // Bidi reorder before advancing to next row
-                        reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
-                        // Skip to next \n
-                        while byte_idx < bytes_read as usize {
+                    if params.selective_display != 0 {
+                        // In selective-display mode, \r hides the rest of
+                        // the line; the newline still ends the row
+                        while byte_idx < bytes_read {
                             let (sch, slen) = decode_utf8(&text[byte_idx..]);
-                            byte_idx += slen;
-                            charpos += 1;
                             if sch == '\n' {
-                                col = 0;
-                                x_offset = 0.0;
-                                row += 1;
-                                row_glyph_start = frame_glyphs.glyphs.len();
-                                current_line += 1;
-                                need_line_number = lnum_enabled;
-                                need_margin_check = has_margins;
-                                wrap_has_break = false;
-                                hscroll_remaining = hscroll;
                                 break;
                             }
+                            byte_idx += slen;
+                            charpos += 1;
+                        }
+                        current_face_id = -1;
+                        if params.selective_display_ellipses && x_offset + 3.0 * char_w <= avail_width {
+                            let gy = row_y[row as usize];
+                            for _ in 0..3 {
+                                frame_glyphs.add_char(
+                                    '.', content_x + x_offset, gy, char_w, char_h, ascent, false,
+                                );
+                                col += 1;
+                                x_offset += char_w;
+                            }
                         }
                     }
                     // Otherwise: carriage return is just skipped
@@ -2248,21 +3302,42 @@ impl LayoutEngine {
                         x_offset += 2.0 * char_w;
                     } else {
                         // Bidi reorder before advancing to next row (control char overflow)
-                        reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                        bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                         if params.truncate_lines {
-                            while byte_idx < bytes_read as usize {
+                            if right_fringe_width <= 0.0 {
+                                Self::add_right_truncation(
+                                    frame_glyphs, row_glyph_start, content_x + avail_width - char_w,
+                                    gy, char_w, char_h, ascent,
+                                );
+                            }
+                            if (row as usize) < row_truncated.len() {
+                                row_truncated[row as usize] = true;
+                            }
+                            while byte_idx < bytes_read {
                                 let (c, l) = decode_utf8(&text[byte_idx..]);
                                 byte_idx += l;
                                 charpos += 1;
                                 if c == '\n' {
+                                    // Record hit-test row (the truncated line ends here),
+                                    // with the line spacing below it and above the next line
+                                    let (above, below) = Self::line_spacing_at(
+                                        host,
+                                        buffer, window, text, byte_idx, charpos, char_h,
+                                    );
+                                    Self::advance_row(
+                                        &mut hit_rows, &mut hit_row_starts, &mut hit_row_charpos_start, charpos,
+                                        &mut row, &mut row_y, &mut row_extra_y, &mut row_above,
+                                        &mut row_max_height, &mut row_max_ascent, (line_below, above), text_y, char_h, ascent,
+                                    );
+                                    line_below = below;
                                     col = 0;
                                     x_offset = 0.0;
-                                    row += 1;
                                     row_glyph_start = frame_glyphs.glyphs.len();
                                     current_line += 1;
                                     need_line_number = lnum_enabled;
                                     need_margin_check = has_margins;
                                     wrap_has_break = false;
+                                    hscroll_pending = hscroll > 0;
                                     break;
                                 }
                             }
@@ -2276,10 +3351,67 @@ impl LayoutEngine {
                     }
                     // Restore text face after escape-glyph
                     if current_face_id >= 0 {
-                        self.apply_face(&self.face_data, frame, frame_glyphs);
+                        self.apply_face(host, &self.face_data, frame, frame_glyphs);
                     }
                 }
                 _ => {
+                    // Word-wrap breakpoint before this character (UAX #14
+                    // with kinsoku), unless it starts the row
+                    if params.word_wrap
+                        && charpos - 1 > hit_row_charpos_start
+                        && break_between(prev_ch, ch)
+                    {
+                        // Flush ligature run so truncate() never cuts
+                        // inside a composed glyph
+                        flush_run(&self.run_buf, frame_glyphs, ligatures);
+                        self.run_buf.clear();
+
+                        wrap_break_col = col;
+                        wrap_break_x = x_offset;
+                        wrap_break_byte_idx = byte_idx - ch_len;
+                        wrap_break_charpos = charpos - 1;
+                        wrap_break_glyph_count = frame_glyphs.glyphs.len();
+                        wrap_has_break = true;
+                    }
+
+                    // Hyphenation points of the word starting here, and
+                    // the hyphenation break before this character
+                    if let Some(ref patterns) = hyph_patterns {
+                        if ch.is_alphabetic() && !prev_ch.is_alphabetic() && charpos > hyph_word_end {
+                            // Letters, and apostrophes between letters
+                            // where the patterns have them ("l'intérêt")
+                            let mut word = String::new();
+                            let mut wi = byte_idx - ch_len;
+                            while wi < text.len() && word.len() < 256 {
+                                let (wc, wlen) = decode_utf8(&text[wi..]);
+                                let joined = patterns.joins(wc)
+                                    && wi + wlen < text.len()
+                                    && decode_utf8(&text[wi + wlen..]).0.is_alphabetic();
+                                if !wc.is_alphabetic() && !joined {
+                                    break;
+                                }
+                                word.push(wc);
+                                wi += wlen;
+                            }
+                            hyph_word_end = charpos - 1 + word.chars().count() as i64;
+                            hyph_points = patterns.hyphenate(&word)
+                                .into_iter()
+                                .map(|i| charpos - 1 + i as i64)
+                                .collect();
+                        }
+                        // Only where the hyphen still fits on the row
+                        if hyph_points.contains(&(charpos - 1)) && x_offset + char_w <= avail_width {
+                            flush_run(&self.run_buf, frame_glyphs, ligatures);
+                            self.run_buf.clear();
+
+                            hyph_row = row;
+                            hyph_break_x = x_offset;
+                            hyph_break_byte_idx = byte_idx - ch_len;
+                            hyph_break_charpos = charpos - 1;
+                            hyph_break_glyph_count = frame_glyphs.glyphs.len();
+                        }
+                    }
+
                     // Non-breaking space and soft hyphen highlighting
                     if params.nobreak_char_display > 0 && (ch == '\u{00A0}' || ch == '\u{00AD}') {
                         // Flush ligature run before nobreak char special handling
@@ -2300,7 +3432,7 @@ impl LayoutEngine {
                         }
                         // Restore text face
                         if current_face_id >= 0 {
-                            self.apply_face(&self.face_data, frame, frame_glyphs);
+                            self.apply_face(host, &self.face_data, frame, frame_glyphs);
                         }
                         window_end_charpos = charpos;
                         continue;
@@ -2310,7 +3442,7 @@ impl LayoutEngine {
                     // ZWJ sequences, variation selectors, skin tone modifiers,
                     // and regional indicator pairs with the base character.
                     let (cluster_text, cluster_extra_bytes, cluster_extra_chars) =
-                        collect_grapheme_cluster(ch, &text[byte_idx..bytes_read as usize]);
+                        collect_grapheme_cluster(ch, &text[byte_idx..bytes_read]);
 
                     if let Some(ref cluster) = cluster_text {
                         // Flush ligature run before grapheme cluster (emoji/ZWJ)
@@ -2326,23 +3458,43 @@ impl LayoutEngine {
                         let glyph_w = char_cols as f32 * char_w;
 
                         if x_offset + glyph_w > avail_width {
-                            reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                            bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                             if params.truncate_lines {
+                                if right_fringe_width <= 0.0 {
+                                    Self::add_right_truncation(
+                                        frame_glyphs, row_glyph_start, content_x + avail_width - char_w,
+                                        row_y[row as usize], char_w, char_h, ascent,
+                                    );
+                                }
+                                if (row as usize) < row_truncated.len() {
+                                    row_truncated[row as usize] = true;
+                                }
                                 // Skip to end of line
-                                while byte_idx < bytes_read as usize {
+                                while byte_idx < bytes_read {
                                     let (c, l) = decode_utf8(&text[byte_idx..]);
                                     byte_idx += l;
                                     charpos += 1;
                                     if c == '\n' {
+                                        // Record hit-test row (the truncated line ends here),
+                                        // with the line spacing below it and above the next line
+                                        let (above, below) = Self::line_spacing_at(
+                                            host,
+                                            buffer, window, text, byte_idx, charpos, char_h,
+                                        );
+                                        Self::advance_row(
+                                            &mut hit_rows, &mut hit_row_starts, &mut hit_row_charpos_start, charpos,
+                                            &mut row, &mut row_y, &mut row_extra_y, &mut row_above,
+                                            &mut row_max_height, &mut row_max_ascent, (line_below, above), text_y, char_h, ascent,
+                                        );
+                                        line_below = below;
                                         col = 0;
                                         x_offset = 0.0;
-                                        row += 1;
                                         row_glyph_start = frame_glyphs.glyphs.len();
                                         current_line += 1;
                                         need_line_number = lnum_enabled;
                                         need_margin_check = has_margins;
                                         wrap_has_break = false;
-                                        hscroll_remaining = hscroll;
+                                        hscroll_pending = hscroll > 0;
                                         break;
                                     }
                                 }
@@ -2403,7 +3555,7 @@ impl LayoutEngine {
                         let mut method: c_int = 0;
                         let mut str_buf = [0u8; 64];
                         let mut str_len: c_int = 0;
-                        neomacs_layout_check_glyphless(
+                        host.check_glyphless(
                             frame,
                             ch as c_int,
                             &mut method,
@@ -2492,19 +3644,13 @@ impl LayoutEngine {
                             }
                             // Restore face
                             if current_face_id >= 0 {
-                                self.apply_face(&self.face_data, frame, frame_glyphs);
+                                self.apply_face(host, &self.face_data, frame, frame_glyphs);
                             }
                             window_end_charpos = charpos;
                             continue;
                         }
                     }
 
-                    // Track word-wrap breakpoints: after space/tab
-                    if params.word_wrap && (ch == ' ' || ch == '\t') {
-                        // Record break AFTER this whitespace
-                        // (will be updated after rendering below)
-                    }
-
                     // Normal character — compute advance width
                     let char_cols = if is_wide_char(ch) { 2 } else { 1 };
                     let advance = if overstrike {
@@ -2513,23 +3659,7 @@ impl LayoutEngine {
                         // alignment (matching official Emacs behavior).
                         char_cols as f32 * char_w
                     } else {
-                        let face_id = self.face_data.face_id;
-                        let font_size = self.face_data.font_size;
-                        let face_char_w = self.face_data.font_char_width;
-                        let font_family = if !self.face_data.font_family.is_null() {
-                            CStr::from_ptr(self.face_data.font_family).to_str().unwrap_or("")
-                        } else {
-                            ""
-                        };
-                        let font_weight = self.face_data.font_weight as u16;
-                        let font_italic = self.face_data.italic != 0;
-                        char_advance(
-                            &mut self.ascii_width_cache,
-                            &mut self.font_metrics,
-                            ch, char_cols, char_w,
-                            face_id, font_size, face_char_w, window,
-                            font_family, font_weight, font_italic,
-                        )
+                        self.face_char_advance(host, ch, char_cols, char_w, window)
                     };
 
                     if x_offset + advance > avail_width {
@@ -2537,52 +3667,91 @@ impl LayoutEngine {
                         flush_run(&self.run_buf, frame_glyphs, ligatures);
                         self.run_buf.clear();
 
+                        // Hyphenate the word instead when wrapping before it
+                        // would leave the row ragged
+                        let mut hyphenated = false;
+                        if !params.truncate_lines
+                            && hyph_row == row
+                            && hyph_break_x > 0.0
+                            && (!wrap_has_break
+                                || wrap_break_x <= 0.0
+                                || (hyph_break_charpos > wrap_break_charpos
+                                    && avail_width - wrap_break_x > hyph_ragged_w))
+                        {
+                            wrap_break_x = hyph_break_x;
+                            wrap_break_byte_idx = hyph_break_byte_idx;
+                            wrap_break_charpos = hyph_break_charpos;
+                            wrap_break_glyph_count = hyph_break_glyph_count;
+                            wrap_has_break = true;
+                            hyphenated = true;
+                        }
+
                         // Line full
                         if params.truncate_lines {
                             // Bidi reorder this completed row before truncation
-                            reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
-                            // Show $ truncation indicator at right edge
-                            let trunc_x = content_x + avail_width - char_w;
-                            let gy = row_y[row as usize];
-                            frame_glyphs.add_char('$', trunc_x, gy, char_w, char_h, ascent, false);
+                            bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
+                            // Without a right fringe, show $ at the right edge
+                            if right_fringe_width <= 0.0 {
+                                Self::add_right_truncation(
+                                    frame_glyphs, row_glyph_start, content_x + avail_width - char_w,
+                                    row_y[row as usize], char_w, char_h, ascent,
+                                );
+                            }
                             if (row as usize) < row_truncated.len() {
                                 row_truncated[row as usize] = true;
                             }
 
-                            while byte_idx < bytes_read as usize {
+                            while byte_idx < bytes_read {
                                 let (c, l) = decode_utf8(&text[byte_idx..]);
                                 byte_idx += l;
                                 charpos += 1;
                                 if c == '\n' {
+                                    // Record hit-test row (the truncated line ends here),
+                                    // with the line spacing below it and above the next line
+                                    let (above, below) = Self::line_spacing_at(
+                                        host,
+                                        buffer, window, text, byte_idx, charpos, char_h,
+                                    );
+                                    Self::advance_row(
+                                        &mut hit_rows, &mut hit_row_starts, &mut hit_row_charpos_start, charpos,
+                                        &mut row, &mut row_y, &mut row_extra_y, &mut row_above,
+                                        &mut row_max_height, &mut row_max_ascent, (line_below, above), text_y, char_h, ascent,
+                                    );
+                                    line_below = below;
                                     col = 0;
                                     x_offset = 0.0;
-                                    row += 1;
                                     row_glyph_start = frame_glyphs.glyphs.len();
-                                    // Apply variable-height row adjustment
-                                    if row_max_height > char_h {
-                                        row_extra_y += row_max_height - char_h;
-                                        for ri in (row as usize)..row_y.len() {
-                                            row_y[ri] = text_y + ri as f32 * char_h + row_extra_y;
-                                        }
-                                    }
-                                    row_max_height = char_h;
-                                    row_max_ascent = ascent;
                                     current_line += 1;
                                     need_line_number = lnum_enabled;
                                     need_margin_check = has_margins;
                                     wrap_has_break = false;
-                                    hscroll_remaining = hscroll;
+                                    hscroll_pending = hscroll > 0;
                                     break;
                                 }
                             }
                             continue;
                         } else if params.word_wrap && wrap_has_break && wrap_break_x > 0.0 {
-                            // Word-wrap: rewind to last breakpoint
+                            // Word-wrap: rewind to last breakpoint, taking
+                            // the cursor along if it was on the text moved
+                            if cursor_placed && cursor_row == row && cursor_charpos >= wrap_break_charpos {
+                                Self::unplace_cursor(frame_glyphs, params.window_id as i32, wrap_break_glyph_count);
+                                cursor_placed = false;
+                            }
                             frame_glyphs.glyphs.truncate(wrap_break_glyph_count);
+                            // Hyphen ending a hyphenated row
+                            let mut fill_x = wrap_break_x;
+                            if hyphenated {
+                                let gy = row_y[row as usize];
+                                frame_glyphs.add_char(
+                                    '-', content_x + fill_x, gy, char_w,
+                                    char_h, ascent, false,
+                                );
+                                fill_x += char_w;
+                            }
                             // Fill from break to end of line with bg
-                            let fill_w = avail_width - wrap_break_x;
+                            let fill_w = avail_width - fill_x;
                             if fill_w > 0.0 {
-                                let gx = content_x + wrap_break_x;
+                                let gx = content_x + fill_x;
                                 let gy = row_y[row as usize];
                                 Self::add_stretch_for_face(
                                     &self.face_data, frame_glyphs,
@@ -2591,7 +3760,7 @@ impl LayoutEngine {
                                 );
                             }
                             // Bidi reorder after word-wrap truncation (re-reorder the truncated glyphs)
-                            reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                            bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                             if (row as usize) < row_continued.len() {
                                 row_continued[row as usize] = true;
                             }
@@ -2599,42 +3768,43 @@ impl LayoutEngine {
                             byte_idx = wrap_break_byte_idx;
                             charpos = wrap_break_charpos;
                             // Record hit-test row (word-wrap break)
-                            if (row as usize) < row_y.len() {
-                                hit_rows.push(HitRow {
-                                    y_start: row_y[row as usize],
-                                    y_end: row_y[row as usize] + row_max_height,
-                                    charpos_start: hit_row_charpos_start,
-                                    charpos_end: charpos,
-                                });
-                                hit_row_charpos_start = charpos;
-                            }
+                            Self::advance_row(
+                                &mut hit_rows, &mut hit_row_starts, &mut hit_row_charpos_start, charpos,
+                                &mut row, &mut row_y, &mut row_extra_y, &mut row_above,
+                                &mut row_max_height, &mut row_max_ascent, (0.0, 0.0), text_y, char_h, ascent,
+                            );
                             // Force face re-check since we rewound
                             current_face_id = -1;
                             col = 0;
                             x_offset = 0.0;
-                            row += 1;
                             row_glyph_start = frame_glyphs.glyphs.len();
-                            // Apply variable-height row adjustment
-                            if row_max_height > char_h {
-                                row_extra_y += row_max_height - char_h;
-                                for ri in (row as usize)..row_y.len() {
-                                    row_y[ri] = text_y + ri as f32 * char_h + row_extra_y;
-                                }
-                            }
-                            row_max_height = char_h;
-                            row_max_ascent = ascent;
                             if (row as usize) < row_continuation.len() {
                                 row_continuation[row as usize] = true;
                             }
                             wrap_has_break = false;
-                            if !params.wrap_prefix.is_empty() { need_prefix = 2; }
+                            need_prefix = 2;
                             if row >= max_rows {
                                 break;
                             }
                             continue;
                         } else {
+                            // Character wrap.  Unless the character starts
+                            // the row, it is laid out again on the next one,
+                            // after the wrap prefix, with the cursor if
+                            // point is on it
+                            let rewind = charpos - 1 > hit_row_charpos_start;
+                            if rewind {
+                                byte_idx -= ch_len;
+                                charpos -= 1;
+                                wrap_prev_ch = prev_ch;
+                                current_face_id = -1;
+                                if cursor_placed && cursor_row == row && cursor_charpos >= charpos {
+                                    Self::unplace_cursor(frame_glyphs, params.window_id as i32, row_glyph_start);
+                                    cursor_placed = false;
+                                }
+                            }
                             // Bidi reorder this completed row before char-wrap
-                            reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                            bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                             // Character wrap: fill remaining space
                             let remaining = avail_width - x_offset;
                             if remaining > 0.0 {
@@ -2646,36 +3816,25 @@ impl LayoutEngine {
                                 row_continued[row as usize] = true;
                             }
                             // Record hit-test row (char-wrap break)
-                            if (row as usize) < row_y.len() {
-                                hit_rows.push(HitRow {
-                                    y_start: row_y[row as usize],
-                                    y_end: row_y[row as usize] + row_max_height,
-                                    charpos_start: hit_row_charpos_start,
-                                    charpos_end: charpos,
-                                });
-                                hit_row_charpos_start = charpos;
-                            }
+                            Self::advance_row(
+                                &mut hit_rows, &mut hit_row_starts, &mut hit_row_charpos_start, charpos,
+                                &mut row, &mut row_y, &mut row_extra_y, &mut row_above,
+                                &mut row_max_height, &mut row_max_ascent, (0.0, 0.0), text_y, char_h, ascent,
+                            );
                             col = 0;
                             x_offset = 0.0;
-                            row += 1;
                             row_glyph_start = frame_glyphs.glyphs.len();
-                            // Apply variable-height row adjustment
-                            if row_max_height > char_h {
-                                row_extra_y += row_max_height - char_h;
-                                for ri in (row as usize)..row_y.len() {
-                                    row_y[ri] = text_y + ri as f32 * char_h + row_extra_y;
-                                }
-                            }
-                            row_max_height = char_h;
-                            row_max_ascent = ascent;
                             if (row as usize) < row_continuation.len() {
                                 row_continuation[row as usize] = true;
                             }
                             wrap_has_break = false;
-                            if !params.wrap_prefix.is_empty() { need_prefix = 2; }
+                            need_prefix = 2;
                             if row >= max_rows {
                                 break;
                             }
+                            if rewind {
+                                continue;
+                            }
                         }
                     }
 
@@ -2747,19 +3906,25 @@ impl LayoutEngine {
                         }
                     }
 
-                    // Record break AFTER whitespace characters
-                    if params.word_wrap && (ch == ' ' || ch == '\t') {
-                        // Flush ligature run at word-wrap boundary so truncate()
-                        // never cuts inside a composed glyph
-                        flush_run(&self.run_buf, frame_glyphs, ligatures);
-                        self.run_buf.clear();
+                    // Also break after each space of a run, so a run
+                    // reaching the edge wraps inside it, unless the text
+                    // after it cannot start a row
+                    if params.word_wrap && ch == ' ' {
+                        let next_ok = byte_idx >= bytes_read || {
+                            let (next, _) = decode_utf8(&text[byte_idx..]);
+                            next == ' ' || break_between(ch, next)
+                        };
+                        if next_ok {
+                            flush_run(&self.run_buf, frame_glyphs, ligatures);
+                            self.run_buf.clear();
 
-                        wrap_break_col = col;
-                        wrap_break_x = x_offset;
-                        wrap_break_byte_idx = byte_idx;
-                        wrap_break_charpos = charpos;
-                        wrap_break_glyph_count = frame_glyphs.glyphs.len();
-                        wrap_has_break = true;
+                            wrap_break_col = col;
+                            wrap_break_x = x_offset;
+                            wrap_break_byte_idx = byte_idx;
+                            wrap_break_charpos = charpos;
+                            wrap_break_glyph_count = frame_glyphs.glyphs.len();
+                            wrap_has_break = true;
+                        }
                     }
                 }
             }
@@ -2839,7 +4004,7 @@ impl LayoutEngine {
 
                 // Apply overlay face for after-string if no per-char runs
                 if !after_has_runs && overlay_after_face.face_id != 0 {
-                    self.apply_face(&overlay_after_face, frame, frame_glyphs);
+                    self.apply_face(host, &overlay_after_face, frame, frame_glyphs);
                 }
 
                 let astr = &overlay_after_buf[..overlay_after_len as usize];
@@ -2879,7 +4044,7 @@ impl LayoutEngine {
                     let (ach, alen) = decode_utf8(&astr[ai..]);
                     ai += alen;
                     if ach == '\n' {
-                        reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                        bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                         col = 0;
                         x_offset = 0.0;
                         row += 1;
@@ -2893,7 +4058,7 @@ impl LayoutEngine {
                     let a_advance = achar_cols as f32 * char_w;
                     if x_offset + a_advance > avail_width {
                         if params.truncate_lines {
-                            reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                            bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                             while ai < astr.len() {
                                 let (sc, sl) = decode_utf8(&astr[ai..]);
                                 ai += sl;
@@ -2908,7 +4073,7 @@ impl LayoutEngine {
                             if row >= max_rows { break; }
                             continue;
                         }
-                        reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                        bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                         col = 0;
                         x_offset = 0.0;
                         row += 1;
@@ -2924,7 +4089,7 @@ impl LayoutEngine {
 
                 // Restore text face after overlay after-string
                 if (after_has_runs || overlay_after_face.face_id != 0) && current_face_id >= 0 {
-                    self.apply_face(&self.face_data, frame, frame_glyphs);
+                    self.apply_face(host, &self.face_data, frame, frame_glyphs);
                 }
             }
 
@@ -2935,6 +4100,18 @@ impl LayoutEngine {
         flush_run(&self.run_buf, frame_glyphs, ligatures);
         self.run_buf.clear();
 
+        // Where the end of the buffer is, before any overlay strings there
+        let hit_end = (charpos >= params.buffer_size
+            && row < max_rows
+            && row_y.get(row as usize).is_some_and(|&y| y < text_y_limit))
+            .then(|| HitEnd {
+                charpos,
+                x: x_offset,
+                y_start: row_y[row as usize] - row_above,
+                y_end: row_y[row as usize] + row_max_height,
+                row: row as usize,
+            });
+
         // Place cursor before end-of-buffer overlay strings.
         // When point is at end-of-buffer and overlays have after-strings there
         // (e.g., fido-vertical-mode completions), the cursor must be placed
@@ -3011,7 +4188,7 @@ impl LayoutEngine {
             let mut eob_right_fringe_bg: u32 = 0;
             let mut eob_before_naligns: i32 = 0;
             let mut eob_after_naligns: i32 = 0;
-            neomacs_layout_overlay_strings_at(
+            host.overlay_strings_at(
                 buffer, window, charpos,
                 overlay_before_buf.as_mut_ptr(),
                 overlay_before_buf.len() as i32,
@@ -3058,7 +4235,7 @@ impl LayoutEngine {
                 let mut eob_bcurrent_align = 0usize;
 
                 if !eob_before_has_runs && eob_before_face.face_id != 0 {
-                    self.apply_face(&eob_before_face, frame, frame_glyphs);
+                    self.apply_face(host, &eob_before_face, frame, frame_glyphs);
                 }
                 let bstr = &overlay_before_buf[..eob_before_len as usize];
                 let mut bi = 0usize;
@@ -3096,7 +4273,7 @@ impl LayoutEngine {
                     let (bch, blen) = decode_utf8(&bstr[bi..]);
                     bi += blen;
                     if bch == '\n' {
-                        reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                        bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                         col = 0;
                         x_offset = 0.0;
                         row += 1;
@@ -3109,7 +4286,7 @@ impl LayoutEngine {
                     let b_advance = bchar_cols as f32 * char_w;
                     if x_offset + b_advance > avail_width {
                         if params.truncate_lines {
-                            reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                            bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                             while bi < bstr.len() {
                                 let (sc, sl) = decode_utf8(&bstr[bi..]);
                                 bi += sl;
@@ -3124,7 +4301,7 @@ impl LayoutEngine {
                             if row >= max_rows { break; }
                             continue;
                         }
-                        reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                        bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                         col = 0;
                         x_offset = 0.0;
                         row += 1;
@@ -3138,7 +4315,7 @@ impl LayoutEngine {
                     x_offset += b_advance;
                 }
                 if (eob_before_has_runs || eob_before_face.face_id != 0) && current_face_id >= 0 {
-                    self.apply_face(&self.face_data, frame, frame_glyphs);
+                    self.apply_face(host, &self.face_data, frame, frame_glyphs);
                 }
             }
 
@@ -3158,7 +4335,7 @@ impl LayoutEngine {
                 let mut eob_acurrent_align = 0usize;
 
                 if !eob_after_has_runs && overlay_after_face.face_id != 0 {
-                    self.apply_face(&overlay_after_face, frame, frame_glyphs);
+                    self.apply_face(host, &overlay_after_face, frame, frame_glyphs);
                 }
                 let astr = &overlay_after_buf[..overlay_after_len as usize];
                 let mut ai = 0usize;
@@ -3196,7 +4373,7 @@ impl LayoutEngine {
                     let (ach, alen) = decode_utf8(&astr[ai..]);
                     ai += alen;
                     if ach == '\n' {
-                        reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                        bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                         col = 0;
                         x_offset = 0.0;
                         row += 1;
@@ -3209,7 +4386,7 @@ impl LayoutEngine {
                     let a_advance = achar_cols as f32 * char_w;
                     if x_offset + a_advance > avail_width {
                         if params.truncate_lines {
-                            reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                            bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                             while ai < astr.len() {
                                 let (sc, sl) = decode_utf8(&astr[ai..]);
                                 ai += sl;
@@ -3224,7 +4401,7 @@ impl LayoutEngine {
                             if row >= max_rows { break; }
                             continue;
                         }
-                        reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+                        bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
                         col = 0;
                         x_offset = 0.0;
                         row += 1;
@@ -3238,7 +4415,7 @@ impl LayoutEngine {
                     x_offset += a_advance;
                 }
                 if (eob_after_has_runs || overlay_after_face.face_id != 0) && current_face_id >= 0 {
-                    self.apply_face(&self.face_data, frame, frame_glyphs);
+                    self.apply_face(host, &self.face_data, frame, frame_glyphs);
                 }
 
             }
@@ -3247,7 +4424,7 @@ impl LayoutEngine {
         // Flush any remaining ligature run and bidi reorder the last row
         flush_run(&self.run_buf, frame_glyphs, ligatures);
         self.run_buf.clear();
-        reorder_row_bidi(frame_glyphs, row_glyph_start, frame_glyphs.glyphs.len(), content_x);
+        bidi.reorder_row(frame_glyphs, row_glyph_start, &mut hit_row_starts);
 
         // Fill rest of last line with :extend background if applicable
         // (handles end-of-buffer without trailing newline)
@@ -3336,100 +4513,196 @@ impl LayoutEngine {
 
         // Render fringe indicators
         let actual_rows = (row + 1).min(max_rows);
-        if right_fringe_width > 0.0 || left_fringe_width > 0.0 {
-            // Use default face for fringe rendering
-            frame_glyphs.set_face(
-                0, default_fg, Some(default_bg),
-                400, false, 0, None, 0, None, 0, None,
+
+        if lnum_enabled {
+            // The empty row after a final newline is a line of its own
+            if need_line_number && row < max_rows {
+                row_lines[row as usize] = Some(current_line);
+            }
+            let labels = line_number_labels(
+                &lnum_config,
+                &row_lines[..actual_rows.max(0) as usize],
+                point_line,
+                cursor_placed.then_some(cursor_row as usize),
+            );
+            self.render_line_numbers(
+                host, window, frame, frame_glyphs, &lnum_config, &labels,
+                text_x, lnum_cols, char_w, char_h, ascent, &row_y,
             );
+            if current_face_id >= 0 {
+                self.apply_face(host, &self.face_data, frame, frame_glyphs);
+            }
+        }
 
-            for r in 0..actual_rows as usize {
+        // Overlay arrows (debugger stop line, next-error): the row showing
+        // the arrow's position gets the arrow bitmap in its left fringe, or
+        // the arrow string over its first columns without a left fringe
+        let mut row_arrow: Vec<i32> = vec![0; actual_rows.max(0) as usize];
+        {
+            let mut arrows = vec![OverlayArrowFFI::default(); 8];
+            let n = host.overlay_arrows(
+                window, buffer, arrows.as_mut_ptr(), arrows.len() as c_int,
+            );
+            let current = (row as usize, hit_row_charpos_start, charpos);
+            for arrow in &arrows[..n.clamp(0, arrows.len() as c_int) as usize] {
+                let Some(r) = row_showing(arrow.charpos, &hit_rows, &row_y, current)
+                    .filter(|&r| r < row_arrow.len())
+                else {
+                    continue;
+                };
+                if arrow.bitmap > 0 {
+                    row_arrow[r] = arrow.bitmap;
+                    continue;
+                }
+                let len = arrow.text_len.clamp(0, arrow.text.len() as c_int) as usize;
+                let arrow_text = String::from_utf8_lossy(&arrow.text[..len]);
                 let gy = row_y[r];
+                frame_glyphs.set_face(
+                    0, default_fg, Some(default_bg),
+                    400, false, 0, None, 0, None, 0, None,
+                );
+                for (i, ch) in arrow_text.chars().enumerate() {
+                    let gx = content_x + i as f32 * char_w;
+                    if gx + char_w > content_x + avail_width {
+                        break;
+                    }
+                    // Replace the character drawn in that column, if any
+                    let existing = frame_glyphs.glyphs[text_glyph_start..].iter_mut().find(|g| {
+                        matches!(g, FrameGlyph::Char { x, y, is_overlay: false, .. }
+                            if (*x - gx).abs() < 0.5 && (*y - gy).abs() < 0.5)
+                    });
+                    if let Some(FrameGlyph::Char { char: c, composed, fg, .. }) = existing {
+                        *c = ch;
+                        *composed = None;
+                        *fg = default_fg;
+                    } else {
+                        frame_glyphs.add_char(ch, gx, gy, char_w, char_h, ascent, false);
+                    }
+                }
+            }
+        }
 
-                // Right fringe: continuation indicator for wrapped lines
-                if right_fringe_width > 0.0 && row_continued.get(r).copied().unwrap_or(false) {
-                    // Bitmap 7: left-curly-arrow (continuation)
-                    render_fringe_bitmap(
-                        7, right_fringe_x, gy,
-                        right_fringe_width, char_h, default_fg,
-                        frame_glyphs,
-                    );
+        if right_fringe_width > 0.0 || left_fringe_width > 0.0 {
+            let mut ind = FringeIndicatorsFFI::default();
+            if host.fringe_indicators(window, &mut ind) == 0 {
+                ind = FringeIndicatorsFFI::standard(params.default_fg, params.default_bg);
+                ind.empty_line_side = params.indicate_empty_lines;
+            }
+            let fringe_fg = Color::from_pixel(ind.fg);
+            if ind.bg != params.default_bg && text_height > 0.0 {
+                let fringe_bg = Color::from_pixel(ind.bg);
+                if left_fringe_width > 0.0 {
+                    frame_glyphs.add_stretch(left_fringe_x, text_y, left_fringe_width, text_height, fringe_bg, 0, false);
+                }
+                if right_fringe_width > 0.0 {
+                    frame_glyphs.add_stretch(right_fringe_x, text_y, right_fringe_width, text_height, fringe_bg, 0, false);
+                }
+            }
+            // Bitmaps given a face by `set-fringe-bitmap-face' use its
+            // foreground, others the `fringe' face's
+            let bitmap_fg = |bitmap: i32| {
+                let mut fg = 0u32;
+                if host.fringe_bitmap_fg(window, bitmap, &mut fg) > 0 {
+                    Color::from_pixel(fg)
+                } else {
+                    fringe_fg
+                }
+            };
+            let user_fg = |(bitmap, fg, _bg): (i32, u32, u32)| {
+                if fg != 0 { Color::from_pixel(fg) } else { bitmap_fg(bitmap) }
+            };
+
+            // `indicate-buffer-boundaries': angles on the rows showing the
+            // buffer's start and end, arrows when there is more text
+            let last_row = actual_rows.max(0) as usize;
+            let at_bob = window_start <= params.buffer_begv;
+            let at_eob = window_end_charpos >= params.buffer_size;
+            let boundary = |r: usize, side: c_int| {
+                let bob = r == 0 && at_bob && ind.top_side == side;
+                let eob = r + 1 == last_row && at_eob && ind.bottom_side == side;
+                match (bob, eob) {
+                    (true, true) => Some(ind.top_bottom),
+                    (true, false) => Some(ind.top),
+                    (false, true) => Some(ind.bottom),
+                    (false, false) => None,
+                }
+            };
+            let scroll_arrow = |r: usize, side: c_int| {
+                if r == 0 && !at_bob && ind.up_side == side {
+                    Some(ind.up)
+                } else if r + 1 == last_row && !at_eob && ind.down_side == side {
+                    Some(ind.down)
+                } else {
+                    None
                 }
+            };
 
-                // Right fringe: truncation indicator
-                if right_fringe_width > 0.0 && row_truncated.get(r).copied().unwrap_or(false) {
-                    // Bitmap 4: right-arrow (truncation)
+            for r in 0..last_row {
+                let gy = row_y[r];
+                let flag = |rows: &[bool]| rows.get(r).copied().unwrap_or(false);
+                let user = |rows: &[(i32, u32, u32)]| rows.get(r).copied().filter(|u| u.0 > 0);
+
+                // Each fringe shows the first indicator that applies, in
+                // the order of Emacs's update_window_fringes
+                let user_left = user(&row_left_fringe);
+                let left = user_left.map(|u| u.0)
+                    .or(flag(&row_left_truncated).then_some(ind.truncation_left))
+                    .or(boundary(r, 1))
+                    .or(flag(&row_continuation).then_some(ind.continuation_left))
+                    .or(scroll_arrow(r, 1))
+                    .unwrap_or(0);
+                let user_right = user(&row_right_fringe);
+                let right = user_right.map(|u| u.0)
+                    .or(flag(&row_truncated).then_some(ind.truncation_right))
+                    .or(boundary(r, 2))
+                    .or(flag(&row_continued).then_some(ind.continuation_right))
+                    .or(scroll_arrow(r, 2))
+                    .unwrap_or(0);
+
+                // An overlay arrow takes the place of the left indicator
+                let arrow = row_arrow.get(r).copied().unwrap_or(0);
+                if left_fringe_width > 0.0 {
+                    let (bitmap, fg) = if arrow > 0 {
+                        (arrow, bitmap_fg(arrow))
+                    } else {
+                        (left, user_left.map_or_else(|| bitmap_fg(left), user_fg))
+                    };
+                    if bitmap > 0 {
+                        render_fringe_bitmap(
+                            host,
+                            bitmap, left_fringe_x, gy,
+                            left_fringe_width, char_h, fg,
+                            frame_glyphs,
+                        );
+                    }
+                }
+                if right_fringe_width > 0.0 && right > 0 {
+                    let fg = user_right.map_or_else(|| bitmap_fg(right), user_fg);
                     render_fringe_bitmap(
-                        4, right_fringe_x, gy,
-                        right_fringe_width, char_h, default_fg,
+                        host,
+                        right, right_fringe_x, gy,
+                        right_fringe_width, char_h, fg,
                         frame_glyphs,
                     );
                 }
+            }
 
-                // Left fringe: continuation indicator for continued lines
-                if left_fringe_width > 0.0 && row_continuation.get(r).copied().unwrap_or(false) {
-                    // Bitmap 8: right-curly-arrow (continuation from prev)
+            // Lines after the end of the buffer (`indicate-empty-lines')
+            let (empty_x, empty_w) = match ind.empty_line_side {
+                1 => (left_fringe_x, left_fringe_width),
+                2 => (right_fringe_x, right_fringe_width),
+                _ => (0.0, 0.0),
+            };
+            if ind.empty_line > 0 && empty_w > 0.0 {
+                let fg = bitmap_fg(ind.empty_line);
+                for &gy in &row_y[last_row.min(max_rows as usize)..max_rows as usize] {
                     render_fringe_bitmap(
-                        8, left_fringe_x, gy,
-                        left_fringe_width, char_h, default_fg,
+                        host,
+                        ind.empty_line, empty_x, gy,
+                        empty_w, char_h, fg,
                         frame_glyphs,
                     );
                 }
-
-                // User-specified left-fringe display property bitmap
-                if left_fringe_width > 0.0 {
-                    if let Some(&(bid, fg, bg)) = row_left_fringe.get(r) {
-                        if bid > 0 {
-                            let ffg = if fg != 0 { Color::from_pixel(fg) } else { default_fg };
-                            render_fringe_bitmap(
-                                bid, left_fringe_x, gy,
-                                left_fringe_width, char_h, ffg,
-                                frame_glyphs,
-                            );
-                        }
-                    }
-                }
-
-                // User-specified right-fringe display property bitmap
-                if right_fringe_width > 0.0 {
-                    if let Some(&(bid, fg, bg)) = row_right_fringe.get(r) {
-                        if bid > 0 {
-                            let ffg = if fg != 0 { Color::from_pixel(fg) } else { default_fg };
-                            render_fringe_bitmap(
-                                bid, right_fringe_x, gy,
-                                right_fringe_width, char_h, ffg,
-                                frame_glyphs,
-                            );
-                        }
-                    }
-                }
-            }
-
-            // EOB empty line indicators (bitmap 24 = empty_line)
-            if params.indicate_empty_lines > 0 {
-                let eob_start = actual_rows;
-                for r in eob_start as usize..max_rows as usize {
-                    let gy = row_y[r];
-                    if params.indicate_empty_lines == 2 {
-                        // Right fringe
-                        if right_fringe_width > 0.0 {
-                            render_fringe_bitmap(
-                                24, right_fringe_x, gy,
-                                right_fringe_width, char_h, default_fg,
-                                frame_glyphs,
-                            );
-                        }
-                    } else {
-                        // Left fringe (default)
-                        if left_fringe_width > 0.0 {
-                            render_fringe_bitmap(
-                                24, left_fringe_x, gy,
-                                left_fringe_width, char_h, default_fg,
-                                frame_glyphs,
-                            );
-                        }
-                    }
-                }
             }
         }
 
@@ -3457,6 +4730,7 @@ impl LayoutEngine {
         // Render tab-line if this window has one
         if params.tab_line_height > 0.0 {
             self.render_status_line(
+                host,
                 params.bounds.x,
                 params.bounds.y,
                 params.bounds.width,
@@ -3473,6 +4747,7 @@ impl LayoutEngine {
         // Render header-line if this window has one
         if params.header_line_height > 0.0 {
             self.render_status_line(
+                host,
                 params.bounds.x,
                 params.bounds.y + params.tab_line_height,
                 params.bounds.width,
@@ -3489,6 +4764,7 @@ impl LayoutEngine {
         // Render mode-line if this window has one
         if params.mode_line_height > 0.0 {
             self.render_status_line(
+                host,
                 params.bounds.x,
                 params.bounds.y + params.bounds.height - params.mode_line_height,
                 params.bounds.width,
@@ -3503,34 +4779,175 @@ impl LayoutEngine {
         }
 
         // Record last hit-test row (end of visible text)
+        let mut open_row = None;
         if row < max_rows && (row as usize) < row_y.len() && charpos > hit_row_charpos_start {
+            open_row = Some(hit_rows.len());
             hit_rows.push(HitRow {
-                y_start: row_y[row as usize],
+                y_start: row_y[row as usize] - row_above,
                 y_end: row_y[row as usize] + row_max_height,
                 charpos_start: hit_row_charpos_start,
                 charpos_end: charpos,
+                starts: std::mem::take(&mut hit_row_starts),
             });
         }
 
+        // Cursor in virtual space past the end of point's line: move the
+        // cursor glyph right without touching the buffer
+        if let Some(extra) = self.virtual_cursors.columns(params.window_id, params.point) {
+            let new_col = shifted_column(cursor_col, extra, cols);
+            if cursor_placed && new_col > cursor_col {
+                let dx = (new_col - cursor_col) as f32 * char_w;
+                let old_x = content_x + cursor_x;
+                for glyph in frame_glyphs.glyphs[text_glyph_start..].iter_mut().rev() {
+                    if let FrameGlyph::Cursor { window_id, x, .. } = glyph {
+                        if *window_id == params.window_id as i32 {
+                            *x += dx;
+                            break;
+                        }
+                    }
+                }
+                if let Some(inv) = frame_glyphs.cursor_inverse.as_mut() {
+                    if inv.x == old_x {
+                        inv.x += dx;
+                    }
+                }
+                cursor_col = new_col;
+                cursor_x += dx;
+            }
+        }
+
+        // Bidi reordering moves the cursor with its character
+        if bidi.active() {
+            let cursor = frame_glyphs.glyphs[text_glyph_start..].iter().find_map(|g| match g {
+                FrameGlyph::Cursor { window_id, x, .. } if *window_id == params.window_id as i32 => Some(*x),
+                _ => None,
+            });
+            if let Some(x) = cursor {
+                cursor_x = x - content_x;
+            }
+        }
+
+        // Right-aligned, centered and justified paragraphs
+        cursor_x += Self::align_paragraphs(
+            host, buffer, window, params.window_id, content_x, avail_width, &hit_rows,
+            &row_continuation, text_glyph_start, &mut hit_cells, frame_glyphs,
+        );
+
+        // Rectangular region highlight and insertion cursors
+        if let Some(rect) = self.rectangles.get(params.window_id, params.buffer_id) {
+            let cursor_y = (cursor_row < max_rows).then(|| row_y[cursor_row as usize]);
+            Self::render_rectangle(
+                rect, params.window_id, content_x, char_w, cols, hscroll, &hit_rows,
+                &row_continuation, text_glyph_start, cursor_y, frame_glyphs,
+            );
+        }
+
+        // Active region and secondary selection
+        Self::render_selections(
+            host, buffer, window, params.window_id, content_x, text_x + text_width - content_x,
+            char_w, &hit_rows, &row_continued, open_row, text_glyph_start, frame_glyphs,
+        );
+
+        // Shell command blocks: tinted rows, status bar and prompt label
+        if self.command_blocks.has_buffer(params.buffer_id) {
+            self.render_command_blocks(
+                params, text_x, text_width, char_w, char_h, ascent,
+                default_bg, &hit_rows, text_glyph_start, frame_glyphs,
+            );
+        }
+
+        // Fading highlights over recently yanked, killed or indented text
+        if self.region_pulses.has_buffer(params.buffer_id) {
+            self.render_region_pulses(
+                params, content_x, char_w, &hit_rows, text_glyph_start, frame_glyphs,
+            );
+        }
+
+        // Underlined link text
+        if self.links.has_buffer(params.buffer_id) {
+            self.render_links(
+                params, content_x, char_w, &hit_rows, text_glyph_start, frame_glyphs,
+            );
+        }
+
+        // Margin notes anchored to visible text
+        if self.annotations.has_buffer(params.buffer_id) {
+            self.render_annotations(
+                params, text_x, text_y, text_width, text_y_limit,
+                char_w, char_h, ascent, &hit_rows, frame_glyphs,
+            );
+        }
+
+        // Bookmark/diagnostic/search ticks along the scroll bar track.
+        // The track spans the text rows, like `window_box' in C, so that
+        // scroll bar click offsets map back to the same positions.
+        if wp.scroll_bar_width > 0.0 && self.scroll_bar_marks.has_buffer(params.buffer_id) {
+            let track_height = params.text_bounds.height
+                - params.header_line_height
+                - params.tab_line_height
+                - params.mode_line_height;
+            for tick in self.scroll_bar_marks.ticks(
+                params.buffer_id, wp.buffer_begv, wp.buffer_zv, track_height,
+            ) {
+                frame_glyphs.add_stretch(
+                    wp.scroll_bar_x + 1.0, text_y + tick.y,
+                    (wp.scroll_bar_width - 2.0).max(1.0), TICK_HEIGHT.min(track_height),
+                    Color::from_pixel(tick.color), 0, false,
+                );
+            }
+        }
+
+        // A chat buffer scrolled up keeps its input in view at the bottom
+        let cursor_y = (cursor_placed && cursor_row < max_rows).then(|| row_y[cursor_row as usize]);
+        let mut end_row = row.min(max_rows - 1);
+        if let Some((end, last_row)) = self.render_chat_input(
+            params, snapshot, content_x, cols, char_w, char_h, ascent,
+            text_y_limit, cursor_y, &mut hit_rows, &mut hit_cells,
+            text_glyph_start, frame_glyphs,
+        ) {
+            window_end_charpos = end;
+            end_row = last_row;
+        }
+
+        // Row geometry for consumers of per-row heights
+        for r in &hit_rows {
+            frame_glyphs.add_text_row(params.window_id, r.y_start, r.y_end - r.y_start);
+        }
+        self.rows_fit.insert(params.window_id, rows_that_fit(&hit_rows, text_height, max_rows));
+
         // Store hit-test data for this window
         self.hit_data.push(WindowHitData {
             window_id: params.window_id,
             content_x,
             char_w,
             rows: hit_rows,
+            cells: hit_cells,
+            vertical: None,
+            end: hit_end,
         });
 
+        // Remember point's row to keep it there if the text reflows
+        if cursor_placed && cursor_row < max_rows && !params.is_minibuffer {
+            self.scroll_anchors.record(params.window_id, ScrollAnchor {
+                window_start,
+                row: cursor_row,
+                ..anchor
+            });
+        } else {
+            self.scroll_anchors.forget(params.window_id);
+        }
+
         // Write layout results back to Emacs
-        neomacs_layout_set_window_end(
+        host.set_window_end(
             wp.window_ptr,
             window_end_charpos,
-            row.min(max_rows - 1),
+            end_row,
         );
 
         // Set cursor position for Emacs (needed for recenter, scroll, etc.)
         // Ensure cursor_row is valid and within text area
         if cursor_row < max_rows && row_y[cursor_row as usize] < text_y_limit {
-            neomacs_layout_set_cursor(
+            host.set_cursor(
                 wp.window_ptr,
                 (content_x + cursor_x) as i32,
                 (row_y[cursor_row as usize]) as i32,
@@ -3539,7 +4956,7 @@ impl LayoutEngine {
             );
         } else {
             // Set cursor at row 0 as fallback — scroll will fix next frame
-            neomacs_layout_set_cursor(
+            host.set_cursor(
                 wp.window_ptr,
                 content_x as i32,
                 text_y as i32,
@@ -3548,6 +4965,768 @@ impl LayoutEngine {
             );
         }
     }
+
+    /// Drop the glyphs from FROM on that overlap AREA.
+    fn hide_glyphs_in(frame_glyphs: &mut FrameGlyphBuffer, from: usize, area: Rect) {
+        let overlaps = |x: f32, y: f32, w: f32, h: f32| {
+            x < area.x + area.width && x + w > area.x && y < area.y + area.height && y + h > area.y
+        };
+        let mut i = from;
+        frame_glyphs.glyphs.retain(|g| {
+            i += 1;
+            if i <= from {
+                return true;
+            }
+            match g {
+                FrameGlyph::Char { x, y, width, height, is_overlay: false, .. }
+                | FrameGlyph::Stretch { x, y, width, height, is_overlay: false, .. }
+                | FrameGlyph::Image { x, y, width, height, .. }
+                | FrameGlyph::Video { x, y, width, height, .. }
+                | FrameGlyph::WebKit { x, y, width, height, .. }
+                | FrameGlyph::Cursor { x, y, width, height, .. }
+                | FrameGlyph::Border { x, y, width, height, .. } => !overlaps(*x, *y, *width, *height),
+                _ => true,
+            }
+        });
+    }
+
+    /// Pin the input area of a chat buffer to the bottom of the text
+    /// area when the window's rows stop before it, over the rows there,
+    /// with the pill counting new messages above it.  Neither covers
+    /// point's row (at CURSOR_Y).  HIT_ROWS lose the covered rows and
+    /// gain the input's; clicks on the pill land at the input.  Returns
+    /// the end of the text left in view and the last row showing it.
+    #[allow(clippy::too_many_arguments)]
+    fn render_chat_input(
+        &self,
+        params: &WindowParams,
+        snapshot: &mut BufferSnapshot,
+        content_x: f32,
+        cols: i32,
+        char_w: f32,
+        char_h: f32,
+        ascent: f32,
+        text_y_limit: f32,
+        cursor_y: Option<f32>,
+        hit_rows: &mut Vec<HitRow>,
+        hit_cells: &mut Vec<HitCell>,
+        glyph_start: usize,
+        frame_glyphs: &mut FrameGlyphBuffer,
+    ) -> Option<(i64, i32)> {
+        let view = self.chat_views.get(params.buffer_id)?;
+        let (first, last) = (hit_rows.first()?, hit_rows.last()?);
+        if last.charpos_end > view.input_start
+            || last.charpos_start >= view.input_start
+            || view.input_start > params.buffer_size
+        {
+            return None;
+        }
+
+        let from = view.input_start.max(params.buffer_size - INPUT_SCAN_LIMIT);
+        let rows = input_rows(
+            snapshot.load_text(from, params.buffer_size),
+            from, cols, params.tab_width, MAX_INPUT_ROWS,
+        );
+        let panel_h = rows.len() as f32 * char_h;
+        let panel_y = text_y_limit - panel_h;
+        let covers_point = |y0: f32, y1: f32| cursor_y.is_some_and(|y| y < y1 && y + char_h > y0);
+        if panel_y < first.y_end || covers_point(panel_y, text_y_limit) {
+            return None;
+        }
+        let first_y = first.y_start;
+
+        let text_x = params.text_bounds.x;
+        let text_w = params.text_bounds.width;
+        let right_edge = text_x + text_w + params.right_fringe_width;
+        Self::hide_glyphs_in(
+            frame_glyphs, glyph_start,
+            Rect::new(params.bounds.x, panel_y, right_edge - params.bounds.x, panel_h),
+        );
+        let input_bg = Color::from_pixel(view.colors.input_bg);
+        frame_glyphs.add_stretch(text_x, panel_y, text_w, panel_h, input_bg, 0, false);
+        frame_glyphs.add_border(text_x, panel_y, text_w, 1.0, Color::from_pixel(view.colors.separator));
+        frame_glyphs.set_face(
+            0, Color::from_pixel(params.default_fg), Some(input_bg),
+            400, false, 0, None, 0, None, 0, None,
+        );
+
+        hit_rows.retain(|r| r.y_end <= panel_y + 0.5);
+        let end = (hit_rows.last()?.charpos_end, hit_rows.len() as i32 - 1);
+        for (i, input_row) in rows.iter().enumerate() {
+            let y = panel_y + i as f32 * char_h;
+            for cell in &input_row.cells {
+                frame_glyphs.add_char(
+                    cell.ch, content_x + cell.col as f32 * char_w, y,
+                    cell.cols as f32 * char_w, char_h, ascent, false,
+                );
+            }
+            hit_rows.push(HitRow {
+                y_start: y,
+                y_end: y + char_h,
+                charpos_start: input_row.start,
+                charpos_end: input_row.end,
+                starts: input_row.cells.iter().map(|c| (c.pos, c.col as f32 * char_w)).collect(),
+            });
+        }
+
+        if let Some(label) = view.pill_label() {
+            let label_cols: i32 = label.chars().map(|ch| if is_wide_char(ch) { 2 } else { 1 }).sum();
+            let pill_w = (label_cols + 2) as f32 * char_w;
+            let pill_x = text_x + ((text_w - pill_w) / 2.0).max(0.0);
+            let pill_y = panel_y - char_h;
+            if pill_w <= text_w && pill_y >= first_y && !covers_point(pill_y, pill_y + char_h) {
+                Self::hide_glyphs_in(frame_glyphs, glyph_start, Rect::new(pill_x, pill_y, pill_w, char_h));
+                let pill_bg = Color::from_pixel(view.colors.pill_bg);
+                frame_glyphs.add_stretch(pill_x, pill_y, pill_w, char_h, pill_bg, 0, false);
+                frame_glyphs.set_face(
+                    0, Color::from_pixel(view.colors.pill_fg), Some(pill_bg),
+                    700, false, 0, None, 0, None, 0, None,
+                );
+                let mut x = pill_x + char_w;
+                for ch in label.chars() {
+                    let adv = if is_wide_char(ch) { 2.0 * char_w } else { char_w };
+                    frame_glyphs.add_char(ch, x, pill_y, adv, char_h, ascent, false);
+                    x += adv;
+                }
+                hit_cells.push(HitCell {
+                    y_start: pill_y,
+                    y_end: pill_y + char_h,
+                    x_start: pill_x,
+                    x_end: pill_x + pill_w,
+                    charpos_start: view.input_start,
+                    char_ends: vec![pill_x + pill_w],
+                });
+            }
+        }
+        Some(end)
+    }
+
+    /// Highlight RECT on the rows of HIT_ROWS that belong to its lines.
+    /// Each row gets a stretch over the rectangle's columns, which also
+    /// covers the virtual space past the end of short lines, and the
+    /// characters inside the span take the highlight background.  Rows
+    /// other than point's (at CURSOR_Y) get a thin insertion cursor.
+    /// Draw the line number column at X, COLS columns wide, for rows
+    /// showing LABELS (see `line_number_labels`): each number
+    /// right-aligned before a blank column, in the line number faces.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn render_line_numbers(
+        &self,
+        host: &dyn LayoutHost,
+        window: EmacsWindow,
+        frame: EmacsFrame,
+        frame_glyphs: &mut FrameGlyphBuffer,
+        config: &LineNumberConfigFFI,
+        labels: &[(Option<i64>, i64, bool)],
+        x: f32,
+        cols: i32,
+        char_w: f32,
+        char_h: f32,
+        ascent: f32,
+        row_y: &[f32],
+    ) {
+        let mut face = FaceDataFFI::default();
+        for (r, &(number, line, current)) in labels.iter().enumerate() {
+            let Some(&gy) = row_y.get(r) else { break };
+            // Fill the whole row, tall rows and line spacing included
+            let row_h = row_y.get(r + 1).map_or(char_h, |&next| (next - gy).max(char_h));
+            host.line_number_face(
+                window,
+                current as c_int,
+                line,
+                config.major_tick,
+                config.minor_tick,
+                &mut face,
+            );
+            self.apply_face(host, &face, frame, frame_glyphs);
+            frame_glyphs.add_stretch(
+                x, gy,
+                cols as f32 * char_w, row_h,
+                Color::from_pixel(face.bg), face.face_id, false,
+            );
+            let Some(number) = number else { continue };
+            let digits = number.to_string();
+            let pad = (cols - 1 - digits.len() as i32).max(0);
+            for (i, ch) in digits.chars().enumerate() {
+                let dx = x + (pad + i as i32) as f32 * char_w;
+                frame_glyphs.add_char(ch, dx, gy, char_w, char_h, ascent, false);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_rectangle(
+        rect: &RectangleRegion,
+        window_id: i64,
+        content_x: f32,
+        char_w: f32,
+        cols: i32,
+        hscroll: i32,
+        hit_rows: &[HitRow],
+        row_continuation: &[bool],
+        glyph_start: usize,
+        cursor_y: Option<f32>,
+        frame_glyphs: &mut FrameGlyphBuffer,
+    ) {
+        let bg = Color::from_pixel(rect.bg);
+        let cursor_color = Color::from_pixel(rect.cursor_color);
+        let bar_w = (char_w / 8.0).max(2.0);
+
+        // (y_start, y_end, x_start, x_end) of the highlight on each row
+        let mut spans: Vec<(f32, f32, f32, f32)> = Vec::new();
+        let mut line_row = 0usize;
+        let mut line_start = hit_rows.first().map_or(0, |r| r.charpos_start);
+        for (r, hit_row) in hit_rows.iter().enumerate() {
+            if r > 0 && !row_continuation.get(r).copied().unwrap_or(false) {
+                line_row = r;
+                line_start = hit_row.charpos_start;
+            }
+            if !rect.contains_line(line_start) {
+                continue;
+            }
+            let first_col = hscroll + (r - line_row) as i32 * cols;
+            let row_h = hit_row.y_end - hit_row.y_start;
+            if let Some((c0, c1)) = rect.row_span(first_col, cols) {
+                let x0 = content_x + c0 as f32 * char_w;
+                let x1 = content_x + c1 as f32 * char_w;
+                frame_glyphs.add_stretch(x0, hit_row.y_start, x1 - x0, row_h, bg, 0, false);
+                frame_glyphs.add_selection_run(SelectionRun {
+                    window_id,
+                    kind: SelectionKind::Rectangle,
+                    x: x0,
+                    y: hit_row.y_start,
+                    width: x1 - x0,
+                    height: row_h,
+                    bg,
+                });
+                spans.push((hit_row.y_start, hit_row.y_end, x0, x1));
+            }
+            if cursor_y != Some(hit_row.y_start) {
+                if let Some(c) = rect.cursor_offset(first_col, cols) {
+                    let x = content_x + c as f32 * char_w;
+                    frame_glyphs.add_border(x, hit_row.y_start, bar_w, row_h, cursor_color);
+                }
+            }
+        }
+        if spans.is_empty() {
+            return;
+        }
+
+        // Characters whose center lies inside a row's span take the highlight
+        for glyph in &mut frame_glyphs.glyphs[glyph_start..] {
+            if let FrameGlyph::Char { x, y, width, bg: char_bg, is_overlay: false, .. } = glyph {
+                let cx = *x + *width / 2.0;
+                let i = spans.partition_point(|s| s.1 <= *y);
+                if let Some(&(y0, _, x0, x1)) = spans.get(i) {
+                    if *y >= y0 && cx >= x0 && cx < x1 {
+                        *char_bg = Some(bg);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Highlight the spans `region_spans' reports for the window over the
+    /// rows in HIT_ROWS: each run of selected characters gets the span's
+    /// background, as do the characters in it, and a selected newline
+    /// continues the run to RIGHT_EDGE (relative to CONTENT_X) if the
+    /// face extends.  Rows in ROW_CONTINUED wrap onto the next row and
+    /// OPEN_ROW ends the visible text; neither ends its line.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn render_selections(
+        host: &dyn LayoutHost,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        window_id: i64,
+        content_x: f32,
+        right_edge: f32,
+        char_w: f32,
+        hit_rows: &[HitRow],
+        row_continued: &[bool],
+        open_row: Option<usize>,
+        glyph_start: usize,
+        frame_glyphs: &mut FrameGlyphBuffer,
+    ) {
+        if hit_rows.is_empty() {
+            return;
+        }
+        let mut spans = vec![RegionSpanFFI::default(); 4];
+        let n = host.region_spans(window, buffer, spans.as_mut_ptr(), spans.len() as c_int);
+        spans.truncate(n.clamp(0, spans.len() as c_int) as usize);
+        if spans.is_empty() {
+            return;
+        }
+
+        // Right edge of the text on each row
+        let mut text_ends: Vec<f32> = vec![0.0; hit_rows.len()];
+        for glyph in &frame_glyphs.glyphs[glyph_start..] {
+            if let FrameGlyph::Char { x, y, width, is_overlay: false, .. } = glyph {
+                let r = hit_rows.partition_point(|row| row.y_end <= *y);
+                if let Some(end) = text_ends.get_mut(r) {
+                    *end = end.max(*x + *width - content_x);
+                }
+            }
+        }
+
+        // (y_start, y_end, x0, x1, bg, fg) of each run, later spans on top
+        let mut runs: Vec<(f32, f32, f32, f32, Color, Option<Color>)> = Vec::new();
+        for span in &spans {
+            let bg = Color::from_pixel(span.bg);
+            let fg = (span.has_fg != 0).then(|| Color::from_pixel(span.fg));
+            let kind = if span.kind == 1 { SelectionKind::Secondary } else { SelectionKind::Region };
+            for (r, hit_row) in hit_rows.iter().enumerate() {
+                let row = SelectionRow {
+                    start: hit_row.charpos_start,
+                    end: hit_row.charpos_end,
+                    starts: &hit_row.starts,
+                    text_end: text_ends[r],
+                    ends_line: !row_continued.get(r).copied().unwrap_or(false) && open_row != Some(r),
+                };
+                let row_h = hit_row.y_end - hit_row.y_start;
+                for (x0, x1) in row_runs(span.start, span.end, &row, char_w, span.extend != 0, right_edge) {
+                    let x = content_x + x0;
+                    frame_glyphs.add_stretch(x, hit_row.y_start, x1 - x0, row_h, bg, 0, false);
+                    frame_glyphs.add_selection_run(SelectionRun {
+                        window_id,
+                        kind,
+                        x,
+                        y: hit_row.y_start,
+                        width: x1 - x0,
+                        height: row_h,
+                        bg,
+                    });
+                    runs.push((hit_row.y_start, hit_row.y_end, x, content_x + x1, bg, fg));
+                }
+            }
+        }
+
+        // Characters whose center lies inside a run take its colors
+        for glyph in &mut frame_glyphs.glyphs[glyph_start..] {
+            if let FrameGlyph::Char { x, y, width, fg: char_fg, bg: char_bg, is_overlay: false, .. } = glyph {
+                let cx = *x + *width / 2.0;
+                if let Some(&(.., bg, fg)) = runs.iter().rev().find(|&&(y0, y1, x0, x1, ..)| {
+                    *y >= y0 && *y < y1 && cx >= x0 && cx < x1
+                }) {
+                    *char_bg = Some(bg);
+                    if let Some(fg) = fg {
+                        *char_fg = fg;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draw the command blocks of the window's buffer laid out in
+    /// HIT_ROWS.  The rows of each block get the tint as background
+    /// (characters drawn over the default background DEFAULT_BG take it
+    /// too) and a bar in the block's status color on the left edge.  The
+    /// prompt row gets a separator line and, if it fits to the right of
+    /// the row's text, the block's label.
+    #[allow(clippy::too_many_arguments)]
+    fn render_command_blocks(
+        &self,
+        params: &WindowParams,
+        text_x: f32,
+        text_width: f32,
+        char_w: f32,
+        char_h: f32,
+        ascent: f32,
+        default_bg: Color,
+        hit_rows: &[HitRow],
+        glyph_start: usize,
+        frame_glyphs: &mut FrameGlyphBuffer,
+    ) {
+        let (Some(first), Some(last)) = (hit_rows.first(), hit_rows.last()) else {
+            return;
+        };
+        let Some(colors) = self.command_blocks.colors(params.buffer_id) else {
+            return;
+        };
+        let blocks = self.command_blocks.in_range(
+            params.buffer_id, first.charpos_start, last.charpos_end + 1,
+        );
+        let starts: Vec<i64> = hit_rows.iter().map(|r| r.charpos_start).collect();
+        let tint = Color::from_pixel(colors.tint);
+        let label_fg = Color::from_pixel(colors.label);
+        let bar_w = (char_w / 4.0).max(2.0);
+        let text_end = text_x + text_width;
+
+        // (y_start, y_end) of the tinted rows
+        let mut spans: Vec<(f32, f32)> = Vec::new();
+        for (i, r0, r1) in block_rows(blocks, &starts) {
+            let block = &blocks[i];
+            let (y0, y1) = (hit_rows[r0].y_start, hit_rows[r1].y_end);
+            let status = Color::from_pixel(colors.status(block));
+            frame_glyphs.add_stretch(text_x, y0, text_width, y1 - y0, tint, 0, false);
+            frame_glyphs.add_stretch(text_x, y0, bar_w, y1 - y0, status, 0, false);
+            spans.push((y0, y1));
+            if starts[r0] > block.start {
+                continue;
+            }
+
+            frame_glyphs.add_border(text_x, y0, text_width, 1.0, label_fg);
+            let prompt_end = hit_rows[r0].y_end;
+            let row_end = frame_glyphs.glyphs[glyph_start..].iter()
+                .filter_map(|g| match g {
+                    FrameGlyph::Char { x, y, width, is_overlay: false, .. }
+                        if *y >= y0 && *y < prompt_end => Some(*x + *width),
+                    _ => None,
+                })
+                .fold(text_x, f32::max);
+            let label = block.label();
+            let label_w: f32 = label.chars()
+                .map(|ch| if is_wide_char(ch) { 2.0 * char_w } else { char_w })
+                .sum();
+            let mut lx = text_end - label_w - char_w;
+            if lx < row_end + char_w {
+                continue;
+            }
+            let icon = block.status_icon();
+            for ch in label.chars() {
+                let fg = if ch == icon { status } else { label_fg };
+                frame_glyphs.set_face(
+                    0, fg, Some(tint),
+                    400, false,
+                    0, None, 0, None, 0, None,
+                );
+                let adv = if is_wide_char(ch) { 2.0 * char_w } else { char_w };
+                frame_glyphs.add_char(ch, lx, y0, adv, char_h, ascent, false);
+                lx += adv;
+            }
+        }
+        if spans.is_empty() {
+            return;
+        }
+
+        for glyph in &mut frame_glyphs.glyphs[glyph_start..] {
+            if let FrameGlyph::Char { y, bg, is_overlay: false, .. } = glyph {
+                if *bg == Some(default_bg) && spans.iter().any(|&(y0, y1)| *y >= y0 && *y < y1) {
+                    *bg = Some(tint);
+                }
+            }
+        }
+    }
+
+    /// Lay out the pulses of the window's buffer over the rows in
+    /// HIT_ROWS.  Each row a pulse touches gets one rectangle, which the
+    /// render thread fades out.
+    fn render_region_pulses(
+        &self,
+        params: &WindowParams,
+        content_x: f32,
+        char_w: f32,
+        hit_rows: &[HitRow],
+        glyph_start: usize,
+        frame_glyphs: &mut FrameGlyphBuffer,
+    ) {
+        // Right edge of the text on each row
+        let mut text_ends: Vec<f32> = vec![content_x; hit_rows.len()];
+        for glyph in &frame_glyphs.glyphs[glyph_start..] {
+            if let FrameGlyph::Char { x, y, width, is_overlay: false, .. } = glyph {
+                let r = hit_rows.partition_point(|row| row.y_end <= *y);
+                if let Some(end) = text_ends.get_mut(r) {
+                    *end = end.max(*x + *width);
+                }
+            }
+        }
+        let rows: Vec<(i64, i64, f32)> = hit_rows.iter().zip(&text_ends)
+            .map(|(row, &end)| (row.charpos_start, row.charpos_end, end))
+            .collect();
+
+        for pulse in self.region_pulses.for_buffer(params.buffer_id) {
+            let color = Color { a: PEAK_ALPHA, ..Color::from_pixel(pulse.color) };
+            for (r, x0, x1) in row_spans(pulse.start, pulse.end, &rows, content_x, char_w) {
+                let row = &hit_rows[r];
+                frame_glyphs.add_region_pulse(RegionPulse {
+                    x: x0,
+                    y: row.y_start,
+                    width: x1 - x0,
+                    height: row.y_end - row.y_start,
+                    color,
+                    started: pulse.started,
+                    duration: pulse.duration,
+                });
+            }
+        }
+    }
+
+    /// Draw the characters of the window's visible links in their link
+    /// color, underlined.
+    fn render_links(
+        &mut self,
+        params: &WindowParams,
+        content_x: f32,
+        char_w: f32,
+        hit_rows: &[HitRow],
+        glyph_start: usize,
+        frame_glyphs: &mut FrameGlyphBuffer,
+    ) {
+        let (Some(first), Some(last)) = (hit_rows.first(), hit_rows.last()) else {
+            return;
+        };
+        let visible = self.links.in_range(
+            params.buffer_id, first.charpos_start, last.charpos_end.max(first.charpos_start + 1),
+        );
+        if visible.is_empty() {
+            return;
+        }
+        let rows: Vec<(i64, i64, f32)> = hit_rows.iter()
+            .map(|row| (row.charpos_start, row.charpos_end, f32::MAX))
+            .collect();
+        // (y_start, y_end, x0, x1, color) of each row piece of each link
+        let mut spans: Vec<(f32, f32, f32, f32, Color)> = Vec::new();
+        for (start, end, _, link) in visible {
+            let color = Color::from_pixel(link.color);
+            for (r, x0, x1) in row_spans(start, end, &rows, content_x, char_w) {
+                spans.push((hit_rows[r].y_start, hit_rows[r].y_end, x0, x1, color));
+            }
+        }
+
+        for glyph in &mut frame_glyphs.glyphs[glyph_start..] {
+            if let FrameGlyph::Char {
+                x, y, width, fg, underline, underline_color, is_overlay: false, ..
+            } = glyph {
+                let cx = *x + *width / 2.0;
+                if let Some(&(.., color)) = spans.iter().rev().find(|&&(y0, y1, x0, x1, _)| {
+                    *y >= y0 && *y < y1 && cx >= x0 && cx < x1
+                }) {
+                    *fg = color;
+                    *underline = 1;
+                    *underline_color = Some(color);
+                }
+            }
+        }
+    }
+
+    /// Align the rows of HIT_ROWS whose lines have a `paragraph-align'
+    /// property (see paragraph_align.rs), moving their text glyphs and
+    /// the cursor on them.  Moved rows of plain characters get hit-test
+    /// cells so clicks land on the characters where they are drawn.
+    /// Returns how far the cursor of WINDOW_ID moved.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn align_paragraphs(
+        host: &dyn LayoutHost,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        window_id: i64,
+        content_x: f32,
+        avail_width: f32,
+        hit_rows: &[HitRow],
+        row_continuation: &[bool],
+        glyph_start: usize,
+        hit_cells: &mut Vec<HitCell>,
+        frame_glyphs: &mut FrameGlyphBuffer,
+    ) -> f32 {
+        // Alignment of each row, from the beginning of its line
+        let mut aligns = Vec::with_capacity(hit_rows.len());
+        let mut align = ParagraphAlign::Left;
+        for (r, row) in hit_rows.iter().enumerate() {
+            if r == 0 || !row_continuation.get(r).copied().unwrap_or(false) {
+                align = ParagraphAlign::from_ffi(
+                    host.paragraph_align(buffer, window, row.charpos_start),
+                );
+            }
+            aligns.push(align);
+        }
+        if aligns.iter().all(|&a| a == ParagraphAlign::Left) {
+            return 0.0;
+        }
+
+        // Text glyphs of each aligned row; stretches reaching the right
+        // edge fill the rest of the row and stay
+        let right = content_x + avail_width;
+        let mut rows: Vec<Vec<usize>> = vec![Vec::new(); hit_rows.len()];
+        for (i, glyph) in frame_glyphs.glyphs.iter().enumerate().skip(glyph_start) {
+            let (x, y, width) = match glyph {
+                FrameGlyph::Char { x, y, width, is_overlay: false, .. }
+                | FrameGlyph::Image { x, y, width, .. }
+                | FrameGlyph::Video { x, y, width, .. }
+                | FrameGlyph::WebKit { x, y, width, .. } => (*x, *y, *width),
+                FrameGlyph::Stretch { x, y, width, is_overlay: false, .. }
+                    if *x + *width < right - 0.5 => (*x, *y, *width),
+                _ => continue,
+            };
+            if x < content_x || x >= right {
+                continue;
+            }
+            let r = hit_rows.partition_point(|row| row.y_end <= y);
+            if aligns.get(r).is_some_and(|&a| a != ParagraphAlign::Left) {
+                rows[r].push(i);
+            }
+        }
+
+        let mut cursor_shift = 0.0;
+        for (r, idxs) in rows.iter_mut().enumerate() {
+            if idxs.is_empty() {
+                continue;
+            }
+            let geometry = |g: &FrameGlyph| match g {
+                FrameGlyph::Char { x, width, char, .. } => (*x, *width, *char == ' '),
+                FrameGlyph::Image { x, width, .. }
+                | FrameGlyph::Video { x, width, .. }
+                | FrameGlyph::WebKit { x, width, .. }
+                | FrameGlyph::Stretch { x, width, .. } => (*x, *width, false),
+                _ => (0.0, 0.0, false),
+            };
+            idxs.sort_by(|&a, &b| {
+                geometry(&frame_glyphs.glyphs[a]).0.total_cmp(&geometry(&frame_glyphs.glyphs[b]).0)
+            });
+            let items: Vec<RowItem> = idxs.iter()
+                .map(|&i| {
+                    let (x, width, space) = geometry(&frame_glyphs.glyphs[i]);
+                    RowItem { x, width, space }
+                })
+                .collect();
+            let last_row = !row_continuation.get(r + 1).copied().unwrap_or(false);
+            let shifts = row_shifts(&items, content_x, avail_width, aligns[r], last_row);
+            if shifts.iter().all(|&s| s == 0.0) {
+                continue;
+            }
+
+            // The shift of the glyph at or before X on this row
+            let shift_at = |x: f32| {
+                let k = items.partition_point(|item| item.x <= x + 0.5);
+                shifts[k.saturating_sub(1)]
+            };
+            let row = &hit_rows[r];
+            for glyph in frame_glyphs.glyphs[glyph_start..].iter_mut() {
+                if let FrameGlyph::Cursor { window_id: w, x, y, .. } = glyph {
+                    if *w == window_id as i32 && *y >= row.y_start && *y < row.y_end {
+                        cursor_shift = shift_at(*x);
+                        *x += cursor_shift;
+                    }
+                }
+            }
+            if let Some(inv) = frame_glyphs.cursor_inverse.as_mut() {
+                if inv.y >= row.y_start && inv.y < row.y_end && inv.x >= content_x && inv.x < right {
+                    inv.x += shift_at(inv.x);
+                }
+            }
+            let mut plain = true;
+            for (&i, &shift) in idxs.iter().zip(&shifts) {
+                match &mut frame_glyphs.glyphs[i] {
+                    FrameGlyph::Char { x, composed, .. } => {
+                        plain &= composed.is_none();
+                        *x += shift;
+                    }
+                    FrameGlyph::Image { x, .. }
+                    | FrameGlyph::Video { x, .. }
+                    | FrameGlyph::WebKit { x, .. }
+                    | FrameGlyph::Stretch { x, .. } => {
+                        plain = false;
+                        *x += shift;
+                    }
+                    _ => {}
+                }
+            }
+            if plain && items.len() as i64 <= row.charpos_end - row.charpos_start {
+                hit_cells.push(HitCell {
+                    y_start: row.y_start,
+                    y_end: row.y_end,
+                    x_start: content_x,
+                    x_end: right,
+                    charpos_start: row.charpos_start,
+                    char_ends: items.iter().zip(&shifts).map(|(i, s)| i.x + i.width + s).collect(),
+                });
+            }
+        }
+        cursor_shift
+    }
+
+    /// Draw the annotations of the window's buffer that are anchored to
+    /// text laid out in HIT_ROWS.  Margin notes go in the right margin;
+    /// cards (and margin notes of windows without a right margin) float
+    /// over the right edge of the text area.  Notes on nearby lines are
+    /// stacked so they do not overlap.
+    #[allow(clippy::too_many_arguments)]
+    fn render_annotations(
+        &mut self,
+        params: &WindowParams,
+        text_x: f32,
+        text_y: f32,
+        text_width: f32,
+        text_y_limit: f32,
+        char_w: f32,
+        char_h: f32,
+        ascent: f32,
+        hit_rows: &[HitRow],
+        frame_glyphs: &mut FrameGlyphBuffer,
+    ) {
+        let (Some(first), Some(last)) = (hit_rows.first(), hit_rows.last()) else {
+            return;
+        };
+        let visible = self.annotations.in_range(
+            params.buffer_id, first.charpos_start, last.charpos_end + 1,
+        );
+
+        let margin_cols = (params.right_margin_width / char_w).floor() as usize;
+        let card_w = (text_width / 3.0).min(40.0 * char_w).floor();
+        let card_cols = ((card_w - char_w) / char_w).floor().max(1.0) as usize;
+        // Accent bar on the left edge of cards
+        let bar_w = (char_w / 4.0).max(2.0);
+        let gap = 2.0;
+
+        // (anchor y, in margin?, lines, annotation)
+        let mut boxes = Vec::with_capacity(visible.len());
+        for (pos, _id, ann) in visible {
+            let Some(hit_row) = hit_rows.iter()
+                .find(|r| pos >= r.charpos_start && pos < r.charpos_end.max(r.charpos_start + 1))
+            else {
+                continue;
+            };
+            let in_margin = ann.style == AnnotationStyle::Margin && margin_cols > 0;
+            let lines = ann.lines(if in_margin { margin_cols } else { card_cols });
+            boxes.push((hit_row.y_start, in_margin, lines, ann.fg, ann.bg));
+        }
+
+        // Margin notes and cards are stacked independently
+        for in_margin in [true, false] {
+            let group: Vec<_> = boxes.iter().filter(|b| b.1 == in_margin).collect();
+            if group.is_empty() {
+                continue;
+            }
+            let extents: Vec<(f32, f32)> = group.iter()
+                .map(|b| (b.0, b.2.len() as f32 * char_h))
+                .collect();
+            let ys = stack_boxes(&extents, text_y, text_y_limit, gap);
+
+            let box_x = if in_margin { text_x + text_width } else { text_x + text_width - card_w };
+            let box_w = if in_margin { params.right_margin_width } else { card_w };
+            let text_start = if in_margin { box_x } else { box_x + char_w };
+            for ((_, _, lines, fg, bg), (&y, &(_, h))) in group.into_iter().zip(ys.iter().zip(&extents)) {
+                let h = h.min(text_y_limit - y);
+                if h <= 0.0 {
+                    continue;
+                }
+                let fg = Color::from_pixel(*fg);
+                let bg = Color::from_pixel(*bg);
+                frame_glyphs.add_stretch(box_x, y, box_w, h, bg, 0, false);
+                if !in_margin {
+                    frame_glyphs.add_stretch(box_x, y, bar_w, h, fg, 0, false);
+                }
+                frame_glyphs.set_face(
+                    0, fg, Some(bg),
+                    400, false,
+                    0, None, 0, None, 0, None,
+                );
+                for (i, line) in lines.iter().enumerate() {
+                    let ly = y + i as f32 * char_h;
+                    if ly + char_h > text_y_limit {
+                        break;
+                    }
+                    let mut lx = text_start;
+                    for ch in line.chars() {
+                        let adv = if is_wide_char(ch) { 2.0 * char_w } else { char_w };
+                        if lx + adv > box_x + box_w {
+                            break;
+                        }
+                        frame_glyphs.add_char(ch, lx, ly, adv, char_h, ascent, false);
+                        lx += adv;
+                    }
+                }
+            }
+        }
+    }
 }
 
 /// Get the advance width for a character in a specific face.
@@ -3555,13 +5734,14 @@ impl LayoutEngine {
 /// Standalone function to avoid borrow conflicts with `LayoutEngine::text_buf`.
 ///
 /// Supports two measurement backends:
-/// - **C FFI** (default): Uses `neomacs_layout_fill_ascii_widths()` / `neomacs_layout_char_width()`
+/// - **C FFI** (default): Uses `host.fill_ascii_widths()` / `host.char_width()`
 ///   which read from Emacs C font metrics (fontconfig/freetype).
 /// - **Cosmic-text**: Uses `FontMetricsService` for measurement, matching the render thread's
 ///   font resolution exactly. Eliminates width mismatches between layout and rendering.
 ///
 /// The backend is selected by `font_metrics_svc` being Some (cosmic) or None (C FFI).
 unsafe fn char_advance(
+    host: &dyn LayoutHost,
     ascii_width_cache: &mut std::collections::HashMap<(u32, i32), [f32; 128]>,
     font_metrics_svc: &mut Option<FontMetricsService>,
     ch: char,
@@ -3592,7 +5772,7 @@ unsafe fn char_advance(
         let cache_key = (face_id, font_size);
         if !ascii_width_cache.contains_key(&cache_key) {
             let mut widths = [0.0f32; 128];
-            neomacs_layout_fill_ascii_widths(
+            host.fill_ascii_widths(
                 window,
                 face_id as c_int,
                 widths.as_mut_ptr(),
@@ -3608,7 +5788,7 @@ unsafe fn char_advance(
     }
 
     // Non-ASCII: query individually via text_extents()
-    let w = neomacs_layout_char_width(window, cp as c_int, face_id as c_int);
+    let w = host.char_width(window, cp as c_int, face_id as c_int);
     if w > 0.0 { w } else { char_cols as f32 * face_w }
 }
 
@@ -3617,6 +5797,7 @@ unsafe fn char_advance(
 /// Queries the actual bitmap data from Emacs via FFI and draws
 /// each set bit as a filled pixel rectangle.
 unsafe fn render_fringe_bitmap(
+    host: &dyn LayoutHost,
     bitmap_id: i32,
     fringe_x: f32,
     row_y: f32,
@@ -3630,7 +5811,7 @@ unsafe fn render_fringe_bitmap(
     let mut bm_height: c_int = 0;
     let mut bm_align: c_int = 0;
 
-    let rows = neomacs_layout_get_fringe_bitmap(
+    let rows = host.get_fringe_bitmap(
         bitmap_id,
         bits.as_mut_ptr(),
         64,
@@ -3699,6 +5880,36 @@ mod tests {
     use super::*;
     use crate::core::frame_glyphs::FrameGlyph;
 
+    fn hit_row(y_start: f32, y_end: f32) -> HitRow {
+        HitRow { y_start, y_end, charpos_start: 1, charpos_end: 1, starts: Vec::new() }
+    }
+
+    #[test]
+    fn row_showing_maps_positions_to_rows() {
+        // Row 1 is a tall heading line with 8px of space above its text
+        let row_y = [0.0, 24.0, 56.0, 72.0];
+        let rows = [
+            HitRow { y_start: 0.0, y_end: 16.0, charpos_start: 1, charpos_end: 10, starts: Vec::new() },
+            HitRow { y_start: 16.0, y_end: 56.0, charpos_start: 10, charpos_end: 20, starts: Vec::new() },
+        ];
+        assert_eq!(row_showing(1, &rows, &row_y, (2, 20, 30)), Some(0));
+        assert_eq!(row_showing(15, &rows, &row_y, (2, 20, 30)), Some(1));
+        assert_eq!(row_showing(20, &rows, &row_y, (2, 20, 30)), Some(2));
+        assert_eq!(row_showing(30, &rows, &row_y, (2, 20, 30)), Some(2));
+        assert_eq!(row_showing(31, &rows, &row_y, (2, 20, 30)), None);
+    }
+
+    #[test]
+    fn rows_that_fit_follows_row_heights() {
+        assert_eq!(rows_that_fit(&[], 400.0, 25), 25);
+        let uniform: Vec<HitRow> = (0..25).map(|i| hit_row(i as f32 * 16.0, (i + 1) as f32 * 16.0)).collect();
+        assert_eq!(rows_that_fit(&uniform, 400.0, 25), 25);
+        // Headings twice as tall as body text
+        let tall: Vec<HitRow> = (0..10).map(|i| hit_row(i as f32 * 32.0, (i + 1) as f32 * 32.0)).collect();
+        assert_eq!(rows_that_fit(&tall, 400.0, 25), 12);
+        assert_eq!(rows_that_fit(&[hit_row(0.0, 900.0)], 400.0, 25), 1);
+    }
+
     #[test]
     fn test_ligature_run_buffer_new() {
         let buf = LigatureRunBuffer::new();
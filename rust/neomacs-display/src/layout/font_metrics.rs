@@ -23,6 +23,25 @@ pub struct FontMetrics {
     pub char_width: f32,
 }
 
+/// A glyph of shaped text, for drawing text outside the GPU pipeline
+/// (see `print`).
+#[derive(Debug, Clone, Copy)]
+pub struct ShapedGlyph {
+    /// Font the glyph comes from, after fallback
+    pub font_id: cosmic_text::fontdb::ID,
+    /// Glyph index in that font
+    pub glyph_id: u16,
+    /// Pen position relative to the start of the text, offsets included
+    pub x: f32,
+    /// Baseline offset, positive upward
+    pub y: f32,
+    /// Advance width
+    pub width: f32,
+    /// Byte range of the cluster the glyph belongs to
+    pub start: usize,
+    pub end: usize,
+}
+
 /// Cache key for font metrics lookups.
 /// Groups: (family, weight, italic, font_size_centipx)
 /// font_size is stored as integer centipixels (size * 100) to avoid float key issues.
@@ -313,6 +332,39 @@ impl FontMetricsService {
         fm
     }
 
+    /// Shape TEXT as one line in the given face, with the same font
+    /// resolution and fallback as the render thread.
+    pub fn shape(&mut self, text: &str, family: &str, weight: u16,
+                 italic: bool, font_size: f32) -> Vec<ShapedGlyph> {
+        let attrs = self.build_attrs(family, weight, italic);
+        let metrics = Metrics::new(font_size, font_size * 1.3);
+        let mut buffer = Buffer::new(&mut self.font_system, metrics);
+        buffer.set_size(&mut self.font_system, None, None);
+        buffer.set_text(&mut self.font_system, text, attrs, cosmic_text::Shaping::Advanced);
+        buffer.shape_until_scroll(&mut self.font_system, false);
+
+        let mut glyphs = Vec::new();
+        for run in buffer.layout_runs() {
+            for glyph in run.glyphs.iter() {
+                glyphs.push(ShapedGlyph {
+                    font_id: glyph.font_id,
+                    glyph_id: glyph.glyph_id,
+                    x: glyph.x + glyph.font_size * glyph.x_offset,
+                    y: glyph.font_size * glyph.y_offset,
+                    width: glyph.w,
+                    start: glyph.start,
+                    end: glyph.end,
+                });
+            }
+        }
+        glyphs
+    }
+
+    /// The font system, for reading the fonts shaped glyphs come from.
+    pub fn font_system_mut(&mut self) -> &mut FontSystem {
+        &mut self.font_system
+    }
+
     /// Clear all caches. Call when fonts change (e.g., text-scale-adjust).
     pub fn clear_caches(&mut self) {
         self.ascii_cache.clear();
@@ -0,0 +1,402 @@
+//! Frame description: one FFI call per redisplay.
+//!
+//! Instead of calling back into C for each window's parameters, each
+//! face run and each display property check, layout asks C once per
+//! frame, with `neomacs_layout_describe_frame`, for a description of the
+//! whole frame written into an arena it keeps between redisplays: the
+//! window parameters, the faces of each window's visible text and the
+//! ranges of it that have a `display` property.  Faces and display
+//! properties are looked up there; only positions outside the text
+//! described, as when layout scrolls past it, still call back.
+//!
+//! The blob is a `FrameDescHeader` followed by arrays of
+//! `WindowDescFFI`, `FaceSpanFFI` and `PropRangeFFI`, matching the C
+//! structs of the same shape in neomacsterm.c.
+
+use std::ffi::{c_char, c_int};
+
+use super::emacs_ffi::*;
+use super::host::LayoutHost;
+
+/// Header of a frame description.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameDescHeader {
+    pub window_count: u32,
+    pub face_span_count: u32,
+    pub prop_range_count: u32,
+    pub reserved: u32,
+    /// Byte offsets of the arrays in the blob, and its whole size
+    pub windows_offset: u64,
+    pub face_spans_offset: u64,
+    pub prop_ranges_offset: u64,
+    pub size: u64,
+}
+
+/// A window of a frame description.
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct WindowDescFFI {
+    pub params: WindowParamsFFI,
+    /// The display ranges cover [described_from, described_to); the
+    /// face spans say what they cover
+    pub described_from: i64,
+    pub described_to: i64,
+    pub first_face_span: u32,
+    pub face_span_count: u32,
+    pub first_prop_range: u32,
+    pub prop_range_count: u32,
+}
+
+/// The face `neomacs_layout_face_at_pos` gives every position in
+/// [start, end).
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct FaceSpanFFI {
+    pub start: i64,
+    pub end: i64,
+    pub face_id: i32,
+    pub reserved: i32,
+    /// The face, with a null `font_family`: the family is in
+    /// `font_family` below, copied so GC cannot move it
+    pub face: FaceDataFFI,
+    pub font_family: [u8; 64],
+}
+
+/// Text in [start, end) has a non-nil `display` property.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PropRangeFFI {
+    pub start: i64,
+    pub end: i64,
+}
+
+/// The last description of a frame, in an arena reused across
+/// redisplays.
+#[derive(Default)]
+pub struct FrameDescription {
+    /// Words, so the blob is 8-byte aligned
+    arena: Vec<u64>,
+    header: FrameDescHeader,
+}
+
+impl FrameDescription {
+    /// Describe FRAME, growing the arena until the description fits.
+    /// Returns false, leaving the description empty, on error.
+    ///
+    /// # Safety
+    /// Must be called on the Emacs thread with a valid FRAME.
+    pub unsafe fn fetch(&mut self, frame: EmacsFrame) -> bool {
+        self.header = FrameDescHeader::default();
+        // Fontification during the first call can add a few spans, so
+        // allow one more try than the growth itself needs
+        for _ in 0..3 {
+            let cap = self.arena.len() * 8;
+            let size = neomacs_layout_describe_frame(frame, self.arena.as_mut_ptr() as *mut u8, cap);
+            if size < 0 {
+                return false;
+            }
+            let size = size as usize;
+            if size <= cap {
+                let header = *(self.arena.as_ptr() as *const FrameDescHeader);
+                if !Self::layout_ok(&header, size) {
+                    log::error!("frame description: bad layout {:?}", header);
+                    return false;
+                }
+                self.header = header;
+                return true;
+            }
+            // Leave room for the description to grow
+            self.arena.resize(size.div_ceil(8) + size.div_ceil(32), 0);
+        }
+        log::warn!("frame description: did not fit after growing the arena");
+        false
+    }
+
+    /// Describe only the windows of FRAME, asking C for each one's
+    /// parameters, with no faces or display ranges, so layout asks for
+    /// those as it goes.  The fallback for when `fetch` fails.  Returns
+    /// false if no window could be read.
+    ///
+    /// # Safety
+    /// Must be called on the Emacs thread with a valid FRAME.
+    pub unsafe fn fetch_windows(&mut self, frame: EmacsFrame) -> bool {
+        let count = super::emacs_types::frame_window_count(frame as *const std::ffi::c_void);
+        let params = (0..count).filter_map(|i| {
+            let mut wp = WindowParamsFFI::default();
+            (neomacs_layout_get_window_params(frame, i, &mut wp) == 0).then_some(wp)
+        });
+        *self = Self::from_window_params(params);
+        count == 0 || self.header.window_count > 0
+    }
+
+    /// Description of windows with PARAMS alone: every face and
+    /// display property lookup calls back.
+    fn from_window_params(params: impl Iterator<Item = WindowParamsFFI>) -> Self {
+        let windows: Vec<WindowDescFFI> = params
+            .map(|params| WindowDescFFI {
+                params,
+                described_from: 0,
+                described_to: 0,
+                first_face_span: 0,
+                face_span_count: 0,
+                first_prop_range: 0,
+                prop_range_count: 0,
+            })
+            .collect();
+        Self::build(&windows, &[], &[])
+    }
+
+    /// Description of WINDOWS, with FACE_SPANS and PROP_RANGES, laid out
+    /// as `neomacs_layout_describe_frame` does; for hosts other than
+    /// Emacs.
+    pub fn build(windows: &[WindowDescFFI], face_spans: &[FaceSpanFFI], prop_ranges: &[PropRangeFFI]) -> Self {
+        let array = |offset: usize, count: usize, elem: usize| (offset, (offset + count * elem).next_multiple_of(8));
+        let (windows_offset, end) =
+            array(std::mem::size_of::<FrameDescHeader>(), windows.len(), std::mem::size_of::<WindowDescFFI>());
+        let (face_spans_offset, end) = array(end, face_spans.len(), std::mem::size_of::<FaceSpanFFI>());
+        let (prop_ranges_offset, size) = array(end, prop_ranges.len(), std::mem::size_of::<PropRangeFFI>());
+        let header = FrameDescHeader {
+            window_count: windows.len() as u32,
+            face_span_count: face_spans.len() as u32,
+            prop_range_count: prop_ranges.len() as u32,
+            reserved: 0,
+            windows_offset: windows_offset as u64,
+            face_spans_offset: face_spans_offset as u64,
+            prop_ranges_offset: prop_ranges_offset as u64,
+            size: size as u64,
+        };
+        let mut arena = vec![0u64; size / 8];
+        unsafe fn put<T: Clone>(base: *mut u8, offset: usize, items: &[T]) {
+            for (i, item) in items.iter().enumerate() {
+                std::ptr::write((base.add(offset) as *mut T).add(i), item.clone());
+            }
+        }
+        // SAFETY: the arrays were laid out above within the arena, aligned
+        unsafe {
+            let base = arena.as_mut_ptr() as *mut u8;
+            std::ptr::write(base as *mut FrameDescHeader, header);
+            put(base, windows_offset, windows);
+            put(base, face_spans_offset, face_spans);
+            put(base, prop_ranges_offset, prop_ranges);
+        }
+        FrameDescription { arena, header }
+    }
+
+    /// Whether HEADER's arrays lie in a SIZE-byte blob at aligned offsets.
+    fn layout_ok(header: &FrameDescHeader, size: usize) -> bool {
+        let array_ok = |offset: u64, count: u32, elem: usize| {
+            let offset = offset as usize;
+            offset.is_multiple_of(8)
+                && offset >= std::mem::size_of::<FrameDescHeader>()
+                && offset + count as usize * elem <= size
+        };
+        header.size as usize == size
+            && array_ok(header.windows_offset, header.window_count, std::mem::size_of::<WindowDescFFI>())
+            && array_ok(header.face_spans_offset, header.face_span_count, std::mem::size_of::<FaceSpanFFI>())
+            && array_ok(header.prop_ranges_offset, header.prop_range_count, std::mem::size_of::<PropRangeFFI>())
+    }
+
+    /// Slice of COUNT Ts at byte OFFSET of the blob.
+    fn slice<T>(&self, offset: u64, count: u32) -> &[T] {
+        if count == 0 {
+            return &[];
+        }
+        // SAFETY: `fetch` checked, and `build` made sure, that the arrays
+        // lie in the arena, aligned
+        unsafe {
+            let base = (self.arena.as_ptr() as *const u8).add(offset as usize) as *const T;
+            std::slice::from_raw_parts(base, count as usize)
+        }
+    }
+
+    /// The windows described, in the order of
+    /// `neomacs_layout_get_window_params`.
+    pub fn windows(&self) -> &[WindowDescFFI] {
+        self.slice(self.header.windows_offset, self.header.window_count)
+    }
+
+    /// Faces and display ranges of window DESC.
+    pub fn window_cache(&self, desc: &WindowDescFFI) -> WindowCache<'_> {
+        let spans: &[FaceSpanFFI] = self.slice(self.header.face_spans_offset, self.header.face_span_count);
+        let ranges: &[PropRangeFFI] = self.slice(self.header.prop_ranges_offset, self.header.prop_range_count);
+        let part = |first: u32, count: u32, len: usize| {
+            let first = (first as usize).min(len);
+            first..(first + count as usize).min(len)
+        };
+        WindowCache {
+            described_from: desc.described_from,
+            described_to: desc.described_to,
+            face_spans: &spans[part(desc.first_face_span, desc.face_span_count, spans.len())],
+            prop_ranges: &ranges[part(desc.first_prop_range, desc.prop_range_count, ranges.len())],
+        }
+    }
+}
+
+/// Faces and display property ranges of one window's visible text.
+/// An empty cache (the default) makes every lookup call back into C.
+#[derive(Default)]
+pub struct WindowCache<'a> {
+    described_from: i64,
+    described_to: i64,
+    face_spans: &'a [FaceSpanFFI],
+    prop_ranges: &'a [PropRangeFFI],
+}
+
+impl<'a> WindowCache<'a> {
+    /// Cache over FACE_SPANS and PROP_RANGES, both sorted, with the
+    /// display ranges covering [FROM, TO).
+    pub fn new(face_spans: &'a [FaceSpanFFI], prop_ranges: &'a [PropRangeFFI], from: i64, to: i64) -> Self {
+        WindowCache { described_from: from, described_to: to, face_spans, prop_ranges }
+    }
+
+    /// The face span containing CHARPOS.
+    pub fn face_span(&self, charpos: i64) -> Option<&'a FaceSpanFFI> {
+        let i = self.face_spans.partition_point(|s| s.start <= charpos);
+        let span = self.face_spans.get(i.checked_sub(1)?)?;
+        (charpos < span.end).then_some(span)
+    }
+
+    /// If CHARPOS is described and has no display property, the
+    /// position where one may start.
+    pub fn display_clear_until(&self, charpos: i64) -> Option<i64> {
+        if charpos < self.described_from || charpos >= self.described_to {
+            return None;
+        }
+        let i = self.prop_ranges.partition_point(|r| r.end <= charpos);
+        match self.prop_ranges.get(i) {
+            Some(r) if r.start <= charpos => None,
+            Some(r) => Some(r.start),
+            None => Some(self.described_to),
+        }
+    }
+
+    /// `LayoutHost::face_at_pos`, answered from the cache when it can.
+    ///
+    /// # Safety
+    /// As `LayoutHost::face_at_pos`.
+    pub unsafe fn face_at_pos(
+        &self,
+        host: &dyn LayoutHost,
+        window: EmacsWindow,
+        charpos: i64,
+        face_out: *mut FaceDataFFI,
+        next_check_out: *mut i64,
+    ) -> c_int {
+        match self.face_span(charpos) {
+            Some(span) => {
+                *face_out = span.face.clone();
+                if span.font_family[0] != 0 {
+                    (*face_out).font_family = span.font_family.as_ptr() as *const c_char;
+                }
+                *next_check_out = span.end;
+                span.face_id
+            }
+            None => host.face_at_pos(window, charpos, face_out, next_check_out),
+        }
+    }
+
+    /// `LayoutHost::check_display_prop`, answered from the cache when
+    /// CHARPOS is known to have no display property.
+    ///
+    /// # Safety
+    /// As `LayoutHost::check_display_prop`.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn check_display_prop(
+        &self,
+        host: &dyn LayoutHost,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        out: *mut DisplayPropFFI,
+    ) -> c_int {
+        match self.display_clear_until(charpos) {
+            Some(until) => {
+                *out = DisplayPropFFI { covers_to: until, image_ascent: 50, ..Default::default() };
+                0
+            }
+            None => host.check_display_prop(buffer, window, charpos, str_buf, str_buf_len, out),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(start: i64, end: i64, face_id: i32) -> FaceSpanFFI {
+        FaceSpanFFI {
+            start,
+            end,
+            face_id,
+            reserved: 0,
+            face: FaceDataFFI::default(),
+            font_family: [0; 64],
+        }
+    }
+
+    #[test]
+    fn face_spans_are_found_by_position() {
+        let spans = [span(10, 15, 1), span(15, 30, 2), span(40, 50, 3)];
+        let cache = WindowCache::new(&spans, &[], 10, 50);
+        assert_eq!(cache.face_span(10).map(|s| s.face_id), Some(1));
+        assert_eq!(cache.face_span(14).map(|s| s.face_id), Some(1));
+        assert_eq!(cache.face_span(15).map(|s| s.face_id), Some(2));
+        // Gaps and positions outside the spans call back
+        assert!(cache.face_span(35).is_none());
+        assert!(cache.face_span(9).is_none());
+        assert!(cache.face_span(50).is_none());
+    }
+
+    #[test]
+    fn built_descriptions_read_back() {
+        let mut window = WindowDescFFI {
+            params: WindowParamsFFI::default(),
+            described_from: 1,
+            described_to: 40,
+            first_face_span: 1,
+            face_span_count: 1,
+            first_prop_range: 0,
+            prop_range_count: 0,
+        };
+        window.params.window_id = 7;
+        let spans = [span(1, 10, 1), span(10, 40, 2)];
+        let desc = FrameDescription::build(&[window], &spans, &[]);
+        assert!(FrameDescription::layout_ok(&desc.header, desc.header.size as usize));
+        let windows = desc.windows();
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0].params.window_id, 7);
+        let cache = desc.window_cache(&windows[0]);
+        assert!(cache.face_span(5).is_none());
+        assert_eq!(cache.face_span(20).map(|s| s.face_id), Some(2));
+        assert_eq!(cache.display_clear_until(20), Some(40));
+    }
+
+    #[test]
+    fn window_params_alone_call_back_for_everything() {
+        let params = (1..=2).map(|id| WindowParamsFFI { window_id: id, ..Default::default() });
+        let desc = FrameDescription::from_window_params(params);
+        let windows = desc.windows();
+        assert_eq!(windows.iter().map(|w| w.params.window_id).collect::<Vec<_>>(), [1, 2]);
+        let cache = desc.window_cache(&windows[1]);
+        assert!(cache.face_span(1).is_none());
+        assert_eq!(cache.display_clear_until(1), None);
+    }
+
+    #[test]
+    fn display_ranges_tell_where_to_check_again() {
+        let ranges = [PropRangeFFI { start: 20, end: 25 }, PropRangeFFI { start: 30, end: 31 }];
+        let cache = WindowCache::new(&[], &ranges, 10, 100);
+        assert_eq!(cache.display_clear_until(10), Some(20));
+        assert_eq!(cache.display_clear_until(20), None);
+        assert_eq!(cache.display_clear_until(24), None);
+        assert_eq!(cache.display_clear_until(25), Some(30));
+        assert_eq!(cache.display_clear_until(31), Some(100));
+        // Outside the described text
+        assert_eq!(cache.display_clear_until(5), None);
+        assert_eq!(cache.display_clear_until(100), None);
+        assert_eq!(WindowCache::default().display_clear_until(10), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Inline suggestions ("ghost text").
+//!
+//! A suggestion is virtual text shown after point in a dimmed color, as
+//! offered by completion engines such as Copilot.  The layout engine
+//! renders it as if it were inserted at its buffer position, wrapping
+//! across as many lines as it spans, but the buffer is not touched until
+//! (part of) the suggestion is accepted.
+
+/// How much of a suggestion to accept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GhostAccept {
+    /// Leading whitespace plus the next word (or punctuation run).
+    Word,
+    /// Up to and including the next newline.
+    Line,
+    /// The whole suggestion.
+    All,
+}
+
+impl GhostAccept {
+    /// Decode the FFI representation: 0 = word, 1 = line, anything else = all.
+    pub fn from_ffi(mode: i32) -> Self {
+        match mode {
+            0 => Self::Word,
+            1 => Self::Line,
+            _ => Self::All,
+        }
+    }
+}
+
+/// An inline suggestion attached to a buffer position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GhostText {
+    /// Buffer the suggestion belongs to (same id as `WindowParams::buffer_id`).
+    pub buffer_id: u64,
+    /// Character position the suggestion is inserted at.
+    pub position: i64,
+    /// Suggested text; may contain newlines.
+    pub text: String,
+    /// Foreground color (sRGB pixel).
+    pub fg: u32,
+}
+
+impl GhostText {
+    /// Whether the suggestion should be drawn at CHARPOS of a window
+    /// showing BUFFER_ID with point at POINT.  Suggestions are only shown
+    /// while point sits at the suggestion.
+    pub fn visible_at(&self, buffer_id: u64, charpos: i64, point: i64) -> bool {
+        !self.text.is_empty()
+            && self.buffer_id == buffer_id
+            && self.position == charpos
+            && point == charpos
+    }
+
+    /// Remove the accepted prefix from the suggestion and return it.  The
+    /// remaining suggestion moves past the accepted text, which the caller
+    /// inserts into the buffer at `position`.
+    pub fn accept(&mut self, how: GhostAccept) -> String {
+        let end = match how {
+            GhostAccept::Word => word_end(&self.text),
+            GhostAccept::Line => line_end(&self.text),
+            GhostAccept::All => self.text.len(),
+        };
+        let rest = self.text.split_off(end);
+        let accepted = std::mem::replace(&mut self.text, rest);
+        self.position += accepted.chars().count() as i64;
+        accepted
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Byte index just past the next word of TEXT, including any whitespace
+/// before it.  A run of punctuation counts as a word.
+fn word_end(text: &str) -> usize {
+    let start = text
+        .char_indices()
+        .find(|(_, c)| !c.is_whitespace())
+        .map_or(text.len(), |(i, _)| i);
+    let mut chars = text[start..].char_indices();
+    let first_is_word = match chars.next() {
+        Some((_, c)) => is_word_char(c),
+        None => return text.len(),
+    };
+    chars
+        .find(|(_, c)| c.is_whitespace() || is_word_char(*c) != first_is_word)
+        .map_or(text.len(), |(i, _)| start + i)
+}
+
+/// Byte index just past the first newline of TEXT, or its length.
+fn line_end(text: &str) -> usize {
+    text.find('\n').map_or(text.len(), |i| i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ghost(text: &str) -> GhostText {
+        GhostText { buffer_id: 7, position: 10, text: text.to_string(), fg: 0x808080 }
+    }
+
+    #[test]
+    fn visible_only_at_point() {
+        let g = ghost("foo");
+        assert!(g.visible_at(7, 10, 10));
+        assert!(!g.visible_at(7, 10, 11));
+        assert!(!g.visible_at(8, 10, 10));
+        assert!(!g.visible_at(7, 9, 9));
+        assert!(!ghost("").visible_at(7, 10, 10));
+    }
+
+    #[test]
+    fn accept_word_takes_leading_space_and_word() {
+        let mut g = ghost("  value = compute(x)\n");
+        assert_eq!(g.accept(GhostAccept::Word), "  value");
+        assert_eq!(g.position, 17);
+        assert_eq!(g.accept(GhostAccept::Word), " =");
+        assert_eq!(g.accept(GhostAccept::Word), " compute");
+        assert_eq!(g.accept(GhostAccept::Word), "(");
+        assert_eq!(g.text, "x)\n");
+    }
+
+    #[test]
+    fn accept_line_includes_newline() {
+        let mut g = ghost("first line\nsecond");
+        assert_eq!(g.accept(GhostAccept::Line), "first line\n");
+        assert_eq!(g.position, 21);
+        assert_eq!(g.accept(GhostAccept::Line), "second");
+        assert!(g.text.is_empty());
+    }
+
+    #[test]
+    fn accept_all_and_multibyte_positions() {
+        let mut g = ghost("héllo wörld");
+        assert_eq!(g.accept(GhostAccept::Word), "héllo");
+        assert_eq!(g.position, 15);
+        assert_eq!(g.accept(GhostAccept::All), " wörld");
+        assert_eq!(g.position, 21);
+        assert!(g.text.is_empty());
+    }
+
+    #[test]
+    fn accept_mode_from_ffi() {
+        assert_eq!(GhostAccept::from_ffi(0), GhostAccept::Word);
+        assert_eq!(GhostAccept::from_ffi(1), GhostAccept::Line);
+        assert_eq!(GhostAccept::from_ffi(2), GhostAccept::All);
+    }
+}
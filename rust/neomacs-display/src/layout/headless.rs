@@ -0,0 +1,1318 @@
+//! Headless layout: the layout engine without Emacs.
+//!
+//! `HeadlessHost` is a `LayoutHost` that answers from windows showing
+//! in-memory buffers, so tests can drive `LayoutEngine` with synthetic
+//! text and make exact assertions about where glyphs land, how lines
+//! wrap and where the cursor goes.  It models a plain frame: one face,
+//! a fixed cell size with double-width East Asian characters, no mode
+//! lines or overlays, and of text properties only `invisible',
+//! `display' and `composition'.  Fringe bitmap N is one row of pixels spelling N in
+//! binary, so tests can read back which bitmap a fringe shows.  Window
+//! and buffer pointers it hands the engine are tokens, never
+//! dereferenced.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{c_int, c_void};
+use std::rc::Rc;
+use std::sync::Mutex;
+
+use crate::core::bidi::BidiDir;
+use crate::core::frame_glyphs::FrameGlyphBuffer;
+use crate::core::types::Rect;
+
+use super::buffer_snapshot::BufferSnapshot;
+use super::emacs_ffi::*;
+use super::engine::LayoutEngine;
+use super::frame_desc::{FrameDescription, PropRangeFFI, WindowDescFFI};
+use super::host::LayoutHost;
+use super::hscroll::{AutoHscroll, HscrollPolicy};
+use super::types::FrameParams;
+use super::unicode::is_wide_char;
+
+/// Frame pointer handed to the engine.
+const HEADLESS_FRAME: EmacsFrame = 1 as EmacsFrame;
+
+/// Layout publishes hit-test data to a global, so headless layouts run
+/// one at a time.
+pub(crate) static LAYOUT_LOCK: Mutex<()> = Mutex::new(());
+
+/// What a `display' property shows instead of the text it covers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeadlessDisplay {
+    /// A replacing string
+    String(String),
+    /// `(space :width COLUMNS)'
+    Space(f32),
+    /// An image of WIDTH x HEIGHT pixels, uploaded as ID
+    Image { id: u32, width: i32, height: i32 },
+}
+
+/// A window showing an in-memory buffer.
+#[derive(Debug, Clone)]
+pub struct HeadlessWindow {
+    /// The buffer's text
+    pub text: Rc<str>,
+    pub point: i64,
+    pub window_start: i64,
+    pub truncate_lines: bool,
+    pub word_wrap: bool,
+    /// `wrap-prefix': text drawn at the start of continuation rows
+    pub wrap_prefix: Option<String>,
+    /// `neomacs-hyphenation', with patterns from `hyphenation_dir', and
+    /// `neomacs-hyphenation-ragged-columns'
+    pub hyphenation: Option<String>,
+    pub hyphenation_dir: String,
+    pub hyphenation_ragged_columns: i32,
+    /// Invisible ranges [start, end), and whether each shows "..."
+    pub invisible: Vec<(i64, i64, bool)>,
+    /// `display' properties over [start, end), in buffer order
+    pub display: Vec<(i64, i64, HeadlessDisplay)>,
+    /// Static compositions over [start, end): the text each is drawn
+    /// as, and how many columns wide
+    pub compositions: Vec<(i64, i64, String, i32)>,
+    /// `selective-display' (-1 for t) and `selective-display-ellipses'
+    pub selective_display: i32,
+    pub selective_display_ellipses: bool,
+    /// `display-line-numbers', configured as `line_number_config'
+    pub line_numbers: bool,
+    /// Mode, width and options of the line numbers; absolute and two
+    /// columns wide by default
+    pub line_number_config: LineNumberConfigFFI,
+    /// Columns scrolled off to the left, with `truncate_lines'
+    pub hscroll: i32,
+    /// `auto-hscroll-mode' and its variables; off by default, so
+    /// `hscroll' stays as set
+    pub auto_hscroll: HscrollPolicy,
+    /// Width of each fringe in pixels; none by default
+    pub fringe_width: f32,
+    /// What the fringes show; Emacs's defaults
+    pub fringe_indicators: FringeIndicatorsFFI,
+    /// Region and secondary selection, as `region_spans' reports them
+    pub region_spans: Vec<RegionSpanFFI>,
+    pub tab_width: i32,
+    pub selected: bool,
+    /// `bidi-display-reordering' and `bidi-paragraph-direction'
+    pub bidi_reordering: bool,
+    pub paragraph_direction: BidiDir,
+    /// Position and size in the frame, in pixels
+    pub bounds: Rect,
+}
+
+impl HeadlessWindow {
+    /// Position after the last character.
+    fn zv(&self) -> i64 {
+        self.text.chars().count() as i64 + 1
+    }
+
+    /// Start of the line N lines before the one containing POS.
+    fn line_start(&self, pos: i64, n: i64) -> i64 {
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut start = pos.clamp(1, self.zv());
+        for i in 0..=n {
+            if i > 0 {
+                if start <= 1 {
+                    break;
+                }
+                start -= 1;
+            }
+            while start > 1 && chars[(start - 2) as usize] != '\n' {
+                start -= 1;
+            }
+        }
+        start
+    }
+}
+
+/// A frame of in-memory windows, laid out without Emacs.
+pub struct HeadlessHost {
+    pub frame: FrameParams,
+    pub windows: Vec<HeadlessWindow>,
+    /// Foreground and background of the one face
+    pub fg: u32,
+    pub bg: u32,
+    /// What layout wrote back, by window index
+    cursors: RefCell<HashMap<usize, (i32, i32, i32, i32)>>,
+    window_ends: RefCell<HashMap<usize, (i64, i32)>>,
+    hscrolls: RefCell<HashMap<usize, i32>>,
+}
+
+impl HeadlessHost {
+    /// A WIDTH x HEIGHT pixel frame with 8x16 character cells.
+    pub fn new(width: f32, height: f32) -> Self {
+        HeadlessHost {
+            frame: FrameParams {
+                width,
+                height,
+                char_width: 8.0,
+                char_height: 16.0,
+                font_pixel_size: 13.0,
+                background: 0x000000,
+                vertical_border_fg: 0x808080,
+                right_divider_width: 0,
+                bottom_divider_width: 0,
+                divider_fg: 0,
+                divider_first_fg: 0,
+                divider_last_fg: 0,
+            },
+            windows: Vec::new(),
+            fg: 0xffffff,
+            bg: 0x000000,
+            cursors: RefCell::new(HashMap::new()),
+            window_ends: RefCell::new(HashMap::new()),
+            hscrolls: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Add a selected window at BOUNDS showing TEXT from its start,
+    /// with point there.
+    pub fn add_window(&mut self, text: &str, bounds: Rect) -> &mut HeadlessWindow {
+        self.windows.push(HeadlessWindow {
+            text: text.into(),
+            point: 1,
+            window_start: 1,
+            truncate_lines: false,
+            word_wrap: false,
+            wrap_prefix: None,
+            hyphenation: None,
+            hyphenation_dir: concat!(env!("CARGO_MANIFEST_DIR"), "/../../etc/hyphenation").to_string(),
+            hyphenation_ragged_columns: 0,
+            invisible: Vec::new(),
+            display: Vec::new(),
+            compositions: Vec::new(),
+            selective_display: 0,
+            selective_display_ellipses: true,
+            line_numbers: false,
+            line_number_config: LineNumberConfigFFI { mode: 1, width: 2, ..Default::default() },
+            hscroll: 0,
+            auto_hscroll: HscrollPolicy::default(),
+            fringe_width: 0.0,
+            fringe_indicators: FringeIndicatorsFFI::standard(self.fg, self.bg),
+            region_spans: Vec::new(),
+            tab_width: 8,
+            selected: true,
+            bidi_reordering: true,
+            paragraph_direction: BidiDir::Auto,
+            bounds,
+        });
+        self.windows.last_mut().unwrap()
+    }
+
+    /// Lay the frame out with ENGINE, using this host's font metrics.
+    pub fn layout(&self, engine: &mut LayoutEngine) -> FrameGlyphBuffer {
+        let _guard = LAYOUT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        engine.use_cosmic_metrics = false;
+        let mut frame_glyphs = FrameGlyphBuffer::with_size(self.frame.width, self.frame.height);
+        // SAFETY: the frame and the pointers in its description are this
+        // host's tokens, which it never dereferences
+        unsafe { engine.layout_frame_with(self, HEADLESS_FRAME, &self.frame, &mut frame_glyphs) };
+        frame_glyphs
+    }
+
+    /// Cursor layout last set in window INDEX: x, y, hpos, vpos.
+    pub fn cursor(&self, index: usize) -> Option<(i32, i32, i32, i32)> {
+        self.cursors.borrow().get(&index).copied()
+    }
+
+    /// Window end layout last set in window INDEX: position and row.
+    pub fn window_end(&self, index: usize) -> Option<(i64, i32)> {
+        self.window_ends.borrow().get(&index).copied()
+    }
+
+    /// Hscroll automatic hscrolling last gave window INDEX.
+    pub fn auto_hscrolled(&self, index: usize) -> Option<i32> {
+        self.hscrolls.borrow().get(&index).copied()
+    }
+
+    fn token(index: usize) -> *mut c_void {
+        (index + 1) as *mut c_void
+    }
+
+    /// Index and window of the window or buffer token PTR.
+    fn window(&self, ptr: *mut c_void) -> Option<(usize, &HeadlessWindow)> {
+        let index = (ptr as usize).checked_sub(1)?;
+        Some((index, self.windows.get(index)?))
+    }
+
+    fn face(&self) -> FaceDataFFI {
+        let ascent = (self.frame.char_height * 0.8).round();
+        FaceDataFFI {
+            face_id: 0,
+            fg: self.fg,
+            bg: self.bg,
+            font_weight: 400,
+            font_size: self.frame.font_pixel_size as c_int,
+            font_char_width: self.frame.char_width,
+            font_ascent: ascent,
+            font_space_width: self.frame.char_width,
+            font_is_monospace: 1,
+            font_descent: (self.frame.char_height - ascent) as c_int,
+            underline_position: 1,
+            underline_thickness: 1,
+            ..Default::default()
+        }
+    }
+
+    fn window_params(&self, index: usize, window: &HeadlessWindow) -> WindowParamsFFI {
+        let b = window.bounds;
+        WindowParamsFFI {
+            window_id: index as i64 + 1,
+            buffer_id: index as u64 + 1,
+            window_ptr: Self::token(index),
+            buffer_ptr: Self::token(index),
+            x: b.x,
+            y: b.y,
+            width: b.width,
+            height: b.height,
+            text_x: b.x + window.fringe_width,
+            text_y: b.y,
+            text_width: b.width - 2.0 * window.fringe_width,
+            text_height: b.height,
+            selected: window.selected as c_int,
+            window_start: window.window_start,
+            point: window.point,
+            buffer_zv: window.zv(),
+            buffer_begv: 1,
+            left_fringe_width: window.fringe_width,
+            right_fringe_width: window.fringe_width,
+            truncate_lines: window.truncate_lines as c_int,
+            hscroll: window.hscroll,
+            word_wrap: window.word_wrap as c_int,
+            tab_width: window.tab_width,
+            selective_display: window.selective_display,
+            selective_display_ellipses: window.selective_display_ellipses as c_int,
+            default_fg: self.fg,
+            default_bg: self.bg,
+            char_width: self.frame.char_width,
+            char_height: self.frame.char_height,
+            font_pixel_size: self.frame.font_pixel_size,
+            font_ascent: self.face().font_ascent,
+            cursor_bar_width: 2,
+            fill_column_indicator_char: '|' as c_int,
+            bidi_reordering: window.bidi_reordering as c_int,
+            bidi_paragraph_direction: match window.paragraph_direction {
+                BidiDir::LTR => 1,
+                BidiDir::RTL => 2,
+                BidiDir::Auto => 0,
+            },
+            auto_hscroll: match window.auto_hscroll.mode {
+                AutoHscroll::Off => 0,
+                AutoHscroll::On => 1,
+                AutoHscroll::CurrentLine => 2,
+            },
+            hscroll_margin: window.auto_hscroll.margin,
+            hscroll_step: window.auto_hscroll.step,
+            hscroll_step_fraction: window.auto_hscroll.step_fraction.unwrap_or(-1.0),
+            min_hscroll: window.auto_hscroll.min_hscroll,
+            ..Default::default()
+        }
+    }
+}
+
+/// Set *OUT to VALUE if OUT is not null.
+unsafe fn put<T>(out: *mut T, value: T) {
+    if !out.is_null() {
+        *out = value;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+impl LayoutHost for HeadlessHost {
+    unsafe fn describe_frame(&self, _frame: EmacsFrame, desc: &mut FrameDescription) -> bool {
+        // Faces come from face_at_pos
+        let mut prop_ranges: Vec<PropRangeFFI> = Vec::new();
+        let windows: Vec<WindowDescFFI> = self.windows.iter().enumerate().map(|(i, w)| {
+            let first_prop_range = prop_ranges.len() as u32;
+            prop_ranges.extend(w.display.iter().map(|&(start, end, _)| PropRangeFFI { start, end }));
+            WindowDescFFI {
+                params: self.window_params(i, w),
+                described_from: 1,
+                described_to: w.zv(),
+                first_face_span: 0,
+                face_span_count: 0,
+                first_prop_range,
+                prop_range_count: w.display.len() as u32,
+            }
+        }).collect();
+        *desc = FrameDescription::build(&windows, &[], &prop_ranges);
+        true
+    }
+
+    unsafe fn capture_buffer(&self, buffer: EmacsBuffer, text_buf: Vec<u8>) -> BufferSnapshot {
+        match self.window(buffer) {
+            Some((_, w)) => BufferSnapshot::in_memory(
+                w.text.clone(), w.point, w.tab_width, w.truncate_lines, w.word_wrap, text_buf,
+            ),
+            None => BufferSnapshot::in_memory("".into(), 1, 8, false, false, text_buf),
+        }
+    }
+
+    unsafe fn face_at_pos(
+        &self,
+        window: EmacsWindow,
+        _charpos: i64,
+        face_out: *mut FaceDataFFI,
+        next_check_out: *mut i64,
+    ) -> c_int {
+        put(face_out, self.face());
+        put(next_check_out, self.window(window).map_or(i64::MAX, |(_, w)| w.zv()));
+        0
+    }
+
+    unsafe fn default_face(&self, _frame: EmacsFrame, face_out: *mut FaceDataFFI) -> c_int {
+        put(face_out, self.face());
+        0
+    }
+
+    unsafe fn get_stipple_bitmap(
+        &self,
+        _frame: *mut c_void,
+        _bitmap_id: c_int,
+        _bits_out: *mut u8,
+        _bits_buf_len: c_int,
+        _width_out: *mut c_int,
+        _height_out: *mut c_int,
+    ) -> c_int {
+        -1
+    }
+
+    unsafe fn char_width(&self, _window: EmacsWindow, charcode: c_int, _face_id: c_int) -> f32 {
+        let wide = char::from_u32(charcode as u32).is_some_and(is_wide_char);
+        self.frame.char_width * if wide { 2.0 } else { 1.0 }
+    }
+
+    unsafe fn fill_ascii_widths(&self, _window: EmacsWindow, _face_id: c_int, widths: *mut f32) {
+        std::slice::from_raw_parts_mut(widths, 128).fill(self.frame.char_width);
+    }
+
+    unsafe fn adjust_window_start(
+        &self,
+        window: EmacsWindow,
+        _buffer: EmacsBuffer,
+        point: i64,
+        lines_above: c_int,
+    ) -> i64 {
+        self.window(window).map_or(1, |(_, w)| w.line_start(point, lines_above as i64))
+    }
+
+    unsafe fn anchor_window_start(
+        &self,
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        point: i64,
+        rows_above: c_int,
+    ) -> i64 {
+        self.adjust_window_start(window, buffer, point, rows_above)
+    }
+
+    unsafe fn set_window_end(&self, window: EmacsWindow, end_pos: i64, end_vpos: c_int) {
+        if let Some((i, _)) = self.window(window) {
+            self.window_ends.borrow_mut().insert(i, (end_pos, end_vpos));
+        }
+    }
+
+    unsafe fn set_cursor(&self, window: EmacsWindow, x: c_int, y: c_int, hpos: c_int, vpos: c_int) {
+        if let Some((i, _)) = self.window(window) {
+            self.cursors.borrow_mut().insert(i, (x, y, hpos, vpos));
+        }
+    }
+
+    unsafe fn set_hscroll(&self, window: EmacsWindow, hscroll: c_int) {
+        if let Some((i, _)) = self.window(window) {
+            self.hscrolls.borrow_mut().insert(i, hscroll);
+        }
+    }
+
+    unsafe fn ensure_fontified(&self, _buffer: EmacsBuffer, _from: i64, _to: i64) -> c_int {
+        0
+    }
+
+    unsafe fn check_invisible(
+        &self,
+        buffer: EmacsBuffer,
+        _window: EmacsWindow,
+        charpos: i64,
+        next_visible_out: *mut i64,
+    ) -> c_int {
+        let Some((_, w)) = self.window(buffer) else {
+            return 0;
+        };
+        if let Some(&(_, end, ellipsis)) =
+            w.invisible.iter().find(|(start, end, _)| (*start..*end).contains(&charpos))
+        {
+            put(next_visible_out, end);
+            return if ellipsis { 2 } else { 1 };
+        }
+        // Visible until the next invisible range starts
+        let next = w.invisible.iter().map(|r| r.0).filter(|&start| start > charpos).min();
+        put(next_visible_out, next.unwrap_or_else(|| w.zv()));
+        0
+    }
+
+    unsafe fn composition_at(
+        &self,
+        buffer: EmacsBuffer,
+        charpos: i64,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        end_out: *mut i64,
+        width_out: *mut c_int,
+    ) -> c_int {
+        let Some((_, w)) = self.window(buffer) else {
+            put(end_out, i64::MAX);
+            return 0;
+        };
+        let found = w.compositions.iter().find(|c| c.0 <= charpos && charpos < c.1);
+        if let Some((_, end, text, columns)) = found {
+            if text.len() > str_buf_len.max(0) as usize {
+                put(end_out, *end);
+                return 0;
+            }
+            std::ptr::copy_nonoverlapping(text.as_ptr(), str_buf, text.len());
+            put(end_out, *end);
+            put(width_out, *columns);
+            return text.len() as c_int;
+        }
+        // Not composed up to the next composition
+        let next = w.compositions.iter().map(|c| c.0).filter(|&start| start > charpos).min();
+        put(end_out, next.unwrap_or_else(|| w.zv()));
+        0
+    }
+
+    unsafe fn mode_line_text(
+        &self,
+        _window: EmacsWindow,
+        _frame: EmacsFrame,
+        _out_buf: *mut u8,
+        _out_buf_len: i64,
+        face_out: *mut FaceDataFFI,
+    ) -> i64 {
+        put(face_out, self.face());
+        0
+    }
+
+    unsafe fn header_line_text(
+        &self,
+        window: EmacsWindow,
+        frame: EmacsFrame,
+        out_buf: *mut u8,
+        out_buf_len: i64,
+        face_out: *mut FaceDataFFI,
+    ) -> i64 {
+        self.mode_line_text(window, frame, out_buf, out_buf_len, face_out)
+    }
+
+    unsafe fn tab_line_text(
+        &self,
+        window: EmacsWindow,
+        frame: EmacsFrame,
+        out_buf: *mut u8,
+        out_buf_len: i64,
+        face_out: *mut FaceDataFFI,
+    ) -> i64 {
+        self.mode_line_text(window, frame, out_buf, out_buf_len, face_out)
+    }
+
+    unsafe fn line_number_config(
+        &self,
+        window: EmacsWindow,
+        _buffer: EmacsBuffer,
+        _buffer_zv: i64,
+        _max_rows: c_int,
+        config_out: *mut LineNumberConfigFFI,
+    ) -> c_int {
+        let config = match self.window(window) {
+            Some((_, w)) if w.line_numbers => w.line_number_config.clone(),
+            _ => LineNumberConfigFFI::default(),
+        };
+        put(config_out, config);
+        0
+    }
+
+    unsafe fn count_line_number(&self, buffer: EmacsBuffer, charpos: i64, _widen: c_int) -> i64 {
+        self.window(buffer).map_or(1, |(_, w)| {
+            1 + w.text.chars().take((charpos - 1).max(0) as usize).filter(|&c| c == '\n').count() as i64
+        })
+    }
+
+    unsafe fn line_number_face(
+        &self,
+        _window: EmacsWindow,
+        _is_current: c_int,
+        _lnum: i64,
+        _major_tick: c_int,
+        _minor_tick: c_int,
+        face_out: *mut FaceDataFFI,
+    ) -> c_int {
+        put(face_out, self.face());
+        0
+    }
+
+    unsafe fn check_display_prop(
+        &self,
+        buffer: EmacsBuffer,
+        _window: EmacsWindow,
+        charpos: i64,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        out: *mut DisplayPropFFI,
+    ) -> c_int {
+        let Some((_, w)) = self.window(buffer) else {
+            put(out, DisplayPropFFI { covers_to: i64::MAX, image_ascent: 50, ..Default::default() });
+            return 0;
+        };
+        let Some((_, end, spec)) =
+            w.display.iter().find(|(start, end, _)| (*start..*end).contains(&charpos))
+        else {
+            // Nothing to replace until the next property starts
+            let next = w.display.iter().map(|r| r.0).filter(|&start| start > charpos).min();
+            put(out, DisplayPropFFI {
+                covers_to: next.unwrap_or_else(|| w.zv()),
+                image_ascent: 50,
+                ..Default::default()
+            });
+            return 0;
+        };
+        let mut prop = DisplayPropFFI { covers_to: *end, image_ascent: 50, ..Default::default() };
+        match spec {
+            HeadlessDisplay::String(string) => {
+                let len = string.len().min(str_buf_len.max(0) as usize);
+                if !str_buf.is_null() {
+                    std::ptr::copy_nonoverlapping(string.as_ptr(), str_buf, len);
+                }
+                prop.prop_type = 1;
+                prop.str_len = len as c_int;
+            }
+            HeadlessDisplay::Space(columns) => {
+                prop.prop_type = 2;
+                prop.space_width = *columns;
+            }
+            HeadlessDisplay::Image { id, width, height } => {
+                prop.prop_type = 4;
+                prop.image_gpu_id = *id;
+                prop.image_width = *width;
+                prop.image_height = *height;
+            }
+        }
+        let prop_type = prop.prop_type;
+        put(out, prop);
+        prop_type
+    }
+
+    unsafe fn overlay_strings_at(
+        &self,
+        _buffer: EmacsBuffer,
+        _window: EmacsWindow,
+        _charpos: i64,
+        _before_buf: *mut u8,
+        _before_buf_len: c_int,
+        before_len_out: *mut c_int,
+        _after_buf: *mut u8,
+        _after_buf_len: c_int,
+        after_len_out: *mut c_int,
+        _before_face_out: *mut FaceDataFFI,
+        _after_face_out: *mut FaceDataFFI,
+        before_nruns_out: *mut c_int,
+        after_nruns_out: *mut c_int,
+        left_fringe_bitmap_out: *mut c_int,
+        _left_fringe_fg_out: *mut u32,
+        _left_fringe_bg_out: *mut u32,
+        right_fringe_bitmap_out: *mut c_int,
+        _right_fringe_fg_out: *mut u32,
+        _right_fringe_bg_out: *mut u32,
+        before_naligns_out: *mut c_int,
+        after_naligns_out: *mut c_int,
+    ) -> c_int {
+        for out in [
+            before_len_out, after_len_out, before_nruns_out, after_nruns_out,
+            left_fringe_bitmap_out, right_fringe_bitmap_out, before_naligns_out, after_naligns_out,
+        ] {
+            put(out, 0);
+        }
+        0
+    }
+
+    unsafe fn check_glyphless(
+        &self,
+        _frame: EmacsFrame,
+        _codepoint: c_int,
+        method_out: *mut c_int,
+        _str_buf: *mut u8,
+        _str_buf_len: c_int,
+        str_len_out: *mut c_int,
+    ) -> c_int {
+        put(method_out, 0);
+        put(str_len_out, 0);
+        0
+    }
+
+    unsafe fn margin_strings_at(
+        &self,
+        _buffer: EmacsBuffer,
+        _window: EmacsWindow,
+        _charpos: i64,
+        _left_buf: *mut u8,
+        _left_buf_len: c_int,
+        left_len_out: *mut c_int,
+        _right_buf: *mut u8,
+        _right_buf_len: c_int,
+        right_len_out: *mut c_int,
+    ) -> c_int {
+        put(left_len_out, 0);
+        put(right_len_out, 0);
+        0
+    }
+
+    unsafe fn check_line_spacing(
+        &self,
+        _buffer: EmacsBuffer,
+        _window: EmacsWindow,
+        _charpos: i64,
+        _base_height: f32,
+        extra_above_out: *mut f32,
+        extra_below_out: *mut f32,
+    ) -> c_int {
+        put(extra_above_out, 0.0);
+        put(extra_below_out, 0.0);
+        0
+    }
+
+    unsafe fn check_line_prefix(
+        &self,
+        _buffer: EmacsBuffer,
+        window: EmacsWindow,
+        _charpos: i64,
+        prefix_type: c_int,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        str_len_out: *mut c_int,
+        width_out: *mut f32,
+    ) -> c_int {
+        put(str_len_out, 0);
+        put(width_out, 0.0);
+        // Only wrap prefixes (type 1), on continuation rows
+        let prefix = self.window(window).and_then(|(_, w)| w.wrap_prefix.as_deref());
+        match prefix {
+            Some(prefix) if prefix_type == 1 && !str_buf.is_null() => {
+                let len = prefix.len().min(str_buf_len.max(0) as usize);
+                std::ptr::copy_nonoverlapping(prefix.as_ptr(), str_buf, len);
+                put(str_len_out, len as c_int);
+                1
+            }
+            _ => 0,
+        }
+    }
+
+    unsafe fn paragraph_align(&self, _buffer: EmacsBuffer, _window: EmacsWindow, _charpos: i64) -> c_int {
+        0
+    }
+
+    unsafe fn vertical_writing(&self, _window: EmacsWindow) -> c_int {
+        0
+    }
+
+    unsafe fn hyphenation(
+        &self,
+        buffer: EmacsBuffer,
+        lang_buf: *mut u8,
+        lang_buf_len: c_int,
+        dir_buf: *mut u8,
+        dir_buf_len: c_int,
+        dir_len_out: *mut c_int,
+        ragged_cols_out: *mut c_int,
+    ) -> c_int {
+        put(dir_len_out, 0);
+        put(ragged_cols_out, 0);
+        let Some((_, window)) = self.window(buffer) else { return 0 };
+        let Some(lang) = window.hyphenation.as_deref() else { return 0 };
+        let dir = window.hyphenation_dir.as_bytes();
+        if lang.len() > lang_buf_len.max(0) as usize || dir.len() > dir_buf_len.max(0) as usize {
+            return 0;
+        }
+        std::ptr::copy_nonoverlapping(lang.as_ptr(), lang_buf, lang.len());
+        std::ptr::copy_nonoverlapping(dir.as_ptr(), dir_buf, dir.len());
+        put(dir_len_out, dir.len() as c_int);
+        put(ragged_cols_out, window.hyphenation_ragged_columns);
+        lang.len() as c_int
+    }
+
+    unsafe fn get_fringe_bitmap(
+        &self,
+        bitmap_id: c_int,
+        bits_out: *mut u16,
+        bits_buf_len: c_int,
+        width_out: *mut c_int,
+        height_out: *mut c_int,
+        align_out: *mut c_int,
+    ) -> c_int {
+        if !(1..=255).contains(&bitmap_id) || bits_buf_len < 1 {
+            return 0;
+        }
+        put(bits_out, bitmap_id as u16);
+        put(width_out, 8);
+        put(height_out, 1);
+        put(align_out, 0);
+        1
+    }
+
+    unsafe fn overlay_arrows(
+        &self,
+        _window: EmacsWindow,
+        _buffer: EmacsBuffer,
+        _arrows_out: *mut OverlayArrowFFI,
+        _max_arrows: c_int,
+    ) -> c_int {
+        0
+    }
+
+    unsafe fn fringe_indicators(&self, window: EmacsWindow, out: *mut FringeIndicatorsFFI) -> c_int {
+        match self.window(window) {
+            Some((_, w)) => {
+                put(out, w.fringe_indicators.clone());
+                1
+            }
+            None => 0,
+        }
+    }
+
+    unsafe fn fringe_bitmap_fg(&self, _window: EmacsWindow, _bitmap_id: c_int, _fg_out: *mut u32) -> c_int {
+        0
+    }
+
+    unsafe fn region_spans(
+        &self,
+        window: EmacsWindow,
+        _buffer: EmacsBuffer,
+        spans_out: *mut RegionSpanFFI,
+        max_spans: c_int,
+    ) -> c_int {
+        let Some((_, w)) = self.window(window) else {
+            return 0;
+        };
+        let n = w.region_spans.len().min(max_spans.max(0) as usize);
+        for (i, span) in w.region_spans[..n].iter().enumerate() {
+            put(spans_out.add(i), span.clone());
+        }
+        n as c_int
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::frame_glyphs::FrameGlyph;
+
+    /// Characters laid out, with their positions.
+    fn chars(frame_glyphs: &FrameGlyphBuffer) -> Vec<(char, f32, f32)> {
+        frame_glyphs.glyphs.iter().filter_map(|g| match g {
+            FrameGlyph::Char { char, x, y, is_overlay: false, .. } => Some((*char, *x, *y)),
+            _ => None,
+        }).collect()
+    }
+
+    /// Text of each row laid out, by y, left to right.
+    fn rows(frame_glyphs: &FrameGlyphBuffer) -> Vec<String> {
+        let mut laid_out = chars(frame_glyphs);
+        laid_out.sort_by(|a, b| a.2.total_cmp(&b.2).then(a.1.total_cmp(&b.1)));
+        let mut rows: Vec<(f32, String)> = Vec::new();
+        for (ch, _, y) in laid_out {
+            match rows.last_mut() {
+                Some((row_y, text)) if *row_y == y => text.push(ch),
+                _ => rows.push((y, ch.to_string())),
+            }
+        }
+        rows.into_iter().map(|(_, text)| text).collect()
+    }
+
+    #[test]
+    fn lines_are_laid_out_on_the_grid() {
+        let mut host = HeadlessHost::new(80.0, 48.0);
+        host.add_window("ab\n漢c", Rect::new(0.0, 0.0, 80.0, 48.0)).point = 5;
+        let fg = host.layout(&mut LayoutEngine::new());
+
+        let laid_out = chars(&fg);
+        assert_eq!(&laid_out[..2], &[('a', 0.0, 0.0), ('b', 8.0, 0.0)]);
+        // The ideograph takes two cells
+        assert!(laid_out.contains(&('漢', 0.0, 16.0)));
+        assert!(laid_out.contains(&('c', 16.0, 16.0)));
+        // Point is on the `c'
+        assert_eq!(host.cursor(0), Some((16, 16, 2, 1)));
+    }
+
+    #[test]
+    fn long_lines_wrap_or_truncate() {
+        let text = "one two three four";
+        let mut host = HeadlessHost::new(80.0, 64.0);
+        host.add_window(text, Rect::new(0.0, 0.0, 80.0, 64.0));
+        // Ten columns, and no fringes for continuation bitmaps
+        let mut engine = LayoutEngine::new();
+        assert_eq!(rows(&host.layout(&mut engine)), ["one two th", "ree four"]);
+
+        host.windows[0].word_wrap = true;
+        assert_eq!(rows(&host.layout(&mut engine)), ["one two ", "three four"]);
+
+        // Truncated lines end in a `$' in place of their last column
+        host.windows[0].truncate_lines = true;
+        assert_eq!(rows(&host.layout(&mut engine)), ["one two t$"]);
+    }
+
+    #[test]
+    fn point_and_clicks_follow_wrapped_words() {
+        let mut host = HeadlessHost::new(80.0, 96.0);
+        host.add_window("one two three four fivesixseveneight x", Rect::new(0.0, 0.0, 80.0, 96.0));
+        host.windows[0].word_wrap = true;
+        let mut engine = LayoutEngine::new();
+        // Point on the `t' of `three', moved to the second row with its word
+        host.windows[0].point = 9;
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["one two ", "three ", "four ", "fivesixsev", "eneight x"]);
+        assert_eq!(host.cursor(0), Some((0, 16, 0, 1)));
+        // Inside a word too long for a row, which breaks between characters
+        host.windows[0].point = 25;
+        host.layout(&mut engine);
+        assert_eq!(host.cursor(0), Some((40, 48, 5, 3)));
+        host.windows[0].point = 30;
+        host.layout(&mut engine);
+        assert_eq!(host.cursor(0), Some((0, 64, 0, 4)));
+        // Clicks at the start of each row land on its first character
+        let starts: Vec<i64> = (0..5)
+            .map(|row| super::super::hit_test::hit_test_charpos_at_pixel(0.0, row as f32 * 16.0))
+            .collect();
+        assert_eq!(starts, [1, 9, 15, 20, 30]);
+    }
+
+    #[test]
+    fn continuation_rows_start_with_the_wrap_prefix() {
+        let mut host = HeadlessHost::new(80.0, 64.0);
+        host.add_window("one two three four", Rect::new(0.0, 0.0, 80.0, 64.0)).point = 10;
+        host.windows[0].wrap_prefix = Some("> ".to_string());
+        let mut engine = LayoutEngine::new();
+        // Without word wrap the row breaks inside `three', and the
+        // character moved to the next row follows the prefix
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["one two th", "> ree four"]);
+        assert_eq!(host.cursor(0), Some((72, 0, 9, 0)));
+        host.windows[0].point = 11;
+        host.layout(&mut engine);
+        assert_eq!(host.cursor(0), Some((16, 16, 2, 1)));
+
+        host.windows[0].word_wrap = true;
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["one two ", "> three ", "> four"]);
+        // Point on the `r' of `three', after the prefix
+        assert_eq!(host.cursor(0), Some((32, 16, 4, 1)));
+    }
+
+    #[test]
+    fn hyphenation_breaks_words_that_would_leave_rows_ragged() {
+        let mut host = HeadlessHost::new(80.0, 64.0);
+        host.add_window("the walking dog", Rect::new(0.0, 0.0, 80.0, 64.0)).point = 9;
+        host.windows[0].word_wrap = true;
+        host.windows[0].hyphenation = Some("en-us".to_string());
+        if !std::path::Path::new(&host.windows[0].hyphenation_dir).is_dir() {
+            return;
+        }
+        let mut engine = LayoutEngine::new();
+        // "walk-ing": the hyphen ends the row and the rest of the word,
+        // with point on its `i', starts the next
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["the walk-", "ing dog"]);
+        assert!(chars(&fg).contains(&('-', 64.0, 0.0)));
+        assert_eq!(host.cursor(0), Some((0, 16, 0, 1)));
+
+        // Wrapping the word leaves 6 columns; allowed, it is not hyphenated
+        host.windows[0].hyphenation_ragged_columns = 6;
+        assert_eq!(rows(&host.layout(&mut engine)), ["the ", "walking ", "dog"]);
+    }
+
+    #[test]
+    fn hyphenation_keeps_elided_words_whole() {
+        let mut host = HeadlessHost::new(80.0, 64.0);
+        host.add_window("vu jusqu'ici", Rect::new(0.0, 0.0, 80.0, 64.0));
+        host.windows[0].word_wrap = true;
+        host.windows[0].hyphenation = Some("fr".to_string());
+        if !std::path::Path::new(&host.windows[0].hyphenation_dir).is_dir() {
+            return;
+        }
+        let mut engine = LayoutEngine::new();
+        // "jus-qu'ici" is one word to the French patterns; "jusqu" alone
+        // has no hyphenation point
+        assert_eq!(rows(&host.layout(&mut engine)), ["vu jus-", "qu'ici"]);
+        host.windows[0].text = "vu jusqu\u{2019}ici".into();
+        assert_eq!(rows(&host.layout(&mut engine)), ["vu jus-", "qu\u{2019}ici"]);
+    }
+
+    #[test]
+    fn folded_text_collapses_to_an_ellipsis() {
+        let mut host = HeadlessHost::new(96.0, 64.0);
+        // Point inside the folded body
+        host.add_window("* a\nbody\n* b", Rect::new(0.0, 0.0, 96.0, 64.0)).point = 6;
+        host.windows[0].invisible = vec![(4, 9, true)];
+        host.windows[0].line_numbers = true;
+        let mut engine = LayoutEngine::new();
+        // The hidden line still counts for the line numbers after it
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["1* a...", "3* b"]);
+        // Point moves to the first visible position, after the ellipsis
+        assert_eq!(host.cursor(0), Some((64, 0, 6, 0)));
+        assert_eq!(super::super::hit_test::hit_test_charpos_at_pixel(16.0, 16.0), 10);
+
+        host.windows[0].invisible = vec![(4, 9, false)];
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["1* a", "3* b"]);
+        assert_eq!(host.cursor(0), Some((40, 0, 3, 0)));
+    }
+
+    #[test]
+    fn line_numbers_count_lines_or_screen_rows() {
+        let mut host = HeadlessHost::new(96.0, 80.0);
+        // Point on the `c' of the third line; the first one wraps
+        host.add_window("aaaaaaaaaaaaaaaa\nb\nc\n", Rect::new(0.0, 0.0, 96.0, 80.0)).point = 20;
+        host.windows[0].line_numbers = true;
+        let mut engine = LayoutEngine::new();
+        // Continuation rows have a blank column, and the empty line
+        // after the last newline has a number
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["1aaaaaaaaaa", "aaaaaa", "2b", "3c", "4"]);
+        assert_eq!(host.cursor(0), Some((16, 48, 0, 3)));
+
+        host.windows[0].line_number_config.mode = 2;
+        assert_eq!(rows(&host.layout(&mut engine)), ["2aaaaaaaaaa", "aaaaaa", "1b", "0c", "1"]);
+        host.windows[0].line_number_config.current_absolute = 1;
+        assert_eq!(rows(&host.layout(&mut engine)), ["2aaaaaaaaaa", "aaaaaa", "1b", "3c", "1"]);
+
+        // Visual numbers count screen rows, continuation rows included
+        host.windows[0].line_number_config.mode = 3;
+        host.windows[0].line_number_config.current_absolute = 0;
+        assert_eq!(rows(&host.layout(&mut engine)), ["3aaaaaaaaaa", "2aaaaaa", "1b", "0c", "1"]);
+        host.windows[0].point = 14;
+        assert_eq!(rows(&host.layout(&mut engine)), ["1aaaaaaaaaa", "0aaaaaa", "1b", "2c", "3"]);
+    }
+
+    #[test]
+    fn line_numbers_stay_put_when_scrolled_sideways() {
+        let mut host = HeadlessHost::new(96.0, 64.0);
+        host.add_window("abcdef\nxyz", Rect::new(0.0, 0.0, 96.0, 64.0)).point = 5;
+        host.windows[0].line_numbers = true;
+        host.windows[0].truncate_lines = true;
+        host.windows[0].hscroll = 2;
+        let mut engine = LayoutEngine::new();
+        let laid_out = chars(&host.layout(&mut engine));
+        assert!(laid_out.contains(&('1', 0.0, 0.0)));
+        assert!(laid_out.contains(&('2', 0.0, 16.0)));
+        // The truncation mark is after the number column
+        assert!(laid_out.contains(&('$', 16.0, 0.0)));
+        assert!(laid_out.contains(&('z', 24.0, 16.0)));
+
+        // With display-line-numbers-grow-only the column keeps its width
+        host.windows[0].hscroll = 0;
+        host.windows[0].line_number_config.width = 4;
+        host.windows[0].line_number_config.grow_only = 1;
+        host.layout(&mut engine);
+        host.windows[0].line_number_config.width = 2;
+        let laid_out = chars(&host.layout(&mut engine));
+        assert!(laid_out.contains(&('1', 16.0, 0.0)));
+        assert!(laid_out.contains(&('a', 32.0, 0.0)));
+    }
+
+    #[test]
+    fn hscroll_cuts_the_character_straddling_the_edge() {
+        let mut host = HeadlessHost::new(96.0, 64.0);
+        host.add_window("中ab\nab\tc\n\nxy", Rect::new(0.0, 0.0, 96.0, 64.0));
+        host.windows[0].truncate_lines = true;
+        host.windows[0].hscroll = 1;
+        let fg = host.layout(&mut LayoutEngine::new());
+        // The right half of 中 shows after the `$', cut at the edge
+        assert!(chars(&fg).contains(&('中', 0.0, 0.0)));
+        assert_eq!(fg.text_clip_left(0.0, 0.0, 16.0), Some(8.0));
+        // Tabs stop where they would unscrolled; empty lines hide nothing
+        assert!(chars(&fg).contains(&('c', 64.0, 16.0)));
+        assert_eq!(rows(&fg), ["$中ab", "$bc", "$y"]);
+    }
+
+    #[test]
+    fn auto_hscroll_keeps_point_in_view() {
+        let mut host = HeadlessHost::new(96.0, 64.0);
+        host.add_window("0123456789abcdefghijklmnop\nshort", Rect::new(0.0, 0.0, 96.0, 64.0)).point = 21;
+        host.windows[0].truncate_lines = true;
+        host.windows[0].auto_hscroll =
+            HscrollPolicy { mode: AutoHscroll::On, margin: 2, ..HscrollPolicy::default() };
+        let mut engine = LayoutEngine::new();
+        // Point, on the `k', is centered; the short line is all hidden
+        let fg = host.layout(&mut engine);
+        assert_eq!(host.auto_hscrolled(0), Some(14));
+        assert_eq!(rows(&fg), ["$efghijklmn$"]);
+        assert_eq!(host.cursor(0), Some((56, 0, 7, 0)));
+
+        // Back in the left margin, the window scrolls back
+        host.windows[0].hscroll = 14;
+        host.windows[0].point = 3;
+        let fg = host.layout(&mut engine);
+        assert_eq!(host.auto_hscrolled(0), Some(0));
+        assert_eq!(rows(&fg), ["0123456789a$", "short"]);
+
+        // In current-line mode the other lines stay
+        host.windows[0].hscroll = 0;
+        host.windows[0].text = "0123456789abcdefghijklmnop\nABCDEFGHIJKLMNOPQRSTUVWXYZ".into();
+        host.windows[0].point = 48;
+        host.windows[0].auto_hscroll.mode = AutoHscroll::CurrentLine;
+        let fg = host.layout(&mut engine);
+        assert_eq!(host.auto_hscrolled(0), Some(14));
+        assert_eq!(rows(&fg), ["0123456789a$", "$OPQRSTUVWX$"]);
+    }
+
+    /// Bitmap shown in the 8 pixel fringe at X on each of ROWS rows;
+    /// headless bitmaps are a row of pixels spelling their number.
+    fn fringe(frame_glyphs: &FrameGlyphBuffer, x: f32, rows: usize) -> Vec<i32> {
+        let mut bitmaps = vec![0; rows];
+        for g in &frame_glyphs.glyphs {
+            if let FrameGlyph::Border { x: bx, y, width, .. } = g {
+                if *bx >= x && *bx < x + 8.0 {
+                    let first = (*bx - x) as i32;
+                    for px in first..first + *width as i32 {
+                        bitmaps[(*y / 16.0) as usize] |= 1 << (7 - px);
+                    }
+                }
+            }
+        }
+        bitmaps
+    }
+
+    #[test]
+    fn fringes_show_indicators_in_emacs_order() {
+        let mut host = HeadlessHost::new(96.0, 64.0);
+        let window = host.add_window("one two three four", Rect::new(0.0, 0.0, 96.0, 64.0));
+        window.fringe_width = 8.0;
+        let ind = &mut window.fringe_indicators;
+        // indicate-buffer-boundaries left, indicate-empty-lines
+        (ind.top, ind.bottom, ind.top_bottom, ind.up, ind.down) = (12, 14, 16, 5, 6);
+        (ind.top_side, ind.bottom_side, ind.up_side, ind.down_side) = (1, 1, 1, 1);
+        ind.empty_line_side = 1;
+        let mut engine = LayoutEngine::new();
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["one two th", "ree four"]);
+        // Buffer boundaries come before the continuation arrow
+        assert_eq!(fringe(&fg, 0.0, 4), [12, 14, 24, 24]);
+        assert_eq!(fringe(&fg, 88.0, 4), [7, 0, 0, 0]);
+
+        // Scrolled into the text, the last row still shows the end
+        host.windows[0].window_start = 9;
+        host.windows[0].point = 9;
+        let fg = host.layout(&mut engine);
+        assert_eq!(fringe(&fg, 0.0, 4), [14, 24, 24, 24]);
+
+        // Text scrolled off the left is marked in the fringe, not by `$'
+        host.windows[0].window_start = 1;
+        host.windows[0].point = 1;
+        host.windows[0].text = "abcdef\nxyz".into();
+        host.windows[0].truncate_lines = true;
+        host.windows[0].hscroll = 2;
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["cdef", "z"]);
+        assert_eq!(fringe(&fg, 0.0, 4), [3, 3, 24, 24]);
+    }
+
+    #[test]
+    fn display_strings_and_spaces_replace_text() {
+        let mut host = HeadlessHost::new(96.0, 64.0);
+        // Point inside the replaced text
+        host.add_window("x lambda y", Rect::new(0.0, 0.0, 96.0, 64.0)).point = 5;
+        host.windows[0].display = vec![(3, 9, HeadlessDisplay::String("λ".into()))];
+        let mut engine = LayoutEngine::new();
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["x λ y"]);
+        // The cursor is on the string, and clicks on it go to its text
+        assert_eq!(host.cursor(0), Some((16, 0, 2, 0)));
+        assert_eq!(super::super::hit_test::hit_test_charpos_at_pixel(18.0, 4.0), 3);
+        assert_eq!(super::super::hit_test::hit_test_charpos_at_pixel(34.0, 4.0), 10);
+
+        // A space three columns wide
+        host.windows[0].display = vec![(3, 9, HeadlessDisplay::Space(3.0))];
+        let laid_out = chars(&host.layout(&mut engine));
+        assert!(laid_out.contains(&('y', 48.0, 0.0)));
+        assert_eq!(host.cursor(0), Some((16, 0, 2, 0)));
+    }
+
+    #[test]
+    fn images_too_wide_for_the_row_start_the_next() {
+        let mut host = HeadlessHost::new(96.0, 96.0);
+        host.add_window("abcdefgh X\nz", Rect::new(0.0, 0.0, 96.0, 96.0)).point = 10;
+        host.windows[0].display = vec![(10, 11, HeadlessDisplay::Image { id: 7, width: 40, height: 32 })];
+        let fg = host.layout(&mut LayoutEngine::new());
+        let image = fg.glyphs.iter().find_map(|g| match g {
+            FrameGlyph::Image { image_id: 7, x, y, width, height } => Some((*x, *y, *width, *height)),
+            _ => None,
+        });
+        assert_eq!(image, Some((0.0, 16.0, 40.0, 32.0)));
+        assert!(!chars(&fg).iter().any(|&(ch, _, _)| ch == 'X'));
+        // The row is as tall as the image
+        assert!(chars(&fg).contains(&('z', 0.0, 48.0)));
+        assert_eq!(host.cursor(0), Some((0, 16, 0, 1)));
+        assert_eq!(super::super::hit_test::hit_test_charpos_at_pixel(20.0, 30.0), 10);
+    }
+
+    #[test]
+    fn compositions_that_overflow_the_row_wrap_whole() {
+        let mut host = HeadlessHost::new(80.0, 64.0);
+        // `->' drawn as a two-column arrow that does not fit after the
+        // nine columns before it; point inside the composition
+        host.add_window("abcdefghi->z\nw", Rect::new(0.0, 0.0, 80.0, 64.0)).point = 11;
+        host.windows[0].compositions = vec![(10, 12, "→".into(), 2)];
+        let mut engine = LayoutEngine::new();
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["abcdefghi", "→z", "w"]);
+        let laid_out = chars(&fg);
+        assert!(laid_out.contains(&('→', 0.0, 16.0)));
+        assert!(laid_out.contains(&('z', 16.0, 16.0)));
+        // The cursor covers the whole unit on the continuation row
+        assert_eq!(host.cursor(0), Some((0, 16, 0, 1)));
+        // The continuation row starts at the composition, and the rows
+        // after it stay on the grid
+        assert_eq!(super::super::hit_test::hit_test_charpos_at_pixel(0.0, 4.0), 1);
+        assert_eq!(super::super::hit_test::hit_test_charpos_at_pixel(0.0, 20.0), 10);
+        assert_eq!(super::super::hit_test::hit_test_charpos_at_pixel(0.0, 36.0), 14);
+
+        // A composition that fits stays on the row
+        host.windows[0].compositions = vec![(9, 11, "→".into(), 1)];
+        assert_eq!(rows(&host.layout(&mut engine)), ["abcdefgh→>", "z", "w"]);
+    }
+
+    #[test]
+    fn selective_display_hides_indented_lines() {
+        let mut host = HeadlessHost::new(96.0, 64.0);
+        host.add_window("a\n  b\n  c\nd", Rect::new(0.0, 0.0, 96.0, 64.0)).point = 11;
+        host.windows[0].selective_display = 1;
+        host.windows[0].line_numbers = true;
+        let mut engine = LayoutEngine::new();
+        // The indented lines join the line before them, behind "..."
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["1a...", "4d"]);
+        assert_eq!(host.cursor(0), Some((16, 16, 0, 1)));
+        assert_eq!(super::super::hit_test::hit_test_charpos_at_pixel(16.0, 16.0), 11);
+
+        host.windows[0].selective_display_ellipses = false;
+        assert_eq!(rows(&host.layout(&mut engine)), ["1a", "4d"]);
+
+        // With `selective-display' t, a carriage return hides the rest
+        // of its line
+        host.windows[0].text = "a\rhidden\nb".into();
+        host.windows[0].selective_display = -1;
+        host.windows[0].selective_display_ellipses = true;
+        host.windows[0].point = 1;
+        assert_eq!(rows(&host.layout(&mut engine)), ["1a...", "2b"]);
+    }
+
+    #[test]
+    fn positions_map_to_pixels_and_back() {
+        let mut host = HeadlessHost::new(96.0, 64.0);
+        host.add_window("hello\nworld", Rect::new(0.0, 0.0, 96.0, 64.0));
+        let mut engine = LayoutEngine::new();
+        host.layout(&mut engine);
+        let at = |pos| engine.pos_to_pixel(1, pos).map(|p| (p.x, p.y, p.row, p.col));
+        assert_eq!(at(2), Some((8.0, 0.0, 0, 1)));
+        assert_eq!(at(9), Some((16.0, 16.0, 1, 2)));
+        // The end of the buffer, after the last character
+        assert_eq!(at(12), Some((40.0, 16.0, 1, 5)));
+        assert_eq!(at(13), None);
+        assert_eq!(engine.pos_to_pixel(2, 2), None);
+        assert_eq!(engine.pixel_to_pos(1, 17.0, 20.0), Some(9));
+        assert_eq!(engine.pixel_to_pos(2, 17.0, 20.0), None);
+
+        // After a final newline the end is on a row of its own
+        host.windows[0].text = "hello\n".into();
+        host.layout(&mut engine);
+        assert_eq!(engine.pos_to_pixel(1, 7).map(|p| (p.x, p.y, p.row)), Some((0.0, 16.0, 1)));
+    }
+
+    #[test]
+    fn chat_input_stays_pinned_when_scrolled_up() {
+        use crate::layout::chat_view::{ChatColors, ChatView};
+
+        let mut host = HeadlessHost::new(160.0, 64.0);
+        host.add_window("m1\nm2\nm3\nm4\nm5\nm6\n> hi", Rect::new(0.0, 0.0, 160.0, 64.0));
+        let mut engine = LayoutEngine::new();
+        let colors = ChatColors { input_bg: 0x202020, separator: 0x808080, pill_bg: 0x3060c0, pill_fg: 0xffffff };
+        engine.chat_views.set(1, ChatView { input_start: 19, unread: 2, colors });
+
+        // The input replaces the last row and the pill the one above
+        let fg = host.layout(&mut engine);
+        assert_eq!(rows(&fg), ["m1", "m2", "m↓ 2 new messages", "> hi"]);
+        assert_eq!(host.window_end(0), Some((10, 2)));
+        // Clicks land in the input, on the pill at its start
+        assert_eq!(super::super::hit_test::hit_test_charpos_at_pixel(16.0, 48.0), 21);
+        assert_eq!(super::super::hit_test::hit_test_charpos_at_pixel(80.0, 40.0), 19);
+
+        // Point's row is never covered
+        host.windows[0].point = 10;
+        assert_eq!(rows(&host.layout(&mut engine)), ["m1", "m2", "m3", "m4"]);
+
+        // Nothing to pin once the input is in view
+        host.windows[0].window_start = 10;
+        host.windows[0].point = 19;
+        assert_eq!(rows(&host.layout(&mut engine)), ["m4", "m5", "m6", "> hi"]);
+    }
+
+    #[test]
+    fn region_highlights_across_wrapped_lines() {
+        use crate::core::frame_glyphs::SelectionKind;
+        use crate::core::types::Color;
+
+        // 10 columns: "abcdefghijkl" wraps after "abcdefghi" and its
+        // continuation glyph
+        let mut host = HeadlessHost::new(80.0, 64.0);
+        host.add_window("abcdefghijkl
+xy
+z", Rect::new(0.0, 0.0, 80.0, 64.0));
+        let region = RegionSpanFFI { start: 3, end: 17, kind: 0, bg: 0x404080, extend: 1, ..Default::default() };
+        host.windows[0].region_spans = vec![region.clone()];
+        let mut engine = LayoutEngine::new();
+        let fg = host.layout(&mut engine);
+        let runs: Vec<(f32, f32, f32)> = fg.selection_runs.iter().map(|r| (r.x, r.y, r.width)).collect();
+        // The wrapped row stops at its text; the line's end and the
+        // selected newline of "xy" reach the right edge
+        let cols = chars(&fg).iter().filter(|&&(_, _, y)| y == 0.0).count() as f32;
+        assert_eq!(runs[0], (16.0, 0.0, (cols - 2.0) * 8.0));
+        assert_eq!(runs[1].1, 16.0);
+        assert_eq!((runs[1].0, runs[1].0 + runs[1].2), (0.0, 80.0));
+        assert_eq!(runs[2], (0.0, 32.0, 80.0));
+        assert!(fg.selection_runs.iter().all(|r| r.kind == SelectionKind::Region));
+        // The selected characters take the background; others keep theirs
+        let bg_of = |ch: char| fg.glyphs.iter().find_map(|g| match g {
+            FrameGlyph::Char { char: c, bg, .. } if *c == ch => Some(*bg),
+            _ => None,
+        });
+        assert_eq!(bg_of('c'), Some(Some(Color::from_pixel(0x404080))));
+        assert_ne!(bg_of('b'), Some(Some(Color::from_pixel(0x404080))));
+        assert_ne!(bg_of('z'), Some(Some(Color::from_pixel(0x404080))));
+
+        // Without :extend a selected newline takes one column; the last
+        // line has no newline to select
+        let region = RegionSpanFFI { extend: 0, ..region };
+        let secondary = RegionSpanFFI { start: 17, end: 18, kind: 1, bg: 0x806000, ..Default::default() };
+        host.windows[0].region_spans = vec![secondary, region];
+        let fg = host.layout(&mut engine);
+        let runs: Vec<(SelectionKind, f32, f32, f32)> = fg.selection_runs.iter()
+            .map(|r| (r.kind, r.x, r.y, r.width))
+            .collect();
+        assert!(runs.contains(&(SelectionKind::Secondary, 0.0, 48.0, 8.0)));
+        assert!(runs.contains(&(SelectionKind::Region, 0.0, 32.0, 24.0)));
+    }
+
+    #[test]
+    fn right_to_left_paragraphs_are_reordered_and_right_aligned() {
+        let mut host = HeadlessHost::new(80.0, 64.0);
+        host.add_window("\u{5e9}\u{5dc}\u{5d5}\u{5dd}\n\nab", Rect::new(0.0, 0.0, 80.0, 64.0)).point = 1;
+        let mut engine = LayoutEngine::new();
+        let laid_out = chars(&host.layout(&mut engine));
+        // The first character is drawn rightmost, and point with it
+        assert!(laid_out.contains(&('\u{5e9}', 72.0, 0.0)));
+        assert!(laid_out.contains(&('\u{5dd}', 48.0, 0.0)));
+        assert_eq!(host.cursor(0), Some((72, 0, 0, 0)));
+        // The next paragraph finds its own direction
+        assert!(laid_out.contains(&('a', 0.0, 32.0)));
+
+        host.windows[0].bidi_reordering = false;
+        let laid_out = chars(&host.layout(&mut engine));
+        assert!(laid_out.contains(&('\u{5e9}', 0.0, 0.0)));
+    }
+}
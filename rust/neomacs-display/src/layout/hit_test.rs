@@ -1,6 +1,10 @@
-//! Hit-test infrastructure: maps pixel coordinates to buffer char positions.
+//! Hit-test infrastructure: maps pixel coordinates to buffer char positions
+//! and back.
 //!
-//! Built during layout and queried from FFI for mouse interaction.
+//! Built during layout and queried from FFI for mouse interaction,
+//! `posn-at-point' and anything else anchored to text, such as tooltips.
+
+use super::vertical::VerticalArea;
 
 /// Per-row hit-test data: maps a Y range to a charpos range.
 #[derive(Clone)]
@@ -9,6 +13,96 @@ pub(crate) struct HitRow {
     pub y_end: f32,
     pub charpos_start: i64,
     pub charpos_end: i64,
+    /// Charpos and left edge (relative to the text area) of each buffer
+    /// position laid out on the row, in buffer order, so clicks on text
+    /// of variable-width faces or reordered by bidi land on the
+    /// character drawn there.  Empty rows fall back to the character
+    /// grid.
+    pub starts: Vec<(i64, f32)>,
+}
+
+impl HitRow {
+    /// Charpos at X (relative to the text area) on this row, CHAR_W
+    /// being the grid's column width.
+    fn charpos_at(&self, x: f32, char_w: f32) -> i64 {
+        // A position re-laid out on the next row (wrapped there) ends
+        // this one
+        let starts = &self.starts[..self.starts.partition_point(|&(pos, _)| pos < self.charpos_end)];
+        if starts.is_empty() {
+            // Guard zero char_w
+            let cw = if char_w > 0.0 { char_w } else { 8.0 };
+            let col = (x / cw).max(0.0) as i64;
+            return (self.charpos_start + col).min(self.charpos_end);
+        }
+        // The rightmost start at or left of X: bidi reordering leaves
+        // the starts out of order on the screen
+        starts.iter()
+            .filter(|&&(_, sx)| sx <= x)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .or_else(|| starts.iter().min_by(|a, b| a.1.total_cmp(&b.1)))
+            .map_or(self.charpos_start, |&(pos, _)| pos)
+    }
+
+    /// Left edge and width (relative to the text area) of CHARPOS on
+    /// this row, or None if the row does not show it.  A position
+    /// hidden by a display string or invisible text is reported at the
+    /// last position drawn before it; the newline ending the row, or a
+    /// position with no character after it on the row, is one CHAR_W
+    /// wide.
+    fn char_box(&self, charpos: i64, char_w: f32) -> Option<(f32, f32)> {
+        if charpos < self.charpos_start || charpos >= self.charpos_end {
+            return None;
+        }
+        let cw = if char_w > 0.0 { char_w } else { 8.0 };
+        let starts = &self.starts[..self.starts.partition_point(|&(pos, _)| pos < self.charpos_end)];
+        if starts.is_empty() {
+            return Some(((charpos - self.charpos_start) as f32 * cw, cw));
+        }
+        let x = starts.iter()
+            .filter(|&&(pos, _)| pos <= charpos)
+            .max_by_key(|&&(pos, _)| pos)
+            .map_or(starts[0].1, |&(_, x)| x);
+        // The character reaches the next start to its right
+        let width = starts.iter()
+            .map(|&(_, sx)| sx)
+            .filter(|&sx| sx > x)
+            .min_by(|a, b| a.total_cmp(b))
+            .map_or(cw, |next| next - x);
+        Some((x, width))
+    }
+}
+
+/// Hit-test data for text not laid out on the character grid, such as
+/// table cells: maps an X/Y box to the cell's characters.
+#[derive(Clone)]
+pub(crate) struct HitCell {
+    pub y_start: f32,
+    pub y_end: f32,
+    pub x_start: f32,
+    pub x_end: f32,
+    pub charpos_start: i64,
+    /// Right edge of each character of the cell
+    pub char_ends: Vec<f32>,
+}
+
+impl HitCell {
+    fn charpos_at(&self, x: f32) -> i64 {
+        let i = self.char_ends.iter().position(|&e| x < e).unwrap_or(self.char_ends.len());
+        self.charpos_start + i as i64
+    }
+}
+
+/// Where the end of the buffer is drawn in a window that shows it: no
+/// character is there, but point can be.
+#[derive(Clone, Copy)]
+pub(crate) struct HitEnd {
+    pub charpos: i64,
+    /// Left edge, relative to the text area
+    pub x: f32,
+    pub y_start: f32,
+    pub y_end: f32,
+    /// Its row, counting from the first text row
+    pub row: usize,
 }
 
 /// Per-window hit-test data built during layout.
@@ -18,6 +112,43 @@ pub(crate) struct WindowHitData {
     pub content_x: f32,
     pub char_w: f32,
     pub rows: Vec<HitRow>,
+    /// Boxes laid out off the character grid; checked before `rows`
+    pub cells: Vec<HitCell>,
+    /// Text area of a window in vertical writing mode, whose rows and
+    /// cells are in the swapped layout
+    pub vertical: Option<VerticalArea>,
+    /// End of the buffer, if the window shows it
+    pub end: Option<HitEnd>,
+}
+
+impl WindowHitData {
+    /// (X, Y) in the space the rows and cells were laid out in.
+    fn layout_point(&self, x: f32, y: f32) -> (f32, f32) {
+        match self.vertical {
+            Some(area) => area.point_to_layout(x, y),
+            None => (x, y),
+        }
+    }
+}
+
+/// Where a buffer position is drawn: the frame-relative box of its
+/// character, and the row and column of the window's text area it is
+/// on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CharPixel {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub row: i32,
+    pub col: i32,
+}
+
+/// Charpos of the cell of WIN containing (X, Y), if any.
+fn cell_charpos_in(win: &WindowHitData, x: f32, y: f32) -> Option<i64> {
+    win.cells.iter()
+        .find(|c| y >= c.y_start && y < c.y_end && x >= c.x_start && x < c.x_end)
+        .map(|c| c.charpos_at(x))
 }
 
 /// Global hit-test data for all windows, updated each frame.
@@ -29,14 +160,14 @@ pub(crate) static mut FRAME_HIT_DATA: Option<Vec<WindowHitData>> = None;
 /// Returns charpos, or -1 if not found.
 fn charpos_at_pixel_in(data: &[WindowHitData], px: f32, py: f32) -> i64 {
     for win in data {
+        let (px, py) = win.layout_point(px, py);
+        if let Some(pos) = cell_charpos_in(win, px, py) {
+            return pos;
+        }
         // Find row by Y
         for row in &win.rows {
             if py >= row.y_start && py < row.y_end {
-                // Compute approximate column from X (guard zero char_w)
-                let cw = if win.char_w > 0.0 { win.char_w } else { 8.0 };
-                let col = ((px - win.content_x) / cw).max(0.0) as i64;
-                let charpos = (row.charpos_start + col).min(row.charpos_end);
-                return charpos;
+                return row.charpos_at(px - win.content_x, win.char_w);
             }
         }
     }
@@ -49,11 +180,13 @@ fn window_charpos_in(data: &[WindowHitData], window_id: i64, wx: f32, wy: f32) -
         if win.window_id != window_id {
             continue;
         }
+        let (wx, wy) = win.layout_point(wx, wy);
+        if let Some(pos) = cell_charpos_in(win, wx, wy) {
+            return pos;
+        }
         for row in &win.rows {
             if wy >= row.y_start && wy < row.y_end {
-                let cw = if win.char_w > 0.0 { win.char_w } else { 8.0 };
-                let col = ((wx - win.content_x) / cw).max(0.0) as i64;
-                return (row.charpos_start + col).min(row.charpos_end);
+                return row.charpos_at(wx - win.content_x, win.char_w);
             }
         }
         // Past last row: return last charpos
@@ -65,6 +198,35 @@ fn window_charpos_in(data: &[WindowHitData], window_id: i64, wx: f32, wy: f32) -
     -1
 }
 
+/// Core logic: where CHARPOS is drawn in window WINDOW_ID, or None if
+/// the window does not show it.
+fn char_pixel_in(data: &[WindowHitData], window_id: i64, charpos: i64) -> Option<CharPixel> {
+    let win = data.iter().find(|w| w.window_id == window_id)?;
+    let cw = if win.char_w > 0.0 { win.char_w } else { 8.0 };
+    // (x, y, width, height, row) in the layout's space
+    let cell = win.cells.iter().find_map(|c| {
+        let i = charpos - c.charpos_start;
+        let end = *c.char_ends.get(usize::try_from(i).ok()?)?;
+        let x0 = if i == 0 { c.x_start } else { c.char_ends[i as usize - 1] };
+        let row = win.rows.partition_point(|r| r.y_end <= c.y_start);
+        Some((x0, c.y_start, end - x0, c.y_end - c.y_start, row))
+    });
+    let in_rows = || win.rows.iter().enumerate().find_map(|(i, row)| {
+        let (x, width) = row.char_box(charpos, cw)?;
+        Some((win.content_x + x, row.y_start, width, row.y_end - row.y_start, i))
+    });
+    let at_end = || win.end.filter(|e| e.charpos == charpos).map(|e| {
+        (win.content_x + e.x, e.y_start, cw, e.y_end - e.y_start, e.row)
+    });
+    let (x, y, width, height, row) = cell.or_else(in_rows).or_else(at_end)?;
+    let col = ((x - win.content_x) / cw).max(0.0) as i32;
+    let (x, y, width, height) = match win.vertical {
+        Some(area) => area.rect_to_screen(x, y, width, height),
+        None => (x, y, width, height),
+    };
+    Some(CharPixel { x, y, width, height, row: row as i32, col })
+}
+
 /// Query charpos at a given frame-relative pixel coordinate.
 /// Searches all windows for the one containing (px, py).
 /// Returns charpos, or -1 if not found.
@@ -87,6 +249,27 @@ pub fn hit_test_window_charpos(window_id: i64, wx: f32, wy: f32) -> i64 {
     }
 }
 
+/// Query where CHARPOS is drawn in a specific window, or None if it is
+/// not shown there.
+pub fn hit_test_char_pixel(window_id: i64, charpos: i64) -> Option<CharPixel> {
+    unsafe {
+        match &*std::ptr::addr_of!(FRAME_HIT_DATA) {
+            Some(data) => char_pixel_in(data, window_id, charpos),
+            None => None,
+        }
+    }
+}
+
+/// Whether the last layout included the window WINDOW_ID.
+pub fn hit_test_has_window(window_id: i64) -> bool {
+    unsafe {
+        match &*std::ptr::addr_of!(FRAME_HIT_DATA) {
+            Some(data) => data.iter().any(|w| w.window_id == window_id),
+            None => false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,6 +280,9 @@ mod tests {
             content_x,
             char_w,
             rows,
+            cells: Vec::new(),
+            vertical: None,
+            end: None,
         }
     }
 
@@ -106,6 +292,7 @@ mod tests {
             y_end,
             charpos_start,
             charpos_end,
+            starts: Vec::new(),
         }
     }
 
@@ -139,6 +326,22 @@ mod tests {
         assert_eq!(charpos_at_pixel_in(&data, 25.0, 10.0), 3);
     }
 
+    #[test]
+    fn charpos_at_pixel_in_vertical_window() {
+        // 200x100 text area at the origin, laid out as 100-pixel rows:
+        // the first row is the rightmost column
+        let mut win = make_window(1, 0.0, 10.0, vec![
+            make_row(0.0, 20.0, 1, 11),
+            make_row(20.0, 40.0, 11, 21),
+        ]);
+        win.vertical = Some(VerticalArea { x: 0.0, y: 0.0, width: 200.0, height: 100.0 });
+        let data = vec![win];
+        // 35 pixels down the rightmost column: its fourth character
+        assert_eq!(charpos_at_pixel_in(&data, 190.0, 35.0), 4);
+        // The column to its left
+        assert_eq!(charpos_at_pixel_in(&data, 170.0, 5.0), 11);
+    }
+
     #[test]
     fn charpos_at_pixel_with_content_x_offset() {
         let data = vec![
@@ -372,6 +575,118 @@ mod tests {
         assert_eq!(window_charpos_in(&data, 1, 0.0, 25.0), 160);
     }
 
+    // --- table cell tests ---
+
+    #[test]
+    fn cells_take_precedence_over_grid_rows() {
+        let mut win = make_window(1, 0.0, 10.0, vec![make_row(0.0, 20.0, 1, 20)]);
+        win.cells.push(HitCell {
+            y_start: 0.0,
+            y_end: 20.0,
+            x_start: 40.0,
+            x_end: 80.0,
+            charpos_start: 10,
+            char_ends: vec![52.0, 60.0, 75.0],
+        });
+        let data = vec![win];
+        assert_eq!(window_charpos_in(&data, 1, 45.0, 5.0), 10);
+        assert_eq!(window_charpos_in(&data, 1, 55.0, 5.0), 11);
+        // Past the last character: end of the cell
+        assert_eq!(window_charpos_in(&data, 1, 78.0, 5.0), 13);
+        // Outside the cell: grid mapping
+        assert_eq!(charpos_at_pixel_in(&data, 25.0, 5.0), 3);
+    }
+
+    #[test]
+    fn variable_width_rows_use_laid_out_positions() {
+        // "Wim\n" in a proportional face: W is wide, i narrow
+        let mut row = make_row(0.0, 20.0, 1, 5);
+        row.starts = vec![(1, 0.0), (2, 14.0), (3, 18.0), (4, 29.0)];
+        let data = vec![make_window(1, 5.0, 10.0, vec![row])];
+        assert_eq!(window_charpos_in(&data, 1, 5.0 + 13.0, 5.0), 1);
+        assert_eq!(window_charpos_in(&data, 1, 5.0 + 14.0, 5.0), 2);
+        assert_eq!(window_charpos_in(&data, 1, 5.0 + 20.0, 5.0), 3);
+        // Left of the text and past its end: first position and newline
+        assert_eq!(charpos_at_pixel_in(&data, 0.0, 5.0), 1);
+        assert_eq!(charpos_at_pixel_in(&data, 200.0, 5.0), 4);
+    }
+
+    #[test]
+    fn wrapped_position_belongs_to_next_row() {
+        // Position 4 did not fit and was laid out again on the next row
+        let mut row = make_row(0.0, 20.0, 1, 4);
+        row.starts = vec![(1, 0.0), (2, 30.0), (3, 60.0), (4, 95.0)];
+        let data = vec![make_window(1, 0.0, 10.0, vec![row])];
+        assert_eq!(window_charpos_in(&data, 1, 98.0, 5.0), 3);
+    }
+
+    #[test]
+    fn reordered_rows_hit_the_character_drawn() {
+        // "ab" followed by two right-to-left characters drawn reversed
+        let mut row = make_row(0.0, 20.0, 1, 5);
+        row.starts = vec![(1, 0.0), (2, 10.0), (3, 30.0), (4, 20.0)];
+        let data = vec![make_window(1, 0.0, 10.0, vec![row])];
+        assert_eq!(window_charpos_in(&data, 1, 15.0, 5.0), 2);
+        assert_eq!(window_charpos_in(&data, 1, 25.0, 5.0), 4);
+        assert_eq!(window_charpos_in(&data, 1, 35.0, 5.0), 3);
+    }
+
+    // --- char_pixel_in tests ---
+
+    #[test]
+    fn char_pixel_finds_the_character_drawn() {
+        // A row, then "ab", two right-to-left characters drawn
+        // reversed and the newline, with the text area at x=50
+        let mut row = make_row(20.0, 40.0, 3, 8);
+        row.starts = vec![(3, 0.0), (4, 10.0), (5, 30.0), (6, 20.0), (7, 40.0)];
+        let data = vec![make_window(1, 50.0, 10.0, vec![make_row(0.0, 20.0, 1, 3), row])];
+        let px = |pos| char_pixel_in(&data, 1, pos).map(|p| (p.x, p.y, p.width, p.row, p.col));
+        assert_eq!(px(2), Some((60.0, 0.0, 10.0, 0, 1)));
+        assert_eq!(px(4), Some((60.0, 20.0, 10.0, 1, 1)));
+        assert_eq!(px(5), Some((80.0, 20.0, 10.0, 1, 3)));
+        assert_eq!(px(6), Some((70.0, 20.0, 10.0, 1, 2)));
+        // The newline ends the row
+        assert_eq!(px(7), Some((90.0, 20.0, 10.0, 1, 4)));
+        assert_eq!(px(8), None);
+        assert_eq!(char_pixel_in(&data, 2, 4), None);
+    }
+
+    #[test]
+    fn char_pixel_at_end_of_buffer_and_in_cells() {
+        let mut win = make_window(1, 0.0, 10.0, vec![make_row(0.0, 20.0, 1, 5)]);
+        win.end = Some(HitEnd { charpos: 5, x: 0.0, y_start: 20.0, y_end: 40.0, row: 1 });
+        win.cells.push(HitCell {
+            y_start: 40.0,
+            y_end: 60.0,
+            x_start: 100.0,
+            x_end: 140.0,
+            charpos_start: 30,
+            char_ends: vec![110.0, 130.0],
+        });
+        let data = vec![win];
+        let grid = char_pixel_in(&data, 1, 3).unwrap();
+        assert_eq!((grid.x, grid.y, grid.height), (20.0, 0.0, 20.0));
+        let end = char_pixel_in(&data, 1, 5).unwrap();
+        assert_eq!((end.x, end.y, end.row, end.col), (0.0, 20.0, 1, 0));
+        let cell = char_pixel_in(&data, 1, 31).unwrap();
+        assert_eq!((cell.x, cell.y, cell.width), (110.0, 40.0, 20.0));
+        assert_eq!(char_pixel_in(&data, 1, 32), None);
+    }
+
+    #[test]
+    fn char_pixel_in_vertical_window() {
+        let mut win = make_window(1, 0.0, 10.0, vec![
+            make_row(0.0, 20.0, 1, 11),
+            make_row(20.0, 40.0, 11, 21),
+        ]);
+        win.vertical = Some(VerticalArea { x: 0.0, y: 0.0, width: 200.0, height: 100.0 });
+        let data = vec![win];
+        // The fourth character of the rightmost column, and back
+        let p = char_pixel_in(&data, 1, 4).unwrap();
+        assert_eq!((p.x, p.y, p.width, p.height), (180.0, 30.0, 20.0, 10.0));
+        assert_eq!(charpos_at_pixel_in(&data, p.x + 1.0, p.y + 1.0), 4);
+    }
+
     // --- Public API tests (verify wrappers return -1 with FRAME_HIT_DATA = None) ---
     // These test the None path of the public functions. They are safe because
     // they only read the global (which defaults to None).
@@ -388,5 +703,7 @@ mod tests {
     fn public_window_charpos_no_data_returns_neg1() {
         unsafe { *std::ptr::addr_of_mut!(FRAME_HIT_DATA) = None; }
         assert_eq!(hit_test_window_charpos(1, 0.0, 0.0), -1);
+        assert_eq!(hit_test_char_pixel(1, 1), None);
+        assert!(!hit_test_has_window(1));
     }
 }
@@ -0,0 +1,615 @@
+//! The Emacs side of layout, behind a trait.
+//!
+//! Everything the layout engine asks Emacs for — the frame description,
+//! buffer text, faces, text and overlay properties, font metrics — and
+//! everything it writes back (window start and end, the cursor, the
+//! hscroll) goes through a `LayoutHost`.  `EmacsHost` forwards each
+//! call to the C function of the same name in `emacs_ffi`;
+//! `headless::HeadlessHost` answers from in-memory buffers so layout
+//! can run in tests without an Emacs process.
+//!
+//! The methods keep the C functions' signatures, raw pointers and all,
+//! so `EmacsHost` adds nothing to a call and the engine's code is the
+//! same against either host.
+
+use std::ffi::{c_int, c_void};
+
+use super::buffer_snapshot::BufferSnapshot;
+use super::emacs_ffi::*;
+use super::frame_desc::FrameDescription;
+
+/// Source of everything layout reads from Emacs and sink of what it
+/// writes back.  Each method documents only how it differs from the C
+/// function it is named after, `neomacs_layout_<name>`.
+///
+/// # Safety
+/// The methods are unsafe as the C functions are: pointer arguments must
+/// be valid for the writes described, and window, buffer and frame
+/// pointers must come from the host's own frame description.
+#[allow(clippy::missing_safety_doc, clippy::too_many_arguments)]
+pub trait LayoutHost {
+    /// Describe FRAME into DESC.  Returns false, leaving DESC empty, on
+    /// error.
+    unsafe fn describe_frame(&self, frame: EmacsFrame, desc: &mut FrameDescription) -> bool;
+
+    /// Snapshot of BUFFER for one layout pass, reusing TEXT_BUF's
+    /// allocation; see `BufferSnapshot::capture`.
+    unsafe fn capture_buffer(&self, buffer: EmacsBuffer, text_buf: Vec<u8>) -> BufferSnapshot;
+
+    unsafe fn face_at_pos(
+        &self,
+        window: EmacsWindow,
+        charpos: i64,
+        face_out: *mut FaceDataFFI,
+        next_check_out: *mut i64,
+    ) -> c_int;
+
+    unsafe fn default_face(&self, frame: EmacsFrame, face_out: *mut FaceDataFFI) -> c_int;
+
+    unsafe fn get_stipple_bitmap(
+        &self,
+        frame: *mut c_void,
+        bitmap_id: c_int,
+        bits_out: *mut u8,
+        bits_buf_len: c_int,
+        width_out: *mut c_int,
+        height_out: *mut c_int,
+    ) -> c_int;
+
+    unsafe fn char_width(&self, window: EmacsWindow, charcode: c_int, face_id: c_int) -> f32;
+
+    unsafe fn fill_ascii_widths(&self, window: EmacsWindow, face_id: c_int, widths: *mut f32);
+
+    unsafe fn adjust_window_start(
+        &self,
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        point: i64,
+        lines_above: c_int,
+    ) -> i64;
+
+    unsafe fn anchor_window_start(
+        &self,
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        point: i64,
+        rows_above: c_int,
+    ) -> i64;
+
+    unsafe fn set_window_end(&self, window: EmacsWindow, end_pos: i64, end_vpos: c_int);
+
+    unsafe fn set_cursor(&self, window: EmacsWindow, x: c_int, y: c_int, hpos: c_int, vpos: c_int);
+
+    unsafe fn set_hscroll(&self, window: EmacsWindow, hscroll: c_int);
+
+    unsafe fn ensure_fontified(&self, buffer: EmacsBuffer, from: i64, to: i64) -> c_int;
+
+    unsafe fn check_invisible(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        next_visible_out: *mut i64,
+    ) -> c_int;
+
+    unsafe fn composition_at(
+        &self,
+        buffer: EmacsBuffer,
+        charpos: i64,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        end_out: *mut i64,
+        width_out: *mut c_int,
+    ) -> c_int;
+
+    unsafe fn mode_line_text(
+        &self,
+        window: EmacsWindow,
+        frame: EmacsFrame,
+        out_buf: *mut u8,
+        out_buf_len: i64,
+        face_out: *mut FaceDataFFI,
+    ) -> i64;
+
+    unsafe fn header_line_text(
+        &self,
+        window: EmacsWindow,
+        frame: EmacsFrame,
+        out_buf: *mut u8,
+        out_buf_len: i64,
+        face_out: *mut FaceDataFFI,
+    ) -> i64;
+
+    unsafe fn tab_line_text(
+        &self,
+        window: EmacsWindow,
+        frame: EmacsFrame,
+        out_buf: *mut u8,
+        out_buf_len: i64,
+        face_out: *mut FaceDataFFI,
+    ) -> i64;
+
+    unsafe fn line_number_config(
+        &self,
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        buffer_zv: i64,
+        max_rows: c_int,
+        config_out: *mut LineNumberConfigFFI,
+    ) -> c_int;
+
+    unsafe fn count_line_number(&self, buffer: EmacsBuffer, charpos: i64, widen: c_int) -> i64;
+
+    unsafe fn line_number_face(
+        &self,
+        window: EmacsWindow,
+        is_current: c_int,
+        lnum: i64,
+        major_tick: c_int,
+        minor_tick: c_int,
+        face_out: *mut FaceDataFFI,
+    ) -> c_int;
+
+    unsafe fn check_display_prop(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        out: *mut DisplayPropFFI,
+    ) -> c_int;
+
+    unsafe fn overlay_strings_at(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        before_buf: *mut u8,
+        before_buf_len: c_int,
+        before_len_out: *mut c_int,
+        after_buf: *mut u8,
+        after_buf_len: c_int,
+        after_len_out: *mut c_int,
+        before_face_out: *mut FaceDataFFI,
+        after_face_out: *mut FaceDataFFI,
+        before_nruns_out: *mut c_int,
+        after_nruns_out: *mut c_int,
+        left_fringe_bitmap_out: *mut c_int,
+        left_fringe_fg_out: *mut u32,
+        left_fringe_bg_out: *mut u32,
+        right_fringe_bitmap_out: *mut c_int,
+        right_fringe_fg_out: *mut u32,
+        right_fringe_bg_out: *mut u32,
+        before_naligns_out: *mut c_int,
+        after_naligns_out: *mut c_int,
+    ) -> c_int;
+
+    unsafe fn check_glyphless(
+        &self,
+        frame: EmacsFrame,
+        codepoint: c_int,
+        method_out: *mut c_int,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        str_len_out: *mut c_int,
+    ) -> c_int;
+
+    unsafe fn margin_strings_at(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        left_buf: *mut u8,
+        left_buf_len: c_int,
+        left_len_out: *mut c_int,
+        right_buf: *mut u8,
+        right_buf_len: c_int,
+        right_len_out: *mut c_int,
+    ) -> c_int;
+
+    unsafe fn check_line_spacing(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        base_height: f32,
+        extra_above_out: *mut f32,
+        extra_below_out: *mut f32,
+    ) -> c_int;
+
+    unsafe fn check_line_prefix(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        prefix_type: c_int,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        str_len_out: *mut c_int,
+        width_out: *mut f32,
+    ) -> c_int;
+
+    unsafe fn paragraph_align(&self, buffer: EmacsBuffer, window: EmacsWindow, charpos: i64) -> c_int;
+
+    unsafe fn vertical_writing(&self, window: EmacsWindow) -> c_int;
+
+    unsafe fn hyphenation(
+        &self,
+        buffer: EmacsBuffer,
+        lang_buf: *mut u8,
+        lang_buf_len: c_int,
+        dir_buf: *mut u8,
+        dir_buf_len: c_int,
+        dir_len_out: *mut c_int,
+        ragged_cols_out: *mut c_int,
+    ) -> c_int;
+
+    unsafe fn get_fringe_bitmap(
+        &self,
+        bitmap_id: c_int,
+        bits_out: *mut u16,
+        bits_buf_len: c_int,
+        width_out: *mut c_int,
+        height_out: *mut c_int,
+        align_out: *mut c_int,
+    ) -> c_int;
+
+    unsafe fn overlay_arrows(
+        &self,
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        arrows_out: *mut OverlayArrowFFI,
+        max_arrows: c_int,
+    ) -> c_int;
+
+    unsafe fn fringe_indicators(&self, window: EmacsWindow, out: *mut FringeIndicatorsFFI) -> c_int;
+
+    unsafe fn fringe_bitmap_fg(&self, window: EmacsWindow, bitmap_id: c_int, fg_out: *mut u32) -> c_int;
+
+    unsafe fn region_spans(
+        &self,
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        spans_out: *mut RegionSpanFFI,
+        max_spans: c_int,
+    ) -> c_int;
+}
+
+/// The running Emacs: every call goes to C.
+pub struct EmacsHost;
+
+#[allow(clippy::too_many_arguments)]
+impl LayoutHost for EmacsHost {
+    unsafe fn describe_frame(&self, frame: EmacsFrame, desc: &mut FrameDescription) -> bool {
+        if desc.fetch(frame) {
+            return true;
+        }
+        log::warn!("describe_frame: falling back to per-window parameters");
+        desc.fetch_windows(frame)
+    }
+
+    unsafe fn capture_buffer(&self, buffer: EmacsBuffer, text_buf: Vec<u8>) -> BufferSnapshot {
+        BufferSnapshot::capture(buffer, text_buf)
+    }
+
+    unsafe fn face_at_pos(
+        &self,
+        window: EmacsWindow,
+        charpos: i64,
+        face_out: *mut FaceDataFFI,
+        next_check_out: *mut i64,
+    ) -> c_int {
+        neomacs_layout_face_at_pos(window, charpos, face_out, next_check_out)
+    }
+
+    unsafe fn default_face(&self, frame: EmacsFrame, face_out: *mut FaceDataFFI) -> c_int {
+        neomacs_layout_default_face(frame, face_out)
+    }
+
+    unsafe fn get_stipple_bitmap(
+        &self,
+        frame: *mut c_void,
+        bitmap_id: c_int,
+        bits_out: *mut u8,
+        bits_buf_len: c_int,
+        width_out: *mut c_int,
+        height_out: *mut c_int,
+    ) -> c_int {
+        neomacs_layout_get_stipple_bitmap(frame, bitmap_id, bits_out, bits_buf_len, width_out, height_out)
+    }
+
+    unsafe fn char_width(&self, window: EmacsWindow, charcode: c_int, face_id: c_int) -> f32 {
+        neomacs_layout_char_width(window, charcode, face_id)
+    }
+
+    unsafe fn fill_ascii_widths(&self, window: EmacsWindow, face_id: c_int, widths: *mut f32) {
+        neomacs_layout_fill_ascii_widths(window, face_id, widths)
+    }
+
+    unsafe fn adjust_window_start(
+        &self,
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        point: i64,
+        lines_above: c_int,
+    ) -> i64 {
+        neomacs_layout_adjust_window_start(window, buffer, point, lines_above)
+    }
+
+    unsafe fn anchor_window_start(
+        &self,
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        point: i64,
+        rows_above: c_int,
+    ) -> i64 {
+        neomacs_layout_anchor_window_start(window, buffer, point, rows_above)
+    }
+
+    unsafe fn set_window_end(&self, window: EmacsWindow, end_pos: i64, end_vpos: c_int) {
+        neomacs_layout_set_window_end(window, end_pos, end_vpos)
+    }
+
+    unsafe fn set_cursor(&self, window: EmacsWindow, x: c_int, y: c_int, hpos: c_int, vpos: c_int) {
+        neomacs_layout_set_cursor(window, x, y, hpos, vpos)
+    }
+
+    unsafe fn set_hscroll(&self, window: EmacsWindow, hscroll: c_int) {
+        neomacs_layout_set_hscroll(window, hscroll)
+    }
+
+    unsafe fn ensure_fontified(&self, buffer: EmacsBuffer, from: i64, to: i64) -> c_int {
+        neomacs_layout_ensure_fontified(buffer, from, to)
+    }
+
+    unsafe fn check_invisible(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        next_visible_out: *mut i64,
+    ) -> c_int {
+        neomacs_layout_check_invisible(buffer, window, charpos, next_visible_out)
+    }
+
+    unsafe fn composition_at(
+        &self,
+        buffer: EmacsBuffer,
+        charpos: i64,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        end_out: *mut i64,
+        width_out: *mut c_int,
+    ) -> c_int {
+        neomacs_layout_composition_at(buffer, charpos, str_buf, str_buf_len, end_out, width_out)
+    }
+
+    unsafe fn mode_line_text(
+        &self,
+        window: EmacsWindow,
+        frame: EmacsFrame,
+        out_buf: *mut u8,
+        out_buf_len: i64,
+        face_out: *mut FaceDataFFI,
+    ) -> i64 {
+        neomacs_layout_mode_line_text(window, frame, out_buf, out_buf_len, face_out)
+    }
+
+    unsafe fn header_line_text(
+        &self,
+        window: EmacsWindow,
+        frame: EmacsFrame,
+        out_buf: *mut u8,
+        out_buf_len: i64,
+        face_out: *mut FaceDataFFI,
+    ) -> i64 {
+        neomacs_layout_header_line_text(window, frame, out_buf, out_buf_len, face_out)
+    }
+
+    unsafe fn tab_line_text(
+        &self,
+        window: EmacsWindow,
+        frame: EmacsFrame,
+        out_buf: *mut u8,
+        out_buf_len: i64,
+        face_out: *mut FaceDataFFI,
+    ) -> i64 {
+        neomacs_layout_tab_line_text(window, frame, out_buf, out_buf_len, face_out)
+    }
+
+    unsafe fn line_number_config(
+        &self,
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        buffer_zv: i64,
+        max_rows: c_int,
+        config_out: *mut LineNumberConfigFFI,
+    ) -> c_int {
+        neomacs_layout_line_number_config(window, buffer, buffer_zv, max_rows, config_out)
+    }
+
+    unsafe fn count_line_number(&self, buffer: EmacsBuffer, charpos: i64, widen: c_int) -> i64 {
+        neomacs_layout_count_line_number(buffer, charpos, widen)
+    }
+
+    unsafe fn line_number_face(
+        &self,
+        window: EmacsWindow,
+        is_current: c_int,
+        lnum: i64,
+        major_tick: c_int,
+        minor_tick: c_int,
+        face_out: *mut FaceDataFFI,
+    ) -> c_int {
+        neomacs_layout_line_number_face(window, is_current, lnum, major_tick, minor_tick, face_out)
+    }
+
+    unsafe fn check_display_prop(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        out: *mut DisplayPropFFI,
+    ) -> c_int {
+        neomacs_layout_check_display_prop(buffer, window, charpos, str_buf, str_buf_len, out)
+    }
+
+    unsafe fn overlay_strings_at(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        before_buf: *mut u8,
+        before_buf_len: c_int,
+        before_len_out: *mut c_int,
+        after_buf: *mut u8,
+        after_buf_len: c_int,
+        after_len_out: *mut c_int,
+        before_face_out: *mut FaceDataFFI,
+        after_face_out: *mut FaceDataFFI,
+        before_nruns_out: *mut c_int,
+        after_nruns_out: *mut c_int,
+        left_fringe_bitmap_out: *mut c_int,
+        left_fringe_fg_out: *mut u32,
+        left_fringe_bg_out: *mut u32,
+        right_fringe_bitmap_out: *mut c_int,
+        right_fringe_fg_out: *mut u32,
+        right_fringe_bg_out: *mut u32,
+        before_naligns_out: *mut c_int,
+        after_naligns_out: *mut c_int,
+    ) -> c_int {
+        neomacs_layout_overlay_strings_at(
+            buffer, window, charpos,
+            before_buf, before_buf_len, before_len_out,
+            after_buf, after_buf_len, after_len_out,
+            before_face_out, after_face_out,
+            before_nruns_out, after_nruns_out,
+            left_fringe_bitmap_out, left_fringe_fg_out, left_fringe_bg_out,
+            right_fringe_bitmap_out, right_fringe_fg_out, right_fringe_bg_out,
+            before_naligns_out, after_naligns_out,
+        )
+    }
+
+    unsafe fn check_glyphless(
+        &self,
+        frame: EmacsFrame,
+        codepoint: c_int,
+        method_out: *mut c_int,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        str_len_out: *mut c_int,
+    ) -> c_int {
+        neomacs_layout_check_glyphless(frame, codepoint, method_out, str_buf, str_buf_len, str_len_out)
+    }
+
+    unsafe fn margin_strings_at(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        left_buf: *mut u8,
+        left_buf_len: c_int,
+        left_len_out: *mut c_int,
+        right_buf: *mut u8,
+        right_buf_len: c_int,
+        right_len_out: *mut c_int,
+    ) -> c_int {
+        neomacs_layout_margin_strings_at(
+            buffer, window, charpos,
+            left_buf, left_buf_len, left_len_out,
+            right_buf, right_buf_len, right_len_out,
+        )
+    }
+
+    unsafe fn check_line_spacing(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        base_height: f32,
+        extra_above_out: *mut f32,
+        extra_below_out: *mut f32,
+    ) -> c_int {
+        neomacs_layout_check_line_spacing(buffer, window, charpos, base_height, extra_above_out, extra_below_out)
+    }
+
+    unsafe fn check_line_prefix(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        prefix_type: c_int,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        str_len_out: *mut c_int,
+        width_out: *mut f32,
+    ) -> c_int {
+        neomacs_layout_check_line_prefix(
+            buffer, window, charpos, prefix_type, str_buf, str_buf_len, str_len_out, width_out,
+        )
+    }
+
+    unsafe fn paragraph_align(&self, buffer: EmacsBuffer, window: EmacsWindow, charpos: i64) -> c_int {
+        neomacs_layout_paragraph_align(buffer, window, charpos)
+    }
+
+    unsafe fn vertical_writing(&self, window: EmacsWindow) -> c_int {
+        neomacs_layout_vertical_writing(window)
+    }
+
+    unsafe fn hyphenation(
+        &self,
+        buffer: EmacsBuffer,
+        lang_buf: *mut u8,
+        lang_buf_len: c_int,
+        dir_buf: *mut u8,
+        dir_buf_len: c_int,
+        dir_len_out: *mut c_int,
+        ragged_cols_out: *mut c_int,
+    ) -> c_int {
+        neomacs_layout_hyphenation(buffer, lang_buf, lang_buf_len, dir_buf, dir_buf_len, dir_len_out, ragged_cols_out)
+    }
+
+    unsafe fn get_fringe_bitmap(
+        &self,
+        bitmap_id: c_int,
+        bits_out: *mut u16,
+        bits_buf_len: c_int,
+        width_out: *mut c_int,
+        height_out: *mut c_int,
+        align_out: *mut c_int,
+    ) -> c_int {
+        neomacs_layout_get_fringe_bitmap(bitmap_id, bits_out, bits_buf_len, width_out, height_out, align_out)
+    }
+
+    unsafe fn overlay_arrows(
+        &self,
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        arrows_out: *mut OverlayArrowFFI,
+        max_arrows: c_int,
+    ) -> c_int {
+        neomacs_layout_overlay_arrows(window, buffer, arrows_out, max_arrows)
+    }
+
+    unsafe fn fringe_indicators(&self, window: EmacsWindow, out: *mut FringeIndicatorsFFI) -> c_int {
+        neomacs_layout_fringe_indicators(window, out)
+    }
+
+    unsafe fn fringe_bitmap_fg(&self, window: EmacsWindow, bitmap_id: c_int, fg_out: *mut u32) -> c_int {
+        neomacs_layout_fringe_bitmap_fg(window, bitmap_id, fg_out)
+    }
+
+    unsafe fn region_spans(
+        &self,
+        window: EmacsWindow,
+        buffer: EmacsBuffer,
+        spans_out: *mut RegionSpanFFI,
+        max_spans: c_int,
+    ) -> c_int {
+        neomacs_layout_region_spans(window, buffer, spans_out, max_spans)
+    }
+}
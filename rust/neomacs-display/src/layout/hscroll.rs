@@ -0,0 +1,246 @@
+//! Horizontal scrolling of truncated lines.
+//!
+//! A window's hscroll hides the first columns of each line, counted in
+//! frame column widths, so the line starts that many pixels to the
+//! left of the text area; the glyph straddling the edge is cut, and
+//! `$` or the fringe's truncation bitmap marks lines with hidden text.
+//!
+//! `auto-hscroll-mode` moves the hscroll to keep point visible.  Emacs
+//! does that in xdisp.c's hscroll_window_tree() from the glyph rows of
+//! the last redisplay, which the Rust engine never builds, so the
+//! engine instead lays out point's line as if the window were
+//! infinitely wide and decides here, by the same rules, whether and
+//! where to scroll before laying the window out.
+
+use super::unicode::{decode_utf8, is_wide_char};
+
+/// Characters looked at on either side of point for its line: point
+/// further into a line than this is measured from this far back.
+pub const LINE_SCAN_LIMIT: i64 = 10_000;
+
+/// Value of `auto-hscroll-mode` for a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoHscroll {
+    /// nil, or suspended by an explicit `scroll-left' or `scroll-right'
+    Off,
+    /// t: scroll the whole window
+    On,
+    /// current-line: scroll only the line showing point; the rest
+    /// keep the window's minimum hscroll
+    CurrentLine,
+}
+
+/// How a window scrolls sideways to follow point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HscrollPolicy {
+    pub mode: AutoHscroll,
+    /// hscroll-margin: columns point keeps from either edge
+    pub margin: i32,
+    /// hscroll-step: columns to scroll by, 0 to center point
+    pub step: i32,
+    /// hscroll-step when a float: part of the text area to scroll by
+    pub step_fraction: Option<f32>,
+    /// Smallest hscroll, set by `scroll-left' and `scroll-right'
+    pub min_hscroll: i32,
+}
+
+impl Default for HscrollPolicy {
+    fn default() -> Self {
+        HscrollPolicy { mode: AutoHscroll::Off, margin: 5, step: 0, step_fraction: None, min_hscroll: 0 }
+    }
+}
+
+/// Point's line laid out from its start with no hscroll.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLine {
+    /// Pixel offset of point from the start of the line
+    pub x: f32,
+    /// Width laid out, at least as far as what shows past the window
+    pub width: f32,
+    /// Whether point is at the end of the line
+    pub at_eol: bool,
+}
+
+impl HscrollPolicy {
+    /// Hscroll of a line of a window scrolled HSCROLL columns: lines
+    /// other than point's only follow point in current-line mode.
+    pub fn line_hscroll(&self, hscroll: i32, on_point_line: bool) -> i32 {
+        if self.mode == AutoHscroll::CurrentLine && !on_point_line {
+            self.min_hscroll
+        } else {
+            hscroll
+        }
+    }
+
+    /// The hscroll keeping point visible and out of the margins in a
+    /// window scrolled HSCROLL columns of COL_W pixels whose text area
+    /// is TEXT_W wide, with LINE point's line.  None when the window
+    /// should stay as it is.
+    pub fn hscroll_for(&self, hscroll: i32, line: &PointLine, text_w: f32, col_w: f32) -> Option<i32> {
+        if self.mode == AutoHscroll::Off || col_w <= 0.0 {
+            return None;
+        }
+        let h_margin = self.margin.clamp(0, 1_000_000) as f32 * col_w;
+        let cursor_x = line.x - hscroll as f32 * col_w;
+        let truncated_on_left = hscroll > 0 && line.width > 0.0;
+        let truncated_on_right = line.width > hscroll as f32 * col_w + text_w;
+        let in_right_margin = cursor_x >= text_w - h_margin;
+        if !((hscroll > 0 && cursor_x <= h_margin)
+            || (truncated_on_right && in_right_margin)
+            || (self.mode == AutoHscroll::CurrentLine
+                && hscroll != self.min_hscroll
+                && !truncated_on_left))
+        {
+            return None;
+        }
+
+        let new_hscroll = if self.step_fraction.is_none() && self.step <= 0 {
+            // Center point, or near the right edge at the end of a line
+            let wanted_x = if line.at_eol { text_w - 4.0 * col_w } else { text_w / 2.0 };
+            ((line.x - wanted_x).max(0.0) / col_w) as i32
+        } else {
+            let step_w = match self.step_fraction {
+                Some(fraction) => text_w * fraction,
+                None => self.step as f32 * col_w,
+            };
+            let wanted_x = if in_right_margin {
+                text_w - step_w - h_margin
+            } else {
+                step_w + h_margin
+            };
+            ((line.x - wanted_x).max(0.0) / col_w) as i32
+        };
+        let new_hscroll = new_hscroll.max(self.min_hscroll);
+        (new_hscroll != hscroll).then_some(new_hscroll)
+    }
+}
+
+/// Columns and pixel width of CH at column COL of its line: a tab
+/// reaches the next tab stop in SPACE_W wide columns, other characters
+/// take ADVANCE of their columns.
+pub fn char_extent(
+    ch: char,
+    col: i32,
+    tab_width: i32,
+    space_w: f32,
+    advance: impl FnOnce(char, i32) -> f32,
+) -> (i32, f32) {
+    if ch == '\t' {
+        let tab_w = tab_width.max(1);
+        let cols = (col / tab_w + 1) * tab_w - col;
+        (cols, cols as f32 * space_w)
+    } else {
+        let cols = if is_wide_char(ch) { 2 } else { 1 };
+        (cols, advance(ch, cols))
+    }
+}
+
+/// Lay out the line TEXT starts with up to point, POINT characters in,
+/// and on until it ends or grows wider than LIMIT pixels.
+pub fn point_line(
+    text: &[u8],
+    point: usize,
+    tab_width: i32,
+    space_w: f32,
+    limit: f32,
+    mut advance: impl FnMut(char, i32) -> f32,
+) -> PointLine {
+    let mut line = PointLine { x: 0.0, width: 0.0, at_eol: false };
+    let (mut col, mut x) = (0, 0.0f32);
+    let mut byte_idx = 0;
+    let mut i = 0;
+    loop {
+        if i == point {
+            line.x = x;
+        }
+        let (ch, len) = decode_utf8(&text[byte_idx..]);
+        if len == 0 || ch == '\n' {
+            line.at_eol = i <= point;
+            break;
+        }
+        if i > point && x > limit {
+            break;
+        }
+        let (cols, w) = char_extent(ch, col, tab_width, space_w, &mut advance);
+        col += cols;
+        x += w;
+        byte_idx += len;
+        i += 1;
+    }
+    line.width = x;
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COL: f32 = 8.0;
+    const TEXT_W: f32 = 80.0 * COL;
+
+    fn policy(mode: AutoHscroll, step: i32) -> HscrollPolicy {
+        HscrollPolicy { mode, step, ..HscrollPolicy::default() }
+    }
+
+    fn line(x_cols: f32, width_cols: f32) -> PointLine {
+        PointLine { x: x_cols * COL, width: width_cols * COL, at_eol: false }
+    }
+
+    #[test]
+    fn measures_tabs_wide_chars_and_point() {
+        let cell = |_: char, cols: i32| cols as f32 * COL;
+        let l = point_line("ab\tc中d\nnext".as_bytes(), 4, 8, COL, f32::MAX, cell);
+        assert_eq!(l.x, 9.0 * COL);
+        assert_eq!(l.width, 12.0 * COL);
+        assert!(!l.at_eol);
+        let l = point_line(b"abc\nnext", 3, 8, COL, f32::MAX, cell);
+        assert_eq!((l.x, l.width, l.at_eol), (3.0 * COL, 3.0 * COL, true));
+        // Measuring stops past the limit
+        let long = "x".repeat(1000);
+        let l = point_line(long.as_bytes(), 10, 8, COL, 100.0 * COL, cell);
+        assert_eq!(l.width, 101.0 * COL);
+        assert_eq!(char_extent('\t', 3, 4, 5.0, cell), (1, 5.0));
+    }
+
+    #[test]
+    fn scrolls_only_near_the_edges() {
+        let p = policy(AutoHscroll::On, 0);
+        // Visible and clear of the margins
+        assert_eq!(p.hscroll_for(0, &line(40.0, 200.0), TEXT_W, COL), None);
+        // Inside the right margin of a line truncated on the right:
+        // step 0 centers point
+        assert_eq!(p.hscroll_for(0, &line(77.0, 200.0), TEXT_W, COL), Some(37));
+        // The line ends before the right edge: nothing is hidden
+        assert_eq!(p.hscroll_for(0, &line(77.0, 78.0), TEXT_W, COL), None);
+        // Back in the left margin of a scrolled window
+        assert_eq!(p.hscroll_for(37, &line(40.0, 200.0), TEXT_W, COL), Some(0));
+        // Point at the end of a long line stays near the right edge
+        let eol = PointLine { at_eol: true, ..line(150.0, 150.0) };
+        assert_eq!(p.hscroll_for(0, &eol, TEXT_W, COL), Some(74));
+        assert_eq!(policy(AutoHscroll::Off, 0).hscroll_for(0, &line(77.0, 200.0), TEXT_W, COL), None);
+    }
+
+    #[test]
+    fn steps_by_columns_or_fraction() {
+        let p = policy(AutoHscroll::On, 10);
+        // Right: point lands step + margin columns from the right edge
+        assert_eq!(p.hscroll_for(0, &line(78.0, 200.0), TEXT_W, COL), Some(13));
+        // Left: step + margin columns from the left edge
+        assert_eq!(p.hscroll_for(50, &line(52.0, 200.0), TEXT_W, COL), Some(37));
+        let p = HscrollPolicy { step_fraction: Some(0.25), ..p };
+        assert_eq!(p.hscroll_for(0, &line(78.0, 200.0), TEXT_W, COL), Some(23));
+        // Never below the minimum hscroll
+        let p = HscrollPolicy { min_hscroll: 45, ..policy(AutoHscroll::On, 10) };
+        assert_eq!(p.hscroll_for(50, &line(52.0, 200.0), TEXT_W, COL), Some(45));
+    }
+
+    #[test]
+    fn current_line_mode_resets_on_short_lines() {
+        let p = HscrollPolicy { min_hscroll: 2, ..policy(AutoHscroll::CurrentLine, 0) };
+        // Moved from a scrolled line onto an empty one
+        assert_eq!(p.hscroll_for(30, &line(0.0, 0.0), TEXT_W, COL), Some(2));
+        assert_eq!(p.line_hscroll(30, true), 30);
+        assert_eq!(p.line_hscroll(30, false), 2);
+        assert_eq!(policy(AutoHscroll::On, 0).line_hscroll(30, false), 30);
+    }
+}
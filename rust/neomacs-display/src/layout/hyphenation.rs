@@ -0,0 +1,198 @@
+//! Hyphenation of wrapped words (Liang's algorithm).
+//!
+//! With `neomacs-hyphenation' set to a language in a buffer, a word that
+//! word wrap would move to the next row, leaving the row ragged, is
+//! broken at a hyphenation point instead and a hyphen is drawn at the
+//! end of the row.  Hyphenation points come from the TeX patterns for
+//! the language, read from `hyph-LANG.pat.txt' in the hyphenation
+//! directory (etc/hyphenation) the first time the language is used.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Words longer than this are not hyphenated.
+const MAX_WORD_LEN: usize = 64;
+
+/// The hyphenation patterns of a language.
+#[derive(Debug, Default)]
+pub struct Patterns {
+    /// Letters of each pattern (with `.' for word edges), and the
+    /// levels between them: one more level than letters.
+    patterns: HashMap<Vec<char>, Vec<u8>>,
+    /// Length in letters of the longest pattern.
+    max_len: usize,
+    /// Some pattern has an apostrophe, which is then a letter of the
+    /// words it joins, as in French "l'intérêt".
+    apostrophe: bool,
+    /// Fewest letters kept before and after a hyphen.
+    pub left_min: usize,
+    pub right_min: usize,
+}
+
+impl Patterns {
+    /// Parse TEXT, one pattern per line or separated by whitespace, with
+    /// `%' comments, which may give `lefthyphenmin' and
+    /// `righthyphenmin'.
+    pub fn parse(text: &str) -> Self {
+        let mut patterns = Patterns { left_min: 2, right_min: 3, ..Default::default() };
+        for line in text.lines() {
+            let (code, comment) = line.split_once('%').unwrap_or((line, ""));
+            let mut words = comment.split_whitespace();
+            match (words.next(), words.next().and_then(|n| n.parse().ok())) {
+                (Some("lefthyphenmin"), Some(n)) => patterns.left_min = n,
+                (Some("righthyphenmin"), Some(n)) => patterns.right_min = n,
+                _ => {}
+            }
+            for pattern in code.split_whitespace() {
+                patterns.insert(pattern);
+            }
+        }
+        patterns
+    }
+
+    fn insert(&mut self, pattern: &str) {
+        let mut letters = Vec::new();
+        let mut levels = vec![0u8];
+        for c in pattern.chars() {
+            match c.to_digit(10) {
+                Some(d) => *levels.last_mut().unwrap() = d as u8,
+                None => {
+                    letters.extend(c.to_lowercase());
+                    levels.push(0);
+                }
+            }
+        }
+        if letters.is_empty() {
+            return;
+        }
+        self.max_len = self.max_len.max(letters.len());
+        self.apostrophe |= letters.contains(&'\'');
+        self.patterns.insert(letters, levels);
+    }
+
+    /// Whether C joins the letters on either side of it into one word.
+    pub fn joins(&self, c: char) -> bool {
+        self.apostrophe && (c == '\'' || c == '\u{2019}')
+    }
+
+    /// Indices of the characters of WORD a hyphen may go before.
+    pub fn hyphenate(&self, word: &str) -> Vec<usize> {
+        let mut dotted = vec!['.'];
+        for c in word.chars() {
+            // Keep one character per letter of the word, with the
+            // typographic apostrophe as the patterns spell it
+            dotted.push(match c {
+                '\u{2019}' => '\'',
+                _ => c.to_lowercase().next().unwrap_or(c),
+            });
+        }
+        dotted.push('.');
+        let len = dotted.len() - 2;
+        if len < self.left_min + self.right_min || len > MAX_WORD_LEN {
+            return Vec::new();
+        }
+
+        // levels[i] is the level between dotted[i - 1] and dotted[i]
+        let mut levels = vec![0u8; dotted.len() + 1];
+        for start in 0..dotted.len() {
+            let end = dotted.len().min(start + self.max_len);
+            for stop in start + 1..=end {
+                if let Some(pattern) = self.patterns.get(&dotted[start..stop]) {
+                    for (i, &level) in pattern.iter().enumerate() {
+                        levels[start + i] = levels[start + i].max(level);
+                    }
+                }
+            }
+        }
+
+        // Odd levels are hyphenation points; index i of the word is
+        // index i + 1 of DOTTED
+        (self.left_min.max(1)..=len - self.right_min.max(1))
+            .filter(|&i| levels[i + 1] % 2 == 1)
+            .collect()
+    }
+}
+
+/// Patterns by language, loaded on first use.
+pub struct Hyphenator {
+    dir: PathBuf,
+    langs: HashMap<String, Option<Rc<Patterns>>>,
+}
+
+impl Hyphenator {
+    pub fn new() -> Self {
+        Self { dir: PathBuf::new(), langs: HashMap::new() }
+    }
+
+    /// Patterns for LANG from `hyph-LANG.pat.txt' in DIR, or None if
+    /// there are none.  Changing DIR forgets the languages loaded.
+    pub fn patterns(&mut self, dir: &Path, lang: &str) -> Option<Rc<Patterns>> {
+        if self.dir != dir {
+            self.dir = dir.to_path_buf();
+            self.langs.clear();
+        }
+        if let Some(patterns) = self.langs.get(lang) {
+            return patterns.clone();
+        }
+        let path = dir.join(format!("hyph-{}.pat.txt", lang));
+        let patterns = match std::fs::read_to_string(&path) {
+            Ok(text) => Some(Rc::new(Patterns::parse(&text))),
+            Err(e) => {
+                log::warn!("hyphenation: cannot read {}: {}", path.display(), e);
+                None
+            }
+        };
+        self.langs.insert(lang.to_string(), patterns.clone());
+        patterns
+    }
+}
+
+impl Default for Hyphenator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// WORD with a `-' at each of its hyphenation points.
+    fn hyphenated(patterns: &Patterns, word: &str) -> String {
+        let points = patterns.hyphenate(word);
+        let mut out = String::new();
+        for (i, c) in word.chars().enumerate() {
+            if points.contains(&i) {
+                out.push('-');
+            }
+            out.push(c);
+        }
+        out
+    }
+
+    #[test]
+    fn liang_example_patterns() {
+        // The patterns of Liang's thesis that hyphenate "hyphenation"
+        let patterns = Patterns::parse(
+            "% lefthyphenmin 2\n% righthyphenmin 2\nhy3ph he2n hena4 hen5at\n1na n2at 1tio 2io o2n",
+        );
+        assert_eq!((patterns.left_min, patterns.right_min), (2, 2));
+        assert_eq!(hyphenated(&patterns, "hyphenation"), "hy-phen-ation");
+        assert_eq!(hyphenated(&patterns, "Hyphenation"), "Hy-phen-ation");
+        // Too short to hyphenate
+        assert_eq!(patterns.hyphenate("hyp"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn bundled_english_patterns() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../etc/hyphenation/hyph-en-us.pat.txt");
+        let Ok(text) = std::fs::read_to_string(path) else { return };
+        let patterns = Patterns::parse(&text);
+        assert_eq!((patterns.left_min, patterns.right_min), (2, 3));
+        for word in ["wel-come", "walk-ing", "ex-ten-sive", "Prob-a-bil-ity", "rec-og-nize", "hy-phen-ation"] {
+            assert_eq!(hyphenated(&patterns, &word.replace('-', "")), word);
+        }
+        assert_eq!(hyphenated(&patterns, "hi"), "hi");
+    }
+}
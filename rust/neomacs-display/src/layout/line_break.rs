@@ -0,0 +1,252 @@
+//! Line break opportunities for word wrap (UAX #14).
+//!
+//! With `word-wrap' on, a row may be broken between two characters when
+//! the Unicode line breaking algorithm allows it: after spaces, after
+//! hyphens, and between ideographs, but not before closing brackets and
+//! punctuation or after opening ones.  The CJK kinsoku rules follow from
+//! the classes: closing brackets, `、' and `。' (CL), `！' and `？' (EX),
+//! and small kana, the prolonged sound mark and iteration marks (NS,
+//! as in strict kinsoku) never start a row, and opening brackets (OP)
+//! never end one.
+//!
+//! Classes come from a table of the ranges that matter in practice
+//! rather than the full Unicode data, and the rules are applied to pairs
+//! of characters only, so the rules that look past a run of spaces
+//! (LB14-LB17) are approximated by their forms without spaces.  Scripts
+//! that need a dictionary to break (Thai, Lao, ...) are treated as
+//! alphabetic and only break at spaces.
+
+/// Line breaking class of a character (a subset of UAX #14).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakClass {
+    /// Mandatory break
+    BK,
+    CR,
+    LF,
+    /// Combining mark
+    CM,
+    /// Space
+    SP,
+    /// Zero width space
+    ZW,
+    /// Zero width joiner
+    ZWJ,
+    /// Word joiner
+    WJ,
+    /// Non-breaking (glue)
+    GL,
+    /// Opening punctuation
+    OP,
+    /// Closing punctuation
+    CL,
+    /// Closing parenthesis
+    CP,
+    /// Quotation
+    QU,
+    /// Exclamation / interrogation
+    EX,
+    /// Infix numeric separator
+    IS,
+    /// Symbols allowing break after
+    SY,
+    /// Nonstarter
+    NS,
+    /// Prefix numeric
+    PR,
+    /// Postfix numeric
+    PO,
+    /// Numeric
+    NU,
+    /// Hyphen
+    HY,
+    /// Break after
+    BA,
+    /// Break before
+    BB,
+    /// Break on either side, but not pair
+    B2,
+    /// Inseparable
+    IN,
+    /// Contingent break (objects)
+    CB,
+    /// Ideographic
+    ID,
+    /// Hangul L, V and T jamo and LV and LVT syllables
+    JL,
+    JV,
+    JT,
+    H2,
+    H3,
+    /// Regional indicator
+    RI,
+    /// Emoji modifier
+    EM,
+    /// Alphabetic
+    AL,
+}
+
+/// Line breaking class of CH.
+pub fn break_class(ch: char) -> BreakClass {
+    use BreakClass::*;
+    match ch {
+        '\n' => LF,
+        '\r' => CR,
+        '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}' => BK,
+        ' ' => SP,
+        '\t' | '|' | '\u{AD}' | '\u{1680}' | '\u{2000}'..='\u{2006}' | '\u{2008}'..='\u{200A}'
+        | '\u{2010}' | '\u{2012}' | '\u{2013}' | '\u{205F}' | '\u{3000}' => BA,
+        '\u{200B}' => ZW,
+        '\u{200D}' => ZWJ,
+        '\u{2060}' | '\u{FEFF}' => WJ,
+        '\u{A0}' | '\u{34F}' | '\u{2007}' | '\u{2011}' | '\u{202F}' => GL,
+        '-' => HY,
+        '\u{2014}' | '\u{2E3A}' | '\u{2E3B}' => B2,
+        '\u{B4}' | '\u{2C8}' | '\u{2CC}' | '\u{2DF}' | '\u{1FFD}' => BB,
+        '\u{2024}'..='\u{2026}' | '\u{22EF}' | '\u{FE19}' => IN,
+        '\u{FFFC}' => CB,
+        '(' | '[' | '{' | '\u{A1}' | '\u{BF}' | '\u{2045}' | '\u{207D}' | '\u{208D}'
+        | '\u{3008}' | '\u{300A}' | '\u{300C}' | '\u{300E}' | '\u{3010}' | '\u{3014}'
+        | '\u{3016}' | '\u{3018}' | '\u{301A}' | '\u{301D}' | '\u{FF08}' | '\u{FF3B}'
+        | '\u{FF5B}' | '\u{FF5F}' | '\u{FF62}' => OP,
+        ')' | ']' | '\u{FF09}' | '\u{FF3D}' => CP,
+        '}' | '\u{2046}' | '\u{207E}' | '\u{208E}' | '\u{3001}' | '\u{3002}' | '\u{3009}'
+        | '\u{300B}' | '\u{300D}' | '\u{300F}' | '\u{3011}' | '\u{3015}' | '\u{3017}'
+        | '\u{3019}' | '\u{301B}' | '\u{301E}' | '\u{301F}' | '\u{FE50}' | '\u{FE52}'
+        | '\u{FF0C}' | '\u{FF0E}' | '\u{FF5D}' | '\u{FF60}' | '\u{FF61}' | '\u{FF63}'
+        | '\u{FF64}' => CL,
+        '"' | '\'' | '\u{AB}' | '\u{BB}' | '\u{2018}' | '\u{2019}' | '\u{201B}'..='\u{201F}'
+        | '\u{2039}' | '\u{203A}' => QU,
+        '!' | '?' | '\u{203C}' | '\u{2048}' | '\u{2049}' | '\u{FF01}' | '\u{FF1F}' => EX,
+        ',' | '.' | ':' | ';' | '\u{37E}' | '\u{589}' => IS,
+        '/' => SY,
+        '\u{203D}' | '\u{2047}' | '\u{3005}' | '\u{301C}' | '\u{303B}' | '\u{3041}' | '\u{3043}'
+        | '\u{3045}' | '\u{3047}' | '\u{3049}' | '\u{3063}' | '\u{3083}' | '\u{3085}'
+        | '\u{3087}' | '\u{308E}' | '\u{3095}' | '\u{3096}' | '\u{309B}'..='\u{309E}'
+        | '\u{30A0}' | '\u{30A1}' | '\u{30A3}' | '\u{30A5}' | '\u{30A7}' | '\u{30A9}'
+        | '\u{30C3}' | '\u{30E3}' | '\u{30E5}' | '\u{30E7}' | '\u{30EE}' | '\u{30F5}'
+        | '\u{30F6}' | '\u{30FB}'..='\u{30FE}' | '\u{31F0}'..='\u{31FF}' | '\u{FF1A}'
+        | '\u{FF1B}' | '\u{FF65}' | '\u{FF67}'..='\u{FF70}' | '\u{FF9E}' | '\u{FF9F}' => NS,
+        '$' | '+' | '\\' | '\u{A3}' | '\u{A5}' | '\u{B1}' | '\u{20A0}'..='\u{20CF}' | '\u{2116}'
+        | '\u{FF04}' | '\u{FFE1}' | '\u{FFE5}' | '\u{FFE6}' => PR,
+        '%' | '\u{A2}' | '\u{B0}' | '\u{2030}'..='\u{2037}' | '\u{2103}' | '\u{2109}'
+        | '\u{FF05}' | '\u{FFE0}' => PO,
+        '0'..='9' | '\u{660}'..='\u{669}' | '\u{6F0}'..='\u{6F9}' | '\u{966}'..='\u{96F}' => NU,
+        '\u{0}'..='\u{1F}' | '\u{7F}'..='\u{9F}' | '\u{300}'..='\u{36F}' | '\u{483}'..='\u{489}'
+        | '\u{591}'..='\u{5BD}' | '\u{1AB0}'..='\u{1AFF}' | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}' | '\u{3099}' | '\u{309A}' | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FE20}'..='\u{FE2F}' | '\u{E0100}'..='\u{E01EF}' => CM,
+        '\u{1100}'..='\u{115F}' | '\u{A960}'..='\u{A97C}' => JL,
+        '\u{1160}'..='\u{11A7}' | '\u{D7B0}'..='\u{D7C6}' => JV,
+        '\u{11A8}'..='\u{11FF}' | '\u{D7CB}'..='\u{D7FB}' => JT,
+        '\u{AC00}'..='\u{D7A3}' => {
+            if (ch as u32 - 0xAC00).is_multiple_of(28) { H2 } else { H3 }
+        }
+        '\u{1F1E6}'..='\u{1F1FF}' => RI,
+        '\u{1F3FB}'..='\u{1F3FF}' => EM,
+        '\u{2E80}'..='\u{2FFF}' | '\u{3003}'..='\u{303F}' | '\u{3040}'..='\u{30FF}'
+        | '\u{3100}'..='\u{31EF}' | '\u{3200}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}'
+        | '\u{A000}'..='\u{A4CF}' | '\u{F900}'..='\u{FAFF}' | '\u{FE30}'..='\u{FE4F}'
+        | '\u{FF00}'..='\u{FFEF}' | '\u{1F000}'..='\u{1FAFF}' | '\u{20000}'..='\u{3FFFD}' => ID,
+        _ => AL,
+    }
+}
+
+/// Whether a row may break between BEFORE and AFTER, adjacent
+/// characters in that order.
+pub fn break_between(before: char, after: char) -> bool {
+    use BreakClass::*;
+    let a = match break_class(before) {
+        // LB10: a combining mark after nothing it can join acts as a letter
+        CM => AL,
+        c => c,
+    };
+    let b = break_class(after);
+
+    match (a, b) {
+        // LB4, LB5: after hard line breaks (handled by the caller)
+        (BK | CR | LF, _) => true,
+        // LB6, LB7: not before hard breaks or spaces
+        (_, BK | CR | LF | SP | ZW) => false,
+        // LB8: after a zero width space
+        (ZW, _) => true,
+        // LB8a, LB9: inside joiner and combining sequences
+        (ZWJ, _) | (_, CM | ZWJ) => false,
+        // LB11, LB12, LB12a: around word joiners and glue
+        (WJ, _) | (_, WJ) | (GL, _) => false,
+        (SP | BA | HY, GL) => true,
+        (_, GL) => false,
+        // LB13: not before closing punctuation (kinsoku: no row starts
+        // with a closing bracket or `、' `。' `！' `？')
+        (_, CL | CP | EX | IS | SY) => false,
+        // LB14: not after opening punctuation
+        (OP, _) => false,
+        // LB15, LB16, LB17
+        (QU, OP) | (CL | CP, NS) | (B2, B2) => false,
+        // LB18: after spaces
+        (SP, _) => true,
+        // LB19: around quotation marks
+        (_, QU) | (QU, _) => false,
+        // LB20: around contingent breaks
+        (_, CB) | (CB, _) => true,
+        // LB21: not before hyphens, small kana and other nonstarters
+        (_, BA | HY | NS) | (BB, _) => false,
+        // LB22, LB23, LB23a, LB24: inside numbers and their prefixes
+        (_, IN) => false,
+        (AL, NU) | (NU, AL) => false,
+        (PR, ID | EM) | (ID | EM, PO) => false,
+        (PR | PO, AL) | (AL, PR | PO) => false,
+        // LB25
+        (CL | CP | NU, PO | PR) | (PO | PR, OP | NU) | (HY | IS | NU | SY, NU) => false,
+        // LB26, LB27: Korean syllable blocks
+        (JL, JL | JV | H2 | H3) | (JV | H2, JV | JT) | (JT | H3, JT) => false,
+        (JL | JV | JT | H2 | H3, PO) | (PR, JL | JV | JT | H2 | H3) => false,
+        // LB28, LB29: inside words
+        (AL, AL) | (IS, AL) => false,
+        // LB30: between letters and brackets
+        (AL | NU, OP) | (CP, AL | NU) => false,
+        // LB30a, LB30b: flag pairs and emoji modifiers
+        (RI, RI) | (ID, EM) => false,
+        // LB31: everywhere else
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Indices of the characters of TEXT a row may start at.
+    fn breaks(text: &str) -> Vec<usize> {
+        let chars: Vec<char> = text.chars().collect();
+        (1..chars.len()).filter(|&i| break_between(chars[i - 1], chars[i])).collect()
+    }
+
+    #[test]
+    fn latin_breaks_after_spaces_and_hyphens() {
+        assert_eq!(breaks("ab cd"), vec![3]);
+        assert_eq!(breaks("ab   cd"), vec![5]);
+        assert_eq!(breaks("well-known"), vec![5]);
+        // Not before punctuation, closing brackets or after opening ones
+        assert_eq!(breaks("(a b) c."), vec![3, 6]);
+        assert_eq!(breaks("wait !"), Vec::<usize>::new());
+        assert_eq!(breaks("$10.50 or 5%"), vec![7, 10]);
+        // Combining marks stay with their base
+        assert_eq!(breaks("e\u{301}e\u{301}"), Vec::<usize>::new());
+        assert_eq!(breaks("a\u{A0}b c"), vec![4]);
+    }
+
+    #[test]
+    fn cjk_breaks_between_ideographs_with_kinsoku() {
+        // 日本語 breaks anywhere
+        assert_eq!(breaks("日本語"), vec![1, 2]);
+        // Not before 。、 or 」, nor after 「
+        assert_eq!(breaks("「日本」です。"), vec![2, 4, 5]);
+        assert_eq!(breaks("日、本"), vec![2]);
+        // Not before small kana or the prolonged sound mark
+        assert_eq!(breaks("ちょっとコーヒー"), vec![3, 4, 6]);
+        // Korean breaks between syllable blocks
+        assert_eq!(breaks("한국어"), vec![1, 2]);
+        // Latin words inside CJK text stay whole
+        assert_eq!(breaks("日本abc語"), vec![1, 2, 5]);
+    }
+}
@@ -0,0 +1,115 @@
+//! Hanging indents for wrapped lines.
+//!
+//! With `neomacs-hanging-indent' set in a buffer, continuation rows of
+//! a line without a `wrap-prefix' are indented to where the text of the
+//! line starts: past its leading whitespace and, for list items, past
+//! the bullet or number, so wrapped items of a list line up under their
+//! first word.  Explicit `line-prefix' and `wrap-prefix' strings and
+//! space specs are resolved on the C side of the FFI.
+
+/// Kind of prefix the FFI reports for a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinePrefixKind {
+    None,
+    /// A string, drawn at the start of the row
+    String,
+    /// Blank space of a given pixel width
+    Space,
+    /// A hanging indent computed from the start of the line
+    Hanging,
+}
+
+impl LinePrefixKind {
+    /// Kind for FFI value V (0 = none, 1 = string, 2 = space,
+    /// 3 = hanging indent)
+    pub fn from_ffi(v: i32) -> Self {
+        match v {
+            1 => Self::String,
+            2 => Self::Space,
+            3 => Self::Hanging,
+            _ => Self::None,
+        }
+    }
+}
+
+/// Bullets that start a list item when followed by a space.
+const BULLETS: &[char] = &['-', '*', '+', '•', '◦', '‣', '–', '—', '>'];
+
+/// Length in characters of the list marker at the start of TEXT,
+/// including the spaces after it: a bullet, a number or single letter
+/// followed by `.' or `)', and a checkbox like `[ ]' after either.
+/// 0 if TEXT does not start with one.
+fn list_marker_len(text: &[char]) -> usize {
+    let mut i = match text.first() {
+        Some(c) if BULLETS.contains(c) => 1,
+        Some(c) if c.is_ascii_digit() => {
+            let digits = text.iter().take_while(|c| c.is_ascii_digit()).count();
+            if digits > 9 {
+                return 0;
+            }
+            digits
+        }
+        Some(c) if c.is_alphabetic() => 1,
+        _ => return 0,
+    };
+    if !BULLETS.contains(&text[0]) {
+        if !matches!(text.get(i), Some('.' | ')')) {
+            return 0;
+        }
+        i += 1;
+    }
+    if text.get(i) != Some(&' ') {
+        return 0;
+    }
+    let spaces = |from: usize| text[from..].iter().take_while(|&&c| c == ' ').count();
+    i += spaces(i);
+    if text.get(i) == Some(&'[') && text.get(i + 2) == Some(&']') && text.get(i + 3) == Some(&' ') {
+        i += 3;
+        i += spaces(i);
+    }
+    i
+}
+
+/// Columns to indent continuation rows of the line starting with LINE
+/// by: its leading whitespace (tabs to multiples of TAB_WIDTH), plus
+/// its list marker, plus EXTRA.
+pub fn hanging_indent_columns(line: &str, tab_width: usize, extra: usize) -> usize {
+    let chars: Vec<char> = line.chars().take_while(|&c| c != '\n').collect();
+    let mut col = 0;
+    let mut i = 0;
+    while let Some(&c) = chars.get(i) {
+        match c {
+            ' ' => col += 1,
+            '\t' => col = (col / tab_width.max(1) + 1) * tab_width.max(1),
+            _ => break,
+        }
+        i += 1;
+    }
+    col + list_marker_len(&chars[i..]) + extra
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indents_past_whitespace_and_list_markers() {
+        assert_eq!(hanging_indent_columns("plain text", 8, 0), 0);
+        assert_eq!(hanging_indent_columns("    indented", 8, 0), 4);
+        assert_eq!(hanging_indent_columns("\t  tabbed", 8, 0), 10);
+        assert_eq!(hanging_indent_columns("- item", 8, 0), 2);
+        assert_eq!(hanging_indent_columns("  *   item", 8, 2), 8);
+        assert_eq!(hanging_indent_columns("12. item", 8, 0), 4);
+        assert_eq!(hanging_indent_columns("b) item", 8, 0), 3);
+        assert_eq!(hanging_indent_columns("- [X] done\nnext", 8, 0), 6);
+        assert_eq!(hanging_indent_columns("• item", 8, 0), 2);
+    }
+
+    #[test]
+    fn words_are_not_list_markers() {
+        assert_eq!(hanging_indent_columns("-dash", 8, 0), 0);
+        assert_eq!(hanging_indent_columns("word. more", 8, 0), 0);
+        assert_eq!(hanging_indent_columns("3.14 is pi", 8, 0), 0);
+        assert_eq!(hanging_indent_columns("  ", 8, 0), 2);
+    }
+}
@@ -0,0 +1,214 @@
+//! Link spans: buffer ranges drawn as clickable links.
+//!
+//! A link covers a range of a buffer and carries a target string (a URL,
+//! a symbol to describe, a `file:line` location...) that Lisp dispatches
+//! on when the link is followed.  Help buffers, Org links and compilation
+//! results all register their links here, so they look and behave alike.
+//!
+//! Ranges live in a per-buffer `ItreeTree`, like annotations, so they
+//! move with the text as the buffer is edited (see `LinkStore::adjust`).
+//! After a window is laid out, the characters of its visible links are
+//! recolored and underlined; mouse motion and clicks look links up by
+//! position with `LinkStore::at`.
+
+use std::collections::HashMap;
+
+use crate::core::itree::{ItreeOrder, ItreeTree, NodeId};
+
+/// A link over a buffer range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkSpan {
+    /// What following the link opens; interpreted by Lisp.
+    pub target: String,
+    /// Text and underline color (sRGB pixel).
+    pub color: u32,
+}
+
+/// Links of one buffer.
+#[derive(Default)]
+struct BufferLinks {
+    tree: ItreeTree,
+    entries: HashMap<u32, (NodeId, LinkSpan)>,
+}
+
+impl BufferLinks {
+    fn nodes_in(&mut self, begin: i64, end: i64) -> Vec<NodeId> {
+        let mut found = Vec::new();
+        let mut iter = self.tree.iterator_start(begin, end, ItreeOrder::Ascending);
+        while let Some(node) = self.tree.iterator_next(&mut iter) {
+            found.push(node);
+        }
+        found
+    }
+}
+
+/// All links, keyed by buffer (same id as `WindowParams::buffer_id`).
+#[derive(Default)]
+pub struct LinkStore {
+    buffers: HashMap<u64, BufferLinks>,
+    owners: HashMap<u32, u64>,
+    next_id: u32,
+}
+
+impl LinkStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether BUFFER_ID has any links.
+    pub fn has_buffer(&self, buffer_id: u64) -> bool {
+        self.buffers.get(&buffer_id).is_some_and(|b| !b.entries.is_empty())
+    }
+
+    /// Make [START, END) of BUFFER_ID a link to TARGET.  Returns its id
+    /// (never 0), or 0 if the range is empty.
+    pub fn add(&mut self, buffer_id: u64, start: i64, end: i64, target: String, color: u32) -> u32 {
+        if end <= start {
+            return 0;
+        }
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+        let id = self.next_id;
+        let buf = self.buffers.entry(buffer_id).or_default();
+        // Text typed at either edge stays outside the link
+        let node = buf.tree.alloc_node(true, false, id as u64);
+        buf.tree.insert(node, start, end);
+        buf.entries.insert(id, (node, LinkSpan { target, color }));
+        self.owners.insert(id, buffer_id);
+        id
+    }
+
+    /// Remove link ID.  Returns false if it does not exist.
+    pub fn remove(&mut self, id: u32) -> bool {
+        let Some(buffer_id) = self.owners.remove(&id) else {
+            return false;
+        };
+        let Some(buf) = self.buffers.get_mut(&buffer_id) else {
+            return false;
+        };
+        if let Some((node, _)) = buf.entries.remove(&id) {
+            buf.tree.remove(node);
+            buf.tree.free_node(node);
+        }
+        if buf.entries.is_empty() {
+            self.buffers.remove(&buffer_id);
+        }
+        true
+    }
+
+    /// Remove all links of BUFFER_ID.
+    pub fn clear_buffer(&mut self, buffer_id: u64) {
+        if let Some(buf) = self.buffers.remove(&buffer_id) {
+            for id in buf.entries.keys() {
+                self.owners.remove(id);
+            }
+        }
+    }
+
+    /// Move links of BUFFER_ID after an edit at POS that deleted DELETED
+    /// characters and inserted INSERTED characters.  Links whose text
+    /// was deleted entirely are removed.
+    pub fn adjust(&mut self, buffer_id: u64, pos: i64, inserted: i64, deleted: i64) {
+        let Some(buf) = self.buffers.get_mut(&buffer_id) else {
+            return;
+        };
+        let mut dead = Vec::new();
+        if deleted > 0 {
+            buf.tree.delete_gap(pos, deleted);
+            for node in buf.nodes_in(pos, pos + 1) {
+                if buf.tree.node_end(node) <= buf.tree.node_begin(node) {
+                    dead.push(buf.tree.node(node).data as u32);
+                }
+            }
+        }
+        if inserted > 0 {
+            buf.tree.insert_gap(pos, inserted, false);
+        }
+        for id in dead {
+            self.remove(id);
+        }
+    }
+
+    /// Links of BUFFER_ID overlapping [BEGIN, END), ordered by start, as
+    /// (start, end, id, link).
+    pub fn in_range(&mut self, buffer_id: u64, begin: i64, end: i64) -> Vec<(i64, i64, u32, &LinkSpan)> {
+        let Some(buf) = self.buffers.get_mut(&buffer_id) else {
+            return Vec::new();
+        };
+        let mut ranges = Vec::new();
+        for node in buf.nodes_in(begin, end) {
+            let start = buf.tree.node_begin(node);
+            let stop = buf.tree.node_end(node);
+            ranges.push((start, stop, buf.tree.node(node).data as u32));
+        }
+        let mut result: Vec<(i64, i64, u32, &LinkSpan)> = ranges
+            .into_iter()
+            .map(|(start, stop, id)| (start, stop, id, &buf.entries[&id].1))
+            .collect();
+        result.sort_by_key(|&(start, _, id, _)| (start, id));
+        result
+    }
+
+    /// The link of BUFFER_ID covering the character at POS, as (id, link).
+    /// The innermost (latest starting) link wins when links nest.
+    pub fn at(&mut self, buffer_id: u64, pos: i64) -> Option<(u32, &LinkSpan)> {
+        self.in_range(buffer_id, pos, pos + 1)
+            .into_iter()
+            .rfind(|&(start, end, _, _)| start <= pos && pos < end)
+            .map(|(_, _, id, link)| (id, link))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(store: &mut LinkStore, buffer_id: u64, start: i64, end: i64, target: &str) -> u32 {
+        store.add(buffer_id, start, end, target.to_string(), 0x3366cc)
+    }
+
+    #[test]
+    fn lookup_by_position_and_range() {
+        let mut links = LinkStore::new();
+        let a = link(&mut links, 1, 10, 20, "https://gnu.org");
+        let b = link(&mut links, 1, 14, 16, "help:car");
+        link(&mut links, 2, 10, 20, "file:main.rs:3");
+        assert_eq!(link(&mut links, 1, 5, 5, "empty"), 0);
+
+        assert_eq!(links.at(1, 9), None);
+        assert_eq!(links.at(1, 10).unwrap().0, a);
+        assert_eq!(links.at(1, 15).unwrap().1.target, "help:car");
+        assert_eq!(links.at(1, 16).unwrap().0, a);
+        assert_eq!(links.at(1, 20), None);
+
+        let visible: Vec<u32> = links.in_range(1, 0, 15).iter().map(|l| l.2).collect();
+        assert_eq!(visible, vec![a, b]);
+
+        assert!(links.remove(b));
+        assert!(!links.remove(b));
+        links.clear_buffer(1);
+        assert!(!links.has_buffer(1));
+        assert!(links.has_buffer(2));
+    }
+
+    #[test]
+    fn links_follow_edits() {
+        let mut links = LinkStore::new();
+        let a = link(&mut links, 1, 10, 20, "https://gnu.org");
+        let b = link(&mut links, 1, 30, 35, "help:car");
+
+        // Typing at the start of the link stays outside it
+        links.adjust(1, 10, 3, 0);
+        let ranges: Vec<(i64, i64)> = links.in_range(1, 0, 100).iter().map(|l| (l.0, l.1)).collect();
+        assert_eq!(ranges, vec![(13, 23), (33, 38)]);
+
+        // Typing at its end does too
+        links.adjust(1, 23, 2, 0);
+        assert_eq!(links.at(1, 23), None);
+
+        // Deleting all of a link's text removes it
+        links.adjust(1, 30, 0, 10);
+        assert!(links.at(1, 30).is_none());
+        assert!(!links.remove(b));
+        assert_eq!(links.at(1, 15).unwrap().0, a);
+    }
+}
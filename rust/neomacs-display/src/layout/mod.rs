@@ -18,12 +18,42 @@ pub mod types;
 pub mod engine;
 pub mod emacs_ffi;
 pub mod emacs_types;
+pub mod buffer_snapshot;
+pub mod frame_desc;
+pub mod host;
+pub mod headless;
 pub mod unicode;
 pub mod hit_test;
 pub mod status_line;
 pub mod bidi_layout;
 pub mod font_metrics;
+pub mod print;
+pub mod pdf;
+pub mod accessibility;
+pub mod ghost_text;
+pub mod annotations;
+pub mod scroll_bar_marks;
+pub mod rectangle;
+pub mod virtual_space;
+pub mod table;
+pub mod command_blocks;
+pub mod region_pulse;
+pub mod link_spans;
+pub mod color_swatches;
+pub mod breadcrumb_bar;
+pub mod scroll_anchor;
+pub mod paragraph_align;
+pub mod line_prefix;
+pub mod line_break;
+pub mod hyphenation;
+pub mod vertical;
+pub mod hscroll;
+pub mod chat_view;
+pub mod selection;
 
 pub use types::*;
 pub use engine::*;
-pub use hit_test::{hit_test_charpos_at_pixel, hit_test_window_charpos};
+pub use hit_test::{
+    hit_test_char_pixel, hit_test_charpos_at_pixel, hit_test_has_window, hit_test_window_charpos,
+    CharPixel,
+};
@@ -0,0 +1,125 @@
+//! Paragraph alignment: right, centered and fully justified text.
+//!
+//! A line whose text has the `paragraph-align' property (as a text or
+//! overlay property at its beginning) is laid out left to right as
+//! usual; then each of its rows is moved to the right edge or the
+//! center of the text area, or, for `full', its inter-word spaces are
+//! widened so the row reaches the right edge.  The last row of a fully
+//! justified line stays left-aligned, as in typeset paragraphs.  Spaces
+//! trailing a row (where word wrap broke it) do not count as its text.
+//! No padding is inserted into the buffer.
+
+/// How the rows of a line are aligned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParagraphAlign {
+    Left,
+    Right,
+    Center,
+    Full,
+}
+
+impl ParagraphAlign {
+    /// Alignment for FFI value V (0 = left, 1 = right, 2 = center,
+    /// 3 = full)
+    pub fn from_ffi(v: i32) -> Self {
+        match v {
+            1 => Self::Right,
+            2 => Self::Center,
+            3 => Self::Full,
+            _ => Self::Left,
+        }
+    }
+}
+
+/// A glyph of a row, for alignment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RowItem {
+    pub x: f32,
+    pub width: f32,
+    /// Whether it is a space, where full justification adds room.
+    pub space: bool,
+}
+
+/// How far to move each of ITEMS (a row's glyphs, left to right) to
+/// align the row with ALIGN in the text area [LEFT, LEFT + WIDTH).
+/// LAST_ROW says the row ends its line.  Shifts are whole pixels.
+pub fn row_shifts(
+    items: &[RowItem],
+    left: f32,
+    width: f32,
+    align: ParagraphAlign,
+    last_row: bool,
+) -> Vec<f32> {
+    let mut shifts = vec![0.0; items.len()];
+    let Some(last_ink) = items.iter().rposition(|i| !i.space) else {
+        return shifts;
+    };
+    let slack = (left + width - (items[last_ink].x + items[last_ink].width)).floor();
+    if slack <= 0.0 {
+        return shifts;
+    }
+    match align {
+        ParagraphAlign::Left => {}
+        ParagraphAlign::Right => shifts.fill(slack),
+        ParagraphAlign::Center => shifts.fill((slack / 2.0).floor()),
+        ParagraphAlign::Full => {
+            if last_row {
+                return shifts;
+            }
+            let first_ink = items.iter().position(|i| !i.space).unwrap_or(0);
+            // Gaps between words: runs of spaces between the first and
+            // last word
+            let gaps = (first_ink + 1..=last_ink)
+                .filter(|&i| !items[i].space && items[i - 1].space)
+                .count();
+            if gaps == 0 {
+                return shifts;
+            }
+            let mut gap = 0;
+            for i in first_ink + 1..items.len() {
+                if i <= last_ink && !items[i].space && items[i - 1].space {
+                    gap += 1;
+                }
+                let shift = if i > last_ink { slack } else { slack * gap as f32 / gaps as f32 };
+                shifts[i] = shift.floor();
+            }
+        }
+    }
+    shifts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Items for TEXT in 10 pixel columns from X 0.
+    fn row(text: &str) -> Vec<RowItem> {
+        text.chars()
+            .enumerate()
+            .map(|(i, c)| RowItem { x: i as f32 * 10.0, width: 10.0, space: c == ' ' })
+            .collect()
+    }
+
+    #[test]
+    fn right_and_center_move_the_whole_row() {
+        // "ab cd " in 100 pixels: the text ends at 50
+        let items = row("ab cd ");
+        assert_eq!(row_shifts(&items, 0.0, 100.0, ParagraphAlign::Right, true), vec![50.0; 6]);
+        assert_eq!(row_shifts(&items, 0.0, 100.0, ParagraphAlign::Center, true), vec![25.0; 6]);
+        assert_eq!(row_shifts(&items, 0.0, 100.0, ParagraphAlign::Left, true), vec![0.0; 6]);
+        // Full rows and blank rows stay
+        assert_eq!(row_shifts(&row("abcde"), 0.0, 50.0, ParagraphAlign::Right, true), vec![0.0; 5]);
+        assert_eq!(row_shifts(&row("   "), 0.0, 50.0, ParagraphAlign::Center, true), vec![0.0; 3]);
+    }
+
+    #[test]
+    fn full_spreads_words_to_the_right_edge() {
+        // " a b  c " in 110 pixels: slack 40 over two gaps
+        let items = row(" a b  c ");
+        let shifts = row_shifts(&items, 0.0, 110.0, ParagraphAlign::Full, false);
+        assert_eq!(shifts, vec![0.0, 0.0, 0.0, 20.0, 20.0, 20.0, 40.0, 40.0]);
+        // The last row of the line is left alone, as is a single word
+        assert_eq!(row_shifts(&items, 0.0, 110.0, ParagraphAlign::Full, true), vec![0.0; 8]);
+        assert_eq!(row_shifts(&row("abc "), 0.0, 110.0, ParagraphAlign::Full, false), vec![0.0; 4]);
+    }
+}
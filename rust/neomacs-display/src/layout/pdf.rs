@@ -0,0 +1,672 @@
+//! PDF output for printing: an object writer and embedded fonts.
+//!
+//! Text is drawn with the fonts layout shaped it with, embedded as CID
+//! fonts with Identity-H encoding, so any glyph a font has can be
+//! printed and each character code is a glyph of the font rather than
+//! a byte of some 8-bit encoding.  TrueType fonts are subset to the
+//! glyphs used, keeping glyph indices so codes stay glyph indices.
+//! CFF-flavored OpenType fonts are embedded whole; for CID-keyed ones
+//! the code of a glyph is its CID from the CFF charset.  A ToUnicode
+//! map makes the text searchable and copyable.  Bitmap-only fonts
+//! (color emoji) cannot be embedded and their glyphs are not drawn.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::sync::Arc;
+
+use cosmic_text::fontdb;
+use cosmic_text::ttf_parser::{self, Tag};
+use cosmic_text::{Font, FontSystem};
+
+/// A PDF file being written: numbered objects, then the cross-reference
+/// table pointing at them.
+#[derive(Default)]
+pub struct PdfWriter {
+    /// Body of object N at index N - 1
+    objects: Vec<Vec<u8>>,
+}
+
+impl PdfWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of a new object, to be written later with `set`.
+    pub fn reserve(&mut self) -> usize {
+        self.objects.push(Vec::new());
+        self.objects.len()
+    }
+
+    /// Write the body of object ID.
+    pub fn set(&mut self, id: usize, body: impl Into<Vec<u8>>) {
+        self.objects[id - 1] = body.into();
+    }
+
+    /// Add an object with BODY and return its number.
+    pub fn add(&mut self, body: impl Into<Vec<u8>>) -> usize {
+        self.objects.push(body.into());
+        self.objects.len()
+    }
+
+    /// Add a Flate-compressed stream of DATA, with DICT's entries in its
+    /// dictionary, and return its number.
+    pub fn add_stream(&mut self, dict: &str, data: &[u8]) -> usize {
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(data, 6);
+        let mut body =
+            format!("<< {dict} /Filter /FlateDecode /Length {} >>\nstream\n", compressed.len()).into_bytes();
+        body.extend_from_slice(&compressed);
+        body.extend_from_slice(b"\nendstream");
+        self.add(body)
+    }
+
+    /// The file, with ROOT as its catalog and INFO as its document
+    /// information dictionary.
+    pub fn finish(self, root: usize, info: usize) -> Vec<u8> {
+        let mut out = b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(self.objects.len());
+        for (i, body) in self.objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {root} 0 R /Info {info} 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            self.objects.len() + 1
+        );
+        out.extend_from_slice(table.as_bytes());
+        out
+    }
+}
+
+/// TEXT as a PDF text string: UTF-16BE with a byte order mark, in hex.
+pub fn text_string(text: &str) -> String {
+    let mut out = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        let _ = write!(out, "{unit:04X}");
+    }
+    out.push('>');
+    out
+}
+
+// ============================================================================
+// Fonts
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Outlines {
+    /// `glyf` outlines: subset, codes are glyph indices
+    TrueType,
+    /// CFF outlines: embedded whole; codes are CIDs, given per glyph
+    /// index for CID-keyed fonts and equal to glyph indices otherwise
+    Cff { cids: Option<Vec<u16>> },
+}
+
+/// A font some printed glyphs come from.
+struct EmbeddedFont {
+    font: Arc<Font>,
+    /// Index of the face in a font collection
+    index: u32,
+    /// PostScript name
+    name: String,
+    outlines: Outlines,
+    /// Glyphs used, by code, with their glyph index and the text they
+    /// stand for
+    glyphs: BTreeMap<u16, (u16, String)>,
+}
+
+/// The fonts of a document, numbered in the order first used; font N
+/// is the page resource `/FN`.
+#[derive(Default)]
+pub struct FontSet {
+    fonts: Vec<EmbeddedFont>,
+    /// Font number of each font seen, None if it cannot be embedded
+    by_id: HashMap<fontdb::ID, Option<usize>>,
+}
+
+impl FontSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Font number and character code to draw glyph GLYPH_ID of font ID,
+    /// which stands for TEXT (empty for the later glyphs of a cluster).
+    /// None if the font cannot be embedded.
+    pub fn glyph(&mut self, fonts: &mut FontSystem, id: fontdb::ID, glyph_id: u16, text: &str) -> Option<(usize, u16)> {
+        let number = match self.by_id.get(&id) {
+            Some(number) => (*number)?,
+            None => {
+                let font = Self::load(fonts, id);
+                if font.is_none() {
+                    log::warn!("print: font {:?} has no outlines to embed", id);
+                }
+                let number = font.map(|font| {
+                    self.fonts.push(font);
+                    self.fonts.len() - 1
+                });
+                self.by_id.insert(id, number);
+                number?
+            }
+        };
+        let font = &mut self.fonts[number];
+        let code = match &font.outlines {
+            Outlines::Cff { cids: Some(cids) } => cids.get(glyph_id as usize).copied().unwrap_or(0),
+            _ => glyph_id,
+        };
+        let entry = font.glyphs.entry(code).or_insert_with(|| (glyph_id, String::new()));
+        if entry.1.is_empty() {
+            entry.1.push_str(text);
+        }
+        Some((number, code))
+    }
+
+    fn load(fonts: &mut FontSystem, id: fontdb::ID) -> Option<EmbeddedFont> {
+        let (index, name) = fonts.db().face(id).map(|face| (face.index, face.post_script_name.clone()))?;
+        let font = fonts.get_font(id)?;
+        let raw = ttf_parser::RawFace::parse(font.data(), index).ok()?;
+        let outlines = if raw.table(Tag::from_bytes(b"glyf")).is_some() {
+            Outlines::TrueType
+        } else {
+            let cff = raw.table(Tag::from_bytes(b"CFF "))?;
+            let glyphs = ttf_parser::Face::parse(font.data(), index).ok()?.number_of_glyphs();
+            Outlines::Cff { cids: cff_cids(cff, glyphs) }
+        };
+        Some(EmbeddedFont { font, index, name, outlines, glyphs: BTreeMap::new() })
+    }
+
+    /// Write the fonts to PDF and return the object of each, by number.
+    pub fn write(&self, pdf: &mut PdfWriter) -> Vec<usize> {
+        self.fonts.iter().map(|font| font.write(pdf).unwrap_or_else(|| pdf.add("null"))).collect()
+    }
+}
+
+impl EmbeddedFont {
+    fn write(&self, pdf: &mut PdfWriter) -> Option<usize> {
+        let data = self.font.data();
+        let face = ttf_parser::Face::parse(data, self.index).ok()?;
+        let raw = face.raw_face();
+        let units = 1000.0 / face.units_per_em() as f32;
+        let scale = |v: i16| (v as f32 * units).round() as i32;
+
+        let name = pdf_name(&self.name);
+        let (base_font, subtype, file) = match &self.outlines {
+            Outlines::TrueType => {
+                let glyphs: Vec<u16> = self.glyphs.values().map(|&(gid, _)| gid).collect();
+                let program = subset_truetype(raw, &glyphs)?;
+                let file = pdf.add_stream(&format!("/Length1 {}", program.len()), &program);
+                (format!("{}+{name}", subset_tag(&name, &glyphs)), "/CIDFontType2", format!("/FontFile2 {file} 0 R"))
+            }
+            Outlines::Cff { .. } => {
+                let program = sfnt(raw.table_records.into_iter().filter_map(|record| {
+                    Some((record.tag, raw.table(record.tag)?.to_vec()))
+                }).collect());
+                let file = pdf.add_stream("/Subtype /OpenType", &program);
+                (name, "/CIDFontType0", format!("/FontFile3 {file} 0 R"))
+            }
+        };
+
+        let bbox = face.global_bounding_box();
+        let mut flags = 4; // symbolic: glyphs outside the standard Latin set
+        if face.is_monospaced() {
+            flags |= 1;
+        }
+        if face.is_italic() {
+            flags |= 64;
+        }
+        let descriptor = pdf.add(format!(
+            "<< /Type /FontDescriptor /FontName /{base_font} /Flags {flags} /FontBBox [{} {} {} {}] \
+             /ItalicAngle {} /Ascent {} /Descent {} /CapHeight {} /StemV 80 {file} >>",
+            scale(bbox.x_min),
+            scale(bbox.y_min),
+            scale(bbox.x_max),
+            scale(bbox.y_max),
+            face.italic_angle().unwrap_or(0.0),
+            scale(face.ascender()),
+            scale(face.descender()),
+            scale(face.capital_height().unwrap_or(face.ascender())),
+        ));
+
+        // Widths, in runs of consecutive codes
+        let mut widths = String::new();
+        let mut next = None;
+        for (&code, &(gid, _)) in &self.glyphs {
+            let advance = face.glyph_hor_advance(ttf_parser::GlyphId(gid)).unwrap_or(0);
+            if next != Some(code) {
+                if next.is_some() {
+                    widths.push_str("] ");
+                }
+                let _ = write!(widths, "{code} [");
+            } else {
+                widths.push(' ');
+            }
+            let _ = write!(widths, "{}", (advance as f32 * units).round() as i32);
+            next = code.checked_add(1);
+        }
+        if !self.glyphs.is_empty() {
+            widths.push(']');
+        }
+
+        let gid_map = if subtype == "/CIDFontType2" { " /CIDToGIDMap /Identity" } else { "" };
+        let cid_font = pdf.add(format!(
+            "<< /Type /Font /Subtype {subtype} /BaseFont /{base_font} \
+             /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+             /FontDescriptor {descriptor} 0 R /DW 1000 /W [{widths}]{gid_map} >>"
+        ));
+        let to_unicode = pdf.add_stream("", to_unicode_cmap(&self.glyphs).as_bytes());
+        Some(pdf.add(format!(
+            "<< /Type /Font /Subtype /Type0 /BaseFont /{base_font} /Encoding /Identity-H \
+             /DescendantFonts [{cid_font} 0 R] /ToUnicode {to_unicode} 0 R >>"
+        )))
+    }
+}
+
+/// NAME with the characters a PDF name cannot hold unescaped dropped.
+fn pdf_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(|&c| c.is_ascii_graphic() && !"()<>[]{}/%#".contains(c))
+        .collect();
+    if name.is_empty() { "Font".to_string() } else { name }
+}
+
+/// The six capital letters tagging a subset of font NAME with GLYPHS.
+fn subset_tag(name: &str, glyphs: &[u16]) -> String {
+    // FNV-1a over the name and glyph set
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let bytes = name.bytes().chain(glyphs.iter().flat_map(|g| g.to_be_bytes()));
+    for byte in bytes {
+        hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+    }
+    (0..6).map(|i| (b'A' + ((hash >> (i * 8)) % 26) as u8) as char).collect()
+}
+
+/// ToUnicode CMap mapping the codes of GLYPHS to the text they stand for.
+fn to_unicode_cmap(glyphs: &BTreeMap<u16, (u16, String)>) -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let mapped: Vec<(u16, &str)> = glyphs
+        .iter()
+        .filter(|(_, (_, text))| !text.is_empty())
+        .map(|(&code, (_, text))| (code, text.as_str()))
+        .collect();
+    // At most 100 mappings per block
+    for block in mapped.chunks(100) {
+        let _ = writeln!(cmap, "{} beginbfchar", block.len());
+        for &(code, text) in block {
+            let units: String = text.encode_utf16().map(|u| format!("{u:04X}")).collect();
+            let _ = writeln!(cmap, "<{code:04X}> <{units}>");
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+// ============================================================================
+// Font programs
+// ============================================================================
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn table_checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// An sfnt font file of TABLES, with its directory and checksums.
+fn sfnt(mut tables: Vec<(Tag, Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| tag.0);
+    // head's checksum is taken with checkSumAdjustment zeroed
+    for (tag, data) in &mut tables {
+        if *tag == Tag::from_bytes(b"head") && data.len() >= 12 {
+            data[8..12].fill(0);
+        }
+    }
+    let count = tables.len() as u16;
+    let power = if count == 0 { 0 } else { 15 - count.leading_zeros() as u16 };
+    let search_range = (1u16 << power) * 16;
+    let mut out = Vec::new();
+    let version: u32 = if tables.iter().any(|(tag, _)| *tag == Tag::from_bytes(b"CFF ")) {
+        u32::from_be_bytes(*b"OTTO")
+    } else {
+        0x00010000
+    };
+    out.extend_from_slice(&version.to_be_bytes());
+    for field in [count, search_range, power, count * 16 - search_range] {
+        out.extend_from_slice(&field.to_be_bytes());
+    }
+    let mut offset = 12 + 16 * tables.len();
+    let mut head_at = None;
+    for (tag, data) in &tables {
+        if *tag == Tag::from_bytes(b"head") {
+            head_at = Some(offset);
+        }
+        out.extend_from_slice(&tag.0.to_be_bytes());
+        out.extend_from_slice(&table_checksum(data).to_be_bytes());
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    for (_, data) in &tables {
+        out.extend_from_slice(data);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+    // head.checkSumAdjustment makes the whole file sum to a constant
+    if let Some(head) = head_at.filter(|&at| at + 12 <= out.len()) {
+        let adjustment = 0xB1B0AFBAu32.wrapping_sub(table_checksum(&out));
+        out[head + 8..head + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    out
+}
+
+/// The TrueType outlines of FACE reduced to GLYPHS, the glyphs of their
+/// composites and glyph 0, keeping glyph indices.  Only the tables a
+/// PDF reader needs are kept.
+fn subset_truetype(face: &ttf_parser::RawFace, glyphs: &[u16]) -> Option<Vec<u8>> {
+    let table = |tag: &[u8; 4]| face.table(Tag::from_bytes(tag));
+    let head = table(b"head")?;
+    let maxp = table(b"maxp")?;
+    let loca = table(b"loca")?;
+    let glyf = table(b"glyf")?;
+    let num_glyphs = read_u16(maxp, 4)? as usize;
+    let long_loca = read_u16(head, 50)? != 0;
+    let glyph_range = |gid: usize| -> Option<std::ops::Range<usize>> {
+        let (start, end) = if long_loca {
+            (read_u32(loca, gid * 4)? as usize, read_u32(loca, gid * 4 + 4)? as usize)
+        } else {
+            (read_u16(loca, gid * 2)? as usize * 2, read_u16(loca, gid * 2 + 2)? as usize * 2)
+        };
+        (start <= end && end <= glyf.len()).then_some(start..end)
+    };
+
+    // Keep the glyphs asked for and every component of a composite
+    let mut keep = vec![false; num_glyphs];
+    let mut pending: Vec<u16> = glyphs.iter().copied().chain([0]).collect();
+    while let Some(gid) = pending.pop() {
+        let gid = gid as usize;
+        if gid >= num_glyphs || keep[gid] {
+            continue;
+        }
+        keep[gid] = true;
+        let data = &glyf[glyph_range(gid)?];
+        if data.len() >= 10 && (read_u16(data, 0)? as i16) < 0 {
+            pending.extend(composite_components(&data[10..]));
+        }
+    }
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity((num_glyphs + 1) * 4);
+    for (gid, &kept) in keep.iter().enumerate() {
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        if kept {
+            new_glyf.extend_from_slice(&glyf[glyph_range(gid)?]);
+            new_glyf.resize(new_glyf.len().next_multiple_of(4), 0);
+        }
+    }
+    new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+    let mut new_head = head.to_vec();
+    new_head.get_mut(50..52)?.copy_from_slice(&1u16.to_be_bytes());
+
+    let mut tables = vec![
+        (Tag::from_bytes(b"head"), new_head),
+        (Tag::from_bytes(b"maxp"), maxp.to_vec()),
+        (Tag::from_bytes(b"loca"), new_loca),
+        (Tag::from_bytes(b"glyf"), new_glyf),
+    ];
+    for tag in [b"hhea", b"hmtx", b"cvt ", b"fpgm", b"prep"] {
+        if let Some(data) = table(tag) {
+            tables.push((Tag::from_bytes(tag), data.to_vec()));
+        }
+    }
+    Some(sfnt(tables))
+}
+
+/// Glyph indices of the components of a composite glyph whose
+/// component records are DATA.
+fn composite_components(mut data: &[u8]) -> Vec<u16> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+    let mut components = Vec::new();
+    while let (Some(flags), Some(gid)) = (read_u16(data, 0), read_u16(data, 2)) {
+        components.push(gid);
+        let mut len = 4 + if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            len += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            len += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            len += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 || len > data.len() {
+            break;
+        }
+        data = &data[len..];
+    }
+    components
+}
+
+/// Items of the CFF INDEX at AT in DATA, and the offset after it.
+fn cff_index(data: &[u8], at: usize) -> Option<(Vec<&[u8]>, usize)> {
+    let count = read_u16(data, at)? as usize;
+    if count == 0 {
+        return Some((Vec::new(), at + 2));
+    }
+    let off_size = *data.get(at + 2)? as usize;
+    if !(1..=4).contains(&off_size) {
+        return None;
+    }
+    let offset = |i: usize| -> Option<usize> {
+        let start = at + 3 + i * off_size;
+        let bytes = data.get(start..start + off_size)?;
+        Some(bytes.iter().fold(0usize, |v, &b| (v << 8) | b as usize))
+    };
+    let base = at + 3 + (count + 1) * off_size - 1;
+    let mut items = Vec::with_capacity(count);
+    for i in 0..count {
+        items.push(data.get(base + offset(i)?..base + offset(i + 1)?)?);
+    }
+    Some((items, base + offset(count)?))
+}
+
+/// Operators of a CFF DICT with their integer operands; real operands
+/// read as 0.  Two-byte operators are 1200 + their second byte.
+fn cff_dict(mut data: &[u8]) -> Vec<(u16, Vec<i32>)> {
+    let mut entries = Vec::new();
+    let mut operands = Vec::new();
+    while let Some(&b0) = data.first() {
+        let (value, len) = match b0 {
+            0..=21 => {
+                let (op, len) = if b0 == 12 { (1200 + *data.get(1).unwrap_or(&0) as u16, 2) } else { (b0 as u16, 1) };
+                entries.push((op, std::mem::take(&mut operands)));
+                data = data.get(len..).unwrap_or(&[]);
+                continue;
+            }
+            28 => (read_u16(data, 1).map(|v| v as i16 as i32), 3),
+            29 => (read_u32(data, 1).map(|v| v as i32), 5),
+            30 => {
+                // Real: nibbles up to an 0xf nibble
+                let end = data[1..].iter().position(|&b| b & 0x0f == 0x0f || b >> 4 == 0x0f);
+                (Some(0), end.map_or(data.len(), |e| e + 2))
+            }
+            32..=246 => (Some(b0 as i32 - 139), 1),
+            247..=250 => (data.get(1).map(|&b1| (b0 as i32 - 247) * 256 + b1 as i32 + 108), 2),
+            251..=254 => (data.get(1).map(|&b1| -(b0 as i32 - 251) * 256 - b1 as i32 - 108), 2),
+            _ => (None, 1),
+        };
+        operands.extend(value);
+        data = data.get(len..).unwrap_or(&[]);
+    }
+    entries
+}
+
+/// CID of each glyph of a CID-keyed CFF font with NUM_GLYPHS glyphs,
+/// from its charset; None if the font is not CID-keyed.
+fn cff_cids(cff: &[u8], num_glyphs: u16) -> Option<Vec<u16>> {
+    const ROS: u16 = 1230;
+    const CHARSET: u16 = 15;
+    let header_size = *cff.get(2)? as usize;
+    let (_, after_names) = cff_index(cff, header_size)?;
+    let (top_dicts, _) = cff_index(cff, after_names)?;
+    let top = cff_dict(top_dicts.first()?);
+    top.iter().find(|(op, _)| *op == ROS)?;
+    let charset = top.iter().find(|(op, _)| *op == CHARSET).and_then(|(_, v)| v.first().copied());
+    let num_glyphs = num_glyphs as usize;
+    let mut cids: Vec<u16> = (0..num_glyphs as u16).collect();
+    // Offsets 0-2 are predefined charsets, identity for our purposes
+    let Some(at) = charset.filter(|&c| c > 2).map(|c| c as usize) else {
+        return Some(cids);
+    };
+    let format = *cff.get(at)?;
+    let mut gid = 1;
+    let mut pos = at + 1;
+    while gid < num_glyphs {
+        match format {
+            0 => {
+                cids[gid] = read_u16(cff, pos)?;
+                gid += 1;
+                pos += 2;
+            }
+            1 | 2 => {
+                let first = read_u16(cff, pos)?;
+                let (left, len) = if format == 1 {
+                    (*cff.get(pos + 2)? as u16, 3)
+                } else {
+                    (read_u16(cff, pos + 2)?, 4)
+                };
+                for i in 0..=left {
+                    if gid >= num_glyphs {
+                        break;
+                    }
+                    cids[gid] = first.wrapping_add(i);
+                    gid += 1;
+                }
+                pos += len;
+            }
+            _ => return None,
+        }
+    }
+    Some(cids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEJAVU: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf";
+
+    #[test]
+    fn writer_numbers_objects_and_points_xref_at_them() {
+        let mut pdf = PdfWriter::new();
+        let root = pdf.reserve();
+        let info = pdf.add(format!("<< /Title {} >>", text_string("Ünï 中")));
+        pdf.set(root, "<< /Type /Catalog >>");
+        let stream = pdf.add_stream("/Type /XObject", b"hello");
+        assert_eq!((root, info, stream), (1, 2, 3));
+        let out = pdf.finish(root, info);
+        // The compressed stream is not text: go by bytes
+        let text = String::from_utf8_lossy(&out);
+        assert!(text.starts_with("%PDF-1.7"));
+        assert!(text.contains("/Title <FEFF00DC006E00EF00204E2D>"));
+        assert!(text.contains("/Filter /FlateDecode"));
+        let number = |at: usize, len: usize| -> usize {
+            std::str::from_utf8(&out[at..at + len]).unwrap().trim().parse().unwrap()
+        };
+        let start = out.windows(10).rposition(|w| w == b"startxref\n").unwrap() + 10;
+        let len = out[start..].iter().position(|&b| b == b'\n').unwrap();
+        let xref = number(start, len);
+        assert!(out[xref..].starts_with(b"xref\n0 4\n"));
+        // The entry of object 1 follows "xref\n0 4\n" and that of object 0
+        let first = number(xref + 9 + 20, 10);
+        assert!(out[first..].starts_with(b"1 0 obj"));
+    }
+
+    #[test]
+    fn cmap_maps_codes_to_utf16() {
+        let mut glyphs = BTreeMap::new();
+        glyphs.insert(3, (3, "a".to_string()));
+        glyphs.insert(700, (700, "😀".to_string()));
+        glyphs.insert(701, (701, String::new()));
+        let cmap = to_unicode_cmap(&glyphs);
+        assert!(cmap.contains("2 beginbfchar\n<0003> <0061>\n<02BC> <D83DDE00>\nendbfchar"));
+    }
+
+    #[test]
+    fn dict_operands_decode() {
+        // 0 100 -100 1000 ROS; 300 charset
+        let dict = [139, 239, 39, 250, 124, 12, 30, 28, 1, 44, 15];
+        assert_eq!(cff_dict(&dict), vec![(1230, vec![0, 100, -100, 1000]), (15, vec![300])]);
+    }
+
+    #[test]
+    fn cid_keyed_charsets_map_glyphs_to_cids() {
+        // Header, empty name INDEX, one top DICT: ROS and charset at 20
+        let mut cff = vec![1, 0, 4, 1, 0, 0];
+        cff.extend([0, 1, 1, 1, 8, 139, 139, 139, 12, 30, 159, 15]);
+        assert_eq!(cff.len(), 18);
+        cff.extend([0, 0]);
+        // Format 2: glyphs 1-3 are CIDs 500-502, glyph 4 is CID 9
+        cff.extend([2, 1, 244, 0, 2, 0, 9, 0, 0]);
+        assert_eq!(cff_cids(&cff, 5), Some(vec![0, 500, 501, 502, 9]));
+        // Without ROS the font is not CID-keyed
+        cff[15] = 31;
+        assert_eq!(cff_cids(&cff, 5), None);
+    }
+
+    #[test]
+    fn composites_list_components() {
+        // Two components, the first with word arguments and a scale
+        let data = [0x00, 0x29, 0, 7, 0, 1, 0, 2, 0x40, 0x00, 0x00, 0x00, 0, 9, 1, 2];
+        assert_eq!(composite_components(&data), vec![7, 9]);
+    }
+
+    #[test]
+    fn truetype_subset_keeps_glyph_indices() {
+        let Ok(data) = std::fs::read(DEJAVU) else { return };
+        let face = ttf_parser::Face::parse(&data, 0).unwrap();
+        let a = face.glyph_index('a').unwrap();
+        let e_acute = face.glyph_index('é').unwrap();
+        let subset = subset_truetype(face.raw_face(), &[a.0, e_acute.0]).unwrap();
+        assert!(subset.len() < data.len() / 4);
+        assert_eq!(table_checksum(&subset), 0xB1B0AFBA);
+
+        let sub = ttf_parser::Face::parse(&subset, 0).unwrap();
+        assert_eq!(sub.number_of_glyphs(), face.number_of_glyphs());
+        assert_eq!(sub.glyph_hor_advance(a), face.glyph_hor_advance(a));
+        assert_eq!(sub.glyph_bounding_box(a), face.glyph_bounding_box(a));
+        assert_eq!(sub.glyph_bounding_box(e_acute), face.glyph_bounding_box(e_acute));
+        let b = face.glyph_index('b').unwrap();
+        assert!(sub.glyph_bounding_box(b).is_none());
+    }
+
+    #[test]
+    fn names_drop_delimiters() {
+        assert_eq!(pdf_name("DejaVu Sans(Mono)/x"), "DejaVuSansMonox");
+        assert_eq!(pdf_name(""), "Font");
+        let tag = subset_tag("DejaVuSansMono", &[1, 2]);
+        assert_eq!(tag.len(), 6);
+        assert!(tag.chars().all(|c| c.is_ascii_uppercase()));
+        assert_ne!(tag, subset_tag("DejaVuSansMono", &[1, 3]));
+    }
+}
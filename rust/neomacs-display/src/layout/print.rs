@@ -0,0 +1,1054 @@
+//! Buffer printing: lay a buffer region out on pages and write it as PDF.
+//!
+//! Pages are laid out by the layout engine itself.  `PrintHost` stands
+//! between the engine and Emacs and shows it the printed window resized
+//! to the page body, narrowed to the region and started at the page's
+//! first line, with no fringes, margins, mode line, cursor or region.
+//! Faces, fontification, invisible text, display and overlay
+//! properties, compositions, wrapping and line numbers are the engine's,
+//! so a page shows what the window would.  A page ends after the last
+//! row that fits whole, and the next page starts with the row that did
+//! not.
+//!
+//! The pages' glyphs are written as PDF, scaled so that the default
+//! face prints at the requested font size.  The text of each glyph cell
+//! is shaped again with cosmic-text in its face's font and drawn at the
+//! cell, and the fonts are embedded (see `pdf`), so any character a font
+//! can show prints and wide characters keep their columns.  Images and
+//! other non-text glyphs are not printed.
+//!
+//! Sending the PDF to a printer goes through the desktop print portal,
+//! from Lisp (`neomacs-print' in neomacs-win.el).
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ffi::{c_int, c_void};
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+use crate::core::frame_glyphs::{FrameGlyph, FrameGlyphBuffer};
+use crate::core::types::Color;
+
+use super::buffer_snapshot::BufferSnapshot;
+use super::emacs_ffi::*;
+use super::engine::LayoutEngine;
+use super::font_metrics::{FontMetricsService, ShapedGlyph};
+use super::frame_desc::{FrameDescription, WindowDescFFI};
+use super::hit_test::FRAME_HIT_DATA;
+use super::host::LayoutHost;
+use super::pdf::{self, FontSet, PdfWriter};
+use super::types::FrameParams;
+
+/// Baseline-to-baseline distance of header and footer lines, as a
+/// fraction of the font size.
+const LINE_SPACING: f32 = 1.2;
+/// Gray level of header and footer text.
+const HEADER_GRAY: f32 = 0.5;
+
+/// Paper dimensions in PDF points (1/72 inch).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaperSize {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl PaperSize {
+    pub const A4: PaperSize = PaperSize { width: 595.0, height: 842.0 };
+    pub const LETTER: PaperSize = PaperSize { width: 612.0, height: 792.0 };
+    pub const LEGAL: PaperSize = PaperSize { width: 612.0, height: 1008.0 };
+
+    /// Look up a paper size by name ("a4", "letter", "legal").
+    pub fn by_name(name: &str) -> Option<PaperSize> {
+        match name.to_ascii_lowercase().as_str() {
+            "a4" => Some(Self::A4),
+            "letter" | "us-letter" => Some(Self::LETTER),
+            "legal" | "us-legal" => Some(Self::LEGAL),
+            _ => None,
+        }
+    }
+}
+
+/// Page setup for a print job.
+#[derive(Debug, Clone)]
+pub struct PrintOptions {
+    pub paper: PaperSize,
+    /// Margin on every side, in points.
+    pub margin: f32,
+    /// Size of the default face's font, in points; other faces keep
+    /// their size relative to it.
+    pub font_size: f32,
+    /// Number the lines, as `display-line-numbers' does.
+    pub line_numbers: bool,
+    /// Header template; empty for none.  See [`expand_template`].
+    pub header: String,
+    /// Footer template; empty for none.
+    pub footer: String,
+    /// Keep face colors; otherwise print black on white.
+    pub color: bool,
+    /// Wrap long lines; otherwise truncate them at the right margin.
+    pub wrap: bool,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            paper: PaperSize::A4,
+            margin: 50.0,
+            font_size: 9.0,
+            line_numbers: false,
+            header: "%b".to_string(),
+            footer: "Page %p of %P".to_string(),
+            color: true,
+            wrap: true,
+        }
+    }
+}
+
+impl PrintOptions {
+    fn line_height(&self) -> f32 {
+        self.font_size * LINE_SPACING
+    }
+
+    /// Top of the body (PDF y grows upward).
+    fn body_top(&self) -> f32 {
+        let header = if self.header.is_empty() { 0.0 } else { 2.0 * self.line_height() };
+        self.paper.height - self.margin - header
+    }
+
+    fn body_bottom(&self) -> f32 {
+        let footer = if self.footer.is_empty() { 0.0 } else { 2.0 * self.line_height() };
+        self.margin + footer
+    }
+
+    /// Width and height of the body, in points.
+    pub fn body_size(&self) -> (f32, f32) {
+        (
+            (self.paper.width - 2.0 * self.margin).max(1.0),
+            (self.body_top() - self.body_bottom()).max(1.0),
+        )
+    }
+}
+
+/// One laid-out page.
+pub struct Page {
+    /// First position shown, and the position after the last
+    pub start: i64,
+    pub end: i64,
+    /// The page body's glyphs, in layout pixels
+    pub glyphs: FrameGlyphBuffer,
+}
+
+/// A buffer region laid out on pages.
+pub struct PrintDocument {
+    /// Buffer name, substituted for `%b`.
+    pub title: String,
+    /// File name, substituted for `%f`.
+    pub file_name: String,
+    pub pages: Vec<Page>,
+    /// Points per layout pixel
+    pub scale: f32,
+    /// The window's default colors, which print as black on white
+    pub default_fg: u32,
+    pub default_bg: u32,
+}
+
+impl PrintDocument {
+    pub fn new(title: &str, file_name: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            file_name: file_name.to_string(),
+            pages: Vec::new(),
+            scale: 1.0,
+            default_fg: 0x000000,
+            default_bg: 0xffffff,
+        }
+    }
+}
+
+/// Expand a header/footer template: `%b` buffer name, `%f` file name,
+/// `%p` page number, `%P` page count, `%%` a literal percent sign.
+pub fn expand_template(template: &str, doc: &PrintDocument, page: usize, total: usize) -> String {
+    let mut out = String::new();
+    let mut chars = template.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => out.push_str(&doc.title),
+            Some('f') => out.push_str(&doc.file_name),
+            Some('p') => out.push_str(&page.to_string()),
+            Some('P') => out.push_str(&total.to_string()),
+            Some('%') => out.push('%'),
+            Some(other) => {
+                out.push('%');
+                out.push(other);
+            }
+            None => out.push('%'),
+        }
+    }
+    out
+}
+
+// ============================================================================
+// Pagination
+// ============================================================================
+
+/// Set *OUT to VALUE if OUT is not null.
+unsafe fn put<T>(out: *mut T, value: T) {
+    if !out.is_null() {
+        *out = value;
+    }
+}
+
+/// The host layout prints through: the printed window as a page.
+struct PrintHost<'a> {
+    host: &'a dyn LayoutHost,
+    /// The printed window, resized to the page body
+    window: WindowDescFFI,
+    from: i64,
+    to: i64,
+    wrap: bool,
+    /// Line number setup, if lines are numbered
+    line_numbers: Option<LineNumberConfigFFI>,
+    /// First position of the page being laid out
+    start: Cell<i64>,
+    /// Where layout ended the page
+    end: Cell<i64>,
+}
+
+impl<'a> PrintHost<'a> {
+    /// Host printing WINDOW of HOST's FRAME from FROM to TO; the page
+    /// size is set with `set_page_size`.  None if FRAME has no such
+    /// window.
+    unsafe fn new(
+        host: &'a dyn LayoutHost,
+        frame: EmacsFrame,
+        window: EmacsWindow,
+        from: i64,
+        to: i64,
+        opts: &PrintOptions,
+    ) -> Option<Self> {
+        let mut desc = FrameDescription::default();
+        host.describe_frame(frame, &mut desc);
+        let mut window = desc.windows().iter().find(|w| w.params.window_ptr == window)?.clone();
+        window.described_from = 0;
+        window.described_to = 0;
+        window.first_face_span = 0;
+        window.face_span_count = 0;
+        window.first_prop_range = 0;
+        window.prop_range_count = 0;
+        let p = &mut window.params;
+        p.selected = 0;
+        p.is_minibuffer = 0;
+        p.cursor_in_non_selected = 0;
+        p.window_end = 0;
+        p.buffer_begv = from;
+        p.buffer_zv = to;
+        p.hscroll = 0;
+        p.vscroll = 0;
+        p.auto_hscroll = 0;
+        p.truncate_lines = !opts.wrap as c_int;
+        p.mode_line_height = 0.0;
+        p.header_line_height = 0.0;
+        p.tab_line_height = 0.0;
+        p.left_fringe_width = 0.0;
+        p.right_fringe_width = 0.0;
+        p.left_margin_width = 0.0;
+        p.right_margin_width = 0.0;
+        p.scroll_bar_width = 0.0;
+        p.indicate_empty_lines = 0;
+        p.show_trailing_whitespace = 0;
+        p.fill_column_indicator = 0;
+
+        let line_numbers = opts.line_numbers.then(|| {
+            // Wide enough for the last line's number and a space
+            let last = host.count_line_number(p.buffer_ptr, to, 0).max(1);
+            LineNumberConfigFFI { mode: 1, width: last.to_string().len() as c_int + 1, ..Default::default() }
+        });
+        Some(PrintHost {
+            host,
+            window,
+            from,
+            to,
+            wrap: opts.wrap,
+            line_numbers,
+            start: Cell::new(from),
+            end: Cell::new(from),
+        })
+    }
+
+    /// Resize the page to WIDTH x HEIGHT pixels.
+    fn set_page_size(&mut self, width: f32, height: f32) {
+        let p = &mut self.window.params;
+        (p.x, p.y, p.width, p.height) = (0.0, 0.0, width, height);
+        (p.text_x, p.text_y, p.text_width, p.text_height) = (0.0, 0.0, width, height);
+    }
+
+    /// Frame parameters of a frame holding just the page.
+    fn frame_params(&self) -> FrameParams {
+        let p = &self.window.params;
+        FrameParams {
+            width: p.width,
+            height: p.height,
+            char_width: p.char_width,
+            char_height: p.char_height,
+            font_pixel_size: p.font_pixel_size,
+            background: p.default_bg,
+            vertical_border_fg: p.default_bg,
+            right_divider_width: 0,
+            bottom_divider_width: 0,
+            divider_fg: 0,
+            divider_first_fg: 0,
+            divider_last_fg: 0,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+impl LayoutHost for PrintHost<'_> {
+    unsafe fn describe_frame(&self, _frame: EmacsFrame, desc: &mut FrameDescription) -> bool {
+        let mut window = self.window.clone();
+        window.params.window_start = self.start.get();
+        window.params.point = self.start.get();
+        *desc = FrameDescription::build(&[window], &[], &[]);
+        true
+    }
+
+    unsafe fn capture_buffer(&self, buffer: EmacsBuffer, text_buf: Vec<u8>) -> BufferSnapshot {
+        let mut snapshot = self.host.capture_buffer(buffer, text_buf);
+        snapshot.narrow(self.from, self.to);
+        snapshot.set_point(self.start.get());
+        snapshot.set_truncate_lines(!self.wrap);
+        snapshot
+    }
+
+    unsafe fn face_at_pos(
+        &self,
+        window: EmacsWindow,
+        charpos: i64,
+        face_out: *mut FaceDataFFI,
+        next_check_out: *mut i64,
+    ) -> c_int {
+        self.host.face_at_pos(window, charpos, face_out, next_check_out)
+    }
+
+    unsafe fn default_face(&self, frame: EmacsFrame, face_out: *mut FaceDataFFI) -> c_int {
+        self.host.default_face(frame, face_out)
+    }
+
+    unsafe fn get_stipple_bitmap(
+        &self,
+        frame: *mut c_void,
+        bitmap_id: c_int,
+        bits_out: *mut u8,
+        bits_buf_len: c_int,
+        width_out: *mut c_int,
+        height_out: *mut c_int,
+    ) -> c_int {
+        self.host.get_stipple_bitmap(frame, bitmap_id, bits_out, bits_buf_len, width_out, height_out)
+    }
+
+    unsafe fn char_width(&self, window: EmacsWindow, charcode: c_int, face_id: c_int) -> f32 {
+        self.host.char_width(window, charcode, face_id)
+    }
+
+    unsafe fn fill_ascii_widths(&self, window: EmacsWindow, face_id: c_int, widths: *mut f32) {
+        self.host.fill_ascii_widths(window, face_id, widths)
+    }
+
+    unsafe fn adjust_window_start(&self, _: EmacsWindow, _: EmacsBuffer, _: i64, _: c_int) -> i64 {
+        self.start.get()
+    }
+
+    unsafe fn anchor_window_start(&self, _: EmacsWindow, _: EmacsBuffer, _: i64, _: c_int) -> i64 {
+        self.start.get()
+    }
+
+    unsafe fn set_window_end(&self, _window: EmacsWindow, end_pos: i64, _end_vpos: c_int) {
+        self.end.set(end_pos);
+    }
+
+    unsafe fn set_cursor(&self, _: EmacsWindow, _: c_int, _: c_int, _: c_int, _: c_int) {}
+
+    unsafe fn set_hscroll(&self, _window: EmacsWindow, _hscroll: c_int) {}
+
+    unsafe fn ensure_fontified(&self, buffer: EmacsBuffer, from: i64, to: i64) -> c_int {
+        self.host.ensure_fontified(buffer, from, to)
+    }
+
+    unsafe fn check_invisible(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        next_visible_out: *mut i64,
+    ) -> c_int {
+        self.host.check_invisible(buffer, window, charpos, next_visible_out)
+    }
+
+    unsafe fn composition_at(
+        &self,
+        buffer: EmacsBuffer,
+        charpos: i64,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        end_out: *mut i64,
+        width_out: *mut c_int,
+    ) -> c_int {
+        self.host.composition_at(buffer, charpos, str_buf, str_buf_len, end_out, width_out)
+    }
+
+    unsafe fn mode_line_text(&self, _: EmacsWindow, _: EmacsFrame, _: *mut u8, _: i64, _: *mut FaceDataFFI) -> i64 {
+        0
+    }
+
+    unsafe fn header_line_text(&self, _: EmacsWindow, _: EmacsFrame, _: *mut u8, _: i64, _: *mut FaceDataFFI) -> i64 {
+        0
+    }
+
+    unsafe fn tab_line_text(&self, _: EmacsWindow, _: EmacsFrame, _: *mut u8, _: i64, _: *mut FaceDataFFI) -> i64 {
+        0
+    }
+
+    unsafe fn line_number_config(
+        &self,
+        _window: EmacsWindow,
+        _buffer: EmacsBuffer,
+        _buffer_zv: i64,
+        _max_rows: c_int,
+        config_out: *mut LineNumberConfigFFI,
+    ) -> c_int {
+        put(config_out, self.line_numbers.clone().unwrap_or_default());
+        0
+    }
+
+    unsafe fn count_line_number(&self, buffer: EmacsBuffer, charpos: i64, widen: c_int) -> i64 {
+        self.host.count_line_number(buffer, charpos, widen)
+    }
+
+    unsafe fn line_number_face(
+        &self,
+        window: EmacsWindow,
+        _is_current: c_int,
+        lnum: i64,
+        major_tick: c_int,
+        minor_tick: c_int,
+        face_out: *mut FaceDataFFI,
+    ) -> c_int {
+        // No line is current on paper
+        self.host.line_number_face(window, 0, lnum, major_tick, minor_tick, face_out)
+    }
+
+    unsafe fn check_display_prop(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        out: *mut DisplayPropFFI,
+    ) -> c_int {
+        self.host.check_display_prop(buffer, window, charpos, str_buf, str_buf_len, out)
+    }
+
+    unsafe fn overlay_strings_at(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        before_buf: *mut u8,
+        before_buf_len: c_int,
+        before_len_out: *mut c_int,
+        after_buf: *mut u8,
+        after_buf_len: c_int,
+        after_len_out: *mut c_int,
+        before_face_out: *mut FaceDataFFI,
+        after_face_out: *mut FaceDataFFI,
+        before_nruns_out: *mut c_int,
+        after_nruns_out: *mut c_int,
+        left_fringe_bitmap_out: *mut c_int,
+        left_fringe_fg_out: *mut u32,
+        left_fringe_bg_out: *mut u32,
+        right_fringe_bitmap_out: *mut c_int,
+        right_fringe_fg_out: *mut u32,
+        right_fringe_bg_out: *mut u32,
+        before_naligns_out: *mut c_int,
+        after_naligns_out: *mut c_int,
+    ) -> c_int {
+        self.host.overlay_strings_at(
+            buffer, window, charpos,
+            before_buf, before_buf_len, before_len_out,
+            after_buf, after_buf_len, after_len_out,
+            before_face_out, after_face_out,
+            before_nruns_out, after_nruns_out,
+            left_fringe_bitmap_out, left_fringe_fg_out, left_fringe_bg_out,
+            right_fringe_bitmap_out, right_fringe_fg_out, right_fringe_bg_out,
+            before_naligns_out, after_naligns_out,
+        )
+    }
+
+    unsafe fn check_glyphless(
+        &self,
+        frame: EmacsFrame,
+        codepoint: c_int,
+        method_out: *mut c_int,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        str_len_out: *mut c_int,
+    ) -> c_int {
+        self.host.check_glyphless(frame, codepoint, method_out, str_buf, str_buf_len, str_len_out)
+    }
+
+    unsafe fn margin_strings_at(
+        &self,
+        _: EmacsBuffer,
+        _: EmacsWindow,
+        _: i64,
+        _: *mut u8,
+        _: c_int,
+        left_len_out: *mut c_int,
+        _: *mut u8,
+        _: c_int,
+        right_len_out: *mut c_int,
+    ) -> c_int {
+        // The page has no margins
+        put(left_len_out, 0);
+        put(right_len_out, 0);
+        0
+    }
+
+    unsafe fn check_line_spacing(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        base_height: f32,
+        extra_above_out: *mut f32,
+        extra_below_out: *mut f32,
+    ) -> c_int {
+        self.host.check_line_spacing(buffer, window, charpos, base_height, extra_above_out, extra_below_out)
+    }
+
+    unsafe fn check_line_prefix(
+        &self,
+        buffer: EmacsBuffer,
+        window: EmacsWindow,
+        charpos: i64,
+        prefix_type: c_int,
+        str_buf: *mut u8,
+        str_buf_len: c_int,
+        str_len_out: *mut c_int,
+        width_out: *mut f32,
+    ) -> c_int {
+        self.host.check_line_prefix(buffer, window, charpos, prefix_type, str_buf, str_buf_len, str_len_out, width_out)
+    }
+
+    unsafe fn paragraph_align(&self, buffer: EmacsBuffer, window: EmacsWindow, charpos: i64) -> c_int {
+        self.host.paragraph_align(buffer, window, charpos)
+    }
+
+    unsafe fn vertical_writing(&self, window: EmacsWindow) -> c_int {
+        self.host.vertical_writing(window)
+    }
+
+    unsafe fn hyphenation(
+        &self,
+        buffer: EmacsBuffer,
+        lang_buf: *mut u8,
+        lang_buf_len: c_int,
+        dir_buf: *mut u8,
+        dir_buf_len: c_int,
+        dir_len_out: *mut c_int,
+        ragged_cols_out: *mut c_int,
+    ) -> c_int {
+        self.host.hyphenation(buffer, lang_buf, lang_buf_len, dir_buf, dir_buf_len, dir_len_out, ragged_cols_out)
+    }
+
+    unsafe fn get_fringe_bitmap(
+        &self,
+        bitmap_id: c_int,
+        bits_out: *mut u16,
+        bits_buf_len: c_int,
+        width_out: *mut c_int,
+        height_out: *mut c_int,
+        align_out: *mut c_int,
+    ) -> c_int {
+        self.host.get_fringe_bitmap(bitmap_id, bits_out, bits_buf_len, width_out, height_out, align_out)
+    }
+
+    unsafe fn overlay_arrows(&self, _: EmacsWindow, _: EmacsBuffer, _: *mut OverlayArrowFFI, _: c_int) -> c_int {
+        0
+    }
+
+    unsafe fn fringe_indicators(&self, window: EmacsWindow, out: *mut FringeIndicatorsFFI) -> c_int {
+        self.host.fringe_indicators(window, out)
+    }
+
+    unsafe fn fringe_bitmap_fg(&self, window: EmacsWindow, bitmap_id: c_int, fg_out: *mut u32) -> c_int {
+        self.host.fringe_bitmap_fg(window, bitmap_id, fg_out)
+    }
+
+    unsafe fn region_spans(&self, _: EmacsWindow, _: EmacsBuffer, _: *mut RegionSpanFFI, _: c_int) -> c_int {
+        0
+    }
+}
+
+/// Whether glyph G lies at or below Y.
+fn glyph_below(g: &FrameGlyph, y: f32) -> bool {
+    match g {
+        FrameGlyph::Char { y: gy, .. }
+        | FrameGlyph::Stretch { y: gy, .. }
+        | FrameGlyph::Image { y: gy, .. }
+        | FrameGlyph::Video { y: gy, .. }
+        | FrameGlyph::WebKit { y: gy, .. }
+        | FrameGlyph::Cursor { y: gy, .. }
+        | FrameGlyph::Border { y: gy, .. } => *gy >= y - 0.5,
+        _ => false,
+    }
+}
+
+/// Lay the text of WINDOW of HOST's FRAME between FROM and TO out on
+/// the pages of DOC with ENGINE, a layout engine of its own.  Returns
+/// false if FRAME does not show WINDOW.
+///
+/// # Safety
+/// As `LayoutEngine::layout_frame_with`; WINDOW must be a window of
+/// FRAME.
+#[allow(clippy::too_many_arguments)]
+pub unsafe fn paginate(
+    engine: &mut LayoutEngine,
+    host: &dyn LayoutHost,
+    frame: EmacsFrame,
+    window: EmacsWindow,
+    from: i64,
+    to: i64,
+    opts: &PrintOptions,
+    doc: &mut PrintDocument,
+) -> bool {
+    let Some(mut print_host) = PrintHost::new(host, frame, window, from, to, opts) else {
+        return false;
+    };
+    let wp = &print_host.window.params;
+    let pixel_size = if wp.font_pixel_size > 0.0 { wp.font_pixel_size } else { 14.0 };
+    doc.scale = opts.font_size / pixel_size;
+    doc.default_fg = wp.default_fg;
+    doc.default_bg = wp.default_bg;
+    let window_id = wp.window_id;
+    let (width, height) = opts.body_size();
+    let (width, height) = (width / doc.scale, height / doc.scale);
+    print_host.set_page_size(width, height);
+    let frame_params = print_host.frame_params();
+
+    // Layout publishes hit-test data for the frame it lays out; keep
+    // the screen's
+    let screen_hit_data = (*std::ptr::addr_of_mut!(FRAME_HIT_DATA)).take();
+    let mut start = from;
+    loop {
+        print_host.start.set(start);
+        print_host.end.set(start);
+        let mut glyphs = FrameGlyphBuffer::with_size(width, height);
+        engine.layout_frame_with(&print_host, frame, &frame_params, &mut glyphs);
+        let rows = (*std::ptr::addr_of_mut!(FRAME_HIT_DATA))
+            .take()
+            .and_then(|data| data.into_iter().find(|w| w.window_id == window_id))
+            .map(|w| w.rows)
+            .unwrap_or_default();
+
+        // End the page before the first row that does not fit whole,
+        // unless that is its only row
+        let mut end = print_host.end.get();
+        if let Some(i) = rows.iter().position(|r| r.y_end > height + 0.5) {
+            let cut = if i == 0 { rows.get(1) } else { rows.get(i) };
+            if let Some(row) = cut {
+                glyphs.glyphs.retain(|g| !glyph_below(g, row.y_start));
+                end = row.charpos_start;
+            }
+        }
+        if end <= start {
+            // Nothing fit: move on past what was laid out
+            end = rows.iter().map(|r| r.charpos_end).max().unwrap_or(to).max(start + 1);
+        }
+        let end = end.min(to);
+        doc.pages.push(Page { start, end, glyphs });
+        if end >= to {
+            break;
+        }
+        start = end;
+    }
+    *std::ptr::addr_of_mut!(FRAME_HIT_DATA) = screen_hit_data;
+    true
+}
+
+// ============================================================================
+// PDF output
+// ============================================================================
+
+/// Shaping key: text, family, weight, italic, size in centipixels.
+type ShapeKey = (String, String, u16, bool, i32);
+
+/// Writes the pages of a document as PDF content streams.
+struct PageWriter<'a> {
+    shaper: &'a mut FontMetricsService,
+    fonts: FontSet,
+    shaped: HashMap<ShapeKey, Vec<ShapedGlyph>>,
+}
+
+/// The PDF fill color operator for COLOR (linear, as glyphs carry it).
+fn fill_color(color: Color) -> String {
+    let c = color.linear_to_srgb();
+    format!("{:.3} {:.3} {:.3} rg", c.r.clamp(0.0, 1.0), c.g.clamp(0.0, 1.0), c.b.clamp(0.0, 1.0))
+}
+
+/// Text state of a content stream, to set only what changes.
+#[derive(Default)]
+struct TextState {
+    font: Option<(usize, i32)>,
+    color: String,
+}
+
+impl PageWriter<'_> {
+    /// Glyphs of TEXT shaped in the given face.
+    fn shape(&mut self, text: &str, family: &str, weight: u16, italic: bool, size: f32) -> Vec<ShapedGlyph> {
+        let key = (text.to_string(), family.to_string(), weight, italic, (size * 100.0) as i32);
+        if let Some(glyphs) = self.shaped.get(&key) {
+            return glyphs.clone();
+        }
+        let glyphs = self.shaper.shape(text, family, weight, italic, size);
+        self.shaped.insert(key, glyphs.clone());
+        glyphs
+    }
+
+    /// Draw TEXT in the given face and COLOR with its baseline at
+    /// (X, Y) in points, scaling its shaped positions by SCALE.
+    #[allow(clippy::too_many_arguments)]
+    fn show(
+        &mut self,
+        out: &mut String,
+        state: &mut TextState,
+        text: &str,
+        face: (&str, u16, bool, f32),
+        color: &str,
+        (x, y): (f32, f32),
+        scale: f32,
+    ) {
+        let (family, weight, italic, size) = face;
+        for glyph in self.shape(text, family, weight, italic, size) {
+            let cluster = text.get(glyph.start..glyph.end).unwrap_or("");
+            let fonts = self.shaper.font_system_mut();
+            let Some((font, code)) = self.fonts.glyph(fonts, glyph.font_id, glyph.glyph_id, cluster) else {
+                continue;
+            };
+            let font_size = ((size * scale) * 100.0).round() as i32;
+            if state.font != Some((font, font_size)) {
+                let _ = writeln!(out, "/F{font} {:.2} Tf", font_size as f32 / 100.0);
+                state.font = Some((font, font_size));
+            }
+            if state.color != color {
+                let _ = writeln!(out, "{color}");
+                state.color = color.to_string();
+            }
+            let _ = writeln!(
+                out,
+                "1 0 0 1 {:.2} {:.2} Tm <{code:04X}> Tj",
+                x + glyph.x * scale,
+                y + glyph.y * scale
+            );
+        }
+    }
+
+    /// Width of TEXT in the given face.
+    fn text_width(&mut self, text: &str, face: (&str, u16, bool, f32)) -> f32 {
+        let (family, weight, italic, size) = face;
+        self.shape(text, family, weight, italic, size).iter().map(|g| g.x + g.width).fold(0.0, f32::max)
+    }
+
+    /// Content stream of page NUMBER of DOC.
+    fn page_content(&mut self, doc: &PrintDocument, opts: &PrintOptions, number: usize) -> String {
+        let page = &doc.pages[number - 1];
+        let total = doc.pages.len();
+        let s = doc.scale;
+        let left = opts.margin;
+        let top = opts.body_top();
+        // Layout pixels to PDF points
+        let px = |x: f32| left + x * s;
+        let py = |y: f32| top - y * s;
+        let default_fg = Color::from_pixel(doc.default_fg);
+        let default_bg = Color::from_pixel(doc.default_bg);
+        let mut out = String::new();
+
+        // Backgrounds first, so text is drawn over them
+        let mut rect = |out: &mut String, color: Color, x: f32, y: f32, w: f32, h: f32| {
+            let _ = writeln!(out, "{} {:.2} {:.2} {:.2} {:.2} re f", fill_color(color), px(x), py(y + h), w * s, h * s);
+        };
+        if opts.color {
+            for g in &page.glyphs.glyphs {
+                match *g {
+                    FrameGlyph::Char { x, y, width, height, bg: Some(bg), .. } if bg != default_bg => {
+                        rect(&mut out, bg, x, y, width, height);
+                    }
+                    FrameGlyph::Stretch { x, y, width, height, bg, .. } if bg != default_bg => {
+                        rect(&mut out, bg, x, y, width, height);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Text, then its decorations
+        let mut decorations = String::new();
+        let mut state = TextState::default();
+        out.push_str("BT\n");
+        for g in &page.glyphs.glyphs {
+            let FrameGlyph::Char {
+                char: ch,
+                ref composed,
+                x,
+                y,
+                width,
+                ascent,
+                fg,
+                face_id,
+                font_weight,
+                italic,
+                font_size,
+                underline,
+                underline_color,
+                strike_through,
+                strike_through_color,
+                overline,
+                overline_color,
+                ..
+            } = *g
+            else {
+                continue;
+            };
+            let fg = if opts.color && fg != default_fg { fg } else { Color::BLACK };
+            let mut buf = [0u8; 4];
+            let text: &str = match composed {
+                Some(text) => text,
+                None => ch.encode_utf8(&mut buf),
+            };
+            if !text.chars().all(char::is_whitespace) {
+                let family = page.glyphs.get_face_font(face_id).to_string();
+                let color = fill_color(fg);
+                let baseline = (px(x), py(y + ascent));
+                self.show(&mut out, &mut state, text, (&family, font_weight, italic, font_size), &color, baseline, s);
+            }
+            let thickness = (font_size / 14.0).max(1.0);
+            let lines = [
+                (underline, underline_color, y + ascent + thickness),
+                (strike_through, strike_through_color, y + ascent * 0.65),
+                (overline, overline_color, y),
+            ];
+            for (style, color, line_y) in lines {
+                if style != 0 {
+                    let color = if opts.color { color.unwrap_or(fg) } else { Color::BLACK };
+                    rect(&mut decorations, color, x, line_y, width, thickness);
+                }
+            }
+        }
+
+        // Header and footer, in the default face's font
+        let family = page.glyphs.get_face_font(0).to_string();
+        let face = (family.as_str(), 400, false, opts.font_size);
+        let gray = format!("{HEADER_GRAY} {HEADER_GRAY} {HEADER_GRAY} rg");
+        if !opts.header.is_empty() {
+            let text = expand_template(&opts.header, doc, number, total);
+            let baseline = (opts.margin, opts.paper.height - opts.margin - opts.font_size);
+            self.show(&mut out, &mut state, &text, face, &gray, baseline, 1.0);
+        }
+        if !opts.footer.is_empty() {
+            let text = expand_template(&opts.footer, doc, number, total);
+            let x = ((opts.paper.width - self.text_width(&text, face)) / 2.0).max(opts.margin);
+            self.show(&mut out, &mut state, &text, face, &gray, (x, opts.margin), 1.0);
+        }
+        out.push_str("ET\n");
+        out.push_str(&decorations);
+        out
+    }
+}
+
+/// DOC as a PDF file, its text shaped with SHAPER.
+pub fn write_pdf(doc: &PrintDocument, opts: &PrintOptions, shaper: &mut FontMetricsService) -> Vec<u8> {
+    let mut writer = PageWriter { shaper, fonts: FontSet::new(), shaped: HashMap::new() };
+    let contents: Vec<String> = (1..=doc.pages.len()).map(|n| writer.page_content(doc, opts, n)).collect();
+
+    let mut pdf = PdfWriter::new();
+    let catalog = pdf.reserve();
+    let pages = pdf.reserve();
+    let fonts = writer.fonts.write(&mut pdf);
+    let font_entries: String = fonts.iter().enumerate().map(|(i, obj)| format!(" /F{i} {obj} 0 R")).collect();
+    let resources = pdf.add(format!("<< /Font <<{font_entries} >> >>"));
+    let mut kids = Vec::with_capacity(contents.len());
+    for content in &contents {
+        let stream = pdf.add_stream("", content.as_bytes());
+        kids.push(pdf.add(format!(
+            "<< /Type /Page /Parent {pages} 0 R /Resources {resources} 0 R /Contents {stream} 0 R >>"
+        )));
+    }
+    let kids: Vec<String> = kids.iter().map(|k| format!("{k} 0 R")).collect();
+    pdf.set(
+        pages,
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} /MediaBox [0 0 {} {}] >>",
+            kids.join(" "),
+            kids.len(),
+            opts.paper.width,
+            opts.paper.height
+        ),
+    );
+    pdf.set(catalog, format!("<< /Type /Catalog /Pages {pages} 0 R >>"));
+    let info = pdf.add(format!("<< /Title {} /Producer (Neomacs) >>", pdf::text_string(&doc.title)));
+    pdf.finish(catalog, info)
+}
+
+/// Write DOC to PATH as PDF.  Returns the number of pages.
+pub fn save_pdf(doc: &PrintDocument, opts: &PrintOptions, shaper: &mut FontMetricsService, path: &Path) -> io::Result<usize> {
+    std::fs::write(path, write_pdf(doc, opts, shaper))?;
+    Ok(doc.pages.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::Rect;
+    use crate::layout::headless::{HeadlessHost, LAYOUT_LOCK};
+
+    /// Pages of TEXT printed from a headless window, as 8x16 cells of a
+    /// 13 pixel font.
+    fn print(text: &str, opts: &PrintOptions) -> PrintDocument {
+        let _guard = LAYOUT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut host = HeadlessHost::new(800.0, 600.0);
+        host.add_window(text, Rect::new(0.0, 0.0, 800.0, 600.0));
+        let mut engine = LayoutEngine::new();
+        engine.use_cosmic_metrics = false;
+        let mut doc = PrintDocument::new("*scratch*", "/tmp/scratch.el");
+        let zv = text.chars().count() as i64 + 1;
+        // SAFETY: the headless host's pointers are tokens it never
+        // dereferences
+        assert!(unsafe { paginate(&mut engine, &host, 1 as EmacsFrame, 1 as EmacsWindow, 1, zv, opts, &mut doc) });
+        doc
+    }
+
+    /// Text of the characters drawn on PAGE, row by row, left to right.
+    fn page_text(page: &Page) -> Vec<String> {
+        let mut chars: Vec<(f32, f32, char)> = page.glyphs.glyphs.iter().filter_map(|g| match g {
+            FrameGlyph::Char { char: ch, x, y, .. } => Some((*y, *x, *ch)),
+            _ => None,
+        }).collect();
+        chars.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        let mut rows: Vec<(f32, String)> = Vec::new();
+        for (y, _, ch) in chars {
+            match rows.last_mut() {
+                Some((ry, row)) if (*ry - y).abs() < 0.5 => row.push(ch),
+                _ => rows.push((y, ch.to_string())),
+            }
+        }
+        rows.into_iter().map(|(_, row)| row.trim_end().to_string()).collect()
+    }
+
+    fn numbered_lines(count: usize) -> String {
+        (1..=count).map(|i| format!("line {i}")).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Rows of 16 pixels that fit on a page with OPTS.
+    fn rows_per_page(opts: &PrintOptions) -> usize {
+        let scale = opts.font_size / 13.0;
+        (opts.body_size().1 / scale / 16.0).floor() as usize
+    }
+
+    #[test]
+    fn pages_break_after_the_last_whole_row() {
+        let opts = PrintOptions::default();
+        let per_page = rows_per_page(&opts);
+        let doc = print(&numbered_lines(per_page + 3), &opts);
+        assert_eq!(doc.pages.len(), 2);
+        let first = page_text(&doc.pages[0]);
+        assert_eq!(first.len(), per_page);
+        assert_eq!(first[0], "line 1");
+        assert_eq!(page_text(&doc.pages[1]), vec![
+            format!("line {}", per_page + 1),
+            format!("line {}", per_page + 2),
+            format!("line {}", per_page + 3),
+        ]);
+        assert_eq!(doc.pages[1].start, doc.pages[0].end);
+        assert_eq!(doc.pages[1].end, (numbered_lines(per_page + 3).chars().count() + 1) as i64);
+    }
+
+    #[test]
+    fn long_lines_wrap_or_truncate_at_the_page_width() {
+        let opts = PrintOptions::default();
+        let columns = (opts.body_size().0 / (opts.font_size / 13.0) / 8.0).floor() as usize;
+        let text = "a".repeat(columns + 5);
+        let doc = print(&text, &opts);
+        let rows = page_text(&doc.pages[0]);
+        assert_eq!(rows.len(), 2);
+        let drawn: usize = rows.iter().map(|row| row.matches('a').count()).sum();
+        assert_eq!(drawn, columns + 5);
+
+        let doc = print(&text, &PrintOptions { wrap: false, ..opts });
+        assert_eq!(page_text(&doc.pages[0]).len(), 1);
+    }
+
+    #[test]
+    fn wide_characters_keep_their_columns() {
+        let doc = print("中文ab", &PrintOptions::default());
+        let xs: Vec<f32> = doc.pages[0].glyphs.glyphs.iter().filter_map(|g| match g {
+            FrameGlyph::Char { x, .. } => Some(*x),
+            _ => None,
+        }).collect();
+        assert_eq!(xs, vec![0.0, 16.0, 32.0, 40.0]);
+    }
+
+    #[test]
+    fn line_numbers_count_from_the_region() {
+        let opts = PrintOptions { line_numbers: true, ..Default::default() };
+        let doc = print("one\ntwo", &opts);
+        let rows = page_text(&doc.pages[0]);
+        assert!(rows[0].trim_start().starts_with("1"), "{rows:?}");
+        assert!(rows[1].trim_start().starts_with("2"), "{rows:?}");
+        assert!(rows[1].ends_with("two"));
+    }
+
+    #[test]
+    fn expand_template_substitutes_fields() {
+        let doc = PrintDocument::new("*scratch*", "/tmp/scratch.el");
+        assert_eq!(
+            expand_template("%b (%f) %p/%P 100%% %x", &doc, 2, 5),
+            "*scratch* (/tmp/scratch.el) 2/5 100% %x"
+        );
+    }
+
+    #[test]
+    fn paper_sizes_by_name() {
+        assert_eq!(PaperSize::by_name("Letter"), Some(PaperSize::LETTER));
+        assert_eq!(PaperSize::by_name("a4"), Some(PaperSize::A4));
+        assert_eq!(PaperSize::by_name("tabloid"), None);
+    }
+
+    #[test]
+    fn pdf_embeds_the_fonts_text_is_shaped_with() {
+        let opts = PrintOptions::default();
+        let doc = print("héllo 中文\nworld", &opts);
+        let mut shaper = FontMetricsService::new();
+        let has_fonts = shaper.font_system_mut().db().len() > 0;
+        let pdf = write_pdf(&doc, &opts, &mut shaper);
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.7"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("/Count 1"));
+        assert!(text.contains("/Title <FEFF002A0073006300720061007400630068002A>"));
+        if has_fonts {
+            assert!(text.contains("/Subtype /Type0"));
+            assert!(text.contains("/Encoding /Identity-H"));
+            assert!(text.contains("/ToUnicode"));
+            assert!(text.contains("/FontFile2") || text.contains("/FontFile3"));
+        }
+        let start = text.rfind("startxref\n").unwrap() + "startxref\n".len();
+        let offset: usize = text[start..].lines().next().unwrap().parse().unwrap();
+        assert!(pdf[offset..].starts_with(b"xref"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Rectangular region display (`rectangle-mark-mode`).
+//!
+//! Emacs shows a rectangular region with one overlay per line, padded
+//! with `after-string' spaces where a line ends before the rectangle's
+//! right edge.  The layout engine instead highlights the rectangle's
+//! column span directly on every row of its lines, including the virtual
+//! space past the end of short lines, so the highlight is a true
+//! rectangle.  While inserting into a zero-width rectangle, a thin cursor
+//! is drawn at the insertion column on each of its lines.
+
+use std::collections::HashMap;
+
+/// The rectangular region shown in one window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RectangleRegion {
+    /// Buffer shown in the window (same id as `WindowParams::buffer_id`).
+    pub buffer_id: u64,
+    /// Beginning of the rectangle's first line.
+    pub start: i64,
+    /// A position on the rectangle's last line (line starts up to and
+    /// including END belong to the rectangle).
+    pub end: i64,
+    /// First column of the rectangle.
+    pub left_col: i32,
+    /// Column just past the rectangle.  Equal to `left_col` for a
+    /// zero-width rectangle, which is not highlighted.
+    pub right_col: i32,
+    /// Highlight background (sRGB pixel).
+    pub bg: u32,
+    /// Column at which text is being inserted on every line, if any.
+    pub cursor_col: Option<i32>,
+    /// Color of the insertion cursors (sRGB pixel).
+    pub cursor_color: u32,
+}
+
+impl RectangleRegion {
+    /// Whether the logical line starting at LINE_START is part of the rectangle.
+    pub fn contains_line(&self, line_start: i64) -> bool {
+        line_start >= self.start && line_start <= self.end
+    }
+
+    /// Columns of the highlight on a visual row showing columns
+    /// FIRST_COL..FIRST_COL+COLS of its line, relative to the row start.
+    pub fn row_span(&self, first_col: i32, cols: i32) -> Option<(i32, i32)> {
+        let from = self.left_col.max(first_col);
+        let to = self.right_col.min(first_col + cols);
+        (from < to).then(|| (from - first_col, to - first_col))
+    }
+
+    /// Column of the insertion cursor on a visual row showing columns
+    /// FIRST_COL..FIRST_COL+COLS of its line, relative to the row start.
+    pub fn cursor_offset(&self, first_col: i32, cols: i32) -> Option<i32> {
+        self.cursor_col
+            .filter(|&c| c >= first_col && c < first_col + cols)
+            .map(|c| c - first_col)
+    }
+}
+
+/// Rectangular regions of all windows, keyed by window id (same id as
+/// `WindowParams::window_id`).
+#[derive(Debug, Default)]
+pub struct RectangleRegions {
+    windows: HashMap<i64, RectangleRegion>,
+}
+
+impl RectangleRegions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Show REGION in WINDOW_ID, replacing any previous one.
+    pub fn set(&mut self, window_id: i64, region: RectangleRegion) {
+        self.windows.insert(window_id, region);
+    }
+
+    /// Remove the rectangle of WINDOW_ID.
+    pub fn clear(&mut self, window_id: i64) {
+        self.windows.remove(&window_id);
+    }
+
+    /// The rectangle to draw in WINDOW_ID, if it still shows BUFFER_ID.
+    pub fn get(&self, window_id: i64, buffer_id: u64) -> Option<&RectangleRegion> {
+        self.windows.get(&window_id).filter(|r| r.buffer_id == buffer_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, right: i32) -> RectangleRegion {
+        RectangleRegion {
+            buffer_id: 3,
+            start: 100,
+            end: 250,
+            left_col: left,
+            right_col: right,
+            bg: 0x334455,
+            cursor_col: None,
+            cursor_color: 0xffffff,
+        }
+    }
+
+    #[test]
+    fn contains_line_is_inclusive() {
+        let r = rect(4, 10);
+        assert!(r.contains_line(100));
+        assert!(r.contains_line(250));
+        assert!(!r.contains_line(99));
+        assert!(!r.contains_line(251));
+    }
+
+    #[test]
+    fn row_span_clips_to_visible_columns() {
+        let r = rect(4, 10);
+        assert_eq!(r.row_span(0, 80), Some((4, 10)));
+        // hscrolled by 6 columns
+        assert_eq!(r.row_span(6, 80), Some((0, 4)));
+        assert_eq!(r.row_span(10, 80), None);
+        // Continuation row of a line wrapped at 8 columns
+        assert_eq!(r.row_span(8, 8), Some((0, 2)));
+        assert_eq!(r.row_span(0, 8), Some((4, 8)));
+    }
+
+    #[test]
+    fn zero_width_rectangle_has_no_highlight() {
+        let mut r = rect(5, 5);
+        assert_eq!(r.row_span(0, 80), None);
+        r.cursor_col = Some(5);
+        assert_eq!(r.cursor_offset(0, 80), Some(5));
+        assert_eq!(r.cursor_offset(6, 80), None);
+        assert_eq!(r.cursor_offset(4, 80), Some(1));
+    }
+
+    #[test]
+    fn regions_follow_window_buffer() {
+        let mut regions = RectangleRegions::new();
+        regions.set(7, rect(1, 2));
+        assert!(regions.get(7, 3).is_some());
+        assert!(regions.get(7, 4).is_none());
+        assert!(regions.get(8, 3).is_none());
+        regions.clear(7);
+        assert!(regions.get(7, 3).is_none());
+    }
+}
@@ -0,0 +1,131 @@
+//! Fading highlights over buffer ranges ("pulses").
+//!
+//! Commands that change text out of the user's sight (yank, kill, paste,
+//! indent) can flash the affected range so the eye finds it.  A pulse is
+//! requested for a range of a buffer with a color and a duration; every
+//! layout of a window showing the buffer while the pulse lasts turns the
+//! part of the range on screen into one rectangle per row, which the
+//! render thread draws above the text, fading out from the request time.
+//! Pulses follow scrolling because they are laid out anew each frame.
+
+use std::time::{Duration, Instant};
+
+/// Opacity of a pulse when it starts.
+pub const PEAK_ALPHA: f32 = 0.45;
+
+/// A requested pulse.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pulse {
+    pub buffer_id: u64,
+    /// First position highlighted.
+    pub start: i64,
+    /// Position just past the last one highlighted.
+    pub end: i64,
+    /// Highlight color (sRGB pixel).
+    pub color: u32,
+    pub started: Instant,
+    pub duration: Duration,
+}
+
+/// Pulses in progress, for all buffers.
+pub struct RegionPulses {
+    pulses: Vec<Pulse>,
+}
+
+impl RegionPulses {
+    pub fn new() -> Self {
+        Self { pulses: Vec::new() }
+    }
+
+    pub fn add(&mut self, pulse: Pulse) {
+        self.pulses.push(pulse);
+    }
+
+    /// Forget the pulses that are over at NOW.
+    pub fn expire(&mut self, now: Instant) {
+        self.pulses.retain(|p| now.saturating_duration_since(p.started) < p.duration);
+    }
+
+    pub fn has_buffer(&self, buffer_id: u64) -> bool {
+        self.pulses.iter().any(|p| p.buffer_id == buffer_id)
+    }
+
+    /// The pulses of BUFFER_ID.
+    pub fn for_buffer(&self, buffer_id: u64) -> impl Iterator<Item = &Pulse> {
+        self.pulses.iter().filter(move |p| p.buffer_id == buffer_id)
+    }
+}
+
+impl Default for RegionPulses {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Horizontal extent of positions [START, END) on each row, given
+/// (start position, end position, right edge of the row's text) for
+/// each laid out row: (row, x0, x1) for every row the range touches.
+/// Positions map to columns of width CHAR_W from CONTENT_X, as in hit
+/// testing.  Rows wholly inside the range extend to the end of their
+/// text, and at least half a column so empty lines show.
+pub fn row_spans(
+    start: i64,
+    end: i64,
+    rows: &[(i64, i64, f32)],
+    content_x: f32,
+    char_w: f32,
+) -> Vec<(usize, f32, f32)> {
+    let mut spans = Vec::new();
+    for (r, &(row_start, row_end, text_end)) in rows.iter().enumerate() {
+        if start >= row_end.max(row_start + 1) || end <= row_start {
+            continue;
+        }
+        let x0 = content_x + (start - row_start).max(0) as f32 * char_w;
+        let x1 = if end < row_end {
+            (content_x + (end - row_start) as f32 * char_w).min(text_end.max(x0))
+        } else {
+            text_end.max(x0 + char_w / 2.0)
+        };
+        if x1 > x0 {
+            spans.push((r, x0, x1));
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pulse(buffer_id: u64, ms: u64, started: Instant) -> Pulse {
+        Pulse {
+            buffer_id, start: 1, end: 5, color: 0xFFCC00,
+            started, duration: Duration::from_millis(ms),
+        }
+    }
+
+    #[test]
+    fn pulses_expire() {
+        let now = Instant::now();
+        let mut pulses = RegionPulses::new();
+        pulses.add(pulse(1, 100, now));
+        pulses.add(pulse(2, 300, now));
+        assert!(pulses.has_buffer(1) && pulses.has_buffer(2));
+        pulses.expire(now + Duration::from_millis(200));
+        assert!(!pulses.has_buffer(1));
+        assert_eq!(pulses.for_buffer(2).count(), 1);
+    }
+
+    #[test]
+    fn spans_cover_range_on_each_row() {
+        // "hello world\n" at 1..13, "\n" at 13, "foo bar\n" at 14..22
+        let rows = [(1, 13, 110.0), (13, 14, 10.0), (14, 22, 80.0)];
+        // "world\n\nfoo" from the middle of the first line
+        let spans = row_spans(7, 17, &rows, 10.0, 10.0);
+        assert_eq!(spans, vec![(0, 70.0, 110.0), (1, 10.0, 15.0), (2, 10.0, 40.0)]);
+        // Within one line
+        assert_eq!(row_spans(2, 4, &rows, 10.0, 10.0), vec![(0, 20.0, 40.0)]);
+        // Off screen
+        assert!(row_spans(30, 40, &rows, 10.0, 10.0).is_empty());
+    }
+}
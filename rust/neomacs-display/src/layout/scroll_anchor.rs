@@ -0,0 +1,105 @@
+//! Scroll anchoring: keep point's line still when the text around it
+//! reflows.
+//!
+//! Emacs keeps a window's start position when the window is resized or
+//! when text between the start and point changes height (a fold opens
+//! or closes, an image loads), so the line at point jumps up or down
+//! the screen.  After each layout the engine records the screen row of
+//! point; if at the next layout point and the window start are where
+//! they were but the window's size or the buffer's contents changed,
+//! the start is moved so point's line lands on the same row, and with
+//! uniform row heights at the same pixel offset.  Commands that move
+//! point or scroll explicitly change one of them and are left alone.
+
+use std::collections::HashMap;
+
+/// What a window showed at its last layout.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollAnchor {
+    pub buffer_id: u64,
+    pub point: i64,
+    pub window_start: i64,
+    /// Screen row of point.
+    pub row: i32,
+    /// Text area size.
+    pub width: f32,
+    pub height: f32,
+    /// Buffer and overlay modification count.
+    pub modiff: i64,
+}
+
+/// Scroll anchors of all windows.
+pub struct ScrollAnchors {
+    anchors: HashMap<i64, ScrollAnchor>,
+}
+
+impl ScrollAnchors {
+    pub fn new() -> Self {
+        Self { anchors: HashMap::new() }
+    }
+
+    /// Remember what WINDOW_ID showed.
+    pub fn record(&mut self, window_id: i64, anchor: ScrollAnchor) {
+        self.anchors.insert(window_id, anchor);
+    }
+
+    /// Forget WINDOW_ID, e.g. when point is not visible in it.
+    pub fn forget(&mut self, window_id: i64) {
+        self.anchors.remove(&window_id);
+    }
+
+    /// The row point of WINDOW_ID should be kept on, given what it is
+    /// about to show, or None if its start should be left alone.
+    pub fn row_to_keep(&self, window_id: i64, now: &ScrollAnchor) -> Option<i32> {
+        let last = self.anchors.get(&window_id)?;
+        let same_view = last.buffer_id == now.buffer_id
+            && last.point == now.point
+            && last.window_start == now.window_start;
+        let changed = last.width != now.width
+            || last.height != now.height
+            || last.modiff != now.modiff;
+        (same_view && changed).then_some(last.row)
+    }
+}
+
+impl Default for ScrollAnchors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anchor(point: i64, height: f32, modiff: i64) -> ScrollAnchor {
+        ScrollAnchor {
+            buffer_id: 1, point, window_start: 100, row: 12,
+            width: 640.0, height, modiff,
+        }
+    }
+
+    #[test]
+    fn keeps_row_when_view_reflows() {
+        let mut anchors = ScrollAnchors::new();
+        anchors.record(5, anchor(300, 400.0, 7));
+        // Unchanged: nothing to do
+        assert_eq!(anchors.row_to_keep(5, &anchor(300, 400.0, 7)), None);
+        // Resized, or text above point folded
+        assert_eq!(anchors.row_to_keep(5, &anchor(300, 200.0, 7)), Some(12));
+        assert_eq!(anchors.row_to_keep(5, &anchor(300, 400.0, 9)), Some(12));
+        // Point moved or the window was scrolled: left alone
+        assert_eq!(anchors.row_to_keep(5, &anchor(301, 200.0, 9)), None);
+        let scrolled = ScrollAnchor { window_start: 80, ..anchor(300, 200.0, 7) };
+        assert_eq!(anchors.row_to_keep(5, &scrolled), None);
+        assert_eq!(anchors.row_to_keep(6, &anchor(300, 200.0, 7)), None);
+    }
+
+    #[test]
+    fn forgotten_windows_are_left_alone() {
+        let mut anchors = ScrollAnchors::new();
+        anchors.record(5, anchor(300, 400.0, 7));
+        anchors.forget(5);
+        assert_eq!(anchors.row_to_keep(5, &anchor(300, 200.0, 7)), None);
+    }
+}
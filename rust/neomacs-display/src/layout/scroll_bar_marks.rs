@@ -0,0 +1,192 @@
+//! Tick marks along the vertical scroll bar track.
+//!
+//! Packages publish lists of buffer positions under a category name
+//! ("bookmarks", "diagnostics", "search", "hunks", ...) with a color.
+//! Each position is drawn as a thin tick at the proportional height of
+//! the window's scroll bar track, like the overview ruler of IDEs, and a
+//! click on a tick can be resolved back to its position with `mark_at`.
+
+use std::collections::{BTreeMap, HashMap};
+
+/// Positions published under one category.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkSet {
+    /// Tick color (sRGB pixel).
+    pub color: u32,
+    /// Buffer positions, sorted.
+    pub positions: Vec<i64>,
+}
+
+/// A tick to draw: offset from the top of the track and color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tick {
+    pub y: f32,
+    pub color: u32,
+}
+
+/// Scroll bar marks of all buffers, keyed by buffer id (same id as
+/// `WindowParams::buffer_id`) and then by category.
+#[derive(Debug, Default)]
+pub struct ScrollBarMarks {
+    buffers: HashMap<u64, BTreeMap<String, MarkSet>>,
+}
+
+/// Height of a tick in pixels.
+pub const TICK_HEIGHT: f32 = 2.0;
+
+/// Offset of the tick for POS within a track of TRACK_HEIGHT pixels
+/// showing the accessible portion BEGV..ZV of a buffer.  The last
+/// position's tick ends at the bottom of the track.
+fn track_offset(pos: i64, begv: i64, zv: i64, track_height: f32) -> f32 {
+    let span = (zv - begv).max(1) as f32;
+    ((pos - begv) as f32 / span).clamp(0.0, 1.0) * (track_height - TICK_HEIGHT).max(0.0)
+}
+
+impl ScrollBarMarks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether BUFFER_ID has any marks.
+    pub fn has_buffer(&self, buffer_id: u64) -> bool {
+        self.buffers.contains_key(&buffer_id)
+    }
+
+    /// Replace the marks of CATEGORY in BUFFER_ID.  Empty POSITIONS
+    /// removes the category.
+    pub fn set(&mut self, buffer_id: u64, category: &str, color: u32, mut positions: Vec<i64>) {
+        if positions.is_empty() {
+            if let Some(categories) = self.buffers.get_mut(&buffer_id) {
+                categories.remove(category);
+                if categories.is_empty() {
+                    self.buffers.remove(&buffer_id);
+                }
+            }
+            return;
+        }
+        positions.sort_unstable();
+        positions.dedup();
+        self.buffers
+            .entry(buffer_id)
+            .or_default()
+            .insert(category.to_string(), MarkSet { color, positions });
+    }
+
+    /// Remove all marks of BUFFER_ID.
+    pub fn clear_buffer(&mut self, buffer_id: u64) {
+        self.buffers.remove(&buffer_id);
+    }
+
+    /// Ticks for BUFFER_ID on a track of TRACK_HEIGHT pixels showing
+    /// BEGV..ZV.  Marks outside the accessible portion are dropped, and
+    /// marks of the same color landing on the same pixel row are drawn
+    /// once.  Categories are drawn in name order, so where different
+    /// categories overlap the later name wins.
+    pub fn ticks(&self, buffer_id: u64, begv: i64, zv: i64, track_height: f32) -> Vec<Tick> {
+        let Some(categories) = self.buffers.get(&buffer_id) else {
+            return Vec::new();
+        };
+        let mut ticks = Vec::new();
+        for set in categories.values() {
+            let mut last_row = None;
+            for &pos in &set.positions {
+                if pos < begv || pos > zv {
+                    continue;
+                }
+                let y = track_offset(pos, begv, zv, track_height).floor();
+                if last_row == Some(y) {
+                    continue;
+                }
+                last_row = Some(y);
+                ticks.push(Tick { y, color: set.color });
+            }
+        }
+        ticks
+    }
+
+    /// The mark of BUFFER_ID nearest to offset Y of a track of
+    /// TRACK_HEIGHT pixels showing BEGV..ZV, if one is within TOLERANCE
+    /// pixels.
+    pub fn mark_at(
+        &self,
+        buffer_id: u64,
+        begv: i64,
+        zv: i64,
+        track_height: f32,
+        y: f32,
+        tolerance: f32,
+    ) -> Option<i64> {
+        let categories = self.buffers.get(&buffer_id)?;
+        let mut best: Option<(f32, i64)> = None;
+        for set in categories.values() {
+            for &pos in &set.positions {
+                if pos < begv || pos > zv {
+                    continue;
+                }
+                let dist = (track_offset(pos, begv, zv, track_height) - y).abs();
+                if dist <= tolerance && best.is_none_or(|(d, _)| dist < d) {
+                    best = Some((dist, pos));
+                }
+            }
+        }
+        best.map(|(_, pos)| pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_are_proportional() {
+        let mut marks = ScrollBarMarks::new();
+        marks.set(1, "bookmarks", 0xff0000, vec![501, 1, 1001]);
+        let ticks = marks.ticks(1, 1, 1001, 102.0);
+        let ys: Vec<f32> = ticks.iter().map(|t| t.y).collect();
+        assert_eq!(ys, vec![0.0, 50.0, 100.0]);
+        assert!(ticks.iter().all(|t| t.color == 0xff0000));
+    }
+
+    #[test]
+    fn ticks_skip_narrowed_out_and_duplicate_rows() {
+        let mut marks = ScrollBarMarks::new();
+        marks.set(1, "search", 0x00ff00, vec![5, 100, 101, 102, 5000]);
+        // 100..102 fall on the same pixel row of a 12px track
+        let ticks = marks.ticks(1, 50, 1050, 12.0);
+        assert_eq!(ticks.len(), 1);
+        assert_eq!(ticks[0].y, 0.0);
+    }
+
+    #[test]
+    fn categories_are_independent() {
+        let mut marks = ScrollBarMarks::new();
+        marks.set(1, "bookmarks", 0xff0000, vec![10]);
+        marks.set(1, "diagnostics", 0xffff00, vec![10, 20]);
+        assert_eq!(marks.ticks(1, 1, 100, 100.0).len(), 3);
+        marks.set(1, "diagnostics", 0, Vec::new());
+        assert_eq!(marks.ticks(1, 1, 100, 100.0).len(), 1);
+        marks.set(1, "bookmarks", 0, Vec::new());
+        assert!(!marks.has_buffer(1));
+    }
+
+    #[test]
+    fn mark_at_finds_nearest_within_tolerance() {
+        let mut marks = ScrollBarMarks::new();
+        marks.set(1, "bookmarks", 0xff0000, vec![100, 120]);
+        marks.set(2, "bookmarks", 0xff0000, vec![110]);
+        // Positions 100 and 120 map to y=10 and y=12 on a 102px track
+        assert_eq!(marks.mark_at(1, 0, 1000, 102.0, 11.5, 3.0), Some(120));
+        assert_eq!(marks.mark_at(1, 0, 1000, 102.0, 9.0, 3.0), Some(100));
+        assert_eq!(marks.mark_at(1, 0, 1000, 102.0, 50.0, 3.0), None);
+        assert_eq!(marks.mark_at(3, 0, 1000, 102.0, 10.0, 3.0), None);
+    }
+
+    #[test]
+    fn clear_buffer_removes_all_categories() {
+        let mut marks = ScrollBarMarks::new();
+        marks.set(1, "a", 1, vec![1]);
+        marks.set(1, "b", 2, vec![2]);
+        marks.clear_buffer(1);
+        assert!(marks.ticks(1, 1, 10, 10.0).is_empty());
+    }
+}
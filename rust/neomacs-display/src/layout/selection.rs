@@ -0,0 +1,130 @@
+//! Region and secondary selection highlighting.
+//!
+//! Emacs highlights the active region with an overlay that
+//! `redisplay-highlight-region-function' moves about, and the secondary
+//! selection with `mouse-secondary-overlay'; both would reach the
+//! engine as faces of the characters they cover.  With the Rust engine
+//! the region is instead reported by `region_spans', and each row it
+//! covers gets one background run per stretch of adjacent characters:
+//! runs follow what is on the row, so text reordered by bidi or drawn
+//! in proportional fonts is covered where it is drawn, and a selected
+//! newline extends the run past the end of the text, to the right edge
+//! of the window with a face that `:extend's.
+
+/// A row of text as the selection code sees it.
+#[derive(Debug, Clone, Copy)]
+pub struct SelectionRow<'a> {
+    /// Positions of its first character and after its last
+    pub start: i64,
+    pub end: i64,
+    /// Position and left edge (relative to the text area) of each
+    /// character on the row, in buffer order
+    pub starts: &'a [(i64, f32)],
+    /// Right edge of its text, relative to the text area
+    pub text_end: f32,
+    /// Whether the row ends its line with the newline at `end - 1`,
+    /// rather than continuing on the next row or ending the buffer
+    pub ends_line: bool,
+}
+
+/// Runs (x0, x1) of ROW, relative to the text area, covering the
+/// characters of START..END.  A selected newline takes a character of
+/// CHAR_W past the text, or reaches RIGHT_EDGE when the face EXTENDs.
+pub fn row_runs(
+    start: i64,
+    end: i64,
+    row: &SelectionRow,
+    char_w: f32,
+    extend: bool,
+    right_edge: f32,
+) -> Vec<(f32, f32)> {
+    let mut runs: Vec<(f32, f32)> = Vec::new();
+    if start >= end || end <= row.start || start >= row.end.max(row.start + 1) {
+        return runs;
+    }
+
+    // Cells of the row left to right; each reaches the next one's edge
+    let mut cells: Vec<(i64, f32)> = row.starts.iter()
+        .copied()
+        .filter(|&(pos, _)| pos >= row.start && pos < row.end)
+        .collect();
+    if cells.is_empty() {
+        let last = if row.ends_line { row.end - 1 } else { row.end };
+        cells = (row.start..last)
+            .map(|pos| (pos, (pos - row.start) as f32 * char_w))
+            .collect();
+    }
+    cells.sort_by(|a, b| a.1.total_cmp(&b.1));
+    for (i, &(pos, x0)) in cells.iter().enumerate() {
+        let x1 = cells.get(i + 1).map_or(row.text_end.max(x0), |c| c.1);
+        if pos < start || pos >= end || x1 <= x0 {
+            continue;
+        }
+        push_run(&mut runs, x0, x1);
+    }
+
+    let newline = row.end - 1;
+    if row.ends_line && newline >= start && newline < end {
+        let x1 = if extend { right_edge } else { row.text_end + char_w };
+        if x1 > row.text_end {
+            push_run(&mut runs, row.text_end, x1);
+        }
+    }
+    runs
+}
+
+/// Add X0..X1 to RUNS, joining it to the last run if they touch.
+fn push_run(runs: &mut Vec<(f32, f32)>, x0: f32, x1: f32) {
+    match runs.last_mut() {
+        Some(last) if (last.1 - x0).abs() < 0.01 => last.1 = x1,
+        _ => runs.push((x0, x1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const W: f32 = 8.0;
+
+    fn grid(start: i64, len: i64) -> Vec<(i64, f32)> {
+        (0..len).map(|i| (start + i, i as f32 * W)).collect()
+    }
+
+    #[test]
+    fn runs_cover_selected_characters_and_newline() {
+        // "hello\n" at 1..7
+        let starts = grid(1, 5);
+        let row = SelectionRow { start: 1, end: 7, starts: &starts, text_end: 5.0 * W, ends_line: true };
+        assert_eq!(row_runs(2, 4, &row, W, true, 80.0 * W), [(W, 3.0 * W)]);
+        // The newline is selected: to the right edge, or one column
+        assert_eq!(row_runs(3, 10, &row, W, true, 80.0 * W), [(2.0 * W, 80.0 * W)]);
+        assert_eq!(row_runs(3, 10, &row, W, false, 80.0 * W), [(2.0 * W, 6.0 * W)]);
+        // Only the newline
+        assert_eq!(row_runs(6, 10, &row, W, false, 80.0 * W), [(5.0 * W, 6.0 * W)]);
+        assert!(row_runs(7, 10, &row, W, true, 80.0 * W).is_empty());
+        assert!(row_runs(3, 3, &row, W, true, 80.0 * W).is_empty());
+    }
+
+    #[test]
+    fn continued_rows_stop_at_the_text() {
+        // First row of a wrapped line "abcdefgh..." showing 1..5
+        let starts = grid(1, 4);
+        let row = SelectionRow { start: 1, end: 5, starts: &starts, text_end: 4.0 * W, ends_line: false };
+        assert_eq!(row_runs(3, 20, &row, W, true, 80.0 * W), [(2.0 * W, 4.0 * W)]);
+    }
+
+    #[test]
+    fn reordered_text_splits_runs() {
+        // Positions 1..4 drawn right to left
+        let starts = [(1, 3.0 * W), (2, 2.0 * W), (3, W), (4, 0.0)];
+        let row = SelectionRow { start: 1, end: 5, starts: &starts, text_end: 4.0 * W, ends_line: false };
+        assert_eq!(row_runs(2, 4, &row, W, true, 80.0 * W), [(W, 3.0 * W)]);
+        assert_eq!(row_runs(1, 2, &row, W, true, 80.0 * W), [(3.0 * W, 4.0 * W)]);
+        // 1 and 4 are at opposite ends
+        let starts = [(1, 0.0), (2, 2.0 * W), (3, W), (4, 3.0 * W)];
+        let row = SelectionRow { starts: &starts, ..row };
+        assert_eq!(row_runs(1, 2, &row, W, true, 80.0 * W), [(0.0, W)]);
+        assert_eq!(row_runs(2, 5, &row, W, true, 80.0 * W), [(W, 4.0 * W)]);
+    }
+}
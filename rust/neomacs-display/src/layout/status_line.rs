@@ -9,6 +9,8 @@ use super::unicode::decode_utf8;
 use super::types::*;
 use super::emacs_ffi::*;
 use super::engine::LayoutEngine;
+use super::host::LayoutHost;
+use super::breadcrumb_bar::{self, PieceKind};
 
 /// Which kind of status line to render.
 pub(crate) enum StatusLineKind {
@@ -126,6 +128,7 @@ impl LayoutEngine {
     /// Render a status line (mode-line, header-line, or tab-line).
     pub(crate) unsafe fn render_status_line(
         &mut self,
+        host: &dyn LayoutHost,
         x: f32,
         y: f32,
         width: f32,
@@ -142,21 +145,21 @@ impl LayoutEngine {
         let mut line_buf = vec![0u8; buf_size];
 
         let bytes = match kind {
-            StatusLineKind::TabLine => neomacs_layout_tab_line_text(
+            StatusLineKind::TabLine => host.tab_line_text(
                 wp.window_ptr,
                 std::ptr::null_mut(),
                 line_buf.as_mut_ptr(),
                 buf_size as i64,
                 &mut line_face,
             ),
-            StatusLineKind::HeaderLine => neomacs_layout_header_line_text(
+            StatusLineKind::HeaderLine => host.header_line_text(
                 wp.window_ptr,
                 std::ptr::null_mut(),
                 line_buf.as_mut_ptr(),
                 buf_size as i64,
                 &mut line_face,
             ),
-            StatusLineKind::ModeLine => neomacs_layout_mode_line_text(
+            StatusLineKind::ModeLine => host.mode_line_text(
                 wp.window_ptr,
                 std::ptr::null_mut(),
                 line_buf.as_mut_ptr(),
@@ -166,7 +169,7 @@ impl LayoutEngine {
         };
 
         // Apply face
-        self.apply_face(&line_face, frame, frame_glyphs);
+        self.apply_face(host, &line_face, frame, frame_glyphs);
         let bg = Color::from_pixel(line_face.bg);
         let default_fg = Color::from_pixel(line_face.fg);
 
@@ -192,6 +195,13 @@ impl LayoutEngine {
         // Draw background
         Self::add_stretch_for_face(&line_face, frame_glyphs, x, y, width, height, bg, line_face.face_id, true);
 
+        // A breadcrumb bar replaces the text of the header line
+        if matches!(kind, StatusLineKind::HeaderLine)
+            && self.render_breadcrumb_bar(host, x, y, text_y, width, height, char_w, ascent, wp, &line_face, frame_glyphs)
+        {
+            return;
+        }
+
         if bytes <= 0 {
             return;
         }
@@ -269,7 +279,6 @@ impl LayoutEngine {
         };
 
         // Use the mode-line face for character width queries
-        let face_id = line_face.face_id;
         let window = wp.window_ptr;
 
         // Render text with face runs, display properties, and align-to entries
@@ -367,35 +376,7 @@ impl LayoutEngine {
 
             // Use actual glyph width from the font instead of fixed char_w.
             // This handles variable-width characters (icons, CJK) correctly.
-            let advance = {
-                let cp = ch as u32;
-                if cp < 128 {
-                    // ASCII: use cached width via text_extents()
-                    let cache_key = (face_id, line_face.font_size);
-                    if !self.ascii_width_cache.contains_key(&cache_key) {
-                        let mut widths = [0.0f32; 128];
-                        neomacs_layout_fill_ascii_widths(
-                            window,
-                            face_id as std::os::raw::c_int,
-                            widths.as_mut_ptr(),
-                        );
-                        for w in widths.iter_mut() {
-                            if *w < 0.0 {
-                                *w = char_w;
-                            }
-                        }
-                        self.ascii_width_cache.insert(cache_key, widths);
-                    }
-                    self.ascii_width_cache[&cache_key][cp as usize]
-                } else {
-                    // Non-ASCII: query individually
-                    let w = neomacs_layout_char_width(
-                        window, cp as std::os::raw::c_int,
-                        face_id as std::os::raw::c_int,
-                    );
-                    if w > 0.0 { w } else { char_w }
-                }
-            };
+            let advance = self.status_char_advance(host, window, &line_face, ch, char_w);
 
             let gx = x + sl_x_offset;
             frame_glyphs.add_char(ch, gx, text_y, advance, height, ascent, true);
@@ -418,6 +399,104 @@ impl LayoutEngine {
         // Box borders are rendered by the renderer's box span detection
         // (supports both sharp and SDF rounded corners).
     }
+
+    /// Advance of CH in a status line drawn with LINE_FACE.
+    unsafe fn status_char_advance(
+        &mut self,
+        host: &dyn LayoutHost,
+        window: EmacsWindow,
+        line_face: &FaceDataFFI,
+        ch: char,
+        char_w: f32,
+    ) -> f32 {
+        let face_id = line_face.face_id;
+        let cp = ch as u32;
+        if cp < 128 {
+            // ASCII: use cached width via text_extents()
+            let cache_key = (face_id, line_face.font_size);
+            if !self.ascii_width_cache.contains_key(&cache_key) {
+                let mut widths = [0.0f32; 128];
+                host.fill_ascii_widths(
+                    window,
+                    face_id as std::os::raw::c_int,
+                    widths.as_mut_ptr(),
+                );
+                for w in widths.iter_mut() {
+                    if *w < 0.0 {
+                        *w = char_w;
+                    }
+                }
+                self.ascii_width_cache.insert(cache_key, widths);
+            }
+            self.ascii_width_cache[&cache_key][cp as usize]
+        } else {
+            // Non-ASCII: query individually
+            let w = host.char_width(
+                window, cp as std::os::raw::c_int,
+                face_id as std::os::raw::c_int,
+            );
+            if w > 0.0 { w } else { char_w }
+        }
+    }
+
+    /// Draw the breadcrumb bar of the window of WP, if it has one, as
+    /// the text of its header line.  Returns whether it did.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn render_breadcrumb_bar(
+        &mut self,
+        host: &dyn LayoutHost,
+        x: f32,
+        y: f32,
+        text_y: f32,
+        width: f32,
+        height: f32,
+        char_w: f32,
+        ascent: f32,
+        wp: &WindowParamsFFI,
+        line_face: &FaceDataFFI,
+        frame_glyphs: &mut FrameGlyphBuffer,
+    ) -> bool {
+        let Some((segments, separator, separator_color)) = self.breadcrumb_bars.get(wp.window_id) else {
+            return false;
+        };
+        let (segments, separator) = (segments.to_vec(), separator.to_string());
+        let window = wp.window_ptr;
+        let bg = Color::from_pixel(line_face.bg);
+        let fg = Color::from_pixel(line_face.fg);
+        let separator_fg = if separator_color != 0 { Color::from_pixel(separator_color) } else { fg };
+
+        let pieces = breadcrumb_bar::fit(&segments, &separator, width, |ch| {
+            self.status_char_advance(host, window, line_face, ch, char_w)
+        });
+        let mut hits = Vec::with_capacity(pieces.len());
+        let mut offset = 0.0;
+        for piece in &pieces {
+            let color = if piece.kind == PieceKind::Separator { separator_fg } else { fg };
+            frame_glyphs.set_face(
+                line_face.face_id, color, Some(bg),
+                400, false, 0, None, 0, None, 0, None,
+            );
+            let start = offset;
+            for ch in piece.text.chars() {
+                let advance = self.status_char_advance(host, window, line_face, ch, char_w);
+                frame_glyphs.add_char(ch, x + offset, text_y, advance, height, ascent, true);
+                offset += advance;
+            }
+            if let Some(segment) = piece.segment() {
+                hits.push((start, offset, segment));
+            }
+        }
+        self.breadcrumb_bars.set_hits(wp.window_id, hits);
+
+        frame_glyphs.set_face(
+            line_face.face_id, fg, Some(bg),
+            400, false, 0, None, 0, None, 0, None,
+        );
+        if offset < width {
+            Self::add_stretch_for_face(line_face, frame_glyphs, x + offset, y, width - offset, height, bg, line_face.face_id, true);
+        }
+        true
+    }
 }
 
 #[cfg(test)]
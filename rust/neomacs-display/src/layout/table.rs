@@ -0,0 +1,259 @@
+//! Native table layout for org/markdown style tables.
+//!
+//! A table is registered for a span of buffer lines together with the
+//! buffer positions of each cell.  Instead of drawing the lines as typed
+//! (`| a | bb |`), the layout engine measures every cell with the real
+//! font metrics, sizes each column to its widest cell and draws the cells
+//! at pixel-aligned column positions with grid lines between them, so
+//! columns line up even with proportional fonts or CJK text.  Cell
+//! positions are kept so point and mouse clicks still map to the buffer
+//! text of the cell.
+
+use std::collections::HashMap;
+
+/// Horizontal alignment of a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl CellAlign {
+    /// Decode the FFI representation: 1 = center, 2 = right, else left.
+    pub fn from_ffi(align: i32) -> Self {
+        match align {
+            1 => Self::Center,
+            2 => Self::Right,
+            _ => Self::Left,
+        }
+    }
+}
+
+/// One cell: its text and the buffer span the text comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableCell {
+    /// Position of the cell's first character.
+    pub start: i64,
+    /// Position just past the cell's last character.
+    pub end: i64,
+    /// Cell contents (trimmed).
+    pub text: String,
+}
+
+/// One line of a table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRow {
+    /// Beginning of the line.
+    pub start: i64,
+    /// End of the line (position of its newline).
+    pub end: i64,
+    /// Cells, left to right.  Empty for a rule line (`|---+---|`).
+    pub cells: Vec<TableCell>,
+    /// Whether this is a horizontal rule line.
+    pub rule: bool,
+}
+
+/// A table occupying consecutive lines of a buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub rows: Vec<TableRow>,
+    /// Alignment of each column; missing columns are left aligned.
+    pub aligns: Vec<CellAlign>,
+    /// Grid line color (sRGB pixel).
+    pub grid_color: u32,
+}
+
+/// Horizontal geometry of a table, computed once per frame from the
+/// measured column widths.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableGeometry {
+    /// Width of each column's content.
+    pub widths: Vec<f32>,
+    /// Space between a grid line and cell content.
+    pub padding: f32,
+    /// Grid line thickness.
+    pub grid: f32,
+}
+
+impl TableGeometry {
+    /// Measure every cell of TABLE with MEASURE (text -> pixel width).
+    pub fn measure(table: &Table, padding: f32, grid: f32, mut measure: impl FnMut(&str) -> f32) -> Self {
+        let mut widths: Vec<f32> = Vec::new();
+        for row in &table.rows {
+            for (i, cell) in row.cells.iter().enumerate() {
+                let w = measure(&cell.text);
+                if i >= widths.len() {
+                    widths.resize(i + 1, 0.0);
+                }
+                widths[i] = widths[i].max(w);
+            }
+        }
+        Self { widths, padding, grid }
+    }
+
+    /// X offsets (from the table's left edge) of the vertical grid lines,
+    /// one more than the number of columns.
+    pub fn grid_lines(&self) -> Vec<f32> {
+        let mut xs = Vec::with_capacity(self.widths.len() + 1);
+        let mut x = 0.0;
+        xs.push(x);
+        for w in &self.widths {
+            x += self.grid + 2.0 * self.padding + w;
+            xs.push(x);
+        }
+        xs
+    }
+
+    /// Total table width including the closing grid line.
+    pub fn total_width(&self) -> f32 {
+        self.grid_lines().last().copied().unwrap_or(0.0) + self.grid
+    }
+
+    /// X offset of the content of column COL whose text is TEXT_W wide.
+    pub fn cell_x(&self, col: usize, text_w: f32, align: CellAlign) -> f32 {
+        let left = self.grid_lines().get(col).copied().unwrap_or(0.0) + self.grid + self.padding;
+        let slack = (self.widths.get(col).copied().unwrap_or(0.0) - text_w).max(0.0);
+        match align {
+            CellAlign::Left => left,
+            CellAlign::Center => left + (slack / 2.0).floor(),
+            CellAlign::Right => left + slack,
+        }
+    }
+}
+
+impl Table {
+    /// Alignment of column COL.
+    pub fn align(&self, col: usize) -> CellAlign {
+        self.aligns.get(col).copied().unwrap_or(CellAlign::Left)
+    }
+
+    /// Cell of ROW showing POS, with the character offset of POS in it.
+    /// Positions on separators map to the end of the cell before them, or
+    /// to the start of the first cell.
+    pub fn cell_at(row: &TableRow, pos: i64) -> Option<(usize, usize)> {
+        if row.cells.is_empty() || pos < row.start || pos > row.end {
+            return None;
+        }
+        let idx = row.cells.iter().rposition(|c| c.start <= pos).unwrap_or(0);
+        let cell = &row.cells[idx];
+        let offset = (pos.clamp(cell.start, cell.end) - cell.start) as usize;
+        Some((idx, offset.min(cell.text.chars().count())))
+    }
+}
+
+/// Tables of all buffers, keyed by buffer id (same id as
+/// `WindowParams::buffer_id`).
+#[derive(Debug, Default)]
+pub struct TableStore {
+    buffers: HashMap<u64, Vec<Table>>,
+}
+
+impl TableStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add TABLE to BUFFER_ID, replacing tables it overlaps.
+    pub fn add(&mut self, buffer_id: u64, table: Table) {
+        let (Some(first), Some(last)) = (table.rows.first(), table.rows.last()) else {
+            return;
+        };
+        let (start, end) = (first.start, last.end);
+        let tables = self.buffers.entry(buffer_id).or_default();
+        tables.retain(|t| {
+            let (s, e) = (t.rows[0].start, t.rows[t.rows.len() - 1].end);
+            e < start || s > end
+        });
+        let at = tables.partition_point(|t| t.rows[0].start < start);
+        tables.insert(at, table);
+    }
+
+    /// Remove all tables of BUFFER_ID.
+    pub fn clear_buffer(&mut self, buffer_id: u64) {
+        self.buffers.remove(&buffer_id);
+    }
+
+    /// Whether BUFFER_ID has any tables.
+    pub fn has_buffer(&self, buffer_id: u64) -> bool {
+        self.buffers.contains_key(&buffer_id)
+    }
+
+    /// The table (with its index in BUFFER_ID) and row index of the table
+    /// line starting at POS.
+    pub fn row_at(&self, buffer_id: u64, pos: i64) -> Option<(usize, &Table, usize)> {
+        let tables = self.buffers.get(&buffer_id)?;
+        let ti = tables.partition_point(|t| t.rows[0].start <= pos).checked_sub(1)?;
+        let ri = tables[ti].rows.binary_search_by_key(&pos, |r| r.start).ok()?;
+        Some((ti, &tables[ti], ri))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(start: i64, text: &str) -> TableCell {
+        TableCell { start, end: start + text.chars().count() as i64, text: text.to_string() }
+    }
+
+    /// | a   | bb |
+    /// |-----+----|
+    /// | ccc | d  |
+    fn table() -> Table {
+        Table {
+            rows: vec![
+                TableRow { start: 1, end: 13, cells: vec![cell(3, "a"), cell(9, "bb")], rule: false },
+                TableRow { start: 14, end: 26, cells: vec![], rule: true },
+                TableRow { start: 27, end: 39, cells: vec![cell(29, "ccc"), cell(35, "d")], rule: false },
+            ],
+            aligns: vec![CellAlign::Left, CellAlign::Right],
+            grid_color: 0x808080,
+        }
+    }
+
+    #[test]
+    fn columns_fit_widest_cell() {
+        let g = TableGeometry::measure(&table(), 4.0, 1.0, |s| s.chars().count() as f32 * 10.0);
+        assert_eq!(g.widths, vec![30.0, 20.0]);
+        assert_eq!(g.grid_lines(), vec![0.0, 39.0, 68.0]);
+        assert_eq!(g.total_width(), 69.0);
+    }
+
+    #[test]
+    fn cell_alignment() {
+        let g = TableGeometry::measure(&table(), 4.0, 1.0, |s| s.chars().count() as f32 * 10.0);
+        assert_eq!(g.cell_x(0, 10.0, CellAlign::Left), 5.0);
+        assert_eq!(g.cell_x(0, 10.0, CellAlign::Center), 15.0);
+        assert_eq!(g.cell_x(1, 10.0, CellAlign::Right), 54.0);
+    }
+
+    #[test]
+    fn positions_map_to_cells() {
+        let t = table();
+        let row = &t.rows[2];
+        assert_eq!(Table::cell_at(row, 29), Some((0, 0)));
+        assert_eq!(Table::cell_at(row, 31), Some((0, 2)));
+        // On the separator after "ccc"
+        assert_eq!(Table::cell_at(row, 33), Some((0, 3)));
+        assert_eq!(Table::cell_at(row, 36), Some((1, 1)));
+        // Leading "| " maps to the first cell's start
+        assert_eq!(Table::cell_at(row, 27), Some((0, 0)));
+        assert_eq!(Table::cell_at(&t.rows[1], 20), None);
+        assert_eq!(Table::cell_at(row, 40), None);
+    }
+
+    #[test]
+    fn store_finds_rows_and_replaces_overlaps() {
+        let mut store = TableStore::new();
+        store.add(5, table());
+        assert_eq!(store.row_at(5, 14).map(|(ti, _, ri)| (ti, ri)), Some((0, 1)));
+        assert!(store.row_at(5, 15).is_none());
+        assert!(store.row_at(6, 14).is_none());
+        let mut other = table();
+        other.rows.truncate(1);
+        store.add(5, other);
+        assert!(store.row_at(5, 27).is_none());
+        assert_eq!(store.row_at(5, 1).unwrap().1.rows.len(), 1);
+    }
+}
@@ -4,7 +4,9 @@
 //! The layout engine produces LayoutOutput which is then converted to
 //! FrameGlyphBuffer for the existing renderer.
 
+use crate::core::bidi::BidiDir;
 use crate::core::types::{Color, Rect};
+use super::hscroll::HscrollPolicy;
 
 /// Complete layout output for one frame.
 /// Produced by the layout engine, consumed by the renderer.
@@ -211,8 +213,11 @@ pub struct WindowParams {
     pub extra_line_spacing: f32,
     /// Whether to show cursor in non-selected windows
     pub cursor_in_non_selected: bool,
-    /// selective-display: 0=off, >0=hide lines indented more than N columns
+    /// selective-display: 0=off, >0=hide lines indented more than N
+    /// columns, -1 (t) = hide the rest of lines after a ^M
     pub selective_display: i32,
+    /// selective-display-ellipses: show `...' for hidden lines
+    pub selective_display_ellipses: bool,
     /// escape-glyph face foreground color
     pub escape_glyph_fg: u32,
     /// nobreak-char-display: 0=off, 1=highlight, 2=escape notation
@@ -229,6 +234,12 @@ pub struct WindowParams {
     pub left_margin_width: f32,
     /// Right margin width in pixels (0 = no margin)
     pub right_margin_width: f32,
+    /// bidi-display-reordering: reorder right-to-left text for display
+    pub bidi_reordering: bool,
+    /// bidi-paragraph-direction: Auto finds each paragraph's direction
+    pub paragraph_direction: BidiDir,
+    /// auto-hscroll-mode and the variables tuning it
+    pub auto_hscroll: HscrollPolicy,
 }
 
 /// Frame-level parameters for layout.
@@ -258,6 +269,54 @@ pub struct FrameParams {
     pub divider_last_fg: u32,
 }
 
+/// Display-level writing aids applied by the layout engine to every
+/// non-minibuffer window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WritingModes {
+    /// Keep point's line vertically centered (typewriter scrolling).
+    pub typewriter: bool,
+    /// Keep point's line on its screen row when the window is resized or
+    /// text before point changes height (scroll anchoring).
+    pub scroll_anchoring: bool,
+    /// Center a fixed-width text column horizontally (zen writing).
+    pub centered_column: bool,
+    /// Width of the centered text column in default-face characters.
+    pub column_width: i32,
+    /// Background of the margins beside the column (sRGB pixel).  None = window background.
+    pub margin_bg: Option<u32>,
+}
+
+impl Default for WritingModes {
+    fn default() -> Self {
+        Self {
+            typewriter: false,
+            scroll_anchoring: true,
+            centered_column: false,
+            column_width: 80,
+            margin_bg: None,
+        }
+    }
+}
+
+impl WritingModes {
+    /// Text area of a window: unchanged unless the centered column is on, in which
+    /// case TEXT is narrowed to `column_width` characters and centered.
+    pub fn text_bounds(&self, text: Rect, char_width: f32) -> Rect {
+        if !self.centered_column || self.column_width <= 0 || char_width <= 0.0 {
+            return text;
+        }
+        let width = (self.column_width as f32 * char_width).min(text.width);
+        let x = text.x + ((text.width - width) / 2.0).floor();
+        Rect::new(x, text.y, width, text.height)
+    }
+
+    /// Number of lines to keep above point when typewriter scrolling a
+    /// text area of MAX_ROWS rows.
+    pub fn typewriter_lines_above(max_rows: i32) -> i32 {
+        (max_rows.max(1) - 1) / 2
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -272,6 +331,39 @@ mod tests {
         Rect::new(10.0, 20.0, 800.0, 600.0)
     }
 
+    // --- WritingModes ---
+
+    #[test]
+    fn writing_modes_default_is_off() {
+        let modes = WritingModes::default();
+        assert!(!modes.typewriter);
+        assert!(!modes.centered_column);
+        assert!(modes.scroll_anchoring);
+        assert_eq!(modes.column_width, 80);
+        assert_eq!(modes.text_bounds(test_rect(), 8.0), test_rect());
+    }
+
+    #[test]
+    fn writing_modes_centered_centers_column() {
+        let modes = WritingModes { centered_column: true, column_width: 50, ..Default::default() };
+        let bounds = modes.text_bounds(test_rect(), 8.0);
+        assert_eq!(bounds, Rect::new(210.0, 20.0, 400.0, 600.0));
+    }
+
+    #[test]
+    fn writing_modes_centered_narrow_window_keeps_width() {
+        let modes = WritingModes { centered_column: true, column_width: 200, ..Default::default() };
+        assert_eq!(modes.text_bounds(test_rect(), 8.0), test_rect());
+    }
+
+    #[test]
+    fn writing_modes_typewriter_lines_above() {
+        assert_eq!(WritingModes::typewriter_lines_above(40), 19);
+        assert_eq!(WritingModes::typewriter_lines_above(41), 20);
+        assert_eq!(WritingModes::typewriter_lines_above(1), 0);
+        assert_eq!(WritingModes::typewriter_lines_above(0), 0);
+    }
+
     // --- LayoutOutput ---
 
     #[test]
@@ -645,6 +737,7 @@ mod tests {
             extra_line_spacing: 0.0,
             cursor_in_non_selected: true,
             selective_display: 0,
+            selective_display_ellipses: true,
             escape_glyph_fg: 0x00FF0000,
             nobreak_char_display: 1,
             nobreak_char_fg: 0x0000FF00,
@@ -653,6 +746,9 @@ mod tests {
             line_prefix: vec![],
             left_margin_width: 0.0,
             right_margin_width: 0.0,
+            bidi_reordering: true,
+            paragraph_direction: BidiDir::Auto,
+            auto_hscroll: HscrollPolicy::default(),
         };
         assert_eq!(params.window_id, 12345);
         assert_eq!(params.buffer_id, 67890);
@@ -707,6 +803,7 @@ mod tests {
             extra_line_spacing: 0.0,
             cursor_in_non_selected: false,
             selective_display: 0,
+            selective_display_ellipses: true,
             escape_glyph_fg: 0,
             nobreak_char_display: 0,
             nobreak_char_fg: 0,
@@ -715,6 +812,9 @@ mod tests {
             line_prefix: vec![],
             left_margin_width: 0.0,
             right_margin_width: 0.0,
+            bidi_reordering: true,
+            paragraph_direction: BidiDir::Auto,
+            auto_hscroll: HscrollPolicy::default(),
         };
         assert!(params.is_minibuffer);
         assert_eq!(params.mode_line_height, 0.0);
@@ -761,6 +861,7 @@ mod tests {
             extra_line_spacing: 2.0,
             cursor_in_non_selected: true,
             selective_display: 3,
+            selective_display_ellipses: true,
             escape_glyph_fg: 0,
             nobreak_char_display: 2,
             nobreak_char_fg: 0,
@@ -769,6 +870,9 @@ mod tests {
             line_prefix: b"> ".to_vec(),
             left_margin_width: 5.0,
             right_margin_width: 5.0,
+            bidi_reordering: true,
+            paragraph_direction: BidiDir::Auto,
+            auto_hscroll: HscrollPolicy::default(),
         };
         let cloned = params.clone();
         assert_eq!(cloned.window_id, params.window_id);
@@ -0,0 +1,242 @@
+//! Vertical writing for East Asian text.
+//!
+//! A window with the `vertical-writing' window parameter shows its text
+//! in columns running top to bottom, the first at the right edge and
+//! each next one to its left, as Chinese and Japanese books are set.
+//! The engine lays the window out as usual with the text area's width
+//! and height swapped, so each row becomes a column as long as the
+//! window is tall, then turns the result a quarter turn onto the screen:
+//! a row's offset from the top of the text area becomes its column's
+//! offset from the right edge, and a glyph's offset along the row its
+//! offset down the column.
+//!
+//! Ideographs, kana and hangul stay upright, centered in their column;
+//! other characters, such as Latin words and digits, are drawn
+//! sideways, turned clockwise (after UAX #50).  Punctuation and
+//! brackets with vertical presentation forms, such as `、' and `「',
+//! are replaced by them.  Hit-testing maps screen points back through
+//! the same turn.
+
+use crate::core::frame_glyphs::{CursorInverseInfo, FrameGlyph};
+
+/// How a character stands in a vertical column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Upright,
+    Sideways,
+}
+
+/// Orientation of CH in vertical text.
+pub fn orientation(ch: char) -> Orientation {
+    match ch {
+        // Prolonged sound mark, wave dashes and fullwidth hyphen turn
+        // with the text
+        '\u{30FC}' | '\u{301C}' | '\u{3030}' | '\u{FF5E}' | '\u{FF0D}' => Orientation::Sideways,
+        '\u{1100}'..='\u{11FF}'
+        | '\u{2460}'..='\u{24FF}'
+        | '\u{2E80}'..='\u{2FFF}'
+        | '\u{3000}'..='\u{303F}'
+        | '\u{3040}'..='\u{31FF}'
+        | '\u{3200}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{A000}'..='\u{A4CF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FE10}'..='\u{FE1F}'
+        | '\u{FE30}'..='\u{FE4F}'
+        | '\u{FF01}'..='\u{FF60}'
+        | '\u{FFE0}'..='\u{FFE6}'
+        | '\u{1F000}'..='\u{1FAFF}'
+        | '\u{20000}'..='\u{3FFFD}' => Orientation::Upright,
+        _ => Orientation::Sideways,
+    }
+}
+
+/// The vertical presentation form of CH, or CH if it has none.
+pub fn vertical_form(ch: char) -> char {
+    match ch {
+        '\u{FF0C}' => '\u{FE10}', // ，
+        '\u{3001}' => '\u{FE11}', // 、
+        '\u{3002}' => '\u{FE12}', // 。
+        '\u{FF1A}' => '\u{FE13}', // ：
+        '\u{FF1B}' => '\u{FE14}', // ；
+        '\u{3016}' => '\u{FE17}', // 〖
+        '\u{3017}' => '\u{FE18}', // 〗
+        '\u{2026}' => '\u{FE19}', // …
+        '\u{2025}' => '\u{FE30}', // ‥
+        '\u{2014}' => '\u{FE31}', // —
+        '\u{2013}' => '\u{FE32}', // –
+        '\u{FF3F}' => '\u{FE33}', // ＿
+        '\u{FF08}' => '\u{FE35}', // （
+        '\u{FF09}' => '\u{FE36}', // ）
+        '\u{FF5B}' => '\u{FE37}', // ｛
+        '\u{FF5D}' => '\u{FE38}', // ｝
+        '\u{3014}' => '\u{FE39}', // 〔
+        '\u{3015}' => '\u{FE3A}', // 〕
+        '\u{3010}' => '\u{FE3B}', // 【
+        '\u{3011}' => '\u{FE3C}', // 】
+        '\u{300A}' => '\u{FE3D}', // 《
+        '\u{300B}' => '\u{FE3E}', // 》
+        '\u{3008}' => '\u{FE3F}', // 〈
+        '\u{3009}' => '\u{FE40}', // 〉
+        '\u{300C}' => '\u{FE41}', // 「
+        '\u{300D}' => '\u{FE42}', // 」
+        '\u{300E}' => '\u{FE43}', // 『
+        '\u{300F}' => '\u{FE44}', // 』
+        '\u{FF3B}' => '\u{FE47}', // ［
+        '\u{FF3D}' => '\u{FE48}', // ］
+        _ => ch,
+    }
+}
+
+/// The text area of a window in vertical writing mode, on screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VerticalArea {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl VerticalArea {
+    /// Whether (X, Y) of the swapped layout falls in the text area.
+    fn contains_layout(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.height && y >= self.y && y < self.y + self.width
+    }
+
+    /// Screen rectangle of the layout rectangle X, Y, W, H.
+    pub fn rect_to_screen(&self, x: f32, y: f32, w: f32, h: f32) -> (f32, f32, f32, f32) {
+        let along = x - self.x;
+        let across = y - self.y;
+        (self.x + self.width - across - h, self.y + along, h, w)
+    }
+
+    /// Layout point of the screen point (X, Y), for hit-testing.
+    pub fn point_to_layout(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.x + (y - self.y), self.y + (self.x + self.width - x))
+    }
+
+    /// Turn the text glyphs from START onto the screen.  Glyphs of the
+    /// text area laid out outside it, such as fringe bitmaps placed for
+    /// swapped rows, are dropped; mode, header and tab line glyphs are
+    /// left as they are.
+    pub fn transform(
+        &self,
+        glyphs: &mut Vec<FrameGlyph>,
+        start: usize,
+        cursor_inverse: Option<&mut CursorInverseInfo>,
+    ) {
+        let inverse_at = cursor_inverse.as_ref().map(|inv| (inv.x, inv.y));
+        let mut inverse_to = None;
+        let tail: Vec<FrameGlyph> = glyphs.drain(start.min(glyphs.len())..).collect();
+        for mut glyph in tail {
+            if glyph.is_overlay() {
+                glyphs.push(glyph);
+                continue;
+            }
+            match &mut glyph {
+                FrameGlyph::Char { char, composed, x, y, width, height, sideways, .. } => {
+                    if !self.contains_layout(*x, *y) {
+                        continue;
+                    }
+                    let (old_x, old_y) = (*x, *y);
+                    let (sx, sy, sw, sh) = self.rect_to_screen(*x, *y, *width, *height);
+                    let upright = match composed {
+                        Some(text) => text.chars().next().map(orientation),
+                        None => Some(orientation(*char)),
+                    } == Some(Orientation::Upright);
+                    if upright {
+                        // Center the glyph in its cell of the column
+                        *char = vertical_form(*char);
+                        *x = sx + (sw - *width) / 2.0;
+                        *y = sy + (sh - *height) / 2.0;
+                    } else {
+                        *x = sx;
+                        *y = sy;
+                        *width = sw;
+                        *height = sh;
+                        *sideways = true;
+                    }
+                    if inverse_at == Some((old_x, old_y)) {
+                        inverse_to = Some((*x, *y, *width, *height));
+                    }
+                }
+                FrameGlyph::Stretch { x, y, width, height, .. }
+                | FrameGlyph::Image { x, y, width, height, .. }
+                | FrameGlyph::Video { x, y, width, height, .. }
+                | FrameGlyph::WebKit { x, y, width, height, .. }
+                | FrameGlyph::Cursor { x, y, width, height, .. } => {
+                    if !self.contains_layout(*x, *y) {
+                        continue;
+                    }
+                    (*x, *y, *width, *height) = self.rect_to_screen(*x, *y, *width, *height);
+                }
+                _ => {}
+            }
+            glyphs.push(glyph);
+        }
+        if let Some(inv) = cursor_inverse {
+            match inverse_to {
+                Some((x, y, w, h)) => (inv.x, inv.y, inv.width, inv.height) = (x, y, w, h),
+                None => {
+                    (inv.x, inv.y, inv.width, inv.height) =
+                        self.rect_to_screen(inv.x, inv.y, inv.width, inv.height);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::frame_glyphs::FrameGlyphBuffer;
+
+    #[test]
+    fn orientation_and_vertical_forms() {
+        assert_eq!(orientation('漢'), Orientation::Upright);
+        assert_eq!(orientation('か'), Orientation::Upright);
+        assert_eq!(orientation('한'), Orientation::Upright);
+        assert_eq!(orientation('A'), Orientation::Sideways);
+        assert_eq!(orientation('7'), Orientation::Sideways);
+        assert_eq!(orientation('ー'), Orientation::Sideways);
+        assert_eq!(vertical_form('、'), '︑');
+        assert_eq!(vertical_form('「'), '﹁');
+        assert_eq!(vertical_form('漢'), '漢');
+    }
+
+    #[test]
+    fn rows_become_columns_from_the_right() {
+        // 200x100 text area at (10, 20): laid out 100 wide and 200 tall
+        let area = VerticalArea { x: 10.0, y: 20.0, width: 200.0, height: 100.0 };
+        let mut fg = FrameGlyphBuffer::new();
+        // Second row (y 36), third cell (x 10 + 32): an ideograph 16 wide
+        fg.add_char('漢', 42.0, 36.0, 16.0, 16.0, 12.0, false);
+        // A Latin letter after it
+        fg.add_char('A', 58.0, 36.0, 8.0, 16.0, 12.0, false);
+        // A mode line character is left alone; a fringe glyph laid out
+        // past the swapped text area is dropped
+        fg.add_char('-', 10.0, 300.0, 8.0, 16.0, 12.0, true);
+        fg.add_stretch(115.0, 20.0, 8.0, 16.0, Default::default(), 0, false);
+        area.transform(&mut fg.glyphs, 0, None);
+
+        assert_eq!(fg.glyphs.len(), 3);
+        // Column 2 from the right spans x 178..194; the ideograph stays
+        // upright 32 pixels down it
+        match &fg.glyphs[0] {
+            FrameGlyph::Char { x, y, width, sideways, .. } => {
+                assert_eq!((*x, *y, *width, *sideways), (178.0, 52.0, 16.0, false));
+            }
+            g => panic!("unexpected {:?}", g),
+        }
+        match &fg.glyphs[1] {
+            FrameGlyph::Char { x, y, width, height, sideways, .. } => {
+                assert_eq!((*x, *y, *width, *height, *sideways), (178.0, 68.0, 16.0, 8.0, true));
+            }
+            g => panic!("unexpected {:?}", g),
+        }
+        assert!(matches!(fg.glyphs[2], FrameGlyph::Char { y, .. } if y == 300.0));
+        // And screen points map back
+        assert_eq!(area.point_to_layout(186.0, 60.0), (50.0, 44.0));
+    }
+}
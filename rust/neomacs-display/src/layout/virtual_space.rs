@@ -0,0 +1,81 @@
+//! Virtual space: cursor positions past the end of a line.
+//!
+//! Column editing (picture-mode, artist-mode, evil's visual block) wants
+//! point to sit in the empty space after a line's last character without
+//! padding the buffer first.  The buffer keeps point at the end of the
+//! line; the window records how many columns further right the cursor
+//! should appear, and the layout engine draws it there.  Inserting text
+//! is expected to pad the gap with spaces first (done on the Lisp side).
+
+use std::collections::HashMap;
+
+/// A cursor drawn past the end of its line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VirtualCursor {
+    /// Point (at end of line) the cursor belongs to.  The cursor is only
+    /// shifted while the window's point is still there.
+    pub point: i64,
+    /// Columns between the end of the line and the cursor.
+    pub columns: i32,
+}
+
+/// Virtual cursors of all windows, keyed by window id (same id as
+/// `WindowParams::window_id`).
+#[derive(Debug, Default)]
+pub struct VirtualCursors {
+    windows: HashMap<i64, VirtualCursor>,
+}
+
+impl VirtualCursors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw the cursor of WINDOW_ID COLUMNS past the end of the line
+    /// while point is at POINT.  COLUMNS <= 0 removes the virtual cursor.
+    pub fn set(&mut self, window_id: i64, point: i64, columns: i32) {
+        if columns <= 0 {
+            self.windows.remove(&window_id);
+        } else {
+            self.windows.insert(window_id, VirtualCursor { point, columns });
+        }
+    }
+
+    /// Extra columns for the cursor of WINDOW_ID with point at POINT.
+    pub fn columns(&self, window_id: i64, point: i64) -> Option<i32> {
+        self.windows
+            .get(&window_id)
+            .filter(|c| c.point == point)
+            .map(|c| c.columns)
+    }
+}
+
+/// Row-relative column of a cursor at CURSOR_COL moved EXTRA columns
+/// right, kept on a row of COLS columns.
+pub fn shifted_column(cursor_col: i32, extra: i32, cols: i32) -> i32 {
+    (cursor_col + extra.max(0)).min(cols - 1).max(cursor_col.min(cols - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_follows_point() {
+        let mut cursors = VirtualCursors::new();
+        cursors.set(1, 40, 5);
+        assert_eq!(cursors.columns(1, 40), Some(5));
+        assert_eq!(cursors.columns(1, 41), None);
+        assert_eq!(cursors.columns(2, 40), None);
+        cursors.set(1, 40, 0);
+        assert_eq!(cursors.columns(1, 40), None);
+    }
+
+    #[test]
+    fn shift_clamps_to_row() {
+        assert_eq!(shifted_column(10, 5, 80), 15);
+        assert_eq!(shifted_column(10, 100, 80), 79);
+        assert_eq!(shifted_column(79, 3, 80), 79);
+        assert_eq!(shifted_column(10, -3, 80), 10);
+    }
+}
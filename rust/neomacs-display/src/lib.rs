@@ -20,8 +20,12 @@ pub mod backend;
 pub mod text;
 pub mod ffi;
 pub mod thread_comm;
+pub mod command_bus;
+pub mod scene_handoff;
+pub mod query;
 pub mod effect_config;
 pub mod layout;
+pub mod logging;
 
 pub mod render_thread;
 
@@ -43,12 +47,33 @@ pub const CORE_BACKEND: &str = "rust";
 #[cfg(not(feature = "core-backend-rust"))]
 pub const CORE_BACKEND: &str = "emacs-c";
 
-/// Read GPU power preference from `NEOMACS_GPU` environment variable.
+/// GPU power preference set with `set_gpu_power_preference`
+/// (0 = unset, 1 = LowPower, 2 = HighPerformance).
+static GPU_POWER_PREFERENCE: std::sync::atomic::AtomicU8 = std::sync::atomic::AtomicU8::new(0);
+
+/// Override the GPU power preference used when the GPU device is next
+/// created; None reverts to the `NEOMACS_GPU` environment variable.
+pub fn set_gpu_power_preference(preference: Option<wgpu::PowerPreference>) {
+    let value = match preference {
+        Some(wgpu::PowerPreference::LowPower) => 1,
+        Some(_) => 2,
+        None => 0,
+    };
+    GPU_POWER_PREFERENCE.store(value, std::sync::atomic::Ordering::Relaxed);
+}
+
+/// Read GPU power preference, set with `set_gpu_power_preference` or
+/// from the `NEOMACS_GPU` environment variable.
 ///
 /// - `"low"` or `"integrated"` → `LowPower` (prefer integrated GPU, e.g. Intel)
 /// - `"high"` or `"discrete"` → `HighPerformance` (prefer discrete GPU, e.g. NVIDIA)
 /// - unset or anything else → `HighPerformance` (default)
 pub fn gpu_power_preference() -> wgpu::PowerPreference {
+    match GPU_POWER_PREFERENCE.load(std::sync::atomic::Ordering::Relaxed) {
+        1 => return wgpu::PowerPreference::LowPower,
+        2 => return wgpu::PowerPreference::HighPerformance,
+        _ => {}
+    }
     match std::env::var("NEOMACS_GPU").as_deref() {
         Ok("low") | Ok("integrated") => {
             log::info!("NEOMACS_GPU={}: using LowPower (integrated GPU)", std::env::var("NEOMACS_GPU").unwrap());
@@ -68,7 +93,7 @@ pub fn gpu_power_preference() -> wgpu::PowerPreference {
 
 /// Initialize the display engine
 pub fn init() -> Result<(), DisplayError> {
-    env_logger::init();
+    logging::init();
     log::info!("Neomacs display engine v{} initializing (wgpu backend)", VERSION);
     Ok(())
 }
@@ -81,4 +106,13 @@ mod tests {
     fn test_version() {
         assert!(!VERSION.is_empty());
     }
+
+    #[test]
+    fn test_gpu_power_preference_override() {
+        set_gpu_power_preference(Some(wgpu::PowerPreference::LowPower));
+        assert_eq!(gpu_power_preference(), wgpu::PowerPreference::LowPower);
+        set_gpu_power_preference(Some(wgpu::PowerPreference::HighPerformance));
+        assert_eq!(gpu_power_preference(), wgpu::PowerPreference::HighPerformance);
+        set_gpu_power_preference(None);
+    }
 }
@@ -0,0 +1,295 @@
+//! Logging for the display engine.
+//!
+//! Records logged with the `log` macros still go to stderr through
+//! env_logger (filtered by `RUST_LOG`), and are also kept in a ring
+//! buffer that the in-editor log viewer queries over FFI.  The ring
+//! captures records up to its own level, independent of `RUST_LOG`.
+//!
+//! Each record carries the spans active on its thread when it was
+//! logged, so messages can be traced to the frame or layout pass that
+//! produced them:
+//!
+//! ```ignore
+//! let _span = logging::span("layout");
+//! log::debug!("window {} laid out", id);   // span "layout"
+//! ```
+//!
+//! Spans in use: `redisplay` (a layout call from Emacs) with `layout`
+//! inside it; `tick` (the render thread's animation and timer pass),
+//! `render` and `glyphs` for drawing; and for file I/O `image-file`,
+//! `thumbnail`, `crypto` and `settings-file`.  Only the display engine
+//! logs here: the Lisp core (neovm-core) is a separate library with no
+//! logging of its own.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+
+/// Records kept by default
+pub const DEFAULT_CAPACITY: usize = 4096;
+
+/// A captured log record
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    /// Sequence number, increasing by one per record
+    pub seq: u64,
+    pub time: SystemTime,
+    pub level: Level,
+    /// Module that logged the record
+    pub target: String,
+    /// Active spans, outermost first, joined with '/'
+    pub span: String,
+    pub message: String,
+}
+
+/// Which records a query returns
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+    /// Only records with a sequence number above this
+    pub after: u64,
+    /// Least severe level returned
+    pub level: LevelFilter,
+    /// Only records whose target contains this (e.g. "layout")
+    pub module: Option<String>,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self { after: 0, level: LevelFilter::Trace, module: None }
+    }
+}
+
+impl LogFilter {
+    fn matches(&self, record: &LogRecord) -> bool {
+        record.seq > self.after
+            && record.level <= self.level
+            && self.module.as_deref().is_none_or(|m| record.target.contains(m))
+    }
+}
+
+/// Fixed-size buffer of the most recent records
+#[derive(Debug)]
+pub struct LogRing {
+    records: VecDeque<LogRecord>,
+    capacity: usize,
+    next_seq: u64,
+}
+
+impl LogRing {
+    pub fn new(capacity: usize) -> Self {
+        Self { records: VecDeque::new(), capacity: capacity.max(1), next_seq: 1 }
+    }
+
+    /// Append a record, dropping the oldest when full.  Returns its
+    /// sequence number.
+    pub fn push(&mut self, level: Level, target: &str, span: String, message: String) -> u64 {
+        if self.records.len() >= self.capacity {
+            self.records.pop_front();
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.records.push_back(LogRecord {
+            seq,
+            time: SystemTime::now(),
+            level,
+            target: target.to_string(),
+            span,
+            message,
+        });
+        seq
+    }
+
+    /// Records matching FILTER, oldest first
+    pub fn query(&self, filter: &LogFilter) -> Vec<LogRecord> {
+        self.records.iter().filter(|r| filter.matches(r)).cloned().collect()
+    }
+
+    /// Sequence number of the newest record (0 if none yet)
+    pub fn last_seq(&self) -> u64 {
+        self.next_seq - 1
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+static RING: Lazy<Mutex<LogRing>> = Lazy::new(|| Mutex::new(LogRing::new(DEFAULT_CAPACITY)));
+
+/// Level captured by the ring, as a `LevelFilter` discriminant
+static CAPTURE_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Info as usize);
+
+/// Level of the env_logger filter, as a `LevelFilter` discriminant
+static ENV_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Off as usize);
+
+fn level_filter_from_usize(n: usize) -> LevelFilter {
+    LevelFilter::iter().nth(n).unwrap_or(LevelFilter::Trace)
+}
+
+fn capture_level() -> LevelFilter {
+    level_filter_from_usize(CAPTURE_LEVEL.load(Ordering::Relaxed))
+}
+
+fn update_max_level() {
+    let env = level_filter_from_usize(ENV_LEVEL.load(Ordering::Relaxed));
+    log::set_max_level(env.max(capture_level()));
+}
+
+/// Level from its FFI code: 0 = off, 1 = error, 2 = warn, 3 = info,
+/// 4 = debug, 5 = trace
+pub fn level_filter_from_ffi(code: i32) -> LevelFilter {
+    level_filter_from_usize(code.clamp(0, 5) as usize)
+}
+
+/// Set the least severe level kept in the ring
+pub fn set_capture_level(level: LevelFilter) {
+    CAPTURE_LEVEL.store(level as usize, Ordering::Relaxed);
+    update_max_level();
+}
+
+/// Records in the ring matching FILTER, oldest first
+pub fn records(filter: &LogFilter) -> Vec<LogRecord> {
+    RING.lock().map(|ring| ring.query(filter)).unwrap_or_default()
+}
+
+/// Drop all records from the ring
+pub fn clear() {
+    if let Ok(mut ring) = RING.lock() {
+        ring.clear();
+    }
+}
+
+thread_local! {
+    static SPANS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Guard of an active span; the span ends when it is dropped
+#[must_use = "the span ends when the guard is dropped"]
+pub struct Span {
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+/// Enter span NAME on this thread until the returned guard is dropped
+pub fn span(name: &'static str) -> Span {
+    SPANS.with(|spans| spans.borrow_mut().push(name));
+    Span { _not_send: std::marker::PhantomData }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        SPANS.with(|spans| {
+            spans.borrow_mut().pop();
+        });
+    }
+}
+
+fn current_span() -> String {
+    SPANS.with(|spans| spans.borrow().join("/"))
+}
+
+/// Logger writing to env_logger and to the ring
+struct RingLogger {
+    inner: env_logger::Logger,
+}
+
+impl Log for RingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= capture_level() || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.inner.matches(record) {
+            self.inner.log(record);
+        }
+        if record.level() <= capture_level() {
+            let message = record.args().to_string();
+            if let Ok(mut ring) = RING.lock() {
+                ring.push(record.level(), record.target(), current_span(), message);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Install the logger.  Does nothing if a logger is already installed.
+pub fn init() {
+    let inner = env_logger::Builder::from_default_env().build();
+    let env_level = inner.filter();
+    if log::set_boxed_logger(Box::new(RingLogger { inner })).is_ok() {
+        ENV_LEVEL.store(env_level as usize, Ordering::Relaxed);
+        update_max_level();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_drops_oldest() {
+        let mut ring = LogRing::new(3);
+        for i in 0..5 {
+            ring.push(Level::Info, "neomacs_display::core", String::new(), format!("m{}", i));
+        }
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring.last_seq(), 5);
+        let records = ring.query(&LogFilter::default());
+        assert_eq!(records.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![3, 4, 5]);
+        assert_eq!(records[0].message, "m2");
+    }
+
+    #[test]
+    fn filter_by_seq_level_and_module() {
+        let mut ring = LogRing::new(10);
+        ring.push(Level::Error, "neomacs_display::layout::engine", String::new(), "a".into());
+        ring.push(Level::Debug, "neomacs_display::layout::engine", String::new(), "b".into());
+        ring.push(Level::Warn, "neomacs_display::backend::wgpu", String::new(), "c".into());
+
+        let warnings = ring.query(&LogFilter { level: LevelFilter::Warn, ..Default::default() });
+        assert_eq!(warnings.iter().map(|r| r.message.as_str()).collect::<Vec<_>>(), vec!["a", "c"]);
+
+        let layout = ring.query(&LogFilter { module: Some("layout".into()), ..Default::default() });
+        assert_eq!(layout.len(), 2);
+
+        let newer = ring.query(&LogFilter { after: 2, ..Default::default() });
+        assert_eq!(newer.len(), 1);
+        assert_eq!(newer[0].message, "c");
+    }
+
+    #[test]
+    fn spans_nest_per_thread() {
+        assert_eq!(current_span(), "");
+        let _outer = span("render");
+        {
+            let _inner = span("glyphs");
+            assert_eq!(current_span(), "render/glyphs");
+            let other = std::thread::spawn(current_span).join().unwrap();
+            assert_eq!(other, "");
+        }
+        assert_eq!(current_span(), "render");
+    }
+
+    #[test]
+    fn level_codes() {
+        assert_eq!(level_filter_from_ffi(0), LevelFilter::Off);
+        assert_eq!(level_filter_from_ffi(2), LevelFilter::Warn);
+        assert_eq!(level_filter_from_ffi(5), LevelFilter::Trace);
+        assert_eq!(level_filter_from_ffi(9), LevelFilter::Trace);
+    }
+}
@@ -0,0 +1,210 @@
+//! Synchronous queries from Emacs to the render thread.
+//!
+//! Some answers only the render thread has: the extent of text shaped
+//! with its fonts, the size of an image it decoded, the monitors its
+//! event loop sees.  Emacs asks with `QueryClient::query`, which sends a
+//! `RenderCommand::Query` tagged with a fresh id and waits for the reply
+//! with that id on a separate channel.
+//!
+//! The wait is bounded: when the render thread is busy (or throttled in
+//! the background) the query fails with `QueryError::Timeout` instead of
+//! blocking the Emacs event loop, and the late reply is discarded by the
+//! next query.  The render thread never blocks on the reply channel.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender, TrySendError};
+
+use crate::command_bus::CommandSender;
+use crate::core::monitor::MonitorInfo;
+use crate::thread_comm::RenderCommand;
+
+/// Replies the render thread can queue before Emacs reads them
+const REPLY_CHANNEL_CAPACITY: usize = 16;
+
+/// Default time to wait for a reply
+pub const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// A question for the render thread
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderQuery {
+    /// Pixel extent of TEXT shaped in a font (empty family = monospace)
+    TextExtent {
+        text: String,
+        family: String,
+        weight: u16,
+        italic: bool,
+        font_size: f32,
+    },
+    /// Size of image ID, once decoded
+    ImageSize { id: u32 },
+    /// Monitors currently connected
+    Monitors,
+    /// JSON description of the frames being drawn (see
+    /// `crate::core::scene_dump`)
+    SceneDump,
+    /// Timeline of display initialization (see
+    /// `crate::render_thread::startup`)
+    StartupReport,
+}
+
+/// The render thread's answer to a `RenderQuery`
+#[derive(Debug, Clone)]
+pub enum QueryResult {
+    TextExtent { width: f32, height: f32 },
+    /// None if the image is unknown or not decoded yet
+    ImageSize(Option<(u32, u32)>),
+    Monitors(Vec<MonitorInfo>),
+    SceneDump(String),
+    StartupReport(String),
+    /// The render thread cannot answer yet (e.g. no window)
+    Unavailable,
+}
+
+/// Reply to the query with id ID
+#[derive(Debug)]
+pub struct QueryReply {
+    pub id: u64,
+    pub result: QueryResult,
+}
+
+/// Why a query got no answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryError {
+    /// The command channel is full
+    Busy,
+    /// No reply within the timeout
+    Timeout,
+    /// The render thread has exited
+    Disconnected,
+}
+
+impl std::fmt::Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Busy => "render command channel full",
+            Self::Timeout => "render thread did not reply in time",
+            Self::Disconnected => "render thread exited",
+        })
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Create the reply channel
+pub fn reply_channel() -> (Sender<QueryReply>, Receiver<QueryReply>) {
+    bounded(REPLY_CHANNEL_CAPACITY)
+}
+
+/// Emacs side of the query protocol.  Queries are meant to be made from
+/// one thread at a time; concurrent callers could take each other's
+/// replies and time out.
+#[derive(Debug)]
+pub struct QueryClient {
+    reply_rx: Receiver<QueryReply>,
+    next_id: AtomicU64,
+    timeouts: AtomicU64,
+}
+
+impl QueryClient {
+    pub fn new(reply_rx: Receiver<QueryReply>) -> Self {
+        Self { reply_rx, next_id: AtomicU64::new(1), timeouts: AtomicU64::new(0) }
+    }
+
+    /// Send QUERY through CMD_TX and wait up to TIMEOUT for its reply
+    pub fn query(
+        &self,
+        cmd_tx: &CommandSender,
+        query: RenderQuery,
+        timeout: Duration,
+    ) -> Result<QueryResult, QueryError> {
+        // Drop replies to earlier queries that timed out
+        while self.reply_rx.try_recv().is_ok() {}
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        cmd_tx.try_send(RenderCommand::Query { id, query }).map_err(|e| match e {
+            TrySendError::Full(_) => QueryError::Busy,
+            TrySendError::Disconnected(_) => QueryError::Disconnected,
+        })?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.reply_rx.recv_deadline(deadline) {
+                Ok(reply) if reply.id == id => return Ok(reply.result),
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => {
+                    self.timeouts.fetch_add(1, Ordering::Relaxed);
+                    log::debug!("render query {} timed out after {:?}", id, timeout);
+                    return Err(QueryError::Timeout);
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(QueryError::Disconnected),
+            }
+        }
+    }
+
+    /// Queries that timed out so far
+    pub fn timeouts(&self) -> u64 {
+        self.timeouts.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_bus::CommandStats;
+    use std::sync::Arc;
+
+    fn setup() -> (CommandSender, Receiver<RenderCommand>, Sender<QueryReply>, QueryClient) {
+        let (tx, rx) = bounded(4);
+        let (reply_tx, reply_rx) = reply_channel();
+        let sender = CommandSender::new(tx, Arc::new(CommandStats::default()));
+        (sender, rx, reply_tx, QueryClient::new(reply_rx))
+    }
+
+    /// Answer the next query on RX with an image size, from another thread
+    fn answer(rx: Receiver<RenderCommand>, reply_tx: Sender<QueryReply>, stale: bool) {
+        std::thread::spawn(move || {
+            if let Ok(RenderCommand::Query { id, .. }) = rx.recv() {
+                if stale {
+                    let _ = reply_tx.send(QueryReply { id: id + 100, result: QueryResult::Unavailable });
+                }
+                let _ = reply_tx.send(QueryReply { id, result: QueryResult::ImageSize(Some((3, 4))) });
+            }
+        });
+    }
+
+    #[test]
+    fn query_gets_matching_reply() {
+        let (tx, rx, reply_tx, client) = setup();
+        answer(rx, reply_tx, true);
+        let result = client.query(&tx, RenderQuery::ImageSize { id: 7 }, Duration::from_secs(5));
+        assert!(matches!(result, Ok(QueryResult::ImageSize(Some((3, 4))))));
+    }
+
+    #[test]
+    fn query_times_out_and_discards_late_reply() {
+        let (tx, rx, reply_tx, client) = setup();
+        let result = client.query(&tx, RenderQuery::Monitors, Duration::from_millis(10));
+        assert_eq!(result.unwrap_err(), QueryError::Timeout);
+        assert_eq!(client.timeouts(), 1);
+
+        // The late reply to the first query must not answer the second
+        let Ok(RenderCommand::Query { id, .. }) = rx.try_recv() else { panic!("no query sent") };
+        reply_tx.send(QueryReply { id, result: QueryResult::Unavailable }).unwrap();
+        answer(rx, reply_tx, false);
+        let result = client.query(&tx, RenderQuery::ImageSize { id: 1 }, Duration::from_secs(5));
+        assert!(matches!(result, Ok(QueryResult::ImageSize(Some(_)))));
+    }
+
+    #[test]
+    fn query_fails_when_renderer_gone() {
+        let (tx, rx, reply_tx, client) = setup();
+        drop(reply_tx);
+        let result = client.query(&tx, RenderQuery::Monitors, Duration::from_secs(5));
+        assert_eq!(result.unwrap_err(), QueryError::Disconnected);
+        drop(rx);
+        let result = client.query(&tx, RenderQuery::Monitors, Duration::from_secs(5));
+        assert_eq!(result.unwrap_err(), QueryError::Disconnected);
+    }
+}
@@ -0,0 +1,291 @@
+//! Screen reader bridge.
+//!
+//! The Emacs thread rebuilds the accessibility tree after each layout
+//! (see `layout::accessibility`) and sends it here whenever it changes.
+//! This module converts it into an AccessKit tree update for the primary
+//! window; accesskit_winit exports it over AT-SPI on Linux, UI Automation
+//! on Windows and NSAccessibility on macOS.  Text nodes carry their text
+//! as one text run per line so that screen readers can read by
+//! character, word and line and follow the caret.
+
+use std::sync::{Arc, Mutex};
+
+use accesskit::{
+    ActionHandler, ActionRequest, ActivationHandler, DeactivationHandler, Node, NodeId, Rect, Role,
+    TextPosition, TextSelection, Tree, TreeUpdate,
+};
+use winit::event::WindowEvent;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::Window;
+
+use crate::layout::accessibility::{AccessibleNode, AccessibleRole};
+
+/// First identifier used for text runs.  Window nodes use window
+/// pointers and status bars their complement, neither of which reaches
+/// this range on any supported platform.
+const TEXT_RUN_BASE: u64 = 1 << 62;
+
+/// Identifier of the placeholder root sent while no tree is available.
+const EMPTY_ROOT: u64 = 0;
+
+/// Tree handed to the adapter when a screen reader connects.
+struct InitialTree(Arc<Mutex<Option<TreeUpdate>>>);
+
+impl ActivationHandler for InitialTree {
+    fn request_initial_tree(&mut self) -> Option<TreeUpdate> {
+        let latest = self.0.lock().ok()?;
+        Some(latest.clone().unwrap_or_else(|| tree_update(None)))
+    }
+}
+
+/// Actions (focus, scroll, set selection) are left to Emacs commands.
+struct IgnoreActions;
+
+impl ActionHandler for IgnoreActions {
+    fn do_action(&mut self, _request: ActionRequest) {}
+}
+
+struct Deactivated;
+
+impl DeactivationHandler for Deactivated {
+    fn deactivate_accessibility(&mut self) {}
+}
+
+/// AccessKit adapter of the primary window.
+pub(crate) struct AccessKit {
+    adapter: accesskit_winit::Adapter,
+    latest: Arc<Mutex<Option<TreeUpdate>>>,
+}
+
+impl AccessKit {
+    /// Create the adapter for WINDOW.  Must be called before the window
+    /// is first shown.
+    pub fn new(event_loop: &ActiveEventLoop, window: &Window) -> Self {
+        let latest = Arc::new(Mutex::new(None));
+        let adapter = accesskit_winit::Adapter::with_direct_handlers(
+            event_loop,
+            window,
+            InitialTree(latest.clone()),
+            IgnoreActions,
+            Deactivated,
+        );
+        Self { adapter, latest }
+    }
+
+    /// Let the adapter track focus and geometry of WINDOW.
+    pub fn process_event(&mut self, window: &Window, event: &WindowEvent) {
+        self.adapter.process_event(window, event);
+    }
+
+    /// Replace the exported tree; ROOT is None once accessibility is
+    /// turned off.
+    pub fn update(&mut self, root: Option<&AccessibleNode>) {
+        let update = tree_update(root);
+        if let Ok(mut latest) = self.latest.lock() {
+            *latest = Some(update.clone());
+        }
+        self.adapter.update_if_active(|| update);
+    }
+}
+
+fn role(role: AccessibleRole) -> Role {
+    match role {
+        AccessibleRole::Frame => Role::Window,
+        AccessibleRole::Text => Role::MultilineTextInput,
+        AccessibleRole::Entry => Role::TextInput,
+        AccessibleRole::StatusBar => Role::Status,
+        AccessibleRole::PopupMenu => Role::Menu,
+        AccessibleRole::MenuItem => Role::MenuItem,
+        AccessibleRole::ToolTip => Role::Tooltip,
+    }
+}
+
+/// Full tree update for ROOT, or an empty window when there is none.
+pub(crate) fn tree_update(root: Option<&AccessibleNode>) -> TreeUpdate {
+    let Some(root) = root else {
+        return TreeUpdate {
+            nodes: vec![(NodeId(EMPTY_ROOT), Node::new(Role::Window))],
+            tree: Some(Tree::new(NodeId(EMPTY_ROOT))),
+            focus: NodeId(EMPTY_ROOT),
+        };
+    };
+    let mut nodes = Vec::new();
+    let mut next_run = TEXT_RUN_BASE;
+    add_node(root, &mut nodes, &mut next_run);
+    let focus = root.focused().unwrap_or(root).id;
+    TreeUpdate { nodes, tree: Some(Tree::new(NodeId(root.id))), focus: NodeId(focus) }
+}
+
+fn add_node(src: &AccessibleNode, nodes: &mut Vec<(NodeId, Node)>, next_run: &mut u64) {
+    let mut node = Node::new(role(src.role));
+    if !src.name.is_empty() {
+        node.set_label(src.name.clone());
+    }
+    let b = &src.bounds;
+    node.set_bounds(Rect::new(
+        b.x as f64,
+        b.y as f64,
+        (b.x + b.width) as f64,
+        (b.y + b.height) as f64,
+    ));
+    if !src.enabled {
+        node.set_disabled();
+    }
+    let is_text = matches!(src.role, AccessibleRole::Text | AccessibleRole::Entry);
+    if is_text {
+        let runs = text_runs(&src.text);
+        let mut caret_pos = None;
+        let mut first_char = 0;
+        for (i, line) in runs.iter().enumerate() {
+            let id = NodeId(*next_run);
+            *next_run += 1;
+            let count = line.chars().count();
+            // The caret sits before the character it is on; past the
+            // end of the text it goes at the end of the last run.
+            if let Some(caret) = src.caret {
+                let end = first_char + count;
+                let here = caret < end || (caret == end && i + 1 == runs.len());
+                if caret_pos.is_none() && caret >= first_char && here {
+                    caret_pos = Some(TextPosition { node: id, character_index: caret - first_char });
+                }
+            }
+            first_char += count;
+            let mut run = Node::new(Role::TextRun);
+            run.set_value(line.to_string());
+            run.set_character_lengths(line.chars().map(|c| c.len_utf8() as u8).collect::<Vec<_>>());
+            run.set_word_lengths(word_lengths(line));
+            node.push_child(id);
+            nodes.push((id, run));
+        }
+        if let Some(position) = caret_pos {
+            node.set_text_selection(TextSelection { anchor: position, focus: position });
+        }
+    } else if !src.text.is_empty() {
+        node.set_value(src.text.clone());
+    }
+    for child in &src.children {
+        node.push_child(NodeId(child.id));
+    }
+    nodes.push((NodeId(src.id), node));
+    for child in &src.children {
+        add_node(child, nodes, next_run);
+    }
+}
+
+/// Split TEXT into lines, each keeping its newline.  A trailing empty
+/// run holds the caret at the end of text ending in a newline.
+fn text_runs(text: &str) -> Vec<&str> {
+    let mut runs: Vec<&str> = text.split_inclusive('\n').collect();
+    if text.is_empty() || text.ends_with('\n') {
+        runs.push("");
+    }
+    runs
+}
+
+/// Character counts of the words of LINE; each word keeps the
+/// whitespace after it.  AccessKit stores counts as bytes, so longer
+/// words are split.
+fn word_lengths(line: &str) -> Vec<u8> {
+    let mut lengths = Vec::new();
+    let mut current = 0u8;
+    let mut in_space = false;
+    for c in line.chars() {
+        let space = c.is_whitespace();
+        if (in_space && !space) || current == u8::MAX {
+            lengths.push(current);
+            current = 0;
+        }
+        in_space = space;
+        current += 1;
+    }
+    if current > 0 {
+        lengths.push(current);
+    }
+    lengths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::accessibility::Bounds;
+
+    fn window(id: u64, text: &str, caret: Option<usize>) -> AccessibleNode {
+        let mut node = AccessibleNode::new(id, AccessibleRole::Text, "*scratch*");
+        node.text = text.to_string();
+        node.caret = caret;
+        node.bounds = Bounds { x: 0.0, y: 0.0, width: 400.0, height: 300.0 };
+        node
+    }
+
+    fn node(update: &TreeUpdate, id: u64) -> &Node {
+        &update.nodes.iter().find(|(n, _)| n.0 == id).unwrap().1
+    }
+
+    #[test]
+    fn runs_split_lines() {
+        assert_eq!(text_runs("ab\ncd"), vec!["ab\n", "cd"]);
+        assert_eq!(text_runs("ab\n"), vec!["ab\n", ""]);
+        assert_eq!(text_runs(""), vec![""]);
+    }
+
+    #[test]
+    fn words_keep_trailing_space() {
+        assert_eq!(word_lengths("foo  bar\n"), vec![5, 4]);
+        assert_eq!(word_lengths(""), Vec::<u8>::new());
+        assert_eq!(word_lengths(&"x".repeat(300)), vec![255, 45]);
+    }
+
+    #[test]
+    fn focused_window_gets_focus() {
+        let mut root = AccessibleNode::new(7, AccessibleRole::Frame, "emacs");
+        let mut win = window(100, "hello", Some(2));
+        win.focused = true;
+        root.children.push(win);
+        let update = tree_update(Some(&root));
+        assert_eq!(update.focus, NodeId(100));
+        assert_eq!(update.tree.as_ref().unwrap().root, NodeId(7));
+        assert_eq!(node(&update, 7).role(), Role::Window);
+        assert_eq!(node(&update, 100).role(), Role::MultilineTextInput);
+        assert_eq!(node(&update, 100).label(), Some("*scratch*"));
+    }
+
+    #[test]
+    fn caret_lands_in_its_line() {
+        let root = window(100, "ab\ncd\n", Some(4));
+        let update = tree_update(Some(&root));
+        let text = node(&update, 100);
+        assert_eq!(text.children().len(), 3);
+        let selection = text.text_selection().unwrap();
+        assert_eq!(selection.focus.node, NodeId(TEXT_RUN_BASE + 1));
+        assert_eq!(selection.focus.character_index, 1);
+        assert_eq!(node(&update, TEXT_RUN_BASE + 1).value(), Some("cd\n"));
+    }
+
+    #[test]
+    fn caret_at_end_of_text() {
+        let update = tree_update(Some(&window(100, "ab\n", Some(3))));
+        let selection = node(&update, 100).text_selection().unwrap();
+        assert_eq!(selection.focus.node, NodeId(TEXT_RUN_BASE + 1));
+        assert_eq!(selection.focus.character_index, 0);
+
+        let update = tree_update(Some(&window(100, "ab", Some(2))));
+        let selection = node(&update, 100).text_selection().unwrap();
+        assert_eq!(selection.focus.node, NodeId(TEXT_RUN_BASE));
+        assert_eq!(selection.focus.character_index, 2);
+    }
+
+    #[test]
+    fn character_lengths_are_utf8() {
+        let update = tree_update(Some(&window(100, "aé中", None)));
+        let run = node(&update, TEXT_RUN_BASE);
+        assert_eq!(run.character_lengths(), &[1, 2, 3]);
+        assert!(node(&update, 100).text_selection().is_none());
+    }
+
+    #[test]
+    fn no_tree_exports_empty_window() {
+        let update = tree_update(None);
+        assert_eq!(update.nodes.len(), 1);
+        assert_eq!(update.focus, NodeId(EMPTY_ROOT));
+    }
+}
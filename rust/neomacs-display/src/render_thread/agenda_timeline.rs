@@ -0,0 +1,735 @@
+//! Agenda timeline overlay state.
+//!
+//! A panel over the frame drawing agenda items as a gantt chart: one
+//! row per item with its label on the left and a bar from its start to
+//! its end (a diamond for an item without duration), under a time axis
+//! whose ticks follow the zoom level (hours, days, weeks or months) and
+//! with a line at the current time.  The arrow keys, the mouse wheel or
+//! dragging pan, `+`/`-` or Ctrl+wheel zoom, `t` goes to today and `f`
+//! fits all items; pan and zoom ease to their target on the animation
+//! clock.  Enter or a click on an item reports it to Emacs, which jumps
+//! to its entry.
+
+use winit::keyboard::{Key, NamedKey};
+
+use super::RenderApp;
+use crate::thread_comm::{AgendaTimelineItem, InputEvent};
+
+const HOUR: f64 = 3600.0;
+const DAY: f64 = 86400.0;
+/// Narrowest and widest time span the chart can show
+const MIN_SPAN: f64 = 2.0 * HOUR;
+const MAX_SPAN: f64 = 20.0 * 365.0 * DAY;
+/// Fraction of the span the arrow keys pan by
+const PAN_STEP: f64 = 0.2;
+/// Rate (per second) at which the view approaches its target
+const EASE_RATE: f64 = 14.0;
+/// Narrowest gap between two tick labels, in pixels
+const MIN_TICK_GAP: f32 = 48.0;
+/// Mouse travel after which a press pans instead of clicking
+const DRAG_THRESHOLD: f32 = 3.0;
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// Civil date (year, month 1-12, day 1-31) of DAYS days since
+/// 1970-01-01.
+pub(super) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Days since 1970-01-01 of the civil date YEAR-MONTH-DAY.
+pub(super) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (i64::from(month) + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Weekday (0 = Sunday) of DAYS days since 1970-01-01.
+pub(super) fn weekday(days: i64) -> usize {
+    (days + 4).rem_euclid(7) as usize
+}
+
+/// Local day number and seconds into that day of time T, for a zone
+/// UTC_OFFSET seconds east of UTC.
+fn local_day(t: f64, utc_offset: i32) -> (i64, f64) {
+    let local = t + f64::from(utc_offset);
+    let day = (local / DAY).floor();
+    (day as i64, local - day * DAY)
+}
+
+/// Text like "Mon 14 Oct" for local day DAYS.
+fn day_label(days: i64) -> String {
+    let (_, month, day) = civil_from_days(days);
+    format!("{} {} {}", WEEKDAYS[weekday(days)], day, MONTHS[month as usize - 1])
+}
+
+/// Text like "Mon 14 Oct 09:30" for time T.
+pub(crate) fn format_time(t: f64, utc_offset: i32) -> String {
+    let (days, secs) = local_day(t, utc_offset);
+    let minutes = (secs / 60.0) as u32;
+    format!("{} {:02}:{:02}", day_label(days), minutes / 60, minutes % 60)
+}
+
+/// A labelled line of the time axis
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TimeTick {
+    /// Seconds since the epoch
+    pub(crate) time: f64,
+    pub(crate) label: String,
+    /// Major ticks (days over hours, months over days and weeks, years
+    /// over months) get the upper header row and a stronger line
+    pub(crate) major: bool,
+}
+
+/// Ticks of the axis for the time span START..START+SPAN drawn WIDTH
+/// pixels wide in a zone UTC_OFFSET seconds east of UTC, in time order
+/// with the major ticks first where both fall on the same time.  The
+/// minor unit is the smallest of 1-12 hours, days, weeks and months
+/// whose labels are at least `MIN_TICK_GAP` pixels apart.
+pub(crate) fn time_ticks(start: f64, span: f64, width: f32, utc_offset: i32) -> Vec<TimeTick> {
+    let mut ticks = Vec::new();
+    if span <= 0.0 || width <= 0.0 {
+        return ticks;
+    }
+    let px_per_sec = f64::from(width) / span;
+    let end = start + span;
+    let offset = f64::from(utc_offset);
+    let (first_day, _) = local_day(start, utc_offset);
+    let (last_day, _) = local_day(end, utc_offset);
+    let day_start = |days: i64| days as f64 * DAY - offset;
+    let min_gap = f64::from(MIN_TICK_GAP);
+
+    let hour_step = [1.0, 2.0, 3.0, 6.0, 12.0]
+        .into_iter()
+        .find(|h| h * HOUR * px_per_sec >= min_gap);
+    if let Some(step) = hour_step {
+        // Hours, with the days as major ticks
+        for days in first_day..=last_day {
+            let midnight = day_start(days);
+            if midnight >= start && midnight <= end {
+                ticks.push(TimeTick { time: midnight, label: day_label(days), major: true });
+            }
+            let mut hour = step;
+            while hour < 24.0 {
+                let t = midnight + hour * HOUR;
+                if t >= start && t <= end {
+                    ticks.push(TimeTick { time: t, label: format!("{:02}:00", hour as u32), major: false });
+                }
+                hour += step;
+            }
+        }
+        // Days start in order, no sorting needed
+        return ticks;
+    }
+
+    let month_tick = |year: i64, month: u32| {
+        let t = day_start(days_from_civil(year, month, 1));
+        let label = format!("{} {}", MONTHS[month as usize - 1], year);
+        TimeTick { time: t, label, major: true }
+    };
+    let (first_year, first_month, _) = civil_from_days(first_day);
+    let (last_year, last_month, _) = civil_from_days(last_day);
+    let months = (first_year * 12 + i64::from(first_month) - 1)..=(last_year * 12 + i64::from(last_month) - 1);
+
+    if DAY * px_per_sec >= min_gap / 2.0 || 7.0 * DAY * px_per_sec >= min_gap {
+        // Days or weeks (starting on Monday), with the months as major
+        // ticks
+        let weekly = DAY * px_per_sec < min_gap / 2.0;
+        for m in months {
+            let tick = month_tick(m.div_euclid(12), (m.rem_euclid(12) + 1) as u32);
+            if tick.time >= start && tick.time <= end {
+                ticks.push(tick);
+            }
+        }
+        for days in first_day..=last_day {
+            let t = day_start(days);
+            if t < start || t > end || (weekly && weekday(days) != 1) {
+                continue;
+            }
+            let (_, _, day) = civil_from_days(days);
+            ticks.push(TimeTick { time: t, label: day.to_string(), major: false });
+        }
+    } else {
+        // Months (every one, every three or every six), with the years
+        // as major ticks
+        let month_px = 30.4 * DAY * px_per_sec;
+        let step = [1, 3, 6, 12].into_iter().find(|&s| s as f64 * month_px >= min_gap).unwrap_or(12);
+        for m in months {
+            let (year, month) = (m.div_euclid(12), (m.rem_euclid(12) + 1) as u32);
+            let mut tick = month_tick(year, month);
+            if tick.time < start || tick.time > end || (month - 1) % step != 0 {
+                continue;
+            }
+            if month == 1 {
+                tick.label = year.to_string();
+                ticks.push(tick);
+            } else {
+                tick.label = MONTHS[month as usize - 1].to_string();
+                tick.major = false;
+                ticks.push(tick);
+            }
+        }
+    }
+    ticks.sort_by(|a, b| a.time.total_cmp(&b.time).then(b.major.cmp(&a.major)));
+    ticks
+}
+
+/// A press on the chart, panning it once the mouse moves
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TimelineDrag {
+    /// Where the button went down
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    /// View start when the button went down
+    pub(crate) view_start: f64,
+    /// Whether the mouse moved far enough to be a drag
+    pub(crate) moved: bool,
+}
+
+pub(crate) struct AgendaTimelineState {
+    /// Items shown, one per row in this order
+    pub(crate) items: Vec<AgendaTimelineItem>,
+    /// Optional title shown in the header
+    pub(crate) title: Option<String>,
+    /// Current time (seconds since the epoch), drawn as a line
+    pub(crate) now: f64,
+    /// Offset of the local time zone from UTC, in seconds
+    pub(crate) utc_offset: i32,
+    /// Face foreground color (sRGB 0.0-1.0), None = default
+    pub(crate) face_fg: Option<(f32, f32, f32)>,
+    /// Face background color (sRGB 0.0-1.0), None = default
+    pub(crate) face_bg: Option<(f32, f32, f32)>,
+    /// Panel (x, y, width, height) in logical pixels
+    pub(crate) bounds: (f32, f32, f32, f32),
+    /// Width of the label column left of the chart
+    pub(crate) label_width: f32,
+    /// Height of a row, header and footer lines included
+    pub(crate) line_height: f32,
+    /// Advance of a character of the overlay font
+    pub(crate) char_width: f32,
+    pub(crate) padding: f32,
+    /// Time span shown (start in seconds since the epoch, span in
+    /// seconds), and the one it is easing to
+    pub(crate) view_start: f64,
+    pub(crate) view_span: f64,
+    pub(crate) target_start: f64,
+    pub(crate) target_span: f64,
+    /// First row shown
+    pub(crate) scroll_row: usize,
+    /// Selected item, and the one under the mouse
+    pub(crate) selected: Option<usize>,
+    pub(crate) hover: Option<usize>,
+    pub(crate) drag: Option<TimelineDrag>,
+}
+
+impl AgendaTimelineState {
+    /// Lay out a timeline of ITEMS over most of a SCREEN_W x SCREEN_H
+    /// window, showing all of them.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn new(
+        items: Vec<AgendaTimelineItem>,
+        title: Option<String>,
+        now: f64,
+        utc_offset: i32,
+        screen_w: f32, screen_h: f32,
+        font_size: f32, line_height: f32,
+    ) -> Self {
+        let padding = 10.0_f32;
+        let row_h = line_height + 6.0;
+        let char_width = font_size * 0.6;
+        let w = (screen_w * 0.9).floor();
+        let h = (screen_h * 0.8).floor().max(6.0 * row_h + 2.0 * padding);
+        let x = ((screen_w - w) / 2.0).max(0.0).floor();
+        let y = ((screen_h - h) / 2.0).max(0.0).floor();
+        let longest = items.iter().map(|item| Self::item_label(item).chars().count()).max().unwrap_or(0);
+        let label_width = ((longest + 1) as f32 * char_width).min((w * 0.3).floor()).max(8.0 * char_width);
+        let mut timeline = AgendaTimelineState {
+            selected: if items.is_empty() { None } else { Some(0) },
+            items,
+            title,
+            now,
+            utc_offset,
+            face_fg: None,
+            face_bg: None,
+            bounds: (x, y, w, h),
+            label_width,
+            line_height: row_h,
+            char_width,
+            padding,
+            view_start: now - 3.0 * DAY,
+            view_span: 14.0 * DAY,
+            target_start: now - 3.0 * DAY,
+            target_span: 14.0 * DAY,
+            scroll_row: 0,
+            hover: None,
+            drag: None,
+        };
+        timeline.fit();
+        timeline.view_start = timeline.target_start;
+        timeline.view_span = timeline.target_span;
+        timeline
+    }
+
+    /// Text of ITEM in the label column.
+    pub(crate) fn item_label(item: &AgendaTimelineItem) -> String {
+        if item.category.is_empty() {
+            item.label.clone()
+        } else {
+            format!("{}: {}", item.category, item.label)
+        }
+    }
+
+    /// Footer text describing item I: its label and when it happens.
+    pub(crate) fn describe(&self, i: usize) -> String {
+        let item = &self.items[i];
+        let mut text = format!("{}  {}", Self::item_label(item), format_time(item.start, self.utc_offset));
+        if item.end > item.start {
+            text.push_str(" - ");
+            text.push_str(&format_time(item.end, self.utc_offset));
+        }
+        if item.done {
+            text.push_str("  (done)");
+        }
+        text
+    }
+
+    /// Chart area (x, y, width, height): right of the labels, between
+    /// the header (title and two axis rows) and the footer.
+    pub(crate) fn chart_rect(&self) -> (f32, f32, f32, f32) {
+        let (x, y, w, h) = self.bounds;
+        let left = x + self.padding + self.label_width;
+        let top = y + self.padding + 3.0 * self.line_height;
+        (
+            left,
+            top,
+            (x + w - self.padding - left).max(1.0),
+            (h - 2.0 * self.padding - 4.0 * self.line_height).max(self.line_height),
+        )
+    }
+
+    /// Rows that fit in the chart.
+    pub(crate) fn visible_rows(&self) -> usize {
+        let (_, _, _, ch) = self.chart_rect();
+        ((ch / self.line_height).floor() as usize).max(1)
+    }
+
+    /// X coordinate of time T in the current view.
+    pub(crate) fn time_to_x(&self, t: f64) -> f32 {
+        let (cx, _, cw, _) = self.chart_rect();
+        cx + ((t - self.view_start) / self.view_span * f64::from(cw)) as f32
+    }
+
+    /// Time at X coordinate X in the current view.
+    pub(crate) fn x_to_time(&self, x: f32) -> f64 {
+        let (cx, _, cw, _) = self.chart_rect();
+        self.view_start + f64::from(x - cx) / f64::from(cw) * self.view_span
+    }
+
+    /// Ticks of the axis for the current view.
+    pub(crate) fn ticks(&self) -> Vec<TimeTick> {
+        let (_, _, cw, _) = self.chart_rect();
+        time_ticks(self.view_start, self.view_span, cw, self.utc_offset)
+    }
+
+    /// Ease toward showing START..START+SPAN, within the span limits.
+    fn set_target(&mut self, start: f64, span: f64) {
+        self.target_span = span.clamp(MIN_SPAN, MAX_SPAN);
+        self.target_start = start + (span - self.target_span) / 2.0;
+    }
+
+    /// Show all items (and now) with a margin on both sides, or the
+    /// fortnight around now when there are none.
+    pub(super) fn fit(&mut self) {
+        let (lo, hi) = self.items.iter().fold((self.now, self.now), |(lo, hi), item| {
+            (lo.min(item.start), hi.max(item.end.max(item.start)))
+        });
+        if hi - lo < DAY {
+            let mid = (lo + hi) / 2.0;
+            self.set_target(mid - 7.0 * DAY, 14.0 * DAY);
+        } else {
+            let margin = (hi - lo) * 0.05;
+            self.set_target(lo - margin, hi - lo + 2.0 * margin);
+        }
+    }
+
+    /// Pan so that now is a fifth of the way into the view, keeping the
+    /// zoom.
+    pub(super) fn go_to_now(&mut self) {
+        self.target_start = self.now - self.target_span * 0.2;
+    }
+
+    /// Pan by FRACTION of the span shown (negative = earlier).
+    pub(super) fn pan(&mut self, fraction: f64) {
+        self.target_start += self.target_span * fraction;
+    }
+
+    /// Zoom by FACTOR (below 1 = in) keeping the time at X, relative to
+    /// the chart width (0.0-1.0), in place.
+    pub(super) fn zoom(&mut self, factor: f64, x: f64) {
+        let anchor = self.target_start + self.target_span * x;
+        self.target_span = (self.target_span * factor).clamp(MIN_SPAN, MAX_SPAN);
+        self.target_start = anchor - self.target_span * x;
+    }
+
+    /// Fraction of the chart width at X coordinate X, clamped to it.
+    pub(super) fn chart_fraction(&self, x: f32) -> f64 {
+        let (cx, _, cw, _) = self.chart_rect();
+        f64::from(((x - cx) / cw).clamp(0.0, 1.0))
+    }
+
+    /// Move the view STEPS animation steps of STEP_SECS seconds toward
+    /// its target.  Returns true while it is still moving.
+    pub(super) fn tick(&mut self, steps: u32, step_secs: f32) -> bool {
+        if self.view_start == self.target_start && self.view_span == self.target_span {
+            return false;
+        }
+        let k = 1.0 - (-EASE_RATE * f64::from(step_secs) * f64::from(steps)).exp();
+        self.view_start += (self.target_start - self.view_start) * k;
+        self.view_span += (self.target_span - self.view_span) * k;
+        // Snap once less than a hundredth of a pixel is left
+        let epsilon = self.target_span * 1e-5;
+        if (self.view_start - self.target_start).abs() < epsilon
+            && (self.view_span - self.target_span).abs() < epsilon
+        {
+            self.view_start = self.target_start;
+            self.view_span = self.target_span;
+        }
+        true
+    }
+
+    /// Scroll the rows by ROWS (negative = up).  Returns true if the
+    /// first row shown changed.
+    pub(super) fn scroll(&mut self, rows: isize) -> bool {
+        let max = self.items.len().saturating_sub(self.visible_rows());
+        let row = self.scroll_row.saturating_add_signed(rows).min(max);
+        let changed = row != self.scroll_row;
+        self.scroll_row = row;
+        changed
+    }
+
+    /// Select item I, scrolling its row into view and panning to it if
+    /// its bar is off the chart.
+    pub(super) fn select(&mut self, i: usize) {
+        let Some(item) = self.items.get(i) else {
+            return;
+        };
+        let (start, end) = (item.start, item.end.max(item.start));
+        self.selected = Some(i);
+        let rows = self.visible_rows();
+        if i < self.scroll_row {
+            self.scroll_row = i;
+        } else if i >= self.scroll_row + rows {
+            self.scroll_row = i + 1 - rows;
+        }
+        let target_end = self.target_start + self.target_span;
+        if end < self.target_start || start > target_end {
+            self.target_start = (start + end) / 2.0 - self.target_span / 2.0;
+        }
+    }
+
+    /// Move the selection by DELTA items.
+    pub(super) fn move_selection(&mut self, delta: isize) {
+        if self.items.is_empty() {
+            return;
+        }
+        let i = match self.selected {
+            Some(i) => i.saturating_add_signed(delta).min(self.items.len() - 1),
+            None => 0,
+        };
+        self.select(i);
+    }
+
+    /// The item whose label or bar is at (X, Y).
+    pub(super) fn hit_test(&self, x: f32, y: f32) -> Option<usize> {
+        let (cx, cy, cw, ch) = self.chart_rect();
+        if y < cy || y >= cy + ch || x < self.bounds.0 + self.padding || x >= cx + cw {
+            return None;
+        }
+        let i = self.scroll_row + ((y - cy) / self.line_height) as usize;
+        let item = self.items.get(i)?;
+        if x < cx {
+            return Some(i);
+        }
+        // Bars and milestones are easier to hit with some slack
+        let slack = self.line_height / 2.0;
+        let x0 = self.time_to_x(item.start);
+        let x1 = self.time_to_x(item.end.max(item.start));
+        (x >= x0 - slack && x <= x1 + slack).then_some(i)
+    }
+
+    /// Whether (X, Y) is inside the panel.
+    pub(super) fn contains(&self, x: f32, y: f32) -> bool {
+        let (bx, by, bw, bh) = self.bounds;
+        x >= bx && x < bx + bw && y >= by && y < by + bh
+    }
+}
+
+impl RenderApp {
+    /// Handle a key press while the agenda timeline is shown.  TEXT is
+    /// the text the key produces, if any.
+    pub(super) fn agenda_timeline_key(&mut self, key: Key<&str>, text: Option<&str>) {
+        let Some(timeline) = self.agenda_timeline.as_mut() else {
+            return;
+        };
+        let page = timeline.visible_rows() as isize;
+        match key {
+            Key::Named(NamedKey::Escape) => {
+                self.finish_agenda_timeline(-1);
+                return;
+            }
+            Key::Named(NamedKey::Enter) => {
+                let index = timeline.selected.map_or(-1, |i| i as i32);
+                if index >= 0 {
+                    self.finish_agenda_timeline(index);
+                }
+                return;
+            }
+            Key::Named(NamedKey::ArrowLeft) => timeline.pan(-PAN_STEP),
+            Key::Named(NamedKey::ArrowRight) => timeline.pan(PAN_STEP),
+            Key::Named(NamedKey::ArrowUp) => timeline.move_selection(-1),
+            Key::Named(NamedKey::ArrowDown) => timeline.move_selection(1),
+            Key::Named(NamedKey::PageUp) => timeline.move_selection(-page),
+            Key::Named(NamedKey::PageDown) => timeline.move_selection(page),
+            Key::Named(NamedKey::Home) => timeline.move_selection(isize::MIN),
+            Key::Named(NamedKey::End) => timeline.move_selection(isize::MAX),
+            _ => match text {
+                Some("q") => {
+                    self.finish_agenda_timeline(-1);
+                    return;
+                }
+                Some("+" | "=") => timeline.zoom(0.5, 0.5),
+                Some("-") => timeline.zoom(2.0, 0.5),
+                Some("t") => timeline.go_to_now(),
+                Some("f") => timeline.fit(),
+                _ => return,
+            },
+        }
+        self.frame_dirty = true;
+    }
+
+    /// Handle a left button press (PRESSED) or release at (X, Y) while
+    /// the agenda timeline is shown: a press outside the panel closes
+    /// it, a release without dragging opens the item under the mouse.
+    pub(super) fn agenda_timeline_click(&mut self, pressed: bool, x: f32, y: f32) {
+        let Some(timeline) = self.agenda_timeline.as_mut() else {
+            return;
+        };
+        if pressed {
+            if !timeline.contains(x, y) {
+                self.finish_agenda_timeline(-1);
+                return;
+            }
+            timeline.drag = Some(TimelineDrag { x, y, view_start: timeline.view_start, moved: false });
+            return;
+        }
+        let Some(drag) = timeline.drag.take() else {
+            return;
+        };
+        if !drag.moved {
+            if let Some(i) = timeline.hit_test(x, y) {
+                self.finish_agenda_timeline(i as i32);
+            }
+        }
+    }
+
+    /// Track the mouse at (X, Y) over the agenda timeline: pan while a
+    /// button is held, otherwise highlight the item under it.
+    pub(super) fn agenda_timeline_mouse_move(&mut self, x: f32, y: f32) {
+        let Some(timeline) = self.agenda_timeline.as_mut() else {
+            return;
+        };
+        if let Some(mut drag) = timeline.drag {
+            if !drag.moved && (x - drag.x).hypot(y - drag.y) < DRAG_THRESHOLD {
+                return;
+            }
+            drag.moved = true;
+            timeline.drag = Some(drag);
+            let (_, _, cw, _) = timeline.chart_rect();
+            // The chart follows the mouse without easing
+            let start = drag.view_start - f64::from(x - drag.x) / f64::from(cw) * timeline.view_span;
+            timeline.view_start = start;
+            timeline.target_start = start;
+            timeline.target_span = timeline.view_span;
+            self.frame_dirty = true;
+        } else {
+            let hover = timeline.hit_test(x, y);
+            if hover != timeline.hover {
+                timeline.hover = hover;
+                self.frame_dirty = true;
+            }
+        }
+    }
+
+    /// Handle the mouse wheel (DX, DY) over the agenda timeline: Ctrl
+    /// zooms around the mouse, Shift or a horizontal wheel pans, the
+    /// vertical wheel scrolls the rows.
+    pub(super) fn agenda_timeline_wheel(&mut self, dx: f32, dy: f32, ctrl: bool, shift: bool) {
+        let mouse_x = self.mouse_pos.0;
+        let Some(timeline) = self.agenda_timeline.as_mut() else {
+            return;
+        };
+        if ctrl {
+            if dy != 0.0 {
+                let at = timeline.chart_fraction(mouse_x);
+                timeline.zoom(if dy > 0.0 { 0.8 } else { 1.25 }, at);
+                self.frame_dirty = true;
+            }
+            return;
+        }
+        let pan = if shift { dy } else { -dx };
+        if pan != 0.0 {
+            timeline.pan(if pan > 0.0 { -PAN_STEP / 2.0 } else { PAN_STEP / 2.0 });
+            self.frame_dirty = true;
+        } else {
+            let rows = if dy > 0.0 { -3 } else if dy < 0.0 { 3 } else { 0 };
+            if timeline.scroll(rows) {
+                self.frame_dirty = true;
+            }
+        }
+    }
+
+    /// Close the agenda timeline, reporting the item at INDEX to Emacs,
+    /// or -1 if it was closed without choosing one.
+    pub(super) fn finish_agenda_timeline(&mut self, index: i32) {
+        if self.agenda_timeline.take().is_none() {
+            return;
+        }
+        self.comms.send_input(InputEvent::AgendaTimelineSelection { index });
+        self.frame_dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2026-10-14 00:00 UTC, a Wednesday
+    const T0: f64 = 1_791_936_000.0;
+
+    fn item(label: &str, start: f64, end: f64) -> AgendaTimelineItem {
+        AgendaTimelineItem {
+            label: label.to_string(),
+            category: String::new(),
+            start,
+            end,
+            color: None,
+            done: false,
+        }
+    }
+
+    fn timeline(items: Vec<AgendaTimelineItem>) -> AgendaTimelineState {
+        AgendaTimelineState::new(items, None, T0 + 10.0 * HOUR, 0, 1000.0, 800.0, 13.0, 17.0)
+    }
+
+    #[test]
+    fn civil_dates_round_trip() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        let days = (T0 / DAY) as i64;
+        assert_eq!(civil_from_days(days), (2026, 10, 14));
+        assert_eq!(days_from_civil(2026, 10, 14), days);
+        assert_eq!(days_from_civil(2024, 3, 1) - days_from_civil(2024, 2, 28), 2);
+        assert_eq!(WEEKDAYS[weekday(days)], "Wed");
+        assert_eq!(format_time(T0 + 9.5 * HOUR, 3600), "Wed 14 Oct 10:30");
+        assert_eq!(format_time(T0 - 1.0, 0), "Tue 13 Oct 23:59");
+    }
+
+    #[test]
+    fn ticks_follow_the_zoom_level() {
+        // A day over 960 pixels: hourly ticks, midnight as a major one
+        let ticks = time_ticks(T0 - 2.0 * HOUR, DAY, 960.0, 0);
+        assert_eq!(ticks.iter().filter(|t| t.major).count(), 1);
+        assert_eq!(ticks[0].label, "22:00");
+        assert_eq!(ticks[1], TimeTick { time: T0, label: "Wed 14 Oct".into(), major: true });
+        assert_eq!(ticks[2].label, "02:00");
+        // Local midnight moves with the zone
+        let ticks = time_ticks(T0 - 2.0 * HOUR, DAY, 960.0, 3600);
+        assert!(ticks.iter().any(|t| t.major && t.time == T0 - HOUR));
+
+        // A month: days with the month as major tick
+        let ticks = time_ticks(T0, 30.0 * DAY, 1000.0, 0);
+        assert!(ticks.iter().any(|t| t.major && t.label == "Nov 2026"));
+        assert!(ticks.iter().any(|t| !t.major && t.label == "15"));
+        // A quarter over 700 pixels: Mondays only
+        let ticks = time_ticks(T0, 90.0 * DAY, 700.0, 0);
+        let minor: Vec<_> = ticks.iter().filter(|t| !t.major).collect();
+        assert!(minor.iter().all(|t| weekday((t.time / DAY) as i64) == 1));
+        assert_eq!(minor[0].label, "19");
+        // Years: months, with January as the year
+        let ticks = time_ticks(T0, 3.0 * 365.0 * DAY, 1000.0, 0);
+        assert!(ticks.iter().any(|t| t.major && t.label == "2027"));
+        assert!(ticks.iter().all(|t| t.label != "Jan"));
+        for pair in ticks.windows(2) {
+            assert!(pair[0].time <= pair[1].time);
+        }
+    }
+
+    #[test]
+    fn fit_zoom_and_easing() {
+        let mut tl = timeline(vec![item("a", T0, T0 + DAY), item("b", T0 + 9.0 * DAY, T0 + 9.0 * DAY)]);
+        // All items are shown from the start, without animating
+        assert!(tl.view_start < T0 && tl.view_start + tl.view_span > T0 + 9.0 * DAY);
+        assert!(!tl.tick(1, 1.0 / 240.0));
+
+        // Zooming keeps the anchored time in place
+        let anchor = tl.x_to_time(tl.chart_rect().0 + tl.chart_rect().2 * 0.25);
+        tl.zoom(0.5, 0.25);
+        assert!((tl.target_start + tl.target_span * 0.25 - anchor).abs() < 1.0);
+        tl.zoom(1e-9, 0.5);
+        assert_eq!(tl.target_span, MIN_SPAN);
+
+        // The view eases to the target and stops there
+        assert!(tl.tick(1, 1.0 / 240.0));
+        assert!(tl.view_span > tl.target_span);
+        let mut steps = 0;
+        while tl.tick(4, 1.0 / 240.0) {
+            steps += 1;
+            assert!(steps < 500);
+        }
+        assert_eq!((tl.view_start, tl.view_span), (tl.target_start, tl.target_span));
+
+        tl.go_to_now();
+        assert!((tl.target_start + tl.target_span * 0.2 - tl.now).abs() < 1e-6);
+    }
+
+    #[test]
+    fn selection_scrolls_and_hit_testing() {
+        let items: Vec<_> = (0..100).map(|i| item(&format!("task {i}"), T0 + i as f64 * DAY, T0 + (i as f64 + 0.5) * DAY)).collect();
+        let mut tl = timeline(items);
+        let rows = tl.visible_rows();
+        assert!(rows < 100);
+        tl.move_selection(isize::MAX);
+        assert_eq!(tl.selected, Some(99));
+        assert_eq!(tl.scroll_row, 100 - rows);
+        tl.move_selection(isize::MIN);
+        assert_eq!((tl.selected, tl.scroll_row), (Some(0), 0));
+        assert!(!tl.scroll(-1));
+        assert!(tl.scroll(3));
+
+        // Labels and bars hit their row's item, empty chart does not
+        let (cx, cy, _, _) = tl.chart_rect();
+        let row_y = cy + tl.line_height * 1.5;
+        assert_eq!(tl.hit_test(cx - 5.0, row_y), Some(4));
+        let bar_x = tl.time_to_x(T0 + 4.25 * DAY);
+        assert_eq!(tl.hit_test(bar_x, row_y), Some(4));
+        assert_eq!(tl.hit_test(tl.time_to_x(T0 + 60.0 * DAY), row_y), None);
+        assert_eq!(tl.hit_test(cx, cy - 1.0), None);
+        assert!(tl.describe(4).starts_with("task 4  Sun 18 Oct 00:00 - Sun 18 Oct 12:00"));
+    }
+}
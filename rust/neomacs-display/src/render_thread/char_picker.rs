@@ -0,0 +1,316 @@
+//! Character picker overlay state.
+//!
+//! A centered panel with a search field and a grid of emoji / Unicode
+//! characters.  Typing filters the grid with the fuzzy matcher over each
+//! entry's name and shortcodes; the arrow keys or the mouse move the
+//! selection and Enter or a click picks it.  The name and shortcodes of
+//! the selected character are shown below the grid.
+
+use winit::keyboard::{Key, NamedKey};
+
+use super::RenderApp;
+use crate::core::matcher;
+use crate::thread_comm::{CharPickerEntry, InputEvent};
+
+pub(crate) struct CharPickerState {
+    /// All characters offered, in their original order
+    pub(crate) entries: Vec<CharPickerEntry>,
+    /// Optional title shown before the query
+    pub(crate) title: Option<String>,
+    /// Current search text
+    pub(crate) query: String,
+    /// Indices into `entries` matching the query, best first
+    pub(crate) filtered: Vec<usize>,
+    /// Selected position in `filtered`
+    pub(crate) selected: usize,
+    /// First grid row shown
+    pub(crate) scroll_row: usize,
+    /// Face foreground color (sRGB 0.0-1.0), None = default
+    pub(crate) face_fg: Option<(f32, f32, f32)>,
+    /// Face background color (sRGB 0.0-1.0), None = default
+    pub(crate) face_bg: Option<(f32, f32, f32)>,
+    /// Panel (x, y, width, height) in logical pixels
+    pub(crate) bounds: (f32, f32, f32, f32),
+    /// Grid columns and visible rows
+    pub(crate) columns: usize,
+    pub(crate) rows: usize,
+    /// Side of a grid cell
+    pub(crate) cell_size: f32,
+    /// Height of the search and name lines
+    pub(crate) line_height: f32,
+    pub(crate) padding: f32,
+}
+
+impl CharPickerState {
+    /// Lay out a picker for ENTRIES centered on a SCREEN_W x SCREEN_H
+    /// window.
+    pub(super) fn new(
+        entries: Vec<CharPickerEntry>,
+        title: Option<String>,
+        screen_w: f32, screen_h: f32,
+        font_size: f32, line_height: f32,
+    ) -> Self {
+        let padding = 8.0_f32;
+        let cell_size = (font_size * 2.4).round();
+        let row_h = line_height + 6.0;
+        let columns = (((screen_w * 0.6 - 2.0 * padding) / cell_size).floor() as usize).clamp(4, 12);
+        let rows = (((screen_h * 0.6 - 2.0 * row_h - 2.0 * padding) / cell_size).floor() as usize).clamp(2, 8);
+        let w = columns as f32 * cell_size + 2.0 * padding;
+        let h = rows as f32 * cell_size + 2.0 * row_h + 2.0 * padding;
+        let x = ((screen_w - w) / 2.0).max(0.0).floor();
+        let y = ((screen_h - h) / 3.0).max(0.0).floor();
+        let filtered = (0..entries.len()).collect();
+        CharPickerState {
+            entries,
+            title,
+            query: String::new(),
+            filtered,
+            selected: 0,
+            scroll_row: 0,
+            face_fg: None,
+            face_bg: None,
+            bounds: (x, y, w, h),
+            columns,
+            rows,
+            cell_size,
+            line_height: row_h,
+            padding,
+        }
+    }
+
+    fn refilter(&mut self) {
+        self.filtered = matcher::rank(
+            &self.query,
+            self.entries.iter().map(|e| {
+                std::iter::once(e.name.as_str()).chain(e.shortcodes.iter().map(String::as_str))
+            }),
+        );
+        self.selected = 0;
+        self.scroll_row = 0;
+    }
+
+    /// Append C to the query.
+    pub(super) fn push_char(&mut self, c: char) {
+        self.query.push(c);
+        self.refilter();
+    }
+
+    /// Delete the last character of the query.  Returns false if it was
+    /// already empty.
+    pub(super) fn pop_char(&mut self) -> bool {
+        if self.query.pop().is_none() {
+            return false;
+        }
+        self.refilter();
+        true
+    }
+
+    /// Move the selection DX columns and DY rows, staying on the grid.
+    /// Returns true if it moved.
+    pub(super) fn move_selection(&mut self, dx: i32, dy: i32) -> bool {
+        if self.filtered.is_empty() {
+            return false;
+        }
+        let last = self.filtered.len() as i64 - 1;
+        let target = (self.selected as i64 + dx as i64 + dy as i64 * self.columns as i64).clamp(0, last);
+        if target as usize == self.selected {
+            return false;
+        }
+        self.selected = target as usize;
+        self.ensure_visible();
+        true
+    }
+
+    fn ensure_visible(&mut self) {
+        let row = self.selected / self.columns;
+        if row < self.scroll_row {
+            self.scroll_row = row;
+        } else if row >= self.scroll_row + self.rows {
+            self.scroll_row = row + 1 - self.rows;
+        }
+    }
+
+    /// Scroll the grid by ROWS without moving the selection.
+    pub(super) fn scroll(&mut self, rows: i32) -> bool {
+        let total_rows = self.filtered.len().div_ceil(self.columns);
+        let max = total_rows.saturating_sub(self.rows);
+        let new = (self.scroll_row as i64 + rows as i64).clamp(0, max as i64) as usize;
+        let changed = new != self.scroll_row;
+        self.scroll_row = new;
+        changed
+    }
+
+    /// The entry index of the selected character.
+    pub(crate) fn selected_entry(&self) -> Option<usize> {
+        self.filtered.get(self.selected).copied()
+    }
+
+    /// Top-left corner of the grid.
+    pub(crate) fn grid_origin(&self) -> (f32, f32) {
+        let (x, y, _, _) = self.bounds;
+        (x + self.padding, y + self.padding + self.line_height)
+    }
+
+    /// Cells shown, as (position in `filtered`, x, y) of each cell.
+    pub(crate) fn visible_cells(&self) -> Vec<(usize, f32, f32)> {
+        let (gx, gy) = self.grid_origin();
+        let first = self.scroll_row * self.columns;
+        let last = (first + self.rows * self.columns).min(self.filtered.len());
+        (first..last)
+            .map(|i| {
+                let r = (i - first) / self.columns;
+                let c = (i - first) % self.columns;
+                (i, gx + c as f32 * self.cell_size, gy + r as f32 * self.cell_size)
+            })
+            .collect()
+    }
+
+    /// Position in `filtered` of the cell at (X, Y).
+    pub(super) fn hit_test(&self, x: f32, y: f32) -> Option<usize> {
+        let (gx, gy) = self.grid_origin();
+        if x < gx || y < gy {
+            return None;
+        }
+        let c = ((x - gx) / self.cell_size) as usize;
+        let r = ((y - gy) / self.cell_size) as usize;
+        if c >= self.columns || r >= self.rows {
+            return None;
+        }
+        let i = (self.scroll_row + r) * self.columns + c;
+        (i < self.filtered.len()).then_some(i)
+    }
+
+    /// Whether (X, Y) is inside the panel.
+    pub(super) fn contains(&self, x: f32, y: f32) -> bool {
+        let (bx, by, bw, bh) = self.bounds;
+        x >= bx && x < bx + bw && y >= by && y < by + bh
+    }
+}
+
+impl RenderApp {
+    /// Handle a key press while the character picker is shown.  TEXT is
+    /// the text the key produces, if any.
+    pub(super) fn char_picker_key(&mut self, key: Key<&str>, text: Option<&str>) {
+        let Some(picker) = self.char_picker.as_mut() else {
+            return;
+        };
+        let changed = match key {
+            Key::Named(NamedKey::Escape) => {
+                self.finish_char_picker(false);
+                return;
+            }
+            Key::Named(NamedKey::Enter) => {
+                self.finish_char_picker(true);
+                return;
+            }
+            Key::Named(NamedKey::ArrowLeft) => picker.move_selection(-1, 0),
+            Key::Named(NamedKey::ArrowRight) => picker.move_selection(1, 0),
+            Key::Named(NamedKey::ArrowUp) => picker.move_selection(0, -1),
+            Key::Named(NamedKey::ArrowDown) => picker.move_selection(0, 1),
+            Key::Named(NamedKey::PageUp) => picker.move_selection(0, -(picker.rows as i32)),
+            Key::Named(NamedKey::PageDown) => picker.move_selection(0, picker.rows as i32),
+            Key::Named(NamedKey::Backspace) => picker.pop_char(),
+            _ => match text {
+                Some(t) if !t.is_empty() && !t.chars().any(char::is_control) => {
+                    t.chars().for_each(|c| picker.push_char(c));
+                    true
+                }
+                _ => false,
+            },
+        };
+        if changed {
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Close the character picker, reporting its selected entry to Emacs
+    /// if ACCEPT, else a cancellation.
+    pub(super) fn finish_char_picker(&mut self, accept: bool) {
+        let Some(picker) = self.char_picker.take() else {
+            return;
+        };
+        let index = if accept { picker.selected_entry().map_or(-1, |i| i as i32) } else { -1 };
+        self.comms.send_input(InputEvent::CharPickerSelection { index });
+        self.frame_dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str, name: &str, codes: &[&str]) -> CharPickerEntry {
+        CharPickerEntry {
+            text: text.to_string(),
+            name: name.to_string(),
+            shortcodes: codes.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn picker(n: usize) -> CharPickerState {
+        let entries = (0..n).map(|i| entry("x", &format!("char {}", i), &[])).collect();
+        // 800x600 with a 13px font: 8 visible rows of 12 columns
+        CharPickerState::new(entries, None, 800.0, 600.0, 13.0, 17.0)
+    }
+
+    #[test]
+    fn layout_is_centered_grid() {
+        let p = picker(100);
+        assert_eq!((p.columns, p.rows), (12, 8));
+        let (x, _, w, _) = p.bounds;
+        assert!((x + w / 2.0 - 400.0).abs() <= 1.0);
+        assert_eq!(p.visible_cells().len(), 96);
+    }
+
+    #[test]
+    fn typing_filters_by_name_and_shortcode() {
+        let mut p = CharPickerState::new(
+            vec![
+                entry("😀", "grinning face", &[":grinning:"]),
+                entry("👍", "thumbs up sign", &[":+1:", ":thumbsup:"]),
+                entry("λ", "greek small letter lamda", &[]),
+            ],
+            None, 800.0, 600.0, 13.0, 17.0,
+        );
+        for c in "thu".chars() {
+            p.push_char(c);
+        }
+        assert_eq!(p.filtered, vec![1]);
+        assert!(p.pop_char() && p.pop_char() && p.pop_char());
+        assert!(!p.pop_char());
+        assert_eq!(p.filtered, vec![0, 1, 2]);
+        p.push_char('l');
+        p.push_char('a');
+        p.push_char('m');
+        assert_eq!(p.selected_entry(), Some(2));
+    }
+
+    #[test]
+    fn selection_moves_and_scrolls() {
+        let mut p = picker(200);
+        assert!(!p.move_selection(-1, 0));
+        assert!(p.move_selection(1, 1));
+        assert_eq!(p.selected, p.columns + 1);
+        // Moving below the last visible row scrolls it into view
+        assert!(p.move_selection(0, p.rows as i32));
+        assert_eq!(p.scroll_row, 2);
+        // Clamped to the last entry
+        assert!(p.move_selection(0, 1000));
+        assert_eq!(p.selected, 199);
+        assert_eq!(p.scroll_row, 199 / p.columns + 1 - p.rows);
+    }
+
+    #[test]
+    fn hit_test_maps_cells() {
+        let mut p = picker(20);
+        let (gx, gy) = p.grid_origin();
+        let cs = p.cell_size;
+        assert_eq!(p.hit_test(gx + 1.0, gy + 1.0), Some(0));
+        assert_eq!(p.hit_test(gx + cs + 1.0, gy + cs + 1.0), Some(p.columns + 1));
+        assert_eq!(p.hit_test(gx - 1.0, gy), None);
+        // Past the last entry
+        assert_eq!(p.hit_test(gx + 1.0, gy + 3.0 * cs), None);
+        assert!(!p.scroll(1));
+        assert!(p.contains(gx, gy));
+    }
+}
@@ -0,0 +1,430 @@
+//! Color picker overlay state.
+//!
+//! A centered panel with a hue/saturation wheel, a value slider and the
+//! original and new colors side by side.  Dragging on the wheel or the
+//! slider, the arrow keys (hue and saturation), PageUp/PageDown (value)
+//! or typing six hex digits change the color; Enter or a click on the
+//! new color picks it, a click on the original color goes back to it.
+//! The eyedropper button (or `p`) closes the picker asking Emacs to pick
+//! a color from the screen, which reopens it with that color.
+
+use winit::keyboard::{Key, NamedKey};
+
+use super::RenderApp;
+use crate::thread_comm::{InputEvent, COLOR_PICKER_EYEDROPPER};
+
+/// Hue step of the arrow keys, in degrees
+const HUE_STEP: f32 = 5.0;
+/// Saturation and value step of the keys and the mouse wheel
+const LEVEL_STEP: f32 = 0.05;
+
+/// Convert HSV (hue in degrees, saturation and value 0.0-1.0) to sRGB
+/// components 0.0-1.0.
+pub(crate) fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let c = v * s;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+    (r + m, g + m, b + m)
+}
+
+/// Convert sRGB components 0.0-1.0 to HSV (hue in degrees).
+pub(crate) fn rgb_to_hsv(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let d = max - min;
+    let h = if d <= 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / d).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / d + 2.0)
+    } else {
+        60.0 * ((r - g) / d + 4.0)
+    };
+    let s = if max <= 0.0 { 0.0 } else { d / max };
+    (h, s, max)
+}
+
+/// Part of the picker under the mouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorPickerPart {
+    Wheel,
+    Value,
+    Original,
+    Current,
+    Eyedropper,
+}
+
+pub(crate) struct ColorPickerState {
+    /// Current color as HSV: hue in degrees, saturation and value 0.0-1.0
+    pub(crate) hue: f32,
+    pub(crate) saturation: f32,
+    pub(crate) value: f32,
+    /// Color the picker was opened for (0xRRGGBB)
+    pub(crate) original: u32,
+    /// Optional title shown before the hex value
+    pub(crate) title: Option<String>,
+    /// Hex digits typed so far
+    pub(crate) typed: String,
+    /// Face foreground color (sRGB 0.0-1.0), None = default
+    pub(crate) face_fg: Option<(f32, f32, f32)>,
+    /// Face background color (sRGB 0.0-1.0), None = default
+    pub(crate) face_bg: Option<(f32, f32, f32)>,
+    /// Panel (x, y, width, height) in logical pixels
+    pub(crate) bounds: (f32, f32, f32, f32),
+    pub(crate) wheel_radius: f32,
+    pub(crate) slider_width: f32,
+    /// Height of the title line and of the preview row
+    pub(crate) line_height: f32,
+    pub(crate) preview_height: f32,
+    pub(crate) padding: f32,
+    /// Part being dragged with the mouse
+    pub(crate) drag: Option<ColorPickerPart>,
+}
+
+impl ColorPickerState {
+    /// Lay out a picker showing COLOR (and ORIGINAL as the color to go
+    /// back to, both 0xRRGGBB) centered on a SCREEN_W x SCREEN_H window.
+    pub(super) fn new(
+        color: u32,
+        original: u32,
+        title: Option<String>,
+        screen_w: f32, screen_h: f32,
+        font_size: f32, line_height: f32,
+    ) -> Self {
+        let padding = 10.0_f32;
+        let row_h = line_height + 6.0;
+        let preview_height = (row_h * 1.5).round();
+        let slider_width = (line_height * 1.2).round();
+        let max_d = (screen_w.min(screen_h) * 0.6 - 2.0 * padding - row_h - preview_height).max(60.0);
+        let diameter = (font_size * 13.0).round().min(max_d).floor();
+        let w = diameter + slider_width + 3.0 * padding;
+        let h = row_h + diameter + preview_height + 3.0 * padding;
+        let x = ((screen_w - w) / 2.0).max(0.0).floor();
+        let y = ((screen_h - h) / 3.0).max(0.0).floor();
+        let mut picker = ColorPickerState {
+            hue: 0.0,
+            saturation: 0.0,
+            value: 0.0,
+            original,
+            title,
+            typed: String::new(),
+            face_fg: None,
+            face_bg: None,
+            bounds: (x, y, w, h),
+            wheel_radius: diameter / 2.0,
+            slider_width,
+            line_height: row_h,
+            preview_height,
+            padding,
+            drag: None,
+        };
+        picker.set_rgb(color);
+        picker
+    }
+
+    /// The current color as 0xRRGGBB.
+    pub(crate) fn rgb(&self) -> u32 {
+        let (r, g, b) = hsv_to_rgb(self.hue, self.saturation, self.value);
+        let byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u32;
+        (byte(r) << 16) | (byte(g) << 8) | byte(b)
+    }
+
+    /// Make COLOR (0xRRGGBB) the current color.
+    pub(crate) fn set_rgb(&mut self, color: u32) {
+        let channel = |shift: u32| ((color >> shift) & 0xFF) as f32 / 255.0;
+        let (h, s, v) = rgb_to_hsv(channel(16), channel(8), channel(0));
+        // A gray has no hue; keep the one the user was on
+        if s > 0.0 {
+            self.hue = h;
+        }
+        self.saturation = s;
+        self.value = v;
+    }
+
+    /// Change hue by DH degrees and saturation and value by DS and DV.
+    /// Returns true if the color changed.
+    pub(super) fn adjust(&mut self, dh: f32, ds: f32, dv: f32) -> bool {
+        let before = (self.hue, self.saturation, self.value);
+        self.hue = (self.hue + dh).rem_euclid(360.0);
+        self.saturation = (self.saturation + ds).clamp(0.0, 1.0);
+        self.value = (self.value + dv).clamp(0.0, 1.0);
+        before != (self.hue, self.saturation, self.value)
+    }
+
+    /// Add hex digit C to the typed value; the sixth digit sets the
+    /// color.  Returns false if C is not a hex digit.
+    pub(super) fn push_hex(&mut self, c: char) -> bool {
+        if !c.is_ascii_hexdigit() {
+            return false;
+        }
+        self.typed.push(c.to_ascii_lowercase());
+        if self.typed.len() == 6 {
+            if let Ok(color) = u32::from_str_radix(&self.typed, 16) {
+                self.set_rgb(color);
+            }
+            self.typed.clear();
+        }
+        true
+    }
+
+    /// Center of the wheel.
+    pub(crate) fn wheel_center(&self) -> (f32, f32) {
+        let (x, y, _, _) = self.bounds;
+        (
+            x + self.padding + self.wheel_radius,
+            y + self.padding + self.line_height + self.wheel_radius,
+        )
+    }
+
+    /// Point of the wheel showing hue H and saturation S: red to the
+    /// right, hue increasing counterclockwise, white at the center.
+    pub(crate) fn wheel_point(&self, h: f32, s: f32) -> (f32, f32) {
+        let (cx, cy) = self.wheel_center();
+        let (sin, cos) = h.to_radians().sin_cos();
+        (cx + cos * s * self.wheel_radius, cy - sin * s * self.wheel_radius)
+    }
+
+    /// Value slider (x, y, width, height): full value at the top.
+    pub(crate) fn slider_rect(&self) -> (f32, f32, f32, f32) {
+        let (x, y, _, _) = self.bounds;
+        (
+            x + 2.0 * self.padding + 2.0 * self.wheel_radius,
+            y + self.padding + self.line_height,
+            self.slider_width,
+            2.0 * self.wheel_radius,
+        )
+    }
+
+    /// Original color, new color and eyedropper button, as (x, y,
+    /// width, height).
+    pub(crate) fn preview_rects(&self) -> [(f32, f32, f32, f32); 3] {
+        let (x, y, _, _) = self.bounds;
+        let top = y + 2.0 * self.padding + self.line_height + 2.0 * self.wheel_radius;
+        let left = x + self.padding;
+        let (sx, _, sw, _) = self.slider_rect();
+        [
+            (left, top, self.wheel_radius, self.preview_height),
+            (left + self.wheel_radius, top, self.wheel_radius, self.preview_height),
+            (sx, top, sw, self.preview_height),
+        ]
+    }
+
+    /// The part of the picker at (X, Y).
+    pub(super) fn hit_test(&self, x: f32, y: f32) -> Option<ColorPickerPart> {
+        let inside = |(rx, ry, rw, rh): (f32, f32, f32, f32)| {
+            x >= rx && x < rx + rw && y >= ry && y < ry + rh
+        };
+        let (cx, cy) = self.wheel_center();
+        let [original, current, eyedropper] = self.preview_rects();
+        if (x - cx).hypot(y - cy) <= self.wheel_radius {
+            Some(ColorPickerPart::Wheel)
+        } else if inside(self.slider_rect()) {
+            Some(ColorPickerPart::Value)
+        } else if inside(original) {
+            Some(ColorPickerPart::Original)
+        } else if inside(current) {
+            Some(ColorPickerPart::Current)
+        } else if inside(eyedropper) {
+            Some(ColorPickerPart::Eyedropper)
+        } else {
+            None
+        }
+    }
+
+    /// Set the color from the mouse at (X, Y) on PART, clamping to its
+    /// edge when the mouse is dragged past it.  Returns true if the
+    /// color changed.
+    pub(super) fn drag_to(&mut self, part: ColorPickerPart, x: f32, y: f32) -> bool {
+        let before = (self.hue, self.saturation, self.value);
+        match part {
+            ColorPickerPart::Wheel => {
+                let (cx, cy) = self.wheel_center();
+                let (dx, dy) = (x - cx, cy - y);
+                self.saturation = (dx.hypot(dy) / self.wheel_radius).min(1.0);
+                if self.saturation > 0.0 {
+                    self.hue = dy.atan2(dx).to_degrees().rem_euclid(360.0);
+                }
+            }
+            ColorPickerPart::Value => {
+                let (_, sy, _, sh) = self.slider_rect();
+                self.value = (1.0 - (y - sy) / sh).clamp(0.0, 1.0);
+            }
+            _ => {}
+        }
+        before != (self.hue, self.saturation, self.value)
+    }
+
+    /// Whether (X, Y) is inside the panel.
+    pub(super) fn contains(&self, x: f32, y: f32) -> bool {
+        let (bx, by, bw, bh) = self.bounds;
+        x >= bx && x < bx + bw && y >= by && y < by + bh
+    }
+}
+
+impl RenderApp {
+    /// Handle a key press while the color picker is shown.  TEXT is the
+    /// text the key produces, if any.
+    pub(super) fn color_picker_key(&mut self, key: Key<&str>, text: Option<&str>) {
+        let Some(picker) = self.color_picker.as_mut() else {
+            return;
+        };
+        let changed = match key {
+            Key::Named(NamedKey::Escape) => {
+                self.finish_color_picker(-1);
+                return;
+            }
+            Key::Named(NamedKey::Enter) => {
+                let color = picker.rgb() as i32;
+                self.finish_color_picker(color);
+                return;
+            }
+            Key::Named(NamedKey::ArrowLeft) => picker.adjust(-HUE_STEP, 0.0, 0.0),
+            Key::Named(NamedKey::ArrowRight) => picker.adjust(HUE_STEP, 0.0, 0.0),
+            Key::Named(NamedKey::ArrowUp) => picker.adjust(0.0, LEVEL_STEP, 0.0),
+            Key::Named(NamedKey::ArrowDown) => picker.adjust(0.0, -LEVEL_STEP, 0.0),
+            Key::Named(NamedKey::PageUp) => picker.adjust(0.0, 0.0, LEVEL_STEP),
+            Key::Named(NamedKey::PageDown) => picker.adjust(0.0, 0.0, -LEVEL_STEP),
+            Key::Named(NamedKey::Backspace) => picker.typed.pop().is_some(),
+            _ => match text {
+                Some("p") => {
+                    self.finish_color_picker(COLOR_PICKER_EYEDROPPER);
+                    return;
+                }
+                Some(t) => t.chars().fold(false, |changed, c| picker.push_hex(c) || changed),
+                None => false,
+            },
+        };
+        if changed {
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Handle a mouse button press (PRESSED) or release at (X, Y) while
+    /// the color picker is shown.
+    pub(super) fn color_picker_click(&mut self, pressed: bool, x: f32, y: f32) {
+        let Some(picker) = self.color_picker.as_mut() else {
+            return;
+        };
+        if !pressed {
+            picker.drag = None;
+            return;
+        }
+        match picker.hit_test(x, y) {
+            Some(part @ (ColorPickerPart::Wheel | ColorPickerPart::Value)) => {
+                picker.drag = Some(part);
+                picker.drag_to(part, x, y);
+                self.frame_dirty = true;
+            }
+            Some(ColorPickerPart::Original) => {
+                let original = picker.original;
+                picker.set_rgb(original);
+                self.frame_dirty = true;
+            }
+            Some(ColorPickerPart::Current) => {
+                let color = picker.rgb() as i32;
+                self.finish_color_picker(color);
+            }
+            Some(ColorPickerPart::Eyedropper) => self.finish_color_picker(COLOR_PICKER_EYEDROPPER),
+            None if picker.contains(x, y) => {}
+            None => self.finish_color_picker(-1),
+        }
+    }
+
+    /// Close the color picker, reporting RESULT to Emacs: the chosen
+    /// color, -1 if cancelled or `COLOR_PICKER_EYEDROPPER`.
+    pub(super) fn finish_color_picker(&mut self, result: i32) {
+        if self.color_picker.take().is_none() {
+            return;
+        }
+        self.comms.send_input(InputEvent::ColorPickerSelection { color: result });
+        self.frame_dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picker(color: u32) -> ColorPickerState {
+        ColorPickerState::new(color, color, None, 800.0, 600.0, 13.0, 17.0)
+    }
+
+    #[test]
+    fn hsv_round_trips_through_rgb() {
+        for color in [0xff0000, 0x00ff00, 0x0000ff, 0x336699, 0xfafad2, 0x808080, 0x000000, 0xffffff] {
+            assert_eq!(picker(color).rgb(), color, "{:06x}", color);
+        }
+        let (h, s, v) = rgb_to_hsv(0.0, 1.0, 1.0);
+        assert_eq!((h, s, v), (180.0, 1.0, 1.0));
+        let (r, g, b) = hsv_to_rgb(-60.0, 1.0, 1.0);
+        assert_eq!((r, g, b), (1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn layout_is_centered_and_parts_do_not_overlap() {
+        let p = picker(0x336699);
+        let (x, _, w, _) = p.bounds;
+        assert!((x + w / 2.0 - 400.0).abs() <= 1.0);
+        let (cx, cy) = p.wheel_center();
+        assert_eq!(p.hit_test(cx, cy), Some(ColorPickerPart::Wheel));
+        let (sx, sy, sw, sh) = p.slider_rect();
+        assert_eq!(p.hit_test(sx + sw / 2.0, sy + sh / 2.0), Some(ColorPickerPart::Value));
+        let parts = [ColorPickerPart::Original, ColorPickerPart::Current, ColorPickerPart::Eyedropper];
+        for (&(rx, ry, rw, rh), part) in p.preview_rects().iter().zip(parts) {
+            assert_eq!(p.hit_test(rx + rw / 2.0, ry + rh / 2.0), Some(part));
+            assert!(p.contains(rx + rw - 1.0, ry + rh - 1.0));
+        }
+    }
+
+    #[test]
+    fn dragging_sets_hue_saturation_and_value() {
+        let mut p = picker(0x000000);
+        p.value = 1.0;
+        // Halfway up from the center: hue 90, half saturation
+        let (tx, ty) = p.wheel_point(90.0, 0.5);
+        assert!(p.drag_to(ColorPickerPart::Wheel, tx, ty));
+        assert!((p.hue - 90.0).abs() < 0.01 && (p.saturation - 0.5).abs() < 0.01);
+        // Past the rim clamps to full saturation
+        let (cx, cy) = p.wheel_center();
+        p.drag_to(ColorPickerPart::Wheel, cx + 3.0 * p.wheel_radius, cy);
+        assert_eq!((p.hue, p.saturation), (0.0, 1.0));
+        assert_eq!(p.rgb(), 0xff0000);
+
+        let (_, sy, _, sh) = p.slider_rect();
+        p.drag_to(ColorPickerPart::Value, 0.0, sy + sh * 0.75);
+        assert!((p.value - 0.25).abs() < 0.01);
+        p.drag_to(ColorPickerPart::Value, 0.0, sy - 50.0);
+        assert_eq!(p.value, 1.0);
+    }
+
+    #[test]
+    fn keys_adjust_and_hex_typing_sets_color() {
+        let mut p = picker(0xff0000);
+        assert!(p.adjust(-HUE_STEP, 0.0, 0.0));
+        assert_eq!(p.hue, 355.0);
+        assert!(!p.adjust(0.0, LEVEL_STEP, LEVEL_STEP));
+        assert!(p.adjust(0.0, 0.0, -0.5));
+
+        for c in "#1A2b3".chars() {
+            p.push_hex(c);
+        }
+        assert_eq!(p.typed, "1a2b3");
+        assert!(p.push_hex('c'));
+        assert!(p.typed.is_empty());
+        assert_eq!(p.rgb(), 0x1a2b3c);
+        // A gray keeps the hue it was reached from
+        let hue = p.hue;
+        p.set_rgb(0x777777);
+        assert_eq!(p.hue, hue);
+    }
+}
@@ -0,0 +1,377 @@
+//! Command palette overlay state.
+//!
+//! A centered panel replacing the minibuffer prompt of M-x: a search
+//! field over a list of commands, each with its key binding in a right
+//! column, and a pane showing the documentation of the selected one.
+//! Typing filters the list with the fuzzy matcher, highlighting the
+//! matched characters; commands used often or recently rank higher by
+//! the weight Lisp gives them.  The arrow keys, C-n/C-p or the mouse
+//! move the selection and Enter or a click runs it.
+
+use winit::keyboard::{Key, NamedKey};
+
+use super::RenderApp;
+use crate::core::matcher;
+use crate::thread_comm::{CommandPaletteEntry, InputEvent};
+
+/// A command matching the query
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PaletteMatch {
+    /// Index into `entries`
+    pub(crate) entry: usize,
+    /// Character indices of the name matched by the query
+    pub(crate) positions: Vec<usize>,
+}
+
+pub(crate) struct CommandPaletteState {
+    /// All commands offered, in their original order
+    pub(crate) entries: Vec<CommandPaletteEntry>,
+    /// Optional title shown before the query
+    pub(crate) title: Option<String>,
+    /// Current search text
+    pub(crate) query: String,
+    /// Commands matching the query, best first
+    pub(crate) matches: Vec<PaletteMatch>,
+    /// Selected position in `matches`
+    pub(crate) selected: usize,
+    /// First list row shown
+    pub(crate) scroll_row: usize,
+    /// Face foreground color (sRGB 0.0-1.0), None = default
+    pub(crate) face_fg: Option<(f32, f32, f32)>,
+    /// Face background color (sRGB 0.0-1.0), None = default
+    pub(crate) face_bg: Option<(f32, f32, f32)>,
+    /// Color of matched characters (sRGB 0.0-1.0), None = default
+    pub(crate) match_fg: Option<(f32, f32, f32)>,
+    /// Panel (x, y, width, height) in logical pixels
+    pub(crate) bounds: (f32, f32, f32, f32),
+    /// Width of the command list; the documentation pane takes the rest
+    pub(crate) list_width: f32,
+    /// Visible list rows
+    pub(crate) rows: usize,
+    /// Height of a row, the search line included
+    pub(crate) line_height: f32,
+    /// Advance of a character of the overlay font
+    pub(crate) char_width: f32,
+    pub(crate) padding: f32,
+}
+
+impl CommandPaletteState {
+    /// Lay out a palette for ENTRIES centered on a SCREEN_W x SCREEN_H
+    /// window.
+    pub(super) fn new(
+        entries: Vec<CommandPaletteEntry>,
+        title: Option<String>,
+        screen_w: f32, screen_h: f32,
+        font_size: f32, line_height: f32,
+    ) -> Self {
+        let padding = 8.0_f32;
+        let row_h = line_height + 4.0;
+        let w = (screen_w * 0.7).clamp(320.0_f32.min(screen_w), 1000.0).floor();
+        let rows = (((screen_h * 0.6 - 2.0 * padding) / row_h).floor() as usize).saturating_sub(1).clamp(3, 16);
+        let h = (rows + 1) as f32 * row_h + 2.0 * padding;
+        let x = ((screen_w - w) / 2.0).max(0.0).floor();
+        let y = ((screen_h - h) / 4.0).max(0.0).floor();
+        let mut palette = CommandPaletteState {
+            entries,
+            title,
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            scroll_row: 0,
+            face_fg: None,
+            face_bg: None,
+            match_fg: None,
+            bounds: (x, y, w, h),
+            list_width: (w * 0.6).floor(),
+            rows,
+            line_height: row_h,
+            char_width: font_size * 0.6,
+            padding,
+        };
+        palette.refilter();
+        palette
+    }
+
+    /// Rank the entries against the query: match score plus usage
+    /// weight, ties in the entries' order.
+    fn refilter(&mut self) {
+        let mut scored: Vec<(i32, PaletteMatch)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(entry, e)| {
+                let m = matcher::fuzzy_match(&self.query, &e.name)?;
+                Some((m.score + e.weight, PaletteMatch { entry, positions: m.positions }))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.entry.cmp(&b.1.entry)));
+        self.matches = scored.into_iter().map(|(_, m)| m).collect();
+        self.selected = 0;
+        self.scroll_row = 0;
+    }
+
+    /// Append C to the query.
+    pub(super) fn push_char(&mut self, c: char) {
+        self.query.push(c);
+        self.refilter();
+    }
+
+    /// Delete the last character of the query.  Returns false if it was
+    /// already empty.
+    pub(super) fn pop_char(&mut self) -> bool {
+        if self.query.pop().is_none() {
+            return false;
+        }
+        self.refilter();
+        true
+    }
+
+    /// Move the selection DY rows, staying on the list.  Returns true if
+    /// it moved.
+    pub(super) fn move_selection(&mut self, dy: i32) -> bool {
+        if self.matches.is_empty() {
+            return false;
+        }
+        let last = self.matches.len() as i64 - 1;
+        let target = (self.selected as i64 + dy as i64).clamp(0, last) as usize;
+        if target == self.selected {
+            return false;
+        }
+        self.selected = target;
+        if self.selected < self.scroll_row {
+            self.scroll_row = self.selected;
+        } else if self.selected >= self.scroll_row + self.rows {
+            self.scroll_row = self.selected + 1 - self.rows;
+        }
+        true
+    }
+
+    /// Scroll the list by ROWS without moving the selection.
+    pub(super) fn scroll(&mut self, rows: i32) -> bool {
+        let max = self.matches.len().saturating_sub(self.rows);
+        let new = (self.scroll_row as i64 + rows as i64).clamp(0, max as i64) as usize;
+        let changed = new != self.scroll_row;
+        self.scroll_row = new;
+        changed
+    }
+
+    /// The entry index of the selected command.
+    pub(super) fn selected_entry(&self) -> Option<usize> {
+        self.matches.get(self.selected).map(|m| m.entry)
+    }
+
+    /// Top-left corner of the list.
+    pub(crate) fn list_origin(&self) -> (f32, f32) {
+        let (x, y, _, _) = self.bounds;
+        (x + self.padding, y + self.padding + self.line_height)
+    }
+
+    /// Rows shown, as (position in `matches`, y) of each row.
+    pub(crate) fn visible_rows(&self) -> Vec<(usize, f32)> {
+        let (_, ly) = self.list_origin();
+        let last = (self.scroll_row + self.rows).min(self.matches.len());
+        (self.scroll_row..last)
+            .map(|i| (i, ly + (i - self.scroll_row) as f32 * self.line_height))
+            .collect()
+    }
+
+    /// Position in `matches` of the row at (X, Y).
+    pub(super) fn hit_test(&self, x: f32, y: f32) -> Option<usize> {
+        let (lx, ly) = self.list_origin();
+        if x < lx || x >= lx + self.list_width - self.padding || y < ly {
+            return None;
+        }
+        let r = ((y - ly) / self.line_height) as usize;
+        let i = self.scroll_row + r;
+        (r < self.rows && i < self.matches.len()).then_some(i)
+    }
+
+    /// Whether (X, Y) is inside the panel.
+    pub(super) fn contains(&self, x: f32, y: f32) -> bool {
+        let (bx, by, bw, bh) = self.bounds;
+        x >= bx && x < bx + bw && y >= by && y < by + bh
+    }
+
+    /// Documentation of the selected command, wrapped to the pane and
+    /// cut to the rows it has.
+    pub(crate) fn doc_lines(&self) -> Vec<String> {
+        let Some(entry) = self.selected_entry().map(|i| &self.entries[i]) else {
+            return Vec::new();
+        };
+        let (_, _, w, _) = self.bounds;
+        let columns = ((w - self.list_width - 2.0 * self.padding) / self.char_width).max(1.0) as usize;
+        let mut lines = wrap_text(&entry.doc, columns);
+        lines.truncate(self.rows);
+        lines
+    }
+}
+
+/// Break TEXT into lines of at most COLUMNS characters, at spaces when
+/// possible.  Newlines in TEXT are kept.
+pub(crate) fn wrap_text(text: &str, columns: usize) -> Vec<String> {
+    let columns = columns.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        let mut len = 0;
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            if len > 0 && len + 1 + word.len() > columns {
+                lines.push(std::mem::take(&mut line));
+                len = 0;
+            }
+            // Words longer than a line are cut
+            while len == 0 && word.len() > columns {
+                lines.push(word.drain(..columns).collect());
+            }
+            if len > 0 {
+                line.push(' ');
+                len += 1;
+            }
+            line.extend(word.iter());
+            len += word.len();
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+impl RenderApp {
+    /// Handle a key press while the command palette is shown.  TEXT is
+    /// the text the key produces, if any; CTRL whether Control is held.
+    pub(super) fn command_palette_key(&mut self, key: Key<&str>, text: Option<&str>, ctrl: bool) {
+        let Some(palette) = self.command_palette.as_mut() else {
+            return;
+        };
+        let changed = match key {
+            Key::Named(NamedKey::Escape) => {
+                self.finish_command_palette(false);
+                return;
+            }
+            Key::Character("g") if ctrl => {
+                self.finish_command_palette(false);
+                return;
+            }
+            Key::Named(NamedKey::Enter) => {
+                self.finish_command_palette(true);
+                return;
+            }
+            Key::Named(NamedKey::ArrowUp) => palette.move_selection(-1),
+            Key::Named(NamedKey::ArrowDown) => palette.move_selection(1),
+            Key::Character("p") if ctrl => palette.move_selection(-1),
+            Key::Character("n") if ctrl => palette.move_selection(1),
+            Key::Named(NamedKey::PageUp) => palette.move_selection(-(palette.rows as i32)),
+            Key::Named(NamedKey::PageDown) => palette.move_selection(palette.rows as i32),
+            Key::Named(NamedKey::Backspace) => palette.pop_char(),
+            _ if ctrl => false,
+            _ => match text {
+                Some(t) if !t.is_empty() && !t.chars().any(char::is_control) => {
+                    t.chars().for_each(|c| palette.push_char(c));
+                    true
+                }
+                _ => false,
+            },
+        };
+        if changed {
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Close the command palette, reporting its selected entry to Emacs
+    /// if ACCEPT, else a cancellation.
+    pub(super) fn finish_command_palette(&mut self, accept: bool) {
+        let Some(palette) = self.command_palette.take() else {
+            return;
+        };
+        let index = if accept { palette.selected_entry().map_or(-1, |i| i as i32) } else { -1 };
+        self.comms.send_input(InputEvent::CommandPaletteSelection { index });
+        self.frame_dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, binding: &str, weight: i32) -> CommandPaletteEntry {
+        CommandPaletteEntry {
+            name: name.to_string(),
+            binding: binding.to_string(),
+            doc: String::new(),
+            weight,
+        }
+    }
+
+    fn palette(entries: Vec<CommandPaletteEntry>) -> CommandPaletteState {
+        CommandPaletteState::new(entries, None, 800.0, 600.0, 13.0, 17.0)
+    }
+
+    #[test]
+    fn layout_is_centered_list_with_doc_pane() {
+        let p = palette((0..40).map(|i| entry(&format!("command-{}", i), "", 0)).collect());
+        let (x, _, w, _) = p.bounds;
+        assert!((x + w / 2.0 - 400.0).abs() <= 1.0);
+        assert!(p.list_width < w);
+        // 600px high with 21px rows: 15 rows under the search line
+        assert_eq!(p.rows, 15);
+        assert_eq!(p.visible_rows().len(), 15);
+    }
+
+    #[test]
+    fn ranks_by_match_then_usage() {
+        let mut p = palette(vec![
+            entry("find-file", "C-x C-f", 0),
+            entry("find-file-other-window", "C-x 4 f", 0),
+            entry("fill-paragraph", "M-q", 0),
+            entry("undo", "C-/", 40),
+        ]);
+        // No query: most used first, then in order
+        assert_eq!(p.selected_entry(), Some(3));
+        for c in "ff".chars() {
+            p.push_char(c);
+        }
+        let order: Vec<usize> = p.matches.iter().map(|m| m.entry).collect();
+        assert_eq!(order, vec![0, 1]);
+        assert_eq!(p.matches[0].positions, vec![0, 5]);
+
+        // Frequent use lifts a weaker match
+        p.entries[1].weight = 60;
+        p.push_char('o');
+        p.pop_char();
+        assert_eq!(p.selected_entry(), Some(1));
+        assert!(p.pop_char() && p.pop_char());
+        assert!(!p.pop_char());
+        assert_eq!(p.matches.len(), 4);
+    }
+
+    #[test]
+    fn selection_moves_scrolls_and_hits() {
+        let mut p = palette((0..40).map(|i| entry(&format!("command-{}", i), "", 0)).collect());
+        assert!(!p.move_selection(-1));
+        assert!(p.move_selection(p.rows as i32));
+        assert_eq!(p.scroll_row, 1);
+        assert!(p.move_selection(100));
+        assert_eq!(p.selected, 39);
+        assert_eq!(p.scroll_row, 40 - p.rows);
+
+        let (lx, ly) = p.list_origin();
+        assert_eq!(p.hit_test(lx + 1.0, ly + 1.0), Some(p.scroll_row));
+        assert_eq!(p.hit_test(lx + 1.0, ly - 1.0), None);
+        // The documentation pane is not part of the list
+        assert_eq!(p.hit_test(lx + p.list_width, ly + 1.0), None);
+        assert!(!p.scroll(1));
+        assert!(p.scroll(-3));
+    }
+
+    #[test]
+    fn doc_wraps_at_spaces() {
+        assert_eq!(
+            wrap_text("Save the current buffer\nin its file.", 10),
+            vec!["Save the", "current", "buffer", "in its", "file."]
+        );
+        assert_eq!(wrap_text("abcdefghij-klm", 5), vec!["abcde", "fghij", "-klm"]);
+        let mut p = palette(vec![entry("save-buffer", "C-x C-s", 0)]);
+        p.entries[0].doc = "Save current buffer in visited file if modified.".to_string();
+        assert_eq!(p.doc_lines().concat().replace(' ', ""), "Savecurrentbufferinvisitedfileifmodified.");
+    }
+}
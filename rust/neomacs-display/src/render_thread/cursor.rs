@@ -1,5 +1,7 @@
 //! Cursor animation, blinking, and size transition state.
 
+use crate::core::animation_clock::AnimationClock;
+use crate::core::buffer_transition::TransitionEasing;
 use crate::core::frame_glyphs::CursorStyle;
 use crate::core::types::{Color, CursorAnimStyle, ease_out_quad, ease_out_cubic, ease_out_expo, ease_in_out_cubic, ease_linear};
 
@@ -45,6 +47,8 @@ pub(super) struct CursorState {
     pub(super) anim_speed: f32,
     pub(super) anim_style: CursorAnimStyle,
     pub(super) anim_duration: f32, // seconds, for non-Exponential styles
+    /// Curve replacing the style's motion, run over `anim_duration`
+    pub(super) anim_curve: Option<TransitionEasing>,
     pub(super) target: Option<CursorTarget>,
     pub(super) current_x: f32,
     pub(super) current_y: f32,
@@ -92,6 +96,7 @@ impl Default for CursorState {
             anim_speed: 15.0,
             anim_style: CursorAnimStyle::CriticallyDampedSpring,
             anim_duration: 0.15,
+            anim_curve: None,
             target: None,
             current_x: 0.0,
             current_y: 0.0,
@@ -167,8 +172,9 @@ impl CursorState {
         }
     }
 
-    /// Tick cursor animation, returns true if position changed (needs redraw)
-    pub(super) fn tick_animation(&mut self) -> bool {
+    /// Tick cursor animation on CLOCK, returns true if position changed
+    /// (needs redraw).  Physics styles advance by the clock's fixed steps.
+    pub(super) fn tick_animation(&mut self, clock: &AnimationClock) -> bool {
         if !self.anim_enabled || !self.animating {
             return false;
         }
@@ -177,12 +183,12 @@ impl CursorState {
             None => return false,
         };
 
-        let now = std::time::Instant::now();
-        let dt = now.duration_since(self.last_anim_time).as_secs_f32();
+        let now = clock.now();
+        let dt = clock.steps() as f32 * clock.step_secs();
         self.last_anim_time = now;
 
         match self.anim_style {
-            CursorAnimStyle::Exponential => {
+            CursorAnimStyle::Exponential if self.anim_curve.is_none() => {
                 let factor = 1.0 - (-self.anim_speed * dt).exp();
                 let dx = target.x - self.current_x;
                 let dy = target.y - self.current_y;
@@ -196,7 +202,7 @@ impl CursorState {
                     self.snap(&target);
                 }
             }
-            CursorAnimStyle::CriticallyDampedSpring => {
+            CursorAnimStyle::CriticallyDampedSpring if self.anim_curve.is_none() => {
                 let mut all_settled = true;
                 for i in 0..4 {
                     let spring = &mut self.corner_springs[i];
@@ -248,12 +254,13 @@ impl CursorState {
             style => {
                 let elapsed = now.duration_since(self.anim_start_time).as_secs_f32();
                 let raw_t = (elapsed / self.anim_duration).min(1.0);
-                let t = match style {
-                    CursorAnimStyle::EaseOutQuad => ease_out_quad(raw_t),
-                    CursorAnimStyle::EaseOutCubic => ease_out_cubic(raw_t),
-                    CursorAnimStyle::EaseOutExpo => ease_out_expo(raw_t),
-                    CursorAnimStyle::EaseInOutCubic => ease_in_out_cubic(raw_t),
-                    CursorAnimStyle::Linear => ease_linear(raw_t),
+                let t = match (self.anim_curve, style) {
+                    (Some(curve), _) => curve.apply(raw_t),
+                    (None, CursorAnimStyle::EaseOutQuad) => ease_out_quad(raw_t),
+                    (None, CursorAnimStyle::EaseOutCubic) => ease_out_cubic(raw_t),
+                    (None, CursorAnimStyle::EaseOutExpo) => ease_out_expo(raw_t),
+                    (None, CursorAnimStyle::EaseInOutCubic) => ease_in_out_cubic(raw_t),
+                    (None, CursorAnimStyle::Linear) => ease_linear(raw_t),
                     _ => raw_t,
                 };
                 self.current_x = self.start_x + (target.x - self.start_x) * t;
@@ -279,11 +286,11 @@ impl CursorState {
     }
 
     /// Tick cursor size transition, returns true if size changed (needs redraw).
-    pub(super) fn tick_size_animation(&mut self) -> bool {
+    pub(super) fn tick_size_animation(&mut self, now: std::time::Instant) -> bool {
         if !self.size_transition_enabled || !self.size_animating {
             return false;
         }
-        let elapsed = self.size_anim_start.elapsed().as_secs_f32();
+        let elapsed = now.saturating_duration_since(self.size_anim_start).as_secs_f32();
         let raw_t = (elapsed / self.size_transition_duration).min(1.0);
         let t = raw_t * (2.0 - raw_t); // ease-out-quad
         self.current_w = self.size_start_w
@@ -298,10 +305,10 @@ impl CursorState {
         true
     }
 
-    /// Reset blink to visible (e.g. when new frame arrives)
-    pub(super) fn reset_blink(&mut self) {
+    /// Reset blink to visible at frame time NOW (e.g. when new frame arrives)
+    pub(super) fn reset_blink(&mut self, now: std::time::Instant) {
         self.blink_on = true;
-        self.last_blink_toggle = std::time::Instant::now();
+        self.last_blink_toggle = now;
     }
 }
 
@@ -310,6 +317,14 @@ mod tests {
     use super::*;
     use std::time::{Duration, Instant};
 
+    /// Tick STATE on a virtual clock DT after its `last_anim_time`
+    fn tick(state: &mut CursorState, dt: Duration) -> bool {
+        let mut clock = AnimationClock::new_virtual(state.last_anim_time);
+        clock.advance(dt);
+        clock.tick();
+        state.tick_animation(&clock)
+    }
+
     // ---------------------------------------------------------------
     // Helper: create a CursorTarget with given position/size/style
     // ---------------------------------------------------------------
@@ -699,20 +714,18 @@ mod tests {
     fn reset_blink_sets_visible() {
         let mut state = CursorState::default();
         state.blink_on = false;
-        let before = Instant::now();
-        state.reset_blink();
-        let after = Instant::now();
+        let now = Instant::now();
+        state.reset_blink(now);
 
         assert!(state.blink_on);
-        assert!(state.last_blink_toggle >= before);
-        assert!(state.last_blink_toggle <= after);
+        assert_eq!(state.last_blink_toggle, now);
     }
 
     #[test]
     fn reset_blink_already_visible_stays_visible() {
         let mut state = CursorState::default();
         assert!(state.blink_on); // default is true
-        state.reset_blink();
+        state.reset_blink(Instant::now());
         assert!(state.blink_on);
     }
 
@@ -720,9 +733,7 @@ mod tests {
     fn reset_blink_updates_timestamp() {
         let mut state = CursorState::default();
         let old_time = state.last_blink_toggle;
-        // Sleep briefly to ensure time advances
-        std::thread::sleep(Duration::from_millis(2));
-        state.reset_blink();
+        state.reset_blink(old_time + Duration::from_millis(2));
         assert!(state.last_blink_toggle > old_time);
     }
 
@@ -736,7 +747,7 @@ mod tests {
         state.anim_enabled = false;
         state.animating = true;
         state.target = Some(make_target(100.0, 100.0, 10.0, 20.0, CursorStyle::FilledBox));
-        assert!(!state.tick_animation());
+        assert!(!tick(&mut state, Duration::from_millis(16)));
     }
 
     #[test]
@@ -745,7 +756,7 @@ mod tests {
         state.anim_enabled = true;
         state.animating = false;
         state.target = Some(make_target(100.0, 100.0, 10.0, 20.0, CursorStyle::FilledBox));
-        assert!(!state.tick_animation());
+        assert!(!tick(&mut state, Duration::from_millis(16)));
     }
 
     #[test]
@@ -754,7 +765,7 @@ mod tests {
         state.anim_enabled = true;
         state.animating = true;
         state.target = None;
-        assert!(!state.tick_animation());
+        assert!(!tick(&mut state, Duration::from_millis(16)));
     }
 
     // ---------------------------------------------------------------
@@ -775,10 +786,8 @@ mod tests {
         state.target = Some(make_target(200.0, 300.0, 10.0, 20.0, CursorStyle::FilledBox));
         state.last_anim_time = Instant::now();
 
-        // Wait a tiny bit so dt > 0
-        std::thread::sleep(Duration::from_millis(5));
-
-        let result = state.tick_animation();
+        // Advance the clock a little so dt > 0
+        let result = tick(&mut state, Duration::from_millis(5));
         assert!(result);
         // Should have moved toward target
         assert!(state.current_x > 0.0, "x should have moved toward 200: got {}", state.current_x);
@@ -803,8 +812,7 @@ mod tests {
         state.target = Some(make_target(100.3, 200.2, 10.1, 20.1, CursorStyle::FilledBox));
         state.last_anim_time = Instant::now();
 
-        std::thread::sleep(Duration::from_millis(1));
-        state.tick_animation();
+        tick(&mut state, Duration::from_millis(1));
 
         // Should have snapped: position == target, animating == false
         assert_eq!(state.current_x, 100.3);
@@ -834,13 +842,11 @@ mod tests {
         state.current_w = 10.0;
         state.current_h = 20.0;
         state.target = Some(make_target(100.0, 200.0, 30.0, 40.0, CursorStyle::FilledBox));
-        state.anim_start_time = Instant::now();
         state.last_anim_time = Instant::now();
+        state.anim_start_time = state.last_anim_time;
 
-        // Sleep to let some time pass
-        std::thread::sleep(Duration::from_millis(10));
-
-        let result = state.tick_animation();
+        // Let some time pass on the clock
+        let result = tick(&mut state, Duration::from_millis(10));
         assert!(result);
 
         // With linear easing, progress should be proportional to time elapsed
@@ -864,10 +870,10 @@ mod tests {
         state.start_h = 20.0;
         state.target = Some(make_target(100.0, 200.0, 30.0, 40.0, CursorStyle::FilledBox));
         // Set start time in the past so elapsed > duration
-        state.anim_start_time = Instant::now() - Duration::from_millis(100);
         state.last_anim_time = Instant::now();
+        state.anim_start_time = state.last_anim_time - Duration::from_millis(100);
 
-        state.tick_animation();
+        tick(&mut state, Duration::ZERO);
 
         // Should snap to target when raw_t >= 1.0
         assert_eq!(state.current_x, 100.0);
@@ -893,11 +899,10 @@ mod tests {
         state.start_w = 10.0;
         state.start_h = 10.0;
         state.target = Some(make_target(100.0, 100.0, 10.0, 10.0, CursorStyle::FilledBox));
-        state.anim_start_time = Instant::now();
         state.last_anim_time = Instant::now();
+        state.anim_start_time = state.last_anim_time;
 
-        std::thread::sleep(Duration::from_millis(10));
-        let result = state.tick_animation();
+        let result = tick(&mut state, Duration::from_millis(10));
         assert!(result);
         assert!(state.current_x > 0.0);
     }
@@ -918,11 +923,10 @@ mod tests {
         state.start_w = 10.0;
         state.start_h = 20.0;
         state.target = Some(make_target(200.0, 200.0, 10.0, 20.0, CursorStyle::FilledBox));
-        state.anim_start_time = Instant::now();
         state.last_anim_time = Instant::now();
+        state.anim_start_time = state.last_anim_time;
 
-        std::thread::sleep(Duration::from_millis(10));
-        let result = state.tick_animation();
+        let result = tick(&mut state, Duration::from_millis(10));
         assert!(result);
         assert!(state.current_x > 50.0, "x should have progressed past start");
     }
@@ -943,11 +947,10 @@ mod tests {
         state.start_w = 5.0;
         state.start_h = 15.0;
         state.target = Some(make_target(300.0, 300.0, 5.0, 15.0, CursorStyle::FilledBox));
-        state.anim_start_time = Instant::now();
         state.last_anim_time = Instant::now();
+        state.anim_start_time = state.last_anim_time;
 
-        std::thread::sleep(Duration::from_millis(10));
-        let result = state.tick_animation();
+        let result = tick(&mut state, Duration::from_millis(10));
         assert!(result);
         assert!(state.current_x > 0.0);
     }
@@ -968,11 +971,10 @@ mod tests {
         state.start_w = 8.0;
         state.start_h = 16.0;
         state.target = Some(make_target(400.0, 400.0, 8.0, 16.0, CursorStyle::FilledBox));
-        state.anim_start_time = Instant::now();
         state.last_anim_time = Instant::now();
+        state.anim_start_time = state.last_anim_time;
 
-        std::thread::sleep(Duration::from_millis(10));
-        let result = state.tick_animation();
+        let result = tick(&mut state, Duration::from_millis(10));
         assert!(result);
         assert!(state.current_x > 10.0);
     }
@@ -1001,8 +1003,7 @@ mod tests {
         }
         state.last_anim_time = Instant::now();
 
-        std::thread::sleep(Duration::from_millis(5));
-        let result = state.tick_animation();
+        let result = tick(&mut state, Duration::from_millis(5));
         assert!(result);
 
         // Springs should have moved corners toward target
@@ -1032,8 +1033,7 @@ mod tests {
         }
         state.last_anim_time = Instant::now();
 
-        std::thread::sleep(Duration::from_millis(5));
-        state.tick_animation();
+        tick(&mut state, Duration::from_millis(5));
 
         // Should have settled: snapped to target
         assert_eq!(state.current_x, 100.0);
@@ -1063,8 +1063,7 @@ mod tests {
         }
         state.last_anim_time = Instant::now();
 
-        std::thread::sleep(Duration::from_millis(5));
-        state.tick_animation();
+        tick(&mut state, Duration::from_millis(5));
 
         // Velocities should be reset to 0
         for spring in &state.corner_springs {
@@ -1093,11 +1092,10 @@ mod tests {
         state.current_w = 10.0;
         state.current_h = 20.0;
         state.target = Some(make_target(100.0, 200.0, 10.0, 20.0, CursorStyle::FilledBox));
-        state.anim_start_time = Instant::now();
         state.last_anim_time = Instant::now();
+        state.anim_start_time = state.last_anim_time;
 
-        std::thread::sleep(Duration::from_millis(5));
-        let result = state.tick_animation();
+        let result = tick(&mut state, Duration::from_millis(5));
         assert!(result);
 
         // Position should stay the same since start == target
@@ -1117,15 +1115,14 @@ mod tests {
         state.start_w = 5.0;
         state.start_h = 10.0;
         state.target = Some(make_target(500.0, 600.0, 15.0, 25.0, CursorStyle::FilledBox));
-        state.anim_start_time = Instant::now();
         state.last_anim_time = Instant::now();
+        state.anim_start_time = state.last_anim_time;
 
         // Even with zero duration, raw_t would be infinity or NaN from 0/0,
         // but it's clamped to min(1.0) so it should snap immediately.
         // The .min(1.0) ensures raw_t = 1.0 regardless of elapsed/0.
         // Actually: elapsed/0.0 = inf, inf.min(1.0) = 1.0
-        std::thread::sleep(Duration::from_millis(1));
-        state.tick_animation();
+        tick(&mut state, Duration::from_millis(1));
 
         assert_eq!(state.current_x, 500.0);
         assert_eq!(state.current_y, 600.0);
@@ -1148,8 +1145,7 @@ mod tests {
         state.target = Some(make_target(100.0, 100.0, 10.0, 20.0, CursorStyle::FilledBox));
         state.last_anim_time = Instant::now();
 
-        std::thread::sleep(Duration::from_millis(1));
-        state.tick_animation();
+        tick(&mut state, Duration::from_millis(1));
 
         // dx, dy, dw, dh are all 0.0 (< 0.5), should snap immediately
         assert_eq!(state.current_x, 100.0);
@@ -1173,15 +1169,16 @@ mod tests {
         state.current_w = 10.0;
         state.current_h = 20.0;
         state.target = Some(make_target(100.0, 100.0, 10.0, 20.0, CursorStyle::FilledBox));
-        state.last_anim_time = Instant::now();
+        let mut clock = AnimationClock::new_virtual(Instant::now());
 
-        // Run many ticks
+        // Run many 60 Hz ticks
         for _ in 0..200 {
-            std::thread::sleep(Duration::from_millis(2));
             if !state.animating {
                 break;
             }
-            state.tick_animation();
+            clock.advance(Duration::from_micros(16_667));
+            clock.tick();
+            state.tick_animation(&clock);
         }
 
         // Should have snapped to target
@@ -1190,6 +1187,28 @@ mod tests {
         assert!(!state.animating);
     }
 
+    #[test]
+    fn tick_animation_exponential_is_frame_rate_independent() {
+        let run = |hz: u64| {
+            let mut state = CursorState::default();
+            state.anim_enabled = true;
+            state.animating = true;
+            state.anim_style = CursorAnimStyle::Exponential;
+            state.anim_speed = 10.0;
+            state.target = Some(make_target(100.0, 0.0, 10.0, 20.0, CursorStyle::FilledBox));
+            let mut clock = AnimationClock::new_virtual(Instant::now());
+            for _ in 0..hz / 5 {
+                clock.advance(Duration::from_nanos(1_000_000_000 / hz));
+                clock.tick();
+                state.tick_animation(&clock);
+            }
+            state.current_x
+        };
+        // 200 ms at each rate covers the same fixed steps
+        assert_eq!(run(30), run(60));
+        assert_eq!(run(60), run(120));
+    }
+
     #[test]
     fn tick_animation_linear_converges_over_duration() {
         let mut state = CursorState::default();
@@ -1206,16 +1225,15 @@ mod tests {
         state.current_w = 10.0;
         state.current_h = 20.0;
         state.target = Some(make_target(100.0, 200.0, 30.0, 40.0, CursorStyle::FilledBox));
-        state.anim_start_time = Instant::now();
         state.last_anim_time = Instant::now();
+        state.anim_start_time = state.last_anim_time;
 
         // Run ticks until animation completes
         for _ in 0..100 {
-            std::thread::sleep(Duration::from_millis(2));
             if !state.animating {
                 break;
             }
-            state.tick_animation();
+            tick(&mut state, Duration::from_millis(2));
         }
 
         assert_eq!(state.current_x, 100.0);
@@ -1234,7 +1252,7 @@ mod tests {
         let mut state = CursorState::default();
         state.size_transition_enabled = false;
         state.size_animating = true;
-        assert!(!state.tick_size_animation());
+        assert!(!state.tick_size_animation(Instant::now()));
     }
 
     #[test]
@@ -1242,7 +1260,7 @@ mod tests {
         let mut state = CursorState::default();
         state.size_transition_enabled = true;
         state.size_animating = false;
-        assert!(!state.tick_size_animation());
+        assert!(!state.tick_size_animation(Instant::now()));
     }
 
     #[test]
@@ -1257,10 +1275,10 @@ mod tests {
         state.size_target_h = 80.0;
         state.current_w = 10.0;
         state.current_h = 20.0;
-        state.size_anim_start = Instant::now();
+        let start = Instant::now();
+        state.size_anim_start = start;
 
-        std::thread::sleep(Duration::from_millis(10));
-        let result = state.tick_size_animation();
+        let result = state.tick_size_animation(start + Duration::from_millis(10));
         assert!(result);
 
         // Size should have moved toward target
@@ -1283,9 +1301,10 @@ mod tests {
         state.size_target_h = 80.0;
         state.current_w = 10.0;
         state.current_h = 20.0;
-        state.size_anim_start = Instant::now() - Duration::from_millis(100);
+        let now = Instant::now();
+        state.size_anim_start = now - Duration::from_millis(100);
 
-        let result = state.tick_size_animation();
+        let result = state.tick_size_animation(now);
         assert!(result);
 
         // Should snap to target size
@@ -1306,10 +1325,10 @@ mod tests {
         state.size_target_h = 60.0;
         state.current_w = 5.0;
         state.current_h = 10.0;
-        state.size_anim_start = Instant::now();
+        let start = Instant::now();
+        state.size_anim_start = start;
 
-        std::thread::sleep(Duration::from_millis(1));
-        state.tick_size_animation();
+        state.tick_size_animation(start + Duration::from_millis(1));
 
         assert_eq!(state.current_w, 30.0);
         assert_eq!(state.current_h, 60.0);
@@ -1328,10 +1347,10 @@ mod tests {
         state.size_target_h = 40.0;
         state.current_w = 20.0;
         state.current_h = 40.0;
-        state.size_anim_start = Instant::now();
+        let start = Instant::now();
+        state.size_anim_start = start;
 
-        std::thread::sleep(Duration::from_millis(5));
-        let result = state.tick_size_animation();
+        let result = state.tick_size_animation(start + Duration::from_millis(5));
         assert!(result);
 
         // Size should remain the same since start == target
@@ -1355,20 +1374,20 @@ mod tests {
         state.current_w = 0.0;
         state.current_h = 0.0;
         // Set start time 50ms ago (halfway through 100ms)
-        state.size_anim_start = Instant::now() - Duration::from_millis(50);
+        let now = Instant::now();
+        state.size_anim_start = now - Duration::from_millis(50);
 
-        state.tick_size_animation();
+        state.tick_size_animation(now);
 
         // At raw_t=0.5, ease-out-quad = 0.5*(2.0-0.5) = 0.75
-        // So width should be ~75.0 and height ~75.0
-        // Allow some tolerance for timing imprecision
+        // So width should be 75.0 and height 75.0
         assert!(
-            (state.current_w - 75.0).abs() < 5.0,
+            (state.current_w - 75.0).abs() < 1e-3,
             "width at halfway should be ~75: got {}",
             state.current_w
         );
         assert!(
-            (state.current_h - 75.0).abs() < 5.0,
+            (state.current_h - 75.0).abs() < 1e-3,
             "height at halfway should be ~75: got {}",
             state.current_h
         );
@@ -1386,14 +1405,15 @@ mod tests {
         state.size_target_h = 100.0;
         state.current_w = 10.0;
         state.current_h = 10.0;
-        state.size_anim_start = Instant::now();
+        let mut now = Instant::now();
+        state.size_anim_start = now;
 
         for _ in 0..100 {
-            std::thread::sleep(Duration::from_millis(2));
+            now += Duration::from_millis(2);
             if !state.size_animating {
                 break;
             }
-            state.tick_size_animation();
+            state.tick_size_animation(now);
         }
 
         assert_eq!(state.current_w, 100.0);
@@ -1486,14 +1506,39 @@ mod tests {
         state.start_w = 10.0;
         state.start_h = 10.0;
         state.target = Some(make_target(100.0, 100.0, 10.0, 10.0, CursorStyle::FilledBox));
-        let old_time = Instant::now() - Duration::from_millis(100);
+        let old_time = Instant::now();
         state.last_anim_time = old_time;
         state.anim_start_time = old_time;
 
-        state.tick_animation();
+        tick(&mut state, Duration::from_millis(16));
+
+        // last_anim_time should have moved to the clock's frame time
+        assert_eq!(state.last_anim_time, old_time + Duration::from_millis(16));
+    }
+
+    #[test]
+    fn tick_animation_curve_overrides_spring_style() {
+        use crate::core::easing::Spring;
+        let mut state = CursorState::default();
+        state.anim_enabled = true;
+        state.animating = true;
+        state.anim_style = CursorAnimStyle::CriticallyDampedSpring;
+        state.anim_curve = Some(TransitionEasing::Spring(Spring::new(300.0, 10.0, 1.0)));
+        state.anim_duration = 1.0;
+        state.start_x = 0.0;
+        state.start_y = 0.0;
+        state.start_w = 10.0;
+        state.start_h = 10.0;
+        state.target = Some(make_target(100.0, 0.0, 10.0, 10.0, CursorStyle::FilledBox));
+        state.last_anim_time = Instant::now();
+        state.anim_start_time = state.last_anim_time - Duration::from_millis(80);
+
+        tick(&mut state, Duration::ZERO);
 
-        // last_anim_time should have been updated to approximately now
-        assert!(state.last_anim_time > old_time);
+        // Follows the underdamped curve over the duration: past the
+        // target during the first bounce
+        assert!(state.current_x > 100.0, "x = {}", state.current_x);
+        assert!(state.animating);
     }
 
     // ---------------------------------------------------------------
@@ -1521,10 +1566,10 @@ mod tests {
             state.start_w = 10.0;
             state.start_h = 20.0;
             state.target = Some(make_target(200.0, 300.0, 40.0, 50.0, CursorStyle::FilledBox));
-            state.anim_start_time = Instant::now() - Duration::from_millis(100);
             state.last_anim_time = Instant::now();
+            state.anim_start_time = state.last_anim_time - Duration::from_millis(100);
 
-            state.tick_animation();
+            tick(&mut state, Duration::ZERO);
 
             assert_eq!(
                 state.current_x, 200.0,
@@ -0,0 +1,475 @@
+//! Date picker overlay state.
+//!
+//! A centered panel with one or more month grids side by side, each a
+//! month title, a row of weekday names and six week rows starting on
+//! the configured weekday.  Today is ringed, the selected day filled and
+//! highlighted days (holidays, days with entries) drawn in their own
+//! color with a dot under the number.  The arrow keys move by a day or a
+//! week, PageUp/PageDown by a month (a year with Shift), Home/End to the
+//! start or end of the month and `.` to today; `<`, `>`, the arrows
+//! beside the month titles and the mouse wheel turn the months shown.
+//! Enter or a click on a day chooses it.
+
+use winit::keyboard::{Key, NamedKey};
+
+use super::agenda_timeline::{civil_from_days, days_from_civil, weekday};
+use super::RenderApp;
+use crate::thread_comm::{DatePickerSpec, InputEvent};
+
+pub(crate) const MONTH_NAMES: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+pub(crate) const WEEKDAY_NAMES: [&str; 7] = ["Su", "Mo", "Tu", "We", "Th", "Fr", "Sa"];
+
+/// Week rows of a month grid, enough for any month
+pub(crate) const WEEK_ROWS: usize = 6;
+/// Most months shown side by side
+const MAX_MONTHS: usize = 3;
+
+/// Number of days in MONTH (1-12) of YEAR.
+pub(crate) fn days_in_month(year: i64, month: u32) -> u32 {
+    let (next_year, next_month) = add_months((year, month), 1);
+    (days_from_civil(next_year, next_month, 1) - days_from_civil(year, month, 1)) as u32
+}
+
+/// The month N months after (YEAR, MONTH).
+fn add_months((year, month): (i64, u32), n: i64) -> (i64, u32) {
+    let index = year * 12 + i64::from(month) - 1 + n;
+    (index.div_euclid(12), index.rem_euclid(12) as u32 + 1)
+}
+
+/// Months from (YEAR, MONTH) to (YEAR2, MONTH2).
+fn months_between((year, month): (i64, u32), (year2, month2): (i64, u32)) -> i64 {
+    (year2 - year) * 12 + i64::from(month2) - i64::from(month)
+}
+
+/// DAYS since 1970-01-01 as YYYYMMDD, the form dates cross the FFI in.
+pub(crate) fn date_code(days: i64) -> i32 {
+    let (year, month, day) = civil_from_days(days);
+    (year.clamp(1, 9999) * 10_000 + i64::from(month) * 100 + i64::from(day)) as i32
+}
+
+/// Days since 1970-01-01 of CODE, a date as YYYYMMDD.  Out of range
+/// months and days are clamped.
+pub(crate) fn days_from_code(code: i32) -> i64 {
+    let year = i64::from(code / 10_000);
+    let month = (code / 100 % 100).clamp(1, 12) as u32;
+    let day = ((code % 100).max(1) as u32).min(days_in_month(year, month));
+    days_from_civil(year, month, day)
+}
+
+/// Part of the picker under the mouse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DatePickerPart {
+    /// A day, as days since 1970-01-01
+    Day(i64),
+    PreviousMonth,
+    NextMonth,
+}
+
+pub(crate) struct DatePickerState {
+    /// Selected day and today, as days since 1970-01-01
+    pub(crate) selected: i64,
+    pub(crate) today: i64,
+    /// First month shown, as (year, month 1-12)
+    pub(crate) first_month: (i64, u32),
+    /// Number of months shown side by side
+    pub(crate) months: usize,
+    /// First day of the week (0 = Sunday)
+    pub(crate) week_start: usize,
+    /// Highlighted days, sorted
+    pub(crate) highlighted: Vec<i64>,
+    /// Optional title line above the months
+    pub(crate) title: Option<String>,
+    /// Face foreground color (sRGB 0.0-1.0), None = default
+    pub(crate) face_fg: Option<(f32, f32, f32)>,
+    /// Face background color (sRGB 0.0-1.0), None = default
+    pub(crate) face_bg: Option<(f32, f32, f32)>,
+    /// Ring around today, None = default
+    pub(crate) today_color: Option<(f32, f32, f32)>,
+    /// Text and fill of the selected day, None = default
+    pub(crate) selected_fg: Option<(f32, f32, f32)>,
+    pub(crate) selected_bg: Option<(f32, f32, f32)>,
+    /// Text and dot of highlighted days, None = default
+    pub(crate) highlight_color: Option<(f32, f32, f32)>,
+    /// Part under the mouse
+    pub(crate) hover: Option<DatePickerPart>,
+    /// Panel (x, y, width, height) in logical pixels
+    pub(crate) bounds: (f32, f32, f32, f32),
+    pub(crate) cell_width: f32,
+    pub(crate) cell_height: f32,
+    pub(crate) padding: f32,
+    /// Space between two months
+    pub(crate) month_gap: f32,
+    /// Height of the title line, 0 without a title
+    pub(crate) title_height: f32,
+    /// Pixel wheel movement not yet turned into months
+    wheel_pixels: f32,
+}
+
+impl DatePickerState {
+    /// Lay out a picker for SPEC centered on a SCREEN_W x SCREEN_H
+    /// window, showing the selected day.
+    pub(super) fn new(
+        spec: DatePickerSpec,
+        screen_w: f32, screen_h: f32,
+        font_size: f32, line_height: f32,
+    ) -> Self {
+        let months = (spec.months as usize).clamp(1, MAX_MONTHS);
+        let padding = 10.0_f32;
+        let cell_height = line_height + 6.0;
+        let cell_width = (font_size * 0.6 * 3.0 + 10.0).round();
+        let month_gap = (cell_width * 0.6).round();
+        let title_height = if spec.title.is_some() { cell_height } else { 0.0 };
+        let w = 2.0 * padding + months as f32 * 7.0 * cell_width + (months - 1) as f32 * month_gap;
+        let h = 2.0 * padding + title_height + (2 + WEEK_ROWS) as f32 * cell_height;
+        let x = ((screen_w - w) / 2.0).max(0.0).floor();
+        let y = ((screen_h - h) / 3.0).max(0.0).floor();
+        let mut highlighted = spec.highlighted;
+        highlighted.sort_unstable();
+        highlighted.dedup();
+        let (year, month, _) = civil_from_days(spec.selected);
+        DatePickerState {
+            selected: spec.selected,
+            today: spec.today,
+            first_month: (year, month),
+            months,
+            week_start: spec.week_start as usize % 7,
+            highlighted,
+            title: spec.title,
+            face_fg: None,
+            face_bg: None,
+            today_color: None,
+            selected_fg: None,
+            selected_bg: None,
+            highlight_color: None,
+            hover: None,
+            bounds: (x, y, w, h),
+            cell_width,
+            cell_height,
+            padding,
+            month_gap,
+            title_height,
+            wheel_pixels: 0.0,
+        }
+    }
+
+    /// The Ith month shown, as (year, month).
+    pub(crate) fn shown_month(&self, i: usize) -> (i64, u32) {
+        add_months(self.first_month, i as i64)
+    }
+
+    /// Left edge of the Ith month.
+    pub(crate) fn month_x(&self, i: usize) -> f32 {
+        self.bounds.0 + self.padding + i as f32 * (7.0 * self.cell_width + self.month_gap)
+    }
+
+    /// Top of the month titles; the weekday names and the weeks follow.
+    pub(crate) fn header_y(&self) -> f32 {
+        self.bounds.1 + self.padding + self.title_height
+    }
+
+    /// Top of the first week row.
+    pub(crate) fn grid_y(&self) -> f32 {
+        self.header_y() + 2.0 * self.cell_height
+    }
+
+    /// Weekday shown in column COL (0 = Sunday).
+    pub(crate) fn column_weekday(&self, col: usize) -> usize {
+        (self.week_start + col) % 7
+    }
+
+    /// Month index, row and column of DAY, if its month is shown.
+    fn cell_of(&self, day: i64) -> Option<(usize, usize, usize)> {
+        let (year, month, _) = civil_from_days(day);
+        let index = months_between(self.first_month, (year, month));
+        if index < 0 || index >= self.months as i64 {
+            return None;
+        }
+        let first = days_from_civil(year, month, 1);
+        let cell = (weekday(first) + 7 - self.week_start) % 7 + (day - first) as usize;
+        Some((index as usize, cell / 7, cell % 7))
+    }
+
+    /// Cell (x, y, width, height) of DAY, if its month is shown.
+    pub(crate) fn day_rect(&self, day: i64) -> Option<(f32, f32, f32, f32)> {
+        let (index, row, col) = self.cell_of(day)?;
+        Some((
+            self.month_x(index) + col as f32 * self.cell_width,
+            self.grid_y() + row as f32 * self.cell_height,
+            self.cell_width,
+            self.cell_height,
+        ))
+    }
+
+    /// The days of the Ith month shown.
+    pub(crate) fn month_days(&self, i: usize) -> std::ops::Range<i64> {
+        let (year, month) = self.shown_month(i);
+        let first = days_from_civil(year, month, 1);
+        first..first + i64::from(days_in_month(year, month))
+    }
+
+    /// Buttons turning to the previous and next months, as (x, y,
+    /// width, height): at the ends of the month titles.
+    pub(crate) fn arrow_rects(&self) -> [(f32, f32, f32, f32); 2] {
+        let y = self.header_y();
+        [
+            (self.month_x(0), y, self.cell_width, self.cell_height),
+            (self.month_x(self.months - 1) + 6.0 * self.cell_width, y, self.cell_width, self.cell_height),
+        ]
+    }
+
+    /// Whether DAY is highlighted.
+    pub(crate) fn is_highlighted(&self, day: i64) -> bool {
+        self.highlighted.binary_search(&day).is_ok()
+    }
+
+    /// The part of the picker at (X, Y).
+    pub(super) fn hit_test(&self, x: f32, y: f32) -> Option<DatePickerPart> {
+        let inside = |(rx, ry, rw, rh): (f32, f32, f32, f32)| {
+            x >= rx && x < rx + rw && y >= ry && y < ry + rh
+        };
+        let [previous, next] = self.arrow_rects();
+        if inside(previous) {
+            return Some(DatePickerPart::PreviousMonth);
+        }
+        if inside(next) {
+            return Some(DatePickerPart::NextMonth);
+        }
+        let grid_y = self.grid_y();
+        if y < grid_y || y >= grid_y + WEEK_ROWS as f32 * self.cell_height {
+            return None;
+        }
+        let row = ((y - grid_y) / self.cell_height) as usize;
+        (0..self.months).find_map(|i| {
+            let mx = self.month_x(i);
+            if x < mx || x >= mx + 7.0 * self.cell_width {
+                return None;
+            }
+            let col = ((x - mx) / self.cell_width) as usize;
+            let days = self.month_days(i);
+            let lead = (weekday(days.start) + 7 - self.week_start) % 7;
+            let day = days.start + (row * 7 + col) as i64 - lead as i64;
+            days.contains(&day).then_some(DatePickerPart::Day(day))
+        })
+    }
+
+    /// Whether (X, Y) is inside the panel.
+    pub(super) fn contains(&self, x: f32, y: f32) -> bool {
+        let (bx, by, bw, bh) = self.bounds;
+        x >= bx && x < bx + bw && y >= by && y < by + bh
+    }
+
+    /// Turn the months shown by N.
+    pub(super) fn turn(&mut self, n: i64) {
+        self.first_month = add_months(self.first_month, n);
+    }
+
+    /// Select DAY, turning the months shown as little as needed to
+    /// show it.
+    pub(super) fn select(&mut self, day: i64) {
+        self.selected = day;
+        let (year, month, _) = civil_from_days(day);
+        let index = months_between(self.first_month, (year, month));
+        if index < 0 {
+            self.first_month = (year, month);
+        } else if index >= self.months as i64 {
+            self.first_month = add_months((year, month), 1 - self.months as i64);
+        }
+    }
+
+    /// Move the selection N months, to the same day of the month or the
+    /// last day of a shorter month.
+    pub(super) fn move_months(&mut self, n: i64) {
+        let (year, month, day) = civil_from_days(self.selected);
+        let (year, month) = add_months((year, month), n);
+        self.select(days_from_civil(year, month, day.min(days_in_month(year, month))));
+    }
+}
+
+impl RenderApp {
+    /// Handle a key press while the date picker is shown.  TEXT is the
+    /// text the key produces, if any; SHIFT makes PageUp/PageDown move
+    /// by a year.
+    pub(super) fn date_picker_key(&mut self, key: Key<&str>, text: Option<&str>, shift: bool) {
+        let Some(picker) = self.date_picker.as_mut() else {
+            return;
+        };
+        let (year, month, _) = civil_from_days(picker.selected);
+        match key {
+            Key::Named(NamedKey::Escape) => {
+                self.finish_date_picker(None);
+                return;
+            }
+            Key::Named(NamedKey::Enter) => {
+                let day = picker.selected;
+                self.finish_date_picker(Some(day));
+                return;
+            }
+            Key::Named(NamedKey::ArrowLeft) => picker.select(picker.selected - 1),
+            Key::Named(NamedKey::ArrowRight) => picker.select(picker.selected + 1),
+            Key::Named(NamedKey::ArrowUp) => picker.select(picker.selected - 7),
+            Key::Named(NamedKey::ArrowDown) => picker.select(picker.selected + 7),
+            Key::Named(NamedKey::PageUp) => picker.move_months(if shift { -12 } else { -1 }),
+            Key::Named(NamedKey::PageDown) => picker.move_months(if shift { 12 } else { 1 }),
+            Key::Named(NamedKey::Home) => picker.select(days_from_civil(year, month, 1)),
+            Key::Named(NamedKey::End) => {
+                picker.select(days_from_civil(year, month, days_in_month(year, month)))
+            }
+            _ => match text {
+                Some(".") => picker.select(picker.today),
+                Some("<") => picker.turn(-1),
+                Some(">") => picker.turn(1),
+                _ => return,
+            },
+        }
+        self.frame_dirty = true;
+    }
+
+    /// Handle a mouse button press (PRESSED) or release at (X, Y) while
+    /// the date picker is shown.
+    pub(super) fn date_picker_click(&mut self, pressed: bool, x: f32, y: f32) {
+        let Some(picker) = self.date_picker.as_mut() else {
+            return;
+        };
+        if !pressed {
+            return;
+        }
+        match picker.hit_test(x, y) {
+            Some(DatePickerPart::Day(day)) => self.finish_date_picker(Some(day)),
+            Some(DatePickerPart::PreviousMonth) => {
+                picker.turn(-1);
+                self.frame_dirty = true;
+            }
+            Some(DatePickerPart::NextMonth) => {
+                picker.turn(1);
+                self.frame_dirty = true;
+            }
+            None if picker.contains(x, y) => {}
+            None => self.finish_date_picker(None),
+        }
+    }
+
+    /// Track the part under the mouse at (X, Y).
+    pub(super) fn date_picker_mouse_move(&mut self, x: f32, y: f32) {
+        let Some(picker) = self.date_picker.as_mut() else {
+            return;
+        };
+        let hover = picker.hit_test(x, y);
+        if hover != picker.hover {
+            picker.hover = hover;
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Turn the months shown by the mouse wheel DY, in pixels if
+    /// PIXEL_PRECISE and lines otherwise: one month per notch.
+    pub(super) fn date_picker_wheel(&mut self, dy: f32, pixel_precise: bool) {
+        let Some(picker) = self.date_picker.as_mut() else {
+            return;
+        };
+        let months = if pixel_precise {
+            let step = 2.0 * picker.cell_height;
+            picker.wheel_pixels += dy;
+            let notches = (picker.wheel_pixels / step).trunc();
+            picker.wheel_pixels -= notches * step;
+            -(notches as i64)
+        } else {
+            -(dy.signum() as i64)
+        };
+        if months != 0 {
+            picker.turn(months);
+            // The hovered day moved under the mouse
+            picker.hover = None;
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Close the date picker, reporting DAY (days since 1970-01-01) to
+    /// Emacs, or None if it was cancelled.
+    pub(super) fn finish_date_picker(&mut self, day: Option<i64>) {
+        if self.date_picker.take().is_none() {
+            return;
+        }
+        self.comms.send_input(InputEvent::DatePickerSelection { date: day.map_or(-1, date_code) });
+        self.frame_dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picker(code: i32, months: u32, week_start: u32) -> DatePickerState {
+        let spec = DatePickerSpec {
+            selected: days_from_code(code),
+            today: days_from_code(20261016),
+            highlighted: vec![days_from_code(20261225), days_from_code(20261031)],
+            months,
+            week_start,
+            title: Some("Schedule".to_string()),
+        };
+        DatePickerState::new(spec, 1200.0, 800.0, 13.0, 17.0)
+    }
+
+    #[test]
+    fn dates_convert_and_months_have_their_length() {
+        assert_eq!(days_in_month(2024, 2), 29);
+        assert_eq!(days_in_month(2100, 2), 28);
+        assert_eq!(days_in_month(2026, 12), 31);
+        assert_eq!(date_code(days_from_code(20261016)), 20261016);
+        assert_eq!(days_from_code(19700101), 0);
+        // Days past the end of the month are clamped
+        assert_eq!(date_code(days_from_code(20260231)), 20260228);
+        assert_eq!(add_months((2026, 11), 3), (2027, 2));
+        assert_eq!(add_months((2026, 1), -1), (2025, 12));
+    }
+
+    #[test]
+    fn grid_starts_weeks_on_the_week_start_day() {
+        // 1 October 2026 is a Thursday
+        let sunday = picker(20261016, 1, 0);
+        let first = days_from_code(20261001);
+        let (x, y, w, h) = sunday.day_rect(first).unwrap();
+        assert_eq!((x, y), (sunday.month_x(0) + 4.0 * sunday.cell_width, sunday.grid_y()));
+        assert_eq!(sunday.hit_test(x + w / 2.0, y + h / 2.0), Some(DatePickerPart::Day(first)));
+        let monday = picker(20261016, 1, 1);
+        let (x, _, _, _) = monday.day_rect(first).unwrap();
+        assert_eq!(x, monday.month_x(0) + 3.0 * monday.cell_width);
+        assert_eq!(monday.column_weekday(6), 0);
+        // Every day of every month shown is hit where it is drawn
+        let three = picker(20261016, 3, 1);
+        for i in 0..3 {
+            for day in three.month_days(i) {
+                let (x, y, w, h) = three.day_rect(day).unwrap();
+                assert_eq!(three.hit_test(x + w / 2.0, y + h / 2.0), Some(DatePickerPart::Day(day)));
+            }
+        }
+        // Blank cells before the first are not days
+        let (x, y, _, _) = sunday.day_rect(first).unwrap();
+        assert_eq!(sunday.hit_test(x - sunday.cell_width / 2.0, y + 1.0), None);
+        let [previous, next] = three.arrow_rects();
+        assert_eq!(three.hit_test(previous.0 + 1.0, previous.1 + 1.0), Some(DatePickerPart::PreviousMonth));
+        assert_eq!(three.hit_test(next.0 + 1.0, next.1 + 1.0), Some(DatePickerPart::NextMonth));
+        assert!(three.is_highlighted(days_from_code(20261031)));
+    }
+
+    #[test]
+    fn selection_keeps_its_month_shown() {
+        let mut p = picker(20261016, 2, 0);
+        p.select(days_from_code(20261203));
+        assert_eq!(p.first_month, (2026, 11));
+        p.select(days_from_code(20261030));
+        assert_eq!(p.first_month, (2026, 10));
+        p.select(days_from_code(20260131));
+        p.move_months(1);
+        assert_eq!(date_code(p.selected), 20260228);
+        p.move_months(-13);
+        assert_eq!(date_code(p.selected), 20250128);
+        assert_eq!(p.first_month, (2025, 1));
+        p.turn(14);
+        assert_eq!(p.first_month, (2026, 3));
+        assert!(p.day_rect(p.selected).is_none());
+    }
+}
@@ -0,0 +1,589 @@
+//! Directory view overlay state.
+//!
+//! A panel listing a directory the way Dired does, with an icon, name,
+//! size and modification time column, but drawn from a listing the
+//! directory service reads on a worker thread rather than from text
+//! Emacs inserts, and drawing only the rows on screen, so directories
+//! of tens of thousands of files open and scroll at once.  Clicking a
+//! column header or Tab sorts by that column (again to reverse); typing
+//! filters the list incrementally.  Enter or the right arrow opens a
+//! directory in place and Backspace or the left arrow goes up; Enter or
+//! a click on the selected row reports a file to Emacs, which visits it.
+
+use std::path::{Path, PathBuf};
+
+use winit::keyboard::{Key, NamedKey};
+
+use super::agenda_timeline::civil_from_days;
+use super::RenderApp;
+use crate::core::dir_listing::{sort_entries, DirEntry, DirectoryService, EntryFilter, SortKey};
+use crate::thread_comm::InputEvent;
+
+/// Width of the size column, in characters
+pub(crate) const SIZE_CHARS: usize = 7;
+/// Width of the modification time column ("2026-10-14 09:30")
+pub(crate) const DATE_CHARS: usize = 16;
+/// Pixels scrolled per line of a mouse wheel without pixel deltas
+const WHEEL_LINE_ROWS: isize = 3;
+
+/// Text like "2026-10-14 09:30" for time T (seconds since the epoch)
+/// in a zone UTC_OFFSET seconds east of UTC, or "" if unknown.
+pub(crate) fn format_modified(t: i64, utc_offset: i32) -> String {
+    if t <= 0 {
+        return String::new();
+    }
+    let local = t + i64::from(utc_offset);
+    let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+    let minutes = local.rem_euclid(86_400) / 60;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, minutes / 60, minutes % 60)
+}
+
+pub(crate) struct DirViewState {
+    service: DirectoryService,
+    /// Directory shown, or being read
+    pub(crate) dir: PathBuf,
+    /// Its entries, sorted
+    pub(crate) entries: Vec<DirEntry>,
+    /// Entries matching the query
+    pub(crate) filter: EntryFilter,
+    /// Why the directory could not be read
+    pub(crate) error: Option<String>,
+    pub(crate) sort: SortKey,
+    pub(crate) descending: bool,
+    pub(crate) show_hidden: bool,
+    /// Icon names of files by name suffix (lowercase), longest first
+    pub(crate) icons: Vec<(String, String)>,
+    /// Offset of the local time zone from UTC, in seconds
+    pub(crate) utc_offset: i32,
+    /// Selected position in `filter.matches`
+    pub(crate) selected: usize,
+    /// First row shown
+    pub(crate) scroll_row: usize,
+    /// Row under the mouse
+    pub(crate) hover: Option<usize>,
+    /// Entry to select once the listing arrives: the directory just
+    /// left when going up
+    reselect: Option<String>,
+    /// Face foreground color (sRGB 0.0-1.0), None = default
+    pub(crate) face_fg: Option<(f32, f32, f32)>,
+    /// Face background color (sRGB 0.0-1.0), None = default
+    pub(crate) face_bg: Option<(f32, f32, f32)>,
+    /// Panel (x, y, width, height) in logical pixels
+    pub(crate) bounds: (f32, f32, f32, f32),
+    /// Height of a row, the title, header and footer lines included
+    pub(crate) line_height: f32,
+    /// Advance of a character of the overlay font
+    pub(crate) char_width: f32,
+    pub(crate) padding: f32,
+}
+
+impl DirViewState {
+    /// Lay out a view of directory DIR over most of a SCREEN_W x
+    /// SCREEN_H window and start reading it.  ICONS are (suffix, icon
+    /// name) pairs naming the icons of files.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn new(
+        dir: PathBuf,
+        show_hidden: bool,
+        icons: Vec<(String, String)>,
+        utc_offset: i32,
+        screen_w: f32, screen_h: f32,
+        font_size: f32, line_height: f32,
+    ) -> Self {
+        let padding = 8.0_f32;
+        let row_h = line_height + 4.0;
+        let w = (screen_w * 0.8).clamp(400.0_f32.min(screen_w), 1200.0).floor();
+        let h = (screen_h * 0.8).floor().max(8.0 * row_h + 2.0 * padding);
+        let x = ((screen_w - w) / 2.0).max(0.0).floor();
+        let y = ((screen_h - h) / 2.0).max(0.0).floor();
+        let mut icons: Vec<(String, String)> =
+            icons.into_iter().map(|(suffix, name)| (suffix.to_lowercase(), name)).collect();
+        icons.sort_by_key(|(suffix, _)| std::cmp::Reverse(suffix.len()));
+        let mut view = DirViewState {
+            service: DirectoryService::new(),
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            filter: EntryFilter::default(),
+            error: None,
+            sort: SortKey::Name,
+            descending: false,
+            show_hidden,
+            icons,
+            utc_offset,
+            selected: 0,
+            scroll_row: 0,
+            hover: None,
+            reselect: None,
+            face_fg: None,
+            face_bg: None,
+            bounds: (x, y, w, h),
+            line_height: row_h,
+            char_width: font_size * 0.6,
+            padding,
+        };
+        view.load(dir, None);
+        view
+    }
+
+    /// Start reading directory DIR, to select entry RESELECT in it.
+    pub(super) fn load(&mut self, dir: PathBuf, reselect: Option<String>) {
+        self.service.request(dir.clone(), self.show_hidden, self.sort, self.descending);
+        self.dir = dir;
+        self.reselect = reselect;
+        self.hover = None;
+    }
+
+    /// Whether the directory is still being read.
+    pub(crate) fn loading(&self) -> bool {
+        self.service.busy()
+    }
+
+    /// Take the listing if it has been read.  Returns true if it
+    /// arrived.
+    pub(super) fn tick(&mut self) -> bool {
+        let Some(listing) = self.service.poll() else {
+            return false;
+        };
+        self.set_listing(listing.entries);
+        true
+    }
+
+    /// Show ENTRIES, the listing of `dir`, or the error reading it.
+    fn set_listing(&mut self, entries: Result<Vec<DirEntry>, String>) {
+        match entries {
+            Ok(entries) => {
+                self.entries = entries;
+                self.error = None;
+            }
+            Err(e) => {
+                self.entries.clear();
+                self.error = Some(e);
+            }
+        }
+        self.filter = EntryFilter::new(&self.entries);
+        self.selected = 0;
+        self.scroll_row = 0;
+        if let Some(name) = self.reselect.take() {
+            if let Some(pos) = self.filter.matches.iter().position(|&i| self.entries[i].name == name) {
+                self.select(pos);
+            }
+        }
+    }
+
+    /// Rows area (x, y, width, height): below the title and the column
+    /// headers, above the footer.
+    pub(crate) fn list_rect(&self) -> (f32, f32, f32, f32) {
+        let (x, y, w, h) = self.bounds;
+        let top = y + self.padding + 2.0 * self.line_height;
+        (
+            x + self.padding,
+            top,
+            (w - 2.0 * self.padding).max(1.0),
+            (h - 2.0 * self.padding - 3.0 * self.line_height).max(self.line_height),
+        )
+    }
+
+    /// Rows that fit in the list.
+    pub(crate) fn visible_rows(&self) -> usize {
+        let (_, _, _, lh) = self.list_rect();
+        ((lh / self.line_height).floor() as usize).max(1)
+    }
+
+    /// Left edges of the icon, name, size and date columns.
+    pub(crate) fn columns(&self) -> [f32; 4] {
+        let (lx, _, lw, _) = self.list_rect();
+        let date = lx + lw - DATE_CHARS as f32 * self.char_width;
+        let size = date - (SIZE_CHARS + 2) as f32 * self.char_width;
+        [lx, lx + 2.5 * self.char_width, size, date]
+    }
+
+    /// Entry shown at row position POS of the filtered list.
+    pub(crate) fn entry_at(&self, pos: usize) -> Option<&DirEntry> {
+        self.filter.matches.get(pos).map(|&i| &self.entries[i])
+    }
+
+    /// Name of the icon of ENTRY: `directory` for directories, else
+    /// that of the longest matching suffix, else `file`.
+    pub(crate) fn icon_name<'a>(&'a self, entry: &DirEntry) -> &'a str {
+        if entry.is_dir() {
+            return "directory";
+        }
+        let name = entry.name.to_lowercase();
+        self.icons
+            .iter()
+            .find(|(suffix, _)| name.ends_with(suffix.as_str()))
+            .map_or("file", |(_, icon)| icon.as_str())
+    }
+
+    /// Footer text: how many entries are shown, or why there are none.
+    pub(crate) fn describe(&self) -> String {
+        if self.loading() {
+            return "Reading\u{2026}".to_string();
+        }
+        if let Some(ref e) = self.error {
+            return e.clone();
+        }
+        if self.filter.query.is_empty() {
+            format!("{} entries", self.entries.len())
+        } else {
+            format!("{} of {} entries", self.filter.matches.len(), self.entries.len())
+        }
+    }
+
+    /// Filter the entries by QUERY.
+    pub(super) fn set_query(&mut self, query: &str) {
+        self.filter.update(&self.entries, query);
+        self.selected = 0;
+        self.scroll_row = 0;
+    }
+
+    /// Append C to the query.
+    pub(super) fn push_char(&mut self, c: char) {
+        let mut query = self.filter.query.clone();
+        query.push(c);
+        self.set_query(&query);
+    }
+
+    /// Delete the last character of the query.  Returns false if it was
+    /// already empty.
+    pub(super) fn pop_char(&mut self) -> bool {
+        let mut query = self.filter.query.clone();
+        if query.pop().is_none() {
+            return false;
+        }
+        self.set_query(&query);
+        true
+    }
+
+    /// Select row position POS, scrolling it into view.
+    pub(super) fn select(&mut self, pos: usize) {
+        if self.filter.matches.is_empty() {
+            return;
+        }
+        self.selected = pos.min(self.filter.matches.len() - 1);
+        let rows = self.visible_rows();
+        if self.selected < self.scroll_row {
+            self.scroll_row = self.selected;
+        } else if self.selected >= self.scroll_row + rows {
+            self.scroll_row = self.selected + 1 - rows;
+        }
+    }
+
+    /// Move the selection by DELTA rows.
+    pub(super) fn move_selection(&mut self, delta: isize) {
+        self.select(self.selected.saturating_add_signed(delta));
+    }
+
+    /// Scroll the rows by ROWS (negative = up).  Returns true if the
+    /// first row shown changed.
+    pub(super) fn scroll(&mut self, rows: isize) -> bool {
+        let max = self.filter.matches.len().saturating_sub(self.visible_rows());
+        let row = self.scroll_row.saturating_add_signed(rows).min(max);
+        let changed = row != self.scroll_row;
+        self.scroll_row = row;
+        changed
+    }
+
+    /// Sort by KEY, or reverse the order if already sorted by it.  Size
+    /// and time sort largest and newest first.  The selected entry stays
+    /// selected.
+    pub(super) fn sort_by(&mut self, key: SortKey) {
+        if key == self.sort {
+            self.descending = !self.descending;
+        } else {
+            self.sort = key;
+            self.descending = key != SortKey::Name;
+        }
+        let selected = self.entry_at(self.selected).map(|e| e.name.clone());
+        sort_entries(&mut self.entries, self.sort, self.descending);
+        let query = self.filter.query.clone();
+        self.filter = EntryFilter::new(&self.entries);
+        self.filter.update(&self.entries, &query);
+        self.selected = 0;
+        self.scroll_row = 0;
+        if let Some(name) = selected {
+            if let Some(pos) = self.filter.matches.iter().position(|&i| self.entries[i].name == name) {
+                self.select(pos);
+            }
+        }
+    }
+
+    /// Open the selected entry: a directory is listed in place, a file
+    /// is returned for Emacs to visit.
+    pub(super) fn open_selected(&mut self) -> Option<PathBuf> {
+        let entry = self.entry_at(self.selected)?;
+        let path = self.dir.join(&entry.name);
+        if entry.is_dir() {
+            self.load(path, None);
+            None
+        } else {
+            Some(path)
+        }
+    }
+
+    /// List the parent directory, selecting the one left.  Returns
+    /// false at the root.
+    pub(super) fn go_up(&mut self) -> bool {
+        let Some(parent) = self.dir.parent().map(Path::to_path_buf) else {
+            return false;
+        };
+        let name = self.dir.file_name().map(|n| n.to_string_lossy().into_owned());
+        self.load(parent, name);
+        true
+    }
+
+    /// Row position at (X, Y), if it shows an entry.
+    pub(super) fn hit_test(&self, x: f32, y: f32) -> Option<usize> {
+        let (lx, ly, lw, lh) = self.list_rect();
+        if x < lx || x >= lx + lw || y < ly || y >= ly + lh {
+            return None;
+        }
+        let pos = self.scroll_row + ((y - ly) / self.line_height) as usize;
+        (pos < self.filter.matches.len()).then_some(pos)
+    }
+
+    /// Column whose header is at (X, Y).
+    pub(super) fn header_at(&self, x: f32, y: f32) -> Option<SortKey> {
+        let (lx, ly, lw, _) = self.list_rect();
+        if y < ly - self.line_height || y >= ly || x < lx || x >= lx + lw {
+            return None;
+        }
+        let [_, _, size, date] = self.columns();
+        Some(if x >= date {
+            SortKey::Modified
+        } else if x >= size {
+            SortKey::Size
+        } else {
+            SortKey::Name
+        })
+    }
+
+    /// Whether (X, Y) is inside the panel.
+    pub(super) fn contains(&self, x: f32, y: f32) -> bool {
+        let (bx, by, bw, bh) = self.bounds;
+        x >= bx && x < bx + bw && y >= by && y < by + bh
+    }
+}
+
+impl RenderApp {
+    /// Handle a key press while the directory view is shown.  TEXT is
+    /// the text the key produces, if any.
+    pub(super) fn dir_view_key(&mut self, key: Key<&str>, text: Option<&str>, ctrl: bool) {
+        let Some(view) = self.dir_view.as_mut() else {
+            return;
+        };
+        let page = view.visible_rows() as isize;
+        match key {
+            Key::Named(NamedKey::Escape) if !view.filter.query.is_empty() => view.set_query(""),
+            Key::Named(NamedKey::Escape) => {
+                self.finish_dir_view(None);
+                return;
+            }
+            Key::Character("g") if ctrl => {
+                self.finish_dir_view(None);
+                return;
+            }
+            Key::Named(NamedKey::Enter) => {
+                if let Some(path) = view.open_selected() {
+                    self.finish_dir_view(Some(path));
+                    return;
+                }
+            }
+            Key::Named(NamedKey::ArrowRight) => {
+                if view.entry_at(view.selected).is_some_and(DirEntry::is_dir) {
+                    view.open_selected();
+                }
+            }
+            Key::Named(NamedKey::ArrowLeft) => {
+                view.go_up();
+            }
+            Key::Named(NamedKey::Backspace) => {
+                if !view.pop_char() {
+                    view.go_up();
+                }
+            }
+            Key::Named(NamedKey::Tab) => view.sort_by(view.sort.next()),
+            Key::Named(NamedKey::ArrowUp) => view.move_selection(-1),
+            Key::Named(NamedKey::ArrowDown) => view.move_selection(1),
+            Key::Character("p") if ctrl => view.move_selection(-1),
+            Key::Character("n") if ctrl => view.move_selection(1),
+            Key::Named(NamedKey::PageUp) => view.move_selection(-page),
+            Key::Named(NamedKey::PageDown) => view.move_selection(page),
+            Key::Named(NamedKey::Home) => view.move_selection(isize::MIN),
+            Key::Named(NamedKey::End) => view.move_selection(isize::MAX),
+            _ if ctrl => return,
+            _ => match text {
+                Some(t) if !t.is_empty() && !t.chars().any(char::is_control) => {
+                    t.chars().for_each(|c| view.push_char(c));
+                }
+                _ => return,
+            },
+        }
+        self.frame_dirty = true;
+    }
+
+    /// Handle a left button press (PRESSED) or release at (X, Y) while
+    /// the directory view is shown: a press outside the panel closes it,
+    /// one on a column header sorts by it, one on a row selects it, and
+    /// one on the selected row opens it.
+    pub(super) fn dir_view_click(&mut self, pressed: bool, x: f32, y: f32) {
+        let Some(view) = self.dir_view.as_mut() else {
+            return;
+        };
+        if !pressed {
+            return;
+        }
+        if !view.contains(x, y) {
+            self.finish_dir_view(None);
+            return;
+        }
+        if let Some(key) = view.header_at(x, y) {
+            view.sort_by(key);
+        } else if let Some(pos) = view.hit_test(x, y) {
+            if pos == view.selected {
+                if let Some(path) = view.open_selected() {
+                    self.finish_dir_view(Some(path));
+                    return;
+                }
+            } else {
+                view.select(pos);
+            }
+        } else {
+            return;
+        }
+        self.frame_dirty = true;
+    }
+
+    /// Highlight the row under the mouse at (X, Y).
+    pub(super) fn dir_view_mouse_move(&mut self, x: f32, y: f32) {
+        let Some(view) = self.dir_view.as_mut() else {
+            return;
+        };
+        let hover = view.hit_test(x, y);
+        if hover != view.hover {
+            view.hover = hover;
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Scroll the directory view by the mouse wheel DY, in pixels if
+    /// PIXEL_PRECISE and lines otherwise.
+    pub(super) fn dir_view_wheel(&mut self, dy: f32, pixel_precise: bool) {
+        let Some(view) = self.dir_view.as_mut() else {
+            return;
+        };
+        let rows = if pixel_precise {
+            (-dy / view.line_height).round() as isize
+        } else {
+            -(dy.signum() as isize) * WHEEL_LINE_ROWS
+        };
+        if view.scroll(rows) {
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Close the directory view, reporting the file at PATH to Emacs,
+    /// or None if it was closed without choosing one.
+    pub(super) fn finish_dir_view(&mut self, path: Option<PathBuf>) {
+        if self.dir_view.take().is_none() {
+            return;
+        }
+        let path = path.map(|p| p.to_string_lossy().into_owned());
+        self.comms.send_input(InputEvent::DirViewSelection { path });
+        self.frame_dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::dir_listing::EntryKind;
+
+    fn view(names: &[(&str, bool)]) -> DirViewState {
+        let icons = vec![(".rs".to_string(), "rust".to_string()), (".tar.gz".to_string(), "archive".to_string()), (".gz".to_string(), "zip".to_string())];
+        let mut v = DirViewState::new(PathBuf::from("/nonexistent/neomacs"), false, icons, 0, 1000.0, 800.0, 13.0, 17.0);
+        v.service = DirectoryService::new();
+        let entries = names
+            .iter()
+            .enumerate()
+            .map(|(i, &(name, dir))| DirEntry {
+                name: name.to_string(),
+                kind: if dir { EntryKind::Directory } else { EntryKind::File },
+                symlink: false,
+                size: i as u64 * 1000,
+                modified: 1_791_936_000 + i as i64,
+            })
+            .collect();
+        v.set_listing(Ok(entries));
+        v
+    }
+
+    #[test]
+    fn dates_are_local_and_empty_when_unknown() {
+        assert_eq!(format_modified(1_791_936_000 + 9 * 3600 + 30 * 60, 3600), "2026-10-14 10:30");
+        assert_eq!(format_modified(0, 0), "");
+    }
+
+    #[test]
+    fn icons_by_longest_suffix() {
+        let v = view(&[("src", true), ("main.RS", false), ("a.tar.gz", false), ("b.gz", false), ("notes", false)]);
+        let icons: Vec<&str> = v.entries.iter().map(|e| v.icon_name(e)).collect();
+        assert_eq!(icons, ["directory", "rust", "archive", "zip", "file"]);
+    }
+
+    #[test]
+    fn sorting_keeps_the_selection_and_rows_scroll() {
+        let names: Vec<(String, bool)> = (0..500).map(|i| (format!("file{:03}", i), false)).collect();
+        let refs: Vec<(&str, bool)> = names.iter().map(|(n, d)| (n.as_str(), *d)).collect();
+        let mut v = view(&refs);
+        assert_eq!(v.describe(), "500 entries");
+        v.move_selection(isize::MAX);
+        assert_eq!(v.selected, 499);
+        assert_eq!(v.scroll_row, 500 - v.visible_rows());
+
+        // Size sorts largest first and keeps file499 selected, now first
+        v.sort_by(SortKey::Size);
+        assert!(v.descending);
+        assert_eq!((v.selected, v.scroll_row), (0, 0));
+        assert_eq!(v.entry_at(0).unwrap().name, "file499");
+        v.sort_by(SortKey::Size);
+        assert!(!v.descending);
+        assert_eq!(v.entry_at(v.selected).unwrap().name, "file499");
+
+        // Clicks on the headers and rows
+        let (lx, ly, _, _) = v.list_rect();
+        let [_, _, size, date] = v.columns();
+        assert_eq!(v.header_at(lx + 1.0, ly - 2.0), Some(SortKey::Name));
+        assert_eq!(v.header_at(size + 1.0, ly - 2.0), Some(SortKey::Size));
+        assert_eq!(v.header_at(date + 1.0, ly - 2.0), Some(SortKey::Modified));
+        assert_eq!(v.hit_test(lx + 1.0, ly + v.line_height * 2.5), Some(v.scroll_row + 2));
+    }
+
+    #[test]
+    fn filtering_and_navigation() {
+        let mut v = view(&[("docs", true), ("main.rs", false), ("mod.rs", false), ("readme", false)]);
+        v.push_char('m');
+        v.push_char('a');
+        assert_eq!(v.filter.matches, [1]);
+        assert_eq!(v.describe(), "1 of 4 entries");
+        assert!(v.pop_char());
+        assert_eq!(v.filter.matches.len(), 3);
+        v.set_query("");
+        assert!(!v.pop_char());
+
+        // Opening a file returns it, opening a directory lists it
+        v.select(1);
+        assert_eq!(v.open_selected(), Some(PathBuf::from("/nonexistent/neomacs/main.rs")));
+        v.select(0);
+        assert_eq!(v.open_selected(), None);
+        assert_eq!(v.dir, PathBuf::from("/nonexistent/neomacs/docs"));
+        assert!(v.loading());
+        // Going up selects the directory left
+        assert!(v.go_up());
+        assert_eq!(v.dir, PathBuf::from("/nonexistent/neomacs"));
+        let entries = v.entries.clone();
+        v.select(3);
+        v.set_listing(Ok(entries));
+        assert_eq!(v.selected, 0);
+    }
+}
@@ -0,0 +1,847 @@
+//! Graph view overlay state.
+//!
+//! A panel over the frame drawing a graph given by Emacs, such as
+//! org-roam notes and their links or the states of an undo tree.  Nodes
+//! are either placed in layers following the edges, parents above their
+//! children, or by a force-directed simulation that settles on the
+//! animation clock.  The arrow keys move the selection to the nearest
+//! node in that direction; the mouse wheel or dragging the background
+//! pans, `+`/`-` or Ctrl+wheel zoom and `f` fits the graph, with pan and
+//! zoom easing to their target.  Dragging a node moves it.  Enter or a
+//! click on a node reports it to Emacs.
+
+use winit::keyboard::{Key, NamedKey};
+
+use super::RenderApp;
+use crate::thread_comm::{GraphNode, InputEvent};
+
+/// Ideal edge length of the force-directed layout, in pixels at zoom 1
+const SPRING_LENGTH: f32 = 90.0;
+/// Steps the force-directed simulation runs before it stops
+const FORCE_STEPS: u32 = 400;
+/// Pull of every node toward the center, keeping components together
+const GRAVITY: f32 = 0.02;
+/// Barycenter sweeps ordering the nodes in their layers
+const ORDER_SWEEPS: usize = 8;
+/// Passes moving the nodes of the layered layout under their parents
+const PLACE_PASSES: usize = 4;
+/// Zoom limits
+const MIN_SCALE: f32 = 0.05;
+const MAX_SCALE: f32 = 4.0;
+/// Rate (per second) at which the view approaches its target
+const EASE_RATE: f32 = 14.0;
+/// Mouse travel after which a press drags instead of clicking
+const DRAG_THRESHOLD: f32 = 3.0;
+/// Pixels panned per line of a mouse wheel without pixel deltas
+const WHEEL_LINE: f32 = 40.0;
+/// Node radius in pixels at zoom 1
+pub(crate) const NODE_RADIUS: f32 = 6.0;
+/// Longest label drawn, in characters
+pub(crate) const MAX_LABEL: usize = 28;
+/// Below this zoom only the labels of the selected node, its neighbors
+/// and the node under the mouse are drawn
+const LABEL_SCALE: f32 = 0.6;
+
+/// Edges of the layered layout with cycles broken, as predecessors and
+/// successors of each node, and the layer of each node.
+struct Layering {
+    preds: Vec<Vec<usize>>,
+    succs: Vec<Vec<usize>>,
+    layer: Vec<usize>,
+}
+
+/// Layer N nodes joined by EDGES: an edge that would close a cycle (in
+/// depth-first order from the first node) is ignored, and each node
+/// goes one layer below the deepest node with an edge to it.
+fn layering(n: usize, edges: &[(usize, usize)]) -> Layering {
+    let mut out: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for &(from, to) in edges {
+        if from < n && to < n && from != to && !out[from].contains(&to) {
+            out[from].push(to);
+        }
+    }
+
+    // Depth-first search keeping the edges that don't lead back to a
+    // node on the stack
+    let mut preds = vec![Vec::new(); n];
+    let mut succs = vec![Vec::new(); n];
+    let mut state = vec![0u8; n]; // 0 = new, 1 = on the stack, 2 = done
+    for root in 0..n {
+        if state[root] != 0 {
+            continue;
+        }
+        let mut stack = vec![(root, 0usize)];
+        state[root] = 1;
+        while let Some(&mut (node, ref mut next)) = stack.last_mut() {
+            if let Some(&to) = out[node].get(*next) {
+                *next += 1;
+                if state[to] == 1 {
+                    continue;
+                }
+                succs[node].push(to);
+                preds[to].push(node);
+                if state[to] == 0 {
+                    state[to] = 1;
+                    stack.push((to, 0));
+                }
+            } else {
+                state[node] = 2;
+                stack.pop();
+            }
+        }
+    }
+
+    // Longest path from the sources, in topological order
+    let mut layer = vec![0usize; n];
+    let mut pending: Vec<usize> = preds.iter().map(Vec::len).collect();
+    let mut ready: Vec<usize> = (0..n).filter(|&i| pending[i] == 0).collect();
+    while let Some(node) = ready.pop() {
+        for &to in &succs[node] {
+            layer[to] = layer[to].max(layer[node] + 1);
+            pending[to] -= 1;
+            if pending[to] == 0 {
+                ready.push(to);
+            }
+        }
+    }
+    Layering { preds, succs, layer }
+}
+
+/// Positions of N nodes joined by EDGES in layers V_GAP apart, parents
+/// above their children and nodes of a layer at least H_GAP apart.  The
+/// nodes of each layer are ordered by the mean rank of their neighbors
+/// in sweeps down and up, then moved under the mean position of their
+/// parents (and over that of their children) where there is room.
+pub(crate) fn layered_positions(
+    n: usize,
+    edges: &[(usize, usize)],
+    h_gap: f32,
+    v_gap: f32,
+) -> Vec<(f32, f32)> {
+    let Layering { preds, succs, layer } = layering(n, edges);
+    let depth = layer.iter().max().map_or(0, |&l| l + 1);
+    let mut layers: Vec<Vec<usize>> = vec![Vec::new(); depth];
+    for i in 0..n {
+        layers[layer[i]].push(i);
+    }
+    let mut rank = vec![0.0f32; n];
+    for nodes in &layers {
+        for (r, &i) in nodes.iter().enumerate() {
+            rank[i] = r as f32;
+        }
+    }
+
+    let mean = |values: &[usize], of: &[f32]| {
+        (!values.is_empty()).then(|| values.iter().map(|&v| of[v]).sum::<f32>() / values.len() as f32)
+    };
+    for sweep in 0..ORDER_SWEEPS {
+        let down = sweep % 2 == 0;
+        let order: Vec<usize> = if down { (1..depth).collect() } else { (0..depth.saturating_sub(1)).rev().collect() };
+        for l in order {
+            let mut keys: Vec<(usize, f32)> = layers[l]
+                .iter()
+                .map(|&i| (i, mean(if down { &preds[i] } else { &succs[i] }, &rank).unwrap_or(rank[i])))
+                .collect();
+            keys.sort_by(|a, b| a.1.total_cmp(&b.1));
+            layers[l] = keys.into_iter().map(|(i, _)| i).collect();
+            for (r, &i) in layers[l].iter().enumerate() {
+                rank[i] = r as f32;
+            }
+        }
+    }
+
+    let mut x = vec![0.0f32; n];
+    for nodes in &layers {
+        let offset = (nodes.len() as f32 - 1.0) / 2.0;
+        for (r, &i) in nodes.iter().enumerate() {
+            x[i] = (r as f32 - offset) * h_gap;
+        }
+    }
+    for pass in 0..PLACE_PASSES {
+        let down = pass % 2 == 0;
+        let order: Vec<usize> = if down { (1..depth).collect() } else { (0..depth.saturating_sub(1)).rev().collect() };
+        for l in order {
+            let nodes = &layers[l];
+            let wanted: Vec<f32> = nodes
+                .iter()
+                .map(|&i| mean(if down { &preds[i] } else { &succs[i] }, &x).unwrap_or(x[i]))
+                .collect();
+            // Left to right at the wanted place or the gap after the
+            // previous node, then the whole layer shifted back by the
+            // mean overshoot
+            let mut placed = Vec::with_capacity(nodes.len());
+            for (k, &w) in wanted.iter().enumerate() {
+                let min = if k == 0 { f32::NEG_INFINITY } else { placed[k - 1] + h_gap };
+                placed.push(w.max(min));
+            }
+            let shift = wanted.iter().zip(&placed).map(|(w, p)| p - w).sum::<f32>() / nodes.len().max(1) as f32;
+            for (k, &i) in nodes.iter().enumerate() {
+                x[i] = placed[k] - shift;
+            }
+        }
+    }
+    (0..n).map(|i| (x[i], layer[i] as f32 * v_gap)).collect()
+}
+
+/// Starting positions of the force-directed layout: a sunflower spiral,
+/// so no two nodes start at the same place.
+pub(crate) fn spiral_positions(n: usize) -> Vec<(f32, f32)> {
+    const GOLDEN_ANGLE: f32 = 2.399_963;
+    (0..n)
+        .map(|i| {
+            let r = SPRING_LENGTH * 0.5 * (i as f32).sqrt();
+            let a = i as f32 * GOLDEN_ANGLE;
+            (r * a.cos(), r * a.sin())
+        })
+        .collect()
+}
+
+/// Advance the force-directed layout of POSITIONS one step: nodes repel
+/// each other, EDGES pull their ends together toward `SPRING_LENGTH`
+/// apart and gravity pulls everything to the origin.  No node moves
+/// more than TEMPERATURE, and PINNED ones not at all.
+pub(crate) fn force_step(
+    positions: &mut [(f32, f32)],
+    edges: &[(usize, usize)],
+    pinned: &[bool],
+    temperature: f32,
+) {
+    let n = positions.len();
+    let k = SPRING_LENGTH;
+    let mut disp = vec![(0.0f32, 0.0f32); n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let (mut dx, mut dy) = (positions[i].0 - positions[j].0, positions[i].1 - positions[j].1);
+            let mut d2 = dx * dx + dy * dy;
+            if d2 < 1e-4 {
+                // Nodes on top of each other part in a fixed direction
+                dx = 0.01 * (1 + i % 7) as f32;
+                dy = 0.01 * (1 + j % 5) as f32;
+                d2 = dx * dx + dy * dy;
+            }
+            // k² / d along the unit vector d / |d|
+            let f = k * k / d2;
+            disp[i].0 += dx * f;
+            disp[i].1 += dy * f;
+            disp[j].0 -= dx * f;
+            disp[j].1 -= dy * f;
+        }
+    }
+    for &(a, b) in edges {
+        if a >= n || b >= n || a == b {
+            continue;
+        }
+        let (dx, dy) = (positions[a].0 - positions[b].0, positions[a].1 - positions[b].1);
+        // d² / k along the unit vector
+        let f = (dx * dx + dy * dy).sqrt() / k;
+        disp[a].0 -= dx * f;
+        disp[a].1 -= dy * f;
+        disp[b].0 += dx * f;
+        disp[b].1 += dy * f;
+    }
+    for (i, p) in positions.iter_mut().enumerate() {
+        if pinned.get(i).copied().unwrap_or(false) {
+            continue;
+        }
+        let (dx, dy) = (disp[i].0 - p.0 * GRAVITY * k / 10.0, disp[i].1 - p.1 * GRAVITY * k / 10.0);
+        let len = dx.hypot(dy);
+        if len > 0.0 {
+            let step = len.min(temperature) / len;
+            p.0 += dx * step;
+            p.1 += dy * step;
+        }
+    }
+}
+
+/// A press in the graph view: on a node it moves the node, elsewhere it
+/// pans the view, once the mouse moves
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GraphDrag {
+    /// Where the button went down
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    /// Node pressed, if any
+    pub(crate) node: Option<usize>,
+    /// Whether the mouse moved far enough to be a drag
+    pub(crate) moved: bool,
+}
+
+pub(crate) struct GraphViewState {
+    pub(crate) nodes: Vec<GraphNode>,
+    /// Edges as (from, to) node indices
+    pub(crate) edges: Vec<(usize, usize)>,
+    /// Nodes joined to each node by an edge, either way
+    pub(crate) neighbors: Vec<Vec<usize>>,
+    /// Positions in pixels at zoom 1
+    pub(crate) positions: Vec<(f32, f32)>,
+    /// Placed in layers rather than by forces
+    pub(crate) layered: bool,
+    /// Nodes the simulation leaves where the user dropped them
+    pub(crate) pinned: Vec<bool>,
+    /// Force-directed layout steps left to run
+    pub(crate) force_steps_left: u32,
+    /// Optional title shown in the header
+    pub(crate) title: Option<String>,
+    /// Face foreground color (sRGB 0.0-1.0), None = default
+    pub(crate) face_fg: Option<(f32, f32, f32)>,
+    /// Face background color (sRGB 0.0-1.0), None = default
+    pub(crate) face_bg: Option<(f32, f32, f32)>,
+    /// Panel (x, y, width, height) in logical pixels
+    pub(crate) bounds: (f32, f32, f32, f32),
+    /// Height of the header and footer lines
+    pub(crate) line_height: f32,
+    /// Advance of a character of the overlay font
+    pub(crate) char_width: f32,
+    pub(crate) padding: f32,
+    /// Graph point at the center of the chart and zoom, and the ones
+    /// they are easing to
+    pub(crate) view: (f32, f32, f32),
+    pub(crate) target: (f32, f32, f32),
+    /// Keep fitting the graph while the simulation moves it, until the
+    /// user pans or zooms
+    pub(crate) follow: bool,
+    /// Selected node, and the one under the mouse
+    pub(crate) selected: Option<usize>,
+    pub(crate) hover: Option<usize>,
+    pub(crate) drag: Option<GraphDrag>,
+}
+
+impl GraphViewState {
+    /// Lay out a graph of NODES joined by EDGES in layers (LAYERED) or
+    /// by forces, over most of a SCREEN_W x SCREEN_H window, with node
+    /// CURRENT selected.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn new(
+        nodes: Vec<GraphNode>,
+        edges: Vec<(usize, usize)>,
+        layered: bool,
+        current: Option<usize>,
+        title: Option<String>,
+        screen_w: f32, screen_h: f32,
+        font_size: f32, line_height: f32,
+    ) -> Self {
+        let n = nodes.len();
+        let edges: Vec<(usize, usize)> = edges.into_iter().filter(|&(a, b)| a < n && b < n && a != b).collect();
+        let mut neighbors = vec![Vec::new(); n];
+        for &(a, b) in &edges {
+            if !neighbors[a].contains(&b) {
+                neighbors[a].push(b);
+                neighbors[b].push(a);
+            }
+        }
+        let padding = 10.0_f32;
+        let char_width = font_size * 0.6;
+        let w = (screen_w * 0.9).floor();
+        let h = (screen_h * 0.85).floor();
+        let x = ((screen_w - w) / 2.0).max(0.0).floor();
+        let y = ((screen_h - h) / 2.0).max(0.0).floor();
+        let positions = if layered {
+            let longest = nodes.iter().map(|node| node.label.chars().count().min(MAX_LABEL)).max().unwrap_or(0);
+            let h_gap = (longest as f32 * char_width + 2.0 * NODE_RADIUS).max(4.0 * NODE_RADIUS);
+            layered_positions(n, &edges, h_gap, 3.0 * line_height + 2.0 * NODE_RADIUS)
+        } else {
+            spiral_positions(n)
+        };
+        let mut graph = GraphViewState {
+            layered,
+            pinned: vec![false; n],
+            force_steps_left: if layered { 0 } else { FORCE_STEPS },
+            nodes,
+            edges,
+            neighbors,
+            positions,
+            title,
+            face_fg: None,
+            face_bg: None,
+            bounds: (x, y, w, h),
+            line_height,
+            char_width,
+            padding,
+            view: (0.0, 0.0, 1.0),
+            target: (0.0, 0.0, 1.0),
+            follow: !layered,
+            selected: current.filter(|&i| i < n).or(if n > 0 { Some(0) } else { None }),
+            hover: None,
+            drag: None,
+        };
+        // Settle the graph halfway before it is first drawn, as far as
+        // a few million node pairs allow
+        let budget = (4_000_000 / (n * n).max(1)).min(FORCE_STEPS as usize / 2);
+        for _ in 0..budget {
+            graph.simulate();
+        }
+        graph.fit();
+        if layered {
+            // Trees open on the current node, not the whole tree
+            if let Some(i) = graph.selected.filter(|_| graph.target.2 < LABEL_SCALE) {
+                graph.target.2 = 1.0;
+                graph.center_on(i);
+            }
+        }
+        graph.view = graph.target;
+        graph
+    }
+
+    /// Chart area (x, y, width, height) between the title and the
+    /// footer.
+    pub(crate) fn chart_rect(&self) -> (f32, f32, f32, f32) {
+        let (x, y, w, h) = self.bounds;
+        let top = y + self.padding + self.line_height + self.padding / 2.0;
+        let bottom = y + h - self.padding - self.line_height - self.padding / 2.0;
+        (x + self.padding, top, (w - 2.0 * self.padding).max(1.0), (bottom - top).max(self.line_height))
+    }
+
+    /// Screen position of graph point P in the current view.
+    pub(crate) fn to_screen(&self, p: (f32, f32)) -> (f32, f32) {
+        let (cx, cy, cw, ch) = self.chart_rect();
+        let (vx, vy, scale) = self.view;
+        (cx + cw / 2.0 + (p.0 - vx) * scale, cy + ch / 2.0 + (p.1 - vy) * scale)
+    }
+
+    /// Graph point at screen position (X, Y) in the current view.
+    pub(crate) fn to_graph(&self, x: f32, y: f32) -> (f32, f32) {
+        let (cx, cy, cw, ch) = self.chart_rect();
+        let (vx, vy, scale) = self.view;
+        (vx + (x - cx - cw / 2.0) / scale, vy + (y - cy - ch / 2.0) / scale)
+    }
+
+    /// Radius of a node on screen.
+    pub(crate) fn node_radius(&self) -> f32 {
+        (NODE_RADIUS * self.view.2).clamp(2.5, 3.0 * NODE_RADIUS)
+    }
+
+    /// Whether the label of node I is drawn: all are when zoomed in far
+    /// enough, otherwise those of the selected node, its neighbors and
+    /// the node under the mouse.
+    pub(crate) fn shows_label(&self, i: usize) -> bool {
+        self.view.2 >= LABEL_SCALE
+            || self.hover == Some(i)
+            || self.selected.is_some_and(|s| s == i || self.neighbors[s].contains(&i))
+    }
+
+    /// Label of node I as drawn, cut to `MAX_LABEL` characters.
+    pub(crate) fn label(&self, i: usize) -> String {
+        let label = &self.nodes[i].label;
+        if label.chars().count() <= MAX_LABEL {
+            label.clone()
+        } else {
+            let mut cut: String = label.chars().take(MAX_LABEL - 1).collect();
+            cut.push('\u{2026}');
+            cut
+        }
+    }
+
+    /// Footer text describing node I: its label and number of links.
+    pub(crate) fn describe(&self, i: usize) -> String {
+        match self.neighbors[i].len() {
+            1 => format!("{}  (1 link)", self.nodes[i].label),
+            links => format!("{}  ({} links)", self.nodes[i].label, links),
+        }
+    }
+
+    /// Ease toward showing the whole graph.
+    pub(super) fn fit(&mut self) {
+        let Some(&first) = self.positions.first() else {
+            self.target = (0.0, 0.0, 1.0);
+            return;
+        };
+        let (x0, y0, x1, y1) = self.positions.iter().fold((first.0, first.1, first.0, first.1), |b, p| {
+            (b.0.min(p.0), b.1.min(p.1), b.2.max(p.0), b.3.max(p.1))
+        });
+        let (_, _, cw, ch) = self.chart_rect();
+        // Room for the nodes and a label below the lowest ones
+        let margin = 2.0 * NODE_RADIUS + 2.0 * self.line_height;
+        let scale = (cw / (x1 - x0 + 2.0 * margin + 10.0 * self.char_width))
+            .min(ch / (y1 - y0 + 2.0 * margin))
+            .clamp(MIN_SCALE, 1.5);
+        self.target = ((x0 + x1) / 2.0, (y0 + y1) / 2.0 + self.line_height / 2.0, scale);
+    }
+
+    /// Ease toward node I at the center of the chart.
+    fn center_on(&mut self, i: usize) {
+        let (x, y) = self.positions[i];
+        self.target.0 = x;
+        self.target.1 = y;
+    }
+
+    /// Pan by DX, DY screen pixels (positive = content moves right and
+    /// down).
+    pub(super) fn pan(&mut self, dx: f32, dy: f32) {
+        self.follow = false;
+        self.target.0 -= dx / self.target.2;
+        self.target.1 -= dy / self.target.2;
+    }
+
+    /// Zoom by FACTOR (above 1 = in) keeping the graph point at screen
+    /// position (X, Y) in place.
+    pub(super) fn zoom(&mut self, factor: f32, x: f32, y: f32) {
+        self.follow = false;
+        let (cx, cy, cw, ch) = self.chart_rect();
+        let (ox, oy) = (x - cx - cw / 2.0, y - cy - ch / 2.0);
+        let (tx, ty, scale) = self.target;
+        let anchor = (tx + ox / scale, ty + oy / scale);
+        let scale = (scale * factor).clamp(MIN_SCALE, MAX_SCALE);
+        self.target = (anchor.0 - ox / scale, anchor.1 - oy / scale, scale);
+    }
+
+    /// Run one step of the force-directed simulation, if it hasn't
+    /// settled.
+    fn simulate(&mut self) -> bool {
+        if self.force_steps_left == 0 {
+            return false;
+        }
+        let temperature = 2.0 * SPRING_LENGTH * self.force_steps_left as f32 / FORCE_STEPS as f32;
+        force_step(&mut self.positions, &self.edges, &self.pinned, temperature);
+        self.force_steps_left -= 1;
+        true
+    }
+
+    /// Advance the simulation and move the view STEPS animation steps
+    /// of STEP_SECS seconds toward its target.  Returns true while
+    /// anything is still moving.
+    pub(super) fn tick(&mut self, steps: u32, step_secs: f32) -> bool {
+        let simulating = self.simulate();
+        if simulating && self.follow {
+            self.fit();
+        }
+        if self.view == self.target {
+            return simulating;
+        }
+        let k = 1.0 - (-EASE_RATE * step_secs * steps as f32).exp();
+        let (vx, vy, vs) = self.view;
+        let (tx, ty, ts) = self.target;
+        self.view = (vx + (tx - vx) * k, vy + (ty - vy) * k, vs + (ts - vs) * k);
+        // Snap once less than a tenth of a pixel is left
+        let epsilon = 0.1 / ts;
+        if (self.view.0 - tx).abs() < epsilon && (self.view.1 - ty).abs() < epsilon && (self.view.2 - ts).abs() < ts * 1e-3 {
+            self.view = self.target;
+        }
+        true
+    }
+
+    /// Select node I, centering it if it is outside the chart.
+    pub(super) fn select(&mut self, i: usize) {
+        if i >= self.nodes.len() {
+            return;
+        }
+        self.selected = Some(i);
+        let (sx, sy) = self.to_screen(self.positions[i]);
+        let (cx, cy, cw, ch) = self.chart_rect();
+        let margin = 2.0 * self.line_height;
+        if sx < cx + margin || sx > cx + cw - margin || sy < cy + margin || sy > cy + ch - margin {
+            self.follow = false;
+            self.center_on(i);
+        }
+    }
+
+    /// Move the selection to the nearest node in direction (DX, DY),
+    /// within 60 degrees of it, preferring nodes straight that way.
+    pub(super) fn move_selection(&mut self, dx: f32, dy: f32) {
+        let Some(from) = self.selected else {
+            if !self.nodes.is_empty() {
+                self.select(0);
+            }
+            return;
+        };
+        let (fx, fy) = self.positions[from];
+        let best = self
+            .positions
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != from)
+            .filter_map(|(i, &(x, y))| {
+                let along = (x - fx) * dx + (y - fy) * dy;
+                let across = ((x - fx) * dy - (y - fy) * dx).abs();
+                (along > 0.0 && across <= along * 1.8).then_some((i, along + 2.0 * across))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, _)) = best {
+            self.select(i);
+        }
+    }
+
+    /// The node at (X, Y), the nearest one if several are.
+    pub(super) fn hit_test(&self, x: f32, y: f32) -> Option<usize> {
+        let (cx, cy, cw, ch) = self.chart_rect();
+        if x < cx || x >= cx + cw || y < cy || y >= cy + ch {
+            return None;
+        }
+        // Small nodes are easier to hit with some slack
+        let reach = self.node_radius() + 3.0;
+        self.positions
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| {
+                let (sx, sy) = self.to_screen(p);
+                (i, (sx - x).hypot(sy - y))
+            })
+            .filter(|&(_, d)| d <= reach)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    /// Whether (X, Y) is inside the panel.
+    pub(super) fn contains(&self, x: f32, y: f32) -> bool {
+        let (bx, by, bw, bh) = self.bounds;
+        x >= bx && x < bx + bw && y >= by && y < by + bh
+    }
+}
+
+impl RenderApp {
+    /// Handle a key press while the graph view is shown.  TEXT is the
+    /// text the key produces, if any.
+    pub(super) fn graph_view_key(&mut self, key: Key<&str>, text: Option<&str>) {
+        let Some(graph) = self.graph_view.as_mut() else {
+            return;
+        };
+        let (cx, cy, cw, ch) = graph.chart_rect();
+        let center = (cx + cw / 2.0, cy + ch / 2.0);
+        match key {
+            Key::Named(NamedKey::Escape) => {
+                self.finish_graph_view(-1);
+                return;
+            }
+            Key::Named(NamedKey::Enter) => {
+                if let Some(i) = graph.selected {
+                    self.finish_graph_view(i as i32);
+                }
+                return;
+            }
+            Key::Named(NamedKey::ArrowLeft) => graph.move_selection(-1.0, 0.0),
+            Key::Named(NamedKey::ArrowRight) => graph.move_selection(1.0, 0.0),
+            Key::Named(NamedKey::ArrowUp) => graph.move_selection(0.0, -1.0),
+            Key::Named(NamedKey::ArrowDown) => graph.move_selection(0.0, 1.0),
+            _ => match text {
+                Some("q") => {
+                    self.finish_graph_view(-1);
+                    return;
+                }
+                Some("+" | "=") => graph.zoom(1.5, center.0, center.1),
+                Some("-") => graph.zoom(1.0 / 1.5, center.0, center.1),
+                Some("f") => {
+                    graph.follow = false;
+                    graph.fit();
+                }
+                Some("c") => {
+                    if let Some(i) = graph.selected {
+                        graph.follow = false;
+                        graph.center_on(i);
+                    }
+                }
+                _ => return,
+            },
+        }
+        self.frame_dirty = true;
+    }
+
+    /// Handle a left button press (PRESSED) or release at (X, Y) while
+    /// the graph view is shown: a press outside the panel closes it, a
+    /// release without dragging chooses the node under the mouse.
+    pub(super) fn graph_view_click(&mut self, pressed: bool, x: f32, y: f32) {
+        let Some(graph) = self.graph_view.as_mut() else {
+            return;
+        };
+        if pressed {
+            if !graph.contains(x, y) {
+                self.finish_graph_view(-1);
+                return;
+            }
+            let node = graph.hit_test(x, y);
+            graph.drag = Some(GraphDrag { x, y, node, moved: false });
+            return;
+        }
+        let Some(drag) = graph.drag.take() else {
+            return;
+        };
+        if !drag.moved {
+            if let Some(i) = drag.node {
+                self.finish_graph_view(i as i32);
+            }
+        }
+    }
+
+    /// Track the mouse at (X, Y) over the graph view: move the node
+    /// pressed or pan while a button is held, otherwise highlight the
+    /// node under it.
+    pub(super) fn graph_view_mouse_move(&mut self, x: f32, y: f32) {
+        let Some(graph) = self.graph_view.as_mut() else {
+            return;
+        };
+        if let Some(mut drag) = graph.drag {
+            if !drag.moved && (x - drag.x).hypot(y - drag.y) < DRAG_THRESHOLD {
+                return;
+            }
+            drag.moved = true;
+            if let Some(i) = drag.node {
+                // The node stays where it is dropped, and the simulation
+                // settles the rest around it
+                graph.positions[i] = graph.to_graph(x, y);
+                graph.pinned[i] = true;
+                graph.selected = Some(i);
+                graph.follow = false;
+                if !graph.layered {
+                    graph.force_steps_left = graph.force_steps_left.max(FORCE_STEPS / 4);
+                }
+            } else {
+                // The graph follows the mouse without easing
+                let (dx, dy) = (x - drag.x, y - drag.y);
+                graph.pan(dx, dy);
+                graph.view = graph.target;
+            }
+            drag.x = x;
+            drag.y = y;
+            graph.drag = Some(drag);
+            self.frame_dirty = true;
+        } else {
+            let hover = graph.hit_test(x, y);
+            if hover != graph.hover {
+                graph.hover = hover;
+                self.frame_dirty = true;
+            }
+        }
+    }
+
+    /// Handle the mouse wheel (DX, DY) over the graph view, in pixels
+    /// if PIXEL_PRECISE and lines otherwise: Ctrl zooms around the
+    /// mouse, otherwise it pans.
+    pub(super) fn graph_view_wheel(&mut self, dx: f32, dy: f32, pixel_precise: bool, ctrl: bool) {
+        let (mx, my) = self.mouse_pos;
+        let Some(graph) = self.graph_view.as_mut() else {
+            return;
+        };
+        if ctrl {
+            if dy != 0.0 {
+                graph.zoom(if dy > 0.0 { 1.25 } else { 0.8 }, mx, my);
+                self.frame_dirty = true;
+            }
+            return;
+        }
+        let unit = if pixel_precise { 1.0 } else { WHEEL_LINE };
+        if dx != 0.0 || dy != 0.0 {
+            graph.pan(-dx * unit, dy * unit);
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Close the graph view, reporting the node at INDEX to Emacs, or
+    /// -1 if it was closed without choosing one.
+    pub(super) fn finish_graph_view(&mut self, index: i32) {
+        if self.graph_view.take().is_none() {
+            return;
+        }
+        self.comms.send_input(InputEvent::GraphViewSelection { index });
+        self.frame_dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(label: &str) -> GraphNode {
+        GraphNode { label: label.to_string(), color: None }
+    }
+
+    fn graph(n: usize, edges: Vec<(usize, usize)>, layered: bool) -> GraphViewState {
+        let nodes = (0..n).map(|i| node(&format!("node {i}"))).collect();
+        GraphViewState::new(nodes, edges, layered, None, None, 1000.0, 800.0, 13.0, 17.0)
+    }
+
+    #[test]
+    fn layers_put_parents_above_children() {
+        // 0 -> 1, 2; 1 -> 3, 4; 2 -> 5; and 5 -> 0 closing a cycle
+        let edges = [(0, 1), (0, 2), (1, 3), (1, 4), (2, 5), (5, 0)];
+        let pos = layered_positions(6, &edges, 50.0, 40.0);
+        let ys: Vec<f32> = pos.iter().map(|p| p.1).collect();
+        assert_eq!(ys, [0.0, 40.0, 40.0, 80.0, 80.0, 80.0]);
+        // Children lie under their parent, siblings at least a gap apart
+        assert!(pos[1].0 < pos[2].0);
+        assert!((pos[1].0 - (pos[3].0 + pos[4].0) / 2.0).abs() < 1.0);
+        assert!(pos[4].0 - pos[3].0 >= 50.0 - 1e-3);
+        assert!(pos[5].0 - pos[4].0 >= 50.0 - 1e-3);
+        // An edge skipping a layer puts its end below both parents
+        let pos = layered_positions(3, &[(0, 1), (1, 2), (0, 2)], 50.0, 40.0);
+        assert_eq!(pos[2].1, 80.0);
+    }
+
+    #[test]
+    fn forces_settle_near_the_spring_length() {
+        // A chain 0 - 1 - 2 and a loose node
+        let edges = [(0, 1), (1, 2)];
+        let mut pos = spiral_positions(4);
+        let pinned = [true, false, false, false];
+        let start = pos[0];
+        for step in (1..=FORCE_STEPS).rev() {
+            force_step(&mut pos, &edges, &pinned, 2.0 * SPRING_LENGTH * step as f32 / FORCE_STEPS as f32);
+        }
+        assert_eq!(pos[0], start);
+        let dist = |a: usize, b: usize| (pos[a].0 - pos[b].0).hypot(pos[a].1 - pos[b].1);
+        for (a, b) in edges {
+            assert!(dist(a, b) > 0.5 * SPRING_LENGTH && dist(a, b) < 1.5 * SPRING_LENGTH, "{}", dist(a, b));
+        }
+        // Linked nodes end up closer than unlinked ones
+        assert!(dist(0, 2) > dist(0, 1));
+        // Nodes at the same place are pushed apart
+        let mut pos = vec![(0.0, 0.0); 2];
+        force_step(&mut pos, &[], &[false, false], 10.0);
+        assert_ne!(pos[0], pos[1]);
+    }
+
+    #[test]
+    fn zoom_keeps_the_anchor_and_the_view_eases() {
+        let mut g = graph(3, vec![(0, 1), (1, 2)], true);
+        assert!(!g.tick(1, 1.0 / 240.0));
+        let (cx, cy, _, _) = g.chart_rect();
+        let anchor = (cx + 30.0, cy + 40.0);
+        let before = g.to_graph(anchor.0, anchor.1);
+        g.zoom(2.0, anchor.0, anchor.1);
+        let mut steps = 0;
+        while g.tick(4, 1.0 / 240.0) {
+            steps += 1;
+            assert!(steps < 500);
+        }
+        let after = g.to_graph(anchor.0, anchor.1);
+        assert!((before.0 - after.0).abs() < 0.5 && (before.1 - after.1).abs() < 0.5);
+        g.zoom(1e9, anchor.0, anchor.1);
+        assert_eq!(g.target.2, MAX_SCALE);
+    }
+
+    #[test]
+    fn selection_moves_by_direction_and_nodes_are_hit() {
+        // 0 over 1 and 2, side by side
+        let mut g = graph(3, vec![(0, 1), (0, 2)], true);
+        assert_eq!(g.selected, Some(0));
+        g.move_selection(0.0, 1.0);
+        let below = g.selected.unwrap();
+        assert!(below == 1 || below == 2);
+        let other = 3 - below;
+        let dir = if g.positions[other].0 > g.positions[below].0 { 1.0 } else { -1.0 };
+        g.move_selection(dir, 0.0);
+        assert_eq!(g.selected, Some(other));
+        g.move_selection(0.0, 1.0);
+        assert_eq!(g.selected, Some(other));
+
+        let (x, y) = g.to_screen(g.positions[1]);
+        assert_eq!(g.hit_test(x + 1.0, y - 1.0), Some(1));
+        assert_eq!(g.hit_test(x + 40.0, y + 40.0), None);
+        assert_eq!(g.describe(0), "node 0  (2 links)");
+        assert_eq!(g.describe(1), "node 1  (1 link)");
+    }
+
+    #[test]
+    fn force_layout_fits_while_settling() {
+        let edges = (1..30).map(|i| (i / 3, i)).collect();
+        let mut g = graph(30, edges, false);
+        assert!(g.follow);
+        while g.tick(1, 1.0 / 240.0) {}
+        assert_eq!(g.force_steps_left, 0);
+        for &p in &g.positions {
+            let (x, y) = g.to_screen(p);
+            let (cx, cy, cw, ch) = g.chart_rect();
+            assert!(x >= cx && x <= cx + cw && y >= cy && y <= cy + ch);
+        }
+        let long = "x".repeat(40);
+        g.nodes[0].label = long;
+        assert_eq!(g.label(0).chars().count(), MAX_LABEL);
+    }
+}
@@ -54,6 +54,86 @@ impl RenderApp {
         }
     }
 
+    /// Translate winit key to a neo-term key
+    #[cfg(feature = "neo-term")]
+    pub(super) fn terminal_key(key: &Key) -> Option<crate::terminal::keyboard::TermKey> {
+        use crate::terminal::keyboard::TermKey;
+        Some(match key {
+            Key::Named(named) => match named {
+                NamedKey::Enter => TermKey::Enter,
+                NamedKey::Tab => TermKey::Tab,
+                NamedKey::Backspace => TermKey::Backspace,
+                NamedKey::Escape => TermKey::Escape,
+                NamedKey::Space => TermKey::Char(' '),
+                NamedKey::ArrowUp => TermKey::Up,
+                NamedKey::ArrowDown => TermKey::Down,
+                NamedKey::ArrowLeft => TermKey::Left,
+                NamedKey::ArrowRight => TermKey::Right,
+                NamedKey::Home => TermKey::Home,
+                NamedKey::End => TermKey::End,
+                NamedKey::PageUp => TermKey::PageUp,
+                NamedKey::PageDown => TermKey::PageDown,
+                NamedKey::Insert => TermKey::Insert,
+                NamedKey::Delete => TermKey::Delete,
+                _ => {
+                    let keysym = Self::translate_key(key);
+                    if (0xffbe..=0xffc9).contains(&keysym) {
+                        TermKey::F((keysym - 0xffbe + 1) as u8)
+                    } else {
+                        return None;
+                    }
+                }
+            },
+            Key::Character(c) => {
+                let mut chars = c.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => TermKey::Char(ch),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        })
+    }
+
+    /// Report a key release to the focused terminal if the program in it
+    /// asked for release events (kitty keyboard protocol).  Presses reach
+    /// the terminal through Emacs, which never sees releases.
+    #[cfg(feature = "neo-term")]
+    pub(super) fn report_terminal_key_release(&mut self, key: &Key) {
+        use crate::backend::wgpu::{NEOMACS_CTRL_MASK, NEOMACS_META_MASK, NEOMACS_SHIFT_MASK, NEOMACS_SUPER_MASK};
+        use crate::terminal::keyboard::{self, KeyEventKind, TermKey};
+        if self.focused_terminal == 0 {
+            return;
+        }
+        let Some(term_key) = Self::terminal_key(key) else {
+            return;
+        };
+        let mut mods = 0;
+        for (mask, bit) in [
+            (NEOMACS_SHIFT_MASK, keyboard::MOD_SHIFT),
+            (NEOMACS_CTRL_MASK, keyboard::MOD_CTRL),
+            (NEOMACS_META_MASK, keyboard::MOD_ALT),
+            (NEOMACS_SUPER_MASK, keyboard::MOD_SUPER),
+        ] {
+            if self.modifiers & mask != 0 {
+                mods |= bit;
+            }
+        }
+        // Emacs reports shifted symbols (`!`) without Shift; match the press
+        if let TermKey::Char(c) = term_key {
+            if !c.is_alphabetic() {
+                mods &= !keyboard::MOD_SHIFT;
+            }
+        }
+        if let Some(view) = self.terminal_manager.get_mut(self.focused_terminal) {
+            if view.reports_key_releases() {
+                if let Err(e) = view.send_key(term_key, mods, KeyEventKind::Release) {
+                    log::warn!("Terminal {} write error: {}", self.focused_terminal, e);
+                }
+            }
+        }
+    }
+
     /// Detect if the mouse is on a resize edge of a borderless window.
     /// Returns the resize direction if within the border zone, or None.
     pub(super) fn detect_resize_edge(
@@ -85,6 +165,25 @@ impl RenderApp {
         }
     }
 
+    /// Pointer icon of Emacs cursor type CURSOR_TYPE (see
+    /// `neomacs_display_set_mouse_cursor`); 0 (hidden) maps to the arrow.
+    pub(super) fn cursor_icon_for_type(cursor_type: i32) -> winit::window::CursorIcon {
+        use winit::window::CursorIcon;
+        match cursor_type {
+            2 => CursorIcon::Text,       // I-beam
+            3 => CursorIcon::Pointer,    // Hand/pointer
+            4 => CursorIcon::Crosshair,
+            5 => CursorIcon::EwResize,   // Horizontal resize
+            6 => CursorIcon::NsResize,   // Vertical resize
+            7 => CursorIcon::Wait,       // Hourglass
+            8 => CursorIcon::NwseResize, // NW-SE (top-left/bottom-right)
+            9 => CursorIcon::NeswResize, // NE-SW (top-right/bottom-left)
+            10 => CursorIcon::NeswResize,
+            11 => CursorIcon::NwseResize,
+            _ => CursorIcon::Default,    // Arrow
+        }
+    }
+
     /// Title bar button width in logical pixels.
     pub(super) const TITLEBAR_BUTTON_WIDTH: f32 = 46.0;
 
@@ -143,6 +242,8 @@ mod tests {
             "test".to_string(),
             image_dimensions,
             shared_monitors,
+            Arc::new(Mutex::new(Default::default())),
+            Default::default(),
             #[cfg(feature = "neo-term")]
             Arc::new(Mutex::new(HashMap::new())),
         );
@@ -319,6 +420,19 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "neo-term")]
+    #[test]
+    fn terminal_key_mapping() {
+        use crate::terminal::keyboard::TermKey;
+        assert_eq!(RenderApp::terminal_key(&Key::Named(NamedKey::Enter)), Some(TermKey::Enter));
+        assert_eq!(RenderApp::terminal_key(&Key::Named(NamedKey::ArrowLeft)), Some(TermKey::Left));
+        assert_eq!(RenderApp::terminal_key(&Key::Named(NamedKey::F7)), Some(TermKey::F(7)));
+        assert_eq!(RenderApp::terminal_key(&Key::Named(NamedKey::Space)), Some(TermKey::Char(' ')));
+        assert_eq!(RenderApp::terminal_key(&Key::Character(SmolStr::new("a"))), Some(TermKey::Char('a')));
+        assert_eq!(RenderApp::terminal_key(&Key::Character(SmolStr::new("ab"))), None);
+        assert_eq!(RenderApp::terminal_key(&Key::Named(NamedKey::Shift)), None);
+    }
+
     #[test]
     fn translate_key_unicode_character() {
         // Multi-byte Unicode characters should return the Unicode code point
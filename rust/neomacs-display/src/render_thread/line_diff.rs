@@ -0,0 +1,199 @@
+//! Line-level diff of a window's displayed rows.
+//!
+//! Used by the smooth text update animation: when a revert or formatting
+//! command rewrites a buffer, the rows of the selected window before and
+//! after are matched (longest common subsequence on a per-row content
+//! key), so kept lines can slide to their new positions while inserted
+//! lines fade in and deleted lines fade out.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use crate::core::frame_glyphs::{FrameGlyph, FrameGlyphBuffer};
+use crate::core::types::Rect;
+
+/// A row of text as displayed in a window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct DisplayRow {
+    /// Frame-absolute top of the row
+    pub(super) y: f32,
+    pub(super) height: f32,
+    /// Hash of the row's content (characters, images and their x offsets
+    /// from the window's left edge)
+    pub(super) key: u64,
+}
+
+/// How one row differs between the old and the new frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum RowChange {
+    /// Present in both frames, possibly at a different y
+    Kept { from_y: f32, to_y: f32, height: f32 },
+    /// Only in the new frame
+    Inserted { y: f32, height: f32 },
+    /// Only in the old frame
+    Deleted { y: f32, height: f32 },
+}
+
+/// Rows of FRAME whose top lies inside AREA (a window's text area),
+/// ordered from top to bottom.  Overlay glyphs (mode lines, echo area)
+/// are ignored.
+pub(super) fn window_rows(frame: &FrameGlyphBuffer, area: &Rect) -> Vec<DisplayRow> {
+    let inside = |x: f32, y: f32| {
+        x >= area.x && x < area.x + area.width && y >= area.y && y < area.y + area.height
+    };
+    // Row y (as bits, for ordering) -> (height, items sorted later)
+    let mut rows: BTreeMap<u32, (f32, Vec<(i32, u32)>)> = BTreeMap::new();
+    let mut add = |x: f32, y: f32, height: f32, item: u32| {
+        let row = rows.entry(y.max(0.0).to_bits()).or_insert((0.0, Vec::new()));
+        row.0 = row.0.max(height);
+        row.1.push(((x - area.x).round() as i32, item));
+    };
+    for glyph in &frame.glyphs {
+        match glyph {
+            FrameGlyph::Char { char, x, y, height, is_overlay: false, .. } if inside(*x, *y) => {
+                add(*x, *y, *height, *char as u32);
+            }
+            FrameGlyph::Stretch { x, y, height, is_overlay: false, .. } if inside(*x, *y) => {
+                // Whitespace only counts through the x offsets it creates
+                add(*x, *y, *height, u32::MAX);
+            }
+            FrameGlyph::Image { image_id, x, y, height, .. } if inside(*x, *y) => {
+                add(*x, *y, *height, 0x11_0000 + *image_id);
+            }
+            _ => {}
+        }
+    }
+    rows.into_iter()
+        .map(|(y_bits, (height, mut items))| {
+            items.sort_unstable();
+            let mut hasher = DefaultHasher::new();
+            for (x, item) in items.iter().filter(|(_, item)| *item != u32::MAX) {
+                x.hash(&mut hasher);
+                item.hash(&mut hasher);
+            }
+            DisplayRow { y: f32::from_bits(y_bits), height, key: hasher.finish() }
+        })
+        .collect()
+}
+
+/// Match OLD rows to NEW rows by content, keeping their order.
+/// Changes are listed in new-frame order, with deleted rows interleaved
+/// where they were.
+pub(super) fn diff_rows(old: &[DisplayRow], new: &[DisplayRow]) -> Vec<RowChange> {
+    let (n, m) = (old.len(), new.len());
+    // lcs[i][j] = length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![0u16; (n + 1) * (m + 1)];
+    let idx = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[idx(i, j)] = if old[i].key == new[j].key {
+                lcs[idx(i + 1, j + 1)] + 1
+            } else {
+                lcs[idx(i + 1, j)].max(lcs[idx(i, j + 1)])
+            };
+        }
+    }
+    let mut changes = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i].key == new[j].key {
+            changes.push(RowChange::Kept { from_y: old[i].y, to_y: new[j].y, height: new[j].height });
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[idx(i, j + 1)] >= lcs[idx(i + 1, j)]) {
+            changes.push(RowChange::Inserted { y: new[j].y, height: new[j].height });
+            j += 1;
+        } else {
+            changes.push(RowChange::Deleted { y: old[i].y, height: old[i].height });
+            i += 1;
+        }
+    }
+    changes
+}
+
+/// Whether CHANGES contain anything to animate.
+pub(super) fn has_changes(changes: &[RowChange]) -> bool {
+    changes.iter().any(|c| match *c {
+        RowChange::Kept { from_y, to_y, .. } => from_y != to_y,
+        _ => true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::Color;
+
+    fn rows(keys: &[u64]) -> Vec<DisplayRow> {
+        keys.iter()
+            .enumerate()
+            .map(|(i, &key)| DisplayRow { y: i as f32 * 10.0, height: 10.0, key })
+            .collect()
+    }
+
+    #[test]
+    fn identical_rows_have_no_changes() {
+        let changes = diff_rows(&rows(&[1, 2, 3]), &rows(&[1, 2, 3]));
+        assert_eq!(changes.len(), 3);
+        assert!(!has_changes(&changes));
+    }
+
+    #[test]
+    fn inserted_row_pushes_following_rows_down() {
+        let changes = diff_rows(&rows(&[1, 2, 3]), &rows(&[1, 9, 2, 3]));
+        assert_eq!(changes, vec![
+            RowChange::Kept { from_y: 0.0, to_y: 0.0, height: 10.0 },
+            RowChange::Inserted { y: 10.0, height: 10.0 },
+            RowChange::Kept { from_y: 10.0, to_y: 20.0, height: 10.0 },
+            RowChange::Kept { from_y: 20.0, to_y: 30.0, height: 10.0 },
+        ]);
+        assert!(has_changes(&changes));
+    }
+
+    #[test]
+    fn deleted_and_replaced_rows() {
+        let changes = diff_rows(&rows(&[1, 2, 3, 4]), &rows(&[1, 5, 4]));
+        let kinds: Vec<&str> = changes.iter().map(|c| match c {
+            RowChange::Kept { .. } => "kept",
+            RowChange::Inserted { .. } => "ins",
+            RowChange::Deleted { .. } => "del",
+        }).collect();
+        assert_eq!(kinds.iter().filter(|k| **k == "kept").count(), 2);
+        assert_eq!(kinds.iter().filter(|k| **k == "ins").count(), 1);
+        assert_eq!(kinds.iter().filter(|k| **k == "del").count(), 2);
+        assert_eq!(changes.last(), Some(&RowChange::Kept { from_y: 30.0, to_y: 20.0, height: 10.0 }));
+    }
+
+    fn char_glyph(c: char, x: f32, y: f32, is_overlay: bool) -> FrameGlyph {
+        FrameGlyph::Char {
+            char: c, composed: None, x, y, width: 8.0, height: 16.0, ascent: 12.0,
+            fg: Color::WHITE, bg: None, face_id: 0, font_weight: 400, italic: false,
+            font_size: 14.0, underline: 0, underline_color: None, strike_through: 0,
+            strike_through_color: None, overline: 0, overline_color: None,
+            is_overlay, overstrike: false, sideways: false,
+        }
+    }
+
+    #[test]
+    fn rows_are_keyed_by_content_and_indentation() {
+        let area = Rect::new(0.0, 0.0, 200.0, 100.0);
+        let mut frame = FrameGlyphBuffer::default();
+        frame.glyphs.push(char_glyph('b', 8.0, 0.0, false));
+        frame.glyphs.push(char_glyph('a', 0.0, 0.0, false));
+        frame.glyphs.push(char_glyph('a', 8.0, 16.0, false));
+        frame.glyphs.push(char_glyph('b', 16.0, 16.0, false));
+        frame.glyphs.push(char_glyph('a', 0.0, 32.0, false));
+        frame.glyphs.push(char_glyph('b', 8.0, 32.0, false));
+        // Mode line text and glyphs outside the window are ignored
+        frame.glyphs.push(char_glyph('m', 0.0, 48.0, true));
+        frame.glyphs.push(char_glyph('z', 300.0, 0.0, false));
+        let rows = window_rows(&frame, &area);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].y, 0.0);
+        assert_eq!(rows[0].height, 16.0);
+        // Same text, different indentation
+        assert_ne!(rows[0].key, rows[1].key);
+        assert_eq!(rows[0].key, rows[2].key);
+    }
+}
@@ -2,12 +2,33 @@
 //!
 //! Owns winit event loop, wgpu, GLib/WebKit. Runs at native VSync.
 
+#[cfg(feature = "accessibility")]
+mod accessibility;
+mod agenda_timeline;
+mod char_picker;
+mod color_picker;
+mod command_palette;
+mod date_picker;
+mod dir_view;
+mod graph_view;
+mod process_monitor;
 pub(crate) mod child_frames;
 mod cursor;
 mod input;
 pub(crate) mod multi_window;
+mod monitors;
 mod popup_menu;
+mod popup_window;
+mod query;
+mod line_diff;
+pub(crate) mod present;
+mod settings_file;
+pub(crate) mod suspend;
 mod transitions;
+mod typeahead;
+mod wheel;
+mod pinch;
+mod startup;
 
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
@@ -25,7 +46,8 @@ use winit::platform::x11::EventLoopBuilderExtX11;
 use winit::platform::wayland::EventLoopBuilderExtWayland;
 
 use crate::backend::wgpu::{
-    WgpuGlyphAtlas, WgpuRenderer,
+    GpuMemoryStats, MemoryBudget, WgpuGlyphAtlas, WgpuRenderer,
+    glyph_disk_cache::{self, GlyphDiskCache},
     NEOMACS_CTRL_MASK, NEOMACS_META_MASK, NEOMACS_SHIFT_MASK, NEOMACS_SUPER_MASK,
 };
 use crate::core::face::Face;
@@ -36,8 +58,20 @@ use crate::core::types::{
 };
 use crate::thread_comm::{InputEvent, PopupMenuItem, RenderCommand, RenderComms};
 use cursor::{CursorTarget, CornerSpring, CursorState};
+pub(crate) use agenda_timeline::AgendaTimelineState;
+pub(crate) use char_picker::CharPickerState;
+pub(crate) use color_picker::{hsv_to_rgb, ColorPickerState};
+pub(crate) use command_palette::CommandPaletteState;
+pub(crate) use date_picker::{days_from_code, DatePickerPart, DatePickerState, MONTH_NAMES, WEEKDAY_NAMES};
+pub(crate) use dir_view::{format_modified, DirViewState, DATE_CHARS, SIZE_CHARS};
+pub(crate) use graph_view::GraphViewState;
+pub(crate) use process_monitor::{
+    sparkline_bars, ProcessMonitorState, CPU_CHARS, MEM_CHARS, PID_CHARS, SPARK_CHARS, USER_CHARS,
+};
 pub(crate) use popup_menu::{MenuPanel, PopupMenuState, TooltipState};
-use transitions::{CrossfadeTransition, ScrollTransition, TransitionState};
+pub(crate) use typeahead::TypeaheadEcho;
+use transitions::{CrossfadeTransition, ForcedTransition, ScrollTransition, TransitionState};
+use startup::{Startup, Task};
 
 #[cfg(all(feature = "wpe-webkit", wpe_platform_available))]
 use crate::backend::wpe::sys::platform as plat;
@@ -50,23 +84,20 @@ use crate::backend::wpe::{WpeBackend, WpeWebView};
 /// Shared storage for image dimensions accessible from both threads
 pub type SharedImageDimensions = Arc<Mutex<HashMap<u32, (u32, u32)>>>;
 
-/// Monitor information collected from winit
-#[derive(Debug, Clone)]
-pub struct MonitorInfo {
-    pub x: i32,
-    pub y: i32,
-    pub width: i32,
-    pub height: i32,
-    pub scale: f64,
-    pub width_mm: i32,
-    pub height_mm: i32,
-    pub name: Option<String>,
-}
+pub use crate::core::monitor::MonitorInfo;
 
 /// Shared storage for monitor info accessible from both threads.
 /// The Condvar is notified once monitors have been populated.
 pub type SharedMonitorInfo = Arc<(Mutex<Vec<MonitorInfo>>, std::sync::Condvar)>;
 
+/// Renderer memory usage, written by the render thread each frame and
+/// read from the FFI thread.
+pub type SharedMemoryStats = Arc<Mutex<GpuMemoryStats>>;
+
+/// Whether a window snapshot for a buffer transition is ready, set by the
+/// render thread and read from the FFI thread.
+pub type SharedTransitionSnapshot = Arc<std::sync::atomic::AtomicBool>;
+
 /// Render thread state
 pub struct RenderThread {
     handle: Option<JoinHandle<()>>,
@@ -81,13 +112,15 @@ impl RenderThread {
         title: String,
         image_dimensions: SharedImageDimensions,
         shared_monitors: SharedMonitorInfo,
+        memory_stats: SharedMemoryStats,
+        transition_snapshot: SharedTransitionSnapshot,
         #[cfg(feature = "neo-term")]
         shared_terminals: crate::terminal::SharedTerminals,
     ) -> Self {
         let handle = thread::spawn(move || {
             run_render_loop(
                 comms, width, height, title, image_dimensions,
-                shared_monitors,
+                shared_monitors, memory_stats, transition_snapshot,
                 #[cfg(feature = "neo-term")]
                 shared_terminals,
             );
@@ -191,6 +224,8 @@ struct WindowChrome {
     last_titlebar_click: std::time::Instant,
     is_fullscreen: bool,
     corner_radius: f32,
+    /// Icon set from Lisp (None = the Neomacs logo)
+    icon: Option<winit::window::Icon>,
 }
 
 impl Default for WindowChrome {
@@ -204,6 +239,7 @@ impl Default for WindowChrome {
             last_titlebar_click: std::time::Instant::now(),
             is_fullscreen: false,
             corner_radius: 0.0,
+            icon: None,
         }
     }
 }
@@ -220,6 +256,8 @@ struct RenderApp {
     renderer: Option<WgpuRenderer>,
     surface: Option<wgpu::Surface<'static>>,
     surface_config: Option<wgpu::SurfaceConfiguration>,
+    /// Present mode and frame latency for all window surfaces
+    present: present::PresentSettings,
     device: Option<Arc<wgpu::Device>>,
     queue: Option<Arc<wgpu::Queue>>,
     glyph_atlas: Option<WgpuGlyphAtlas>,
@@ -237,6 +275,12 @@ struct RenderApp {
     mouse_pos: (f32, f32),
     /// Whether the mouse cursor is hidden during keyboard input
     mouse_hidden_for_typing: bool,
+    /// Whether keyboard input hides the mouse cursor
+    pointer_auto_hide: bool,
+    /// Pointer shape last requested by Emacs (see `cursor_icon_for_type`)
+    mouse_cursor_type: i32,
+    /// Mouse wheel and touchpad scroll settings
+    wheel_config: wheel::WheelConfig,
 
     // Shared image dimensions (written here, read from main thread)
     image_dimensions: SharedImageDimensions,
@@ -272,6 +316,12 @@ struct RenderApp {
     terminal_manager: crate::terminal::TerminalManager,
     #[cfg(feature = "neo-term")]
     shared_terminals: crate::terminal::SharedTerminals,
+    /// Terminal with keyboard focus, which gets key releases (0 = none)
+    #[cfg(feature = "neo-term")]
+    focused_terminal: crate::terminal::TerminalId,
+    /// Bell flash overlays of terminals this frame: (x, y, w, h, alpha)
+    #[cfg(feature = "neo-term")]
+    terminal_flashes: Vec<(f32, f32, f32, f32, f32)>,
 
     // Multi-window manager (secondary OS windows for top-level frames)
     multi_windows: multi_window::MultiWindowManager,
@@ -287,9 +337,37 @@ struct RenderApp {
     child_frame_shadow_offset: f32,
     child_frame_shadow_opacity: f32,
 
+    // Screen reader adapter of the primary window
+    #[cfg(feature = "accessibility")]
+    accessibility: Option<accessibility::AccessKit>,
+
     // Active popup menu (shown by x-popup-menu)
     popup_menu: Option<PopupMenuState>,
 
+    // Active character picker (shown by neomacs-char-picker)
+    char_picker: Option<CharPickerState>,
+
+    // Active command palette (shown by neomacs-command-palette)
+    command_palette: Option<CommandPaletteState>,
+
+    // Active color picker (shown by neomacs-color-picker)
+    color_picker: Option<ColorPickerState>,
+
+    // Active agenda timeline (shown by neomacs-show-agenda-timeline)
+    agenda_timeline: Option<AgendaTimelineState>,
+
+    // Active graph view (shown by neomacs-show-graph)
+    graph_view: Option<GraphViewState>,
+
+    // Active directory view (shown by neomacs-directory-view)
+    dir_view: Option<DirViewState>,
+
+    // Active process monitor (shown by neomacs-process-monitor)
+    process_monitor: Option<ProcessMonitorState>,
+
+    // Active date picker (shown by neomacs-date-picker)
+    date_picker: Option<DatePickerState>,
+
     // Active tooltip overlay
     tooltip: Option<TooltipState>,
 
@@ -321,10 +399,126 @@ struct RenderApp {
     key_press_times: Vec<std::time::Instant>,
     /// Smoothed WPM value for display
     displayed_wpm: f32,
+    /// Typed characters drawn before the core draws them
+    typeahead: TypeaheadEcho,
 
     /// Shared monitor info (populated in resumed(), read from FFI thread)
     shared_monitors: Option<SharedMonitorInfo>,
     monitors_populated: bool,
+    /// When the monitor list was last rechecked
+    last_monitor_check: std::time::Instant,
+
+    /// Memory budgets of the renderer's caches
+    memory_budget: MemoryBudget,
+    /// Memory usage (written each frame, read from FFI thread)
+    memory_stats: SharedMemoryStats,
+    /// Whether a buffer transition snapshot is ready (read from FFI thread)
+    transition_snapshot: SharedTransitionSnapshot,
+    /// Frame clock sampled by all animations
+    clock: crate::core::animation_clock::AnimationClock,
+    /// Background throttling while unfocused or occluded
+    suspend: suspend::SuspendState,
+    /// Display settings file watched for changes
+    settings_file: Option<settings_file::SettingsFile>,
+    /// Icon-font glyphs registered from Lisp (copied into the glyph atlas)
+    icons: crate::core::icons::IconRegistry,
+    /// Initialization started before the window existed, until the
+    /// first frame is presented
+    startup: Option<StartupTasks>,
+    /// Timeline of initialization, once the first frame was presented
+    startup_report: Option<String>,
+    /// The embedded Neomacs logo, decoded during startup
+    logo_icon: Option<winit::window::Icon>,
+}
+
+/// GPU adapter and device requested before the window exists
+struct GpuInit {
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+impl GpuInit {
+    fn request() -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let (adapter, device, queue) = request_gpu(&instance, None)?;
+        Some(Self { instance, adapter, device, queue })
+    }
+}
+
+/// Request an adapter able to present to SURFACE (any adapter if None)
+/// and a device from it
+fn request_gpu(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+) -> Option<(wgpu::Adapter, wgpu::Device, wgpu::Queue)> {
+    let adapter = match pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: crate::gpu_power_preference(),
+        compatible_surface: surface,
+        force_fallback_adapter: false,
+    })) {
+        Some(a) => a,
+        None => {
+            log::error!("Failed to find suitable GPU adapter");
+            return None;
+        }
+    };
+
+    let adapter_info = adapter.get_info();
+    log::info!(
+        "wgpu adapter: {} (vendor={:04x}, device={:04x}, backend={:?})",
+        adapter_info.name,
+        adapter_info.vendor,
+        adapter_info.device,
+        adapter_info.backend
+    );
+
+    match pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some("Neomacs Render Thread Device"),
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::default(),
+            memory_hints: Default::default(),
+        },
+        None,
+    )) {
+        Ok((device, queue)) => Some((adapter, device, queue)),
+        Err(e) => {
+            log::error!("Failed to create wgpu device: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Tasks started by `run_render_loop` while the event loop connects to
+/// the display server, each taken when the window needs its result
+struct StartupTasks {
+    startup: Startup,
+    gpu: Option<Task<Option<GpuInit>>>,
+    /// Font database, and the glyph cache opened for its fonts
+    glyphs: Option<Task<(cosmic_text::FontSystem, GlyphDiskCache)>>,
+    logo: Option<Task<Option<winit::window::Icon>>>,
+}
+
+impl StartupTasks {
+    fn start() -> Self {
+        let startup = Startup::new();
+        let gpu = startup.spawn("gpu", GpuInit::request);
+        let fonts = startup.spawn("fonts", cosmic_text::FontSystem::new);
+        let glyphs = startup.spawn("glyph-cache", move || {
+            let fonts = fonts.wait().unwrap_or_else(cosmic_text::FontSystem::new);
+            let fingerprint = glyph_disk_cache::font_fingerprint(fonts.db());
+            (fonts, GlyphDiskCache::open(GlyphDiskCache::default_path(), fingerprint))
+        });
+        let logo = startup.spawn("window-icon", || {
+            RenderApp::decode_window_icon(include_bytes!("../../assets/logo-128.png"))
+        });
+        Self { startup, gpu: Some(gpu), glyphs: Some(glyphs), logo: Some(logo) }
+    }
 }
 
 impl RenderApp {
@@ -335,6 +529,8 @@ impl RenderApp {
         title: String,
         image_dimensions: SharedImageDimensions,
         shared_monitors: SharedMonitorInfo,
+        memory_stats: SharedMemoryStats,
+        transition_snapshot: SharedTransitionSnapshot,
         #[cfg(feature = "neo-term")]
         shared_terminals: crate::terminal::SharedTerminals,
     ) -> Self {
@@ -352,6 +548,7 @@ impl RenderApp {
             renderer: None,
             surface: None,
             surface_config: None,
+            present: present::PresentSettings::default(),
             device: None,
             queue: None,
             glyph_atlas: None,
@@ -359,6 +556,9 @@ impl RenderApp {
             modifiers: 0,
             mouse_pos: (0.0, 0.0),
             mouse_hidden_for_typing: false,
+            pointer_auto_hide: true,
+            mouse_cursor_type: 1,
+            wheel_config: wheel::WheelConfig::default(),
             image_dimensions,
             frame_dirty: false,
             cursor: CursorState::default(),
@@ -376,6 +576,10 @@ impl RenderApp {
             terminal_manager: crate::terminal::TerminalManager::new(),
             #[cfg(feature = "neo-term")]
             shared_terminals,
+            #[cfg(feature = "neo-term")]
+            focused_terminal: 0,
+            #[cfg(feature = "neo-term")]
+            terminal_flashes: Vec::new(),
             multi_windows: multi_window::MultiWindowManager::new(),
             adapter: None,
             child_frames: child_frames::ChildFrameManager::new(),
@@ -384,7 +588,17 @@ impl RenderApp {
             child_frame_shadow_layers: 4,
             child_frame_shadow_offset: 2.0,
             child_frame_shadow_opacity: 0.3,
+            #[cfg(feature = "accessibility")]
+            accessibility: None,
             popup_menu: None,
+            char_picker: None,
+            command_palette: None,
+            color_picker: None,
+            agenda_timeline: None,
+            graph_view: None,
+            dir_view: None,
+            process_monitor: None,
+            date_picker: None,
             tooltip: None,
             visual_bell_start: None,
             ime_enabled: false,
@@ -398,6 +612,7 @@ impl RenderApp {
             prev_selected_window_id: 0,
             key_press_times: Vec::new(),
             displayed_wpm: 0.0,
+            typeahead: TypeaheadEcho::default(),
             prev_background: None,
             last_activity_time: std::time::Instant::now(),
             idle_dim_current_alpha: 0.0,
@@ -405,6 +620,37 @@ impl RenderApp {
 
             shared_monitors: Some(shared_monitors),
             monitors_populated: false,
+            last_monitor_check: std::time::Instant::now(),
+
+            memory_budget: MemoryBudget::default(),
+            memory_stats,
+            transition_snapshot,
+            clock: crate::core::animation_clock::AnimationClock::new(),
+            suspend: suspend::SuspendState::default(),
+            settings_file: None,
+            icons: crate::core::icons::IconRegistry::new(),
+            startup: None,
+            startup_report: None,
+            logo_icon: None,
+        }
+    }
+
+    /// Run F as the startup task NAME while starting up, or just run it
+    fn startup_run<T>(&self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        match &self.startup {
+            Some(tasks) => tasks.startup.run(name, f),
+            None => f(),
+        }
+    }
+
+    /// Record the first frame and log how long startup took
+    fn finish_startup(&mut self) {
+        if let Some(tasks) = self.startup.take() {
+            let timeline = tasks.startup.timeline();
+            timeline.mark("first-frame");
+            let report = timeline.report();
+            log::info!("{}", report.trim_end());
+            self.startup_report = Some(report);
         }
     }
 
@@ -412,11 +658,24 @@ impl RenderApp {
     fn init_wgpu(&mut self, window: Arc<Window>) {
         log::info!("Initializing wgpu for render thread");
 
-        // Create wgpu instance
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
+        // The adapter and device requested during startup, unless the
+        // adapter cannot present to the window
+        let early = self
+            .startup
+            .as_mut()
+            .and_then(|tasks| tasks.gpu.take())
+            .and_then(Task::wait)
+            .flatten();
+        let (instance, early) = match early {
+            Some(gpu) => (gpu.instance, Some((gpu.adapter, gpu.device, gpu.queue))),
+            None => (
+                wgpu::Instance::new(wgpu::InstanceDescriptor {
+                    backends: wgpu::Backends::all(),
+                    ..Default::default()
+                }),
+                None,
+            ),
+        };
 
         // Create surface from window
         let surface = match instance.create_surface(window.clone()) {
@@ -427,44 +686,19 @@ impl RenderApp {
             }
         };
 
-        // Request adapter
-        let adapter = match pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: crate::gpu_power_preference(),
-            compatible_surface: Some(&surface),
-            force_fallback_adapter: false,
-        })) {
-            Some(a) => a,
-            None => {
-                log::error!("Failed to find suitable GPU adapter");
-                return;
+        let gpu = match early {
+            Some(gpu) if gpu.0.is_surface_supported(&surface) => Some(gpu),
+            Some(_) => {
+                log::info!("GPU adapter from startup cannot present to the window, requesting another");
+                self.startup_run("gpu-retry", || request_gpu(&instance, Some(&surface)))
             }
+            None => self.startup_run("gpu", || request_gpu(&instance, Some(&surface))),
         };
-
-        let adapter_info = adapter.get_info();
-        log::info!(
-            "wgpu adapter: {} (vendor={:04x}, device={:04x}, backend={:?})",
-            adapter_info.name,
-            adapter_info.vendor,
-            adapter_info.device,
-            adapter_info.backend
-        );
-
-        // Request device and queue
-        let (device, queue) = match pollster::block_on(adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                label: Some("Neomacs Render Thread Device"),
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
-                memory_hints: Default::default(),
-            },
-            None,
-        )) {
-            Ok((d, q)) => (d, q),
-            Err(e) => {
-                log::error!("Failed to create wgpu device: {:?}", e);
-                return;
-            }
+        let Some((adapter, device, queue)) = gpu else {
+            return;
         };
+        #[cfg(feature = "wpe-webkit")]
+        let adapter_info = adapter.get_info();
 
         let device = Arc::new(device);
         let queue = Arc::new(queue);
@@ -484,7 +718,7 @@ impl RenderApp {
         } else {
             caps.alpha_modes[0]
         };
-        let config = wgpu::SurfaceConfiguration {
+        let mut config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format,
             width: self.width,
@@ -494,18 +728,36 @@ impl RenderApp {
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
+        self.present.apply(&mut config, &caps.present_modes);
         surface.configure(&device, &config);
 
         // Create renderer with existing device and surface format
-        let renderer = WgpuRenderer::with_device(
-            device.clone(), queue.clone(),
-            self.width, self.height,
-            format,
-            self.scale_factor as f32,
-        );
+        let renderer = self.startup_run("renderer", || {
+            WgpuRenderer::with_device(
+                device.clone(), queue.clone(),
+                self.width, self.height,
+                format,
+                self.scale_factor as f32,
+            )
+        });
 
-        // Create glyph atlas with scale factor for crisp HiDPI text
-        let glyph_atlas = WgpuGlyphAtlas::new_with_scale(&device, self.scale_factor as f32);
+        // Create glyph atlas with scale factor for crisp HiDPI text, with
+        // the fonts and glyph cache loaded during startup
+        let glyphs = self
+            .startup
+            .as_mut()
+            .and_then(|tasks| tasks.glyphs.take())
+            .and_then(Task::wait);
+        let mut glyph_atlas = self.startup_run("glyph-atlas", || match glyphs {
+            Some((fonts, cache)) => {
+                let mut atlas = WgpuGlyphAtlas::with_font_system(&device, self.scale_factor as f32, fonts);
+                atlas.set_disk_cache(cache);
+                atlas
+            }
+            None => WgpuGlyphAtlas::new_with_scale(&device, self.scale_factor as f32),
+        });
+        glyph_atlas.set_memory_budget(self.memory_budget.glyphs);
+        glyph_atlas.set_icons(self.icons.clone());
 
         log::info!(
             "wgpu initialized: {}x{}, format: {:?}",
@@ -521,6 +773,9 @@ impl RenderApp {
         self.queue = Some(queue);
         self.renderer = Some(renderer);
         self.glyph_atlas = Some(glyph_atlas);
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.set_memory_budget(&self.memory_budget);
+        }
 
         // Initialize WPE backend for WebKit
         #[cfg(feature = "wpe-webkit")]
@@ -579,11 +834,12 @@ impl RenderApp {
         // Cancel active transitions (they reference old-sized textures)
         self.transitions.crossfades.clear();
         self.transitions.scroll_slides.clear();
+        self.transitions.clear_buffer_transitions();
 
         // Trigger resize padding transition
         if self.effects.resize_padding.enabled {
             if let Some(renderer) = self.renderer.as_mut() {
-                renderer.trigger_resize_padding(std::time::Instant::now());
+                renderer.trigger_resize_padding(self.clock.sample());
             }
         }
 
@@ -596,11 +852,32 @@ impl RenderApp {
     }
 
 
+    /// Reconfigure all window surfaces with the current present settings.
+    fn apply_present_settings(&mut self) {
+        let (Some(adapter), Some(device)) = (&self.adapter, &self.device) else {
+            return;
+        };
+        if let (Some(surface), Some(config)) = (&self.surface, &mut self.surface_config) {
+            let caps = surface.get_capabilities(adapter);
+            self.present.apply(config, &caps.present_modes);
+            surface.configure(device, config);
+            log::info!("Present mode {:?}, max frame latency {}",
+                config.present_mode, config.desired_maximum_frame_latency);
+        }
+        for state in self.multi_windows.windows.values_mut() {
+            let caps = state.surface.get_capabilities(adapter);
+            self.present.apply(&mut state.surface_config, &caps.present_modes);
+            state.surface.configure(device, &state.surface_config);
+            state.frame_dirty = true;
+        }
+    }
+
     /// Process pending commands from Emacs
     fn process_commands(&mut self) -> bool {
         let mut should_exit = false;
 
-        while let Ok(cmd) = self.comms.cmd_rx.try_recv() {
+        let batch = self.comms.cmd_bus.drain(&self.comms.cmd_rx);
+        for cmd in batch {
             match cmd {
                 RenderCommand::Shutdown => {
                     log::info!("Render thread received shutdown command");
@@ -661,6 +938,24 @@ impl RenderApp {
                         renderer.free_image(id);
                     }
                 }
+                RenderCommand::ImageSetEdits { id, edits } => {
+                    log::debug!("Setting edits of image {}", id);
+                    if let Some(ref mut renderer) = self.renderer {
+                        match renderer.set_image_edits(id, edits) {
+                            Some((w, h)) => {
+                                if let Ok(mut dims) = self.image_dimensions.lock() {
+                                    dims.insert(id, (w, h));
+                                }
+                                self.comms.send_input(InputEvent::ImageDimensionsReady {
+                                    id,
+                                    width: w,
+                                    height: h,
+                                });
+                            }
+                            None => log::warn!("Image {} can't be edited", id),
+                        }
+                    }
+                }
                 RenderCommand::WebKitCreate { id, width, height } => {
                     log::info!("Creating WebKit view: id={}, {}x{}", id, width, height);
                     #[cfg(feature = "wpe-webkit")]
@@ -814,30 +1109,34 @@ impl RenderApp {
                     }
                 }
                 RenderCommand::SetMouseCursor { cursor_type } => {
+                    self.mouse_cursor_type = cursor_type;
                     if let Some(ref window) = self.window {
                         if cursor_type == 0 {
                             // Hidden/invisible cursor
                             window.set_cursor_visible(false);
                         } else {
-                            use winit::window::CursorIcon;
                             window.set_cursor_visible(true);
-                            let icon = match cursor_type {
-                                2 => CursorIcon::Text,       // I-beam
-                                3 => CursorIcon::Pointer,    // Hand/pointer
-                                4 => CursorIcon::Crosshair,
-                                5 => CursorIcon::EwResize,   // Horizontal resize
-                                6 => CursorIcon::NsResize,   // Vertical resize
-                                7 => CursorIcon::Wait,       // Hourglass
-                                8 => CursorIcon::NwseResize, // NW-SE (top-left/bottom-right)
-                                9 => CursorIcon::NeswResize, // NE-SW (top-right/bottom-left)
-                                10 => CursorIcon::NeswResize,
-                                11 => CursorIcon::NwseResize,
-                                _ => CursorIcon::Default,    // Arrow
-                            };
-                            window.set_cursor(icon);
+                            // Borderless resize edges keep their own cursor
+                            if self.chrome.resize_edge.is_none() {
+                                window.set_cursor(Self::cursor_icon_for_type(cursor_type));
+                            }
                         }
                     }
                 }
+                RenderCommand::SetPointerAutoHide { enabled } => {
+                    self.pointer_auto_hide = enabled;
+                }
+                RenderCommand::SetWheelConfig {
+                    wheel_speed, wheel_reverse, touchpad_speed, touchpad_reverse,
+                    shift_horizontal, alt_page,
+                } => {
+                    self.wheel_config = wheel::WheelConfig {
+                        wheel: wheel::WheelDeviceConfig { speed: wheel_speed, reverse: wheel_reverse },
+                        touchpad: wheel::WheelDeviceConfig { speed: touchpad_speed, reverse: touchpad_reverse },
+                        shift_horizontal,
+                        alt_page,
+                    };
+                }
                 RenderCommand::WarpMouse { x, y } => {
                     if let Some(ref window) = self.window {
                         use winit::dpi::PhysicalPosition;
@@ -989,7 +1288,7 @@ impl RenderApp {
                         Ok(view) => {
                             // Register term Arc in shared map for cross-thread access
                             if let Ok(mut shared) = self.shared_terminals.lock() {
-                                shared.insert(id, view.term.clone());
+                                shared.insert(id, view.shared());
                             }
                             self.terminal_manager.terminals.insert(id, view);
                             log::info!("Terminal {} created ({}x{}, {:?})", id, cols, rows, term_mode);
@@ -1029,12 +1328,62 @@ impl RenderApp {
                         view.float_opacity = opacity;
                     }
                 }
+                #[cfg(feature = "neo-term")]
+                RenderCommand::TerminalKey { id, key, mods, kind } => {
+                    if let Some(view) = self.terminal_manager.get_mut(id) {
+                        if let Err(e) = view.send_key(key, mods, kind) {
+                            log::warn!("Terminal {} write error: {}", id, e);
+                        }
+                    }
+                }
+                #[cfg(feature = "neo-term")]
+                RenderCommand::TerminalMouse { id, button, kind, col, row, mods } => {
+                    if let Some(view) = self.terminal_manager.get_mut(id) {
+                        if let Err(e) = view.send_mouse(button, kind, col, row, mods) {
+                            log::warn!("Terminal {} write error: {}", id, e);
+                        }
+                    }
+                }
+                #[cfg(feature = "neo-term")]
+                RenderCommand::TerminalFocus { id } => {
+                    self.focused_terminal = id;
+                }
+                #[cfg(feature = "neo-term")]
+                RenderCommand::TerminalFlash { id } => {
+                    if let Some(view) = self.terminal_manager.get_mut(id) {
+                        view.flash();
+                        self.frame_dirty = true;
+                    }
+                }
+                #[cfg(feature = "neo-term")]
+                RenderCommand::TerminalJumpToPrompt { id, n } => {
+                    if let Some(view) = self.terminal_manager.get_mut(id) {
+                        if view.jump_to_prompt(n) {
+                            self.frame_dirty = true;
+                        }
+                    }
+                }
+                #[cfg(feature = "neo-term")]
+                RenderCommand::TerminalSetPalette { id, palette } => {
+                    if let Some(view) = self.terminal_manager.get_mut(id) {
+                        view.set_palette(*palette);
+                        self.frame_dirty = true;
+                    }
+                }
                 RenderCommand::ShowPopupMenu { x, y, items, title, fg, bg } => {
                     log::info!("ShowPopupMenu at ({}, {}) with {} items", x, y, items.len());
                     let (fs, lh) = self.glyph_atlas.as_ref()
                         .map(|a| (a.default_font_size(), a.default_line_height()))
                         .unwrap_or((13.0, 17.0));
                     let mut menu = PopupMenuState::new(x, y, items, title, fs, lh);
+                    let minibuffer = self.current_frame.as_ref()
+                        .map(|f| f.window_infos.iter().filter(|i| i.is_minibuffer).map(|i| i.bounds).collect())
+                        .unwrap_or_default();
+                    menu.fit_to_screen(
+                        self.width as f32 / self.scale_factor as f32,
+                        self.height as f32 / self.scale_factor as f32,
+                        minibuffer,
+                    );
                     menu.face_fg = fg;
                     menu.face_bg = bg;
                     self.popup_menu = Some(menu);
@@ -1045,6 +1394,179 @@ impl RenderApp {
                     self.popup_menu = None;
                     self.frame_dirty = true;
                 }
+                RenderCommand::UpdateAccessibility { root } => {
+                    #[cfg(feature = "accessibility")]
+                    if let Some(adapter) = self.accessibility.as_mut() {
+                        adapter.update(root.as_deref());
+                    }
+                    #[cfg(not(feature = "accessibility"))]
+                    let _ = root;
+                }
+                RenderCommand::ShowCharPicker { entries, title, fg, bg } => {
+                    log::info!("ShowCharPicker with {} entries", entries.len());
+                    let (fs, lh) = self.glyph_atlas.as_ref()
+                        .map(|a| (a.default_font_size(), a.default_line_height()))
+                        .unwrap_or((13.0, 17.0));
+                    let mut picker = CharPickerState::new(
+                        entries, title,
+                        self.width as f32 / self.scale_factor as f32,
+                        self.height as f32 / self.scale_factor as f32,
+                        fs, lh,
+                    );
+                    picker.face_fg = fg;
+                    picker.face_bg = bg;
+                    self.char_picker = Some(picker);
+                    self.frame_dirty = true;
+                }
+                RenderCommand::HideCharPicker => {
+                    self.char_picker = None;
+                    self.frame_dirty = true;
+                }
+                RenderCommand::ShowCommandPalette { entries, title, fg, bg, match_fg } => {
+                    log::info!("ShowCommandPalette with {} entries", entries.len());
+                    let (fs, lh) = self.glyph_atlas.as_ref()
+                        .map(|a| (a.default_font_size(), a.default_line_height()))
+                        .unwrap_or((13.0, 17.0));
+                    let mut palette = CommandPaletteState::new(
+                        entries, title,
+                        self.width as f32 / self.scale_factor as f32,
+                        self.height as f32 / self.scale_factor as f32,
+                        fs, lh,
+                    );
+                    palette.face_fg = fg;
+                    palette.face_bg = bg;
+                    palette.match_fg = match_fg;
+                    self.command_palette = Some(palette);
+                    self.frame_dirty = true;
+                }
+                RenderCommand::HideCommandPalette => {
+                    self.command_palette = None;
+                    self.frame_dirty = true;
+                }
+                RenderCommand::ShowColorPicker { color, original, title, fg, bg } => {
+                    log::info!("ShowColorPicker for #{:06x}", color);
+                    let (fs, lh) = self.glyph_atlas.as_ref()
+                        .map(|a| (a.default_font_size(), a.default_line_height()))
+                        .unwrap_or((13.0, 17.0));
+                    let mut picker = ColorPickerState::new(
+                        color, original, title,
+                        self.width as f32 / self.scale_factor as f32,
+                        self.height as f32 / self.scale_factor as f32,
+                        fs, lh,
+                    );
+                    picker.face_fg = fg;
+                    picker.face_bg = bg;
+                    self.color_picker = Some(picker);
+                    self.frame_dirty = true;
+                }
+                RenderCommand::HideColorPicker => {
+                    self.color_picker = None;
+                    self.frame_dirty = true;
+                }
+                RenderCommand::ShowAgendaTimeline { items, title, now, utc_offset, fg, bg } => {
+                    log::info!("ShowAgendaTimeline with {} items", items.len());
+                    let (fs, lh) = self.glyph_atlas.as_ref()
+                        .map(|a| (a.default_font_size(), a.default_line_height()))
+                        .unwrap_or((13.0, 17.0));
+                    let mut timeline = AgendaTimelineState::new(
+                        items, title, now, utc_offset,
+                        self.width as f32 / self.scale_factor as f32,
+                        self.height as f32 / self.scale_factor as f32,
+                        fs, lh,
+                    );
+                    timeline.face_fg = fg;
+                    timeline.face_bg = bg;
+                    self.agenda_timeline = Some(timeline);
+                    self.frame_dirty = true;
+                }
+                RenderCommand::HideAgendaTimeline => {
+                    self.agenda_timeline = None;
+                    self.frame_dirty = true;
+                }
+                RenderCommand::ShowGraphView { nodes, edges, layered, current, title, fg, bg } => {
+                    log::info!("ShowGraphView with {} nodes, {} edges", nodes.len(), edges.len());
+                    let (fs, lh) = self.glyph_atlas.as_ref()
+                        .map(|a| (a.default_font_size(), a.default_line_height()))
+                        .unwrap_or((13.0, 17.0));
+                    let mut graph = GraphViewState::new(
+                        nodes.into_vec(), edges.into_vec(), layered, current, title,
+                        self.width as f32 / self.scale_factor as f32,
+                        self.height as f32 / self.scale_factor as f32,
+                        fs, lh,
+                    );
+                    graph.face_fg = fg;
+                    graph.face_bg = bg;
+                    self.graph_view = Some(graph);
+                    self.frame_dirty = true;
+                }
+                RenderCommand::HideGraphView => {
+                    self.graph_view = None;
+                    self.frame_dirty = true;
+                }
+                RenderCommand::ShowDirView { dir, show_hidden, icons, utc_offset, fg, bg } => {
+                    log::info!("ShowDirView for {}", dir);
+                    let (fs, lh) = self.glyph_atlas.as_ref()
+                        .map(|a| (a.default_font_size(), a.default_line_height()))
+                        .unwrap_or((13.0, 17.0));
+                    let mut view = DirViewState::new(
+                        dir.into(), show_hidden, icons, utc_offset,
+                        self.width as f32 / self.scale_factor as f32,
+                        self.height as f32 / self.scale_factor as f32,
+                        fs, lh,
+                    );
+                    view.face_fg = fg;
+                    view.face_bg = bg;
+                    self.dir_view = Some(view);
+                    self.frame_dirty = true;
+                }
+                RenderCommand::HideDirView => {
+                    self.dir_view = None;
+                    self.frame_dirty = true;
+                }
+                RenderCommand::ShowProcessMonitor { interval, fg, bg } => {
+                    log::info!("ShowProcessMonitor every {}s", interval);
+                    let (fs, lh) = self.glyph_atlas.as_ref()
+                        .map(|a| (a.default_font_size(), a.default_line_height()))
+                        .unwrap_or((13.0, 17.0));
+                    let mut view = ProcessMonitorState::new(
+                        std::time::Duration::from_secs_f32(interval.max(0.25)),
+                        self.width as f32 / self.scale_factor as f32,
+                        self.height as f32 / self.scale_factor as f32,
+                        fs, lh,
+                    );
+                    view.face_fg = fg;
+                    view.face_bg = bg;
+                    self.process_monitor = Some(view);
+                    self.frame_dirty = true;
+                }
+                RenderCommand::HideProcessMonitor => {
+                    self.process_monitor = None;
+                    self.frame_dirty = true;
+                }
+                RenderCommand::ShowDatePicker { spec, fg, bg, today, selected_fg, selected_bg, highlight } => {
+                    log::info!("ShowDatePicker: {} months", spec.months);
+                    let (fs, lh) = self.glyph_atlas.as_ref()
+                        .map(|a| (a.default_font_size(), a.default_line_height()))
+                        .unwrap_or((13.0, 17.0));
+                    let mut picker = DatePickerState::new(
+                        *spec,
+                        self.width as f32 / self.scale_factor as f32,
+                        self.height as f32 / self.scale_factor as f32,
+                        fs, lh,
+                    );
+                    picker.face_fg = fg;
+                    picker.face_bg = bg;
+                    picker.today_color = today;
+                    picker.selected_fg = selected_fg;
+                    picker.selected_bg = selected_bg;
+                    picker.highlight_color = highlight;
+                    self.date_picker = Some(picker);
+                    self.frame_dirty = true;
+                }
+                RenderCommand::HideDatePicker => {
+                    self.date_picker = None;
+                    self.frame_dirty = true;
+                }
                 RenderCommand::ShowTooltip { x, y, text, fg_r, fg_g, fg_b, bg_r, bg_g, bg_b } => {
                     log::debug!("ShowTooltip at ({}, {})", x, y);
                     let (fs, lh) = self.glyph_atlas.as_ref()
@@ -1066,11 +1588,11 @@ impl RenderApp {
                     self.frame_dirty = true;
                 }
                 RenderCommand::VisualBell => {
-                    self.visual_bell_start = Some(std::time::Instant::now());
+                    self.visual_bell_start = Some(self.clock.now());
                     // Trigger cursor error pulse if enabled
                     if self.effects.cursor_error_pulse.enabled {
                         if let Some(renderer) = self.renderer.as_mut() {
-                            renderer.trigger_cursor_error_pulse(std::time::Instant::now());
+                            renderer.trigger_cursor_error_pulse(self.clock.now());
                         }
                     }
                     // Trigger edge snap indicator if enabled
@@ -1088,7 +1610,7 @@ impl RenderApp {
                                                 info.mode_line_height,
                                                 at_top,
                                                 at_bottom,
-                                                std::time::Instant::now(),
+                                                self.clock.now(),
                                             );
                                         }
                                     }
@@ -1109,6 +1631,24 @@ impl RenderApp {
                         window.request_user_attention(attention);
                     }
                 }
+                RenderCommand::CancelAttention => {
+                    if let Some(ref window) = self.window {
+                        window.request_user_attention(None);
+                    }
+                }
+                RenderCommand::SetWindowIcon { data } => {
+                    self.chrome.icon = match data {
+                        Some(data) => {
+                            let icon = Self::decode_window_icon(&data);
+                            if icon.is_none() {
+                                log::warn!("SetWindowIcon: cannot decode {} bytes of image data", data.len());
+                            }
+                            icon
+                        }
+                        None => None,
+                    };
+                    self.apply_window_icon();
+                }
                 RenderCommand::UpdateEffect(updater) => {
                     (updater.0)(&mut self.effects);
                     if let Some(renderer) = self.renderer.as_mut() {
@@ -1120,6 +1660,134 @@ impl RenderApp {
                     self.scroll_indicators_enabled = enabled;
                     self.frame_dirty = true;
                 }
+                RenderCommand::StartSmoothTextUpdate => {
+                    if self.effects.smooth_text_update.enabled {
+                        self.transitions.smooth_update_requested = Some(self.clock.now());
+                    }
+                }
+                RenderCommand::CursorBeacon => {
+                    if let Some(renderer) = self.renderer.as_mut() {
+                        renderer.trigger_cursor_beacon();
+                    }
+                    self.frame_dirty = true;
+                }
+                RenderCommand::SetWindowBackground { target, background } => {
+                    if let Some(renderer) = self.renderer.as_mut() {
+                        renderer.window_backgrounds.set(target, background);
+                    }
+                    self.frame_dirty = true;
+                }
+                RenderCommand::SetBackgroundThrottle { policy } => {
+                    let was = self.suspend.is_suspended();
+                    self.suspend.policy = policy;
+                    if was != self.suspend.is_suspended() {
+                        self.on_suspend_changed();
+                    } else {
+                        self.update_background_videos();
+                    }
+                }
+                RenderCommand::SetSettingsFile { path } => {
+                    let path = path.map(std::path::PathBuf::from);
+                    if path.as_deref() != self.settings_file.as_ref().map(|f| f.path()) {
+                        // A new file is read on the next poll
+                        self.settings_file = path.map(settings_file::SettingsFile::new);
+                    }
+                }
+                RenderCommand::Query { id, query } => {
+                    self.answer_query(id, query);
+                }
+                RenderCommand::SetPresentMode { mode, max_frame_latency } => {
+                    self.present = present::PresentSettings { mode, max_frame_latency };
+                    self.apply_present_settings();
+                    self.frame_dirty = true;
+                }
+                RenderCommand::RegisterIcon { spec } => {
+                    self.icons.register(spec);
+                    if let Some(atlas) = self.glyph_atlas.as_mut() {
+                        atlas.set_icons(self.icons.clone());
+                    }
+                    self.frame_dirty = true;
+                }
+                RenderCommand::RemoveIcon { name } => {
+                    match name {
+                        Some(name) => {
+                            self.icons.remove(&name);
+                        }
+                        None => {
+                            self.icons.clear();
+                        }
+                    }
+                    if let Some(atlas) = self.glyph_atlas.as_mut() {
+                        atlas.set_icons(self.icons.clone());
+                    }
+                    self.frame_dirty = true;
+                }
+                RenderCommand::SetMemoryBudget { budget } => {
+                    self.memory_budget = budget;
+                    if let Some(renderer) = self.renderer.as_mut() {
+                        renderer.set_memory_budget(&budget);
+                    }
+                    if let Some(atlas) = self.glyph_atlas.as_mut() {
+                        atlas.set_memory_budget(budget.glyphs);
+                    }
+                    self.transitions.enforce_snapshot_budget(budget.snapshots);
+                    self.frame_dirty = true;
+                }
+                RenderCommand::SetPostShader { path } => {
+                    if let Some(renderer) = self.renderer.as_mut() {
+                        renderer.set_post_shader(path.map(std::path::PathBuf::from));
+                    }
+                    self.frame_dirty = true;
+                }
+                RenderCommand::StartWindowTransition { effect, duration_ms } => {
+                    self.transitions.forced = Some(ForcedTransition {
+                        requested: self.clock.now(),
+                        duration: std::time::Duration::from_millis(duration_ms as u64),
+                        effect,
+                    });
+                }
+                RenderCommand::SetBufferTransition { effect, duration_ms, auto_detect } => {
+                    let animator = &mut self.transitions.buffer_animator;
+                    animator.set_default_effect(effect);
+                    animator.set_default_duration(std::time::Duration::from_millis(duration_ms as u64));
+                    animator.auto_detect = auto_detect;
+                    if effect == crate::core::buffer_transition::BufferTransitionEffect::None {
+                        self.transitions.clear_buffer_transitions();
+                    }
+                }
+                RenderCommand::PrepareBufferTransition => {
+                    self.prepare_buffer_transition();
+                }
+                RenderCommand::PrepareFrameTransition => {
+                    self.prepare_frame_transition();
+                }
+                RenderCommand::TriggerBufferTransition => {
+                    self.trigger_buffer_transition();
+                }
+                RenderCommand::AddTimelineAnimation { id, animation } => {
+                    self.transitions.timeline.add(id, animation);
+                    self.frame_dirty = true;
+                }
+                RenderCommand::CancelTimelineAnimation { id } => {
+                    self.transitions.timeline.cancel(id);
+                    self.frame_dirty = true;
+                }
+                RenderCommand::SetAnimationCurve { target, easing } => {
+                    use crate::core::easing::EasingTarget;
+                    use crate::core::scroll_animation::ScrollEasing;
+                    match target {
+                        EasingTarget::Cursor => self.cursor.anim_curve = easing,
+                        EasingTarget::Scroll => {
+                            self.transitions.scroll_easing = easing.map_or(ScrollEasing::default(), ScrollEasing::Curve);
+                        }
+                        EasingTarget::Crossfade => {
+                            self.transitions.crossfade_easing = easing.map_or(ScrollEasing::EaseOutQuad, ScrollEasing::Curve);
+                        }
+                        EasingTarget::BufferTransition => {
+                            self.transitions.buffer_animator.set_default_easing(easing.unwrap_or_default());
+                        }
+                    }
+                }
                 RenderCommand::SetTitlebarHeight { height } => {
                     self.chrome.titlebar_height = height;
                     self.frame_dirty = true;
@@ -1193,6 +1861,14 @@ impl RenderApp {
                     log::info!("DestroyWindow request: frame_id=0x{:x}", emacs_frame_id);
                     self.multi_windows.request_destroy(emacs_frame_id);
                 }
+                RenderCommand::CreatePopupWindow { emacs_frame_id, width, height } => {
+                    log::info!("CreatePopupWindow request: frame_id=0x{:x} {}x{}",
+                        emacs_frame_id, width, height);
+                    self.multi_windows.request_popup(emacs_frame_id, width, height);
+                }
+                RenderCommand::ResizePopupWindow { emacs_frame_id, width, height } => {
+                    self.multi_windows.resize_popup(emacs_frame_id, width, height);
+                }
             }
         }
 
@@ -1205,13 +1881,15 @@ impl RenderApp {
         // Route child frames to the child frame manager, root frames to current_frame
         // Secondary windows route to multi_windows manager
         self.child_frames.tick();
-        while let Ok(frame) = self.comms.frame_rx.try_recv() {
+        let mut root_arrived = false;
+        for scene in self.comms.scenes.take() {
+            let frame = scene.frame;
             // Check if this frame belongs to a secondary window
             let frame_id = frame.frame_id;
             let parent_id = frame.parent_id;
 
             // Try routing to secondary windows first (by frame_id)
-            if frame_id != 0 && parent_id == 0 && self.multi_windows.windows.contains_key(&frame_id) {
+            if frame_id != 0 && parent_id == 0 && self.multi_windows.owns(frame_id) {
                 self.multi_windows.route_frame(frame);
                 continue;
             }
@@ -1225,10 +1903,14 @@ impl RenderApp {
                 // Child frame: store in primary window's manager
                 self.child_frames.update_frame(frame);
             } else {
-                // Root frame: update primary window's current_frame
-                self.current_frame = Some(frame);
+                // Root frame: update primary window's current_frame,
+                // giving the one it replaces back for reuse
+                if let Some(old) = self.current_frame.replace(frame) {
+                    self.comms.scenes.recycle(old);
+                }
                 // Reset blink to visible when new frame arrives (cursor just moved/redrawn)
-                self.cursor.reset_blink();
+                self.cursor.reset_blink(self.clock.now());
+                root_arrived = true;
             }
             self.frame_dirty = true;
         }
@@ -1256,6 +1938,12 @@ impl RenderApp {
                 });
             }
 
+            // Take back the typeahead the new root frame has drawn
+            if root_arrived {
+                let cursor = active_cursor.as_ref().map(|c| (c.window_id, c.x, c.y));
+                self.typeahead.reconcile(cursor, std::time::Instant::now());
+            }
+
             // If no active cursor in root frame, check child frames
             if active_cursor.is_none() {
                 for (_, entry) in &self.child_frames.frames {
@@ -1306,7 +1994,7 @@ impl RenderApp {
                     self.cursor.prev_target_cx = new_target.x + new_target.width / 2.0;
                     self.cursor.prev_target_cy = new_target.y + new_target.height / 2.0;
                 } else if target_moved {
-                    let now = std::time::Instant::now();
+                    let now = self.clock.now();
                     self.cursor.animating = true;
                     self.cursor.last_anim_time = now;
                     // Capture start position for easing/linear/spring styles
@@ -1417,7 +2105,7 @@ impl RenderApp {
                         self.cursor.size_animating = true;
                         self.cursor.size_start_w = self.cursor.current_w;
                         self.cursor.size_start_h = self.cursor.current_h;
-                        self.cursor.size_anim_start = std::time::Instant::now();
+                        self.cursor.size_anim_start = self.clock.now();
                     }
                     self.cursor.size_target_w = new_target.width;
                     self.cursor.size_target_h = new_target.height;
@@ -1439,6 +2127,12 @@ impl RenderApp {
         if !self.cursor.blink_enabled || self.current_frame.is_none() {
             return false;
         }
+        // Hold the cursor visible in the background
+        if self.suspend.is_suspended() {
+            let toggled = !self.cursor.blink_on;
+            self.cursor.blink_on = true;
+            return toggled;
+        }
         // Check if any cursor exists in the current frame
         let has_cursor = self.current_frame.as_ref()
             .map(|f| f.glyphs.iter().any(|g| matches!(g, crate::core::frame_glyphs::FrameGlyph::Cursor { .. })))
@@ -1446,8 +2140,8 @@ impl RenderApp {
         if !has_cursor {
             return false;
         }
-        let now = std::time::Instant::now();
-        if now.duration_since(self.cursor.last_blink_toggle) >= self.cursor.blink_interval {
+        let now = self.clock.now();
+        if now.saturating_duration_since(self.cursor.last_blink_toggle) >= self.cursor.blink_interval {
             let was_off = !self.cursor.blink_on;
             self.cursor.blink_on = !self.cursor.blink_on;
             self.cursor.last_blink_toggle = now;
@@ -1650,6 +2344,41 @@ impl RenderApp {
     #[cfg(not(feature = "video"))]
     fn has_playing_videos(&self) -> bool { false }
 
+    /// Pause playing videos when going to the background, resume the
+    /// ones paused that way when coming back
+    #[cfg(feature = "video")]
+    fn update_background_videos(&mut self) {
+        let Some(renderer) = self.renderer.as_mut() else { return };
+        if self.suspend.pauses_video() {
+            for id in renderer.playing_videos() {
+                renderer.video_pause(id);
+                self.suspend.paused_videos.push(id);
+            }
+        } else {
+            for id in self.suspend.paused_videos.drain(..) {
+                renderer.video_play(id);
+            }
+        }
+    }
+
+    #[cfg(not(feature = "video"))]
+    fn update_background_videos(&mut self) {}
+
+    /// Apply a focus or occlusion change that suspended or resumed
+    /// rendering
+    fn on_suspend_changed(&mut self) {
+        self.update_background_videos();
+        if !self.suspend.is_suspended() {
+            // Resume at once: restart the blink cycle and redraw now
+            self.cursor.blink_on = true;
+            self.cursor.last_blink_toggle = self.clock.sample();
+            self.frame_dirty = true;
+            if let Some(ref window) = self.window {
+                window.request_redraw();
+            }
+        }
+    }
+
     /// Check if any WebKit view needs redraw
     #[cfg(feature = "wpe-webkit")]
     fn has_webkit_needing_redraw(&self) -> bool {
@@ -1680,6 +2409,22 @@ impl RenderApp {
         }
     }
 
+    /// Publish the memory usage of the renderer's caches
+    fn update_memory_stats(&self) {
+        let mut stats = GpuMemoryStats { budget: self.memory_budget, ..GpuMemoryStats::default() };
+        if let Some(ref renderer) = self.renderer {
+            renderer.collect_memory_stats(&mut stats);
+        }
+        if let Some(ref atlas) = self.glyph_atlas {
+            stats.glyph_bytes = atlas.memory_usage();
+            stats.glyph_count = atlas.len();
+            stats.glyph_evictions = atlas.evictions();
+        }
+        stats.snapshot_bytes = self.transitions.snapshot_bytes();
+        stats.snapshot_evictions = self.transitions.snapshot_evictions;
+        *self.memory_stats.lock().unwrap_or_else(|e| e.into_inner()) = stats;
+    }
+
     /// Update terminal content and expand Terminal glyphs into renderable cells.
     #[cfg(feature = "neo-term")]
     fn update_terminals(&mut self) {
@@ -1720,9 +2465,12 @@ impl RenderApp {
         // Update all terminal content (check for PTY data)
         self.terminal_manager.update_all();
 
-        // Check for exited terminals and notify Emacs
+        // Check for exited terminals and bells, and notify Emacs
         for id in self.terminal_manager.ids() {
             if let Some(view) = self.terminal_manager.get_mut(id) {
+                if view.event_proxy.take_bell() {
+                    self.comms.send_input(InputEvent::TerminalBell { id });
+                }
                 if view.event_proxy.is_exited() && !view.exit_notified {
                     view.exit_notified = true;
                     self.comms.send_input(InputEvent::TerminalExited { id });
@@ -1730,13 +2478,18 @@ impl RenderApp {
             }
         }
 
+        self.terminal_flashes.clear();
+
         // Expand FrameGlyph::Terminal entries (placed by C redisplay) into cells
         if let Some(ref mut frame) = self.current_frame {
             let mut extra_glyphs = Vec::new();
 
             for glyph in &frame.glyphs {
                 if let FrameGlyph::Terminal { terminal_id, x, y, width, height } = glyph {
-                    if let Some(view) = self.terminal_manager.get(*terminal_id) {
+                    if let Some(view) = self.terminal_manager.get_mut(*terminal_id) {
+                        if let Some(alpha) = view.flash_alpha() {
+                            self.terminal_flashes.push((*x, *y, *width, *height, alpha));
+                        }
                         if let Some(content) = view.content() {
                             extra_glyphs.push(FrameGlyph::Stretch {
                                 x: *x, y: *y, width: *width, height: *height,
@@ -1763,15 +2516,19 @@ impl RenderApp {
         if let Some(ref mut frame) = self.current_frame {
             let mut win_glyphs = Vec::new();
             for id in self.terminal_manager.ids() {
-                if let Some(view) = self.terminal_manager.get(id) {
+                if let Some(view) = self.terminal_manager.get_mut(id) {
                     if view.mode != TerminalMode::Window {
                         continue;
                     }
+                    let flash = view.flash_alpha();
                     if let Some(content) = view.content() {
                         let x = 0.0_f32;
                         let y = 0.0_f32;
                         let width = content.cols as f32 * cell_w;
                         let height = content.rows as f32 * cell_h;
+                        if let Some(alpha) = flash {
+                            self.terminal_flashes.push((x, y, width, height, alpha));
+                        }
 
                         // Terminal background
                         win_glyphs.push(FrameGlyph::Stretch {
@@ -1798,15 +2555,19 @@ impl RenderApp {
         if let Some(ref mut frame) = self.current_frame {
             let mut float_glyphs = Vec::new();
             for id in self.terminal_manager.ids() {
-                if let Some(view) = self.terminal_manager.get(id) {
+                if let Some(view) = self.terminal_manager.get_mut(id) {
                     if view.mode != TerminalMode::Floating {
                         continue;
                     }
+                    let flash = view.flash_alpha();
                     if let Some(content) = view.content() {
                         let x = view.float_x;
                         let y = view.float_y;
                         let width = content.cols as f32 * cell_w;
                         let height = content.rows as f32 * cell_h;
+                        if let Some(alpha) = flash {
+                            self.terminal_flashes.push((x, y, width, height, alpha));
+                        }
 
                         let mut bg = content.default_bg;
                         bg.a = view.float_opacity;
@@ -1880,6 +2641,22 @@ impl RenderApp {
                     overline: 0, overline_color: None,
                     overstrike: false,
                     is_overlay,
+                    sideways: false,
+                });
+            }
+        }
+
+        // Subtle separators above shell prompts, between commands
+        if !content.prompt_rows.is_empty() {
+            let mut color = content.default_fg;
+            color.a = 0.15 * opacity;
+            let width = content.cols as f32 * cell_w;
+            for &row in &content.prompt_rows {
+                out.push(FrameGlyph::Stretch {
+                    x: origin_x, y: origin_y + row as f32 * cell_h,
+                    width, height: 1.0,
+                    bg: color, face_id: 0, is_overlay,
+                    stipple_id: 0, stipple_fg: None,
                 });
             }
         }
@@ -2056,9 +2833,17 @@ impl RenderApp {
             }
         };
 
-        let surface_view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        // With post-processing (CRT effect or a user shader), render to an
+        // intermediate texture and run the passes into the surface just
+        // before presenting.
+        let post_view = match self.renderer.as_mut() {
+            Some(renderer) => renderer.prepare_post_effects(self.current_frame.as_ref()),
+            None => None,
+        };
+        let post_active = post_view.is_some();
+        let surface_view = post_view.unwrap_or_else(|| {
+            output.texture.create_view(&wgpu::TextureViewDescriptor::default())
+        });
 
         // Build animated cursor override if applicable
         let animated_cursor = if let (true, Some(target)) =
@@ -2097,7 +2882,16 @@ impl RenderApp {
         };
 
         // Check if we need offscreen rendering (for transitions)
-        let need_offscreen = self.transitions.crossfade_enabled || self.transitions.scroll_enabled;
+        let need_offscreen = self.transitions.crossfade_enabled
+            || self.transitions.scroll_enabled
+            || self.transitions.forced.is_some()
+            || self.transitions.buffer_animator.auto_detect
+            || !self.transitions.buffer_transitions.is_empty()
+            || self.transitions.timeline.is_active()
+            || self.effects.text_zoom.enabled
+            || self.effects.smooth_text_update.enabled
+            || self.effects.magnifier.enabled
+            || self.transitions.pinch.is_some();
 
         if need_offscreen {
             // Swap: previous ← current
@@ -2132,6 +2926,7 @@ impl RenderApp {
 
             // Detect transitions (compare window_infos)
             self.detect_transitions();
+            self.transitions.enforce_snapshot_budget(self.memory_budget.snapshots);
 
             // Blit current offscreen to surface
             if let Some((_, current_bg)) = self.current_offscreen_view_and_bg()
@@ -2260,6 +3055,36 @@ impl RenderApp {
             }
         }
 
+        // Render magnifier lens from the offscreen frame
+        if self.effects.magnifier.enabled {
+            let focus = if self.effects.magnifier.follow_mouse {
+                self.mouse_pos
+            } else {
+                (
+                    self.cursor.current_x + self.cursor.current_w / 2.0,
+                    self.cursor.current_y + self.cursor.current_h / 2.0,
+                )
+            };
+            if let (Some(renderer), Some(glyph_atlas), Some(frame), Some((_, current_bg))) = (
+                self.renderer.as_ref(),
+                self.glyph_atlas.as_mut(),
+                self.current_frame.as_ref(),
+                self.transitions.current_offscreen(),
+            ) {
+                renderer.render_magnifier(
+                    &surface_view,
+                    current_bg,
+                    frame,
+                    glyph_atlas,
+                    &self.faces,
+                    self.cursor.blink_on,
+                    focus,
+                    self.width,
+                    self.height,
+                );
+            }
+        }
+
         // Render popup menu overlay (topmost layer)
         if let Some(ref menu) = self.popup_menu {
             if let (Some(ref renderer), Some(ref mut glyph_atlas)) =
@@ -2269,6 +3094,78 @@ impl RenderApp {
             }
         }
 
+        // Render character picker overlay
+        if let Some(ref picker) = self.char_picker {
+            if let (Some(ref renderer), Some(ref mut glyph_atlas)) =
+                (&self.renderer, &mut self.glyph_atlas)
+            {
+                renderer.render_char_picker(&surface_view, picker, glyph_atlas, self.width, self.height);
+            }
+        }
+
+        // Render command palette overlay
+        if let Some(ref palette) = self.command_palette {
+            if let (Some(ref renderer), Some(ref mut glyph_atlas)) =
+                (&self.renderer, &mut self.glyph_atlas)
+            {
+                renderer.render_command_palette(&surface_view, palette, glyph_atlas, self.width, self.height);
+            }
+        }
+
+        // Render color picker overlay
+        if let Some(ref picker) = self.color_picker {
+            if let (Some(ref renderer), Some(ref mut glyph_atlas)) =
+                (&self.renderer, &mut self.glyph_atlas)
+            {
+                renderer.render_color_picker(&surface_view, picker, glyph_atlas, self.width, self.height);
+            }
+        }
+
+        // Render agenda timeline overlay
+        if let Some(ref timeline) = self.agenda_timeline {
+            if let (Some(ref renderer), Some(ref mut glyph_atlas)) =
+                (&self.renderer, &mut self.glyph_atlas)
+            {
+                renderer.render_agenda_timeline(&surface_view, timeline, glyph_atlas, self.width, self.height);
+            }
+        }
+
+        // Render graph view overlay
+        if let Some(ref graph) = self.graph_view {
+            if let (Some(ref renderer), Some(ref mut glyph_atlas)) =
+                (&self.renderer, &mut self.glyph_atlas)
+            {
+                renderer.render_graph_view(&surface_view, graph, glyph_atlas, self.width, self.height);
+            }
+        }
+
+        // Render directory view overlay
+        if let Some(ref view) = self.dir_view {
+            if let (Some(ref renderer), Some(ref mut glyph_atlas)) =
+                (&self.renderer, &mut self.glyph_atlas)
+            {
+                renderer.render_dir_view(&surface_view, view, glyph_atlas, self.width, self.height);
+            }
+        }
+
+        // Render process monitor overlay
+        if let Some(ref view) = self.process_monitor {
+            if let (Some(ref renderer), Some(ref mut glyph_atlas)) =
+                (&self.renderer, &mut self.glyph_atlas)
+            {
+                renderer.render_process_monitor(&surface_view, view, glyph_atlas, self.width, self.height);
+            }
+        }
+
+        // Render date picker overlay
+        if let Some(ref picker) = self.date_picker {
+            if let (Some(ref renderer), Some(ref mut glyph_atlas)) =
+                (&self.renderer, &mut self.glyph_atlas)
+            {
+                renderer.render_date_picker(&surface_view, picker, glyph_atlas, self.width, self.height);
+            }
+        }
+
         // Render tooltip overlay (above everything including popup menu)
         if let Some(ref tip) = self.tooltip {
             if let (Some(ref renderer), Some(ref mut glyph_atlas)) =
@@ -2296,9 +3193,29 @@ impl RenderApp {
             }
         }
 
+        // Fading highlights over recently yanked, killed or indented text
+        if let Some(ref frame) = self.current_frame {
+            if !frame.region_pulses.is_empty() {
+                let now = self.clock.now();
+                let rects: Vec<_> = frame.region_pulses.iter()
+                    .filter_map(|p| Some((p.x, p.y, p.width, p.height, p.color_at(now)?)))
+                    .collect();
+                if !rects.is_empty() {
+                    if let Some(ref renderer) = self.renderer {
+                        renderer.render_tinted_rects(
+                            &surface_view,
+                            self.width, self.height,
+                            &rects,
+                        );
+                    }
+                    self.frame_dirty = true; // Keep redrawing while fading out
+                }
+            }
+        }
+
         // Render visual bell flash overlay (above everything)
         if let Some(start) = self.visual_bell_start {
-            let elapsed = start.elapsed().as_secs_f32();
+            let elapsed = self.clock.now().saturating_duration_since(start).as_secs_f32();
             let duration = 0.15; // 150ms flash
             if elapsed < duration {
                 let alpha = (1.0 - elapsed / duration) * 0.3; // max 30% opacity, fading out
@@ -2315,6 +3232,19 @@ impl RenderApp {
             }
         }
 
+        // Render terminal bell flashes over their terminals
+        #[cfg(feature = "neo-term")]
+        if !self.terminal_flashes.is_empty() {
+            if let Some(ref renderer) = self.renderer {
+                renderer.render_flash_rects(
+                    &surface_view,
+                    self.width, self.height,
+                    &self.terminal_flashes,
+                );
+            }
+            self.frame_dirty = true; // Keep redrawing while fading out
+        }
+
         // Render FPS counter overlay (topmost) with profiling stats
         if self.fps.enabled {
             // Measure frame time
@@ -2385,6 +3315,28 @@ impl RenderApp {
             }
         }
 
+        // Render typeahead echo until the core draws the keys itself
+        if self.effects.typeahead_echo.enabled {
+            let timeout = std::time::Duration::from_millis(self.effects.typeahead_echo.timeout_ms as u64);
+            self.typeahead.expire(std::time::Instant::now(), timeout);
+            if !self.typeahead.is_empty() {
+                if let (Some(ref renderer), Some(ref mut glyph_atlas), Some(ref frame), Some(ref target)) =
+                    (&self.renderer, &mut self.glyph_atlas, &self.current_frame, &self.cursor.target)
+                {
+                    renderer.render_typeahead_echo(
+                        &surface_view,
+                        frame,
+                        glyph_atlas,
+                        &self.typeahead,
+                        target.color,
+                        self.effects.typeahead_echo.opacity,
+                    );
+                }
+                // Keep redrawing so the echo expires on time
+                self.frame_dirty = true;
+            }
+        }
+
         // Render corner mask for rounded window corners (borderless only, not fullscreen)
         if !self.chrome.decorations_enabled && !self.chrome.is_fullscreen && self.chrome.corner_radius > 0.0 {
             if let Some(ref renderer) = self.renderer {
@@ -2397,19 +3349,57 @@ impl RenderApp {
             }
         }
 
+        // Apply post-processing passes
+        if post_active {
+            if let Some(ref mut renderer) = self.renderer {
+                let output_view = output
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                renderer.render_post_effects(
+                    &output_view,
+                    (self.cursor.current_x, self.cursor.current_y,
+                     self.cursor.current_w, self.cursor.current_h),
+                    self.mouse_pos,
+                );
+            }
+        }
+
         // Present the frame
         output.present();
+
+        self.finish_startup();
+        self.update_memory_stats();
     }
 
-    /// Set the window icon from the embedded Neomacs logo PNG.
-    fn set_window_icon(window: &Window) {
-        let icon_bytes = include_bytes!("../../assets/logo-128.png");
-        if let Ok(img) = image::load_from_memory(icon_bytes) {
-            let rgba = img.to_rgba8();
-            let (w, h) = rgba.dimensions();
-            if let Ok(icon) = winit::window::Icon::from_rgba(rgba.into_raw(), w, h) {
-                window.set_window_icon(Some(icon));
-            }
+    /// Decode an icon from encoded image DATA (PNG, ICO, ...), scaled
+    /// down to at most 256x256
+    fn decode_window_icon(data: &[u8]) -> Option<winit::window::Icon> {
+        let img = image::load_from_memory(data).ok()?;
+        let img = if img.width() > 256 || img.height() > 256 {
+            img.thumbnail(256, 256)
+        } else {
+            img
+        };
+        let rgba = img.to_rgba8();
+        let (w, h) = rgba.dimensions();
+        winit::window::Icon::from_rgba(rgba.into_raw(), w, h).ok()
+    }
+
+    /// The icon set from Lisp, or the embedded Neomacs logo
+    fn window_icon(&self) -> Option<winit::window::Icon> {
+        self.chrome.icon.clone().or_else(|| self.logo_icon.clone()).or_else(|| {
+            Self::decode_window_icon(include_bytes!("../../assets/logo-128.png"))
+        })
+    }
+
+    /// Show the current icon on the main window and all frame windows
+    fn apply_window_icon(&self) {
+        let icon = self.window_icon();
+        if let Some(ref window) = self.window {
+            window.set_window_icon(icon.clone());
+        }
+        for state in self.multi_windows.windows.values() {
+            state.window.set_window_icon(icon.clone());
         }
     }
 
@@ -2423,10 +3413,18 @@ impl ApplicationHandler for RenderApp {
                 .with_title(&self.title)
                 .with_inner_size(winit::dpi::LogicalSize::new(self.width, self.height))
                 .with_transparent(true);
+            // The AccessKit adapter must exist before the window is shown
+            #[cfg(feature = "accessibility")]
+            let attrs = attrs.with_visible(false);
 
-            match event_loop.create_window(attrs) {
+            match self.startup_run("window", || event_loop.create_window(attrs)) {
                 Ok(window) => {
                     let window = Arc::new(window);
+                    #[cfg(feature = "accessibility")]
+                    {
+                        self.accessibility = Some(accessibility::AccessKit::new(event_loop, &window));
+                        window.set_visible(true);
+                    }
 
                     // Read scale factor once at launch
                     self.scale_factor = window.scale_factor();
@@ -2444,8 +3442,14 @@ impl ApplicationHandler for RenderApp {
                     // Enable IME input for CJK and compose support
                     window.set_ime_allowed(true);
 
-                    // Set window icon from embedded Emacs icon
-                    Self::set_window_icon(&window);
+                    // Set window icon (the Neomacs logo unless set from Lisp)
+                    self.logo_icon = self
+                        .startup
+                        .as_mut()
+                        .and_then(|tasks| tasks.logo.take())
+                        .and_then(Task::wait)
+                        .flatten();
+                    window.set_window_icon(self.window_icon());
 
                     self.window = Some(window);
                 }
@@ -2458,44 +3462,11 @@ impl ApplicationHandler for RenderApp {
         // Populate monitor info on first resume (requires ActiveEventLoop)
         if !self.monitors_populated {
             self.monitors_populated = true;
-            if let Some(ref shared) = self.shared_monitors {
-                let mut monitors = Vec::new();
-                for monitor in event_loop.available_monitors() {
-                    let pos = monitor.position();
-                    let size = monitor.size();
-                    let scale = monitor.scale_factor();
-                    let name = monitor.name();
-                    let width_mm = if scale > 0.0 {
-                        (size.width as f64 * 25.4 / (96.0 * scale)) as i32
-                    } else {
-                        0
-                    };
-                    let height_mm = if scale > 0.0 {
-                        (size.height as f64 * 25.4 / (96.0 * scale)) as i32
-                    } else {
-                        0
-                    };
-                    log::info!(
-                        "Monitor: {:?} pos=({},{}) size={}x{} scale={} mm={}x{}",
-                        name, pos.x, pos.y, size.width, size.height, scale, width_mm, height_mm
-                    );
-                    monitors.push(MonitorInfo {
-                        x: pos.x,
-                        y: pos.y,
-                        width: size.width as i32,
-                        height: size.height as i32,
-                        scale,
-                        width_mm,
-                        height_mm,
-                        name,
-                    });
-                }
-                let (ref lock, ref cvar) = **shared;
-                if let Ok(mut shared) = lock.lock() {
-                    *shared = monitors;
-                    cvar.notify_all();
-                }
-            }
+            let monitors = crate::backend::wgpu::monitors::collect_monitors(
+                event_loop.available_monitors(),
+                event_loop.primary_monitor(),
+            );
+            self.publish_monitors(monitors);
         }
     }
 
@@ -2505,6 +3476,13 @@ impl ApplicationHandler for RenderApp {
         _window_id: WindowId,
         event: WindowEvent,
     ) {
+        #[cfg(feature = "accessibility")]
+        if let (Some(adapter), Some(window)) = (self.accessibility.as_mut(), self.window.as_ref()) {
+            if window.id() == _window_id {
+                adapter.process_event(window, &event);
+            }
+        }
+
         match event {
             WindowEvent::CloseRequested => {
                 log::info!("Window close requested");
@@ -2553,6 +3531,19 @@ impl ApplicationHandler for RenderApp {
             WindowEvent::Focused(focused) => {
                 let emacs_fid = self.multi_windows.emacs_frame_for_winit(_window_id).unwrap_or(0);
                 self.comms.send_input(InputEvent::WindowFocus { focused, emacs_frame_id: emacs_fid });
+                // Focus moving between Neomacs windows arrives as a loss
+                // and a gain, so only the main window drives throttling
+                if emacs_fid == 0 && self.suspend.set_focused(focused) {
+                    self.on_suspend_changed();
+                }
+            }
+
+            WindowEvent::Occluded(occluded) => {
+                if self.multi_windows.emacs_frame_for_winit(_window_id).is_none()
+                    && self.suspend.set_occluded(occluded)
+                {
+                    self.on_suspend_changed();
+                }
             }
 
             WindowEvent::KeyboardInput {
@@ -2644,12 +3635,52 @@ impl ApplicationHandler for RenderApp {
                         }
                         _ => {} // Swallow other keys
                     }
+                } else if self.char_picker.is_some() {
+                    if state == ElementState::Pressed {
+                        self.char_picker_key(logical_key.as_ref(), text.as_ref().map(|t| t.as_str()));
+                    }
+                } else if self.command_palette.is_some() {
+                    if state == ElementState::Pressed {
+                        let ctrl = self.modifiers & NEOMACS_CTRL_MASK != 0;
+                        self.command_palette_key(logical_key.as_ref(), text.as_ref().map(|t| t.as_str()), ctrl);
+                    }
+                } else if self.color_picker.is_some() {
+                    if state == ElementState::Pressed {
+                        self.color_picker_key(logical_key.as_ref(), text.as_ref().map(|t| t.as_str()));
+                    }
+                } else if self.agenda_timeline.is_some() {
+                    if state == ElementState::Pressed {
+                        self.agenda_timeline_key(logical_key.as_ref(), text.as_ref().map(|t| t.as_str()));
+                    }
+                } else if self.graph_view.is_some() {
+                    if state == ElementState::Pressed {
+                        self.graph_view_key(logical_key.as_ref(), text.as_ref().map(|t| t.as_str()));
+                    }
+                } else if self.dir_view.is_some() {
+                    if state == ElementState::Pressed {
+                        let ctrl = self.modifiers & NEOMACS_CTRL_MASK != 0;
+                        self.dir_view_key(logical_key.as_ref(), text.as_ref().map(|t| t.as_str()), ctrl);
+                    }
+                } else if self.process_monitor.is_some() {
+                    if state == ElementState::Pressed {
+                        let ctrl = self.modifiers & NEOMACS_CTRL_MASK != 0;
+                        self.process_monitor_key(logical_key.as_ref(), text.as_ref().map(|t| t.as_str()), ctrl);
+                    }
+                } else if self.date_picker.is_some() {
+                    if state == ElementState::Pressed {
+                        let shift = self.modifiers & NEOMACS_SHIFT_MASK != 0;
+                        self.date_picker_key(logical_key.as_ref(), text.as_ref().map(|t| t.as_str()), shift);
+                    }
                 } else if self.ime_preedit_active {
                     // When IME preedit is active, suppress character
                     // keys to avoid double input.  The committed text
                     // will arrive via Ime::Commit instead.
                     log::debug!("IME preedit active, suppressing KeyboardInput: {:?}", logical_key);
                 } else {
+                    #[cfg(feature = "neo-term")]
+                    if state == ElementState::Released {
+                        self.report_terminal_key_release(&logical_key);
+                    }
                     // On X11, some IME backends (e.g. fcitx5 with certain XIM
                     // styles) deliver committed text via KeyboardInput's `text`
                     // field instead of Ime::Commit.  Check `text` first for
@@ -2671,6 +3702,9 @@ impl ApplicationHandler for RenderApp {
                                             modifiers: 0,
                                             pressed: true,
                                         });
+                                        if !ch.is_control() {
+                                            self.typeahead_echo_char(ch);
+                                        }
                                     }
                                 }
                                 handled_via_text = true;
@@ -2681,7 +3715,10 @@ impl ApplicationHandler for RenderApp {
                         let keysym = Self::translate_key(&logical_key);
                         if keysym != 0 {
                             // Hide mouse cursor on keyboard input
-                            if state == ElementState::Pressed && !self.mouse_hidden_for_typing {
+                            if state == ElementState::Pressed
+                                && self.pointer_auto_hide
+                                && !self.mouse_hidden_for_typing
+                            {
                                 if let Some(ref window) = self.window {
                                     window.set_cursor_visible(false);
                                     self.mouse_hidden_for_typing = true;
@@ -2700,6 +3737,13 @@ impl ApplicationHandler for RenderApp {
                                 modifiers: self.modifiers,
                                 pressed: state == ElementState::Pressed,
                             });
+                            if state == ElementState::Pressed {
+                                if let Some(ch) = text.as_deref()
+                                    .and_then(|t| typeahead::echoable(t, self.modifiers))
+                                {
+                                    self.typeahead_echo_char(ch);
+                                }
+                            }
                         }
                     }
                 }
@@ -2747,6 +3791,76 @@ impl ApplicationHandler for RenderApp {
                         self.popup_menu = None;
                         self.frame_dirty = true;
                     }
+                } else if let Some(ref mut picker) = self.char_picker {
+                    if state == ElementState::Pressed {
+                        let (mx, my) = self.mouse_pos;
+                        if button == MouseButton::Left && picker.contains(mx, my) {
+                            // Clicks on the panel outside the grid do nothing
+                            if let Some(i) = picker.hit_test(mx, my) {
+                                picker.selected = i;
+                                self.finish_char_picker(true);
+                            }
+                        } else {
+                            self.finish_char_picker(false);
+                        }
+                    }
+                } else if let Some(ref mut palette) = self.command_palette {
+                    if state == ElementState::Pressed {
+                        let (mx, my) = self.mouse_pos;
+                        if button == MouseButton::Left && palette.contains(mx, my) {
+                            // Clicks on the search line or the
+                            // documentation pane do nothing
+                            if let Some(i) = palette.hit_test(mx, my) {
+                                palette.selected = i;
+                                self.finish_command_palette(true);
+                            }
+                        } else {
+                            self.finish_command_palette(false);
+                        }
+                    }
+                } else if self.color_picker.is_some() {
+                    let (mx, my) = self.mouse_pos;
+                    if button == MouseButton::Left {
+                        self.color_picker_click(state == ElementState::Pressed, mx, my);
+                    } else if state == ElementState::Pressed {
+                        // Any other button cancels the picker
+                        self.finish_color_picker(-1);
+                    }
+                } else if self.agenda_timeline.is_some() {
+                    let (mx, my) = self.mouse_pos;
+                    if button == MouseButton::Left {
+                        self.agenda_timeline_click(state == ElementState::Pressed, mx, my);
+                    } else if state == ElementState::Pressed {
+                        self.finish_agenda_timeline(-1);
+                    }
+                } else if self.graph_view.is_some() {
+                    let (mx, my) = self.mouse_pos;
+                    if button == MouseButton::Left {
+                        self.graph_view_click(state == ElementState::Pressed, mx, my);
+                    } else if state == ElementState::Pressed {
+                        self.finish_graph_view(-1);
+                    }
+                } else if self.dir_view.is_some() {
+                    let (mx, my) = self.mouse_pos;
+                    if button == MouseButton::Left {
+                        self.dir_view_click(state == ElementState::Pressed, mx, my);
+                    } else if state == ElementState::Pressed {
+                        self.finish_dir_view(None);
+                    }
+                } else if self.process_monitor.is_some() {
+                    let (mx, my) = self.mouse_pos;
+                    if button == MouseButton::Left {
+                        self.process_monitor_click(state == ElementState::Pressed, mx, my);
+                    } else if state == ElementState::Pressed {
+                        self.finish_process_monitor(None);
+                    }
+                } else if self.date_picker.is_some() {
+                    let (mx, my) = self.mouse_pos;
+                    if button == MouseButton::Left {
+                        self.date_picker_click(state == ElementState::Pressed, mx, my);
+                    } else if state == ElementState::Pressed {
+                        self.finish_date_picker(None);
+                    }
                 } else if state == ElementState::Pressed
                     && button == MouseButton::Left
                     && self.chrome.resize_edge.is_some()
@@ -2833,7 +3947,7 @@ impl ApplicationHandler for RenderApp {
                     // Click halo effect on press
                     if state == ElementState::Pressed && self.effects.click_halo.enabled {
                         if let Some(renderer) = self.renderer.as_mut() {
-                            renderer.trigger_click_halo(self.mouse_pos.0, self.mouse_pos.1, std::time::Instant::now());
+                            renderer.trigger_click_halo(self.mouse_pos.0, self.mouse_pos.1, self.clock.sample());
                         }
                         self.frame_dirty = true;
                     }
@@ -2845,6 +3959,9 @@ impl ApplicationHandler for RenderApp {
                 let lx = (position.x / self.scale_factor) as f32;
                 let ly = (position.y / self.scale_factor) as f32;
                 self.mouse_pos = (lx, ly);
+                if self.effects.magnifier.enabled && self.effects.magnifier.follow_mouse {
+                    self.frame_dirty = true;
+                }
                 // Track activity for idle dimming
                 if self.effects.idle_dim.enabled {
                     self.last_activity_time = std::time::Instant::now();
@@ -2866,7 +3983,7 @@ impl ApplicationHandler for RenderApp {
                         use winit::window::CursorIcon;
                         let icon = match edge {
                             Some(dir) => CursorIcon::from(dir),
-                            None => CursorIcon::Default,
+                            None => Self::cursor_icon_for_type(self.mouse_cursor_type),
                         };
                         window.set_cursor(icon);
                     }
@@ -2884,7 +4001,7 @@ impl ApplicationHandler for RenderApp {
                                 use winit::window::CursorIcon;
                                 let icon = match new_hover {
                                     2 | 3 | 4 => CursorIcon::Pointer, // buttons
-                                    _ => CursorIcon::Default,
+                                    _ => Self::cursor_icon_for_type(self.mouse_cursor_type),
                                 };
                                 window.set_cursor(icon);
                             }
@@ -2920,6 +4037,36 @@ impl ApplicationHandler for RenderApp {
                             }
                         }
                     }
+                } else if let Some(ref mut picker) = self.char_picker {
+                    if let Some(i) = picker.hit_test(lx, ly) {
+                        if i != picker.selected {
+                            picker.selected = i;
+                            self.frame_dirty = true;
+                        }
+                    }
+                } else if let Some(ref mut palette) = self.command_palette {
+                    if let Some(i) = palette.hit_test(lx, ly) {
+                        if i != palette.selected {
+                            palette.selected = i;
+                            self.frame_dirty = true;
+                        }
+                    }
+                } else if let Some(ref mut picker) = self.color_picker {
+                    if let Some(part) = picker.drag {
+                        if picker.drag_to(part, lx, ly) {
+                            self.frame_dirty = true;
+                        }
+                    }
+                } else if self.agenda_timeline.is_some() {
+                    self.agenda_timeline_mouse_move(lx, ly);
+                } else if self.graph_view.is_some() {
+                    self.graph_view_mouse_move(lx, ly);
+                } else if self.dir_view.is_some() {
+                    self.dir_view_mouse_move(lx, ly);
+                } else if self.process_monitor.is_some() {
+                    self.process_monitor_mouse_move(lx, ly);
+                } else if self.date_picker.is_some() {
+                    self.date_picker_mouse_move(lx, ly);
                 } else {
                     // Hit test child frames for mouse move
                     let (ev_x, ev_y, target_fid) =
@@ -2949,6 +4096,58 @@ impl ApplicationHandler for RenderApp {
                          true)
                     }
                 };
+                // The character picker scrolls its grid instead
+                if let Some(ref mut picker) = self.char_picker {
+                    let rows = if dy > 0.0 { -1 } else if dy < 0.0 { 1 } else { 0 };
+                    if picker.scroll(rows) {
+                        self.frame_dirty = true;
+                    }
+                    return;
+                }
+                // So does the command palette its list
+                if let Some(ref mut palette) = self.command_palette {
+                    let rows = if dy > 0.0 { -3 } else if dy < 0.0 { 3 } else { 0 };
+                    if palette.scroll(rows) {
+                        self.frame_dirty = true;
+                    }
+                    return;
+                }
+                // The color picker changes the value
+                if let Some(ref mut picker) = self.color_picker {
+                    let step = if dy > 0.0 { 0.05 } else if dy < 0.0 { -0.05 } else { 0.0 };
+                    if picker.adjust(0.0, 0.0, step) {
+                        self.frame_dirty = true;
+                    }
+                    return;
+                }
+                // The agenda timeline pans, zooms or scrolls its rows
+                if self.agenda_timeline.is_some() {
+                    let ctrl = self.modifiers & NEOMACS_CTRL_MASK != 0;
+                    let shift = self.modifiers & NEOMACS_SHIFT_MASK != 0;
+                    self.agenda_timeline_wheel(dx, dy, ctrl, shift);
+                    return;
+                }
+                // The graph view pans or zooms
+                if self.graph_view.is_some() {
+                    let ctrl = self.modifiers & NEOMACS_CTRL_MASK != 0;
+                    self.graph_view_wheel(dx, dy, pixel_precise, ctrl);
+                    return;
+                }
+                // The directory view scrolls its rows
+                if self.dir_view.is_some() {
+                    self.dir_view_wheel(dy, pixel_precise);
+                    return;
+                }
+                // So does the process monitor
+                if self.process_monitor.is_some() {
+                    self.process_monitor_wheel(dy, pixel_precise);
+                    return;
+                }
+                // The date picker turns its months
+                if self.date_picker.is_some() {
+                    self.date_picker_wheel(dy, pixel_precise);
+                    return;
+                }
                 // Hit test child frames for scroll
                 let (ev_x, ev_y, target_fid) =
                     if let Some((fid, local_x, local_y)) = self.child_frames.hit_test(self.mouse_pos.0, self.mouse_pos.1) {
@@ -2956,20 +4155,76 @@ impl ApplicationHandler for RenderApp {
                     } else {
                         (self.mouse_pos.0, self.mouse_pos.1, 0)
                     };
+                let page_lines = if target_fid == 0 {
+                    self.current_frame.as_ref()
+                        .map_or(1, |frame| wheel::page_lines(frame, ev_x, ev_y))
+                } else {
+                    self.child_frames.frames.get(&target_fid)
+                        .map_or(1, |entry| wheel::page_lines(&entry.frame, ev_x, ev_y))
+                };
+                let scroll = self.wheel_config.apply(
+                    wheel::WheelDevice::detect(pixel_precise), dx, dy, self.modifiers, page_lines,
+                );
                 self.comms.send_input(InputEvent::MouseScroll {
-                    delta_x: dx,
-                    delta_y: dy,
+                    delta_x: scroll.delta_x,
+                    delta_y: scroll.delta_y,
                     x: ev_x,
                     y: ev_y,
-                    modifiers: self.modifiers,
+                    modifiers: scroll.modifiers,
                     pixel_precise,
+                    lines: scroll.lines,
                     target_frame_id: target_fid,
                 });
             }
 
+            WindowEvent::PinchGesture { delta, phase, .. } => {
+                use winit::event::TouchPhase;
+                match phase {
+                    TouchPhase::Started => {
+                        self.transitions.pinch = self.current_frame.as_ref().and_then(|frame| {
+                            pinch::PinchZoom::start(frame, self.mouse_pos.0, self.mouse_pos.1)
+                        });
+                    }
+                    TouchPhase::Moved => {
+                        if let Some(ref mut zoom) = self.transitions.pinch {
+                            zoom.magnify(delta);
+                            self.frame_dirty = true;
+                        }
+                    }
+                    TouchPhase::Ended => {
+                        if let Some(ref mut zoom) = self.transitions.pinch {
+                            if zoom.ended.is_none() && zoom.end(self.clock.now()) {
+                                // Keep the preview until Emacs redraws the
+                                // window at the snapped size
+                                self.comms.send_input(InputEvent::PinchZoom {
+                                    x: zoom.focus_x,
+                                    y: zoom.focus_y,
+                                    scale: zoom.scale,
+                                    target_frame_id: 0,
+                                });
+                            } else {
+                                self.transitions.pinch = None;
+                            }
+                            self.frame_dirty = true;
+                        }
+                    }
+                    TouchPhase::Cancelled => {
+                        if self.transitions.pinch.take().is_some() {
+                            self.frame_dirty = true;
+                        }
+                    }
+                }
+            }
+
             WindowEvent::RedrawRequested => {
-                self.render();
-                self.frame_dirty = false;
+                let _span = crate::logging::span("render");
+                if let Some(ws) = self.multi_windows.get_by_winit_mut(_window_id) {
+                    ws.frame_dirty = true;
+                    self.render_popup_windows();
+                } else {
+                    self.render();
+                    self.frame_dirty = false;
+                }
             }
 
             WindowEvent::ModifiersChanged(mods) => {
@@ -3017,6 +4272,9 @@ impl ApplicationHandler for RenderApp {
                                     modifiers: 0,
                                     pressed: true,
                                 });
+                                if !ch.is_control() {
+                                    self.typeahead_echo_char(ch);
+                                }
                             }
                         }
                     }
@@ -3059,6 +4317,7 @@ impl ApplicationHandler for RenderApp {
 
             WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
                 log::info!("Scale factor changed: {} -> {}", self.scale_factor, scale_factor);
+                self.poll_monitors(true);
                 self.scale_factor = scale_factor;
                 // Update renderer's scale factor
                 if let Some(ref mut renderer) = self.renderer {
@@ -3076,7 +4335,22 @@ impl ApplicationHandler for RenderApp {
         }
     }
 
+    fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        // Let the next session start without rasterizing its glyphs
+        if let Some(atlas) = self.glyph_atlas.as_mut() {
+            atlas.save_disk_cache();
+        }
+    }
+
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let _span = crate::logging::span("tick");
+        // Sample the animation clock once for everything animated below,
+        // including animations started by Emacs' commands
+        let now = self.clock.tick();
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.set_frame_time(now);
+        }
+
         // Check for shutdown
         if self.process_commands() {
             event_loop.exit();
@@ -3084,13 +4358,27 @@ impl ApplicationHandler for RenderApp {
         }
 
         // Process multi-window creates/destroys
-        if let (Some(device), Some(adapter)) = (&self.device, &self.adapter) {
-            self.multi_windows.process_creates(event_loop, device, adapter);
+        if !self.multi_windows.pending_creates.is_empty() {
+            let monitor = self.focused_monitor();
+            if let (Some(device), Some(adapter)) = (&self.device, &self.adapter) {
+                self.multi_windows.process_creates(
+                    event_loop, device, adapter, &self.present, monitor.as_ref(),
+                );
+            }
         }
         self.multi_windows.process_destroys();
 
+        // Notice monitors being connected, removed or reconfigured
+        self.poll_monitors(false);
+
         // Get latest frame from Emacs
         self.poll_frame();
+        if self.multi_windows.any_dirty() {
+            self.render_popup_windows();
+        }
+
+        // Apply the display settings file if it changed
+        self.poll_settings_file();
 
         // Pump GLib for WebKit
         self.pump_glib();
@@ -3101,15 +4389,43 @@ impl ApplicationHandler for RenderApp {
         }
 
         // Tick cursor animation
-        if self.cursor.tick_animation() {
+        if self.cursor.tick_animation(&self.clock) {
             self.frame_dirty = true;
         }
 
         // Tick cursor size transition (runs after position animation, overrides w/h)
-        if self.cursor.tick_size_animation() {
+        if self.cursor.tick_size_animation(now) {
             self.frame_dirty = true;
         }
 
+        // Ease the agenda timeline toward its pan and zoom target
+        if let Some(ref mut timeline) = self.agenda_timeline {
+            if timeline.tick(self.clock.steps(), self.clock.step_secs()) {
+                self.frame_dirty = true;
+            }
+        }
+
+        // Settle the graph view's layout and ease its view
+        if let Some(ref mut graph) = self.graph_view {
+            if graph.tick(self.clock.steps(), self.clock.step_secs()) {
+                self.frame_dirty = true;
+            }
+        }
+
+        // Show the directory view's listing once it has been read
+        if let Some(ref mut view) = self.dir_view {
+            if view.tick() {
+                self.frame_dirty = true;
+            }
+        }
+
+        // Refresh the process monitor when a new sample arrives
+        if let Some(ref mut view) = self.process_monitor {
+            if view.tick() {
+                self.frame_dirty = true;
+            }
+        }
+
         // Tick idle dimming
         if self.effects.idle_dim.enabled {
             let idle_time = self.last_activity_time.elapsed();
@@ -3120,8 +4436,9 @@ impl ApplicationHandler for RenderApp {
             };
             let diff = target_alpha - self.idle_dim_current_alpha;
             if diff.abs() > 0.001 {
+                let step = self.clock.steps() as f32 * self.clock.step_secs();
                 let fade_speed = if self.effects.idle_dim.fade_duration.as_secs_f32() > 0.0 {
-                    1.0 / self.effects.idle_dim.fade_duration.as_secs_f32() * 0.016
+                    1.0 / self.effects.idle_dim.fade_duration.as_secs_f32() * step
                 } else {
                     1.0
                 };
@@ -3140,14 +4457,26 @@ impl ApplicationHandler for RenderApp {
             }
         }
 
+        // Continuous effects (pulse, particles, animated shaders) pause
+        // in the background
+        let suspended = self.suspend.is_suspended();
+
         // Keep dirty if cursor pulse is active (needs continuous redraw)
-        if self.effects.cursor_pulse.enabled && self.effects.cursor_glow.enabled {
+        if !suspended && self.effects.cursor_pulse.enabled && self.effects.cursor_glow.enabled {
             self.frame_dirty = true;
         }
 
         // Keep dirty if renderer signals need for continuous redraws (dim fade)
         if let Some(ref renderer) = self.renderer {
-            if renderer.needs_continuous_redraw {
+            if !suspended && renderer.needs_continuous_redraw {
+                self.frame_dirty = true;
+            }
+        }
+
+        // Reload the post shader if its file changed; animated shaders
+        // need continuous redraws
+        if let Some(ref mut renderer) = self.renderer {
+            if renderer.poll_post_shader() && !suspended {
                 self.frame_dirty = true;
             }
         }
@@ -3166,17 +4495,27 @@ impl ApplicationHandler for RenderApp {
         let has_active_content = self.has_webkit_needing_redraw() || self.has_playing_videos();
 
         // Request redraw when we have new frame data, cursor blink toggled,
-        // or webkit/video content changed
-        if self.frame_dirty || has_active_content {
+        // or webkit/video content changed.  In the background, frames are
+        // spaced to the trickle rate and pending work waits for the next.
+        let now = std::time::Instant::now();
+        let wants_redraw = self.frame_dirty || has_active_content;
+        if wants_redraw && self.suspend.render_due(now) {
             if let Some(ref window) = self.window {
                 window.request_redraw();
             }
+            self.suspend.note_render(now);
         }
 
         // Use WaitUntil with smart timeouts instead of Poll to save CPU.
         // Window events (key, mouse, resize) still wake immediately.
-        let now = std::time::Instant::now();
-        let next_wake = if self.frame_dirty || has_active_content
+        let next_wake = if suspended {
+            if wants_redraw || self.transitions.has_active() {
+                self.suspend.next_render(now)
+            } else {
+                // Nothing pending: poll for new Emacs frames at the trickle rate
+                now + self.suspend.frame_interval().unwrap_or_default()
+            }
+        } else if self.frame_dirty || has_active_content
             || self.cursor.animating || self.cursor.size_animating
             || self.idle_dim_active || self.transitions.has_active()
         {
@@ -3201,6 +4540,8 @@ fn run_render_loop(
     title: String,
     image_dimensions: SharedImageDimensions,
     shared_monitors: SharedMonitorInfo,
+    memory_stats: SharedMemoryStats,
+    transition_snapshot: SharedTransitionSnapshot,
     #[cfg(feature = "neo-term")]
     shared_terminals: crate::terminal::SharedTerminals,
 ) {
@@ -3225,9 +4566,13 @@ fn run_render_loop(
         ctx
     };
 
+    // Load fonts and set up the GPU on worker threads while the event
+    // loop connects to the display server
+    let startup = StartupTasks::start();
+
     // Use any_thread() since we're running on a non-main thread
     #[cfg(target_os = "linux")]
-    let event_loop = {
+    let event_loop = startup.startup.run("event-loop", || {
         let mut builder = EventLoop::builder();
         // Try Wayland first, fall back to X11
         if std::env::var("WAYLAND_DISPLAY").is_ok() {
@@ -3236,9 +4581,11 @@ fn run_render_loop(
             EventLoopBuilderExtX11::with_any_thread(&mut builder, true);
         }
         builder.build().expect("Failed to create event loop")
-    };
+    });
     #[cfg(not(target_os = "linux"))]
-    let event_loop = EventLoop::new().expect("Failed to create event loop");
+    let event_loop = startup
+        .startup
+        .run("event-loop", || EventLoop::new().expect("Failed to create event loop"));
 
     // Start with WaitUntil to avoid busy-polling; about_to_wait() adjusts dynamically
     event_loop.set_control_flow(ControlFlow::WaitUntil(
@@ -3247,10 +4594,11 @@ fn run_render_loop(
 
     let mut app = RenderApp::new(
         comms, width, height, title, image_dimensions,
-        shared_monitors,
+        shared_monitors, memory_stats, transition_snapshot,
         #[cfg(feature = "neo-term")]
         shared_terminals,
     );
+    app.startup = Some(startup);
 
     if let Err(e) = event_loop.run_app(&mut app) {
         log::error!("Event loop error: {:?}", e);
@@ -0,0 +1,59 @@
+//! Monitor topology tracking on the render thread.
+//!
+//! winit has no event for monitors being connected, removed or
+//! reconfigured, so the list is rechecked periodically and on scale
+//! changes.  When it differs from the shared list, the shared list is
+//! replaced and Emacs gets `InputEvent::MonitorsChanged`.
+
+use std::time::{Duration, Instant};
+
+use super::RenderApp;
+use crate::backend::wgpu::monitors::collect_monitors;
+use crate::core::monitor::MonitorInfo;
+use crate::thread_comm::InputEvent;
+
+/// How often the monitor list is rechecked
+const MONITOR_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+impl RenderApp {
+    /// The monitors as seen by the main window, or None without a window
+    pub(super) fn current_monitors(&self) -> Option<Vec<MonitorInfo>> {
+        let window = self.window.as_ref()?;
+        Some(collect_monitors(window.available_monitors(), window.primary_monitor()))
+    }
+
+    /// Replace the shared monitor list with MONITORS, waking anyone
+    /// waiting for it.  Returns whether the list changed.
+    pub(super) fn publish_monitors(&mut self, monitors: Vec<MonitorInfo>) -> bool {
+        let Some(ref shared) = self.shared_monitors else { return false };
+        let (ref lock, ref cvar) = **shared;
+        let Ok(mut current) = lock.lock() else { return false };
+        if *current == monitors {
+            return false;
+        }
+        for m in &monitors {
+            log::info!(
+                "Monitor: {:?} pos=({},{}) size={}x{} scale={} refresh={}mHz primary={}",
+                m.name, m.x, m.y, m.width, m.height, m.scale, m.refresh_mhz, m.primary
+            );
+        }
+        *current = monitors;
+        cvar.notify_all();
+        true
+    }
+
+    /// Recheck the monitors if due (or FORCE), and tell Emacs if they
+    /// changed
+    pub(super) fn poll_monitors(&mut self, force: bool) {
+        let now = Instant::now();
+        if !force && now.duration_since(self.last_monitor_check) < MONITOR_POLL_INTERVAL {
+            return;
+        }
+        self.last_monitor_check = now;
+        if let Some(monitors) = self.current_monitors() {
+            if self.publish_monitors(monitors) {
+                self.comms.send_input(InputEvent::MonitorsChanged);
+            }
+        }
+    }
+}
@@ -11,6 +11,7 @@ use winit::event_loop::ActiveEventLoop;
 use winit::window::{Window, WindowId};
 
 use crate::core::frame_glyphs::FrameGlyphBuffer;
+use crate::core::monitor::MonitorInfo;
 use super::child_frames::ChildFrameManager;
 
 /// Per-window state. Each Emacs top-level frame gets its own OS window
@@ -38,6 +39,8 @@ pub(crate) struct WindowState {
     pub frame_dirty: bool,
     /// Window title.
     pub title: String,
+    /// For a popup window, the monitor it is centered on.
+    pub popup: Option<MonitorInfo>,
 }
 
 impl WindowState {
@@ -76,6 +79,10 @@ pub(crate) struct PendingWindow {
     pub width: u32,
     pub height: u32,
     pub title: String,
+    /// Whether this is a popup window (see `request_popup`).
+    pub popup: bool,
+    /// Latest frame that arrived before the window was created.
+    pub frame: Option<FrameGlyphBuffer>,
 }
 
 impl MultiWindowManager {
@@ -95,21 +102,63 @@ impl MultiWindowManager {
             width,
             height,
             title,
+            popup: false,
+            frame: None,
         });
     }
 
+    /// Schedule an undecorated, always-on-top popup window, to be
+    /// centered on the focused monitor when it is created.
+    pub fn request_popup(&mut self, emacs_frame_id: u64, width: u32, height: u32) {
+        self.pending_creates.push(PendingWindow {
+            emacs_frame_id,
+            width,
+            height,
+            title: String::new(),
+            popup: true,
+            frame: None,
+        });
+    }
+
+    /// Resize popup window EMACS_FRAME_ID to WIDTH x HEIGHT logical
+    /// pixels, keeping it in place on its monitor.
+    pub fn resize_popup(&mut self, emacs_frame_id: u64, width: u32, height: u32) {
+        if let Some(req) = self.pending_creates.iter_mut()
+            .find(|req| req.emacs_frame_id == emacs_frame_id)
+        {
+            req.width = width;
+            req.height = height;
+            return;
+        }
+        let Some(ws) = self.windows.get(&emacs_frame_id) else { return };
+        let Some(ref monitor) = ws.popup else { return };
+        let size = winit::dpi::LogicalSize::new(width, height).to_physical::<i32>(ws.scale_factor);
+        let (x, y) = monitor.popup_position(size.width, size.height);
+        let _ = ws.window.request_inner_size(size);
+        ws.window.set_outer_position(winit::dpi::PhysicalPosition::new(x, y));
+    }
+
+    /// Whether EMACS_FRAME_ID has a window, or one about to be created.
+    pub fn owns(&self, emacs_frame_id: u64) -> bool {
+        self.windows.contains_key(&emacs_frame_id)
+            || self.pending_creates.iter().any(|req| req.emacs_frame_id == emacs_frame_id)
+    }
+
     /// Schedule a window for destruction.
     pub fn request_destroy(&mut self, emacs_frame_id: u64) {
         self.pending_destroys.push(emacs_frame_id);
     }
 
     /// Process pending window creations. Must be called from the event loop
-    /// (requires ActiveEventLoop for window creation).
+    /// (requires ActiveEventLoop for window creation).  Popup windows are
+    /// centered on MONITOR, the focused one.
     pub fn process_creates(
         &mut self,
         event_loop: &ActiveEventLoop,
         device: &wgpu::Device,
         adapter: &wgpu::Adapter,
+        present: &super::present::PresentSettings,
+        monitor: Option<&MonitorInfo>,
     ) {
         let pending = std::mem::take(&mut self.pending_creates);
         for req in pending {
@@ -118,10 +167,23 @@ impl MultiWindowManager {
                 continue;
             }
 
-            let attrs = Window::default_attributes()
+            let mut attrs = Window::default_attributes()
                 .with_title(&req.title)
                 .with_inner_size(winit::dpi::LogicalSize::new(req.width, req.height))
                 .with_transparent(true);
+            let popup = if req.popup { monitor.cloned() } else { None };
+            if req.popup {
+                attrs = attrs
+                    .with_decorations(false)
+                    .with_resizable(false)
+                    .with_window_level(winit::window::WindowLevel::AlwaysOnTop);
+            }
+            if let Some(ref m) = popup {
+                let size = winit::dpi::LogicalSize::new(req.width, req.height)
+                    .to_physical::<i32>(m.scale);
+                let (x, y) = m.popup_position(size.width, size.height);
+                attrs = attrs.with_position(winit::dpi::PhysicalPosition::new(x, y));
+            }
 
             match event_loop.create_window(attrs) {
                 Ok(window) => {
@@ -152,7 +214,7 @@ impl MultiWindowManager {
                     } else {
                         caps.alpha_modes[0]
                     };
-                    let config = wgpu::SurfaceConfiguration {
+                    let mut config = wgpu::SurfaceConfiguration {
                         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
                         format,
                         width: phys.width,
@@ -162,10 +224,14 @@ impl MultiWindowManager {
                         view_formats: vec![],
                         desired_maximum_frame_latency: 2,
                     };
+                    present.apply(&mut config, &caps.present_modes);
                     surface.configure(device, &config);
 
                     // Enable IME
                     window.set_ime_allowed(true);
+                    if req.popup {
+                        window.focus_window();
+                    }
 
                     let winit_id = window.id();
                     log::info!(
@@ -182,10 +248,11 @@ impl MultiWindowManager {
                         height: phys.height,
                         scale_factor,
                         emacs_frame_id: req.emacs_frame_id,
-                        current_frame: None,
+                        frame_dirty: req.frame.is_some(),
+                        current_frame: req.frame,
                         child_frames: ChildFrameManager::new(),
-                        frame_dirty: false,
                         title: req.title,
+                        popup,
                     });
                 }
                 Err(e) => {
@@ -254,6 +321,12 @@ impl MultiWindowManager {
                 ws.current_frame = Some(frame);
                 ws.frame_dirty = true;
                 return true;
+            } else if let Some(req) = self.pending_creates.iter_mut()
+                .find(|req| req.emacs_frame_id == frame_id)
+            {
+                // Shown once the window exists
+                req.frame = Some(frame);
+                return true;
             }
         }
         false // Not handled — belongs to primary window
@@ -578,6 +651,8 @@ mod tests {
             width: 1920,
             height: 1080,
             title: "My Emacs Frame".to_string(),
+            popup: false,
+            frame: None,
         };
 
         assert_eq!(pw.emacs_frame_id, 123);
@@ -593,6 +668,8 @@ mod tests {
             width: 800,
             height: 600,
             title: "Emacs \u{2014} \u{1F680} Neomacs".to_string(),
+            popup: false,
+            frame: None,
         };
 
         assert!(pw.title.contains('\u{2014}')); // em dash
@@ -668,6 +745,40 @@ mod tests {
         assert!(mgr.windows.is_empty());
     }
 
+    // =======================================================================
+    // request_popup() / owns() — popup windows
+    // =======================================================================
+
+    #[test]
+    fn request_popup_queues_popup_window() {
+        let mut mgr = MultiWindowManager::new();
+        mgr.request_popup(5, 640, 40);
+
+        assert!(mgr.pending_creates[0].popup);
+        assert!(mgr.owns(5));
+        assert!(!mgr.owns(6));
+    }
+
+    #[test]
+    fn resize_pending_popup_updates_request() {
+        let mut mgr = MultiWindowManager::new();
+        mgr.request_popup(5, 640, 40);
+        mgr.resize_popup(5, 640, 300);
+
+        assert_eq!(mgr.pending_creates[0].height, 300);
+    }
+
+    #[test]
+    fn route_frame_holds_frame_for_pending_window() {
+        let mut mgr = MultiWindowManager::new();
+        mgr.request_popup(5, 640, 40);
+
+        assert!(mgr.route_frame(make_frame(5, 0)));
+        assert!(mgr.route_frame(make_frame(5, 0)));
+        assert!(mgr.pending_creates[0].frame.is_some());
+        assert!(mgr.windows.is_empty());
+    }
+
     // =======================================================================
     // any_dirty() / dirty_windows() / count() — empty manager
     // =======================================================================
@@ -0,0 +1,174 @@
+//! Touchpad pinch-to-zoom of a window's text.
+//!
+//! While the fingers move, the text area of the window under the mouse
+//! is scaled on the GPU, about the point the pinch started at, from the
+//! frame already rasterized, so the zoom follows the fingers at frame
+//! rate.  When they lift, the scale goes to Emacs, which snaps it to
+//! the nearest whole font size (see `neomacs-text-scale-pinch').  The
+//! preview stays up until the window is redrawn at that size, so the
+//! crisp frame replaces the scaled one without a jump.
+
+use std::time::{Duration, Instant};
+
+use crate::core::frame_glyphs::FrameGlyphBuffer;
+use crate::core::types::{Color, Rect};
+
+/// Smallest and largest scale a pinch can preview
+const MIN_SCALE: f32 = 0.25;
+const MAX_SCALE: f32 = 4.0;
+
+/// How long an ended pinch waits for the window to be redrawn
+const SNAP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A pinch in progress, or ended and waiting for the redraw
+#[derive(Debug, Clone, PartialEq)]
+pub struct PinchZoom {
+    pub window_id: i64,
+    /// Text area of the window (without tab, header and mode lines)
+    pub bounds: Rect,
+    /// Point the text is scaled about
+    pub focus_x: f32,
+    pub focus_y: f32,
+    /// Character height of the window when the pinch started
+    pub char_height: f32,
+    /// Frame background, shown where shrunk text no longer reaches
+    pub background: Color,
+    pub scale: f32,
+    /// When the fingers lifted
+    pub ended: Option<Instant>,
+}
+
+impl PinchZoom {
+    /// Start a pinch at X, Y of FRAME, over the text area of the window
+    /// there.  None if X, Y is not over the text of a window.
+    pub fn start(frame: &FrameGlyphBuffer, x: f32, y: f32) -> Option<Self> {
+        let info = frame.window_infos.iter().find(|w| {
+            !w.is_minibuffer
+                && x >= w.bounds.x && x < w.bounds.x + w.bounds.width
+                && y >= w.bounds.y && y < w.bounds.y + w.bounds.height
+        })?;
+        let top = info.tab_line_height + info.header_line_height;
+        let bounds = Rect::new(
+            info.bounds.x, info.bounds.y + top,
+            info.bounds.width, info.bounds.height - top - info.mode_line_height,
+        );
+        if bounds.height <= 0.0 || y < bounds.y || y >= bounds.y + bounds.height {
+            return None;
+        }
+        let char_height = if info.char_height > 0.0 { info.char_height } else { frame.char_height };
+        Some(Self {
+            window_id: info.window_id,
+            bounds,
+            focus_x: x,
+            focus_y: y,
+            char_height,
+            background: frame.background,
+            scale: 1.0,
+            ended: None,
+        })
+    }
+
+    /// Follow the fingers; DELTA is the change in magnification since
+    /// the last gesture event.
+    pub fn magnify(&mut self, delta: f64) {
+        if self.ended.is_none() {
+            self.scale = (self.scale * (1.0 + delta as f32)).clamp(MIN_SCALE, MAX_SCALE);
+        }
+    }
+
+    /// The fingers lifted at NOW.  Returns whether the pinch changes the
+    /// text size by a pixel or more; if not, there is nothing to snap to.
+    pub fn end(&mut self, now: Instant) -> bool {
+        self.ended = Some(now);
+        (self.char_height * self.scale).round() != self.char_height.round()
+    }
+
+    /// Whether the redraw of WINDOW_ID is the one this pinch waits for
+    pub fn snaps_to(&self, window_id: i64) -> bool {
+        self.ended.is_some() && self.window_id == window_id
+    }
+
+    /// Whether Emacs failed to redraw the window in time after the end
+    pub fn timed_out(&self, now: Instant) -> bool {
+        self.ended.is_some_and(|t| now.duration_since(t) > SNAP_TIMEOUT)
+    }
+
+    /// Offset of the scaled text area from one scaled about its center,
+    /// which puts the focus point back where it was
+    pub fn offset(&self) -> (f32, f32) {
+        let shrink = 1.0 - self.scale;
+        (
+            shrink * (self.focus_x - self.bounds.x - self.bounds.width / 2.0),
+            shrink * (self.focus_y - self.bounds.y - self.bounds.height / 2.0),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame() -> FrameGlyphBuffer {
+        let mut frame = FrameGlyphBuffer::new();
+        frame.char_height = 20.0;
+        frame.add_window_info(
+            1, 1, 1, 100, 100,
+            0.0, 0.0, 400.0, 420.0,
+            20.0, 20.0, 0.0, true, false, 20.0,
+            String::new(), false,
+        );
+        frame
+    }
+
+    #[test]
+    fn starts_over_text_area_only() {
+        let frame = frame();
+        let pinch = PinchZoom::start(&frame, 100.0, 200.0).unwrap();
+        assert_eq!(pinch.window_id, 1);
+        assert_eq!(pinch.bounds, Rect::new(0.0, 20.0, 400.0, 380.0));
+        // Header line, mode line and outside the window
+        assert!(PinchZoom::start(&frame, 100.0, 10.0).is_none());
+        assert!(PinchZoom::start(&frame, 100.0, 410.0).is_none());
+        assert!(PinchZoom::start(&frame, 500.0, 200.0).is_none());
+    }
+
+    #[test]
+    fn scale_follows_fingers_and_keeps_focus_in_place() {
+        let mut pinch = PinchZoom::start(&frame(), 100.0, 200.0).unwrap();
+        pinch.magnify(0.5);
+        pinch.magnify(0.0);
+        assert_eq!(pinch.scale, 1.5);
+        pinch.magnify(100.0);
+        assert_eq!(pinch.scale, MAX_SCALE);
+
+        // Scaling about the center, then offsetting, leaves the focus
+        // point where it is
+        pinch.scale = 2.0;
+        let (ox, oy) = pinch.offset();
+        let b = pinch.bounds;
+        let x0 = b.x + (b.width - b.width * pinch.scale) / 2.0 + ox;
+        let y0 = b.y + (b.height - b.height * pinch.scale) / 2.0 + oy;
+        assert_eq!(x0 + (pinch.focus_x - b.x) * pinch.scale, pinch.focus_x);
+        assert_eq!(y0 + (pinch.focus_y - b.y) * pinch.scale, pinch.focus_y);
+    }
+
+    #[test]
+    fn ended_pinch_waits_for_redraw() {
+        let now = Instant::now();
+        let mut pinch = PinchZoom::start(&frame(), 100.0, 200.0).unwrap();
+        assert!(!pinch.snaps_to(1));
+        pinch.magnify(0.01);
+        // 20px * 1.01 still rounds to 20px
+        assert!(!pinch.clone().end(now));
+        pinch.magnify(0.2);
+        assert!(pinch.end(now));
+        assert!(pinch.snaps_to(1));
+        assert!(!pinch.snaps_to(2));
+        // No more scaling once the fingers lifted
+        let scale = pinch.scale;
+        pinch.magnify(0.5);
+        assert_eq!(pinch.scale, scale);
+        assert!(!pinch.timed_out(now));
+        assert!(pinch.timed_out(now + Duration::from_secs(1)));
+    }
+}
@@ -1,5 +1,7 @@
 //! Popup menu and tooltip overlay state.
 
+use crate::core::popup_placement::{place_popup, PopupSide};
+use crate::core::types::Rect;
 use crate::thread_comm::PopupMenuItem;
 
 pub(crate) struct MenuPanel {
@@ -31,6 +33,9 @@ pub(crate) struct PopupMenuState {
     pub(crate) face_fg: Option<(f32, f32, f32)>,
     /// Face background color (sRGB 0.0-1.0), None = default
     pub(crate) face_bg: Option<(f32, f32, f32)>,
+    /// Area panels are kept inside and rectangles they stay off (the
+    /// minibuffer), set by `fit_to_screen`
+    screen: Option<(Rect, Vec<Rect>)>,
     /// Font metrics
     font_size: f32,
     line_height: f32,
@@ -108,11 +113,28 @@ impl PopupMenuState {
             submenu_panels: Vec::new(),
             face_fg: None,
             face_bg: None,
+            screen: None,
             font_size,
             line_height,
         }
     }
 
+    /// Keep the menu inside a SCREEN_W x SCREEN_H area and off AVOID:
+    /// the root panel opens below its position or above it, shifted
+    /// sideways as needed, and submenus open to the right of their item
+    /// or to the left.
+    pub(super) fn fit_to_screen(&mut self, screen_w: f32, screen_h: f32, avoid: Vec<Rect>) {
+        let screen = Rect::new(0.0, 0.0, screen_w, screen_h);
+        let (x, y, w, h) = self.root_panel.bounds;
+        let at = place_popup(Rect::new(x, y, 0.0, 0.0), w, h, PopupSide::Below, 0.0, &[screen], &avoid);
+        let indices = self.root_panel.item_indices.clone();
+        self.root_panel = Self::layout_panel(
+            at.x, at.y, &self.all_items, &indices,
+            self.title.as_deref(), self.font_size, self.line_height,
+        );
+        self.screen = Some((screen, avoid));
+    }
+
     /// Get the active panel (deepest open submenu, or root)
     pub(super) fn active_panel(&self) -> &MenuPanel {
         self.submenu_panels.last().unwrap_or(&self.root_panel)
@@ -192,10 +214,20 @@ impl PopupMenuState {
         let sub_x = px + pw - 2.0; // Overlap by 2px
         let sub_y = item_y;
 
-        let sub_panel = Self::layout_panel(
+        let mut sub_panel = Self::layout_panel(
             sub_x, sub_y, &self.all_items, &child_indices,
             None, self.font_size, self.line_height,
         );
+        if let Some((screen, ref avoid)) = self.screen {
+            // Flip to the left of the parent when there is no room
+            let item = Rect::new(px, item_y, pw - 2.0, panel.item_height);
+            let (_, _, w, h) = sub_panel.bounds;
+            let at = place_popup(item, w, h, PopupSide::Right, 0.0, &[screen], avoid);
+            sub_panel = Self::layout_panel(
+                at.x, at.y, &self.all_items, &child_indices,
+                None, self.font_size, self.line_height,
+            );
+        }
         self.submenu_panels.push(sub_panel);
         true
     }
@@ -310,13 +342,12 @@ impl TooltipState {
         let w = (max_line_len as f32 * char_width + padding * 2.0).max(40.0);
         let h = lines.len() as f32 * line_height + padding * 2.0;
 
-        // Position tooltip below and to the right of cursor, clamping to screen
-        let mut tx = x + 10.0;
-        let mut ty = y + 20.0;
-        if tx + w > screen_w { tx = screen_w - w - 2.0; }
-        if ty + h > screen_h { ty = y - h - 5.0; } // flip above cursor
-        if tx < 0.0 { tx = 0.0; }
-        if ty < 0.0 { ty = 0.0; }
+        // Position tooltip below and to the right of cursor, flipped
+        // above it or shifted to stay on screen
+        let pointer = Rect::new(x + 10.0, y, 0.0, 15.0);
+        let screen = Rect::new(0.0, 0.0, screen_w, screen_h);
+        let at = place_popup(pointer, w, h, PopupSide::Below, 5.0, &[screen], &[]);
+        let (tx, ty) = (at.x, at.y);
 
         TooltipState {
             x: tx, y: ty, lines, fg, bg,
@@ -1134,6 +1165,21 @@ mod tests {
             "tooltip y ({}) should be above cursor y ({})", tt.y, cursor_y);
     }
 
+    #[test]
+    fn fitted_menu_flips_up_and_submenus_left() {
+        let items = menu_with_submenu().all_items;
+        let mut menu = PopupMenuState::new(400.0, 50.0, items, None, FONT_SIZE, LINE_HEIGHT);
+        let h = menu.root_panel.bounds.3;
+        // Opened near the bottom right corner of a small screen
+        menu.fit_to_screen(600.0, h + 10.0, Vec::new());
+        assert_eq!((menu.root_panel.bounds.0, menu.root_panel.bounds.1), (400.0, 0.0));
+        menu.root_panel.hover_index = 1;
+        assert!(menu.open_submenu());
+        let (sx, ..) = menu.submenu_panels[0].bounds;
+        assert!(sx + menu.submenu_panels[0].bounds.2 <= menu.root_panel.bounds.0 + 2.0,
+            "submenu at {} should open left of the menu at {}", sx, menu.root_panel.bounds.0);
+    }
+
     #[test]
     fn tooltip_clamps_negative_x() {
         let tt = TooltipState::new(
@@ -0,0 +1,58 @@
+//! Popup windows: undecorated, always-on-top OS windows for minibuffer
+//! launcher frames (`neomacs-popup-frame-call` in Lisp).
+//!
+//! A popup is centered on the monitor of the main window, which is the
+//! focused one when the popup is requested, and drawn with a compact
+//! path: its glyphs straight to its surface, without the transitions,
+//! cursor animation and post effects of the main window.
+
+use super::RenderApp;
+use crate::backend::wgpu::monitors::monitor_info;
+use crate::core::monitor::MonitorInfo;
+
+impl RenderApp {
+    /// The monitor showing the main window, else the primary monitor
+    pub(super) fn focused_monitor(&self) -> Option<MonitorInfo> {
+        let window = self.window.as_ref()?;
+        let primary = window.primary_monitor();
+        let monitor = window.current_monitor().or_else(|| primary.clone())?;
+        Some(monitor_info(&monitor, primary.as_ref()))
+    }
+
+    /// Draw the popup windows whose frames changed
+    pub(super) fn render_popup_windows(&mut self) {
+        let (Some(renderer), Some(glyph_atlas)) = (self.renderer.as_mut(), self.glyph_atlas.as_mut())
+        else {
+            return;
+        };
+        for ws in self.multi_windows.windows.values_mut() {
+            if ws.popup.is_none() || !ws.frame_dirty {
+                continue;
+            }
+            let Some(ref frame) = ws.current_frame else { continue };
+            let output = match ws.surface.get_current_texture() {
+                Ok(output) => output,
+                Err(e) => {
+                    log::debug!("Popup window surface error: {:?}", e);
+                    continue;
+                }
+            };
+            let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+            renderer.render_frame_glyphs(
+                &view,
+                frame,
+                glyph_atlas,
+                &frame.faces,
+                ws.width,
+                ws.height,
+                true,
+                None,
+                (-1.0, -1.0),
+                None,
+            );
+            ws.window.pre_present_notify();
+            output.present();
+            ws.frame_dirty = false;
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Surface presentation settings: present mode (vsync/tearing) and the
+//! maximum number of frames queued for presentation.
+
+/// How frames are handed to the compositor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PresentSettings {
+    pub mode: wgpu::PresentMode,
+    /// Maximum frames queued ahead of the display (1 = lowest latency)
+    pub max_frame_latency: u32,
+}
+
+impl Default for PresentSettings {
+    fn default() -> Self {
+        Self { mode: wgpu::PresentMode::Fifo, max_frame_latency: 2 }
+    }
+}
+
+/// Present mode from its FFI code: 0 = Fifo (vsync), 1 = Mailbox
+/// (vsync without blocking), 2 = Immediate (no vsync, may tear),
+/// 3 = FifoRelaxed (vsync, tears when late).
+pub(crate) fn present_mode_from_ffi(code: i32) -> wgpu::PresentMode {
+    match code {
+        1 => wgpu::PresentMode::Mailbox,
+        2 => wgpu::PresentMode::Immediate,
+        3 => wgpu::PresentMode::FifoRelaxed,
+        _ => wgpu::PresentMode::Fifo,
+    }
+}
+
+impl PresentSettings {
+    /// The requested mode if SUPPORTED, else the closest supported one.
+    /// Fifo is always supported.
+    pub(crate) fn mode_for(&self, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+        use wgpu::PresentMode::*;
+        let fallbacks: &[wgpu::PresentMode] = match self.mode {
+            Mailbox => &[Mailbox, Immediate, Fifo],
+            Immediate => &[Immediate, Mailbox, Fifo],
+            FifoRelaxed => &[FifoRelaxed, Fifo],
+            _ => &[Fifo],
+        };
+        fallbacks
+            .iter()
+            .copied()
+            .find(|mode| supported.contains(mode))
+            .unwrap_or(Fifo)
+    }
+
+    /// Apply these settings to CONFIG for a surface supporting SUPPORTED
+    /// present modes.
+    pub(crate) fn apply(&self, config: &mut wgpu::SurfaceConfiguration, supported: &[wgpu::PresentMode]) {
+        let mode = self.mode_for(supported);
+        if mode != self.mode {
+            log::info!("Present mode {:?} unsupported, using {:?}", self.mode, mode);
+        }
+        config.present_mode = mode;
+        config.desired_maximum_frame_latency = self.max_frame_latency.clamp(1, 3);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::PresentMode::*;
+
+    #[test]
+    fn ffi_codes() {
+        assert_eq!(present_mode_from_ffi(0), Fifo);
+        assert_eq!(present_mode_from_ffi(1), Mailbox);
+        assert_eq!(present_mode_from_ffi(2), Immediate);
+        assert_eq!(present_mode_from_ffi(3), FifoRelaxed);
+        assert_eq!(present_mode_from_ffi(99), Fifo);
+    }
+
+    #[test]
+    fn unsupported_modes_fall_back() {
+        let settings = |mode| PresentSettings { mode, max_frame_latency: 2 };
+        assert_eq!(settings(Mailbox).mode_for(&[Fifo, Mailbox]), Mailbox);
+        assert_eq!(settings(Mailbox).mode_for(&[Fifo, Immediate]), Immediate);
+        assert_eq!(settings(Immediate).mode_for(&[Fifo, Mailbox]), Mailbox);
+        assert_eq!(settings(FifoRelaxed).mode_for(&[Fifo]), Fifo);
+        assert_eq!(settings(Immediate).mode_for(&[]), Fifo);
+    }
+}
@@ -0,0 +1,631 @@
+//! Process monitor overlay state.
+//!
+//! A live table of processes, like Proced, refreshed from samples the
+//! process monitor takes on a worker thread.  Each row shows the pid,
+//! owner, CPU use with a sparkline of its recent history, resident
+//! memory and command line; the title shows the load of the whole
+//! system.  Clicking a column header or Tab sorts by that column, `/`
+//! filters the rows as the query is typed, and the selection follows
+//! its process across refreshes.  `k`, `K`, `h`, `s` and `c` send the
+//! selected process TERM, KILL, HUP, STOP and CONT after asking for
+//! confirmation; `+` and `-` change its nice value.  Enter or a click
+//! on the selected row reports the process to Emacs.
+
+use std::time::Duration;
+
+use winit::keyboard::{Key, NamedKey};
+
+use super::RenderApp;
+use crate::core::process_monitor::{
+    filter_processes, renice, send_signal, sort_processes, ProcessInfo, ProcessMonitor,
+    ProcessSortKey, Sampler, Snapshot, SIGNALS,
+};
+use crate::thread_comm::InputEvent;
+
+/// Widths of the pid, user, CPU, sparkline and memory columns, in
+/// characters
+pub(crate) const PID_CHARS: usize = 7;
+pub(crate) const USER_CHARS: usize = 9;
+pub(crate) const CPU_CHARS: usize = 6;
+pub(crate) const SPARK_CHARS: usize = 12;
+pub(crate) const MEM_CHARS: usize = 7;
+/// Rows scrolled per line of a mouse wheel without pixel deltas
+const WHEEL_LINE_ROWS: isize = 3;
+
+/// Bars (x, y, width, height) of a sparkline of SAMPLES in the box X, Y,
+/// W x H, newest at the right.  Bars are scaled to 100 percent, or to
+/// the largest sample if more.
+pub(crate) fn sparkline_bars(samples: &[f32], x: f32, y: f32, w: f32, h: f32) -> Vec<(f32, f32, f32, f32)> {
+    let slots = crate::core::process_monitor::HISTORY_LEN;
+    let bar_w = w / slots as f32;
+    let max = samples.iter().copied().fold(100.0_f32, f32::max);
+    let start = slots.saturating_sub(samples.len());
+    samples
+        .iter()
+        .rev()
+        .take(slots)
+        .rev()
+        .enumerate()
+        .filter(|(_, &s)| s > 0.0)
+        .map(|(i, &s)| {
+            // At least a pixel, so any use shows
+            let bar_h = (h * s / max).max(1.0);
+            (x + (start + i) as f32 * bar_w, y + h - bar_h, (bar_w - 1.0).max(1.0), bar_h)
+        })
+        .collect()
+}
+
+/// An action on a process waiting for confirmation
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PendingSignal {
+    pub(crate) pid: i32,
+    pub(crate) name: String,
+    pub(crate) signal: (&'static str, i32),
+}
+
+pub(crate) struct ProcessMonitorState {
+    /// Sampler thread; None in tests
+    monitor: Option<ProcessMonitor>,
+    /// The last sample, processes sorted
+    pub(crate) snapshot: Snapshot,
+    /// Whether a sample has arrived
+    pub(crate) sampled: bool,
+    /// Indices of the processes matching the query
+    pub(crate) matches: Vec<usize>,
+    pub(crate) query: String,
+    /// Whether typing edits the query
+    pub(crate) filtering: bool,
+    pub(crate) sort: ProcessSortKey,
+    pub(crate) descending: bool,
+    /// Selected position in `matches`
+    pub(crate) selected: usize,
+    /// Process selected, kept selected across samples
+    selected_pid: Option<i32>,
+    /// First row shown
+    pub(crate) scroll_row: usize,
+    /// Row under the mouse
+    pub(crate) hover: Option<usize>,
+    /// Signal to send once confirmed
+    pub(crate) pending: Option<PendingSignal>,
+    /// Result of the last action, for the footer
+    pub(crate) message: Option<String>,
+    /// Face foreground color (sRGB 0.0-1.0), None = default
+    pub(crate) face_fg: Option<(f32, f32, f32)>,
+    /// Face background color (sRGB 0.0-1.0), None = default
+    pub(crate) face_bg: Option<(f32, f32, f32)>,
+    /// Panel (x, y, width, height) in logical pixels
+    pub(crate) bounds: (f32, f32, f32, f32),
+    /// Height of a row, the title, header and footer lines included
+    pub(crate) line_height: f32,
+    /// Advance of a character of the overlay font
+    pub(crate) char_width: f32,
+    pub(crate) padding: f32,
+}
+
+impl ProcessMonitorState {
+    /// Lay out a process monitor over most of a SCREEN_W x SCREEN_H
+    /// window and start sampling every INTERVAL.
+    pub(super) fn new(
+        interval: Duration,
+        screen_w: f32, screen_h: f32,
+        font_size: f32, line_height: f32,
+    ) -> Self {
+        Self::with_monitor(
+            Some(ProcessMonitor::start(Sampler::new(), interval)),
+            screen_w, screen_h, font_size, line_height,
+        )
+    }
+
+    fn with_monitor(
+        monitor: Option<ProcessMonitor>,
+        screen_w: f32, screen_h: f32,
+        font_size: f32, line_height: f32,
+    ) -> Self {
+        let padding = 8.0_f32;
+        let row_h = line_height + 4.0;
+        let w = (screen_w * 0.85).clamp(500.0_f32.min(screen_w), 1400.0).floor();
+        let h = (screen_h * 0.8).floor().max(8.0 * row_h + 2.0 * padding);
+        let x = ((screen_w - w) / 2.0).max(0.0).floor();
+        let y = ((screen_h - h) / 2.0).max(0.0).floor();
+        ProcessMonitorState {
+            monitor,
+            snapshot: Snapshot::default(),
+            sampled: false,
+            matches: Vec::new(),
+            query: String::new(),
+            filtering: false,
+            sort: ProcessSortKey::Cpu,
+            descending: true,
+            selected: 0,
+            selected_pid: None,
+            scroll_row: 0,
+            hover: None,
+            pending: None,
+            message: None,
+            face_fg: None,
+            face_bg: None,
+            bounds: (x, y, w, h),
+            line_height: row_h,
+            char_width: font_size * 0.6,
+            padding,
+        }
+    }
+
+    /// Take the latest sample if one arrived.  Returns true if so.
+    pub(super) fn tick(&mut self) -> bool {
+        let Some(snapshot) = self.monitor.as_ref().and_then(ProcessMonitor::poll) else {
+            return false;
+        };
+        self.set_snapshot(snapshot);
+        true
+    }
+
+    /// Show SNAPSHOT, keeping the selected process selected.
+    fn set_snapshot(&mut self, mut snapshot: Snapshot) {
+        sort_processes(&mut snapshot.processes, self.sort, self.descending);
+        self.snapshot = snapshot;
+        self.sampled = true;
+        self.refilter();
+    }
+
+    /// Filter the processes by the query again, and find the selected
+    /// process in the result, or select the first.
+    fn refilter(&mut self) {
+        self.matches = filter_processes(&self.snapshot.processes, &self.query);
+        let pos = self.selected_pid.and_then(|pid| {
+            self.matches.iter().position(|&i| self.snapshot.processes[i].pid == pid)
+        });
+        match pos {
+            Some(pos) => self.select(pos),
+            None => {
+                self.scroll_row = self.scroll_row.min(self.matches.len().saturating_sub(self.visible_rows()));
+                self.select(self.selected.min(self.matches.len().saturating_sub(1)));
+            }
+        }
+    }
+
+    /// Rows area (x, y, width, height): below the title and the column
+    /// headers, above the footer.
+    pub(crate) fn list_rect(&self) -> (f32, f32, f32, f32) {
+        let (x, y, w, h) = self.bounds;
+        let top = y + self.padding + 2.0 * self.line_height;
+        (
+            x + self.padding,
+            top,
+            (w - 2.0 * self.padding).max(1.0),
+            (h - 2.0 * self.padding - 3.0 * self.line_height).max(self.line_height),
+        )
+    }
+
+    /// Rows that fit in the list.
+    pub(crate) fn visible_rows(&self) -> usize {
+        let (_, _, _, lh) = self.list_rect();
+        ((lh / self.line_height).floor() as usize).max(1)
+    }
+
+    /// Left edges of the pid, user, CPU, sparkline, memory and command
+    /// columns.
+    pub(crate) fn columns(&self) -> [f32; 6] {
+        let (lx, _, _, _) = self.list_rect();
+        let cw = self.char_width;
+        let user = lx + (PID_CHARS + 1) as f32 * cw;
+        let cpu = user + (USER_CHARS + 1) as f32 * cw;
+        let spark = cpu + (CPU_CHARS + 1) as f32 * cw;
+        let mem = spark + (SPARK_CHARS + 1) as f32 * cw;
+        [lx, user, cpu, spark, mem, mem + (MEM_CHARS + 2) as f32 * cw]
+    }
+
+    /// Process shown at row position POS.
+    pub(crate) fn process_at(&self, pos: usize) -> Option<&ProcessInfo> {
+        self.matches.get(pos).map(|&i| &self.snapshot.processes[i])
+    }
+
+    /// Footer text: how many processes are shown.
+    pub(crate) fn describe(&self) -> String {
+        if !self.sampled {
+            return "Sampling\u{2026}".to_string();
+        }
+        let total = self.snapshot.processes.len();
+        if self.query.is_empty() {
+            format!("{} processes", total)
+        } else {
+            format!("{} of {} processes", self.matches.len(), total)
+        }
+    }
+
+    /// Footer prompt: the confirmation asked for, the result of the
+    /// last action, or the query.
+    pub(crate) fn prompt(&self) -> Option<String> {
+        if let Some(ref p) = self.pending {
+            return Some(format!("Send {} to {} ({})? (y or n)", p.signal.0, p.pid, p.name));
+        }
+        if let Some(ref m) = self.message {
+            return Some(m.clone());
+        }
+        (self.filtering || !self.query.is_empty()).then(|| format!("Filter: {}", self.query))
+    }
+
+    /// Filter the processes by QUERY.
+    pub(super) fn set_query(&mut self, query: &str) {
+        self.query = query.to_string();
+        self.refilter();
+    }
+
+    /// Append C to the query.
+    pub(super) fn push_char(&mut self, c: char) {
+        let mut query = self.query.clone();
+        query.push(c);
+        self.set_query(&query);
+    }
+
+    /// Delete the last character of the query.  Returns false if it was
+    /// already empty.
+    pub(super) fn pop_char(&mut self) -> bool {
+        let mut query = self.query.clone();
+        if query.pop().is_none() {
+            return false;
+        }
+        self.set_query(&query);
+        true
+    }
+
+    /// Select row position POS, scrolling it into view.
+    pub(super) fn select(&mut self, pos: usize) {
+        if self.matches.is_empty() {
+            self.selected = 0;
+            return;
+        }
+        self.selected = pos.min(self.matches.len() - 1);
+        self.selected_pid = self.process_at(self.selected).map(|p| p.pid);
+        let rows = self.visible_rows();
+        if self.selected < self.scroll_row {
+            self.scroll_row = self.selected;
+        } else if self.selected >= self.scroll_row + rows {
+            self.scroll_row = self.selected + 1 - rows;
+        }
+    }
+
+    /// Move the selection by DELTA rows.
+    pub(super) fn move_selection(&mut self, delta: isize) {
+        self.select(self.selected.saturating_add_signed(delta));
+    }
+
+    /// Scroll the rows by ROWS (negative = up).  Returns true if the
+    /// first row shown changed.
+    pub(super) fn scroll(&mut self, rows: isize) -> bool {
+        let max = self.matches.len().saturating_sub(self.visible_rows());
+        let row = self.scroll_row.saturating_add_signed(rows).min(max);
+        let changed = row != self.scroll_row;
+        self.scroll_row = row;
+        changed
+    }
+
+    /// Sort by KEY, or reverse the order if already sorted by it.  CPU
+    /// and memory sort largest first.  The selected process stays
+    /// selected.
+    pub(super) fn sort_by(&mut self, key: ProcessSortKey) {
+        if key == self.sort {
+            self.descending = !self.descending;
+        } else {
+            self.sort = key;
+            self.descending = matches!(key, ProcessSortKey::Cpu | ProcessSortKey::Memory);
+        }
+        sort_processes(&mut self.snapshot.processes, self.sort, self.descending);
+        self.refilter();
+    }
+
+    /// Ask to send SIGNAL, one of `SIGNALS` by name, to the selected
+    /// process.
+    pub(super) fn request_signal(&mut self, signal: &str) {
+        let Some(&signal) = SIGNALS.iter().find(|(name, _)| *name == signal) else {
+            return;
+        };
+        if let Some(p) = self.process_at(self.selected) {
+            self.pending = Some(PendingSignal { pid: p.pid, name: p.name.clone(), signal });
+            self.message = None;
+        }
+    }
+
+    /// Send the signal asked for if CONFIRMED, and forget it.
+    pub(super) fn answer(&mut self, confirmed: bool) {
+        let Some(p) = self.pending.take() else {
+            return;
+        };
+        if confirmed {
+            self.message = Some(match send_signal(p.pid, p.signal.1) {
+                Ok(()) => format!("Sent {} to {}", p.signal.0, p.pid),
+                Err(e) => format!("{}: {}", p.pid, e),
+            });
+        }
+    }
+
+    /// Change the nice value of the selected process by DELTA.
+    pub(super) fn renice_selected(&mut self, delta: i32) {
+        let Some(p) = self.process_at(self.selected) else {
+            return;
+        };
+        let (pid, nice) = (p.pid, (p.nice + delta).clamp(-20, 19));
+        self.message = Some(match renice(pid, nice) {
+            Ok(()) => {
+                // Show the new value before the next sample
+                let index = self.matches[self.selected];
+                self.snapshot.processes[index].nice = nice;
+                format!("Niceness of {} is now {}", pid, nice)
+            }
+            Err(e) => format!("{}: {}", pid, e),
+        });
+    }
+
+    /// Row position at (X, Y), if it shows a process.
+    pub(super) fn hit_test(&self, x: f32, y: f32) -> Option<usize> {
+        let (lx, ly, lw, lh) = self.list_rect();
+        if x < lx || x >= lx + lw || y < ly || y >= ly + lh {
+            return None;
+        }
+        let pos = self.scroll_row + ((y - ly) / self.line_height) as usize;
+        (pos < self.matches.len()).then_some(pos)
+    }
+
+    /// Column whose header is at (X, Y); the sparkline belongs to CPU.
+    pub(super) fn header_at(&self, x: f32, y: f32) -> Option<ProcessSortKey> {
+        let (lx, ly, lw, _) = self.list_rect();
+        if y < ly - self.line_height || y >= ly || x < lx || x >= lx + lw {
+            return None;
+        }
+        let [_, user, cpu, _, mem, command] = self.columns();
+        Some(if x >= command {
+            ProcessSortKey::Command
+        } else if x >= mem {
+            ProcessSortKey::Memory
+        } else if x >= cpu {
+            ProcessSortKey::Cpu
+        } else if x >= user {
+            ProcessSortKey::User
+        } else {
+            ProcessSortKey::Pid
+        })
+    }
+
+    /// Whether (X, Y) is inside the panel.
+    pub(super) fn contains(&self, x: f32, y: f32) -> bool {
+        let (bx, by, bw, bh) = self.bounds;
+        x >= bx && x < bx + bw && y >= by && y < by + bh
+    }
+}
+
+impl RenderApp {
+    /// Handle a key press while the process monitor is shown.  TEXT is
+    /// the text the key produces, if any.
+    pub(super) fn process_monitor_key(&mut self, key: Key<&str>, text: Option<&str>, ctrl: bool) {
+        let Some(view) = self.process_monitor.as_mut() else {
+            return;
+        };
+        let page = view.visible_rows() as isize;
+        // A confirmation takes y or n; anything else declines
+        if view.pending.is_some() {
+            view.answer(matches!(key, Key::Character("y") | Key::Character("Y")) && !ctrl);
+            self.frame_dirty = true;
+            return;
+        }
+        view.message = None;
+        match key {
+            Key::Named(NamedKey::Escape) if view.filtering || !view.query.is_empty() => {
+                view.filtering = false;
+                view.set_query("");
+            }
+            Key::Named(NamedKey::Escape) => {
+                self.finish_process_monitor(None);
+                return;
+            }
+            Key::Character("g") if ctrl => {
+                self.finish_process_monitor(None);
+                return;
+            }
+            Key::Named(NamedKey::Enter) if view.filtering => view.filtering = false,
+            Key::Named(NamedKey::Enter) => {
+                let pid = view.process_at(view.selected).map(|p| p.pid);
+                if pid.is_some() {
+                    self.finish_process_monitor(pid);
+                    return;
+                }
+            }
+            Key::Named(NamedKey::Backspace) if view.filtering => {
+                if !view.pop_char() {
+                    view.filtering = false;
+                }
+            }
+            Key::Named(NamedKey::Tab) => view.sort_by(view.sort.next()),
+            Key::Named(NamedKey::ArrowUp) => view.move_selection(-1),
+            Key::Named(NamedKey::ArrowDown) => view.move_selection(1),
+            Key::Character("p") if ctrl => view.move_selection(-1),
+            Key::Character("n") if ctrl => view.move_selection(1),
+            Key::Named(NamedKey::PageUp) => view.move_selection(-page),
+            Key::Named(NamedKey::PageDown) => view.move_selection(page),
+            Key::Named(NamedKey::Home) => view.move_selection(isize::MIN),
+            Key::Named(NamedKey::End) => view.move_selection(isize::MAX),
+            _ if ctrl => return,
+            _ if view.filtering => match text {
+                Some(t) if !t.is_empty() && !t.chars().any(char::is_control) => {
+                    t.chars().for_each(|c| view.push_char(c));
+                }
+                _ => return,
+            },
+            _ => match text {
+                Some("/") => view.filtering = true,
+                Some("k") => view.request_signal("TERM"),
+                Some("K") => view.request_signal("KILL"),
+                Some("h") => view.request_signal("HUP"),
+                Some("s") => view.request_signal("STOP"),
+                Some("c") => view.request_signal("CONT"),
+                Some("+") => view.renice_selected(1),
+                Some("-") => view.renice_selected(-1),
+                Some("q") => {
+                    self.finish_process_monitor(None);
+                    return;
+                }
+                _ => return,
+            },
+        }
+        self.frame_dirty = true;
+    }
+
+    /// Handle a left button press (PRESSED) or release at (X, Y) while
+    /// the process monitor is shown: a press outside the panel closes
+    /// it, one on a column header sorts by it, one on a row selects it,
+    /// and one on the selected row reports its process.
+    pub(super) fn process_monitor_click(&mut self, pressed: bool, x: f32, y: f32) {
+        let Some(view) = self.process_monitor.as_mut() else {
+            return;
+        };
+        if !pressed {
+            return;
+        }
+        if !view.contains(x, y) {
+            self.finish_process_monitor(None);
+            return;
+        }
+        view.pending = None;
+        if let Some(key) = view.header_at(x, y) {
+            view.sort_by(key);
+        } else if let Some(pos) = view.hit_test(x, y) {
+            if pos == view.selected {
+                let pid = view.process_at(pos).map(|p| p.pid);
+                self.finish_process_monitor(pid);
+                return;
+            }
+            view.select(pos);
+        } else {
+            return;
+        }
+        self.frame_dirty = true;
+    }
+
+    /// Highlight the row under the mouse at (X, Y).
+    pub(super) fn process_monitor_mouse_move(&mut self, x: f32, y: f32) {
+        let Some(view) = self.process_monitor.as_mut() else {
+            return;
+        };
+        let hover = view.hit_test(x, y);
+        if hover != view.hover {
+            view.hover = hover;
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Scroll the process monitor by the mouse wheel DY, in pixels if
+    /// PIXEL_PRECISE and lines otherwise.
+    pub(super) fn process_monitor_wheel(&mut self, dy: f32, pixel_precise: bool) {
+        let Some(view) = self.process_monitor.as_mut() else {
+            return;
+        };
+        let rows = if pixel_precise {
+            (-dy / view.line_height).round() as isize
+        } else {
+            -(dy.signum() as isize) * WHEEL_LINE_ROWS
+        };
+        if view.scroll(rows) {
+            self.frame_dirty = true;
+        }
+    }
+
+    /// Close the process monitor, reporting process PID to Emacs, or
+    /// None if it was closed without choosing one.
+    pub(super) fn finish_process_monitor(&mut self, pid: Option<i32>) {
+        if self.process_monitor.take().is_none() {
+            return;
+        }
+        self.comms.send_input(InputEvent::ProcessMonitorSelection { pid: pid.unwrap_or(-1) });
+        self.frame_dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: i32, cpu: f32, command: &str) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            ppid: 1,
+            name: command.to_string(),
+            command: command.to_string(),
+            user: "root".to_string(),
+            state: 'S',
+            nice: 0,
+            threads: 1,
+            rss: pid as u64 * 1024,
+            cpu,
+            cpu_history: vec![cpu],
+        }
+    }
+
+    fn view(processes: Vec<ProcessInfo>) -> ProcessMonitorState {
+        let mut v = ProcessMonitorState::with_monitor(None, 1000.0, 800.0, 13.0, 17.0);
+        v.set_snapshot(Snapshot { processes, ..Default::default() });
+        v
+    }
+
+    #[test]
+    fn sparklines_scale_to_a_cpu() {
+        // Newest sample at the right, idle samples drawn as nothing
+        let bars = sparkline_bars(&[0.0, 50.0, 100.0], 0.0, 0.0, 60.0, 10.0);
+        assert_eq!(bars, [(56.0, 5.0, 1.0, 5.0), (58.0, 0.0, 1.0, 10.0)]);
+        // Above 100 percent the largest sample fills the height
+        let bars = sparkline_bars(&[200.0, 100.0], 0.0, 0.0, 60.0, 10.0);
+        assert_eq!(bars[1].3, 5.0);
+        assert!(sparkline_bars(&[], 0.0, 0.0, 60.0, 10.0).is_empty());
+    }
+
+    #[test]
+    fn selection_follows_its_process_across_samples() {
+        let mut v = view(vec![process(1, 1.0, "init"), process(2, 5.0, "emacs"), process(3, 9.0, "cc")]);
+        // Sorted by CPU, busiest first
+        assert_eq!(v.process_at(0).unwrap().pid, 3);
+        v.select(1);
+        assert_eq!(v.process_at(v.selected).unwrap().pid, 2);
+        // emacs is now the busiest and stays selected
+        v.set_snapshot(Snapshot {
+            processes: vec![process(1, 1.0, "init"), process(2, 50.0, "emacs"), process(3, 9.0, "cc")],
+            ..Default::default()
+        });
+        assert_eq!(v.selected, 0);
+        // When it exits the position is kept
+        v.set_snapshot(Snapshot { processes: vec![process(1, 1.0, "init"), process(3, 9.0, "cc")], ..Default::default() });
+        assert_eq!((v.selected, v.process_at(0).unwrap().pid), (0, 3));
+
+        v.sort_by(ProcessSortKey::Pid);
+        assert!(!v.descending);
+        assert_eq!(v.process_at(v.selected).unwrap().pid, 3);
+        let (lx, ly, _, _) = v.list_rect();
+        let [_, user, cpu, spark, mem, command] = v.columns();
+        assert_eq!(v.header_at(lx + 1.0, ly - 2.0), Some(ProcessSortKey::Pid));
+        assert_eq!(v.header_at(user + 1.0, ly - 2.0), Some(ProcessSortKey::User));
+        assert_eq!(v.header_at(cpu + 1.0, ly - 2.0), Some(ProcessSortKey::Cpu));
+        assert_eq!(v.header_at(spark + 1.0, ly - 2.0), Some(ProcessSortKey::Cpu));
+        assert_eq!(v.header_at(mem + 1.0, ly - 2.0), Some(ProcessSortKey::Memory));
+        assert_eq!(v.header_at(command + 1.0, ly - 2.0), Some(ProcessSortKey::Command));
+        assert_eq!(v.hit_test(lx + 1.0, ly + v.line_height * 0.5), Some(0));
+        assert_eq!(v.hit_test(lx + 1.0, ly + v.line_height * 2.5), None);
+    }
+
+    #[test]
+    fn filtering_and_signals() {
+        let mut v = view(vec![process(1, 1.0, "init"), process(2, 5.0, "emacs"), process(3, 9.0, "cc")]);
+        assert_eq!(v.describe(), "3 processes");
+        v.push_char('e');
+        v.push_char('m');
+        assert_eq!(v.describe(), "1 of 3 processes");
+        assert_eq!(v.prompt().as_deref(), Some("Filter: em"));
+        assert!(v.pop_char());
+        assert_eq!(v.matches.len(), 1);
+
+        // Signals wait for confirmation; declining sends nothing
+        v.request_signal("KILL");
+        assert_eq!(v.prompt().as_deref(), Some("Send KILL to 2 (emacs)? (y or n)"));
+        v.answer(false);
+        assert!(v.pending.is_none());
+        assert_eq!(v.prompt().as_deref(), Some("Filter: e"));
+        v.request_signal("BOGUS");
+        assert!(v.pending.is_none());
+    }
+}
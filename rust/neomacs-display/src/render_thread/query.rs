@@ -0,0 +1,116 @@
+//! Render-side answers to synchronous queries from Emacs
+//! (see `crate::query`).
+
+use super::RenderApp;
+use crate::core::face::{Face, FaceAttributes};
+use crate::core::scene_dump::{AnimationState, CursorAnimationState, SceneDump};
+use crate::core::types::Rect;
+use crate::query::{QueryReply, QueryResult, RenderQuery};
+
+impl RenderApp {
+    /// Answer QUERY and send the reply tagged ID.  Never blocks: if Emacs
+    /// stopped waiting and the reply channel is full, the reply is dropped.
+    pub(super) fn answer_query(&mut self, id: u64, query: RenderQuery) {
+        let result = match query {
+            RenderQuery::TextExtent { text, family, weight, italic, font_size } => {
+                match self.glyph_atlas.as_mut() {
+                    Some(atlas) => {
+                        let mut face = Face::new(0);
+                        face.font_family = family;
+                        face.font_weight = weight;
+                        face.font_size = font_size;
+                        if italic {
+                            face.attributes |= FaceAttributes::ITALIC;
+                        }
+                        let (width, height) = atlas.text_extent(&text, Some(&face));
+                        QueryResult::TextExtent { width, height }
+                    }
+                    None => QueryResult::Unavailable,
+                }
+            }
+            RenderQuery::ImageSize { id } => match self.renderer.as_ref() {
+                Some(renderer) => QueryResult::ImageSize(renderer.get_image_size(id)),
+                None => QueryResult::Unavailable,
+            },
+            RenderQuery::Monitors => match self.current_monitors() {
+                Some(monitors) => QueryResult::Monitors(monitors),
+                None => QueryResult::Unavailable,
+            },
+            RenderQuery::SceneDump => QueryResult::SceneDump(self.scene_dump()),
+            RenderQuery::StartupReport => match self.startup_report.clone() {
+                Some(report) => QueryResult::StartupReport(report),
+                None => QueryResult::Unavailable,
+            },
+        };
+        if self.comms.reply_tx.try_send(QueryReply { id, result }).is_err() {
+            log::debug!("dropped reply to render query {}", id);
+        }
+    }
+
+    /// JSON description of the frames and animations being drawn
+    fn scene_dump(&self) -> String {
+        let now = self.clock.now();
+        let fraction = |started: std::time::Instant, duration: std::time::Duration| {
+            if duration.is_zero() {
+                1.0
+            } else {
+                (now.saturating_duration_since(started).as_secs_f32() / duration.as_secs_f32()).min(1.0)
+            }
+        };
+
+        let mut dump = SceneDump::default();
+        dump.frames.extend(self.current_frame.as_ref());
+        dump.frames.extend(
+            self.child_frames
+                .sorted_for_rendering()
+                .iter()
+                .filter_map(|id| self.child_frames.frames.get(id))
+                .map(|entry| &entry.frame),
+        );
+
+        let cursor = &self.cursor;
+        dump.cursor = Some(CursorAnimationState {
+            animating: cursor.animating,
+            blink_on: cursor.blink_on,
+            current: Rect::new(cursor.current_x, cursor.current_y, cursor.current_w, cursor.current_h),
+            target: cursor.target.as_ref().map(|t| Rect::new(t.x, t.y, t.width, t.height)),
+        });
+
+        let t = &self.transitions;
+        let animations = &mut dump.animations;
+        animations.extend(t.crossfades.iter().map(|(&window_id, x)| AnimationState {
+            kind: "crossfade",
+            window_id,
+            progress: fraction(x.started, x.duration),
+        }));
+        animations.extend(t.scroll_slides.iter().map(|(&window_id, x)| AnimationState {
+            kind: "scroll",
+            window_id,
+            progress: fraction(x.started, x.duration),
+        }));
+        animations.extend(t.text_zooms.iter().map(|(&window_id, x)| AnimationState {
+            kind: "text-zoom",
+            window_id,
+            progress: fraction(x.started, x.duration),
+        }));
+        animations.extend(t.line_diffs.iter().map(|(&window_id, x)| AnimationState {
+            kind: "line-diff",
+            window_id,
+            progress: fraction(x.started, x.duration),
+        }));
+        animations.extend(t.buffer_transitions.iter().map(|(&window_id, x)| AnimationState {
+            kind: "buffer",
+            window_id,
+            progress: x.transition.eased_progress(),
+        }));
+        animations.extend(t.timeline.animations().map(|a| AnimationState {
+            kind: "keyframe",
+            window_id: a.window_id,
+            progress: a.progress_at(now),
+        }));
+        // Maps iterate in no fixed order; keep dumps comparable
+        animations.sort_by(|a, b| (a.kind, a.window_id).cmp(&(b.kind, b.window_id)));
+
+        dump.to_json()
+    }
+}
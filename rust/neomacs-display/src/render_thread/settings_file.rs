@@ -0,0 +1,541 @@
+//! Display settings file: renderer options read from a TOML file and
+//! applied again whenever the file changes, so visuals can be tuned
+//! live without restarting or writing Lisp.
+//!
+//! ```toml
+//! [fonts]
+//! line-spacing = 2.0
+//!
+//! [cursor]
+//! blink = false
+//! animation-style = "spring"
+//! animation-duration = 120
+//!
+//! [scroll]
+//! effect = "page-curl"
+//! easing = "ease-out-cubic"
+//!
+//! [effects]
+//! cursor-glow = true
+//!
+//! [colors]
+//! cursor-glow = "#66ccff"
+//! ```
+//!
+//! Durations are in milliseconds, colors `#rgb` or `#rrggbb`.  Options
+//! the file leaves out keep their current value, so the file and Lisp
+//! settings can be mixed; whichever changed last wins.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::core::scroll_animation::{ScrollEasing, ScrollEffect};
+use crate::core::types::CursorAnimStyle;
+use crate::effect_config::EffectsConfig;
+
+use super::RenderApp;
+
+/// How often the file's modification time is checked
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Effects the file can switch on and off by name
+const EFFECTS: &[&str] = &[
+    "cursor-glow",
+    "cursor-pulse",
+    "cursor-particles",
+    "cursor-trail-fade",
+    "focus-ring",
+    "idle-dim",
+    "inactive-dim",
+    "line-highlight",
+    "noise-grain",
+    "scroll-progress",
+    "typing-ripple",
+    "vignette",
+];
+
+/// Enable flag of effect NAME
+fn effect_flag<'a>(effects: &'a mut EffectsConfig, name: &str) -> Option<&'a mut bool> {
+    Some(match name {
+        "cursor-glow" => &mut effects.cursor_glow.enabled,
+        "cursor-pulse" => &mut effects.cursor_pulse.enabled,
+        "cursor-particles" => &mut effects.cursor_particles.enabled,
+        "cursor-trail-fade" => &mut effects.cursor_trail_fade.enabled,
+        "focus-ring" => &mut effects.focus_ring.enabled,
+        "idle-dim" => &mut effects.idle_dim.enabled,
+        "inactive-dim" => &mut effects.inactive_dim.enabled,
+        "line-highlight" => &mut effects.line_highlight.enabled,
+        "noise-grain" => &mut effects.noise_grain.enabled,
+        "scroll-progress" => &mut effects.scroll_progress.enabled,
+        "typing-ripple" => &mut effects.typing_ripple.enabled,
+        "vignette" => &mut effects.vignette.enabled,
+        _ => return None,
+    })
+}
+
+/// Set the color of effect NAME.  Returns false if it has no color.
+fn set_effect_color(effects: &mut EffectsConfig, name: &str, (r, g, b): (f32, f32, f32)) -> bool {
+    match name {
+        "cursor-glow" => effects.cursor_glow.color = (r, g, b),
+        "cursor-particles" => effects.cursor_particles.color = (r, g, b),
+        "focus-ring" => effects.focus_ring.color = (r, g, b),
+        "scroll-progress" => effects.scroll_progress.color = (r, g, b),
+        "line-highlight" => {
+            let alpha = effects.line_highlight.color.3;
+            effects.line_highlight.color = (r, g, b, alpha);
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Parse `#rgb` or `#rrggbb` into sRGB components
+fn parse_color(s: &str) -> Option<(f32, f32, f32)> {
+    let hex = s.strip_prefix('#')?;
+    let digits: Vec<u32> = hex.chars().map(|c| c.to_digit(16)).collect::<Option<_>>()?;
+    let (r, g, b) = match digits.as_slice() {
+        [r, g, b] => (r * 17, g * 17, b * 17),
+        [r1, r0, g1, g0, b1, b0] => (r1 * 16 + r0, g1 * 16 + g0, b1 * 16 + b0),
+        _ => return None,
+    };
+    Some((r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0))
+}
+
+fn cursor_style_from_name(name: &str) -> Option<CursorAnimStyle> {
+    Some(match name {
+        "exponential" => CursorAnimStyle::Exponential,
+        "spring" | "critically-damped-spring" => CursorAnimStyle::CriticallyDampedSpring,
+        "ease-out-quad" => CursorAnimStyle::EaseOutQuad,
+        "ease-out-cubic" => CursorAnimStyle::EaseOutCubic,
+        "ease-out-expo" => CursorAnimStyle::EaseOutExpo,
+        "ease-in-out-cubic" => CursorAnimStyle::EaseInOutCubic,
+        "linear" => CursorAnimStyle::Linear,
+        _ => return None,
+    })
+}
+
+/// Settings of a scroll or crossfade transition
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct TransitionSettings {
+    pub enabled: Option<bool>,
+    pub duration: Option<Duration>,
+    pub effect: Option<ScrollEffect>,
+    pub easing: Option<ScrollEasing>,
+}
+
+/// Contents of a display settings file.  None leaves an option as is.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct DisplaySettings {
+    pub line_spacing: Option<f32>,
+    pub letter_spacing: Option<f32>,
+    pub cursor_blink: Option<bool>,
+    pub cursor_blink_interval: Option<Duration>,
+    pub cursor_animation: Option<bool>,
+    pub cursor_style: Option<CursorAnimStyle>,
+    pub cursor_duration: Option<Duration>,
+    pub cursor_speed: Option<f32>,
+    pub trail_size: Option<f32>,
+    pub scroll: TransitionSettings,
+    pub crossfade: TransitionSettings,
+    /// Effects to switch on or off
+    pub effects: Vec<(&'static str, bool)>,
+    /// Effect colors
+    pub colors: Vec<(&'static str, (f32, f32, f32))>,
+}
+
+/// Reader of the values of one table, collecting problems as warnings
+struct Section<'a> {
+    name: &'a str,
+    table: &'a toml::Table,
+    warnings: &'a mut Vec<String>,
+}
+
+impl Section<'_> {
+    fn value(&mut self, key: &str) -> Option<&toml::Value> {
+        self.table.get(key)
+    }
+
+    fn bad(&mut self, key: &str, expected: &str) {
+        self.warnings.push(format!("[{}] {}: expected {}", self.name, key, expected));
+    }
+
+    fn bool(&mut self, key: &str) -> Option<bool> {
+        match self.value(key)? {
+            toml::Value::Boolean(b) => Some(*b),
+            _ => {
+                self.bad(key, "true or false");
+                None
+            }
+        }
+    }
+
+    fn number(&mut self, key: &str) -> Option<f32> {
+        match self.value(key)? {
+            toml::Value::Integer(n) => Some(*n as f32),
+            toml::Value::Float(f) => Some(*f as f32),
+            _ => {
+                self.bad(key, "a number");
+                None
+            }
+        }
+    }
+
+    fn millis(&mut self, key: &str) -> Option<Duration> {
+        match self.value(key)? {
+            toml::Value::Integer(n) if *n >= 0 => Some(Duration::from_millis(*n as u64)),
+            _ => {
+                self.bad(key, "milliseconds");
+                None
+            }
+        }
+    }
+
+    fn string(&mut self, key: &str) -> Option<String> {
+        match self.value(key)? {
+            toml::Value::String(s) => Some(s.to_lowercase().replace('_', "-")),
+            _ => {
+                self.bad(key, "a string");
+                None
+            }
+        }
+    }
+
+    /// Warn about keys not in KNOWN
+    fn check_keys(&mut self, known: &[&str]) {
+        for key in self.table.keys() {
+            if !known.contains(&key.as_str()) {
+                self.warnings.push(format!("[{}] {}: unknown option", self.name, key));
+            }
+        }
+    }
+}
+
+fn parse_transition(section: &mut Section) -> TransitionSettings {
+    section.check_keys(&["enabled", "duration", "effect", "easing"]);
+    TransitionSettings {
+        enabled: section.bool("enabled"),
+        duration: section.millis("duration"),
+        effect: section.string("effect").map(|s| ScrollEffect::from_str(&s)),
+        easing: section.string("easing").map(|s| ScrollEasing::from_str(&s)),
+    }
+}
+
+impl DisplaySettings {
+    /// Parse the TOML text of a settings file.  Unknown options and
+    /// values of the wrong type are skipped and reported in the returned
+    /// warnings; a file that is not valid TOML is an error.
+    pub(super) fn parse(text: &str) -> Result<(Self, Vec<String>), String> {
+        let root: toml::Table = text.parse().map_err(|e: toml::de::Error| e.message().to_string())?;
+        let mut settings = Self::default();
+        let mut warnings = Vec::new();
+        let empty = toml::Table::new();
+        for (name, value) in &root {
+            let table = match value {
+                toml::Value::Table(table) => table,
+                _ => {
+                    warnings.push(format!("{}: expected a [section]", name));
+                    &empty
+                }
+            };
+            let mut section = Section { name, table, warnings: &mut warnings };
+            match name.as_str() {
+                "fonts" => {
+                    section.check_keys(&["line-spacing", "letter-spacing"]);
+                    settings.line_spacing = section.number("line-spacing");
+                    settings.letter_spacing = section.number("letter-spacing");
+                }
+                "cursor" => {
+                    section.check_keys(&[
+                        "blink", "blink-interval", "animation", "animation-style",
+                        "animation-duration", "animation-speed", "trail-size",
+                    ]);
+                    settings.cursor_blink = section.bool("blink");
+                    settings.cursor_blink_interval = section.millis("blink-interval");
+                    settings.cursor_animation = section.bool("animation");
+                    if let Some(style) = section.string("animation-style") {
+                        settings.cursor_style = cursor_style_from_name(&style);
+                        if settings.cursor_style.is_none() {
+                            section.bad("animation-style", "a cursor animation style");
+                        }
+                    }
+                    settings.cursor_duration = section.millis("animation-duration");
+                    settings.cursor_speed = section.number("animation-speed");
+                    settings.trail_size = section.number("trail-size");
+                }
+                "scroll" => settings.scroll = parse_transition(&mut section),
+                "crossfade" => settings.crossfade = parse_transition(&mut section),
+                "effects" => {
+                    for &effect in EFFECTS {
+                        if let Some(enabled) = section.bool(effect) {
+                            settings.effects.push((effect, enabled));
+                        }
+                    }
+                    section.check_keys(EFFECTS);
+                }
+                "colors" => {
+                    for &effect in EFFECTS {
+                        let Some(value) = section.string(effect) else { continue };
+                        match parse_color(&value) {
+                            Some(color) if set_effect_color(&mut EffectsConfig::default(), effect, color) => {
+                                settings.colors.push((effect, color));
+                            }
+                            Some(_) => section.bad(effect, "an effect with a color"),
+                            None => section.bad(effect, "a #rrggbb color"),
+                        }
+                    }
+                    section.check_keys(EFFECTS);
+                }
+                _ => warnings.push(format!("[{}]: unknown section", name)),
+            }
+        }
+        Ok((settings, warnings))
+    }
+}
+
+/// A settings file and the modification time it was last read at
+#[derive(Debug)]
+pub(super) struct SettingsFile {
+    path: PathBuf,
+    /// None until read, and while the file does not exist
+    modified: Option<SystemTime>,
+    last_check: Option<Instant>,
+}
+
+impl SettingsFile {
+    pub(super) fn new(path: PathBuf) -> Self {
+        Self { path, modified: None, last_check: None }
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Settings from the file if it was created or changed since the
+    /// last call.  Checks at most every `RELOAD_CHECK_INTERVAL`.
+    pub(super) fn poll(&mut self, now: Instant) -> Option<DisplaySettings> {
+        if self.last_check.is_some_and(|t| now.duration_since(t) < RELOAD_CHECK_INTERVAL) {
+            return None;
+        }
+        self.last_check = Some(now);
+        let modified = std::fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified.is_none() || modified == self.modified {
+            self.modified = modified;
+            return None;
+        }
+        self.modified = modified;
+        let _span = crate::logging::span("settings-file");
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) => {
+                log::warn!("Display settings {}: {}", self.path.display(), e);
+                return None;
+            }
+        };
+        match DisplaySettings::parse(&text) {
+            Ok((settings, warnings)) => {
+                for warning in warnings {
+                    log::warn!("Display settings {}: {}", self.path.display(), warning);
+                }
+                log::info!("Loaded display settings from {}", self.path.display());
+                Some(settings)
+            }
+            Err(e) => {
+                log::warn!("Display settings {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+impl RenderApp {
+    /// Apply the settings file if it changed
+    pub(super) fn poll_settings_file(&mut self) {
+        let now = Instant::now();
+        let Some(settings) = self.settings_file.as_mut().and_then(|f| f.poll(now)) else {
+            return;
+        };
+        self.apply_display_settings(&settings);
+    }
+
+    /// Apply the options SETTINGS sets
+    pub(super) fn apply_display_settings(&mut self, settings: &DisplaySettings) {
+        if let Some(spacing) = settings.line_spacing {
+            self.extra_line_spacing = spacing;
+        }
+        if let Some(spacing) = settings.letter_spacing {
+            self.extra_letter_spacing = spacing;
+        }
+
+        if let Some(blink) = settings.cursor_blink {
+            self.cursor.blink_enabled = blink;
+            if !blink {
+                self.cursor.blink_on = true;
+            }
+        }
+        if let Some(interval) = settings.cursor_blink_interval {
+            self.cursor.blink_interval = interval;
+        }
+        if let Some(enabled) = settings.cursor_animation {
+            self.cursor.anim_enabled = enabled;
+            if !enabled {
+                self.cursor.animating = false;
+            }
+        }
+        if let Some(style) = settings.cursor_style {
+            self.cursor.anim_style = style;
+        }
+        if let Some(duration) = settings.cursor_duration {
+            self.cursor.anim_duration = duration.as_secs_f32();
+        }
+        if let Some(speed) = settings.cursor_speed {
+            self.cursor.anim_speed = speed;
+        }
+        if let Some(size) = settings.trail_size {
+            self.cursor.trail_size = size.clamp(0.0, 1.0);
+        }
+
+        let scroll = &settings.scroll;
+        let transitions = &mut self.transitions;
+        if let Some(enabled) = scroll.enabled {
+            transitions.scroll_enabled = enabled;
+        }
+        if let Some(duration) = scroll.duration {
+            transitions.scroll_duration = duration;
+        }
+        if let Some(effect) = scroll.effect {
+            transitions.scroll_effect = effect;
+        }
+        if let Some(easing) = scroll.easing {
+            transitions.scroll_easing = easing;
+        }
+        let crossfade = &settings.crossfade;
+        if let Some(enabled) = crossfade.enabled {
+            transitions.crossfade_enabled = enabled;
+        }
+        if let Some(duration) = crossfade.duration {
+            transitions.crossfade_duration = duration;
+        }
+        if let Some(effect) = crossfade.effect {
+            transitions.crossfade_effect = effect;
+        }
+        if let Some(easing) = crossfade.easing {
+            transitions.crossfade_easing = easing;
+        }
+
+        if !settings.effects.is_empty() || !settings.colors.is_empty() {
+            for &(name, enabled) in &settings.effects {
+                if let Some(flag) = effect_flag(&mut self.effects, name) {
+                    *flag = enabled;
+                }
+            }
+            for &(name, color) in &settings.colors {
+                set_effect_color(&mut self.effects, name, color);
+            }
+            if let Some(renderer) = self.renderer.as_mut() {
+                renderer.effects = self.effects.clone();
+            }
+        }
+        self.frame_dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_effect_has_a_flag() {
+        let mut effects = EffectsConfig::default();
+        for name in EFFECTS {
+            assert!(effect_flag(&mut effects, name).is_some(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn parses_sections() {
+        let (settings, warnings) = DisplaySettings::parse(
+            r##"
+            [fonts]
+            line-spacing = 2
+
+            [cursor]
+            blink = false
+            animation-style = "spring"
+            animation-duration = 120
+
+            [scroll]
+            effect = "page-curl"
+            easing = "ease-out-cubic"
+
+            [effects]
+            cursor-glow = true
+
+            [colors]
+            cursor-glow = "#6cf"
+            "##,
+        )
+        .unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(settings.line_spacing, Some(2.0));
+        assert_eq!(settings.cursor_blink, Some(false));
+        assert_eq!(settings.cursor_style, Some(CursorAnimStyle::CriticallyDampedSpring));
+        assert_eq!(settings.cursor_duration, Some(Duration::from_millis(120)));
+        assert_eq!(settings.scroll.effect, Some(ScrollEffect::PageCurl));
+        assert_eq!(settings.scroll.easing, Some(ScrollEasing::EaseOutCubic));
+        assert_eq!(settings.crossfade, TransitionSettings::default());
+        assert_eq!(settings.effects, vec![("cursor-glow", true)]);
+        assert_eq!(settings.colors, vec![("cursor-glow", (0.4, 0.8, 1.0))]);
+    }
+
+    #[test]
+    fn bad_values_are_skipped_with_warnings() {
+        let (settings, warnings) = DisplaySettings::parse(
+            r##"
+            [cursor]
+            blink = "yes"
+            animation-style = "bouncy"
+            trail-size = 0.5
+
+            [colors]
+            vignette = "#000"
+            focus-ring = "blue"
+
+            [sparkles]
+            "##,
+        )
+        .unwrap();
+        assert_eq!(settings.cursor_blink, None);
+        assert_eq!(settings.cursor_style, None);
+        assert_eq!(settings.trail_size, Some(0.5));
+        assert!(settings.colors.is_empty());
+        assert_eq!(warnings.len(), 5, "{:?}", warnings);
+        assert!(DisplaySettings::parse("[cursor").is_err());
+    }
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(parse_color("#ff0000"), Some((1.0, 0.0, 0.0)));
+        assert_eq!(parse_color("#fff"), Some((1.0, 1.0, 1.0)));
+        assert_eq!(parse_color("fff"), None);
+        assert_eq!(parse_color("#ffff"), None);
+    }
+
+    #[test]
+    fn file_is_read_when_created_and_changed() {
+        let dir = std::env::temp_dir().join(format!("neomacs-settings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("display.toml");
+        let _ = std::fs::remove_file(&path);
+        let mut file = SettingsFile::new(path.clone());
+        let start = Instant::now();
+        assert!(file.poll(start).is_none());
+
+        std::fs::write(&path, "[fonts]\nline-spacing = 1\n").unwrap();
+        // Checks are rate limited
+        assert!(file.poll(start + Duration::from_millis(100)).is_none());
+        let settings = file.poll(start + RELOAD_CHECK_INTERVAL).unwrap();
+        assert_eq!(settings.line_spacing, Some(1.0));
+        assert!(file.poll(start + RELOAD_CHECK_INTERVAL * 2).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,296 @@
+//! Parallel initialization of the display subsystems, and a timeline of
+//! it.
+//!
+//! Before the first frame can be drawn the render thread needs a GPU
+//! device, the font database, the glyph cache of past sessions and a
+//! window.  Each takes from a few to a few hundred milliseconds, mostly
+//! waiting on the system, and few depend on each other, so they run as
+//! tasks on worker threads while the render thread connects to the
+//! display server and opens the window.
+//!
+//! A task that needs another's result gets it with `Task::wait`; the
+//! wait is recorded, so the timeline shows the dependency graph as well
+//! as where each task ran and for how long.  `StartupTimeline::report`
+//! formats it, ending at the first frame presented, so changes to
+//! cold-start time can be tracked (see `neomacs-show-startup-report').
+
+use std::cell::Cell;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+thread_local! {
+    /// Task running on this thread, to attribute waits to it
+    static CURRENT_TASK: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+/// Width of the bars of the report
+const BAR_COLUMNS: usize = 32;
+
+/// One task, or a mark when `start == end`
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartupSpan {
+    pub name: &'static str,
+    pub thread: String,
+    /// Offsets from the start of initialization
+    pub start: Duration,
+    pub end: Duration,
+    /// Tasks whose results this one waited for
+    pub after: Vec<&'static str>,
+}
+
+/// What happened during startup, shared by the tasks
+#[derive(Debug)]
+pub struct StartupTimeline {
+    origin: Instant,
+    spans: Mutex<Vec<StartupSpan>>,
+    /// (task, task it waited for)
+    waits: Mutex<Vec<(&'static str, &'static str)>>,
+}
+
+impl StartupTimeline {
+    fn record(&self, span: StartupSpan) {
+        if let Ok(mut spans) = self.spans.lock() {
+            spans.push(span);
+        }
+    }
+
+    fn add_dependency(&self, name: &'static str, on: &'static str) {
+        if let Ok(mut waits) = self.waits.lock() {
+            waits.push((name, on));
+        }
+    }
+
+    /// Record that NAME happened now, such as the first frame
+    pub fn mark(&self, name: &'static str) {
+        let at = self.origin.elapsed();
+        self.record(StartupSpan {
+            name,
+            thread: current_thread_name(),
+            start: at,
+            end: at,
+            after: Vec::new(),
+        });
+    }
+
+    /// Tasks and marks in the order they started, with the tasks each
+    /// waited for
+    pub fn spans(&self) -> Vec<StartupSpan> {
+        let mut spans = self.spans.lock().map(|s| s.clone()).unwrap_or_default();
+        let waits = self.waits.lock().map(|w| w.clone()).unwrap_or_default();
+        for (name, on) in waits {
+            if let Some(span) = spans.iter_mut().find(|s| s.name == name) {
+                span.after.push(on);
+            }
+        }
+        spans.sort_by_key(|s| s.start);
+        spans
+    }
+
+    /// Time from the start of initialization to the last task or mark
+    pub fn total(&self) -> Duration {
+        self.spans().iter().map(|s| s.end).max().unwrap_or_default()
+    }
+
+    /// The timeline as a table, one line per task with a bar showing
+    /// when it ran
+    pub fn report(&self) -> String {
+        let spans = self.spans();
+        let total = self.total();
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let column = |d: Duration| {
+            if total.is_zero() {
+                0
+            } else {
+                ((d.as_secs_f64() / total.as_secs_f64()) * BAR_COLUMNS as f64).round() as usize
+            }
+        };
+        let name_width = spans.iter().map(|s| s.name.len()).max().unwrap_or(0).max(4);
+        let thread_width = spans.iter().map(|s| s.thread.len()).max().unwrap_or(0).max(6);
+
+        let mut out = String::new();
+        let _ = writeln!(out, "Display startup: {:.1} ms", ms(total));
+        for span in &spans {
+            let (from, to) = (column(span.start), column(span.end).min(BAR_COLUMNS));
+            let bar: String = (0..BAR_COLUMNS)
+                .map(|c| {
+                    if span.start == span.end {
+                        if c == from.min(BAR_COLUMNS - 1) { '|' } else { ' ' }
+                    } else if c >= from && c < to.max(from + 1) {
+                        '#'
+                    } else {
+                        ' '
+                    }
+                })
+                .collect();
+            let _ = write!(
+                out,
+                "  {:<name_width$}  {:<thread_width$}  {:>7.1} {:>7.1} ms  [{}]",
+                span.name, span.thread, ms(span.start), ms(span.end), bar,
+            );
+            if !span.after.is_empty() {
+                let _ = write!(out, "  after {}", span.after.join(", "));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn current_thread_name() -> String {
+    thread::current().name().unwrap_or("unnamed").to_string()
+}
+
+/// Runs startup tasks and records them on a timeline
+#[derive(Debug, Clone)]
+pub struct Startup {
+    timeline: Arc<StartupTimeline>,
+}
+
+impl Default for Startup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Startup {
+    /// Start timing initialization now
+    pub fn new() -> Self {
+        Self {
+            timeline: Arc::new(StartupTimeline {
+                origin: Instant::now(),
+                spans: Mutex::new(Vec::new()),
+                waits: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    pub fn timeline(&self) -> Arc<StartupTimeline> {
+        self.timeline.clone()
+    }
+
+    /// Run F now on this thread as the task NAME
+    pub fn run<T>(&self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        run_task(&self.timeline, name, f)
+    }
+
+    /// Run F as the task NAME on a worker thread.  Its result is taken
+    /// with `Task::wait`.
+    pub fn spawn<T: Send + 'static>(
+        &self,
+        name: &'static str,
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> Task<T> {
+        let timeline = self.timeline.clone();
+        let handle = thread::Builder::new()
+            .name(format!("startup-{}", name))
+            .spawn(move || run_task(&timeline, name, f))
+            .expect("Failed to spawn startup thread");
+        Task { name, timeline: self.timeline.clone(), handle }
+    }
+}
+
+fn run_task<T>(timeline: &StartupTimeline, name: &'static str, f: impl FnOnce() -> T) -> T {
+    let outer = CURRENT_TASK.with(|c| c.replace(Some(name)));
+    let start = timeline.origin.elapsed();
+    let value = f();
+    timeline.record(StartupSpan {
+        name,
+        thread: current_thread_name(),
+        start,
+        end: timeline.origin.elapsed(),
+        after: Vec::new(),
+    });
+    CURRENT_TASK.with(|c| c.set(outer));
+    value
+}
+
+/// A task started with `Startup::spawn`
+pub struct Task<T> {
+    name: &'static str,
+    timeline: Arc<StartupTimeline>,
+    handle: JoinHandle<T>,
+}
+
+impl<T> Task<T> {
+    /// Block until the task is done and take its result.  None if it
+    /// panicked.  Called from another task, records that one as
+    /// depending on this one.
+    pub fn wait(self) -> Option<T> {
+        if let Some(waiter) = CURRENT_TASK.with(|c| c.get()) {
+            self.timeline.add_dependency(waiter, self.name);
+        }
+        match self.handle.join() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                log::error!("startup: task {} panicked", self.name);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tasks_run_in_parallel_and_record_dependencies() {
+        let startup = Startup::new();
+        let fonts = startup.spawn("fonts", || {
+            thread::sleep(Duration::from_millis(30));
+            7
+        });
+        let gpu = startup.spawn("gpu", || {
+            thread::sleep(Duration::from_millis(30));
+            "device"
+        });
+        let cache = startup.spawn("glyph-cache", move || fonts.wait().unwrap() * 2);
+        let window = startup.run("window", || 1);
+        assert_eq!((cache.wait(), gpu.wait(), window), (Some(14), Some("device"), 1));
+        startup.timeline().mark("first-frame");
+
+        let timeline = startup.timeline();
+        let spans = timeline.spans();
+        let span = |name: &str| spans.iter().find(|s| s.name == name).unwrap().clone();
+        assert_eq!(span("glyph-cache").after, vec!["fonts"]);
+        assert_eq!(span("fonts").thread, "startup-fonts");
+        assert!(span("gpu").after.is_empty());
+        // fonts and gpu overlapped instead of taking 60ms one after the other
+        assert!(span("fonts").start < span("gpu").end && span("gpu").start < span("fonts").end);
+        assert!(span("glyph-cache").end >= span("fonts").end);
+        assert_eq!(timeline.total(), span("first-frame").end);
+        // The waits from the render thread itself are not tasks
+        assert_eq!(spans.len(), 5);
+    }
+
+    #[test]
+    fn report_lists_tasks_with_bars() {
+        let timeline = Startup::new().timeline();
+        let span = |name, start, end, after: Vec<&'static str>| StartupSpan {
+            name,
+            thread: "render".to_string(),
+            start: Duration::from_millis(start),
+            end: Duration::from_millis(end),
+            after,
+        };
+        timeline.record(span("fonts", 0, 50, vec![]));
+        timeline.record(span("atlas", 50, 100, vec![]));
+        timeline.add_dependency("atlas", "fonts");
+        timeline.record(span("first-frame", 100, 100, vec![]));
+        let report = timeline.report();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], "Display startup: 100.0 ms");
+        assert!(lines[1].starts_with("  fonts        render      0.0    50.0 ms  [################                ]"));
+        assert!(lines[2].ends_with("[                ################]  after fonts"));
+        assert!(lines[3].contains("[                               |]"));
+    }
+
+    #[test]
+    fn panicking_task_gives_none() {
+        let startup = Startup::new();
+        let task = startup.spawn("broken", || -> u32 { panic!("no fonts") });
+        assert_eq!(task.wait(), None);
+    }
+}
@@ -0,0 +1,148 @@
+//! Background throttling: while no Neomacs window has focus, or the
+//! frame is occluded, cursor blink and continuous effects pause and
+//! frames render at a trickle rate.  Focus resumes full speed at once.
+
+use std::time::{Duration, Instant};
+
+/// How rendering is throttled in the background.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SuspendPolicy {
+    pub enabled: bool,
+    /// Render rate while unfocused but visible (frames per second)
+    pub unfocused_fps: u32,
+    /// Render rate while occluded or minimized (frames per second)
+    pub hidden_fps: u32,
+    /// Pause playing videos in the background
+    pub pause_video: bool,
+}
+
+impl Default for SuspendPolicy {
+    fn default() -> Self {
+        Self { enabled: true, unfocused_fps: 10, hidden_fps: 1, pause_video: false }
+    }
+}
+
+/// Focus and visibility of the frame, and what was paused for them.
+#[derive(Debug)]
+pub(super) struct SuspendState {
+    pub(super) policy: SuspendPolicy,
+    focused: bool,
+    occluded: bool,
+    last_render: Option<Instant>,
+    /// Videos paused on suspension, resumed on focus
+    pub(super) paused_videos: Vec<u32>,
+}
+
+impl Default for SuspendState {
+    fn default() -> Self {
+        Self {
+            policy: SuspendPolicy::default(),
+            focused: true,
+            occluded: false,
+            last_render: None,
+            paused_videos: Vec::new(),
+        }
+    }
+}
+
+impl SuspendState {
+    /// Record a focus change.  Returns true if this suspends or resumes.
+    pub(super) fn set_focused(&mut self, focused: bool) -> bool {
+        let was = self.is_suspended();
+        self.focused = focused;
+        was != self.is_suspended()
+    }
+
+    /// Record an occlusion change.  Returns true if this suspends or
+    /// resumes.
+    pub(super) fn set_occluded(&mut self, occluded: bool) -> bool {
+        let was = self.is_suspended();
+        self.occluded = occluded;
+        was != self.is_suspended()
+    }
+
+    /// Whether rendering is throttled
+    pub(super) fn is_suspended(&self) -> bool {
+        self.policy.enabled && (!self.focused || self.occluded)
+    }
+
+    /// Whether videos should be paused
+    pub(super) fn pauses_video(&self) -> bool {
+        self.is_suspended() && self.policy.pause_video
+    }
+
+    /// Time between frames while suspended, None when not suspended
+    pub(super) fn frame_interval(&self) -> Option<Duration> {
+        if !self.is_suspended() {
+            return None;
+        }
+        let fps = if self.occluded { self.policy.hidden_fps } else { self.policy.unfocused_fps };
+        Some(Duration::from_secs(1) / fps.max(1))
+    }
+
+    /// When the next frame may be rendered
+    pub(super) fn next_render(&self, now: Instant) -> Instant {
+        match (self.frame_interval(), self.last_render) {
+            (Some(interval), Some(last)) => (last + interval).max(now),
+            _ => now,
+        }
+    }
+
+    /// Whether a frame may be rendered at NOW
+    pub(super) fn render_due(&self, now: Instant) -> bool {
+        self.next_render(now) <= now
+    }
+
+    /// Record that a frame was requested at NOW
+    pub(super) fn note_render(&mut self, now: Instant) {
+        self.last_render = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn focus_loss_suspends_and_focus_resumes() {
+        let mut state = SuspendState::default();
+        assert!(!state.is_suspended());
+        assert!(state.set_focused(false));
+        assert!(state.is_suspended());
+        assert!(!state.set_focused(false));
+        assert!(state.set_focused(true));
+        assert!(state.frame_interval().is_none());
+    }
+
+    #[test]
+    fn occlusion_uses_hidden_rate() {
+        let mut state = SuspendState::default();
+        state.set_focused(false);
+        assert_eq!(state.frame_interval(), Some(Duration::from_millis(100)));
+        state.set_occluded(true);
+        assert_eq!(state.frame_interval(), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn disabled_policy_never_suspends() {
+        let mut state = SuspendState::default();
+        state.policy.enabled = false;
+        state.set_focused(false);
+        state.set_occluded(true);
+        assert!(!state.is_suspended());
+        assert!(!state.pauses_video());
+    }
+
+    #[test]
+    fn renders_are_spaced_while_suspended() {
+        let mut state = SuspendState::default();
+        let now = Instant::now();
+        state.set_focused(false);
+        assert!(state.render_due(now));
+        state.note_render(now);
+        assert!(!state.render_due(now + Duration::from_millis(50)));
+        assert!(state.render_due(now + Duration::from_millis(100)));
+        state.set_focused(true);
+        assert!(state.render_due(now));
+    }
+}
@@ -5,6 +5,10 @@ use crate::core::types::Rect;
 #[allow(unused_imports)]
 use crate::core::frame_glyphs::FrameGlyph;
 use super::RenderApp;
+use crate::backend::wgpu::memory::texture_bytes;
+use crate::core::buffer_transition::{BufferTransition, BufferTransitionAnimator, BufferTransitionEffect};
+use crate::core::timeline::{Animated, Timeline};
+use super::line_diff::{diff_rows, has_changes, window_rows, DisplayRow, RowChange};
 
 /// State for an active crossfade transition
 pub(super) struct CrossfadeTransition {
@@ -34,6 +38,129 @@ pub(super) struct ScrollTransition {
     pub(super) old_bind_group: wgpu::BindGroup,
 }
 
+/// Zoom between two text scales of a window (text-scale-adjust).
+///
+/// The frame is laid out and rasterized only at the new scale; the old
+/// frame and the new one are both scaled about the top-left of the text
+/// area so the content appears to grow or shrink smoothly.
+pub(super) struct TextZoomTransition {
+    pub(super) started: std::time::Instant,
+    pub(super) duration: std::time::Duration,
+    pub(super) bounds: Rect,
+    /// Old character height divided by the new one
+    pub(super) ratio: f32,
+    pub(super) easing: crate::core::scroll_animation::ScrollEasing,
+    pub(super) old_texture: wgpu::Texture,
+    pub(super) old_view: wgpu::TextureView,
+    pub(super) old_bind_group: wgpu::BindGroup,
+}
+
+/// Scale factors (old frame, new frame) at eased progress T of a text
+/// zoom whose old character height is RATIO times the new one.  The
+/// apparent text size moves linearly from the old size to the new one.
+pub(super) fn text_zoom_scales(ratio: f32, t: f32) -> (f32, f32) {
+    let new_scale = ratio + (1.0 - ratio) * t;
+    (new_scale / ratio, new_scale)
+}
+
+/// Smooth text update of a rewritten buffer: kept lines slide from
+/// their old rows to their new ones, inserted lines fade in and deleted
+/// lines fade out.
+pub(super) struct LineDiffTransition {
+    pub(super) started: std::time::Instant,
+    pub(super) duration: std::time::Duration,
+    /// Text area of the window
+    pub(super) bounds: Rect,
+    /// Frame background, used to clear the text area under the moving rows
+    pub(super) background: crate::core::types::Color,
+    pub(super) changes: Vec<RowChange>,
+    pub(super) old_texture: wgpu::Texture,
+    pub(super) old_view: wgpu::TextureView,
+    pub(super) old_bind_group: wgpu::BindGroup,
+}
+
+/// Row strips to draw at eased progress T of a smooth text update, as
+/// (source y, destination y, height, alpha) for the old frame and for
+/// the new frame.  Kept rows move from their old y to their new one,
+/// inserted rows fade in and deleted rows fade out in place.
+pub(super) fn line_diff_strips(
+    changes: &[RowChange],
+    t: f32,
+) -> (Vec<(f32, f32, f32, f32)>, Vec<(f32, f32, f32, f32)>) {
+    let mut old_strips = Vec::new();
+    let mut new_strips = Vec::new();
+    for change in changes {
+        match *change {
+            RowChange::Kept { from_y, to_y, height } => {
+                new_strips.push((to_y, from_y + (to_y - from_y) * t, height, 1.0));
+            }
+            RowChange::Inserted { y, height } => new_strips.push((y, y, height, t)),
+            RowChange::Deleted { y, height } => old_strips.push((y, y, height, 1.0 - t)),
+        }
+    }
+    (old_strips, new_strips)
+}
+
+/// Transition requested explicitly from Lisp via
+/// `neomacs-start-buffer-transition`, applied to the selected window when
+/// the next frame arrives.
+pub(super) struct ForcedTransition {
+    pub(super) requested: std::time::Instant,
+    pub(super) duration: std::time::Duration,
+    pub(super) effect: crate::core::scroll_animation::ScrollEffect,
+}
+
+/// Snapshot of a window before its buffer changed: the old content of
+/// a buffer transition.
+pub(super) struct BufferSnapshot {
+    pub(super) window_id: i64,
+    pub(super) taken: std::time::Instant,
+    /// Window bounds when the snapshot was taken
+    pub(super) bounds: Rect,
+    pub(super) texture: wgpu::Texture,
+    pub(super) view: wgpu::TextureView,
+    pub(super) bind_group: wgpu::BindGroup,
+}
+
+/// Buffer transition of one window, clipped to the window
+pub(super) struct WindowBufferTransition {
+    pub(super) transition: BufferTransition,
+    pub(super) snapshot: BufferSnapshot,
+}
+
+/// Whether a buffer change in INFO, a window of FRAME, is animated by a
+/// buffer transition.  The minibuffer (whose echo area alternates
+/// between two buffers), popups shown in child frames and windows too
+/// small to show the effect are not.
+pub(super) fn wants_buffer_transition(
+    frame: &crate::core::frame_glyphs::FrameGlyphBuffer,
+    info: &crate::core::frame_glyphs::WindowInfo,
+) -> bool {
+    frame.parent_id == 0 && !info.is_minibuffer && info.bounds.width >= 50.0 && info.bounds.height >= 50.0
+}
+
+/// Id under which a transition of all windows of the frame is kept
+/// among the per-window buffer transitions (window ids are never 0).
+pub(super) const FRAME_TRANSITION_ID: i64 = 0;
+
+/// Area animated by a transition of the whole window layout: the
+/// smallest rectangle holding every window of INFOS but the minibuffer.
+pub(super) fn frame_transition_bounds<'a>(
+    infos: impl Iterator<Item = &'a crate::core::frame_glyphs::WindowInfo>,
+) -> Option<Rect> {
+    infos
+        .filter(|i| !i.is_minibuffer)
+        .map(|i| i.bounds)
+        .reduce(|a, b| {
+            let x = a.x.min(b.x);
+            let y = a.y.min(b.y);
+            Rect::new(x, y, a.right().max(b.right()) - x, a.bottom().max(b.bottom()) - y)
+        })
+}
+
+/// Requests not consumed by a frame within this time are dropped.
+const FORCED_TRANSITION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
 /// Window transition state (crossfade and scroll animations).
 ///
 /// Groups configuration, double-buffer textures, and active transition maps.
@@ -56,9 +183,35 @@ pub(super) struct TransitionState {
     // Active transitions
     pub(super) crossfades: HashMap<i64, CrossfadeTransition>,
     pub(super) scroll_slides: HashMap<i64, ScrollTransition>,
+    pub(super) text_zooms: HashMap<i64, TextZoomTransition>,
+    pub(super) forced: Option<ForcedTransition>,
+    pub(super) line_diffs: HashMap<i64, LineDiffTransition>,
+    /// Buffer transition settings (effect, duration, and whether buffer
+    /// changes are detected automatically)
+    pub(super) buffer_animator: BufferTransitionAnimator,
+    /// Snapshot taken by `neomacs-prepare-buffer-transition`, waiting for
+    /// `neomacs-trigger-buffer-transition`
+    pub(super) buffer_snapshot: Option<BufferSnapshot>,
+    pub(super) buffer_transitions: HashMap<i64, WindowBufferTransition>,
+    /// Keyframe animations of window offset, opacity and scale created
+    /// from Lisp
+    pub(super) timeline: Timeline,
+    /// When Lisp announced that the selected window's buffer is about to
+    /// be rewritten (smooth text update)
+    pub(super) smooth_update_requested: Option<std::time::Instant>,
+    /// Rows of the selected window in the previous frame, as
+    /// (window_id, buffer_id, rows); kept while smooth text update is on
+    pub(super) prev_selected_rows: Option<(i64, u64, Vec<DisplayRow>)>,
 
     // Per-window metadata from previous frame (for transition detection)
     pub(super) prev_window_infos: HashMap<i64, crate::core::frame_glyphs::WindowInfo>,
+
+    /// Touchpad pinch being previewed, or waiting for the window to be
+    /// redrawn at the snapped size
+    pub(super) pinch: Option<super::pinch::PinchZoom>,
+
+    /// Transitions ended early to keep snapshots within budget
+    pub(super) snapshot_evictions: u64,
 }
 
 impl Default for TransitionState {
@@ -77,7 +230,24 @@ impl Default for TransitionState {
             current_is_a: true,
             crossfades: HashMap::new(),
             scroll_slides: HashMap::new(),
+            text_zooms: HashMap::new(),
+            forced: None,
+            line_diffs: HashMap::new(),
+            // Buffer changes crossfade unless automatic buffer
+            // transitions are enabled from Lisp
+            buffer_animator: {
+                let mut animator = BufferTransitionAnimator::new();
+                animator.auto_detect = false;
+                animator
+            },
+            buffer_snapshot: None,
+            buffer_transitions: HashMap::new(),
+            timeline: Timeline::new(),
+            smooth_update_requested: None,
+            prev_selected_rows: None,
             prev_window_infos: HashMap::new(),
+            pinch: None,
+            snapshot_evictions: 0,
         }
     }
 }
@@ -85,7 +255,95 @@ impl Default for TransitionState {
 impl TransitionState {
     /// Check if any transitions are currently active
     pub(super) fn has_active(&self) -> bool {
-        !self.crossfades.is_empty() || !self.scroll_slides.is_empty()
+        !self.crossfades.is_empty()
+            || !self.scroll_slides.is_empty()
+            || !self.text_zooms.is_empty()
+            || !self.line_diffs.is_empty()
+            || !self.buffer_transitions.is_empty()
+            || self.timeline.is_active()
+            || self.pinch.is_some()
+    }
+
+    /// Memory held by the old-frame snapshots of active transitions
+    pub(super) fn snapshot_bytes(&self) -> usize {
+        self.crossfades.values().map(|t| texture_bytes(&t.old_texture))
+            .chain(self.scroll_slides.values().map(|t| texture_bytes(&t.old_texture)))
+            .chain(self.text_zooms.values().map(|t| texture_bytes(&t.old_texture)))
+            .chain(self.line_diffs.values().map(|t| texture_bytes(&t.old_texture)))
+            .chain(self.buffer_transitions.values().map(|t| texture_bytes(&t.snapshot.texture)))
+            .chain(self.buffer_snapshot.iter().map(|s| texture_bytes(&s.texture)))
+            .sum()
+    }
+
+    /// End the oldest transitions until their snapshots fit in BUDGET bytes
+    pub(super) fn enforce_snapshot_budget(&mut self, budget: usize) {
+        while self.snapshot_bytes() > budget {
+            // (started, kind, window id) of the oldest transition
+            let oldest = [
+                self.crossfades.iter().map(|(id, t)| (t.started, 0, *id)).min(),
+                self.scroll_slides.iter().map(|(id, t)| (t.started, 1, *id)).min(),
+                self.text_zooms.iter().map(|(id, t)| (t.started, 2, *id)).min(),
+                self.line_diffs.iter().map(|(id, t)| (t.started, 3, *id)).min(),
+                self.buffer_transitions.iter().map(|(id, t)| (t.transition.start_time, 4, *id)).min(),
+            ]
+            .into_iter()
+            .flatten()
+            .min();
+            let Some((_, kind, window_id)) = oldest else {
+                // Only the buffer transition snapshot is left
+                if self.buffer_snapshot.take().is_some() {
+                    self.snapshot_evictions += 1;
+                }
+                break;
+            };
+            match kind {
+                0 => { self.crossfades.remove(&window_id); }
+                1 => { self.scroll_slides.remove(&window_id); }
+                2 => { self.text_zooms.remove(&window_id); }
+                3 => { self.line_diffs.remove(&window_id); }
+                _ => { self.buffer_transitions.remove(&window_id); }
+            }
+            self.snapshot_evictions += 1;
+            log::debug!("Ended transition of window {} to free snapshot memory", window_id);
+        }
+    }
+}
+
+impl TransitionState {
+    /// The "current" offscreen texture view and bind group
+    pub(super) fn current_offscreen(&self) -> Option<(&wgpu::TextureView, &wgpu::BindGroup)> {
+        let (_, ref view, ref bg) = if self.current_is_a {
+            self.offscreen_a.as_ref()?
+        } else {
+            self.offscreen_b.as_ref()?
+        };
+        Some((view, bg))
+    }
+
+    /// Drop all buffer transitions and snapshots
+    pub(super) fn clear_buffer_transitions(&mut self) {
+        self.buffer_transitions.clear();
+        self.buffer_snapshot = None;
+    }
+
+    /// Animate window SNAPSHOT.window_id from SNAPSHOT to its current
+    /// contents with the configured effect, replacing other transitions
+    /// of the window.  Returns false if the effect is `None`.
+    pub(super) fn start_buffer_transition(&mut self, snapshot: BufferSnapshot, now: std::time::Instant) -> bool {
+        let effect = self.buffer_animator.default_effect;
+        if effect == BufferTransitionEffect::None {
+            return false;
+        }
+        let wid = snapshot.window_id;
+        let mut transition = BufferTransition::new(effect, effect.direction(), self.buffer_animator.default_duration, now);
+        transition.easing = self.buffer_animator.default_easing;
+        transition.old_width = snapshot.bounds.width;
+        transition.old_height = snapshot.bounds.height;
+        self.crossfades.remove(&wid);
+        self.scroll_slides.remove(&wid);
+        log::debug!("Starting buffer transition for window {} (effect={:?})", wid, effect);
+        self.buffer_transitions.insert(wid, WindowBufferTransition { transition, snapshot });
+        true
     }
 }
 
@@ -116,12 +374,7 @@ impl RenderApp {
 
     /// Get the "current" offscreen texture view and bind group
     pub(super) fn current_offscreen_view_and_bg(&self) -> Option<(&wgpu::TextureView, &wgpu::BindGroup)> {
-        let (_, ref view, ref bg) = if self.transitions.current_is_a {
-            self.transitions.offscreen_a.as_ref()?
-        } else {
-            self.transitions.offscreen_b.as_ref()?
-        };
-        Some((view, bg))
+        self.transitions.current_offscreen()
     }
 
     /// Get the "previous" offscreen texture, view, and bind group
@@ -170,6 +423,112 @@ impl RenderApp {
         Some((snap, snap_view, snap_bg))
     }
 
+    /// Snapshot window INFO from TEXTURE, a frame-sized offscreen texture
+    fn snapshot_window(
+        &self,
+        texture: &wgpu::Texture,
+        info: &crate::core::frame_glyphs::WindowInfo,
+    ) -> Option<BufferSnapshot> {
+        let renderer = self.renderer.as_ref()?;
+        let (texture, view, bind_group) = renderer.snapshot_region(texture, &info.bounds)?;
+        Some(BufferSnapshot {
+            window_id: info.window_id,
+            taken: self.clock.now(),
+            bounds: info.bounds,
+            texture,
+            view,
+            bind_group,
+        })
+    }
+
+    /// Snapshot the selected window as last displayed, for a buffer
+    /// transition started by `trigger_buffer_transition`.
+    pub(super) fn prepare_buffer_transition(&mut self) {
+        self.transitions.buffer_snapshot = None;
+        let snapshot = (|| {
+            let info = self.transitions.prev_window_infos.values()
+                .find(|i| i.selected && !i.is_minibuffer)?;
+            // The frame being shown is the most recently rendered one
+            let (tex, _, _) = if self.transitions.current_is_a {
+                self.transitions.offscreen_a.as_ref()?
+            } else {
+                self.transitions.offscreen_b.as_ref()?
+            };
+            self.snapshot_window(tex, info)
+        })();
+        match snapshot {
+            Some(snapshot) => {
+                log::debug!("Captured buffer transition snapshot of window {}", snapshot.window_id);
+                self.transitions.buffer_snapshot = Some(snapshot);
+                self.transitions.enforce_snapshot_budget(self.memory_budget.snapshots);
+            }
+            None => log::debug!("No window to snapshot for buffer transition"),
+        }
+        self.transition_snapshot.store(
+            self.transitions.buffer_snapshot.is_some(),
+            std::sync::atomic::Ordering::Relaxed,
+        );
+    }
+
+    /// Snapshot all windows but the minibuffer as last displayed, for a
+    /// transition of the whole window layout (e.g. a workspace switch)
+    /// started by `trigger_buffer_transition`.
+    pub(super) fn prepare_frame_transition(&mut self) {
+        self.transitions.buffer_snapshot = None;
+        let snapshot = (|| {
+            let bounds = frame_transition_bounds(self.transitions.prev_window_infos.values())?;
+            let (tex, _, _) = if self.transitions.current_is_a {
+                self.transitions.offscreen_a.as_ref()?
+            } else {
+                self.transitions.offscreen_b.as_ref()?
+            };
+            let renderer = self.renderer.as_ref()?;
+            let (texture, view, bind_group) = renderer.snapshot_region(tex, &bounds)?;
+            Some(BufferSnapshot {
+                window_id: FRAME_TRANSITION_ID,
+                taken: self.clock.now(),
+                bounds,
+                texture,
+                view,
+                bind_group,
+            })
+        })();
+        if snapshot.is_some() {
+            log::debug!("Captured frame transition snapshot");
+            self.transitions.buffer_snapshot = snapshot;
+            self.transitions.enforce_snapshot_budget(self.memory_budget.snapshots);
+        } else {
+            log::debug!("No windows to snapshot for frame transition");
+        }
+        self.transition_snapshot.store(
+            self.transitions.buffer_snapshot.is_some(),
+            std::sync::atomic::Ordering::Relaxed,
+        );
+    }
+
+    /// Start animating from the snapshot taken by
+    /// `prepare_buffer_transition`.  Returns false without a snapshot.
+    pub(super) fn trigger_buffer_transition(&mut self) -> bool {
+        self.transition_snapshot.store(false, std::sync::atomic::Ordering::Relaxed);
+        let Some(snapshot) = self.transitions.buffer_snapshot.take() else {
+            return false;
+        };
+        if self.clock.now().saturating_duration_since(snapshot.taken) > FORCED_TRANSITION_TIMEOUT {
+            log::debug!("Dropping stale buffer transition snapshot");
+            return false;
+        }
+        if snapshot.window_id == FRAME_TRANSITION_ID {
+            // The whole layout is animated; drop those of single windows
+            self.transitions.buffer_transitions.clear();
+        }
+        if !self.transitions.start_buffer_transition(snapshot, self.clock.now()) {
+            return false;
+        }
+        self.transitions.forced = None;
+        self.frame_dirty = true;
+        true
+    }
+
     /// Detect transitions by comparing current and previous window infos
     pub(super) fn detect_transitions(&mut self) {
         let frame = match self.current_frame.as_ref() {
@@ -177,7 +536,7 @@ impl RenderApp {
             None => return,
         };
 
-        let now = std::time::Instant::now();
+        let now = self.clock.now();
 
         for info in &frame.window_infos {
             if let Some(prev) = self.transitions.prev_window_infos.get(&info.window_id) {
@@ -189,12 +548,26 @@ impl RenderApp {
                                 renderer.trigger_text_fade_in(info.window_id, info.bounds, now);
                             }
                         }
+                        // Buffer switch → buffer transition of this window
+                        // when detected automatically
+                        if self.transitions.buffer_animator.auto_detect
+                            && wants_buffer_transition(frame, info)
+                            && !self.transitions.buffer_transitions.contains_key(&FRAME_TRANSITION_ID)
+                        {
+                            let snapshot = self.previous_offscreen()
+                                .and_then(|(tex, _, _)| self.snapshot_window(tex, prev));
+                            if let Some(mut snapshot) = snapshot {
+                                // Clip to where the window is now
+                                snapshot.bounds = info.bounds;
+                                self.transitions.start_buffer_transition(snapshot, now);
+                            }
+                        }
                         // Buffer switch → crossfade
                         // Suppress for minibuffer: echo area alternates between
                         // echo_area_buffer[0] and [1] on every message() call,
                         // causing rapid buffer_id changes.  Crossfading these
                         // blends old and new text, creating overlapping text.
-                        if self.transitions.crossfade_enabled && !info.is_minibuffer && info.bounds.height >= 50.0 {
+                        else if self.transitions.crossfade_enabled && !info.is_minibuffer && info.bounds.height >= 50.0 {
                             // Cancel existing transition for this window
                             self.transitions.crossfades.remove(&info.window_id);
                             self.transitions.scroll_slides.remove(&info.window_id);
@@ -284,9 +657,43 @@ impl RenderApp {
                                 });
                             }
                         }
+                    } else if (prev.char_height - info.char_height).abs() > 1.0
+                        && self.transitions.pinch.as_ref().is_some_and(|p| p.snaps_to(info.window_id))
+                    {
+                        // The pinch preview already shows the text at
+                        // about this size; the crisp frame replaces it
+                        self.transitions.pinch = None;
                     } else if (prev.char_height - info.char_height).abs() > 1.0 {
-                        // Font size changed (text-scale-adjust) → crossfade
-                        if self.transitions.crossfade_enabled {
+                        // Font size changed (text-scale-adjust) → zoom the
+                        // text area, or crossfade if zooming is disabled
+                        let top_chrome = info.tab_line_height + info.header_line_height;
+                        let content_height = info.bounds.height - info.mode_line_height - top_chrome;
+                        if self.effects.text_zoom.enabled && !info.is_minibuffer && content_height > 0.0 {
+                            self.transitions.crossfades.remove(&info.window_id);
+                            self.transitions.scroll_slides.remove(&info.window_id);
+                            self.transitions.text_zooms.remove(&info.window_id);
+
+                            let content_bounds = Rect::new(
+                                info.bounds.x, info.bounds.y + top_chrome,
+                                info.bounds.width, content_height,
+                            );
+                            if let Some((tex, view, bg)) = self.snapshot_prev_texture() {
+                                log::debug!("Starting text zoom for window {} (char_height {} → {})",
+                                    info.window_id, prev.char_height, info.char_height);
+                                self.transitions.text_zooms.insert(info.window_id, TextZoomTransition {
+                                    started: now,
+                                    duration: std::time::Duration::from_millis(
+                                        self.effects.text_zoom.duration_ms as u64,
+                                    ),
+                                    bounds: content_bounds,
+                                    ratio: prev.char_height / info.char_height,
+                                    easing: crate::core::scroll_animation::ScrollEasing::EaseOutQuad,
+                                    old_texture: tex,
+                                    old_view: view,
+                                    old_bind_group: bg,
+                                });
+                            }
+                        } else if self.transitions.crossfade_enabled {
                             self.transitions.crossfades.remove(&info.window_id);
                             self.transitions.scroll_slides.remove(&info.window_id);
 
@@ -463,6 +870,83 @@ impl RenderApp {
             self.prev_background = Some(new_bg);
         }
 
+        // Explicit transition requested from Lisp: animate the selected
+        // window from the previous frame, replacing any transition started
+        // above (e.g. a scroll slide caused by narrowing).
+        if let Some(forced) = self.transitions.forced.take() {
+            if now.duration_since(forced.requested) > FORCED_TRANSITION_TIMEOUT {
+                log::debug!("Dropping stale forced transition ({:?})", forced.effect);
+            } else if let Some(info) = frame.window_infos.iter().find(|i| i.selected && !i.is_minibuffer) {
+                self.transitions.crossfades.remove(&info.window_id);
+                self.transitions.scroll_slides.remove(&info.window_id);
+                if let Some((tex, view, bg)) = self.snapshot_prev_texture() {
+                    log::debug!("Starting forced transition for window {} (effect={:?})", info.window_id, forced.effect);
+                    self.transitions.crossfades.insert(info.window_id, CrossfadeTransition {
+                        started: now,
+                        duration: forced.duration,
+                        bounds: info.bounds,
+                        effect: forced.effect,
+                        easing: self.transitions.crossfade_easing,
+                        old_texture: tex,
+                        old_view: view,
+                        old_bind_group: bg,
+                    });
+                }
+            }
+        }
+
+        // Smooth text update: diff the selected window's rows against the
+        // previous frame once Lisp has announced a rewrite of its buffer.
+        if self.effects.smooth_text_update.enabled {
+            let selected = frame.window_infos.iter().find(|i| i.selected && !i.is_minibuffer);
+            let current = selected.map(|info| {
+                let top = info.tab_line_height + info.header_line_height;
+                let area = Rect::new(
+                    info.bounds.x, info.bounds.y + top,
+                    info.bounds.width, info.bounds.height - top - info.mode_line_height,
+                );
+                (info.window_id, info.buffer_id, area, window_rows(frame, &area))
+            });
+            if let Some(requested) = self.transitions.smooth_update_requested {
+                if now.duration_since(requested) > FORCED_TRANSITION_TIMEOUT {
+                    self.transitions.smooth_update_requested = None;
+                } else if let (Some((wid, bid, area, rows)), Some((prev_wid, prev_bid, prev_rows))) =
+                    (current.as_ref(), self.transitions.prev_selected_rows.as_ref())
+                {
+                    let changes = if wid == prev_wid && bid == prev_bid {
+                        diff_rows(prev_rows, rows)
+                    } else {
+                        Vec::new()
+                    };
+                    // Wait for the frame that shows the rewritten text
+                    if has_changes(&changes) && area.height > 0.0 {
+                        self.transitions.smooth_update_requested = None;
+                        self.transitions.crossfades.remove(wid);
+                        self.transitions.scroll_slides.remove(wid);
+                        if let Some((tex, view, bg)) = self.snapshot_prev_texture() {
+                            log::debug!("Starting smooth text update for window {} ({} rows)",
+                                wid, changes.len());
+                            self.transitions.line_diffs.insert(*wid, LineDiffTransition {
+                                started: now,
+                                duration: std::time::Duration::from_millis(
+                                    self.effects.smooth_text_update.duration_ms as u64,
+                                ),
+                                bounds: *area,
+                                background: frame.background,
+                                changes,
+                                old_texture: tex,
+                                old_view: view,
+                                old_bind_group: bg,
+                            });
+                        }
+                    }
+                }
+            }
+            self.transitions.prev_selected_rows = current.map(|(wid, bid, _, rows)| (wid, bid, rows));
+        } else {
+            self.transitions.prev_selected_rows = None;
+        }
+
         // Update prev_window_infos from current frame
         self.transitions.prev_window_infos.clear();
         for info in &frame.window_infos {
@@ -472,7 +956,7 @@ impl RenderApp {
 
     /// Render active transitions on top of the surface
     pub(super) fn render_transitions(&mut self, surface_view: &wgpu::TextureView) {
-        let now = std::time::Instant::now();
+        let now = self.clock.now();
         let renderer = match self.renderer.as_ref() {
             Some(r) => r,
             None => return,
@@ -544,6 +1028,138 @@ impl RenderApp {
         for wid in completed_scrolls {
             self.transitions.scroll_slides.remove(&wid);
         }
+
+        // Render text zooms
+        let mut completed_zooms = Vec::new();
+        for (&wid, transition) in &self.transitions.text_zooms {
+            let elapsed = now.duration_since(transition.started);
+            let raw_t = (elapsed.as_secs_f32() / transition.duration.as_secs_f32().max(1e-3)).min(1.0);
+            let t = transition.easing.apply(raw_t);
+            let (old_scale, new_scale) = text_zoom_scales(transition.ratio, t);
+
+            renderer.render_text_zoom(
+                surface_view,
+                &transition.old_bind_group,
+                unsafe { &*current_bg },
+                old_scale,
+                new_scale,
+                t,
+                &transition.bounds,
+                self.width,
+                self.height,
+            );
+
+            if raw_t >= 1.0 {
+                completed_zooms.push(wid);
+            }
+        }
+        for wid in completed_zooms {
+            self.transitions.text_zooms.remove(&wid);
+        }
+
+        // Render smooth text updates
+        let mut completed_diffs = Vec::new();
+        for (&wid, transition) in &self.transitions.line_diffs {
+            let elapsed = now.duration_since(transition.started);
+            let raw_t = (elapsed.as_secs_f32() / transition.duration.as_secs_f32().max(1e-3)).min(1.0);
+            let t = crate::core::scroll_animation::ScrollEasing::EaseOutQuad.apply(raw_t);
+            let (old_strips, new_strips) = line_diff_strips(&transition.changes, t);
+
+            renderer.render_line_diff(
+                surface_view,
+                &transition.old_bind_group,
+                unsafe { &*current_bg },
+                &old_strips,
+                &new_strips,
+                transition.background,
+                &transition.bounds,
+                self.width,
+                self.height,
+            );
+
+            if raw_t >= 1.0 {
+                completed_diffs.push(wid);
+            }
+        }
+        for wid in completed_diffs {
+            self.transitions.line_diffs.remove(&wid);
+        }
+
+        // Render buffer transitions, each clipped to its window
+        let mut completed_buffer = Vec::new();
+        for (&wid, window) in self.transitions.buffer_transitions.iter_mut() {
+            if !window.transition.advance(now) {
+                completed_buffer.push(wid);
+                continue;
+            }
+            renderer.render_buffer_transition(
+                surface_view,
+                &window.snapshot.bind_group,
+                unsafe { &*current_bg },
+                &window.transition.quads(),
+                &window.snapshot.bounds,
+                self.width,
+                self.height,
+            );
+        }
+        for wid in completed_buffer {
+            self.transitions.buffer_transitions.remove(&wid);
+        }
+        // Prepared but never triggered
+        if self.transitions.buffer_snapshot.as_ref()
+            .is_some_and(|s| now.duration_since(s.taken) > FORCED_TRANSITION_TIMEOUT)
+        {
+            self.transitions.buffer_snapshot = None;
+        }
+
+        // Preview a touchpad pinch over the window's text area
+        if self.transitions.pinch.as_ref().is_some_and(|p| {
+            p.timed_out(now) || !self.transitions.prev_window_infos.contains_key(&p.window_id)
+        }) {
+            self.transitions.pinch = None;
+        }
+        if let Some(pinch) = self.transitions.pinch.as_ref() {
+            let (offset_x, offset_y) = pinch.offset();
+            let transform = crate::core::timeline::WindowTransform {
+                offset_x,
+                offset_y,
+                opacity: 1.0,
+                scale: pinch.scale,
+            };
+            renderer.render_window_transform(
+                surface_view,
+                unsafe { &*current_bg },
+                &transform,
+                pinch.background,
+                &pinch.bounds,
+                self.width,
+                self.height,
+            );
+        }
+
+        // Apply timeline animations to their windows
+        if self.transitions.timeline.tick(now) {
+            let background = self.current_frame.as_ref()
+                .map_or(crate::core::types::Color::BLACK, |f| f.background);
+            for wid in self.transitions.timeline.windows() {
+                let Some(info) = self.transitions.prev_window_infos.get(&wid) else {
+                    continue;
+                };
+                let transform = self.transitions.timeline.window_transform(wid);
+                if transform.is_identity() {
+                    continue;
+                }
+                renderer.render_window_transform(
+                    surface_view,
+                    unsafe { &*current_bg },
+                    &transform,
+                    background,
+                    &info.bounds,
+                    self.width,
+                    self.height,
+                );
+            }
+        }
     }
 }
 
@@ -660,6 +1276,61 @@ mod tests {
         assert!(ts.scroll_slides.is_empty());
     }
 
+    #[test]
+    fn default_no_forced_transition() {
+        let ts = TransitionState::default();
+        assert!(ts.forced.is_none());
+    }
+
+    #[test]
+    fn default_holds_no_snapshots() {
+        let mut ts = TransitionState::default();
+        assert_eq!(ts.snapshot_bytes(), 0);
+        ts.enforce_snapshot_budget(0);
+        assert_eq!(ts.snapshot_evictions, 0);
+    }
+
+    #[test]
+    fn default_buffer_transitions_are_not_automatic() {
+        let ts = TransitionState::default();
+        assert!(!ts.buffer_animator.auto_detect);
+        assert!(ts.buffer_transitions.is_empty());
+        assert!(ts.buffer_snapshot.is_none());
+    }
+
+    #[test]
+    fn buffer_transitions_skip_minibuffer_and_popups() {
+        use crate::core::frame_glyphs::FrameGlyphBuffer;
+        let mut frame = FrameGlyphBuffer::default();
+        let mut info = make_window_info(1, 100, 0, Rect::new(0.0, 0.0, 800.0, 600.0));
+        assert!(wants_buffer_transition(&frame, &info));
+
+        info.is_minibuffer = true;
+        assert!(!wants_buffer_transition(&frame, &info));
+        info.is_minibuffer = false;
+
+        // Windows of child frames (popups)
+        frame.parent_id = 0x200;
+        assert!(!wants_buffer_transition(&frame, &info));
+        frame.parent_id = 0;
+
+        info.bounds.height = 20.0;
+        assert!(!wants_buffer_transition(&frame, &info));
+    }
+
+    #[test]
+    fn frame_transitions_cover_all_windows_but_minibuffer() {
+        let left = make_window_info(1, 100, 0, Rect::new(0.0, 20.0, 400.0, 560.0));
+        let right = make_window_info(2, 101, 0, Rect::new(400.0, 20.0, 400.0, 280.0));
+        let mut mini = make_window_info(3, 102, 0, Rect::new(0.0, 580.0, 800.0, 20.0));
+        mini.is_minibuffer = true;
+        assert_eq!(
+            frame_transition_bounds([&left, &right, &mini].into_iter()),
+            Some(Rect::new(0.0, 20.0, 800.0, 560.0)),
+        );
+        assert_eq!(frame_transition_bounds([&mini].into_iter()), None);
+    }
+
     #[test]
     fn default_no_prev_window_infos() {
         let ts = TransitionState::default();
@@ -1072,6 +1743,69 @@ mod tests {
         assert!(is_font_size_change(24.0, 16.0));
     }
 
+    // =====================================================================
+    // Text zoom scales
+    // =====================================================================
+
+    #[test]
+    fn text_zoom_starts_at_old_size() {
+        // 16px → 24px: the new frame starts shrunk to the old size
+        let (old_scale, new_scale) = text_zoom_scales(16.0 / 24.0, 0.0);
+        assert!((old_scale - 1.0).abs() < 1e-6);
+        assert!((new_scale - 16.0 / 24.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn text_zoom_ends_at_new_size() {
+        let (old_scale, new_scale) = text_zoom_scales(16.0 / 24.0, 1.0);
+        assert!((old_scale - 24.0 / 16.0).abs() < 1e-6);
+        assert!((new_scale - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn text_zoom_frames_stay_aligned() {
+        // Both frames show text at the same apparent size mid-animation
+        let ratio = 24.0 / 16.0;
+        let (old_scale, new_scale) = text_zoom_scales(ratio, 0.4);
+        assert!((old_scale * 24.0 - new_scale * 16.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn default_no_text_zooms() {
+        let ts = TransitionState::default();
+        assert!(ts.text_zooms.is_empty());
+    }
+
+    // =====================================================================
+    // Smooth text update
+    // =====================================================================
+
+    #[test]
+    fn default_no_smooth_text_update() {
+        let ts = TransitionState::default();
+        assert!(ts.line_diffs.is_empty());
+        assert!(ts.smooth_update_requested.is_none());
+        assert!(ts.prev_selected_rows.is_none());
+    }
+
+    #[test]
+    fn line_diff_strips_interpolate() {
+        let changes = [
+            RowChange::Kept { from_y: 0.0, to_y: 20.0, height: 10.0 },
+            RowChange::Inserted { y: 0.0, height: 10.0 },
+            RowChange::Deleted { y: 40.0, height: 10.0 },
+        ];
+        let (old, new) = line_diff_strips(&changes, 0.25);
+        assert_eq!(old, vec![(40.0, 40.0, 10.0, 0.75)]);
+        assert_eq!(new, vec![(20.0, 5.0, 10.0, 1.0), (0.0, 0.0, 10.0, 0.25)]);
+
+        // At the end, every kept row sits at its new position
+        let (old, new) = line_diff_strips(&changes, 1.0);
+        assert_eq!(old[0].3, 0.0);
+        assert_eq!(new[0].1, 20.0);
+        assert_eq!(new[1].3, 1.0);
+    }
+
     // =====================================================================
     // Window resize detection
     // =====================================================================
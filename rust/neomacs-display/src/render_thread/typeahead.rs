@@ -0,0 +1,322 @@
+//! Typeahead echo.
+//!
+//! While Lisp is busy, keys pile up in the input queue and what they
+//! insert only shows once the core gets round to redisplay.  With the
+//! echo on, the render thread draws each printable character it sends
+//! right away, after the cursor of the last frame and in a provisional
+//! style, and takes it back when a frame shows the core has caught up.
+//!
+//! A frame whose cursor stayed on its row and moved right by the width
+//! of the first few echoed characters has inserted those; the rest stay
+//! echoed after the new cursor.  A cursor that did anything else means
+//! the keys did something other than self-insert (a keymap, a read-only
+//! buffer, a wrap to the next row), and the whole echo is dropped: it
+//! only ever shows what the core is about to draw, never what it might.
+
+use std::time::{Duration, Instant};
+
+use super::RenderApp;
+use crate::backend::wgpu::{NEOMACS_CTRL_MASK, NEOMACS_META_MASK, NEOMACS_SUPER_MASK};
+use crate::layout::unicode::is_wide_char;
+
+/// Slack allowed between the echoed widths and how far the cursor moved
+const WIDTH_TOLERANCE: f32 = 1.0;
+
+/// How long a key may go unprocessed before a frame that ignores it
+/// drops the echo: frames drawn for other reasons (timers, process
+/// output) can come before the core has even read the key
+const RECONCILE_GRACE: Duration = Duration::from_millis(200);
+
+/// Where echoed text starts: the cursor of the last frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EchoAnchor {
+    pub window_id: i32,
+    pub x: f32,
+    pub y: f32,
+    pub height: f32,
+    /// Right edge of the window; text is not echoed past it
+    pub right: f32,
+}
+
+/// An echoed character as drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EchoChar {
+    pub ch: char,
+    pub x: f32,
+    pub width: f32,
+}
+
+/// Characters typed but not yet drawn by the core.
+#[derive(Debug, Default)]
+pub struct TypeaheadEcho {
+    anchor: Option<EchoAnchor>,
+    /// Each character, its width and when it was typed
+    pending: Vec<(char, f32, Instant)>,
+    /// A character did not fit; echo nothing more until the next frame
+    blocked: bool,
+}
+
+/// The character to echo for a key press producing TEXT with MODIFIERS
+/// held, if it self-inserts in the usual keymaps.
+pub fn echoable(text: &str, modifiers: u32) -> Option<char> {
+    if modifiers & (NEOMACS_CTRL_MASK | NEOMACS_META_MASK | NEOMACS_SUPER_MASK) != 0 {
+        return None;
+    }
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) if !ch.is_control() => Some(ch),
+        _ => None,
+    }
+}
+
+impl TypeaheadEcho {
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.anchor = None;
+        self.pending.clear();
+    }
+
+    pub fn anchor(&self) -> Option<&EchoAnchor> {
+        self.anchor.as_ref()
+    }
+
+    /// Echo CH, WIDTH wide, typed at NOW.  The first character after a
+    /// reconcile starts at ANCHOR; later ones follow the last.  Returns
+    /// whether it is shown.
+    pub fn type_char(&mut self, anchor: EchoAnchor, ch: char, width: f32, now: Instant) -> bool {
+        if self.blocked {
+            return false;
+        }
+        let anchor = *self.anchor.get_or_insert(anchor);
+        let x = anchor.x + self.pending.iter().map(|p| p.1).sum::<f32>();
+        if x + width > anchor.right {
+            self.clear();
+            self.blocked = true;
+            return false;
+        }
+        self.pending.push((ch, width, now));
+        true
+    }
+
+    /// A frame arrived at NOW with its cursor at (WINDOW_ID, X, Y), or
+    /// none: take back what it drew.
+    pub fn reconcile(&mut self, cursor: Option<(i32, f32, f32)>, now: Instant) {
+        self.blocked = false;
+        let Some(anchor) = self.anchor else {
+            return;
+        };
+        let Some((window_id, x, y)) = cursor else {
+            self.clear();
+            return;
+        };
+        if window_id != anchor.window_id || (y - anchor.y).abs() > 0.5 {
+            self.clear();
+            return;
+        }
+
+        let moved = x - anchor.x;
+        if moved.abs() <= WIDTH_TOLERANCE {
+            // Nothing inserted yet; fine while the keys are fresh
+            let stale = self
+                .pending
+                .first()
+                .is_some_and(|p| now.saturating_duration_since(p.2) > RECONCILE_GRACE);
+            if stale {
+                self.clear();
+            }
+            return;
+        }
+        let mut drawn = 0.0;
+        for (n, p) in self.pending.iter().enumerate() {
+            drawn += p.1;
+            if (drawn - moved).abs() <= WIDTH_TOLERANCE {
+                self.pending.drain(..=n);
+                if self.pending.is_empty() {
+                    self.anchor = None;
+                } else {
+                    self.anchor = Some(EchoAnchor { x, ..anchor });
+                }
+                return;
+            }
+        }
+        self.clear();
+    }
+
+    /// Drop the echo if its oldest character has waited TIMEOUT.
+    pub fn expire(&mut self, now: Instant, timeout: Duration) {
+        let expired = self
+            .pending
+            .first()
+            .is_some_and(|p| now.saturating_duration_since(p.2) >= timeout);
+        if expired {
+            self.clear();
+        }
+    }
+
+    /// The echoed characters, left to right.
+    pub fn chars(&self) -> impl Iterator<Item = EchoChar> + '_ {
+        let mut x = self.anchor.map_or(0.0, |a| a.x);
+        self.pending.iter().map(move |&(ch, width, _)| {
+            let c = EchoChar { ch, x, width };
+            x += width;
+            c
+        })
+    }
+}
+
+impl RenderApp {
+    /// Echo CH, just sent to Emacs, after the cursor of the root frame.
+    pub(super) fn typeahead_echo_char(&mut self, ch: char) {
+        if !self.effects.typeahead_echo.enabled {
+            return;
+        }
+        let (Some(target), Some(frame)) =
+            (self.cursor.target.as_ref(), self.current_frame.as_ref())
+        else {
+            return;
+        };
+        if target.frame_id != 0 {
+            return;
+        }
+        let Some(info) = frame.window_infos.iter().find(|w| {
+            let b = &w.bounds;
+            target.x >= b.x
+                && target.x < b.x + b.width
+                && target.y >= b.y
+                && target.y < b.y + b.height
+        }) else {
+            return;
+        };
+        let anchor = EchoAnchor {
+            window_id: target.window_id,
+            x: target.x,
+            y: target.y,
+            height: target.height,
+            right: info.bounds.x + info.bounds.width,
+        };
+        let width = if is_wide_char(ch) {
+            frame.char_width * 2.0
+        } else {
+            frame.char_width
+        };
+        if self.typeahead.type_char(anchor, ch, width, Instant::now()) {
+            self.frame_dirty = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::wgpu::NEOMACS_SHIFT_MASK;
+
+    const W: f32 = 8.0;
+
+    fn anchor(x: f32) -> EchoAnchor {
+        EchoAnchor {
+            window_id: 1,
+            x,
+            y: 32.0,
+            height: 16.0,
+            right: 80.0,
+        }
+    }
+
+    fn typed(echo: &mut TypeaheadEcho, s: &str, at: Instant) {
+        for ch in s.chars() {
+            assert!(echo.type_char(anchor(16.0), ch, W, at));
+        }
+    }
+
+    fn text(echo: &TypeaheadEcho) -> (String, Vec<f32>) {
+        (
+            echo.chars().map(|c| c.ch).collect(),
+            echo.chars().map(|c| c.x).collect(),
+        )
+    }
+
+    #[test]
+    fn only_plain_printable_keys_echo() {
+        assert_eq!(echoable("a", 0), Some('a'));
+        assert_eq!(echoable("A", NEOMACS_SHIFT_MASK), Some('A'));
+        assert_eq!(echoable("é", 0), Some('é'));
+        assert_eq!(echoable("a", NEOMACS_CTRL_MASK), None);
+        assert_eq!(echoable("x", NEOMACS_META_MASK), None);
+        assert_eq!(echoable("\r", 0), None);
+        assert_eq!(echoable("ab", 0), None);
+        assert_eq!(echoable("", 0), None);
+    }
+
+    #[test]
+    fn frames_take_back_what_they_drew() {
+        let t0 = Instant::now();
+        let mut echo = TypeaheadEcho::default();
+        typed(&mut echo, "abc", t0);
+        assert_eq!(text(&echo), ("abc".into(), vec![16.0, 24.0, 32.0]));
+
+        // The core inserted "ab"; "c" follows its cursor
+        echo.reconcile(Some((1, 32.0, 32.0)), t0);
+        assert_eq!(text(&echo), ("c".into(), vec![32.0]));
+        // More typing continues after the echo, not the cursor
+        assert!(echo.type_char(anchor(0.0), 'd', W, t0));
+        assert_eq!(text(&echo), ("cd".into(), vec![32.0, 40.0]));
+        echo.reconcile(Some((1, 48.0, 32.0)), t0);
+        assert!(echo.is_empty());
+        assert!(echo.anchor().is_none());
+    }
+
+    #[test]
+    fn other_edits_drop_the_echo() {
+        let t0 = Instant::now();
+        let mut echo = TypeaheadEcho::default();
+        // Moved by something other than the echoed widths
+        typed(&mut echo, "ab", t0);
+        echo.reconcile(Some((1, 20.0, 32.0)), t0);
+        assert!(echo.is_empty());
+        // Another row or window
+        typed(&mut echo, "ab", t0);
+        echo.reconcile(Some((1, 16.0, 48.0)), t0);
+        assert!(echo.is_empty());
+        typed(&mut echo, "ab", t0);
+        echo.reconcile(Some((2, 24.0, 32.0)), t0);
+        assert!(echo.is_empty());
+        typed(&mut echo, "ab", t0);
+        echo.reconcile(None, t0);
+        assert!(echo.is_empty());
+    }
+
+    #[test]
+    fn unmoved_cursor_keeps_fresh_keys_only() {
+        let t0 = Instant::now();
+        let mut echo = TypeaheadEcho::default();
+        typed(&mut echo, "ab", t0);
+        echo.reconcile(Some((1, 16.0, 32.0)), t0 + Duration::from_millis(50));
+        assert_eq!(text(&echo).0, "ab");
+        // Long enough to have been read: the keys did not insert
+        echo.reconcile(Some((1, 16.0, 32.0)), t0 + Duration::from_millis(500));
+        assert!(echo.is_empty());
+    }
+
+    #[test]
+    fn stops_at_the_window_edge_and_expires() {
+        let t0 = Instant::now();
+        let mut echo = TypeaheadEcho::default();
+        // Eight characters fill 16..80
+        typed(&mut echo, "abcdefgh", t0);
+        assert!(!echo.type_char(anchor(16.0), 'i', W, t0));
+        assert!(echo.is_empty());
+        // Nothing more until a frame shows where the text went
+        assert!(!echo.type_char(anchor(16.0), 'j', W, t0));
+        echo.reconcile(Some((1, 16.0, 48.0)), t0);
+        assert!(echo.type_char(anchor(16.0), 'j', W, t0));
+
+        let timeout = Duration::from_secs(2);
+        echo.expire(t0 + Duration::from_secs(1), timeout);
+        assert!(!echo.is_empty());
+        echo.expire(t0 + timeout, timeout);
+        assert!(echo.is_empty());
+    }
+}
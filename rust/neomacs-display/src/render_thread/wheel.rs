@@ -0,0 +1,186 @@
+//! Mouse wheel and touchpad scroll settings.
+//!
+//! Scroll events are shaped here, before they are sent to Emacs: the
+//! device is told apart by its deltas (notched wheels report lines,
+//! touchpads report pixels), each device has its own speed and
+//! direction, Shift can turn vertical wheel motion into horizontal
+//! scrolling and Alt can scroll a page per notch.  The number of lines
+//! goes to Emacs as the event's line count (see `event-line-count'), so
+//! `mwheel-scroll' moves exactly that far.
+
+use crate::backend::wgpu::{NEOMACS_META_MASK, NEOMACS_SHIFT_MASK};
+use crate::core::frame_glyphs::FrameGlyphBuffer;
+
+/// Lines of context kept by a page scroll, like
+/// `next-screen-context-lines'
+const PAGE_CONTEXT_LINES: i32 = 2;
+
+/// What produced a scroll event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WheelDevice {
+    /// A notched wheel: deltas count lines (fractions of one for
+    /// high-resolution wheels)
+    Wheel,
+    /// A touchpad or other precise device: deltas are pixels
+    Touchpad,
+}
+
+impl WheelDevice {
+    /// The device of a scroll whose deltas are pixels if PIXEL_PRECISE
+    pub fn detect(pixel_precise: bool) -> Self {
+        if pixel_precise { Self::Touchpad } else { Self::Wheel }
+    }
+}
+
+/// Settings of one kind of device
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WheelDeviceConfig {
+    /// Lines per wheel notch, or the factor touchpad pixels are
+    /// multiplied by
+    pub speed: f32,
+    /// Scroll the other way ("natural" scrolling)
+    pub reverse: bool,
+}
+
+impl Default for WheelDeviceConfig {
+    fn default() -> Self {
+        Self { speed: 1.0, reverse: false }
+    }
+}
+
+/// Scroll settings, set from Lisp
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct WheelConfig {
+    pub wheel: WheelDeviceConfig,
+    pub touchpad: WheelDeviceConfig,
+    /// Shift turns vertical motion into horizontal scrolling
+    pub shift_horizontal: bool,
+    /// Alt scrolls a page per wheel notch
+    pub alt_page: bool,
+}
+
+/// A scroll as sent to Emacs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WheelScroll {
+    pub delta_x: f32,
+    pub delta_y: f32,
+    /// Lines to scroll, or 0 to let Emacs decide
+    pub lines: i32,
+    pub modifiers: u32,
+}
+
+impl WheelConfig {
+    /// Shape a scroll of DX, DY from DEVICE with MODIFIERS held.
+    /// PAGE_LINES is how far a page scroll moves in the window under
+    /// the mouse.
+    pub fn apply(
+        &self,
+        device: WheelDevice,
+        dx: f32,
+        dy: f32,
+        modifiers: u32,
+        page_lines: i32,
+    ) -> WheelScroll {
+        let config = match device {
+            WheelDevice::Wheel => self.wheel,
+            WheelDevice::Touchpad => self.touchpad,
+        };
+        let sign = if config.reverse { -1.0 } else { 1.0 };
+        let (mut dx, mut dy) = (dx * sign * config.speed, dy * sign * config.speed);
+        let mut modifiers = modifiers;
+
+        if self.shift_horizontal && modifiers & NEOMACS_SHIFT_MASK != 0 && dx == 0.0 {
+            dx = dy;
+            dy = 0.0;
+            modifiers &= !NEOMACS_SHIFT_MASK;
+        }
+
+        let mut lines = 0;
+        if device == WheelDevice::Wheel {
+            let notches = dx.abs().max(dy.abs());
+            if notches > 0.0 {
+                lines = (notches.round() as i32).max(1);
+            }
+            if self.alt_page && modifiers & NEOMACS_META_MASK != 0 && notches > 0.0 {
+                // Scale the deltas so pixel-precise consumers move a
+                // page too
+                let page = (page_lines * lines) as f32 / notches;
+                dx *= page;
+                dy *= page;
+                lines *= page_lines.max(1);
+                modifiers &= !NEOMACS_META_MASK;
+            }
+        }
+        WheelScroll { delta_x: dx, delta_y: dy, lines, modifiers }
+    }
+}
+
+/// Lines a page scroll moves in the window of FRAME at X, Y: its text
+/// rows less `PAGE_CONTEXT_LINES`, at least 1.
+pub fn page_lines(frame: &FrameGlyphBuffer, x: f32, y: f32) -> i32 {
+    let Some(info) = frame.window_infos.iter().find(|w| {
+        x >= w.bounds.x && x < w.bounds.x + w.bounds.width
+            && y >= w.bounds.y && y < w.bounds.y + w.bounds.height
+    }) else {
+        return 1;
+    };
+    let char_h = if info.char_height > 0.0 { info.char_height } else { frame.char_height };
+    if char_h <= 0.0 {
+        return 1;
+    }
+    let text_h = info.bounds.height - info.mode_line_height
+        - info.header_line_height - info.tab_line_height;
+    ((text_h / char_h) as i32 - PAGE_CONTEXT_LINES).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wheel_notches_become_lines() {
+        let mut config = WheelConfig::default();
+        let plain = config.apply(WheelDevice::Wheel, 0.0, -1.0, 0, 30);
+        assert_eq!(plain, WheelScroll { delta_x: 0.0, delta_y: -1.0, lines: 1, modifiers: 0 });
+
+        config.wheel = WheelDeviceConfig { speed: 3.0, reverse: true };
+        let fast = config.apply(WheelDevice::Wheel, 0.0, -1.0, 0, 30);
+        assert_eq!((fast.delta_y, fast.lines), (3.0, 3));
+        // High-resolution wheels send fractions of a notch
+        assert_eq!(config.apply(WheelDevice::Wheel, 0.0, 0.1, 0, 30).lines, 1);
+
+        // Touchpads keep their pixels and leave the line count to Emacs
+        config.touchpad.speed = 2.0;
+        let pad = config.apply(WheelDevice::Touchpad, 4.0, 10.0, 0, 30);
+        assert_eq!((pad.delta_x, pad.delta_y, pad.lines), (8.0, 20.0, 0));
+        assert_eq!(WheelDevice::detect(true), WheelDevice::Touchpad);
+    }
+
+    #[test]
+    fn shift_scrolls_sideways_and_alt_scrolls_pages() {
+        let config = WheelConfig { shift_horizontal: true, alt_page: true, ..Default::default() };
+        let side = config.apply(WheelDevice::Wheel, 0.0, 1.0, NEOMACS_SHIFT_MASK, 30);
+        assert_eq!(side, WheelScroll { delta_x: 1.0, delta_y: 0.0, lines: 1, modifiers: 0 });
+
+        let page = config.apply(WheelDevice::Wheel, 0.0, -1.0, NEOMACS_META_MASK, 30);
+        assert_eq!(page, WheelScroll { delta_x: 0.0, delta_y: -30.0, lines: 30, modifiers: 0 });
+
+        // Off: modifiers pass through for Emacs's own bindings
+        let off = WheelConfig::default().apply(WheelDevice::Wheel, 0.0, 1.0, NEOMACS_META_MASK, 30);
+        assert_eq!((off.lines, off.modifiers), (1, NEOMACS_META_MASK));
+    }
+
+    #[test]
+    fn page_lines_of_window_under_mouse() {
+        let mut frame = FrameGlyphBuffer::new();
+        frame.char_height = 20.0;
+        frame.add_window_info(
+            1, 1, 1, 100, 100,
+            0.0, 0.0, 400.0, 420.0,
+            20.0, 0.0, 0.0, true, false, 20.0,
+            String::new(), false,
+        );
+        assert_eq!(page_lines(&frame, 10.0, 10.0), 18);
+        assert_eq!(page_lines(&frame, 500.0, 10.0), 1);
+    }
+}
@@ -0,0 +1,227 @@
+//! Scene handoff: how laid-out frames get from Emacs to the render
+//! thread.
+//!
+//! Each frame (root, child or secondary window) is triple-buffered: the
+//! buffer Emacs lays out into, at most one published buffer waiting for
+//! the render thread, and the buffer the render thread is drawing.
+//! Publishing copies the finished scene into a spare buffer and swaps it
+//! into the frame's pending slot, replacing a scene the renderer has not
+//! taken yet; taking swaps the pending slots out.  The lock both sides
+//! share guards only those swaps, never a copy or a draw, so the renderer
+//! never sees a scene half written and Emacs never waits for the GPU.
+//!
+//! Every publish gets the next generation number.  The render thread
+//! checks the latest generation without locking to tell whether there is
+//! anything to take, and gives the buffers it is done with back, so
+//! steady-state redisplay reuses the same few allocations.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::core::frame_glyphs::FrameGlyphBuffer;
+
+/// Spare buffers kept for reuse.  Three per frame covers the usual
+/// single-frame case; more frames just allocate a little more.
+const MAX_SPARE_BUFFERS: usize = 3;
+
+/// A published scene.
+#[derive(Debug)]
+pub struct Scene {
+    /// Publish order, from 1
+    pub generation: u64,
+    pub frame: FrameGlyphBuffer,
+}
+
+#[derive(Default)]
+struct Slots {
+    /// The latest scene of each frame not yet taken, in publish order
+    pending: Vec<Scene>,
+    /// Buffers to copy the next scenes into
+    spare: Vec<FrameGlyphBuffer>,
+    /// Scenes replaced before the renderer took them
+    superseded: u64,
+}
+
+/// The state both threads share.
+#[derive(Default)]
+struct Mailbox {
+    slots: Mutex<Slots>,
+    /// Generation of the last scene published
+    generation: AtomicU64,
+}
+
+impl Mailbox {
+    fn slots(&self) -> std::sync::MutexGuard<'_, Slots> {
+        // Slots stay consistent even if a holder panicked
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Create the Emacs and render thread ends of a scene handoff.
+pub fn scene_handoff() -> (ScenePublisher, SceneReceiver) {
+    let mailbox = Arc::new(Mailbox::default());
+    (
+        ScenePublisher { mailbox: mailbox.clone() },
+        SceneReceiver { mailbox, taken: 0 },
+    )
+}
+
+/// Emacs thread end: publishes finished scenes.
+pub struct ScenePublisher {
+    mailbox: Arc<Mailbox>,
+}
+
+impl ScenePublisher {
+    /// Publish a copy of FRAME, replacing any scene of the same frame
+    /// the renderer has not taken yet.  Returns its generation.
+    pub fn publish(&self, frame: &FrameGlyphBuffer) -> u64 {
+        let spare = self.mailbox.slots().spare.pop();
+        let mut copy = spare.unwrap_or_default();
+        copy.copy_from(frame);
+        self.publish_owned(copy)
+    }
+
+    /// Publish FRAME itself.
+    pub fn publish_owned(&self, frame: FrameGlyphBuffer) -> u64 {
+        let mut slots = self.mailbox.slots();
+        // Generations only change under the lock, so they are published
+        // in order
+        let generation = self.mailbox.generation.load(Ordering::Relaxed) + 1;
+        let scene = Scene { generation, frame };
+        let frame_id = scene.frame.frame_id;
+        if let Some(i) = slots.pending.iter().position(|s| s.frame.frame_id == frame_id) {
+            let old = slots.pending.remove(i);
+            slots.superseded += 1;
+            if slots.spare.len() < MAX_SPARE_BUFFERS {
+                slots.spare.push(old.frame);
+            }
+        }
+        slots.pending.push(scene);
+        self.mailbox.generation.store(generation, Ordering::Release);
+        generation
+    }
+
+    /// Generation of the last scene published.
+    pub fn generation(&self) -> u64 {
+        self.mailbox.generation.load(Ordering::Acquire)
+    }
+}
+
+/// Render thread end: takes the latest scenes.
+pub struct SceneReceiver {
+    mailbox: Arc<Mailbox>,
+    /// Generation of the last scene taken
+    taken: u64,
+}
+
+impl SceneReceiver {
+    /// Whether a scene newer than the last one taken is waiting.  Does
+    /// not lock.
+    pub fn has_new(&self) -> bool {
+        self.mailbox.generation.load(Ordering::Acquire) > self.taken
+    }
+
+    /// Take the latest scene of every frame published since the last
+    /// call, oldest first.
+    pub fn take(&mut self) -> Vec<Scene> {
+        if !self.has_new() {
+            return Vec::new();
+        }
+        let scenes = std::mem::take(&mut self.mailbox.slots().pending);
+        if let Some(last) = scenes.last() {
+            self.taken = last.generation;
+        }
+        scenes
+    }
+
+    /// Give back FRAME, a scene the renderer has replaced, for reuse.
+    pub fn recycle(&self, frame: FrameGlyphBuffer) {
+        let mut slots = self.mailbox.slots();
+        if slots.spare.len() < MAX_SPARE_BUFFERS {
+            slots.spare.push(frame);
+        }
+    }
+
+    /// Generation of the last scene taken.
+    pub fn taken(&self) -> u64 {
+        self.taken
+    }
+
+    /// How many scenes were replaced before the renderer took them.
+    pub fn superseded(&self) -> u64 {
+        self.mailbox.slots().superseded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(frame_id: u64, width: f32) -> FrameGlyphBuffer {
+        let mut frame = FrameGlyphBuffer::with_size(width, 100.0);
+        frame.frame_id = frame_id;
+        frame
+    }
+
+    #[test]
+    fn only_the_latest_scene_of_each_frame_is_taken() {
+        let (publisher, mut receiver) = scene_handoff();
+        assert!(!receiver.has_new());
+        publisher.publish(&frame(1, 10.0));
+        publisher.publish(&frame(2, 20.0));
+        assert_eq!(publisher.publish(&frame(1, 30.0)), 3);
+        assert!(receiver.has_new());
+
+        let scenes = receiver.take();
+        let taken: Vec<(u64, u64, f32)> =
+            scenes.iter().map(|s| (s.generation, s.frame.frame_id, s.frame.width)).collect();
+        assert_eq!(taken, vec![(2, 2, 20.0), (3, 1, 30.0)]);
+        assert_eq!(receiver.taken(), 3);
+        assert_eq!(receiver.superseded(), 1);
+        assert!(!receiver.has_new());
+        assert!(receiver.take().is_empty());
+    }
+
+    #[test]
+    fn recycled_buffers_are_reused() {
+        let (publisher, mut receiver) = scene_handoff();
+        let mut big = frame(0, 10.0);
+        big.add_char('a', 0.0, 0.0, 8.0, 16.0, 12.0, false);
+        big.glyphs.reserve(1000);
+        let capacity = big.glyphs.capacity();
+        receiver.recycle(big);
+
+        publisher.publish(&frame(0, 50.0));
+        let scene = receiver.take().pop().unwrap();
+        // The copy went into the recycled buffer, leaving none of its glyphs
+        assert_eq!(scene.frame.width, 50.0);
+        assert!(scene.frame.glyphs.is_empty());
+        assert_eq!(scene.frame.glyphs.capacity(), capacity);
+    }
+
+    #[test]
+    fn scenes_cross_threads_whole() {
+        let (publisher, mut receiver) = scene_handoff();
+        let writer = std::thread::spawn(move || {
+            for i in 1..=200 {
+                let mut f = frame(0, i as f32);
+                for _ in 0..i % 7 {
+                    f.add_char('x', 0.0, 0.0, i as f32, 16.0, 12.0, false);
+                }
+                publisher.publish(&f);
+            }
+        });
+        let mut last = 0;
+        while last < 200 {
+            for scene in receiver.take() {
+                let width = scene.frame.width;
+                assert!(scene.generation > last);
+                assert_eq!(scene.frame.glyphs.len(), width as usize % 7);
+                last = scene.generation;
+                receiver.recycle(scene.frame);
+            }
+            std::thread::yield_now();
+        }
+        writer.join().unwrap();
+    }
+}
@@ -1,7 +1,8 @@
 //! Color conversion from alacritty_terminal colors to neomacs Color.
 
 use crate::core::types::Color;
-use alacritty_terminal::vte::ansi::{Color as AnsiColor, NamedColor};
+use alacritty_terminal::term::color::Colors;
+use alacritty_terminal::vte::ansi::{Color as AnsiColor, NamedColor, Rgb};
 
 /// Default 256-color palette (standard ANSI + extended colors).
 /// First 16 are the standard terminal colors, 16-231 are the 6x6x6 color cube,
@@ -111,6 +112,85 @@ fn named_to_color(named: NamedColor, default_fg: &Color, default_bg: &Color) ->
     }
 }
 
+/// Colors of one terminal: its 256-color palette and default
+/// foreground and background.
+///
+/// Programs may override any of these with OSC 4/10/11; those colors
+/// are kept by `Term` (see `Term::colors`) and win over the palette
+/// until the program resets them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerminalPalette {
+    pub colors: [Color; 256],
+    pub fg: Color,
+    pub bg: Color,
+}
+
+impl Default for TerminalPalette {
+    fn default() -> Self {
+        Self { colors: *COLOR_256, fg: Color::WHITE, bg: Color::BLACK }
+    }
+}
+
+impl TerminalPalette {
+    /// A palette with ANSI as its first 16 colors (missing or `None`
+    /// entries keep the defaults) and FG / BG as default colors.
+    pub fn new(ansi: &[Option<Color>], fg: Option<Color>, bg: Option<Color>) -> Self {
+        let mut palette = Self::default();
+        for (i, c) in ansi.iter().take(16).enumerate() {
+            if let Some(c) = c {
+                palette.colors[i] = *c;
+            }
+        }
+        palette.fg = fg.unwrap_or(palette.fg);
+        palette.bg = bg.unwrap_or(palette.bg);
+        palette
+    }
+
+    /// Color at INDEX of alacritty's color table: 0-255 are the palette,
+    /// then foreground, background, cursor, the dim colors, and the
+    /// bright and dim foreground (see `NamedColor`).
+    pub fn indexed(&self, index: usize) -> Color {
+        let dim = |c: Color| Color::rgb(c.r * 0.66, c.g * 0.66, c.b * 0.66);
+        match index {
+            0..=255 => self.colors[index],
+            i if i == NamedColor::Background as usize => self.bg,
+            i if (NamedColor::DimBlack as usize..=NamedColor::DimWhite as usize).contains(&i) => {
+                dim(self.colors[i - NamedColor::DimBlack as usize])
+            }
+            i if i == NamedColor::DimForeground as usize => dim(self.fg),
+            _ => self.fg,
+        }
+    }
+
+    /// Color at INDEX, as overridden by the program in OVERRIDES.
+    pub fn lookup(&self, index: usize, overrides: &Colors) -> Color {
+        match overrides[index] {
+            Some(rgb) => Color::from_u8(rgb.r, rgb.g, rgb.b, 255),
+            None => self.indexed(index),
+        }
+    }
+
+    /// Resolve a cell color.
+    pub fn resolve(&self, color: &AnsiColor, overrides: &Colors) -> Color {
+        match color {
+            AnsiColor::Named(named) => self.lookup(*named as usize, overrides),
+            AnsiColor::Spec(rgb) => Color::from_u8(rgb.r, rgb.g, rgb.b, 255),
+            AnsiColor::Indexed(idx) => self.lookup(*idx as usize, overrides),
+        }
+    }
+
+    /// Color at INDEX as reported to a program querying it (OSC 4/10/11
+    /// with `?`).
+    pub fn query(&self, index: usize, overrides: &Colors) -> Rgb {
+        if let Some(rgb) = overrides[index] {
+            return rgb;
+        }
+        let c = self.indexed(index);
+        let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Rgb { r: to_u8(c.r), g: to_u8(c.g), b: to_u8(c.b) }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -628,4 +708,38 @@ mod tests {
         let v = 8.0 / 255.0;
         assert_color_eq(gray_first, v, v, v);
     }
+
+    // ---------------------------------------------------------------
+    // Per-terminal palettes
+    // ---------------------------------------------------------------
+
+    #[test]
+    fn test_palette_overrides_ansi_and_defaults() {
+        let red = Color::from_u8(200, 40, 40, 255);
+        let bg = Color::from_u8(30, 30, 30, 255);
+        let p = TerminalPalette::new(&[None, Some(red)], None, Some(bg));
+        let none = Colors::default();
+        assert_eq!(p.resolve(&AnsiColor::Named(NamedColor::Red), &none), red);
+        assert_eq!(p.resolve(&AnsiColor::Indexed(1), &none), red);
+        assert_eq!(p.resolve(&AnsiColor::Named(NamedColor::Black), &none), COLOR_256[0]);
+        assert_eq!(p.resolve(&AnsiColor::Named(NamedColor::Background), &none), bg);
+        assert_eq!(p.resolve(&AnsiColor::Named(NamedColor::Foreground), &none), Color::WHITE);
+        // Cube colors are not part of the ANSI palette
+        assert_eq!(p.indexed(100), COLOR_256[100]);
+    }
+
+    #[test]
+    fn test_palette_program_colors_win() {
+        let p = TerminalPalette::default();
+        let mut overrides = Colors::default();
+        overrides[NamedColor::Background] = Some(Rgb { r: 0, g: 0, b: 0x80 });
+        overrides[4] = Some(Rgb { r: 1, g: 2, b: 3 });
+        let bg = p.resolve(&AnsiColor::Named(NamedColor::Background), &overrides);
+        assert_color_rgb(&bg, 0, 0, 0x80);
+        assert_color_rgb(&p.resolve(&AnsiColor::Indexed(4), &overrides), 1, 2, 3);
+        // Queries report overrides, else the palette
+        assert_eq!(p.query(NamedColor::Background as usize, &overrides), Rgb { r: 0, g: 0, b: 0x80 });
+        assert_eq!(p.query(NamedColor::Foreground as usize, &overrides), Rgb { r: 255, g: 255, b: 255 });
+        assert_eq!(p.query(1, &overrides), Rgb { r: 205, g: 0, b: 0 });
+    }
 }
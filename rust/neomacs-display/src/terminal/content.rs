@@ -8,7 +8,8 @@ use alacritty_terminal::grid::Dimensions;
 use alacritty_terminal::index::{Column, Line, Point};
 use alacritty_terminal::term::cell::Flags as CellFlags;
 use alacritty_terminal::term::Term;
-use super::colors::ansi_to_color;
+use alacritty_terminal::vte::ansi::NamedColor;
+use super::colors::TerminalPalette;
 
 /// A single cell ready for GPU rendering.
 #[derive(Debug, Clone)]
@@ -49,24 +50,31 @@ pub struct TerminalContent {
     pub default_bg: Color,
     /// Default foreground color.
     pub default_fg: Color,
+    /// Rows where a shell prompt starts (see `shell::ShellMarks`).
+    pub prompt_rows: Vec<usize>,
 }
 
 impl TerminalContent {
-    /// Extract renderable content from an alacritty Term.
+    /// Extract renderable content from an alacritty Term, with colors
+    /// from PALETTE unless the program set them itself.
     pub fn from_term<T: alacritty_terminal::event::EventListener>(
         term: &Term<T>,
+        palette: &TerminalPalette,
     ) -> Self {
         let grid = term.grid();
         let num_cols = grid.columns();
         let num_lines = grid.screen_lines();
+        // Lines scrolled back into the history
+        let offset = grid.display_offset() as i32;
 
-        let default_fg = Color::WHITE;
-        let default_bg = Color::BLACK;
+        let overrides = term.colors();
+        let default_fg = palette.lookup(NamedColor::Foreground as usize, overrides);
+        let default_bg = palette.lookup(NamedColor::Background as usize, overrides);
 
         let mut cells = Vec::with_capacity(num_cols * num_lines);
 
         for row_idx in 0..num_lines {
-            let line = Line(row_idx as i32);
+            let line = Line(row_idx as i32 - offset);
             for col_idx in 0..num_cols {
                 let point = Point::new(line, Column(col_idx));
                 let cell = &grid[point];
@@ -77,8 +85,8 @@ impl TerminalContent {
                     continue;
                 }
 
-                let fg = ansi_to_color(&cell.fg, &default_fg, &default_bg);
-                let bg = ansi_to_color(&cell.bg, &default_fg, &default_bg);
+                let fg = palette.resolve(&cell.fg, overrides);
+                let bg = palette.resolve(&cell.bg, overrides);
 
                 cells.push(RenderCell {
                     col: col_idx,
@@ -92,10 +100,12 @@ impl TerminalContent {
         }
 
         let cursor_point = term.grid().cursor.point;
+        let cursor_row = (cursor_point.line.0 + offset) as usize;
         let cursor = RenderCursor {
             col: cursor_point.column.0,
-            row: cursor_point.line.0 as usize,
-            visible: term.mode().contains(alacritty_terminal::term::TermMode::SHOW_CURSOR),
+            row: cursor_row,
+            visible: cursor_row < num_lines
+                && term.mode().contains(alacritty_terminal::term::TermMode::SHOW_CURSOR),
         };
 
         TerminalContent {
@@ -105,6 +115,7 @@ impl TerminalContent {
             cursor,
             default_bg,
             default_fg,
+            prompt_rows: Vec::new(),
         }
     }
 }
@@ -147,6 +158,36 @@ pub fn extract_text<T: alacritty_terminal::event::EventListener>(
         .join("\n")
 }
 
+/// Text of grid lines START to END (exclusive; negative lines are in the
+/// scrollback), with wrapped lines joined.
+pub fn extract_lines<T: alacritty_terminal::event::EventListener>(
+    term: &Term<T>,
+    start: i64,
+    end: i64,
+) -> String {
+    let grid = term.grid();
+    let first = start.max(-(grid.history_size() as i64));
+    let last = end.min(grid.screen_lines() as i64);
+    let mut text = String::new();
+    for l in first..last {
+        let row = &grid[Line(l as i32)];
+        let mut line = String::new();
+        for cell in row {
+            if !cell.flags.contains(CellFlags::WIDE_CHAR_SPACER) {
+                line.push(cell.c);
+            }
+        }
+        let wrapped = row[Column(grid.columns() - 1)].flags.contains(CellFlags::WRAPLINE);
+        if wrapped {
+            text.push_str(&line);
+        } else {
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+    }
+    text
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -174,6 +215,7 @@ mod tests {
             cursor: RenderCursor { col: 0, row: 0, visible: true },
             default_bg: Color::BLACK,
             default_fg: Color::WHITE,
+            prompt_rows: vec![],
         };
         assert_eq!(content.cols, 80);
         assert_eq!(content.rows, 24);
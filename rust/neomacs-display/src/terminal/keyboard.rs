@@ -0,0 +1,423 @@
+//! Keyboard input encoding for neo-term.
+//!
+//! Turns key presses into the bytes a terminal program expects.  By
+//! default keys use the legacy xterm encoding.  Programs that enable the
+//! kitty keyboard protocol (`CSI > flags u`) get its progressive
+//! enhancements instead: unambiguous `CSI ... u` sequences for modified
+//! keys and Escape, press/repeat/release event types, shifted alternate
+//! keys, every key as an escape code, and the text a key produces.
+//! The flags themselves are tracked by `alacritty_terminal`, which also
+//! answers the program's `CSI ? u` queries.
+
+use alacritty_terminal::term::TermMode;
+
+/// Modifier bits, as encoded (plus one) in kitty key sequences.
+pub const MOD_SHIFT: u8 = 1 << 0;
+pub const MOD_ALT: u8 = 1 << 1;
+pub const MOD_CTRL: u8 = 1 << 2;
+pub const MOD_SUPER: u8 = 1 << 3;
+pub const MOD_HYPER: u8 = 1 << 4;
+pub const MOD_META: u8 = 1 << 5;
+
+/// Kitty keyboard progressive enhancement flags.
+pub const FLAG_DISAMBIGUATE: u8 = 1 << 0;
+pub const FLAG_REPORT_EVENT_TYPES: u8 = 1 << 1;
+pub const FLAG_REPORT_ALTERNATE_KEYS: u8 = 1 << 2;
+pub const FLAG_REPORT_ALL_KEYS_AS_ESC: u8 = 1 << 3;
+pub const FLAG_REPORT_ASSOCIATED_TEXT: u8 = 1 << 4;
+
+/// Kind of key event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEventKind {
+    Press,
+    Repeat,
+    Release,
+}
+
+impl KeyEventKind {
+    /// Decode the FFI representation: 1 = repeat, 2 = release, else press.
+    pub fn from_ffi(kind: u8) -> Self {
+        match kind {
+            1 => Self::Repeat,
+            2 => Self::Release,
+            _ => Self::Press,
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            Self::Press => 1,
+            Self::Repeat => 2,
+            Self::Release => 3,
+        }
+    }
+}
+
+/// A key as seen by the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TermKey {
+    /// A key producing a character, unshifted (`a`, not `A`).
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    /// Function key F1-F35.
+    F(u8),
+}
+
+impl TermKey {
+    /// Parse an Emacs key name (`return`, `prior`, `f5`, ...) or a single
+    /// character.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Some(Self::Char(c));
+        }
+        Some(match name {
+            "return" | "kp-enter" => Self::Enter,
+            "tab" => Self::Tab,
+            "backspace" => Self::Backspace,
+            "escape" => Self::Escape,
+            "up" => Self::Up,
+            "down" => Self::Down,
+            "left" => Self::Left,
+            "right" => Self::Right,
+            "home" => Self::Home,
+            "end" => Self::End,
+            "prior" => Self::PageUp,
+            "next" => Self::PageDown,
+            "insert" => Self::Insert,
+            "delete" | "deletechar" => Self::Delete,
+            _ => {
+                let n: u8 = name.strip_prefix('f')?.parse().ok()?;
+                if !(1..=35).contains(&n) {
+                    return None;
+                }
+                Self::F(n)
+            }
+        })
+    }
+
+    /// Canonical form of KEY with MODS: control characters become their
+    /// named key or Ctrl+letter, and an uppercase letter becomes
+    /// Shift+lowercase.
+    fn normalize(self, mods: u8) -> (Self, u8) {
+        match self {
+            Self::Char('\r') => (Self::Enter, mods),
+            Self::Char('\t') => (Self::Tab, mods),
+            Self::Char('\x1b') => (Self::Escape, mods),
+            Self::Char('\x7f') => (Self::Backspace, mods),
+            Self::Char('\0') => (Self::Char(' '), mods | MOD_CTRL),
+            Self::Char(c @ '\x01'..='\x1a') => (Self::Char((c as u8 + 0x60) as char), mods | MOD_CTRL),
+            Self::Char(c) if c.is_uppercase() => match single_case(c.to_lowercase()) {
+                Some(lower) if lower != c => (Self::Char(lower), mods | MOD_SHIFT),
+                _ => (self, mods),
+            },
+            _ => (self, mods),
+        }
+    }
+
+    /// The character a normalized key types with MODS, if it types one.
+    fn text(self, mods: u8) -> Option<char> {
+        let Self::Char(c) = self else {
+            return None;
+        };
+        if mods & !MOD_SHIFT != 0 {
+            return None;
+        }
+        Some(shifted(c, mods))
+    }
+
+    /// Number and final byte of the key's `CSI number ; mods final`
+    /// sequence.
+    fn csi(self) -> (u32, char) {
+        match self {
+            Self::Char(c) => (c as u32, 'u'),
+            Self::Enter => (13, 'u'),
+            Self::Tab => (9, 'u'),
+            Self::Backspace => (127, 'u'),
+            Self::Escape => (27, 'u'),
+            Self::Up => (1, 'A'),
+            Self::Down => (1, 'B'),
+            Self::Right => (1, 'C'),
+            Self::Left => (1, 'D'),
+            Self::Home => (1, 'H'),
+            Self::End => (1, 'F'),
+            Self::Insert => (2, '~'),
+            Self::Delete => (3, '~'),
+            Self::PageUp => (5, '~'),
+            Self::PageDown => (6, '~'),
+            Self::F(1) => (1, 'P'),
+            Self::F(2) => (1, 'Q'),
+            Self::F(3) => (13, '~'),
+            Self::F(4) => (1, 'S'),
+            Self::F(n @ 5..=12) => ([15, 17, 18, 19, 20, 21, 23, 24][n as usize - 5], '~'),
+            // F13 and up live in kitty's private use area
+            Self::F(n) => (57376 + n as u32 - 13, 'u'),
+        }
+    }
+}
+
+fn single_case(mut chars: impl Iterator<Item = char>) -> Option<char> {
+    let c = chars.next()?;
+    chars.next().is_none().then_some(c)
+}
+
+/// C as typed with MODS' shift.
+fn shifted(c: char, mods: u8) -> char {
+    if mods & MOD_SHIFT != 0 {
+        single_case(c.to_uppercase()).unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+/// Kitty keyboard flags enabled in terminal MODE.
+pub fn flags_from_mode(mode: TermMode) -> u8 {
+    [
+        (TermMode::DISAMBIGUATE_ESC_CODES, FLAG_DISAMBIGUATE),
+        (TermMode::REPORT_EVENT_TYPES, FLAG_REPORT_EVENT_TYPES),
+        (TermMode::REPORT_ALTERNATE_KEYS, FLAG_REPORT_ALTERNATE_KEYS),
+        (TermMode::REPORT_ALL_KEYS_AS_ESC, FLAG_REPORT_ALL_KEYS_AS_ESC),
+        (TermMode::REPORT_ASSOCIATED_TEXT, FLAG_REPORT_ASSOCIATED_TEXT),
+    ]
+    .iter()
+    .filter(|(m, _)| mode.contains(*m))
+    .fold(0, |flags, (_, f)| flags | f)
+}
+
+/// Bytes to send for KEY with MODS (`MOD_*` bits) under the kitty
+/// FLAGS.  APP_CURSOR is DECCKM (application cursor keys).  Returns
+/// `None` if the event is not reported, e.g. releases without
+/// `FLAG_REPORT_EVENT_TYPES`.
+pub fn encode_key(key: TermKey, mods: u8, kind: KeyEventKind, flags: u8, app_cursor: bool) -> Option<Vec<u8>> {
+    let (key, mods) = key.normalize(mods);
+    let all_as_esc = flags & FLAG_REPORT_ALL_KEYS_AS_ESC != 0;
+    if flags == 0 {
+        return (kind != KeyEventKind::Release).then(|| legacy(key, mods, app_cursor));
+    }
+    // Without "all keys as escape codes", text keys still send their
+    // text, and so do unmodified Enter, Tab and Backspace so a shell stays
+    // usable after a program exits without resetting the flags.
+    // Unmodified cursor and function keys keep their legacy form, though
+    // repeats and releases need the escape code to carry the event type.
+    if !all_as_esc {
+        let legacy_form = match key {
+            TermKey::Char(_) => key.text(mods).is_some(),
+            TermKey::Enter | TermKey::Tab | TermKey::Backspace => mods == 0,
+            TermKey::Escape => false,
+            _ => mods == 0 && kind == KeyEventKind::Press,
+        };
+        if legacy_form {
+            return match key {
+                TermKey::Char(_) | TermKey::Enter | TermKey::Tab | TermKey::Backspace if kind == KeyEventKind::Release => None,
+                _ => Some(legacy(key, mods, app_cursor)),
+            };
+        }
+    }
+    if kind != KeyEventKind::Press && flags & FLAG_REPORT_EVENT_TYPES == 0 {
+        // A repeat is just another press; releases are not reported
+        return (kind == KeyEventKind::Repeat).then(|| encode_key(key, mods, KeyEventKind::Press, flags, app_cursor)).flatten();
+    }
+
+    let (number, last) = key.csi();
+    let mut fields = vec![number.to_string()];
+    if flags & FLAG_REPORT_ALTERNATE_KEYS != 0 && mods & MOD_SHIFT != 0 {
+        if let TermKey::Char(c) = key {
+            let shifted = shifted(c, mods);
+            if shifted != c {
+                fields[0] = format!("{}:{}", number, shifted as u32);
+            }
+        }
+    }
+    let event = kind.code();
+    let mods_field = match (mods, event) {
+        (0, 1) => String::new(),
+        (_, 1) => (mods as u32 + 1).to_string(),
+        _ => format!("{}:{}", mods as u32 + 1, event),
+    };
+    let text = key.text(mods).filter(|_| {
+        all_as_esc && flags & FLAG_REPORT_ASSOCIATED_TEXT != 0 && kind != KeyEventKind::Release
+    });
+    if let Some(t) = text {
+        fields.push(if mods_field.is_empty() { "1".to_string() } else { mods_field });
+        fields.push((t as u32).to_string());
+    } else if !mods_field.is_empty() {
+        fields.push(mods_field);
+    }
+    // `CSI 1 A` is just `CSI A`
+    if last != 'u' && last != '~' && fields.len() == 1 {
+        fields.clear();
+    }
+    Some(format!("\x1b[{}{}", fields.join(";"), last).into_bytes())
+}
+
+/// Legacy xterm encoding of a normalized key.
+fn legacy(key: TermKey, mods: u8, app_cursor: bool) -> Vec<u8> {
+    let alt = mods & (MOD_ALT | MOD_META) != 0;
+    let ctrl = mods & MOD_CTRL != 0;
+    let mut out = Vec::new();
+    let push_char = |out: &mut Vec<u8>, c: char| {
+        let mut buf = [0u8; 4];
+        out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    };
+    match key {
+        TermKey::Char(c) => {
+            if alt {
+                out.push(0x1b);
+            }
+            let c = shifted(c, mods);
+            match c {
+                _ if !ctrl => push_char(&mut out, c),
+                'a'..='z' | 'A'..='Z' | '@' | '[' | '\\' | ']' | '^' | '_' => out.push(c as u8 & 0x1f),
+                ' ' | '2' => out.push(0),
+                '/' => out.push(0x1f),
+                '?' => out.push(0x7f),
+                _ => push_char(&mut out, c),
+            }
+        }
+        TermKey::Enter | TermKey::Backspace | TermKey::Escape | TermKey::Tab => {
+            if key == TermKey::Tab && mods & MOD_SHIFT != 0 {
+                return b"\x1b[Z".to_vec();
+            }
+            if alt {
+                out.push(0x1b);
+            }
+            out.push(match key {
+                TermKey::Enter => b'\r',
+                TermKey::Tab => b'\t',
+                TermKey::Escape => 0x1b,
+                _ if ctrl => 0x08,
+                _ => 0x7f,
+            });
+        }
+        // xterm sends F13-F24 as shifted F1-F12
+        TermKey::F(n @ 13..=24) => return legacy(TermKey::F(n - 12), mods | MOD_SHIFT, app_cursor),
+        TermKey::F(n) if n > 24 => {}
+        _ => {
+            let (number, last) = match key {
+                TermKey::F(3) => (1, 'R'),
+                _ => key.csi(),
+            };
+            let m = mods & !MOD_META | if mods & MOD_META != 0 { MOD_ALT } else { 0 };
+            let ss3 = matches!(key, TermKey::F(1..=4))
+                || (app_cursor && matches!(key, TermKey::Up | TermKey::Down | TermKey::Left | TermKey::Right | TermKey::Home | TermKey::End));
+            let s = if m != 0 {
+                format!("\x1b[{};{}{}", number, m + 1, last)
+            } else if ss3 {
+                format!("\x1bO{}", last)
+            } else if last == '~' {
+                format!("\x1b[{}~", number)
+            } else {
+                format!("\x1b[{}", last)
+            };
+            out.extend_from_slice(s.as_bytes());
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enc(key: TermKey, mods: u8, kind: KeyEventKind, flags: u8) -> Option<String> {
+        encode_key(key, mods, kind, flags, false).map(|b| String::from_utf8(b).unwrap())
+    }
+
+    fn press(key: TermKey, mods: u8, flags: u8) -> String {
+        enc(key, mods, KeyEventKind::Press, flags).unwrap()
+    }
+
+    #[test]
+    fn names_parse() {
+        assert_eq!(TermKey::from_name("a"), Some(TermKey::Char('a')));
+        assert_eq!(TermKey::from_name("é"), Some(TermKey::Char('é')));
+        assert_eq!(TermKey::from_name("prior"), Some(TermKey::PageUp));
+        assert_eq!(TermKey::from_name("f12"), Some(TermKey::F(12)));
+        assert_eq!(TermKey::from_name("f36"), None);
+        assert_eq!(TermKey::from_name("mouse-1"), None);
+    }
+
+    #[test]
+    fn legacy_encoding() {
+        assert_eq!(press(TermKey::Char('a'), 0, 0), "a");
+        assert_eq!(press(TermKey::Char('a'), MOD_SHIFT, 0), "A");
+        assert_eq!(press(TermKey::Char('a'), MOD_CTRL, 0), "\x01");
+        assert_eq!(press(TermKey::Char('x'), MOD_META, 0), "\x1bx");
+        assert_eq!(press(TermKey::Char('\x03'), 0, 0), "\x03");
+        assert_eq!(press(TermKey::Tab, MOD_SHIFT, 0), "\x1b[Z");
+        assert_eq!(press(TermKey::Up, 0, 0), "\x1b[A");
+        assert_eq!(press(TermKey::Up, MOD_CTRL, 0), "\x1b[1;5A");
+        assert_eq!(press(TermKey::F(1), 0, 0), "\x1bOP");
+        assert_eq!(press(TermKey::F(5), MOD_SHIFT, 0), "\x1b[15;2~");
+        assert_eq!(encode_key(TermKey::Up, 0, KeyEventKind::Press, 0, true), Some(b"\x1bOA".to_vec()));
+        assert_eq!(enc(TermKey::Char('a'), 0, KeyEventKind::Release, 0), None);
+    }
+
+    #[test]
+    fn disambiguate_escape_codes() {
+        let f = FLAG_DISAMBIGUATE;
+        // Text and unmodified Enter/Tab/Backspace are unchanged
+        assert_eq!(press(TermKey::Char('a'), 0, f), "a");
+        assert_eq!(press(TermKey::Char('A'), 0, f), "A");
+        assert_eq!(press(TermKey::Enter, 0, f), "\r");
+        assert_eq!(press(TermKey::Up, 0, f), "\x1b[A");
+        // Escape and modified keys become CSI u
+        assert_eq!(press(TermKey::Escape, 0, f), "\x1b[27u");
+        assert_eq!(press(TermKey::Char('a'), MOD_CTRL, f), "\x1b[97;5u");
+        assert_eq!(press(TermKey::Char('\x09'), MOD_CTRL, f), "\x1b[9;5u");
+        assert_eq!(press(TermKey::Char('i'), MOD_CTRL, f), "\x1b[105;5u");
+        assert_eq!(press(TermKey::Char('a'), MOD_CTRL | MOD_SHIFT, f), "\x1b[97;6u");
+        assert_eq!(press(TermKey::Enter, MOD_SHIFT, f), "\x1b[13;2u");
+        assert_eq!(press(TermKey::Up, MOD_ALT, f), "\x1b[1;3A");
+        assert_eq!(press(TermKey::F(5), MOD_CTRL, f), "\x1b[15;5~");
+    }
+
+    #[test]
+    fn event_types_and_releases() {
+        let f = FLAG_DISAMBIGUATE | FLAG_REPORT_EVENT_TYPES;
+        assert_eq!(enc(TermKey::Up, 0, KeyEventKind::Release, f).unwrap(), "\x1b[1;1:3A");
+        assert_eq!(enc(TermKey::Up, 0, KeyEventKind::Repeat, f).unwrap(), "\x1b[1;1:2A");
+        assert_eq!(enc(TermKey::Char('a'), MOD_CTRL, KeyEventKind::Release, f).unwrap(), "\x1b[97;5:3u");
+        // Text keys' releases need all keys as escape codes
+        assert_eq!(enc(TermKey::Char('a'), 0, KeyEventKind::Release, f), None);
+        assert_eq!(enc(TermKey::Enter, 0, KeyEventKind::Release, f), None);
+        // Without event types, releases are dropped and repeats are presses
+        assert_eq!(enc(TermKey::Escape, 0, KeyEventKind::Release, FLAG_DISAMBIGUATE), None);
+        assert_eq!(enc(TermKey::Escape, 0, KeyEventKind::Repeat, FLAG_DISAMBIGUATE).unwrap(), "\x1b[27u");
+    }
+
+    #[test]
+    fn all_keys_alternates_and_text() {
+        let f = FLAG_DISAMBIGUATE | FLAG_REPORT_ALL_KEYS_AS_ESC;
+        assert_eq!(press(TermKey::Char('a'), 0, f), "\x1b[97u");
+        assert_eq!(press(TermKey::Enter, 0, f), "\x1b[13u");
+        assert_eq!(press(TermKey::Up, 0, f), "\x1b[A");
+        assert_eq!(press(TermKey::F(13), 0, f), "\x1b[57376u");
+        let f = f | FLAG_REPORT_ALTERNATE_KEYS;
+        assert_eq!(press(TermKey::Char('A'), 0, f), "\x1b[97:65;2u");
+        let f = f | FLAG_REPORT_ASSOCIATED_TEXT | FLAG_REPORT_EVENT_TYPES;
+        assert_eq!(press(TermKey::Char('a'), 0, f), "\x1b[97;1;97u");
+        assert_eq!(press(TermKey::Char('A'), 0, f), "\x1b[97:65;2;65u");
+        assert_eq!(enc(TermKey::Char('a'), 0, KeyEventKind::Release, f).unwrap(), "\x1b[97;1:3u");
+    }
+
+    #[test]
+    fn mode_flags() {
+        let mode = TermMode::DISAMBIGUATE_ESC_CODES | TermMode::REPORT_ALL_KEYS_AS_ESC | TermMode::APP_CURSOR;
+        assert_eq!(flags_from_mode(mode), FLAG_DISAMBIGUATE | FLAG_REPORT_ALL_KEYS_AS_ESC);
+        assert_eq!(flags_from_mode(TermMode::empty()), 0);
+    }
+}
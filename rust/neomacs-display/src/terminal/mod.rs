@@ -5,6 +5,10 @@
 
 pub mod colors;
 pub mod content;
+pub mod keyboard;
+pub mod mouse;
+pub mod reflow;
+pub mod shell;
 pub mod view;
 
 pub use content::TerminalContent;
@@ -14,16 +18,38 @@ pub use view::{TerminalManager, TerminalView};
 pub type TerminalId = u32;
 
 /// Shared terminal state accessible from both Emacs and render threads.
-/// Maps terminal ID to its Arc<FairMutex<Term>> for cross-thread text extraction.
+/// Maps terminal ID to its state for cross-thread text extraction and
+/// mode queries.
 pub type SharedTerminals = std::sync::Arc<
-    std::sync::Mutex<
-        std::collections::HashMap<
-            TerminalId,
-            std::sync::Arc<parking_lot::FairMutex<alacritty_terminal::term::Term<view::NeomacsEventProxy>>>,
-        >,
-    >,
+    std::sync::Mutex<std::collections::HashMap<TerminalId, SharedTerminal>>,
 >;
 
+/// One terminal's state as shared with the Emacs thread.
+#[derive(Clone)]
+pub struct SharedTerminal {
+    pub term: std::sync::Arc<parking_lot::FairMutex<alacritty_terminal::term::Term<view::NeomacsEventProxy>>>,
+    /// X10 mouse mode, which `Term` does not track.
+    pub x10_mouse: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// Shell integration marks, which `Term` does not track either.
+    pub marks: std::sync::Arc<parking_lot::Mutex<shell::ShellMarks>>,
+}
+
+impl SharedTerminal {
+    /// Text of the output of the last finished command, if the shell
+    /// marks its commands.
+    pub fn last_output(&self) -> Option<String> {
+        let term = self.term.lock();
+        let (start, end) = self.marks.lock().last_output()?;
+        Some(content::extract_lines(&*term, start, end))
+    }
+
+    /// Mouse events the program in the terminal asked for.
+    pub fn mouse_tracking(&self) -> mouse::MouseTracking {
+        let mode = *self.term.lock().mode();
+        mouse::MouseTracking::from_mode(mode, self.x10_mouse.load(std::sync::atomic::Ordering::Relaxed))
+    }
+}
+
 /// Terminal display mode.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TerminalMode {
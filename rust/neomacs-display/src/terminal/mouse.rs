@@ -0,0 +1,349 @@
+//! Mouse reporting for neo-term.
+//!
+//! Programs ask for mouse events with the xterm private modes 9 (X10:
+//! button presses only), 1000 (normal: presses and releases), 1002
+//! (button-event: also motion while a button is held) and 1003
+//! (any-event: all motion), and choose the report format with 1006
+//! (SGR, `CSI < b ; x ; y M/m`) or 1005 (UTF-8 coordinates) over the
+//! default `CSI M b x y` bytes.  `alacritty_terminal` tracks every mode
+//! but X10, which `X10Scanner` picks out of the output stream.
+
+use alacritty_terminal::term::TermMode;
+
+use super::keyboard::{MOD_ALT, MOD_CTRL, MOD_META, MOD_SHIFT};
+
+/// Which mouse events the program in a terminal wants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseTracking {
+    Off,
+    /// Mode 9: presses only, without modifiers
+    X10,
+    /// Mode 1000: presses and releases
+    Normal,
+    /// Mode 1002: also motion while a button is held
+    ButtonEvent,
+    /// Mode 1003: all motion
+    AnyEvent,
+}
+
+impl MouseTracking {
+    /// Tracking enabled by terminal MODE, with X10 from `X10Scanner`.
+    pub fn from_mode(mode: TermMode, x10: bool) -> Self {
+        if mode.contains(TermMode::MOUSE_MOTION) {
+            Self::AnyEvent
+        } else if mode.contains(TermMode::MOUSE_DRAG) {
+            Self::ButtonEvent
+        } else if mode.contains(TermMode::MOUSE_REPORT_CLICK) {
+            Self::Normal
+        } else if x10 {
+            Self::X10
+        } else {
+            Self::Off
+        }
+    }
+
+    /// FFI representation: 0 = off, 1 = X10, 2 = normal, 3 = button-event,
+    /// 4 = any-event.
+    pub fn to_ffi(self) -> i32 {
+        match self {
+            Self::Off => 0,
+            Self::X10 => 1,
+            Self::Normal => 2,
+            Self::ButtonEvent => 3,
+            Self::AnyEvent => 4,
+        }
+    }
+}
+
+/// A mouse button or wheel direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+    WheelLeft,
+    WheelRight,
+}
+
+impl MouseButton {
+    /// Decode the FFI representation: 1-3 = left, middle, right;
+    /// 4-7 = wheel up, down, left, right; else none.
+    pub fn from_ffi(button: u8) -> Option<Self> {
+        Some(match button {
+            1 => Self::Left,
+            2 => Self::Middle,
+            3 => Self::Right,
+            4 => Self::WheelUp,
+            5 => Self::WheelDown,
+            6 => Self::WheelLeft,
+            7 => Self::WheelRight,
+            _ => return None,
+        })
+    }
+
+    pub fn is_wheel(self) -> bool {
+        !matches!(self, Self::Left | Self::Middle | Self::Right)
+    }
+
+    fn code(self) -> u32 {
+        match self {
+            Self::Left => 0,
+            Self::Middle => 1,
+            Self::Right => 2,
+            Self::WheelUp => 64,
+            Self::WheelDown => 65,
+            Self::WheelLeft => 66,
+            Self::WheelRight => 67,
+        }
+    }
+}
+
+/// Kind of mouse event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseEventKind {
+    Press,
+    Release,
+    Motion,
+}
+
+impl MouseEventKind {
+    /// Decode the FFI representation: 1 = release, 2 = motion, else press.
+    pub fn from_ffi(kind: u8) -> Self {
+        match kind {
+            1 => Self::Release,
+            2 => Self::Motion,
+            _ => Self::Press,
+        }
+    }
+}
+
+/// Report format chosen by the program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MouseFormat {
+    /// Mode 1006
+    pub sgr: bool,
+    /// Mode 1005
+    pub utf8: bool,
+}
+
+impl MouseFormat {
+    pub fn from_mode(mode: TermMode) -> Self {
+        Self {
+            sgr: mode.contains(TermMode::SGR_MOUSE),
+            utf8: mode.contains(TermMode::UTF8_MOUSE),
+        }
+    }
+}
+
+/// Bytes reporting a mouse event at 0-based cell (COL, ROW) with MODS
+/// (`keyboard::MOD_*` bits).  BUTTON is the button pressed or released,
+/// or for motion the button held, if any.  Returns `None` if TRACKING
+/// does not report the event or the position cannot be encoded.
+pub fn encode_mouse(
+    tracking: MouseTracking,
+    format: MouseFormat,
+    button: Option<MouseButton>,
+    kind: MouseEventKind,
+    col: u32,
+    row: u32,
+    mods: u8,
+) -> Option<Vec<u8>> {
+    let reported = match (tracking, kind) {
+        (MouseTracking::Off, _) => false,
+        (MouseTracking::X10, MouseEventKind::Press) => button.is_some_and(|b| !b.is_wheel()),
+        (MouseTracking::X10, _) => false,
+        (_, MouseEventKind::Press) => button.is_some(),
+        // Wheel "buttons" are never released
+        (_, MouseEventKind::Release) => button.is_some_and(|b| !b.is_wheel()),
+        (MouseTracking::Normal, MouseEventKind::Motion) => false,
+        (MouseTracking::ButtonEvent, MouseEventKind::Motion) => button.is_some(),
+        (MouseTracking::AnyEvent, MouseEventKind::Motion) => true,
+    };
+    if !reported {
+        return None;
+    }
+
+    let mut code = match (kind, button) {
+        // Legacy releases do not say which button
+        (MouseEventKind::Release, _) if !format.sgr => 3,
+        (_, Some(b)) => b.code(),
+        (_, None) => 3,
+    };
+    if kind == MouseEventKind::Motion {
+        code += 32;
+    }
+    if tracking != MouseTracking::X10 {
+        if mods & MOD_SHIFT != 0 {
+            code += 4;
+        }
+        if mods & (MOD_ALT | MOD_META) != 0 {
+            code += 8;
+        }
+        if mods & MOD_CTRL != 0 {
+            code += 16;
+        }
+    }
+
+    if format.sgr {
+        let last = if kind == MouseEventKind::Release { 'm' } else { 'M' };
+        return Some(format!("\x1b[<{};{};{}{}", code, col + 1, row + 1, last).into_bytes());
+    }
+    let mut out = b"\x1b[M".to_vec();
+    out.push(32 + code as u8);
+    for v in [col, row] {
+        let v = 32 + v + 1;
+        if format.utf8 {
+            let c = char::from_u32(v).filter(|_| v < 2048)?;
+            let mut buf = [0u8; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        } else {
+            out.push(u8::try_from(v).ok()?);
+        }
+    }
+    Some(out)
+}
+
+/// Watches terminal output for `CSI ? 9 h` / `CSI ? 9 l` (X10 mouse
+/// mode), which `alacritty_terminal` ignores.  Sequences may be split
+/// across reads.
+#[derive(Debug, Default)]
+pub struct X10Scanner {
+    state: ScanState,
+    params: Vec<u32>,
+    current: Option<u32>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ScanState {
+    #[default]
+    Ground,
+    Escape,
+    Csi,
+    Private,
+}
+
+impl X10Scanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scan BYTES.  Returns the X10 mode set by the last sequence in
+    /// them that changed it, if any.
+    pub fn feed(&mut self, bytes: &[u8]) -> Option<bool> {
+        let mut result = None;
+        for &b in bytes {
+            self.state = match (self.state, b) {
+                (_, 0x1b) => ScanState::Escape,
+                (ScanState::Escape, b'[') => {
+                    self.params.clear();
+                    self.current = None;
+                    ScanState::Csi
+                }
+                // RIS resets all modes
+                (ScanState::Escape, b'c') => {
+                    result = Some(false);
+                    ScanState::Ground
+                }
+                (ScanState::Csi, b'?') => ScanState::Private,
+                (ScanState::Private, b'0'..=b'9') => {
+                    let d = (b - b'0') as u32;
+                    self.current = Some(self.current.unwrap_or(0).saturating_mul(10).saturating_add(d));
+                    ScanState::Private
+                }
+                (ScanState::Private, b';') => {
+                    self.params.push(self.current.take().unwrap_or(0));
+                    ScanState::Private
+                }
+                (ScanState::Private, b'h' | b'l') => {
+                    self.params.extend(self.current.take());
+                    if self.params.contains(&9) {
+                        result = Some(b == b'h');
+                    }
+                    ScanState::Ground
+                }
+                _ => ScanState::Ground,
+            };
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SGR: MouseFormat = MouseFormat { sgr: true, utf8: false };
+    const LEGACY: MouseFormat = MouseFormat { sgr: false, utf8: false };
+
+    fn enc(t: MouseTracking, f: MouseFormat, b: Option<MouseButton>, k: MouseEventKind, mods: u8) -> Option<Vec<u8>> {
+        encode_mouse(t, f, b, k, 4, 9, mods)
+    }
+
+    #[test]
+    fn tracking_modes_filter_events() {
+        use MouseEventKind::*;
+        let left = Some(MouseButton::Left);
+        assert_eq!(enc(MouseTracking::Off, SGR, left, Press, 0), None);
+        assert!(enc(MouseTracking::X10, SGR, left, Press, 0).is_some());
+        assert_eq!(enc(MouseTracking::X10, SGR, left, Release, 0), None);
+        assert_eq!(enc(MouseTracking::X10, SGR, Some(MouseButton::WheelUp), Press, 0), None);
+        assert!(enc(MouseTracking::Normal, SGR, left, Release, 0).is_some());
+        assert_eq!(enc(MouseTracking::Normal, SGR, left, Motion, 0), None);
+        assert_eq!(enc(MouseTracking::Normal, SGR, Some(MouseButton::WheelUp), Release, 0), None);
+        assert!(enc(MouseTracking::ButtonEvent, SGR, left, Motion, 0).is_some());
+        assert_eq!(enc(MouseTracking::ButtonEvent, SGR, None, Motion, 0), None);
+        assert!(enc(MouseTracking::AnyEvent, SGR, None, Motion, 0).is_some());
+    }
+
+    #[test]
+    fn sgr_encoding() {
+        use MouseEventKind::*;
+        let s = |b, k, m| String::from_utf8(enc(MouseTracking::AnyEvent, SGR, b, k, m).unwrap()).unwrap();
+        assert_eq!(s(Some(MouseButton::Left), Press, 0), "\x1b[<0;5;10M");
+        assert_eq!(s(Some(MouseButton::Right), Release, 0), "\x1b[<2;5;10m");
+        assert_eq!(s(Some(MouseButton::Left), Motion, 0), "\x1b[<32;5;10M");
+        assert_eq!(s(None, Motion, 0), "\x1b[<35;5;10M");
+        assert_eq!(s(Some(MouseButton::WheelDown), Press, MOD_CTRL), "\x1b[<81;5;10M");
+        assert_eq!(s(Some(MouseButton::Middle), Press, MOD_SHIFT | MOD_META), "\x1b[<13;5;10M");
+        // X10 drops modifiers
+        let x10 = enc(MouseTracking::X10, SGR, Some(MouseButton::Left), Press, MOD_CTRL).unwrap();
+        assert_eq!(x10, b"\x1b[<0;5;10M");
+    }
+
+    #[test]
+    fn legacy_and_utf8_encoding() {
+        use MouseEventKind::*;
+        let left = Some(MouseButton::Left);
+        assert_eq!(enc(MouseTracking::Normal, LEGACY, left, Press, 0).unwrap(), b"\x1b[M \x25\x2a");
+        assert_eq!(enc(MouseTracking::Normal, LEGACY, left, Release, 0).unwrap(), b"\x1b[M#\x25\x2a");
+        // Past column 222 legacy coordinates overflow a byte
+        let far = encode_mouse(MouseTracking::Normal, LEGACY, left, Press, 300, 0, 0);
+        assert_eq!(far, None);
+        let utf8 = MouseFormat { sgr: false, utf8: true };
+        let far = encode_mouse(MouseTracking::Normal, utf8, left, Press, 300, 0, 0).unwrap();
+        assert_eq!(far, "\x1b[M \u{14d}!".as_bytes());
+    }
+
+    #[test]
+    fn scanner_tracks_x10_mode() {
+        let mut s = X10Scanner::new();
+        assert_eq!(s.feed(b"hello\x1b[?1000h"), None);
+        assert_eq!(s.feed(b"\x1b[?1;9h"), Some(true));
+        // Split across reads
+        assert_eq!(s.feed(b"text\x1b[?"), None);
+        assert_eq!(s.feed(b"9l"), Some(false));
+        assert_eq!(s.feed(b"\x1b[9h\x1b[?19h"), None);
+        assert_eq!(s.feed(b"\x1b[?9h\x1bc"), Some(false));
+    }
+
+    #[test]
+    fn tracking_from_mode() {
+        let mode = TermMode::MOUSE_REPORT_CLICK | TermMode::MOUSE_DRAG;
+        assert_eq!(MouseTracking::from_mode(mode, true), MouseTracking::ButtonEvent);
+        assert_eq!(MouseTracking::from_mode(TermMode::empty(), true), MouseTracking::X10);
+        assert_eq!(MouseTracking::from_mode(TermMode::empty(), false), MouseTracking::Off);
+        assert!(MouseFormat::from_mode(TermMode::SGR_MOUSE).sgr);
+    }
+}
@@ -0,0 +1,192 @@
+//! Keeping positions on the same text when a terminal is resized.
+//!
+//! `Term::resize` rewraps the scrollback and screen of the primary grid
+//! to the new width and scrolls lines into or out of the history when
+//! the height changes, keeping the cursor on its text.  Grid positions
+//! held outside the grid (the shell marks) or dropped by alacritty on a
+//! width change (the selection) would then point at other text.
+//! `ReflowAnchor` records them as logical (unwrapped) lines counted from
+//! the cursor's, plus a cell offset into the logical line, which reflow
+//! does not change, and maps them back after the resize.
+
+use alacritty_terminal::event::EventListener;
+use alacritty_terminal::grid::Dimensions;
+use alacritty_terminal::index::{Column, Line, Point, Side};
+use alacritty_terminal::selection::{Selection, SelectionType};
+use alacritty_terminal::term::cell::Flags as CellFlags;
+use alacritty_terminal::term::{Term, TermMode};
+
+use super::shell::ShellMarks;
+
+/// A position relative to the cursor's logical line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogicalPoint {
+    /// Logical lines after the cursor's (negative: before)
+    pub line: i64,
+    /// Cells from the start of the logical line
+    pub offset: usize,
+}
+
+/// The logical line layout of a grid.
+#[derive(Debug)]
+pub struct ReflowAnchor {
+    /// Grid lines starting a logical line, top to bottom
+    starts: Vec<i64>,
+    /// Index in `starts` of the cursor's logical line
+    cursor: usize,
+    columns: usize,
+    /// One past the last grid line
+    bottom: i64,
+}
+
+impl ReflowAnchor {
+    pub fn capture<T: EventListener>(term: &Term<T>) -> Self {
+        let grid = term.grid();
+        let columns = grid.columns();
+        let top = -(grid.history_size() as i64);
+        let bottom = grid.screen_lines() as i64;
+        let last = Column(columns - 1);
+        let mut starts = Vec::new();
+        let mut wrapped = false;
+        for l in top..bottom {
+            if !wrapped {
+                starts.push(l);
+            }
+            wrapped = grid[Line(l as i32)][last].flags.contains(CellFlags::WRAPLINE);
+        }
+        let cursor_line = grid.cursor.point.line.0 as i64;
+        let cursor = starts.partition_point(|&s| s <= cursor_line).saturating_sub(1);
+        Self { starts, cursor, columns, bottom }
+    }
+
+    /// Logical position of POINT.
+    pub fn to_logical(&self, point: Point) -> LogicalPoint {
+        let l = point.line.0 as i64;
+        let idx = self.starts.partition_point(|&s| s <= l).saturating_sub(1);
+        let row = (l - self.starts.get(idx).copied().unwrap_or(l)).max(0) as usize;
+        LogicalPoint {
+            line: idx as i64 - self.cursor as i64,
+            offset: row * self.columns + point.column.0,
+        }
+    }
+
+    /// Grid point of logical position P, or None if its line is gone.
+    /// Offsets past the end of a logical line clamp to its last row.
+    pub fn to_point(&self, p: LogicalPoint) -> Option<Point> {
+        let idx = usize::try_from(self.cursor as i64 + p.line).ok()?;
+        let start = *self.starts.get(idx)?;
+        let end = self.starts.get(idx + 1).copied().unwrap_or(self.bottom);
+        let row = ((p.offset / self.columns) as i64).min(end - start - 1);
+        Some(Point::new(Line((start + row) as i32), Column(p.offset % self.columns)))
+    }
+}
+
+/// Resize TERM to SIZE, keeping the selection and the shell MARKS on
+/// the same text.  On the alternate screen, which does not reflow, the
+/// marks (which belong to the primary screen) are left alone.
+pub fn resize_anchored<T: EventListener, S: Dimensions>(
+    term: &mut Term<T>,
+    size: S,
+    marks: &mut ShellMarks,
+) {
+    if term.columns() == size.columns() && term.screen_lines() == size.screen_lines() {
+        return;
+    }
+    let alt = term.mode().contains(TermMode::ALT_SCREEN);
+    let before = ReflowAnchor::capture(term);
+    let selection = term.selection.as_ref().and_then(|s| s.to_range(term)).map(|r| {
+        (before.to_logical(r.start), before.to_logical(r.end), r.is_block)
+    });
+    let mark_lines: Vec<Option<LogicalPoint>> = if alt {
+        Vec::new()
+    } else {
+        marks.grid_lines().map(|l| Some(before.to_logical(Point::new(Line(l as i32), Column(0))))).collect()
+    };
+
+    term.resize(size);
+
+    let after = ReflowAnchor::capture(term);
+    if !alt {
+        let mut lines = mark_lines.into_iter();
+        marks.remap(|_| {
+            let p = lines.next().flatten()?;
+            after.to_point(p).map(|p| p.line.0 as i64)
+        });
+    }
+    term.selection = selection.and_then(|(start, end, is_block)| {
+        let ty = if is_block { SelectionType::Block } else { SelectionType::Simple };
+        let mut sel = Selection::new(ty, after.to_point(start)?, Side::Left);
+        sel.update(after.to_point(end)?, Side::Right);
+        Some(sel)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::shell::ShellMark;
+    use alacritty_terminal::term::Config;
+    use alacritty_terminal::vte::ansi;
+
+    struct Size(usize, usize);
+
+    impl Dimensions for Size {
+        fn total_lines(&self) -> usize {
+            self.1
+        }
+        fn screen_lines(&self) -> usize {
+            self.1
+        }
+        fn columns(&self) -> usize {
+            self.0
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    struct NoEvents;
+    impl EventListener for NoEvents {}
+
+    fn term(cols: usize, lines: usize, data: &[u8]) -> Term<NoEvents> {
+        let mut term = Term::new(Config::default(), &Size(cols, lines), NoEvents);
+        let mut processor: ansi::Processor = ansi::Processor::new();
+        processor.advance(&mut term, data);
+        term
+    }
+
+    #[test]
+    fn logical_points_survive_rewrap() {
+        // "abcdefghij" wraps onto two 5-column rows, then "xy"
+        let t = term(5, 4, b"abcdefghij\r\nxy");
+        let a = ReflowAnchor::capture(&t);
+        let j = a.to_logical(Point::new(Line(1), Column(4)));
+        assert_eq!(j, LogicalPoint { line: -1, offset: 9 });
+        assert_eq!(a.to_logical(Point::new(Line(2), Column(1))), LogicalPoint { line: 0, offset: 1 });
+
+        let wide = term(10, 4, b"abcdefghij\r\nxy");
+        let b = ReflowAnchor::capture(&wide);
+        assert_eq!(b.to_point(j), Some(Point::new(Line(0), Column(9))));
+        assert_eq!(b.to_point(LogicalPoint { line: -5, offset: 0 }), None);
+    }
+
+    #[test]
+    fn resize_keeps_marks_and_selection() {
+        let mut t = term(5, 4, b"");
+        let mut marks = ShellMarks::new(Config::default().scrolling_history);
+        let mut processor: ansi::Processor = ansi::Processor::new();
+        processor.advance(&mut t, b"$ ls\r\nabcdefghij\r\n");
+        marks.mark(ShellMark::PromptStart, 0);
+        marks.mark(ShellMark::OutputStart, 1);
+        marks.mark(ShellMark::CommandEnd(Some(0)), 3);
+        marks.mark(ShellMark::PromptStart, 3);
+        let mut sel = Selection::new(SelectionType::Simple, Point::new(Line(2), Column(0)), Side::Left);
+        sel.update(Point::new(Line(2), Column(4)), Side::Right);
+        t.selection = Some(sel);
+
+        // Widening unwraps "abcdefghij" onto one line
+        resize_anchored(&mut t, Size(10, 4), &mut marks);
+        assert_eq!(marks.grid_lines().collect::<Vec<_>>(), vec![0, 1, 2, 2]);
+        let range = t.selection.as_ref().unwrap().to_range(&t).unwrap();
+        assert_eq!((range.start, range.end), (Point::new(Line(1), Column(5)), Point::new(Line(1), Column(9))));
+        assert_eq!(t.grid().cursor.point.line, Line(2));
+    }
+}
@@ -0,0 +1,344 @@
+//! Shell integration: prompt and command boundaries (OSC 133).
+//!
+//! Shells set up for it mark their output with `OSC 133 ; A` where a
+//! prompt starts, `B` where the command line starts (after the prompt),
+//! `C` where the command's output starts and `D [; exit-code]` when the
+//! command finished.  `alacritty_terminal` ignores these, so
+//! `Osc133Scanner` picks them out of the output stream; the PTY reader
+//! feeds the terminal up to each mark and records it in `ShellMarks` at
+//! the cursor line.
+//!
+//! Lines are numbered from the first line the terminal showed, so marks
+//! stay put as output scrolls: `ShellMarks` counts the lines scrolled
+//! off the top of the screen and converts to grid lines (0 = top of the
+//! screen, negative = scrollback) on demand.
+
+use alacritty_terminal::event::EventListener;
+use alacritty_terminal::grid::Dimensions;
+use alacritty_terminal::term::{Term, TermMode};
+
+/// A shell integration mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellMark {
+    /// `A`: a prompt starts
+    PromptStart,
+    /// `B`: the prompt ended and the command line starts
+    CommandStart,
+    /// `C`: the command runs and its output starts
+    OutputStart,
+    /// `D`: the command finished, with its exit code if given
+    CommandEnd(Option<i32>),
+}
+
+/// Watches terminal output for `OSC 133` marks.  Sequences may be split
+/// across reads.
+#[derive(Debug, Default)]
+pub struct Osc133Scanner {
+    state: OscState,
+    /// OSC payload so far, while it may still be an `OSC 133`
+    payload: Vec<u8>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum OscState {
+    #[default]
+    Ground,
+    Escape,
+    Osc,
+    /// ESC inside an OSC, possibly starting the ST terminator
+    OscEscape,
+}
+
+/// Longest OSC payload kept; `133;D;<exit code>` and options fit.
+const MAX_PAYLOAD: usize = 64;
+
+impl Osc133Scanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scan BYTES.  Returns each mark in them with the offset just past
+    /// its terminator.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<(usize, ShellMark)> {
+        let mut marks = Vec::new();
+        for (i, &b) in bytes.iter().enumerate() {
+            self.state = match (self.state, b) {
+                (OscState::Osc, 0x07) | (OscState::OscEscape, b'\\') => {
+                    if let Some(mark) = self.parse() {
+                        marks.push((i + 1, mark));
+                    }
+                    OscState::Ground
+                }
+                (OscState::Osc, 0x1b) => OscState::OscEscape,
+                (OscState::Osc, _) => {
+                    if self.payload.len() <= MAX_PAYLOAD {
+                        self.payload.push(b);
+                    }
+                    OscState::Osc
+                }
+                (_, 0x1b) => OscState::Escape,
+                (OscState::Escape | OscState::OscEscape, b']') => {
+                    self.payload.clear();
+                    OscState::Osc
+                }
+                _ => OscState::Ground,
+            };
+        }
+        marks
+    }
+
+    fn parse(&self) -> Option<ShellMark> {
+        let rest = self.payload.strip_prefix(b"133;")?;
+        let mut fields = rest.split(|&b| b == b';');
+        Some(match fields.next()? {
+            b"A" => ShellMark::PromptStart,
+            b"B" => ShellMark::CommandStart,
+            b"C" => ShellMark::OutputStart,
+            b"D" => ShellMark::CommandEnd(
+                fields.next().and_then(|f| std::str::from_utf8(f).ok()?.parse().ok()),
+            ),
+            _ => return None,
+        })
+    }
+}
+
+/// Where a terminal's grid stands, to tell how far output scrolled it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GridState {
+    /// Lines of scrollback
+    pub history: usize,
+    /// Screen line of the cursor
+    pub cursor_line: i32,
+    pub alt_screen: bool,
+}
+
+impl GridState {
+    pub fn of<T: EventListener>(term: &Term<T>) -> Self {
+        let grid = term.grid();
+        Self {
+            history: grid.history_size(),
+            cursor_line: grid.cursor.point.line.0,
+            alt_screen: term.mode().contains(TermMode::ALT_SCREEN),
+        }
+    }
+}
+
+/// One command, as absolute lines (see the module documentation).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandBlock {
+    pub prompt: i64,
+    pub command: Option<i64>,
+    pub output: Option<i64>,
+    /// Line after the last line of output, once the command finished
+    pub end: Option<i64>,
+    pub exit_code: Option<i32>,
+}
+
+/// Commands seen in a terminal, oldest first.
+#[derive(Debug)]
+pub struct ShellMarks {
+    blocks: Vec<CommandBlock>,
+    /// Lines scrolled off the top of the screen so far
+    scrolled: i64,
+    /// Scrollback limit of the terminal
+    max_history: usize,
+}
+
+impl ShellMarks {
+    pub fn new(max_history: usize) -> Self {
+        Self { blocks: Vec::new(), scrolled: 0, max_history }
+    }
+
+    /// Note that the terminal processed DATA, going from BEFORE to AFTER.
+    pub fn note_output(&mut self, data: &[u8], before: GridState, after: GridState) {
+        if before.alt_screen || after.alt_screen {
+            return;
+        }
+        let scrolled = if after.history > before.history {
+            (after.history - before.history) as i64
+        } else if after.history == self.max_history && before.history == self.max_history {
+            // Full scrollback no longer grows: count the line feeds that
+            // did not move the cursor down
+            let feeds = data.iter().filter(|&&b| b == b'\n').count() as i64;
+            (feeds - (after.cursor_line - before.cursor_line) as i64).max(0)
+        } else {
+            0
+        };
+        self.scrolled += scrolled;
+        // Forget commands whose prompt left the scrollback (or was cleared)
+        let top = self.scrolled - after.history as i64;
+        self.blocks.retain(|b| b.prompt >= top);
+    }
+
+    /// Record MARK at screen line CURSOR_LINE.
+    pub fn mark(&mut self, mark: ShellMark, cursor_line: i32) {
+        let line = self.scrolled + cursor_line as i64;
+        match mark {
+            ShellMark::PromptStart => {
+                if let Some(last) = self.blocks.last_mut() {
+                    // An interrupted command ends where the next prompt starts
+                    last.end.get_or_insert(line);
+                }
+                self.blocks.push(CommandBlock {
+                    prompt: line,
+                    command: None,
+                    output: None,
+                    end: None,
+                    exit_code: None,
+                });
+            }
+            ShellMark::CommandStart => {
+                if let Some(last) = self.blocks.last_mut() {
+                    last.command = Some(line);
+                }
+            }
+            ShellMark::OutputStart => {
+                if let Some(last) = self.blocks.last_mut() {
+                    last.output = Some(line);
+                }
+            }
+            ShellMark::CommandEnd(code) => {
+                if let Some(last) = self.blocks.last_mut() {
+                    if last.end.is_none() {
+                        last.end = Some(line);
+                        last.exit_code = code;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn blocks(&self) -> &[CommandBlock] {
+        &self.blocks
+    }
+
+    /// Grid line of absolute line LINE.
+    pub fn grid_line(&self, line: i64) -> i64 {
+        line - self.scrolled
+    }
+
+    /// Grid lines of all marks: each command's prompt, then its command,
+    /// output and end lines that are known.
+    pub fn grid_lines(&self) -> impl Iterator<Item = i64> + '_ {
+        self.blocks.iter().flat_map(|b| {
+            std::iter::once(b.prompt)
+                .chain(b.command)
+                .chain(b.output)
+                .chain(b.end)
+                .map(|l| self.grid_line(l))
+        })
+    }
+
+    /// Move the marks to other grid lines, e.g. after reflow.  F is
+    /// called with each line in the order of `grid_lines` and returns
+    /// the new grid line, or None if the line is gone; commands whose
+    /// prompt is gone are dropped.
+    pub fn remap(&mut self, mut f: impl FnMut(i64) -> Option<i64>) {
+        let scrolled = self.scrolled;
+        let mut map = |l: i64| f(l - scrolled).map(|g| g + scrolled);
+        self.blocks = std::mem::take(&mut self.blocks)
+            .into_iter()
+            .filter_map(|b| {
+                let prompt = map(b.prompt);
+                let command = b.command.and_then(&mut map);
+                let output = b.output.and_then(&mut map);
+                let end = b.end.and_then(&mut map);
+                Some(CommandBlock { prompt: prompt?, command, output, end, exit_code: b.exit_code })
+            })
+            .collect();
+    }
+
+    /// Grid lines of all prompts, oldest first.
+    pub fn prompt_lines(&self) -> impl Iterator<Item = i64> + '_ {
+        self.blocks.iter().map(|b| self.grid_line(b.prompt))
+    }
+
+    /// Grid line of the Nth prompt after grid line FROM (before it if N
+    /// is negative), or the farthest one in that direction.  None if
+    /// there is no prompt that way.
+    pub fn prompt_target(&self, from: i64, n: i32) -> Option<i64> {
+        if n < 0 {
+            let before: Vec<i64> = self.prompt_lines().filter(|&l| l < from).collect();
+            let k = (n.unsigned_abs() as usize).min(before.len());
+            (k > 0).then(|| before[before.len() - k])
+        } else {
+            self.prompt_lines().filter(|&l| l > from).take(n.max(1) as usize).last()
+        }
+    }
+
+    /// Grid line range (end exclusive) of the output of the last
+    /// finished command that had output.
+    pub fn last_output(&self) -> Option<(i64, i64)> {
+        self.blocks.iter().rev().find_map(|b| {
+            let (start, end) = (b.output?, b.end?);
+            (end > start).then(|| (self.grid_line(start), self.grid_line(end)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(history: usize, cursor_line: i32) -> GridState {
+        GridState { history, cursor_line, alt_screen: false }
+    }
+
+    #[test]
+    fn scanner_finds_marks() {
+        let mut s = Osc133Scanner::new();
+        let data = b"\x1b]133;A\x07$ \x1b]133;B\x1b\\ls\r\n\x1b]133;C\x07";
+        let marks = s.feed(data);
+        assert_eq!(
+            marks.iter().map(|m| m.1).collect::<Vec<_>>(),
+            vec![ShellMark::PromptStart, ShellMark::CommandStart, ShellMark::OutputStart]
+        );
+        assert_eq!(marks[0].0, 8);
+        assert_eq!(marks[2].0, data.len());
+        // Other OSCs and split sequences
+        assert!(s.feed(b"\x1b]0;title\x07\x1b]133;D;1").is_empty());
+        assert_eq!(s.feed(b"27\x07"), vec![(3, ShellMark::CommandEnd(Some(127)))]);
+        assert_eq!(s.feed(b"\x1b]133;D\x07"), vec![(8, ShellMark::CommandEnd(None))]);
+        assert!(s.feed(b"\x1b]133;Z\x07\x1b[133;A\x07").is_empty());
+    }
+
+    #[test]
+    fn marks_follow_scrolling() {
+        let mut m = ShellMarks::new(100);
+        m.mark(ShellMark::PromptStart, 0);
+        m.mark(ShellMark::CommandStart, 0);
+        m.mark(ShellMark::OutputStart, 1);
+        // Output scrolls the screen by 5 lines
+        m.note_output(b"", state(0, 1), state(5, 23));
+        m.mark(ShellMark::CommandEnd(Some(0)), 23);
+        m.mark(ShellMark::PromptStart, 23);
+        assert_eq!(m.prompt_lines().collect::<Vec<_>>(), vec![-5, 23]);
+        assert_eq!(m.last_output(), Some((-4, 23)));
+        assert_eq!(m.blocks()[0].exit_code, Some(0));
+        // With a full scrollback line feeds count
+        let mut full = ShellMarks::new(5);
+        full.mark(ShellMark::PromptStart, 20);
+        full.note_output(b"a\nb\nc\n", state(5, 22), state(5, 23));
+        assert_eq!(full.prompt_lines().collect::<Vec<_>>(), vec![18]);
+        // Prompts in cleared scrollback are forgotten
+        m.note_output(b"", state(5, 23), state(0, 23));
+        assert_eq!(m.prompt_lines().collect::<Vec<_>>(), vec![23]);
+    }
+
+    #[test]
+    fn prompt_navigation() {
+        let mut m = ShellMarks::new(100);
+        for line in [0, 4, 9, 15] {
+            m.mark(ShellMark::PromptStart, line);
+        }
+        assert_eq!(m.prompt_target(9, -1), Some(4));
+        assert_eq!(m.prompt_target(9, -5), Some(0));
+        assert_eq!(m.prompt_target(0, -1), None);
+        assert_eq!(m.prompt_target(4, 1), Some(9));
+        assert_eq!(m.prompt_target(4, 10), Some(15));
+        assert_eq!(m.prompt_target(15, 1), None);
+        // An interrupted command ends at the next prompt, without output
+        assert_eq!(m.blocks()[0].end, Some(4));
+        assert_eq!(m.last_output(), None);
+    }
+}
@@ -6,21 +6,28 @@
 
 use std::collections::HashMap;
 use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
-use parking_lot::FairMutex;
+use parking_lot::{FairMutex, Mutex};
 
 use alacritty_terminal::event::{Event as TermEvent, EventListener, OnResize, WindowSize};
-use alacritty_terminal::grid::Dimensions;
+use alacritty_terminal::grid::{Dimensions, Scroll};
 use alacritty_terminal::index::Column;
-use alacritty_terminal::term::{Config as TermConfig, Term};
+use alacritty_terminal::term::{Config as TermConfig, Term, TermMode};
 use alacritty_terminal::tty;
 use alacritty_terminal::tty::EventedReadWrite;
-use alacritty_terminal::vte::ansi;
+use alacritty_terminal::vte::ansi::{self, Rgb};
 
+use super::colors::TerminalPalette;
 use super::content::TerminalContent;
-use super::{TerminalId, TerminalMode};
+use super::keyboard::{self, KeyEventKind, TermKey};
+use super::mouse::{self, MouseButton, MouseEventKind, MouseFormat, MouseTracking, X10Scanner};
+use super::reflow;
+use super::shell::{GridState, Osc133Scanner, ShellMarks};
+use super::{SharedTerminal, TerminalId, TerminalMode};
 
 /// Grid dimensions for Term::new() and Term::resize().
 ///
@@ -54,6 +61,49 @@ impl Dimensions for TermGridSize {
     }
 }
 
+/// How long the bell flash takes to fade out.
+const BELL_FLASH_DURATION: Duration = Duration::from_millis(150);
+
+/// Opacity of the bell flash ELAPSED after it started: 30% fading
+/// linearly to nothing, like the frame's visual bell.
+fn bell_flash_alpha(elapsed: Duration) -> Option<f32> {
+    (elapsed < BELL_FLASH_DURATION)
+        .then(|| (1.0 - elapsed.as_secs_f32() / BELL_FLASH_DURATION.as_secs_f32()) * 0.3)
+}
+
+/// A reply to the program in the terminal, e.g. to a device status or
+/// color query.
+enum PtyReply {
+    Text(String),
+    /// Report the color at an index of `Term::colors` with the formatter.
+    Color(usize, Arc<dyn Fn(Rgb) -> String + Send + Sync>),
+}
+
+/// Feed DATA to TERM, recording the shell integration marks SCANNER
+/// finds in it at the cursor line where each occurs.
+fn advance_with_marks<T: EventListener>(
+    processor: &mut ansi::Processor,
+    term: &mut Term<T>,
+    scanner: &mut Osc133Scanner,
+    marks: &Mutex<ShellMarks>,
+    data: &[u8],
+) {
+    let mut marks = marks.lock();
+    let found = scanner.feed(data).into_iter().map(|(end, mark)| (end, Some(mark)));
+    let mut start = 0;
+    for (end, mark) in found.chain(std::iter::once((data.len(), None))) {
+        let chunk = &data[start..end];
+        let before = GridState::of(term);
+        processor.advance(term, chunk);
+        let after = GridState::of(term);
+        marks.note_output(chunk, before, after);
+        if let Some(mark) = mark.filter(|_| !after.alt_screen) {
+            marks.mark(mark, after.cursor_line);
+        }
+        start = end;
+    }
+}
+
 /// Event listener that bridges alacritty events to neomacs.
 #[derive(Clone)]
 pub struct NeomacsEventProxy {
@@ -62,6 +112,11 @@ pub struct NeomacsEventProxy {
     wakeup: Arc<std::sync::atomic::AtomicBool>,
     /// Signals that the terminal child process has exited.
     exited: Arc<std::sync::atomic::AtomicBool>,
+    /// Signals that the terminal rang the bell (BEL).
+    bell: Arc<std::sync::atomic::AtomicBool>,
+    /// Replies queued while `Term` processed PTY output.  The PTY reader
+    /// sends them once it is done, since color replies need the terminal.
+    replies: Arc<Mutex<Vec<PtyReply>>>,
 }
 
 impl NeomacsEventProxy {
@@ -70,6 +125,8 @@ impl NeomacsEventProxy {
             id,
             wakeup: Arc::new(std::sync::atomic::AtomicBool::new(false)),
             exited: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            bell: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            replies: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -87,6 +144,27 @@ impl NeomacsEventProxy {
     pub fn is_exited(&self) -> bool {
         self.exited.load(std::sync::atomic::Ordering::Relaxed)
     }
+
+    /// Check and clear the bell flag.  Bells rung since the last call
+    /// are coalesced into one.
+    pub fn take_bell(&self) -> bool {
+        self.bell.swap(false, std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Take the queued replies, resolving color queries against the
+    /// program's colors in TERM and PALETTE.
+    fn take_replies(&self, term: &Term<Self>, palette: &TerminalPalette) -> Vec<u8> {
+        let mut out = Vec::new();
+        for reply in self.replies.lock().drain(..) {
+            match reply {
+                PtyReply::Text(text) => out.extend_from_slice(text.as_bytes()),
+                PtyReply::Color(index, format) => {
+                    out.extend_from_slice(format(palette.query(index, term.colors())).as_bytes());
+                }
+            }
+        }
+        out
+    }
 }
 
 impl EventListener for NeomacsEventProxy {
@@ -100,6 +178,13 @@ impl EventListener for NeomacsEventProxy {
             }
             TermEvent::Bell => {
                 log::debug!("Terminal {}: bell", self.id);
+                self.bell.store(true, std::sync::atomic::Ordering::Relaxed);
+            }
+            TermEvent::PtyWrite(text) => {
+                self.replies.lock().push(PtyReply::Text(text));
+            }
+            TermEvent::ColorRequest(index, format) => {
+                self.replies.lock().push(PtyReply::Color(index, format));
             }
             TermEvent::Exit => {
                 log::info!("Terminal {}: child process exited", self.id);
@@ -118,6 +203,15 @@ pub struct TerminalView {
     pub term: Arc<FairMutex<Term<NeomacsEventProxy>>>,
     /// Event proxy for wakeup notifications.
     pub event_proxy: NeomacsEventProxy,
+    /// X10 mouse mode, set by the PTY reader.
+    x10_mouse: Arc<AtomicBool>,
+    /// Palette and default colors (shared with the PTY reader, which
+    /// answers color queries).
+    palette: Arc<Mutex<TerminalPalette>>,
+    /// Shell integration marks, recorded by the PTY reader.
+    marks: Arc<Mutex<ShellMarks>>,
+    /// Mouse button held down, for drag reports.
+    mouse_button: Option<MouseButton>,
     /// PTY handle - MUST be kept alive to prevent SIGHUP to child shell.
     /// Also used for on_resize() to send TIOCSWINSZ to the child.
     pty: tty::Pty,
@@ -135,6 +229,8 @@ pub struct TerminalView {
     pub float_x: f32,
     pub float_y: f32,
     pub float_opacity: f32,
+    /// When the bell flash started, while it is fading out.
+    flash_start: Option<Instant>,
 }
 
 impl TerminalView {
@@ -148,8 +244,10 @@ impl TerminalView {
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let event_proxy = NeomacsEventProxy::new(id);
 
-        // Create the terminal with our Dimensions-compatible size
-        let config = TermConfig::default();
+        // Create the terminal with our Dimensions-compatible size.
+        // Programs may opt in to the kitty keyboard protocol.
+        let config = TermConfig { kitty_keyboard: true, ..TermConfig::default() };
+        let config_history = config.scrolling_history;
         let grid_size = TermGridSize::new(cols, rows);
 
         let term = Term::new(config, &grid_size, event_proxy.clone());
@@ -188,15 +286,25 @@ impl TerminalView {
             .map_err(|e| format!("Failed to clone PTY reader: {}", e))?;
         let pty_write_file = pty.writer().try_clone()
             .map_err(|e| format!("Failed to clone PTY writer: {}", e))?;
+        let mut reply_file = pty.writer().try_clone()
+            .map_err(|e| format!("Failed to clone PTY writer: {}", e))?;
 
         // Spawn reader thread: reads from PTY, feeds into term via ansi::Processor
         let term_clone = Arc::clone(&term);
         let proxy_clone = event_proxy.clone();
+        let x10_mouse = Arc::new(AtomicBool::new(false));
+        let x10_clone = Arc::clone(&x10_mouse);
+        let palette = Arc::new(Mutex::new(TerminalPalette::default()));
+        let palette_clone = Arc::clone(&palette);
+        let marks = Arc::new(Mutex::new(ShellMarks::new(config_history)));
+        let marks_clone = Arc::clone(&marks);
         let reader_thread = thread::Builder::new()
             .name(format!("neo-term-{}-pty", id))
             .spawn(move || {
                 let mut reader = pty_read_file;
                 let mut processor: ansi::Processor = ansi::Processor::new();
+                let mut x10_scanner = X10Scanner::new();
+                let mut osc133_scanner = Osc133Scanner::new();
                 let mut buf = [0u8; 4096];
                 loop {
                     match reader.read(&mut buf) {
@@ -206,8 +314,21 @@ impl TerminalView {
                             break;
                         }
                         Ok(n) => {
+                            if let Some(on) = x10_scanner.feed(&buf[..n]) {
+                                x10_clone.store(on, Ordering::Relaxed);
+                            }
                             let mut term = term_clone.lock();
-                            processor.advance(&mut *term, &buf[..n]);
+                            advance_with_marks(
+                                &mut processor, &mut term, &mut osc133_scanner,
+                                &marks_clone, &buf[..n],
+                            );
+                            let replies = proxy_clone.take_replies(&term, &palette_clone.lock());
+                            drop(term);
+                            if !replies.is_empty() {
+                                if let Err(e) = reply_file.write_all(&replies) {
+                                    log::warn!("Terminal {} reply write error: {}", id, e);
+                                }
+                            }
                             // Signal that content changed
                             proxy_clone.send_event(TermEvent::Wakeup);
                         }
@@ -232,6 +353,10 @@ impl TerminalView {
             mode,
             term,
             event_proxy,
+            x10_mouse,
+            palette,
+            marks,
+            mouse_button: None,
             pty,
             pty_writer: Box::new(pty_write_file),
             _reader_thread: Some(reader_thread),
@@ -241,20 +366,128 @@ impl TerminalView {
             float_x: 0.0,
             float_y: 0.0,
             float_opacity: 1.0,
+            flash_start: None,
         })
     }
 
     /// Write input data to the terminal's PTY (keyboard input from user).
     pub fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        // Input brings a view scrolled back to the bottom
+        let mut term = self.term.lock();
+        if term.grid().display_offset() != 0 {
+            term.scroll_display(Scroll::Bottom);
+            self.dirty = true;
+        }
+        drop(term);
         self.pty_writer.write_all(data)?;
         self.pty_writer.flush()
     }
 
+    /// Send a key event, encoded for the keyboard protocol the program
+    /// running in the terminal asked for.  MODS are `keyboard::MOD_*` bits.
+    pub fn send_key(&mut self, key: TermKey, mods: u8, kind: KeyEventKind) -> std::io::Result<()> {
+        let (flags, app_cursor) = {
+            let term = self.term.lock();
+            let mode = *term.mode();
+            (keyboard::flags_from_mode(mode), mode.contains(TermMode::APP_CURSOR))
+        };
+        match keyboard::encode_key(key, mods, kind, flags, app_cursor) {
+            Some(bytes) => self.write(&bytes),
+            None => Ok(()),
+        }
+    }
+
+    /// The state shared with the Emacs thread.
+    pub fn shared(&self) -> SharedTerminal {
+        SharedTerminal {
+            term: Arc::clone(&self.term),
+            x10_mouse: Arc::clone(&self.x10_mouse),
+            marks: Arc::clone(&self.marks),
+        }
+    }
+
+    /// Report a mouse event at 0-based cell (COL, ROW) to the program in
+    /// the terminal, if it asked for such events.  BUTTON is ignored for
+    /// motion, which reports the button held.  MODS are
+    /// `keyboard::MOD_*` bits.
+    pub fn send_mouse(
+        &mut self,
+        button: Option<MouseButton>,
+        kind: MouseEventKind,
+        col: u32,
+        row: u32,
+        mods: u8,
+    ) -> std::io::Result<()> {
+        let button = match kind {
+            MouseEventKind::Press => {
+                if let Some(b) = button.filter(|b| !b.is_wheel()) {
+                    self.mouse_button = Some(b);
+                }
+                button
+            }
+            MouseEventKind::Release => {
+                self.mouse_button = None;
+                button
+            }
+            MouseEventKind::Motion => self.mouse_button,
+        };
+        let mode = *self.term.lock().mode();
+        let tracking = MouseTracking::from_mode(mode, self.x10_mouse.load(Ordering::Relaxed));
+        let format = MouseFormat::from_mode(mode);
+        match mouse::encode_mouse(tracking, format, button, kind, col, row, mods) {
+            Some(bytes) => self.write(&bytes),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the program in the terminal wants key release events.
+    pub fn reports_key_releases(&self) -> bool {
+        self.term.lock().mode().contains(TermMode::REPORT_EVENT_TYPES)
+    }
+
+    /// Scroll the view so that the Nth prompt after the top line (before
+    /// it if N is negative) is at the top.  Returns false if there is no
+    /// prompt that way.
+    pub fn jump_to_prompt(&mut self, n: i32) -> bool {
+        let mut term = self.term.lock();
+        let offset = term.grid().display_offset() as i64;
+        let Some(target) = self.marks.lock().prompt_target(-offset, n) else {
+            return false;
+        };
+        term.scroll_display(Scroll::Delta((-target - offset) as i32));
+        self.dirty = true;
+        true
+    }
+
+    /// Use PALETTE for colors the program has not set itself.
+    pub fn set_palette(&mut self, palette: TerminalPalette) {
+        *self.palette.lock() = palette;
+        self.dirty = true;
+    }
+
+    /// Start flashing the terminal for a bell.
+    pub fn flash(&mut self) {
+        self.flash_start = Some(Instant::now());
+    }
+
+    /// Opacity of the bell flash overlay, or None once it has faded out.
+    pub fn flash_alpha(&mut self) -> Option<f32> {
+        let alpha = bell_flash_alpha(self.flash_start?.elapsed());
+        if alpha.is_none() {
+            self.flash_start = None;
+        }
+        alpha
+    }
+
     /// Resize the terminal grid and PTY.
+    ///
+    /// Wrapped lines of the scrollback and screen are reflowed to the
+    /// new width; the cursor, selection and shell marks stay on their
+    /// text.
     pub fn resize(&mut self, cols: u16, rows: u16) {
         let grid_size = TermGridSize::new(cols, rows);
         let mut term = self.term.lock();
-        term.resize(grid_size);
+        reflow::resize_anchored(&mut term, grid_size, &mut self.marks.lock());
         drop(term);
 
         // Send TIOCSWINSZ to the PTY so the child process gets SIGWINCH
@@ -272,7 +505,15 @@ impl TerminalView {
     pub fn update_content(&mut self) -> bool {
         if self.event_proxy.take_wakeup() || self.dirty {
             let term = self.term.lock();
-            self.last_content = Some(TerminalContent::from_term(&*term));
+            let mut content = TerminalContent::from_term(&*term, &self.palette.lock());
+            // Prompt separators, except on the top row
+            let offset = term.grid().display_offset() as i64;
+            content.prompt_rows = self.marks.lock().prompt_lines()
+                .map(|l| l + offset)
+                .filter(|&r| r > 0 && r < content.rows as i64)
+                .map(|r| r as usize)
+                .collect();
+            self.last_content = Some(content);
             self.dirty = false;
             true
         } else {
@@ -382,6 +623,58 @@ impl Default for TerminalManager {
 mod tests {
     use super::*;
 
+    #[test]
+    fn bell_sets_flag_once() {
+        let proxy = NeomacsEventProxy::new(1);
+        assert!(!proxy.take_bell());
+        proxy.send_event(TermEvent::Bell);
+        proxy.send_event(TermEvent::Bell);
+        assert!(proxy.take_bell());
+        assert!(!proxy.take_bell());
+    }
+
+    #[test]
+    fn color_queries_use_palette_and_program_colors() {
+        let proxy = NeomacsEventProxy::new(1);
+        let mut term = Term::new(TermConfig::default(), &TermGridSize::new(80, 24), proxy.clone());
+        let mut processor: ansi::Processor = ansi::Processor::new();
+        let palette = TerminalPalette::new(&[], None, Some(crate::core::types::Color::from_u8(0x10, 0x20, 0x30, 255)));
+
+        processor.advance(&mut term, b"\x1b]11;?\x07");
+        assert_eq!(proxy.take_replies(&term, &palette), b"\x1b]11;rgb:1010/2020/3030\x07");
+
+        // OSC 4 sets a palette entry for this terminal only
+        processor.advance(&mut term, b"\x1b]4;1;rgb:ff/80/00\x1b\\\x1b]4;1;?\x1b\\");
+        assert_eq!(proxy.take_replies(&term, &palette), b"\x1b]4;1;rgb:ffff/8080/0000\x1b\\");
+        assert!(proxy.take_replies(&term, &palette).is_empty());
+    }
+
+    #[test]
+    fn shell_marks_follow_output() {
+        let mut term = Term::new(TermConfig::default(), &TermGridSize::new(20, 4), NeomacsEventProxy::new(1));
+        let mut processor: ansi::Processor = ansi::Processor::new();
+        let mut scanner = Osc133Scanner::new();
+        let marks = Mutex::new(ShellMarks::new(TermConfig::default().scrolling_history));
+        let mut feed = |term: &mut Term<NeomacsEventProxy>, data: &[u8]| {
+            advance_with_marks(&mut processor, term, &mut scanner, &marks, data)
+        };
+
+        feed(&mut term, b"\x1b]133;A\x07$ \x1b]133;B\x07seq 3\r\n\x1b]133;C\x07");
+        feed(&mut term, b"1\r\n2\r\n3\r\n\x1b]133;D;0\x07\x1b]133;A\x07$ ");
+        // The first prompt scrolled off the 4-line screen
+        assert_eq!(marks.lock().prompt_lines().collect::<Vec<_>>(), vec![-1, 3]);
+        let (start, end) = marks.lock().last_output().unwrap();
+        assert_eq!(super::super::content::extract_lines(&term, start, end), "1\n2\n3\n");
+    }
+
+    #[test]
+    fn bell_flash_fades_out() {
+        assert_eq!(bell_flash_alpha(Duration::ZERO), Some(0.3));
+        let mid = bell_flash_alpha(Duration::from_millis(75)).unwrap();
+        assert!((mid - 0.15).abs() < 1e-6);
+        assert_eq!(bell_flash_alpha(BELL_FLASH_DURATION), None);
+    }
+
     #[test]
     fn test_alacritty_pty_explicit_cmd() {
         use std::io::Read;
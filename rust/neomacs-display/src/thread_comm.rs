@@ -2,10 +2,13 @@
 //!
 //! Provides lock-free channels and wakeup mechanism between Emacs and render threads.
 
-use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
+use crossbeam_channel::{bounded, Receiver, Sender};
 use std::os::unix::io::RawFd;
+use std::sync::Arc;
 
-use crate::core::frame_glyphs::FrameGlyphBuffer;
+use crate::command_bus::{CommandBus, CommandSender, CommandStats};
+use crate::scene_handoff::{scene_handoff, ScenePublisher, SceneReceiver};
+use crate::query::{self, QueryClient, QueryReply};
 
 /// Input event from render thread to Emacs
 #[derive(Debug, Clone)]
@@ -39,6 +42,8 @@ pub enum InputEvent {
         modifiers: u32,
         /// True if deltas are in pixels (touchpad), false if in lines (mouse wheel)
         pixel_precise: bool,
+        /// Lines to scroll for Emacs's `event-line-count' (0 = unspecified)
+        lines: i32,
         /// Target frame for child frame hit testing (0 = parent frame)
         target_frame_id: u64,
     },
@@ -57,6 +62,9 @@ pub enum InputEvent {
         /// Emacs frame_id of the window that gained/lost focus (0 = primary)
         emacs_frame_id: u64,
     },
+    /// Monitors were connected, removed or reconfigured; the shared
+    /// monitor list is up to date
+    MonitorsChanged,
     /// WebKit view title changed
     #[cfg(feature = "wpe-webkit")]
     WebKitTitleChanged {
@@ -89,11 +97,40 @@ pub enum InputEvent {
     /// Terminal child process exited
     #[cfg(feature = "neo-term")]
     TerminalExited { id: u32 },
+    /// Terminal program rang the bell
+    #[cfg(feature = "neo-term")]
+    TerminalBell { id: u32 },
     /// Terminal title changed
     #[cfg(feature = "neo-term")]
     TerminalTitleChanged { id: u32, title: String },
     /// Popup menu selection made (index into menu items, -1 = cancelled)
     MenuSelection { index: i32 },
+    /// Character picker selection made (index into entries, -1 = cancelled)
+    CharPickerSelection { index: i32 },
+    /// Command palette selection made (index into entries, -1 = cancelled)
+    CommandPaletteSelection { index: i32 },
+    /// Color picker closed with COLOR (0xRRGGBB), -1 = cancelled, or
+    /// `COLOR_PICKER_EYEDROPPER`
+    ColorPickerSelection { color: i32 },
+    /// Agenda timeline item chosen (index into items, -1 = closed)
+    AgendaTimelineSelection { index: i32 },
+    /// Graph view node chosen (index into nodes, -1 = closed)
+    GraphViewSelection { index: i32 },
+    /// Directory view closed on file PATH, or None without choosing one
+    DirViewSelection { path: Option<String> },
+    /// Process monitor closed on process PID (-1 = without choosing one)
+    ProcessMonitorSelection { pid: i32 },
+    /// Date picker closed on DATE (YYYYMMDD), -1 = cancelled
+    DatePickerSelection { date: i32 },
+    /// Touchpad pinch ended over the text of a window: scale its text
+    /// by SCALE (Emacs snaps it to a whole font size)
+    PinchZoom {
+        x: f32,
+        y: f32,
+        scale: f32,
+        /// Target frame for child frame hit testing (0 = parent frame)
+        target_frame_id: u64,
+    },
     /// File(s) dropped onto the window
     FileDrop {
         paths: Vec<String>,
@@ -119,6 +156,76 @@ pub struct PopupMenuItem {
     pub depth: u32,
 }
 
+/// A character offered by the character picker
+#[derive(Debug, Clone)]
+pub struct CharPickerEntry {
+    /// The character (or grapheme cluster, e.g. an emoji ZWJ sequence)
+    pub text: String,
+    /// Unicode name, matched and shown for the selected entry
+    pub name: String,
+    /// Alternative names such as emoji shortcodes (":thumbsup:")
+    pub shortcodes: Vec<String>,
+}
+
+/// `InputEvent::ColorPickerSelection` value asking Emacs to pick a color
+/// from the screen and reopen the picker with it
+pub const COLOR_PICKER_EYEDROPPER: i32 = -3;
+
+/// A command offered by the command palette
+#[derive(Debug, Clone)]
+pub struct CommandPaletteEntry {
+    /// Command name, matched against the query
+    pub name: String,
+    /// Key binding shown in the right column, or empty
+    pub binding: String,
+    /// Documentation shown in the preview pane
+    pub doc: String,
+    /// Ranking bonus from recent and frequent use, added to the match
+    /// score
+    pub weight: i32,
+}
+
+/// A task shown by the agenda timeline
+#[derive(Debug, Clone)]
+pub struct AgendaTimelineItem {
+    /// Task title
+    pub label: String,
+    /// Category shown before the title, or empty
+    pub category: String,
+    /// Start and end in seconds since the epoch; a task ending where it
+    /// starts is drawn as a milestone
+    pub start: f64,
+    pub end: f64,
+    /// Bar color (sRGB 0.0-1.0), None = default
+    pub color: Option<(f32, f32, f32)>,
+    /// Finished tasks are drawn dimmed
+    pub done: bool,
+}
+
+/// What the date picker shows
+#[derive(Debug, Clone)]
+pub struct DatePickerSpec {
+    /// Selected day and today, as days since 1970-01-01
+    pub selected: i64,
+    pub today: i64,
+    /// Days drawn in the highlight color
+    pub highlighted: Vec<i64>,
+    /// Months shown side by side
+    pub months: u32,
+    /// First day of the week (0 = Sunday)
+    pub week_start: u32,
+    pub title: Option<String>,
+}
+
+/// A node shown by the graph view
+#[derive(Debug, Clone)]
+pub struct GraphNode {
+    /// Text drawn under the node
+    pub label: String,
+    /// Fill color (sRGB 0.0-1.0), None = default
+    pub color: Option<(f32, f32, f32)>,
+}
+
 /// Wrapper for effect update closures that implements Debug.
 pub struct EffectUpdater(pub Box<dyn FnOnce(&mut crate::effect_config::EffectsConfig) + Send>);
 
@@ -170,6 +277,11 @@ pub enum RenderCommand {
     },
     /// Free an image from cache
     ImageFree { id: u32 },
+    /// Set the edits of a file image (None undoes them) and decode it again
+    ImageSetEdits {
+        id: u32,
+        edits: Option<crate::core::image_edit::ImageEdits>,
+    },
     /// Create a WebKit view
     WebKitCreate { id: u32, width: u32, height: u32 },
     /// Load URL in WebKit view
@@ -208,6 +320,17 @@ pub enum RenderCommand {
     SetMouseCursor { cursor_type: i32 },
     /// Warp (move) the mouse pointer to given pixel position
     WarpMouse { x: i32, y: i32 },
+    /// Hide the mouse pointer on keyboard input until the mouse moves
+    SetPointerAutoHide { enabled: bool },
+    /// Mouse wheel and touchpad scroll settings (see render_thread/wheel.rs)
+    SetWheelConfig {
+        wheel_speed: f32,
+        wheel_reverse: bool,
+        touchpad_speed: f32,
+        touchpad_reverse: bool,
+        shift_horizontal: bool,
+        alt_page: bool,
+    },
     /// Set the window title
     SetWindowTitle { title: String },
     /// Set fullscreen mode (0=none, 1=fullscreen, 4=maximized)
@@ -261,6 +384,40 @@ pub enum RenderCommand {
     /// Set floating terminal position and opacity
     #[cfg(feature = "neo-term")]
     TerminalSetFloat { id: u32, x: f32, y: f32, opacity: f32 },
+    /// Send a key event to a terminal, encoded for its keyboard protocol
+    #[cfg(feature = "neo-term")]
+    TerminalKey {
+        id: u32,
+        key: crate::terminal::keyboard::TermKey,
+        mods: u8,
+        kind: crate::terminal::keyboard::KeyEventKind,
+    },
+    /// Report a mouse event at a 0-based cell to a terminal's program
+    #[cfg(feature = "neo-term")]
+    TerminalMouse {
+        id: u32,
+        button: Option<crate::terminal::mouse::MouseButton>,
+        kind: crate::terminal::mouse::MouseEventKind,
+        col: u32,
+        row: u32,
+        mods: u8,
+    },
+    /// Set the terminal that receives key releases (0 = none)
+    #[cfg(feature = "neo-term")]
+    TerminalFocus { id: u32 },
+    /// Flash a terminal's view for a bell
+    #[cfg(feature = "neo-term")]
+    TerminalFlash { id: u32 },
+    /// Scroll a terminal's view to the Nth next (previous if negative)
+    /// shell prompt
+    #[cfg(feature = "neo-term")]
+    TerminalJumpToPrompt { id: u32, n: i32 },
+    /// Set a terminal's palette and default colors
+    #[cfg(feature = "neo-term")]
+    TerminalSetPalette {
+        id: u32,
+        palette: Box<crate::terminal::colors::TerminalPalette>,
+    },
     /// Show a popup menu at position (x, y)
     ShowPopupMenu {
         x: f32,
@@ -273,6 +430,116 @@ pub enum RenderCommand {
     },
     /// Hide the active popup menu
     HidePopupMenu,
+    /// Replace the accessibility tree exported to screen readers (None
+    /// once accessibility is turned off)
+    UpdateAccessibility {
+        root: Option<Box<crate::layout::accessibility::AccessibleNode>>,
+    },
+    /// Show the character picker centered in the main window
+    ShowCharPicker {
+        entries: Vec<CharPickerEntry>,
+        title: Option<String>,
+        /// Panel face colors (sRGB 0.0-1.0). None = use defaults.
+        fg: Option<(f32, f32, f32)>,
+        bg: Option<(f32, f32, f32)>,
+    },
+    /// Hide the character picker
+    HideCharPicker,
+    /// Show the command palette centered in the main window
+    ShowCommandPalette {
+        entries: Vec<CommandPaletteEntry>,
+        title: Option<String>,
+        /// Panel face colors (sRGB 0.0-1.0). None = use defaults.
+        fg: Option<(f32, f32, f32)>,
+        bg: Option<(f32, f32, f32)>,
+        /// Color of matched characters. None = use default.
+        match_fg: Option<(f32, f32, f32)>,
+    },
+    /// Hide the command palette
+    HideCommandPalette,
+    /// Show the color picker centered in the main window
+    ShowColorPicker {
+        /// Color shown, and color to go back to (0xRRGGBB)
+        color: u32,
+        original: u32,
+        title: Option<String>,
+        /// Panel face colors (sRGB 0.0-1.0). None = use defaults.
+        fg: Option<(f32, f32, f32)>,
+        bg: Option<(f32, f32, f32)>,
+    },
+    /// Hide the color picker
+    HideColorPicker,
+    /// Show the agenda timeline over the main window
+    ShowAgendaTimeline {
+        items: Vec<AgendaTimelineItem>,
+        title: Option<String>,
+        /// Current time (seconds since the epoch) and the local time
+        /// zone's offset from UTC in seconds
+        now: f64,
+        utc_offset: i32,
+        /// Panel face colors (sRGB 0.0-1.0). None = use defaults.
+        fg: Option<(f32, f32, f32)>,
+        bg: Option<(f32, f32, f32)>,
+    },
+    /// Hide the agenda timeline
+    HideAgendaTimeline,
+    /// Show the graph view over the main window
+    ShowGraphView {
+        nodes: Box<[GraphNode]>,
+        /// Edges as (from, to) indices into nodes
+        edges: Box<[(usize, usize)]>,
+        /// Place the nodes in layers along the edges instead of by
+        /// forces
+        layered: bool,
+        /// Node selected at first
+        current: Option<usize>,
+        title: Option<String>,
+        /// Panel face colors (sRGB 0.0-1.0). None = use defaults.
+        fg: Option<(f32, f32, f32)>,
+        bg: Option<(f32, f32, f32)>,
+    },
+    /// Hide the graph view
+    HideGraphView,
+    /// Show the directory view over the main window
+    ShowDirView {
+        dir: String,
+        /// List hidden (dot) files too
+        show_hidden: bool,
+        /// Icon names of files as (name suffix, icon name)
+        icons: Vec<(String, String)>,
+        /// Offset of the local time zone from UTC, in seconds
+        utc_offset: i32,
+        /// Panel face colors (sRGB 0.0-1.0). None = use defaults.
+        fg: Option<(f32, f32, f32)>,
+        bg: Option<(f32, f32, f32)>,
+    },
+    /// Hide the directory view
+    HideDirView,
+    /// Show the process monitor over the main window
+    ShowProcessMonitor {
+        /// Seconds between samples
+        interval: f32,
+        /// Panel face colors (sRGB 0.0-1.0). None = use defaults.
+        fg: Option<(f32, f32, f32)>,
+        bg: Option<(f32, f32, f32)>,
+    },
+    /// Hide the process monitor
+    HideProcessMonitor,
+    /// Show the date picker centered in the main window
+    ShowDatePicker {
+        spec: Box<DatePickerSpec>,
+        /// Panel face colors (sRGB 0.0-1.0). None = use defaults.
+        fg: Option<(f32, f32, f32)>,
+        bg: Option<(f32, f32, f32)>,
+        /// Ring around today, selected day text and fill, and
+        /// highlighted day text.  None = use defaults.
+        today: Option<(f32, f32, f32)>,
+        selected_fg: Option<(f32, f32, f32)>,
+        selected_bg: Option<(f32, f32, f32)>,
+        highlight: Option<(f32, f32, f32)>,
+    },
+    /// Hide the date picker
+    HideDatePicker,
     /// Show a tooltip at position (x, y)
     ShowTooltip {
         x: f32,
@@ -287,11 +554,72 @@ pub enum RenderCommand {
     VisualBell,
     /// Request window attention (urgency hint / taskbar flash)
     RequestAttention { urgent: bool },
+    /// Withdraw a pending attention request
+    CancelAttention,
+    /// Set the window icon from encoded image data (PNG, ICO, ...);
+    /// None restores the Neomacs logo
+    SetWindowIcon { data: Option<Vec<u8>> },
     /// Update visual effect configuration.
     /// The closure modifies the shared EffectsConfig in-place.
     UpdateEffect(EffectUpdater),
     /// Toggle scroll indicators and focus ring
     SetScrollIndicators { enabled: bool },
+    /// Pulse the cursor beacon
+    CursorBeacon,
+    /// The selected window's buffer is about to be rewritten: animate the
+    /// next frame that changes it line by line
+    StartSmoothTextUpdate,
+    /// Set (or remove, if None) the background image of a window or buffer
+    SetWindowBackground {
+        target: crate::core::window_background::BackgroundTarget,
+        background: Option<crate::core::window_background::WindowBackground>,
+    },
+    /// Install (or remove, if None) a user WGSL post-processing shader
+    SetPostShader { path: Option<String> },
+    /// Set the surface present mode (vsync/tearing) and frame latency
+    SetPresentMode { mode: wgpu::PresentMode, max_frame_latency: u32 },
+    /// Watch the display settings file at PATH (None stops watching)
+    SetSettingsFile { path: Option<String> },
+    /// Draw codepoint `spec.codepoint` as the icon SPEC
+    RegisterIcon { spec: crate::core::icons::IconSpec },
+    /// Forget the icon NAME (None forgets all icons)
+    RemoveIcon { name: Option<String> },
+    /// Set the memory budgets of the renderer's caches
+    SetMemoryBudget { budget: crate::backend::wgpu::MemoryBudget },
+    /// Start a transition in the selected window on the next frame,
+    /// animating from the previous frame even if the buffer is unchanged.
+    StartWindowTransition {
+        effect: crate::core::scroll_animation::ScrollEffect,
+        duration_ms: u32,
+    },
+    /// Set the effect and duration of snapshot-based buffer transitions,
+    /// and whether they start whenever a window's buffer changes
+    SetBufferTransition {
+        effect: crate::core::buffer_transition::BufferTransitionEffect,
+        duration_ms: u32,
+        auto_detect: bool,
+    },
+    /// Snapshot the selected window before its buffer is switched
+    PrepareBufferTransition,
+    /// Snapshot all windows before the window layout is replaced
+    PrepareFrameTransition,
+    /// Animate from the last snapshot to the new contents
+    TriggerBufferTransition,
+    /// Start (or replace) timeline animation ID of a window property
+    AddTimelineAnimation {
+        id: u32,
+        animation: crate::core::timeline::KeyframeAnimation,
+    },
+    /// Cancel timeline animation ID (0 = all)
+    CancelTimelineAnimation { id: u32 },
+    /// Drive TARGET with a spring or Bezier easing curve (None restores
+    /// its default easing)
+    SetAnimationCurve {
+        target: crate::core::easing::EasingTarget,
+        easing: Option<crate::core::buffer_transition::TransitionEasing>,
+    },
+    /// Set how rendering is throttled while unfocused or occluded
+    SetBackgroundThrottle { policy: crate::render_thread::suspend::SuspendPolicy },
     /// Set custom title bar height (0 = hidden, >0 = show with given height)
     SetTitlebarHeight { height: f32 },
     /// Toggle FPS counter overlay
@@ -327,6 +655,19 @@ pub enum RenderCommand {
     DestroyWindow {
         emacs_frame_id: u64,
     },
+    /// Create an undecorated, always-on-top popup window for a top-level
+    /// Emacs frame, centered on the focused monitor
+    CreatePopupWindow {
+        emacs_frame_id: u64,
+        width: u32,
+        height: u32,
+    },
+    /// Resize a popup window, keeping it centered on its monitor
+    ResizePopupWindow {
+        emacs_frame_id: u64,
+        width: u32,
+        height: u32,
+    },
     /// Configure child frame visual style (drop shadow, rounded corners)
     SetChildFrameStyle {
         corner_radius: f32,
@@ -335,6 +676,8 @@ pub enum RenderCommand {
         shadow_offset: f32,
         shadow_opacity: f32,
     },
+    /// Answer QUERY with a `QueryReply` tagged ID (see `crate::query`)
+    Query { id: u64, query: crate::query::RenderQuery },
 }
 
 /// Wakeup pipe for signaling Emacs from render thread
@@ -389,21 +732,24 @@ impl Drop for WakeupPipe {
 }
 
 /// Channel capacities
-// Frame channel: unbounded so try_send never drops frames.
-// The render thread drains all queued frames and keeps only the latest
-// (see poll_frame()), so memory stays bounded in practice.
+// Frames do not go through a channel: see scene_handoff.
 const INPUT_CHANNEL_CAPACITY: usize = 256;
 const COMMAND_CHANNEL_CAPACITY: usize = 64;
 
 /// Communication channels between threads
 pub struct ThreadComms {
     /// Frame glyphs: Emacs → Render
-    pub frame_tx: Sender<FrameGlyphBuffer>,
-    pub frame_rx: Receiver<FrameGlyphBuffer>,
+    pub scene_publisher: ScenePublisher,
+    pub scene_receiver: SceneReceiver,
 
     /// Commands: Emacs → Render
-    pub cmd_tx: Sender<RenderCommand>,
+    pub cmd_tx: CommandSender,
     pub cmd_rx: Receiver<RenderCommand>,
+    pub cmd_stats: Arc<CommandStats>,
+
+    /// Query replies: Render → Emacs
+    pub reply_tx: Sender<QueryReply>,
+    pub reply_rx: Receiver<QueryReply>,
 
     /// Input events: Render → Emacs
     pub input_tx: Sender<InputEvent>,
@@ -416,16 +762,22 @@ pub struct ThreadComms {
 impl ThreadComms {
     /// Create new thread communication channels
     pub fn new() -> std::io::Result<Self> {
-        let (frame_tx, frame_rx) = unbounded();
+        let (scene_publisher, scene_receiver) = scene_handoff();
         let (cmd_tx, cmd_rx) = bounded(COMMAND_CHANNEL_CAPACITY);
+        let cmd_stats = Arc::new(CommandStats::default());
+        let cmd_tx = CommandSender::new(cmd_tx, cmd_stats.clone());
+        let (reply_tx, reply_rx) = query::reply_channel();
         let (input_tx, input_rx) = bounded(INPUT_CHANNEL_CAPACITY);
         let wakeup = WakeupPipe::new()?;
 
         Ok(Self {
-            frame_tx,
-            frame_rx,
+            scene_publisher,
+            scene_receiver,
             cmd_tx,
             cmd_rx,
+            cmd_stats,
+            reply_tx,
+            reply_rx,
             input_tx,
             input_rx,
             wakeup,
@@ -435,16 +787,19 @@ impl ThreadComms {
     /// Split into Emacs-side and Render-side handles
     pub fn split(self) -> (EmacsComms, RenderComms) {
         let emacs = EmacsComms {
-            frame_tx: self.frame_tx,
+            scenes: self.scene_publisher,
             cmd_tx: self.cmd_tx,
+            queries: QueryClient::new(self.reply_rx),
             input_rx: self.input_rx,
             wakeup_read_fd: self.wakeup.read_fd(),
             wakeup_clear: WakeupClear { fd: self.wakeup.read_fd },
         };
 
         let render = RenderComms {
-            frame_rx: self.frame_rx,
+            scenes: self.scene_receiver,
             cmd_rx: self.cmd_rx,
+            cmd_bus: CommandBus::new(self.cmd_stats),
+            reply_tx: self.reply_tx,
             input_tx: self.input_tx,
             wakeup: self.wakeup,
         };
@@ -455,8 +810,11 @@ impl ThreadComms {
 
 /// Emacs thread communication handle
 pub struct EmacsComms {
-    pub frame_tx: Sender<FrameGlyphBuffer>,
-    pub cmd_tx: Sender<RenderCommand>,
+    /// Publishes laid-out frames
+    pub scenes: ScenePublisher,
+    pub cmd_tx: CommandSender,
+    /// Synchronous queries to the render thread
+    pub queries: QueryClient,
     pub input_rx: Receiver<InputEvent>,
     pub wakeup_read_fd: RawFd,
     pub wakeup_clear: WakeupClear,
@@ -481,8 +839,12 @@ impl WakeupClear {
 
 /// Render thread communication handle
 pub struct RenderComms {
-    pub frame_rx: Receiver<FrameGlyphBuffer>,
+    /// Latest laid-out frames
+    pub scenes: SceneReceiver,
     pub cmd_rx: Receiver<RenderCommand>,
+    /// Batches, coalesces and orders commands from `cmd_rx`
+    pub cmd_bus: CommandBus,
+    pub reply_tx: Sender<QueryReply>,
     pub input_tx: Sender<InputEvent>,
     pub wakeup: WakeupPipe,
 }
@@ -499,6 +861,7 @@ impl RenderComms {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::frame_glyphs::FrameGlyphBuffer;
 
     // ===================================================================
     // Constants
@@ -679,32 +1042,31 @@ mod tests {
     }
 
     #[test]
-    fn thread_comms_frame_channel_roundtrip() {
-        let comms = ThreadComms::new().unwrap();
+    fn thread_comms_frame_roundtrip() {
+        let mut comms = ThreadComms::new().unwrap();
 
         let buf = FrameGlyphBuffer::new();
-        comms.frame_tx.send(buf).unwrap();
+        comms.scene_publisher.publish(&buf);
 
-        let received = comms.frame_rx.try_recv().unwrap();
-        assert_eq!(received.width, 0.0);
-        assert_eq!(received.height, 0.0);
+        let received = comms.scene_receiver.take().pop().unwrap();
+        assert_eq!(received.frame.width, 0.0);
+        assert_eq!(received.frame.height, 0.0);
     }
 
     #[test]
-    fn thread_comms_frame_channel_is_unbounded() {
-        let comms = ThreadComms::new().unwrap();
+    fn thread_comms_frames_never_block() {
+        let mut comms = ThreadComms::new().unwrap();
 
-        // Send many frames without blocking -- unbounded channel
+        // Publish many frames without blocking; only the latest is kept
         for i in 0..100 {
             let buf = FrameGlyphBuffer::with_size(i as f32, i as f32);
-            comms.frame_tx.send(buf).unwrap();
+            comms.scene_publisher.publish(&buf);
         }
 
-        // Drain and verify
-        for i in 0..100 {
-            let received = comms.frame_rx.try_recv().unwrap();
-            assert_eq!(received.width, i as f32);
-        }
+        let received = comms.scene_receiver.take();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].frame.width, 99.0);
+        assert_eq!(received[0].generation, 100);
     }
 
     #[test]
@@ -751,7 +1113,7 @@ mod tests {
     #[test]
     fn thread_comms_split_channels_work() {
         let comms = ThreadComms::new().unwrap();
-        let (emacs, render) = comms.split();
+        let (emacs, mut render) = comms.split();
 
         // Emacs sends command, render receives
         emacs.cmd_tx.send(RenderCommand::VisualBell).unwrap();
@@ -771,8 +1133,8 @@ mod tests {
 
         // Emacs sends frame, render receives
         let buf = FrameGlyphBuffer::with_size(800.0, 600.0);
-        emacs.frame_tx.send(buf).unwrap();
-        let frame = render.frame_rx.try_recv().unwrap();
+        emacs.scenes.publish(&buf);
+        let frame = render.scenes.take().pop().unwrap().frame;
         assert_eq!(frame.width, 800.0);
         assert_eq!(frame.height, 600.0);
     }
@@ -915,13 +1277,15 @@ mod tests {
             y: 500.0,
             modifiers: 0,
             pixel_precise: false,
+            lines: 3,
             target_frame_id: 0,
         };
         match event {
-            InputEvent::MouseScroll { delta_x, delta_y, pixel_precise, .. } => {
+            InputEvent::MouseScroll { delta_x, delta_y, pixel_precise, lines, .. } => {
                 assert_eq!(delta_x, 0.0);
                 assert_eq!(delta_y, -3.0);
                 assert!(!pixel_precise);
+                assert_eq!(lines, 3);
             }
             _ => panic!("Wrong variant"),
         }
@@ -936,6 +1300,7 @@ mod tests {
             y: 0.0,
             modifiers: 0,
             pixel_precise: true,
+            lines: 0,
             target_frame_id: 0,
         };
         match event {
@@ -999,6 +1364,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn input_event_pinch_zoom_construction() {
+        let event = InputEvent::PinchZoom { x: 120.0, y: 80.0, scale: 1.25, target_frame_id: 0 };
+        match event {
+            InputEvent::PinchZoom { x, y, scale, .. } => {
+                assert_eq!((x, y), (120.0, 80.0));
+                assert_eq!(scale, 1.25);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn input_event_command_palette_selection_construction() {
+        let event = InputEvent::CommandPaletteSelection { index: -1 };
+        assert!(matches!(event, InputEvent::CommandPaletteSelection { index: -1 }));
+    }
+
+    #[test]
+    fn input_event_color_picker_selection_construction() {
+        let event = InputEvent::ColorPickerSelection { color: COLOR_PICKER_EYEDROPPER };
+        assert!(matches!(event, InputEvent::ColorPickerSelection { color: -3 }));
+    }
+
+    #[test]
+    fn input_event_agenda_timeline_selection_construction() {
+        let event = InputEvent::AgendaTimelineSelection { index: 3 };
+        assert!(matches!(event, InputEvent::AgendaTimelineSelection { index: 3 }));
+    }
+
+    #[test]
+    fn input_event_graph_view_selection_construction() {
+        let event = InputEvent::GraphViewSelection { index: -1 };
+        assert!(matches!(event, InputEvent::GraphViewSelection { index: -1 }));
+    }
+
+    #[test]
+    fn input_event_dir_view_selection_construction() {
+        let event = InputEvent::DirViewSelection { path: Some("/tmp/a.txt".to_string()) };
+        assert!(matches!(event, InputEvent::DirViewSelection { path: Some(ref p) } if p == "/tmp/a.txt"));
+    }
+
+    #[test]
+    fn input_event_process_monitor_selection_construction() {
+        let event = InputEvent::ProcessMonitorSelection { pid: 4242 };
+        assert!(matches!(event, InputEvent::ProcessMonitorSelection { pid: 4242 }));
+    }
+
+    #[test]
+    fn input_event_date_picker_selection_construction() {
+        let event = InputEvent::DatePickerSelection { date: 20261016 };
+        assert!(matches!(event, InputEvent::DatePickerSelection { date: 20261016 }));
+    }
+
+    #[test]
+    fn input_event_monitors_changed_construction() {
+        let event = InputEvent::MonitorsChanged;
+        assert!(matches!(event, InputEvent::MonitorsChanged));
+    }
+
     #[test]
     fn input_event_image_dimensions_ready_construction() {
         let event = InputEvent::ImageDimensionsReady {
@@ -1149,6 +1574,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn render_command_image_set_edits() {
+        let edits = crate::core::image_edit::ImageEdits { rotation: 1, ..Default::default() };
+        let cmd = RenderCommand::ImageSetEdits { id: 7, edits: Some(edits.clone()) };
+        match cmd {
+            RenderCommand::ImageSetEdits { id, edits: Some(e) } => {
+                assert_eq!(id, 7);
+                assert_eq!(e, edits);
+            }
+            other => panic!("Expected ImageSetEdits, got {:?}", other),
+        }
+    }
+
     #[test]
     fn render_command_webkit_create() {
         let cmd = RenderCommand::WebKitCreate { id: 1, width: 800, height: 600 };
@@ -1198,6 +1636,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn render_command_set_pointer_auto_hide() {
+        let cmd = RenderCommand::SetPointerAutoHide { enabled: false };
+        match cmd {
+            RenderCommand::SetPointerAutoHide { enabled } => assert!(!enabled),
+            other => panic!("Expected SetPointerAutoHide, got {:?}", other),
+        }
+    }
+
     #[test]
     fn render_command_set_window_title() {
         let cmd = RenderCommand::SetWindowTitle {
@@ -1443,6 +1890,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn render_command_cancel_attention() {
+        let cmd = RenderCommand::CancelAttention;
+        assert!(matches!(cmd, RenderCommand::CancelAttention));
+    }
+
+    #[test]
+    fn render_command_set_window_icon() {
+        let cmd = RenderCommand::SetWindowIcon { data: Some(vec![0x89, b'P', b'N', b'G']) };
+        match cmd {
+            RenderCommand::SetWindowIcon { data } => assert_eq!(data.unwrap().len(), 4),
+            other => panic!("Expected SetWindowIcon, got {:?}", other),
+        }
+    }
+
     #[test]
     fn render_command_update_effect() {
         let cmd = RenderCommand::UpdateEffect(EffectUpdater(Box::new(|_config| {
@@ -1463,6 +1925,174 @@ mod tests {
         }
     }
 
+    #[test]
+    fn render_command_cursor_beacon() {
+        let cmd = RenderCommand::CursorBeacon;
+        assert!(matches!(cmd, RenderCommand::CursorBeacon));
+    }
+
+    #[test]
+    fn render_command_start_smooth_text_update() {
+        let cmd = RenderCommand::StartSmoothTextUpdate;
+        assert!(matches!(cmd, RenderCommand::StartSmoothTextUpdate));
+    }
+
+    #[test]
+    fn render_command_set_present_mode() {
+        let cmd = RenderCommand::SetPresentMode { mode: wgpu::PresentMode::Mailbox, max_frame_latency: 1 };
+        match cmd {
+            RenderCommand::SetPresentMode { mode, max_frame_latency } => {
+                assert_eq!(mode, wgpu::PresentMode::Mailbox);
+                assert_eq!(max_frame_latency, 1);
+            }
+            other => panic!("Expected SetPresentMode, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn render_command_set_settings_file() {
+        let cmd = RenderCommand::SetSettingsFile { path: Some("/tmp/display.toml".to_string()) };
+        match cmd {
+            RenderCommand::SetSettingsFile { path } => assert_eq!(path.as_deref(), Some("/tmp/display.toml")),
+            other => panic!("Expected SetSettingsFile, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn render_command_set_memory_budget() {
+        let budget = crate::backend::wgpu::MemoryBudget::from_mb(16, 8, 32, 4);
+        let cmd = RenderCommand::SetMemoryBudget { budget };
+        match cmd {
+            RenderCommand::SetMemoryBudget { budget: b } => assert_eq!(b, budget),
+            other => panic!("Expected SetMemoryBudget, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn render_command_set_post_shader() {
+        let cmd = RenderCommand::SetPostShader { path: Some("/tmp/crt.wgsl".to_string()) };
+        match cmd {
+            RenderCommand::SetPostShader { path } => assert_eq!(path.as_deref(), Some("/tmp/crt.wgsl")),
+            other => panic!("Expected SetPostShader, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn render_command_set_window_background() {
+        use crate::core::window_background::*;
+        let cmd = RenderCommand::SetWindowBackground {
+            target: BackgroundTarget::Buffer(42),
+            background: Some(WindowBackground {
+                image_id: 3,
+                opacity: 0.25,
+                scaling: BackgroundScaling::Fit,
+                anchor: BackgroundAnchor::BottomRight,
+            }),
+        };
+        match cmd {
+            RenderCommand::SetWindowBackground { target, background } => {
+                assert_eq!(target, BackgroundTarget::Buffer(42));
+                let bg = background.unwrap();
+                assert_eq!(bg.image_id, 3);
+                assert_eq!(bg.anchor, BackgroundAnchor::BottomRight);
+            }
+            other => panic!("Expected SetWindowBackground, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn render_command_start_window_transition() {
+        use crate::core::scroll_animation::ScrollEffect;
+        let cmd = RenderCommand::StartWindowTransition { effect: ScrollEffect::PageCurl, duration_ms: 400 };
+        match cmd {
+            RenderCommand::StartWindowTransition { effect, duration_ms } => {
+                assert_eq!(effect, ScrollEffect::PageCurl);
+                assert_eq!(duration_ms, 400);
+            }
+            other => panic!("Expected StartWindowTransition, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn render_command_set_buffer_transition() {
+        use crate::core::buffer_transition::BufferTransitionEffect;
+        let cmd = RenderCommand::SetBufferTransition {
+            effect: BufferTransitionEffect::SlideUp,
+            duration_ms: 250,
+            auto_detect: true,
+        };
+        match cmd {
+            RenderCommand::SetBufferTransition { effect, duration_ms, auto_detect } => {
+                assert_eq!(effect, BufferTransitionEffect::SlideUp);
+                assert_eq!(duration_ms, 250);
+                assert!(auto_detect);
+            }
+            other => panic!("Expected SetBufferTransition, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn render_command_add_timeline_animation() {
+        use crate::core::buffer_transition::TransitionEasing;
+        use crate::core::timeline::{AnimProperty, Keyframe, KeyframeAnimation};
+        let keyframes = vec![
+            Keyframe { time: std::time::Duration::ZERO, value: 0.0, easing: TransitionEasing::Linear },
+            Keyframe { time: std::time::Duration::from_millis(200), value: 1.0, easing: TransitionEasing::EaseOut },
+        ];
+        let animation = KeyframeAnimation::new(5, AnimProperty::Opacity, keyframes, std::time::Instant::now());
+        let cmd = RenderCommand::AddTimelineAnimation { id: 3, animation };
+        match cmd {
+            RenderCommand::AddTimelineAnimation { id, animation } => {
+                assert_eq!(id, 3);
+                assert_eq!(animation.window_id, 5);
+                assert_eq!(animation.property, AnimProperty::Opacity);
+                assert_eq!(animation.keyframes.len(), 2);
+            }
+            other => panic!("Expected AddTimelineAnimation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn render_command_set_animation_curve() {
+        use crate::core::buffer_transition::TransitionEasing;
+        use crate::core::easing::{CubicBezier, EasingTarget};
+        let cmd = RenderCommand::SetAnimationCurve {
+            target: EasingTarget::Scroll,
+            easing: Some(TransitionEasing::CubicBezier(CubicBezier::EASE)),
+        };
+        match cmd {
+            RenderCommand::SetAnimationCurve { target, easing } => {
+                assert_eq!(target, EasingTarget::Scroll);
+                assert_eq!(easing, Some(TransitionEasing::CubicBezier(CubicBezier::EASE)));
+            }
+            other => panic!("Expected SetAnimationCurve, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn render_command_set_background_throttle() {
+        use crate::render_thread::suspend::SuspendPolicy;
+        let policy = SuspendPolicy { enabled: true, unfocused_fps: 5, hidden_fps: 1, pause_video: true };
+        let cmd = RenderCommand::SetBackgroundThrottle { policy };
+        match cmd {
+            RenderCommand::SetBackgroundThrottle { policy: p } => assert_eq!(p, policy),
+            other => panic!("Expected SetBackgroundThrottle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn render_command_query() {
+        use crate::query::RenderQuery;
+        let cmd = RenderCommand::Query { id: 9, query: RenderQuery::ImageSize { id: 3 } };
+        match cmd {
+            RenderCommand::Query { id, query } => {
+                assert_eq!(id, 9);
+                assert_eq!(query, RenderQuery::ImageSize { id: 3 });
+            }
+            other => panic!("Expected Query, got {:?}", other),
+        }
+    }
+
     #[test]
     fn render_command_set_titlebar_height() {
         let cmd = RenderCommand::SetTitlebarHeight { height: 32.0 };
@@ -1581,6 +2211,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn render_command_create_popup_window() {
+        let cmd = RenderCommand::CreatePopupWindow { emacs_frame_id: 7, width: 640, height: 40 };
+        match cmd {
+            RenderCommand::CreatePopupWindow { emacs_frame_id, width, height } => {
+                assert_eq!(emacs_frame_id, 7);
+                assert_eq!((width, height), (640, 40));
+            }
+            other => panic!("Expected CreatePopupWindow, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn render_command_resize_popup_window() {
+        let cmd = RenderCommand::ResizePopupWindow { emacs_frame_id: 7, width: 640, height: 320 };
+        match cmd {
+            RenderCommand::ResizePopupWindow { emacs_frame_id, width, height } => {
+                assert_eq!(emacs_frame_id, 7);
+                assert_eq!((width, height), (640, 320));
+            }
+            other => panic!("Expected ResizePopupWindow, got {:?}", other),
+        }
+    }
+
     #[test]
     fn render_command_set_child_frame_style() {
         let cmd = RenderCommand::SetChildFrameStyle {
@@ -1966,7 +2620,7 @@ mod tests {
         let comms = ThreadComms::new().unwrap();
         assert!(comms.input_rx.try_recv().is_err());
         assert!(comms.cmd_rx.try_recv().is_err());
-        assert!(comms.frame_rx.try_recv().is_err());
+        assert!(!comms.scene_receiver.has_new());
     }
 
     // ===================================================================
@@ -2040,13 +2694,19 @@ mod tests {
         let (emacs, render) = comms.split();
 
         let handle = std::thread::spawn(move || {
-            let frame = render.frame_rx.recv().unwrap();
+            let mut render = render;
+            let frame = loop {
+                if let Some(scene) = render.scenes.take().pop() {
+                    break scene.frame;
+                }
+                std::thread::yield_now();
+            };
             assert_eq!(frame.width, 1920.0);
             assert_eq!(frame.height, 1080.0);
         });
 
         let buf = FrameGlyphBuffer::with_size(1920.0, 1080.0);
-        emacs.frame_tx.send(buf).unwrap();
+        emacs.scenes.publish(&buf);
 
         handle.join().unwrap();
     }
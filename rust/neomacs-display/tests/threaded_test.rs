@@ -14,7 +14,7 @@ fn test_thread_comms_creation() {
     let comms = ThreadComms::new().expect("Failed to create ThreadComms");
 
     // Verify we can access channels before split
-    assert!(comms.frame_rx.is_empty());
+    assert!(!comms.scene_receiver.has_new());
     assert!(comms.cmd_rx.is_empty());
     assert!(comms.input_rx.is_empty());
 }
@@ -27,7 +27,7 @@ fn test_thread_comms_split() {
     // Verify both sides have access to their channels
     assert!(emacs.input_rx.is_empty());
     assert!(render.cmd_rx.is_empty());
-    assert!(render.frame_rx.is_empty());
+    assert!(!render.scenes.has_new());
 }
 
 #[test]
@@ -190,6 +190,7 @@ fn test_input_event_mouse() {
         y: 250.0,
         modifiers: 0,
         pixel_precise: false,
+        lines: 3,
         target_frame_id: 0,
     });
 
@@ -215,8 +216,9 @@ fn test_input_event_mouse() {
     }
 
     match emacs.input_rx.recv().unwrap() {
-        InputEvent::MouseScroll { delta_y, .. } => {
+        InputEvent::MouseScroll { delta_y, lines, .. } => {
             assert_eq!(delta_y, -3.0);
+            assert_eq!(lines, 3);
         }
         _ => panic!("Expected MouseScroll event"),
     }
@@ -259,9 +261,9 @@ fn test_input_event_window() {
 }
 
 #[test]
-fn test_frame_channel() {
+fn test_scene_handoff() {
     let comms = ThreadComms::new().expect("Failed to create ThreadComms");
-    let (emacs, render) = comms.split();
+    let (emacs, mut render) = comms.split();
 
     // Create a frame buffer
     let mut frame = FrameGlyphBuffer::with_size(800.0, 600.0);
@@ -272,34 +274,45 @@ fn test_frame_channel() {
     frame.add_char('H', 0.0, 0.0, 10.0, 20.0, 16.0, false);
     frame.add_char('i', 10.0, 0.0, 10.0, 20.0, 16.0, false);
 
-    // Send frame
-    emacs.frame_tx.send(frame).unwrap();
-
-    // Receive on render side
-    let received = render.frame_rx.recv().unwrap();
-    assert_eq!(received.width, 800.0);
-    assert_eq!(received.height, 600.0);
-    assert_eq!(received.glyphs.len(), 2);
+    // Publish scene
+    emacs.scenes.publish(&frame);
+    assert!(render.scenes.has_new());
+
+    // Take on render side
+    let received = render.scenes.take().pop().unwrap();
+    assert_eq!(received.frame.width, 800.0);
+    assert_eq!(received.frame.height, 600.0);
+    assert_eq!(received.frame.glyphs.len(), 2);
+    assert!(!render.scenes.has_new());
+    assert!(render.scenes.take().is_empty());
 }
 
 #[test]
-fn test_frame_channel_unbounded() {
+fn test_scene_handoff_latest_wins() {
     let comms = ThreadComms::new().expect("Failed to create ThreadComms");
-    let (emacs, render) = comms.split();
+    let (emacs, mut render) = comms.split();
 
-    // Frame channel is unbounded — can send multiple frames without blocking
-    let frame1 = FrameGlyphBuffer::with_size(800.0, 600.0);
-    let frame2 = FrameGlyphBuffer::with_size(800.0, 600.0);
-    let frame3 = FrameGlyphBuffer::with_size(800.0, 600.0);
-
-    emacs.frame_tx.send(frame1).unwrap();
-    emacs.frame_tx.send(frame2).unwrap();
-    emacs.frame_tx.send(frame3).unwrap();
+    // Publishing never blocks; a scene not taken yet is replaced
+    for width in [800.0, 1024.0, 1280.0] {
+        emacs.scenes.publish(&FrameGlyphBuffer::with_size(width, 600.0));
+    }
 
-    // All three should be receivable
-    let _ = render.frame_rx.recv().unwrap();
-    let _ = render.frame_rx.recv().unwrap();
-    let _ = render.frame_rx.recv().unwrap();
+    let received = render.scenes.take();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].frame.width, 1280.0);
+    assert_eq!(received[0].generation, 3);
+    assert_eq!(render.scenes.taken(), 3);
+    assert_eq!(render.scenes.superseded(), 2);
+
+    // Scenes of other frames are kept alongside
+    let mut child = FrameGlyphBuffer::with_size(200.0, 100.0);
+    child.frame_id = 7;
+    emacs.scenes.publish(&FrameGlyphBuffer::with_size(640.0, 480.0));
+    emacs.scenes.publish(&child);
+    let received = render.scenes.take();
+    assert_eq!(received.len(), 2);
+    assert_eq!(received[0].frame.width, 640.0);
+    assert_eq!(received[1].frame.frame_id, 7);
 }
 
 #[test]
@@ -466,7 +479,9 @@ fn test_frame_glyph_buffer_cursor_append() {
 #[test]
 #[ignore = "Requires display server (X11/Wayland)"]
 fn test_render_thread_lifecycle() {
+    use neomacs_display::backend::wgpu::GpuMemoryStats;
     use neomacs_display::render_thread::RenderThread;
+    use std::sync::atomic::AtomicBool;
     use std::sync::{Arc, Mutex};
     use std::collections::HashMap;
 
@@ -481,6 +496,8 @@ fn test_render_thread_lifecycle() {
         render, 800, 600, "Test Window".to_string(),
         image_dimensions,
         shared_monitors,
+        Arc::new(Mutex::new(GpuMemoryStats::default())),
+        Arc::new(AtomicBool::new(false)),
         #[cfg(feature = "neo-term")]
         Arc::new(Mutex::new(HashMap::new())),
     );
@@ -499,7 +516,9 @@ fn test_render_thread_lifecycle() {
 #[test]
 #[ignore = "Requires display server (X11/Wayland)"]
 fn test_render_thread_with_frames() {
+    use neomacs_display::backend::wgpu::GpuMemoryStats;
     use neomacs_display::render_thread::RenderThread;
+    use std::sync::atomic::AtomicBool;
     use std::sync::{Arc, Mutex};
     use std::collections::HashMap;
 
@@ -513,6 +532,8 @@ fn test_render_thread_with_frames() {
         render, 800, 600, "Test Frame Render".to_string(),
         image_dimensions,
         shared_monitors,
+        Arc::new(Mutex::new(GpuMemoryStats::default())),
+        Arc::new(AtomicBool::new(false)),
         #[cfg(feature = "neo-term")]
         Arc::new(Mutex::new(HashMap::new())),
     );
@@ -527,9 +548,8 @@ fn test_render_thread_with_frames() {
         frame.set_face(0, Color::WHITE, None, 400, false, 0, None, 0, None, 0, None);
         frame.add_char('X', 100.0, 100.0, 10.0, 20.0, 16.0, false);
 
-        if emacs.frame_tx.try_send(frame).is_err() {
-            // Channel full, that's OK
-        }
+        // Replaces any scene the render thread has not taken yet
+        emacs.scenes.publish(&frame);
 
         thread::sleep(Duration::from_millis(50));
     }
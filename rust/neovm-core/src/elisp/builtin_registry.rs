@@ -201,6 +201,7 @@ const DISPATCH_BUILTIN_NAMES: &[&str] = &[
     "current-case-table",
     "current-column",
     "current-global-map",
+    "current-idle-time",
     "current-indentation",
     "current-kill",
     "current-local-map",
@@ -246,6 +247,17 @@ const DISPATCH_BUILTIN_NAMES: &[&str] = &[
     "directory-files",
     "directory-files-and-attributes",
     "directory-name-p",
+    "dired-service-column",
+    "dired-service-count",
+    "dired-service-directory",
+    "dired-service-list",
+    "dired-service-notify",
+    "dired-service-ready-p",
+    "dired-service-refresh",
+    "dired-service-release",
+    "dired-service-sort",
+    "dired-service-stale-p",
+    "dired-service-wait",
     "ding",
     "display-backing-store",
     "display-buffer",
@@ -320,6 +332,7 @@ const DISPATCH_BUILTIN_NAMES: &[&str] = &[
     "file-newer-than-file-p",
     "file-readable-p",
     "file-regular-p",
+    "file-remote-p",
     "file-symlink-p",
     "file-truename",
     "file-writable-p",
@@ -754,6 +767,22 @@ const DISPATCH_BUILTIN_NAMES: &[&str] = &[
     "sort",
     "special-form-p",
     "split-window",
+    "sqlite-available-p",
+    "sqlite-close",
+    "sqlite-columns",
+    "sqlite-commit",
+    "sqlite-execute",
+    "sqlite-execute-batch",
+    "sqlite-finalize",
+    "sqlite-more-p",
+    "sqlite-next",
+    "sqlite-open",
+    "sqlite-pragma",
+    "sqlite-rollback",
+    "sqlite-select",
+    "sqlite-transaction",
+    "sqlite-version",
+    "sqlitep",
     "standard-case-table",
     "standard-category-table",
     "start-kbd-macro",
@@ -828,6 +857,7 @@ const DISPATCH_BUILTIN_NAMES: &[&str] = &[
     "time-less-p",
     "time-subtract",
     "timer-activate",
+    "timer-statistics",
     "timerp",
     "tool-bar-height",
     "top-level",
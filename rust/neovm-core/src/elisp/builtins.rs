@@ -5946,6 +5946,7 @@ pub(crate) fn dispatch_builtin(
         "file-attributes" => return Some(super::dired::builtin_file_attributes_eval(eval, args)),
         "file-exists-p" => return Some(super::fileio::builtin_file_exists_p_eval(eval, args)),
         "file-readable-p" => return Some(super::fileio::builtin_file_readable_p_eval(eval, args)),
+        "file-remote-p" => return Some(super::fileio::builtin_file_remote_p(eval, args)),
         "file-writable-p" => return Some(super::fileio::builtin_file_writable_p_eval(eval, args)),
         "file-directory-p" => {
             return Some(super::fileio::builtin_file_directory_p_eval(eval, args))
@@ -6010,6 +6011,8 @@ pub(crate) fn dispatch_builtin(
         }
         "cancel-timer" => return Some(super::timer::builtin_cancel_timer(eval, args)),
         "timer-activate" => return Some(super::timer::builtin_timer_activate(eval, args)),
+        "timer-statistics" => return Some(super::timer::builtin_timer_statistics(eval, args)),
+        "current-idle-time" => return Some(super::timer::builtin_current_idle_time(eval, args)),
         "sleep-for" => return Some(super::timer::builtin_sleep_for(args)),
         // Advice system
         "advice-add" => return Some(super::advice::builtin_advice_add(eval, args)),
@@ -6072,6 +6075,55 @@ pub(crate) fn dispatch_builtin(
         "bookmark-rename" => return Some(super::bookmark::builtin_bookmark_rename(eval, args)),
         "bookmark-save" => return Some(super::bookmark::builtin_bookmark_save(eval, args)),
         "bookmark-load" => return Some(super::bookmark::builtin_bookmark_load(eval, args)),
+
+        // SQLite (evaluator-dependent)
+        "sqlitep" => return Some(super::sqlite::builtin_sqlitep(eval, args)),
+        "sqlite-open" => return Some(super::sqlite::builtin_sqlite_open(eval, args)),
+        "sqlite-close" => return Some(super::sqlite::builtin_sqlite_close(eval, args)),
+        "sqlite-execute" => return Some(super::sqlite::builtin_sqlite_execute(eval, args)),
+        "sqlite-execute-batch" => {
+            return Some(super::sqlite::builtin_sqlite_execute_batch(eval, args))
+        }
+        "sqlite-select" => return Some(super::sqlite::builtin_sqlite_select(eval, args)),
+        "sqlite-next" => return Some(super::sqlite::builtin_sqlite_next(eval, args)),
+        "sqlite-columns" => return Some(super::sqlite::builtin_sqlite_columns(eval, args)),
+        "sqlite-more-p" => return Some(super::sqlite::builtin_sqlite_more_p(eval, args)),
+        "sqlite-finalize" => return Some(super::sqlite::builtin_sqlite_finalize(eval, args)),
+        "sqlite-transaction" => {
+            return Some(super::sqlite::builtin_sqlite_transaction(eval, args))
+        }
+        "sqlite-commit" => return Some(super::sqlite::builtin_sqlite_commit(eval, args)),
+        "sqlite-rollback" => return Some(super::sqlite::builtin_sqlite_rollback(eval, args)),
+        "sqlite-pragma" => return Some(super::sqlite::builtin_sqlite_pragma(eval, args)),
+
+        // Directory listing service (evaluator-dependent)
+        "dired-service-list" => return Some(super::dirsvc::builtin_dired_service_list(eval, args)),
+        "dired-service-ready-p" => {
+            return Some(super::dirsvc::builtin_dired_service_ready_p(eval, args))
+        }
+        "dired-service-wait" => return Some(super::dirsvc::builtin_dired_service_wait(eval, args)),
+        "dired-service-directory" => {
+            return Some(super::dirsvc::builtin_dired_service_directory(eval, args))
+        }
+        "dired-service-count" => {
+            return Some(super::dirsvc::builtin_dired_service_count(eval, args))
+        }
+        "dired-service-column" => {
+            return Some(super::dirsvc::builtin_dired_service_column(eval, args))
+        }
+        "dired-service-sort" => return Some(super::dirsvc::builtin_dired_service_sort(eval, args)),
+        "dired-service-notify" => {
+            return Some(super::dirsvc::builtin_dired_service_notify(eval, args))
+        }
+        "dired-service-stale-p" => {
+            return Some(super::dirsvc::builtin_dired_service_stale_p(eval, args))
+        }
+        "dired-service-refresh" => {
+            return Some(super::dirsvc::builtin_dired_service_refresh(eval, args))
+        }
+        "dired-service-release" => {
+            return Some(super::dirsvc::builtin_dired_service_release(eval, args))
+        }
         // Abbreviation operations (evaluator-dependent)
         "define-abbrev" => return Some(super::abbrev::builtin_define_abbrev(eval, args)),
         "expand-abbrev" => return Some(super::abbrev::builtin_expand_abbrev(eval, args)),
@@ -7209,6 +7261,10 @@ pub(crate) fn dispatch_builtin(
         "json-serialize" => super::json::builtin_json_serialize(args),
         "json-parse-string" => super::json::builtin_json_parse_string(args),
 
+        // SQLite (pure)
+        "sqlite-available-p" => super::sqlite::builtin_sqlite_available_p(args),
+        "sqlite-version" => super::sqlite::builtin_sqlite_version(args),
+
         // Subr introspection (pure)
         "subr-name" => super::subr_info::builtin_subr_name(args),
         "subr-arity" => super::subr_info::builtin_subr_arity(args),
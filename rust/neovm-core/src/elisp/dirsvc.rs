@@ -0,0 +1,1252 @@
+//! Native directory listing service for dired.
+//!
+//! Listing a huge directory from Lisp means one `file-attributes` call (and
+//! one cons-heavy attribute list) per entry, plus a `git status` per buffer
+//! revert.  This service does the whole scan on a worker thread and hands
+//! the result back as a column-oriented `DirectoryListing`, so dired can
+//! fetch exactly the columns it renders.
+//!
+//! Provides:
+//! - `dired-service-list` -- start scanning a directory, returns a handle
+//! - `dired-service-ready-p`, `dired-service-wait` -- completion
+//! - `dired-service-count`, `dired-service-column`, `dired-service-directory`
+//! - `dired-service-sort` -- index permutation for a sort key
+//! - `dired-service-notify`, `dired-service-stale-p`, `dired-service-refresh`
+//!   -- change tracking.  On Linux listed directories are watched with
+//!   inotify, so a listing goes stale as soon as a file in it changes;
+//!   `dired-service-notify` reports changes from other sources, such as a
+//!   `file-notify-add-watch` callback
+//! - `dired-service-release`
+//!
+//! Listings are integer handles into the evaluator's `DirectoryService`,
+//! the same way processes are.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::error::{signal, EvalResult, Flow};
+use super::value::Value;
+
+// ---------------------------------------------------------------------------
+// Types
+// ---------------------------------------------------------------------------
+
+/// What kind of filesystem object an entry is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+    Other,
+}
+
+impl EntryKind {
+    pub fn symbol_name(self) -> &'static str {
+        match self {
+            EntryKind::File => "file",
+            EntryKind::Directory => "directory",
+            EntryKind::Symlink => "symlink",
+            EntryKind::Other => "other",
+        }
+    }
+}
+
+/// Per-entry VCS state, as reported by `git status --porcelain`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GitStatus {
+    Modified,
+    Added,
+    Deleted,
+    Renamed,
+    Untracked,
+    Ignored,
+    Conflicted,
+}
+
+impl GitStatus {
+    /// Map a porcelain `XY` code to a status.
+    fn from_porcelain(x: u8, y: u8) -> Option<Self> {
+        match (x, y) {
+            (b'?', b'?') => Some(GitStatus::Untracked),
+            (b'!', b'!') => Some(GitStatus::Ignored),
+            (b'U', _) | (_, b'U') | (b'A', b'A') | (b'D', b'D') => Some(GitStatus::Conflicted),
+            (b'R', _) | (_, b'R') => Some(GitStatus::Renamed),
+            (b'A', _) => Some(GitStatus::Added),
+            (b'D', _) | (_, b'D') => Some(GitStatus::Deleted),
+            (b'M', _) | (_, b'M') | (b'T', _) | (_, b'T') => Some(GitStatus::Modified),
+            _ => None,
+        }
+    }
+
+    /// Rank used when a directory aggregates the status of its contents.
+    fn priority(self) -> u8 {
+        match self {
+            GitStatus::Ignored => 0,
+            GitStatus::Untracked => 1,
+            GitStatus::Renamed => 2,
+            GitStatus::Added => 3,
+            GitStatus::Deleted => 4,
+            GitStatus::Modified => 5,
+            GitStatus::Conflicted => 6,
+        }
+    }
+
+    pub fn symbol_name(self) -> &'static str {
+        match self {
+            GitStatus::Modified => "modified",
+            GitStatus::Added => "added",
+            GitStatus::Deleted => "deleted",
+            GitStatus::Renamed => "renamed",
+            GitStatus::Untracked => "untracked",
+            GitStatus::Ignored => "ignored",
+            GitStatus::Conflicted => "conflicted",
+        }
+    }
+}
+
+/// Sort orders understood by `dired-service-sort`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    Size,
+    Mtime,
+    Extension,
+}
+
+impl SortKey {
+    fn from_symbol(name: &str) -> Option<Self> {
+        match name {
+            "name" => Some(SortKey::Name),
+            "size" => Some(SortKey::Size),
+            "mtime" | "time" => Some(SortKey::Mtime),
+            "extension" => Some(SortKey::Extension),
+            _ => None,
+        }
+    }
+}
+
+/// A scanned directory, stored column by column.  Row `i` of every column
+/// describes the same entry; rows are in `readdir` order.
+#[derive(Clone, Debug, Default)]
+pub struct DirectoryListing {
+    pub directory: String,
+    pub names: Vec<String>,
+    pub kinds: Vec<EntryKind>,
+    pub sizes: Vec<u64>,
+    /// Modification time in seconds since the epoch.
+    pub mtimes: Vec<i64>,
+    /// `st_mode` bits.
+    pub modes: Vec<u32>,
+    pub link_targets: Vec<Option<String>>,
+    /// Icon names (e.g. "folder", "file-code"); the renderer maps them to
+    /// glyphs from its icon font.
+    pub icons: Vec<&'static str>,
+    pub git: Vec<Option<GitStatus>>,
+    /// Directory mtime when the scan started, used for staleness checks.
+    scanned_mtime: Option<SystemTime>,
+}
+
+impl DirectoryListing {
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Return row indices ordered by KEY.  Ties fall back to the name so the
+    /// order is stable across rescans.
+    pub fn sorted_indices(&self, key: SortKey, reverse: bool, dirs_first: bool) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.sort_by(|&a, &b| {
+            let primary = match key {
+                SortKey::Name => std::cmp::Ordering::Equal,
+                // Largest and newest first, like `ls -S` and `ls -t`.
+                SortKey::Size => self.sizes[b].cmp(&self.sizes[a]),
+                SortKey::Mtime => self.mtimes[b].cmp(&self.mtimes[a]),
+                SortKey::Extension => extension(&self.names[a]).cmp(extension(&self.names[b])),
+            };
+            let ord = primary.then_with(|| self.names[a].cmp(&self.names[b]));
+            if reverse {
+                ord.reverse()
+            } else {
+                ord
+            }
+        });
+        if dirs_first {
+            // Stable sort keeps the key order within each group.
+            order.sort_by_key(|&i| self.kinds[i] != EntryKind::Directory);
+        }
+        order
+    }
+
+    fn push(&mut self, name: String, meta: Option<&fs::Metadata>, target: Option<String>) {
+        let kind = match meta.map(|m| m.file_type()) {
+            Some(t) if t.is_symlink() => EntryKind::Symlink,
+            Some(t) if t.is_dir() => EntryKind::Directory,
+            Some(t) if t.is_file() => EntryKind::File,
+            _ => EntryKind::Other,
+        };
+        let mtime = meta
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_secs() as i64);
+        self.icons.push(icon_for(&name, kind));
+        self.names.push(name);
+        self.kinds.push(kind);
+        self.sizes.push(meta.map_or(0, |m| m.len()));
+        self.mtimes.push(mtime);
+        self.modes.push(meta.map_or(0, mode_bits));
+        self.link_targets.push(target);
+        self.git.push(None);
+    }
+}
+
+#[cfg(unix)]
+fn mode_bits(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::MetadataExt;
+    meta.mode()
+}
+
+#[cfg(not(unix))]
+fn mode_bits(meta: &fs::Metadata) -> u32 {
+    if meta.permissions().readonly() {
+        0o444
+    } else {
+        0o644
+    }
+}
+
+fn extension(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(0) | None => "",
+        Some(i) => &name[i + 1..],
+    }
+}
+
+/// Choose an icon name for an entry from its kind and extension.
+pub fn icon_for(name: &str, kind: EntryKind) -> &'static str {
+    match kind {
+        EntryKind::Directory => return "folder",
+        EntryKind::Symlink => return "file-symlink",
+        EntryKind::Other => return "file",
+        EntryKind::File => {}
+    }
+    match extension(name).to_ascii_lowercase().as_str() {
+        "el" | "elc" => "file-elisp",
+        "rs" | "c" | "h" | "cc" | "cpp" | "py" | "js" | "ts" | "go" | "java" | "sh" | "rb" => {
+            "file-code"
+        }
+        "org" | "md" | "txt" | "rst" | "tex" => "file-text",
+        "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" | "bmp" => "file-image",
+        "mp4" | "mkv" | "webm" | "mov" | "avi" => "file-video",
+        "mp3" | "ogg" | "flac" | "wav" => "file-audio",
+        "pdf" => "file-pdf",
+        "zip" | "tar" | "gz" | "xz" | "bz2" | "zst" | "7z" => "file-archive",
+        "json" | "toml" | "yaml" | "yml" | "xml" | "ini" => "file-config",
+        _ => "file",
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Scanning (runs on the worker thread)
+// ---------------------------------------------------------------------------
+
+fn directory_mtime(dir: &str) -> Option<SystemTime> {
+    fs::metadata(dir).and_then(|m| m.modified()).ok()
+}
+
+/// Scan DIR into a listing.  `.` and `..` are not included.
+pub fn scan_directory(dir: &str, with_git: bool) -> io::Result<DirectoryListing> {
+    let mut listing = DirectoryListing {
+        directory: dir.to_string(),
+        scanned_mtime: directory_mtime(dir),
+        ..DirectoryListing::default()
+    };
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        let meta = fs::symlink_metadata(&path).ok();
+        let target = match &meta {
+            Some(m) if m.file_type().is_symlink() => fs::read_link(&path)
+                .ok()
+                .map(|t| t.to_string_lossy().into_owned()),
+            _ => None,
+        };
+        listing.push(name, meta.as_ref(), target);
+    }
+    if with_git {
+        if let Some(statuses) = git_statuses(dir) {
+            apply_git_statuses(&mut listing, &statuses);
+        }
+    }
+    Ok(listing)
+}
+
+/// Run `git status` for DIR.  Returns `(path relative to DIR, status)`
+/// pairs, or `None` when DIR is not inside a work tree.
+fn git_statuses(dir: &str) -> Option<Vec<(String, GitStatus)>> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .map(|out| out.stdout)
+    };
+    let prefix = String::from_utf8(git(&["rev-parse", "--show-prefix"])?).ok()?;
+    let prefix = prefix.trim_end_matches('\n');
+    let out = git(&[
+        "status",
+        "--porcelain=v1",
+        "-z",
+        "--ignored=matching",
+        "--untracked-files=normal",
+        "--",
+        ".",
+    ])?;
+    Some(parse_porcelain(&out, prefix))
+}
+
+/// Parse `git status --porcelain=v1 -z` output, keeping paths under PREFIX
+/// (the directory's path relative to the work tree root).
+fn parse_porcelain(out: &[u8], prefix: &str) -> Vec<(String, GitStatus)> {
+    let mut result = Vec::new();
+    let mut records = out.split(|&b| b == 0).filter(|r| !r.is_empty());
+    while let Some(record) = records.next() {
+        if record.len() < 4 {
+            continue;
+        }
+        let (x, y) = (record[0], record[1]);
+        // Renames and copies carry the original path as an extra record.
+        if matches!(x, b'R' | b'C') {
+            records.next();
+        }
+        let Some(status) = GitStatus::from_porcelain(x, y) else {
+            continue;
+        };
+        let path = String::from_utf8_lossy(&record[3..]);
+        if let Some(rel) = path.strip_prefix(prefix) {
+            result.push((rel.to_string(), status));
+        }
+    }
+    result
+}
+
+/// Attach statuses to rows.  A path below an entry marks that entry (a
+/// directory) with the most significant status of its contents.
+fn apply_git_statuses(listing: &mut DirectoryListing, statuses: &[(String, GitStatus)]) {
+    let rows: HashMap<&str, usize> = listing
+        .names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.as_str(), i))
+        .collect();
+    let mut git = listing.git.clone();
+    for (path, status) in statuses {
+        let first = path.trim_end_matches('/').split('/').next().unwrap_or("");
+        if let Some(&row) = rows.get(first) {
+            let keep = git[row].is_some_and(|old| old.priority() >= status.priority());
+            if !keep {
+                git[row] = Some(*status);
+            }
+        }
+    }
+    listing.git = git;
+}
+
+// ---------------------------------------------------------------------------
+// DirectoryService
+// ---------------------------------------------------------------------------
+
+enum JobState {
+    Pending(Receiver<io::Result<DirectoryListing>>),
+    Ready(Box<DirectoryListing>),
+    Failed(ErrorKind, String),
+}
+
+struct Job {
+    directory: String,
+    with_git: bool,
+    state: JobState,
+    /// Set by `notify` when a watcher reports a change.
+    invalidated: bool,
+}
+
+impl Job {
+    fn start(directory: String, with_git: bool) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let dir = directory.clone();
+        thread::spawn(move || {
+            let _ = tx.send(scan_directory(&dir, with_git));
+        });
+        Self {
+            directory,
+            with_git,
+            state: JobState::Pending(rx),
+            invalidated: false,
+        }
+    }
+
+    fn settle(&mut self, result: Result<io::Result<DirectoryListing>, ()>) {
+        self.state = match result {
+            Ok(Ok(listing)) => JobState::Ready(Box::new(listing)),
+            Ok(Err(e)) => JobState::Failed(e.kind(), e.to_string()),
+            Err(()) => JobState::Failed(ErrorKind::Other, "directory scan aborted".into()),
+        };
+    }
+
+    /// Collect a finished scan without blocking.
+    fn poll(&mut self) {
+        if let JobState::Pending(rx) = &self.state {
+            match rx.try_recv() {
+                Ok(result) => self.settle(Ok(result)),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.settle(Err(())),
+            }
+        }
+    }
+
+    /// Block until the scan finishes or TIMEOUT elapses.
+    fn wait(&mut self, timeout: Option<Duration>) {
+        if let JobState::Pending(rx) = &self.state {
+            let result = match timeout {
+                Some(t) => match rx.recv_timeout(t) {
+                    Ok(result) => Ok(result),
+                    Err(RecvTimeoutError::Timeout) => return,
+                    Err(RecvTimeoutError::Disconnected) => Err(()),
+                },
+                None => rx.recv().map_err(|_| ()),
+            };
+            self.settle(result);
+        }
+    }
+}
+
+/// Watches listed directories with inotify and reports the ones whose
+/// entries changed.  Writing a file leaves its directory's mtime alone,
+/// so without a watcher such changes only show through `notify`.
+#[cfg(target_os = "linux")]
+mod watcher {
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Events that change what a listing shows
+    const EVENTS: u32 = libc::IN_ATTRIB
+        | libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_DELETE_SELF
+        | libc::IN_MODIFY
+        | libc::IN_MOVE_SELF
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO;
+
+    /// How often the reader thread checks whether the watcher is gone.
+    const POLL_MS: libc::c_int = 200;
+
+    pub(super) struct DirectoryWatcher {
+        fd: Arc<OwnedFd>,
+        /// Watch descriptor of each watched directory
+        watches: HashMap<String, i32>,
+        /// Watch descriptors with events, from the reader thread
+        changes: Receiver<i32>,
+        stop: Arc<AtomicBool>,
+    }
+
+    impl DirectoryWatcher {
+        pub(super) fn start() -> Option<Self> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return None;
+            }
+            // SAFETY: inotify_init1 just returned FD, and nothing else owns it
+            let fd = Arc::new(unsafe { OwnedFd::from_raw_fd(fd) });
+            let (tx, changes) = mpsc::channel();
+            let stop = Arc::new(AtomicBool::new(false));
+            let (reader_fd, reader_stop) = (Arc::clone(&fd), Arc::clone(&stop));
+            thread::Builder::new()
+                .name("dired-watch".into())
+                .spawn(move || read_events(&reader_fd, &tx, &reader_stop))
+                .ok()?;
+            Some(Self {
+                fd,
+                watches: HashMap::new(),
+                changes,
+                stop,
+            })
+        }
+
+        pub(super) fn watch(&mut self, directory: &str) {
+            if self.watches.contains_key(directory) {
+                return;
+            }
+            let Ok(path) = CString::new(directory) else {
+                return;
+            };
+            let wd = unsafe {
+                libc::inotify_add_watch(
+                    self.fd.as_raw_fd(),
+                    path.as_ptr(),
+                    EVENTS | libc::IN_ONLYDIR,
+                )
+            };
+            if wd >= 0 {
+                self.watches.insert(directory.to_string(), wd);
+            }
+        }
+
+        pub(super) fn unwatch(&mut self, directory: &str) {
+            if let Some(wd) = self.watches.remove(directory) {
+                unsafe { libc::inotify_rm_watch(self.fd.as_raw_fd(), wd) };
+            }
+        }
+
+        /// Directories with changes since the last call.  When the kernel
+        /// dropped events, that is every watched directory.
+        pub(super) fn changed(&mut self) -> Vec<String> {
+            let mut changed = Vec::new();
+            for wd in self.changes.try_iter() {
+                if wd < 0 {
+                    return self.watches.keys().cloned().collect();
+                }
+                if let Some((dir, _)) = self.watches.iter().find(|(_, &w)| w == wd) {
+                    if !changed.contains(dir) {
+                        changed.push(dir.clone());
+                    }
+                }
+            }
+            changed
+        }
+    }
+
+    impl Drop for DirectoryWatcher {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Send the watch descriptor of each event on FD until STOP is set.
+    fn read_events(fd: &OwnedFd, tx: &Sender<i32>, stop: &AtomicBool) {
+        let header = std::mem::size_of::<libc::inotify_event>();
+        let mut buf = [0u8; 4096];
+        while !stop.load(Ordering::Relaxed) {
+            let mut pfd = libc::pollfd {
+                fd: fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let ready = unsafe { libc::poll(&mut pfd, 1, POLL_MS) };
+            if ready < 0 && io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                return;
+            }
+            if ready <= 0 {
+                continue;
+            }
+            let n = unsafe { libc::read(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+            let mut at = 0;
+            while n > 0 && at + header <= n as usize {
+                // struct inotify_event: wd, mask, cookie, len, then the name
+                let wd = i32::from_ne_bytes(buf[at..at + 4].try_into().unwrap());
+                let len = u32::from_ne_bytes(buf[at + 12..at + 16].try_into().unwrap());
+                if tx.send(wd).is_err() {
+                    return;
+                }
+                at += header + len as usize;
+            }
+        }
+    }
+}
+
+/// Registry of directory listings, owned by the evaluator.
+pub struct DirectoryService {
+    jobs: HashMap<u64, Job>,
+    next_id: u64,
+    /// Started with the first listing
+    #[cfg(target_os = "linux")]
+    watcher: Option<watcher::DirectoryWatcher>,
+}
+
+impl Default for DirectoryService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result of looking up a finished listing.
+pub enum ListingLookup<'a> {
+    Ready(&'a DirectoryListing),
+    Pending,
+    Failed(ErrorKind, &'a str),
+}
+
+impl DirectoryService {
+    pub fn new() -> Self {
+        Self {
+            jobs: HashMap::new(),
+            next_id: 1,
+            #[cfg(target_os = "linux")]
+            watcher: None,
+        }
+    }
+
+    /// Start scanning DIRECTORY on a worker thread, watching it for
+    /// changes from before the scan on.
+    pub fn request(&mut self, directory: &str, with_git: bool) -> u64 {
+        #[cfg(target_os = "linux")]
+        {
+            if self.watcher.is_none() {
+                self.watcher = watcher::DirectoryWatcher::start();
+            }
+            if let Some(watcher) = self.watcher.as_mut() {
+                watcher.watch(directory.trim_end_matches('/'));
+            }
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.jobs
+            .insert(id, Job::start(directory.to_string(), with_git));
+        id
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.jobs.contains_key(&id)
+    }
+
+    pub fn release(&mut self, id: u64) -> bool {
+        let Some(job) = self.jobs.remove(&id) else {
+            return false;
+        };
+        #[cfg(target_os = "linux")]
+        {
+            let dir = job.directory.trim_end_matches('/');
+            let still_listed = self
+                .jobs
+                .values()
+                .any(|j| j.directory.trim_end_matches('/') == dir);
+            if let (false, Some(watcher)) = (still_listed, self.watcher.as_mut()) {
+                watcher.unwatch(dir);
+            }
+        }
+        drop(job);
+        true
+    }
+
+    /// Mark listings of directories the watcher saw change as stale.
+    fn collect_changes(&mut self) {
+        #[cfg(target_os = "linux")]
+        if let Some(watcher) = self.watcher.as_mut() {
+            for dir in watcher.changed() {
+                for job in self.jobs.values_mut() {
+                    if job.directory.trim_end_matches('/') == dir {
+                        job.invalidated = true;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn directory(&self, id: u64) -> Option<&str> {
+        self.jobs.get(&id).map(|job| job.directory.as_str())
+    }
+
+    /// Whether the scan for ID has finished (successfully or not).
+    pub fn is_ready(&mut self, id: u64) -> Option<bool> {
+        let job = self.jobs.get_mut(&id)?;
+        job.poll();
+        Some(!matches!(job.state, JobState::Pending(_)))
+    }
+
+    /// Wait for the scan for ID; `None` timeout waits indefinitely.
+    pub fn wait(&mut self, id: u64, timeout: Option<Duration>) -> Option<bool> {
+        let job = self.jobs.get_mut(&id)?;
+        job.wait(timeout);
+        Some(!matches!(job.state, JobState::Pending(_)))
+    }
+
+    /// The listing for ID, polling the worker first.
+    pub fn listing(&mut self, id: u64) -> Option<ListingLookup<'_>> {
+        let job = self.jobs.get_mut(&id)?;
+        job.poll();
+        Some(match &job.state {
+            JobState::Ready(listing) => ListingLookup::Ready(listing),
+            JobState::Pending(_) => ListingLookup::Pending,
+            JobState::Failed(kind, message) => ListingLookup::Failed(*kind, message),
+        })
+    }
+
+    /// Record a change notification for DIRECTORY (or a file inside it).
+    /// Returns the number of listings that became stale.
+    pub fn notify(&mut self, path: &str) -> usize {
+        let path = path.trim_end_matches('/');
+        let parent = Path::new(path)
+            .parent()
+            .map(|p| p.to_string_lossy().into_owned());
+        let mut count = 0;
+        for job in self.jobs.values_mut() {
+            let dir = job.directory.trim_end_matches('/');
+            if dir == path || parent.as_deref() == Some(dir) {
+                if !job.invalidated {
+                    count += 1;
+                }
+                job.invalidated = true;
+            }
+        }
+        count
+    }
+
+    /// Whether the listing for ID no longer reflects the directory: either a
+    /// watcher reported a change or the directory's mtime moved.
+    pub fn is_stale(&mut self, id: u64) -> Option<bool> {
+        self.collect_changes();
+        let job = self.jobs.get_mut(&id)?;
+        if job.invalidated {
+            return Some(true);
+        }
+        job.poll();
+        Some(match &job.state {
+            JobState::Ready(listing) => directory_mtime(&job.directory) != listing.scanned_mtime,
+            _ => false,
+        })
+    }
+
+    /// Rescan the directory behind ID, keeping the handle.
+    pub fn refresh(&mut self, id: u64) -> bool {
+        // Changes so far are in the new scan
+        self.collect_changes();
+        let Some(job) = self.jobs.get_mut(&id) else {
+            return false;
+        };
+        *job = Job::start(job.directory.clone(), job.with_git);
+        true
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Argument helpers
+// ---------------------------------------------------------------------------
+
+fn expect_arg_range(name: &str, args: &[Value], min: usize, max: usize) -> Result<(), Flow> {
+    if args.len() < min || args.len() > max {
+        Err(signal(
+            "wrong-number-of-arguments",
+            vec![Value::symbol(name), Value::Int(args.len() as i64)],
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn expect_string(value: &Value) -> Result<String, Flow> {
+    match value {
+        Value::Str(s) => Ok((**s).clone()),
+        other => Err(signal(
+            "wrong-type-argument",
+            vec![Value::symbol("stringp"), other.clone()],
+        )),
+    }
+}
+
+fn expect_symbol(value: &Value) -> Result<&str, Flow> {
+    value.as_symbol_name().ok_or_else(|| {
+        signal(
+            "wrong-type-argument",
+            vec![Value::symbol("symbolp"), value.clone()],
+        )
+    })
+}
+
+fn file_error_symbol(kind: ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::NotFound => "file-missing",
+        ErrorKind::PermissionDenied => "permission-denied",
+        _ => "file-error",
+    }
+}
+
+fn resolve_listing(eval: &super::eval::Evaluator, value: &Value) -> Result<u64, Flow> {
+    match value {
+        Value::Int(n) if *n > 0 && eval.directory_service.contains(*n as u64) => Ok(*n as u64),
+        other => Err(signal(
+            "wrong-type-argument",
+            vec![Value::symbol("dired-service-listing-p"), other.clone()],
+        )),
+    }
+}
+
+/// Run F on the finished listing for the handle in ARGS[0], waiting for the
+/// worker if it is still scanning.
+fn with_listing<T>(
+    eval: &mut super::eval::Evaluator,
+    handle: &Value,
+    f: impl FnOnce(&DirectoryListing) -> T,
+) -> Result<T, Flow> {
+    let id = resolve_listing(eval, handle)?;
+    eval.directory_service.wait(id, None);
+    match eval.directory_service.listing(id) {
+        Some(ListingLookup::Ready(listing)) => Ok(f(listing)),
+        Some(ListingLookup::Failed(kind, message)) => Err(signal(
+            file_error_symbol(kind),
+            vec![
+                Value::string("Opening directory"),
+                Value::string(message),
+                Value::string(eval.directory_service.directory(id).unwrap_or("")),
+            ],
+        )),
+        _ => Err(signal(
+            "error",
+            vec![Value::string("Directory scan did not complete")],
+        )),
+    }
+}
+
+fn time_list(secs: i64) -> Value {
+    Value::list(vec![
+        Value::Int(secs >> 16),
+        Value::Int(secs & 0xffff),
+        Value::Int(0),
+        Value::Int(0),
+    ])
+}
+
+fn mode_string(mode: u32, kind: EntryKind) -> String {
+    let mut out = String::with_capacity(10);
+    out.push(match kind {
+        EntryKind::Directory => 'd',
+        EntryKind::Symlink => 'l',
+        EntryKind::File => '-',
+        EntryKind::Other => '?',
+    });
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 7;
+        out.push(if bits & 4 != 0 { 'r' } else { '-' });
+        out.push(if bits & 2 != 0 { 'w' } else { '-' });
+        out.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// Builtins
+// ---------------------------------------------------------------------------
+
+/// `(dired-service-list DIRECTORY &optional NO-VC)` -- start scanning
+/// DIRECTORY in the background and return a listing handle.  With NO-VC
+/// non-nil, skip collecting git status.
+pub(crate) fn builtin_dired_service_list(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("dired-service-list", &args, 1, 2)?;
+    let name = expect_string(&args[0])?;
+    let default_dir = eval
+        .obarray
+        .symbol_value("default-directory")
+        .and_then(|v| v.as_str().map(str::to_string));
+    let dir = super::fileio::expand_file_name(&name, default_dir.as_deref());
+    let with_git = !args.get(1).is_some_and(|v| v.is_truthy());
+    let id = eval.directory_service.request(&dir, with_git);
+    Ok(Value::Int(id as i64))
+}
+
+/// `(dired-service-ready-p LISTING)` -- non-nil once the scan is finished.
+pub(crate) fn builtin_dired_service_ready_p(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("dired-service-ready-p", &args, 1, 1)?;
+    let id = resolve_listing(eval, &args[0])?;
+    Ok(Value::bool(
+        eval.directory_service.is_ready(id).unwrap_or(false),
+    ))
+}
+
+/// `(dired-service-wait LISTING &optional TIMEOUT)` -- wait up to TIMEOUT
+/// seconds (forever if nil) for the scan; non-nil if it finished.
+pub(crate) fn builtin_dired_service_wait(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("dired-service-wait", &args, 1, 2)?;
+    let id = resolve_listing(eval, &args[0])?;
+    let timeout = match args.get(1) {
+        None | Some(Value::Nil) => None,
+        Some(v) => match v.as_float() {
+            Some(secs) if secs >= 0.0 => Some(Duration::from_secs_f64(secs)),
+            _ => {
+                return Err(signal(
+                    "wrong-type-argument",
+                    vec![Value::symbol("numberp"), v.clone()],
+                ))
+            }
+        },
+    };
+    Ok(Value::bool(
+        eval.directory_service.wait(id, timeout).unwrap_or(false),
+    ))
+}
+
+/// `(dired-service-directory LISTING)` -- the directory LISTING scans.
+pub(crate) fn builtin_dired_service_directory(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("dired-service-directory", &args, 1, 1)?;
+    let id = resolve_listing(eval, &args[0])?;
+    Ok(eval
+        .directory_service
+        .directory(id)
+        .map_or(Value::Nil, Value::string))
+}
+
+/// `(dired-service-count LISTING)` -- number of entries.
+pub(crate) fn builtin_dired_service_count(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("dired-service-count", &args, 1, 1)?;
+    with_listing(eval, &args[0], |listing| Value::Int(listing.len() as i64))
+}
+
+/// `(dired-service-column LISTING COLUMN)` -- a vector with one element per
+/// entry.  COLUMN is one of `name`, `kind`, `size`, `mtime`, `modes`,
+/// `mode-string`, `link-target`, `icon` or `vc-state`.
+pub(crate) fn builtin_dired_service_column(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("dired-service-column", &args, 2, 2)?;
+    let column = expect_symbol(&args[1])?.to_string();
+    let result = with_listing(eval, &args[0], |listing| {
+        let values: Vec<Value> = match column.as_str() {
+            "name" => listing.names.iter().map(Value::string).collect(),
+            "kind" => listing
+                .kinds
+                .iter()
+                .map(|k| Value::symbol(k.symbol_name()))
+                .collect(),
+            "size" => listing
+                .sizes
+                .iter()
+                .map(|&s| Value::Int(s as i64))
+                .collect(),
+            "mtime" => listing.mtimes.iter().map(|&t| time_list(t)).collect(),
+            "modes" => listing
+                .modes
+                .iter()
+                .map(|&m| Value::Int((m & 0o7777) as i64))
+                .collect(),
+            "mode-string" => listing
+                .modes
+                .iter()
+                .zip(&listing.kinds)
+                .map(|(&m, &k)| Value::string(mode_string(m, k)))
+                .collect(),
+            "link-target" => listing
+                .link_targets
+                .iter()
+                .map(|t| t.as_ref().map_or(Value::Nil, Value::string))
+                .collect(),
+            "icon" => listing.icons.iter().map(|&i| Value::string(i)).collect(),
+            "vc-state" => listing
+                .git
+                .iter()
+                .map(|g| g.map_or(Value::Nil, |s| Value::symbol(s.symbol_name())))
+                .collect(),
+            _ => return None,
+        };
+        Some(Value::vector(values))
+    })?;
+    result.ok_or_else(|| {
+        signal(
+            "error",
+            vec![Value::string("Unknown column"), args[1].clone()],
+        )
+    })
+}
+
+/// `(dired-service-sort LISTING KEY &optional REVERSE DIRS-FIRST)` -- a
+/// vector of entry indices ordered by KEY (`name`, `size`, `mtime` or
+/// `extension`).
+pub(crate) fn builtin_dired_service_sort(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("dired-service-sort", &args, 2, 4)?;
+    let key = SortKey::from_symbol(expect_symbol(&args[1])?).ok_or_else(|| {
+        signal(
+            "error",
+            vec![Value::string("Unknown sort key"), args[1].clone()],
+        )
+    })?;
+    let reverse = args.get(2).is_some_and(|v| v.is_truthy());
+    let dirs_first = args.get(3).is_some_and(|v| v.is_truthy());
+    with_listing(eval, &args[0], |listing| {
+        Value::vector(
+            listing
+                .sorted_indices(key, reverse, dirs_first)
+                .into_iter()
+                .map(|i| Value::Int(i as i64))
+                .collect(),
+        )
+    })
+}
+
+/// `(dired-service-notify FILE)` -- tell the service FILE changed.  Marks
+/// listings of FILE (a directory) or of its parent as stale and returns how
+/// many were affected.
+pub(crate) fn builtin_dired_service_notify(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("dired-service-notify", &args, 1, 1)?;
+    let file = expect_string(&args[0])?;
+    Ok(Value::Int(eval.directory_service.notify(&file) as i64))
+}
+
+/// `(dired-service-stale-p LISTING)` -- non-nil if LISTING is out of date.
+pub(crate) fn builtin_dired_service_stale_p(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("dired-service-stale-p", &args, 1, 1)?;
+    let id = resolve_listing(eval, &args[0])?;
+    Ok(Value::bool(
+        eval.directory_service.is_stale(id).unwrap_or(false),
+    ))
+}
+
+/// `(dired-service-refresh LISTING)` -- rescan in the background.
+pub(crate) fn builtin_dired_service_refresh(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("dired-service-refresh", &args, 1, 1)?;
+    let id = resolve_listing(eval, &args[0])?;
+    eval.directory_service.refresh(id);
+    Ok(args[0].clone())
+}
+
+/// `(dired-service-release LISTING)` -- free LISTING.
+pub(crate) fn builtin_dired_service_release(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("dired-service-release", &args, 1, 1)?;
+    let released = match &args[0] {
+        Value::Int(n) if *n > 0 => eval.directory_service.release(*n as u64),
+        _ => false,
+    };
+    Ok(Value::bool(released))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elisp::eval::Evaluator;
+
+    fn temp_dir(name: &str) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("neovm-dirsvc-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.to_string_lossy().into_owned()
+    }
+
+    // -----------------------------------------------------------------------
+    // Scanning
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn scan_collects_columns() {
+        let dir = temp_dir("scan");
+        fs::create_dir(format!("{dir}/sub")).unwrap();
+        fs::write(format!("{dir}/big.rs"), vec![b'x'; 100]).unwrap();
+        fs::write(format!("{dir}/a.png"), b"p").unwrap();
+
+        let listing = scan_directory(&dir, false).unwrap();
+        assert_eq!(listing.len(), 3);
+        let row = |name: &str| listing.names.iter().position(|n| n == name).unwrap();
+        assert_eq!(listing.kinds[row("sub")], EntryKind::Directory);
+        assert_eq!(listing.icons[row("sub")], "folder");
+        assert_eq!(listing.icons[row("big.rs")], "file-code");
+        assert_eq!(listing.icons[row("a.png")], "file-image");
+        assert_eq!(listing.sizes[row("big.rs")], 100);
+        assert!(listing.git.iter().all(Option::is_none));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn sorted_indices_orders() {
+        let mut listing = DirectoryListing::default();
+        listing.push("b.txt".into(), None, None);
+        listing.push("a.el".into(), None, None);
+        listing.push("c".into(), None, None);
+        listing.sizes = vec![10, 30, 20];
+        listing.kinds[2] = EntryKind::Directory;
+
+        let names = |order: Vec<usize>| -> Vec<&str> {
+            order.iter().map(|&i| listing.names[i].as_str()).collect()
+        };
+        assert_eq!(
+            names(listing.sorted_indices(SortKey::Name, false, false)),
+            ["a.el", "b.txt", "c"]
+        );
+        assert_eq!(
+            names(listing.sorted_indices(SortKey::Name, true, false)),
+            ["c", "b.txt", "a.el"]
+        );
+        assert_eq!(
+            names(listing.sorted_indices(SortKey::Size, false, false)),
+            ["a.el", "c", "b.txt"]
+        );
+        assert_eq!(
+            names(listing.sorted_indices(SortKey::Extension, false, false)),
+            ["c", "a.el", "b.txt"]
+        );
+        assert_eq!(
+            names(listing.sorted_indices(SortKey::Name, false, true)),
+            ["c", "a.el", "b.txt"]
+        );
+    }
+
+    #[test]
+    fn porcelain_parsing_and_directory_aggregation() {
+        let out = b"?? sub/new.txt\0 M sub/lib.rs\0M  top.c\0R  renamed.c\0orig.c\0!! other/x\0";
+        let statuses = parse_porcelain(out, "sub/");
+        assert_eq!(
+            statuses,
+            vec![
+                ("new.txt".to_string(), GitStatus::Untracked),
+                ("lib.rs".to_string(), GitStatus::Modified),
+            ]
+        );
+
+        let root = parse_porcelain(out, "");
+        assert!(root.contains(&("renamed.c".to_string(), GitStatus::Renamed)));
+        assert!(!root.iter().any(|(p, _)| p == "orig.c"));
+
+        let mut listing = DirectoryListing::default();
+        listing.push("sub".into(), None, None);
+        listing.push("top.c".into(), None, None);
+        apply_git_statuses(&mut listing, &root);
+        // The modified file outranks the untracked one for the directory.
+        assert_eq!(
+            listing.git,
+            vec![Some(GitStatus::Modified), Some(GitStatus::Modified)]
+        );
+    }
+
+    // -----------------------------------------------------------------------
+    // Service and builtins
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn notify_marks_parent_listing_stale() {
+        let dir = temp_dir("notify");
+        let mut service = DirectoryService::new();
+        let id = service.request(&dir, false);
+        assert_eq!(service.wait(id, None), Some(true));
+        assert_eq!(service.is_stale(id), Some(false));
+        assert_eq!(service.notify(&format!("{dir}/new-file")), 1);
+        assert_eq!(service.is_stale(id), Some(true));
+        assert!(service.refresh(id));
+        service.wait(id, None);
+        assert_eq!(service.is_stale(id), Some(false));
+        assert!(service.release(id));
+        assert!(!service.contains(id));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn watcher_marks_listing_stale_when_a_file_changes() {
+        let dir = temp_dir("watch");
+        fs::write(format!("{dir}/notes.txt"), b"old").unwrap();
+        let mut service = DirectoryService::new();
+        let id = service.request(&dir, false);
+        assert_eq!(service.wait(id, None), Some(true));
+        assert_eq!(service.is_stale(id), Some(false));
+        // Rewriting a file leaves the directory's mtime alone
+        fs::write(format!("{dir}/notes.txt"), b"new").unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while service.is_stale(id) != Some(true) && std::time::Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(service.is_stale(id), Some(true));
+        assert!(service.refresh(id));
+        service.wait(id, None);
+        assert_eq!(service.is_stale(id), Some(false));
+        assert!(service.release(id));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn builtins_round_trip() {
+        let dir = temp_dir("builtins");
+        fs::write(format!("{dir}/one.txt"), b"1").unwrap();
+        fs::write(format!("{dir}/two.txt"), b"22").unwrap();
+        let mut eval = Evaluator::new();
+
+        let handle =
+            builtin_dired_service_list(&mut eval, vec![Value::string(&dir), Value::True]).unwrap();
+        assert_eq!(
+            builtin_dired_service_wait(&mut eval, vec![handle.clone()]).unwrap(),
+            Value::True
+        );
+        assert_eq!(
+            builtin_dired_service_count(&mut eval, vec![handle.clone()]).unwrap(),
+            Value::Int(2)
+        );
+        let order =
+            builtin_dired_service_sort(&mut eval, vec![handle.clone(), Value::symbol("size")])
+                .unwrap();
+        let names =
+            builtin_dired_service_column(&mut eval, vec![handle.clone(), Value::symbol("name")])
+                .unwrap();
+        let (Value::Vector(order), Value::Vector(names)) = (order, names) else {
+            panic!("expected vectors");
+        };
+        let first = order.lock().unwrap()[0].as_int().unwrap() as usize;
+        assert_eq!(names.lock().unwrap()[first], Value::string("two.txt"));
+
+        let bad =
+            builtin_dired_service_column(&mut eval, vec![handle.clone(), Value::symbol("bogus")]);
+        assert!(bad.is_err());
+        assert_eq!(
+            builtin_dired_service_release(&mut eval, vec![handle.clone()]).unwrap(),
+            Value::True
+        );
+        assert!(builtin_dired_service_count(&mut eval, vec![handle]).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn missing_directory_signals_file_missing() {
+        let mut eval = Evaluator::new();
+        let handle =
+            builtin_dired_service_list(&mut eval, vec![Value::string("/nonexistent/neovm-dirsvc")])
+                .unwrap();
+        match builtin_dired_service_count(&mut eval, vec![handle]) {
+            Err(Flow::Signal(sig)) => assert_eq!(sig.symbol, "file-missing"),
+            other => panic!("expected file-missing, got {:?}", other),
+        }
+    }
+}
@@ -305,6 +305,15 @@ pub fn init_standard_errors(obarray: &mut Obarray) {
         &["json-error"],
     );
 
+    // --- sqlite-error family ---
+    register_simple(obarray, "sqlite-error", "Database error", &["error"]);
+    register_simple(
+        obarray,
+        "sqlite-locked-error",
+        "Database locked",
+        &["sqlite-error"],
+    );
+
     // --- remote-file-error (child of file-error) ---
     register_simple(
         obarray,
@@ -764,6 +773,14 @@ impl ErrorRegistry {
                 .insert(name.to_string(), vec!["json-error".to_string()]);
         }
 
+        // sqlite-error family.
+        self.parents
+            .insert("sqlite-error".to_string(), vec!["error".to_string()]);
+        self.parents.insert(
+            "sqlite-locked-error".to_string(),
+            vec!["sqlite-error".to_string()],
+        );
+
         // remote-file-error is a child of file-error.
         self.parents.insert(
             "remote-file-error".to_string(),
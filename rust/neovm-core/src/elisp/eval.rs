@@ -10,6 +10,7 @@ use super::builtins;
 use super::category::CategoryManager;
 use super::coding::CodingSystemManager;
 use super::custom::CustomManager;
+use super::dirsvc::DirectoryService;
 use super::error::*;
 use super::expr::Expr;
 use super::interactive::InteractiveRegistry;
@@ -22,11 +23,13 @@ use super::process::ProcessManager;
 use super::rect::RectangleState;
 use super::regex::MatchData;
 use super::register::RegisterManager;
+use super::sqlite::SqliteManager;
 use super::symbol::Obarray;
 use super::threads::ThreadManager;
 use super::timer::TimerManager;
 use super::value::*;
 use crate::buffer::BufferManager;
+use crate::remote::FileHandlerRegistry;
 use crate::window::FrameManager;
 
 #[derive(Clone, Debug)]
@@ -104,6 +107,14 @@ pub struct Evaluator {
     pub(crate) kmacro: KmacroManager,
     /// Coding system manager — encoding/decoding registry.
     pub(crate) coding_systems: CodingSystemManager,
+    /// SQLite manager — open databases and result sets.
+    pub(crate) sqlite: SqliteManager,
+    /// File name handlers — remote (TRAMP-style) file names.
+    pub(crate) file_handlers: FileHandlerRegistry,
+    /// Directory listing service — background scans for dired.
+    pub(crate) directory_service: DirectoryService,
+    /// Input events from the host, if one is connected.
+    input: Option<std::sync::mpsc::Receiver<crate::keyboard::InputEvent>>,
     /// Recursion depth counter.
     depth: usize,
     /// Maximum recursion depth.
@@ -213,6 +224,10 @@ impl Evaluator {
             category_manager: CategoryManager::new(),
             kmacro: KmacroManager::new(),
             coding_systems: CodingSystemManager::new(),
+            sqlite: SqliteManager::new(),
+            file_handlers: FileHandlerRegistry::new(),
+            directory_service: DirectoryService::new(),
+            input: None,
             depth: 0,
             max_depth: 200,
             named_call_cache: None,
@@ -319,6 +334,110 @@ impl Evaluator {
         self.obarray.set_symbol_function(name, value);
     }
 
+    /// Report when input went idle, or None when input arrives; the
+    /// display engine knows, and idle timers count from it.
+    pub fn set_input_idle(&mut self, since: Option<std::time::Instant>) {
+        self.timers.set_input_idle(since);
+    }
+
+    /// Connect the host's input events.  Reading an event waits on them,
+    /// running timers as they come due.
+    pub fn set_input_source(
+        &mut self,
+        events: std::sync::mpsc::Receiver<crate::keyboard::InputEvent>,
+    ) {
+        self.input = Some(events);
+    }
+
+    /// Wait for a key from the host, at most TIMEOUT, running timers as
+    /// they come due.  Input is idle from the moment none is pending until
+    /// a key arrives.  With no host connected nothing can arrive, so
+    /// without TIMEOUT this only runs the timers that are due.
+    pub(crate) fn wait_for_input(&mut self, timeout: Option<std::time::Duration>) -> Option<Value> {
+        use std::sync::mpsc::{RecvTimeoutError, TryRecvError};
+        use std::time::Instant;
+
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            // Pending input first: the user is not idle while it lasts
+            while let Some(events) = &self.input {
+                match events.try_recv() {
+                    Ok(event) => {
+                        if let Some(key) = input_event_key(event) {
+                            self.set_input_idle(None);
+                            return Some(key);
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => self.input = None,
+                }
+            }
+            let now = Instant::now();
+            if self.timers.idle_time(now).is_none() {
+                self.set_input_idle(Some(now));
+            }
+            self.run_timers();
+
+            let left = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+            if left == Some(std::time::Duration::ZERO) {
+                return None;
+            }
+            let wait = match (left, self.next_timer_delay()) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            let event = match (&self.input, wait) {
+                (Some(events), Some(wait)) => match events.recv_timeout(wait) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        self.input = None;
+                        None
+                    }
+                },
+                (Some(events), None) => match events.recv() {
+                    Ok(event) => Some(event),
+                    Err(_) => {
+                        self.input = None;
+                        None
+                    }
+                },
+                (None, _) if deadline.is_none() => return None,
+                (None, wait) => {
+                    std::thread::sleep(wait.unwrap_or_default());
+                    None
+                }
+            };
+            if let Some(key) = event.and_then(input_event_key) {
+                self.set_input_idle(None);
+                return Some(key);
+            }
+        }
+    }
+
+    /// How long the host may wait for input before a timer is due, or
+    /// None if no timer is scheduled.
+    pub fn next_timer_delay(&self) -> Option<std::time::Duration> {
+        self.timers.next_fire_time()
+    }
+
+    /// Run the callbacks of the timers that are due, timing each for
+    /// `timer-statistics`.  An error in one does not stop the others.
+    /// Returns how many ran.
+    pub fn run_timers(&mut self) -> usize {
+        let due = self.timers.fire_due(std::time::Instant::now());
+        for &id in &due {
+            let Some(timer) = self.timers.timer(id) else {
+                continue;
+            };
+            let (callback, args) = (timer.callback.clone(), timer.args.clone());
+            let started = std::time::Instant::now();
+            let failed = matches!(self.apply(callback, args), Err(Flow::Signal(_)));
+            self.timers.record_run(id, started.elapsed(), failed);
+        }
+        due.len()
+    }
+
     // -----------------------------------------------------------------------
     // Core eval
     // -----------------------------------------------------------------------
@@ -1952,6 +2071,15 @@ fn rewrite_wrong_arity_function_object(flow: Flow, name: &str) -> Flow {
 // Helpers
 // ---------------------------------------------------------------------------
 
+/// The Lisp event for a key press from the host; other events are not
+/// read yet.
+fn input_event_key(event: crate::keyboard::InputEvent) -> Option<Value> {
+    match event {
+        crate::keyboard::InputEvent::KeyPress(key) => Some(Value::Int(key.to_event_int() as i64)),
+        _ => None,
+    }
+}
+
 /// Convert an Expr AST node to a Value (for quote).
 pub fn quote_to_value(expr: &Expr) -> Value {
     match expr {
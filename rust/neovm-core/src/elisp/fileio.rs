@@ -17,7 +17,10 @@ use regex::Regex;
 
 use super::error::{signal, EvalResult, Flow};
 use super::eval::Evaluator;
+use super::string_escape::{bytes_to_storage_string, storage_string_to_bytes};
 use super::value::{list_to_vec, Value};
+use crate::encoding::decode_bytes;
+use crate::remote::{FileAttrs, FileHandler, RemotePath};
 
 // ===========================================================================
 // Path operations (pure, no evaluator needed)
@@ -646,7 +649,26 @@ fn directory_files(
     };
 
     let names = read_directory_names(dir)?;
+    Ok(filter_directory_names(
+        dir,
+        names,
+        full,
+        re.as_ref(),
+        nosort,
+        count,
+    ))
+}
 
+/// Apply `directory-files` MATCH/FULL/NOSORT/COUNT handling to raw entry
+/// NAMES read from DIR.
+fn filter_directory_names(
+    dir: &str,
+    names: Vec<String>,
+    full: bool,
+    re: Option<&Regex>,
+    nosort: bool,
+    count: Option<usize>,
+) -> Vec<String> {
     // Emacs builds this list via `cons` while scanning readdir output.
     // That makes NOSORT results reverse the traversal order and applies COUNT
     // before sort.
@@ -659,7 +681,7 @@ fn directory_files(
     };
 
     for name in names {
-        if let Some(re) = re.as_ref() {
+        if let Some(re) = re {
             if !re.is_match(&name) {
                 continue;
             }
@@ -683,7 +705,7 @@ fn directory_files(
     if !nosort {
         result.sort();
     }
-    Ok(result)
+    result
 }
 
 /// Create directory DIR.  If PARENTS is true, create parent directories as needed.
@@ -1383,6 +1405,33 @@ fn default_directory_for_eval(eval: &Evaluator) -> Option<String> {
     }
 }
 
+/// The coding system to decode remote file contents with:
+/// `coding-system-for-read` if bound to one, else UTF-8.
+fn coding_system_for_read(eval: &Evaluator) -> String {
+    let value = eval
+        .dynamic
+        .iter()
+        .rev()
+        .find_map(|frame| frame.get("coding-system-for-read").cloned())
+        .or_else(|| eval.obarray.symbol_value("coding-system-for-read").cloned());
+    match value.as_ref().and_then(|v| v.as_symbol_name()) {
+        Some(name) if name != "nil" => name.to_string(),
+        _ => "utf-8".to_string(),
+    }
+}
+
+/// Decode the contents of a remote file.  UTF-8 and unknown coding systems
+/// keep invalid sequences as raw bytes, so a remote file that is not UTF-8
+/// still round-trips when it is written back.
+fn decode_remote_contents(bytes: &[u8], coding_system: &str) -> String {
+    match coding_system {
+        "latin-1" | "iso-8859-1" | "iso-latin-1" | "ascii" | "us-ascii" => {
+            decode_bytes(bytes, coding_system)
+        }
+        _ => bytes_to_storage_string(bytes),
+    }
+}
+
 pub(crate) fn resolve_filename_for_eval(eval: &Evaluator, filename: &str) -> String {
     if filename.is_empty() || Path::new(filename).is_absolute() {
         return filename.to_string();
@@ -1516,6 +1565,9 @@ pub(crate) fn builtin_file_exists_p_eval(eval: &Evaluator, args: Vec<Value>) ->
     expect_args("file-exists-p", &args, 1)?;
     let filename = expect_string_strict(&args[0])?;
     let filename = resolve_filename_for_eval(eval, &filename);
+    if let Some(result) = remote_file_predicate(eval, &filename, |_| true) {
+        return result;
+    }
     Ok(Value::bool(file_exists_p(&filename)))
 }
 
@@ -1532,6 +1584,12 @@ pub(crate) fn builtin_file_readable_p_eval(eval: &Evaluator, args: Vec<Value>) -
     expect_args("file-readable-p", &args, 1)?;
     let filename = expect_string_strict(&args[0])?;
     let filename = resolve_filename_for_eval(eval, &filename);
+    if let Some((handler, path)) = eval.file_handlers.find(&filename) {
+        return handler
+            .file_readable(&path)
+            .map(Value::bool)
+            .map_err(|err| signal_remote_io_path(err, "Checking", &filename));
+    }
     Ok(Value::bool(file_readable_p(&filename)))
 }
 
@@ -1564,6 +1622,9 @@ pub(crate) fn builtin_file_directory_p_eval(eval: &Evaluator, args: Vec<Value>)
     expect_args("file-directory-p", &args, 1)?;
     let filename = expect_string_strict(&args[0])?;
     let filename = resolve_filename_for_eval(eval, &filename);
+    if let Some(result) = remote_file_predicate(eval, &filename, FileAttrs::is_dir) {
+        return result;
+    }
     Ok(Value::bool(file_directory_p(&filename)))
 }
 
@@ -1580,6 +1641,9 @@ pub(crate) fn builtin_file_regular_p_eval(eval: &Evaluator, args: Vec<Value>) ->
     expect_args("file-regular-p", &args, 1)?;
     let filename = expect_string_strict(&args[0])?;
     let filename = resolve_filename_for_eval(eval, &filename);
+    if let Some(result) = remote_file_predicate(eval, &filename, FileAttrs::is_regular) {
+        return result;
+    }
     Ok(Value::bool(file_regular_p(&filename)))
 }
 
@@ -1881,6 +1945,14 @@ pub(crate) fn builtin_delete_file_eval(eval: &Evaluator, args: Vec<Value>) -> Ev
     }
     let filename = expect_string_strict(&args[0])?;
     let filename = resolve_filename_for_eval(eval, &filename);
+    if let Some((handler, path)) = eval.file_handlers.find(&filename) {
+        return match handler.delete_file(&path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(signal_remote_io_path(err, "Removing old name", &filename))
+            }
+            _ => Ok(Value::Nil),
+        };
+    }
     delete_file_compat(&filename)?;
     Ok(Value::Nil)
 }
@@ -1924,6 +1996,15 @@ pub(crate) fn builtin_delete_directory_eval(eval: &Evaluator, args: Vec<Value>)
     let directory = expect_string_strict(&args[0])?;
     let directory = resolve_filename_for_eval(eval, &directory);
     let recursive = args.get(1).is_some_and(|value| value.is_truthy());
+    if let Some((handler, path)) = eval.file_handlers.find(&directory) {
+        if recursive {
+            remote_delete_tree(handler.as_ref(), &path)
+        } else {
+            handler.delete_directory(&path)
+        }
+        .map_err(|err| signal_remote_io_path(err, "Removing directory", &directory))?;
+        return Ok(Value::Nil);
+    }
     let result = if recursive {
         fs::remove_dir_all(&directory)
     } else {
@@ -2060,6 +2141,9 @@ pub(crate) fn builtin_rename_file_eval(eval: &Evaluator, args: Vec<Value>) -> Ev
     let from = resolve_filename_for_eval(eval, &expect_string_strict(&args[0])?);
     let to = resolve_filename_for_eval(eval, &expect_string_strict(&args[1])?);
     let ok_if_exists = args.get(2).is_some_and(|value| value.is_truthy());
+    if eval.file_handlers.is_remote(&from) || eval.file_handlers.is_remote(&to) {
+        return remote_rename_file(eval, &from, &to, ok_if_exists);
+    }
     if fs::symlink_metadata(&to).is_ok() {
         if ok_if_exists {
             fs::remove_file(&to).map_err(|e| signal_file_io_path(e, "Removing old name", &to))?;
@@ -2209,6 +2293,11 @@ pub(crate) fn builtin_make_directory_eval(eval: &Evaluator, args: Vec<Value>) ->
     }
     let dir = resolve_filename_for_eval(eval, &expect_string_strict(&args[0])?);
     let parents = args.get(1).is_some_and(|v| v.is_truthy());
+    if let Some((handler, path)) = eval.file_handlers.find(&dir) {
+        remote_make_directory(handler.as_ref(), &path, parents)
+            .map_err(|e| signal_remote_io_path(e, "Creating directory", &dir))?;
+        return Ok(Value::Nil);
+    }
     make_directory(&dir, parents)
         .map_err(|e| signal_file_io_path(e, "Creating directory", &dir))?;
     Ok(Value::Nil)
@@ -2296,11 +2385,222 @@ pub(crate) fn builtin_directory_files_eval(eval: &Evaluator, args: Vec<Value>) -
         None
     };
 
+    if let Some((handler, path)) = eval.file_handlers.find(&dir) {
+        return remote_directory_files(
+            handler.as_ref(),
+            &path,
+            &dir,
+            full,
+            match_pattern.as_deref(),
+            nosort,
+            count,
+        );
+    }
     let files = directory_files(&dir, full, match_pattern.as_deref(), nosort, count)
         .map_err(|e| signal_directory_files_error(e, &dir))?;
     Ok(Value::list(files.into_iter().map(Value::string).collect()))
 }
 
+// ===========================================================================
+// Remote file name handlers
+// ===========================================================================
+
+/// Like `signal_file_io_path`, but failures other than missing files and
+/// permission problems are reported as `remote-file-error`.
+fn signal_remote_io_path(err: std::io::Error, action: &str, path: &str) -> Flow {
+    let symbol = match err.kind() {
+        ErrorKind::NotFound | ErrorKind::AlreadyExists | ErrorKind::PermissionDenied => {
+            file_error_symbol(err.kind())
+        }
+        _ => "remote-file-error",
+    };
+    signal(
+        symbol,
+        vec![Value::string(format!("{action} {path}: {err}"))],
+    )
+}
+
+/// Answer a file predicate for a remote FILENAME.  Returns `None` when no
+/// handler claims the name, so the caller falls back to the local check.
+fn remote_file_predicate(
+    eval: &Evaluator,
+    filename: &str,
+    pred: impl FnOnce(&FileAttrs) -> bool,
+) -> Option<EvalResult> {
+    let (handler, path) = eval.file_handlers.find(filename)?;
+    Some(
+        handler
+            .file_attributes(&path)
+            .map(|attrs| Value::bool(attrs.as_ref().is_some_and(pred)))
+            .map_err(|err| signal_remote_io_path(err, "Checking", filename)),
+    )
+}
+
+fn remote_directory_files(
+    handler: &dyn FileHandler,
+    path: &RemotePath,
+    dir: &str,
+    full: bool,
+    match_regex: Option<&str>,
+    nosort: bool,
+    count: Option<usize>,
+) -> EvalResult {
+    if count == Some(0) {
+        return Ok(Value::Nil);
+    }
+    let re = match match_regex {
+        Some(pattern) => Some(Regex::new(pattern).map_err(|e| {
+            signal(
+                "invalid-regexp",
+                vec![Value::string(format!(
+                    "Invalid regexp \"{}\": {}",
+                    pattern, e
+                ))],
+            )
+        })?),
+        None => None,
+    };
+    let entries = handler
+        .directory_files(path)
+        .map_err(|err| signal_remote_io_path(err, "Opening directory", dir))?;
+    let mut names = vec![".".to_string(), "..".to_string()];
+    names.extend(entries.into_iter().map(|entry| entry.name));
+    let files = filter_directory_names(dir, names, full, re.as_ref(), nosort, count);
+    Ok(Value::list(files.into_iter().map(Value::string).collect()))
+}
+
+fn remote_make_directory(
+    handler: &dyn FileHandler,
+    path: &RemotePath,
+    parents: bool,
+) -> std::io::Result<()> {
+    if !parents {
+        return handler.make_directory(path);
+    }
+    let absolute = path.localname.starts_with('/');
+    let mut current = String::new();
+    for component in path.localname.split('/').filter(|c| !c.is_empty()) {
+        if !current.is_empty() || absolute {
+            current.push('/');
+        }
+        current.push_str(component);
+        let step = path.with_localname(current.clone());
+        match handler.file_attributes(&step)? {
+            Some(attrs) if attrs.is_dir() => continue,
+            Some(_) => {
+                return Err(std::io::Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("{} is not a directory", step),
+                ))
+            }
+            None => handler.make_directory(&step)?,
+        }
+    }
+    Ok(())
+}
+
+fn remote_delete_tree(handler: &dyn FileHandler, path: &RemotePath) -> std::io::Result<()> {
+    for entry in handler.directory_files(path)? {
+        let child = path.with_localname(format!(
+            "{}/{}",
+            path.localname.trim_end_matches('/'),
+            entry.name
+        ));
+        if entry.attrs.is_dir() {
+            remote_delete_tree(handler, &child)?;
+        } else {
+            handler.delete_file(&child)?;
+        }
+    }
+    handler.delete_directory(path)
+}
+
+/// `rename-file` where at least one side is remote.  Renames on one
+/// connection are done by the server; everything else is copy + delete.
+fn remote_rename_file(eval: &Evaluator, from: &str, to: &str, ok_if_exists: bool) -> EvalResult {
+    let source = eval.file_handlers.find(from);
+    let target = eval.file_handlers.find(to);
+    let target_exists = match &target {
+        Some((handler, path)) => handler
+            .file_attributes(path)
+            .map_err(|e| signal_remote_io_path(e, "Renaming", to))?
+            .is_some(),
+        None => fs::symlink_metadata(to).is_ok(),
+    };
+    if target_exists && !ok_if_exists {
+        return Err(signal(
+            "file-already-exists",
+            vec![
+                Value::string("Renaming"),
+                Value::string(format!("File exists: {to}")),
+                Value::string(from),
+                Value::string(to),
+            ],
+        ));
+    }
+    if let (Some((handler, src)), Some((_, dst))) = (&source, &target) {
+        if src.same_connection(dst) {
+            if target_exists {
+                let _ = handler.delete_file(dst);
+            }
+            handler
+                .rename_file(src, dst)
+                .map_err(|e| signal_remote_io_path(e, "Renaming", from))?;
+            return Ok(Value::Nil);
+        }
+    }
+    let data = match &source {
+        Some((handler, path)) => handler.read_file(path),
+        None => fs::read(from),
+    }
+    .map_err(|e| signal_remote_io_path(e, "Renaming", from))?;
+    match &target {
+        Some((handler, path)) => handler.write_file(path, &data, false),
+        None => fs::write(to, &data),
+    }
+    .map_err(|e| signal_remote_io_path(e, "Renaming", to))?;
+    match &source {
+        Some((handler, path)) => handler.delete_file(path),
+        None => fs::remove_file(from),
+    }
+    .map_err(|e| signal_remote_io_path(e, "Removing old name", from))?;
+    Ok(Value::Nil)
+}
+
+/// (file-remote-p FILE &optional IDENTIFICATION CONNECTED)
+///
+/// Returns the remote prefix of FILE, or the component named by
+/// IDENTIFICATION (`method`, `user`, `host`, `localname`).  With CONNECTED
+/// non-nil, returns nil unless a connection to the host is already open.
+pub(crate) fn builtin_file_remote_p(eval: &Evaluator, args: Vec<Value>) -> EvalResult {
+    expect_min_args("file-remote-p", &args, 1)?;
+    if args.len() > 3 {
+        return Err(signal(
+            "wrong-number-of-arguments",
+            vec![
+                Value::symbol("file-remote-p"),
+                Value::Int(args.len() as i64),
+            ],
+        ));
+    }
+    let filename = resolve_filename_for_eval(eval, &expect_string_strict(&args[0])?);
+    let Some((handler, path)) = eval.file_handlers.find(&filename) else {
+        return Ok(Value::Nil);
+    };
+    if args.get(2).is_some_and(|v| v.is_truthy()) && !handler.connected(&path) {
+        return Ok(Value::Nil);
+    }
+    Ok(match args.get(1).and_then(|v| v.as_symbol_name()) {
+        None | Some("nil") => Value::string(path.prefix()),
+        Some("method") => Value::string(path.method.clone()),
+        Some("user") => path.user.clone().map(Value::string).unwrap_or(Value::Nil),
+        Some("host") => Value::string(path.host.clone()),
+        Some("localname") => Value::string(path.localname.clone()),
+        Some(_) => Value::Nil,
+    })
+}
+
+
 // ===========================================================================
 // Evaluator-dependent builtins
 // ===========================================================================
@@ -2330,8 +2630,14 @@ pub(crate) fn builtin_insert_file_contents(
     let visit = args.get(1).is_some_and(|v| v.is_truthy());
 
     // Read file contents
-    let contents = read_file_contents(&resolved)
-        .map_err(|e| signal_file_io_path(e, "Opening input file", &resolved))?;
+    let contents = match eval.file_handlers.find(&resolved) {
+        Some((handler, path)) => handler
+            .read_file(&path)
+            .map(|bytes| decode_remote_contents(&bytes, &coding_system_for_read(eval)))
+            .map_err(|e| signal_remote_io_path(e, "Opening input file", &resolved))?,
+        None => read_file_contents(&resolved)
+            .map_err(|e| signal_file_io_path(e, "Opening input file", &resolved))?,
+    };
 
     let char_count = contents.chars().count() as i64;
 
@@ -2387,8 +2693,14 @@ pub(crate) fn builtin_write_region(
         buf.buffer_substring(byte_start, byte_end)
     };
 
-    write_string_to_file(&content, &resolved, append)
-        .map_err(|e| signal_file_io_path(e, "Writing to", &resolved))?;
+    // Raw bytes kept by `decode_remote_contents` are written back as they were
+    match eval.file_handlers.find(&resolved) {
+        Some((handler, path)) => handler
+            .write_file(&path, &storage_string_to_bytes(&content), append)
+            .map_err(|e| signal_remote_io_path(e, "Writing to", &resolved))?,
+        None => write_string_to_file(&content, &resolved, append)
+            .map_err(|e| signal_file_io_path(e, "Writing to", &resolved))?,
+    }
 
     if visit {
         // Need mutable access to set file_name and modified flag
@@ -4524,4 +4836,282 @@ mod tests {
             other => panic!("Expected Buffer, got {:?}", other),
         }
     }
+
+    // -----------------------------------------------------------------------
+    // Remote file name handlers
+    // -----------------------------------------------------------------------
+
+    /// Handler that serves `/mock:host:` names from a local directory.
+    struct LocalDirHandler {
+        root: std::path::PathBuf,
+    }
+
+    impl LocalDirHandler {
+        fn local(&self, path: &RemotePath) -> std::path::PathBuf {
+            self.root.join(path.localname.trim_start_matches('/'))
+        }
+    }
+
+    fn attrs_for(meta: &fs::Metadata) -> FileAttrs {
+        use std::os::unix::fs::PermissionsExt;
+        FileAttrs {
+            size: Some(meta.len()),
+            permissions: Some(meta.permissions().mode()),
+            ..FileAttrs::default()
+        }
+    }
+
+    impl FileHandler for LocalDirHandler {
+        fn file_attributes(&self, path: &RemotePath) -> std::io::Result<Option<FileAttrs>> {
+            match fs::symlink_metadata(self.local(path)) {
+                Ok(meta) => Ok(Some(attrs_for(&meta))),
+                Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err),
+            }
+        }
+        fn file_readable(&self, path: &RemotePath) -> std::io::Result<bool> {
+            // By the mode bits, as the tests run with any privileges
+            Ok(self
+                .file_attributes(path)?
+                .and_then(|attrs| attrs.permissions)
+                .is_some_and(|mode| mode & 0o444 != 0))
+        }
+        fn read_file(&self, path: &RemotePath) -> std::io::Result<Vec<u8>> {
+            fs::read(self.local(path))
+        }
+        fn write_file(&self, path: &RemotePath, data: &[u8], append: bool) -> std::io::Result<()> {
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(append)
+                .truncate(!append)
+                .open(self.local(path))?;
+            file.write_all(data)
+        }
+        fn directory_files(
+            &self,
+            path: &RemotePath,
+        ) -> std::io::Result<Vec<crate::remote::DirEntry>> {
+            fs::read_dir(self.local(path))?
+                .map(|entry| {
+                    let entry = entry?;
+                    Ok(crate::remote::DirEntry {
+                        name: entry.file_name().to_string_lossy().into_owned(),
+                        longname: String::new(),
+                        attrs: attrs_for(&entry.metadata()?),
+                    })
+                })
+                .collect()
+        }
+        fn delete_file(&self, path: &RemotePath) -> std::io::Result<()> {
+            fs::remove_file(self.local(path))
+        }
+        fn delete_directory(&self, path: &RemotePath) -> std::io::Result<()> {
+            fs::remove_dir(self.local(path))
+        }
+        fn make_directory(&self, path: &RemotePath) -> std::io::Result<()> {
+            fs::create_dir(self.local(path))
+        }
+        fn rename_file(&self, from: &RemotePath, to: &RemotePath) -> std::io::Result<()> {
+            fs::rename(self.local(from), self.local(to))
+        }
+        fn connected(&self, _path: &RemotePath) -> bool {
+            true
+        }
+    }
+
+    fn remote_test_evaluator(name: &str) -> (super::super::eval::Evaluator, std::path::PathBuf) {
+        let root =
+            std::env::temp_dir().join(format!("neovm-remote-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let mut eval = super::super::eval::Evaluator::new();
+        eval.file_handlers = crate::remote::FileHandlerRegistry::empty();
+        eval.file_handlers.register(
+            "mock",
+            std::sync::Arc::new(LocalDirHandler { root: root.clone() }),
+        );
+        (eval, root)
+    }
+
+    #[test]
+    fn test_file_remote_p_components() {
+        let (eval, root) = remote_test_evaluator("components");
+        let file = Value::string("/mock:alice@example.org:/etc/hosts");
+        assert_eq!(
+            builtin_file_remote_p(&eval, vec![file.clone()]).unwrap(),
+            Value::string("/mock:alice@example.org:")
+        );
+        let part =
+            |id: &str| builtin_file_remote_p(&eval, vec![file.clone(), Value::symbol(id)]).unwrap();
+        assert_eq!(part("method"), Value::string("mock"));
+        assert_eq!(part("user"), Value::string("alice"));
+        assert_eq!(part("host"), Value::string("example.org"));
+        assert_eq!(part("localname"), Value::string("/etc/hosts"));
+        assert_eq!(
+            builtin_file_remote_p(&eval, vec![Value::string("/etc/hosts")]).unwrap(),
+            Value::Nil
+        );
+        // Unregistered methods are treated as local names.
+        assert_eq!(
+            builtin_file_remote_p(&eval, vec![Value::string("/ssh:host:/tmp")]).unwrap(),
+            Value::Nil
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_remote_predicates_and_directory_files() {
+        let (eval, root) = remote_test_evaluator("predicates");
+        fs::create_dir(root.join("sub")).unwrap();
+        fs::write(root.join("a.txt"), "a").unwrap();
+        fs::write(root.join("b.el"), "b").unwrap();
+
+        let exists =
+            |name: &str| builtin_file_exists_p_eval(&eval, vec![Value::string(name)]).unwrap();
+        assert!(exists("/mock:h:/a.txt").is_truthy());
+        assert!(exists("/mock:h:/missing").is_nil());
+        assert!(
+            builtin_file_directory_p_eval(&eval, vec![Value::string("/mock:h:/sub")])
+                .unwrap()
+                .is_truthy()
+        );
+        assert!(
+            builtin_file_regular_p_eval(&eval, vec![Value::string("/mock:h:/sub")])
+                .unwrap()
+                .is_nil()
+        );
+
+        let files = builtin_directory_files_eval(&eval, vec![Value::string("/mock:h:/")]).unwrap();
+        let names: Vec<String> = list_to_vec(&files)
+            .unwrap()
+            .iter()
+            .map(|v| v.as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(names, vec![".", "..", "a.txt", "b.el", "sub"]);
+
+        let full = builtin_directory_files_eval(
+            &eval,
+            vec![
+                Value::string("/mock:h:/"),
+                Value::True,
+                Value::string("\\.el$"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            list_to_vec(&full).unwrap(),
+            vec![Value::string("/mock:h:/b.el")]
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_remote_readable_p_checks_access() {
+        use std::os::unix::fs::PermissionsExt;
+        let (eval, root) = remote_test_evaluator("readable");
+        fs::write(root.join("open"), "a").unwrap();
+        fs::write(root.join("locked"), "b").unwrap();
+        fs::set_permissions(root.join("locked"), fs::Permissions::from_mode(0o200)).unwrap();
+        let readable =
+            |name: &str| builtin_file_readable_p_eval(&eval, vec![Value::string(name)]).unwrap();
+        assert!(readable("/mock:h:/open").is_truthy());
+        assert!(readable("/mock:h:/locked").is_nil());
+        assert!(readable("/mock:h:/missing").is_nil());
+        // It still exists
+        assert!(builtin_file_exists_p_eval(&eval, vec![Value::string("/mock:h:/locked")])
+            .unwrap()
+            .is_truthy());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_remote_insert_file_contents_keeps_raw_bytes() {
+        use crate::elisp::string_escape::bytes_to_storage_string;
+        let (mut eval, root) = remote_test_evaluator("rawbytes");
+        let bytes = b"caf\xe9 \xff\xfe ok";
+        fs::write(root.join("latin"), bytes).unwrap();
+
+        builtin_insert_file_contents(&mut eval, vec![Value::string("/mock:h:/latin")]).unwrap();
+        let text = eval.buffers.current_buffer().unwrap().buffer_string();
+        assert_eq!(text, bytes_to_storage_string(bytes));
+        assert!(!text.contains('\u{fffd}'));
+
+        // With the coding system bound, the bytes decode as Latin-1
+        let buf = eval.buffers.current_buffer_mut().unwrap();
+        let end = buf.point_max();
+        buf.delete_region(buf.point_min(), end);
+        eval.dynamic.push(
+            [("coding-system-for-read".to_string(), Value::symbol("latin-1"))]
+                .into_iter()
+                .collect(),
+        );
+        builtin_insert_file_contents(&mut eval, vec![Value::string("/mock:h:/latin")]).unwrap();
+        eval.dynamic.pop();
+        assert_eq!(
+            eval.buffers.current_buffer().unwrap().buffer_string(),
+            "café \u{ff}\u{fe} ok"
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_remote_invalid_utf8_round_trips() {
+        let (mut eval, root) = remote_test_evaluator("roundtrip");
+        let bytes = b"a\xff\xc3\xa9b\n\xe9t\xe9 \xf4\x90\x80\x80\n";
+        fs::write(root.join("in"), bytes).unwrap();
+
+        builtin_insert_file_contents(&mut eval, vec![Value::string("/mock:h:/in")]).unwrap();
+        assert!(!eval
+            .buffers
+            .current_buffer()
+            .unwrap()
+            .buffer_string()
+            .contains('\u{fffd}'));
+        builtin_write_region(
+            &mut eval,
+            vec![Value::Nil, Value::Nil, Value::string("/mock:h:/out")],
+        )
+        .unwrap();
+        assert_eq!(fs::read(root.join("out")).unwrap(), bytes);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_remote_make_delete_and_rename() {
+        let (eval, root) = remote_test_evaluator("mutate");
+        builtin_make_directory_eval(&eval, vec![Value::string("/mock:h:/x/y/z"), Value::True])
+            .unwrap();
+        assert!(root.join("x/y/z").is_dir());
+
+        fs::write(root.join("x/y/file"), "payload").unwrap();
+        builtin_rename_file_eval(
+            &eval,
+            vec![
+                Value::string("/mock:h:/x/y/file"),
+                Value::string("/mock:h:/moved"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(root.join("moved")).unwrap(), "payload");
+
+        // Cross-handler rename copies the data and removes the source.
+        let local = std::env::temp_dir().join(format!("neovm-remote-local-{}", std::process::id()));
+        builtin_rename_file_eval(
+            &eval,
+            vec![
+                Value::string("/mock:h:/moved"),
+                Value::string(local.to_string_lossy()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(&local).unwrap(), "payload");
+        assert!(!root.join("moved").exists());
+        let _ = fs::remove_file(&local);
+
+        builtin_delete_directory_eval(&eval, vec![Value::string("/mock:h:/x"), Value::True])
+            .unwrap();
+        assert!(!root.join("x").exists());
+        let _ = fs::remove_dir_all(&root);
+    }
 }
@@ -185,6 +185,24 @@ fn pop_unread_command_event(eval: &mut super::eval::Evaluator) -> Option<Value>
     }
 }
 
+/// How long to wait for input given an optional SECONDS argument; nil
+/// waits until input arrives.
+pub(crate) fn input_timeout(seconds: Option<&Value>) -> Result<Option<std::time::Duration>, Flow> {
+    match seconds {
+        None | Some(Value::Nil) => Ok(None),
+        Some(value) => match value.as_number_f64() {
+            Some(secs) if secs.is_finite() => {
+                Ok(Some(std::time::Duration::from_secs_f64(secs.max(0.0))))
+            }
+            Some(_) => Ok(None),
+            None => Err(signal(
+                "wrong-type-argument",
+                vec![Value::symbol("numberp"), value.clone()],
+            )),
+        },
+    }
+}
+
 fn event_to_int(event: &Value) -> Option<i64> {
     match event {
         Value::Int(n) => Some(*n),
@@ -205,7 +223,8 @@ fn expect_optional_prompt_string(args: &[Value]) -> Result<(), Flow> {
 
 /// `(read-event &optional PROMPT INHERIT-INPUT-METHOD SECONDS)`
 ///
-/// Stub: returns nil (no event input available in batch mode).
+/// Returns the next `unread-command-events` event, else waits for one
+/// from the host; nil when none arrives (always, in batch mode).
 pub(crate) fn builtin_read_event(
     eval: &mut super::eval::Evaluator,
     args: Vec<Value>,
@@ -217,7 +236,12 @@ pub(crate) fn builtin_read_event(
         ));
     }
     expect_optional_prompt_string(&args)?;
-    if let Some(event) = pop_unread_command_event(eval) {
+    let timeout = input_timeout(args.get(2))?;
+    let event = match pop_unread_command_event(eval) {
+        Some(event) => Some(event),
+        None => eval.wait_for_input(timeout),
+    };
+    if let Some(event) = event {
         if let Some(n) = event_to_int(&event) {
             return Ok(Value::Int(n));
         }
@@ -228,7 +252,7 @@ pub(crate) fn builtin_read_event(
 
 /// `(read-char-exclusive &optional PROMPT INHERIT-INPUT-METHOD SECONDS)`
 ///
-/// Batch stub: returns nil (no terminal input available).
+/// Like `read-event`, skipping non-character events.
 pub(crate) fn builtin_read_char_exclusive(
     eval: &mut super::eval::Evaluator,
     args: Vec<Value>,
@@ -243,12 +267,16 @@ pub(crate) fn builtin_read_char_exclusive(
         ));
     }
     expect_optional_prompt_string(&args)?;
+    let timeout = input_timeout(args.get(2))?;
     while let Some(event) = pop_unread_command_event(eval) {
         if let Some(n) = event_to_int(&event) {
             return Ok(Value::Int(n));
         }
         // Skip non-character events.
     }
+    if let Some(n) = eval.wait_for_input(timeout).as_ref().and_then(event_to_int) {
+        return Ok(Value::Int(n));
+    }
     Ok(Value::Nil)
 }
 
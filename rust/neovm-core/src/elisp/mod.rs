@@ -31,6 +31,7 @@ pub mod composite;
 pub mod custom;
 pub mod debug;
 pub mod dired;
+pub mod dirsvc;
 pub mod display;
 pub mod doc;
 pub mod editfns;
@@ -70,6 +71,7 @@ pub mod regex;
 pub mod register;
 pub mod search;
 pub mod setf;
+pub mod sqlite;
 pub mod subr_info;
 pub mod symbol;
 pub mod syntax;
@@ -78,6 +80,7 @@ pub mod textprop;
 pub mod threads;
 pub mod timefns;
 pub mod timer;
+pub mod timer_wheel;
 pub mod undo;
 pub mod value;
 pub mod window_cmds;
@@ -69,6 +69,7 @@ pub fn print_value(value: &Value) -> String {
         }
         Value::Buffer(id) => format!("#<buffer {}>", id.0),
         Value::Timer(id) => format!("#<timer {}>", id),
+        Value::Sqlite(id) => format!("#<sqlite {}>", id),
     }
 }
 
@@ -148,6 +149,7 @@ fn append_print_value_bytes(value: &Value, out: &mut Vec<u8>) {
         }
         Value::Buffer(id) => out.extend_from_slice(format!("#<buffer {}>", id.0).as_bytes()),
         Value::Timer(id) => out.extend_from_slice(format!("#<timer {}>", id).as_bytes()),
+        Value::Sqlite(id) => out.extend_from_slice(format!("#<sqlite {}>", id).as_bytes()),
     }
 }
 
@@ -819,9 +819,10 @@ pub(crate) fn builtin_yes_or_no_p(args: Vec<Value>) -> EvalResult {
 // 11. read-char (stub)
 // ---------------------------------------------------------------------------
 
-/// `(read-char &optional PROMPT ...)`
+/// `(read-char &optional PROMPT INHERIT-INPUT-METHOD SECONDS)`
 ///
-/// Batch stub: returns nil (no input available).
+/// Returns the next `unread-command-events` event, else waits for one
+/// from the host; nil when none arrives (always, in batch mode).
 pub(crate) fn builtin_read_char(
     eval: &mut super::eval::Evaluator,
     args: Vec<Value>,
@@ -833,7 +834,12 @@ pub(crate) fn builtin_read_char(
         ));
     }
     expect_optional_prompt_string(&args)?;
-    if let Some(event) = pop_unread_command_event(eval) {
+    let timeout = super::lread::input_timeout(args.get(2))?;
+    let event = match pop_unread_command_event(eval) {
+        Some(event) => Some(event),
+        None => eval.wait_for_input(timeout),
+    };
+    if let Some(event) = event {
         if let Some(n) = event_to_int(&event) {
             return Ok(Value::Int(n));
         }
@@ -844,7 +850,8 @@ pub(crate) fn builtin_read_char(
 
 /// `(read-key &optional PROMPT)`
 ///
-/// Batch stub: return next `unread-command-events` event when present, else nil.
+/// Returns the next `unread-command-events` event, else waits for a key
+/// from the host; nil in batch mode.
 pub(crate) fn builtin_read_key(
     eval: &mut super::eval::Evaluator,
     args: Vec<Value>,
@@ -856,7 +863,11 @@ pub(crate) fn builtin_read_key(
         ));
     }
     expect_optional_prompt_string(&args)?;
-    if let Some(event) = pop_unread_command_event(eval) {
+    let event = match pop_unread_command_event(eval) {
+        Some(event) => Some(event),
+        None => eval.wait_for_input(None),
+    };
+    if let Some(event) = event {
         if let Some(n) = event_to_int(&event) {
             return Ok(Value::Int(n));
         }
@@ -871,7 +882,8 @@ pub(crate) fn builtin_read_key(
 
 /// `(read-key-sequence PROMPT)`
 ///
-/// Stub: returns empty vector.
+/// Reads a single event, as `read-key`; an empty string when none
+/// arrives.
 pub(crate) fn builtin_read_key_sequence(
     eval: &mut super::eval::Evaluator,
     args: Vec<Value>,
@@ -879,7 +891,11 @@ pub(crate) fn builtin_read_key_sequence(
     expect_min_args("read-key-sequence", &args, 1)?;
     expect_max_args("read-key-sequence", &args, 6)?;
     expect_optional_prompt_string(&args)?;
-    if let Some(event) = pop_unread_command_event(eval) {
+    let event = match pop_unread_command_event(eval) {
+        Some(event) => Some(event),
+        None => eval.wait_for_input(None),
+    };
+    if let Some(event) = event {
         if let Some(c) = event_to_char(&event) {
             return Ok(Value::string(c.to_string()));
         }
@@ -890,8 +906,8 @@ pub(crate) fn builtin_read_key_sequence(
 
 /// `(read-key-sequence-vector PROMPT)`
 ///
-/// Batch stub: returns next `unread-command-events` event as a single-element
-/// vector when present, otherwise an empty vector.
+/// Reads a single event, as `read-key`, into a one-element vector; an
+/// empty vector when none arrives.
 pub(crate) fn builtin_read_key_sequence_vector(
     eval: &mut super::eval::Evaluator,
     args: Vec<Value>,
@@ -899,7 +915,11 @@ pub(crate) fn builtin_read_key_sequence_vector(
     expect_min_args("read-key-sequence-vector", &args, 1)?;
     expect_max_args("read-key-sequence-vector", &args, 6)?;
     expect_optional_prompt_string(&args)?;
-    if let Some(event) = pop_unread_command_event(eval) {
+    let event = match pop_unread_command_event(eval) {
+        Some(event) => Some(event),
+        None => eval.wait_for_input(None),
+    };
+    if let Some(event) = event {
         if let Some(n) = event_to_int(&event) {
             return Ok(Value::vector(vec![Value::Int(n)]));
         }
@@ -0,0 +1,886 @@
+//! SQLite database support -- Emacs 29 compatible `sqlite-*` primitives.
+//!
+//! Provides:
+//! - `sqlite-available-p`, `sqlite-version`, `sqlitep`
+//! - `sqlite-open` / `sqlite-close` -- file-backed or in-memory databases
+//! - `sqlite-execute` / `sqlite-execute-batch` -- statements with bound values
+//! - `sqlite-select` -- queries returning rows, rows with a header, or a set
+//! - `sqlite-next`, `sqlite-columns`, `sqlite-more-p`, `sqlite-finalize` --
+//!   row iteration over a set returned by `sqlite-select`
+//! - `sqlite-transaction`, `sqlite-commit`, `sqlite-rollback`, `sqlite-pragma`
+//!
+//! Database and set objects are `Value::Sqlite` handles into the
+//! evaluator's `SqliteManager`, as timers are, so a plain integer is never
+//! a SQLite object.  Sets are materialized when the query runs, so they
+//! stay valid independently of later statements on the same connection.
+
+use std::collections::{HashMap, VecDeque};
+
+use rusqlite::types::Value as SqlValue;
+use rusqlite::Connection;
+
+use super::error::{signal, EvalResult, Flow};
+use super::string_escape::bytes_to_unibyte_storage_string;
+use super::value::{list_to_vec, Value};
+
+// ---------------------------------------------------------------------------
+// Types
+// ---------------------------------------------------------------------------
+
+/// An open database connection.
+pub struct SqliteDatabase {
+    conn: Connection,
+    /// The file the database was opened from (`None` for in-memory).
+    pub file: Option<String>,
+}
+
+/// A materialized result set returned by `(sqlite-select ... 'set)`.
+#[derive(Clone, Debug)]
+pub struct SqliteSet {
+    /// Handle of the database the set was produced from.
+    pub db: u64,
+    pub columns: Vec<String>,
+    rows: VecDeque<Vec<SqlValue>>,
+}
+
+impl SqliteSet {
+    /// Pop the next row, or `None` when the set is exhausted.
+    pub fn next_row(&mut self) -> Option<Vec<SqlValue>> {
+        self.rows.pop_front()
+    }
+
+    /// Whether more rows remain.
+    pub fn has_more(&self) -> bool {
+        !self.rows.is_empty()
+    }
+}
+
+/// Result of running a single statement.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StatementResult {
+    /// A statement that produced no columns; carries the changed row count.
+    Changes(usize),
+    /// A statement that produced rows.
+    Rows {
+        columns: Vec<String>,
+        rows: Vec<Vec<SqlValue>>,
+    },
+}
+
+// ---------------------------------------------------------------------------
+// SqliteManager
+// ---------------------------------------------------------------------------
+
+/// Central registry for open databases and result sets.
+pub struct SqliteManager {
+    databases: HashMap<u64, SqliteDatabase>,
+    sets: HashMap<u64, SqliteSet>,
+    next_id: u64,
+}
+
+impl Default for SqliteManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SqliteManager {
+    pub fn new() -> Self {
+        Self {
+            databases: HashMap::new(),
+            sets: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    fn alloc_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Open a database.  `None` opens a fresh in-memory database.
+    pub fn open(&mut self, file: Option<&str>) -> Result<u64, String> {
+        let conn = match file {
+            Some(path) => Connection::open(path),
+            None => Connection::open_in_memory(),
+        }
+        .map_err(|e| e.to_string())?;
+        let id = self.alloc_id();
+        self.databases.insert(
+            id,
+            SqliteDatabase {
+                conn,
+                file: file.map(str::to_string),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Close a database and drop every set produced from it.
+    pub fn close(&mut self, id: u64) -> bool {
+        if self.databases.remove(&id).is_none() {
+            return false;
+        }
+        self.sets.retain(|_, set| set.db != id);
+        true
+    }
+
+    pub fn is_database(&self, id: u64) -> bool {
+        self.databases.contains_key(&id)
+    }
+
+    pub fn is_set(&self, id: u64) -> bool {
+        self.sets.contains_key(&id)
+    }
+
+    pub fn database(&self, id: u64) -> Option<&SqliteDatabase> {
+        self.databases.get(&id)
+    }
+
+    pub fn set_mut(&mut self, id: u64) -> Option<&mut SqliteSet> {
+        self.sets.get_mut(&id)
+    }
+
+    /// Drop a set.  Returns false if the handle was unknown.
+    pub fn finalize(&mut self, id: u64) -> bool {
+        self.sets.remove(&id).is_some()
+    }
+
+    /// Run a single statement with positional parameters.
+    pub fn execute(
+        &mut self,
+        db: u64,
+        sql: &str,
+        params: &[SqlValue],
+    ) -> Result<StatementResult, String> {
+        let database = self
+            .databases
+            .get(&db)
+            .ok_or_else(|| "Database closed".to_string())?;
+        let mut stmt = database.conn.prepare(sql).map_err(|e| e.to_string())?;
+        let expected = stmt.parameter_count();
+        if expected != params.len() {
+            return Err(format!(
+                "Wrong number of values: expected {}, got {}",
+                expected,
+                params.len()
+            ));
+        }
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        if columns.is_empty() {
+            let changes = stmt
+                .execute(rusqlite::params_from_iter(params.iter()))
+                .map_err(|e| e.to_string())?;
+            return Ok(StatementResult::Changes(changes));
+        }
+        let width = columns.len();
+        let mut rows = Vec::new();
+        let mut cursor = stmt
+            .query(rusqlite::params_from_iter(params.iter()))
+            .map_err(|e| e.to_string())?;
+        while let Some(row) = cursor.next().map_err(|e| e.to_string())? {
+            let mut values = Vec::with_capacity(width);
+            for i in 0..width {
+                values.push(row.get::<_, SqlValue>(i).map_err(|e| e.to_string())?);
+            }
+            rows.push(values);
+        }
+        Ok(StatementResult::Rows { columns, rows })
+    }
+
+    /// Run several `;`-separated statements without parameters.
+    pub fn execute_batch(&mut self, db: u64, sql: &str) -> Result<(), String> {
+        let database = self
+            .databases
+            .get(&db)
+            .ok_or_else(|| "Database closed".to_string())?;
+        database.conn.execute_batch(sql).map_err(|e| e.to_string())
+    }
+
+    /// Run a query and keep its rows as an iterable set.
+    pub fn select_set(&mut self, db: u64, sql: &str, params: &[SqlValue]) -> Result<u64, String> {
+        let (columns, rows) = match self.execute(db, sql, params)? {
+            StatementResult::Rows { columns, rows } => (columns, rows),
+            StatementResult::Changes(_) => (Vec::new(), Vec::new()),
+        };
+        let id = self.alloc_id();
+        self.sets.insert(
+            id,
+            SqliteSet {
+                db,
+                columns,
+                rows: rows.into(),
+            },
+        );
+        Ok(id)
+    }
+}
+
+// ===========================================================================
+// Value conversion
+// ===========================================================================
+
+/// Convert a Lisp value to a value bindable in a statement.
+///
+/// Mirrors Emacs: nil binds NULL, t binds 1, `false` binds 0, other symbols
+/// bind their name as text.
+fn lisp_to_sql(value: &Value) -> Result<SqlValue, Flow> {
+    match value {
+        Value::Nil => Ok(SqlValue::Null),
+        Value::True => Ok(SqlValue::Integer(1)),
+        Value::Symbol(s) if s == "false" => Ok(SqlValue::Integer(0)),
+        Value::Symbol(s) => Ok(SqlValue::Text(s.clone())),
+        Value::Keyword(s) => Ok(SqlValue::Text(s.clone())),
+        Value::Int(n) => Ok(SqlValue::Integer(*n)),
+        Value::Char(c) => Ok(SqlValue::Integer(*c as i64)),
+        Value::Float(f) => Ok(SqlValue::Real(*f)),
+        Value::Str(s) => Ok(SqlValue::Text((**s).clone())),
+        other => Err(signal(
+            "wrong-type-argument",
+            vec![Value::symbol("atom"), other.clone()],
+        )),
+    }
+}
+
+fn sql_to_lisp(value: &SqlValue) -> Value {
+    match value {
+        SqlValue::Null => Value::Nil,
+        SqlValue::Integer(n) => Value::Int(*n),
+        SqlValue::Real(f) => Value::Float(*f),
+        SqlValue::Text(s) => Value::string(s.clone()),
+        // Raw bytes, as a unibyte string
+        SqlValue::Blob(bytes) => Value::string(bytes_to_unibyte_storage_string(bytes)),
+    }
+}
+
+fn row_to_lisp(row: &[SqlValue]) -> Value {
+    Value::list(row.iter().map(sql_to_lisp).collect())
+}
+
+fn columns_to_lisp(columns: &[String]) -> Value {
+    Value::list(columns.iter().map(|c| Value::string(c.clone())).collect())
+}
+
+// ===========================================================================
+// Builtin helpers
+// ===========================================================================
+
+fn expect_args(name: &str, args: &[Value], n: usize) -> Result<(), Flow> {
+    if args.len() != n {
+        Err(signal(
+            "wrong-number-of-arguments",
+            vec![Value::symbol(name), Value::Int(args.len() as i64)],
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn expect_arg_range(name: &str, args: &[Value], min: usize, max: usize) -> Result<(), Flow> {
+    if args.len() < min || args.len() > max {
+        Err(signal(
+            "wrong-number-of-arguments",
+            vec![Value::symbol(name), Value::Int(args.len() as i64)],
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn expect_string(value: &Value) -> Result<String, Flow> {
+    match value {
+        Value::Str(s) => Ok((**s).clone()),
+        other => Err(signal(
+            "wrong-type-argument",
+            vec![Value::symbol("stringp"), other.clone()],
+        )),
+    }
+}
+
+fn sqlite_error(message: impl Into<String>) -> Flow {
+    signal("sqlite-error", vec![Value::string(message.into())])
+}
+
+fn resolve_database(eval: &super::eval::Evaluator, value: &Value) -> Result<u64, Flow> {
+    match value {
+        Value::Sqlite(id) if eval.sqlite.is_database(*id) => Ok(*id),
+        Value::Sqlite(id) if eval.sqlite.is_set(*id) => Err(signal(
+            "wrong-type-argument",
+            vec![Value::symbol("sqlitep"), value.clone()],
+        )),
+        Value::Sqlite(_) => Err(sqlite_error("Database closed")),
+        other => Err(signal(
+            "wrong-type-argument",
+            vec![Value::symbol("sqlitep"), other.clone()],
+        )),
+    }
+}
+
+fn resolve_set(eval: &super::eval::Evaluator, value: &Value) -> Result<u64, Flow> {
+    match value {
+        Value::Sqlite(id) if eval.sqlite.is_set(*id) => Ok(*id),
+        Value::Sqlite(_) => Err(sqlite_error("Statement closed")),
+        other => Err(signal(
+            "wrong-type-argument",
+            vec![Value::symbol("sqlitep"), other.clone()],
+        )),
+    }
+}
+
+/// Bound values may be given as a list or a vector.
+fn collect_params(value: Option<&Value>) -> Result<Vec<SqlValue>, Flow> {
+    let items = match value {
+        None | Some(Value::Nil) => return Ok(Vec::new()),
+        Some(Value::Vector(v)) => v.lock().expect("poisoned").clone(),
+        Some(other) => list_to_vec(other).ok_or_else(|| {
+            signal(
+                "wrong-type-argument",
+                vec![Value::symbol("listp"), other.clone()],
+            )
+        })?,
+    };
+    items.iter().map(lisp_to_sql).collect()
+}
+
+fn run_simple(
+    eval: &mut super::eval::Evaluator,
+    name: &str,
+    args: &[Value],
+    sql: &str,
+) -> EvalResult {
+    expect_args(name, args, 1)?;
+    let db = resolve_database(eval, &args[0])?;
+    eval.sqlite.execute(db, sql, &[]).map_err(sqlite_error)?;
+    Ok(Value::True)
+}
+
+// ===========================================================================
+// Builtins (pure)
+// ===========================================================================
+
+/// (sqlite-available-p) -> t
+pub(crate) fn builtin_sqlite_available_p(args: Vec<Value>) -> EvalResult {
+    expect_args("sqlite-available-p", &args, 0)?;
+    Ok(Value::True)
+}
+
+/// (sqlite-version) -> version string of the linked SQLite library
+pub(crate) fn builtin_sqlite_version(args: Vec<Value>) -> EvalResult {
+    expect_args("sqlite-version", &args, 0)?;
+    Ok(Value::string(rusqlite::version()))
+}
+
+// ===========================================================================
+// Builtins (evaluator-dependent)
+// ===========================================================================
+
+/// (sqlitep OBJECT) -> t if OBJECT is a live database or set
+pub(crate) fn builtin_sqlitep(eval: &mut super::eval::Evaluator, args: Vec<Value>) -> EvalResult {
+    expect_args("sqlitep", &args, 1)?;
+    Ok(Value::bool(match &args[0] {
+        Value::Sqlite(id) => eval.sqlite.is_database(*id) || eval.sqlite.is_set(*id),
+        _ => false,
+    }))
+}
+
+/// (sqlite-open &optional FILE) -> database
+///
+/// FILE nil opens an in-memory database.
+pub(crate) fn builtin_sqlite_open(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("sqlite-open", &args, 0, 1)?;
+    let file = match args.first() {
+        None | Some(Value::Nil) => None,
+        Some(v) => {
+            let name = expect_string(v)?;
+            let default_dir = eval
+                .obarray
+                .symbol_value("default-directory")
+                .and_then(|v| v.as_str().map(str::to_string));
+            Some(super::fileio::expand_file_name(
+                &name,
+                default_dir.as_deref(),
+            ))
+        }
+    };
+    let id = eval.sqlite.open(file.as_deref()).map_err(sqlite_error)?;
+    Ok(Value::Sqlite(id))
+}
+
+/// (sqlite-close DB) -> t
+pub(crate) fn builtin_sqlite_close(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_args("sqlite-close", &args, 1)?;
+    let db = resolve_database(eval, &args[0])?;
+    eval.sqlite.close(db);
+    Ok(Value::True)
+}
+
+/// (sqlite-execute DB STATEMENT &optional VALUES)
+///
+/// Returns the number of changed rows, or the rows themselves when the
+/// statement produces data (e.g. `SELECT` or `RETURNING`).
+pub(crate) fn builtin_sqlite_execute(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("sqlite-execute", &args, 2, 3)?;
+    let db = resolve_database(eval, &args[0])?;
+    let sql = expect_string(&args[1])?;
+    let params = collect_params(args.get(2))?;
+    match eval
+        .sqlite
+        .execute(db, &sql, &params)
+        .map_err(sqlite_error)?
+    {
+        StatementResult::Changes(n) => Ok(Value::Int(n as i64)),
+        StatementResult::Rows { rows, .. } => {
+            Ok(Value::list(rows.iter().map(|r| row_to_lisp(r)).collect()))
+        }
+    }
+}
+
+/// (sqlite-execute-batch DB STATEMENTS) -> t
+pub(crate) fn builtin_sqlite_execute_batch(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_args("sqlite-execute-batch", &args, 2)?;
+    let db = resolve_database(eval, &args[0])?;
+    let sql = expect_string(&args[1])?;
+    eval.sqlite.execute_batch(db, &sql).map_err(sqlite_error)?;
+    Ok(Value::True)
+}
+
+/// (sqlite-select DB QUERY &optional VALUES RETURN-TYPE)
+///
+/// RETURN-TYPE nil returns a list of rows, `full` prepends the list of
+/// column names, and `set` returns a set object for `sqlite-next`.
+pub(crate) fn builtin_sqlite_select(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_arg_range("sqlite-select", &args, 2, 4)?;
+    let db = resolve_database(eval, &args[0])?;
+    let sql = expect_string(&args[1])?;
+    let params = collect_params(args.get(2))?;
+    let return_type = args.get(3).cloned().unwrap_or(Value::Nil);
+    match return_type.as_symbol_name() {
+        Some("set") => {
+            let id = eval
+                .sqlite
+                .select_set(db, &sql, &params)
+                .map_err(sqlite_error)?;
+            Ok(Value::Sqlite(id))
+        }
+        Some("nil") | Some("full") => {
+            let (columns, rows) = match eval
+                .sqlite
+                .execute(db, &sql, &params)
+                .map_err(sqlite_error)?
+            {
+                StatementResult::Rows { columns, rows } => (columns, rows),
+                StatementResult::Changes(_) => (Vec::new(), Vec::new()),
+            };
+            let mut out: Vec<Value> = rows.iter().map(|r| row_to_lisp(r)).collect();
+            if return_type.is_truthy() {
+                out.insert(0, columns_to_lisp(&columns));
+            }
+            Ok(Value::list(out))
+        }
+        _ => Err(signal(
+            "error",
+            vec![Value::string("Invalid return-type"), return_type],
+        )),
+    }
+}
+
+/// (sqlite-next SET) -> next row, or nil when exhausted
+pub(crate) fn builtin_sqlite_next(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_args("sqlite-next", &args, 1)?;
+    let id = resolve_set(eval, &args[0])?;
+    let set = eval.sqlite.set_mut(id).expect("resolved set");
+    Ok(set
+        .next_row()
+        .map(|r| row_to_lisp(&r))
+        .unwrap_or(Value::Nil))
+}
+
+/// (sqlite-columns SET) -> list of column names
+pub(crate) fn builtin_sqlite_columns(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_args("sqlite-columns", &args, 1)?;
+    let id = resolve_set(eval, &args[0])?;
+    let set = eval.sqlite.set_mut(id).expect("resolved set");
+    Ok(columns_to_lisp(&set.columns))
+}
+
+/// (sqlite-more-p SET) -> t if more rows remain
+pub(crate) fn builtin_sqlite_more_p(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_args("sqlite-more-p", &args, 1)?;
+    let id = resolve_set(eval, &args[0])?;
+    let set = eval.sqlite.set_mut(id).expect("resolved set");
+    Ok(Value::bool(set.has_more()))
+}
+
+/// (sqlite-finalize SET) -> t
+pub(crate) fn builtin_sqlite_finalize(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_args("sqlite-finalize", &args, 1)?;
+    let id = resolve_set(eval, &args[0])?;
+    eval.sqlite.finalize(id);
+    Ok(Value::True)
+}
+
+/// (sqlite-transaction DB) -> t
+pub(crate) fn builtin_sqlite_transaction(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    run_simple(eval, "sqlite-transaction", &args, "begin")
+}
+
+/// (sqlite-commit DB) -> t
+pub(crate) fn builtin_sqlite_commit(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    run_simple(eval, "sqlite-commit", &args, "commit")
+}
+
+/// (sqlite-rollback DB) -> t
+pub(crate) fn builtin_sqlite_rollback(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    run_simple(eval, "sqlite-rollback", &args, "rollback")
+}
+
+/// (sqlite-pragma DB PRAGMA) -> t
+pub(crate) fn builtin_sqlite_pragma(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_args("sqlite-pragma", &args, 2)?;
+    let db = resolve_database(eval, &args[0])?;
+    let pragma = expect_string(&args[1])?;
+    eval.sqlite
+        .execute_batch(db, &format!("PRAGMA {}", pragma))
+        .map_err(sqlite_error)?;
+    Ok(Value::True)
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elisp::eval::Evaluator;
+
+    fn open_memory(eval: &mut Evaluator) -> Value {
+        builtin_sqlite_open(eval, vec![]).expect("open in-memory db")
+    }
+
+    fn exec(eval: &mut Evaluator, db: &Value, sql: &str, values: Vec<Value>) -> Value {
+        builtin_sqlite_execute(
+            eval,
+            vec![db.clone(), Value::string(sql), Value::list(values)],
+        )
+        .expect("execute")
+    }
+
+    // -----------------------------------------------------------------------
+    // SqliteManager unit tests
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn manager_open_close() {
+        let mut mgr = SqliteManager::new();
+        let id = mgr.open(None).unwrap();
+        assert!(mgr.is_database(id));
+        assert!(mgr.database(id).unwrap().file.is_none());
+        assert!(mgr.close(id));
+        assert!(!mgr.is_database(id));
+        assert!(!mgr.close(id));
+    }
+
+    #[test]
+    fn manager_close_drops_sets() {
+        let mut mgr = SqliteManager::new();
+        let db = mgr.open(None).unwrap();
+        let set = mgr.select_set(db, "select 1", &[]).unwrap();
+        assert!(mgr.is_set(set));
+        mgr.close(db);
+        assert!(!mgr.is_set(set));
+    }
+
+    #[test]
+    fn manager_rejects_wrong_parameter_count() {
+        let mut mgr = SqliteManager::new();
+        let db = mgr.open(None).unwrap();
+        let err = mgr
+            .execute(db, "select ?, ?", &[SqlValue::Integer(1)])
+            .unwrap_err();
+        assert!(err.contains("Wrong number of values"));
+    }
+
+    // -----------------------------------------------------------------------
+    // Builtin-level tests
+    // -----------------------------------------------------------------------
+
+    #[test]
+    fn execute_and_select_roundtrip() {
+        let mut eval = Evaluator::new();
+        let db = open_memory(&mut eval);
+        exec(
+            &mut eval,
+            &db,
+            "create table notes (id integer primary key, title text, score real)",
+            vec![],
+        );
+        let changed = exec(
+            &mut eval,
+            &db,
+            "insert into notes (title, score) values (?, ?)",
+            vec![Value::string("roam"), Value::Float(1.5)],
+        );
+        assert_eq!(changed, Value::Int(1));
+        exec(
+            &mut eval,
+            &db,
+            "insert into notes (title, score) values (?, ?)",
+            vec![Value::symbol("agenda"), Value::Nil],
+        );
+
+        let rows = builtin_sqlite_select(
+            &mut eval,
+            vec![
+                db.clone(),
+                Value::string("select title, score from notes order by id"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            rows,
+            Value::list(vec![
+                Value::list(vec![Value::string("roam"), Value::Float(1.5)]),
+                Value::list(vec![Value::string("agenda"), Value::Nil]),
+            ])
+        );
+    }
+
+    #[test]
+    fn blobs_select_as_unibyte_strings() {
+        let mut eval = Evaluator::new();
+        let db = open_memory(&mut eval);
+        let rows = builtin_sqlite_select(
+            &mut eval,
+            vec![db, Value::string("select x'00ff41', cast('é' as blob)")],
+        )
+        .unwrap();
+        assert_eq!(
+            rows,
+            Value::list(vec![Value::list(vec![
+                Value::string(bytes_to_unibyte_storage_string(&[0x00, 0xff, 0x41])),
+                Value::string(bytes_to_unibyte_storage_string("é".as_bytes())),
+            ])])
+        );
+    }
+
+    #[test]
+    fn select_full_prepends_column_names() {
+        let mut eval = Evaluator::new();
+        let db = open_memory(&mut eval);
+        let rows = builtin_sqlite_select(
+            &mut eval,
+            vec![
+                db,
+                Value::string("select 1 as a, 'x' as b"),
+                Value::Nil,
+                Value::symbol("full"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            rows,
+            Value::list(vec![
+                Value::list(vec![Value::string("a"), Value::string("b")]),
+                Value::list(vec![Value::Int(1), Value::string("x")]),
+            ])
+        );
+    }
+
+    #[test]
+    fn select_set_iteration() {
+        let mut eval = Evaluator::new();
+        let db = open_memory(&mut eval);
+        exec(&mut eval, &db, "create table t (n integer)", vec![]);
+        for n in 1..=3 {
+            exec(
+                &mut eval,
+                &db,
+                "insert into t values (?)",
+                vec![Value::Int(n)],
+            );
+        }
+        let set = builtin_sqlite_select(
+            &mut eval,
+            vec![
+                db.clone(),
+                Value::string("select n from t where n > ? order by n"),
+                Value::vector(vec![Value::Int(1)]),
+                Value::symbol("set"),
+            ],
+        )
+        .unwrap();
+        assert!(builtin_sqlitep(&mut eval, vec![set.clone()])
+            .unwrap()
+            .is_truthy());
+        assert_eq!(
+            builtin_sqlite_columns(&mut eval, vec![set.clone()]).unwrap(),
+            Value::list(vec![Value::string("n")])
+        );
+        assert_eq!(
+            builtin_sqlite_next(&mut eval, vec![set.clone()]).unwrap(),
+            Value::list(vec![Value::Int(2)])
+        );
+        assert!(builtin_sqlite_more_p(&mut eval, vec![set.clone()])
+            .unwrap()
+            .is_truthy());
+        assert_eq!(
+            builtin_sqlite_next(&mut eval, vec![set.clone()]).unwrap(),
+            Value::list(vec![Value::Int(3)])
+        );
+        assert!(builtin_sqlite_more_p(&mut eval, vec![set.clone()])
+            .unwrap()
+            .is_nil());
+        assert!(builtin_sqlite_next(&mut eval, vec![set.clone()])
+            .unwrap()
+            .is_nil());
+        builtin_sqlite_finalize(&mut eval, vec![set.clone()]).unwrap();
+        assert!(builtin_sqlite_next(&mut eval, vec![set]).is_err());
+    }
+
+    #[test]
+    fn transaction_rollback_discards_changes() {
+        let mut eval = Evaluator::new();
+        let db = open_memory(&mut eval);
+        exec(&mut eval, &db, "create table t (n integer)", vec![]);
+        builtin_sqlite_transaction(&mut eval, vec![db.clone()]).unwrap();
+        exec(&mut eval, &db, "insert into t values (1)", vec![]);
+        builtin_sqlite_rollback(&mut eval, vec![db.clone()]).unwrap();
+        builtin_sqlite_transaction(&mut eval, vec![db.clone()]).unwrap();
+        exec(&mut eval, &db, "insert into t values (2)", vec![]);
+        builtin_sqlite_commit(&mut eval, vec![db.clone()]).unwrap();
+        let rows =
+            builtin_sqlite_select(&mut eval, vec![db, Value::string("select n from t")]).unwrap();
+        assert_eq!(rows, Value::list(vec![Value::list(vec![Value::Int(2)])]));
+    }
+
+    #[test]
+    fn booleans_bind_as_integers() {
+        let mut eval = Evaluator::new();
+        let db = open_memory(&mut eval);
+        let rows = builtin_sqlite_select(
+            &mut eval,
+            vec![
+                db,
+                Value::string("select ?, ?"),
+                Value::list(vec![Value::True, Value::symbol("false")]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            rows,
+            Value::list(vec![Value::list(vec![Value::Int(1), Value::Int(0)])])
+        );
+    }
+
+    #[test]
+    fn integers_are_not_sqlite_objects() {
+        let mut eval = Evaluator::new();
+        let db = open_memory(&mut eval);
+        assert_eq!(db.type_name(), "sqlite");
+        let Value::Sqlite(id) = db else {
+            panic!("expected a sqlite object, got {:?}", db)
+        };
+        let number = Value::Int(id as i64);
+        assert!(builtin_sqlitep(&mut eval, vec![number.clone()])
+            .unwrap()
+            .is_nil());
+        match builtin_sqlite_execute(&mut eval, vec![number, Value::string("select 1")]) {
+            Err(Flow::Signal(sig)) => assert_eq!(sig.symbol, "wrong-type-argument"),
+            other => panic!("expected wrong-type-argument, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn closed_database_signals_error() {
+        let mut eval = Evaluator::new();
+        let db = open_memory(&mut eval);
+        builtin_sqlite_close(&mut eval, vec![db.clone()]).unwrap();
+        assert!(builtin_sqlitep(&mut eval, vec![db.clone()])
+            .unwrap()
+            .is_nil());
+        match builtin_sqlite_execute(&mut eval, vec![db, Value::string("select 1")]) {
+            Err(Flow::Signal(sig)) => assert_eq!(sig.symbol, "sqlite-error"),
+            other => panic!("expected sqlite-error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn sql_errors_signal_sqlite_error() {
+        let mut eval = Evaluator::new();
+        let db = open_memory(&mut eval);
+        match builtin_sqlite_execute(&mut eval, vec![db, Value::string("not valid sql")]) {
+            Err(Flow::Signal(sig)) => assert_eq!(sig.symbol, "sqlite-error"),
+            other => panic!("expected sqlite-error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn pragma_and_file_database() {
+        let mut eval = Evaluator::new();
+        let path = std::env::temp_dir().join(format!("neovm-sqlite-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let db = builtin_sqlite_open(
+            &mut eval,
+            vec![Value::string(path.to_string_lossy().into_owned())],
+        )
+        .unwrap();
+        builtin_sqlite_pragma(
+            &mut eval,
+            vec![db.clone(), Value::string("journal_mode = WAL")],
+        )
+        .unwrap();
+        exec(&mut eval, &db, "create table t (n integer)", vec![]);
+        builtin_sqlite_close(&mut eval, vec![db]).unwrap();
+        assert!(path.exists());
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("db-wal"));
+        let _ = std::fs::remove_file(path.with_extension("db-shm"));
+    }
+
+    #[test]
+    fn available_and_version() {
+        assert_eq!(builtin_sqlite_available_p(vec![]).unwrap(), Value::True);
+        assert!(builtin_sqlite_version(vec![]).unwrap().is_string());
+    }
+}
@@ -280,6 +280,39 @@ pub(crate) fn bytes_to_unibyte_storage_string(bytes: &[u8]) -> String {
     out
 }
 
+/// Recover the bytes that internal string storage stands for.
+///
+/// This is the inverse of [`bytes_to_storage_string`]: raw-byte, unibyte
+/// and extended-sequence sentinels turn back into their original bytes and
+/// everything else is written as UTF-8.
+pub(crate) fn storage_string_to_bytes(s: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let code = ch as u32;
+        if (UNIBYTE_BYTE_SENTINEL_MIN..=UNIBYTE_BYTE_SENTINEL_MAX).contains(&code) {
+            out.push((code - UNIBYTE_BYTE_SENTINEL_BASE) as u8);
+            continue;
+        }
+
+        if (RAW_BYTE_SENTINEL_MIN..=RAW_BYTE_SENTINEL_MAX).contains(&code) {
+            out.push((code - RAW_BYTE_SENTINEL_BASE) as u8);
+            continue;
+        }
+
+        if code == EXT_SEQ_PREFIX {
+            if let Some(bytes) = decode_extended_sequence(&mut chars) {
+                out.extend_from_slice(&bytes);
+                continue;
+            }
+        }
+
+        let mut tmp = [0u8; 4];
+        out.extend_from_slice(ch.encode_utf8(&mut tmp).as_bytes());
+    }
+    out
+}
+
 fn decode_storage_units(s: &str) -> Vec<(u32, usize)> {
     scan_storage_units(s)
         .into_iter()
@@ -485,6 +518,8 @@ mod tests {
             format_lisp_string_bytes(&encoded),
             vec![b'"', 0xF4, 0x90, 0x80, 0x80, b'A', b'"']
         );
+        assert_eq!(storage_string_to_bytes(&encoded), raw);
+        assert_eq!(storage_string_to_bytes("café"), "café".as_bytes());
     }
 
     #[test]
@@ -185,6 +185,12 @@ fn parse_time(val: &Value) -> Result<TimeMicros, Flow> {
     }
 }
 
+/// Seconds since the epoch of the Lisp time value VAL, as `parse_time`
+/// reads it.
+pub(crate) fn time_value_secs(val: &Value) -> Result<f64, Flow> {
+    parse_time(val).map(|tm| tm.to_float())
+}
+
 // ---------------------------------------------------------------------------
 // Date/time breakdown helpers (UTC only, no chrono)
 // ---------------------------------------------------------------------------
@@ -390,7 +396,7 @@ fn format_fixed_offset_name(offset_secs: i64) -> String {
 }
 
 #[cfg(unix)]
-fn local_offset_name_at_epoch(epoch_secs: i64) -> (i64, String) {
+pub(crate) fn local_offset_name_at_epoch(epoch_secs: i64) -> (i64, String) {
     let mut time_val: libc::time_t = epoch_secs as libc::time_t;
     let mut tm: libc::tm = unsafe { std::mem::zeroed() };
     let tm_ptr = unsafe { libc::localtime_r(&mut time_val as *mut _, &mut tm as *mut _) };
@@ -409,7 +415,7 @@ fn local_offset_name_at_epoch(epoch_secs: i64) -> (i64, String) {
 }
 
 #[cfg(not(unix))]
-fn local_offset_name_at_epoch(_epoch_secs: i64) -> (i64, String) {
+pub(crate) fn local_offset_name_at_epoch(_epoch_secs: i64) -> (i64, String) {
     (0, "UTC".to_string())
 }
 
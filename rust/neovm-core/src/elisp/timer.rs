@@ -6,13 +6,32 @@
 //! - `cancel-timer` — deactivate a timer
 //! - `timerp` — type predicate
 //! - `timer-activate` — reactivate a timer
+//! - `current-idle-time` — how long input has been idle
+//! - `timer-statistics` — what each timer has cost, for runaway timers
 //! - `sit-for` — sleep/yield (stub)
-
-use std::time::{Duration, Instant};
+//!
+//! Deadlines live in a hierarchical timer wheel (see `timer_wheel.rs`),
+//! so finding the timers due costs nothing for the ones that are not.
+//! Repeating timers keep their phase: each run is scheduled one
+//! interval after the previous deadline, not after the late moment it
+//! actually ran, and a timer that fell far behind (Emacs was busy or
+//! suspended) catches up at most `TIMER_MAX_REPEATS` times, like
+//! `timer-max-repeats`.  Idle timers count from the moment input went
+//! idle.  `read-event` and friends wait for host input with
+//! `Evaluator::wait_for_input`, which runs timers as they come due and
+//! counts input as idle while none is pending; a host can also report
+//! idleness itself with `set_input_idle`.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use super::error::{signal, EvalResult, Flow};
+use super::timer_wheel::TimerWheel;
 use super::value::Value;
 
+/// Most overdue runs a repeating timer catches up on, as
+/// `timer-max-repeats`.
+pub const TIMER_MAX_REPEATS: u32 = 10;
+
 // ---------------------------------------------------------------------------
 // Timer types
 // ---------------------------------------------------------------------------
@@ -20,6 +39,22 @@ use super::value::Value;
 /// Unique timer identifier.
 pub type TimerId = u64;
 
+/// What a timer has cost so far.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimerStats {
+    /// Times the callback ran.
+    pub runs: u64,
+    /// Overdue runs dropped by drift correction.
+    pub skipped: u64,
+    /// Runs that signaled an error.
+    pub errors: u64,
+    /// How late the last run started, and the latest any run started.
+    pub last_lateness: Duration,
+    pub max_lateness: Duration,
+    /// Time spent in the callback.
+    pub run_time: Duration,
+}
+
 /// A single timer entry.
 #[derive(Clone, Debug)]
 pub struct Timer {
@@ -37,6 +72,13 @@ pub struct Timer {
     pub active: bool,
     /// Whether this is an idle timer.
     pub idle: bool,
+    /// For idle timers, how long input must be idle before it fires.
+    pub idle_delay: Duration,
+    /// Whether an idle timer already ran in the current idle period.
+    pub ran_this_idle: bool,
+    pub stats: TimerStats,
+    /// Key of its deadline in the wheel, while scheduled
+    wheel_key: Option<u64>,
 }
 
 // ---------------------------------------------------------------------------
@@ -45,7 +87,13 @@ pub struct Timer {
 
 /// Central registry for all timers.
 pub struct TimerManager {
+    /// All timers ever made, in id order
     timers: Vec<Timer>,
+    wheel: TimerWheel,
+    /// When input went idle, or None while the user is active
+    idle_since: Option<Instant>,
+    /// Whether the host has reported input idleness at all
+    idle_reported: bool,
     next_id: TimerId,
 }
 
@@ -54,13 +102,63 @@ impl TimerManager {
     pub fn new() -> Self {
         Self {
             timers: Vec::new(),
+            wheel: TimerWheel::new(Instant::now()),
+            idle_since: None,
+            idle_reported: false,
             next_id: 1,
         }
     }
 
+    fn get_mut(&mut self, id: TimerId) -> Option<&mut Timer> {
+        let i = self.timers.binary_search_by_key(&id, |t| t.id).ok()?;
+        Some(&mut self.timers[i])
+    }
+
+    /// Put timer ID in the wheel for DEADLINE, replacing any deadline it
+    /// had.
+    fn schedule(&mut self, id: TimerId, deadline: Instant) {
+        self.unschedule(id);
+        let key = self.wheel.insert(id, deadline);
+        if let Some(timer) = self.get_mut(id) {
+            timer.fire_time = deadline;
+            timer.wheel_key = Some(key);
+        }
+    }
+
+    fn unschedule(&mut self, id: TimerId) {
+        let key = self.get_mut(id).and_then(|t| t.wheel_key.take());
+        if let Some(key) = key {
+            self.wheel.remove(id, key);
+        }
+    }
+
+    /// Schedule an active timer for its next run: ordinary timers at
+    /// their `fire_time`, idle timers once input has been idle long
+    /// enough, if it is idle and they have not run yet this time.  Until
+    /// the host reports idleness, idle timers count from when they were
+    /// set, as if input went idle then.
+    fn arm(&mut self, id: TimerId) {
+        let (idle_since, idle_reported) = (self.idle_since, self.idle_reported);
+        let Some(timer) = self.get_mut(id) else {
+            return;
+        };
+        let deadline = if !timer.active || timer.ran_this_idle {
+            None
+        } else if timer.idle && idle_reported {
+            idle_since.map(|since| since + timer.idle_delay)
+        } else {
+            Some(timer.fire_time)
+        };
+        match deadline {
+            Some(deadline) => self.schedule(id, deadline),
+            None => self.unschedule(id),
+        }
+    }
+
     /// Add a new timer that fires after `delay_secs` seconds.
     ///
     /// If `repeat_secs` is > 0, the timer repeats at that interval.
+    /// Idle timers fire once input has been idle for `delay_secs`.
     /// Returns the timer id.
     pub fn add_timer(
         &mut self,
@@ -89,53 +187,110 @@ impl TimerManager {
             args,
             active: true,
             idle,
+            idle_delay: delay,
+            ran_this_idle: false,
+            stats: TimerStats::default(),
+            wheel_key: None,
         });
+        self.arm(id);
 
         id
     }
 
     /// Cancel a timer by id. Returns true if the timer was found and cancelled.
     pub fn cancel_timer(&mut self, id: TimerId) -> bool {
-        for timer in &mut self.timers {
-            if timer.id == id {
-                timer.active = false;
-                return true;
-            }
-        }
-        false
+        let Some(timer) = self.get_mut(id) else {
+            return false;
+        };
+        timer.active = false;
+        self.unschedule(id);
+        true
     }
 
     /// Check if a timer is active.
     pub fn timer_active_p(&self, id: TimerId) -> bool {
-        self.timers.iter().any(|t| t.id == id && t.active)
+        self.timer(id).is_some_and(|t| t.active)
+    }
+
+    /// The timer with ID.
+    pub fn timer(&self, id: TimerId) -> Option<&Timer> {
+        let i = self.timers.binary_search_by_key(&id, |t| t.id).ok()?;
+        Some(&self.timers[i])
     }
 
     /// Update a timer's delay (reschedules from now).
     pub fn timer_set_time(&mut self, id: TimerId, new_delay: f64) {
         let delay = Duration::from_secs_f64(new_delay.max(0.0));
-        for timer in &mut self.timers {
-            if timer.id == id {
-                timer.fire_time = Instant::now() + delay;
-                timer.active = true;
-                return;
-            }
-        }
+        let Some(timer) = self.get_mut(id) else {
+            return;
+        };
+        timer.fire_time = Instant::now() + delay;
+        timer.idle_delay = delay;
+        timer.ran_this_idle = false;
+        timer.active = true;
+        self.arm(id);
     }
 
     /// Reactivate a cancelled timer (reschedules from now using its repeat interval or zero).
     pub fn timer_activate(&mut self, id: TimerId) -> bool {
-        for timer in &mut self.timers {
-            if timer.id == id {
-                if !timer.active {
-                    timer.active = true;
-                    // Reschedule from now using repeat interval or immediately.
-                    let delay = timer.repeat_interval.unwrap_or(Duration::ZERO);
-                    timer.fire_time = Instant::now() + delay;
-                }
-                return true;
-            }
+        let Some(timer) = self.get_mut(id) else {
+            return false;
+        };
+        if !timer.active {
+            timer.active = true;
+            timer.ran_this_idle = false;
+            // Reschedule from now using repeat interval or immediately.
+            let delay = timer.repeat_interval.unwrap_or(Duration::ZERO);
+            timer.fire_time = Instant::now() + delay;
+            self.arm(id);
+        }
+        true
+    }
+
+    /// Input went idle at SINCE, or with None, the user did something.
+    /// The display engine knows when input last arrived; idle timers
+    /// count from then, and each runs at most once per idle period.
+    pub fn set_input_idle(&mut self, since: Option<Instant>) {
+        if self.idle_reported && since == self.idle_since {
+            return;
+        }
+        self.idle_reported = true;
+        // Either way a new idle period starts, now or later
+        for timer in self.timers.iter_mut().filter(|t| t.idle) {
+            timer.ran_this_idle = false;
         }
-        false
+        self.idle_since = since;
+        let idle_ids: Vec<TimerId> = self
+            .timers
+            .iter()
+            .filter(|t| t.idle && t.active)
+            .map(|t| t.id)
+            .collect();
+        for id in idle_ids {
+            self.arm(id);
+        }
+    }
+
+    /// How long input has been idle at NOW, or None if it is not.
+    pub fn idle_time(&self, now: Instant) -> Option<Duration> {
+        self.idle_since
+            .map(|since| now.saturating_duration_since(since))
+    }
+
+    /// The deadline after FIRE_TIME for a timer repeating every INTERVAL
+    /// that ran at NOW, and how many overdue runs it skips: counting the
+    /// one at NOW, it catches up at most `TIMER_MAX_REPEATS` times.
+    fn next_repeat(fire_time: Instant, interval: Duration, now: Instant) -> (Instant, u64) {
+        let next = fire_time + interval;
+        if next > now || interval.is_zero() {
+            return (next, 0);
+        }
+        let overdue = (now.duration_since(next).as_nanos() / interval.as_nanos()) as u64 + 1;
+        let skipped = overdue.saturating_sub(TIMER_MAX_REPEATS as u64 - 1);
+        let skip = Duration::from_nanos(
+            (interval.as_nanos() * skipped as u128).min(u64::MAX as u128) as u64,
+        );
+        (next + skip, skipped)
     }
 
     /// Collect all pending callbacks whose fire_time has passed.
@@ -143,41 +298,64 @@ impl TimerManager {
     /// Returns a vec of (callback, args) pairs to be executed by the evaluator.
     /// Repeating timers are rescheduled; one-shot timers are deactivated.
     pub fn fire_pending_timers(&mut self, current_time: Instant) -> Vec<(Value, Vec<Value>)> {
-        let mut fired = Vec::new();
+        self.fire_due(current_time)
+            .into_iter()
+            .filter_map(|id| self.timer(id).map(|t| (t.callback.clone(), t.args.clone())))
+            .collect()
+    }
 
-        for timer in &mut self.timers {
+    /// Take the timers due at NOW off the wheel and reschedule them as
+    /// `fire_pending_timers` describes.  Returns their ids in the order
+    /// they fell due.
+    pub fn fire_due(&mut self, now: Instant) -> Vec<TimerId> {
+        let mut fired = Vec::new();
+        for (id, deadline) in self.wheel.expire(now) {
+            let Some(timer) = self.get_mut(id) else {
+                continue;
+            };
+            timer.wheel_key = None;
             if !timer.active {
                 continue;
             }
-            if current_time >= timer.fire_time {
-                fired.push((timer.callback.clone(), timer.args.clone()));
-
-                if let Some(interval) = timer.repeat_interval {
-                    // Reschedule: advance fire_time by interval (catch up if needed)
-                    timer.fire_time = current_time + interval;
-                } else {
-                    timer.active = false;
-                }
+            let lateness = now.saturating_duration_since(deadline);
+            timer.stats.last_lateness = lateness;
+            timer.stats.max_lateness = timer.stats.max_lateness.max(lateness);
+            fired.push(id);
+
+            if timer.idle {
+                // Again in the next idle period, if at all
+                timer.ran_this_idle = true;
+                timer.active = timer.repeat_interval.is_some();
+            } else if let Some(interval) = timer.repeat_interval {
+                let (next, skipped) = Self::next_repeat(deadline, interval, now);
+                timer.stats.skipped += skipped;
+                timer.fire_time = next;
+                self.arm(id);
+            } else {
+                timer.active = false;
             }
         }
-
         fired
     }
 
+    /// Record that a run of timer ID took ELAPSED, and whether it
+    /// signaled an error.
+    pub fn record_run(&mut self, id: TimerId, elapsed: Duration, failed: bool) {
+        if let Some(timer) = self.get_mut(id) {
+            timer.stats.runs += 1;
+            timer.stats.run_time += elapsed;
+            if failed {
+                timer.stats.errors += 1;
+            }
+        }
+    }
+
     /// Return the duration until the next timer fires, or None if no active timers.
     pub fn next_fire_time(&self) -> Option<Duration> {
         let now = Instant::now();
-        self.timers
-            .iter()
-            .filter(|t| t.active)
-            .map(|t| {
-                if t.fire_time > now {
-                    t.fire_time - now
-                } else {
-                    Duration::ZERO
-                }
-            })
-            .min()
+        self.wheel
+            .next_deadline()
+            .map(|deadline| deadline.saturating_duration_since(now))
     }
 
     /// Return a list of all timer ids (both active and inactive).
@@ -194,9 +372,27 @@ impl TimerManager {
             .collect()
     }
 
+    /// Timers that have run or are active, costliest first: by time
+    /// spent in their callbacks, then by runs.
+    pub fn timers_by_cost(&self) -> Vec<&Timer> {
+        let mut timers: Vec<&Timer> = self
+            .timers
+            .iter()
+            .filter(|t| t.active || t.stats.runs > 0)
+            .collect();
+        timers.sort_by(|a, b| {
+            b.stats
+                .run_time
+                .cmp(&a.stats.run_time)
+                .then(b.stats.runs.cmp(&a.stats.runs))
+                .then(a.id.cmp(&b.id))
+        });
+        timers
+    }
+
     /// Check if the given id refers to a known timer.
     pub fn is_timer(&self, id: TimerId) -> bool {
-        self.timers.iter().any(|t| t.id == id)
+        self.timer(id).is_some()
     }
 }
 
@@ -244,38 +440,135 @@ fn expect_number(value: &Value) -> Result<f64, Flow> {
     }
 }
 
-fn parse_run_at_time_delay(value: &Value) -> Result<f64, Flow> {
+/// Units of relative time strings, as `timer-duration-words`.
+const DURATION_WORDS: &[(&str, f64)] = &[
+    ("microsec", 1e-6),
+    ("microsecond", 1e-6),
+    ("millisec", 1e-3),
+    ("millisecond", 1e-3),
+    ("sec", 1.0),
+    ("second", 1.0),
+    ("min", 60.0),
+    ("minute", 60.0),
+    ("hour", 3600.0),
+    ("day", 86400.0),
+    ("week", 604800.0),
+    ("fortnight", 1209600.0),
+    ("month", 2592000.0),
+    ("year", 31557600.0),
+];
+
+/// Seconds in a relative time like "90" or "2 hours 35 min", as
+/// `timer-duration`: numbers, which default to 1, each followed by a
+/// unit, which may be plural.
+fn parse_duration(spec: &str) -> Option<f64> {
+    if let Ok(secs) = spec.parse::<f64>() {
+        return Some(secs);
+    }
+    let mut secs = 0.0;
+    let mut rest = spec.trim_start();
+    while !rest.is_empty() {
+        let num_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let num = if num_len == 0 {
+            1.0
+        } else {
+            rest[..num_len].parse::<f64>().ok()?
+        };
+        rest = rest[num_len..].trim_start();
+        let word_len = rest
+            .find(|c: char| !c.is_ascii_lowercase())
+            .unwrap_or(rest.len());
+        let word = &rest[..word_len];
+        let unit = DURATION_WORDS
+            .iter()
+            .find(|(w, _)| *w == word || word.strip_suffix('s') == Some(*w))?
+            .1;
+        secs += num * unit;
+        rest = rest[word_len..].trim_start();
+    }
+    Some(secs)
+}
+
+/// Minutes after midnight of a clock time like "11:23pm", "23.30" or
+/// "9am", as `diary-entry-time` reads them.
+fn parse_clock_time(spec: &str) -> Option<i64> {
+    let lower = spec.to_ascii_lowercase();
+    let (digits, meridian) = match lower
+        .strip_suffix("am")
+        .or_else(|| lower.strip_suffix("pm"))
+    {
+        Some(digits) => (digits.trim_end(), Some(lower.ends_with("pm"))),
+        None => (lower.as_str(), None),
+    };
+    let (hours, minutes) = match digits.split_once([':', '.']) {
+        Some((h, m)) if m.len() == 2 => (h, m),
+        Some(_) => return None,
+        None if meridian.is_some() => (digits, "00"),
+        None => return None,
+    };
+    if hours.is_empty()
+        || hours.len() > 2
+        || !(hours
+            .bytes()
+            .chain(minutes.bytes())
+            .all(|b| b.is_ascii_digit()))
+    {
+        return None;
+    }
+    let (mut h, m): (i64, i64) = (hours.parse().ok()?, minutes.parse().ok()?);
+    if m > 59 {
+        return None;
+    }
+    match meridian {
+        Some(pm) if (1..=12).contains(&h) => h = h % 12 + if pm { 12 } else { 0 },
+        Some(_) => return None,
+        None if h > 23 => return None,
+        None => {}
+    }
+    Some(h * 60 + m)
+}
+
+fn epoch_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// Seconds from now until TIME of `run-at-time`, which repeats every
+/// REPEAT seconds (0 for never): nil for now, a number of seconds, a
+/// relative time string, a clock time string for today (perhaps
+/// already past), a time value, or t for the next multiple of REPEAT
+/// since the epoch.
+fn parse_run_at_time_delay(value: &Value, repeat: f64) -> Result<f64, Flow> {
+    let invalid = || signal("error", vec![Value::string("Invalid time specification")]);
     match value {
         Value::Nil => Ok(0.0),
         Value::Int(_) | Value::Float(_) | Value::Char(_) => expect_number(value),
+        Value::True if repeat > 0.0 => {
+            let now = epoch_now();
+            Ok(((now / repeat).floor() + 1.0) * repeat - now)
+        }
         Value::Str(s) => {
             let spec = s.trim();
             if spec.is_empty() {
-                return Err(signal(
-                    "error",
-                    vec![Value::string("Invalid time specification")],
-                ));
+                return Err(invalid());
             }
-
-            if let Ok(delay) = spec.parse::<f64>() {
+            if let Some(delay) = parse_duration(spec) {
                 return Ok(delay);
             }
-
-            if let Some(token) = spec.split_whitespace().next() {
-                if let Ok(delay) = token.parse::<f64>() {
-                    return Ok(delay);
-                }
-            }
-
-            Err(signal(
-                "error",
-                vec![Value::string("Invalid time specification")],
-            ))
+            let minutes = parse_clock_time(spec).ok_or_else(invalid)?;
+            let now = epoch_now();
+            let (offset, _) = super::timefns::local_offset_name_at_epoch(now as i64);
+            let midnight = ((now + offset as f64) / 86400.0).floor() * 86400.0 - offset as f64;
+            Ok(midnight + (minutes * 60) as f64 - now)
         }
-        _ => Err(signal(
-            "error",
-            vec![Value::string("Invalid time specification")],
-        )),
+        Value::Cons(_) => {
+            let at = super::timefns::time_value_secs(value).map_err(|_| invalid())?;
+            Ok(at - epoch_now())
+        }
+        _ => Err(invalid()),
     }
 }
 
@@ -306,18 +599,20 @@ fn expect_timer_id(value: &Value) -> Result<TimerId, Flow> {
 
 /// (run-at-time TIME REPEAT FUNCTION &rest ARGS) -> timer
 ///
-/// TIME is seconds from now (float or int). REPEAT is nil or seconds.
+/// TIME is nil, seconds from now, a relative time string like "2 min",
+/// a clock time string like "11:30pm", a time value, or t with REPEAT
+/// for the next multiple of REPEAT.  REPEAT is nil or seconds.
 pub(crate) fn builtin_run_at_time(
     eval: &mut super::eval::Evaluator,
     args: Vec<Value>,
 ) -> EvalResult {
     expect_min_args("run-at-time", &args, 3)?;
-    let delay = parse_run_at_time_delay(&args[0])?;
     let repeat = if args[1].is_nil() {
         0.0
     } else {
         expect_number(&args[1])?
     };
+    let delay = parse_run_at_time_delay(&args[0], repeat)?;
     let callback = args[2].clone();
     let timer_args: Vec<Value> = args[3..].to_vec();
 
@@ -397,6 +692,81 @@ pub(crate) fn builtin_timer_activate(
     Ok(Value::Nil)
 }
 
+/// (current-idle-time) -> (HIGH LOW USEC PSEC) or nil
+///
+/// How long input has been idle, or nil if it is not.
+pub(crate) fn builtin_current_idle_time(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_args("current-idle-time", &args, 0)?;
+    let Some(idle) = eval.timers.idle_time(Instant::now()) else {
+        return Ok(Value::Nil);
+    };
+    let secs = idle.as_secs() as i64;
+    Ok(Value::list(vec![
+        Value::Int(secs >> 16),
+        Value::Int(secs & 0xFFFF),
+        Value::Int(idle.subsec_micros() as i64),
+        Value::Int((idle.subsec_nanos() % 1000) as i64 * 1000),
+    ]))
+}
+
+/// (timer-statistics) -> list of plists
+///
+/// One plist per timer that is active or has run, costliest first:
+/// (:timer T :function F :idle B :active B :repeat SECS :next SECS
+///  :runs N :skipped N :errors N :lateness SECS :max-lateness SECS
+///  :run-time SECS).  :repeat is nil for one-shot timers and :next is
+/// nil unless the timer is scheduled.
+pub(crate) fn builtin_timer_statistics(
+    eval: &mut super::eval::Evaluator,
+    args: Vec<Value>,
+) -> EvalResult {
+    expect_args("timer-statistics", &args, 0)?;
+    let now = Instant::now();
+    let secs = |d: Duration| Value::Float(d.as_secs_f64());
+    let entries = eval
+        .timers
+        .timers_by_cost()
+        .into_iter()
+        .map(|t| {
+            let next = if t.wheel_key.is_some() {
+                Value::Float(t.fire_time.saturating_duration_since(now).as_secs_f64())
+            } else {
+                Value::Nil
+            };
+            Value::list(vec![
+                Value::keyword(":timer"),
+                Value::Timer(t.id),
+                Value::keyword(":function"),
+                t.callback.clone(),
+                Value::keyword(":idle"),
+                Value::bool(t.idle),
+                Value::keyword(":active"),
+                Value::bool(t.active),
+                Value::keyword(":repeat"),
+                t.repeat_interval.map_or(Value::Nil, secs),
+                Value::keyword(":next"),
+                next,
+                Value::keyword(":runs"),
+                Value::Int(t.stats.runs as i64),
+                Value::keyword(":skipped"),
+                Value::Int(t.stats.skipped as i64),
+                Value::keyword(":errors"),
+                Value::Int(t.stats.errors as i64),
+                Value::keyword(":lateness"),
+                secs(t.stats.last_lateness),
+                Value::keyword(":max-lateness"),
+                secs(t.stats.max_lateness),
+                Value::keyword(":run-time"),
+                secs(t.stats.run_time),
+            ])
+        })
+        .collect();
+    Ok(Value::list(entries))
+}
+
 /// (sleep-for SECONDS &optional MILLISECONDS) -> nil
 pub(crate) fn builtin_sleep_for(args: Vec<Value>) -> EvalResult {
     expect_min_args("sleep-for", &args, 1)?;
@@ -885,4 +1255,227 @@ mod tests {
         let result = builtin_timer_activate(&mut eval, vec![Value::Nil]);
         assert!(matches!(result, Err(Flow::Signal(sig)) if sig.symbol == "error"));
     }
+
+    #[test]
+    fn repeating_timer_keeps_phase_and_caps_catch_up() {
+        let mut mgr = TimerManager::new();
+        let id = mgr.add_timer(0.0, 1.0, Value::symbol("tick"), vec![], false);
+        let start = mgr.timer(id).unwrap().fire_time;
+
+        // Running 300ms late schedules the next run from the deadline
+        assert_eq!(mgr.fire_due(start + Duration::from_millis(300)), [id]);
+        let timer = mgr.timer(id).unwrap();
+        assert_eq!(timer.fire_time, start + Duration::from_secs(1));
+        assert_eq!(timer.stats.last_lateness, Duration::from_millis(300));
+
+        // Asleep for a minute: it catches up only TIMER_MAX_REPEATS times
+        let woke = start + Duration::from_millis(61_500);
+        assert_eq!(mgr.fire_due(woke), [id]);
+        let timer = mgr.timer(id).unwrap();
+        assert_eq!(timer.stats.skipped, 61 - TIMER_MAX_REPEATS as u64);
+        assert_eq!(
+            timer.fire_time,
+            start + Duration::from_secs(63 - TIMER_MAX_REPEATS as u64)
+        );
+        let mut runs = 1;
+        while !mgr.fire_due(woke).is_empty() {
+            runs += 1;
+        }
+        assert_eq!(runs, TIMER_MAX_REPEATS);
+        assert_eq!(
+            mgr.timer(id).unwrap().fire_time,
+            start + Duration::from_secs(62)
+        );
+    }
+
+    #[test]
+    fn idle_timers_follow_input_idle_state() {
+        let mut mgr = TimerManager::new();
+        let once = mgr.add_timer(2.0, 0.0, Value::symbol("once"), vec![], true);
+        let every = mgr.add_timer(1.0, 1.0, Value::symbol("every"), vec![], true);
+        let t0 = Instant::now();
+        // Not idle: nothing is scheduled however long we wait
+        mgr.set_input_idle(None);
+        assert!(mgr.next_fire_time().is_none());
+        assert!(mgr.fire_due(t0 + Duration::from_secs(10)).is_empty());
+
+        mgr.set_input_idle(Some(t0));
+        assert_eq!(
+            mgr.idle_time(t0 + Duration::from_secs(3)),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(mgr.fire_due(t0 + Duration::from_secs(1)), [every]);
+        assert_eq!(mgr.fire_due(t0 + Duration::from_secs(5)), [once]);
+        // Each runs once per idle period
+        assert!(mgr.fire_due(t0 + Duration::from_secs(9)).is_empty());
+        assert!(!mgr.timer_active_p(once));
+
+        // Input arrives, then goes idle again: only the repeating one
+        mgr.set_input_idle(None);
+        assert_eq!(mgr.idle_time(t0), None);
+        let t1 = t0 + Duration::from_secs(20);
+        mgr.set_input_idle(Some(t1));
+        assert_eq!(mgr.fire_due(t1 + Duration::from_secs(3)), [every]);
+    }
+
+    #[test]
+    fn test_eval_idle_timers_run_before_idleness_is_reported() {
+        use super::super::eval::Evaluator;
+
+        let mut eval = Evaluator::new();
+        eval.set_variable("idle-ran", Value::Nil);
+        let forms = super::super::parser::parse_forms(
+            "(run-with-idle-timer 0 nil (lambda () (setq idle-ran t)))
+             (run-with-idle-timer 60 nil 'ignore)",
+        )
+        .unwrap();
+        for result in eval.eval_forms(&forms) {
+            result.unwrap();
+        }
+        // No host reports idleness: each counts its delay from now
+        let delay = eval.next_timer_delay().unwrap();
+        assert!(delay <= Duration::from_millis(10));
+        assert_eq!(eval.run_timers(), 1);
+        assert_eq!(eval.obarray.symbol_value("idle-ran"), Some(&Value::True));
+        assert!(eval.next_timer_delay().unwrap() > Duration::from_secs(50));
+
+        // Once it does, only idleness counts
+        eval.set_input_idle(None);
+        assert!(eval.next_timer_delay().is_none());
+        eval.set_input_idle(Some(Instant::now() - Duration::from_secs(61)));
+        assert_eq!(eval.run_timers(), 1);
+    }
+
+    fn eval_all(eval: &mut super::super::eval::Evaluator, src: &str) -> Value {
+        let forms = super::super::parser::parse_forms(src).unwrap();
+        eval.eval_forms(&forms).pop().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_eval_read_event_runs_timers_while_waiting_for_input() {
+        use crate::keyboard::{InputEvent, KeyEvent};
+
+        let mut eval = super::super::eval::Evaluator::new();
+        let (tx, rx) = std::sync::mpsc::channel();
+        eval.set_input_source(rx);
+        eval_all(
+            &mut eval,
+            "(setq ran nil idle-ran nil)
+             (run-at-time 0.02 nil (lambda () (setq ran t)))
+             (run-with-idle-timer 0.05 nil (lambda () (setq idle-ran t)))",
+        );
+        let typist = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            tx.send(InputEvent::KeyPress(KeyEvent::char('a'))).unwrap();
+            tx
+        });
+        // Both timers run while read-event waits for the key
+        assert_eq!(eval_all(&mut eval, "(read-event)"), Value::Int(97));
+        assert_eq!(eval.obarray.symbol_value("ran"), Some(&Value::True));
+        assert_eq!(eval.obarray.symbol_value("idle-ran"), Some(&Value::True));
+        assert!(eval_all(&mut eval, "(current-idle-time)").is_nil());
+
+        // With a key already pending, input is not idle
+        let tx = typist.join().unwrap();
+        eval_all(
+            &mut eval,
+            "(setq idle-ran nil)
+             (run-with-idle-timer 0 nil (lambda () (setq idle-ran t)))",
+        );
+        tx.send(InputEvent::KeyPress(KeyEvent::char('b'))).unwrap();
+        assert_eq!(eval_all(&mut eval, "(read-event)"), Value::Int(98));
+        assert!(eval.obarray.symbol_value("idle-ran").unwrap().is_nil());
+
+        // No key within SECONDS: idle from the start of the wait
+        assert!(eval_all(&mut eval, "(read-event nil nil 0.05)").is_nil());
+        assert_eq!(eval.obarray.symbol_value("idle-ran"), Some(&Value::True));
+        assert!(!eval_all(&mut eval, "(current-idle-time)").is_nil());
+    }
+
+    #[test]
+    fn test_eval_batch_read_event_runs_due_timers() {
+        let mut eval = super::super::eval::Evaluator::new();
+        eval_all(
+            &mut eval,
+            "(setq ran nil) (run-at-time 0.02 nil (lambda () (setq ran t)))",
+        );
+        // Nothing can arrive without a host: SECONDS is waited out
+        assert!(eval_all(&mut eval, "(read-char nil nil 0.1)").is_nil());
+        assert_eq!(eval.obarray.symbol_value("ran"), Some(&Value::True));
+        assert!(eval.next_timer_delay().is_none());
+    }
+
+    #[test]
+    fn statistics_rank_timers_by_cost() {
+        let mut mgr = TimerManager::new();
+        let cheap = mgr.add_timer(0.0, 1.0, Value::symbol("cheap"), vec![], false);
+        let costly = mgr.add_timer(0.0, 1.0, Value::symbol("costly"), vec![], false);
+        let idle = mgr.add_timer(5.0, 0.0, Value::symbol("idle"), vec![], true);
+        mgr.cancel_timer(idle);
+        mgr.record_run(cheap, Duration::from_millis(1), false);
+        mgr.record_run(costly, Duration::from_millis(40), true);
+        mgr.record_run(costly, Duration::from_millis(40), false);
+
+        let ranked: Vec<TimerId> = mgr.timers_by_cost().iter().map(|t| t.id).collect();
+        assert_eq!(ranked, [costly, cheap]);
+        let stats = &mgr.timer(costly).unwrap().stats;
+        assert_eq!((stats.runs, stats.errors), (2, 1));
+        assert_eq!(stats.run_time, Duration::from_millis(80));
+    }
+
+    #[test]
+    fn test_eval_run_timers_records_statistics() {
+        use super::super::eval::Evaluator;
+
+        let mut eval = Evaluator::new();
+        builtin_run_at_time(
+            &mut eval,
+            vec![Value::Nil, Value::Nil, Value::symbol("car"), Value::Int(1)],
+        )
+        .unwrap();
+        assert_eq!(eval.run_timers(), 1);
+        assert_eq!(eval.run_timers(), 0);
+        assert!(eval.next_timer_delay().is_none());
+
+        let stats = builtin_timer_statistics(&mut eval, vec![]).unwrap();
+        let entries = super::super::value::list_to_vec(&stats).unwrap();
+        assert_eq!(entries.len(), 1);
+        let plist = super::super::value::list_to_vec(&entries[0]).unwrap();
+        assert_eq!(plist[3], Value::symbol("car"));
+        assert_eq!(plist[12], Value::keyword(":runs"));
+        assert_eq!(plist[13], Value::Int(1));
+        // `car' of 1 signals
+        assert_eq!(plist[17], Value::Int(1));
+
+        assert!(builtin_current_idle_time(&mut eval, vec![])
+            .unwrap()
+            .is_nil());
+        eval.set_input_idle(Some(Instant::now() - Duration::from_secs(70_000)));
+        let idle = builtin_current_idle_time(&mut eval, vec![]).unwrap();
+        let parts = super::super::value::list_to_vec(&idle).unwrap();
+        assert_eq!(parts[0], Value::Int(1));
+        assert_eq!(parts[1], Value::Int(70_000 - 65_536));
+    }
+
+    #[test]
+    fn run_at_time_string_specs() {
+        assert_eq!(parse_duration("90"), Some(90.0));
+        assert_eq!(
+            parse_duration("2 hours 35 min"),
+            Some(2.0 * 3600.0 + 35.0 * 60.0)
+        );
+        assert_eq!(parse_duration("1.5 sec"), Some(1.5));
+        assert_eq!(parse_duration("min"), Some(60.0));
+        assert_eq!(parse_duration("3 parsecs"), None);
+        assert_eq!(parse_clock_time("11:30"), Some(11 * 60 + 30));
+        assert_eq!(parse_clock_time("11:30pm"), Some(23 * 60 + 30));
+        assert_eq!(parse_clock_time("12am"), Some(0));
+        assert_eq!(parse_clock_time("9.05 am"), Some(9 * 60 + 5));
+        assert_eq!(parse_clock_time("24:00"), None);
+        assert_eq!(parse_clock_time("abc"), None);
+
+        // t with REPEAT: the next multiple of REPEAT since the epoch
+        let delay = parse_run_at_time_delay(&Value::True, 60.0).unwrap();
+        assert!(delay > 0.0 && delay <= 60.0);
+    }
 }
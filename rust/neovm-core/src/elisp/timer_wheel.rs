@@ -0,0 +1,276 @@
+//! Hierarchical timer wheel.
+//!
+//! Timers are hashed by their deadline, in ticks of one millisecond
+//! since the wheel was made, into four levels of 64 slots.  Level 0
+//! holds the timers due within the current block of 64 ticks, one slot
+//! per tick; level 1 those due later in the current block of 64 * 64
+//! ticks, one slot per 64 ticks; and so on, with timers too far ahead
+//! for level 3 (about 4.6 hours) kept in an overflow list.  Whenever the
+//! current tick enters a new block, the slot of the next level up that
+//! covers the block is emptied into the levels below ("cascading").
+//!
+//! Scheduling and cancelling cost O(1) and finding what is due costs
+//! what is due, however many timers are waiting, instead of a scan of
+//! every timer.  Deadlines are kept exactly: a slot only narrows them to
+//! a tick, and entries due later in the same millisecond stay put.
+
+use std::time::{Duration, Instant};
+
+/// Length of a tick.
+pub const TICK: Duration = Duration::from_millis(1);
+
+const LEVELS: usize = 4;
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const SLOT_MASK: u64 = SLOTS as u64 - 1;
+
+/// A scheduled deadline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Entry {
+    id: u64,
+    deadline: Instant,
+    /// Tick of the deadline, or the tick it was scheduled at if that
+    /// was later
+    tick: u64,
+}
+
+/// Deadlines of timers, by timer id.
+pub struct TimerWheel {
+    origin: Instant,
+    /// The tick everything before has expired
+    tick: u64,
+    slots: Vec<Vec<Entry>>,
+    /// Entries in each level
+    counts: [usize; LEVELS],
+    overflow: Vec<Entry>,
+}
+
+impl TimerWheel {
+    /// An empty wheel whose tick 0 starts at ORIGIN.
+    pub fn new(origin: Instant) -> Self {
+        Self {
+            origin,
+            tick: 0,
+            slots: vec![Vec::new(); LEVELS * SLOTS],
+            counts: [0; LEVELS],
+            overflow: Vec::new(),
+        }
+    }
+
+    fn tick_of(&self, at: Instant) -> u64 {
+        (at.saturating_duration_since(self.origin).as_nanos() / TICK.as_nanos()) as u64
+    }
+
+    /// Level and slot index of an entry due at TICK, or None if it
+    /// belongs in the overflow list.
+    fn slot_of(&self, tick: u64) -> Option<usize> {
+        let diff = tick ^ self.tick;
+        let level = if diff == 0 {
+            0
+        } else {
+            ((63 - diff.leading_zeros()) / SLOT_BITS) as usize
+        };
+        (level < LEVELS)
+            .then(|| level * SLOTS + ((tick >> (SLOT_BITS * level as u32)) & SLOT_MASK) as usize)
+    }
+
+    fn place(&mut self, mut entry: Entry) {
+        entry.tick = entry.tick.max(self.tick);
+        match self.slot_of(entry.tick) {
+            Some(slot) => {
+                self.counts[slot / SLOTS] += 1;
+                self.slots[slot].push(entry);
+            }
+            None => self.overflow.push(entry),
+        }
+    }
+
+    /// Schedule timer ID for DEADLINE.  Returns the key to remove it by.
+    pub fn insert(&mut self, id: u64, deadline: Instant) -> u64 {
+        let tick = self.tick_of(deadline).max(self.tick);
+        self.place(Entry { id, deadline, tick });
+        tick
+    }
+
+    /// Unschedule timer ID, inserted with KEY.  Returns whether it was
+    /// scheduled.
+    pub fn remove(&mut self, id: u64, key: u64) -> bool {
+        let bucket = match self.slot_of(key) {
+            Some(slot) => &mut self.slots[slot],
+            None => &mut self.overflow,
+        };
+        let Some(i) = bucket.iter().position(|e| e.id == id) else {
+            return false;
+        };
+        bucket.swap_remove(i);
+        if let Some(slot) = self.slot_of(key) {
+            self.counts[slot / SLOTS] -= 1;
+        }
+        true
+    }
+
+    /// Number of scheduled timers.
+    pub fn len(&self) -> usize {
+        self.counts.iter().sum::<usize>() + self.overflow.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Move the entries of the slots covering the block the current
+    /// tick just entered down to the levels below.
+    fn cascade(&mut self) {
+        for level in 1..LEVELS {
+            let idx = ((self.tick >> (SLOT_BITS * level as u32)) & SLOT_MASK) as usize;
+            let entries = std::mem::take(&mut self.slots[level * SLOTS + idx]);
+            self.counts[level] -= entries.len();
+            for entry in entries {
+                self.place(entry);
+            }
+            if idx != 0 {
+                return;
+            }
+        }
+        for entry in std::mem::take(&mut self.overflow) {
+            self.place(entry);
+        }
+    }
+
+    /// Remove and return the timers due at NOW, as (id, deadline) in
+    /// deadline order.
+    pub fn expire(&mut self, now: Instant) -> Vec<(u64, Instant)> {
+        let target = self.tick_of(now).max(self.tick);
+        let mut due: Vec<Entry> = Vec::new();
+        loop {
+            let block_end = self.tick | SLOT_MASK;
+            if self.counts[0] > 0 {
+                for tick in self.tick..=target.min(block_end) {
+                    let slot = &mut self.slots[(tick & SLOT_MASK) as usize];
+                    self.counts[0] -= slot.len();
+                    due.append(slot);
+                }
+            }
+            if target <= block_end {
+                self.tick = target;
+                break;
+            }
+            // Level 0 is empty up to the next block; skip the blocks of
+            // every empty level up to the lowest holding anything
+            let level = match (1..LEVELS).find(|&l| self.counts[l] > 0) {
+                Some(level) => level,
+                None if !self.overflow.is_empty() => LEVELS,
+                None => {
+                    self.tick = target;
+                    break;
+                }
+            };
+            let shift = SLOT_BITS * level as u32;
+            let next = ((self.tick >> shift) + 1) << shift;
+            if next > target {
+                self.tick = target;
+                break;
+            }
+            self.tick = next;
+            self.cascade();
+        }
+
+        // Entries due later in the current millisecond wait for it
+        let (mut ready, later): (Vec<Entry>, Vec<Entry>) =
+            due.into_iter().partition(|e| e.deadline <= now);
+        for entry in later {
+            self.place(entry);
+        }
+        ready.sort_by_key(|e| (e.deadline, e.id));
+        ready.into_iter().map(|e| (e.id, e.deadline)).collect()
+    }
+
+    /// The earliest scheduled deadline.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        for level in 0..LEVELS {
+            if self.counts[level] == 0 {
+                continue;
+            }
+            // Level 0 starts at the current tick; entries of higher
+            // levels lie in later blocks than the current one
+            let current = ((self.tick >> (SLOT_BITS * level as u32)) & SLOT_MASK) as usize;
+            let first = if level == 0 { current } else { current + 1 };
+            let earliest = (first..SLOTS)
+                .map(|idx| &self.slots[level * SLOTS + idx])
+                .find(|slot| !slot.is_empty())
+                .and_then(|slot| slot.iter().map(|e| e.deadline).min());
+            if earliest.is_some() {
+                return earliest;
+            }
+        }
+        self.overflow.iter().map(|e| e.deadline).min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn expires_in_deadline_order_across_levels() {
+        let t0 = Instant::now();
+        let mut wheel = TimerWheel::new(t0);
+        // One deadline per level, and one past them all
+        let deadlines = [ms(5), ms(300), ms(70_000), ms(5_000_000), ms(30_000_000)];
+        for (id, &d) in deadlines.iter().enumerate().rev() {
+            wheel.insert(id as u64, t0 + d);
+        }
+        assert_eq!(wheel.len(), 5);
+        assert_eq!(wheel.next_deadline(), Some(t0 + ms(5)));
+
+        assert!(wheel.expire(t0 + ms(4)).is_empty());
+        assert_eq!(wheel.expire(t0 + ms(5)), [(0, t0 + ms(5))]);
+        assert_eq!(wheel.next_deadline(), Some(t0 + ms(300)));
+        // A long jump expires everything on the way, in order
+        let due: Vec<u64> = wheel
+            .expire(t0 + ms(6_000_000))
+            .iter()
+            .map(|d| d.0)
+            .collect();
+        assert_eq!(due, [1, 2, 3]);
+        assert_eq!(wheel.next_deadline(), Some(t0 + ms(30_000_000)));
+        assert_eq!(
+            wheel.expire(t0 + ms(30_000_000)),
+            [(4, t0 + ms(30_000_000))]
+        );
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn keeps_deadlines_exact_within_a_tick() {
+        let t0 = Instant::now();
+        let mut wheel = TimerWheel::new(t0);
+        let d = t0 + Duration::from_micros(10_600);
+        wheel.insert(1, d);
+        assert!(wheel.expire(t0 + Duration::from_micros(10_300)).is_empty());
+        assert_eq!(wheel.next_deadline(), Some(d));
+        assert_eq!(wheel.expire(d), [(1, d)]);
+
+        // Deadlines already past are due at once
+        wheel.insert(2, t0);
+        assert_eq!(wheel.expire(d), [(2, t0)]);
+    }
+
+    #[test]
+    fn removes_entries_after_cascading() {
+        let t0 = Instant::now();
+        let mut wheel = TimerWheel::new(t0);
+        let key = wheel.insert(7, t0 + ms(200));
+        wheel.insert(8, t0 + ms(210));
+        // Cascade the block holding both into level 0
+        assert!(wheel.expire(t0 + ms(195)).is_empty());
+        assert!(wheel.remove(7, key));
+        assert!(!wheel.remove(7, key));
+        assert_eq!(wheel.next_deadline(), Some(t0 + ms(210)));
+        assert_eq!(wheel.expire(t0 + ms(400)), [(8, t0 + ms(210))]);
+    }
+}
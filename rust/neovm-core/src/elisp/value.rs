@@ -35,6 +35,8 @@ pub enum Value {
     Buffer(crate::buffer::BufferId),
     /// Timer reference (opaque id into the TimerManager).
     Timer(u64),
+    /// SQLite database or set reference (opaque id into the SqliteManager).
+    Sqlite(u64),
 }
 
 impl PartialEq for Value {
@@ -290,6 +292,7 @@ impl Value {
             Value::ByteCode(_) => "byte-code-function",
             Value::Buffer(_) => "buffer",
             Value::Timer(_) => "timer",
+            Value::Sqlite(_) => "sqlite",
         }
     }
 
@@ -364,6 +367,7 @@ impl Value {
             Value::ByteCode(b) => HashKey::Ptr(Arc::as_ptr(b) as usize),
             Value::Buffer(id) => HashKey::Int(id.0 as i64),
             Value::Timer(id) => HashKey::Int(*id as i64),
+            Value::Sqlite(id) => HashKey::Int(*id as i64),
         }
     }
 
@@ -419,6 +423,7 @@ pub fn eq_value(left: &Value, right: &Value) -> bool {
         (Value::ByteCode(a), Value::ByteCode(b)) => Arc::ptr_eq(a, b),
         (Value::Buffer(a), Value::Buffer(b)) => a == b,
         (Value::Timer(a), Value::Timer(b)) => a == b,
+        (Value::Sqlite(a), Value::Sqlite(b)) => a == b,
         _ => false,
     }
 }
@@ -469,6 +474,7 @@ pub fn equal_value(left: &Value, right: &Value, depth: usize) -> bool {
         (Value::ByteCode(a), Value::ByteCode(b)) => Arc::ptr_eq(a, b),
         (Value::Buffer(a), Value::Buffer(b)) => a == b,
         (Value::Timer(a), Value::Timer(b)) => a == b,
+        (Value::Sqlite(a), Value::Sqlite(b)) => a == b,
         _ => false,
     }
 }
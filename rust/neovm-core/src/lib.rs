@@ -5,6 +5,7 @@ pub mod face;
 pub mod gc;
 pub mod hooks;
 pub mod keyboard;
+pub mod remote;
 pub mod window;
 
 #[cfg(all(feature = "core-backend-emacs-c", feature = "core-backend-rust"))]
@@ -0,0 +1,725 @@
+//! Remote file access for TRAMP-style file names.
+//!
+//! # Architecture
+//!
+//! ```text
+//! fileio builtin ──► FileHandlerRegistry ──► FileHandler (per method)
+//!                                              │
+//!                                              └─► SshFileHandler ──► pool ──► SftpClient
+//! ```
+//!
+//! - **Remote names** use TRAMP syntax: `/METHOD:[USER@]HOST[#PORT]:LOCALNAME`.
+//! - **Dispatch**: `FileHandlerRegistry` maps a method name to a handler, the
+//!   same role `file-name-handler-alist` plays in Emacs.  Local names never
+//!   reach a handler.
+//! - **Connection pooling**: `SshFileHandler` keeps one `ssh -s sftp`
+//!   subsystem process per `(user, host, port)` and reuses it for every
+//!   operation.  A connection that fails is dropped and re-established once.
+//! - **Background connect**: `SshFileHandler::connect_in_background` performs
+//!   the SSH handshake on a worker thread so the first remote operation does
+//!   not pay for it on the Lisp thread.
+//! - **Timeouts**: ssh gives up on an unreachable host after
+//!   `CONNECT_TIMEOUT_SECS`, and a reply that does not arrive within
+//!   `IO_TIMEOUT` drops the connection instead of hanging the Lisp thread.
+//!   Requests are sent one at a time and stay far below the pipe buffer, so
+//!   writes to a stalled server do not block.
+
+pub mod sftp;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, ErrorKind, Read};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+pub use sftp::{DirEntry, FileAttrs, SftpClient};
+
+// ---------------------------------------------------------------------------
+// Remote file names
+// ---------------------------------------------------------------------------
+
+/// A parsed TRAMP file name.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RemotePath {
+    pub method: String,
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+    /// The file name on the remote host.
+    pub localname: String,
+}
+
+impl RemotePath {
+    /// Parse `/METHOD:[USER@]HOST[#PORT]:LOCALNAME`.  Returns `None` for
+    /// ordinary local file names.
+    pub fn parse(name: &str) -> Option<Self> {
+        let rest = name.strip_prefix('/')?;
+        let (method, rest) = rest.split_once(':')?;
+        if method.is_empty()
+            || !method
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return None;
+        }
+        let (userhost, localname) = rest.split_once(':')?;
+        let (user, hostport) = match userhost.rsplit_once('@') {
+            Some((user, hostport)) if !user.is_empty() => (Some(user.to_string()), hostport),
+            Some((_, hostport)) => (None, hostport),
+            None => (None, userhost),
+        };
+        let (host, port) = match hostport.split_once('#') {
+            Some((host, port)) => (host, Some(port.parse::<u16>().ok()?)),
+            None => (hostport, None),
+        };
+        // A leading '-' would reach ssh as an option (-oProxyCommand=...)
+        if host.is_empty()
+            || host.contains('/')
+            || host.starts_with('-')
+            || user.as_deref().is_some_and(|u| u.starts_with('-'))
+        {
+            return None;
+        }
+        Some(Self {
+            method: method.to_string(),
+            user,
+            host: host.to_string(),
+            port,
+            localname: if localname.is_empty() {
+                "~".to_string()
+            } else {
+                localname.to_string()
+            },
+        })
+    }
+
+    /// The remote prefix, e.g. `/ssh:user@host:`.
+    pub fn prefix(&self) -> String {
+        let mut out = format!("/{}:", self.method);
+        if let Some(user) = &self.user {
+            out.push_str(user);
+            out.push('@');
+        }
+        out.push_str(&self.host);
+        if let Some(port) = self.port {
+            out.push('#');
+            out.push_str(&port.to_string());
+        }
+        out.push(':');
+        out
+    }
+
+    /// The same connection with a different local name.
+    pub fn with_localname(&self, localname: impl Into<String>) -> Self {
+        Self {
+            localname: localname.into(),
+            ..self.clone()
+        }
+    }
+
+    /// Whether two names live on the same connection.
+    pub fn same_connection(&self, other: &RemotePath) -> bool {
+        self.connection_key() == other.connection_key()
+    }
+
+    fn connection_key(&self) -> ConnectionKey {
+        ConnectionKey {
+            user: self.user.clone(),
+            host: self.host.clone(),
+            port: self.port,
+        }
+    }
+}
+
+impl fmt::Display for RemotePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.prefix(), self.localname)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// File handler dispatch
+// ---------------------------------------------------------------------------
+
+/// Operations a handler performs on behalf of the file primitives.
+///
+/// All paths are already parsed; handlers work on `RemotePath::localname`.
+pub trait FileHandler: Send + Sync {
+    /// Attributes of PATH, or `None` if it does not exist.
+    fn file_attributes(&self, path: &RemotePath) -> io::Result<Option<FileAttrs>>;
+
+    /// Whether PATH exists and the remote user may read it.
+    fn file_readable(&self, path: &RemotePath) -> io::Result<bool>;
+
+    fn read_file(&self, path: &RemotePath) -> io::Result<Vec<u8>>;
+
+    fn write_file(&self, path: &RemotePath, data: &[u8], append: bool) -> io::Result<()>;
+
+    fn directory_files(&self, path: &RemotePath) -> io::Result<Vec<DirEntry>>;
+
+    fn delete_file(&self, path: &RemotePath) -> io::Result<()>;
+
+    fn delete_directory(&self, path: &RemotePath) -> io::Result<()>;
+
+    fn make_directory(&self, path: &RemotePath) -> io::Result<()>;
+
+    /// Rename within one connection.
+    fn rename_file(&self, from: &RemotePath, to: &RemotePath) -> io::Result<()>;
+
+    /// Whether a live connection for PATH exists (for `file-remote-p`'s
+    /// CONNECTED argument).
+    fn connected(&self, path: &RemotePath) -> bool;
+}
+
+/// Maps TRAMP method names to handlers.
+#[derive(Clone)]
+pub struct FileHandlerRegistry {
+    handlers: HashMap<String, Arc<dyn FileHandler>>,
+}
+
+impl Default for FileHandlerRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileHandlerRegistry {
+    /// A registry with the SSH/SFTP handler installed for the `ssh`, `sftp`
+    /// and `scp` methods.
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        let ssh: Arc<dyn FileHandler> = Arc::new(SshFileHandler::new());
+        for method in ["ssh", "sftp", "scp"] {
+            registry.register(method, ssh.clone());
+        }
+        registry
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, method: &str, handler: Arc<dyn FileHandler>) {
+        self.handlers.insert(method.to_string(), handler);
+    }
+
+    pub fn unregister(&mut self, method: &str) -> bool {
+        self.handlers.remove(method).is_some()
+    }
+
+    /// Find the handler responsible for FILENAME.
+    pub fn find(&self, filename: &str) -> Option<(Arc<dyn FileHandler>, RemotePath)> {
+        let path = RemotePath::parse(filename)?;
+        let handler = self.handlers.get(&path.method)?.clone();
+        Some((handler, path))
+    }
+
+    /// Whether FILENAME names a file handled by a registered method.
+    pub fn is_remote(&self, filename: &str) -> bool {
+        RemotePath::parse(filename).is_some_and(|p| self.handlers.contains_key(&p.method))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// SSH/SFTP handler with connection pooling
+// ---------------------------------------------------------------------------
+
+/// Seconds ssh waits for the TCP connection to the host.
+const CONNECT_TIMEOUT_SECS: u32 = 15;
+
+/// How long to wait for any part of a reply before the server is presumed
+/// stalled.
+const IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct ConnectionKey {
+    user: Option<String>,
+    host: String,
+    port: Option<u16>,
+}
+
+/// One `ssh -s sftp` subsystem process and its protocol client.
+pub struct SshConnection {
+    child: Child,
+    client: SftpClient<TimeoutReader, ChildStdin>,
+    /// Remote home directory, used to resolve `~`-relative names.
+    home: Option<String>,
+}
+
+/// The command that starts the SFTP subsystem for KEY.  The host follows
+/// `--`, so nothing taken from a file name is read as an ssh option.
+fn ssh_command(program: &str, key: &ConnectionKey) -> Command {
+    let mut cmd = Command::new(program);
+    cmd.args([
+        "-x",
+        "-a",
+        "-o",
+        "BatchMode=yes",
+        "-o",
+        "ServerAliveInterval=30",
+        "-o",
+    ])
+    .arg(format!("ConnectTimeout={}", CONNECT_TIMEOUT_SECS));
+    if let Some(port) = key.port {
+        cmd.arg("-p").arg(port.to_string());
+    }
+    if let Some(user) = &key.user {
+        cmd.arg("-l").arg(user);
+    }
+    cmd.args(["-s", "--"])
+        .arg(&key.host)
+        .arg("sftp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    cmd
+}
+
+impl SshConnection {
+    fn open(program: &str, key: &ConnectionKey) -> io::Result<Self> {
+        let mut cmd = ssh_command(program, key);
+        // A missing ssh binary must not read as a missing remote file.
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| io::Error::other(format!("cannot run {}: {}", program, e)))?;
+        let stdin = child.stdin.take().expect("piped stdin");
+        let stdout = TimeoutReader::new(child.stdout.take().expect("piped stdout"), IO_TIMEOUT);
+        match SftpClient::handshake(stdout, stdin) {
+            Ok(mut client) => {
+                let home = client.realpath(".").ok();
+                Ok(Self {
+                    child,
+                    client,
+                    home,
+                })
+            }
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(e)
+            }
+        }
+    }
+
+    fn resolve(&self, localname: &str) -> String {
+        resolve_home(self.home.as_deref(), localname)
+    }
+}
+
+/// LOCALNAME with a leading `~` replaced by HOME, when HOME is known.
+fn resolve_home(home: Option<&str>, localname: &str) -> String {
+    match (home, localname.strip_prefix('~')) {
+        (Some(home), Some("")) => home.to_string(),
+        (Some(home), Some(rest)) if rest.starts_with('/') => format!("{}{}", home, rest),
+        _ => localname.to_string(),
+    }
+}
+
+impl Drop for SshConnection {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Reads a child's output on a pump thread, so that a read can give up
+/// after a timeout instead of blocking until the child writes.  The pump
+/// thread exits when the output closes, which killing the child ensures.
+struct TimeoutReader {
+    chunks: Receiver<io::Result<Vec<u8>>>,
+    pending: Vec<u8>,
+    pos: usize,
+    timeout: Duration,
+}
+
+impl TimeoutReader {
+    fn new<R: Read + Send + 'static>(mut source: R, timeout: Duration) -> Self {
+        let (tx, chunks) = mpsc::sync_channel(4);
+        thread::spawn(move || {
+            let mut buf = vec![0u8; 64 * 1024];
+            loop {
+                let chunk = match source.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => Ok(buf[..n].to_vec()),
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => Err(e),
+                };
+                let failed = chunk.is_err();
+                if tx.send(chunk).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Self {
+            chunks,
+            pending: Vec::new(),
+            pos: 0,
+            timeout,
+        }
+    }
+}
+
+impl Read for TimeoutReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.pending.len() {
+            match self.chunks.recv_timeout(self.timeout) {
+                Ok(chunk) => {
+                    self.pending = chunk?;
+                    self.pos = 0;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        ErrorKind::TimedOut,
+                        "remote host stopped responding",
+                    ))
+                }
+                // The output closed
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.pending.len() - self.pos);
+        buf[..n].copy_from_slice(&self.pending[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+type Pool = Arc<Mutex<HashMap<ConnectionKey, Arc<Mutex<SshConnection>>>>>;
+
+/// File handler for the `ssh`/`sftp`/`scp` methods.
+pub struct SshFileHandler {
+    program: String,
+    pool: Pool,
+}
+
+impl Default for SshFileHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SshFileHandler {
+    pub fn new() -> Self {
+        Self::with_program("ssh")
+    }
+
+    /// Use PROGRAM instead of `ssh` to start the SFTP subsystem.
+    pub fn with_program(program: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            pool: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Number of pooled connections.
+    pub fn connection_count(&self) -> usize {
+        self.pool.lock().expect("poisoned").len()
+    }
+
+    fn connection(&self, key: &ConnectionKey) -> io::Result<Arc<Mutex<SshConnection>>> {
+        if let Some(conn) = self.pool.lock().expect("poisoned").get(key) {
+            return Ok(conn.clone());
+        }
+        let conn = Arc::new(Mutex::new(SshConnection::open(&self.program, key)?));
+        Ok(self
+            .pool
+            .lock()
+            .expect("poisoned")
+            .entry(key.clone())
+            .or_insert(conn)
+            .clone())
+    }
+
+    /// Start connecting to PATH's host on a worker thread.
+    pub fn connect_in_background(&self, path: &RemotePath) -> thread::JoinHandle<io::Result<()>> {
+        let key = path.connection_key();
+        let pool = self.pool.clone();
+        let program = self.program.clone();
+        thread::spawn(move || {
+            if pool.lock().expect("poisoned").contains_key(&key) {
+                return Ok(());
+            }
+            let conn = SshConnection::open(&program, &key)?;
+            pool.lock()
+                .expect("poisoned")
+                .entry(key)
+                .or_insert_with(|| Arc::new(Mutex::new(conn)));
+            Ok(())
+        })
+    }
+
+    /// Drop the pooled connection for PATH's host, if any.
+    pub fn disconnect(&self, path: &RemotePath) -> bool {
+        self.pool
+            .lock()
+            .expect("poisoned")
+            .remove(&path.connection_key())
+            .is_some()
+    }
+
+    /// Run OP on the pooled connection for PATH.  Transport failures (as
+    /// opposed to server-reported errors) evict the connection and retry
+    /// once, except a timeout: a stalled server would stall the retry too.
+    fn with_connection<T>(
+        &self,
+        path: &RemotePath,
+        mut op: impl FnMut(&mut SshConnection) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let key = path.connection_key();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let conn = self.connection(&key)?;
+            let result = op(&mut conn.lock().expect("poisoned"));
+            match result {
+                Err(e) if is_transport_error(&e) => {
+                    self.pool.lock().expect("poisoned").remove(&key);
+                    if attempts >= 2 || e.kind() == ErrorKind::TimedOut {
+                        return Err(e);
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+
+    /// Run OP on PATH's client with PATH's local name resolved.
+    fn with_client<T>(
+        &self,
+        path: &RemotePath,
+        mut op: impl FnMut(&mut SftpClient<TimeoutReader, ChildStdin>, &str) -> io::Result<T>,
+    ) -> io::Result<T> {
+        self.with_connection(path, |conn| {
+            let localname = conn.resolve(&path.localname);
+            op(&mut conn.client, &localname)
+        })
+    }
+}
+
+fn is_transport_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::BrokenPipe
+            | ErrorKind::UnexpectedEof
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::InvalidData
+            | ErrorKind::TimedOut
+    )
+}
+
+impl FileHandler for SshFileHandler {
+    fn file_attributes(&self, path: &RemotePath) -> io::Result<Option<FileAttrs>> {
+        match self.with_client(path, |c, p| c.stat(p)) {
+            Ok(attrs) => Ok(Some(attrs)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn file_readable(&self, path: &RemotePath) -> io::Result<bool> {
+        self.with_client(path, |c, p| match c.stat(p) {
+            Ok(attrs) => c.can_read(p, attrs.is_dir()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        })
+    }
+
+    fn read_file(&self, path: &RemotePath) -> io::Result<Vec<u8>> {
+        self.with_client(path, |c, p| c.read_file(p))
+    }
+
+    fn write_file(&self, path: &RemotePath, data: &[u8], append: bool) -> io::Result<()> {
+        self.with_client(path, |c, p| c.write_file(p, data, append))
+    }
+
+    fn directory_files(&self, path: &RemotePath) -> io::Result<Vec<DirEntry>> {
+        self.with_client(path, |c, p| c.read_dir(p))
+    }
+
+    fn delete_file(&self, path: &RemotePath) -> io::Result<()> {
+        self.with_client(path, |c, p| c.remove(p))
+    }
+
+    fn delete_directory(&self, path: &RemotePath) -> io::Result<()> {
+        self.with_client(path, |c, p| c.rmdir(p))
+    }
+
+    fn make_directory(&self, path: &RemotePath) -> io::Result<()> {
+        self.with_client(path, |c, p| c.mkdir(p))
+    }
+
+    fn rename_file(&self, from: &RemotePath, to: &RemotePath) -> io::Result<()> {
+        if !from.same_connection(to) {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "rename across remote hosts",
+            ));
+        }
+        self.with_connection(from, |conn| {
+            let (source, target) = (conn.resolve(&from.localname), conn.resolve(&to.localname));
+            conn.client.rename(&source, &target)
+        })
+    }
+
+    fn connected(&self, path: &RemotePath) -> bool {
+        self.pool
+            .lock()
+            .expect("poisoned")
+            .contains_key(&path.connection_key())
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_full_remote_name() {
+        let p = RemotePath::parse("/ssh:alice@example.com#2222:/etc/hosts").unwrap();
+        assert_eq!(p.method, "ssh");
+        assert_eq!(p.user.as_deref(), Some("alice"));
+        assert_eq!(p.host, "example.com");
+        assert_eq!(p.port, Some(2222));
+        assert_eq!(p.localname, "/etc/hosts");
+        assert_eq!(p.to_string(), "/ssh:alice@example.com#2222:/etc/hosts");
+    }
+
+    #[test]
+    fn parse_minimal_remote_name() {
+        let p = RemotePath::parse("/sftp:box:").unwrap();
+        assert_eq!(p.user, None);
+        assert_eq!(p.port, None);
+        assert_eq!(p.localname, "~");
+        assert_eq!(p.prefix(), "/sftp:box:");
+    }
+
+    #[test]
+    fn parse_rejects_local_names() {
+        assert!(RemotePath::parse("/etc/hosts").is_none());
+        assert!(RemotePath::parse("relative:name:x").is_none());
+        assert!(RemotePath::parse("/ssh::/x").is_none());
+        assert!(RemotePath::parse("/ssh:host#notaport:/x").is_none());
+        assert!(RemotePath::parse("/a/b:c:d").is_none());
+    }
+
+    #[test]
+    fn parse_rejects_option_like_user_or_host() {
+        assert!(RemotePath::parse("/ssh:-oProxyCommand=sh -c id:/x").is_none());
+        assert!(RemotePath::parse("/ssh:-oProxyCommand=x@host:/x").is_none());
+        assert!(RemotePath::parse("/ssh:alice@-F/dev/null:/x").is_none());
+        assert!(RemotePath::parse("/ssh:-p#22:/x").is_none());
+        // Dashes elsewhere are fine
+        let p = RemotePath::parse("/ssh:a-b@my-host:/x").unwrap();
+        assert_eq!(p.user.as_deref(), Some("a-b"));
+        assert_eq!(p.host, "my-host");
+    }
+
+    #[test]
+    fn ssh_command_ends_options_before_host() {
+        let path = RemotePath::parse("/ssh:alice@example.com#2222:/x").unwrap();
+        let cmd = ssh_command("ssh", &path.connection_key());
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy()).collect();
+        assert_eq!(
+            args[args.len() - 8..],
+            [
+                "-p",
+                "2222",
+                "-l",
+                "alice",
+                "-s",
+                "--",
+                "example.com",
+                "sftp"
+            ]
+        );
+    }
+
+    #[test]
+    fn same_connection_ignores_localname() {
+        let a = RemotePath::parse("/ssh:h:/a").unwrap();
+        let b = a.with_localname("/b");
+        let c = RemotePath::parse("/ssh:other@h:/a").unwrap();
+        assert!(a.same_connection(&b));
+        assert!(!a.same_connection(&c));
+    }
+
+    #[test]
+    fn home_relative_names_resolve() {
+        let home = Some("/home/alice");
+        assert_eq!(resolve_home(home, "~"), "/home/alice");
+        assert_eq!(resolve_home(home, "~/notes.org"), "/home/alice/notes.org");
+        assert_eq!(resolve_home(home, "~bob/x"), "~bob/x");
+        assert_eq!(resolve_home(home, "/etc/hosts"), "/etc/hosts");
+        assert_eq!(resolve_home(None, "~/x"), "~/x");
+    }
+
+    #[test]
+    fn registry_dispatches_by_method() {
+        let registry = FileHandlerRegistry::new();
+        assert!(registry.is_remote("/ssh:host:/x"));
+        assert!(registry.is_remote("/scp:host:/x"));
+        assert!(!registry.is_remote("/docker:host:/x"));
+        assert!(!registry.is_remote("/tmp/x"));
+        let (_, path) = registry.find("/sftp:u@h:/srv").unwrap();
+        assert_eq!(path.localname, "/srv");
+    }
+
+    #[test]
+    fn registry_unregister() {
+        let mut registry = FileHandlerRegistry::new();
+        assert!(registry.unregister("scp"));
+        assert!(!registry.is_remote("/scp:host:/x"));
+        assert!(!registry.unregister("scp"));
+    }
+
+    #[test]
+    fn timeout_reader_passes_output_through() {
+        let mut reader = TimeoutReader::new(io::Cursor::new(b"sftp reply".to_vec()), IO_TIMEOUT);
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"sftp reply");
+    }
+
+    #[test]
+    fn timeout_reader_gives_up_on_stalled_output() {
+        /// Output that blocks until the test drops the sender.
+        struct Stalled(Receiver<()>);
+        impl Read for Stalled {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                let _ = self.0.recv();
+                Ok(0)
+            }
+        }
+        let (release, stalled) = mpsc::channel();
+        let mut reader = TimeoutReader::new(Stalled(stalled), Duration::from_millis(20));
+        let err = reader.read(&mut [0u8; 4]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(is_transport_error(&err));
+        drop(release);
+    }
+
+    #[test]
+    fn failed_connection_is_not_pooled() {
+        let handler = SshFileHandler::with_program("/nonexistent/neovm-ssh");
+        let path = RemotePath::parse("/ssh:h:/x").unwrap();
+        assert!(handler.file_attributes(&path).is_err());
+        assert!(!handler.connected(&path));
+        assert_eq!(handler.connection_count(), 0);
+        assert!(handler
+            .connect_in_background(&path)
+            .join()
+            .unwrap()
+            .is_err());
+        assert_eq!(handler.connection_count(), 0);
+    }
+}
@@ -0,0 +1,818 @@
+//! SFTP version 3 client (draft-ietf-secsh-filexfer-02).
+//!
+//! The client speaks the binary SFTP protocol over any `Read`/`Write` pair.
+//! In production that pair is the stdio of an `ssh -s sftp` subsystem
+//! process; in tests it is an in-memory script of server replies.
+//!
+//! Every request carries a monotonically increasing id and the client waits
+//! for the matching reply, so a single client must not be shared between
+//! threads without external locking (the connection pool does that).
+
+use std::io::{self, ErrorKind, Read, Write};
+
+// ---------------------------------------------------------------------------
+// Protocol constants
+// ---------------------------------------------------------------------------
+
+pub const SFTP_VERSION: u32 = 3;
+
+const FXP_INIT: u8 = 1;
+const FXP_VERSION: u8 = 2;
+const FXP_OPEN: u8 = 3;
+const FXP_CLOSE: u8 = 4;
+const FXP_READ: u8 = 5;
+const FXP_WRITE: u8 = 6;
+const FXP_LSTAT: u8 = 7;
+const FXP_OPENDIR: u8 = 11;
+const FXP_READDIR: u8 = 12;
+const FXP_REMOVE: u8 = 13;
+const FXP_MKDIR: u8 = 14;
+const FXP_RMDIR: u8 = 15;
+const FXP_REALPATH: u8 = 16;
+const FXP_STAT: u8 = 17;
+const FXP_RENAME: u8 = 18;
+
+const FXP_STATUS: u8 = 101;
+const FXP_HANDLE: u8 = 102;
+const FXP_DATA: u8 = 103;
+const FXP_NAME: u8 = 104;
+const FXP_ATTRS: u8 = 105;
+
+const FXF_READ: u32 = 0x01;
+const FXF_WRITE: u32 = 0x02;
+const FXF_APPEND: u32 = 0x04;
+const FXF_CREAT: u32 = 0x08;
+const FXF_TRUNC: u32 = 0x10;
+
+const ATTR_SIZE: u32 = 0x0000_0001;
+const ATTR_UIDGID: u32 = 0x0000_0002;
+const ATTR_PERMISSIONS: u32 = 0x0000_0004;
+const ATTR_ACMODTIME: u32 = 0x0000_0008;
+const ATTR_EXTENDED: u32 = 0x8000_0000;
+
+const FX_OK: u32 = 0;
+const FX_EOF: u32 = 1;
+const FX_NO_SUCH_FILE: u32 = 2;
+const FX_PERMISSION_DENIED: u32 = 3;
+const FX_OP_UNSUPPORTED: u32 = 8;
+
+/// Largest chunk requested per READ/WRITE.  OpenSSH accepts up to 256 KiB,
+/// but 32 KiB is the conservative limit every server must support.
+const MAX_CHUNK: usize = 32 * 1024;
+
+/// Refuse packets larger than this to avoid unbounded allocation on a
+/// corrupted stream.
+const MAX_PACKET: usize = 4 * 1024 * 1024;
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFREG: u32 = 0o100000;
+
+// ---------------------------------------------------------------------------
+// Attributes
+// ---------------------------------------------------------------------------
+
+/// File attributes as reported by the server.  Absent fields are `None`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileAttrs {
+    pub size: Option<u64>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub permissions: Option<u32>,
+    pub atime: Option<u32>,
+    pub mtime: Option<u32>,
+}
+
+impl FileAttrs {
+    fn file_type(&self) -> Option<u32> {
+        self.permissions.map(|p| p & S_IFMT)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.file_type() == Some(S_IFDIR)
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.file_type() == Some(S_IFLNK)
+    }
+
+    pub fn is_regular(&self) -> bool {
+        self.file_type() == Some(S_IFREG)
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut flags = 0;
+        if self.size.is_some() {
+            flags |= ATTR_SIZE;
+        }
+        if self.uid.is_some() && self.gid.is_some() {
+            flags |= ATTR_UIDGID;
+        }
+        if self.permissions.is_some() {
+            flags |= ATTR_PERMISSIONS;
+        }
+        if self.atime.is_some() && self.mtime.is_some() {
+            flags |= ATTR_ACMODTIME;
+        }
+        put_u32(out, flags);
+        if let Some(size) = self.size {
+            put_u64(out, size);
+        }
+        if let (Some(uid), Some(gid)) = (self.uid, self.gid) {
+            put_u32(out, uid);
+            put_u32(out, gid);
+        }
+        if let Some(perm) = self.permissions {
+            put_u32(out, perm);
+        }
+        if let (Some(atime), Some(mtime)) = (self.atime, self.mtime) {
+            put_u32(out, atime);
+            put_u32(out, mtime);
+        }
+    }
+
+    fn decode(buf: &mut Decoder<'_>) -> io::Result<Self> {
+        let flags = buf.u32()?;
+        let mut attrs = FileAttrs::default();
+        if flags & ATTR_SIZE != 0 {
+            attrs.size = Some(buf.u64()?);
+        }
+        if flags & ATTR_UIDGID != 0 {
+            attrs.uid = Some(buf.u32()?);
+            attrs.gid = Some(buf.u32()?);
+        }
+        if flags & ATTR_PERMISSIONS != 0 {
+            attrs.permissions = Some(buf.u32()?);
+        }
+        if flags & ATTR_ACMODTIME != 0 {
+            attrs.atime = Some(buf.u32()?);
+            attrs.mtime = Some(buf.u32()?);
+        }
+        if flags & ATTR_EXTENDED != 0 {
+            let count = buf.u32()?;
+            for _ in 0..count {
+                buf.bytes()?;
+                buf.bytes()?;
+            }
+        }
+        Ok(attrs)
+    }
+}
+
+/// One entry returned by READDIR.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    /// `ls -l` style line supplied by the server.
+    pub longname: String,
+    pub attrs: FileAttrs,
+}
+
+// ---------------------------------------------------------------------------
+// Wire encoding
+// ---------------------------------------------------------------------------
+
+fn put_u32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+fn put_u64(out: &mut Vec<u8>, v: u64) {
+    out.extend_from_slice(&v.to_be_bytes());
+}
+
+fn put_bytes(out: &mut Vec<u8>, data: &[u8]) {
+    put_u32(out, data.len() as u32);
+    out.extend_from_slice(data);
+}
+
+fn protocol_error(msg: impl Into<String>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg.into())
+}
+
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.pos + n > self.data.len() {
+            return Err(protocol_error("truncated SFTP packet"));
+        }
+        let slice = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        let b = self.take(8)?;
+        let mut arr = [0u8; 8];
+        arr.copy_from_slice(b);
+        Ok(u64::from_be_bytes(arr))
+    }
+
+    fn bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        Ok(String::from_utf8_lossy(self.bytes()?).into_owned())
+    }
+}
+
+/// Encode a full packet: length prefix, type byte, payload.
+fn encode_packet(kind: u8, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 5);
+    put_u32(&mut out, payload.len() as u32 + 1);
+    out.push(kind);
+    out.extend_from_slice(payload);
+    out
+}
+
+fn read_packet<R: Read>(reader: &mut R) -> io::Result<(u8, Vec<u8>)> {
+    let mut len_buf = [0u8; 4];
+    reader.read_exact(&mut len_buf)?;
+    let len = u32::from_be_bytes(len_buf) as usize;
+    if len == 0 || len > MAX_PACKET {
+        return Err(protocol_error(format!("bad SFTP packet length {}", len)));
+    }
+    let mut body = vec![0u8; len];
+    reader.read_exact(&mut body)?;
+    let kind = body[0];
+    body.remove(0);
+    Ok((kind, body))
+}
+
+fn status_error(code: u32, message: &str) -> io::Error {
+    let kind = match code {
+        FX_NO_SUCH_FILE => ErrorKind::NotFound,
+        FX_PERMISSION_DENIED => ErrorKind::PermissionDenied,
+        FX_OP_UNSUPPORTED => ErrorKind::Unsupported,
+        // FX_EOF is an ordinary reply: `UnexpectedEof` is kept for a
+        // truncated stream, which the pool treats as a dead connection.
+        _ => ErrorKind::Other,
+    };
+    let message = if message.is_empty() {
+        format!("SFTP status {}", code)
+    } else {
+        message.to_string()
+    };
+    io::Error::new(kind, message)
+}
+
+// ---------------------------------------------------------------------------
+// Client
+// ---------------------------------------------------------------------------
+
+/// A reply with its request id already stripped.
+enum Reply {
+    Status(u32, String),
+    Handle(Vec<u8>),
+    Data(Vec<u8>),
+    Name(Vec<DirEntry>),
+    Attrs(FileAttrs),
+}
+
+pub struct SftpClient<R: Read, W: Write> {
+    reader: R,
+    writer: W,
+    next_id: u32,
+    /// Protocol version negotiated with the server.
+    pub version: u32,
+}
+
+impl<R: Read, W: Write> SftpClient<R, W> {
+    /// Send INIT and wait for VERSION.
+    pub fn handshake(mut reader: R, mut writer: W) -> io::Result<Self> {
+        let mut payload = Vec::new();
+        put_u32(&mut payload, SFTP_VERSION);
+        writer.write_all(&encode_packet(FXP_INIT, &payload))?;
+        writer.flush()?;
+        let (kind, body) = read_packet(&mut reader)?;
+        if kind != FXP_VERSION {
+            return Err(protocol_error(format!(
+                "expected SFTP VERSION, got packet type {}",
+                kind
+            )));
+        }
+        let version = Decoder::new(&body).u32()?;
+        if version < SFTP_VERSION {
+            return Err(protocol_error(format!(
+                "unsupported SFTP version {}",
+                version
+            )));
+        }
+        Ok(Self {
+            reader,
+            writer,
+            next_id: 1,
+            version,
+        })
+    }
+
+    fn request(&mut self, kind: u8, body: &[u8]) -> io::Result<Reply> {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        let mut payload = Vec::with_capacity(body.len() + 4);
+        put_u32(&mut payload, id);
+        payload.extend_from_slice(body);
+        self.writer.write_all(&encode_packet(kind, &payload))?;
+        self.writer.flush()?;
+
+        let (reply_kind, reply) = read_packet(&mut self.reader)?;
+        let mut dec = Decoder::new(&reply);
+        let reply_id = dec.u32()?;
+        if reply_id != id {
+            return Err(protocol_error(format!(
+                "SFTP reply id {} does not match request {}",
+                reply_id, id
+            )));
+        }
+        match reply_kind {
+            FXP_STATUS => {
+                let code = dec.u32()?;
+                // Version 3 servers always send a message, but be lenient.
+                let message = dec.string().unwrap_or_default();
+                Ok(Reply::Status(code, message))
+            }
+            FXP_HANDLE => Ok(Reply::Handle(dec.bytes()?.to_vec())),
+            FXP_DATA => Ok(Reply::Data(dec.bytes()?.to_vec())),
+            FXP_NAME => {
+                // COUNT comes from the server; entries grow as they decode
+                // rather than trusting it for an allocation.
+                let count = dec.u32()?;
+                let mut entries = Vec::new();
+                for _ in 0..count {
+                    let name = dec.string()?;
+                    let longname = dec.string()?;
+                    let attrs = FileAttrs::decode(&mut dec)?;
+                    entries.push(DirEntry {
+                        name,
+                        longname,
+                        attrs,
+                    });
+                }
+                Ok(Reply::Name(entries))
+            }
+            FXP_ATTRS => Ok(Reply::Attrs(FileAttrs::decode(&mut dec)?)),
+            other => Err(protocol_error(format!(
+                "unexpected SFTP packet type {}",
+                other
+            ))),
+        }
+    }
+
+    fn expect_ok(&mut self, kind: u8, body: &[u8]) -> io::Result<()> {
+        match self.request(kind, body)? {
+            Reply::Status(FX_OK, _) => Ok(()),
+            Reply::Status(code, msg) => Err(status_error(code, &msg)),
+            _ => Err(protocol_error("expected SFTP STATUS reply")),
+        }
+    }
+
+    fn expect_handle(&mut self, kind: u8, body: &[u8]) -> io::Result<Vec<u8>> {
+        match self.request(kind, body)? {
+            Reply::Handle(h) => Ok(h),
+            Reply::Status(code, msg) => Err(status_error(code, &msg)),
+            _ => Err(protocol_error("expected SFTP HANDLE reply")),
+        }
+    }
+
+    fn path_request(&mut self, kind: u8, path: &str) -> io::Result<Reply> {
+        let mut body = Vec::new();
+        put_bytes(&mut body, path.as_bytes());
+        self.request(kind, &body)
+    }
+
+    fn stat_impl(&mut self, kind: u8, path: &str) -> io::Result<FileAttrs> {
+        match self.path_request(kind, path)? {
+            Reply::Attrs(attrs) => Ok(attrs),
+            Reply::Status(code, msg) => Err(status_error(code, &msg)),
+            _ => Err(protocol_error("expected SFTP ATTRS reply")),
+        }
+    }
+
+    /// Attributes of PATH, following symlinks.
+    pub fn stat(&mut self, path: &str) -> io::Result<FileAttrs> {
+        self.stat_impl(FXP_STAT, path)
+    }
+
+    /// Attributes of PATH itself (symlinks are not followed).
+    pub fn lstat(&mut self, path: &str) -> io::Result<FileAttrs> {
+        self.stat_impl(FXP_LSTAT, path)
+    }
+
+    /// Canonicalize PATH on the server (resolves `.`/`..` and `~`-relative
+    /// paths to absolute ones).
+    pub fn realpath(&mut self, path: &str) -> io::Result<String> {
+        match self.path_request(FXP_REALPATH, path)? {
+            Reply::Name(mut entries) if !entries.is_empty() => Ok(entries.remove(0).name),
+            Reply::Status(code, msg) => Err(status_error(code, &msg)),
+            _ => Err(protocol_error("expected SFTP NAME reply")),
+        }
+    }
+
+    fn close_handle(&mut self, handle: &[u8]) -> io::Result<()> {
+        let mut body = Vec::new();
+        put_bytes(&mut body, handle);
+        self.expect_ok(FXP_CLOSE, &body)
+    }
+
+    /// Whether the remote user may read PATH, a directory if DIR: the
+    /// server opens and closes it, checking access the way a read would.
+    pub fn can_read(&mut self, path: &str, dir: bool) -> io::Result<bool> {
+        let mut body = Vec::new();
+        put_bytes(&mut body, path.as_bytes());
+        let kind = if dir {
+            FXP_OPENDIR
+        } else {
+            put_u32(&mut body, FXF_READ);
+            FileAttrs::default().encode(&mut body);
+            FXP_OPEN
+        };
+        match self.expect_handle(kind, &body) {
+            Ok(handle) => self.close_handle(&handle).map(|_| true),
+            Err(e) if matches!(e.kind(), ErrorKind::PermissionDenied | ErrorKind::NotFound) => {
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read the whole file at PATH.
+    pub fn read_file(&mut self, path: &str) -> io::Result<Vec<u8>> {
+        let mut body = Vec::new();
+        put_bytes(&mut body, path.as_bytes());
+        put_u32(&mut body, FXF_READ);
+        FileAttrs::default().encode(&mut body);
+        let handle = self.expect_handle(FXP_OPEN, &body)?;
+
+        let mut contents = Vec::new();
+        let result = loop {
+            let mut body = Vec::new();
+            put_bytes(&mut body, &handle);
+            put_u64(&mut body, contents.len() as u64);
+            put_u32(&mut body, MAX_CHUNK as u32);
+            match self.request(FXP_READ, &body) {
+                Ok(Reply::Data(chunk)) if chunk.is_empty() => break Ok(()),
+                Ok(Reply::Data(chunk)) => contents.extend_from_slice(&chunk),
+                Ok(Reply::Status(FX_EOF, _)) => break Ok(()),
+                Ok(Reply::Status(code, msg)) => break Err(status_error(code, &msg)),
+                Ok(_) => break Err(protocol_error("expected SFTP DATA reply")),
+                Err(e) => break Err(e),
+            }
+        };
+        let closed = self.close_handle(&handle);
+        result.and(closed).map(|_| contents)
+    }
+
+    /// Write DATA to PATH, creating it if needed.  APPEND keeps the existing
+    /// contents, otherwise the file is truncated first.
+    pub fn write_file(&mut self, path: &str, data: &[u8], append: bool) -> io::Result<()> {
+        let mut flags = FXF_WRITE | FXF_CREAT;
+        flags |= if append { FXF_APPEND } else { FXF_TRUNC };
+        let mut start = 0u64;
+        if append {
+            // Not every server honours FXF_APPEND, so write at the end
+            // explicitly.
+            start = match self.stat(path) {
+                Ok(attrs) => attrs.size.unwrap_or(0),
+                Err(e) if e.kind() == ErrorKind::NotFound => 0,
+                Err(e) => return Err(e),
+            };
+        }
+        let mut body = Vec::new();
+        put_bytes(&mut body, path.as_bytes());
+        put_u32(&mut body, flags);
+        FileAttrs::default().encode(&mut body);
+        let handle = self.expect_handle(FXP_OPEN, &body)?;
+
+        let mut result = Ok(());
+        for (i, chunk) in data.chunks(MAX_CHUNK).enumerate() {
+            let mut body = Vec::new();
+            put_bytes(&mut body, &handle);
+            put_u64(&mut body, start + (i * MAX_CHUNK) as u64);
+            put_bytes(&mut body, chunk);
+            if let Err(e) = self.expect_ok(FXP_WRITE, &body) {
+                result = Err(e);
+                break;
+            }
+        }
+        let closed = self.close_handle(&handle);
+        result.and(closed)
+    }
+
+    /// List the directory at PATH, excluding `.` and `..`.
+    pub fn read_dir(&mut self, path: &str) -> io::Result<Vec<DirEntry>> {
+        let mut body = Vec::new();
+        put_bytes(&mut body, path.as_bytes());
+        let handle = self.expect_handle(FXP_OPENDIR, &body)?;
+
+        let mut entries = Vec::new();
+        let result = loop {
+            let mut body = Vec::new();
+            put_bytes(&mut body, &handle);
+            match self.request(FXP_READDIR, &body) {
+                Ok(Reply::Name(batch)) => entries.extend(
+                    batch
+                        .into_iter()
+                        .filter(|e| e.name != "." && e.name != ".."),
+                ),
+                Ok(Reply::Status(FX_EOF, _)) => break Ok(()),
+                Ok(Reply::Status(code, msg)) => break Err(status_error(code, &msg)),
+                Ok(_) => break Err(protocol_error("expected SFTP NAME reply")),
+                Err(e) => break Err(e),
+            }
+        };
+        let closed = self.close_handle(&handle);
+        result.and(closed).map(|_| entries)
+    }
+
+    pub fn remove(&mut self, path: &str) -> io::Result<()> {
+        let mut body = Vec::new();
+        put_bytes(&mut body, path.as_bytes());
+        self.expect_ok(FXP_REMOVE, &body)
+    }
+
+    pub fn mkdir(&mut self, path: &str) -> io::Result<()> {
+        let mut body = Vec::new();
+        put_bytes(&mut body, path.as_bytes());
+        FileAttrs::default().encode(&mut body);
+        self.expect_ok(FXP_MKDIR, &body)
+    }
+
+    pub fn rmdir(&mut self, path: &str) -> io::Result<()> {
+        let mut body = Vec::new();
+        put_bytes(&mut body, path.as_bytes());
+        self.expect_ok(FXP_RMDIR, &body)
+    }
+
+    pub fn rename(&mut self, from: &str, to: &str) -> io::Result<()> {
+        let mut body = Vec::new();
+        put_bytes(&mut body, from.as_bytes());
+        put_bytes(&mut body, to.as_bytes());
+        self.expect_ok(FXP_RENAME, &body)
+    }
+}
+
+// ===========================================================================
+// Tests
+// ===========================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Builds the byte stream a server would send, one reply per request.
+    #[derive(Default)]
+    struct ScriptedServer {
+        out: Vec<u8>,
+        next_id: u32,
+    }
+
+    impl ScriptedServer {
+        fn new() -> Self {
+            let mut server = Self {
+                out: Vec::new(),
+                next_id: 1,
+            };
+            let mut payload = Vec::new();
+            put_u32(&mut payload, SFTP_VERSION);
+            server.out.extend(encode_packet(FXP_VERSION, &payload));
+            server
+        }
+
+        fn reply(&mut self, kind: u8, body: &[u8]) -> &mut Self {
+            let mut payload = Vec::new();
+            put_u32(&mut payload, self.next_id);
+            payload.extend_from_slice(body);
+            self.next_id += 1;
+            self.out.extend(encode_packet(kind, &payload));
+            self
+        }
+
+        fn status(&mut self, code: u32) -> &mut Self {
+            let mut body = Vec::new();
+            put_u32(&mut body, code);
+            put_bytes(&mut body, b"");
+            put_bytes(&mut body, b"en");
+            self.reply(FXP_STATUS, &body)
+        }
+
+        fn ok(&mut self) -> &mut Self {
+            self.status(FX_OK)
+        }
+
+        fn eof(&mut self) -> &mut Self {
+            self.status(FX_EOF)
+        }
+
+        fn handle(&mut self, h: &[u8]) -> &mut Self {
+            let mut body = Vec::new();
+            put_bytes(&mut body, h);
+            self.reply(FXP_HANDLE, &body)
+        }
+
+        fn data(&mut self, d: &[u8]) -> &mut Self {
+            let mut body = Vec::new();
+            put_bytes(&mut body, d);
+            self.reply(FXP_DATA, &body)
+        }
+
+        fn attrs(&mut self, attrs: &FileAttrs) -> &mut Self {
+            let mut body = Vec::new();
+            attrs.encode(&mut body);
+            self.reply(FXP_ATTRS, &body)
+        }
+
+        fn names(&mut self, entries: &[(&str, FileAttrs)]) -> &mut Self {
+            let mut body = Vec::new();
+            put_u32(&mut body, entries.len() as u32);
+            for (name, attrs) in entries {
+                put_bytes(&mut body, name.as_bytes());
+                put_bytes(&mut body, name.as_bytes());
+                attrs.encode(&mut body);
+            }
+            self.reply(FXP_NAME, &body)
+        }
+
+        fn client(&self) -> SftpClient<Cursor<Vec<u8>>, Vec<u8>> {
+            SftpClient::handshake(Cursor::new(self.out.clone()), Vec::new()).expect("handshake")
+        }
+    }
+
+    fn file_attrs(size: u64) -> FileAttrs {
+        FileAttrs {
+            size: Some(size),
+            permissions: Some(S_IFREG | 0o644),
+            ..FileAttrs::default()
+        }
+    }
+
+    #[test]
+    fn handshake_sends_init() {
+        let server = ScriptedServer::new();
+        let client = server.client();
+        assert_eq!(client.version, 3);
+        assert_eq!(client.writer, vec![0, 0, 0, 5, FXP_INIT, 0, 0, 0, 3]);
+    }
+
+    #[test]
+    fn handshake_rejects_wrong_packet() {
+        let bytes = encode_packet(FXP_STATUS, &[0, 0, 0, 0]);
+        assert!(SftpClient::handshake(Cursor::new(bytes), Vec::new()).is_err());
+    }
+
+    #[test]
+    fn attrs_roundtrip() {
+        let attrs = FileAttrs {
+            size: Some(1234),
+            uid: Some(1000),
+            gid: Some(100),
+            permissions: Some(S_IFDIR | 0o755),
+            atime: Some(10),
+            mtime: Some(20),
+        };
+        let mut buf = Vec::new();
+        attrs.encode(&mut buf);
+        let decoded = FileAttrs::decode(&mut Decoder::new(&buf)).unwrap();
+        assert_eq!(decoded, attrs);
+        assert!(decoded.is_dir());
+        assert!(!decoded.is_regular());
+    }
+
+    #[test]
+    fn stat_reports_missing_file() {
+        let mut server = ScriptedServer::new();
+        server.attrs(&file_attrs(7)).status(FX_NO_SUCH_FILE);
+        let mut client = server.client();
+        assert_eq!(client.stat("/etc/hosts").unwrap().size, Some(7));
+        let err = client.stat("/nope").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn read_file_collects_chunks_until_eof() {
+        let mut server = ScriptedServer::new();
+        server
+            .handle(b"h1")
+            .data(b"hello ")
+            .data(b"world")
+            .eof()
+            .ok();
+        let mut client = server.client();
+        assert_eq!(client.read_file("/tmp/x").unwrap(), b"hello world");
+    }
+
+    #[test]
+    fn can_read_opens_and_closes() {
+        let mut server = ScriptedServer::new();
+        server
+            .handle(b"h")
+            .ok()
+            .status(FX_PERMISSION_DENIED)
+            .handle(b"d")
+            .ok();
+        let mut client = server.client();
+        assert!(client.can_read("/etc/hosts", false).unwrap());
+        assert!(!client.can_read("/etc/shadow", false).unwrap());
+        assert!(client.can_read("/etc", true).unwrap());
+        let mut reader = Cursor::new(client.writer.clone());
+        let kinds: Vec<u8> = std::iter::from_fn(|| read_packet(&mut reader).ok())
+            .map(|(kind, _)| kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![FXP_INIT, FXP_OPEN, FXP_CLOSE, FXP_OPEN, FXP_OPENDIR, FXP_CLOSE]
+        );
+    }
+
+    #[test]
+    fn write_file_truncates_and_closes() {
+        let mut server = ScriptedServer::new();
+        server.handle(b"h").ok().ok();
+        let mut client = server.client();
+        client.write_file("/tmp/out", b"data", false).unwrap();
+        // INIT, OPEN, WRITE, CLOSE were all sent.
+        let mut reader = Cursor::new(client.writer.clone());
+        let kinds: Vec<u8> = std::iter::from_fn(|| read_packet(&mut reader).ok())
+            .map(|(kind, _)| kind)
+            .collect();
+        assert_eq!(kinds, vec![FXP_INIT, FXP_OPEN, FXP_WRITE, FXP_CLOSE]);
+    }
+
+    #[test]
+    fn read_dir_skips_dot_entries() {
+        let mut server = ScriptedServer::new();
+        server
+            .handle(b"d")
+            .names(&[
+                (".", FileAttrs::default()),
+                ("..", FileAttrs::default()),
+                ("a.txt", file_attrs(1)),
+            ])
+            .names(&[("b.txt", file_attrs(2))])
+            .eof()
+            .ok();
+        let mut client = server.client();
+        let names: Vec<String> = client
+            .read_dir("/tmp")
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, vec!["a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn realpath_returns_first_name() {
+        let mut server = ScriptedServer::new();
+        server.names(&[("/home/user", FileAttrs::default())]);
+        let mut client = server.client();
+        assert_eq!(client.realpath(".").unwrap(), "/home/user");
+    }
+
+    #[test]
+    fn eof_status_is_not_a_truncated_stream() {
+        let mut server = ScriptedServer::new();
+        server.eof();
+        let mut client = server.client();
+        let err = client.realpath("/x").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Other);
+        // Running out of bytes mid-reply is
+        let err = client.remove("/x").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn name_count_beyond_packet_is_an_error() {
+        let mut server = ScriptedServer::new();
+        let mut body = Vec::new();
+        put_u32(&mut body, u32::MAX);
+        server.reply(FXP_NAME, &body);
+        let mut client = server.client();
+        let err = client.realpath(".").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn mismatched_reply_id_is_an_error() {
+        let mut server = ScriptedServer::new();
+        server.next_id = 99;
+        server.ok();
+        let mut client = server.client();
+        assert!(client.remove("/tmp/x").is_err());
+    }
+}
@@ -1728,6 +1728,7 @@ enum lface_attribute_index
   LFACE_FONTSET_INDEX,
   LFACE_DISTANT_FOREGROUND_INDEX,
   LFACE_EXTEND_INDEX,
+  LFACE_TEXT_SHADOW_INDEX,
   LFACE_VECTOR_SIZE
 };
 
@@ -1835,6 +1836,14 @@ struct face
      corners; positive values give rounded corners.  */
   int box_corner_radius;
 
+  /* Shadow drawn under the text when text_shadow_p: its color, offset
+     from the glyphs in pixels, and blur radius in pixels.  A shadow
+     with no offset and a blur radius is a glow around the text.  */
+  unsigned long text_shadow_color;
+  int text_shadow_x_offset;
+  int text_shadow_y_offset;
+  int text_shadow_blur;
+
   /* The amount of pixels above the descent line the underline should
      be displayed.  It does not take effect unless
      `underline_at_descent_line_p` is t.  */
@@ -1859,6 +1868,9 @@ struct face
   bool_bf overline_p : 1;
   bool_bf strike_through_p : 1;
 
+  /* True if text in this face casts a shadow or glow.  */
+  bool_bf text_shadow_p : 1;
+
   /* True means that the colors specified for this face could not be
      loaded, and were replaced by default colors, so they shouldn't be
      freed.  */
@@ -1872,6 +1884,7 @@ struct face
   bool_bf overline_color_defaulted_p : 1;
   bool_bf strike_through_color_defaulted_p : 1;
   bool_bf box_color_defaulted_p : 1;
+  bool_bf text_shadow_color_defaulted_p : 1;
 
   /* True means the underline should be drawn at the descent line.  */
   bool_bf underline_at_descent_line_p : 1;
@@ -52,6 +52,7 @@ along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.  */
 #ifdef HAVE_WINDOW_SYSTEM
 #include TERM_HEADER
 #endif /* HAVE_WINDOW_SYSTEM */
+#include "neomacs_display.h"
 
 #include <errno.h>
 
@@ -6269,6 +6270,41 @@ buffer_posn_from_coords (struct window *w, int *x, int *y, struct display_pos *p
   int x0, x1, to_x, it_vpos;
   void *itdata = NULL;
 
+  /* On Neomacs frames, what is under (*X, *Y) is what the Rust layout
+     engine drew there, unless it has not laid out W yet.  */
+  if (FRAME_NEOMACS_P (WINDOW_XFRAME (w)))
+    {
+      int64_t window_id = (int64_t) (intptr_t) w;
+      int fx = *x + window_box_left (w, TEXT_AREA);
+      int fy = *y + WINDOW_TOP_EDGE_Y (w);
+      int64_t charpos = neomacs_layout_window_charpos (window_id, fx, fy);
+      if (charpos >= 0)
+	{
+	  struct buffer *b = XBUFFER (w->contents);
+	  NeomacsCharPixel px;
+	  bool drawn = neomacs_layout_pos_to_pixel (window_id, charpos, &px) > 0;
+
+	  charpos = clip_to_bounds (BUF_BEGV (b), charpos, BUF_ZV (b));
+	  SET_TEXT_POS (pos->pos, charpos, buf_charpos_to_bytepos (b, charpos));
+	  pos->overlay_string_index = -1;
+	  SET_TEXT_POS (pos->string_pos, -1, -1);
+	  pos->dpvec_index = -1;
+	  if (!drawn)
+	    {
+	      *x = *y = *dx = *dy = *width = *height = 0;
+	      return w->contents;
+	    }
+	  *dx = fx - (int) px.x;
+	  *dy = fy - (int) px.y;
+	  *width = (int) px.width;
+	  *height = (int) px.height;
+	  /* Add extra (default width) columns if clicked after EOL.  */
+	  *x = px.col + max (0, *dx - *width) / WINDOW_FRAME_COLUMN_WIDTH (w);
+	  *y = px.row;
+	  return w->contents;
+	}
+    }
+
   /* We used to set current_buffer directly here, but that does the
      wrong thing with `face-remapping-alist' (bug#2044).  */
   Fset_buffer (w->contents);
@@ -7132,9 +7132,14 @@ make_monitor_attribute_list (struct MonitorInfo *monitors,
 
       attributes = Fcons (Fcons (Qframes, AREF (monitor_frames, i)),
 			  attributes);
-#ifdef HAVE_PGTK
+#if defined HAVE_PGTK || defined HAVE_NEOMACS
       attributes = Fcons (Fcons (Qscale_factor, make_float (mi->scale_factor)),
 			  attributes);
+#endif
+#ifdef HAVE_NEOMACS
+      if (mi->refresh_rate > 0)
+	attributes = Fcons (Fcons (Qrefresh_rate, make_float (mi->refresh_rate)),
+			    attributes);
 #endif
       attributes = Fcons (Fcons (Qmm_size,
                                  list2i (mi->mm_width, mi->mm_height)),
@@ -7252,8 +7257,11 @@ syms_of_frame (void)
 
   DEFSYM (Qworkarea, "workarea");
   DEFSYM (Qmm_size, "mm-size");
-#ifdef HAVE_PGTK
+#if defined HAVE_PGTK || defined HAVE_NEOMACS
   DEFSYM (Qscale_factor, "scale-factor");
+#endif
+#ifdef HAVE_NEOMACS
+  DEFSYM (Qrefresh_rate, "refresh-rate");
 #endif
   DEFSYM (Qframes, "frames");
   DEFSYM (Qsource, "source");
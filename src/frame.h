@@ -1932,9 +1932,13 @@ struct MonitorInfo {
   Emacs_Rectangle geom, work;
   int mm_width, mm_height;
   char *name;
-#ifdef HAVE_PGTK
+#if defined HAVE_PGTK || defined HAVE_NEOMACS
   double scale_factor;
 #endif
+#ifdef HAVE_NEOMACS
+  /* Refresh rate in Hz, 0 if unknown.  */
+  double refresh_rate;
+#endif
 };
 
 extern void free_monitors (struct MonitorInfo *monitors, int n_monitors);
@@ -26,6 +26,7 @@ along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.  */
 #include "window.h"
 #include "dispextern.h"
 #include "buffer.h"
+#include "coding.h"
 #include "blockinput.h"
 #include "termhooks.h"
 #include "pdumper.h"
@@ -1791,6 +1792,226 @@ neomacs_layout_get_fringe_bitmap (int bitmap_id,
   return copy_h;
 }
 
+/* FFI struct for an overlay arrow shown in a window.
+   Matches Rust OverlayArrowFFI.  */
+struct OverlayArrowFFI {
+  int64_t charpos;   /* Position the arrow points at */
+  int bitmap;        /* Left fringe bitmap ID, 0 = draw TEXT instead */
+  int text_len;      /* Bytes of UTF-8 arrow string in TEXT */
+  char text[32];     /* Arrow string, for windows without a left fringe */
+};
+
+/* Collect the overlay arrows (`overlay-arrow-variable-list') pointing
+   into BUFFER_PTR, shown in WINDOW_PTR, into ARROWS_OUT (an array of
+   MAX_ARROWS OverlayArrowFFI).  As in Emacs, an arrow is drawn as its
+   `overlay-arrow-bitmap' (default: the `overlay-arrow' fringe
+   indicator) when the window has a left fringe, and as its
+   `overlay-arrow-string' over the start of the line otherwise.
+   Returns the number of arrows written.  */
+int
+neomacs_layout_overlay_arrows (void *window_ptr, void *buffer_ptr,
+                               void *arrows_out, int max_arrows)
+{
+  struct window *w = (struct window *) window_ptr;
+  struct buffer *buf = (struct buffer *) buffer_ptr;
+  struct OverlayArrowFFI *arrows = (struct OverlayArrowFFI *) arrows_out;
+  if (!w || !buf || !arrows || max_arrows <= 0)
+    return 0;
+
+  int n = 0;
+  for (Lisp_Object vlist = Voverlay_arrow_variable_list;
+       CONSP (vlist) && n < max_arrows;
+       vlist = XCDR (vlist))
+    {
+      Lisp_Object var = XCAR (vlist);
+      if (!SYMBOLP (var))
+        continue;
+      Lisp_Object val = find_symbol_value (var);
+      if (!MARKERP (val) || XMARKER (val)->buffer != buf)
+        continue;
+
+      struct OverlayArrowFFI *a = &arrows[n++];
+      a->charpos = marker_position (val);
+      a->bitmap = 0;
+      a->text_len = 0;
+      if (WINDOW_LEFT_FRINGE_WIDTH (w) > 0)
+        {
+          Lisp_Object bm = Fget (var, Qoverlay_arrow_bitmap);
+          if (SYMBOLP (bm) && !NILP (bm))
+            a->bitmap = lookup_fringe_bitmap (bm);
+          if (a->bitmap <= 0)
+            a->bitmap = get_logical_fringe_bitmap (w, Qoverlay_arrow, 0, 0);
+        }
+      if (a->bitmap <= 0)
+        {
+          a->bitmap = 0;
+          Lisp_Object str = Fget (var, Qoverlay_arrow_string);
+          if (!STRINGP (str))
+            str = Voverlay_arrow_string;
+          if (STRINGP (str))
+            {
+              Lisp_Object encoded = ENCODE_UTF_8 (str);
+              ptrdiff_t len = min (SBYTES (encoded), sizeof a->text);
+              /* Don't cut a multibyte character in half.  */
+              while (len > 0 && len < SBYTES (encoded)
+                     && (SREF (encoded, len) & 0xC0) == 0x80)
+                len--;
+              memcpy (a->text, SDATA (encoded), len);
+              a->text_len = len;
+            }
+        }
+    }
+  return n;
+}
+
+/* FFI struct for the fringe indicators of a window.
+   Matches Rust FringeIndicatorsFFI.  Bitmap IDs come from the
+   window's `fringe-indicator-alist', 0 meaning none.  */
+struct FringeIndicatorsFFI {
+  int continuation_left;   /* Row continued from the one above */
+  int continuation_right;  /* Row continued on the one below */
+  int truncation_left;     /* Text hidden by horizontal scrolling */
+  int truncation_right;    /* Text cut off at the right */
+  int empty_line;          /* Lines after the end of the buffer */
+  int empty_line_side;     /* 0 = none, 1 = left, 2 = right */
+  /* `indicate-buffer-boundaries': each bitmap goes in the fringe its
+     side says (0 = none, 1 = left, 2 = right).  */
+  int top, top_side;       /* First row shows the buffer's start */
+  int bottom, bottom_side; /* Last row shows the buffer's end */
+  int top_bottom;          /* Both on one row, when on the same side */
+  int up, up_side;         /* First row, with text above it */
+  int down, down_side;     /* Last row, with text below it */
+  uint32_t fg;             /* `fringe' face colors, 0xRRGGBB */
+  uint32_t bg;
+};
+
+/* The colors of face FACE_ID on frame F as 0xRRGGBB, in *FG and *BG.  */
+static void
+neomacs_fringe_face_colors (struct frame *f, int face_id,
+                            uint32_t *fg, uint32_t *bg)
+{
+  struct face *face = FACE_FROM_ID_OR_NULL (f, face_id);
+  unsigned long fore = FRAME_FOREGROUND_PIXEL (f);
+  unsigned long back = FRAME_BACKGROUND_PIXEL (f);
+  if (face)
+    {
+      if (!face->foreground_defaulted_p)
+        fore = face->foreground;
+      if (!face->background_defaulted_p)
+        back = face->background;
+    }
+  *fg = fore & 0xffffff;
+  *bg = back & 0xffffff;
+}
+
+/* The side, 1 for left or 2 for right, an `indicate-buffer-boundaries'
+   entry POS asks for, or 0.  */
+static int
+neomacs_fringe_side (Lisp_Object pos)
+{
+  return EQ (pos, Qleft) ? 1 : EQ (pos, Qright) ? 2 : 0;
+}
+
+/* Fill OUT with the fringe indicators of WINDOW_PTR, resolved as
+   update_window_fringes does.  Returns 1, or 0 for a window without a
+   buffer.  */
+int
+neomacs_layout_fringe_indicators (void *window_ptr, void *out_ptr)
+{
+  struct window *w = (struct window *) window_ptr;
+  struct FringeIndicatorsFFI *out = (struct FringeIndicatorsFFI *) out_ptr;
+  if (!w || !out || !BUFFERP (w->contents))
+    return 0;
+  struct buffer *b = XBUFFER (w->contents);
+  memset (out, 0, sizeof *out);
+
+  out->continuation_left = get_logical_fringe_bitmap (w, Qcontinuation, 0, 0);
+  out->continuation_right = get_logical_fringe_bitmap (w, Qcontinuation, 1, 0);
+  out->truncation_left = get_logical_fringe_bitmap (w, Qtruncation, 0, 0);
+  out->truncation_right = get_logical_fringe_bitmap (w, Qtruncation, 1, 0);
+
+  Lisp_Object empty_pos = BVAR (b, indicate_empty_lines);
+  if (!NILP (empty_pos))
+    {
+      out->empty_line_side
+        = (EQ (empty_pos, Qright) || WINDOW_LEFT_FRINGE_WIDTH (w) == 0
+           ? 2 : 1);
+      out->empty_line = get_logical_fringe_bitmap (w, Qempty_line,
+                                                   out->empty_line_side == 2,
+                                                   0);
+    }
+
+  Lisp_Object ind = BVAR (b, indicate_buffer_boundaries);
+  Lisp_Object boundary_top = Qnil, boundary_bot = Qnil;
+  Lisp_Object arrow_top = Qnil, arrow_bot = Qnil;
+  if (MINI_WINDOW_P (w) || NILP (ind))
+    ;
+  else if (EQ (ind, Qleft) || EQ (ind, Qright))
+    boundary_top = boundary_bot = arrow_top = arrow_bot = ind;
+  else if (CONSP (ind) && CONSP (XCAR (ind)))
+    {
+      Lisp_Object pos;
+      if (pos = Fassq (Qt, ind), !NILP (pos))
+        boundary_top = boundary_bot = arrow_top = arrow_bot = XCDR (pos);
+      if (pos = Fassq (Qtop, ind), !NILP (pos))
+        boundary_top = XCDR (pos);
+      if (pos = Fassq (Qbottom, ind), !NILP (pos))
+        boundary_bot = XCDR (pos);
+      if (pos = Fassq (Qup, ind), !NILP (pos))
+        arrow_top = XCDR (pos);
+      if (pos = Fassq (Qdown, ind), !NILP (pos))
+        arrow_bot = XCDR (pos);
+    }
+  else
+    /* Anything else means boundary on left and no arrows.  */
+    boundary_top = boundary_bot = Qleft;
+
+  out->top_side = neomacs_fringe_side (boundary_top);
+  out->bottom_side = neomacs_fringe_side (boundary_bot);
+  out->up_side = neomacs_fringe_side (arrow_top);
+  out->down_side = neomacs_fringe_side (arrow_bot);
+  if (out->top_side)
+    out->top = get_logical_fringe_bitmap (w, Qtop, out->top_side == 2, 0);
+  if (out->bottom_side)
+    out->bottom = get_logical_fringe_bitmap (w, Qbottom,
+                                             out->bottom_side == 2, 0);
+  if (out->top_side && out->top_side == out->bottom_side)
+    out->top_bottom = get_logical_fringe_bitmap (w, Qtop_bottom,
+                                                 out->top_side == 2, 0);
+  if (out->up_side)
+    out->up = get_logical_fringe_bitmap (w, Qup, out->up_side == 2, 0);
+  if (out->down_side)
+    out->down = get_logical_fringe_bitmap (w, Qdown, out->down_side == 2, 0);
+
+  struct frame *f = XFRAME (w->frame);
+  int face_id = lookup_named_face (w, f, Qfringe, false);
+  neomacs_fringe_face_colors (f, face_id < 0 ? FRINGE_FACE_ID : face_id,
+                              &out->fg, &out->bg);
+  return 1;
+}
+
+/* The foreground of fringe bitmap BITMAP_ID in WINDOW_PTR, when
+   `set-fringe-bitmap-face' gave it a face of its own, in *FG_OUT as
+   0xRRGGBB.  Returns 1 if it has one, 0 if it uses the `fringe'
+   face.  */
+int
+neomacs_layout_fringe_bitmap_fg (void *window_ptr, int bitmap_id,
+                                 uint32_t *fg_out)
+{
+  struct window *w = (struct window *) window_ptr;
+  if (!w || !fg_out || bitmap_id <= 0 || bitmap_id >= max_used_fringe_bitmap
+      || NILP (fringe_faces[bitmap_id]))
+    return 0;
+  struct frame *f = XFRAME (w->frame);
+  int face_id = lookup_derived_face (w, f, fringe_faces[bitmap_id],
+                                     FRINGE_FACE_ID, 0);
+  if (face_id < 0)
+    return 0;
+  uint32_t bg;
+  neomacs_fringe_face_colors (f, face_id, fg_out, &bg);
+  return 1;
+}
+
 /***********************************************************************
 			    Initialization
  ***********************************************************************/
@@ -40,6 +40,20 @@
 #define NEOMACS_EVENT_MENU_SELECTION 13
 #define NEOMACS_EVENT_FILE_DROP 14
 #define NEOMACS_EVENT_TERMINAL_TITLE_CHANGED 15
+#define NEOMACS_EVENT_MONITORS_CHANGED 16
+#define NEOMACS_EVENT_CHAR_PICKER_SELECTION 17
+#define NEOMACS_EVENT_TERMINAL_BELL 18
+#define NEOMACS_EVENT_PINCH_ZOOM 19
+#define NEOMACS_EVENT_COMMAND_PALETTE_SELECTION 20
+#define NEOMACS_EVENT_COLOR_PICKER_SELECTION 21
+#define NEOMACS_EVENT_AGENDA_TIMELINE_SELECTION 22
+#define NEOMACS_EVENT_GRAPH_VIEW_SELECTION 23
+#define NEOMACS_EVENT_DIR_VIEW_SELECTION 24
+#define NEOMACS_EVENT_PROCESS_MONITOR_SELECTION 25
+#define NEOMACS_EVENT_DATE_PICKER_SELECTION 26
+
+/* Color picker result asking to pick a color from the screen.  */
+#define NEOMACS_COLOR_PICKER_EYEDROPPER (-3)
 
 #define DRM_FORMAT_ARGB8888 875713089
 
@@ -164,6 +178,8 @@ typedef struct NeomacsInputEvent {
   uint32_t height;
   /** Target frame pointer for child frame mouse event routing (0 = parent frame) */
   uint64_t targetFrameId;
+  /** Lines a scroll event moves (0 = unspecified) */
+  int32_t scrollLines;
 } NeomacsInputEvent;
 
 #define VA_STATUS_SUCCESS 0
@@ -457,6 +473,123 @@ int neomacs_display_query_image_file_size(struct NeomacsDisplay *handle,
  */
 int neomacs_display_free_image(struct NeomacsDisplay *handle, uint32_t imageId);
 
+/**
+ * An annotation drawn over an edited image.  Coordinates are fractions
+ * of the edited image.
+ */
+struct CImageAnnotation
+{
+  int kind;			/* 0 = arrow, 1 = rectangle, 2 = text */
+  float x0, y0;			/* arrow tail, corner, or text top left */
+  float x1, y1;			/* arrow head or opposite corner */
+  uint32_t color;		/* 0xRRGGBB */
+  float size;			/* line width or text size, as a fraction
+				   of the shorter side */
+  const char *text;		/* text annotations, else NULL */
+};
+
+/**
+ * Non-destructive edits of an image: applied in order rotate, flip,
+ * crop, annotate.
+ */
+struct CImageEdits
+{
+  int rotation;			/* quarter turns clockwise */
+  int flip_h, flip_v;
+  float crop_x, crop_y;		/* fractions of the rotated image; */
+  float crop_w, crop_h;		/* no crop unless both are positive */
+  const struct CImageAnnotation *annotations;
+  int annotation_count;
+};
+
+/**
+ * Set the edits of a file image (NULL undoes them) and decode it again.
+ * The new size arrives as an IMAGE_DIMENSIONS_READY event.
+ */
+int neomacs_display_set_image_edits(struct NeomacsDisplay *handle,
+                                    uint32_t imageId,
+                                    const struct CImageEdits *edits);
+
+/**
+ * Store the size of a WIDTH x HEIGHT image after EDITS.
+ */
+void neomacs_display_edited_image_size(const struct CImageEdits *edits,
+                                       int width,
+                                       int height,
+                                       int *out_width,
+                                       int *out_height);
+
+/**
+ * Apply EDITS to the image file SOURCE at full size and save the result
+ * as a PNG file at OUTPUT.  Returns 0 and the saved size, or -1 with
+ * the reason in ERR_BUF.
+ */
+int neomacs_display_export_edited_image(const char *source,
+                                        const struct CImageEdits *edits,
+                                        const char *output,
+                                        int *width,
+                                        int *height,
+                                        char *err_buf,
+                                        size_t err_len);
+
+/**
+ * A data series of a chart.
+ */
+struct CChartSeries
+{
+  const char *name;		/* legend text, or NULL */
+  const double *values;		/* NaN for missing values */
+  int count;
+  uint32_t color;		/* 0xRRGGBB */
+};
+
+/**
+ * A chart drawn by neomacs_display_render_chart.
+ */
+struct CChartSpec
+{
+  int kind;			/* 0 = line, 1 = bar, 2 = sparkline */
+  int width, height;
+  const struct CChartSeries *series;
+  int series_count;
+  const char *const *labels;	/* labels of the x positions */
+  int label_count;
+  const char *title;		/* or NULL */
+  uint32_t fg;			/* text and axes, 0xRRGGBB */
+  uint32_t bg;			/* used if OPAQUE is nonzero */
+  int opaque;
+  double min, max;		/* value range, NaN for the data's */
+  float font_size;		/* pixel size of the labels */
+};
+
+/**
+ * Area of a chart image showing x position INDEX.
+ */
+struct CChartHotSpot
+{
+  int index;
+  int x0, y0, x1, y1;
+};
+
+/**
+ * Draw the chart SPEC as a PNG image.  Returns 0 with the PNG data and
+ * the area of each x position, or -1.  Free the results with
+ * neomacs_display_free_chart.
+ */
+int neomacs_display_render_chart(const struct CChartSpec *spec,
+                                 uint8_t **png,
+                                 size_t *png_len,
+                                 struct CChartHotSpot **hot_spots,
+                                 int *hot_spot_count);
+
+/**
+ * Free the results of neomacs_display_render_chart.
+ */
+void neomacs_display_free_chart(uint8_t *png,
+                                size_t png_len,
+                                struct CChartHotSpot *hot_spots,
+                                int hot_spot_count);
+
 /**
  * Set a floating video at a specific screen position
  */
@@ -487,6 +620,18 @@ void neomacs_display_set_floating_image(struct NeomacsDisplay *handle,
  */
 void neomacs_display_clear_floating_image(struct NeomacsDisplay *handle, uint32_t imageId);
 
+/**
+ * Set the background image of a window (windowId non-zero) or buffer,
+ * drawn under the text.  imageId 0 removes it.
+ */
+void neomacs_display_set_window_background(struct NeomacsDisplay *handle,
+                                           int64_t windowId,
+                                           uint64_t bufferId,
+                                           uint32_t imageId,
+                                           float opacity,
+                                           int scaling,
+                                           int anchor);
+
 /**
  * Clear a rectangular area of the display
  */
@@ -557,6 +702,25 @@ void neomacs_display_smooth_scroll(struct NeomacsDisplay *handle,
 void neomacs_display_set_mouse_cursor(struct NeomacsDisplay *handle,
                                        int cursor_type);
 
+/**
+ * Hide the mouse pointer on keyboard input, until the mouse moves, if
+ * ENABLED is nonzero.
+ */
+void neomacs_display_set_pointer_auto_hide(struct NeomacsDisplay *handle,
+                                           int enabled);
+
+/**
+ * Set the scroll settings: lines per wheel notch, touchpad speed factor,
+ * reversed directions, Shift for horizontal and Alt for page scrolling.
+ */
+void neomacs_display_set_wheel_config(struct NeomacsDisplay *handle,
+                                      float wheel_speed,
+                                      int wheel_reverse,
+                                      float touchpad_speed,
+                                      int touchpad_reverse,
+                                      int shift_horizontal,
+                                      int alt_page);
+
 /**
  * Warp (move) the mouse pointer to pixel position (x, y).
  */
@@ -593,6 +757,237 @@ void neomacs_display_show_popup_menu(struct NeomacsDisplay *handle,
  */
 void neomacs_display_hide_popup_menu(struct NeomacsDisplay *handle);
 
+/**
+ * Character picker entry structure for FFI.
+ */
+struct CCharPickerEntry
+{
+  const char *text;
+  const char *name;
+  const char *shortcodes;	/* space-separated, or NULL */
+};
+
+/**
+ * Show the character picker with the given entries.
+ * The render thread renders the picker and sends a CharPickerSelection
+ * event with the index of the picked entry (-1 = cancelled).
+ */
+void neomacs_display_show_char_picker(struct NeomacsDisplay *handle,
+                                      const struct CCharPickerEntry *entries,
+                                      int entry_count,
+                                      const char *title,
+                                      uint32_t fg_color,
+                                      uint32_t bg_color);
+
+/**
+ * Hide the character picker.
+ */
+void neomacs_display_hide_char_picker(struct NeomacsDisplay *handle);
+
+/**
+ * A command offered by the command palette.
+ */
+struct CCommandPaletteEntry
+{
+  const char *name;
+  const char *binding;		/* or NULL */
+  const char *doc;		/* or NULL */
+  int weight;			/* ranking bonus from recent use */
+};
+
+/**
+ * Show the command palette with the given entries.
+ * The render thread renders the palette and sends a
+ * CommandPaletteSelection event with the index of the chosen entry
+ * (-1 = cancelled).
+ */
+void neomacs_display_show_command_palette(struct NeomacsDisplay *handle,
+                                          const struct CCommandPaletteEntry *entries,
+                                          int entry_count,
+                                          const char *title,
+                                          uint32_t fg_color,
+                                          uint32_t bg_color,
+                                          uint32_t match_color);
+
+/**
+ * Hide the command palette.
+ */
+void neomacs_display_hide_command_palette(struct NeomacsDisplay *handle);
+
+/**
+ * Show the color picker with COLOR selected and ORIGINAL as the color
+ * to go back to (both 0xRRGGBB).  The render thread renders the picker
+ * and sends a ColorPickerSelection event with the chosen color (-1 =
+ * cancelled, NEOMACS_COLOR_PICKER_EYEDROPPER = pick from the screen).
+ */
+void neomacs_display_show_color_picker(struct NeomacsDisplay *handle,
+                                       uint32_t color,
+                                       uint32_t original,
+                                       const char *title,
+                                       uint32_t fg_color,
+                                       uint32_t bg_color);
+
+/**
+ * Hide the color picker.
+ */
+void neomacs_display_hide_color_picker(struct NeomacsDisplay *handle);
+
+/**
+ * A task shown by the agenda timeline.
+ */
+struct CAgendaTimelineItem
+{
+  const char *label;
+  const char *category;		/* or NULL */
+  double start;			/* seconds since the epoch */
+  double end;			/* == start for a milestone */
+  uint32_t color;		/* 0xRRGGBB, 0 = default */
+  int done;
+};
+
+/**
+ * Show the agenda timeline with the given items.  NOW is the current
+ * time in seconds since the epoch and UTC_OFFSET the local time zone's
+ * offset from UTC in seconds.  The render thread renders the timeline
+ * and sends an AgendaTimelineSelection event with the index of the
+ * chosen item (-1 = closed).
+ */
+void neomacs_display_show_agenda_timeline(struct NeomacsDisplay *handle,
+                                          const struct CAgendaTimelineItem *items,
+                                          int item_count,
+                                          double now,
+                                          int utc_offset,
+                                          const char *title,
+                                          uint32_t fg_color,
+                                          uint32_t bg_color);
+
+/**
+ * Hide the agenda timeline.
+ */
+void neomacs_display_hide_agenda_timeline(struct NeomacsDisplay *handle);
+
+/**
+ * A node shown by the graph view.
+ */
+struct CGraphNode
+{
+  const char *label;
+  uint32_t color;		/* 0xRRGGBB, 0 = default */
+};
+
+/**
+ * An edge shown by the graph view, as indices into its nodes.
+ */
+struct CGraphEdge
+{
+  int from;
+  int to;
+};
+
+/**
+ * Show the graph view with the given nodes and edges.  LAYERED nonzero
+ * places the nodes in layers along the edges, parents above children,
+ * instead of by a force-directed layout.  CURRENT is the node selected
+ * at first, or -1.  The render thread renders the graph and sends a
+ * GraphViewSelection event with the index of the chosen node (-1 =
+ * closed).
+ */
+void neomacs_display_show_graph_view(struct NeomacsDisplay *handle,
+                                     const struct CGraphNode *nodes,
+                                     int node_count,
+                                     const struct CGraphEdge *edges,
+                                     int edge_count,
+                                     int layered,
+                                     int current,
+                                     const char *title,
+                                     uint32_t fg_color,
+                                     uint32_t bg_color);
+
+/**
+ * Hide the graph view.
+ */
+void neomacs_display_hide_graph_view(struct NeomacsDisplay *handle);
+
+/**
+ * The icon of directory view entries whose names end in SUFFIX.
+ */
+struct CDirViewIcon
+{
+  const char *suffix;
+  const char *name;		/* A registered icon */
+};
+
+/**
+ * Show the directory view listing DIR, with hidden files if SHOW_HIDDEN
+ * is nonzero.  ICONS name the icons of files by suffix and UTC_OFFSET
+ * is the local time zone's offset from UTC in seconds.  The render
+ * thread reads the directory in the background and sends a
+ * DirViewSelection event when a file is chosen (x = 1; fetch it with
+ * neomacs_display_take_dir_view_choice()) or the view is closed
+ * (x = -1).
+ */
+void neomacs_display_show_dir_view(struct NeomacsDisplay *handle,
+                                   const char *dir,
+                                   int show_hidden,
+                                   const struct CDirViewIcon *icons,
+                                   int icon_count,
+                                   int utc_offset,
+                                   uint32_t fg_color,
+                                   uint32_t bg_color);
+
+/**
+ * Hide the directory view.
+ */
+void neomacs_display_hide_dir_view(struct NeomacsDisplay *handle);
+
+/**
+ * Show the process monitor, sampling processes every INTERVAL seconds.
+ * The render thread sends a ProcessMonitorSelection event with the pid
+ * of the process chosen in x, or -1 when the monitor is closed without
+ * choosing one.
+ */
+void neomacs_display_show_process_monitor(struct NeomacsDisplay *handle,
+                                          float interval,
+                                          uint32_t fg_color,
+                                          uint32_t bg_color);
+
+/**
+ * Hide the process monitor.
+ */
+void neomacs_display_hide_process_monitor(struct NeomacsDisplay *handle);
+
+/**
+ * Date picker colors, each 0xRRGGBB, 0 for the default.
+ */
+struct CDatePickerColors
+{
+  uint32_t fg, bg;			/* Panel */
+  uint32_t today;			/* Ring around today */
+  uint32_t selected_fg, selected_bg;	/* Selected day */
+  uint32_t highlight;			/* Highlighted days */
+};
+
+/**
+ * Show the date picker with SELECTED chosen, TODAY ringed and the
+ * HIGHLIGHT_COUNT dates at HIGHLIGHTED highlighted, all as YYYYMMDD.
+ * MONTHS months (1-3) are shown side by side, weeks starting on
+ * WEEK_START (0 = Sunday).  The render thread sends a
+ * DatePickerSelection event with the chosen date as YYYYMMDD in x, or
+ * -1 when the picker is cancelled.
+ */
+void neomacs_display_show_date_picker(struct NeomacsDisplay *handle,
+                                      int selected, int today,
+                                      const int *highlighted,
+                                      int highlight_count,
+                                      int months, int week_start,
+                                      const char *title,
+                                      const struct CDatePickerColors *colors);
+
+/**
+ * Hide the date picker.
+ */
+void neomacs_display_hide_date_picker(struct NeomacsDisplay *handle);
+
 /**
  * Show a tooltip at position (x, y) with the given text and colors.
  * Colors are in sRGB float format (0.0-1.0).
@@ -609,10 +1004,11 @@ void neomacs_display_show_tooltip(struct NeomacsDisplay *handle,
 void neomacs_display_hide_tooltip(struct NeomacsDisplay *handle);
 
 /**
- * Set the window title (threaded mode - sends to render thread)
+ * Set the window title (threaded mode - sends to render thread).
+ * Returns 1 if queued, 0 if the command channel was full.
  */
-void neomacs_display_set_title(struct NeomacsDisplay *handle,
-                                const char *title);
+int neomacs_display_set_title(struct NeomacsDisplay *handle,
+                              const char *title);
 
 /**
  * Set fullscreen mode (threaded mode)
@@ -981,20 +1377,67 @@ int neomacs_display_start_buffer_transition(struct NeomacsDisplay *handle,
                                             int durationMs);
 
 /**
- * Prepare for buffer transition (stub)
+ * Set the effect and duration of snapshot-based buffer transitions, and
+ * whether they start whenever a window's buffer changes
+ */
+void neomacs_display_set_buffer_transition(struct NeomacsDisplay *handle,
+                                           const char *effect,
+                                           int durationMs,
+                                           int autoDetect);
+
+/**
+ * Snapshot the selected window before switching its buffer
  */
 int neomacs_display_prepare_buffer_transition(struct NeomacsDisplay *handle);
 
 /**
- * Trigger buffer transition animation (stub)
+ * Snapshot all windows but the minibuffer before the window layout changes
+ */
+int neomacs_display_prepare_frame_transition(struct NeomacsDisplay *handle);
+
+/**
+ * Animate the selected window, or all windows after
+ * neomacs_display_prepare_frame_transition, from the snapshot
  */
 int neomacs_display_trigger_buffer_transition(struct NeomacsDisplay *handle);
 
 /**
- * Check if buffer transition is ready (stub)
+ * Check if a buffer transition snapshot is ready
  */
 int neomacs_display_has_transition_snapshot(struct NeomacsDisplay *handle);
 
+/**
+ * Animate a property of a window through keyframes; returns the
+ * animation id, or 0 on failure
+ */
+uint32_t neomacs_display_animate(struct NeomacsDisplay *handle,
+                                 int64_t windowId,
+                                 int property,
+                                 const int *times,
+                                 const double *values,
+                                 const int *easings,
+                                 const double *params,
+                                 int count,
+                                 int delayMs,
+                                 int iterations);
+
+/**
+ * Cancel a timeline animation (0 = all)
+ */
+void neomacs_display_cancel_animation(struct NeomacsDisplay *handle, uint32_t id);
+
+/**
+ * Set the easing curve of cursor, scroll, crossfade or buffer transition
+ * animations (easing -1 restores the default)
+ */
+void neomacs_display_set_animation_curve(struct NeomacsDisplay *handle,
+                                         int target,
+                                         int easing,
+                                         double p0,
+                                         double p1,
+                                         double p2,
+                                         double p3);
+
 /**
  * Initialize display in threaded mode
  *
@@ -1014,6 +1457,15 @@ struct NeomacsMonitorInfo {
   double scale;
   int width_mm;
   int height_mm;
+  /* Work area: the monitor minus panels and docks */
+  int work_x;
+  int work_y;
+  int work_width;
+  int work_height;
+  /* Refresh rate in millihertz (0 = unknown) */
+  uint32_t refresh_mhz;
+  /* 1 for the primary monitor */
+  int primary;
 };
 
 /**
@@ -1040,6 +1492,71 @@ int neomacs_display_get_monitor_info(int index, struct NeomacsMonitorInfo *info)
  */
 const char *neomacs_display_get_monitor_name(int index);
 
+/**
+ * Place a width x height popup on side (0 below, 1 above, 2 right,
+ * 3 left) of anchor, gap pixels away, inside the frame at frame (desktop
+ * coordinates) and the work area of the anchor's monitor, off avoid (or
+ * NULL).  Rectangles are {x, y, width, height}; anchor, avoid and out
+ * {x, y} are frame-relative.  Returns the side used.
+ */
+int neomacs_display_place_popup(const int *frame,
+                                const int *anchor,
+                                int width,
+                                int height,
+                                int side,
+                                int gap,
+                                const int *avoid,
+                                int *out);
+
+/**
+ * Ask the render thread for the current monitors, waiting up to
+ * timeout_ms (<= 0 for the default), and update the list read by
+ * neomacs_display_get_monitor_info.  Returns the number of monitors, or
+ * -1 if the render thread did not answer (the previous list is kept).
+ */
+int neomacs_display_refresh_monitors(int timeout_ms);
+
+/**
+ * Measure text as the render thread shapes it in family (NULL for
+ * monospace).  Stores the extent in logical pixels in width and height.
+ * Returns 1 on success, 0 if the render thread did not answer within
+ * timeout_ms.
+ */
+int neomacs_display_query_text_extent(const char *text,
+                                      const char *family,
+                                      int weight,
+                                      int italic,
+                                      float font_size,
+                                      int timeout_ms,
+                                      float *width,
+                                      float *height);
+
+/**
+ * Ask the render thread for the size of an image, waiting up to
+ * timeout_ms.  Returns 1 and stores the size on success, 0 if the image
+ * is not decoded yet or the render thread did not answer.
+ */
+int neomacs_display_query_image_size(uint32_t image_id,
+                                     int timeout_ms,
+                                     int *width,
+                                     int *height);
+
+/**
+ * Ask the render thread for a JSON description of the frames, glyphs,
+ * faces, cursors and animations it is drawing, waiting up to timeout_ms.
+ * Returns the JSON (free with neomacs_display_free_string), or NULL if
+ * the render thread did not answer.
+ */
+char *neomacs_display_dump_scene(int timeout_ms);
+
+/**
+ * Ask the render thread how long each step of its initialization took,
+ * waiting up to timeout_ms.  Returns the table (free with
+ * neomacs_display_free_string), or NULL before the first frame or if the
+ * render thread did not answer.
+ */
+char *neomacs_display_startup_report(int timeout_ms);
+
 /**
  * Drain input events from render thread
  *
@@ -1120,6 +1637,67 @@ uint32_t neomacs_display_terminal_create(uint16_t cols, uint16_t rows,
 void neomacs_display_terminal_write(uint32_t terminal_id,
                                      const uint8_t *data, size_t len);
 
+/**
+ * Send a key event to a terminal, encoded for the keyboard protocol
+ * (legacy or kitty) its program enabled.
+ * key: a single UTF-8 character or an Emacs key name ("return", "f5")
+ * modifiers: 1=Shift, 2=Alt, 4=Ctrl, 8=Super, 16=Hyper, 32=Meta
+ * kind: 0=press, 1=repeat, 2=release
+ * Returns 0 if the key name is unknown.
+ */
+int neomacs_display_terminal_send_key(uint32_t terminal_id, const char *key,
+                                      uint8_t modifiers, uint8_t kind);
+
+/**
+ * Report a mouse event to the program in a terminal, if it enabled
+ * mouse tracking.
+ * button: 0=none (motion), 1-3=left/middle/right, 4-7=wheel up/down/left/right
+ * kind: 0=press, 1=release, 2=motion
+ * col, row: 0-based cell; modifiers as for neomacs_display_terminal_send_key
+ */
+void neomacs_display_terminal_send_mouse(uint32_t terminal_id, uint8_t button,
+                                         uint8_t kind, uint32_t col,
+                                         uint32_t row, uint8_t modifiers);
+
+/**
+ * Get the mouse tracking mode the program in a terminal enabled.
+ * Returns 0=off, 1=X10, 2=normal, 3=button-event, 4=any-event.
+ */
+int neomacs_display_terminal_mouse_mode(uint32_t terminal_id);
+
+/**
+ * Set the terminal with keyboard focus (0 for none); it gets key
+ * release events from the render thread.
+ */
+void neomacs_display_terminal_focus(uint32_t terminal_id);
+
+/**
+ * Flash a terminal's view briefly, as a visual bell.
+ */
+void neomacs_display_terminal_flash(uint32_t terminal_id);
+
+/**
+ * Scroll a terminal's view so that the Nth shell prompt after its top
+ * line (before it if N is negative) is at the top.
+ */
+void neomacs_display_terminal_jump_to_prompt(uint32_t terminal_id, int n);
+
+/**
+ * Output of the last finished command in a terminal, as marked by the
+ * shell (OSC 133), or NULL.  The caller frees it with free().
+ */
+char *neomacs_display_terminal_last_output(uint32_t terminal_id);
+
+/**
+ * Set a terminal's ANSI palette (NCOLORS entries, at most 16 used) and
+ * default FG / BG, all 0xRRGGBB; 0xFFFFFFFF keeps the built-in color.
+ */
+void neomacs_display_terminal_set_palette(uint32_t terminal_id,
+                                          const uint32_t *colors,
+                                          int ncolors,
+                                          uint32_t fg,
+                                          uint32_t bg);
+
 /**
  * Resize a terminal.
  */
@@ -1179,6 +1757,301 @@ int neomacs_primary_selection_set_text(const char *text);
  */
 char *neomacs_primary_selection_get_text(void);
 
+#define NEOMACS_THUMBNAIL_READY 1
+#define NEOMACS_THUMBNAIL_PENDING 0
+#define NEOMACS_THUMBNAIL_FAILED -1
+#define NEOMACS_THUMBNAIL_UNKNOWN -2
+
+/**
+ * Request a thumbnail of the file at PATH (absolute), at least SIZE pixels
+ * on its longer edge.  Returns the thumbnail file name, to be freed with
+ * neomacs_thumbnail_free_path(), or NULL if it is being generated.
+ */
+char *neomacs_thumbnail_request(const char *path, int size);
+
+/**
+ * Status of an earlier thumbnail request (NEOMACS_THUMBNAIL_*).
+ */
+int neomacs_thumbnail_status(const char *path, int size);
+
+/**
+ * Number of thumbnails still being generated.
+ */
+int neomacs_thumbnail_pending_count(void);
+
+/**
+ * Free a string returned by neomacs_thumbnail_request().
+ */
+void neomacs_thumbnail_free_path(char *path);
+
+#define NEOMACS_CRYPTO_DONE 1
+#define NEOMACS_CRYPTO_RUNNING 0
+#define NEOMACS_CRYPTO_FAILED -1
+#define NEOMACS_CRYPTO_NEED_PASSPHRASE -2
+#define NEOMACS_CRYPTO_BAD_PASSPHRASE -3
+#define NEOMACS_CRYPTO_NO_KEY -4
+#define NEOMACS_CRYPTO_UNKNOWN -5
+
+#define NEOMACS_CRYPTO_AGE 0
+#define NEOMACS_CRYPTO_OPENPGP 1
+
+/**
+ * Whether this build can encrypt and decrypt files.
+ */
+int neomacs_crypto_available(void);
+
+/**
+ * Set the secret keys for decryption, newline-separated file names (or
+ * NULL), and how long passphrases are cached, in seconds.
+ */
+void neomacs_crypto_configure(const char *age_identities,
+                              const char *openpgp_secret_keys,
+                              double cache_timeout);
+
+/**
+ * Forget all cached passphrases.
+ */
+void neomacs_crypto_clear_cache(void);
+
+/**
+ * Start decrypting the file at PATH, with PASSPHRASE or NULL.
+ * Returns the job id, 0 on error.
+ */
+uint64_t neomacs_crypto_decrypt_file(const char *path, const char *passphrase);
+
+/**
+ * Start decrypting LEN bytes at DATA from the file CONTEXT, with
+ * PASSPHRASE or NULL.  Returns the job id.
+ */
+uint64_t neomacs_crypto_decrypt_data(const uint8_t *data, size_t len,
+                                     const char *context,
+                                     const char *passphrase);
+
+/**
+ * Start encrypting LEN bytes at DATA in FORMAT (NEOMACS_CRYPTO_AGE or
+ * _OPENPGP) to newline-separated RECIPIENTS, or with PASSPHRASE or the
+ * one cached for CONTEXT.  With OUTPUT the ciphertext replaces that
+ * file; otherwise it is the result, ASCII-armored if ARMOR.
+ */
+uint64_t neomacs_crypto_encrypt(const uint8_t *data, size_t len, int format,
+                                const char *recipients,
+                                const char *passphrase,
+                                const char *context, const char *output,
+                                int armor);
+
+/**
+ * State of JOB (NEOMACS_CRYPTO_*).  A finished job is forgotten: its
+ * result is stored in *DATA_OUT and *LEN_OUT, to be freed with
+ * neomacs_crypto_free_data(), or a failure message in *MESSAGE_OUT, to
+ * be freed with neomacs_crypto_free_message().
+ */
+int neomacs_crypto_job_result(uint64_t job, uint8_t **data_out,
+                              size_t *len_out, char **message_out);
+
+/**
+ * Clear and free data returned by neomacs_crypto_job_result().
+ */
+void neomacs_crypto_free_data(uint8_t *data, size_t len);
+
+/**
+ * Free a message returned by neomacs_crypto_job_result().
+ */
+void neomacs_crypto_free_message(char *message);
+
+/**
+ * Whether this build can use the system keyring.
+ */
+int neomacs_secrets_available(void);
+
+/**
+ * Find up to MAX secrets matching HOST, USER and PORT (NULL for any).
+ * Stores them in *RESULTS_OUT as "HOST\tUSER\tPORT\n" lines and returns
+ * how many, or -1 with a message in *MESSAGE_OUT.
+ */
+int neomacs_secrets_search(const char *host, const char *user,
+                           const char *port, int max, char **results_out,
+                           char **message_out);
+
+/**
+ * The secret stored under exactly HOST, USER and PORT, in *SECRET_OUT.
+ * Returns 1 if found, 0 if not, -1 with a message in *MESSAGE_OUT.
+ */
+int neomacs_secrets_get(const char *host, const char *user, const char *port,
+                        char **secret_out, char **message_out);
+
+/**
+ * Store SECRET under HOST, USER and PORT.  Returns 0, or -1 with a
+ * message in *MESSAGE_OUT.
+ */
+int neomacs_secrets_store(const char *host, const char *user,
+                          const char *port, const char *secret,
+                          char **message_out);
+
+/**
+ * Delete the secrets stored under exactly HOST, USER and PORT (NULL for
+ * any).  Returns how many, or -1 with a message in *MESSAGE_OUT.
+ */
+int neomacs_secrets_delete(const char *host, const char *user,
+                           const char *port, char **message_out);
+
+/**
+ * Read the index of stored secrets from the keyring again.
+ */
+void neomacs_secrets_reload(void);
+
+/**
+ * Clear and free a string returned by the neomacs_secrets_ functions.
+ */
+void neomacs_secrets_free_string(char *s);
+
+#define NEOMACS_HTML_BOLD 1
+#define NEOMACS_HTML_ITALIC 2
+#define NEOMACS_HTML_UNDERLINE 4
+#define NEOMACS_HTML_STRIKE 8
+#define NEOMACS_HTML_CODE 16
+
+/**
+ * A styled run of rendered HTML (see neomacs_html_render).
+ */
+typedef struct NeomacsHtmlRun {
+  int64_t start;
+  int64_t end;
+  int flags;
+  int heading;
+  int fg;
+  int bg;
+  char *link;
+  char *image;
+  int image_width;
+  int image_height;
+} NeomacsHtmlRun;
+
+/**
+ * Render LEN bytes of UTF-8 HTML at DATA as text filled to WIDTH
+ * columns.  Stores the text in *TEXT_OUT, its styled runs in *RUNS_OUT
+ * and the title or NULL in *TITLE_OUT; returns the number of runs, or
+ * -1 if DATA is not UTF-8.
+ */
+int neomacs_html_render(const uint8_t *data, size_t len, int width,
+                        char **text_out, NeomacsHtmlRun **runs_out,
+                        char **title_out);
+
+/**
+ * Free what neomacs_html_render() returned.
+ */
+void neomacs_html_free(char *text, NeomacsHtmlRun *runs, int nruns,
+                       char *title);
+
+#define NEOMACS_MIME_BODY 0
+#define NEOMACS_MIME_ATTACHMENT 1
+#define NEOMACS_MIME_MESSAGE 2
+#define NEOMACS_MIME_HIDDEN 3
+
+/**
+ * A decoded message header (see neomacs_mime_parse).
+ */
+typedef struct NeomacsMimeHeader {
+  char *name;
+  char *value;
+} NeomacsMimeHeader;
+
+/**
+ * A part of a MIME message and how a reader shows it (see
+ * neomacs_mime_parse).
+ */
+typedef struct NeomacsMimePart {
+  int role;
+  char *mime_type;
+  char *charset;
+  char *filename;
+  char *content_id;
+  char *description;
+  uint8_t *body;
+  size_t body_len;
+  NeomacsMimeHeader *headers;
+  int nheaders;
+} NeomacsMimePart;
+
+/**
+ * Parse the LEN bytes of the message at DATA.  Stores its headers in
+ * *HEADERS_OUT and *NHEADERS_OUT and the parts a reader shows in
+ * *PARTS_OUT; returns the number of parts, or -1 on bad arguments.
+ * PREFER_HTML nonzero chooses HTML over plain text alternatives.
+ */
+int neomacs_mime_parse(const uint8_t *data, size_t len, int prefer_html,
+                       NeomacsMimeHeader **headers_out, int *nheaders_out,
+                       NeomacsMimePart **parts_out);
+
+/**
+ * Free what neomacs_mime_parse() returned.
+ */
+void neomacs_mime_free(NeomacsMimeHeader *headers, int nheaders,
+                       NeomacsMimePart *parts, int nparts);
+
+#define NEOMACS_A11Y_FOCUS 1
+#define NEOMACS_A11Y_CARET 2
+#define NEOMACS_A11Y_INSERT 3
+#define NEOMACS_A11Y_DELETE 4
+#define NEOMACS_A11Y_NAME 5
+#define NEOMACS_A11Y_ADD 6
+#define NEOMACS_A11Y_REMOVE 7
+
+/**
+ * An accessibility event (see neomacs_accessibility_next_event).
+ */
+typedef struct NeomacsAccessibilityEvent {
+  int kind;
+  uint64_t node;
+  const char *role;
+  int64_t position;
+  char *text;
+} NeomacsAccessibilityEvent;
+
+/**
+ * Enable or disable the accessibility tree.
+ */
+void neomacs_accessibility_set_enabled(int enabled);
+
+/**
+ * Rebuild the accessibility tree of FRAME after layout.
+ */
+void neomacs_accessibility_update(void *frame, const char *title);
+
+/**
+ * Pop the oldest accessibility event into OUT.  Returns 1 if an event
+ * was stored, 0 if there are none.
+ */
+int neomacs_accessibility_next_event(NeomacsAccessibilityEvent *out);
+
+/**
+ * Free the text of a NeomacsAccessibilityEvent.
+ */
+void neomacs_accessibility_free_string(char *text);
+
+/**
+ * Print the text between FROM and TO of the buffer WINDOW shows, laid
+ * out as WINDOW of FRAME would show it, to the PDF file PDF_PATH.
+ * PAPER names the paper size; if NULL, PAPER_WIDTH by PAPER_HEIGHT
+ * points is used when both are positive, and A4 otherwise.  Returns the
+ * number of pages, or -1 on error.
+ */
+int neomacs_print_buffer(void *frame,
+                         void *window,
+                         int64_t from,
+                         int64_t to,
+                         const char *paper,
+                         double paper_width,
+                         double paper_height,
+                         double font_size,
+                         int line_numbers,
+                         int color,
+                         int wrap,
+                         const char *header,
+                         const char *footer,
+                         const char *title,
+                         const char *file_name,
+                         const char *pdf_path);
+
 /**
  * Trigger a visual bell flash effect on the render thread.
  */
@@ -1216,6 +2089,22 @@ void neomacs_display_create_os_window(struct NeomacsDisplay *handle,
                                        int width, int height,
                                        const char *title);
 
+/**
+ * Create a popup window for a top-level Emacs frame: undecorated, always
+ * on top and centered on the focused monitor.  Destroy it with
+ * neomacs_display_destroy_os_window.
+ */
+void neomacs_display_create_popup_window(struct NeomacsDisplay *handle,
+                                          uint64_t emacs_frame_id,
+                                          int width, int height);
+
+/**
+ * Resize a popup window, keeping it centered on its monitor.
+ */
+void neomacs_display_resize_popup_window(struct NeomacsDisplay *handle,
+                                          uint64_t emacs_frame_id,
+                                          int width, int height);
+
 /**
  * Destroy an OS window for a top-level Emacs frame.
  */
@@ -1239,6 +2128,19 @@ void neomacs_display_set_child_frame_style(struct NeomacsDisplay *handle,
 void neomacs_display_request_attention(struct NeomacsDisplay *handle,
                                        int urgent);
 
+/**
+ * Withdraw a pending attention request.
+ */
+void neomacs_display_cancel_attention(struct NeomacsDisplay *handle);
+
+/**
+ * Set the window icon from len bytes of encoded image data (PNG, ICO,
+ * JPEG, ...).  NULL data restores the Neomacs logo.
+ */
+void neomacs_display_set_window_icon(struct NeomacsDisplay *handle,
+                                     const uint8_t *data,
+                                     size_t len);
+
 /**
  * Enable or disable scroll indicators and focus ring overlay.
  */
@@ -1275,6 +2177,28 @@ void neomacs_display_set_extra_spacing(struct NeomacsDisplay *handle,
 void neomacs_display_set_ligatures_enabled(struct NeomacsDisplay *handle,
                                             int enabled);
 
+/**
+ * Draw codepoint as the icon name, taken from font family (NULL or empty
+ * for the face's font).  y_offset moves it up by that fraction of the
+ * font size and scale sizes it relative to the font.  If has_color is
+ * nonzero the icon is drawn in color (0xRRGGBB) instead of the face's
+ * foreground.  Returns 0 if name is NULL or codepoint is not a character.
+ */
+int neomacs_display_register_icon(struct NeomacsDisplay *handle,
+                                  const char *name,
+                                  uint32_t codepoint,
+                                  const char *family,
+                                  float y_offset,
+                                  float scale,
+                                  uint32_t color,
+                                  int has_color);
+
+/**
+ * Forget the icon name, or all icons if name is NULL.
+ */
+void neomacs_display_remove_icon(struct NeomacsDisplay *handle,
+                                 const char *name);
+
 /**
  * Set the font metrics backend for the layout engine.
  * backend: 0 = Emacs C (default), 1 = cosmic-text.
@@ -1284,6 +2208,315 @@ void neomacs_display_set_ligatures_enabled(struct NeomacsDisplay *handle,
 void neomacs_display_set_font_backend(struct NeomacsDisplay *handle,
                                        int backend);
 
+/**
+ * Enable or disable typewriter scrolling (point's line kept centered).
+ */
+void neomacs_display_set_typewriter_scrolling(struct NeomacsDisplay *handle,
+                                              int enabled);
+
+/**
+ * Enable or disable scroll anchoring (point's line kept on its row when
+ * the window is resized or text before point changes height).
+ */
+void neomacs_display_set_scroll_anchoring(struct NeomacsDisplay *handle,
+                                          int enabled);
+
+/**
+ * Center a text column of COLUMNS characters in every non-minibuffer window.
+ * margin_bg: margin background as 0xRRGGBB, or -1 for the window background.
+ */
+void neomacs_display_set_centered_column(struct NeomacsDisplay *handle,
+                                         int enabled,
+                                         int columns,
+                                         int margin_bg);
+
+/**
+ * Show TEXT as an inline suggestion at POSITION of the buffer BUFFER_ID
+ * (a struct buffer pointer).  FG is 0xRRGGBB.  NULL or empty TEXT removes it.
+ * Returns 1 if shown (requires the Rust layout engine).
+ */
+int neomacs_display_set_ghost_text(struct NeomacsDisplay *handle,
+                                   uint64_t buffer_id,
+                                   int64_t position,
+                                   const char *text,
+                                   uint32_t fg);
+
+/**
+ * Accept part of the inline suggestion: MODE 0 = word, 1 = line, 2 = all.
+ * Returns the accepted text to insert at *POSITION (free with
+ * neomacs_display_free_string), or NULL if there is none.
+ */
+char *neomacs_display_accept_ghost_text(struct NeomacsDisplay *handle,
+                                        int mode,
+                                        int64_t *position);
+
+/**
+ * Attach a note to position POS of the buffer BUFFER_ID (a struct buffer
+ * pointer).  ICON may be NULL.  FG/BG are 0xRRGGBB.  STYLE 0 = right
+ * margin, 1 = floating card.  Returns the annotation id, or 0.
+ */
+uint32_t neomacs_display_annotation_add(struct NeomacsDisplay *handle,
+                                        uint64_t buffer_id,
+                                        int64_t pos,
+                                        const char *text,
+                                        const char *icon,
+                                        uint32_t fg,
+                                        uint32_t bg,
+                                        int style);
+
+/** Remove annotation ID.  Returns 1 if it existed.  */
+int neomacs_display_annotation_remove(struct NeomacsDisplay *handle,
+                                      uint32_t id);
+
+/** Remove all annotations of BUFFER_ID.  */
+void neomacs_display_annotation_clear(struct NeomacsDisplay *handle,
+                                      uint64_t buffer_id);
+
+/**
+ * Move the annotations of BUFFER_ID after an edit at POS that deleted
+ * DELETED and inserted INSERTED characters.
+ */
+void neomacs_display_annotation_adjust(struct NeomacsDisplay *handle,
+                                       uint64_t buffer_id,
+                                       int64_t pos,
+                                       int64_t inserted,
+                                       int64_t deleted);
+
+/**
+ * Replace the scroll bar tick marks of CATEGORY in buffer BUFFER_ID with
+ * the COUNT positions at POSITIONS (COUNT 0 removes it).  COLOR is 0xRRGGBB.
+ */
+void neomacs_display_set_scroll_bar_marks(struct NeomacsDisplay *handle,
+                                          uint64_t buffer_id,
+                                          const char *category,
+                                          uint32_t color,
+                                          const int64_t *positions,
+                                          int count);
+
+/** Remove all scroll bar tick marks of BUFFER_ID.  */
+void neomacs_display_clear_scroll_bar_marks(struct NeomacsDisplay *handle,
+                                            uint64_t buffer_id);
+
+/**
+ * Position of the scroll bar mark of BUFFER_ID nearest to offset Y of a
+ * TRACK_HEIGHT track showing BEGV..ZV, or -1 if none within TOLERANCE px.
+ */
+int64_t neomacs_display_scroll_bar_mark_at(struct NeomacsDisplay *handle,
+                                           uint64_t buffer_id,
+                                           int64_t begv,
+                                           int64_t zv,
+                                           int track_height,
+                                           int y,
+                                           int tolerance);
+
+/**
+ * Show a rectangular region in window WINDOW_ID showing BUFFER_ID: columns
+ * LEFT_COL..RIGHT_COL of the lines from START through the line of END, on
+ * background BG (0xRRGGBB).  CURSOR_COL >= 0 draws an insertion cursor in
+ * CURSOR_COLOR at that column on every line.
+ */
+void neomacs_display_set_rectangle_region(struct NeomacsDisplay *handle,
+                                          int64_t window_id,
+                                          uint64_t buffer_id,
+                                          int64_t start,
+                                          int64_t end,
+                                          int left_col,
+                                          int right_col,
+                                          uint32_t bg,
+                                          int cursor_col,
+                                          uint32_t cursor_color);
+
+/** Remove the rectangular region of window WINDOW_ID.  */
+void neomacs_display_clear_rectangle_region(struct NeomacsDisplay *handle,
+                                            int64_t window_id);
+
+/**
+ * Draw the cursor of window WINDOW_ID COLUMNS columns past the end of its
+ * line while point is at POINT.  COLUMNS <= 0 puts it back at point.
+ */
+void neomacs_display_set_virtual_cursor(struct NeomacsDisplay *handle,
+                                        int64_t window_id,
+                                        int64_t point,
+                                        int columns);
+
+/**
+ * Register a table of NROWS lines in buffer BUFFER_ID.  ROWS holds
+ * (start, end, ncells) per line, ncells < 0 for a rule line; CELLS holds
+ * (start, end) per cell and TEXTS its UTF-8 text.  ALIGNS holds NCOLS
+ * column alignments (0 left, 1 center, 2 right).
+ */
+void neomacs_display_add_table(struct NeomacsDisplay *handle,
+                               uint64_t buffer_id,
+                               int nrows,
+                               const int64_t *rows,
+                               const int64_t *cells,
+                               const char *const *texts,
+                               const int *aligns,
+                               int ncols,
+                               uint32_t grid_color);
+
+/** Remove all tables of buffer BUFFER_ID.  */
+void neomacs_display_clear_tables(struct NeomacsDisplay *handle,
+                                  uint64_t buffer_id);
+
+/**
+ * Replace the command blocks of buffer BUFFER_ID.  BLOCKS holds
+ * (start, output_start, end, exit_code, duration_ms, hidden_lines, flags)
+ * per block; duration_ms < 0 is unknown, flags bit 0 = collapsed, bit 1 =
+ * exit_code valid.  COLORS holds the tint, success, failure and label
+ * colors.  NBLOCKS = 0 removes them.
+ */
+void neomacs_display_set_command_blocks(struct NeomacsDisplay *handle,
+                                        uint64_t buffer_id,
+                                        int nblocks,
+                                        const int64_t *blocks,
+                                        const uint32_t *colors);
+
+/**
+ * Make buffer BUFFER_ID a chat buffer whose input area begins at
+ * INPUT_START, with UNREAD new messages.  COLORS holds the input
+ * background, separator, pill background and pill text colors.
+ * INPUT_START <= 0 makes it an ordinary buffer again.
+ */
+void neomacs_display_set_chat_view(struct NeomacsDisplay *handle,
+                                   uint64_t buffer_id,
+                                   int64_t input_start,
+                                   int unread,
+                                   const uint32_t *colors);
+
+/**
+ * Flash a highlight of COLOR (0xRRGGBB) over positions [START, END) of
+ * buffer BUFFER_ID, fading out over DURATION_MS milliseconds.
+ */
+void neomacs_display_pulse_region(struct NeomacsDisplay *handle,
+                                  uint64_t buffer_id,
+                                  int64_t start,
+                                  int64_t end,
+                                  uint32_t color,
+                                  int duration_ms);
+
+/**
+ * Make positions [START, END) of buffer BUFFER_ID a link to TARGET,
+ * underlined in COLOR (0xRRGGBB).  Returns the link id, or 0.
+ */
+uint32_t neomacs_display_link_add(struct NeomacsDisplay *handle,
+                                  uint64_t buffer_id,
+                                  int64_t start,
+                                  int64_t end,
+                                  const char *target,
+                                  uint32_t color);
+
+/** Remove link ID.  Returns 1 if it existed.  */
+int neomacs_display_link_remove(struct NeomacsDisplay *handle, uint32_t id);
+
+/** Remove all links of BUFFER_ID.  */
+void neomacs_display_link_clear(struct NeomacsDisplay *handle,
+                                uint64_t buffer_id);
+
+/**
+ * Move the links of BUFFER_ID after an edit at POS that deleted DELETED
+ * and inserted INSERTED characters.
+ */
+void neomacs_display_link_adjust(struct NeomacsDisplay *handle,
+                                 uint64_t buffer_id,
+                                 int64_t pos,
+                                 int64_t inserted,
+                                 int64_t deleted);
+
+/**
+ * Return the id of the link of BUFFER_ID covering POS, or 0, writing its
+ * target to TARGET_BUF (TARGET_LEN bytes including the NUL).
+ */
+uint32_t neomacs_display_link_at(struct NeomacsDisplay *handle,
+                                 uint64_t buffer_id,
+                                 int64_t pos,
+                                 char *target_buf,
+                                 size_t target_len);
+
+/**
+ * Replace the color swatches of BUFFER_ID whose literal starts in
+ * [START, END) with COUNT new ones: RANGES holds the start and end of
+ * each literal (2 * COUNT values), COLORS its color (0xRRGGBB).
+ */
+void neomacs_display_set_color_swatches(struct NeomacsDisplay *handle,
+                                        uint64_t buffer_id,
+                                        int64_t start,
+                                        int64_t end,
+                                        const int64_t *ranges,
+                                        const uint32_t *colors,
+                                        int count);
+
+/** Remove all color swatches of BUFFER_ID.  */
+void neomacs_display_clear_color_swatches(struct NeomacsDisplay *handle,
+                                          uint64_t buffer_id);
+
+/**
+ * Move the color swatches of BUFFER_ID after an edit at POS that deleted
+ * DELETED and inserted INSERTED characters.
+ */
+void neomacs_display_adjust_color_swatches(struct NeomacsDisplay *handle,
+                                           uint64_t buffer_id,
+                                           int64_t pos,
+                                           int64_t inserted,
+                                           int64_t deleted);
+
+/**
+ * Show a breadcrumb bar of COUNT segments as the header line text of
+ * WINDOW_ID.  KINDS gives each segment's kind (0 directory, 1 file,
+ * 2 symbol) and LABELS its text; SEPARATOR is drawn between segments
+ * in SEPARATOR_COLOR (0xRRGGBB, 0 for the header line's foreground).
+ * COUNT 0 removes the bar.
+ */
+void neomacs_display_set_breadcrumb_bar(struct NeomacsDisplay *handle,
+                                        int64_t window_id,
+                                        const uint8_t *kinds,
+                                        const char *const *labels,
+                                        int count,
+                                        const char *separator,
+                                        uint32_t separator_color);
+
+/**
+ * Index of the breadcrumb bar segment of WINDOW_ID drawn X pixels from
+ * the window's left edge, or -1.
+ */
+int neomacs_display_breadcrumb_bar_segment_at(struct NeomacsDisplay *handle,
+                                              int64_t window_id,
+                                              int x);
+
+/**
+ * Buffer position under frame pixel PX, PY from the last Rust layout,
+ * or -1.
+ */
+int64_t neomacs_layout_charpos_at_pixel(float px, float py);
+
+/**
+ * Buffer position under frame pixel PX, PY in the window WINDOW_ID from
+ * the last Rust layout: past the end of a row, its last position, and
+ * below the text, the last position shown.  -1 if the last layout did
+ * not include the window.
+ */
+int64_t neomacs_layout_window_charpos(int64_t window_id, float px, float py);
+
+/**
+ * Where a buffer position is drawn (see neomacs_layout_pos_to_pixel).
+ */
+typedef struct NeomacsCharPixel {
+  float x;
+  float y;
+  float width;
+  float height;
+  int row;
+  int col;
+} NeomacsCharPixel;
+
+/**
+ * Store in OUT where CHARPOS is drawn in the window WINDOW_ID by the last
+ * Rust layout.  Returns 1 if it is shown, 0 if not, and -1 if the last
+ * layout did not include the window.
+ */
+int neomacs_layout_pos_to_pixel(int64_t window_id, int64_t charpos,
+                                NeomacsCharPixel *out);
+
 void neomacs_display_set_background_gradient(
     struct NeomacsDisplay *handle,
     int enabled,
@@ -1430,6 +2663,12 @@ void neomacs_display_set_typing_speed(
     struct NeomacsDisplay *handle,
     int enabled);
 
+void neomacs_display_set_typeahead_echo(
+    struct NeomacsDisplay *handle,
+    int enabled,
+    int opacity,
+    int timeout_ms);
+
 void neomacs_display_set_border_transition(
     struct NeomacsDisplay *handle,
     int enabled,
@@ -1447,6 +2686,25 @@ void neomacs_display_set_frosted_glass(
     int opacity,
     int blur);
 
+void neomacs_display_set_magnifier(
+    struct NeomacsDisplay *handle,
+    int enabled,
+    int circular,
+    int width, int height,
+    int zoom_pct,
+    int follow_mouse,
+    int r, int g, int b,
+    int border_width);
+
+void neomacs_display_set_crt_effect(
+    struct NeomacsDisplay *handle,
+    int enabled,
+    int scanlines_pct,
+    int curvature_pct,
+    int glow_pct,
+    int aberration_pct,
+    int terminals_only);
+
 void neomacs_display_set_region_glow(
     struct NeomacsDisplay *handle,
     int enabled,
@@ -1480,6 +2738,13 @@ void neomacs_display_set_window_watermark(
     int opacity,
     int threshold);
 
+void neomacs_display_set_background_contrast(
+    struct NeomacsDisplay *handle,
+    int enabled,
+    int style,
+    int min_ratio_tenths,
+    int max_scrim);
+
 void neomacs_display_set_mode_line_transition(
     struct NeomacsDisplay *handle,
     int enabled,
@@ -1581,6 +2846,17 @@ void neomacs_display_set_cursor_magnetism(
     int duration_ms,
     int opacity);
 
+void neomacs_display_set_cursor_beacon(
+    struct NeomacsDisplay *handle,
+    int enabled,
+    int r, int g, int b,
+    int style,
+    int duration_ms,
+    int opacity,
+    int triggers);
+
+void neomacs_display_cursor_beacon(struct NeomacsDisplay *handle);
+
 void neomacs_display_set_depth_shadow(
     struct NeomacsDisplay *handle,
     int enabled,
@@ -1714,6 +2990,125 @@ void neomacs_display_set_text_fade_in(
     int enabled,
     int duration_ms);
 
+void neomacs_display_set_text_zoom_animation(
+    struct NeomacsDisplay *handle,
+    int enabled,
+    int duration_ms);
+
+void neomacs_display_set_smooth_text_update(
+    struct NeomacsDisplay *handle,
+    int enabled,
+    int duration_ms);
+
+void neomacs_display_start_smooth_text_update(struct NeomacsDisplay *handle);
+
+void neomacs_display_set_post_shader(struct NeomacsDisplay *handle, const char *path);
+
+void neomacs_display_set_settings_file(struct NeomacsDisplay *handle, const char *path);
+
+void neomacs_display_set_present_mode(
+    struct NeomacsDisplay *handle,
+    int mode,
+    int max_frame_latency);
+
+void neomacs_display_set_gpu_preference(int preference);
+
+void neomacs_display_set_background_throttle(
+    struct NeomacsDisplay *handle,
+    int enabled,
+    int unfocused_fps,
+    int hidden_fps,
+    int pause_video);
+
+void neomacs_display_set_memory_budget(
+    struct NeomacsDisplay *handle,
+    int images_mb,
+    int glyphs_mb,
+    int snapshots_mb,
+    int staging_mb);
+
+/**
+ * Renderer memory usage returned by neomacs_display_get_memory_stats.
+ * Sizes and budgets are in bytes.
+ */
+struct NeomacsMemoryStats {
+  uint64_t image_bytes;
+  uint64_t image_count;
+  uint64_t image_evictions;
+  uint64_t glyph_bytes;
+  uint64_t glyph_count;
+  uint64_t glyph_evictions;
+  uint64_t video_bytes;
+  uint64_t snapshot_bytes;
+  uint64_t snapshot_evictions;
+  uint64_t staging_bytes;
+  uint64_t image_budget;
+  uint64_t glyph_budget;
+  uint64_t snapshot_budget;
+  uint64_t staging_budget;
+};
+
+/**
+ * Get the memory usage of the renderer's caches as of the last frame.
+ * Returns 1 on success, 0 on failure.
+ */
+int neomacs_display_get_memory_stats(struct NeomacsMemoryStats *out);
+
+/**
+ * Render command bus counters returned by neomacs_display_get_command_stats.
+ */
+struct NeomacsCommandStats {
+  uint64_t sent;
+  uint64_t dropped;
+  uint64_t coalesced;
+  uint64_t processed;
+  uint64_t batches;
+  uint64_t max_batch;
+  uint64_t pending;
+  uint64_t capacity;
+  int congested;
+};
+
+/**
+ * Get the render command bus counters, so Emacs can send fewer
+ * commands while the renderer falls behind.
+ * Returns 1 on success, 0 on failure.
+ */
+int neomacs_display_get_command_stats(struct NeomacsCommandStats *out);
+
+/**
+ * A record of the display engine's log ring.  The strings are owned by
+ * the record; free them with neomacs_display_free_log_records.
+ */
+struct NeomacsLogRecord {
+  uint64_t seq;
+  /* Milliseconds since the Unix epoch */
+  uint64_t time_ms;
+  /* 1 = error, 2 = warn, 3 = info, 4 = debug, 5 = trace */
+  int level;
+  char *target;
+  char *span;
+  char *message;
+};
+
+/**
+ * Copy the oldest MAX log records after sequence number AFTER into OUT,
+ * keeping those at LEVEL or more severe whose module contains MODULE
+ * (NULL for any).  Returns the number copied.
+ */
+int neomacs_display_get_log_records(
+    uint64_t after,
+    int level,
+    const char *module,
+    struct NeomacsLogRecord *out,
+    int max);
+
+void neomacs_display_free_log_records(struct NeomacsLogRecord *records, int count);
+
+void neomacs_display_set_log_level(int level);
+
+void neomacs_display_clear_log(void);
+
 void neomacs_display_set_scroll_line_spacing(
     struct NeomacsDisplay *handle,
     int enabled,
@@ -2596,4 +3991,11 @@ void neomacs_display_free_dropped_path(char *path);
  */
 char *neomacs_display_get_terminal_title(uint32_t terminal_id);
 
+/**
+ * Take the file chosen in the directory view.  Returns a C string that
+ * must be freed with neomacs_display_free_dropped_path(), or NULL if
+ * none was chosen.
+ */
+char *neomacs_display_take_dir_view_choice(void);
+
 #endif  /* NEOMACS_DISPLAY_H */
@@ -21,6 +21,7 @@ along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.  */
 #define NLOG_MODULE "frame"
 #include "neomacs_log.h"
 
+#include <fcntl.h>
 #include <math.h>
 
 #include "lisp.h"
@@ -389,8 +390,8 @@ neomacs_set_icon_name (struct frame *f, Lisp_Object arg, Lisp_Object oldval)
 static void
 neomacs_set_icon_type (struct frame *f, Lisp_Object arg, Lisp_Object oldval)
 {
-  /* Wayland doesn't use bitmap icons (icons come from desktop files),
-     so this is intentionally a no-op.  Just store if provided.  */
+  /* The window icon is shared by all frames; the icon file is loaded
+     (and converted if need be) in Lisp.  */
   if (STRINGP (arg))
     {
       if (STRINGP (oldval) && EQ (Fstring_equal (oldval, arg), Qt))
@@ -398,6 +399,10 @@ neomacs_set_icon_type (struct frame *f, Lisp_Object arg, Lisp_Object oldval)
     }
   else if (!STRINGP (oldval) && NILP (oldval) == NILP (arg))
     return;
+
+  Lisp_Object fn = intern_c_string ("neomacs--set-icon-type");
+  if (!NILP (Ffboundp (fn)))
+    safe_calln (fn, arg);
 }
 
 /* Change the title of frame F to NAME.
@@ -412,18 +417,10 @@ neomacs_set_title_handler (struct frame *f, Lisp_Object name,
   update_mode_lines = 22;
   fset_title (f, name);
 
-  if (NILP (name))
-    name = f->name;
-  else
+  if (!NILP (name))
     CHECK_STRING (name);
 
-  struct neomacs_display_info *dpyinfo = FRAME_DISPLAY_INFO (f);
-  if (dpyinfo && dpyinfo->display_handle)
-    {
-      Lisp_Object encoded = ENCODE_UTF_8 (name);
-      neomacs_display_set_title (dpyinfo->display_handle,
-                                 SSDATA (encoded));
-    }
+  neomacs_send_title (f);
 }
 
 static void
@@ -474,7 +471,8 @@ neomacs_set_undecorated (struct frame *f, Lisp_Object arg, Lisp_Object oldval)
     {
       FRAME_UNDECORATED (f) = NILP (arg) ? false : true;
       struct neomacs_display_info *dpyinfo = FRAME_DISPLAY_INFO (f);
-      if (dpyinfo && dpyinfo->display_handle)
+      /* Popup windows are always undecorated.  */
+      if (dpyinfo && dpyinfo->display_handle && !FRAME_NEOMACS_POPUP_P (f))
 	neomacs_display_set_decorated (dpyinfo->display_handle,
 				       NILP (arg) ? 1 : 0);
     }
@@ -768,8 +766,9 @@ neomacs_create_frame_widgets (struct frame *f)
 
   /* Child frames share the parent's winit window and are composited as
      overlays.  Don't overwrite the global resize callback (it must
-     point to the root frame) and don't show a separate OS window.  */
-  if (!FRAME_PARENT_FRAME (f))
+     point to the root frame) and don't show a separate OS window.
+     Popup frames get a window of their own when made visible.  */
+  if (!FRAME_PARENT_FRAME (f) && !output->popup_window)
     {
       neomacs_display_set_resize_callback (neomacs_widget_resize_cb, f);
       neomacs_display_show_window (dpyinfo->display_handle, window_id, true);
@@ -956,6 +955,13 @@ If the parameters specify a display, that display is used.  */)
       }
   }
 
+  /* A popup frame gets an undecorated, always-on-top window of its own,
+     centered on the focused monitor (see `neomacs-popup-frame-call').  */
+  tem = gui_display_get_arg (dpyinfo, parms, Qneomacs_popup, NULL, NULL,
+                             RES_TYPE_BOOLEAN);
+  FRAME_NEOMACS_OUTPUT (f)->popup_window
+    = !FRAME_PARENT_FRAME (f) && EQ (tem, Qt);
+
   /* Initialize frame dimensions */
   f->border_width = 0;
   f->internal_border_width = 0;
@@ -1673,6 +1679,11 @@ Internal use only, use `display-monitor-attributes-list' instead.  */)
   struct MonitorInfo *monitors;
 
   block_input ();
+  /* Pick up monitors connected or removed since startup; on timeout
+     the last known list is used.  */
+  neomacs_display_refresh_monitors (clip_to_bounds (1,
+                                                    neomacs_render_query_timeout,
+                                                    INT_MAX));
   n_monitors = neomacs_display_get_monitor_count ();
   if (n_monitors == 0)
     {
@@ -1683,19 +1694,6 @@ Internal use only, use `display-monitor-attributes-list' instead.  */)
   monitor_frames = make_nil_vector (n_monitors);
   monitors = xzalloc (n_monitors * sizeof *monitors);
 
-  /* Collect frames per monitor.  Since neomacs uses a single winit
-     window, all frames are effectively on the same monitor.  Assign
-     all non-tooltip frames to the primary monitor.  */
-  FOR_EACH_FRAME (rest, frame)
-    {
-      struct frame *f = XFRAME (frame);
-      if (FRAME_NEOMACS_P (f)
-	  && FRAME_DISPLAY_INFO (f) == dpyinfo
-	  && !FRAME_TOOLTIP_P (f))
-	ASET (monitor_frames, 0,
-	      Fcons (frame, AREF (monitor_frames, 0)));
-    }
-
   for (int i = 0; i < n_monitors; i++)
     {
       struct NeomacsMonitorInfo nmi;
@@ -1708,16 +1706,46 @@ Internal use only, use `display-monitor-attributes-list' instead.  */)
       mi->geom.y = nmi.y;
       mi->geom.width = nmi.width;
       mi->geom.height = nmi.height;
-      /* winit doesn't provide workarea; use geometry.  */
-      mi->work = mi->geom;
+      mi->work.x = nmi.work_x;
+      mi->work.y = nmi.work_y;
+      mi->work.width = nmi.work_width;
+      mi->work.height = nmi.work_height;
       mi->mm_width = nmi.width_mm;
       mi->mm_height = nmi.height_mm;
+      mi->scale_factor = nmi.scale;
+      mi->refresh_rate = nmi.refresh_mhz / 1000.0;
+      if (nmi.primary)
+	primary_monitor = i;
 
       const char *name = neomacs_display_get_monitor_name (i);
       if (name)
 	dupstring (&mi->name, name);
     }
 
+  /* Assign each frame to the monitor showing its top left corner, or
+     to the primary monitor if none does.  */
+  FOR_EACH_FRAME (rest, frame)
+    {
+      struct frame *f = XFRAME (frame);
+      if (FRAME_NEOMACS_P (f)
+	  && FRAME_DISPLAY_INFO (f) == dpyinfo
+	  && !FRAME_TOOLTIP_P (f))
+	{
+	  int m = primary_monitor;
+	  for (int i = 0; i < n_monitors; i++)
+	    {
+	      Emacs_Rectangle *g = &monitors[i].geom;
+	      if (f->left_pos >= g->x && f->left_pos < g->x + g->width
+		  && f->top_pos >= g->y && f->top_pos < g->y + g->height)
+		{
+		  m = i;
+		  break;
+		}
+	    }
+	  ASET (monitor_frames, m, Fcons (frame, AREF (monitor_frames, m)));
+	}
+    }
+
   attributes_list = make_monitor_attribute_list (monitors,
 						 n_monitors,
 						 primary_monitor,
@@ -1729,6 +1757,65 @@ Internal use only, use `display-monitor-attributes-list' instead.  */)
   return attributes_list;
 }
 
+DEFUN ("neomacs-place-popup", Fneomacs_place_popup, Sneomacs_place_popup,
+       3, 6, 0,
+       doc: /* Return where a popup of WIDTH x HEIGHT pixels goes next to ANCHOR.
+ANCHOR is (X Y WIDTH HEIGHT), the rectangle the popup belongs to (the
+character at point, a menu item, the mouse pointer) in pixels relative
+to FRAME, which defaults to the selected frame.  SIDE is the side of
+ANCHOR the popup prefers: `below' (the default), `above', `right' or
+`left'; GAP is the distance from ANCHOR in pixels (default 0).
+
+The popup is kept inside FRAME and inside the work area of the monitor
+showing ANCHOR, and off FRAME's minibuffer window.  When it does not
+fit on SIDE it flips to the opposite side; along SIDE it is shifted to
+stay inside.  The value is (X Y SIDE-USED), with X and Y relative to
+FRAME, suitable for positioning a child frame of FRAME.  */)
+  (Lisp_Object anchor, Lisp_Object width, Lisp_Object height,
+   Lisp_Object side, Lisp_Object gap, Lisp_Object frame)
+{
+  struct frame *f = decode_window_system_frame (frame);
+  Lisp_Object sides[] = { Qbelow, Qabove, Qright, Qleft };
+  int frame_rect[4] = { f->left_pos, f->top_pos,
+			FRAME_PIXEL_WIDTH (f), FRAME_PIXEL_HEIGHT (f) };
+  int anchor_rect[4], avoid[4], out[2] = { 0, 0 };
+  int side_index = 0;
+
+  for (int i = 0; i < 4; i++)
+    {
+      CHECK_CONS (anchor);
+      anchor_rect[i] = check_integer_range (XCAR (anchor), INT_MIN, INT_MAX);
+      anchor = XCDR (anchor);
+    }
+  int w = check_integer_range (width, 0, INT_MAX);
+  int h = check_integer_range (height, 0, INT_MAX);
+  if (!NILP (side))
+    {
+      for (side_index = 0; side_index < 4; side_index++)
+	if (EQ (side, sides[side_index]))
+	  break;
+      if (side_index == 4)
+	xsignal2 (Qargs_out_of_range, side, list4 (Qbelow, Qabove, Qright, Qleft));
+    }
+  int gap_px = NILP (gap) ? 0 : check_integer_range (gap, 0, INT_MAX);
+
+  bool has_mini = FRAME_HAS_MINIBUF_P (f) && !FRAME_MINIBUF_ONLY_P (f);
+  if (has_mini)
+    {
+      struct window *mini = XWINDOW (FRAME_MINIBUF_WINDOW (f));
+      avoid[0] = WINDOW_LEFT_EDGE_X (mini);
+      avoid[1] = WINDOW_TOP_EDGE_Y (mini);
+      avoid[2] = WINDOW_PIXEL_WIDTH (mini);
+      avoid[3] = WINDOW_PIXEL_HEIGHT (mini);
+    }
+
+  int used = neomacs_display_place_popup (frame_rect, anchor_rect, w, h,
+					  side_index, gap_px,
+					  has_mini ? avoid : NULL, out);
+  return list3 (make_fixnum (out[0]), make_fixnum (out[1]),
+		sides[clip_to_bounds (0, used, 3)]);
+}
+
 DEFUN ("x-open-connection", Fx_open_connection, Sx_open_connection, 1, 3, 0,
        doc: /* Open a connection to a Neomacs display.
 DISPLAY is the name of the display.  Optional second arg
@@ -1937,6 +2024,737 @@ Returns a string, or nil if the selection is empty or an error occurred.  */)
   return result;
 }
 
+/* ============================================================================
+ * Thumbnails
+ * ============================================================================ */
+
+/* Convert the optional SIZE argument of the thumbnail functions to a
+   pixel size.  */
+static int
+neomacs_thumbnail_size (Lisp_Object size)
+{
+  if (NILP (size) || EQ (size, Qnormal))
+    return 128;
+  if (EQ (size, Qlarge))
+    return 256;
+  if (EQ (size, Qx_large))
+    return 512;
+  if (EQ (size, Qxx_large))
+    return 1024;
+  CHECK_FIXNAT (size);
+  return XFIXNAT (size) > INT_MAX ? INT_MAX : XFIXNAT (size);
+}
+
+DEFUN ("neomacs-thumbnail", Fneomacs_thumbnail, Sneomacs_thumbnail, 1, 2, 0,
+       doc: /* Return the file name of a thumbnail of FILE, or nil.
+SIZE is the minimum size of the thumbnail's longer edge: a number of
+pixels, or one of the symbols `normal' (128, the default), `large'
+(256), `x-large' (512) or `xx-large' (1024).
+
+Thumbnails are cached in the freedesktop.org thumbnail directory.  If no
+up-to-date thumbnail exists yet, one is generated in the background and
+this returns nil; use `neomacs-thumbnail-status' to find out when it is
+ready.  The returned file can be displayed with `create-image'.  */)
+  (Lisp_Object file, Lisp_Object size)
+{
+  CHECK_STRING (file);
+  int pixels = neomacs_thumbnail_size (size);
+  Lisp_Object encoded = ENCODE_FILE (Fexpand_file_name (file, Qnil));
+  char *thumb = neomacs_thumbnail_request (SSDATA (encoded), pixels);
+  if (!thumb)
+    return Qnil;
+  Lisp_Object result = DECODE_FILE (build_unibyte_string (thumb));
+  neomacs_thumbnail_free_path (thumb);
+  return result;
+}
+
+DEFUN ("neomacs-thumbnail-status", Fneomacs_thumbnail_status,
+       Sneomacs_thumbnail_status, 1, 2, 0,
+       doc: /* Return the state of the thumbnail of FILE at SIZE.
+The value is `ready', `pending' or `failed', or nil if no thumbnail was
+requested with `neomacs-thumbnail'.  SIZE is as for `neomacs-thumbnail'.  */)
+  (Lisp_Object file, Lisp_Object size)
+{
+  CHECK_STRING (file);
+  int pixels = neomacs_thumbnail_size (size);
+  Lisp_Object encoded = ENCODE_FILE (Fexpand_file_name (file, Qnil));
+  switch (neomacs_thumbnail_status (SSDATA (encoded), pixels))
+    {
+    case NEOMACS_THUMBNAIL_READY:
+      return Qready;
+    case NEOMACS_THUMBNAIL_PENDING:
+      return Qpending;
+    case NEOMACS_THUMBNAIL_FAILED:
+      return Qfailed;
+    default:
+      return Qnil;
+    }
+}
+
+DEFUN ("neomacs-thumbnail-pending-count", Fneomacs_thumbnail_pending_count,
+       Sneomacs_thumbnail_pending_count, 0, 0, 0,
+       doc: /* Return the number of thumbnails still being generated.  */)
+  (void)
+{
+  return make_fixnum (neomacs_thumbnail_pending_count ());
+}
+
+/* ============================================================================
+ * Encrypted files
+ * ============================================================================ */
+
+/* Join the strings in LIST with newlines, expanded and encoded as file
+   names if FILES, or return nil if it has none.  */
+static Lisp_Object
+neomacs_crypto_lines (Lisp_Object list, bool files)
+{
+  Lisp_Object result = Qnil;
+  for (Lisp_Object tail = list; CONSP (tail); tail = XCDR (tail))
+    {
+      Lisp_Object item = XCAR (tail);
+      CHECK_STRING (item);
+      item = (files ? ENCODE_FILE (Fexpand_file_name (item, Qnil))
+              : ENCODE_UTF_8 (item));
+      result = NILP (result) ? item : concat3 (result, build_string ("\n"), item);
+    }
+  return result;
+}
+
+/* Return the encoded contents of the optional string VALUE, or NULL.  */
+static const char *
+neomacs_crypto_string (Lisp_Object value, bool file)
+{
+  if (NILP (value))
+    return NULL;
+  CHECK_STRING (value);
+  return SSDATA (file ? ENCODE_FILE (Fexpand_file_name (value, Qnil))
+                 : ENCODE_UTF_8 (value));
+}
+
+DEFUN ("neomacs-crypto-available-p", Fneomacs_crypto_available_p,
+       Sneomacs_crypto_available_p, 0, 0, 0,
+       doc: /* Return non-nil if age and OpenPGP files can be encrypted.  */)
+  (void)
+{
+  return neomacs_crypto_available () ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-crypto-configure", Fneomacs_crypto_configure,
+       Sneomacs_crypto_configure, 3, 3, 0,
+       doc: /* Set the secret keys and passphrase cache for encrypted files.
+AGE-IDENTITIES is a list of age identity files, OPENPGP-SECRET-KEYS a
+list of exported OpenPGP secret key files.  Passphrases that worked are
+cached for CACHE-TIMEOUT seconds; 0 disables the cache.  */)
+  (Lisp_Object age_identities, Lisp_Object openpgp_secret_keys,
+   Lisp_Object cache_timeout)
+{
+  Lisp_Object age = neomacs_crypto_lines (age_identities, true);
+  Lisp_Object openpgp = neomacs_crypto_lines (openpgp_secret_keys, true);
+  CHECK_NUMBER (cache_timeout);
+  neomacs_crypto_configure (NILP (age) ? NULL : SSDATA (age),
+                            NILP (openpgp) ? NULL : SSDATA (openpgp),
+                            XFLOATINT (cache_timeout));
+  return Qnil;
+}
+
+DEFUN ("neomacs-crypto-clear-cache", Fneomacs_crypto_clear_cache,
+       Sneomacs_crypto_clear_cache, 0, 0, 0,
+       doc: /* Forget all cached passphrases of encrypted files.  */)
+  (void)
+{
+  neomacs_crypto_clear_cache ();
+  return Qnil;
+}
+
+DEFUN ("neomacs-crypto-decrypt-file", Fneomacs_crypto_decrypt_file,
+       Sneomacs_crypto_decrypt_file, 1, 2, 0,
+       doc: /* Start decrypting the age or OpenPGP file FILE.
+Without PASSPHRASE, the secret keys and cached passphrases are tried.
+Return a job for `neomacs-crypto-job-result'.  */)
+  (Lisp_Object file, Lisp_Object passphrase)
+{
+  CHECK_STRING (file);
+  Lisp_Object encoded = ENCODE_FILE (Fexpand_file_name (file, Qnil));
+  uint64_t job = neomacs_crypto_decrypt_file (SSDATA (encoded),
+                                              neomacs_crypto_string (passphrase,
+                                                                     false));
+  return job ? make_fixnum (job) : Qnil;
+}
+
+DEFUN ("neomacs-crypto-decrypt-string", Fneomacs_crypto_decrypt_string,
+       Sneomacs_crypto_decrypt_string, 1, 3, 0,
+       doc: /* Start decrypting STRING, an age or OpenPGP message.
+CONTEXT is the file it comes from, under which a passphrase for it is
+cached.  PASSPHRASE is as for `neomacs-crypto-decrypt-file'.  Return a
+job for `neomacs-crypto-job-result'.  */)
+  (Lisp_Object string, Lisp_Object context, Lisp_Object passphrase)
+{
+  CHECK_STRING (string);
+  if (STRING_MULTIBYTE (string))
+    string = ENCODE_UTF_8 (string);
+  uint64_t job
+    = neomacs_crypto_decrypt_data ((const uint8_t *) SDATA (string),
+                                   SBYTES (string),
+                                   neomacs_crypto_string (context, true),
+                                   neomacs_crypto_string (passphrase, false));
+  return make_fixnum (job);
+}
+
+DEFUN ("neomacs-crypto-encrypt", Fneomacs_crypto_encrypt,
+       Sneomacs_crypto_encrypt, 3, 7, 0,
+       doc: /* Start encrypting STRING in FORMAT, `age' or `openpgp'.
+RECIPIENTS is a list of age recipients ("age1...") or of OpenPGP
+public key files.  If it is nil, STRING is encrypted with PASSPHRASE,
+or with the passphrase cached for CONTEXT, the file it belongs to.
+
+If FILE is non-nil the result replaces that file; otherwise the job's
+result is the encrypted string, ASCII-armored if ARMOR is non-nil.
+Return a job for `neomacs-crypto-job-result'.  */)
+  (Lisp_Object string, Lisp_Object format, Lisp_Object recipients,
+   Lisp_Object passphrase, Lisp_Object context, Lisp_Object file,
+   Lisp_Object armor)
+{
+  CHECK_STRING (string);
+  if (STRING_MULTIBYTE (string))
+    string = ENCODE_UTF_8 (string);
+  bool age = EQ (format, Qage);
+  Lisp_Object keys = neomacs_crypto_lines (recipients, !age);
+  uint64_t job
+    = neomacs_crypto_encrypt ((const uint8_t *) SDATA (string),
+                              SBYTES (string),
+                              age ? NEOMACS_CRYPTO_AGE : NEOMACS_CRYPTO_OPENPGP,
+                              NILP (keys) ? NULL : SSDATA (keys),
+                              neomacs_crypto_string (passphrase, false),
+                              neomacs_crypto_string (context, true),
+                              neomacs_crypto_string (file, true),
+                              !NILP (armor));
+  return make_fixnum (job);
+}
+
+DEFUN ("neomacs-crypto-job-result", Fneomacs_crypto_job_result,
+       Sneomacs_crypto_job_result, 1, 1, 0,
+       doc: /* Return the result of the encryption or decryption JOB.
+While it runs the value is `pending'.  Once it is done the value is
+the resulting unibyte string (empty if it wrote a file), or one of
+`need-passphrase', `bad-passphrase' or `no-key', or (failed . MESSAGE);
+the job is then forgotten.  Return nil for an unknown job.  */)
+  (Lisp_Object job)
+{
+  CHECK_FIXNAT (job);
+  uint8_t *data = NULL;
+  size_t len = 0;
+  char *message = NULL;
+  switch (neomacs_crypto_job_result (XFIXNAT (job), &data, &len, &message))
+    {
+    case NEOMACS_CRYPTO_RUNNING:
+      return Qpending;
+    case NEOMACS_CRYPTO_DONE:
+      {
+        Lisp_Object result = make_unibyte_string ((char *) data, len);
+        neomacs_crypto_free_data (data, len);
+        return result;
+      }
+    case NEOMACS_CRYPTO_NEED_PASSPHRASE:
+      return Qneed_passphrase;
+    case NEOMACS_CRYPTO_BAD_PASSPHRASE:
+      return Qbad_passphrase;
+    case NEOMACS_CRYPTO_NO_KEY:
+      return Qno_key;
+    case NEOMACS_CRYPTO_FAILED:
+      {
+        Lisp_Object text = (message ? build_string (message)
+                            : build_string ("Encryption failed"));
+        neomacs_crypto_free_message (message);
+        return Fcons (Qfailed, text);
+      }
+    default:
+      return Qnil;
+    }
+}
+
+/* ============================================================================
+ * Secrets
+ * ============================================================================ */
+
+/* The UTF-8 text of VALUE, a string, number or symbol, or NULL if nil.  */
+static const char *
+neomacs_secrets_field (Lisp_Object value)
+{
+  if (NILP (value))
+    return NULL;
+  if (NUMBERP (value))
+    value = Fnumber_to_string (value);
+  else if (SYMBOLP (value))
+    value = SYMBOL_NAME (value);
+  CHECK_STRING (value);
+  return SSDATA (ENCODE_UTF_8 (value));
+}
+
+/* Signal an error with MESSAGE from the secret store, and free it.  */
+static AVOID
+neomacs_secrets_error (char *message)
+{
+  Lisp_Object text = build_string (message ? message
+                                   : "Secret store failed");
+  neomacs_secrets_free_string (message);
+  error ("%s", SSDATA (text));
+}
+
+/* A field of a search result, nil if blank.  */
+static Lisp_Object
+neomacs_secrets_result_field (const char *start, const char *end)
+{
+  return end > start ? make_string (start, end - start) : Qnil;
+}
+
+DEFUN ("neomacs-secrets-available-p", Fneomacs_secrets_available_p,
+       Sneomacs_secrets_available_p, 0, 0, 0,
+       doc: /* Return non-nil if secrets can be kept in the system keyring.  */)
+  (void)
+{
+  return neomacs_secrets_available () ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-secrets-search", Fneomacs_secrets_search,
+       Sneomacs_secrets_search, 0, 4, 0,
+       doc: /* Return the secrets in the system keyring for HOST, USER and PORT.
+Each is a string, or nil for any; PORT may also be a number.  An entry
+stored without a user or port matches any.  Return at most MAX entries,
+all if nil, as a list of (HOST USER PORT), with nil for blank fields.
+Fetch their secrets with `neomacs-secrets-get'.  */)
+  (Lisp_Object host, Lisp_Object user, Lisp_Object port, Lisp_Object max)
+{
+  int limit = INT_MAX;
+  if (!NILP (max))
+    {
+      CHECK_FIXNAT (max);
+      limit = min (XFIXNAT (max), INT_MAX);
+    }
+  char *results = NULL, *message = NULL;
+  int count = neomacs_secrets_search (neomacs_secrets_field (host),
+                                      neomacs_secrets_field (user),
+                                      neomacs_secrets_field (port),
+                                      limit, &results, &message);
+  if (count < 0)
+    neomacs_secrets_error (message);
+
+  Lisp_Object entries = Qnil;
+  for (const char *line = results; line && *line;)
+    {
+      const char *end = strchr (line, '\n');
+      if (!end)
+        end = line + strlen (line);
+      Lisp_Object fields = Qnil;
+      const char *field = line;
+      for (int i = 0; i < 3; i++)
+        {
+          const char *stop = memchr (field, '\t', end - field);
+          if (!stop)
+            stop = end;
+          fields = Fcons (neomacs_secrets_result_field (field, stop), fields);
+          field = stop < end ? stop + 1 : end;
+        }
+      entries = Fcons (Fnreverse (fields), entries);
+      line = *end ? end + 1 : end;
+    }
+  neomacs_secrets_free_string (results);
+  return Fnreverse (entries);
+}
+
+DEFUN ("neomacs-secrets-get", Fneomacs_secrets_get, Sneomacs_secrets_get,
+       3, 3, 0,
+       doc: /* Return the secret stored for HOST, USER and PORT, or nil.
+They name the entry exactly, as returned by `neomacs-secrets-search'.  */)
+  (Lisp_Object host, Lisp_Object user, Lisp_Object port)
+{
+  char *secret = NULL, *message = NULL;
+  int found = neomacs_secrets_get (neomacs_secrets_field (host),
+                                   neomacs_secrets_field (user),
+                                   neomacs_secrets_field (port),
+                                   &secret, &message);
+  if (found < 0)
+    neomacs_secrets_error (message);
+  if (!found)
+    return Qnil;
+  Lisp_Object result = build_string (secret);
+  neomacs_secrets_free_string (secret);
+  return result;
+}
+
+DEFUN ("neomacs-secrets-store", Fneomacs_secrets_store,
+       Sneomacs_secrets_store, 4, 4, 0,
+       doc: /* Keep SECRET in the system keyring for HOST, USER and PORT.
+USER and PORT may be nil, for an entry that matches any.  A secret
+already stored for them is replaced.  */)
+  (Lisp_Object host, Lisp_Object user, Lisp_Object port, Lisp_Object secret)
+{
+  CHECK_STRING (host);
+  CHECK_STRING (secret);
+  char *message = NULL;
+  if (neomacs_secrets_store (neomacs_secrets_field (host),
+                             neomacs_secrets_field (user),
+                             neomacs_secrets_field (port),
+                             neomacs_secrets_field (secret), &message) < 0)
+    neomacs_secrets_error (message);
+  return Qnil;
+}
+
+DEFUN ("neomacs-secrets-delete", Fneomacs_secrets_delete,
+       Sneomacs_secrets_delete, 0, 3, 0,
+       doc: /* Delete the secrets stored for HOST, USER and PORT.
+Each is nil for any, or must be the stored field exactly; "" stands for
+a blank one.  Return the number of secrets deleted.  */)
+  (Lisp_Object host, Lisp_Object user, Lisp_Object port)
+{
+  char *message = NULL;
+  int count = neomacs_secrets_delete (neomacs_secrets_field (host),
+                                      neomacs_secrets_field (user),
+                                      neomacs_secrets_field (port),
+                                      &message);
+  if (count < 0)
+    neomacs_secrets_error (message);
+  return make_fixnum (count);
+}
+
+DEFUN ("neomacs-secrets-reload", Fneomacs_secrets_reload,
+       Sneomacs_secrets_reload, 0, 0, 0,
+       doc: /* Read the list of stored secrets from the system keyring again.
+Use this after another Emacs session stored or deleted secrets.  */)
+  (void)
+{
+  neomacs_secrets_reload ();
+  return Qnil;
+}
+
+/* ============================================================================
+ * HTML documents
+ * ============================================================================ */
+
+/* The color 0xRRGGBB as "#rrggbb", nil if negative.  */
+static Lisp_Object
+neomacs_html_color (int color)
+{
+  if (color < 0)
+    return Qnil;
+  return CALLN (Fformat, build_string ("#%06x"), make_fixnum (color));
+}
+
+DEFUN ("neomacs-html-render", Fneomacs_html_render, Sneomacs_html_render,
+       1, 2, 0,
+       doc: /* Render the HTML document HTML as text filled to WIDTH columns.
+WIDTH defaults to 70.  Return (TEXT TITLE RUNS), where TITLE is the
+contents of the document's <title>, or nil, and RUNS lists the styled
+parts of TEXT as vectors
+
+  [START END FLAGS HEADING FOREGROUND BACKGROUND LINK IMAGE]
+
+START and END are offsets in TEXT.  FLAGS has bit 1 set for bold, 2 for
+italic, 4 for underlined, 8 for struck through and 16 for code.
+HEADING is the heading level from 1 to 6, or 0.  FOREGROUND and
+BACKGROUND are colors the document asks for, as "#rrggbb", or nil.
+LINK is the link target, as written in the document.  IMAGE is nil, or
+\(SRC WIDTH HEIGHT) when the text is the alternative text of an image,
+with 0 for a size the document does not give.  */)
+  (Lisp_Object html, Lisp_Object width)
+{
+  CHECK_STRING (html);
+  int columns = 70;
+  if (!NILP (width))
+    {
+      CHECK_FIXNAT (width);
+      columns = min (XFIXNAT (width), 10000);
+    }
+  Lisp_Object encoded = ENCODE_UTF_8 (html);
+  char *text = NULL, *title = NULL;
+  NeomacsHtmlRun *runs = NULL;
+  int count = neomacs_html_render ((const uint8_t *) SDATA (encoded),
+                                   SBYTES (encoded), columns,
+                                   &text, &runs, &title);
+  if (count < 0)
+    error ("Cannot render HTML");
+
+  Lisp_Object result_runs = Qnil;
+  for (int i = count - 1; i >= 0; i--)
+    {
+      NeomacsHtmlRun *run = &runs[i];
+      Lisp_Object image = Qnil;
+      if (run->image)
+        image = list3 (build_string (run->image),
+                       make_fixnum (run->image_width),
+                       make_fixnum (run->image_height));
+      Lisp_Object link = run->link ? build_string (run->link) : Qnil;
+      result_runs
+        = Fcons (CALLN (Fvector, make_fixnum (run->start),
+                        make_fixnum (run->end), make_fixnum (run->flags),
+                        make_fixnum (run->heading),
+                        neomacs_html_color (run->fg),
+                        neomacs_html_color (run->bg), link, image),
+                 result_runs);
+    }
+  Lisp_Object result = list3 (build_string (text),
+                              title ? build_string (title) : Qnil,
+                              result_runs);
+  neomacs_html_free (text, runs, count, title);
+  return result;
+}
+
+/* ============================================================================
+ * MIME messages
+ * ============================================================================ */
+
+/* The COUNT headers at HEADERS as an alist of (NAME . VALUE).  */
+static Lisp_Object
+neomacs_mime_headers (NeomacsMimeHeader *headers, int count)
+{
+  Lisp_Object result = Qnil;
+  for (int i = count - 1; i >= 0; i--)
+    result = Fcons (Fcons (build_string (headers[i].name),
+                           build_string (headers[i].value)),
+                    result);
+  return result;
+}
+
+static Lisp_Object
+neomacs_mime_string (const char *s)
+{
+  return s ? build_string (s) : Qnil;
+}
+
+DEFUN ("neomacs-mime-parse", Fneomacs_mime_parse, Sneomacs_mime_parse,
+       1, 2, 0,
+       doc: /* Parse the MIME message MESSAGE, a unibyte string.
+Return (HEADERS PARTS).  HEADERS is an alist of (NAME . VALUE), with
+the values unfolded and their encoded words in UTF-8, ASCII, Latin-1
+or Windows-1252 decoded; other encoded words are left as written.
+PARTS lists the parts of the message in order, as vectors
+
+  [ROLE TYPE CHARSET FILENAME CONTENT-ID DESCRIPTION BODY HEADERS]
+
+ROLE says how a reader shows the part: `body' for text and images in
+the message, `attachment', `message' for a forwarded message, whose
+parts follow, and `hidden' for an alternative not chosen or an image
+an HTML part refers to by Content-ID.  TYPE is the lowercase MIME
+type.  CHARSET, FILENAME, CONTENT-ID and DESCRIPTION are strings, or
+nil when the part does not give them.  BODY is a unibyte string with
+the transfer encoding undone; charsets are left to the caller.
+HEADERS is the alist of headers of a forwarded message, else nil.
+
+Where the message offers both, HTML is chosen over plain text unless
+PREFER-PLAIN is non-nil.  */)
+  (Lisp_Object message, Lisp_Object prefer_plain)
+{
+  CHECK_STRING (message);
+  if (STRING_MULTIBYTE (message))
+    message = ENCODE_UTF_8 (message);
+  NeomacsMimeHeader *headers = NULL;
+  NeomacsMimePart *parts = NULL;
+  int nheaders = 0;
+  int count = neomacs_mime_parse ((const uint8_t *) SDATA (message),
+                                  SBYTES (message), NILP (prefer_plain),
+                                  &headers, &nheaders, &parts);
+  if (count < 0)
+    error ("Cannot parse MIME message");
+
+  Lisp_Object result_parts = Qnil;
+  for (int i = count - 1; i >= 0; i--)
+    {
+      NeomacsMimePart *part = &parts[i];
+      Lisp_Object role;
+      switch (part->role)
+        {
+        case NEOMACS_MIME_ATTACHMENT: role = Qattachment; break;
+        case NEOMACS_MIME_MESSAGE: role = Qmessage; break;
+        case NEOMACS_MIME_HIDDEN: role = Qhidden; break;
+        default: role = Qbody; break;
+        }
+      result_parts
+        = Fcons (CALLN (Fvector, role, build_string (part->mime_type),
+                        neomacs_mime_string (part->charset),
+                        neomacs_mime_string (part->filename),
+                        neomacs_mime_string (part->content_id),
+                        neomacs_mime_string (part->description),
+                        make_unibyte_string ((const char *) part->body,
+                                             part->body_len),
+                        neomacs_mime_headers (part->headers,
+                                              part->nheaders)),
+                 result_parts);
+    }
+  Lisp_Object result = list2 (neomacs_mime_headers (headers, nheaders),
+                              result_parts);
+  neomacs_mime_free (headers, nheaders, parts, count);
+  return result;
+}
+
+/* ============================================================================
+ * Printing
+ * ============================================================================ */
+
+/* Return the encoded contents of the string option VALUE, or NULL if it
+   is not a string.  */
+static const char *
+neomacs_print_string_option (Lisp_Object value)
+{
+  return STRINGP (value) ? SSDATA (ENCODE_UTF_8 (value)) : NULL;
+}
+
+DEFUN ("neomacs-print-buffer", Fneomacs_print_buffer,
+       Sneomacs_print_buffer, 1, 4, 0,
+       doc: /* Print the buffer of WINDOW to the PDF file FILE.
+START and END delimit the text to print; they default to the accessible
+portion of the buffer.  The text is laid out on the pages as WINDOW
+(which defaults to the selected window) would show it, with its faces,
+fonts, invisible text, display properties and overlays, but without
+fringes, margins, mode line or cursor.  Faces keep their colors if
+`neomacs-print-color' is non-nil.  Long lines are wrapped unless
+`truncate-lines' is non-nil in the buffer.
+
+Page setup comes from `neomacs-print-paper', `neomacs-print-font-size',
+`neomacs-print-line-numbers', `neomacs-print-header' and
+`neomacs-print-footer'.  To send the pages to a printer, use
+`neomacs-print'.
+
+Return the number of pages printed.  */)
+  (Lisp_Object file, Lisp_Object start, Lisp_Object end, Lisp_Object window)
+{
+  struct window *w = decode_live_window (window);
+  struct buffer *b = XBUFFER (w->contents);
+  CHECK_STRING (file);
+
+  ptrdiff_t from = NILP (start) ? BUF_BEGV (b) : fix_position (start);
+  ptrdiff_t to = NILP (end) ? BUF_ZV (b) : fix_position (end);
+  if (from > to)
+    {
+      ptrdiff_t tem = from;
+      from = to;
+      to = tem;
+    }
+  if (from < BUF_BEGV (b) || to > BUF_ZV (b))
+    args_out_of_range (start, end);
+
+  /* The paper is a named size or a (WIDTH . HEIGHT) cons in points.  */
+  const char *paper = NULL;
+  double paper_width = 0, paper_height = 0;
+  if (CONSP (Vneomacs_print_paper))
+    {
+      Lisp_Object width = XCAR (Vneomacs_print_paper);
+      Lisp_Object height = XCDR (Vneomacs_print_paper);
+      CHECK_NUMBER (width);
+      CHECK_NUMBER (height);
+      paper_width = XFLOATINT (width);
+      paper_height = XFLOATINT (height);
+    }
+  else
+    paper = SYMBOLP (Vneomacs_print_paper)
+      ? SSDATA (SYMBOL_NAME (Vneomacs_print_paper))
+      : neomacs_print_string_option (Vneomacs_print_paper);
+  double font_size = NUMBERP (Vneomacs_print_font_size)
+    ? XFLOATINT (Vneomacs_print_font_size) : 0;
+  Lisp_Object encoded = ENCODE_FILE (Fexpand_file_name (file, Qnil));
+  Lisp_Object file_name = BVAR (b, filename);
+
+  int pages = neomacs_print_buffer (XFRAME (WINDOW_FRAME (w)), w, from, to,
+				    paper, paper_width, paper_height,
+				    font_size,
+				    !NILP (Vneomacs_print_line_numbers),
+				    !NILP (Vneomacs_print_color),
+				    NILP (BVAR (b, truncate_lines)),
+				    neomacs_print_string_option
+				      (Vneomacs_print_header),
+				    neomacs_print_string_option
+				      (Vneomacs_print_footer),
+				    SSDATA (ENCODE_UTF_8 (BVAR (b, name))),
+				    neomacs_print_string_option (file_name),
+				    SSDATA (encoded));
+  if (pages < 0)
+    error ("Printing to %s failed", SSDATA (file));
+  return make_fixnum (pages);
+}
+
+DEFUN ("neomacs-print-open-file", Fneomacs_print_open_file,
+       Sneomacs_print_open_file, 1, 1, 0,
+       doc: /* Open FILE for reading and return its file descriptor.
+The descriptor is for passing the file to the print portal as a
+`:unix-fd' D-Bus argument; close it with `neomacs-print-close-file'.  */)
+  (Lisp_Object file)
+{
+  CHECK_STRING (file);
+  Lisp_Object encoded = ENCODE_FILE (Fexpand_file_name (file, Qnil));
+  int fd = emacs_open (SSDATA (encoded), O_RDONLY, 0);
+  if (fd < 0)
+    report_file_error ("Opening print file", file);
+  return make_fixnum (fd);
+}
+
+DEFUN ("neomacs-print-close-file", Fneomacs_print_close_file,
+       Sneomacs_print_close_file, 1, 1, 0,
+       doc: /* Close FD, a descriptor from `neomacs-print-open-file'.  */)
+  (Lisp_Object fd)
+{
+  CHECK_FIXNAT (fd);
+  emacs_close (XFIXNAT (fd));
+  return Qnil;
+}
+
+/* ============================================================================
+ * Accessibility
+ * ============================================================================ */
+
+DEFUN ("neomacs-accessibility-enable", Fneomacs_accessibility_enable,
+       Sneomacs_accessibility_enable, 1, 1, 0,
+       doc: /* Enable the accessibility tree if ARG is non-nil, else disable it.
+While enabled, every redisplay describes the frame's windows, the text
+around point, the mode lines and any popup menu or tooltip, exports
+that to screen readers, and queues change events for
+`neomacs-accessibility-events'.  */)
+  (Lisp_Object arg)
+{
+  neomacs_accessibility_set_enabled (!NILP (arg));
+  return arg;
+}
+
+DEFUN ("neomacs-accessibility-events", Fneomacs_accessibility_events,
+       Sneomacs_accessibility_events, 0, 0, 0,
+       doc: /* Return and clear the pending accessibility events.
+Each event is a list (TYPE NODE POSITION TEXT), oldest first.  TYPE is
+one of `focus', `caret-moved', `text-inserted', `text-deleted',
+`name-changed', `node-added' or `node-removed'.  NODE is an integer
+identifying the window (or popup) concerned.  POSITION is the buffer
+position of caret and text events, else nil.  TEXT is the inserted or
+deleted text, the new name, or for `node-added' a cons (ROLE . NAME).  */)
+  (void)
+{
+  Lisp_Object events = Qnil;
+  NeomacsAccessibilityEvent ev;
+
+  while (neomacs_accessibility_next_event (&ev))
+    {
+      Lisp_Object type, text = Qnil, position = Qnil;
+      if (ev.text)
+	{
+	  text = build_string (ev.text);
+	  neomacs_accessibility_free_string (ev.text);
+	}
+      switch (ev.kind)
+	{
+	case NEOMACS_A11Y_FOCUS: type = Qfocus; break;
+	case NEOMACS_A11Y_CARET: type = Qcaret_moved; break;
+	case NEOMACS_A11Y_INSERT: type = Qtext_inserted; break;
+	case NEOMACS_A11Y_DELETE: type = Qtext_deleted; break;
+	case NEOMACS_A11Y_NAME: type = Qname_changed; break;
+	case NEOMACS_A11Y_ADD: type = Qnode_added; break;
+	default: type = Qnode_removed; break;
+	}
+      if (ev.kind == NEOMACS_A11Y_CARET || ev.kind == NEOMACS_A11Y_INSERT
+	  || ev.kind == NEOMACS_A11Y_DELETE)
+	position = make_fixnum (ev.position);
+      if (ev.kind == NEOMACS_A11Y_ADD && ev.role)
+	text = Fcons (build_string (ev.role), text);
+      events = Fcons (list4 (type, make_uint (ev.node), position, text),
+		      events);
+    }
+  return Fnreverse (events);
+}
+
 /* ============================================================================
  * Core backend query
  * ============================================================================ */
@@ -1994,6 +2812,7 @@ syms_of_neomacsfns (void)
   defsubr (&Sneomacs_mouse_absolute_pixel_position);
   defsubr (&Sneomacs_set_mouse_absolute_pixel_position);
   defsubr (&Sneomacs_display_monitor_attributes_list);
+  defsubr (&Sneomacs_place_popup);
 
   /* Connection functions */
   defsubr (&Sx_open_connection);
@@ -2011,6 +2830,74 @@ syms_of_neomacsfns (void)
   defsubr (&Sneomacs_primary_selection_set);
   defsubr (&Sneomacs_primary_selection_get);
 
+  /* Thumbnails */
+  defsubr (&Sneomacs_thumbnail);
+  defsubr (&Sneomacs_thumbnail_status);
+  defsubr (&Sneomacs_thumbnail_pending_count);
+
+  /* Encrypted files */
+  defsubr (&Sneomacs_crypto_available_p);
+  defsubr (&Sneomacs_crypto_configure);
+  defsubr (&Sneomacs_crypto_clear_cache);
+  defsubr (&Sneomacs_crypto_decrypt_file);
+  defsubr (&Sneomacs_crypto_decrypt_string);
+  defsubr (&Sneomacs_crypto_encrypt);
+  defsubr (&Sneomacs_crypto_job_result);
+
+  /* Secrets */
+  defsubr (&Sneomacs_secrets_available_p);
+  defsubr (&Sneomacs_secrets_search);
+  defsubr (&Sneomacs_secrets_get);
+  defsubr (&Sneomacs_secrets_store);
+  defsubr (&Sneomacs_secrets_delete);
+  defsubr (&Sneomacs_secrets_reload);
+
+  /* HTML documents */
+  defsubr (&Sneomacs_html_render);
+
+  /* MIME messages */
+  defsubr (&Sneomacs_mime_parse);
+
+  /* Printing */
+  defsubr (&Sneomacs_print_buffer);
+  defsubr (&Sneomacs_print_open_file);
+  defsubr (&Sneomacs_print_close_file);
+
+  DEFVAR_LISP ("neomacs-print-paper", Vneomacs_print_paper,
+    doc: /* Paper size for `neomacs-print-buffer'.
+One of the symbols `a4', `letter' or `legal', or a cons (WIDTH . HEIGHT)
+giving the size in points (1/72 inch).  */);
+  Vneomacs_print_paper = intern_c_string ("a4");
+
+  DEFVAR_LISP ("neomacs-print-font-size", Vneomacs_print_font_size,
+    doc: /* Font size in points for `neomacs-print-buffer'.  */);
+  Vneomacs_print_font_size = make_fixnum (9);
+
+  DEFVAR_LISP ("neomacs-print-line-numbers", Vneomacs_print_line_numbers,
+    doc: /* Non-nil means `neomacs-print-buffer' prints line numbers.  */);
+  Vneomacs_print_line_numbers = Qnil;
+
+  DEFVAR_LISP ("neomacs-print-color", Vneomacs_print_color,
+    doc: /* Non-nil means `neomacs-print-buffer' keeps face colors.
+Otherwise text is printed black on white, keeping the faces' fonts,
+underlines and other decorations.  */);
+  Vneomacs_print_color = Qt;
+
+  DEFVAR_LISP ("neomacs-print-header", Vneomacs_print_header,
+    doc: /* Page header for `neomacs-print-buffer', or nil for none.
+In the string, %b stands for the buffer name, %f for the file name, %p
+for the page number, %P for the number of pages and %% for a literal %.  */);
+  Vneomacs_print_header = build_string ("%b");
+
+  DEFVAR_LISP ("neomacs-print-footer", Vneomacs_print_footer,
+    doc: /* Page footer for `neomacs-print-buffer', or nil for none.
+The same %-sequences as in `neomacs-print-header' are recognized.  */);
+  Vneomacs_print_footer = build_string ("Page %p of %P");
+
+  /* Accessibility */
+  defsubr (&Sneomacs_accessibility_enable);
+  defsubr (&Sneomacs_accessibility_events);
+
   /* Core backend query */
   defsubr (&Sneomacs_core_backend);
 
@@ -2033,4 +2920,27 @@ syms_of_neomacsfns (void)
   DEFSYM (Qheight, "height");
   DEFSYM (Qnone, "none");
   DEFSYM (Qonly, "only");
+  DEFSYM (Qnormal, "normal");
+  DEFSYM (Qlarge, "large");
+  DEFSYM (Qx_large, "x-large");
+  DEFSYM (Qxx_large, "xx-large");
+  DEFSYM (Qready, "ready");
+  DEFSYM (Qpending, "pending");
+  DEFSYM (Qfailed, "failed");
+  DEFSYM (Qage, "age");
+  DEFSYM (Qneed_passphrase, "need-passphrase");
+  DEFSYM (Qbad_passphrase, "bad-passphrase");
+  DEFSYM (Qno_key, "no-key");
+  DEFSYM (Qfocus, "focus");
+  DEFSYM (Qcaret_moved, "caret-moved");
+  DEFSYM (Qtext_inserted, "text-inserted");
+  DEFSYM (Qtext_deleted, "text-deleted");
+  DEFSYM (Qname_changed, "name-changed");
+  DEFSYM (Qnode_added, "node-added");
+  DEFSYM (Qnode_removed, "node-removed");
+  DEFSYM (Qneomacs_popup, "neomacs-popup");
+  DEFSYM (Qbody, "body");
+  DEFSYM (Qattachment, "attachment");
+  DEFSYM (Qmessage, "message");
+  DEFSYM (Qhidden, "hidden");
 }
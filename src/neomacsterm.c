@@ -22,6 +22,7 @@ along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.  */
 #include "neomacs_log.h"
 
 #include <dlfcn.h>
+#include <math.h>
 #include <string.h>
 #include <stdint.h>
 #include <signal.h>
@@ -44,6 +45,7 @@ along with GNU Emacs.  If not, see <https://www.gnu.org/licenses/>.  */
 #include "fontset.h"
 #include "composite.h"  /* For composition_gstring_from_id, LGSTRING_GLYPH, etc. */
 #include "intervals.h"  /* For TEXT_PROP_MEANS_INVISIBLE */
+#include "indent.h"  /* For vmotion */
 #include "process.h"  /* For add_read_fd, delete_read_fd */
 #include "termopts.h"  /* For interrupt_input */
 #include "menu.h"  /* For MENU_KEYMAPS, MENU_FOR_CLICK, menu_items macros */
@@ -71,6 +73,11 @@ static void neomacs_hide_hourglass (struct frame *f);
 static void neomacs_compute_glyph_string_overhangs (struct glyph_string *s);
 static void neomacs_clear_under_internal_border (struct frame *f);
 static void neomacs_display_wakeup_handler (int fd, void *data);
+static void neomacs_set_hyphenation_dir (Lisp_Object dir);
+
+/* Check that the Rust library reads Emacs structs at the offsets this
+   Emacs has (defined in ffi/layout.rs).  */
+extern int neomacs_rust_check_abi (char *err_buf, size_t err_len);
 
 /* Rust layout engine FFI entry point (defined in layout/engine.rs via ffi.rs) */
 extern void neomacs_rust_layout_frame (void *display_handle, void *frame_ptr,
@@ -339,6 +346,12 @@ neomacs_open_display (const char *display_name)
 {
   struct neomacs_display_info *dpyinfo;
 
+  /* A display library built for another Emacs would read our structs
+     at the wrong offsets; refuse to start rather than corrupt memory.  */
+  char abi_error[1024];
+  if (!neomacs_rust_check_abi (abi_error, sizeof abi_error))
+    fatal ("%s", abi_error);
+
   dpyinfo = xzalloc (sizeof *dpyinfo);
   neomacs_initialize_display_info (dpyinfo);
 
@@ -382,6 +395,9 @@ neomacs_open_display (const char *display_name)
      frame sizing logic), NOT the desired window size.  The window
      will be resized when a frame is created with proper parameters.  */
 
+  /* Layout reads the hyphenation directory without running Lisp */
+  neomacs_set_hyphenation_dir (Vneomacs_hyphenation_directory);
+
   /* Add to display list */
   dpyinfo->next = neomacs_display_list;
   neomacs_display_list = dpyinfo;
@@ -786,6 +802,8 @@ neomacs_update_begin (struct frame *f)
 
       /* Use window-targeted begin_frame if we have a winit window */
       struct neomacs_output *output = FRAME_NEOMACS_OUTPUT (f);
+      if (output && output->title_pending)
+        neomacs_send_title (f);
       if (output && output->window_id > 0)
         neomacs_display_begin_frame_window (dpyinfo->display_handle, output->window_id,
                                             (float) FRAME_COLUMN_WIDTH (f),
@@ -1631,6 +1649,45 @@ neomacs_get_struct_offsets (struct neomacs_struct_offsets *out)
   out->pseudovector_flag = (size_t) PSEUDOVECTOR_FLAG;
 }
 
+/* Version of struct neomacs_struct_offsets.  Bump it together with
+   FFI_ABI_VERSION in emacs_types.rs whenever the struct changes.  */
+#define NEOMACS_FFI_ABI_VERSION 1
+
+/* This struct must match Rust's FfiAbi in emacs_types.rs exactly.  */
+struct neomacs_ffi_abi
+{
+  uint32_t version;
+  size_t offsets_size;
+  uint64_t schema_hash;
+};
+
+/* Describe our offset table to Rust, which checks it before asking for
+   the table itself, so a library built for another table fails with a
+   message instead of having neomacs_get_struct_offsets write past the
+   end of its copy.  SCHEMA_HASH is FNV-1a over the version, the table
+   size and the offsets, one 64-bit word at a time.  */
+void
+neomacs_ffi_abi (struct neomacs_ffi_abi *out)
+{
+  struct neomacs_struct_offsets off;
+  neomacs_get_struct_offsets (&off);
+
+  uint64_t words[2 + sizeof off / sizeof (size_t)];
+  words[0] = NEOMACS_FFI_ABI_VERSION;
+  words[1] = sizeof off;
+  size_t *fields = (size_t *) &off;
+  for (size_t i = 0; i < sizeof off / sizeof (size_t); i++)
+    words[2 + i] = fields[i];
+
+  uint64_t hash = 0xcbf29ce484222325;
+  for (size_t i = 0; i < ARRAYELTS (words); i++)
+    hash = (hash ^ words[i]) * 0x100000001b3;
+
+  out->version = NEOMACS_FFI_ABI_VERSION;
+  out->offsets_size = sizeof off;
+  out->schema_hash = hash;
+}
+
 /* Return the character position of a Lisp marker object.
    Used by Rust for indirect buffer marker fallback. */
 int64_t
@@ -1694,8 +1751,11 @@ struct neomacs_window_params_ffi {
   float extra_line_spacing;
   /* Whether to show cursor in non-selected windows */
   int cursor_in_non_selected;
-  /* selective-display: 0=off, >0=hide lines indented more than N columns */
+  /* selective-display: 0=off, >0=hide lines indented more than N
+     columns, -1 (t) = hide the rest of lines after a ^M */
   int selective_display;
+  /* selective-display-ellipses: show `...' for hidden lines */
+  int selective_display_ellipses;
   /* escape-glyph face foreground color for control chars */
   uint32_t escape_glyph_fg;
   /* nobreak-char-display: 0=off, 1=highlight, 2=escape notation */
@@ -1717,8 +1777,32 @@ struct neomacs_window_params_ffi {
   const char *buffer_file_name;
   /* Whether the buffer has unsaved modifications */
   int modified;
+  /* Vertical scroll bar area (frame-absolute x and width, 0 width if none) */
+  float scroll_bar_x;
+  float scroll_bar_width;
+  /* Buffer text plus overlay modification count */
+  int64_t modiff;
+  /* bidi-display-reordering (0 = off) */
+  int bidi_reordering;
+  /* bidi-paragraph-direction: 0 = per paragraph, 1 = left-to-right,
+     2 = right-to-left */
+  int bidi_paragraph_direction;
+  /* auto-hscroll-mode: 0 = off or suspended, 1 = on, 2 = current-line */
+  int auto_hscroll;
+  /* hscroll-margin in columns */
+  int hscroll_margin;
+  /* hscroll-step in columns, or as a fraction of the text area width
+     (negative when it is a number of columns) */
+  int hscroll_step;
+  float hscroll_step_fraction;
+  /* Smallest hscroll, set by scroll-left and scroll-right */
+  int min_hscroll;
 };
 
+static int neomacs_fill_window_params (struct frame *,
+                                       struct window *,
+                                       struct neomacs_window_params_ffi *);
+
 /* Get window parameters for the Nth leaf window.
    Returns 0 on success, -1 on error. */
 int
@@ -1771,8 +1855,14 @@ neomacs_layout_get_window_params (void *frame_ptr, int window_index,
   if (!found)
     return -1;
 
-  struct window *w = found;
+  return neomacs_fill_window_params (f, found, params);
+}
 
+/* Fill PARAMS for window W of frame F.  Returns 0.  */
+static int
+neomacs_fill_window_params (struct frame *f, struct window *w,
+                            struct neomacs_window_params_ffi *params)
+{
   /* Fill params */
   params->window_id = (int64_t)(intptr_t) w;
   params->window_ptr = (void *) w;
@@ -1793,6 +1883,7 @@ neomacs_layout_get_window_params (void *frame_ptr, int window_index,
       Lisp_Object fn = BVAR (buf, filename);
       params->buffer_file_name = STRINGP (fn) ? SSDATA (fn) : NULL;
       params->modified = (BUF_SAVE_MODIFF (buf) < BUF_MODIFF (buf)) ? 1 : 0;
+      params->modiff = BUF_MODIFF (buf) + BUF_OVERLAY_MODIFF (buf);
     }
   else
     {
@@ -1806,6 +1897,7 @@ neomacs_layout_get_window_params (void *frame_ptr, int window_index,
       params->word_wrap = 0;
       params->buffer_file_name = NULL;
       params->modified = 0;
+      params->modiff = 0;
     }
 
   params->x = (float) WINDOW_LEFT_EDGE_X (w);
@@ -1823,6 +1915,18 @@ neomacs_layout_get_window_params (void *frame_ptr, int window_index,
   params->left_margin_width = (float) WINDOW_LEFT_MARGIN_WIDTH (w);
   params->right_margin_width = (float) WINDOW_RIGHT_MARGIN_WIDTH (w);
 
+  /* Vertical scroll bar area, where scroll bar marks are drawn */
+  if (WINDOW_HAS_VERTICAL_SCROLL_BAR (w))
+    {
+      params->scroll_bar_x = (float) WINDOW_SCROLL_BAR_AREA_X (w);
+      params->scroll_bar_width = (float) WINDOW_SCROLL_BAR_AREA_WIDTH (w);
+    }
+  else
+    {
+      params->scroll_bar_x = 0;
+      params->scroll_bar_width = 0;
+    }
+
   params->selected = (w == XWINDOW (f->selected_window)) ? 1 : 0;
   params->is_minibuffer = MINI_WINDOW_P (w) ? 1 : 0;
 
@@ -2089,7 +2193,8 @@ neomacs_layout_get_window_params (void *frame_ptr, int window_index,
         }
     }
 
-  /* Extra line spacing: buffer-local or frame-level */
+  /* Extra line spacing: buffer-local or frame-level.  A float
+     `line-spacing' is a fraction of the frame's line height.  */
   params->extra_line_spacing = 0.0f;
   if (BUFFERP (w->contents))
     {
@@ -2097,7 +2202,8 @@ neomacs_layout_get_window_params (void *frame_ptr, int window_index,
       if (FIXNUMP (els))
         params->extra_line_spacing = (float) XFIXNUM (els);
       else if (FLOATP (els))
-        params->extra_line_spacing = (float) XFLOAT_DATA (els);
+        params->extra_line_spacing
+          = (float) (XFLOAT_DATA (els) * FRAME_LINE_HEIGHT (f));
     }
   if (params->extra_line_spacing == 0.0f)
     params->extra_line_spacing = (float) f->extra_line_spacing;
@@ -2166,15 +2272,53 @@ neomacs_layout_get_window_params (void *frame_ptr, int window_index,
       }
   }
 
-  /* selective-display: when a fixnum, hide lines indented deeper */
+  /* selective-display: when a fixnum, hide lines indented deeper;
+     when t, hide the rest of each line after a ^M */
   params->selective_display = 0;
+  params->selective_display_ellipses = 1;
   if (BUFFERP (w->contents))
     {
-      Lisp_Object sd = BVAR (XBUFFER (w->contents), selective_display);
+      struct buffer *b = XBUFFER (w->contents);
+      Lisp_Object sd = BVAR (b, selective_display);
       if (FIXNUMP (sd) && XFIXNUM (sd) > 0)
-        params->selective_display = (int) XFIXNUM (sd);
+        params->selective_display = (int) min (XFIXNUM (sd), INT_MAX);
+      else if (!NILP (sd) && !FIXNUMP (sd))
+        params->selective_display = -1;
+      params->selective_display_ellipses
+        = !NILP (BVAR (b, selective_display_ellipses));
     }
 
+  /* Bidi reordering and the paragraph direction it starts from */
+  params->bidi_reordering = 0;
+  params->bidi_paragraph_direction = 0;
+  if (BUFFERP (w->contents))
+    {
+      struct buffer *b = XBUFFER (w->contents);
+      Lisp_Object dir = BVAR (b, bidi_paragraph_direction);
+      params->bidi_reordering = !NILP (BVAR (b, bidi_display_reordering));
+      params->bidi_paragraph_direction
+        = EQ (dir, Qleft_to_right) ? 1 : EQ (dir, Qright_to_left) ? 2 : 0;
+    }
+
+  /* Automatic hscrolling, done by the layout engine since the glyph
+     rows hscroll_window_tree works from are never built */
+  params->auto_hscroll = 0;
+  if (BUFFERP (w->contents) && !w->suspend_auto_hscroll)
+    {
+      Lisp_Object mode = Fbuffer_local_value (Qauto_hscroll_mode,
+                                              w->contents);
+      params->auto_hscroll = (EQ (mode, Qcurrent_line) ? 2
+                              : !NILP (mode) ? 1 : 0);
+    }
+  params->hscroll_margin = clip_to_bounds (0, hscroll_margin, 1000000);
+  params->hscroll_step = 0;
+  params->hscroll_step_fraction = -1;
+  if (FLOATP (Vhscroll_step) && XFLOAT_DATA (Vhscroll_step) >= 0)
+    params->hscroll_step_fraction = XFLOAT_DATA (Vhscroll_step);
+  else if (TYPE_RANGED_FIXNUMP (int, Vhscroll_step))
+    params->hscroll_step = max (0, XFIXNUM (Vhscroll_step));
+  params->min_hscroll = w->min_hscroll;
+
   /* wrap-prefix and line-prefix (global variables, may also be per-char props) */
   params->wrap_prefix_len = 0;
   params->line_prefix_len = 0;
@@ -2246,6 +2390,40 @@ neomacs_layout_adjust_window_start (void *window_ptr, void *buffer_ptr,
   return (int64_t) new_start;
 }
 
+/* Set window start so that point's screen line is ROWS_ABOVE screen
+   lines below it.  Unlike neomacs_layout_adjust_window_start, this
+   counts screen lines with vmotion, so continuation lines and invisible
+   text are accounted for.  Returns the new window start.  */
+int64_t
+neomacs_layout_anchor_window_start (void *window_ptr, void *buffer_ptr,
+				    int64_t point, int rows_above)
+{
+  struct window *w = (struct window *) window_ptr;
+  struct buffer *buf = (struct buffer *) buffer_ptr;
+  if (!w || !buf)
+    return 1;
+
+  struct buffer *old = current_buffer;
+  set_buffer_internal_1 (buf);
+
+  ptrdiff_t pt = clip_to_bounds (BUF_BEGV (buf), point, BUF_ZV (buf));
+  struct position pos = *vmotion (pt, buf_charpos_to_bytepos (buf, pt),
+				  -max (rows_above, 0), w);
+
+  set_marker_restricted_both (w->start, w->contents,
+			      pos.bufpos, pos.bytepos);
+  w->start_at_line_beg = (pos.bufpos == BUF_BEGV (buf)
+			  || BUF_FETCH_BYTE (buf, pos.bytepos - 1) == '\n');
+  w->window_end_valid = false;
+
+  set_buffer_internal_1 (old);
+
+  nlog_debug ("anchor_window_start: point=%ld rows_above=%d new_start=%ld",
+	      (long) pt, rows_above, (long) pos.bufpos);
+
+  return (int64_t) pos.bufpos;
+}
+
 /* Set window_end_pos on an Emacs window. */
 void
 neomacs_layout_set_window_end (void *window_ptr, int64_t end_pos, int end_vpos)
@@ -2271,6 +2449,16 @@ neomacs_layout_set_cursor (void *window_ptr, int x, int y, int hpos, int vpos)
   w->cursor.vpos = vpos;
 }
 
+/* Set a window's hscroll, as automatic hscrolling moved it. */
+void
+neomacs_layout_set_hscroll (void *window_ptr, int hscroll)
+{
+  struct window *w = (struct window *) window_ptr;
+  if (!w)
+    return;
+  w->hscroll = max (0, hscroll);
+}
+
 /* Trigger fontification for the entire range [FROM, TO).
    Walks through the range checking the 'fontified text property,
    and calls fontification-functions at each unfontified gap.
@@ -2399,6 +2587,85 @@ neomacs_layout_check_invisible (void *buffer_ptr, void *window_ptr,
   return invis;
 }
 
+/* Check for a static composition (the `composition' text property set
+   by compose-region and prettify-symbols-mode) at CHARPOS.
+   Writes the characters the composed text is displayed as into STR_BUF
+   as UTF-8 and returns the number of bytes, with *END_OUT set to the
+   end of the composed text and *WIDTH_OUT to the columns it occupies.
+   Space components (padding between composition rules) are dropped.
+   Returns 0 if there is no valid composition at CHARPOS; *END_OUT is
+   then the position where the next one starts (or ZV).  */
+int
+neomacs_layout_composition_at (void *buffer_ptr, int64_t charpos,
+                               uint8_t *str_buf, int str_buf_len,
+                               int64_t *end_out, int *width_out)
+{
+  struct buffer *buf = (struct buffer *) buffer_ptr;
+  *width_out = 0;
+  if (!buf)
+    {
+      *end_out = charpos + 1;
+      return 0;
+    }
+
+  struct buffer *old = current_buffer;
+  set_buffer_internal_1 (buf);
+
+  ptrdiff_t zv = BUF_ZV (buf);
+  ptrdiff_t start, end;
+  Lisp_Object prop;
+  *end_out = zv;
+
+  if (charpos >= zv
+      || !find_composition (charpos, zv, &start, &end, &prop, Qnil))
+    {
+      set_buffer_internal_1 (old);
+      return 0;
+    }
+  if (start > charpos)
+    {
+      *end_out = start;
+      set_buffer_internal_1 (old);
+      return 0;
+    }
+
+  *end_out = end;
+  if (!composition_valid_p (start, end, prop))
+    {
+      set_buffer_internal_1 (old);
+      return 0;
+    }
+
+  ptrdiff_t id = (composition_registered_p (prop)
+                  ? COMPOSITION_ID (prop)
+                  : get_composition_id (start, CHAR_TO_BYTE (start),
+                                        end - start, prop, Qnil));
+  if (id < 0)
+    {
+      set_buffer_internal_1 (old);
+      return 0;
+    }
+
+  struct composition *cmp = composition_table[id];
+  int len = 0;
+  for (int i = 0; i < cmp->glyph_len; i++)
+    {
+      int c = COMPOSITION_GLYPH (cmp, i);
+      if (c == ' ' || c == '\t')
+        continue;
+      unsigned char tmp[MAX_MULTIBYTE_LENGTH];
+      int n = CHAR_STRING (c, tmp);
+      if (len + n > str_buf_len)
+        break;
+      memcpy (str_buf + len, tmp, n);
+      len += n;
+    }
+  *width_out = max (cmp->width, 1);
+
+  set_buffer_internal_1 (old);
+  return len;
+}
+
 /* Helper: fill a FaceDataFFI struct from a resolved Emacs face.
    The struct layout must match the Rust FaceDataFFI in emacs_ffi.rs. */
 struct FaceDataFFI {
@@ -2430,6 +2697,11 @@ struct FaceDataFFI {
   int font_descent;  /* FONT_DESCENT in pixels */
   int underline_position;  /* font->underline_position (>=1) */
   int underline_thickness;  /* font->underline_thickness (>=1) */
+  int text_shadow;  /* 1 if the text casts a shadow or glow */
+  uint32_t text_shadow_color;
+  int text_shadow_x_offset;
+  int text_shadow_y_offset;
+  int text_shadow_blur;
 };
 
 static void
@@ -2579,6 +2851,22 @@ fill_face_data (struct frame *f, struct face *face, struct FaceDataFFI *out)
       out->box_h_line_width = face->box_horizontal_line_width;
     }
 
+  /* Text shadow or glow */
+  out->text_shadow = face->text_shadow_p ? 1 : 0;
+  out->text_shadow_color = 0;
+  out->text_shadow_x_offset = 0;
+  out->text_shadow_y_offset = 0;
+  out->text_shadow_blur = 0;
+  if (face->text_shadow_p)
+    {
+      out->text_shadow_color = ((RED_FROM_ULONG (face->text_shadow_color) << 16) |
+                                (GREEN_FROM_ULONG (face->text_shadow_color) << 8) |
+                                BLUE_FROM_ULONG (face->text_shadow_color));
+      out->text_shadow_x_offset = face->text_shadow_x_offset;
+      out->text_shadow_y_offset = face->text_shadow_y_offset;
+      out->text_shadow_blur = face->text_shadow_blur;
+    }
+
   /* Extend: face background extends to end of visual line */
   out->extend = FACE_EXTENSIBLE_P (face) ? 1 : 0;
 
@@ -3236,6 +3524,7 @@ struct LineNumberConfigFFI {
   int minor_tick;  /* display-line-numbers-minor-tick */
   int current_absolute; /* show absolute for current line */
   int widen;       /* count from buffer BEG */
+  int grow_only;   /* display-line-numbers-grow-only */
 };
 
 /* Get line number display configuration for a window.
@@ -3279,8 +3568,22 @@ neomacs_layout_line_number_config (void *window_ptr,
     = !NILP (Vdisplay_line_numbers_current_absolute) ? 1 : 0;
   cfg->widen = display_line_numbers_widen ? 1 : 0;
 
-  /* Calculate width: digits needed for max line number.  */
-  ptrdiff_t max_line = buffer_zv;  /* conservative estimate */
+  Lisp_Object grow_only
+    = find_symbol_value (Qdisplay_line_numbers_grow_only);
+  cfg->grow_only = !BASE_EQ (grow_only, Qunbound) && !NILP (grow_only);
+
+  /* Calculate width: digits needed for the largest number shown, the
+     buffer's last line, or the window's height for numbers relative
+     to point.  */
+  ptrdiff_t max_line;
+  if (cfg->mode == 1 || cfg->current_absolute)
+    {
+      ptrdiff_t start = cfg->widen ? BUF_BEG_BYTE (buf) : BUF_BEGV_BYTE (buf);
+      ptrdiff_t end = cfg->widen ? BUF_Z_BYTE (buf) : BUF_ZV_BYTE (buf);
+      max_line = count_lines (start, end) + 1 + cfg->offset;
+    }
+  else
+    max_line = max_rows;
   int digits = 1;
   while (max_line >= 10)
     {
@@ -3293,8 +3596,8 @@ neomacs_layout_line_number_config (void *window_ptr,
       && XFIXNUM (Vdisplay_line_numbers_width) > digits)
     digits = XFIXNUM (Vdisplay_line_numbers_width);
 
-  /* Add 1 column for space padding after number.  */
-  cfg->width = digits + 1;
+  /* Add a blank column before and after the number.  */
+  cfg->width = digits + 2;
 
   set_buffer_internal_1 (old);
   return 0;
@@ -3406,6 +3709,9 @@ struct DisplayPropFFI {
    Handles:
      - String replacement: (put-text-property ... 'display "text")
      - Space spec: (put-text-property ... 'display (space :width N))
+     - Images, video and webkit views: (image :type png :file F) ...
+     - (when CONDITION . SPEC), and lists and vectors of specs, whose
+       first replacing spec wins
    Writes replacement string into str_buf (for type=1).
    Returns 0 on success. */
 int
@@ -3474,6 +3780,9 @@ neomacs_layout_check_display_prop (void *buffer_ptr, void *window_ptr,
       = Fnext_single_char_property_change (pos, Qdisplay, Qnil, limit);
   out->covers_to = FIXNUMP (next_change) ? XFIXNUM (next_change) : zv;
 
+  /* A replacing spec found inside `when', a list or a vector of specs
+     is parsed again from here, as if it were the whole property.  */
+ parse_spec:
   if (STRINGP (display_prop))
     {
       /* String replacement: 'display "text" or 'display #("text" 0 4 (face foo)) */
@@ -3506,6 +3815,17 @@ neomacs_layout_check_display_prop (void *buffer_ptr, void *window_ptr,
               uint32_t prev_fg = 0xFFFFFFFF;
               uint32_t prev_bg = 0xFFFFFFFF;
 
+              /* Faces of the string merge onto the face of the text
+                 it replaces, whatever form they take: a face name, a
+                 list of them or an attribute plist.  */
+              ptrdiff_t base_end;
+              int base_face
+                = face_at_buffer_position (sw, (ptrdiff_t) charpos,
+                                           &base_end, zv, false,
+                                           lookup_basic_face (sw, sf,
+                                                              DEFAULT_FACE_ID),
+                                           0);
+
               while (fcharpos < nchars && nruns < max_runs)
                 {
                   Lisp_Object face_prop
@@ -3521,10 +3841,11 @@ neomacs_layout_check_display_prop (void *buffer_ptr, void *window_ptr,
                   uint32_t fg = 0, bg = 0;
                   if (!NILP (face_prop))
                     {
-                      int rid = lookup_named_face (
-                          sw, sf,
-                          SYMBOLP (face_prop) ? face_prop : Qdefault,
-                          false);
+                      ptrdiff_t endpos;
+                      int rid = face_at_string_position (sw, display_prop,
+                                                         fcharpos, charpos,
+                                                         &endpos, base_face,
+                                                         false, 0);
                       if (rid >= 0)
                         {
                           struct face *rf
@@ -3632,30 +3953,11 @@ neomacs_layout_check_display_prop (void *buffer_ptr, void *window_ptr,
 
           /* Evaluate condition in a safe way */
           Lisp_Object result = safe_eval (condition);
-          if (!NILP (result))
+          if (!NILP (result) && !NILP (rest))
             {
-              /* Condition true — recursively process remaining spec.
-                 The spec is (SPEC) or SPEC, depending on form. */
-              if (CONSP (rest))
-                {
-                  Lisp_Object inner_spec = XCAR (rest);
-                  /* Re-check with inner spec as if it were the
-                     display property */
-                  if (STRINGP (inner_spec))
-                    {
-                      ptrdiff_t len = SBYTES (inner_spec);
-                      ptrdiff_t copy_len
-                        = len < str_buf_len - 1
-                          ? len : str_buf_len - 1;
-                      memcpy (str_buf, SDATA (inner_spec),
-                              copy_len);
-                      str_buf[copy_len] = 0;
-                      out->type = 1;
-                      out->str_len = (int) copy_len;
-                      set_buffer_internal_1 (old);
-                      return 0;
-                    }
-                }
+              /* Condition true: REST is the spec, any kind of it */
+              display_prop = rest;
+              goto parse_spec;
             }
           /* Condition false or no spec — skip */
           set_buffer_internal_1 (old);
@@ -3917,14 +4219,18 @@ neomacs_layout_check_display_prop (void *buffer_ptr, void *window_ptr,
               struct neomacs_display_info *dpyinfo = FRAME_NEOMACS_DISPLAY_INFO (f);
               if (dpyinfo && dpyinfo->display_handle)
                 {
-                  /* Get face for this position (needed by lookup_image for fg/bg) */
-                  int face_id = DEFAULT_FACE_ID;
-                  if (w)
-                    {
-                      face_id = lookup_named_face (w, f, Qdefault, false);
-                      if (face_id < 0)
-                        face_id = DEFAULT_FACE_ID;
-                    }
+                  /* The face of the text the image replaces gives
+                     lookup_image its fg/bg, as for monochrome and SVG
+                     images */
+                  ptrdiff_t face_end;
+                  int face_id
+                    = face_at_buffer_position (w, (ptrdiff_t) charpos,
+                                               &face_end, zv, false,
+                                               lookup_basic_face (w, f,
+                                                                  DEFAULT_FACE_ID),
+                                               0);
+                  if (face_id < 0)
+                    face_id = DEFAULT_FACE_ID;
 
                   ptrdiff_t img_id = lookup_image (f, display_prop, face_id);
                   if (img_id >= 0)
@@ -3993,7 +4299,8 @@ neomacs_layout_check_display_prop (void *buffer_ptr, void *window_ptr,
       /* If car is not a known keyword, treat as list of specs.
          Each element is a single spec like (raise 0.3) or
          (height 0.7). Process all non-replacing specs, stop
-         at first replacing spec (string/image/space). */
+         at first replacing spec (string/image/space/media),
+         which is parsed as if it were the whole property. */
       if (!EQ (car, Qspace) && !EQ (car, Qimage) && !EQ (car, Qraise)
           && !EQ (car, Qleft_fringe) && !EQ (car, Qright_fringe)
           && !EQ (car, Qvideo) && !EQ (car, Qwebkit)
@@ -4008,15 +4315,8 @@ neomacs_layout_check_display_prop (void *buffer_ptr, void *window_ptr,
               /* String replacement in list form */
               if (STRINGP (spec))
                 {
-                  ptrdiff_t len = SBYTES (spec);
-                  ptrdiff_t copy_len
-                    = len < str_buf_len - 1 ? len : str_buf_len - 1;
-                  memcpy (str_buf, SDATA (spec), copy_len);
-                  str_buf[copy_len] = 0;
-                  out->type = 1;
-                  out->str_len = (int) copy_len;
-                  set_buffer_internal_1 (old);
-                  return 0;
+                  display_prop = spec;
+                  goto parse_spec;
                 }
 
               if (!CONSP (spec))
@@ -4024,6 +4324,15 @@ neomacs_layout_check_display_prop (void *buffer_ptr, void *window_ptr,
 
               Lisp_Object scar = XCAR (spec);
 
+              /* Replacing specs and conditional ones */
+              if (EQ (scar, Qspace) || EQ (scar, Qimage)
+                  || EQ (scar, Qvideo) || EQ (scar, Qwebkit)
+                  || EQ (scar, Qwhen))
+                {
+                  display_prop = spec;
+                  goto parse_spec;
+                }
+
               /* (raise FACTOR) — accumulate */
               if (EQ (scar, Qraise) && CONSP (XCDR (spec)))
                 {
@@ -4046,25 +4355,6 @@ neomacs_layout_check_display_prop (void *buffer_ptr, void *window_ptr,
                   continue;
                 }
 
-              /* (space ...) — replacing spec, stop.
-                 Extract basic :width for the space. */
-              if (EQ (scar, Qspace))
-                {
-                  Lisp_Object plist = XCDR (spec);
-                  Lisp_Object width_val
-                    = Fplist_get (plist, QCwidth, Qnil);
-                  if (FIXNUMP (width_val))
-                    out->space_width
-                      = (float) XFIXNUM (width_val);
-                  else if (FLOATP (width_val))
-                    out->space_width
-                      = (float) XFLOAT_DATA (width_val);
-                  else
-                    out->space_width = 1.0;
-                  out->type = 2;
-                  set_buffer_internal_1 (old);
-                  return 0;
-                }
             }
           /* Processed all list elements.
              If any non-replacing effects found, use type 5
@@ -4089,11 +4379,23 @@ neomacs_layout_check_display_prop (void *buffer_ptr, void *window_ptr,
       for (ptrdiff_t vi = 0; vi < vlen; vi++)
         {
           Lisp_Object spec = AREF (display_prop, vi);
+          if (STRINGP (spec))
+            {
+              display_prop = spec;
+              goto parse_spec;
+            }
           if (!CONSP (spec))
             continue;
           Lisp_Object scar = XCAR (spec);
 
-          if (EQ (scar, Qraise) && CONSP (XCDR (spec)))
+          if (EQ (scar, Qspace) || EQ (scar, Qimage)
+              || EQ (scar, Qvideo) || EQ (scar, Qwebkit)
+              || EQ (scar, Qwhen))
+            {
+              display_prop = spec;
+              goto parse_spec;
+            }
+          else if (EQ (scar, Qraise) && CONSP (XCDR (spec)))
             {
               Lisp_Object factor = XCAR (XCDR (spec));
               if (FIXNUMP (factor))
@@ -4123,6 +4425,254 @@ neomacs_layout_check_display_prop (void *buffer_ptr, void *window_ptr,
   return 0;
 }
 
+/* ========================================================================
+   Frame description: one call per redisplay
+   ======================================================================== */
+
+/* Layout reads everything it needs up front for all windows of a frame
+   with neomacs_layout_describe_frame: the window parameters, the faces
+   of the visible text and the ranges of it with a `display' property.
+   The engine looks faces and display properties up there and only
+   calls back for positions outside the described text.  These structs
+   must match layout/frame_desc.rs exactly.  */
+
+struct neomacs_frame_desc
+{
+  uint32_t window_count;
+  uint32_t face_span_count;
+  uint32_t prop_range_count;
+  uint32_t reserved;
+  /* Byte offsets of the arrays in the blob, and its whole size */
+  uint64_t windows_offset;
+  uint64_t face_spans_offset;
+  uint64_t prop_ranges_offset;
+  uint64_t size;
+};
+
+struct neomacs_window_desc
+{
+  struct neomacs_window_params_ffi params;
+  /* The display ranges cover [DESCRIBED_FROM, DESCRIBED_TO); the face
+     spans say what they cover */
+  int64_t described_from;
+  int64_t described_to;
+  uint32_t first_face_span;
+  uint32_t face_span_count;
+  uint32_t first_prop_range;
+  uint32_t prop_range_count;
+};
+
+/* What neomacs_layout_face_at_pos returns for every position in
+   [START, END).  The font family is copied into FONT_FAMILY, since
+   the Lisp string FACE.font_family would point into may be moved by
+   GC before layout reads it.  */
+struct neomacs_face_span
+{
+  int64_t start;
+  int64_t end;
+  int32_t face_id;
+  int32_t reserved;
+  struct FaceDataFFI face;
+  char font_family[64];
+};
+
+/* Text in [START, END) has a non-nil `display' property.  */
+struct neomacs_prop_range
+{
+  int64_t start;
+  int64_t end;
+};
+
+/* Most face spans and display ranges described per window.  */
+#define FRAME_DESC_MAX_SPANS 4096
+
+/* Append SIZE bytes of SRC at *USED in the CAP-byte ARENA if they fit,
+   and advance *USED either way, so a blob too large for the arena is
+   still measured.  */
+static void
+frame_desc_put (uint8_t *arena, size_t cap, size_t *used,
+                const void *src, size_t size)
+{
+  if (arena && *used + size <= cap)
+    memcpy (arena + *used, src, size);
+  *used += size;
+}
+
+/* The leaf windows of F, then its minibuffer window if it owns one, in
+   the order of neomacs_layout_get_window_params.  Returns how many, at
+   most MAX.  */
+static int
+frame_desc_windows (struct frame *f, struct window **out, int max)
+{
+  int count = 0;
+  struct window *stack[64];
+  int sp = 0;
+  stack[sp++] = XWINDOW (FRAME_ROOT_WINDOW (f));
+  while (sp > 0 && count < max)
+    {
+      struct window *w = stack[--sp];
+      if (WINDOWP (w->contents))
+        {
+          struct window *child = XWINDOW (w->contents);
+          while (child)
+            {
+              if (sp < 64)
+                stack[sp++] = child;
+              child = NILP (child->next) ? NULL : XWINDOW (child->next);
+            }
+        }
+      else
+        out[count++] = w;
+    }
+  Lisp_Object mini = FRAME_MINIBUF_WINDOW (f);
+  if (count < max && !NILP (mini) && FRAME_HAS_MINIBUF_P (f))
+    out[count++] = XWINDOW (mini);
+  return count;
+}
+
+/* Describe frame FRAME_PTR into the CAP-byte ARENA (8-byte aligned):
+   a struct neomacs_frame_desc, then its windows, face spans and
+   display ranges.  Visible text is fontified first, as layout would.
+   Returns the size of the description, which was only written if it
+   is at most CAP; call again with a larger arena if not.  Returns -1
+   on error.  */
+int64_t
+neomacs_layout_describe_frame (void *frame_ptr, uint8_t *arena, size_t cap)
+{
+  struct frame *f = (struct frame *) frame_ptr;
+  if (!f)
+    return -1;
+
+  struct window *windows[256];
+  int nwindows = frame_desc_windows (f, windows, 256);
+  struct neomacs_frame_desc head = { 0 };
+  size_t used = 0;
+  frame_desc_put (arena, cap, &used, &head, sizeof head);
+
+  /* The text each window will show: as many characters from its start
+     as layout reads for it */
+  ptrdiff_t from[256], to[256];
+  for (int i = 0; i < nwindows; i++)
+    {
+      struct window *w = windows[i];
+      from[i] = to[i] = 0;
+      if (!BUFFERP (w->contents))
+        continue;
+      struct buffer *buf = XBUFFER (w->contents);
+      from[i] = clip_to_bounds (BUF_BEGV (buf), marker_position (w->start),
+                                BUF_ZV (buf));
+      to[i] = min (BUF_ZV (buf),
+                   from[i] + ((ptrdiff_t) WINDOW_TOTAL_COLS (w)
+                              * WINDOW_TOTAL_LINES (w) * 2));
+      neomacs_layout_ensure_fontified (buf, from[i], to[i]);
+    }
+
+  /* Window descriptions, filled in once the spans are known */
+  USE_SAFE_ALLOCA;
+  struct neomacs_window_desc *descs;
+  SAFE_NALLOCA (descs, 1, nwindows);
+  memset (descs, 0, nwindows * sizeof *descs);
+  for (int i = 0; i < nwindows; i++)
+    {
+      neomacs_fill_window_params (f, windows[i], &descs[i].params);
+      descs[i].described_from = from[i];
+      descs[i].described_to = to[i];
+    }
+  head.window_count = nwindows;
+  head.windows_offset = used;
+  used += nwindows * sizeof *descs;
+
+  /* Face spans, as neomacs_layout_face_at_pos would return them */
+  head.face_spans_offset = used;
+  for (int i = 0; i < nwindows; i++)
+    {
+      struct window *w = windows[i];
+      descs[i].first_face_span = head.face_span_count;
+      if (!BUFFERP (w->contents))
+        continue;
+      struct buffer *buf = XBUFFER (w->contents);
+      struct buffer *old = current_buffer;
+      set_buffer_internal_1 (buf);
+      int base_face = lookup_basic_face (w, f, DEFAULT_FACE_ID);
+      uint32_t n = 0;
+      for (ptrdiff_t pos = from[i]; pos < to[i] && n < FRAME_DESC_MAX_SPANS; )
+        {
+          ptrdiff_t next_check = 0;
+          int face_id = face_at_buffer_position (w, pos, &next_check,
+                                                 BUF_ZV (buf), false,
+                                                 base_face, 0);
+          struct face *face = FACE_FROM_ID_OR_NULL (f, face_id);
+          if (!face)
+            face = FACE_FROM_ID_OR_NULL (f, base_face);
+          ptrdiff_t end = next_check > pos ? next_check : pos + 1;
+          if (face)
+            {
+              struct neomacs_face_span span;
+              memset (&span, 0, sizeof span);
+              span.start = pos;
+              span.end = end;
+              span.face_id = face_id;
+              fill_face_data (f, face, &span.face);
+              if (span.face.font_family)
+                snprintf (span.font_family, sizeof span.font_family, "%s",
+                          span.face.font_family);
+              span.face.font_family = NULL;
+              frame_desc_put (arena, cap, &used, &span, sizeof span);
+              n++;
+            }
+          pos = end;
+        }
+      set_buffer_internal_1 (old);
+      descs[i].face_span_count = n;
+      head.face_span_count += n;
+    }
+
+  /* Ranges of the text with a display property */
+  head.prop_ranges_offset = used;
+  for (int i = 0; i < nwindows; i++)
+    {
+      struct window *w = windows[i];
+      descs[i].first_prop_range = head.prop_range_count;
+      if (!BUFFERP (w->contents))
+        continue;
+      struct buffer *old = current_buffer;
+      set_buffer_internal_1 (XBUFFER (w->contents));
+      Lisp_Object limit = make_fixnum (to[i]);
+      uint32_t n = 0;
+      ptrdiff_t pos = from[i];
+      while (pos < to[i] && n < FRAME_DESC_MAX_SPANS)
+        {
+          Lisp_Object here = make_fixnum (pos);
+          Lisp_Object next
+            = Fnext_single_char_property_change (here, Qdisplay, Qnil, limit);
+          ptrdiff_t end = (FIXNUMP (next) && XFIXNUM (next) > pos
+                           ? XFIXNUM (next) : to[i]);
+          if (!NILP (Fget_char_property (here, Qdisplay, Qnil)))
+            {
+              struct neomacs_prop_range range = { pos, end };
+              frame_desc_put (arena, cap, &used, &range, sizeof range);
+              n++;
+            }
+          pos = end;
+        }
+      /* Past the last range looked at, text is not known to be clear
+         of display properties */
+      descs[i].described_to = pos;
+      set_buffer_internal_1 (old);
+      descs[i].prop_range_count = n;
+      head.prop_range_count += n;
+    }
+
+  head.size = used;
+  if (arena && used <= cap)
+    {
+      memcpy (arena, &head, sizeof head);
+      memcpy (arena + head.windows_offset, descs, nwindows * sizeof *descs);
+    }
+  SAFE_FREE ();
+  return used;
+}
+
 /* Resolve align-to value from a (space :align-to SPEC) display property.
    Returns the align-to column value, or -1 if not a space align-to spec.
    Handles simple integers/floats (column values), (N) pixel forms,
@@ -4925,25 +5475,30 @@ neomacs_layout_margin_strings_at (void *buffer_ptr, void *window_ptr,
   return 0;
 }
 
-/* Check line-height and line-spacing text properties at CHARPOS.
-   Returns extra height (in pixels) beyond BASE_HEIGHT that should
-   be added to the row containing this position.
+/* Check line-height and line-spacing text properties at CHARPOS, the
+   newline ending a line.  As in Emacs, a taller line-height adds space
+   above the line's text, so the text sits at the bottom of the taller
+   line, and line-spacing adds space below it.
    line-height can be: integer (pixel height), float (multiplier).
-   line-spacing can be: integer (extra pixels), float (fraction of height). */
+   line-spacing can be: integer (extra pixels), float (fraction of height).
+   *EXTRA_ABOVE_OUT and *EXTRA_BELOW_OUT receive the extra pixels beyond
+   BASE_HEIGHT.  */
 int
 neomacs_layout_check_line_spacing (void *buffer_ptr, void *window_ptr,
                                    int64_t charpos, float base_height,
-                                   float *extra_height_out)
+                                   float *extra_above_out,
+                                   float *extra_below_out)
 {
   struct buffer *buf = (struct buffer *) buffer_ptr;
   struct window *w = (struct window *) window_ptr;
-  if (!buf || !w || !extra_height_out)
+  if (!buf || !w || !extra_above_out || !extra_below_out)
     return -1;
 
   struct buffer *old = current_buffer;
   set_buffer_internal_1 (buf);
 
-  *extra_height_out = 0.0f;
+  *extra_above_out = 0.0f;
+  *extra_below_out = 0.0f;
 
   ptrdiff_t zv = BUF_ZV (buf);
   if (charpos < BUF_BEGV (buf) || charpos >= zv)
@@ -4962,13 +5517,13 @@ neomacs_layout_check_line_spacing (void *buffer_ptr, void *window_ptr,
         {
           int h = XFIXNUM (line_height);
           if ((float) h > base_height)
-            *extra_height_out += (float) h - base_height;
+            *extra_above_out = (float) h - base_height;
         }
       else if (FLOATP (line_height))
         {
           float h = (float) (XFLOAT_DATA (line_height) * (double) base_height);
           if (h > base_height)
-            *extra_height_out += h - base_height;
+            *extra_above_out = h - base_height;
         }
     }
 
@@ -4980,13 +5535,13 @@ neomacs_layout_check_line_spacing (void *buffer_ptr, void *window_ptr,
         {
           int s = XFIXNUM (line_spacing);
           if (s > 0)
-            *extra_height_out += (float) s;
+            *extra_below_out = (float) s;
         }
       else if (FLOATP (line_spacing))
         {
           float s = (float) (XFLOAT_DATA (line_spacing) * (double) base_height);
           if (s > 0.0f)
-            *extra_height_out += s;
+            *extra_below_out = s;
         }
     }
 
@@ -4994,107 +5549,364 @@ neomacs_layout_check_line_spacing (void *buffer_ptr, void *window_ptr,
   return 0;
 }
 
-/* Check line-prefix and wrap-prefix text properties at a position.
-   Returns the prefix as a space width in columns.
-   prefix_type: 0=line-prefix, 1=wrap-prefix
-   Returns 0 on success, sets *width_out to the prefix width.
-   If no prefix text property, *width_out = -1.0 (use window default). */
+/* Pixel width of the space spec SPEC used as a line prefix in window
+   W: `(space :width N)' or `(space :align-to N)' with N in columns, or
+   (N) in pixels.  The prefix starts at the left of the text area, so
+   :align-to is a width too.  -1 if SPEC is not a space spec.  */
+static float
+neomacs_prefix_space_width (struct window *w, Lisp_Object spec)
+{
+  if (!CONSP (spec) || !EQ (XCAR (spec), Qspace))
+    return -1.0f;
+
+  Lisp_Object plist = XCDR (spec);
+  Lisp_Object width = plist_get (plist, QCwidth);
+  if (NILP (width))
+    width = plist_get (plist, QCalign_to);
+
+  float col_w = (float) FRAME_COLUMN_WIDTH (XFRAME (WINDOW_FRAME (w)));
+  if (FIXNUMP (width))
+    return (float) XFIXNUM (width) * col_w;
+  if (FLOATP (width))
+    return (float) XFLOAT_DATA (width) * col_w;
+  if (CONSP (width) && FIXNUMP (XCAR (width)))
+    return (float) XFIXNUM (XCAR (width));
+  if (CONSP (width) && FLOATP (XCAR (width)))
+    return (float) XFLOAT_DATA (XCAR (width));
+  return 0.0f;
+}
+
+/* Resolve the line prefix of the row starting at CHARPOS, as in
+   Emacs: a `line-prefix' (PREFIX_TYPE 0) or `wrap-prefix' (PREFIX_TYPE
+   1) text or overlay property, else the buffer's `line-prefix' or
+   `wrap-prefix' variable.  A line prefix asked for in the middle of a
+   line is a wrap prefix.  Returns the kind of prefix:
+     0  none;
+     1  a string, whose bytes go to STR_BUF and length to *STR_LEN_OUT;
+     2  blank space, *WIDTH_OUT pixels wide (space specs, and strings
+        displayed as one);
+     3  a hanging indent (`neomacs-hanging-indent' set and no wrap
+        prefix): the text of the line up to its newline goes to
+        STR_BUF, and *WIDTH_OUT is the extra columns to indent by.  */
 int
 neomacs_layout_check_line_prefix (void *buffer_ptr, void *window_ptr,
                                   int64_t charpos, int prefix_type,
-                                  float *width_out)
+                                  uint8_t *str_buf, int str_buf_len,
+                                  int *str_len_out, float *width_out)
 {
   struct buffer *buf = (struct buffer *) buffer_ptr;
   struct window *w = (struct window *) window_ptr;
-  if (!buf || !w || !width_out)
-    return -1;
+  if (!buf || !w || !str_buf || str_buf_len <= 0 || !str_len_out
+      || !width_out)
+    return 0;
 
-  *width_out = -1.0f; /* no override */
+  *str_len_out = 0;
+  *width_out = 0.0f;
 
   struct buffer *old_buf = current_buffer;
   set_buffer_internal_1 (buf);
 
-  ptrdiff_t zv = BUF_ZV (buf);
-  if (charpos < BUF_BEGV (buf) || charpos >= zv)
+  if (charpos < BUF_BEGV (buf) || charpos > BUF_ZV (buf))
     {
       set_buffer_internal_1 (old_buf);
       return 0;
     }
 
-  Lisp_Object pos = make_fixnum (charpos);
-  Lisp_Object prop_sym = prefix_type == 0 ? Qline_prefix : Qwrap_prefix;
-  Lisp_Object prefix = Fget_char_property (pos, prop_sym, Qnil);
+  bool line_start = (charpos == BUF_BEGV (buf)
+                     || FETCH_CHAR (CHAR_TO_BYTE (charpos - 1)) == '\n');
+  if (prefix_type == 0 && !line_start)
+    prefix_type = 1;
 
+  Lisp_Object window;
+  XSETWINDOW (window, w);
+  Lisp_Object prop_sym = prefix_type == 0 ? Qline_prefix : Qwrap_prefix;
+  Lisp_Object prefix = (charpos < BUF_ZV (buf)
+                        ? Fget_char_property (make_fixnum (charpos),
+                                              prop_sym, window)
+                        : Qnil);
   if (NILP (prefix))
-    {
-      set_buffer_internal_1 (old_buf);
-      return 0;
-    }
+    prefix = prefix_type == 0 ? Vline_prefix : Vwrap_prefix;
 
-  /* Evaluate the prefix spec to get a width in columns. */
-  if (STRINGP (prefix))
+  int kind = 0;
+  float space_w = neomacs_prefix_space_width (w, prefix);
+  if (space_w >= 0.0f)
     {
-      /* String prefix: width = string length */
-      *width_out = (float) SCHARS (prefix);
+      *width_out = space_w;
+      kind = 2;
     }
-  else if (CONSP (prefix) && EQ (XCAR (prefix), Qspace))
+  else if (STRINGP (prefix) && SCHARS (prefix) > 0)
     {
-      /* (space :width N) or (space :width (N)) */
-      Lisp_Object plist = XCDR (prefix);
-      Lisp_Object width_val = Fplist_get (plist, QCwidth, Qnil);
-      if (FIXNUMP (width_val))
-        *width_out = (float) XFIXNUM (width_val);
-      else if (FLOATP (width_val))
-        *width_out = (float) XFLOAT_DATA (width_val);
-      else if (CONSP (width_val))
+      /* A string displayed as a space, as `visual-wrap-prefix-mode'
+         makes them */
+      Lisp_Object display = Fget_text_property (make_fixnum (0), Qdisplay,
+                                                prefix);
+      space_w = neomacs_prefix_space_width (w, display);
+      if (space_w >= 0.0f)
         {
-          /* (N) — pixel width, convert to columns */
-          Lisp_Object n = XCAR (width_val);
-          float pixel_w = 0;
-          if (FIXNUMP (n))
-            pixel_w = (float) XFIXNUM (n);
-          else if (FLOATP (n))
-            pixel_w = (float) XFLOAT_DATA (n);
-          float col_w = (float) FRAME_COLUMN_WIDTH (
-              XFRAME (WINDOW_FRAME (w)));
-          if (col_w > 0)
-            *width_out = pixel_w / col_w;
+          *width_out = space_w;
+          kind = 2;
         }
       else
-        *width_out = 0;
+        {
+          ptrdiff_t len = min (SBYTES (prefix), str_buf_len);
+          memcpy (str_buf, SDATA (prefix), len);
+          *str_len_out = (int) len;
+          kind = 1;
+        }
+    }
+  else if (NILP (prefix) && prefix_type == 1
+           && !NILP (Vneomacs_hanging_indent))
+    {
+      ptrdiff_t pos = find_newline_no_quit (charpos, CHAR_TO_BYTE (charpos),
+                                            -1, NULL);
+      ptrdiff_t pos_byte = CHAR_TO_BYTE (pos);
+      ptrdiff_t end_byte = min (CHAR_TO_BYTE (BUF_ZV (buf)),
+                                pos_byte + str_buf_len);
+      int len = 0;
+      while (pos_byte + len < end_byte
+             && FETCH_BYTE (pos_byte + len) != '\n')
+        len++;
+      /* Don't cut a multibyte character in two */
+      while (len > 0 && pos_byte + len < end_byte
+             && !CHAR_HEAD_P (FETCH_BYTE (pos_byte + len)))
+        len--;
+      for (int i = 0; i < len; i++)
+        str_buf[i] = FETCH_BYTE (pos_byte + i);
+      *str_len_out = len;
+      *width_out = (FIXNATP (Vneomacs_hanging_indent)
+                    ? (float) XFIXNAT (Vneomacs_hanging_indent) : 0.0f);
+      kind = 3;
     }
 
   set_buffer_internal_1 (old_buf);
-  return 0;
+  return kind;
 }
 
-/* Called at the end of updating a frame */
-void
-neomacs_update_end (struct frame *f)
+/* Alignment of the line starting at CHARPOS, from its `paragraph-align'
+   text or overlay property: 0 for `left' or none, 1 for `right', 2 for
+   `center' and 3 for `full'.  */
+int
+neomacs_layout_paragraph_align (void *buffer_ptr, void *window_ptr,
+                                int64_t charpos)
 {
-  struct neomacs_display_info *dpyinfo = FRAME_NEOMACS_DISPLAY_INFO (f);
-  struct neomacs_output *output = FRAME_NEOMACS_OUTPUT (f);
+  struct buffer *buf = (struct buffer *) buffer_ptr;
+  struct window *w = (struct window *) window_ptr;
+  if (!buf || !w)
+    return 0;
 
-  if (dpyinfo && dpyinfo->display_handle)
+  struct buffer *old_buf = current_buffer;
+  set_buffer_internal_1 (buf);
+
+  int align = 0;
+  if (charpos >= BUF_BEGV (buf) && charpos < BUF_ZV (buf))
     {
-      /* Rust layout engine reads buffer data directly via FFI
-         and produces FrameGlyphBuffer internally. */
-      struct face *default_face = FACE_FROM_ID_OR_NULL (f, DEFAULT_FACE_ID);
-      unsigned long bg_pixel = default_face && !default_face->background_defaulted_p
-        ? default_face->background : FRAME_BACKGROUND_PIXEL (f);
-      uint32_t bg_rgb = ((RED_FROM_ULONG (bg_pixel) << 16)
-                         | (GREEN_FROM_ULONG (bg_pixel) << 8)
-                         | BLUE_FROM_ULONG (bg_pixel));
+      Lisp_Object window;
+      XSETWINDOW (window, w);
+      Lisp_Object value = Fget_char_property (make_fixnum (charpos),
+                                              Qparagraph_align, window);
+      if (EQ (value, Qright))
+        align = 1;
+      else if (EQ (value, Qcenter))
+        align = 2;
+      else if (EQ (value, Qfull))
+        align = 3;
+    }
 
-      /* Get vertical border face color */
-      struct face *vborder_face = FACE_FROM_ID_OR_NULL (f, VERTICAL_BORDER_FACE_ID);
-      unsigned long vb_fg = vborder_face ? vborder_face->foreground : 0;
-      uint32_t vb_rgb = ((RED_FROM_ULONG (vb_fg) << 16)
-                         | (GREEN_FROM_ULONG (vb_fg) << 8)
-                         | BLUE_FROM_ULONG (vb_fg));
+  set_buffer_internal_1 (old_buf);
+  return align;
+}
 
-      /* Window divider face colors */
-      int rdw = FRAME_RIGHT_DIVIDER_WIDTH (f);
+/* Whether WINDOW_PTR shows its text in vertical columns, from its
+   `vertical-writing' window parameter.  */
+int
+neomacs_layout_vertical_writing (void *window_ptr)
+{
+  struct window *w = (struct window *) window_ptr;
+  if (!w)
+    return 0;
+
+  Lisp_Object window;
+  XSETWINDOW (window, w);
+  return !NILP (Fwindow_parameter (window, Qvertical_writing));
+}
+
+/* A highlighted span of a buffer.  Must match RegionSpanFFI in
+   layout/emacs_ffi.rs.  */
+struct RegionSpanFFI
+{
+  int64_t start;
+  int64_t end;
+  int kind;
+  uint32_t bg;
+  uint32_t fg;
+  int has_fg;
+  int extend;
+};
+
+/* Fill *SPAN with START..END of kind KIND drawn in FACE as realized
+   for window W.  Returns false if there is nothing to draw.  */
+static bool
+neomacs_region_span (struct window *w, Lisp_Object face, ptrdiff_t start,
+                     ptrdiff_t end, int kind, struct RegionSpanFFI *span)
+{
+  struct frame *f = XFRAME (w->frame);
+  if (start >= end)
+    return false;
+  int face_id = lookup_named_face (w, f, face, false);
+  struct face *pface = face_id >= 0 ? FACE_FROM_ID_OR_NULL (f, face_id) : NULL;
+  if (!pface)
+    return false;
+
+  unsigned long bg = pface->background;
+  unsigned long fg = pface->foreground;
+  span->start = start;
+  span->end = end;
+  span->kind = kind;
+  span->bg = (uint32_t) ((RED_FROM_ULONG (bg) << 16)
+                         | (GREEN_FROM_ULONG (bg) << 8)
+                         | BLUE_FROM_ULONG (bg));
+  span->fg = (uint32_t) ((RED_FROM_ULONG (fg) << 16)
+                         | (GREEN_FROM_ULONG (fg) << 8)
+                         | BLUE_FROM_ULONG (fg));
+  /* The text keeps its colors unless the face sets a foreground */
+  Lisp_Object attr = Finternal_get_lisp_face_attribute (face, QCforeground,
+                                                         w->frame);
+  span->has_fg = !NILP (attr) && !EQ (attr, Qunspecified);
+  attr = Finternal_get_lisp_face_attribute (face, QCextend, w->frame);
+  span->extend = !NILP (attr) && !EQ (attr, Qunspecified);
+  return true;
+}
+
+/* Highlighted spans of BUFFER_PTR shown in WINDOW_PTR, for the Rust
+   layout engine: the secondary selection, from `mouse-secondary-overlay',
+   then the active region, which `redisplay-highlight-region-function'
+   leaves in the window's `neomacs-region' parameter instead of an
+   overlay when the engine draws it (see neomacs-win.el).  Writes up to
+   MAX_SPANS entries into SPANS_PTR and returns how many it wrote.  */
+int
+neomacs_layout_region_spans (void *window_ptr, void *buffer_ptr,
+                             void *spans_ptr, int max_spans)
+{
+  struct window *w = (struct window *) window_ptr;
+  struct buffer *buf = (struct buffer *) buffer_ptr;
+  struct RegionSpanFFI *spans = (struct RegionSpanFFI *) spans_ptr;
+  if (!w || !buf || !spans || max_spans <= 0)
+    return 0;
+
+  int n = 0;
+  Lisp_Object secondary = find_symbol_value (Qmouse_secondary_overlay);
+  if (OVERLAYP (secondary) && OVERLAY_BUFFER (secondary) == buf
+      && neomacs_region_span (w, Qsecondary_selection,
+                              OVERLAY_START (secondary),
+                              OVERLAY_END (secondary), 1, &spans[n]))
+    n++;
+
+  Lisp_Object window;
+  XSETWINDOW (window, w);
+  Lisp_Object region = Fwindow_parameter (window, Qneomacs_region);
+  if (n < max_spans && CONSP (region)
+      && FIXNUMP (XCAR (region)) && FIXNUMP (XCDR (region)))
+    {
+      ptrdiff_t a = clip_to_bounds (BUF_BEGV (buf), XFIXNUM (XCAR (region)),
+                                    BUF_ZV (buf));
+      ptrdiff_t b = clip_to_bounds (BUF_BEGV (buf), XFIXNUM (XCDR (region)),
+                                    BUF_ZV (buf));
+      if (neomacs_region_span (w, Qregion, min (a, b), max (a, b), 0,
+                               &spans[n]))
+        n++;
+    }
+  return n;
+}
+
+/* The directory of the hyphenation pattern files, encoded for the file
+   system.  Expanding and encoding it can run Lisp, so it is computed
+   outside redisplay: when the display opens and whenever
+   `neomacs-hyphenation-directory' is set.  */
+static Lisp_Object neomacs_hyphenation_dir;
+
+static void
+neomacs_set_hyphenation_dir (Lisp_Object dir)
+{
+  if (!STRINGP (dir))
+    dir = Fexpand_file_name (build_string ("hyphenation"), Vdata_directory);
+  neomacs_hyphenation_dir = ENCODE_FILE (dir);
+}
+
+static Lisp_Object
+watch_neomacs_hyphenation_directory (Lisp_Object symbol, Lisp_Object newval,
+                                     Lisp_Object operation, Lisp_Object where)
+{
+  neomacs_set_hyphenation_dir (newval);
+  return Qnil;
+}
+
+/* Hyphenation settings of BUFFER_PTR for the Rust layout engine: the
+   language of its `neomacs-hyphenation' goes to LANG_BUF, the
+   directory holding the pattern files to DIR_BUF (its length to
+   *DIR_LEN_OUT), and `neomacs-hyphenation-ragged-columns' to
+   *RAGGED_COLS_OUT.  Returns the length of the language, 0 if the
+   buffer is not hyphenated.  */
+int
+neomacs_layout_hyphenation (void *buffer_ptr, uint8_t *lang_buf,
+                            int lang_buf_len, uint8_t *dir_buf,
+                            int dir_buf_len, int *dir_len_out,
+                            int *ragged_cols_out)
+{
+  struct buffer *buf = (struct buffer *) buffer_ptr;
+  if (!buf || !lang_buf || !dir_buf || !dir_len_out || !ragged_cols_out)
+    return 0;
+
+  *dir_len_out = 0;
+  *ragged_cols_out = (int) max (0, min (neomacs_hyphenation_ragged_columns,
+                                        INT_MAX));
+
+  struct buffer *old_buf = current_buffer;
+  set_buffer_internal_1 (buf);
+  Lisp_Object lang = Vneomacs_hyphenation;
+  set_buffer_internal_1 (old_buf);
+
+  if (SYMBOLP (lang) && !NILP (lang) && !EQ (lang, Qt))
+    lang = SYMBOL_NAME (lang);
+  if (!STRINGP (lang) || SBYTES (lang) == 0 || SBYTES (lang) > lang_buf_len)
+    return 0;
+
+  Lisp_Object dir = neomacs_hyphenation_dir;
+  if (!STRINGP (dir) || SBYTES (dir) > dir_buf_len)
+    return 0;
+
+  memcpy (dir_buf, SDATA (dir), SBYTES (dir));
+  *dir_len_out = (int) SBYTES (dir);
+  memcpy (lang_buf, SDATA (lang), SBYTES (lang));
+  return (int) SBYTES (lang);
+}
+
+/* Called at the end of updating a frame */
+void
+neomacs_update_end (struct frame *f)
+{
+  struct neomacs_display_info *dpyinfo = FRAME_NEOMACS_DISPLAY_INFO (f);
+  struct neomacs_output *output = FRAME_NEOMACS_OUTPUT (f);
+
+  if (dpyinfo && dpyinfo->display_handle)
+    {
+      /* Rust layout engine reads buffer data directly via FFI
+         and produces FrameGlyphBuffer internally. */
+      struct face *default_face = FACE_FROM_ID_OR_NULL (f, DEFAULT_FACE_ID);
+      unsigned long bg_pixel = default_face && !default_face->background_defaulted_p
+        ? default_face->background : FRAME_BACKGROUND_PIXEL (f);
+      uint32_t bg_rgb = ((RED_FROM_ULONG (bg_pixel) << 16)
+                         | (GREEN_FROM_ULONG (bg_pixel) << 8)
+                         | BLUE_FROM_ULONG (bg_pixel));
+
+      /* Get vertical border face color */
+      struct face *vborder_face = FACE_FROM_ID_OR_NULL (f, VERTICAL_BORDER_FACE_ID);
+      unsigned long vb_fg = vborder_face ? vborder_face->foreground : 0;
+      uint32_t vb_rgb = ((RED_FROM_ULONG (vb_fg) << 16)
+                         | (GREEN_FROM_ULONG (vb_fg) << 8)
+                         | BLUE_FROM_ULONG (vb_fg));
+
+      /* Window divider face colors */
+      int rdw = FRAME_RIGHT_DIVIDER_WIDTH (f);
       int bdw = FRAME_BOTTOM_DIVIDER_WIDTH (f);
       uint32_t div_fg = vb_rgb, div_first_fg = vb_rgb, div_last_fg = vb_rgb;
       if (rdw > 0 || bdw > 0)
@@ -5172,6 +5984,7 @@ neomacs_update_end (struct frame *f)
             }
         }
 
+      neomacs_ghost_text_check ();
       neomacs_rust_layout_frame (
           dpyinfo->display_handle,
           (void *) f,
@@ -5195,6 +6008,12 @@ neomacs_update_end (struct frame *f)
                            marker_byte_position (saved_mini_pointm));
         }
 
+      /* Refresh the accessibility tree from the new layout (no-op
+         unless a screen reader interface is enabled).  */
+      if (STRINGP (f->name))
+        neomacs_accessibility_update ((void *) f,
+                                      SSDATA (ENCODE_UTF_8 (f->name)));
+
       /* Extract menu bar and tool bar from current_matrix.
          Their desired_matrix was populated by
          neomacs_display_menu_and_tool_bar() in redisplay_internal,
@@ -5317,6 +6136,22 @@ neomacs_set_window_size (struct frame *f, bool change_gravity,
       return;
     }
 
+  /* Popup windows are resized (and kept centered) by the render
+     thread; the main window is left alone.  */
+  if (output->popup_window)
+    {
+      output->pixel_width = width;
+      output->pixel_height = height;
+      change_frame_size (f, width, height, false, true, false);
+      if (dpyinfo && dpyinfo->display_handle)
+        neomacs_display_resize_popup_window (dpyinfo->display_handle,
+                                             (uint64_t)(uintptr_t) f,
+                                             width, height);
+      SET_FRAME_GARBAGED (f);
+      unblock_input ();
+      return;
+    }
+
   /* Clamp to display dimensions so the window doesn't extend beyond
      the screen.  On a real display the WM would do this; without a WM
      (e.g. Xvfb) the window would grow beyond the screen otherwise.
@@ -5742,6 +6577,27 @@ neomacs_make_frame_visible_invisible (struct frame *f, bool visible)
       return;
     }
 
+  /* A hidden popup frame gives up its window; showing it again makes a
+     new one, centered on the monitor focused then.  */
+  if (output->popup_window && dpyinfo && dpyinfo->display_handle)
+    {
+      if (visible && !FRAME_VISIBLE_P (f))
+        neomacs_display_create_popup_window (dpyinfo->display_handle,
+                                             (uint64_t)(uintptr_t) f,
+                                             FRAME_PIXEL_WIDTH (f),
+                                             FRAME_PIXEL_HEIGHT (f));
+      else if (!visible && FRAME_VISIBLE_P (f))
+        neomacs_display_destroy_os_window (dpyinfo->display_handle,
+                                           (uint64_t)(uintptr_t) f);
+      SET_FRAME_VISIBLE (f, visible);
+      if (visible)
+        {
+          SET_FRAME_ICONIFIED (f, false);
+          SET_FRAME_GARBAGED (f);
+        }
+      return;
+    }
+
   /* Handle winit windows (no GTK widget) */
   if (output->window_id > 0 && dpyinfo && dpyinfo->display_handle)
     {
@@ -7761,6 +8617,7 @@ neomacs_define_frame_cursor (struct frame *f, Emacs_Cursor cursor)
   if (!dpyinfo || !dpyinfo->display_handle)
     return;
 
+  FRAME_OUTPUT_DATA (f)->current_cursor = cursor;
   int cursor_type = (int)(intptr_t) cursor;
   neomacs_display_set_mouse_cursor (dpyinfo->display_handle,
                                      cursor_type);
@@ -8092,6 +8949,27 @@ neomacs_iconify_frame (struct frame *f)
   SET_FRAME_VISIBLE (f, 0);
 }
 
+/* Send the title of frame F (its `title' parameter, else its name) to
+   the window.  Child frames have no window title.  If the render
+   thread's queue is full, the title is resent at the next redisplay.  */
+void
+neomacs_send_title (struct frame *f)
+{
+  struct neomacs_display_info *dpyinfo = FRAME_NEOMACS_DISPLAY_INFO (f);
+  struct neomacs_output *output = FRAME_NEOMACS_OUTPUT (f);
+  if (!dpyinfo || !dpyinfo->display_handle || !output
+      || FRAME_PARENT_FRAME (f) || FRAME_TOOLTIP_P (f)
+      || output->popup_window)
+    return;
+
+  Lisp_Object title = !NILP (f->title) ? f->title : f->name;
+  if (!STRINGP (title))
+    return;
+  Lisp_Object encoded = ENCODE_UTF_8 (title);
+  output->title_pending
+    = !neomacs_display_set_title (dpyinfo->display_handle, SSDATA (encoded));
+}
+
 /* Set frame name/title — common logic for implicit and explicit.  */
 void
 neomacs_set_name (struct frame *f, Lisp_Object name, bool explicit_p)
@@ -8114,18 +8992,7 @@ neomacs_set_name (struct frame *f, Lisp_Object name, bool explicit_p)
     return;
 
   fset_name (f, name);
-
-  /* Title overrides name.  */
-  if (!NILP (f->title))
-    name = f->title;
-
-  struct neomacs_display_info *dpyinfo = FRAME_NEOMACS_DISPLAY_INFO (f);
-  if (dpyinfo && dpyinfo->display_handle)
-    {
-      Lisp_Object encoded = ENCODE_UTF_8 (name);
-      neomacs_display_set_title (dpyinfo->display_handle,
-                                 SSDATA (encoded));
-    }
+  neomacs_send_title (f);
 }
 
 /* Set frame title implicitly (from buffer name, etc.).  */
@@ -8153,8 +9020,9 @@ neomacs_set_frame_offset (struct frame *f, int xoff, int yoff,
       f->win_gravity = NorthWestGravity;
     }
 
+  /* Popup windows are placed by the render thread.  */
   struct neomacs_display_info *dpyinfo = FRAME_NEOMACS_DISPLAY_INFO (f);
-  if (dpyinfo && dpyinfo->display_handle)
+  if (dpyinfo && dpyinfo->display_handle && !FRAME_NEOMACS_POPUP_P (f))
     neomacs_display_set_position (dpyinfo->display_handle, xoff, yoff);
 }
 
@@ -8177,6 +9045,9 @@ neomacs_free_frame_resources (struct frame *f)
   if (FRAME_PARENT_FRAME (f) && dpyinfo->display_handle)
     neomacs_display_remove_child_frame (dpyinfo->display_handle,
                                         (uint64_t)(uintptr_t) f);
+  else if (output->popup_window && dpyinfo->display_handle)
+    neomacs_display_destroy_os_window (dpyinfo->display_handle,
+                                       (uint64_t)(uintptr_t) f);
 
   free_frame_faces (f);
 
@@ -8656,6 +9527,303 @@ Returns t on success, nil on failure.  */)
   return result == 0 ? Qt : Qnil;
 }
 
+static unsigned int neomacs_annotation_color (Lisp_Object, unsigned int);
+
+/* Number N of an image edit, which must be a number.  */
+static float
+neomacs_image_edit_number (Lisp_Object n)
+{
+  CHECK_NUMBER (n);
+  return (float) XFLOATINT (n);
+}
+
+/* Check that EDITS is a valid edit plist (see `neomacs-image-set-edits')
+   and return its number of annotations.  */
+static ptrdiff_t
+neomacs_check_image_edits (Lisp_Object edits)
+{
+  Lisp_Object rotate = plist_get (edits, QCrotate);
+  if (!NILP (rotate))
+    CHECK_FIXNUM (rotate);
+  Lisp_Object crop = plist_get (edits, QCcrop);
+  if (!NILP (crop))
+    {
+      CHECK_LIST (crop);
+      if (list_length (crop) != 4)
+        xsignal2 (Qerror, build_string ("Crop must be (X Y W H)"), crop);
+      for (Lisp_Object tail = crop; CONSP (tail); tail = XCDR (tail))
+        CHECK_NUMBER (XCAR (tail));
+    }
+
+  ptrdiff_t count = 0;
+  Lisp_Object annotations = plist_get (edits, QCannotations);
+  CHECK_LIST (annotations);
+  for (Lisp_Object tail = annotations; CONSP (tail); tail = XCDR (tail))
+    {
+      Lisp_Object a = XCAR (tail);
+      CHECK_CONS (a);
+      bool text = EQ (XCAR (a), Qtext);
+      if (!text && !EQ (XCAR (a), Qarrow) && !EQ (XCAR (a), Qrect))
+        xsignal2 (Qerror, build_string ("Unknown annotation"), a);
+      if (list_length (a) != (text ? 6 : 7))
+        xsignal2 (Qerror, build_string ("Malformed annotation"), a);
+      Lisp_Object args = XCDR (a);
+      for (int i = 0; i < (text ? 2 : 4); i++, args = XCDR (args))
+        CHECK_NUMBER (XCAR (args));
+      if (text)
+        {
+          CHECK_STRING (XCAR (args));
+          args = XCDR (args);
+        }
+      CHECK_STRING (XCAR (args));
+      CHECK_NUMBER (XCAR (XCDR (args)));
+      count++;
+    }
+  return count;
+}
+
+/* Fill OUT from the edit plist EDITS, already checked by
+   neomacs_check_image_edits.  Returns the annotation array, to be freed
+   with xfree; text annotations point into EDITS' strings.  */
+static struct CImageAnnotation *
+neomacs_image_edits_from_lisp (Lisp_Object edits, ptrdiff_t count,
+                               struct CImageEdits *out)
+{
+  Lisp_Object rotate = plist_get (edits, QCrotate);
+  Lisp_Object crop = plist_get (edits, QCcrop);
+  memset (out, 0, sizeof *out);
+  out->rotation = FIXNUMP (rotate) ? (int) (XFIXNUM (rotate) & 3) : 0;
+  out->flip_h = !NILP (plist_get (edits, QCflip_h));
+  out->flip_v = !NILP (plist_get (edits, QCflip_v));
+  if (CONSP (crop))
+    {
+      out->crop_x = neomacs_image_edit_number (Fnth (make_fixnum (0), crop));
+      out->crop_y = neomacs_image_edit_number (Fnth (make_fixnum (1), crop));
+      out->crop_w = neomacs_image_edit_number (Fnth (make_fixnum (2), crop));
+      out->crop_h = neomacs_image_edit_number (Fnth (make_fixnum (3), crop));
+    }
+
+  struct CImageAnnotation *annotations
+    = xzalloc (max (count, 1) * sizeof *annotations);
+  ptrdiff_t i = 0;
+  for (Lisp_Object tail = plist_get (edits, QCannotations);
+       CONSP (tail); tail = XCDR (tail), i++)
+    {
+      Lisp_Object a = XCAR (tail);
+      Lisp_Object args = XCDR (a);
+      struct CImageAnnotation *c = &annotations[i];
+      c->kind = EQ (XCAR (a), Qarrow) ? 0 : EQ (XCAR (a), Qrect) ? 1 : 2;
+      c->x0 = neomacs_image_edit_number (XCAR (args));
+      args = XCDR (args);
+      c->y0 = neomacs_image_edit_number (XCAR (args));
+      args = XCDR (args);
+      if (c->kind == 2)
+        {
+          c->text = SSDATA (XCAR (args));
+          args = XCDR (args);
+        }
+      else
+        {
+          c->x1 = neomacs_image_edit_number (XCAR (args));
+          args = XCDR (args);
+          c->y1 = neomacs_image_edit_number (XCAR (args));
+          args = XCDR (args);
+        }
+      c->color = neomacs_annotation_color (XCAR (args), 0xFF0000);
+      c->size = neomacs_image_edit_number (XCAR (XCDR (args)));
+    }
+  out->annotations = annotations;
+  out->annotation_count = (int) count;
+  return annotations;
+}
+
+DEFUN ("neomacs-image-set-edits", Fneomacs_image_set_edits,
+       Sneomacs_image_set_edits, 4, 4, 0,
+       doc: /* Show image IMAGE-ID with EDITS, leaving its file untouched.
+EDITS is a plist; nil undoes all edits.  Its properties apply in order:
+
+  :rotate N          rotate by N quarter turns clockwise
+  :flip-h, :flip-v   mirror left to right, top to bottom
+  :crop (X Y W H)    keep only this part of the rotated image
+  :annotations LIST  draw these over the cropped image, in order
+
+Each annotation is (arrow X0 Y0 X1 Y1 COLOR SIZE), an arrow pointing at
+\(X1, Y1); (rect X0 Y0 X1 Y1 COLOR SIZE), a rectangle outline; or (text
+X Y STRING COLOR SIZE), STRING with its top left corner at (X, Y).
+Coordinates are fractions (0 to 1) of the edited image, COLOR is a
+"#rrggbb" string, and SIZE is the line width or text height as a
+fraction of the image's shorter side.
+
+WIDTH and HEIGHT are the unedited size IMAGE-ID is displayed at.
+Returns (WIDTH . HEIGHT) of the edited image; use it in the image spec
+so redisplay makes room for the new size.  Only images loaded from
+files or data can be edited.  */)
+  (Lisp_Object image_id, Lisp_Object edits, Lisp_Object width,
+   Lisp_Object height)
+{
+  CHECK_FIXNUM (image_id);
+  CHECK_FIXNAT (width);
+  CHECK_FIXNAT (height);
+  ptrdiff_t count = neomacs_check_image_edits (edits);
+
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct CImageEdits c;
+  struct CImageAnnotation *annotations
+    = neomacs_image_edits_from_lisp (edits, count, &c);
+  int w = 0, h = 0;
+  neomacs_display_edited_image_size (&c, (int) XFIXNUM (width),
+                                     (int) XFIXNUM (height), &w, &h);
+  neomacs_display_set_image_edits (dpyinfo->display_handle,
+                                   (uint32_t) XFIXNUM (image_id),
+                                   NILP (edits) ? NULL : &c);
+  xfree (annotations);
+  return Fcons (make_fixnum (w), make_fixnum (h));
+}
+
+DEFUN ("neomacs-image-export-png", Fneomacs_image_export_png,
+       Sneomacs_image_export_png, 3, 3, 0,
+       doc: /* Apply EDITS to the image FILE and save the result as PNG file OUTPUT.
+EDITS is a plist as for `neomacs-image-set-edits'; the edits are
+applied to the image at its full size.  FILE itself is not changed.
+Returns (WIDTH . HEIGHT) of the saved image; signals an error if FILE
+can't be decoded or OUTPUT can't be written.  */)
+  (Lisp_Object file, Lisp_Object edits, Lisp_Object output)
+{
+  CHECK_STRING (file);
+  CHECK_STRING (output);
+  ptrdiff_t count = neomacs_check_image_edits (edits);
+  Lisp_Object source = ENCODE_FILE (Fexpand_file_name (file, Qnil));
+  Lisp_Object target = ENCODE_FILE (Fexpand_file_name (output, Qnil));
+
+  struct CImageEdits c;
+  struct CImageAnnotation *annotations
+    = neomacs_image_edits_from_lisp (edits, count, &c);
+  char err[512] = "";
+  int w = 0, h = 0;
+  int result = neomacs_display_export_edited_image (SSDATA (source), &c,
+                                                    SSDATA (target), &w, &h,
+                                                    err, sizeof err);
+  xfree (annotations);
+  if (result != 0)
+    error ("Cannot export image: %s", err);
+  return Fcons (make_fixnum (w), make_fixnum (h));
+}
+
+DEFUN ("neomacs-render-chart", Fneomacs_render_chart,
+       Sneomacs_render_chart, 4, 8, 0,
+       doc: /* Draw a chart of SERIES as a WIDTH x HEIGHT pixel PNG image.
+TYPE is `line', `bar' or `sparkline'.  SERIES is a list of
+(NAME COLOR . VALUES): NAME is a string shown in the legend when there
+is more than one series, COLOR a "#rrggbb" string, and VALUES the
+numbers to plot, nil for a missing one.  LABELS is a list of strings
+shown under the x positions of line and bar charts; sparklines have no
+axes or labels.  TITLE, if non-nil, is a string shown above the chart.
+COLORS is (FOREGROUND . BACKGROUND), "#rrggbb" strings for the text and
+axes and for the background; BACKGROUND nil leaves it transparent.
+RANGE is (MIN . MAX), the values shown at the bottom and top of the
+chart; nil fits the data.  Labels use the size of the selected frame's
+font.
+
+Return (PNG . AREAS): PNG is a unibyte string with the image data, and
+AREAS a list of (INDEX X0 Y0 X1 Y1), the rectangle of the chart showing
+the values at position INDEX.  */)
+  (Lisp_Object type, Lisp_Object width, Lisp_Object height,
+   Lisp_Object series, Lisp_Object labels, Lisp_Object title,
+   Lisp_Object colors, Lisp_Object range)
+{
+  CHECK_FIXNAT (width);
+  CHECK_FIXNAT (height);
+  if (!NILP (title))
+    CHECK_STRING (title);
+  ptrdiff_t nseries = list_length (series);
+  ptrdiff_t nlabels = list_length (labels);
+  if (nseries > INT_MAX || nlabels > INT_MAX)
+    error ("Too many chart series or labels");
+
+  /* UTF-8 encoded names and labels, kept in a vector so they stay
+     alive while the chart is drawn.  */
+  Lisp_Object encoded = make_nil_vector (nseries + nlabels);
+  ptrdiff_t nvalues = 0, i = 0;
+  for (Lisp_Object tail = series; CONSP (tail); tail = XCDR (tail), i++)
+    {
+      Lisp_Object name = Fcar (XCAR (tail));
+      if (STRINGP (name))
+        ASET (encoded, i, ENCODE_UTF_8 (name));
+      nvalues += list_length (Fcdr (Fcdr (XCAR (tail))));
+    }
+  for (Lisp_Object tail = labels; CONSP (tail); tail = XCDR (tail), i++)
+    {
+      CHECK_STRING (XCAR (tail));
+      ASET (encoded, i, ENCODE_UTF_8 (XCAR (tail)));
+    }
+
+  struct CChartSeries *cseries = xmalloc (max (nseries, 1) * sizeof *cseries);
+  double *values = xmalloc (max (nvalues, 1) * sizeof *values);
+  const char **clabels = xmalloc (max (nlabels, 1) * sizeof *clabels);
+  double *next = values;
+  i = 0;
+  for (Lisp_Object tail = series; CONSP (tail); tail = XCDR (tail), i++)
+    {
+      Lisp_Object entry = XCAR (tail);
+      Lisp_Object name = AREF (encoded, i);
+      cseries[i].name = STRINGP (name) ? SSDATA (name) : NULL;
+      cseries[i].color = neomacs_annotation_color (Fcar (Fcdr (entry)),
+                                                   0x4C9BE8);
+      cseries[i].values = next;
+      for (Lisp_Object v = Fcdr (Fcdr (entry)); CONSP (v); v = XCDR (v))
+        *next++ = NUMBERP (XCAR (v)) ? XFLOATINT (XCAR (v)) : NAN;
+      cseries[i].count = (int) (next - cseries[i].values);
+    }
+  for (ptrdiff_t j = 0; j < nlabels; j++)
+    clabels[j] = SSDATA (AREF (encoded, nseries + j));
+
+  struct frame *f = SELECTED_FRAME ();
+  Lisp_Object title_enc = NILP (title) ? Qnil : ENCODE_UTF_8 (title);
+  Lisp_Object bg = Fcdr (colors);
+  struct CChartSpec spec = {
+    .kind = EQ (type, Qbar) ? 1 : EQ (type, Qsparkline) ? 2 : 0,
+    .width = (int) min (XFIXNAT (width), INT_MAX),
+    .height = (int) min (XFIXNAT (height), INT_MAX),
+    .series = cseries,
+    .series_count = (int) nseries,
+    .labels = clabels,
+    .label_count = (int) nlabels,
+    .title = NILP (title_enc) ? NULL : SSDATA (title_enc),
+    .fg = neomacs_annotation_color (Fcar (colors), 0x808080),
+    .bg = neomacs_annotation_color (bg, 0xFFFFFF),
+    .opaque = STRINGP (bg),
+    .min = NUMBERP (Fcar (range)) ? XFLOATINT (Fcar (range)) : NAN,
+    .max = NUMBERP (Fcdr (range)) ? XFLOATINT (Fcdr (range)) : NAN,
+    .font_size = (FRAME_LIVE_P (f) && FRAME_FONT (f)
+                  ? (float) FRAME_FONT (f)->pixel_size : 14.0f),
+  };
+
+  uint8_t *png = NULL;
+  size_t png_len = 0;
+  struct CChartHotSpot *spots = NULL;
+  int nspots = 0;
+  int result = neomacs_display_render_chart (&spec, &png, &png_len,
+                                             &spots, &nspots);
+  xfree (clabels);
+  xfree (values);
+  xfree (cseries);
+  if (result != 0)
+    error ("Cannot draw chart");
+
+  Lisp_Object data = make_unibyte_string ((const char *) png, png_len);
+  Lisp_Object areas = Qnil;
+  for (int k = nspots - 1; k >= 0; k--)
+    areas = Fcons (list5 (make_fixnum (spots[k].index),
+                          make_fixnum (spots[k].x0), make_fixnum (spots[k].y0),
+                          make_fixnum (spots[k].x1), make_fixnum (spots[k].y1)),
+                   areas);
+  neomacs_display_free_chart (png, png_len, spots, nspots);
+  return Fcons (data, areas);
+}
+
 DEFUN ("neomacs-image-floating", Fneomacs_image_floating, Sneomacs_image_floating, 5, 5, 0,
        doc: /* Show image IMAGE-ID as a floating layer at position (X, Y) with size (WIDTH, HEIGHT).
 The image will be rendered on top of the frame content at a fixed screen position.  */)
@@ -9519,6 +10687,38 @@ This includes cursor animation and buffer transition animation.  */)
   return active ? Qt : Qnil;
 }
 
+DEFUN ("neomacs-set-buffer-transition", Fneomacs_set_buffer_transition, Sneomacs_set_buffer_transition, 1, 3, 0,
+       doc: /* Set the EFFECT used by buffer transitions.
+EFFECT is a string naming the effect, as for `neomacs-start-buffer-transition':
+\"crossfade\", \"slide-left\", \"slide-right\", \"slide-up\", \"slide-down\",
+\"scale-fade\", \"push\", \"blur\" (drawn as a crossfade), \"page-curl\",
+\"flip\" (card flip around the vertical axis), \"cube\" (cube rotation)
+or \"none\".
+Optional DURATION is the animation duration in milliseconds (default 200).
+Transitions are started by `neomacs-trigger-buffer-transition'.  If
+AUTO is non-nil, they also start whenever a window shows a different
+buffer, independently in each window.  */)
+  (Lisp_Object effect, Lisp_Object duration, Lisp_Object autop)
+{
+  CHECK_STRING (effect);
+
+  int duration_ms = 200;
+  if (!NILP (duration))
+    {
+      CHECK_FIXNUM (duration);
+      duration_ms = clip_to_bounds (0, XFIXNUM (duration), 5000);
+    }
+
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  neomacs_display_set_buffer_transition (dpyinfo->display_handle,
+                                         SSDATA (effect), duration_ms,
+                                         !NILP (autop));
+  return Qt;
+}
+
 DEFUN ("neomacs-prepare-buffer-transition", Fneomacs_prepare_buffer_transition, Sneomacs_prepare_buffer_transition, 0, 0, 0,
        doc: /* Prepare for buffer transition by capturing current frame.
 Call this BEFORE switching buffers to capture the "old" frame.
@@ -9534,6 +10734,23 @@ Returns t on success, nil on failure.  */)
   return result ? Qt : Qnil;
 }
 
+DEFUN ("neomacs-prepare-frame-transition", Fneomacs_prepare_frame_transition, Sneomacs_prepare_frame_transition, 0, 0, 0,
+       doc: /* Capture all windows of the frame for a transition.
+Call this BEFORE replacing the window layout, e.g. with
+`window-state-put', then `neomacs-trigger-buffer-transition' to
+animate the area of all windows but the minibuffer from the old
+layout to the new one as a whole.
+Returns t on success, nil on failure.  */)
+  (void)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int result = neomacs_display_prepare_frame_transition (dpyinfo->display_handle);
+  return result ? Qt : Qnil;
+}
+
 DEFUN ("neomacs-trigger-buffer-transition", Fneomacs_trigger_buffer_transition, Sneomacs_trigger_buffer_transition, 0, 0, 0,
        doc: /* Trigger buffer transition animation after buffer has changed.
 Call this AFTER switching buffers to start the transition animation.
@@ -9563,6 +10780,190 @@ and a frame has been captured.  */)
   return has_snapshot ? Qt : Qnil;
 }
 
+/* Most keyframes accepted by `neomacs-animate-window'.  */
+#define NEOMACS_MAX_KEYFRAMES 64
+
+/* Decode EASING, a symbol or a (spring STIFFNESS DAMPING MASS) or
+   (cubic-bezier X1 Y1 X2 Y2) list, into its code for the display
+   engine.  Store the parameters of a curve in PARAMS.  */
+static int
+neomacs_decode_easing (Lisp_Object easing, double params[4])
+{
+  params[0] = params[1] = params[2] = params[3] = 0.0;
+  if (NILP (easing) || EQ (easing, Qease_out))
+    return 1;
+  if (EQ (easing, Qlinear))
+    return 0;
+  if (EQ (easing, Qease_in))
+    return 2;
+  if (EQ (easing, Qease_in_out))
+    return 3;
+  if (EQ (easing, Qease_out_back))
+    return 4;
+  if (CONSP (easing)
+      && (EQ (XCAR (easing), Qspring) || EQ (XCAR (easing), Qcubic_bezier)))
+    {
+      int n = EQ (XCAR (easing), Qspring) ? 3 : 4;
+      Lisp_Object tail = XCDR (easing);
+      for (int i = 0; i < n; i++)
+        {
+          CHECK_CONS (tail);
+          CHECK_NUMBER (XCAR (tail));
+          params[i] = XFLOATINT (XCAR (tail));
+          tail = XCDR (tail);
+        }
+      return n == 3 ? 5 : 6;
+    }
+  xsignal2 (Qerror, build_string ("Unknown easing"), easing);
+}
+
+DEFUN ("neomacs-animate-window", Fneomacs_animate_window, Sneomacs_animate_window, 3, 5, 0,
+       doc: /* Animate PROPERTY of WINDOW through KEYFRAMES.
+WINDOW must be a live window; nil means the selected window.
+PROPERTY is one of the symbols `offset-x', `offset-y' (in pixels),
+`opacity' (0.0 to 1.0) or `scale' (1.0 is the natural size, scaled
+about the window center).
+KEYFRAMES is a list of (TIME VALUE [EASING]) where TIME is in
+milliseconds from the start of the animation, VALUE is a number and
+EASING, used for the segment ending at this keyframe, is one of
+`linear', `ease-out' (the default), `ease-in', `ease-in-out',
+`ease-out-back', (spring STIFFNESS DAMPING MASS) or
+\(cubic-bezier X1 Y1 X2 Y2), as for `neomacs-set-animation-curve'.
+Optional DELAY is a wait in milliseconds before the animation starts.
+Optional ITERATIONS is how many times to play it (default 1); t means
+repeat until cancelled with `neomacs-cancel-animation'.
+Several animations of one window compose: offsets add up, opacity
+and scale multiply.
+Return an animation id, or nil if the animation could not be started.  */)
+  (Lisp_Object window, Lisp_Object property, Lisp_Object keyframes,
+   Lisp_Object delay, Lisp_Object iterations)
+{
+  int prop;
+  if (EQ (property, Qoffset_x))
+    prop = 0;
+  else if (EQ (property, Qoffset_y))
+    prop = 1;
+  else if (EQ (property, Qopacity))
+    prop = 2;
+  else if (EQ (property, Qscale))
+    prop = 3;
+  else
+    xsignal2 (Qerror, build_string ("Unknown animation property"), property);
+
+  int times[NEOMACS_MAX_KEYFRAMES];
+  double values[NEOMACS_MAX_KEYFRAMES];
+  int easings[NEOMACS_MAX_KEYFRAMES];
+  double params[NEOMACS_MAX_KEYFRAMES * 4];
+  int count = 0;
+  for (Lisp_Object tail = keyframes; CONSP (tail); tail = XCDR (tail))
+    {
+      Lisp_Object key = XCAR (tail);
+      CHECK_CONS (key);
+      if (count == NEOMACS_MAX_KEYFRAMES)
+        error ("Too many keyframes (at most %d)", NEOMACS_MAX_KEYFRAMES);
+      Lisp_Object time = XCAR (key);
+      Lisp_Object rest = XCDR (key);
+      CHECK_FIXNUM (time);
+      CHECK_CONS (rest);
+      CHECK_NUMBER (XCAR (rest));
+      Lisp_Object easing = CONSP (XCDR (rest)) ? XCAR (XCDR (rest)) : Qnil;
+      times[count] = clip_to_bounds (0, XFIXNUM (time), 600000);
+      values[count] = XFLOATINT (XCAR (rest));
+      easings[count] = neomacs_decode_easing (easing, &params[count * 4]);
+      count++;
+    }
+  if (count == 0)
+    return Qnil;
+
+  int delay_ms = 0;
+  if (!NILP (delay))
+    {
+      CHECK_FIXNUM (delay);
+      delay_ms = clip_to_bounds (0, XFIXNUM (delay), 600000);
+    }
+  int iters = 1;
+  if (EQ (iterations, Qt))
+    iters = 0;
+  else if (!NILP (iterations))
+    {
+      CHECK_FIXNUM (iterations);
+      iters = clip_to_bounds (1, XFIXNUM (iterations), 10000);
+    }
+
+  int64_t window_id = (int64_t) (intptr_t) decode_live_window (window);
+
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  uint32_t id = neomacs_display_animate (dpyinfo->display_handle, window_id,
+                                         prop, times, values, easings,
+                                         params, count, delay_ms, iters);
+  return id ? make_fixnum (id) : Qnil;
+}
+
+DEFUN ("neomacs-cancel-animation", Fneomacs_cancel_animation, Sneomacs_cancel_animation, 0, 1, 0,
+       doc: /* Cancel the animation with ID, as returned by `neomacs-animate-window'.
+With ID nil, cancel all animations.  Cancelled properties return to
+their resting values.  */)
+  (Lisp_Object id)
+{
+  uint32_t anim_id = 0;
+  if (!NILP (id))
+    {
+      CHECK_FIXNAT (id);
+      anim_id = XFIXNAT (id);
+    }
+
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  neomacs_display_cancel_animation (dpyinfo->display_handle, anim_id);
+  return Qt;
+}
+
+DEFUN ("neomacs-set-animation-curve", Fneomacs_set_animation_curve, Sneomacs_set_animation_curve, 2, 2, 0,
+       doc: /* Set the easing curve of the animations of TARGET.
+TARGET is one of the symbols `cursor' (cursor motion), `scroll'
+\(scroll transitions), `crossfade' (crossfades on buffer change) or
+`buffer-transition'.
+CURVE is one of:
+  (spring STIFFNESS DAMPING MASS)  a damped spring; a damping ratio
+      DAMPING / (2 * sqrt (STIFFNESS * MASS)) below 1 overshoots and
+      bounces, 1 settles fastest without overshoot.
+  (cubic-bezier X1 Y1 X2 Y2)  a CSS-style Bezier curve; Y values
+      outside 0..1 overshoot.
+  `linear', `ease-out', `ease-in', `ease-in-out' or `ease-out-back'.
+  nil, to restore the default easing of TARGET.
+The curve runs over the duration configured for TARGET.  */)
+  (Lisp_Object target, Lisp_Object curve)
+{
+  int target_code;
+  if (EQ (target, Qcursor))
+    target_code = 0;
+  else if (EQ (target, intern ("scroll")))
+    target_code = 1;
+  else if (EQ (target, Qcrossfade))
+    target_code = 2;
+  else if (EQ (target, Qbuffer_transition))
+    target_code = 3;
+  else
+    xsignal2 (Qerror, build_string ("Unknown animation target"), target);
+
+  double params[4] = { 0.0, 0.0, 0.0, 0.0 };
+  int easing = NILP (curve) ? -1 : neomacs_decode_easing (curve, params);
+
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  neomacs_display_set_animation_curve (dpyinfo->display_handle, target_code,
+                                       easing, params[0], params[1],
+                                       params[2], params[3]);
+  return Qt;
+}
+
 
 DEFUN ("neomacs-set-scroll-indicators", Fneomacs_set_scroll_indicators,
        Sneomacs_set_scroll_indicators, 1, 1, 0,
@@ -9684,595 +11085,3067 @@ when C fontconfig and cosmic-text resolve different font files.  */)
   return use_cosmic ? intern ("cosmic") : intern ("emacs");
 }
 
-DEFUN ("neomacs-set-background-gradient",
-       Fneomacs_set_background_gradient,
-       Sneomacs_set_background_gradient, 2, 2, 0,
-       doc: /* Set a background gradient from TOP-COLOR to BOTTOM-COLOR.
-Colors are strings like \"#rrggbb\".  Pass nil for either to disable.  */)
-  (Lisp_Object top_color, Lisp_Object bottom_color)
+DEFUN ("neomacs-set-typewriter-scrolling", Fneomacs_set_typewriter_scrolling,
+       Sneomacs_set_typewriter_scrolling, 1, 1, 0,
+       doc: /* Enable or disable typewriter scrolling.
+When ENABLED is non-nil, the layout engine keeps the line containing
+point vertically centered in every window except the minibuffer.
+Window start changes are animated as smooth scrolls.  */)
+  (Lisp_Object enabled)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  if (NILP (top_color) || NILP (bottom_color))
-    {
-      neomacs_display_set_background_gradient (
-        dpyinfo->display_handle, 0, 0, 0, 0, 0, 0, 0);
-      return Qnil;
-    }
-
-  CHECK_STRING (top_color);
-  CHECK_STRING (bottom_color);
+  neomacs_display_set_typewriter_scrolling (dpyinfo->display_handle,
+                                            !NILP (enabled));
+  return !NILP (enabled) ? Qt : Qnil;
+}
 
-  Emacs_Color tc, bc;
-  if (!neomacs_defined_color (NULL, SSDATA (top_color), &tc, false, false))
-    error ("Undefined color: %s", SSDATA (top_color));
-  if (!neomacs_defined_color (NULL, SSDATA (bottom_color), &bc, false, false))
-    error ("Undefined color: %s", SSDATA (bottom_color));
+DEFUN ("neomacs-set-scroll-anchoring", Fneomacs_set_scroll_anchoring,
+       Sneomacs_set_scroll_anchoring, 1, 1, 0,
+       doc: /* Enable or disable scroll anchoring.
+When ENABLED is non-nil and a window is resized, or text between its
+start and point changes height (e.g. a fold is opened or closed), while
+point and the window start stay where they were, the layout engine
+moves the window start so the line at point stays on the same screen
+row instead of jumping.  */)
+  (Lisp_Object enabled)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
 
-  neomacs_display_set_background_gradient (
-    dpyinfo->display_handle, 1,
-    tc.red >> 8, tc.green >> 8, tc.blue >> 8,
-    bc.red >> 8, bc.green >> 8, bc.blue >> 8);
-  return Qt;
+  neomacs_display_set_scroll_anchoring (dpyinfo->display_handle,
+                                        !NILP (enabled));
+  return !NILP (enabled) ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-line-highlight",
-       Fneomacs_set_line_highlight,
-       Sneomacs_set_line_highlight, 0, 2, 0,
-       doc: /* Configure current line highlight rendering.
-ENABLED non-nil enables a subtle background on the cursor line.
-Optional COLOR is a color string (default inherits from hl-line face).  */)
-  (Lisp_Object enabled, Lisp_Object color)
+DEFUN ("neomacs-set-centered-column", Fneomacs_set_centered_column,
+       Sneomacs_set_centered_column, 1, 3, 0,
+       doc: /* Center a fixed-width text column in every window.
+When ENABLED is non-nil, the layout engine narrows the text area of
+every window except the minibuffer to COLUMNS characters (default 80)
+and centers it horizontally.  Unlike window margins, this needs no
+per-window Lisp setup and follows window resizes automatically.
+MARGIN-COLOR is a "#rrggbb" string used to fill the space beside the
+column, or nil to use the window background.  */)
+  (Lisp_Object enabled, Lisp_Object columns, Lisp_Object margin_color)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int r = 50, g = 50, b = 80;
-  int opacity = 15;
-
-  if (!NILP (color) && STRINGP (color))
+  int cols = 80;
+  int margin_bg = -1;
+  if (!NILP (columns))
     {
-      Emacs_Color c;
-      if (neomacs_defined_color (NULL, SSDATA (color), &c, false, false))
+      CHECK_FIXNAT (columns);
+      cols = XFIXNUM (columns);
+    }
+  if (STRINGP (margin_color))
+    {
+      const char *s = SSDATA (margin_color);
+      if (s[0] == '#' && strlen (s) == 7)
         {
-          r = c.red >> 8;
-          g = c.green >> 8;
-          b = c.blue >> 8;
-          opacity = 20;
+          unsigned int hex;
+          sscanf (s + 1, "%06x", &hex);
+          margin_bg = hex & 0xFFFFFF;
         }
     }
 
-  neomacs_display_set_line_highlight (
-    dpyinfo->display_handle, on, r, g, b, opacity);
-  return on ? Qt : Qnil;
+  neomacs_display_set_centered_column (dpyinfo->display_handle,
+                                       !NILP (enabled), cols, margin_bg);
+  return !NILP (enabled) ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-show-whitespace",
-       Fneomacs_set_show_whitespace,
-       Sneomacs_set_show_whitespace, 0, 2, 0,
-       doc: /* Configure visible whitespace rendering.
-ENABLED non-nil shows dots for spaces and arrows for tabs.
-Optional COLOR is a color string (default \"gray50\").  */)
-  (Lisp_Object enabled, Lisp_Object color)
+/* Buffer showing the inline suggestion (ghost text), and its modification
+   count when the suggestion was last set or partially accepted.  Any
+   other edit dismisses the suggestion before the next layout.  */
+static Lisp_Object neomacs_ghost_buffer;
+static modiff_count neomacs_ghost_modiff;
+
+static void
+neomacs_ghost_text_clear (void)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (dpyinfo && dpyinfo->display_handle)
+    neomacs_display_set_ghost_text (dpyinfo->display_handle, 0, 0, NULL, 0);
+  neomacs_ghost_buffer = Qnil;
+}
+
+/* Dismiss the inline suggestion if its buffer was edited or killed.  */
+static void
+neomacs_ghost_text_check (void)
+{
+  if (NILP (neomacs_ghost_buffer))
+    return;
+  if (!BUFFER_LIVE_P (XBUFFER (neomacs_ghost_buffer))
+      || BUF_MODIFF (XBUFFER (neomacs_ghost_buffer)) != neomacs_ghost_modiff)
+    neomacs_ghost_text_clear ();
+}
+
+DEFUN ("neomacs-set-ghost-text", Fneomacs_set_ghost_text,
+       Sneomacs_set_ghost_text, 1, 3, 0,
+       doc: /* Show TEXT as an inline suggestion at point.
+The suggestion is drawn after point in COLOR (a "#rrggbb" string,
+default a dim gray) without changing the buffer.  It may span several
+lines.  POSITION defaults to point; the suggestion is only visible
+while point is there.  Editing the buffer dismisses it; use
+`neomacs-accept-ghost-text' to insert it.  TEXT nil or empty removes
+the current suggestion.  Returns t if the suggestion is shown.
+Requires the Rust layout engine.  */)
+  (Lisp_Object text, Lisp_Object position, Lisp_Object color)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int r = 128, g = 128, b = 128;
-  int opacity = 30;
+  if (!NILP (text))
+    CHECK_STRING (text);
+  if (NILP (text) || SCHARS (text) == 0)
+    {
+      neomacs_ghost_text_clear ();
+      return Qnil;
+    }
 
-  if (!NILP (color) && STRINGP (color))
+  ptrdiff_t pos = PT;
+  if (!NILP (position))
     {
-      Emacs_Color c;
-      if (neomacs_defined_color (NULL, SSDATA (color), &c, false, false))
+      CHECK_FIXNUM_COERCE_MARKER (position);
+      pos = clip_to_bounds (BEGV, XFIXNUM (position), ZV);
+    }
+
+  unsigned int fg = 0x808080;
+  if (STRINGP (color))
+    {
+      const char *s = SSDATA (color);
+      if (s[0] == '#' && strlen (s) == 7)
         {
-          r = c.red >> 8;
-          g = c.green >> 8;
-          b = c.blue >> 8;
-          opacity = 35;
+          unsigned int hex;
+          sscanf (s + 1, "%06x", &hex);
+          fg = hex & 0xFFFFFF;
         }
     }
 
-  neomacs_display_set_show_whitespace (
-    dpyinfo->display_handle, on, r, g, b, opacity);
-  return on ? Qt : Qnil;
+  int shown = neomacs_display_set_ghost_text (dpyinfo->display_handle,
+                                              (uint64_t) (uintptr_t) current_buffer,
+                                              pos, SSDATA (ENCODE_UTF_8 (text)),
+                                              fg);
+  if (!shown)
+    {
+      neomacs_ghost_buffer = Qnil;
+      return Qnil;
+    }
+  XSETBUFFER (neomacs_ghost_buffer, current_buffer);
+  neomacs_ghost_modiff = MODIFF;
+  return Qt;
 }
 
-DEFUN ("neomacs-set-inactive-dim",
-       Fneomacs_set_inactive_dim,
-       Sneomacs_set_inactive_dim, 0, 2, 0,
-       doc: /* Configure inactive window dimming.
-ENABLED non-nil dims inactive windows with a dark overlay.
-Optional OPACITY is a number 0.0-1.0 for dimming strength (default 0.15).  */)
-  (Lisp_Object enabled, Lisp_Object opacity)
+DEFUN ("neomacs-accept-ghost-text", Fneomacs_accept_ghost_text,
+       Sneomacs_accept_ghost_text, 0, 1, 0,
+       doc: /* Insert the current inline suggestion, or part of it.
+HOW is `word' to accept the next word, `line' to accept up to the end
+of the suggestion's current line, and nil to accept all of it.  The
+rest of the suggestion stays visible after the inserted text.
+Returns the inserted string, or nil if there is no suggestion.  */)
+  (Lisp_Object how)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int op = 15; /* default 0.15 */
+  neomacs_ghost_text_check ();
+  if (NILP (neomacs_ghost_buffer))
+    return Qnil;
 
-  if (NUMBERP (opacity))
+  int mode = EQ (how, Qword) ? 0 : EQ (how, Qline) ? 1 : 2;
+  int64_t pos = 0;
+  char *accepted = neomacs_display_accept_ghost_text (dpyinfo->display_handle,
+                                                      mode, &pos);
+  if (!accepted)
     {
-      double val = XFLOATINT (opacity);
-      if (val < 0.0) val = 0.0;
-      if (val > 1.0) val = 1.0;
-      op = (int)(val * 100.0);
+      neomacs_ghost_buffer = Qnil;
+      return Qnil;
     }
-
-  neomacs_display_set_inactive_dim (dpyinfo->display_handle, on, op);
-  return on ? Qt : Qnil;
+  Lisp_Object str = build_string (accepted);
+  neomacs_display_free_string (accepted);
+
+  Lisp_Object buffer = neomacs_ghost_buffer;
+  specpdl_ref count = SPECPDL_INDEX ();
+  record_unwind_current_buffer ();
+  set_buffer_internal (XBUFFER (buffer));
+  SET_PT (clip_to_bounds (BEGV, pos, ZV));
+  Finsert (1, &str);
+  /* Our own insertion must not dismiss the remaining suggestion.  */
+  if (EQ (neomacs_ghost_buffer, buffer))
+    neomacs_ghost_modiff = MODIFF;
+  unbind_to (count, Qnil);
+  return str;
+}
+
+DEFUN ("neomacs-dismiss-ghost-text", Fneomacs_dismiss_ghost_text,
+       Sneomacs_dismiss_ghost_text, 0, 0, 0,
+       doc: /* Remove the current inline suggestion without inserting it.  */)
+  (void)
+{
+  neomacs_ghost_text_clear ();
+  return Qnil;
 }
 
-DEFUN ("neomacs-set-mode-line-separator",
-       Fneomacs_set_mode_line_separator,
-       Sneomacs_set_mode_line_separator, 0, 3, 0,
-       doc: /* Configure mode-line separator rendering.
-STYLE is a symbol: nil (none), `line' (thin line), `shadow' (shadow effect),
-or `gradient' (gradient fade).
-Optional COLOR is a color string (default \"black\").
-Optional HEIGHT is the separator height in pixels (default 3).  */)
-  (Lisp_Object style, Lisp_Object color, Lisp_Object height)
+DEFUN ("neomacs-ghost-text-p", Fneomacs_ghost_text_p,
+       Sneomacs_ghost_text_p, 0, 0, 0,
+       doc: /* Return the buffer showing an inline suggestion, or nil.  */)
+  (void)
 {
-  struct neomacs_display_info *dpyinfo = neomacs_display_list;
-  if (!dpyinfo || !dpyinfo->display_handle)
-    return Qnil;
-
-  int s = 0;
-  if (EQ (style, intern ("line")))
-    s = 1;
-  else if (EQ (style, intern ("shadow")))
-    s = 2;
-  else if (EQ (style, intern ("gradient")))
-    s = 3;
+  neomacs_ghost_text_check ();
+  return neomacs_ghost_buffer;
+}
 
-  int r = 0, g = 0, b = 0;
-  if (!NILP (color) && STRINGP (color))
+/* Return the 0xRRGGBB value of COLOR, a "#rrggbb" string, or DFLT.  */
+static unsigned int
+neomacs_annotation_color (Lisp_Object color, unsigned int dflt)
+{
+  if (STRINGP (color))
     {
-      Emacs_Color c;
-      if (neomacs_defined_color (NULL, SSDATA (color), &c, false, false))
+      const char *s = SSDATA (color);
+      if (s[0] == '#' && strlen (s) == 7)
         {
-          r = c.red >> 8;
-          g = c.green >> 8;
-          b = c.blue >> 8;
+          unsigned int hex;
+          sscanf (s + 1, "%06x", &hex);
+          return hex & 0xFFFFFF;
         }
     }
-
-  int h = 3;
-  if (FIXNUMP (height))
-    h = (int) XFIXNUM (height);
-  if (h < 1) h = 1;
-  if (h > 20) h = 20;
-
-  neomacs_display_set_mode_line_separator (
-    dpyinfo->display_handle, s, r, g, b, h);
-  return s > 0 ? Qt : Qnil;
+  return dflt;
 }
 
-DEFUN ("neomacs-set-cursor-glow",
-       Fneomacs_set_cursor_glow,
-       Sneomacs_set_cursor_glow, 0, 3, 0,
-       doc: /* Configure cursor glow effect.
-ENABLED non-nil enables a soft glow around the cursor.
-Optional COLOR is a color string (default cursor color).
-Optional RADIUS is the glow radius in pixels (default 30).  */)
-  (Lisp_Object enabled, Lisp_Object color, Lisp_Object radius)
+DEFUN ("neomacs-annotation-add", Fneomacs_annotation_add,
+       Sneomacs_annotation_add, 2, 6, 0,
+       doc: /* Attach the note TEXT to position POS of the current buffer.
+ICON, if non-nil, is a short string (e.g. an emoji) shown before TEXT.
+FG and BG are "#rrggbb" strings for the note's text and background.
+STYLE `card' draws the note as a card floating over the right edge of
+the text; otherwise it is drawn in the right margin, or as a card if
+the window has no right margin.  Notes on nearby lines stack instead
+of overlapping.  Call `neomacs-annotation-adjust' from
+`after-change-functions' so notes follow edits.
+Returns the annotation id, or nil.  Requires the Rust layout engine.  */)
+  (Lisp_Object pos, Lisp_Object text, Lisp_Object icon,
+   Lisp_Object fg, Lisp_Object bg, Lisp_Object style)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int r = 102, g = 153, b = 255; /* light blue default */
-  int rad = 30;
-  int opacity = 15; /* 0.15 */
-
-  if (!NILP (color) && STRINGP (color))
-    {
-      Emacs_Color c;
-      if (neomacs_defined_color (NULL, SSDATA (color), &c, false, false))
-        {
-          r = c.red >> 8;
-          g = c.green >> 8;
-          b = c.blue >> 8;
-        }
-    }
-
-  if (FIXNUMP (radius))
-    {
-      rad = (int) XFIXNUM (radius);
-      if (rad < 5) rad = 5;
-      if (rad > 100) rad = 100;
-    }
+  CHECK_FIXNUM_COERCE_MARKER (pos);
+  CHECK_STRING (text);
+  if (!NILP (icon))
+    CHECK_STRING (icon);
 
-  neomacs_display_set_cursor_glow (
-    dpyinfo->display_handle, on, r, g, b, rad, opacity);
-  return on ? Qt : Qnil;
+  uint32_t id = neomacs_display_annotation_add
+    (dpyinfo->display_handle, (uint64_t) (uintptr_t) current_buffer,
+     clip_to_bounds (BEG, XFIXNUM (pos), Z),
+     SSDATA (ENCODE_UTF_8 (text)),
+     NILP (icon) ? NULL : SSDATA (ENCODE_UTF_8 (icon)),
+     neomacs_annotation_color (fg, 0xE0E0E0),
+     neomacs_annotation_color (bg, 0x303040),
+     EQ (style, Qcard) ? 1 : 0);
+  return id ? make_fixnum (id) : Qnil;
 }
 
-DEFUN ("neomacs-set-cursor-pulse",
-       Fneomacs_set_cursor_pulse,
-       Sneomacs_set_cursor_pulse, 0, 2, 0,
-       doc: /* Configure cursor pulse animation.
-ENABLED non-nil enables sinusoidal glow modulation on idle cursor.
-Optional SPEED is the pulse frequency as integer (100 = 1.0 Hz, default 100).
-Requires cursor glow to be enabled for visible effect.  */)
-  (Lisp_Object enabled, Lisp_Object speed)
+DEFUN ("neomacs-annotation-remove", Fneomacs_annotation_remove,
+       Sneomacs_annotation_remove, 1, 1, 0,
+       doc: /* Remove the annotation with id ID.
+Returns t if it existed.  */)
+  (Lisp_Object id)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int spd = 100;  /* 1.0 Hz */
-  int min_opacity = 30;  /* 0.3 */
-
-  if (FIXNUMP (speed))
-    {
-      spd = (int) XFIXNUM (speed);
-      if (spd < 10) spd = 10;
-      if (spd > 500) spd = 500;
-    }
-
-  neomacs_display_set_cursor_pulse (
-    dpyinfo->display_handle, on, spd, min_opacity);
-  return on ? Qt : Qnil;
+  CHECK_FIXNAT (id);
+  return neomacs_display_annotation_remove (dpyinfo->display_handle,
+                                            XFIXNAT (id))
+    ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-focus-mode",
-       Fneomacs_set_focus_mode,
-       Sneomacs_set_focus_mode, 0, 2, 0,
-       doc: /* Configure focus mode (dim text outside current paragraph).
-ENABLED non-nil enables focus mode where only the paragraph around the
-cursor is fully visible.  Surrounding text is dimmed.
-OPACITY is an integer 0-100 for the dimming overlay opacity (default 40).  */)
-  (Lisp_Object enabled, Lisp_Object opacity)
+DEFUN ("neomacs-annotation-clear", Fneomacs_annotation_clear,
+       Sneomacs_annotation_clear, 0, 1, 0,
+       doc: /* Remove all annotations of BUFFER (default the current buffer).  */)
+  (Lisp_Object buffer)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int op = 40;  /* 0.4 default */
-  if (FIXNUMP (opacity))
-    op = XFIXNUM (opacity);
-
-  neomacs_display_set_focus_mode (
-    dpyinfo->display_handle, on, op);
-  return on ? Qt : Qnil;
+  struct buffer *b = decode_buffer (buffer);
+  neomacs_display_annotation_clear (dpyinfo->display_handle,
+                                    (uint64_t) (uintptr_t) b);
+  return Qnil;
 }
 
-DEFUN ("neomacs-set-minimap",
-       Fneomacs_set_minimap,
-       Sneomacs_set_minimap, 0, 2, 0,
-       doc: /* Configure minimap code overview column.
-ENABLED non-nil shows a minimap on the right side of each window.
-WIDTH is the minimap column width in pixels (default 80).  */)
-  (Lisp_Object enabled, Lisp_Object width)
+DEFUN ("neomacs-annotation-adjust", Fneomacs_annotation_adjust,
+       Sneomacs_annotation_adjust, 3, 3, 0,
+       doc: /* Move the current buffer's annotations after a change.
+BEG, END and OLD-LEN are as for `after-change-functions': the text
+from BEG to END replaced OLD-LEN characters.  */)
+  (Lisp_Object beg, Lisp_Object end, Lisp_Object old_len)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int w = 80;
-  if (FIXNUMP (width))
-    w = XFIXNUM (width);
+  CHECK_FIXNUM (beg);
+  CHECK_FIXNUM (end);
+  CHECK_FIXNAT (old_len);
+  neomacs_display_annotation_adjust (dpyinfo->display_handle,
+                                     (uint64_t) (uintptr_t) current_buffer,
+                                     XFIXNUM (beg),
+                                     max (0, XFIXNUM (end) - XFIXNUM (beg)),
+                                     XFIXNAT (old_len));
+  return Qnil;
+}
+
+DEFUN ("neomacs-set-scroll-bar-marks", Fneomacs_set_scroll_bar_marks,
+       Sneomacs_set_scroll_bar_marks, 2, 4, 0,
+       doc: /* Show tick marks for POSITIONS along the vertical scroll bar.
+CATEGORY is a symbol or string naming the set of marks, such as
+`bookmarks', `diagnostics', `search' or `hunks'; setting a category
+replaces its previous marks.  POSITIONS is a list of buffer positions
+or markers in BUFFER (default the current buffer); nil removes the
+category.  COLOR is a "#rrggbb" string for the ticks.
+Use `neomacs-scroll-bar-mark-at' to map a scroll bar click to a mark.
+Requires the Rust layout engine.  */)
+  (Lisp_Object category, Lisp_Object positions, Lisp_Object color,
+   Lisp_Object buffer)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
 
-  neomacs_display_set_minimap (
-    dpyinfo->display_handle, on, w);
-  return on ? Qt : Qnil;
+  struct buffer *b = decode_buffer (buffer);
+  if (SYMBOLP (category))
+    category = SYMBOL_NAME (category);
+  CHECK_STRING (category);
+
+  ptrdiff_t count = list_length (positions);
+  int64_t *pos = xmalloc (max (count, 1) * sizeof *pos);
+  ptrdiff_t i = 0;
+  for (Lisp_Object tail = positions; CONSP (tail); tail = XCDR (tail))
+    {
+      Lisp_Object p = XCAR (tail);
+      CHECK_FIXNUM_COERCE_MARKER (p);
+      pos[i++] = XFIXNUM (p);
+    }
+
+  neomacs_display_set_scroll_bar_marks (dpyinfo->display_handle,
+                                        (uint64_t) (uintptr_t) b,
+                                        SSDATA (ENCODE_UTF_8 (category)),
+                                        neomacs_annotation_color (color,
+                                                                  0xE0A000),
+                                        pos, i);
+  xfree (pos);
+  return Qnil;
 }
 
-DEFUN ("neomacs-set-typing-ripple",
-       Fneomacs_set_typing_ripple,
-       Sneomacs_set_typing_ripple, 0, 3, 0,
-       doc: /* Configure typing ripple effect.
-ENABLED non-nil shows expanding ripple circles from cursor on movement.
-MAX-RADIUS is the maximum ripple radius in pixels (default 40).
-DURATION-MS is the ripple duration in milliseconds (default 300).  */)
-  (Lisp_Object enabled, Lisp_Object max_radius, Lisp_Object duration_ms)
+DEFUN ("neomacs-clear-scroll-bar-marks", Fneomacs_clear_scroll_bar_marks,
+       Sneomacs_clear_scroll_bar_marks, 0, 1, 0,
+       doc: /* Remove all scroll bar marks of BUFFER (default the current buffer).  */)
+  (Lisp_Object buffer)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int r = 40;
-  int d = 300;
-  if (FIXNUMP (max_radius))
-    r = XFIXNUM (max_radius);
-  if (FIXNUMP (duration_ms))
-    d = XFIXNUM (duration_ms);
-
-  neomacs_display_set_typing_ripple (
-    dpyinfo->display_handle, on, r, d);
-  return on ? Qt : Qnil;
+  struct buffer *b = decode_buffer (buffer);
+  neomacs_display_clear_scroll_bar_marks (dpyinfo->display_handle,
+                                          (uint64_t) (uintptr_t) b);
+  return Qnil;
 }
 
-DEFUN ("neomacs-set-search-pulse",
-       Fneomacs_set_search_pulse,
-       Sneomacs_set_search_pulse, 0, 1, 0,
-       doc: /* Configure search highlight pulse effect.
-ENABLED non-nil draws a pulsing glow around the current isearch match.
-The isearch face is resolved automatically.  */)
-  (Lisp_Object enabled)
+DEFUN ("neomacs-scroll-bar-mark-at", Fneomacs_scroll_bar_mark_at,
+       Sneomacs_scroll_bar_mark_at, 3, 3, 0,
+       doc: /* Return the position of the scroll bar mark under a click.
+WINDOW's vertical scroll bar was clicked Y pixels from the top of its
+track, which is HEIGHT pixels high; these are the two numbers of the
+scroll bar event's position.  Returns the buffer position of the
+nearest mark within a few pixels of Y, or nil.  */)
+  (Lisp_Object window, Lisp_Object y, Lisp_Object height)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct window *w = decode_live_window (window);
+  struct buffer *b = XBUFFER (w->contents);
+  CHECK_FIXNUM (y);
+  CHECK_FIXNAT (height);
+
+  int64_t pos = neomacs_display_scroll_bar_mark_at (dpyinfo->display_handle,
+                                                    (uint64_t) (uintptr_t) b,
+                                                    BUF_BEGV (b), BUF_ZV (b),
+                                                    XFIXNAT (height),
+                                                    XFIXNUM (y), 3);
+  return pos >= 0 ? make_fixnum (pos) : Qnil;
+}
+
+DEFUN ("neomacs-set-rectangle-region", Fneomacs_set_rectangle_region,
+       Sneomacs_set_rectangle_region, 1, 8, 0,
+       doc: /* Highlight a rectangular region in WINDOW.
+The rectangle covers columns LEFT up to (but not including) RIGHT of
+the lines from the one starting at START through the line containing
+END.  The highlight includes the virtual space past the end of lines
+shorter than RIGHT.  BG is a "#rrggbb" string for its background.
+CURSOR-COLUMN, if non-nil, draws a thin insertion cursor in
+CURSOR-COLOR at that column on every line of the rectangle.
+START nil removes WINDOW's rectangle.  Requires the Rust layout
+engine.  */)
+  (Lisp_Object window, Lisp_Object start, Lisp_Object end,
+   Lisp_Object left, Lisp_Object right, Lisp_Object bg,
+   Lisp_Object cursor_column, Lisp_Object cursor_color)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct window *w = decode_live_window (window);
+  if (NILP (start))
+    {
+      neomacs_display_clear_rectangle_region (dpyinfo->display_handle,
+                                              (int64_t) (intptr_t) w);
+      return Qnil;
+    }
+
+  CHECK_FIXNUM_COERCE_MARKER (start);
+  CHECK_FIXNUM_COERCE_MARKER (end);
+  CHECK_FIXNAT (left);
+  CHECK_FIXNAT (right);
+  if (!NILP (cursor_column))
+    CHECK_FIXNAT (cursor_column);
+
+  neomacs_display_set_rectangle_region
+    (dpyinfo->display_handle, (int64_t) (intptr_t) w,
+     (uint64_t) (uintptr_t) XBUFFER (w->contents),
+     XFIXNUM (start), XFIXNUM (end),
+     min (XFIXNAT (left), INT_MAX), min (XFIXNAT (right), INT_MAX),
+     neomacs_annotation_color (bg, 0x3A5F8F),
+     NILP (cursor_column) ? -1 : min (XFIXNAT (cursor_column), INT_MAX),
+     neomacs_annotation_color (cursor_color, 0xFFFFFF));
+  return Qt;
+}
+
+DEFUN ("neomacs-set-virtual-cursor", Fneomacs_set_virtual_cursor,
+       Sneomacs_set_virtual_cursor, 2, 2, 0,
+       doc: /* Show WINDOW's cursor COLUMNS columns past the end of its line.
+Point must be at the end of a line; the buffer is not changed, the
+cursor is only drawn in the empty space after the line, as if the line
+were padded with COLUMNS spaces.  The cursor returns to point as soon
+as point moves, or when COLUMNS is nil or 0.  Requires the Rust layout
+engine.  */)
+  (Lisp_Object window, Lisp_Object columns)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct window *w = decode_live_window (window);
+  if (!NILP (columns))
+    CHECK_FIXNAT (columns);
+  int cols = NILP (columns) ? 0 : min (XFIXNAT (columns), INT_MAX);
+  /* The layout engine lays out each window with its buffer's point.  */
+  neomacs_display_set_virtual_cursor (dpyinfo->display_handle,
+                                      (int64_t) (intptr_t) w,
+                                      BUF_PT (XBUFFER (w->contents)), cols);
+  return cols > 0 ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-add-table", Fneomacs_add_table, Sneomacs_add_table,
+       2, 4, 0,
+       doc: /* Lay out lines of BUFFER as a table with aligned columns.
+ROWS is a list with one element per table line, in buffer order:
+\(START END CELLS), where START and END are the beginning and end of
+the line and CELLS is either the symbol `rule' for a horizontal rule
+line, or a list of (CELL-START CELL-END TEXT) for the cells of the
+line.  Instead of the typed separators, each cell's TEXT is drawn in
+a column as wide as its widest cell, measured with the real font, with
+grid lines in GRID-COLOR (a "#rrggbb" string) between columns.
+ALIGNS is a list of `left', `center' or `right', one per column.
+The table replaces any table it overlaps.  BUFFER defaults to the
+current buffer.  Requires the Rust layout engine.  */)
+  (Lisp_Object rows, Lisp_Object aligns, Lisp_Object grid_color,
+   Lisp_Object buffer)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct buffer *b = decode_buffer (buffer);
+
+  /* Validate everything and encode the cell texts before allocating,
+     so a signal cannot leak the arrays.  */
+  ptrdiff_t nrows = 0, ncells = 0;
+  Lisp_Object texts = Qnil;
+  for (Lisp_Object tail = rows; CONSP (tail); tail = XCDR (tail))
+    {
+      Lisp_Object row = XCAR (tail);
+      CHECK_CONS (row);
+      Lisp_Object start = Fnth (make_fixnum (0), row);
+      Lisp_Object end = Fnth (make_fixnum (1), row);
+      Lisp_Object cells = Fnth (make_fixnum (2), row);
+      CHECK_FIXNUM_COERCE_MARKER (start);
+      CHECK_FIXNUM_COERCE_MARKER (end);
+      if (!EQ (cells, Qrule))
+        for (Lisp_Object c = cells; CONSP (c); c = XCDR (c))
+          {
+            Lisp_Object cell = XCAR (c);
+            CHECK_CONS (cell);
+            Lisp_Object cell_start = XCAR (cell);
+            Lisp_Object cell_end = Fnth (make_fixnum (1), cell);
+            Lisp_Object text = Fnth (make_fixnum (2), cell);
+            CHECK_FIXNUM_COERCE_MARKER (cell_start);
+            CHECK_FIXNUM_COERCE_MARKER (cell_end);
+            CHECK_STRING (text);
+            texts = Fcons (ENCODE_UTF_8 (text), texts);
+            ncells++;
+          }
+      nrows++;
+    }
+  if (nrows == 0 || nrows > INT_MAX)
+    return Qnil;
+  texts = Fnreverse (texts);
+  ptrdiff_t ncols = list_length (aligns);
+
+  int64_t *row_data = xmalloc (nrows * 3 * sizeof *row_data);
+  int64_t *cell_data = xmalloc (max (ncells, 1) * 2 * sizeof *cell_data);
+  const char **text_data = xmalloc (max (ncells, 1) * sizeof *text_data);
+  int *align_data = xmalloc (max (ncols, 1) * sizeof *align_data);
+
+  ptrdiff_t r = 0, k = 0;
+  for (Lisp_Object tail = rows; CONSP (tail); tail = XCDR (tail), r++)
+    {
+      Lisp_Object row = XCAR (tail);
+      Lisp_Object cells = Fnth (make_fixnum (2), row);
+      row_data[r * 3] = fix_position (Fnth (make_fixnum (0), row));
+      row_data[r * 3 + 1] = fix_position (Fnth (make_fixnum (1), row));
+      if (EQ (cells, Qrule))
+        {
+          row_data[r * 3 + 2] = -1;
+          continue;
+        }
+      ptrdiff_t n = 0;
+      for (Lisp_Object c = cells; CONSP (c); c = XCDR (c), n++, k++)
+        {
+          Lisp_Object cell = XCAR (c);
+          cell_data[k * 2] = fix_position (XCAR (cell));
+          cell_data[k * 2 + 1] = fix_position (Fnth (make_fixnum (1), cell));
+          text_data[k] = SSDATA (XCAR (texts));
+          texts = XCDR (texts);
+        }
+      row_data[r * 3 + 2] = n;
+    }
+
+  ptrdiff_t i = 0;
+  for (Lisp_Object tail = aligns; CONSP (tail); tail = XCDR (tail))
+    {
+      Lisp_Object a = XCAR (tail);
+      align_data[i++] = EQ (a, Qcenter) ? 1 : EQ (a, Qright) ? 2 : 0;
+    }
+
+  neomacs_display_add_table (dpyinfo->display_handle,
+                             (uint64_t) (uintptr_t) b, nrows,
+                             row_data, cell_data, text_data, align_data,
+                             min (ncols, INT_MAX),
+                             neomacs_annotation_color (grid_color,
+                                                       0x808080));
+  xfree (row_data);
+  xfree (cell_data);
+  xfree (text_data);
+  xfree (align_data);
+  return Qt;
+}
+
+DEFUN ("neomacs-clear-tables", Fneomacs_clear_tables,
+       Sneomacs_clear_tables, 0, 1, 0,
+       doc: /* Remove all tables added with `neomacs-add-table' in BUFFER.
+BUFFER defaults to the current buffer.  */)
+  (Lisp_Object buffer)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct buffer *b = decode_buffer (buffer);
+  neomacs_display_clear_tables (dpyinfo->display_handle,
+                                (uint64_t) (uintptr_t) b);
+  return Qnil;
+}
+
+DEFUN ("neomacs-set-command-blocks", Fneomacs_set_command_blocks,
+       Sneomacs_set_command_blocks, 1, 3, 0,
+       doc: /* Draw the shell commands BLOCKS of BUFFER as separated blocks.
+BLOCKS is a list of (START OUTPUT-START END EXIT-CODE DURATION COLLAPSED
+HIDDEN-LINES), one per command: START is the beginning of its prompt,
+OUTPUT-START the beginning of its output and END the end of the output.
+EXIT-CODE is the command's exit status and DURATION its run time in
+milliseconds, or nil if unknown.  COLLAPSED non-nil means the output is
+hidden (by an `invisible' property), HIDDEN-LINES lines of it.
+The rows of each block get a tinted background and a bar in its status
+color, and its prompt row shows a collapse indicator, the status and
+the run time.  COLORS is a list of "#rrggbb" strings (TINT SUCCESS
+FAILURE LABEL).  The blocks replace those set before; nil removes
+them.  BUFFER defaults to the current buffer.  Requires the Rust layout
+engine.  */)
+  (Lisp_Object blocks, Lisp_Object colors, Lisp_Object buffer)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct buffer *b = decode_buffer (buffer);
+
+  /* Validate everything before allocating, so a signal cannot leak
+     the array.  */
+  ptrdiff_t n = 0;
+  for (Lisp_Object tail = blocks; CONSP (tail); tail = XCDR (tail), n++)
+    {
+      Lisp_Object block = XCAR (tail);
+      CHECK_CONS (block);
+      for (int i = 0; i < 3; i++)
+        {
+          Lisp_Object pos = Fnth (make_fixnum (i), block);
+          CHECK_FIXNUM_COERCE_MARKER (pos);
+        }
+      Lisp_Object exit_code = Fnth (make_fixnum (3), block);
+      Lisp_Object duration = Fnth (make_fixnum (4), block);
+      Lisp_Object hidden = Fnth (make_fixnum (6), block);
+      if (!NILP (exit_code))
+        CHECK_FIXNUM (exit_code);
+      if (!NILP (duration))
+        CHECK_FIXNAT (duration);
+      if (!NILP (hidden))
+        CHECK_FIXNAT (hidden);
+    }
+  if (n > INT_MAX / 7)
+    return Qnil;
+
+  uint32_t color_data[4];
+  static const uint32_t color_defaults[4]
+    = { 0x202830, 0x4CAF50, 0xE05050, 0x808080 };
+  for (int i = 0; i < 4; i++)
+    color_data[i] = neomacs_annotation_color (Fnth (make_fixnum (i), colors),
+                                              color_defaults[i]);
+
+  int64_t *data = xmalloc (max (n, 1) * 7 * sizeof *data);
+  ptrdiff_t k = 0;
+  for (Lisp_Object tail = blocks; CONSP (tail); tail = XCDR (tail), k++)
+    {
+      Lisp_Object block = XCAR (tail);
+      Lisp_Object exit_code = Fnth (make_fixnum (3), block);
+      Lisp_Object duration = Fnth (make_fixnum (4), block);
+      Lisp_Object hidden = Fnth (make_fixnum (6), block);
+      int64_t *d = data + k * 7;
+      d[0] = fix_position (Fnth (make_fixnum (0), block));
+      d[1] = fix_position (Fnth (make_fixnum (1), block));
+      d[2] = fix_position (Fnth (make_fixnum (2), block));
+      d[3] = NILP (exit_code) ? 0 : XFIXNUM (exit_code);
+      d[4] = NILP (duration) ? -1 : XFIXNAT (duration);
+      d[5] = NILP (hidden) ? 0 : XFIXNAT (hidden);
+      d[6] = ((NILP (Fnth (make_fixnum (5), block)) ? 0 : 1)
+              | (NILP (exit_code) ? 0 : 2));
+    }
+
+  neomacs_display_set_command_blocks (dpyinfo->display_handle,
+                                      (uint64_t) (uintptr_t) b, n,
+                                      data, color_data);
+  xfree (data);
+  return Qt;
+}
+
+DEFUN ("neomacs-set-chat-view", Fneomacs_set_chat_view,
+       Sneomacs_set_chat_view, 1, 4, 0,
+       doc: /* Show BUFFER as a chat buffer whose input area begins at INPUT-START.
+The input area is the prompt and the message being typed, up to the
+end of the buffer.  When a window on BUFFER is scrolled up so far that
+the input area is below its last row, the input is drawn pinned to the
+bottom of the window over the rows there, and above it a pill saying
+how many messages arrived, UNREAD, unless that is nil or zero.  Clicks
+on the pill or the pinned input move point into the input area.
+COLORS is a list of "#rrggbb" strings (INPUT-BACKGROUND SEPARATOR
+PILL-BACKGROUND PILL-FOREGROUND).  INPUT-START nil shows BUFFER as an
+ordinary buffer again.  BUFFER defaults to the current buffer.
+Requires the Rust layout engine.  */)
+  (Lisp_Object input_start, Lisp_Object unread, Lisp_Object colors,
+   Lisp_Object buffer)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct buffer *b = decode_buffer (buffer);
+  if (!NILP (input_start))
+    CHECK_FIXNUM_COERCE_MARKER (input_start);
+  if (!NILP (unread))
+    CHECK_FIXNAT (unread);
+
+  uint32_t color_data[4];
+  static const uint32_t color_defaults[4]
+    = { 0x1E232B, 0x808080, 0x2F66C4, 0xFFFFFF };
+  for (int i = 0; i < 4; i++)
+    color_data[i] = neomacs_annotation_color (Fnth (make_fixnum (i), colors),
+                                              color_defaults[i]);
+
+  neomacs_display_set_chat_view (dpyinfo->display_handle,
+                                 (uint64_t) (uintptr_t) b,
+                                 NILP (input_start) ? 0 : XFIXNUM (input_start),
+                                 NILP (unread) ? 0 : min (XFIXNAT (unread), INT_MAX),
+                                 color_data);
+  return Qt;
+}
+
+DEFUN ("neomacs-pulse-region", Fneomacs_pulse_region,
+       Sneomacs_pulse_region, 2, 5, 0,
+       doc: /* Flash a fading highlight over the text from START to END.
+The highlight is drawn above the text in every window showing it, in
+the background color of FACE (default `neomacs-pulse'), and fades out
+over DURATION seconds (default 0.4).  Use it to show where a command
+changed text, e.g. after yanking, killing or indenting.  BUFFER
+defaults to the current buffer.  Requires the Rust layout engine.  */)
+  (Lisp_Object start, Lisp_Object end, Lisp_Object face,
+   Lisp_Object duration, Lisp_Object buffer)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct buffer *b = decode_buffer (buffer);
+  CHECK_FIXNUM_COERCE_MARKER (start);
+  CHECK_FIXNUM_COERCE_MARKER (end);
+  if (!NILP (duration))
+    CHECK_NUMBER (duration);
+  if (NILP (face))
+    face = Qneomacs_pulse;
+  CHECK_SYMBOL (face);
+
+  EMACS_INT from = min (XFIXNUM (start), XFIXNUM (end));
+  EMACS_INT to = max (XFIXNUM (start), XFIXNUM (end));
+  double seconds = NILP (duration) ? 0.4 : XFLOATINT (duration);
+  if (from == to || !(seconds > 0))
+    return Qnil;
+
+  uint32_t color = 0xFFD54F;
+  struct frame *f = SELECTED_FRAME ();
+  int face_id = lookup_named_face (NULL, f, face, false);
+  if (face_id >= 0)
+    {
+      struct face *pface = FACE_FROM_ID_OR_NULL (f, face_id);
+      if (pface && !pface->background_defaulted_p)
+        {
+          unsigned long bg = pface->background;
+          color = ((RED_FROM_ULONG (bg) << 16)
+                   | (GREEN_FROM_ULONG (bg) << 8)
+                   | BLUE_FROM_ULONG (bg));
+        }
+    }
+
+  neomacs_display_pulse_region (dpyinfo->display_handle,
+                                (uint64_t) (uintptr_t) b, from, to, color,
+                                (int) min (seconds * 1000, 60000));
+  return Qt;
+}
+
+DEFUN ("neomacs-link-add", Fneomacs_link_add,
+       Sneomacs_link_add, 3, 5, 0,
+       doc: /* Make the text from START to END a link to TARGET.
+TARGET is a string passed to `neomacs-link-follow-function' when the
+link is followed with mouse-2 or C-mouse-1; the mouse pointer turns
+into a hand over the link and its help-echo shows TARGET.  The text is
+drawn underlined in COLOR, a "#rrggbb" string.  BUFFER defaults to the
+current buffer.  Call `neomacs-link-adjust' from `after-change-functions'
+so links follow edits.  Returns the link id, or nil.  Requires the Rust
+layout engine.  */)
+  (Lisp_Object start, Lisp_Object end, Lisp_Object target,
+   Lisp_Object color, Lisp_Object buffer)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct buffer *b = decode_buffer (buffer);
+  CHECK_FIXNUM_COERCE_MARKER (start);
+  CHECK_FIXNUM_COERCE_MARKER (end);
+  CHECK_STRING (target);
+
+  uint32_t id = neomacs_display_link_add
+    (dpyinfo->display_handle, (uint64_t) (uintptr_t) b,
+     min (XFIXNUM (start), XFIXNUM (end)),
+     max (XFIXNUM (start), XFIXNUM (end)),
+     SSDATA (ENCODE_UTF_8 (target)),
+     neomacs_annotation_color (color, 0x6AB0F3));
+  return id ? make_fixnum (id) : Qnil;
+}
+
+DEFUN ("neomacs-link-remove", Fneomacs_link_remove,
+       Sneomacs_link_remove, 1, 1, 0,
+       doc: /* Remove the link with id ID.
+Returns t if it existed.  */)
+  (Lisp_Object id)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  CHECK_FIXNAT (id);
+  return neomacs_display_link_remove (dpyinfo->display_handle, XFIXNAT (id))
+    ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-link-clear", Fneomacs_link_clear,
+       Sneomacs_link_clear, 0, 1, 0,
+       doc: /* Remove all links of BUFFER (default the current buffer).  */)
+  (Lisp_Object buffer)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct buffer *b = decode_buffer (buffer);
+  neomacs_display_link_clear (dpyinfo->display_handle,
+                              (uint64_t) (uintptr_t) b);
+  return Qnil;
+}
+
+DEFUN ("neomacs-link-adjust", Fneomacs_link_adjust,
+       Sneomacs_link_adjust, 3, 3, 0,
+       doc: /* Move the current buffer's links after a change.
+BEG, END and OLD-LEN are as for `after-change-functions': the text
+from BEG to END replaced OLD-LEN characters.  Links whose text was
+deleted are removed.  */)
+  (Lisp_Object beg, Lisp_Object end, Lisp_Object old_len)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  CHECK_FIXNUM (beg);
+  CHECK_FIXNUM (end);
+  CHECK_FIXNAT (old_len);
+  neomacs_display_link_adjust (dpyinfo->display_handle,
+                               (uint64_t) (uintptr_t) current_buffer,
+                               XFIXNUM (beg),
+                               max (0, XFIXNUM (end) - XFIXNUM (beg)),
+                               XFIXNAT (old_len));
+  return Qnil;
+}
+
+DEFUN ("neomacs-link-at", Fneomacs_link_at,
+       Sneomacs_link_at, 1, 2, 0,
+       doc: /* Return the link covering position POS of BUFFER, or nil.
+The value is (ID . TARGET).  BUFFER defaults to the current buffer.  */)
+  (Lisp_Object pos, Lisp_Object buffer)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct buffer *b = decode_buffer (buffer);
+  CHECK_FIXNUM_COERCE_MARKER (pos);
+  char target[1024];
+  uint32_t id = neomacs_display_link_at (dpyinfo->display_handle,
+                                         (uint64_t) (uintptr_t) b,
+                                         XFIXNUM (pos), target,
+                                         sizeof target);
+  return id ? Fcons (make_fixnum (id), build_string (target)) : Qnil;
+}
+
+DEFUN ("neomacs-set-color-swatches", Fneomacs_set_color_swatches,
+       Sneomacs_set_color_swatches, 3, 4, 0,
+       doc: /* Show color swatches before the color literals between START and END.
+SWATCHES is a list of (BEG END . COLOR), one per literal from BEG to
+END, where COLOR is the "#rrggbb" string it denotes; it replaces the
+swatches of BUFFER (default the current buffer) whose literal starts
+between START and END.  Each swatch is a square of COLOR two columns
+wide, drawn before the literal without being part of the text.  Call
+`neomacs-color-swatches-adjust' from `after-change-functions' so
+swatches follow edits.  Requires the Rust layout engine.  */)
+  (Lisp_Object start, Lisp_Object end, Lisp_Object swatches,
+   Lisp_Object buffer)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct buffer *b = decode_buffer (buffer);
+  CHECK_FIXNUM_COERCE_MARKER (start);
+  CHECK_FIXNUM_COERCE_MARKER (end);
+
+  /* Validate before allocating, so a signal cannot leak the arrays.  */
+  ptrdiff_t count = 0;
+  for (Lisp_Object tail = swatches; CONSP (tail); tail = XCDR (tail))
+    {
+      Lisp_Object swatch = XCAR (tail);
+      CHECK_CONS (swatch);
+      CHECK_FIXNUM (XCAR (swatch));
+      CHECK_CONS (XCDR (swatch));
+      CHECK_FIXNUM (XCAR (XCDR (swatch)));
+      CHECK_STRING (XCDR (XCDR (swatch)));
+      count++;
+    }
+
+  int64_t *ranges = xmalloc (max (count, 1) * 2 * sizeof *ranges);
+  uint32_t *colors = xmalloc (max (count, 1) * sizeof *colors);
+  ptrdiff_t i = 0;
+  for (Lisp_Object tail = swatches; CONSP (tail); tail = XCDR (tail), i++)
+    {
+      Lisp_Object swatch = XCAR (tail);
+      ranges[2 * i] = XFIXNUM (XCAR (swatch));
+      ranges[2 * i + 1] = XFIXNUM (XCAR (XCDR (swatch)));
+      colors[i] = neomacs_annotation_color (XCDR (XCDR (swatch)), 0);
+    }
+
+  neomacs_display_set_color_swatches (dpyinfo->display_handle,
+                                      (uint64_t) (uintptr_t) b,
+                                      min (XFIXNUM (start), XFIXNUM (end)),
+                                      max (XFIXNUM (start), XFIXNUM (end)),
+                                      ranges, colors, (int) count);
+  xfree (ranges);
+  xfree (colors);
+  return Qnil;
+}
+
+DEFUN ("neomacs-color-swatches-clear", Fneomacs_color_swatches_clear,
+       Sneomacs_color_swatches_clear, 0, 1, 0,
+       doc: /* Remove all color swatches of BUFFER (default the current buffer).  */)
+  (Lisp_Object buffer)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct buffer *b = decode_buffer (buffer);
+  neomacs_display_clear_color_swatches (dpyinfo->display_handle,
+                                        (uint64_t) (uintptr_t) b);
+  return Qnil;
+}
+
+DEFUN ("neomacs-color-swatches-adjust", Fneomacs_color_swatches_adjust,
+       Sneomacs_color_swatches_adjust, 3, 3, 0,
+       doc: /* Move the current buffer's color swatches after a change.
+BEG, END and OLD-LEN are as for `after-change-functions'.  Swatches
+whose literal was deleted are removed.  */)
+  (Lisp_Object beg, Lisp_Object end, Lisp_Object old_len)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  CHECK_FIXNUM (beg);
+  CHECK_FIXNUM (end);
+  CHECK_FIXNAT (old_len);
+  neomacs_display_adjust_color_swatches (dpyinfo->display_handle,
+                                         (uint64_t) (uintptr_t) current_buffer,
+                                         XFIXNUM (beg),
+                                         max (0, XFIXNUM (end) - XFIXNUM (beg)),
+                                         XFIXNAT (old_len));
+  return Qnil;
+}
+
+DEFUN ("neomacs-set-breadcrumb-bar", Fneomacs_set_breadcrumb_bar,
+       Sneomacs_set_breadcrumb_bar, 2, 4, 0,
+       doc: /* Show SEGMENTS as a breadcrumb bar in the header line of WINDOW.
+SEGMENTS is a list of (KIND . LABEL), outermost first, where KIND is
+`directory', `file' or `symbol' and LABEL a string.  The labels are
+drawn instead of the header line's text, in its face, joined by
+SEPARATOR (a string, default " > ") in SEPARATOR-COLOR (a "#rrggbb"
+string, default the header line's foreground).  When they do not fit,
+leading directories collapse into "...", then long names are cut.
+WINDOW must have a header line for the bar to show.  SEGMENTS nil
+gives the header line back to its text; WINDOW may then be a deleted
+window.  Use
+`neomacs-breadcrumb-bar-segment-at' to map a click to a segment.
+Requires the Rust layout engine.  */)
+  (Lisp_Object window, Lisp_Object segments, Lisp_Object separator,
+   Lisp_Object separator_color)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  /* WINDOW may be dead, to clear the bar it had.  */
+  struct window *w = decode_any_window (window);
+  if (!NILP (separator))
+    CHECK_STRING (separator);
+
+  /* Validate everything and encode the labels before allocating, so a
+     signal cannot leak the arrays.  */
+  ptrdiff_t count = 0;
+  Lisp_Object labels = Qnil;
+  for (Lisp_Object tail = segments; CONSP (tail); tail = XCDR (tail))
+    {
+      Lisp_Object segment = XCAR (tail);
+      CHECK_CONS (segment);
+      CHECK_SYMBOL (XCAR (segment));
+      CHECK_STRING (XCDR (segment));
+      labels = Fcons (ENCODE_UTF_8 (XCDR (segment)), labels);
+      count++;
+    }
+  if (count > INT_MAX)
+    return Qnil;
+  labels = Fnreverse (labels);
+  if (!NILP (separator))
+    separator = ENCODE_UTF_8 (separator);
+
+  uint8_t *kinds = xmalloc (max (count, 1) * sizeof *kinds);
+  const char **label_data = xmalloc (max (count, 1) * sizeof *label_data);
+  ptrdiff_t i = 0;
+  for (Lisp_Object tail = segments; CONSP (tail);
+       tail = XCDR (tail), labels = XCDR (labels), i++)
+    {
+      Lisp_Object kind = XCAR (XCAR (tail));
+      kinds[i] = EQ (kind, Qdirectory) ? 0 : EQ (kind, Qfile) ? 1 : 2;
+      label_data[i] = SSDATA (XCAR (labels));
+    }
+
+  neomacs_display_set_breadcrumb_bar (dpyinfo->display_handle,
+                                      (int64_t) (intptr_t) w,
+                                      kinds, label_data, count,
+                                      NILP (separator)
+                                      ? NULL : SSDATA (separator),
+                                      neomacs_annotation_color
+                                        (separator_color, 0));
+  xfree (kinds);
+  xfree (label_data);
+  return count > 0 ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-breadcrumb-bar-segment-at", Fneomacs_breadcrumb_bar_segment_at,
+       Sneomacs_breadcrumb_bar_segment_at, 2, 2, 0,
+       doc: /* Return the index of the breadcrumb bar segment of WINDOW at X.
+X is in pixels from the left edge of WINDOW, as in the position of a
+`header-line' mouse event.  The index counts from 0 in the list given
+to `neomacs-set-breadcrumb-bar'; a click on "..." selects the innermost
+segment it hides.  Return nil if X is not over a segment.  */)
+  (Lisp_Object window, Lisp_Object x)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  struct window *w = decode_live_window (window);
+  CHECK_FIXNUM (x);
+  int index = neomacs_display_breadcrumb_bar_segment_at
+    (dpyinfo->display_handle, (int64_t) (intptr_t) w,
+     clip_to_bounds (INT_MIN, XFIXNUM (x), INT_MAX));
+  return index >= 0 ? make_fixnum (index) : Qnil;
+}
+
+DEFUN ("neomacs-set-pointer-auto-hide", Fneomacs_set_pointer_auto_hide,
+       Sneomacs_set_pointer_auto_hide, 1, 1, 0,
+       doc: /* Non-nil ENABLED hides the mouse pointer as soon as a key is typed.
+The pointer reappears when the mouse moves.  Unlike
+`make-pointer-invisible', this acts in the display engine, before
+Emacs reads the key.  */)
+  (Lisp_Object enabled)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  neomacs_display_set_pointer_auto_hide (dpyinfo->display_handle,
+                                         !NILP (enabled));
+  return enabled;
+}
+
+DEFUN ("neomacs-set-wheel-config", Fneomacs_set_wheel_config,
+       Sneomacs_set_wheel_config, 6, 6, 0,
+       doc: /* Set how the mouse wheel and touchpad scroll.
+WHEEL-LINES is the number of lines a wheel notch scrolls (each notch
+sends that many as the event's `event-line-count').  TOUCHPAD-SPEED
+multiplies touchpad pixel deltas.  Non-nil WHEEL-REVERSE and
+TOUCHPAD-REVERSE reverse each device's direction.  Non-nil
+SHIFT-HORIZONTAL turns Shift+wheel into horizontal scrolling, and
+non-nil ALT-PAGE makes Alt+wheel scroll a page of the window under the
+mouse per notch; the modifier is then removed from the event.  */)
+  (Lisp_Object wheel_lines, Lisp_Object wheel_reverse,
+   Lisp_Object touchpad_speed, Lisp_Object touchpad_reverse,
+   Lisp_Object shift_horizontal, Lisp_Object alt_page)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  CHECK_NUMBER (wheel_lines);
+  CHECK_NUMBER (touchpad_speed);
+  neomacs_display_set_wheel_config (dpyinfo->display_handle,
+                                    (float) XFLOATINT (wheel_lines),
+                                    !NILP (wheel_reverse),
+                                    (float) XFLOATINT (touchpad_speed),
+                                    !NILP (touchpad_reverse),
+                                    !NILP (shift_horizontal),
+                                    !NILP (alt_page));
+  return Qt;
+}
+
+/* Return the id of the link under frame pixel X, Y of F, or 0.  Store
+   its target in TARGET (LEN bytes), and its window and position in
+   *WINDOW and *POS.  */
+static uint32_t
+neomacs_link_at_pixel (struct frame *f, int x, int y, char *target,
+                       size_t len, Lisp_Object *window, EMACS_INT *pos)
+{
+  struct neomacs_display_info *dpyinfo = FRAME_NEOMACS_DISPLAY_INFO (f);
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return 0;
+
+  enum window_part part;
+  Lisp_Object w = window_from_coordinates (f, x, y, &part,
+                                           false, false, false);
+  if (!WINDOWP (w) || part != ON_TEXT || !BUFFERP (XWINDOW (w)->contents))
+    return 0;
+  int64_t charpos = neomacs_layout_charpos_at_pixel (x, y);
+  if (charpos < 0)
+    return 0;
+
+  *window = w;
+  *pos = charpos;
+  return neomacs_display_link_at (dpyinfo->display_handle,
+                                  (uint64_t) (uintptr_t)
+                                  XBUFFER (XWINDOW (w)->contents),
+                                  charpos, target, len);
+}
+
+/* Return the pointer cursor of F for SHAPE, one of the symbols of the
+   `pointer' text property, or DFLT if SHAPE is not one.  */
+static Emacs_Cursor
+neomacs_pointer_cursor (struct frame *f, Lisp_Object shape, Emacs_Cursor dflt)
+{
+  struct neomacs_output *output = FRAME_OUTPUT_DATA (f);
+  if (EQ (shape, Qarrow))
+    return output->nontext_cursor;
+  if (EQ (shape, Qtext))
+    return output->text_cursor;
+  if (EQ (shape, Qhand))
+    return output->hand_cursor;
+  if (EQ (shape, Qhdrag))
+    return output->horizontal_drag_cursor;
+  if (EQ (shape, Qvdrag) || EQ (shape, Qnhdrag))
+    return output->vertical_drag_cursor;
+  if (EQ (shape, Qhourglass))
+    return output->hourglass_cursor;
+  if (EQ (shape, Qmodeline))
+    return output->modeline_cursor;
+  return dflt;
+}
+
+/* Set the mouse pointer of F for frame pixel X, Y from what is under
+   it: a link, text (honoring its `pointer' property), a mode line, a
+   fringe or margin, a scroll bar or a window divider, each looked up in
+   `neomacs-pointer-shapes'.  Over a link, also show its target as
+   help-echo.  */
+static void
+neomacs_note_mouse_pointer (struct frame *f, int x, int y)
+{
+  struct neomacs_output *output = FRAME_OUTPUT_DATA (f);
+
+  /* Do not change the shape while dragging, like `define_frame_cursor1'.  */
+  if (EQ (track_mouse, Qdragging) || EQ (track_mouse, Qdropping)
+      || EQ (track_mouse, Qdrag_source))
+    return;
+
+  char target[1024];
+  Lisp_Object window;
+  EMACS_INT pos;
+  Lisp_Object context = Qnil, shape = Qnil;
+  Emacs_Cursor dflt = output->nontext_cursor;
+
+  if (neomacs_link_at_pixel (f, x, y, target, sizeof target, &window, &pos))
+    {
+      context = Qlink;
+      dflt = output->hand_cursor;
+      help_echo_string = build_string (target);
+      help_echo_window = window;
+      help_echo_object = Qnil;
+      help_echo_pos = pos;
+    }
+  else
+    {
+      enum window_part part = ON_NOTHING;
+      window = window_from_coordinates (f, x, y, &part, false, false, false);
+      switch (part)
+        {
+        case ON_TEXT:
+          context = Qtext;
+          dflt = output->text_cursor;
+          if (BUFFERP (XWINDOW (window)->contents))
+            {
+              int64_t charpos = neomacs_layout_charpos_at_pixel (x, y);
+              if (charpos >= BUF_BEGV (XBUFFER (XWINDOW (window)->contents))
+                  && charpos < BUF_ZV (XBUFFER (XWINDOW (window)->contents)))
+                shape = Fget_char_property (make_fixnum (charpos), Qpointer,
+                                            XWINDOW (window)->contents);
+            }
+          break;
+        case ON_MODE_LINE:
+        case ON_HEADER_LINE:
+        case ON_TAB_LINE:
+          context = Qmode_line;
+          dflt = output->modeline_cursor;
+          break;
+        case ON_LEFT_FRINGE:
+        case ON_RIGHT_FRINGE:
+        case ON_LEFT_MARGIN:
+        case ON_RIGHT_MARGIN:
+          context = Qfringe;
+          break;
+        case ON_VERTICAL_SCROLL_BAR:
+        case ON_HORIZONTAL_SCROLL_BAR:
+          context = Qscroll_bar;
+          break;
+        case ON_VERTICAL_BORDER:
+        case ON_RIGHT_DIVIDER:
+          context = Qvertical_divider;
+          dflt = output->horizontal_drag_cursor;
+          break;
+        case ON_BOTTOM_DIVIDER:
+          context = Qhorizontal_divider;
+          dflt = output->vertical_drag_cursor;
+          break;
+        case ON_NOTHING:
+          break;
+        }
+    }
+
+  if (NILP (shape) && !NILP (context))
+    shape = CDR_SAFE (Fassq (context, Vneomacs_pointer_shapes));
+  if (!f->pointer_invisible)
+    neomacs_define_frame_cursor (f, neomacs_pointer_cursor (f, shape, dflt));
+}
+
+/* The mouse button whose press followed a link; its release is
+   swallowed too.  0 if none.  */
+static int neomacs_link_button;
+
+/* Follow the link under a press of BUTTON with MODIFIERS at frame pixel
+   X, Y of F: mouse-2, or mouse-1 with Control.  Returns true if the
+   press (or the release of such a press) was consumed.  */
+static bool
+neomacs_link_click (struct frame *f, int button, int modifiers,
+                    bool press, int x, int y)
+{
+  if (!press)
+    {
+      if (button != neomacs_link_button)
+        return false;
+      neomacs_link_button = 0;
+      return true;
+    }
+  if (!(button == 2 || (button == 1 && (modifiers & NEOMACS_CTRL_MASK))))
+    return false;
+
+  char target[1024];
+  Lisp_Object window;
+  EMACS_INT pos;
+  if (!neomacs_link_at_pixel (f, x, y, target, sizeof target, &window, &pos))
+    return false;
+
+  neomacs_link_button = button;
+  Lisp_Object handler = intern ("neomacs-link--handle-click");
+  if (!NILP (Ffboundp (handler)))
+    safe_calln (Fsymbol_function (handler), build_string (target),
+                window, make_fixnum (pos));
+  return true;
+}
+
+/* Store in *FG and *BG the colors of face FACE_NAME on F as 0xRRGGBB,
+   leaving them alone if F has no such face.  */
+static void
+neomacs_face_colors (struct frame *f, Lisp_Object face_name,
+                     uint32_t *fg, uint32_t *bg)
+{
+  if (!FRAME_NEOMACS_P (f))
+    return;
+  int face_id = lookup_named_face (NULL, f, face_name, false);
+  struct face *face = face_id >= 0 ? FACE_FROM_ID_OR_NULL (f, face_id) : NULL;
+  if (!face)
+    return;
+  *fg = ((RED_FROM_ULONG (face->foreground) << 16)
+         | (GREEN_FROM_ULONG (face->foreground) << 8)
+         | BLUE_FROM_ULONG (face->foreground));
+  *bg = ((RED_FROM_ULONG (face->background) << 16)
+         | (GREEN_FROM_ULONG (face->background) << 8)
+         | BLUE_FROM_ULONG (face->background));
+}
+
+/* Block until the render thread reports the choice made in an overlay
+   shown over F, as an event of KIND, as for popup menus.  Resizes of F
+   are applied; other events are discarded while the overlay is up.
+   Return the chosen index, -1 if cancelled, or -2 after 10 minutes
+   without an answer.  */
+static int
+neomacs_wait_for_overlay_choice (struct neomacs_display_info *dpyinfo,
+                                 struct frame *f, uint32_t kind)
+{
+  int selection = -2;
+  NeomacsInputEvent events[16];
+  int max_iterations = 12000; /* 12000 * 50ms */
+  while (selection == -2 && max_iterations-- > 0)
+    {
+      struct timespec timeout = { 0, 50000000 }; /* 50ms */
+      fd_set readfds;
+      FD_ZERO (&readfds);
+      FD_SET (dpyinfo->connection, &readfds);
+      pselect (dpyinfo->connection + 1, &readfds,
+               NULL, NULL, &timeout, NULL);
+
+      int got = neomacs_display_drain_input (events, 16);
+      for (int j = 0; j < got; j++)
+        {
+          if (events[j].kind == kind)
+            selection = events[j].x;
+          else if (events[j].kind == NEOMACS_EVENT_RESIZE
+                   && events[j].width > 0 && events[j].height > 0
+                   && FRAME_NEOMACS_P (f))
+            change_frame_size (f, events[j].width, events[j].height,
+                               false, true, false);
+        }
+    }
+  return selection;
+}
+
+DEFUN ("neomacs-char-picker", Fneomacs_char_picker,
+       Sneomacs_char_picker, 1, 2, 0,
+       doc: /* Let the user pick a character from a searchable grid.
+CANDIDATES is a list of (TEXT NAME . SHORTCODES), where TEXT is the
+character (a character or a string, such as an emoji sequence) shown
+in the grid, NAME its name and SHORTCODES a list of alternative names
+such as ":thumbsup:".  Typing filters the grid by fuzzy matching NAME
+and SHORTCODES; arrow keys or the mouse select, and RET or a click
+picks.  TITLE is the prompt before the search text.
+
+Return the picked TEXT as a string, or nil if the picker was
+cancelled with ESC or a click outside it.  */)
+  (Lisp_Object candidates, Lisp_Object title)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    error ("Not running on a Neomacs display");
+  if (!NILP (title))
+    CHECK_STRING (title);
+
+  ptrdiff_t n = list_length (candidates);
+  if (n == 0 || n > INT_MAX)
+    return Qnil;
+
+  /* UTF-8 encoded TEXT, NAME and shortcodes of each candidate, kept in a
+     vector on the stack so they stay alive while the picker is shown.  */
+  Lisp_Object encoded = make_nil_vector (3 * n);
+  Lisp_Object space = build_string (" ");
+  ptrdiff_t i = 0;
+  for (Lisp_Object tail = candidates; CONSP (tail); tail = XCDR (tail), i++)
+    {
+      Lisp_Object c = XCAR (tail);
+      CHECK_CONS (c);
+      Lisp_Object text = XCAR (c);
+      if (CHARACTERP (text))
+        text = Fchar_to_string (text);
+      CHECK_STRING (text);
+      CHECK_CONS (XCDR (c));
+      Lisp_Object name = XCAR (XCDR (c));
+      CHECK_STRING (name);
+      Lisp_Object codes = XCDR (XCDR (c));
+      ASET (encoded, 3 * i, ENCODE_UTF_8 (text));
+      ASET (encoded, 3 * i + 1, ENCODE_UTF_8 (name));
+      ASET (encoded, 3 * i + 2,
+            NILP (codes) ? Qnil
+            : ENCODE_UTF_8 (Fmapconcat (Qidentity, codes, space)));
+    }
+
+  struct CCharPickerEntry *entries = xmalloc (n * sizeof *entries);
+  for (i = 0; i < n; i++)
+    {
+      Lisp_Object codes = AREF (encoded, 3 * i + 2);
+      entries[i].text = SSDATA (AREF (encoded, 3 * i));
+      entries[i].name = SSDATA (AREF (encoded, 3 * i + 1));
+      entries[i].shortcodes = STRINGP (codes) ? SSDATA (codes) : NULL;
+    }
+
+  /* Theme the panel like popup menus.  */
+  struct frame *f = SELECTED_FRAME ();
+  uint32_t fg = 0, bg = 0;
+  neomacs_face_colors (f, Qmenu, &fg, &bg);
+
+  Lisp_Object title_enc = NILP (title) ? Qnil : ENCODE_UTF_8 (title);
+  neomacs_popup_activated_flag = 1;
+  neomacs_display_show_char_picker (dpyinfo->display_handle, entries,
+                                    (int) n,
+                                    NILP (title_enc) ? NULL : SSDATA (title_enc),
+                                    fg, bg);
+
+  int selection
+    = neomacs_wait_for_overlay_choice (dpyinfo, f,
+                                       NEOMACS_EVENT_CHAR_PICKER_SELECTION);
+  if (selection == -2)
+    neomacs_display_hide_char_picker (dpyinfo->display_handle);
+  neomacs_popup_activated_flag = 0;
+  xfree (entries);
+
+  if (selection < 0 || selection >= n)
+    return Qnil;
+  Lisp_Object picked = Fnth (make_fixnum (selection), candidates);
+  Lisp_Object text = XCAR (picked);
+  return CHARACTERP (text) ? Fchar_to_string (text) : text;
+}
+
+DEFUN ("neomacs-command-palette", Fneomacs_command_palette,
+       Sneomacs_command_palette, 1, 2, 0,
+       doc: /* Let the user choose a command from a searchable palette.
+CANDIDATES is a list of (NAME BINDING DOC WEIGHT), where NAME is the
+command name (a string), BINDING a description of its key binding,
+DOC its documentation, shown for the selected command, and WEIGHT an
+integer added to the match score, such as a bonus for recent use.
+BINDING, DOC and WEIGHT may be nil.  Typing filters the list by fuzzy
+matching NAME, highlighting the matched characters in the face
+`completions-common-part'; arrow keys, C-n, C-p or the mouse select,
+and RET or a click chooses.  TITLE is the prompt before the search
+text.
+
+Return the chosen NAME, or nil if the palette was cancelled with ESC,
+C-g or a click outside it.  */)
+  (Lisp_Object candidates, Lisp_Object title)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    error ("Not running on a Neomacs display");
+  if (!NILP (title))
+    CHECK_STRING (title);
+
+  ptrdiff_t n = list_length (candidates);
+  if (n == 0 || n > INT_MAX)
+    return Qnil;
+
+  /* UTF-8 encoded NAME, BINDING and DOC of each candidate, kept in a
+     vector on the stack so they stay alive while the palette is
+     shown.  */
+  Lisp_Object encoded = make_nil_vector (3 * n);
+  ptrdiff_t i = 0;
+  for (Lisp_Object tail = candidates; CONSP (tail); tail = XCDR (tail), i++)
+    {
+      Lisp_Object c = XCAR (tail);
+      Lisp_Object name = Fcar (c);
+      Lisp_Object binding = Fcar (Fcdr (c));
+      Lisp_Object doc = Fcar (Fnthcdr (make_fixnum (2), c));
+      CHECK_STRING (name);
+      ASET (encoded, 3 * i, ENCODE_UTF_8 (name));
+      ASET (encoded, 3 * i + 1,
+            STRINGP (binding) ? ENCODE_UTF_8 (binding) : Qnil);
+      ASET (encoded, 3 * i + 2, STRINGP (doc) ? ENCODE_UTF_8 (doc) : Qnil);
+    }
+
+  struct CCommandPaletteEntry *entries = xmalloc (n * sizeof *entries);
+  i = 0;
+  for (Lisp_Object tail = candidates; CONSP (tail); tail = XCDR (tail), i++)
+    {
+      Lisp_Object binding = AREF (encoded, 3 * i + 1);
+      Lisp_Object doc = AREF (encoded, 3 * i + 2);
+      Lisp_Object weight = Fcar (Fnthcdr (make_fixnum (3), XCAR (tail)));
+      entries[i].name = SSDATA (AREF (encoded, 3 * i));
+      entries[i].binding = STRINGP (binding) ? SSDATA (binding) : NULL;
+      entries[i].doc = STRINGP (doc) ? SSDATA (doc) : NULL;
+      entries[i].weight
+        = FIXNUMP (weight) ? clip_to_bounds (INT_MIN, XFIXNUM (weight), INT_MAX) : 0;
+    }
+
+  /* Theme the panel like popup menus, matches like completions.  */
+  struct frame *f = SELECTED_FRAME ();
+  uint32_t fg = 0, bg = 0, match_fg = 0, match_bg = 0;
+  neomacs_face_colors (f, Qmenu, &fg, &bg);
+  neomacs_face_colors (f, intern ("completions-common-part"),
+                       &match_fg, &match_bg);
+
+  Lisp_Object title_enc = NILP (title) ? Qnil : ENCODE_UTF_8 (title);
+  neomacs_popup_activated_flag = 1;
+  neomacs_display_show_command_palette (dpyinfo->display_handle, entries,
+                                        (int) n,
+                                        NILP (title_enc) ? NULL : SSDATA (title_enc),
+                                        fg, bg, match_fg);
+
+  int selection
+    = neomacs_wait_for_overlay_choice (dpyinfo, f,
+                                       NEOMACS_EVENT_COMMAND_PALETTE_SELECTION);
+  if (selection == -2)
+    neomacs_display_hide_command_palette (dpyinfo->display_handle);
+  neomacs_popup_activated_flag = 0;
+  xfree (entries);
+
+  if (selection < 0 || selection >= n)
+    return Qnil;
+  return Fcar (Fnth (make_fixnum (selection), candidates));
+}
+
+DEFUN ("neomacs-color-picker", Fneomacs_color_picker,
+       Sneomacs_color_picker, 1, 3, 0,
+       doc: /* Let the user choose a color with a color wheel.
+INITIAL is the color selected at first, a "#rrggbb" string.  ORIGINAL,
+default INITIAL, is shown beside the chosen color and clicking it goes
+back to it.  Dragging on the wheel picks hue and saturation, the slider
+beside it the value; arrow keys and PageUp/PageDown change them too, and
+typing six hex digits sets the color.  RET or a click on the new color
+chooses it.  TITLE is shown before the color's hex value.
+
+Return the chosen color as a "#rrggbb" string, nil if the picker was
+cancelled with ESC or a click outside it, or `eyedropper' if the user
+asked to pick a color from the screen (with the eyedropper button or
+`p').  */)
+  (Lisp_Object initial, Lisp_Object title, Lisp_Object original)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    error ("Not running on a Neomacs display");
+  CHECK_STRING (initial);
+  if (!NILP (original))
+    CHECK_STRING (original);
+  if (!NILP (title))
+    CHECK_STRING (title);
+
+  uint32_t color = neomacs_annotation_color (initial, 0);
+  uint32_t original_color
+    = NILP (original) ? color : neomacs_annotation_color (original, color);
+
+  /* Theme the panel like popup menus.  */
+  struct frame *f = SELECTED_FRAME ();
+  uint32_t fg = 0, bg = 0;
+  neomacs_face_colors (f, Qmenu, &fg, &bg);
+
+  Lisp_Object title_enc = NILP (title) ? Qnil : ENCODE_UTF_8 (title);
+  neomacs_popup_activated_flag = 1;
+  neomacs_display_show_color_picker (dpyinfo->display_handle, color,
+                                     original_color,
+                                     NILP (title_enc) ? NULL : SSDATA (title_enc),
+                                     fg, bg);
+
+  int selection
+    = neomacs_wait_for_overlay_choice (dpyinfo, f,
+                                       NEOMACS_EVENT_COLOR_PICKER_SELECTION);
+  if (selection == -2)
+    neomacs_display_hide_color_picker (dpyinfo->display_handle);
+  neomacs_popup_activated_flag = 0;
+
+  if (selection == NEOMACS_COLOR_PICKER_EYEDROPPER)
+    return Qeyedropper;
+  if (selection < 0)
+    return Qnil;
+  char hex[8];
+  snprintf (hex, sizeof hex, "#%06x", (unsigned int) selection & 0xFFFFFF);
+  return build_string (hex);
+}
+
+DEFUN ("neomacs-agenda-timeline", Fneomacs_agenda_timeline,
+       Sneomacs_agenda_timeline, 1, 2, 0,
+       doc: /* Show ITEMS as a zoomable timeline and let the user choose one.
+ITEMS is a list of (LABEL START END CATEGORY COLOR DONE), one row each
+in this order.  LABEL is a string; START and END are Lisp time values,
+END nil or equal to START for a milestone drawn as a diamond; CATEGORY,
+if non-nil, is a string shown before LABEL; COLOR, a "#rrggbb" string
+or nil for the default, colors the item's bar; DONE non-nil draws it
+dimmed.  The chart starts out showing all items, with a line at the
+current time.  Arrow keys, the mouse wheel or dragging pan, + and - or
+C-wheel zoom, t goes to today and f fits all items again; up and down
+select, and RET or a click on an item chooses it.  TITLE is shown above
+the chart.
+
+Return the chosen element of ITEMS, or nil if the timeline was closed
+with ESC, q or a click outside it.  */)
+  (Lisp_Object items, Lisp_Object title)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    error ("Not running on a Neomacs display");
+  if (!NILP (title))
+    CHECK_STRING (title);
+
+  ptrdiff_t n = list_length (items);
+  if (n > INT_MAX)
+    return Qnil;
+
+  /* UTF-8 encoded LABEL and CATEGORY of each item, kept in a vector on
+     the stack so they stay alive while the timeline is shown.  */
+  Lisp_Object encoded = make_nil_vector (2 * n);
+  ptrdiff_t i = 0;
+  for (Lisp_Object tail = items; CONSP (tail); tail = XCDR (tail), i++)
+    {
+      Lisp_Object item = XCAR (tail);
+      Lisp_Object label = Fcar (item);
+      Lisp_Object category = Fnth (make_fixnum (3), item);
+      CHECK_STRING (label);
+      ASET (encoded, 2 * i, ENCODE_UTF_8 (label));
+      ASET (encoded, 2 * i + 1,
+            STRINGP (category) ? ENCODE_UTF_8 (category) : Qnil);
+    }
+
+  struct CAgendaTimelineItem *entries = xmalloc (max (n, 1) * sizeof *entries);
+  i = 0;
+  for (Lisp_Object tail = items; CONSP (tail); tail = XCDR (tail), i++)
+    {
+      Lisp_Object item = XCAR (tail);
+      Lisp_Object end = Fnth (make_fixnum (2), item);
+      Lisp_Object category = AREF (encoded, 2 * i + 1);
+      entries[i].label = SSDATA (AREF (encoded, 2 * i));
+      entries[i].category = STRINGP (category) ? SSDATA (category) : NULL;
+      entries[i].start = XFLOAT_DATA (Ffloat_time (Fnth (make_fixnum (1), item)));
+      entries[i].end = NILP (end) ? entries[i].start : XFLOAT_DATA (Ffloat_time (end));
+      entries[i].color
+        = neomacs_annotation_color (Fnth (make_fixnum (4), item), 0);
+      entries[i].done = !NILP (Fnth (make_fixnum (5), item));
+    }
+
+  /* The chart is drawn in local time, with the zone's current offset.  */
+  Lisp_Object zone = Fcar (Fcurrent_time_zone (Qnil, Qnil));
+  int utc_offset = FIXNUMP (zone) ? XFIXNUM (zone) : 0;
+  double now = XFLOAT_DATA (Ffloat_time (Qnil));
+
+  /* Theme the panel like popup menus.  */
+  struct frame *f = SELECTED_FRAME ();
+  uint32_t fg = 0, bg = 0;
+  neomacs_face_colors (f, Qmenu, &fg, &bg);
+
+  Lisp_Object title_enc = NILP (title) ? Qnil : ENCODE_UTF_8 (title);
+  neomacs_popup_activated_flag = 1;
+  neomacs_display_show_agenda_timeline (dpyinfo->display_handle, entries,
+                                        (int) n, now, utc_offset,
+                                        NILP (title_enc) ? NULL : SSDATA (title_enc),
+                                        fg, bg);
+
+  int selection
+    = neomacs_wait_for_overlay_choice (dpyinfo, f,
+                                       NEOMACS_EVENT_AGENDA_TIMELINE_SELECTION);
+  if (selection == -2)
+    neomacs_display_hide_agenda_timeline (dpyinfo->display_handle);
+  neomacs_popup_activated_flag = 0;
+  xfree (entries);
+
+  if (selection < 0 || selection >= n)
+    return Qnil;
+  return Fnth (make_fixnum (selection), items);
+}
+
+DEFUN ("neomacs-graph-view", Fneomacs_graph_view,
+       Sneomacs_graph_view, 2, 5, 0,
+       doc: /* Show a graph of NODES and EDGES and let the user choose a node.
+NODES is a list of (LABEL COLOR), where LABEL is a string drawn under
+the node and COLOR a "#rrggbb" string or nil for the default.  EDGES is
+a list of (FROM . TO), indices into NODES counting from 0.  LAYERED
+non-nil places the nodes in layers following the edges, each below the
+nodes with edges to it, as for trees; otherwise a force-directed layout
+settles while the graph is shown.  TITLE is shown above the graph, and
+CURRENT, an index into NODES, is the node selected at first.
+
+The arrow keys move the selection to the nearest node in that
+direction; the mouse wheel or dragging the background pans, + and - or
+C-wheel zoom, f fits the graph and c centers the selected node.
+Dragging a node moves it.  RET or a click on a node chooses it.
+
+Return the chosen element of NODES, or nil if the graph was closed with
+ESC, q or a click outside it.  */)
+  (Lisp_Object nodes, Lisp_Object edges, Lisp_Object layered,
+   Lisp_Object title, Lisp_Object current)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    error ("Not running on a Neomacs display");
+  if (!NILP (title))
+    CHECK_STRING (title);
+  if (!NILP (current))
+    CHECK_FIXNUM (current);
+
+  ptrdiff_t n = list_length (nodes);
+  ptrdiff_t n_edges = list_length (edges);
+  if (n > INT_MAX || n_edges > INT_MAX)
+    return Qnil;
+
+  /* UTF-8 encoded labels, kept in a vector on the stack so they stay
+     alive while the graph is shown.  */
+  Lisp_Object encoded = make_nil_vector (n);
+  ptrdiff_t i = 0;
+  for (Lisp_Object tail = nodes; CONSP (tail); tail = XCDR (tail), i++)
+    {
+      Lisp_Object label = Fcar (XCAR (tail));
+      CHECK_STRING (label);
+      ASET (encoded, i, ENCODE_UTF_8 (label));
+    }
+
+  struct CGraphNode *cnodes = xmalloc (max (n, 1) * sizeof *cnodes);
+  i = 0;
+  for (Lisp_Object tail = nodes; CONSP (tail); tail = XCDR (tail), i++)
+    {
+      cnodes[i].label = SSDATA (AREF (encoded, i));
+      cnodes[i].color = neomacs_annotation_color (Fnth (make_fixnum (1),
+                                                        XCAR (tail)), 0);
+    }
+
+  /* Edges with an end that is not a node are dropped.  */
+  struct CGraphEdge *cedges = xmalloc (max (n_edges, 1) * sizeof *cedges);
+  int edge_count = 0;
+  for (Lisp_Object tail = edges; CONSP (tail); tail = XCDR (tail))
+    {
+      Lisp_Object edge = XCAR (tail);
+      if (CONSP (edge) && FIXNATP (XCAR (edge)) && FIXNATP (XCDR (edge))
+          && XFIXNAT (XCAR (edge)) < n && XFIXNAT (XCDR (edge)) < n)
+        {
+          cedges[edge_count].from = XFIXNAT (XCAR (edge));
+          cedges[edge_count].to = XFIXNAT (XCDR (edge));
+          edge_count++;
+        }
+    }
+
+  /* Theme the panel like popup menus.  */
+  struct frame *f = SELECTED_FRAME ();
+  uint32_t fg = 0, bg = 0;
+  neomacs_face_colors (f, Qmenu, &fg, &bg);
+
+  Lisp_Object title_enc = NILP (title) ? Qnil : ENCODE_UTF_8 (title);
+  int start = (FIXNUMP (current) && XFIXNUM (current) >= 0
+               && XFIXNUM (current) < n) ? XFIXNUM (current) : -1;
+  neomacs_popup_activated_flag = 1;
+  neomacs_display_show_graph_view (dpyinfo->display_handle, cnodes, (int) n,
+                                   cedges, edge_count, !NILP (layered), start,
+                                   NILP (title_enc) ? NULL : SSDATA (title_enc),
+                                   fg, bg);
+
+  int selection
+    = neomacs_wait_for_overlay_choice (dpyinfo, f,
+                                       NEOMACS_EVENT_GRAPH_VIEW_SELECTION);
+  if (selection == -2)
+    neomacs_display_hide_graph_view (dpyinfo->display_handle);
+  neomacs_popup_activated_flag = 0;
+  xfree (cedges);
+  xfree (cnodes);
+
+  if (selection < 0 || selection >= n)
+    return Qnil;
+  return Fnth (make_fixnum (selection), nodes);
+}
+
+DEFUN ("neomacs-directory-view", Fneomacs_directory_view,
+       Sneomacs_directory_view, 1, 3, 0,
+       doc: /* List DIRECTORY in a panel and let the user choose a file.
+The directory is read in the background and only the rows on screen
+are drawn, so large directories open at once.  Each entry shows an
+icon, its name, size and modification time.  SHOW-HIDDEN non-nil lists
+files whose names start with a dot too.  ICONS is a list of (SUFFIX .
+ICON) naming the icon, registered with `neomacs-register-icon', of
+files whose names end in the string SUFFIX, ignoring case; directories
+use the icon `directory' and other files `file'.
+
+Typing filters the entries by name; up and down select, and clicking a
+column header or TAB sorts by that column, again to reverse the order.
+RET or a click on the selected entry opens a directory in the panel
+and chooses a file; BACKSPACE or left goes to the parent directory.
+
+Return the absolute file name of the chosen file, or nil if the panel
+was closed with ESC or a click outside it.  */)
+  (Lisp_Object directory, Lisp_Object show_hidden, Lisp_Object icons)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    error ("Not running on a Neomacs display");
+  CHECK_STRING (directory);
+
+  ptrdiff_t n = list_length (icons);
+  if (n > INT_MAX)
+    return Qnil;
+
+  /* UTF-8 encoded SUFFIX and ICON of each entry, kept in a vector on
+     the stack so they stay alive until the view has copied them.  */
+  Lisp_Object encoded = make_nil_vector (2 * n);
+  int icon_count = 0;
+  for (Lisp_Object tail = icons; CONSP (tail); tail = XCDR (tail))
+    {
+      Lisp_Object icon = XCAR (tail);
+      if (!CONSP (icon) || !STRINGP (XCAR (icon)))
+        continue;
+      Lisp_Object name = SYMBOLP (XCDR (icon)) ? SYMBOL_NAME (XCDR (icon))
+                                               : XCDR (icon);
+      if (!STRINGP (name))
+        continue;
+      ASET (encoded, 2 * icon_count, ENCODE_UTF_8 (XCAR (icon)));
+      ASET (encoded, 2 * icon_count + 1, ENCODE_UTF_8 (name));
+      icon_count++;
+    }
+  struct CDirViewIcon *cicons = xmalloc (max (icon_count, 1) * sizeof *cicons);
+  for (int i = 0; i < icon_count; i++)
+    {
+      cicons[i].suffix = SSDATA (AREF (encoded, 2 * i));
+      cicons[i].name = SSDATA (AREF (encoded, 2 * i + 1));
+    }
+
+  /* Times are shown in local time, with the zone's current offset.  */
+  Lisp_Object zone = Fcar (Fcurrent_time_zone (Qnil, Qnil));
+  int utc_offset = FIXNUMP (zone) ? XFIXNUM (zone) : 0;
+
+  /* Theme the panel like popup menus.  */
+  struct frame *f = SELECTED_FRAME ();
+  uint32_t fg = 0, bg = 0;
+  neomacs_face_colors (f, Qmenu, &fg, &bg);
+
+  Lisp_Object dir = ENCODE_FILE (Fexpand_file_name (directory, Qnil));
+  neomacs_popup_activated_flag = 1;
+  neomacs_display_show_dir_view (dpyinfo->display_handle, SSDATA (dir),
+                                 !NILP (show_hidden), cicons, icon_count,
+                                 utc_offset, fg, bg);
+  xfree (cicons);
+
+  int selection
+    = neomacs_wait_for_overlay_choice (dpyinfo, f,
+                                       NEOMACS_EVENT_DIR_VIEW_SELECTION);
+  if (selection == -2)
+    neomacs_display_hide_dir_view (dpyinfo->display_handle);
+  neomacs_popup_activated_flag = 0;
+
+  /* The chosen file is fetched separately; take it even when closed
+     so a stale choice is never returned later.  */
+  char *path = neomacs_display_take_dir_view_choice ();
+  if (!path)
+    return Qnil;
+  Lisp_Object file = selection > 0 ? DECODE_FILE (build_unibyte_string (path))
+                                   : Qnil;
+  neomacs_display_free_dropped_path (path);
+  return file;
+}
+
+DEFUN ("neomacs-process-monitor", Fneomacs_process_monitor,
+       Sneomacs_process_monitor, 0, 1, 0,
+       doc: /* Show running processes in a live, sortable table.
+The processes are sampled in the background every INTERVAL seconds
+(default 2), and each row shows a process's pid, owner, CPU use with a
+sparkline of its recent history, resident memory and command line.
+Clicking a column header or TAB sorts by that column, again to reverse
+the order; `/' starts filtering the rows by what is typed.  On the
+selected row, `k' sends SIGTERM, `K' SIGKILL, `h' SIGHUP, `s' SIGSTOP
+and `c' SIGCONT, each after confirmation, and `+' and `-' change its
+niceness.
+
+Return the pid of the process chosen with RET or a click on the
+selected row, or nil if the monitor was closed with ESC, `q' or a
+click outside it.  */)
+  (Lisp_Object interval)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    error ("Not running on a Neomacs display");
+  double seconds = 2.0;
+  if (!NILP (interval))
+    {
+      CHECK_NUMBER (interval);
+      seconds = XFLOATINT (interval);
+    }
+
+  /* Theme the panel like popup menus.  */
+  struct frame *f = SELECTED_FRAME ();
+  uint32_t fg = 0, bg = 0;
+  neomacs_face_colors (f, Qmenu, &fg, &bg);
+
+  neomacs_popup_activated_flag = 1;
+  neomacs_display_show_process_monitor (dpyinfo->display_handle,
+                                        (float) seconds, fg, bg);
+
+  int selection
+    = neomacs_wait_for_overlay_choice (dpyinfo, f,
+                                       NEOMACS_EVENT_PROCESS_MONITOR_SELECTION);
+  if (selection == -2)
+    neomacs_display_hide_process_monitor (dpyinfo->display_handle);
+  neomacs_popup_activated_flag = 0;
+
+  return selection > 0 ? make_fixnum (selection) : Qnil;
+}
+
+/* DATE, a calendar date (MONTH DAY YEAR), as YYYYMMDD, or -1 if it is
+   not one.  */
+static int
+neomacs_calendar_date_code (Lisp_Object date)
+{
+  if (!CONSP (date) || !CONSP (XCDR (date)) || !CONSP (XCDR (XCDR (date))))
+    return -1;
+  Lisp_Object month = XCAR (date);
+  Lisp_Object day = XCAR (XCDR (date));
+  Lisp_Object year = XCAR (XCDR (XCDR (date)));
+  if (!FIXNUMP (month) || !FIXNUMP (day) || !FIXNUMP (year)
+      || XFIXNUM (month) < 1 || XFIXNUM (month) > 12
+      || XFIXNUM (day) < 1 || XFIXNUM (day) > 31
+      || XFIXNUM (year) < 1 || XFIXNUM (year) > 9999)
+    return -1;
+  return (int) (XFIXNUM (year) * 10000 + XFIXNUM (month) * 100
+                + XFIXNUM (day));
+}
+
+DEFUN ("neomacs-date-picker", Fneomacs_date_picker,
+       Sneomacs_date_picker, 0, 5, 0,
+       doc: /* Let the user choose a date from a calendar.
+Dates are calendar dates, lists (MONTH DAY YEAR).  INITIAL is the date
+selected at first, today if nil.  TITLE, if non-nil, is shown above
+the months.  HIGHLIGHT is a list of dates to highlight, such as
+holidays or days with entries.  MONTHS is how many months to show side
+by side, 1 (the default) to 3, and WEEK-START the day weeks start on,
+0 for Sunday (the default) to 6.
+
+Today is drawn in the face `neomacs-date-picker-today', the selected
+day in `neomacs-date-picker-selected' and highlighted days in
+`neomacs-date-picker-highlight'.  The arrow keys move by a day or a
+week, PageUp and PageDown by a month (a year with Shift), Home and End
+to the start or end of the month and `.' to today; `<', `>', the
+arrows beside the month names and the mouse wheel turn the months.
+
+Return the date chosen with RET or a click on a day, or nil if the
+picker was closed with ESC or a click outside it.  */)
+  (Lisp_Object initial, Lisp_Object title, Lisp_Object highlight,
+   Lisp_Object months, Lisp_Object week_start)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    error ("Not running on a Neomacs display");
+  if (!NILP (title))
+    CHECK_STRING (title);
+
+  /* Decoded local time is (SEC MINUTE HOUR DAY MONTH YEAR ...).  */
+  Lisp_Object now = Fdecode_time (Qnil, Qnil, Qnil);
+  int today = neomacs_calendar_date_code (list3 (Fnth (make_fixnum (4), now),
+                                                 Fnth (make_fixnum (3), now),
+                                                 Fnth (make_fixnum (5), now)));
+  int selected = NILP (initial) ? today : neomacs_calendar_date_code (initial);
+  if (selected < 0)
+    signal_error ("Invalid calendar date", initial);
+
+  ptrdiff_t n = list_length (highlight);
+  if (n > INT_MAX)
+    return Qnil;
+  int *dates = xmalloc (max (n, 1) * sizeof *dates);
+  int highlight_count = 0;
+  for (Lisp_Object tail = highlight; CONSP (tail); tail = XCDR (tail))
+    {
+      int code = neomacs_calendar_date_code (XCAR (tail));
+      if (code >= 0)
+        dates[highlight_count++] = code;
+    }
+
+  /* Theme the panel like popup menus and the days with their faces.  */
+  struct frame *f = SELECTED_FRAME ();
+  struct CDatePickerColors colors = { 0 };
+  uint32_t unused = 0;
+  neomacs_face_colors (f, Qmenu, &colors.fg, &colors.bg);
+  neomacs_face_colors (f, Qneomacs_date_picker_today, &colors.today, &unused);
+  neomacs_face_colors (f, Qneomacs_date_picker_selected,
+                       &colors.selected_fg, &colors.selected_bg);
+  neomacs_face_colors (f, Qneomacs_date_picker_highlight,
+                       &colors.highlight, &unused);
+
+  Lisp_Object title_enc = NILP (title) ? Qnil : ENCODE_UTF_8 (title);
+  neomacs_popup_activated_flag = 1;
+  neomacs_display_show_date_picker (dpyinfo->display_handle, selected, today,
+                                    dates, highlight_count,
+                                    FIXNUMP (months) ? XFIXNUM (months) : 1,
+                                    FIXNUMP (week_start)
+                                    ? XFIXNUM (week_start) : 0,
+                                    NILP (title_enc) ? NULL : SSDATA (title_enc),
+                                    &colors);
+  xfree (dates);
+
+  int selection
+    = neomacs_wait_for_overlay_choice (dpyinfo, f,
+                                       NEOMACS_EVENT_DATE_PICKER_SELECTION);
+  if (selection == -2)
+    neomacs_display_hide_date_picker (dpyinfo->display_handle);
+  neomacs_popup_activated_flag = 0;
+
+  if (selection <= 0)
+    return Qnil;
+  return list3 (make_fixnum (selection / 100 % 100),
+                make_fixnum (selection % 100),
+                make_fixnum (selection / 10000));
+}
+
+DEFUN ("neomacs-set-window-background", Fneomacs_set_window_background,
+       Sneomacs_set_window_background, 2, 5, 0,
+       doc: /* Draw image FILE under the text of TARGET.
+TARGET is a window, or a buffer whose windows all get the image; nil
+means the current buffer.  A window's own background takes precedence
+over its buffer's.  FILE nil removes TARGET's background.
+OPACITY is a number from 0.0 to 1.0 (default 0.15).
+SCALING is one of `none' (natural size, the default), `stretch', `fit',
+`fill' (cover the window, cropping) or `tile'.
+POSITION anchors the image: `center' (default), `top-left', `top',
+`top-right', `left', `right', `bottom-left', `bottom' or `bottom-right'.
+The image is clipped to the window's text area.  */)
+  (Lisp_Object target, Lisp_Object file, Lisp_Object opacity,
+   Lisp_Object scaling, Lisp_Object position)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int64_t window_id = 0;
+  uint64_t buffer_id = 0;
+  if (WINDOWP (target))
+    window_id = (int64_t) (intptr_t) decode_live_window (target);
+  else
+    buffer_id = (uint64_t) (uintptr_t) decode_buffer (target);
+
+  uint32_t image_id = 0;
+  if (!NILP (file))
+    {
+      CHECK_STRING (file);
+      Lisp_Object encoded = ENCODE_FILE (Fexpand_file_name (file, Qnil));
+      image_id = neomacs_display_load_image_file (dpyinfo->display_handle,
+                                                  SSDATA (encoded));
+      if (image_id == 0)
+        error ("Cannot load image %s", SDATA (file));
+    }
+
+  float alpha = NUMBERP (opacity) ? (float) XFLOATINT (opacity) : 0.15f;
+
+  int mode = 0;
+  if (EQ (scaling, Qstretch)) mode = 1;
+  else if (EQ (scaling, Qfit)) mode = 2;
+  else if (EQ (scaling, Qfill)) mode = 3;
+  else if (EQ (scaling, Qtile)) mode = 4;
+
+  int anchor = 0;
+  if (EQ (position, Qtop_left)) anchor = 1;
+  else if (EQ (position, Qtop)) anchor = 2;
+  else if (EQ (position, Qtop_right)) anchor = 3;
+  else if (EQ (position, Qleft)) anchor = 4;
+  else if (EQ (position, Qright)) anchor = 5;
+  else if (EQ (position, Qbottom_left)) anchor = 6;
+  else if (EQ (position, Qbottom)) anchor = 7;
+  else if (EQ (position, Qbottom_right)) anchor = 8;
+
+  neomacs_display_set_window_background (dpyinfo->display_handle,
+                                         window_id, buffer_id, image_id,
+                                         alpha, mode, anchor);
+  return Qnil;
+}
+
+DEFUN ("neomacs-set-background-gradient",
+       Fneomacs_set_background_gradient,
+       Sneomacs_set_background_gradient, 2, 2, 0,
+       doc: /* Set a background gradient from TOP-COLOR to BOTTOM-COLOR.
+Colors are strings like \"#rrggbb\".  Pass nil for either to disable.  */)
+  (Lisp_Object top_color, Lisp_Object bottom_color)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  if (NILP (top_color) || NILP (bottom_color))
+    {
+      neomacs_display_set_background_gradient (
+        dpyinfo->display_handle, 0, 0, 0, 0, 0, 0, 0);
+      return Qnil;
+    }
+
+  CHECK_STRING (top_color);
+  CHECK_STRING (bottom_color);
+
+  Emacs_Color tc, bc;
+  if (!neomacs_defined_color (NULL, SSDATA (top_color), &tc, false, false))
+    error ("Undefined color: %s", SSDATA (top_color));
+  if (!neomacs_defined_color (NULL, SSDATA (bottom_color), &bc, false, false))
+    error ("Undefined color: %s", SSDATA (bottom_color));
+
+  neomacs_display_set_background_gradient (
+    dpyinfo->display_handle, 1,
+    tc.red >> 8, tc.green >> 8, tc.blue >> 8,
+    bc.red >> 8, bc.green >> 8, bc.blue >> 8);
+  return Qt;
+}
+
+DEFUN ("neomacs-set-line-highlight",
+       Fneomacs_set_line_highlight,
+       Sneomacs_set_line_highlight, 0, 2, 0,
+       doc: /* Configure current line highlight rendering.
+ENABLED non-nil enables a subtle background on the cursor line.
+Optional COLOR is a color string (default inherits from hl-line face).  */)
+  (Lisp_Object enabled, Lisp_Object color)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int r = 50, g = 50, b = 80;
+  int opacity = 15;
+
+  if (!NILP (color) && STRINGP (color))
+    {
+      Emacs_Color c;
+      if (neomacs_defined_color (NULL, SSDATA (color), &c, false, false))
+        {
+          r = c.red >> 8;
+          g = c.green >> 8;
+          b = c.blue >> 8;
+          opacity = 20;
+        }
+    }
+
+  neomacs_display_set_line_highlight (
+    dpyinfo->display_handle, on, r, g, b, opacity);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-show-whitespace",
+       Fneomacs_set_show_whitespace,
+       Sneomacs_set_show_whitespace, 0, 2, 0,
+       doc: /* Configure visible whitespace rendering.
+ENABLED non-nil shows dots for spaces and arrows for tabs.
+Optional COLOR is a color string (default \"gray50\").  */)
+  (Lisp_Object enabled, Lisp_Object color)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int r = 128, g = 128, b = 128;
+  int opacity = 30;
+
+  if (!NILP (color) && STRINGP (color))
+    {
+      Emacs_Color c;
+      if (neomacs_defined_color (NULL, SSDATA (color), &c, false, false))
+        {
+          r = c.red >> 8;
+          g = c.green >> 8;
+          b = c.blue >> 8;
+          opacity = 35;
+        }
+    }
+
+  neomacs_display_set_show_whitespace (
+    dpyinfo->display_handle, on, r, g, b, opacity);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-inactive-dim",
+       Fneomacs_set_inactive_dim,
+       Sneomacs_set_inactive_dim, 0, 2, 0,
+       doc: /* Configure inactive window dimming.
+ENABLED non-nil dims inactive windows with a dark overlay.
+Optional OPACITY is a number 0.0-1.0 for dimming strength (default 0.15).  */)
+  (Lisp_Object enabled, Lisp_Object opacity)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int op = 15; /* default 0.15 */
+
+  if (NUMBERP (opacity))
+    {
+      double val = XFLOATINT (opacity);
+      if (val < 0.0) val = 0.0;
+      if (val > 1.0) val = 1.0;
+      op = (int)(val * 100.0);
+    }
+
+  neomacs_display_set_inactive_dim (dpyinfo->display_handle, on, op);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-mode-line-separator",
+       Fneomacs_set_mode_line_separator,
+       Sneomacs_set_mode_line_separator, 0, 3, 0,
+       doc: /* Configure mode-line separator rendering.
+STYLE is a symbol: nil (none), `line' (thin line), `shadow' (shadow effect),
+or `gradient' (gradient fade).
+Optional COLOR is a color string (default \"black\").
+Optional HEIGHT is the separator height in pixels (default 3).  */)
+  (Lisp_Object style, Lisp_Object color, Lisp_Object height)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int s = 0;
+  if (EQ (style, intern ("line")))
+    s = 1;
+  else if (EQ (style, intern ("shadow")))
+    s = 2;
+  else if (EQ (style, intern ("gradient")))
+    s = 3;
+
+  int r = 0, g = 0, b = 0;
+  if (!NILP (color) && STRINGP (color))
+    {
+      Emacs_Color c;
+      if (neomacs_defined_color (NULL, SSDATA (color), &c, false, false))
+        {
+          r = c.red >> 8;
+          g = c.green >> 8;
+          b = c.blue >> 8;
+        }
+    }
+
+  int h = 3;
+  if (FIXNUMP (height))
+    h = (int) XFIXNUM (height);
+  if (h < 1) h = 1;
+  if (h > 20) h = 20;
+
+  neomacs_display_set_mode_line_separator (
+    dpyinfo->display_handle, s, r, g, b, h);
+  return s > 0 ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-cursor-glow",
+       Fneomacs_set_cursor_glow,
+       Sneomacs_set_cursor_glow, 0, 3, 0,
+       doc: /* Configure cursor glow effect.
+ENABLED non-nil enables a soft glow around the cursor.
+Optional COLOR is a color string (default cursor color).
+Optional RADIUS is the glow radius in pixels (default 30).  */)
+  (Lisp_Object enabled, Lisp_Object color, Lisp_Object radius)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int r = 102, g = 153, b = 255; /* light blue default */
+  int rad = 30;
+  int opacity = 15; /* 0.15 */
+
+  if (!NILP (color) && STRINGP (color))
+    {
+      Emacs_Color c;
+      if (neomacs_defined_color (NULL, SSDATA (color), &c, false, false))
+        {
+          r = c.red >> 8;
+          g = c.green >> 8;
+          b = c.blue >> 8;
+        }
+    }
+
+  if (FIXNUMP (radius))
+    {
+      rad = (int) XFIXNUM (radius);
+      if (rad < 5) rad = 5;
+      if (rad > 100) rad = 100;
+    }
+
+  neomacs_display_set_cursor_glow (
+    dpyinfo->display_handle, on, r, g, b, rad, opacity);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-cursor-pulse",
+       Fneomacs_set_cursor_pulse,
+       Sneomacs_set_cursor_pulse, 0, 2, 0,
+       doc: /* Configure cursor pulse animation.
+ENABLED non-nil enables sinusoidal glow modulation on idle cursor.
+Optional SPEED is the pulse frequency as integer (100 = 1.0 Hz, default 100).
+Requires cursor glow to be enabled for visible effect.  */)
+  (Lisp_Object enabled, Lisp_Object speed)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int spd = 100;  /* 1.0 Hz */
+  int min_opacity = 30;  /* 0.3 */
+
+  if (FIXNUMP (speed))
+    {
+      spd = (int) XFIXNUM (speed);
+      if (spd < 10) spd = 10;
+      if (spd > 500) spd = 500;
+    }
+
+  neomacs_display_set_cursor_pulse (
+    dpyinfo->display_handle, on, spd, min_opacity);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-focus-mode",
+       Fneomacs_set_focus_mode,
+       Sneomacs_set_focus_mode, 0, 2, 0,
+       doc: /* Configure focus mode (dim text outside current paragraph).
+ENABLED non-nil enables focus mode where only the paragraph around the
+cursor is fully visible.  Surrounding text is dimmed.
+OPACITY is an integer 0-100 for the dimming overlay opacity (default 40).  */)
+  (Lisp_Object enabled, Lisp_Object opacity)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int op = 40;  /* 0.4 default */
+  if (FIXNUMP (opacity))
+    op = XFIXNUM (opacity);
+
+  neomacs_display_set_focus_mode (
+    dpyinfo->display_handle, on, op);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-minimap",
+       Fneomacs_set_minimap,
+       Sneomacs_set_minimap, 0, 2, 0,
+       doc: /* Configure minimap code overview column.
+ENABLED non-nil shows a minimap on the right side of each window.
+WIDTH is the minimap column width in pixels (default 80).  */)
+  (Lisp_Object enabled, Lisp_Object width)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int w = 80;
+  if (FIXNUMP (width))
+    w = XFIXNUM (width);
+
+  neomacs_display_set_minimap (
+    dpyinfo->display_handle, on, w);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-typing-ripple",
+       Fneomacs_set_typing_ripple,
+       Sneomacs_set_typing_ripple, 0, 3, 0,
+       doc: /* Configure typing ripple effect.
+ENABLED non-nil shows expanding ripple circles from cursor on movement.
+MAX-RADIUS is the maximum ripple radius in pixels (default 40).
+DURATION-MS is the ripple duration in milliseconds (default 300).  */)
+  (Lisp_Object enabled, Lisp_Object max_radius, Lisp_Object duration_ms)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int r = 40;
+  int d = 300;
+  if (FIXNUMP (max_radius))
+    r = XFIXNUM (max_radius);
+  if (FIXNUMP (duration_ms))
+    d = XFIXNUM (duration_ms);
+
+  neomacs_display_set_typing_ripple (
+    dpyinfo->display_handle, on, r, d);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-search-pulse",
+       Fneomacs_set_search_pulse,
+       Sneomacs_set_search_pulse, 0, 1, 0,
+       doc: /* Configure search highlight pulse effect.
+ENABLED non-nil draws a pulsing glow around the current isearch match.
+The isearch face is resolved automatically.  */)
+  (Lisp_Object enabled)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int fid = 0;
+  if (on)
+    {
+      struct frame *f = SELECTED_FRAME ();
+      Lisp_Object isearch_sym = Fintern (build_string ("isearch"), Qnil);
+      fid = lookup_named_face (NULL, f, isearch_sym, false);
+      if (fid < 0)
+        fid = 0;
+    }
+
+  neomacs_display_set_search_pulse (
+    dpyinfo->display_handle, on, fid);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-zen-mode",
+       Fneomacs_set_zen_mode,
+       Sneomacs_set_zen_mode, 0, 3, 0,
+       doc: /* Configure zen/distraction-free mode.
+ENABLED non-nil activates zen mode with centered content.
+CONTENT-WIDTH-PCT is the content width as percentage of window (default 60).
+MARGIN-OPACITY is 0-100 for margin overlay opacity (default 30).  */)
+  (Lisp_Object enabled, Lisp_Object content_width_pct, Lisp_Object margin_opacity)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int cw = 60;
+  int mo = 30;
+  if (FIXNUMP (content_width_pct))
+    cw = XFIXNUM (content_width_pct);
+  if (FIXNUMP (margin_opacity))
+    mo = XFIXNUM (margin_opacity);
+
+  neomacs_display_set_zen_mode (
+    dpyinfo->display_handle, on, cw, mo);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-cursor-color-cycle",
+       Fneomacs_set_cursor_color_cycle,
+       Sneomacs_set_cursor_color_cycle, 0, 4, 0,
+       doc: /* Configure cursor color cycling (rainbow hue rotation).
+ENABLED non-nil cycles the cursor color through the rainbow.
+SPEED is cycles per second * 100 (default 50 = 0.5 cps).
+SATURATION is 0-100 (default 80).
+LIGHTNESS is 0-100 (default 60).  */)
+  (Lisp_Object enabled, Lisp_Object speed, Lisp_Object saturation, Lisp_Object lightness)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int sp = 50;
+  int sat = 80;
+  int lig = 60;
+  if (FIXNUMP (speed))
+    sp = XFIXNUM (speed);
+  if (FIXNUMP (saturation))
+    sat = XFIXNUM (saturation);
+  if (FIXNUMP (lightness))
+    lig = XFIXNUM (lightness);
+
+  neomacs_display_set_cursor_color_cycle (
+    dpyinfo->display_handle, on, sp, sat, lig);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-header-shadow",
+       Fneomacs_set_header_shadow,
+       Sneomacs_set_header_shadow, 0, 3, 0,
+       doc: /* Configure header/mode-line shadow depth effect.
+ENABLED non-nil draws gradient shadows below header-line and above mode-line.
+INTENSITY is 0-100 for shadow darkness (default 30).
+SIZE is the shadow gradient size in pixels (default 6).  */)
+  (Lisp_Object enabled, Lisp_Object intensity, Lisp_Object size)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int inten = 30;
+  int sz = 6;
+  if (FIXNUMP (intensity))
+    inten = XFIXNUM (intensity);
+  if (FIXNUMP (size))
+    sz = XFIXNUM (size);
+
+  neomacs_display_set_header_shadow (
+    dpyinfo->display_handle, on, inten, sz);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-line-animation",
+       Fneomacs_set_line_animation,
+       Sneomacs_set_line_animation, 0, 2, 0,
+       doc: /* Configure smooth line insertion/deletion animation.
+ENABLED non-nil activates the animation.
+DURATION-MS is the animation duration in milliseconds (default 150).  */)
+  (Lisp_Object enabled, Lisp_Object duration_ms)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int dur = 150;
+  if (FIXNUMP (duration_ms))
+    dur = XFIXNUM (duration_ms);
+
+  neomacs_display_set_line_animation (
+    dpyinfo->display_handle, on, dur);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-vignette",
+       Fneomacs_set_vignette,
+       Sneomacs_set_vignette, 0, 3, 0,
+       doc: /* Configure vignette (edge darkening) effect.
+ENABLED non-nil activates vignette overlay on frame edges.
+INTENSITY is 0-100 for darkening strength (default 50).
+RADIUS is the inset in pixels from each edge (default 80).  */)
+  (Lisp_Object enabled, Lisp_Object intensity, Lisp_Object radius)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int inten = 50;
+  int rad = 80;
+  if (FIXNUMP (intensity))
+    inten = XFIXNUM (intensity);
+  if (FIXNUMP (radius))
+    rad = XFIXNUM (radius);
+
+  neomacs_display_set_vignette (
+    dpyinfo->display_handle, on, inten, rad);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-scroll-progress",
+       Fneomacs_set_scroll_progress,
+       Sneomacs_set_scroll_progress, 0, 6, 0,
+       doc: /* Configure scroll progress indicator bar.
+ENABLED non-nil shows a thin colored bar at the top of each window
+indicating scroll position within the buffer.
+HEIGHT is bar height in pixels (default 2).
+R, G, B are color components 0-255 (default 102 153 255).
+OPACITY is 0-100 (default 80).  */)
+  (Lisp_Object enabled, Lisp_Object height, Lisp_Object r, Lisp_Object g,
+   Lisp_Object b, Lisp_Object opacity)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int h = 2;
+  int cr = 102, cg = 153, cb = 255;
+  int op = 80;
+  if (FIXNUMP (height)) h = XFIXNUM (height);
+  if (FIXNUMP (r)) cr = XFIXNUM (r);
+  if (FIXNUMP (g)) cg = XFIXNUM (g);
+  if (FIXNUMP (b)) cb = XFIXNUM (b);
+  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
+
+  neomacs_display_set_scroll_progress (
+    dpyinfo->display_handle, on, h, cr, cg, cb, op);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-inactive-tint",
+       Fneomacs_set_inactive_tint,
+       Sneomacs_set_inactive_tint, 0, 5, 0,
+       doc: /* Configure inactive window color tint.
+ENABLED non-nil applies a color overlay on inactive (non-selected) windows.
+R, G, B are color components 0-255 (default 51 26 0, warm sepia).
+OPACITY is 0-100 (default 10).  */)
+  (Lisp_Object enabled, Lisp_Object r, Lisp_Object g, Lisp_Object b,
+   Lisp_Object opacity)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int cr = 51, cg = 26, cb = 0;
+  int op = 10;
+  if (FIXNUMP (r)) cr = XFIXNUM (r);
+  if (FIXNUMP (g)) cg = XFIXNUM (g);
+  if (FIXNUMP (b)) cb = XFIXNUM (b);
+  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
+
+  neomacs_display_set_inactive_tint (
+    dpyinfo->display_handle, on, cr, cg, cb, op);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-window-glow",
+       Fneomacs_set_window_glow,
+       Sneomacs_set_window_glow, 0, 6, 0,
+       doc: /* Configure active window border glow effect.
+ENABLED non-nil draws a soft glow around the selected window border.
+R, G, B are color components 0-255 (default 102 153 255, light blue).
+RADIUS is glow size in pixels (default 8).
+INTENSITY is 0-100 for peak glow opacity (default 40).  */)
+  (Lisp_Object enabled, Lisp_Object r, Lisp_Object g, Lisp_Object b,
+   Lisp_Object radius, Lisp_Object intensity)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int cr = 102, cg = 153, cb = 255;
+  int rad = 8;
+  int inten = 40;
+  if (FIXNUMP (r)) cr = XFIXNUM (r);
+  if (FIXNUMP (g)) cg = XFIXNUM (g);
+  if (FIXNUMP (b)) cb = XFIXNUM (b);
+  if (FIXNUMP (radius)) rad = XFIXNUM (radius);
+  if (FIXNUMP (intensity)) inten = XFIXNUM (intensity);
+
+  neomacs_display_set_window_glow (
+    dpyinfo->display_handle, on, cr, cg, cb, rad, inten);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-breadcrumb",
+       Fneomacs_set_breadcrumb,
+       Sneomacs_set_breadcrumb, 0, 2, 0,
+       doc: /* Configure breadcrumb/path bar overlay.
+ENABLED non-nil shows the buffer file path as a breadcrumb bar at
+the top of each window.
+OPACITY is 0-100 for background opacity (default 70).  */)
+  (Lisp_Object enabled, Lisp_Object opacity)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int op = 70;
+  if (FIXNUMP (opacity))
+    op = XFIXNUM (opacity);
+
+  neomacs_display_set_breadcrumb (
+    dpyinfo->display_handle, on, op);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-title-fade",
+       Fneomacs_set_title_fade,
+       Sneomacs_set_title_fade, 0, 2, 0,
+       doc: /* Configure breadcrumb title fade animation.
+ENABLED non-nil crossfades the breadcrumb text when switching buffers
+in a window, providing smooth visual feedback.
+DURATION-MS is the fade duration in milliseconds (default 300).  */)
+  (Lisp_Object enabled, Lisp_Object duration_ms)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int dur = 300;
+  if (FIXNUMP (duration_ms))
+    dur = XFIXNUM (duration_ms);
+
+  neomacs_display_set_title_fade (
+    dpyinfo->display_handle, on, dur);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-typing-speed",
+       Fneomacs_set_typing_speed,
+       Sneomacs_set_typing_speed, 0, 1, 0,
+       doc: /* Configure typing speed (WPM) indicator overlay.
+ENABLED non-nil shows a live words-per-minute counter in the bottom-right
+corner of the active window, calculated from recent keystroke rate.  */)
+  (Lisp_Object enabled)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  neomacs_display_set_typing_speed (dpyinfo->display_handle, on);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-typeahead-echo",
+       Fneomacs_set_typeahead_echo,
+       Sneomacs_set_typeahead_echo, 0, 3, 0,
+       doc: /* Configure typeahead echo of typed characters.
+ENABLED non-nil makes the render thread draw typed printable characters
+at the cursor at once, in a provisional dimmed and underlined style,
+until a redisplay shows them inserted.  Typing then feels instant even
+while Lisp is busy.
+OPACITY is a percentage 0-100 for the provisional text (default 60).
+TIMEOUT-MS is how long an echo waits for redisplay before it is
+dropped, in milliseconds (default 3000).  */)
+  (Lisp_Object enabled, Lisp_Object opacity, Lisp_Object timeout_ms)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int op = 60;
+  int timeout = 3000;
+  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
+  if (FIXNUMP (timeout_ms)) timeout = XFIXNUM (timeout_ms);
+
+  neomacs_display_set_typeahead_echo (dpyinfo->display_handle, on, op, timeout);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-border-transition",
+       Fneomacs_set_border_transition,
+       Sneomacs_set_border_transition, 0, 5, 0,
+       doc: /* Configure smooth border color transition on focus change.
+ENABLED non-nil draws a colored border around windows that smoothly
+fades in/out when focus changes between windows.
+R, G, B are the active border color 0-255 (default 102 153 255).
+DURATION-MS is the transition duration in milliseconds (default 200).  */)
+  (Lisp_Object enabled, Lisp_Object r, Lisp_Object g, Lisp_Object b,
+   Lisp_Object duration_ms)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
   int on = !NILP (enabled);
-  int fid = 0;
-  if (on)
-    {
-      struct frame *f = SELECTED_FRAME ();
-      Lisp_Object isearch_sym = Fintern (build_string ("isearch"), Qnil);
-      fid = lookup_named_face (NULL, f, isearch_sym, false);
-      if (fid < 0)
-        fid = 0;
-    }
+  int cr = 102, cg = 153, cb = 255;
+  int dur = 200;
+  if (FIXNUMP (r)) cr = XFIXNUM (r);
+  if (FIXNUMP (g)) cg = XFIXNUM (g);
+  if (FIXNUMP (b)) cb = XFIXNUM (b);
+  if (FIXNUMP (duration_ms)) dur = XFIXNUM (duration_ms);
 
-  neomacs_display_set_search_pulse (
-    dpyinfo->display_handle, on, fid);
+  neomacs_display_set_border_transition (
+    dpyinfo->display_handle, on, cr, cg, cb, dur);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-zen-mode",
-       Fneomacs_set_zen_mode,
-       Sneomacs_set_zen_mode, 0, 3, 0,
-       doc: /* Configure zen/distraction-free mode.
-ENABLED non-nil activates zen mode with centered content.
-CONTENT-WIDTH-PCT is the content width as percentage of window (default 60).
-MARGIN-OPACITY is 0-100 for margin overlay opacity (default 30).  */)
-  (Lisp_Object enabled, Lisp_Object content_width_pct, Lisp_Object margin_opacity)
+DEFUN ("neomacs-set-accent-strip",
+       Fneomacs_set_accent_strip,
+       Sneomacs_set_accent_strip, 0, 2, 0,
+       doc: /* Configure buffer-local accent color strip.
+ENABLED non-nil renders a thin colored strip on the left edge of each
+window, with color derived from the buffer's file extension.
+WIDTH is strip width in pixels (default 3).  */)
+  (Lisp_Object enabled, Lisp_Object width)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
   int on = !NILP (enabled);
-  int cw = 60;
-  int mo = 30;
-  if (FIXNUMP (content_width_pct))
-    cw = XFIXNUM (content_width_pct);
-  if (FIXNUMP (margin_opacity))
-    mo = XFIXNUM (margin_opacity);
+  int w = 3;
+  if (FIXNUMP (width)) w = XFIXNUM (width);
 
-  neomacs_display_set_zen_mode (
-    dpyinfo->display_handle, on, cw, mo);
+  neomacs_display_set_accent_strip (dpyinfo->display_handle, on, w);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-cursor-color-cycle",
-       Fneomacs_set_cursor_color_cycle,
-       Sneomacs_set_cursor_color_cycle, 0, 4, 0,
-       doc: /* Configure cursor color cycling (rainbow hue rotation).
-ENABLED non-nil cycles the cursor color through the rainbow.
-SPEED is cycles per second * 100 (default 50 = 0.5 cps).
-SATURATION is 0-100 (default 80).
-LIGHTNESS is 0-100 (default 60).  */)
-  (Lisp_Object enabled, Lisp_Object speed, Lisp_Object saturation, Lisp_Object lightness)
+DEFUN ("neomacs-set-frosted-glass",
+       Fneomacs_set_frosted_glass,
+       Sneomacs_set_frosted_glass, 0, 3, 0,
+       doc: /* Configure frosted glass effect on mode-lines.
+ENABLED non-nil adds a semi-transparent frosted glass overlay with
+noise grain to mode-line areas, creating a blurred glass appearance.
+OPACITY is 0-100 for frost intensity (default 30).
+BLUR is the blur spread radius in pixels (default 4).  */)
+  (Lisp_Object enabled, Lisp_Object opacity, Lisp_Object blur)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
   int on = !NILP (enabled);
-  int sp = 50;
-  int sat = 80;
-  int lig = 60;
-  if (FIXNUMP (speed))
-    sp = XFIXNUM (speed);
-  if (FIXNUMP (saturation))
-    sat = XFIXNUM (saturation);
-  if (FIXNUMP (lightness))
-    lig = XFIXNUM (lightness);
+  int op = 30;
+  int bl = 4;
+  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
+  if (FIXNUMP (blur)) bl = XFIXNUM (blur);
 
-  neomacs_display_set_cursor_color_cycle (
-    dpyinfo->display_handle, on, sp, sat, lig);
+  neomacs_display_set_frosted_glass (
+    dpyinfo->display_handle, on, op, bl);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-header-shadow",
-       Fneomacs_set_header_shadow,
-       Sneomacs_set_header_shadow, 0, 3, 0,
-       doc: /* Configure header/mode-line shadow depth effect.
-ENABLED non-nil draws gradient shadows below header-line and above mode-line.
-INTENSITY is 0-100 for shadow darkness (default 30).
-SIZE is the shadow gradient size in pixels (default 6).  */)
-  (Lisp_Object enabled, Lisp_Object intensity, Lisp_Object size)
+DEFUN ("neomacs-set-magnifier",
+       Fneomacs_set_magnifier,
+       Sneomacs_set_magnifier, 0, 6, 0,
+       doc: /* Configure the magnifier lens overlay.
+ENABLED non-nil shows a lens that re-renders the area under it at a
+larger scale.
+SHAPE is `circle' (default) or `rectangle'.
+SIZE is the lens diameter in pixels (default 240), or a cons
+\(WIDTH . HEIGHT) for a rectangular lens.
+ZOOM is the magnification as a percentage, 100-800 (default 200).
+FOLLOW is `mouse' (default) to follow the mouse pointer, or `point' to
+follow the cursor.
+BORDER-COLOR is a hex color string like "#RRGGBB" (default "#6699FF").  */)
+  (Lisp_Object enabled, Lisp_Object shape, Lisp_Object size,
+   Lisp_Object zoom, Lisp_Object follow, Lisp_Object border_color)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
   int on = !NILP (enabled);
-  int inten = 30;
-  int sz = 6;
-  if (FIXNUMP (intensity))
-    inten = XFIXNUM (intensity);
+  int circular = !EQ (shape, Qrectangle);
+  int w = 240, h = 160, z = 200;
+  int r = 0x66, g = 0x99, b = 0xFF;
   if (FIXNUMP (size))
-    sz = XFIXNUM (size);
+    w = h = XFIXNUM (size);
+  else if (CONSP (size) && FIXNUMP (XCAR (size)) && FIXNUMP (XCDR (size)))
+    {
+      w = XFIXNUM (XCAR (size));
+      h = XFIXNUM (XCDR (size));
+    }
+  if (FIXNUMP (zoom)) z = XFIXNUM (zoom);
+  int follow_mouse = !EQ (follow, Qpoint);
+  if (STRINGP (border_color))
+    {
+      const char *s = SSDATA (border_color);
+      if (s[0] == '#' && strlen (s) == 7)
+        {
+          unsigned int hex;
+          sscanf (s + 1, "%06x", &hex);
+          r = (hex >> 16) & 0xFF;
+          g = (hex >> 8) & 0xFF;
+          b = hex & 0xFF;
+        }
+    }
 
-  neomacs_display_set_header_shadow (
-    dpyinfo->display_handle, on, inten, sz);
+  neomacs_display_set_magnifier (dpyinfo->display_handle, on, circular,
+                                 w, h, z, follow_mouse, r, g, b, 2);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-line-animation",
-       Fneomacs_set_line_animation,
-       Sneomacs_set_line_animation, 0, 2, 0,
-       doc: /* Configure smooth line insertion/deletion animation.
-ENABLED non-nil activates the animation.
-DURATION-MS is the animation duration in milliseconds (default 150).  */)
-  (Lisp_Object enabled, Lisp_Object duration_ms)
+/* Intensity NUM (0.0 to 1.0) as a percentage, or DEFAULT_PCT if NUM is
+   not a number.  */
+static int
+neomacs_intensity_pct (Lisp_Object num, int default_pct)
+{
+  if (!NUMBERP (num))
+    return default_pct;
+  double v = XFLOATINT (num);
+  if (v < 0.0) v = 0.0;
+  if (v > 1.0) v = 1.0;
+  return (int) (v * 100.0 + 0.5);
+}
+
+DEFUN ("neomacs-set-crt-effect",
+       Fneomacs_set_crt_effect,
+       Sneomacs_set_crt_effect, 0, 6, 0,
+       doc: /* Configure the CRT/retro post-processing effect.
+ENABLED non-nil renders the display like an old CRT monitor.
+SCANLINES, CURVATURE, GLOW and ABERRATION are the intensities (0.0 to
+1.0) of the scanlines, barrel distortion, phosphor glow and chromatic
+aberration; they default to 0.5, 0.3, 0.3 and 0.2.
+SCOPE `terminal' applies the effect only to windows showing a terminal;
+any other value applies it to the whole frame.  */)
+  (Lisp_Object enabled, Lisp_Object scanlines, Lisp_Object curvature,
+   Lisp_Object glow, Lisp_Object aberration, Lisp_Object scope)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
   int on = !NILP (enabled);
-  int dur = 150;
-  if (FIXNUMP (duration_ms))
-    dur = XFIXNUM (duration_ms);
+  neomacs_display_set_crt_effect (dpyinfo->display_handle, on,
+                                  neomacs_intensity_pct (scanlines, 50),
+                                  neomacs_intensity_pct (curvature, 30),
+                                  neomacs_intensity_pct (glow, 30),
+                                  neomacs_intensity_pct (aberration, 20),
+                                  EQ (scope, Qterminal));
+  return on ? Qt : Qnil;
+}
 
-  neomacs_display_set_line_animation (
-    dpyinfo->display_handle, on, dur);
+DEFUN ("neomacs-set-region-glow",
+       Fneomacs_set_region_glow,
+       Sneomacs_set_region_glow, 0, 4, 0,
+       doc: /* Configure selection region glow highlight.
+ENABLED non-nil renders a soft glow around the active text selection
+region, using the region face background color.
+FACE-ID is the numeric face ID of the region face (auto-detected if nil).
+RADIUS is glow radius in pixels (default 6).
+OPACITY is 0-100 for glow intensity (default 30).  */)
+  (Lisp_Object enabled, Lisp_Object face_id, Lisp_Object radius,
+   Lisp_Object opacity)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int fid = 0;
+  int rad = 6;
+  int op = 30;
+
+  if (FIXNUMP (face_id))
+    fid = XFIXNUM (face_id);
+  else if (on)
+    {
+      /* Auto-detect region face ID */
+      Lisp_Object region_sym = intern ("region");
+      fid = lookup_named_face (NULL, NULL, region_sym, false);
+      if (fid < 0) fid = 0;
+    }
+
+  if (FIXNUMP (radius)) rad = XFIXNUM (radius);
+  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
+
+  neomacs_display_set_region_glow (dpyinfo->display_handle, on, fid, rad, op);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-vignette",
-       Fneomacs_set_vignette,
-       Sneomacs_set_vignette, 0, 3, 0,
-       doc: /* Configure vignette (edge darkening) effect.
-ENABLED non-nil activates vignette overlay on frame edges.
-INTENSITY is 0-100 for darkening strength (default 50).
-RADIUS is the inset in pixels from each edge (default 80).  */)
-  (Lisp_Object enabled, Lisp_Object intensity, Lisp_Object radius)
+DEFUN ("neomacs-set-idle-dim",
+       Fneomacs_set_idle_dim,
+       Sneomacs_set_idle_dim, 0, 4, 0,
+       doc: /* Configure idle screen dimming after inactivity.
+ENABLED non-nil dims the entire frame after a period of no keyboard
+or mouse activity, then smoothly restores brightness on input.
+DELAY-SECS is seconds of inactivity before dimming (default 60).
+OPACITY is 0-100 for dimming darkness (default 40).
+FADE-MS is the fade transition duration in milliseconds (default 500).  */)
+  (Lisp_Object enabled, Lisp_Object delay_secs, Lisp_Object opacity,
+   Lisp_Object fade_ms)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
   int on = !NILP (enabled);
-  int inten = 50;
-  int rad = 80;
-  if (FIXNUMP (intensity))
-    inten = XFIXNUM (intensity);
-  if (FIXNUMP (radius))
-    rad = XFIXNUM (radius);
+  int delay = 60;
+  int op = 40;
+  int fade = 500;
+  if (FIXNUMP (delay_secs)) delay = XFIXNUM (delay_secs);
+  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
+  if (FIXNUMP (fade_ms)) fade = XFIXNUM (fade_ms);
 
-  neomacs_display_set_vignette (
-    dpyinfo->display_handle, on, inten, rad);
+  neomacs_display_set_idle_dim (dpyinfo->display_handle, on, delay, op, fade);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-scroll-progress",
-       Fneomacs_set_scroll_progress,
-       Sneomacs_set_scroll_progress, 0, 6, 0,
-       doc: /* Configure scroll progress indicator bar.
-ENABLED non-nil shows a thin colored bar at the top of each window
-indicating scroll position within the buffer.
-HEIGHT is bar height in pixels (default 2).
-R, G, B are color components 0-255 (default 102 153 255).
-OPACITY is 0-100 (default 80).  */)
-  (Lisp_Object enabled, Lisp_Object height, Lisp_Object r, Lisp_Object g,
-   Lisp_Object b, Lisp_Object opacity)
+DEFUN ("neomacs-set-cursor-shadow",
+       Fneomacs_set_cursor_shadow,
+       Sneomacs_set_cursor_shadow, 0, 4, 0,
+       doc: /* Configure cursor drop shadow.
+ENABLED non-nil renders a soft drop shadow behind the cursor for
+a 3D depth effect.
+OFFSET-X is horizontal shadow offset in pixels (default 2).
+OFFSET-Y is vertical shadow offset in pixels (default 2).
+OPACITY is 0-100 for shadow darkness (default 30).  */)
+  (Lisp_Object enabled, Lisp_Object offset_x, Lisp_Object offset_y,
+   Lisp_Object opacity)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int ox = 2, oy = 2, op = 30;
+  if (FIXNUMP (offset_x)) ox = XFIXNUM (offset_x);
+  if (FIXNUMP (offset_y)) oy = XFIXNUM (offset_y);
+  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
+
+  neomacs_display_set_cursor_shadow (dpyinfo->display_handle, on, ox, oy, op);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-set-focus-ring",
+       Fneomacs_set_focus_ring,
+       Sneomacs_set_focus_ring, 0, 7, 0,
+       doc: /* Configure animated focus ring around selected window.
+ENABLED non-nil renders a marching-ants-style dashed border around
+the focused window with continuous animation.
+R, G, B are 0-255 ring color components (default 102, 153, 255).
+OPACITY is 0-100 for ring intensity (default 50).
+DASH-LENGTH is the dash segment length in pixels (default 8).
+SPEED is the animation speed in pixels per second (default 40).  */)
+  (Lisp_Object enabled, Lisp_Object r, Lisp_Object g, Lisp_Object b,
+   Lisp_Object opacity, Lisp_Object dash_length, Lisp_Object speed)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
   int on = !NILP (enabled);
-  int h = 2;
   int cr = 102, cg = 153, cb = 255;
-  int op = 80;
-  if (FIXNUMP (height)) h = XFIXNUM (height);
+  int op = 50;
+  int dl = 8;
+  int sp = 40;
   if (FIXNUMP (r)) cr = XFIXNUM (r);
   if (FIXNUMP (g)) cg = XFIXNUM (g);
   if (FIXNUMP (b)) cb = XFIXNUM (b);
   if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
+  if (FIXNUMP (dash_length)) dl = XFIXNUM (dash_length);
+  if (FIXNUMP (speed)) sp = XFIXNUM (speed);
 
-  neomacs_display_set_scroll_progress (
-    dpyinfo->display_handle, on, h, cr, cg, cb, op);
+  neomacs_display_set_focus_ring (dpyinfo->display_handle, on, cr, cg, cb, op, dl, sp);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-inactive-tint",
-       Fneomacs_set_inactive_tint,
-       Sneomacs_set_inactive_tint, 0, 5, 0,
-       doc: /* Configure inactive window color tint.
-ENABLED non-nil applies a color overlay on inactive (non-selected) windows.
-R, G, B are color components 0-255 (default 51 26 0, warm sepia).
-OPACITY is 0-100 (default 10).  */)
-  (Lisp_Object enabled, Lisp_Object r, Lisp_Object g, Lisp_Object b,
-   Lisp_Object opacity)
+DEFUN ("neomacs-set-window-mode-tint",
+       Fneomacs_set_window_mode_tint,
+       Sneomacs_set_window_mode_tint, 0, 2, 0,
+       doc: /* Configure window background tint based on file type.
+ENABLED non-nil applies a subtle background color tint to each window
+based on the file extension of its buffer, providing a visual cue for
+the type of file being edited.
+OPACITY is 0-100 for tint intensity (default 3).  */)
+  (Lisp_Object enabled, Lisp_Object opacity)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
   int on = !NILP (enabled);
-  int cr = 51, cg = 26, cb = 0;
-  int op = 10;
-  if (FIXNUMP (r)) cr = XFIXNUM (r);
-  if (FIXNUMP (g)) cg = XFIXNUM (g);
-  if (FIXNUMP (b)) cb = XFIXNUM (b);
+  int op = 3;
   if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
 
-  neomacs_display_set_inactive_tint (
-    dpyinfo->display_handle, on, cr, cg, cb, op);
+  neomacs_display_set_window_mode_tint (dpyinfo->display_handle, on, op);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-window-glow",
-       Fneomacs_set_window_glow,
-       Sneomacs_set_window_glow, 0, 6, 0,
-       doc: /* Configure active window border glow effect.
-ENABLED non-nil draws a soft glow around the selected window border.
-R, G, B are color components 0-255 (default 102 153 255, light blue).
-RADIUS is glow size in pixels (default 8).
-INTENSITY is 0-100 for peak glow opacity (default 40).  */)
-  (Lisp_Object enabled, Lisp_Object r, Lisp_Object g, Lisp_Object b,
-   Lisp_Object radius, Lisp_Object intensity)
+DEFUN ("neomacs-set-window-watermark",
+       Fneomacs_set_window_watermark,
+       Sneomacs_set_window_watermark, 0, 3, 0,
+       doc: /* Configure window watermark for empty buffers.
+ENABLED non-nil renders a large faded text showing the buffer name
+centered in windows whose buffer has very little content.
+OPACITY is 0-100 for watermark visibility (default 8).
+THRESHOLD is the maximum buffer size in characters to show watermark (default 10).  */)
+  (Lisp_Object enabled, Lisp_Object opacity, Lisp_Object threshold)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
   int on = !NILP (enabled);
-  int cr = 102, cg = 153, cb = 255;
-  int rad = 8;
-  int inten = 40;
-  if (FIXNUMP (r)) cr = XFIXNUM (r);
-  if (FIXNUMP (g)) cg = XFIXNUM (g);
-  if (FIXNUMP (b)) cb = XFIXNUM (b);
-  if (FIXNUMP (radius)) rad = XFIXNUM (radius);
-  if (FIXNUMP (intensity)) inten = XFIXNUM (intensity);
+  int op = 8;
+  int thresh = 10;
+  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
+  if (FIXNUMP (threshold)) thresh = XFIXNUM (threshold);
 
-  neomacs_display_set_window_glow (
-    dpyinfo->display_handle, on, cr, cg, cb, rad, inten);
+  neomacs_display_set_window_watermark (dpyinfo->display_handle, on, op, thresh);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-breadcrumb",
-       Fneomacs_set_breadcrumb,
-       Sneomacs_set_breadcrumb, 0, 2, 0,
-       doc: /* Configure breadcrumb/path bar overlay.
-ENABLED non-nil shows the buffer file path as a breadcrumb bar at
-the top of each window.
-OPACITY is 0-100 for background opacity (default 70).  */)
-  (Lisp_Object enabled, Lisp_Object opacity)
+DEFUN ("neomacs-set-background-contrast",
+       Fneomacs_set_background_contrast,
+       Sneomacs_set_background_contrast, 0, 4, 0,
+       doc: /* Configure text contrast over window background images.
+ENABLED non-nil checks the contrast of text drawn over a window's
+background image against the part of the image under each character,
+and fixes text that would be hard to read.
+STYLE is `scrim' (the default) to draw a translucent shade behind such
+text, darkening or lightening the image under it, or `recolor' to move
+the text color towards black or white instead.
+MIN-RATIO is the WCAG contrast ratio to keep, a number from 1 to 21
+(default 4.5).
+MAX-SCRIM is the opacity a scrim may reach, 0-100 (default 60).  */)
+  (Lisp_Object enabled, Lisp_Object style, Lisp_Object min_ratio,
+   Lisp_Object max_scrim)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
   int on = !NILP (enabled);
-  int op = 70;
-  if (FIXNUMP (opacity))
-    op = XFIXNUM (opacity);
-
-  neomacs_display_set_breadcrumb (
-    dpyinfo->display_handle, on, op);
+  int st = EQ (style, Qrecolor) ? 1 : 0;
+  int ratio = 45;
+  int scrim = 60;
+  if (NUMBERP (min_ratio)) ratio = (int) (XFLOATINT (min_ratio) * 10 + 0.5);
+  if (FIXNUMP (max_scrim)) scrim = XFIXNUM (max_scrim);
+
+  neomacs_display_set_background_contrast (dpyinfo->display_handle, on, st,
+                                           ratio, scrim);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-title-fade",
-       Fneomacs_set_title_fade,
-       Sneomacs_set_title_fade, 0, 2, 0,
-       doc: /* Configure breadcrumb title fade animation.
-ENABLED non-nil crossfades the breadcrumb text when switching buffers
-in a window, providing smooth visual feedback.
-DURATION-MS is the fade duration in milliseconds (default 300).  */)
+DEFUN ("neomacs-set-text-fade-in",
+       Fneomacs_set_text_fade_in,
+       Sneomacs_set_text_fade_in, 0, 2, 0,
+       doc: /* Configure text fade-in animation for new content.
+ENABLED non-nil makes text in a window fade in from transparent when
+the buffer content changes (scroll, buffer switch).
+DURATION-MS is the fade duration in milliseconds (default 150).  */)
   (Lisp_Object enabled, Lisp_Object duration_ms)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
@@ -10280,296 +14153,664 @@ DURATION-MS is the fade duration in milliseconds (default 300).  */)
     return Qnil;
 
   int on = !NILP (enabled);
-  int dur = 300;
-  if (FIXNUMP (duration_ms))
-    dur = XFIXNUM (duration_ms);
+  int dur = 150;
+  if (FIXNUMP (duration_ms)) dur = XFIXNUM (duration_ms);
 
-  neomacs_display_set_title_fade (
-    dpyinfo->display_handle, on, dur);
+  neomacs_display_set_text_fade_in (dpyinfo->display_handle, on, dur);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-typing-speed",
-       Fneomacs_set_typing_speed,
-       Sneomacs_set_typing_speed, 0, 1, 0,
-       doc: /* Configure typing speed (WPM) indicator overlay.
-ENABLED non-nil shows a live words-per-minute counter in the bottom-right
-corner of the active window, calculated from recent keystroke rate.  */)
-  (Lisp_Object enabled)
+DEFUN ("neomacs-set-text-zoom-animation",
+       Fneomacs_set_text_zoom_animation,
+       Sneomacs_set_text_zoom_animation, 0, 2, 0,
+       doc: /* Configure the zoom animation for text scale changes.
+ENABLED non-nil makes `text-scale-adjust' and other changes of a
+window's font size zoom the text smoothly instead of snapping; the
+text is only rasterized at the new size.  With ENABLED nil, such
+changes crossfade.
+DURATION-MS is the zoom duration in milliseconds (default 150).  */)
+  (Lisp_Object enabled, Lisp_Object duration_ms)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
   int on = !NILP (enabled);
-  neomacs_display_set_typing_speed (dpyinfo->display_handle, on);
+  int dur = 150;
+  if (FIXNUMP (duration_ms)) dur = XFIXNUM (duration_ms);
+
+  neomacs_display_set_text_zoom_animation (dpyinfo->display_handle, on, dur);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-border-transition",
-       Fneomacs_set_border_transition,
-       Sneomacs_set_border_transition, 0, 5, 0,
-       doc: /* Configure smooth border color transition on focus change.
-ENABLED non-nil draws a colored border around windows that smoothly
-fades in/out when focus changes between windows.
-R, G, B are the active border color 0-255 (default 102 153 255).
-DURATION-MS is the transition duration in milliseconds (default 200).  */)
-  (Lisp_Object enabled, Lisp_Object r, Lisp_Object g, Lisp_Object b,
-   Lisp_Object duration_ms)
+DEFUN ("neomacs-set-smooth-text-update",
+       Fneomacs_set_smooth_text_update,
+       Sneomacs_set_smooth_text_update, 0, 2, 0,
+       doc: /* Configure the smooth text update animation.
+ENABLED non-nil lets `neomacs-start-smooth-text-update' animate the
+next change of the selected window: its displayed lines are diffed
+against the previous frame, kept lines slide to their new positions,
+new lines fade in and removed lines fade out.
+DURATION-MS is the animation duration in milliseconds (default 250).  */)
+  (Lisp_Object enabled, Lisp_Object duration_ms)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
   int on = !NILP (enabled);
-  int cr = 102, cg = 153, cb = 255;
-  int dur = 200;
-  if (FIXNUMP (r)) cr = XFIXNUM (r);
-  if (FIXNUMP (g)) cg = XFIXNUM (g);
-  if (FIXNUMP (b)) cb = XFIXNUM (b);
+  int dur = 250;
   if (FIXNUMP (duration_ms)) dur = XFIXNUM (duration_ms);
 
-  neomacs_display_set_border_transition (
-    dpyinfo->display_handle, on, cr, cg, cb, dur);
+  neomacs_display_set_smooth_text_update (dpyinfo->display_handle, on, dur);
   return on ? Qt : Qnil;
 }
 
-DEFUN ("neomacs-set-accent-strip",
-       Fneomacs_set_accent_strip,
-       Sneomacs_set_accent_strip, 0, 2, 0,
-       doc: /* Configure buffer-local accent color strip.
-ENABLED non-nil renders a thin colored strip on the left edge of each
-window, with color derived from the buffer's file extension.
-WIDTH is strip width in pixels (default 3).  */)
-  (Lisp_Object enabled, Lisp_Object width)
+DEFUN ("neomacs-start-smooth-text-update",
+       Fneomacs_start_smooth_text_update,
+       Sneomacs_start_smooth_text_update, 0, 0, 0,
+       doc: /* Animate the next redisplay of the selected window line by line.
+Call this when a command is about to rewrite the buffer shown in the
+selected window, such as a revert or a formatter.  The first frame
+within a second that changes the window's text is animated as
+configured by `neomacs-set-smooth-text-update'; does nothing if that
+animation is disabled.  */)
+  (void)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int w = 3;
-  if (FIXNUMP (width)) w = XFIXNUM (width);
-
-  neomacs_display_set_accent_strip (dpyinfo->display_handle, on, w);
-  return on ? Qt : Qnil;
+  neomacs_display_start_smooth_text_update (dpyinfo->display_handle);
+  return Qnil;
 }
 
-DEFUN ("neomacs-set-frosted-glass",
-       Fneomacs_set_frosted_glass,
-       Sneomacs_set_frosted_glass, 0, 3, 0,
-       doc: /* Configure frosted glass effect on mode-lines.
-ENABLED non-nil adds a semi-transparent frosted glass overlay with
-noise grain to mode-line areas, creating a blurred glass appearance.
-OPACITY is 0-100 for frost intensity (default 30).
-BLUR is the blur spread radius in pixels (default 4).  */)
-  (Lisp_Object enabled, Lisp_Object opacity, Lisp_Object blur)
+DEFUN ("neomacs-set-post-shader",
+       Fneomacs_set_post_shader,
+       Sneomacs_set_post_shader, 1, 1, 0,
+       doc: /* Post-process every frame with the WGSL shader in FILE.
+FILE must define a function
+
+  fn effect(uv: vec2<f32>) -> vec4<f32>
+
+returning the final color at UV (0..1, origin at the top left).  It can
+call `frame_color(uv)' to sample the rendered frame and read the
+uniforms `fx.resolution' (frame size in pixels), `fx.time' (seconds
+since the shader was installed), `fx.cursor' (cursor x, y, width and
+height in pixels) and `fx.mouse' (mouse position in pixels).
+
+The shader is validated before use and reloaded whenever FILE changes;
+a shader that fails to compile is reported in the display log and the
+previous one is kept.  If FILE is nil, remove the shader.  */)
+  (Lisp_Object file)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int op = 30;
-  int bl = 4;
-  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
-  if (FIXNUMP (blur)) bl = XFIXNUM (blur);
+  if (NILP (file))
+    {
+      neomacs_display_set_post_shader (dpyinfo->display_handle, NULL);
+      return Qnil;
+    }
 
-  neomacs_display_set_frosted_glass (
-    dpyinfo->display_handle, on, op, bl);
-  return on ? Qt : Qnil;
+  CHECK_STRING (file);
+  Lisp_Object expanded = Fexpand_file_name (file, Qnil);
+  if (NILP (Ffile_readable_p (expanded)))
+    error ("Cannot read shader %s", SDATA (file));
+  Lisp_Object encoded = ENCODE_FILE (expanded);
+  neomacs_display_set_post_shader (dpyinfo->display_handle, SSDATA (encoded));
+  return Qnil;
+}
+
+DEFUN ("neomacs-set-display-settings-file",
+       Fneomacs_set_display_settings_file,
+       Sneomacs_set_display_settings_file, 1, 1, 0,
+       doc: /* Read display settings from the TOML file FILE and watch it.
+FILE sets renderer options in the sections [fonts] (line-spacing,
+letter-spacing), [cursor] (blink, blink-interval, animation,
+animation-style, animation-duration, animation-speed, trail-size),
+[scroll] and [crossfade] (enabled, duration, effect, easing), [effects]
+(EFFECT = true or false) and [colors] (EFFECT = "#rrggbb").  Durations
+are in milliseconds.
+
+FILE is applied when it is created and again whenever it changes;
+options it leaves out keep their current values.  Problems in the file
+are reported in the display log.  If FILE is nil, stop watching.  */)
+  (Lisp_Object file)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  if (NILP (file))
+    {
+      neomacs_display_set_settings_file (dpyinfo->display_handle, NULL);
+      return Qnil;
+    }
+
+  CHECK_STRING (file);
+  Lisp_Object encoded = ENCODE_FILE (Fexpand_file_name (file, Qnil));
+  neomacs_display_set_settings_file (dpyinfo->display_handle,
+				     SSDATA (encoded));
+  return file;
+}
+
+DEFUN ("neomacs-set-present-mode",
+       Fneomacs_set_present_mode,
+       Sneomacs_set_present_mode, 1, 2, 0,
+       doc: /* Set how frames are presented to the screen.
+MODE is one of:
+  `fifo'          wait for vertical sync (no tearing; the default)
+  `mailbox'       vertical sync, but never block on it: lower latency
+  `immediate'     present at once: lowest latency, may tear
+  `fifo-relaxed'  vertical sync, but tear when a frame is late
+A mode the GPU does not support falls back to the closest supported
+one.  FRAME-LATENCY is the maximum number of frames queued ahead of the
+display, 1 to 3 (default 2); lower values reduce input latency at the
+cost of throughput.  */)
+  (Lisp_Object mode, Lisp_Object frame_latency)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int code;
+  if (NILP (mode) || EQ (mode, Qfifo))
+    code = 0;
+  else if (EQ (mode, Qmailbox))
+    code = 1;
+  else if (EQ (mode, Qimmediate))
+    code = 2;
+  else if (EQ (mode, Qfifo_relaxed))
+    code = 3;
+  else
+    error ("Invalid present mode");
+
+  int latency = 2;
+  if (FIXNUMP (frame_latency))
+    latency = clip_to_bounds (1, XFIXNUM (frame_latency), 3);
+
+  neomacs_display_set_present_mode (dpyinfo->display_handle, code, latency);
+  return mode;
+}
+
+DEFUN ("neomacs-set-gpu-preference",
+       Fneomacs_set_gpu_preference,
+       Sneomacs_set_gpu_preference, 1, 1, 0,
+       doc: /* Set which GPU the display engine prefers.
+PREFERENCE is `integrated' (low power), `discrete' (high performance)
+or nil to use the NEOMACS_GPU environment variable.  It takes effect
+when the GPU device is created, so set it in your early init file.  */)
+  (Lisp_Object preference)
+{
+  int code;
+  if (NILP (preference))
+    code = 0;
+  else if (EQ (preference, Qintegrated))
+    code = 1;
+  else if (EQ (preference, Qdiscrete))
+    code = 2;
+  else
+    error ("Invalid GPU preference");
+
+  neomacs_display_set_gpu_preference (code);
+  return preference;
 }
 
-DEFUN ("neomacs-set-region-glow",
-       Fneomacs_set_region_glow,
-       Sneomacs_set_region_glow, 0, 4, 0,
-       doc: /* Configure selection region glow highlight.
-ENABLED non-nil renders a soft glow around the active text selection
-region, using the region face background color.
-FACE-ID is the numeric face ID of the region face (auto-detected if nil).
-RADIUS is glow radius in pixels (default 6).
-OPACITY is 0-100 for glow intensity (default 30).  */)
-  (Lisp_Object enabled, Lisp_Object face_id, Lisp_Object radius,
-   Lisp_Object opacity)
+DEFUN ("neomacs-set-background-throttle",
+       Fneomacs_set_background_throttle,
+       Sneomacs_set_background_throttle, 1, 4, 0,
+       doc: /* Set how rendering is throttled in the background.
+When ENABLED is non-nil and no Neomacs frame has focus, or the frame is
+hidden behind other windows, the cursor stops blinking, continuous
+effects pause and frames are rendered at most UNFOCUSED-FPS times a
+second (default 10), or HIDDEN-FPS times while hidden (default 1).
+Non-nil PAUSE-VIDEO also pauses playing videos.  Full speed resumes as
+soon as a frame gets focus.  */)
+  (Lisp_Object enabled, Lisp_Object unfocused_fps, Lisp_Object hidden_fps,
+   Lisp_Object pause_video)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int fid = 0;
-  int rad = 6;
-  int op = 30;
+  int unfocused = 10, hidden = 1;
+  if (FIXNUMP (unfocused_fps))
+    unfocused = clip_to_bounds (1, XFIXNUM (unfocused_fps), 240);
+  if (FIXNUMP (hidden_fps))
+    hidden = clip_to_bounds (1, XFIXNUM (hidden_fps), 240);
+
+  neomacs_display_set_background_throttle (dpyinfo->display_handle,
+					   !NILP (enabled), unfocused,
+					   hidden, !NILP (pause_video));
+  return enabled;
+}
+
+DEFUN ("neomacs-set-memory-budget",
+       Fneomacs_set_memory_budget,
+       Sneomacs_set_memory_budget, 0, 4, 0,
+       doc: /* Set the GPU memory budgets of the display engine, in megabytes.
+IMAGES limits the image cache (default 64), GLYPHS the glyph cache
+(default 32), SNAPSHOTS the window snapshots held by transitions
+(default 128) and STAGING the upload buffers kept for reuse (default
+64).  A nil argument uses the default.  Caches over budget evict their
+least recently used entries; evicted images are decoded again when next
+displayed.  See `neomacs-memory-stats' for the current usage.  */)
+  (Lisp_Object images, Lisp_Object glyphs, Lisp_Object snapshots,
+   Lisp_Object staging)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
 
-  if (FIXNUMP (face_id))
-    fid = XFIXNUM (face_id);
-  else if (on)
-    {
-      /* Auto-detect region face ID */
-      Lisp_Object region_sym = intern ("region");
-      fid = lookup_named_face (NULL, NULL, region_sym, false);
-      if (fid < 0) fid = 0;
-    }
+  int budget[4] = { 64, 32, 128, 64 };
+  Lisp_Object args[4] = { images, glyphs, snapshots, staging };
+  for (int i = 0; i < 4; i++)
+    if (FIXNUMP (args[i]))
+      budget[i] = clip_to_bounds (1, XFIXNUM (args[i]), 1 << 20);
 
-  if (FIXNUMP (radius)) rad = XFIXNUM (radius);
-  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
+  neomacs_display_set_memory_budget (dpyinfo->display_handle, budget[0],
+                                     budget[1], budget[2], budget[3]);
+  return Qt;
+}
 
-  neomacs_display_set_region_glow (dpyinfo->display_handle, on, fid, rad, op);
-  return on ? Qt : Qnil;
+DEFUN ("neomacs-memory-stats",
+       Fneomacs_memory_stats,
+       Sneomacs_memory_stats, 0, 0, 0,
+       doc: /* Return the GPU memory usage of the display engine.
+The value is an alist of (NAME . VALUE) as of the last frame drawn.
+Sizes and budgets are estimated from texture dimensions, in bytes:
+`image-bytes', `image-count', `image-evictions', `glyph-bytes',
+`glyph-count', `glyph-evictions', `video-bytes', `snapshot-bytes',
+`snapshot-evictions', `staging-bytes', `image-budget', `glyph-budget',
+`snapshot-budget' and `staging-budget'.  Return nil if the display
+engine is not running.  */)
+  (void)
+{
+  struct NeomacsMemoryStats stats;
+  if (!neomacs_display_get_memory_stats (&stats))
+    return Qnil;
+
+  static const char *const names[] = {
+    "image-bytes", "image-count", "image-evictions",
+    "glyph-bytes", "glyph-count", "glyph-evictions",
+    "video-bytes", "snapshot-bytes", "snapshot-evictions",
+    "staging-bytes", "image-budget", "glyph-budget",
+    "snapshot-budget", "staging-budget",
+  };
+  uint64_t values[] = {
+    stats.image_bytes, stats.image_count, stats.image_evictions,
+    stats.glyph_bytes, stats.glyph_count, stats.glyph_evictions,
+    stats.video_bytes, stats.snapshot_bytes, stats.snapshot_evictions,
+    stats.staging_bytes, stats.image_budget, stats.glyph_budget,
+    stats.snapshot_budget, stats.staging_budget,
+  };
+
+  Lisp_Object result = Qnil;
+  for (int i = ARRAYELTS (names) - 1; i >= 0; i--)
+    result = Fcons (Fcons (intern_c_string (names[i]), make_uint (values[i])),
+                    result);
+  return result;
 }
 
-DEFUN ("neomacs-set-idle-dim",
-       Fneomacs_set_idle_dim,
-       Sneomacs_set_idle_dim, 0, 4, 0,
-       doc: /* Configure idle screen dimming after inactivity.
-ENABLED non-nil dims the entire frame after a period of no keyboard
-or mouse activity, then smoothly restores brightness on input.
-DELAY-SECS is seconds of inactivity before dimming (default 60).
-OPACITY is 0-100 for dimming darkness (default 40).
-FADE-MS is the fade transition duration in milliseconds (default 500).  */)
-  (Lisp_Object enabled, Lisp_Object delay_secs, Lisp_Object opacity,
-   Lisp_Object fade_ms)
+DEFUN ("neomacs-command-stats",
+       Fneomacs_command_stats,
+       Sneomacs_command_stats, 0, 0, 0,
+       doc: /* Return the render command bus counters of the display engine.
+The value is an alist of (NAME . VALUE): `sent' and `dropped' count
+commands accepted and refused because the renderer's queue was full,
+`coalesced' counts commands superseded by a later one before they ran,
+`processed', `batches' and `max-batch' describe what the renderer ran,
+and `pending' and `capacity' describe its queue now.  `congested' is t
+when the queue is at least three quarters full; callers can then send
+fewer updates.  Return nil if the display engine is not running.  */)
+  (void)
+{
+  struct NeomacsCommandStats stats;
+  if (!neomacs_display_get_command_stats (&stats))
+    return Qnil;
+
+  static const char *const names[] = {
+    "sent", "dropped", "coalesced", "processed",
+    "batches", "max-batch", "pending", "capacity",
+  };
+  uint64_t values[] = {
+    stats.sent, stats.dropped, stats.coalesced, stats.processed,
+    stats.batches, stats.max_batch, stats.pending, stats.capacity,
+  };
+
+  Lisp_Object result = list1 (Fcons (intern_c_string ("congested"),
+                                     stats.congested ? Qt : Qnil));
+  for (int i = ARRAYELTS (names) - 1; i >= 0; i--)
+    result = Fcons (Fcons (intern_c_string (names[i]), make_uint (values[i])),
+                    result);
+  return result;
+}
+
+DEFUN ("neomacs-set-window-icon",
+       Fneomacs_set_window_icon,
+       Sneomacs_set_window_icon, 0, 1, 0,
+       doc: /* Set the icon of the Neomacs windows to the image in DATA.
+DATA is a unibyte string holding an encoded image (PNG, ICO, JPEG, BMP
+or another format the display engine decodes); large images are scaled
+down.  If DATA is nil, restore the Neomacs logo.  Use
+`neomacs-set-frame-icon' to set the icon from a file.  */)
+  (Lisp_Object data)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int delay = 60;
-  int op = 40;
-  int fade = 500;
-  if (FIXNUMP (delay_secs)) delay = XFIXNUM (delay_secs);
-  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
-  if (FIXNUMP (fade_ms)) fade = XFIXNUM (fade_ms);
-
-  neomacs_display_set_idle_dim (dpyinfo->display_handle, on, delay, op, fade);
-  return on ? Qt : Qnil;
+  if (NILP (data))
+    neomacs_display_set_window_icon (dpyinfo->display_handle, NULL, 0);
+  else
+    {
+      CHECK_STRING (data);
+      if (STRING_MULTIBYTE (data))
+        error ("Icon data must be a unibyte string");
+      neomacs_display_set_window_icon (dpyinfo->display_handle,
+                                       SDATA (data), SBYTES (data));
+    }
+  return Qt;
 }
 
-DEFUN ("neomacs-set-cursor-shadow",
-       Fneomacs_set_cursor_shadow,
-       Sneomacs_set_cursor_shadow, 0, 4, 0,
-       doc: /* Configure cursor drop shadow.
-ENABLED non-nil renders a soft drop shadow behind the cursor for
-a 3D depth effect.
-OFFSET-X is horizontal shadow offset in pixels (default 2).
-OFFSET-Y is vertical shadow offset in pixels (default 2).
-OPACITY is 0-100 for shadow darkness (default 30).  */)
-  (Lisp_Object enabled, Lisp_Object offset_x, Lisp_Object offset_y,
-   Lisp_Object opacity)
+DEFUN ("neomacs-request-attention",
+       Fneomacs_request_attention,
+       Sneomacs_request_attention, 0, 1, 0,
+       doc: /* Ask the window manager to draw the user's attention to Neomacs.
+This usually flashes the taskbar entry until the window is focused.
+With non-nil URGENT, request critical attention, which some desktops
+show more insistently.  See also `neomacs-cancel-attention'.  */)
+  (Lisp_Object urgent)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int ox = 2, oy = 2, op = 30;
-  if (FIXNUMP (offset_x)) ox = XFIXNUM (offset_x);
-  if (FIXNUMP (offset_y)) oy = XFIXNUM (offset_y);
-  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
-
-  neomacs_display_set_cursor_shadow (dpyinfo->display_handle, on, ox, oy, op);
-  return on ? Qt : Qnil;
+  neomacs_display_request_attention (dpyinfo->display_handle,
+                                     !NILP (urgent));
+  return Qt;
 }
 
-DEFUN ("neomacs-set-focus-ring",
-       Fneomacs_set_focus_ring,
-       Sneomacs_set_focus_ring, 0, 7, 0,
-       doc: /* Configure animated focus ring around selected window.
-ENABLED non-nil renders a marching-ants-style dashed border around
-the focused window with continuous animation.
-R, G, B are 0-255 ring color components (default 102, 153, 255).
-OPACITY is 0-100 for ring intensity (default 50).
-DASH-LENGTH is the dash segment length in pixels (default 8).
-SPEED is the animation speed in pixels per second (default 40).  */)
-  (Lisp_Object enabled, Lisp_Object r, Lisp_Object g, Lisp_Object b,
-   Lisp_Object opacity, Lisp_Object dash_length, Lisp_Object speed)
+DEFUN ("neomacs-cancel-attention",
+       Fneomacs_cancel_attention,
+       Sneomacs_cancel_attention, 0, 0, 0,
+       doc: /* Withdraw a request made by `neomacs-request-attention'.  */)
+  (void)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int cr = 102, cg = 153, cb = 255;
-  int op = 50;
-  int dl = 8;
-  int sp = 40;
-  if (FIXNUMP (r)) cr = XFIXNUM (r);
-  if (FIXNUMP (g)) cg = XFIXNUM (g);
-  if (FIXNUMP (b)) cb = XFIXNUM (b);
-  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
-  if (FIXNUMP (dash_length)) dl = XFIXNUM (dash_length);
-  if (FIXNUMP (speed)) sp = XFIXNUM (speed);
-
-  neomacs_display_set_focus_ring (dpyinfo->display_handle, on, cr, cg, cb, op, dl, sp);
-  return on ? Qt : Qnil;
+  neomacs_display_cancel_attention (dpyinfo->display_handle);
+  return Qt;
 }
 
-DEFUN ("neomacs-set-window-mode-tint",
-       Fneomacs_set_window_mode_tint,
-       Sneomacs_set_window_mode_tint, 0, 2, 0,
-       doc: /* Configure window background tint based on file type.
-ENABLED non-nil applies a subtle background color tint to each window
-based on the file extension of its buffer, providing a visual cue for
-the type of file being edited.
-OPACITY is 0-100 for tint intensity (default 3).  */)
-  (Lisp_Object enabled, Lisp_Object opacity)
+DEFUN ("neomacs-text-extent",
+       Fneomacs_text_extent,
+       Sneomacs_text_extent, 1, 5, 0,
+       doc: /* Return the pixel extent of STRING as the renderer draws it.
+The value is (WIDTH . HEIGHT) in logical pixels, measured with the fonts
+of the render thread without wrapping.  FAMILY is a font family name,
+nil for the default monospace font.  SIZE is the font size in pixels,
+default 14.  WEIGHT is a CSS font weight, default 400.  Non-nil ITALIC
+measures the italic style.
+
+Return nil if the display engine is not running or the render thread
+does not answer within `neomacs-render-query-timeout' milliseconds.  */)
+  (Lisp_Object string, Lisp_Object family, Lisp_Object size,
+   Lisp_Object weight, Lisp_Object italic)
 {
+  CHECK_STRING (string);
+  if (!NILP (family))
+    CHECK_STRING (family);
+
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int op = 3;
-  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
+  float font_size = NILP (size) ? 14.0f : (float) extract_float (size);
+  int font_weight = NILP (weight) ? 400 : clip_to_bounds (100, XFIXNUM (weight), 900);
+  float width, height;
+  if (!neomacs_display_query_text_extent (SSDATA (ENCODE_UTF_8 (string)),
+                                          NILP (family) ? NULL
+                                          : SSDATA (ENCODE_UTF_8 (family)),
+                                          font_weight, !NILP (italic),
+                                          font_size,
+                                          clip_to_bounds (1,
+                                                          neomacs_render_query_timeout,
+                                                          INT_MAX),
+                                          &width, &height))
+    return Qnil;
+  return Fcons (make_float (width), make_float (height));
+}
+
+DEFUN ("neomacs-dump-scene",
+       Fneomacs_dump_scene,
+       Sneomacs_dump_scene, 0, 0, 0,
+       doc: /* Return what the renderer is drawing, as a JSON string.
+The value describes every frame the render thread holds, root frame
+first: its windows, the faces in use, its text as runs of characters
+sharing a face and a row, its other glyphs and its cursors.  It ends
+with the cursor animation and the running window animations.  Colors
+are sRGB "#rrggbb" strings and coordinates are frame pixels.  Parse it
+with `json-parse-string'.
+
+Return nil if the display engine is not running or the render thread
+does not answer within `neomacs-render-query-timeout' milliseconds.  */)
+  (void)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
 
-  neomacs_display_set_window_mode_tint (dpyinfo->display_handle, on, op);
-  return on ? Qt : Qnil;
+  char *json = neomacs_display_dump_scene (clip_to_bounds (1,
+                                                           neomacs_render_query_timeout,
+                                                           INT_MAX));
+  if (!json)
+    return Qnil;
+  Lisp_Object result = build_string (json);
+  neomacs_display_free_string (json);
+  return result;
 }
 
-DEFUN ("neomacs-set-window-watermark",
-       Fneomacs_set_window_watermark,
-       Sneomacs_set_window_watermark, 0, 3, 0,
-       doc: /* Configure window watermark for empty buffers.
-ENABLED non-nil renders a large faded text showing the buffer name
-centered in windows whose buffer has very little content.
-OPACITY is 0-100 for watermark visibility (default 8).
-THRESHOLD is the maximum buffer size in characters to show watermark (default 10).  */)
-  (Lisp_Object enabled, Lisp_Object opacity, Lisp_Object threshold)
+DEFUN ("neomacs-startup-report",
+       Fneomacs_startup_report,
+       Sneomacs_startup_report, 0, 0, 0,
+       doc: /* Return how long the display engine took to start, as a string.
+The value is a table of the steps of initialization, such as loading
+fonts, opening the glyph cache and setting up the GPU, with the thread
+each ran on, when it started and ended in milliseconds, a bar showing
+when it ran, and the steps it waited for.  It ends with the first frame
+presented.
+
+Return nil if the display engine is not running, has not presented its
+first frame yet, or the render thread does not answer within
+`neomacs-render-query-timeout' milliseconds.  */)
+  (void)
 {
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int op = 8;
-  int thresh = 10;
-  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
-  if (FIXNUMP (threshold)) thresh = XFIXNUM (threshold);
-
-  neomacs_display_set_window_watermark (dpyinfo->display_handle, on, op, thresh);
-  return on ? Qt : Qnil;
+  char *report = neomacs_display_startup_report (clip_to_bounds (1,
+                                                                 neomacs_render_query_timeout,
+                                                                 INT_MAX));
+  if (!report)
+    return Qnil;
+  Lisp_Object result = build_string (report);
+  neomacs_display_free_string (report);
+  return result;
 }
 
-DEFUN ("neomacs-set-text-fade-in",
-       Fneomacs_set_text_fade_in,
-       Sneomacs_set_text_fade_in, 0, 2, 0,
-       doc: /* Configure text fade-in animation for new content.
-ENABLED non-nil makes text in a window fade in from transparent when
-the buffer content changes (scroll, buffer switch).
-DURATION-MS is the fade duration in milliseconds (default 150).  */)
-  (Lisp_Object enabled, Lisp_Object duration_ms)
-{
+DEFUN ("neomacs-register-icon",
+       Fneomacs_register_icon,
+       Sneomacs_register_icon, 2, 6, 0,
+       doc: /* Draw character CODEPOINT as the icon NAME.
+NAME is a string or symbol; registering it again replaces the icon.
+FAMILY is the icon font to draw CODEPOINT from, such as "Symbols Nerd
+Font Mono"; nil uses the font of the face the character is shown in.
+Y-OFFSET moves the icon up by that fraction of the font size (negative
+moves it down), to line up fonts that center icons on the em box.
+SCALE sizes it relative to the font, default 1.0.  COLOR, a "#rrggbb"
+string, draws the icon in that color instead of the face's foreground.
+
+Only drawing changes: the icon still takes the width Emacs gives the
+character.  Return t if the icon was registered, nil if the display
+engine is not running.  */)
+  (Lisp_Object name, Lisp_Object codepoint, Lisp_Object family,
+   Lisp_Object y_offset, Lisp_Object scale, Lisp_Object color)
+{
+  if (SYMBOLP (name))
+    name = SYMBOL_NAME (name);
+  CHECK_STRING (name);
+  CHECK_CHARACTER (codepoint);
+  if (!NILP (family))
+    CHECK_STRING (family);
+  if (!NILP (color))
+    CHECK_STRING (color);
+
   struct neomacs_display_info *dpyinfo = neomacs_display_list;
   if (!dpyinfo || !dpyinfo->display_handle)
     return Qnil;
 
-  int on = !NILP (enabled);
-  int dur = 150;
-  if (FIXNUMP (duration_ms)) dur = XFIXNUM (duration_ms);
+  int registered
+    = neomacs_display_register_icon (dpyinfo->display_handle,
+                                     SSDATA (ENCODE_UTF_8 (name)),
+                                     XFIXNAT (codepoint),
+                                     NILP (family) ? NULL
+                                     : SSDATA (ENCODE_UTF_8 (family)),
+                                     NILP (y_offset) ? 0.0f
+                                     : (float) extract_float (y_offset),
+                                     NILP (scale) ? 1.0f
+                                     : (float) extract_float (scale),
+                                     neomacs_annotation_color (color, 0),
+                                     !NILP (color));
+  return registered ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-remove-icon",
+       Fneomacs_remove_icon,
+       Sneomacs_remove_icon, 1, 1, 0,
+       doc: /* Forget the icon NAME registered with `neomacs-register-icon'.
+Its character is drawn from the face's font again.  NAME t forgets all
+icons.  */)
+  (Lisp_Object name)
+{
+  if (SYMBOLP (name) && !EQ (name, Qt))
+    name = SYMBOL_NAME (name);
+  if (!EQ (name, Qt))
+    CHECK_STRING (name);
 
-  neomacs_display_set_text_fade_in (dpyinfo->display_handle, on, dur);
-  return on ? Qt : Qnil;
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  neomacs_display_remove_icon (dpyinfo->display_handle,
+                               EQ (name, Qt) ? NULL
+                               : SSDATA (ENCODE_UTF_8 (name)));
+  return Qt;
+}
+
+/* Return the FFI code of log LEVEL: 1 = error through 5 = trace.  */
+static int
+neomacs_log_level_code (Lisp_Object level)
+{
+  if (EQ (level, Qerror))
+    return 1;
+  if (EQ (level, Qwarning))
+    return 2;
+  if (EQ (level, Qinfo))
+    return 3;
+  if (EQ (level, Qdebug))
+    return 4;
+  if (EQ (level, Qtrace))
+    return 5;
+  xsignal2 (Qerror, build_string ("Unknown log level"), level);
+}
+
+DEFUN ("neomacs-log-records",
+       Fneomacs_log_records,
+       Sneomacs_log_records, 0, 3, 0,
+       doc: /* Return records of the display engine's log, oldest first.
+Each record is a vector [SEQ TIME LEVEL MODULE SPAN MESSAGE]: SEQ is a
+sequence number increasing by one per record, TIME the time logged in
+seconds since the epoch, LEVEL one of `error', `warning', `info',
+`debug' and `trace', MODULE the Rust module that logged it and SPAN the
+active spans (such as "render/glyphs"), or "".
+
+Only records with a sequence number above AFTER are returned, so a
+viewer can pass the last SEQ it has seen.  LEVEL, if non-nil, omits
+records less severe than it.  MODULE, if non-nil, keeps only records
+whose module contains it.  The log keeps a bounded number of recent
+records; see `neomacs-set-log-level' for which levels it captures.  */)
+  (Lisp_Object after, Lisp_Object level, Lisp_Object module)
+{
+  uint64_t after_seq = 0;
+  if (!NILP (after))
+    {
+      CHECK_FIXNAT (after);
+      after_seq = XFIXNAT (after);
+    }
+  int code = NILP (level) ? 5 : neomacs_log_level_code (level);
+  char *module_name = NULL;
+  if (!NILP (module))
+    {
+      CHECK_STRING (module);
+      module_name = xstrdup (SSDATA (module));
+    }
+
+  Lisp_Object levels[] = { Qnil, Qerror, Qwarning, Qinfo, Qdebug, Qtrace };
+  struct NeomacsLogRecord records[256];
+  Lisp_Object result = Qnil;
+  int n;
+  while ((n = neomacs_display_get_log_records (after_seq, code, module_name,
+					       records, ARRAYELTS (records)))
+	 > 0)
+    {
+      for (int i = 0; i < n; i++)
+	{
+	  struct NeomacsLogRecord *r = &records[i];
+	  result = Fcons (CALLN (Fvector,
+				 make_uint (r->seq),
+				 make_float (r->time_ms / 1000.0),
+				 levels[clip_to_bounds (0, r->level, 5)],
+				 build_string (r->target ? r->target : ""),
+				 build_string (r->span ? r->span : ""),
+				 build_string (r->message ? r->message : "")),
+			  result);
+	  after_seq = r->seq;
+	}
+      neomacs_display_free_log_records (records, n);
+    }
+  xfree (module_name);
+  return Fnreverse (result);
+}
+
+DEFUN ("neomacs-set-log-level",
+       Fneomacs_set_log_level,
+       Sneomacs_set_log_level, 1, 1, 0,
+       doc: /* Set the least severe LEVEL kept in the display engine's log.
+LEVEL is one of `error', `warning', `info' (the default), `debug' and
+`trace', or nil to keep nothing.  This only affects what
+`neomacs-log-records' returns; the RUST_LOG environment variable
+controls what is printed to standard error.  */)
+  (Lisp_Object level)
+{
+  neomacs_display_set_log_level (NILP (level) ? 0
+				 : neomacs_log_level_code (level));
+  return level;
+}
+
+DEFUN ("neomacs-clear-log",
+       Fneomacs_clear_log,
+       Sneomacs_clear_log, 0, 0, 0,
+       doc: /* Drop all records from the display engine's log.  */)
+  (void)
+{
+  neomacs_display_clear_log ();
+  return Qnil;
 }
 
 DEFUN ("neomacs-set-scroll-line-spacing",
@@ -11010,6 +15251,69 @@ OPACITY is a percentage 0-100 (default 50).  */)
   return on ? Qt : Qnil;
 }
 
+DEFUN ("neomacs-set-cursor-beacon",
+       Fneomacs_set_cursor_beacon,
+       Sneomacs_set_cursor_beacon, 0, 6, 0,
+       doc: /* Configure the cursor beacon.
+The beacon highlights the cursor briefly so it is easy to find on a
+large screen.  ENABLED non-nil fires it automatically on the TRIGGERS;
+`neomacs-cursor-beacon' fires it on demand regardless.
+COLOR is an RGB hex string (default "#FF803D").
+STYLE is `ring' (a ring collapsing onto the cursor, the default),
+`line' (a flash of the cursor line) or `both'.
+DURATION-MS is the animation duration in milliseconds (default 500).
+OPACITY is a percentage 0-100 (default 60).
+TRIGGERS is a list of `switch' (selecting another window or buffer)
+and `scroll' (scrolling by more than a screenful); nil means both.  */)
+  (Lisp_Object enabled, Lisp_Object color, Lisp_Object style,
+   Lisp_Object duration_ms, Lisp_Object opacity, Lisp_Object triggers)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  int on = !NILP (enabled);
+  int st = 0, dm = 500, op = 60, tr = 3;
+  int r = 0xFF, g = 0x80, b = 0x3D;
+  if (EQ (style, Qline)) st = 1;
+  else if (EQ (style, Qboth)) st = 2;
+  if (FIXNUMP (duration_ms)) dm = XFIXNUM (duration_ms);
+  if (FIXNUMP (opacity)) op = XFIXNUM (opacity);
+  if (CONSP (triggers))
+    tr = (!NILP (Fmemq (Qswitch, triggers)) ? 1 : 0)
+         | (!NILP (Fmemq (Qscroll, triggers)) ? 2 : 0);
+  if (STRINGP (color))
+    {
+      const char *s = SSDATA (color);
+      if (s[0] == '#' && strlen (s) == 7)
+        {
+          unsigned hex = 0;
+          sscanf (s + 1, "%06x", &hex);
+          r = (hex >> 16) & 0xFF;
+          g = (hex >> 8) & 0xFF;
+          b = hex & 0xFF;
+        }
+    }
+
+  neomacs_display_set_cursor_beacon (dpyinfo->display_handle, on, r, g, b,
+                                     st, dm, op, tr);
+  return on ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-cursor-beacon", Fneomacs_cursor_beacon,
+       Sneomacs_cursor_beacon, 0, 0, "",
+       doc: /* Highlight the cursor briefly to make it easy to find.
+Uses the appearance configured with `neomacs-set-cursor-beacon'.  */)
+  (void)
+{
+  struct neomacs_display_info *dpyinfo = neomacs_display_list;
+  if (!dpyinfo || !dpyinfo->display_handle)
+    return Qnil;
+
+  neomacs_display_cursor_beacon (dpyinfo->display_handle);
+  return Qnil;
+}
+
 DEFUN ("neomacs-set-depth-shadow",
        Fneomacs_set_depth_shadow,
        Sneomacs_set_depth_shadow, 0, 5, 0,
@@ -15231,6 +19535,223 @@ STRING is sent as keyboard input to the terminal's PTY.  */)
   return Qt;
 }
 
+/* COLOR, a color name, as 0xRRGGBB for the terminal palette, or
+   0xFFFFFFFF (the built-in color) if it is nil or undefined.  */
+static uint32_t
+neomacs_terminal_color (Lisp_Object color)
+{
+  Emacs_Color c;
+  if (STRINGP (color)
+      && neomacs_defined_color (NULL, SSDATA (color), &c, false, false))
+    return ((uint32_t) (c.red >> 8) << 16
+            | (uint32_t) (c.green >> 8) << 8
+            | (uint32_t) (c.blue >> 8));
+  return 0xFFFFFFFF;
+}
+
+/* Kitty modifier bits for the Emacs modifier symbols in MODIFIERS.  */
+static uint8_t
+neomacs_terminal_modifiers (Lisp_Object modifiers)
+{
+  uint8_t mods = 0;
+  if (!NILP (Fmemq (Qshift, modifiers)))
+    mods |= 1;
+  if (!NILP (Fmemq (Qmeta, modifiers)) || !NILP (Fmemq (Qalt, modifiers)))
+    mods |= 2;
+  if (!NILP (Fmemq (Qcontrol, modifiers)))
+    mods |= 4;
+  if (!NILP (Fmemq (Qsuper, modifiers)))
+    mods |= 8;
+  if (!NILP (Fmemq (Qhyper, modifiers)))
+    mods |= 16;
+  return mods;
+}
+
+DEFUN ("neomacs-terminal-send-key", Fneomacs_terminal_send_key, Sneomacs_terminal_send_key, 2, 4, 0,
+       doc: /* Send key KEY with MODIFIERS to terminal TERMINAL-ID.
+KEY is a character or a function key symbol such as `return', `up' or
+`f5', as returned by `event-basic-type'.  MODIFIERS is a list of
+modifier symbols as returned by `event-modifiers'.  The key is encoded
+the way the program in the terminal asked for: legacy xterm sequences,
+or the kitty keyboard protocol with its enhancements.  KIND is `press'
+\(the default), `repeat' or `release'.
+Return nil if KEY cannot be sent to a terminal.  */)
+  (Lisp_Object terminal_id, Lisp_Object key, Lisp_Object modifiers,
+   Lisp_Object kind)
+{
+  CHECK_FIXNUM (terminal_id);
+
+  char name[MAX_MULTIBYTE_LENGTH + 1];
+  const char *key_str;
+  if (CHARACTERP (key))
+    {
+      int len = CHAR_STRING (XFIXNAT (key), (unsigned char *) name);
+      name[len] = '\0';
+      key_str = name;
+    }
+  else
+    {
+      CHECK_SYMBOL (key);
+      key_str = SSDATA (SYMBOL_NAME (key));
+    }
+
+  uint8_t k = EQ (kind, Qrelease) ? 2 : EQ (kind, Qrepeat) ? 1 : 0;
+  return neomacs_display_terminal_send_key ((uint32_t) XFIXNUM (terminal_id),
+                                            key_str,
+                                            neomacs_terminal_modifiers (modifiers),
+                                            k)
+    ? Qt : Qnil;
+}
+
+DEFUN ("neomacs-terminal-send-mouse", Fneomacs_terminal_send_mouse, Sneomacs_terminal_send_mouse, 5, 6, 0,
+       doc: /* Report a mouse event to the program in terminal TERMINAL-ID.
+BUTTON is 1, 2 or 3 for the left, middle and right buttons, one of
+`wheel-up', `wheel-down', `wheel-left' and `wheel-right', or nil for
+motion without a button.  KIND is `press', `release' or `motion'.  COL
+and ROW are the 0-based terminal cell.  MODIFIERS is a list of modifier
+symbols as returned by `event-modifiers'.
+The event is only sent if the program enabled mouse tracking (see
+`neomacs-terminal-mouse-mode'), in the format it asked for.  */)
+  (Lisp_Object terminal_id, Lisp_Object button, Lisp_Object kind,
+   Lisp_Object col, Lisp_Object row, Lisp_Object modifiers)
+{
+  CHECK_FIXNUM (terminal_id);
+  CHECK_FIXNAT (col);
+  CHECK_FIXNAT (row);
+
+  uint8_t b = 0;
+  if (FIXNUMP (button) && XFIXNUM (button) >= 1 && XFIXNUM (button) <= 3)
+    b = XFIXNUM (button);
+  else if (EQ (button, Qwheel_up))
+    b = 4;
+  else if (EQ (button, Qwheel_down))
+    b = 5;
+  else if (EQ (button, Qwheel_left))
+    b = 6;
+  else if (EQ (button, Qwheel_right))
+    b = 7;
+  else if (!NILP (button))
+    signal_error ("Invalid mouse button", button);
+
+  uint8_t k = EQ (kind, Qrelease) ? 1 : EQ (kind, Qmotion) ? 2 : 0;
+  neomacs_display_terminal_send_mouse ((uint32_t) XFIXNUM (terminal_id), b, k,
+                                       (uint32_t) XFIXNAT (col),
+                                       (uint32_t) XFIXNAT (row),
+                                       neomacs_terminal_modifiers (modifiers));
+  return Qnil;
+}
+
+DEFUN ("neomacs-terminal-mouse-mode", Fneomacs_terminal_mouse_mode, Sneomacs_terminal_mouse_mode, 1, 1, 0,
+       doc: /* Return the mouse tracking mode of terminal TERMINAL-ID.
+This is what the program in the terminal asked for: nil for none,
+`x10' for button presses, `normal' for presses and releases,
+`button-event' for motion while a button is held too, or `any-event'
+for all motion.  */)
+  (Lisp_Object terminal_id)
+{
+  CHECK_FIXNUM (terminal_id);
+
+  switch (neomacs_display_terminal_mouse_mode ((uint32_t) XFIXNUM (terminal_id)))
+    {
+    case 1: return Qx10;
+    case 2: return Qnormal;
+    case 3: return Qbutton_event;
+    case 4: return Qany_event;
+    default: return Qnil;
+    }
+}
+
+DEFUN ("neomacs-terminal-focus", Fneomacs_terminal_focus, Sneomacs_terminal_focus, 1, 1, 0,
+       doc: /* Give terminal TERMINAL-ID the keyboard focus.
+The focused terminal gets key release events when its program enabled
+them through the kitty keyboard protocol, since Emacs itself only sees
+key presses.  nil means no terminal has the focus.  */)
+  (Lisp_Object terminal_id)
+{
+  if (!NILP (terminal_id))
+    CHECK_FIXNUM (terminal_id);
+
+  neomacs_display_terminal_focus (NILP (terminal_id)
+                                  ? 0 : (uint32_t) XFIXNUM (terminal_id));
+  return Qnil;
+}
+
+DEFUN ("neomacs-terminal-flash", Fneomacs_terminal_flash, Sneomacs_terminal_flash, 1, 1, 0,
+       doc: /* Flash the view of terminal TERMINAL-ID briefly, as a visual bell.
+Only the terminal's own area flashes, not the whole frame.  */)
+  (Lisp_Object terminal_id)
+{
+  CHECK_FIXNUM (terminal_id);
+  neomacs_display_terminal_flash ((uint32_t) XFIXNUM (terminal_id));
+  return Qnil;
+}
+
+DEFUN ("neomacs-terminal-set-palette", Fneomacs_terminal_set_palette,
+       Sneomacs_terminal_set_palette, 2, 4, 0,
+       doc: /* Set the colors of terminal TERMINAL-ID.
+COLORS is a list or vector of up to 16 color names for the ANSI colors
+black, red, green, yellow, blue, magenta, cyan, white and their bright
+variants; FG and BG are the default foreground and background.  A nil
+entry keeps the built-in color.  Colors the program in the terminal
+sets itself (OSC 4, 10 and 11) take precedence until it resets them.  */)
+  (Lisp_Object terminal_id, Lisp_Object colors, Lisp_Object fg,
+   Lisp_Object bg)
+{
+  CHECK_FIXNUM (terminal_id);
+  if (!NILP (colors) && !VECTORP (colors))
+    CHECK_LIST (colors);
+
+  uint32_t palette[16];
+  int n = 0;
+  if (VECTORP (colors))
+    for (; n < 16 && n < ASIZE (colors); n++)
+      palette[n] = neomacs_terminal_color (AREF (colors, n));
+  else
+    for (Lisp_Object tail = colors; n < 16 && CONSP (tail);
+         tail = XCDR (tail), n++)
+      palette[n] = neomacs_terminal_color (XCAR (tail));
+
+  neomacs_display_terminal_set_palette ((uint32_t) XFIXNUM (terminal_id),
+                                        palette, n,
+                                        neomacs_terminal_color (fg),
+                                        neomacs_terminal_color (bg));
+  return Qnil;
+}
+
+DEFUN ("neomacs-terminal-jump-to-prompt", Fneomacs_terminal_jump_to_prompt,
+       Sneomacs_terminal_jump_to_prompt, 2, 2, 0,
+       doc: /* Scroll terminal TERMINAL-ID to the Nth shell prompt.
+The view scrolls so that the Nth prompt after its top line, or before
+it if N is negative, is at the top.  This needs a shell that marks its
+prompts with OSC 133 escape sequences.  */)
+  (Lisp_Object terminal_id, Lisp_Object n)
+{
+  CHECK_FIXNUM (terminal_id);
+  CHECK_FIXNUM (n);
+  neomacs_display_terminal_jump_to_prompt ((uint32_t) XFIXNUM (terminal_id),
+                                           (int) XFIXNUM (n));
+  return Qnil;
+}
+
+DEFUN ("neomacs-terminal-last-output", Fneomacs_terminal_last_output,
+       Sneomacs_terminal_last_output, 1, 1, 0,
+       doc: /* Return the output of the last finished command in TERMINAL-ID.
+Commands are known from the OSC 133 marks of the shell; return nil if
+there is none with output.  */)
+  (Lisp_Object terminal_id)
+{
+  CHECK_FIXNUM (terminal_id);
+
+  char *text
+    = neomacs_display_terminal_last_output ((uint32_t) XFIXNUM (terminal_id));
+  if (!text)
+    return Qnil;
+
+  Lisp_Object result = build_string (text);
+  free (text);
+  return result;
+}
+
 DEFUN ("neomacs-terminal-resize", Fneomacs_terminal_resize, Sneomacs_terminal_resize, 3, 3, 0,
        doc: /* Resize terminal TERMINAL-ID to COLS columns and ROWS rows.  */)
   (Lisp_Object terminal_id, Lisp_Object cols, Lisp_Object rows)
@@ -15645,6 +20166,13 @@ neomacs_display_wakeup_handler (int fd, void *data)
                     }
                 }
 
+              if (!scroll_bar_handled
+                  && NILP (tab_bar_arg)
+                  && neomacs_link_click (f, ev->button, ev->modifiers,
+                                         ev->kind == NEOMACS_EVENT_MOUSE_PRESS,
+                                         ev->x, ev->y))
+                scroll_bar_handled = true;
+
               if (!scroll_bar_handled)
                 {
                   inev.ie.kind = MOUSE_CLICK_EVENT;
@@ -15725,6 +20253,10 @@ neomacs_display_wakeup_handler (int fd, void *data)
                 px_dy = (double) -dy * lh;
               }
 
+            /* Lines to scroll, for `event-line-count' */
+            Lisp_Object lines = (ev->scrollLines > 0
+                                 ? make_fixnum (ev->scrollLines) : Qnil);
+
             /* Determine primary axis and generate event */
             if (abs_dy >= abs_dx)
               {
@@ -15733,7 +20265,7 @@ neomacs_display_wakeup_handler (int fd, void *data)
                     inev.ie.kind = WHEEL_EVENT;
                     inev.ie.modifiers
                       |= (dy > 0) ? up_modifier : down_modifier;
-                    inev.ie.arg = list3 (Qnil,
+                    inev.ie.arg = list3 (lines,
                                          make_float (px_dx),
                                          make_float (px_dy));
                     if (ev->modifiers & NEOMACS_SHIFT_MASK)
@@ -15755,7 +20287,7 @@ neomacs_display_wakeup_handler (int fd, void *data)
                     inev.ie.kind = HORIZ_WHEEL_EVENT;
                     inev.ie.modifiers
                       |= (dx > 0) ? up_modifier : down_modifier;
-                    inev.ie.arg = list3 (Qnil,
+                    inev.ie.arg = list3 (lines,
                                          make_float (px_dx),
                                          make_float (px_dy));
                     if (ev->modifiers & NEOMACS_SHIFT_MASK)
@@ -15773,6 +20305,20 @@ neomacs_display_wakeup_handler (int fd, void *data)
           }
           break;
 
+        case NEOMACS_EVENT_PINCH_ZOOM:
+          /* A touchpad pinch ended; the render thread previewed it and
+             sends the total scale once, as (pinch POS 0 0 SCALE 0),
+             for `neomacs-text-scale-pinch' to snap to a font size.  */
+          inev.ie.kind = PINCH_EVENT;
+          inev.ie.arg = list4 (make_float (0.0), make_float (0.0),
+                               make_float (ev->scrollDeltaX),
+                               make_float (0.0));
+          XSETINT (inev.ie.x, ev->x);
+          XSETINT (inev.ie.y, ev->y);
+          XSETFRAME (inev.ie.frame_or_window, f);
+          neomacs_evq_enqueue (&inev);
+          break;
+
         case NEOMACS_EVENT_MOUSE_MOVE:
           {
             struct neomacs_display_info *dpyinfo
@@ -15838,6 +20384,7 @@ neomacs_display_wakeup_handler (int fd, void *data)
                     f->mouse_moved = true;
                     dpyinfo->last_mouse_scroll_bar = NULL;
                     note_mouse_highlight (f, ev->x, ev->y);
+                    neomacs_note_mouse_pointer (f, ev->x, ev->y);
                     remember_mouse_glyph (
                         f, ev->x, ev->y, r);
                     dpyinfo->last_mouse_glyph_frame = f;
@@ -15872,8 +20419,10 @@ neomacs_display_wakeup_handler (int fd, void *data)
             int new_width = ev->width;
             int new_height = ev->height;
 
-            /* Update the Rust display handle */
-            if (dpyinfo && dpyinfo->display_handle)
+            /* Update the Rust display handle (popup windows are
+               sized on their own) */
+            if (dpyinfo && dpyinfo->display_handle
+                && !FRAME_NEOMACS_POPUP_P (f))
               {
                 neomacs_display_resize (dpyinfo->display_handle, new_width, new_height);
               }
@@ -15935,6 +20484,19 @@ neomacs_display_wakeup_handler (int fd, void *data)
           }
           break;
 
+        case NEOMACS_EVENT_MONITORS_CHANGED:
+          /* Runs `display-monitors-changed-functions'.  */
+          {
+            struct neomacs_display_info *dpyinfo = FRAME_NEOMACS_DISPLAY_INFO (f);
+            if (dpyinfo && dpyinfo->terminal)
+              {
+                inev.ie.kind = MONITORS_CHANGED_EVENT;
+                XSETTERMINAL (inev.ie.arg, dpyinfo->terminal);
+                neomacs_evq_enqueue (&inev);
+              }
+          }
+          break;
+
         case NEOMACS_EVENT_TERMINAL_EXITED:
           {
             Lisp_Object handler = intern ("neo-term--handle-exit");
@@ -15943,6 +20505,14 @@ neomacs_display_wakeup_handler (int fd, void *data)
           }
           break;
 
+        case NEOMACS_EVENT_TERMINAL_BELL:
+          {
+            Lisp_Object handler = intern ("neo-term--handle-bell");
+            if (!NILP (Ffboundp (handler)))
+              safe_calln (Fsymbol_function (handler), make_fixnum (ev->keysym));
+          }
+          break;
+
         case NEOMACS_EVENT_FILE_DROP:
           {
             /* Retrieve dropped file paths from Rust */
@@ -16109,6 +20679,9 @@ syms_of_neomacsterm (void)
   defsubr (&Sneomacs_image_load);
   defsubr (&Sneomacs_image_size);
   defsubr (&Sneomacs_image_free);
+  defsubr (&Sneomacs_image_set_edits);
+  defsubr (&Sneomacs_image_export_png);
+  defsubr (&Sneomacs_render_chart);
   defsubr (&Sneomacs_image_floating);
   defsubr (&Sneomacs_image_floating_clear);
   defsubr (&Sneomacs_insert_image);
@@ -16145,9 +20718,14 @@ syms_of_neomacsterm (void)
   defsubr (&Sneomacs_get_animation_option);
   defsubr (&Sneomacs_start_buffer_transition);
   defsubr (&Sneomacs_animation_active_p);
+  defsubr (&Sneomacs_set_buffer_transition);
   defsubr (&Sneomacs_prepare_buffer_transition);
+  defsubr (&Sneomacs_prepare_frame_transition);
   defsubr (&Sneomacs_trigger_buffer_transition);
   defsubr (&Sneomacs_has_transition_snapshot_p);
+  defsubr (&Sneomacs_animate_window);
+  defsubr (&Sneomacs_cancel_animation);
+  defsubr (&Sneomacs_set_animation_curve);
 
   /* Scroll indicators */
   defsubr (&Sneomacs_set_scroll_indicators);
@@ -16163,6 +20741,48 @@ syms_of_neomacsterm (void)
   defsubr (&Sneomacs_set_extra_spacing);
   defsubr (&Sneomacs_set_ligatures_enabled);
   defsubr (&Sneomacs_set_font_backend);
+  defsubr (&Sneomacs_set_typewriter_scrolling);
+  defsubr (&Sneomacs_set_scroll_anchoring);
+  defsubr (&Sneomacs_set_centered_column);
+  defsubr (&Sneomacs_set_ghost_text);
+  defsubr (&Sneomacs_accept_ghost_text);
+  defsubr (&Sneomacs_dismiss_ghost_text);
+  defsubr (&Sneomacs_ghost_text_p);
+  defsubr (&Sneomacs_annotation_add);
+  defsubr (&Sneomacs_annotation_remove);
+  defsubr (&Sneomacs_annotation_clear);
+  defsubr (&Sneomacs_annotation_adjust);
+  defsubr (&Sneomacs_set_scroll_bar_marks);
+  defsubr (&Sneomacs_clear_scroll_bar_marks);
+  defsubr (&Sneomacs_scroll_bar_mark_at);
+  defsubr (&Sneomacs_set_rectangle_region);
+  defsubr (&Sneomacs_set_virtual_cursor);
+  defsubr (&Sneomacs_add_table);
+  defsubr (&Sneomacs_clear_tables);
+  defsubr (&Sneomacs_set_command_blocks);
+  defsubr (&Sneomacs_set_chat_view);
+  defsubr (&Sneomacs_pulse_region);
+  defsubr (&Sneomacs_link_add);
+  defsubr (&Sneomacs_link_remove);
+  defsubr (&Sneomacs_link_clear);
+  defsubr (&Sneomacs_link_adjust);
+  defsubr (&Sneomacs_link_at);
+  defsubr (&Sneomacs_set_color_swatches);
+  defsubr (&Sneomacs_color_swatches_clear);
+  defsubr (&Sneomacs_color_swatches_adjust);
+  defsubr (&Sneomacs_set_breadcrumb_bar);
+  defsubr (&Sneomacs_breadcrumb_bar_segment_at);
+  defsubr (&Sneomacs_set_pointer_auto_hide);
+  defsubr (&Sneomacs_set_wheel_config);
+  defsubr (&Sneomacs_char_picker);
+  defsubr (&Sneomacs_command_palette);
+  defsubr (&Sneomacs_color_picker);
+  defsubr (&Sneomacs_agenda_timeline);
+  defsubr (&Sneomacs_graph_view);
+  defsubr (&Sneomacs_directory_view);
+  defsubr (&Sneomacs_process_monitor);
+  defsubr (&Sneomacs_date_picker);
+  defsubr (&Sneomacs_set_window_background);
   defsubr (&Sneomacs_set_background_gradient);
   defsubr (&Sneomacs_set_scroll_bar_config);
   defsubr (&Sneomacs_set_indent_guides);
@@ -16187,9 +20807,12 @@ syms_of_neomacsterm (void)
   defsubr (&Sneomacs_set_breadcrumb);
   defsubr (&Sneomacs_set_title_fade);
   defsubr (&Sneomacs_set_typing_speed);
+  defsubr (&Sneomacs_set_typeahead_echo);
   defsubr (&Sneomacs_set_border_transition);
   defsubr (&Sneomacs_set_accent_strip);
   defsubr (&Sneomacs_set_frosted_glass);
+  defsubr (&Sneomacs_set_magnifier);
+  defsubr (&Sneomacs_set_crt_effect);
   defsubr (&Sneomacs_set_cursor_size_transition);
   defsubr (&Sneomacs_set_padding_gradient);
   defsubr (&Sneomacs_set_noise_grain);
@@ -16198,9 +20821,32 @@ syms_of_neomacsterm (void)
   defsubr (&Sneomacs_set_focus_ring);
   defsubr (&Sneomacs_set_window_mode_tint);
   defsubr (&Sneomacs_set_window_watermark);
+  defsubr (&Sneomacs_set_background_contrast);
   defsubr (&Sneomacs_set_cursor_trail_fade);
   defsubr (&Sneomacs_set_scroll_line_spacing);
   defsubr (&Sneomacs_set_text_fade_in);
+  defsubr (&Sneomacs_set_text_zoom_animation);
+  defsubr (&Sneomacs_set_smooth_text_update);
+  defsubr (&Sneomacs_start_smooth_text_update);
+  defsubr (&Sneomacs_set_post_shader);
+  defsubr (&Sneomacs_set_display_settings_file);
+  defsubr (&Sneomacs_set_present_mode);
+  defsubr (&Sneomacs_set_gpu_preference);
+  defsubr (&Sneomacs_set_background_throttle);
+  defsubr (&Sneomacs_set_memory_budget);
+  defsubr (&Sneomacs_memory_stats);
+  defsubr (&Sneomacs_command_stats);
+  defsubr (&Sneomacs_set_window_icon);
+  defsubr (&Sneomacs_request_attention);
+  defsubr (&Sneomacs_cancel_attention);
+  defsubr (&Sneomacs_text_extent);
+  defsubr (&Sneomacs_dump_scene);
+  defsubr (&Sneomacs_startup_report);
+  defsubr (&Sneomacs_register_icon);
+  defsubr (&Sneomacs_remove_icon);
+  defsubr (&Sneomacs_log_records);
+  defsubr (&Sneomacs_set_log_level);
+  defsubr (&Sneomacs_clear_log);
   defsubr (&Sneomacs_set_mode_line_transition);
   defsubr (&Sneomacs_set_cursor_wake);
   defsubr (&Sneomacs_set_scroll_momentum);
@@ -16228,6 +20874,8 @@ syms_of_neomacsterm (void)
   defsubr (&Sneomacs_set_breathing_border);
   defsubr (&Sneomacs_set_focus_gradient_border);
   defsubr (&Sneomacs_set_cursor_magnetism);
+  defsubr (&Sneomacs_set_cursor_beacon);
+  defsubr (&Sneomacs_cursor_beacon);
   defsubr (&Sneomacs_set_depth_shadow);
   defsubr (&Sneomacs_set_matrix_rain);
   defsubr (&Sneomacs_set_cursor_elastic_snap);
@@ -16323,6 +20971,14 @@ syms_of_neomacsterm (void)
   /* Terminal emulator (neo-term) */
   defsubr (&Sneomacs_terminal_create);
   defsubr (&Sneomacs_terminal_write);
+  defsubr (&Sneomacs_terminal_send_key);
+  defsubr (&Sneomacs_terminal_send_mouse);
+  defsubr (&Sneomacs_terminal_mouse_mode);
+  defsubr (&Sneomacs_terminal_focus);
+  defsubr (&Sneomacs_terminal_flash);
+  defsubr (&Sneomacs_terminal_set_palette);
+  defsubr (&Sneomacs_terminal_jump_to_prompt);
+  defsubr (&Sneomacs_terminal_last_output);
   defsubr (&Sneomacs_terminal_resize);
   defsubr (&Sneomacs_terminal_destroy);
   defsubr (&Sneomacs_terminal_set_float);
@@ -16330,6 +20986,7 @@ syms_of_neomacsterm (void)
   defsubr (&Sneomacs_set_child_frame_style);
 
   DEFSYM (Qneomacs, "neomacs");
+  DEFSYM (Qdisplay_line_numbers_grow_only, "display-line-numbers-grow-only");
   /* Qvideo and Qwebkit are defined in xdisp.c for use in VIDEOP/WEBKITP */
   DEFSYM (QCid, ":id");
 
@@ -16342,6 +20999,40 @@ syms_of_neomacsterm (void)
   DEFSYM (Qease_in_out_cubic, "ease-in-out-cubic");
   DEFSYM (Qlinear, "linear");
 
+  /* Animation timeline symbols */
+  DEFSYM (Qoffset_x, "offset-x");
+  DEFSYM (Qoffset_y, "offset-y");
+  DEFSYM (Qopacity, "opacity");
+  DEFSYM (Qease_out, "ease-out");
+  DEFSYM (Qease_in, "ease-in");
+  DEFSYM (Qease_in_out, "ease-in-out");
+  DEFSYM (Qease_out_back, "ease-out-back");
+  DEFSYM (Qcubic_bezier, "cubic-bezier");
+  DEFSYM (Qbuffer_transition, "buffer-transition");
+
+  /* Terminal key event symbols */
+  DEFSYM (Qshift, "shift");
+  DEFSYM (Qcontrol, "control");
+  DEFSYM (Qmeta, "meta");
+  DEFSYM (Qsuper, "super");
+  DEFSYM (Qhyper, "hyper");
+  DEFSYM (Qalt, "alt");
+  DEFSYM (Qrepeat, "repeat");
+  DEFSYM (Qrelease, "release");
+  DEFSYM (Qmotion, "motion");
+  DEFSYM (Qwheel_up, "wheel-up");
+  DEFSYM (Qwheel_down, "wheel-down");
+  DEFSYM (Qwheel_left, "wheel-left");
+  DEFSYM (Qwheel_right, "wheel-right");
+  DEFSYM (Qx10, "x10");
+  DEFSYM (Qbutton_event, "button-event");
+  DEFSYM (Qany_event, "any-event");
+
+  /* Log level symbols */
+  DEFSYM (Qwarning, "warning");
+  DEFSYM (Qinfo, "info");
+  DEFSYM (Qtrace, "trace");
+
   /* Scroll effect symbols */
   DEFSYM (Qslide, "slide");
   DEFSYM (Qcrossfade, "crossfade");
@@ -16364,6 +21055,48 @@ syms_of_neomacsterm (void)
   DEFSYM (Qcrt_scanlines, "crt-scanlines");
   DEFSYM (Qdepth_of_field, "depth-of-field");
   DEFSYM (Qtypewriter_reveal, "typewriter-reveal");
+  DEFSYM (Qrectangle, "rectangle");
+  DEFSYM (Qpoint, "point");
+  DEFSYM (Qword, "word");
+  DEFSYM (Qcard, "card");
+  DEFSYM (Qstretch, "stretch");
+  DEFSYM (Qfit, "fit");
+  DEFSYM (Qfill, "fill");
+  DEFSYM (Qtile, "tile");
+  DEFSYM (Qsparkline, "sparkline");
+  DEFSYM (Qtop_left, "top-left");
+  DEFSYM (Qtop_right, "top-right");
+  DEFSYM (Qbottom_left, "bottom-left");
+  DEFSYM (Qbottom_right, "bottom-right");
+  DEFSYM (Qswitch, "switch");
+  DEFSYM (Qfifo, "fifo");
+  DEFSYM (Qmailbox, "mailbox");
+  DEFSYM (Qimmediate, "immediate");
+  DEFSYM (Qfifo_relaxed, "fifo-relaxed");
+  DEFSYM (Qintegrated, "integrated");
+  DEFSYM (Qdiscrete, "discrete");
+  DEFSYM (Qrule, "rule");
+  DEFSYM (Qdirectory, "directory");
+  DEFSYM (Qfile, "file");
+  DEFSYM (Qrecolor, "recolor");
+  DEFSYM (Qeyedropper, "eyedropper");
+  DEFSYM (Qneomacs_date_picker_today, "neomacs-date-picker-today");
+  DEFSYM (Qneomacs_date_picker_selected, "neomacs-date-picker-selected");
+  DEFSYM (Qneomacs_date_picker_highlight, "neomacs-date-picker-highlight");
+  DEFSYM (QCrotate, ":rotate");
+  DEFSYM (QCflip_h, ":flip-h");
+  DEFSYM (QCflip_v, ":flip-v");
+  DEFSYM (QCannotations, ":annotations");
+  DEFSYM (Qparagraph_align, "paragraph-align");
+  DEFSYM (Qvertical_writing, "vertical-writing");
+  DEFSYM (Qneomacs_region, "neomacs-region");
+  DEFSYM (Qregion, "region");
+  DEFSYM (Qsecondary_selection, "secondary-selection");
+  DEFSYM (Qmouse_secondary_overlay, "mouse-secondary-overlay");
+  DEFSYM (Qneomacs_pulse, "neomacs-pulse");
+
+  neomacs_ghost_buffer = Qnil;
+  staticpro (&neomacs_ghost_buffer);
 
   /* WebKit new window callback */
   DEFVAR_LISP ("neomacs-webkit-new-window-function", Vneomacs_webkit_new_window_function,
@@ -16388,6 +21121,81 @@ WebKit view.  Used by `neomacs-webkit-interaction-mode' to auto-enter
 keyboard input forwarding.  Set to nil to clear. */);
   Vneomacs_webkit_clicked_view_id = Qnil;
 
+  DEFVAR_LISP ("neomacs-hanging-indent", Vneomacs_hanging_indent,
+    doc: /* Non-nil means indent continuation lines under the text they wrap.
+Rows after the first of a line without a `wrap-prefix' are indented
+past the line's leading whitespace and any list bullet or number, so
+wrapped list items line up under their first word.  A natural number
+indents them that many columns further.  Used by the Rust layout
+engine.  Automatically becomes buffer-local when set.  */);
+  Vneomacs_hanging_indent = Qnil;
+  DEFSYM (Qneomacs_hanging_indent, "neomacs-hanging-indent");
+  Fmake_variable_buffer_local (Qneomacs_hanging_indent);
+
+  DEFVAR_LISP ("neomacs-hyphenation", Vneomacs_hyphenation,
+    doc: /* Language to hyphenate wrapped words in, or nil.
+When non-nil and `word-wrap' is on, a word that would be moved to the
+next row leaving the row ragged is broken at a hyphenation point and a
+hyphen is drawn at the end of the row.  The value is a language code,
+a string or symbol such as "en-us", naming the pattern file
+hyph-LANG.pat.txt in `neomacs-hyphenation-directory'.  Used by the Rust
+layout engine.  Automatically becomes buffer-local when set.  */);
+  Vneomacs_hyphenation = Qnil;
+  DEFSYM (Qneomacs_hyphenation, "neomacs-hyphenation");
+  Fmake_variable_buffer_local (Qneomacs_hyphenation);
+
+  DEFVAR_LISP ("neomacs-hyphenation-directory", Vneomacs_hyphenation_directory,
+    doc: /* Directory of the hyphenation pattern files, or nil.
+nil means the hyphenation directory of `data-directory'.  */);
+  Vneomacs_hyphenation_directory = Qnil;
+  DEFSYM (Qneomacs_hyphenation_directory, "neomacs-hyphenation-directory");
+  neomacs_hyphenation_dir = Qnil;
+  staticpro (&neomacs_hyphenation_dir);
+  {
+    Lisp_Object watcher;
+    static union Aligned_Lisp_Subr Swatch_neomacs_hyphenation_directory =
+      {{{ PSEUDOVECTOR_FLAG | (PVEC_SUBR << PSEUDOVECTOR_AREA_BITS) },
+        { .a4 = watch_neomacs_hyphenation_directory },
+        4, 4, "watch_neomacs_hyphenation_directory", {0}, lisp_h_Qnil}};
+    XSETSUBR (watcher, &Swatch_neomacs_hyphenation_directory.s);
+    Fadd_variable_watcher (Qneomacs_hyphenation_directory, watcher);
+  }
+
+  DEFVAR_INT ("neomacs-hyphenation-ragged-columns",
+              neomacs_hyphenation_ragged_columns,
+    doc: /* Columns a word-wrapped row may leave empty before hyphenating.
+A word is hyphenated, when `neomacs-hyphenation' is on, only if moving
+it whole to the next row would leave more than this many columns at the
+end of the row.  */);
+  neomacs_hyphenation_ragged_columns = 6;
+
+  DEFSYM (Qlink, "link");
+  DEFSYM (Qvertical_divider, "vertical-divider");
+  DEFSYM (Qhorizontal_divider, "horizontal-divider");
+  DEFVAR_LISP ("neomacs-pointer-shapes", Vneomacs_pointer_shapes,
+    doc: /* Mouse pointer shapes by what the mouse is over.
+An alist of (CONTEXT . SHAPE).  CONTEXT is one of `text', `link' (see
+`neomacs-link-add'), `mode-line' (also header and tab lines), `fringe'
+(also margins), `scroll-bar', `vertical-divider' and
+`horizontal-divider'.  SHAPE is a value of the `pointer' text property:
+`text', `arrow', `hand', `hdrag', `vdrag', `modeline' or `hourglass'.
+A `pointer' property of the text under the mouse takes precedence.
+Contexts without an entry use the frame's usual pointers.  */);
+  Vneomacs_pointer_shapes
+    = list (Fcons (Qtext, Qtext),
+            Fcons (Qlink, Qhand),
+            Fcons (Qmode_line, Qarrow),
+            Fcons (Qfringe, Qarrow),
+            Fcons (Qscroll_bar, Qarrow),
+            Fcons (Qvertical_divider, Qhdrag),
+            Fcons (Qhorizontal_divider, Qvdrag));
+
+  DEFVAR_INT ("neomacs-render-query-timeout", neomacs_render_query_timeout,
+    doc: /* Milliseconds to wait for the render thread to answer a query.
+Queries such as `neomacs-text-extent' give up after this long when the
+render thread is busy, instead of blocking Emacs.  */);
+  neomacs_render_query_timeout = 100;
+
   /* Required variables for cus-start */
   DEFVAR_BOOL ("x-use-underline-position-properties",
 	       x_use_underline_position_properties,
@@ -253,6 +253,14 @@ struct neomacs_output
   /* Title bar height */
   int title_bar_height;
 
+  /* True if the window title could not be sent to the render thread
+     and must be resent at the next redisplay */
+  bool title_pending;
+
+  /* True if this top-level frame has a popup window of its own
+     (the `neomacs-popup' frame parameter) instead of the main window */
+  bool popup_window;
+
   /* Menu bar height */
   int menu_bar_height;
 
@@ -379,6 +387,10 @@ extern void neomacs_delete_terminal (struct terminal *);
 
 /* Frame name/title */
 extern void neomacs_set_name (struct frame *, Lisp_Object, bool);
+extern void neomacs_send_title (struct frame *);
+
+#define FRAME_NEOMACS_POPUP_P(f) \
+  (FRAME_NEOMACS_OUTPUT (f) && FRAME_NEOMACS_OUTPUT (f)->popup_window)
 
 /* Lisp symbols */
 extern void syms_of_neomacsterm (void);
@@ -1696,6 +1696,32 @@ pos_visible_p (struct window *w, ptrdiff_t charpos, int *x, int *y,
   if (FRAME_INITIAL_P (XFRAME (WINDOW_FRAME (w))))
     return visible_p;
 
+  /* On Neomacs frames the Rust layout engine, not the glyph matrices
+     or the iterator, knows where text is drawn; ask it, unless it has
+     not laid out W yet.  */
+  if (FRAME_NEOMACS_P (XFRAME (WINDOW_FRAME (w))) && charpos >= 0)
+    {
+      NeomacsCharPixel px;
+      int shown = neomacs_layout_pos_to_pixel ((int64_t) (intptr_t) w,
+					       charpos, &px);
+      if (shown >= 0)
+	{
+	  bidi_unshelve_cache (itdata, false);
+	  if (shown == 0)
+	    return false;
+	  *x = (int) px.x - window_box_left (w, TEXT_AREA);
+	  *y = (int) px.y - WINDOW_TOP_EDGE_Y (w);
+	  /* How much of the row hangs below the text area.  */
+	  *rtop = max (0, WINDOW_TAB_LINE_HEIGHT (w)
+			  + WINDOW_HEADER_LINE_HEIGHT (w) - *y);
+	  *rbot = max (0, *y + (int) px.height
+			  - WINDOW_BOX_HEIGHT_NO_MODE_LINE (w));
+	  *rowh = (int) px.height - *rtop - *rbot;
+	  *vpos = px.row;
+	  return *rowh > 0;
+	}
+    }
+
   if (XBUFFER (w->contents) != current_buffer)
     {
       old_buffer = current_buffer;
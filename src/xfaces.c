@@ -1318,7 +1318,8 @@ load_color2 (struct frame *f, struct face *face, Lisp_Object name,
 	   || target_index == LFACE_UNDERLINE_INDEX
 	   || target_index == LFACE_OVERLINE_INDEX
 	   || target_index == LFACE_STRIKE_THROUGH_INDEX
-	   || target_index == LFACE_BOX_INDEX);
+	   || target_index == LFACE_BOX_INDEX
+	   || target_index == LFACE_TEXT_SHADOW_INDEX);
 
   /* if the color map is full, defined_color_hook will return a best match
      to the values in an existing cell. */
@@ -1359,6 +1360,11 @@ load_color2 (struct frame *f, struct face *face, Lisp_Object name,
 	  color->pixel = FRAME_FOREGROUND_PIXEL (f);
 	  break;
 
+	case LFACE_TEXT_SHADOW_INDEX:
+	  face->text_shadow_color_defaulted_p = true;
+	  color->pixel = FRAME_BACKGROUND_PIXEL (f);
+	  break;
+
 	default:
 	  emacs_abort ();
 	}
@@ -1374,7 +1380,8 @@ load_color2 (struct frame *f, struct face *face, Lisp_Object name,
 /* Load color with name NAME for use by face FACE on frame F.
    TARGET_INDEX must be one of LFACE_FOREGROUND_INDEX,
    LFACE_BACKGROUND_INDEX, LFACE_UNDERLINE_INDEX, LFACE_OVERLINE_INDEX,
-   LFACE_STRIKE_THROUGH_INDEX, or LFACE_BOX_INDEX.  Value is the
+   LFACE_STRIKE_THROUGH_INDEX, LFACE_BOX_INDEX or
+   LFACE_TEXT_SHADOW_INDEX.  Value is the
    pixel color.  If color cannot be loaded, display a message, and
    return the foreground, background or underline color of F, but
    record that fact in flags of the face so that we don't try to free
@@ -1510,6 +1517,13 @@ free_face_colors (struct frame *f, struct face *face)
       IF_DEBUG (--ncolors_allocated);
     }
 
+  if (face->text_shadow_p
+      && !face->text_shadow_color_defaulted_p)
+    {
+      x_free_colors (f, &face->text_shadow_color, 1);
+      IF_DEBUG (--ncolors_allocated);
+    }
+
   unblock_input ();
 }
 
@@ -1828,6 +1842,7 @@ the WIDTH times as wide as FACE on FRAME.  */)
 #define LFACE_INHERIT(LFACE)	    AREF (LFACE, LFACE_INHERIT_INDEX)
 #define LFACE_FONTSET(LFACE)	    AREF (LFACE, LFACE_FONTSET_INDEX)
 #define LFACE_EXTEND(LFACE)	    AREF (LFACE, LFACE_EXTEND_INDEX)
+#define LFACE_TEXT_SHADOW(LFACE)    AREF (LFACE, LFACE_TEXT_SHADOW_INDEX)
 #define LFACE_DISTANT_FOREGROUND(LFACE) \
   AREF (LFACE, LFACE_DISTANT_FOREGROUND_INDEX)
 
@@ -1886,6 +1901,12 @@ check_lface_attrs (Lisp_Object attrs[LFACE_VECTOR_SIZE])
 	   || RESET_P (attrs[LFACE_EXTEND_INDEX])
 	   || SYMBOLP (attrs[LFACE_EXTEND_INDEX])
 	   || STRINGP (attrs[LFACE_EXTEND_INDEX]));
+  eassert (UNSPECIFIEDP (attrs[LFACE_TEXT_SHADOW_INDEX])
+	   || IGNORE_DEFFACE_P (attrs[LFACE_TEXT_SHADOW_INDEX])
+	   || RESET_P (attrs[LFACE_TEXT_SHADOW_INDEX])
+	   || SYMBOLP (attrs[LFACE_TEXT_SHADOW_INDEX])
+	   || STRINGP (attrs[LFACE_TEXT_SHADOW_INDEX])
+	   || CONSP (attrs[LFACE_TEXT_SHADOW_INDEX]));
   eassert (UNSPECIFIEDP (attrs[LFACE_OVERLINE_INDEX])
 	   || IGNORE_DEFFACE_P (attrs[LFACE_OVERLINE_INDEX])
 	   || RESET_P (attrs[LFACE_OVERLINE_INDEX])
@@ -2676,6 +2697,46 @@ filter_face_ref (Lisp_Object face_ref,
   return Qnil;
 }
 
+/* Return true if VALUE is a valid `:text-shadow' face attribute:
+   nil, t, a color name, or a property list with `:color' COLOR,
+   `:offset' (X . Y) or a single integer for both, and `:blur' RADIUS,
+   a non-negative integer.  */
+
+static bool
+text_shadow_spec_p (Lisp_Object value)
+{
+  if (NILP (value) || EQ (value, Qt))
+    return true;
+  if (STRINGP (value))
+    return SCHARS (value) > 0;
+  while (CONSP (value) && CONSP (XCDR (value)))
+    {
+      Lisp_Object key = XCAR (value), val = XCAR (XCDR (value));
+
+      if (EQ (key, QCcolor))
+	{
+	  if (!NILP (val) && !STRINGP (val))
+	    return false;
+	}
+      else if (EQ (key, QCoffset))
+	{
+	  if (!FIXNUMP (val)
+	      && !(CONSP (val) && FIXNUMP (XCAR (val))
+		   && FIXNUMP (XCDR (val))))
+	    return false;
+	}
+      else if (EQ (key, QCblur))
+	{
+	  if (!FIXNATP (val))
+	    return false;
+	}
+      else
+	return false;
+      value = XCDR (XCDR (value));
+    }
+  return NILP (value);
+}
+
 /* Merge face attributes from the lisp `face reference' FACE_REF on
    frame F into the face attribute vector TO as appropriate for
    window W; W is used only for filtering face specs.  If ERR_MSGS
@@ -3003,6 +3064,13 @@ merge_face_ref (struct window *w,
 		  else
 		    err = true;
 		}
+	      else if (EQ (keyword, QCtext_shadow))
+		{
+		  if (text_shadow_spec_p (value))
+		    to[LFACE_TEXT_SHADOW_INDEX] = value;
+		  else
+		    err = true;
+		}
 	      else
 		err = true;
 
@@ -3581,6 +3649,16 @@ FRAME 0 means change the face on all frames, and change the default
       old_value = LFACE_EXTEND (lface);
       ASET (lface, LFACE_EXTEND_INDEX, value);
     }
+  else if (EQ (attr, QCtext_shadow))
+    {
+      if (!UNSPECIFIEDP (value)
+	  && !IGNORE_DEFFACE_P (value)
+	  && !RESET_P (value)
+	  && !text_shadow_spec_p (value))
+	signal_error ("Invalid face text shadow", value);
+      old_value = LFACE_TEXT_SHADOW (lface);
+      ASET (lface, LFACE_TEXT_SHADOW_INDEX, value);
+    }
   else if (EQ (attr, QCforeground))
     {
       HANDLE_INVALID_NIL_VALUE (QCforeground, face);
@@ -4301,6 +4379,8 @@ frames).  If FRAME is omitted or nil, use the selected frame.  */)
     value = LFACE_INHERIT (lface);
   else if (EQ (keyword, QCextend))
     value = LFACE_EXTEND (lface);
+  else if (EQ (keyword, QCtext_shadow))
+    value = LFACE_TEXT_SHADOW (lface);
   else if (EQ (keyword, QCfont))
     value = LFACE_FONT (lface);
   else if (EQ (keyword, QCfontset))
@@ -4329,7 +4409,8 @@ Value is nil if ATTR doesn't have a discrete set of valid values.  */)
   if (EQ (attr, QCunderline) || EQ (attr, QCoverline)
       || EQ (attr, QCstrike_through)
       || EQ (attr, QCinverse_video)
-      || EQ (attr, QCextend))
+      || EQ (attr, QCextend)
+      || EQ (attr, QCtext_shadow))
     result = list2 (Qt, Qnil);
 
   return result;
@@ -5574,7 +5655,8 @@ tty_supports_face_attributes_p (struct frame *f,
       || !UNSPECIFIEDP (attrs[LFACE_HEIGHT_INDEX])
       || !UNSPECIFIEDP (attrs[LFACE_SWIDTH_INDEX])
       || !UNSPECIFIEDP (attrs[LFACE_OVERLINE_INDEX])
-      || !UNSPECIFIEDP (attrs[LFACE_BOX_INDEX]))
+      || !UNSPECIFIEDP (attrs[LFACE_BOX_INDEX])
+      || !UNSPECIFIEDP (attrs[LFACE_TEXT_SHADOW_INDEX]))
     return false;
 
   /* Test for terminal `capabilities' (non-color character attributes).  */
@@ -6068,6 +6150,9 @@ realize_default_face (struct frame *f)
   if (UNSPECIFIEDP (LFACE_EXTEND (lface)))
     ASET (lface, LFACE_EXTEND_INDEX, Qnil);
 
+  if (UNSPECIFIEDP (LFACE_TEXT_SHADOW (lface)))
+    ASET (lface, LFACE_TEXT_SHADOW_INDEX, Qnil);
+
   if (UNSPECIFIEDP (LFACE_UNDERLINE (lface)))
     ASET (lface, LFACE_UNDERLINE_INDEX, Qnil);
 
@@ -6300,6 +6385,7 @@ realize_gui_face (struct face_cache *cache, Lisp_Object attrs[LFACE_VECTOR_SIZE]
   struct face *default_face;
   struct frame *f;
   Lisp_Object stipple, underline, overline, strike_through, box;
+  Lisp_Object text_shadow;
 
   eassert (FRAME_WINDOW_P (cache->f));
 
@@ -6626,6 +6712,45 @@ realize_gui_face (struct face_cache *cache, Lisp_Object attrs[LFACE_VECTOR_SIZE]
       face->strike_through_p = true;
     }
 
+  /* Text shadow or glow: t, a color, or
+     `(:color COLOR :offset (X . Y) :blur RADIUS)'.  The color defaults
+     to the frame's background, so the text stands out from whatever
+     is drawn behind it.  */
+  text_shadow = attrs[LFACE_TEXT_SHADOW_INDEX];
+  if (!NILP (text_shadow) && text_shadow_spec_p (text_shadow))
+    {
+      Lisp_Object color = Qnil, offset = Qnil, blur = Qnil;
+
+      if (STRINGP (text_shadow))
+	color = text_shadow;
+      else if (CONSP (text_shadow))
+	{
+	  color = plist_get (text_shadow, QCcolor);
+	  offset = plist_get (text_shadow, QCoffset);
+	  blur = plist_get (text_shadow, QCblur);
+	}
+
+      face->text_shadow_p = true;
+      if (STRINGP (color))
+	face->text_shadow_color
+	  = load_color (f, face, color, LFACE_TEXT_SHADOW_INDEX);
+      else
+	{
+	  face->text_shadow_color = FRAME_BACKGROUND_PIXEL (f);
+	  face->text_shadow_color_defaulted_p = true;
+	}
+      face->text_shadow_x_offset = face->text_shadow_y_offset = 1;
+      if (CONSP (offset))
+	{
+	  face->text_shadow_x_offset = XFIXNUM (XCAR (offset));
+	  face->text_shadow_y_offset = XFIXNUM (XCDR (offset));
+	}
+      else if (FIXNUMP (offset))
+	face->text_shadow_x_offset = face->text_shadow_y_offset
+	  = XFIXNUM (offset);
+      face->text_shadow_blur = FIXNUMP (blur) ? XFIXNUM (blur) : 2;
+    }
+
   stipple = attrs[LFACE_STIPPLE_INDEX];
   if (!NILP (stipple))
     face->stipple = load_pixmap (f, stipple);
@@ -7482,6 +7607,7 @@ init_xfaces (void)
   face_attr_sym[LFACE_FONTSET_INDEX] = QCfontset;
   face_attr_sym[LFACE_DISTANT_FOREGROUND_INDEX] = QCdistant_foreground;
   face_attr_sym[LFACE_EXTEND_INDEX] = QCextend;
+  face_attr_sym[LFACE_TEXT_SHADOW_INDEX] = QCtext_shadow;
 }
 
 void
@@ -7521,12 +7647,15 @@ syms_of_xfaces (void)
   DEFSYM (QCbox, ":box");
   DEFSYM (QCinherit, ":inherit");
   DEFSYM (QCextend, ":extend");
+  DEFSYM (QCtext_shadow, ":text-shadow");
 
   /* Symbols used for Lisp face attribute values.  */
   DEFSYM (QCcolor, ":color");
   DEFSYM (QCline_width, ":line-width");
   DEFSYM (QCstyle, ":style");
   DEFSYM (QCcorner_radius, ":corner-radius");
+  DEFSYM (QCoffset, ":offset");
+  DEFSYM (QCblur, ":blur");
   DEFSYM (QCposition, ":position");
   DEFSYM (Qline, "line");
   DEFSYM (Qwave, "wave");
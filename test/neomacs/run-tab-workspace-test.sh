@@ -0,0 +1,19 @@
+#!/usr/bin/env bash
+# Test tab workspaces (neomacs-tab-workspace-mode) with ERT
+# Usage: ./test/neomacs/run-tab-workspace-test.sh
+#
+# What this tests:
+# - Buffers recorded per tab and the tab buffer predicate
+# - Switching tabs with a transition and the tab's workspace
+# - Moving and closing tabs
+# - Turning the mode off removes its advice, hooks and predicate
+#
+# Runs in batch mode; no display is needed.
+
+set -e
+
+cd "$(dirname "$0")/../.."
+
+echo "=== Tab Workspace Test ==="
+./src/emacs --batch -Q -l ert -l test/neomacs/tab-workspace-test.el \
+    -f ert-run-tests-batch-and-exit
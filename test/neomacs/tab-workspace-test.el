@@ -0,0 +1,157 @@
+;;; tab-workspace-test.el --- ERT tests for neomacs-tab-workspace-mode -*- lexical-binding: t -*-
+
+;; Usage: ./src/emacs --batch -l ert -l test/neomacs/tab-workspace-test.el \
+;;          -f ert-run-tests-batch-and-exit
+;;
+;; Tests switching, moving and closing tabs with
+;; `neomacs-tab-workspace-mode', the buffers each tab records in its
+;; `neomacs-buffers' parameter, and that turning the mode off removes
+;; its advice, hooks and buffer predicate.  The frame transition
+;; primitives are stubbed, so no GPU frame is needed.
+
+;;; Code:
+
+(require 'ert)
+(require 'cl-lib)
+(require 'tab-bar)
+(ignore-errors (require 'term/neomacs-win))
+
+(defvar tab-workspace-test--effects nil
+  "Transition effects sent while a test ran, most recent first.")
+
+(defmacro tab-workspace-test--with-mode (&rest body)
+  "Run BODY in a frame with one tab and `neomacs-tab-workspace-mode' on.
+The frame transition primitives only record the effects sent."
+  (declare (indent 0) (debug t))
+  `(let ((neomacs-workspaces nil)
+         (neomacs-workspace-transition-effect nil)
+         (neomacs-tab-buffer-filter t)
+         (tab-bar-new-tab-choice t)
+         (tab-workspace-test--effects nil)
+         (buffers (mapcar #'get-buffer-create
+                          '("*tw-a*" "*tw-b*" "*tw-c*"))))
+     (cl-letf (((symbol-function 'neomacs-prepare-frame-transition)
+                (lambda () t))
+               ((symbol-function 'neomacs--send-buffer-transition)
+                (lambda (effect) (push effect tab-workspace-test--effects)))
+               ((symbol-function 'neomacs-trigger-buffer-transition)
+                #'ignore))
+       (unwind-protect
+           (progn
+             (tab-bar-tabs-set nil)
+             (set-frame-parameter nil 'neomacs-workspace nil)
+             (delete-other-windows)
+             (switch-to-buffer (nth 0 buffers))
+             (neomacs-tab-workspace-mode 1)
+             ,@body)
+         (neomacs-tab-workspace-mode -1)
+         (tab-bar-tabs-set nil)
+         (set-frame-parameter nil 'neomacs-workspace nil)
+         (mapc #'kill-buffer buffers)))))
+
+(defun tab-workspace-test--show (name)
+  "Show buffer NAME in the selected window and record it, as redisplay does."
+  (switch-to-buffer name)
+  (neomacs--tab-record-buffers (selected-frame)))
+
+(defun tab-workspace-test--buffers (index)
+  "The `neomacs-buffers' of the tab at 1-based INDEX."
+  (alist-get 'neomacs-buffers (cdr (nth (1- index) (funcall tab-bar-tabs-function)))))
+
+(ert-deftest tab-workspace-test-records-buffers-per-tab ()
+  (skip-unless (fboundp 'neomacs-tab-workspace-mode))
+  (tab-workspace-test--with-mode
+    (tab-workspace-test--show "*tw-a*")
+    (tab-bar-new-tab)
+    (tab-workspace-test--show "*tw-b*")
+    (should (member "*tw-b*" (tab-workspace-test--buffers 2)))
+    (should (member "*tw-a*" (tab-workspace-test--buffers 1)))
+    (should-not (member "*tw-b*" (tab-workspace-test--buffers 1)))
+    ;; Buffer switching offers only the current tab's buffers
+    (should (eq (frame-parameter nil 'buffer-predicate)
+                #'neomacs--tab-buffer-p))
+    (should (neomacs--tab-buffer-p (get-buffer "*tw-b*")))
+    (should-not (neomacs--tab-buffer-p (get-buffer "*tw-c*")))
+    (let ((neomacs-tab-buffer-filter nil))
+      (should (neomacs--tab-buffer-p (get-buffer "*tw-c*"))))))
+
+(ert-deftest tab-workspace-test-switching-tabs ()
+  (skip-unless (fboundp 'neomacs-tab-workspace-mode))
+  (tab-workspace-test--with-mode
+    (tab-workspace-test--show "*tw-a*")
+    (neomacs-workspace-save "one")
+    (tab-bar-new-tab)
+    (tab-workspace-test--show "*tw-b*")
+    (neomacs-workspace-save "two")
+    (should (equal (alist-get 'name (tab-bar--current-tab)) "two"))
+    ;; Going left slides the layout in from the left
+    (setq tab-workspace-test--effects nil)
+    (tab-bar-select-tab 1)
+    (should (member "slide-right" tab-workspace-test--effects))
+    (should (eq (window-buffer) (get-buffer "*tw-a*")))
+    (should (equal (neomacs-workspace-current) "one"))
+    (should-not (neomacs--tab-buffer-p (get-buffer "*tw-b*")))
+    (setq tab-workspace-test--effects nil)
+    (tab-bar-select-tab 2)
+    (should (member "slide-left" tab-workspace-test--effects))
+    (should (eq (window-buffer) (get-buffer "*tw-b*")))
+    (should (equal (neomacs-workspace-current) "two"))
+    ;; The workspace's tab is selected rather than a new one made
+    (neomacs-workspace-switch "one")
+    (should (= (length (funcall tab-bar-tabs-function)) 2))
+    (should (= (tab-bar--current-tab-index) 0))))
+
+(ert-deftest tab-workspace-test-moving-tabs-keeps-their-buffers ()
+  (skip-unless (fboundp 'neomacs-tab-workspace-mode))
+  (tab-workspace-test--with-mode
+    (tab-workspace-test--show "*tw-a*")
+    (tab-bar-new-tab)
+    (tab-workspace-test--show "*tw-b*")
+    (should (advice-member-p 'neomacs-tab-drag 'tab-bar-mouse-move-tab))
+    (tab-bar-move-tab-to 1)
+    (should (member "*tw-b*" (tab-workspace-test--buffers 1)))
+    (should-not (member "*tw-b*" (tab-workspace-test--buffers 2)))
+    (should (member "*tw-a*" (tab-workspace-test--buffers 2)))
+    (should (neomacs--tab-buffer-p (get-buffer "*tw-b*")))))
+
+(ert-deftest tab-workspace-test-closing-tabs ()
+  (skip-unless (fboundp 'neomacs-tab-workspace-mode))
+  (tab-workspace-test--with-mode
+    (tab-workspace-test--show "*tw-a*")
+    (neomacs-workspace-save "one")
+    (tab-bar-new-tab)
+    (tab-workspace-test--show "*tw-b*")
+    (neomacs-workspace-save "two")
+    (tab-bar-close-tab)
+    (should (= (length (funcall tab-bar-tabs-function)) 1))
+    (should (eq (window-buffer) (get-buffer "*tw-a*")))
+    (should (member "*tw-a*" (tab-workspace-test--buffers 1)))
+    (should-not (neomacs--tab-buffer-p (get-buffer "*tw-b*")))
+    ;; The closed tab's workspace is still there to switch back to
+    (should (assoc "two" neomacs-workspaces))))
+
+(ert-deftest tab-workspace-test-turning-off-removes-advice-and-predicate ()
+  (skip-unless (fboundp 'neomacs-tab-workspace-mode))
+  (tab-workspace-test--with-mode
+    (should (advice-member-p 'neomacs-tab-transition 'tab-bar-select-tab))
+    (should (memq #'neomacs--tab-setup-frame after-make-frame-functions))
+    (should (eq (frame-parameter nil 'buffer-predicate)
+                #'neomacs--tab-buffer-p))
+    (neomacs-tab-workspace-mode -1)
+    (should-not (advice-member-p 'neomacs-tab-transition 'tab-bar-select-tab))
+    (should-not (advice-member-p 'neomacs-tab-drag 'tab-bar-mouse-move-tab))
+    (should-not (memq #'neomacs--tab-record-buffers
+                      window-buffer-change-functions))
+    (should-not (memq #'neomacs--tab-select-workspace
+                      tab-bar-tab-post-select-functions))
+    (should-not (memq #'neomacs--tab-setup-frame after-make-frame-functions))
+    (should-not (frame-parameter nil 'buffer-predicate))
+    ;; Tabs switch without an animation again
+    (tab-bar-new-tab)
+    (setq tab-workspace-test--effects nil)
+    (tab-bar-select-tab 1)
+    (should-not tab-workspace-test--effects)))
+
+(provide 'tab-workspace-test)
+
+;;; tab-workspace-test.el ends here